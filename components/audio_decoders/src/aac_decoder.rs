@@ -2,10 +2,14 @@
 //!
 //! Provides decoding of AAC-encoded audio packets to PCM samples.
 
-use cortenbrowser_shared_types::{AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, MediaError};
+use crate::gapless::GaplessTrimmer;
+use cortenbrowser_shared_types::{
+    AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, AudioSpecificConfig, GaplessInfo,
+    MediaError,
+};
 use std::io::Cursor;
 use symphonia::core::audio::{AudioBufferRef, Signal};
-use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::codecs::{Decoder, DecoderOptions};
 use symphonia::core::formats::FormatOptions;
 use symphonia::core::io::MediaSourceStream;
 use symphonia::core::meta::MetadataOptions;
@@ -16,6 +20,10 @@ use symphonia::core::probe::Hint;
 /// Decodes AAC-encoded audio packets into PCM audio samples.
 /// Uses Symphonia for pure Rust AAC decoding.
 ///
+/// AAC has no in-band gapless information; the priming and padding samples
+/// signalled by the container (`iTunSMPB` or an MP4 edit list) are trimmed
+/// once passed to [`AudioDecoder::set_gapless_info`].
+///
 /// # Examples
 ///
 /// ```no_run
@@ -27,14 +35,29 @@ use symphonia::core::probe::Hint;
 ///     data: vec![/* aac data */],
 ///     pts: Some(0),
 ///     dts: Some(0),
+///     is_last: false,
+///     encryption: None,
 /// };
 /// let buffer = decoder.decode(&packet).expect("Failed to decode");
 /// ```
 pub struct AACDecoder {
-    // Symphonia decoders are created per-stream, so we'll initialize on first decode
-    _initialized: bool,
+    /// Created on the first packet and kept across packets, since each frame
+    /// is overlap-added with the previous one
+    decoder: Option<Box<dyn Decoder>>,
+    /// Stream configuration from the container, used to frame raw packets
+    config: Option<AudioSpecificConfig>,
+    /// Trimming of the priming and padding samples, if known
+    gapless: Option<GaplessTrimmer>,
 }
 
+/// ADTS sampling frequency indices by sample rate
+const ADTS_SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// Size of an ADTS header without CRC
+const ADTS_HEADER_SIZE: usize = 7;
+
 impl AACDecoder {
     /// Create a new AAC decoder
     ///
@@ -43,12 +66,50 @@ impl AACDecoder {
     /// `Ok(AACDecoder)` on success
     pub fn new() -> Result<Self, MediaError> {
         Ok(Self {
-            _initialized: false,
+            decoder: None,
+            config: None,
+            gapless: None,
         })
     }
 
+    /// Prefix a raw AAC frame, as stored in MP4 and Matroska, with an ADTS
+    /// header built from the stream configuration
+    fn to_adts(config: &AudioSpecificConfig, frame: &[u8]) -> Result<Vec<u8>, MediaError> {
+        let frequency_index = ADTS_SAMPLE_RATES
+            .iter()
+            .position(|&rate| rate == config.sample_rate)
+            .ok_or_else(|| MediaError::CodecError {
+                details: format!("No ADTS index for sample rate {}", config.sample_rate),
+            })? as u8;
+        let profile = config.object_type.saturating_sub(1) & 0x03;
+        let channels = if config.channels == 8 {
+            7
+        } else {
+            config.channels
+        };
+        let length = ADTS_HEADER_SIZE + frame.len();
+        if length >= 1 << 13 {
+            return Err(MediaError::CodecError {
+                details: format!("AAC frame of {} bytes is too large for ADTS", frame.len()),
+            });
+        }
+
+        let mut adts = Vec::with_capacity(length);
+        adts.extend_from_slice(&[
+            0xFF,
+            0xF1,
+            (profile << 6) | (frequency_index << 2) | (channels >> 2),
+            ((channels & 0x03) << 6) | (length >> 11) as u8,
+            (length >> 3) as u8,
+            ((length & 0x07) << 5) as u8 | 0x1F,
+            0xFC,
+        ]);
+        adts.extend_from_slice(frame);
+        Ok(adts)
+    }
+
     /// Decode AAC packet using Symphonia
-    fn decode_with_symphonia(&self, data: &[u8]) -> Result<(Vec<f32>, u32, u8), MediaError> {
+    fn decode_with_symphonia(&mut self, data: &[u8]) -> Result<(Vec<f32>, u32, u8), MediaError> {
         // Create a media source from the packet data - needs to own the data
         let owned_data = data.to_vec();
         let cursor = Cursor::new(owned_data);
@@ -77,13 +138,19 @@ impl AACDecoder {
                 details: "No default track found in AAC stream".to_string(),
             })?;
 
-        // Create decoder
-        let decoder_opts = DecoderOptions::default();
-        let mut decoder = symphonia::default::get_codecs()
-            .make(&track.codec_params, &decoder_opts)
-            .map_err(|e| MediaError::CodecError {
-                details: format!("Failed to create AAC decoder: {}", e),
-            })?;
+        // Create the decoder on the first packet
+        let decoder = match &mut self.decoder {
+            Some(decoder) => decoder,
+            None => {
+                let decoder_opts = DecoderOptions::default();
+                let decoder = symphonia::default::get_codecs()
+                    .make(&track.codec_params, &decoder_opts)
+                    .map_err(|e| MediaError::CodecError {
+                        details: format!("Failed to create AAC decoder: {}", e),
+                    })?;
+                self.decoder.insert(decoder)
+            }
+        };
 
         // Decode the packet
         let packet = format.next_packet().map_err(|e| MediaError::CodecError {
@@ -127,8 +194,16 @@ impl AudioDecoder for AACDecoder {
             });
         }
 
-        // Attempt to decode with Symphonia
-        let (samples, sample_rate, channels) = self.decode_with_symphonia(&packet.data)?;
+        // Raw frames need ADTS framing for Symphonia to identify the stream
+        let is_adts =
+            packet.data.len() >= 2 && packet.data[0] == 0xFF && packet.data[1] & 0xF0 == 0xF0;
+        let (samples, sample_rate, channels) = match self.config {
+            Some(config) if !is_adts => {
+                let adts = Self::to_adts(&config, &packet.data)?;
+                self.decode_with_symphonia(&adts)?
+            }
+            _ => self.decode_with_symphonia(&packet.data)?,
+        };
 
         // Calculate timestamp
         let timestamp = if let Some(pts) = packet.pts {
@@ -141,20 +216,47 @@ impl AudioDecoder for AACDecoder {
         let sample_count = samples.len() / channels as usize;
         let duration = std::time::Duration::from_secs_f64(sample_count as f64 / sample_rate as f64);
 
-        Ok(AudioBuffer {
+        let mut buffer = AudioBuffer {
             format: AudioFormat::F32LE,
             sample_rate,
             channels,
             samples,
             timestamp,
             duration,
-        })
+            channel_map: None,
+        };
+        if let Some(trimmer) = &mut self.gapless {
+            trimmer.trim(&mut buffer, packet.pts, packet.is_last);
+        }
+        Ok(buffer)
+    }
+
+    /// Takes the AudioSpecificConfig of a stream whose packets are raw AAC
+    /// frames rather than ADTS
+    fn configure(&mut self, extradata: &[u8]) -> Result<(), MediaError> {
+        self.config = Some(AudioSpecificConfig::parse(extradata)?);
+        Ok(())
+    }
+
+    fn set_gapless_info(&mut self, info: GaplessInfo) {
+        self.gapless = Some(GaplessTrimmer::new(info));
     }
 
     fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
         // Symphonia handles buffering internally
+        self.reset();
         Ok(vec![])
     }
+
+    fn reset(&mut self) {
+        // Drops the overlap-add state of the previous frame
+        if let Some(decoder) = &mut self.decoder {
+            decoder.reset();
+        }
+        if let Some(trimmer) = &mut self.gapless {
+            trimmer.reset();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -166,4 +268,23 @@ mod tests {
         let decoder = AACDecoder::new();
         assert!(decoder.is_ok());
     }
+
+    #[test]
+    fn test_to_adts_header() {
+        // AAC LC, 44.1 kHz, stereo
+        let config = AudioSpecificConfig::parse(&[0x12, 0x10]).unwrap();
+        let adts = AACDecoder::to_adts(&config, &[0xAA; 100]).unwrap();
+
+        // Frame length 107 = 0b000_0000_0110_1011
+        assert_eq!(&adts[..7], &[0xFF, 0xF1, 0x50, 0x80, 0x0D, 0x7F, 0xFC]);
+        assert_eq!(adts.len(), 107);
+    }
+
+    #[test]
+    fn test_configure_rejects_invalid_config() {
+        let mut decoder = AACDecoder::new().unwrap();
+
+        assert!(decoder.configure(&[0x12]).is_err());
+        assert!(decoder.configure(&[0x12, 0x10]).is_ok());
+    }
 }
@@ -2,8 +2,10 @@
 //!
 //! Provides a factory pattern for creating appropriate decoders based on codec type.
 
-use crate::{AACDecoder, MP3Decoder, OpusDecoder};
-use cortenbrowser_shared_types::{AudioCodec, AudioDecoder, MediaError};
+use crate::{AACDecoder, FlacDecoder, MP3Decoder, OpusDecoder};
+use cortenbrowser_shared_types::{
+    AACProfile, AudioCodec, AudioDecoder, MP3Layer, MediaError, OpusApplication,
+};
 
 /// Factory for creating audio decoders
 ///
@@ -44,11 +46,11 @@ impl DecoderFactory {
     /// - Opus
     /// - MP3
     /// - AAC
+    /// - FLAC
     ///
     /// # Unsupported Codecs
     ///
     /// - Vorbis (use VorbisDecoder separately)
-    /// - FLAC (not yet implemented)
     /// - PCM (no decoding needed)
     pub fn create_decoder(codec: AudioCodec) -> Result<Box<dyn AudioDecoder>, MediaError> {
         match codec {
@@ -75,21 +77,97 @@ impl DecoderFactory {
                 format: "Vorbis codec not yet implemented in factory".to_string(),
             }),
 
-            AudioCodec::FLAC => Err(MediaError::UnsupportedFormat {
-                format: "FLAC codec not yet implemented".to_string(),
-            }),
+            AudioCodec::FLAC => {
+                let decoder = FlacDecoder::new()?;
+                Ok(Box::new(decoder))
+            }
 
             AudioCodec::PCM { .. } => Err(MediaError::UnsupportedFormat {
                 format: "PCM does not require decoding".to_string(),
             }),
         }
     }
+
+    /// Create a decoder from an RFC 6381 codec string
+    ///
+    /// This is the entry point for codec strings as they appear in MIME
+    /// types (e.g. `audio/mp4; codecs="mp4a.40.2"`).
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - The codec identifier, e.g. `"mp4a.40.2"` or `"opus"`
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` if the string is not a known
+    /// audio codec identifier, or any error from [`DecoderFactory::create_decoder`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_audio_decoders::DecoderFactory;
+    ///
+    /// let decoder = DecoderFactory::from_codec_string("mp4a.40.2")
+    ///     .expect("Failed to create decoder");
+    /// ```
+    pub fn from_codec_string(codec: &str) -> Result<Box<dyn AudioDecoder>, MediaError> {
+        let codec = Self::parse_codec_string(codec)?;
+        Self::create_decoder(codec)
+    }
+
+    /// Map an RFC 6381 codec string to an [`AudioCodec`]
+    ///
+    /// Matching is case-insensitive. Parameters that the codec string does not
+    /// carry (sample rate, channels) are filled with defaults; decoders pick up
+    /// the real values from the bitstream.
+    ///
+    /// # Supported Identifiers
+    ///
+    /// - `mp4a.40.2` - AAC-LC
+    /// - `mp4a.40.5` - HE-AAC
+    /// - `mp4a.40.29` - HE-AACv2
+    /// - `mp4a.40.34`, `mp4a.69`, `mp4a.6B`, `mp3` - MP3
+    /// - `opus`
+    /// - `vorbis`
+    /// - `flac`
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` containing the offending string
+    /// if it is not recognized.
+    pub fn parse_codec_string(codec: &str) -> Result<AudioCodec, MediaError> {
+        let aac = |profile| AudioCodec::AAC {
+            profile,
+            sample_rate: 48000,
+            channels: 2,
+        };
+
+        match codec.trim().to_ascii_lowercase().as_str() {
+            "mp4a.40.2" => Ok(aac(AACProfile::LC)),
+            "mp4a.40.5" => Ok(aac(AACProfile::HE)),
+            "mp4a.40.29" => Ok(aac(AACProfile::HEv2)),
+            "mp4a.40.34" | "mp4a.69" | "mp4a.6b" | "mp3" => Ok(AudioCodec::MP3 {
+                layer: MP3Layer::Layer3,
+                bitrate: 0,
+            }),
+            "opus" => Ok(AudioCodec::Opus {
+                sample_rate: 48000,
+                channels: 2,
+                application: OpusApplication::Audio,
+            }),
+            "vorbis" => Ok(AudioCodec::Vorbis),
+            "flac" => Ok(AudioCodec::FLAC),
+            _ => Err(MediaError::UnsupportedFormat {
+                format: format!("Unknown audio codec string: {}", codec),
+            }),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cortenbrowser_shared_types::{AACProfile, MP3Layer, OpusApplication, PCMFormat};
+    use cortenbrowser_shared_types::PCMFormat;
 
     #[test]
     fn test_factory_creates_opus_decoder() {
@@ -131,10 +209,10 @@ mod tests {
     }
 
     #[test]
-    fn test_factory_rejects_flac() {
+    fn test_factory_creates_flac_decoder() {
         let codec = AudioCodec::FLAC;
         let result = DecoderFactory::create_decoder(codec);
-        assert!(result.is_err());
+        assert!(result.is_ok());
     }
 
     #[test]
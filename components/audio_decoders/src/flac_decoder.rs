@@ -0,0 +1,237 @@
+//! FLAC audio decoder implementation
+//!
+//! Provides decoding of FLAC frames, as carried natively in Matroska and
+//! encapsulated in Ogg, to PCM samples.
+
+use cortenbrowser_shared_types::{AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, MediaError};
+use std::time::Duration;
+use symphonia::core::audio::{AudioBufferRef, Signal};
+use symphonia::core::codecs::{CodecParameters, Decoder, DecoderOptions, CODEC_TYPE_FLAC};
+use symphonia::core::formats::Packet;
+
+/// Size of the STREAMINFO metadata block body
+const STREAMINFO_SIZE: usize = 34;
+
+/// Size of a metadata block header
+const METADATA_HEADER_SIZE: usize = 4;
+
+/// Size of the Ogg FLAC mapping header before the `fLaC` marker
+const OGG_MAPPING_HEADER_SIZE: usize = 9;
+
+/// FLAC audio decoder
+///
+/// Decodes FLAC frames into PCM audio samples using Symphonia. The stream's
+/// STREAMINFO block must be known before the first frame, either from the
+/// container's codec configuration passed to [`AudioDecoder::configure`]
+/// (Matroska `CodecPrivate`) or from the Ogg FLAC header packet, which can be
+/// decoded like any other packet. Other metadata packets decode to empty
+/// buffers.
+///
+/// Samples are scaled from the source bit depth to `[-1.0, 1.0)`, so 16-bit
+/// and 24-bit samples convert exactly.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cortenbrowser_audio_decoders::FlacDecoder;
+/// use cortenbrowser_shared_types::{AudioDecoder, AudioPacket};
+///
+/// let mut decoder = FlacDecoder::new().expect("Failed to create decoder");
+/// decoder
+///     .configure(&[/* fLaC marker and STREAMINFO */])
+///     .expect("Invalid stream configuration");
+/// let packet = AudioPacket {
+///     data: vec![/* flac frame */],
+///     pts: Some(0),
+///     dts: Some(0),
+///     is_last: false,
+///     encryption: None,
+/// };
+/// let buffer = decoder.decode(&packet).expect("Failed to decode");
+/// ```
+pub struct FlacDecoder {
+    /// Created once the STREAMINFO block is known
+    decoder: Option<Box<dyn Decoder>>,
+    sample_rate: u32,
+    channels: u8,
+    /// Position in samples per channel of the next frame, for packets
+    /// without a timestamp
+    position: u64,
+}
+
+impl FlacDecoder {
+    /// Create a new FLAC decoder
+    ///
+    /// # Returns
+    ///
+    /// `Ok(FlacDecoder)` on success
+    pub fn new() -> Result<Self, MediaError> {
+        Ok(Self {
+            decoder: None,
+            sample_rate: 0,
+            channels: 0,
+            position: 0,
+        })
+    }
+
+    /// Find the STREAMINFO block body in codec configuration
+    ///
+    /// Accepts the Ogg FLAC mapping header, a `fLaC` stream header followed
+    /// by metadata blocks, a bare STREAMINFO metadata block, or the bare
+    /// STREAMINFO body.
+    fn stream_info(extradata: &[u8]) -> Result<&[u8], MediaError> {
+        let mut data = extradata;
+        if data.starts_with(b"\x7FFLAC") && data.len() >= OGG_MAPPING_HEADER_SIZE {
+            data = &data[OGG_MAPPING_HEADER_SIZE..];
+        }
+        if let Some(rest) = data.strip_prefix(b"fLaC") {
+            data = rest;
+        }
+
+        if data.len() == STREAMINFO_SIZE {
+            return Ok(data);
+        }
+        if data.len() >= METADATA_HEADER_SIZE + STREAMINFO_SIZE && data[0] & 0x7F == 0 {
+            return Ok(&data[METADATA_HEADER_SIZE..METADATA_HEADER_SIZE + STREAMINFO_SIZE]);
+        }
+        Err(MediaError::CodecError {
+            details: "FLAC configuration has no STREAMINFO block".to_string(),
+        })
+    }
+
+    /// Whether a packet starts with a FLAC frame sync code
+    fn is_frame(data: &[u8]) -> bool {
+        data.len() >= 2 && data[0] == 0xFF && data[1] & 0xFE == 0xF8
+    }
+
+    /// Decoded buffer with no samples, for header and metadata packets
+    fn empty_buffer(&self) -> AudioBuffer {
+        AudioBuffer {
+            format: AudioFormat::F32LE,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            samples: Vec::new(),
+            timestamp: Duration::ZERO,
+            duration: Duration::ZERO,
+            channel_map: None,
+        }
+    }
+}
+
+impl AudioDecoder for FlacDecoder {
+    fn decode(&mut self, packet: &AudioPacket) -> Result<AudioBuffer, MediaError> {
+        if packet.data.is_empty() {
+            return Err(MediaError::CodecError {
+                details: "Cannot decode empty packet".to_string(),
+            });
+        }
+
+        // Ogg FLAC carries the stream header and metadata blocks in-band
+        if !Self::is_frame(&packet.data) {
+            if packet.data.starts_with(b"\x7FFLAC") || packet.data.starts_with(b"fLaC") {
+                self.configure(&packet.data)?;
+            }
+            return Ok(self.empty_buffer());
+        }
+
+        let decoder = self
+            .decoder
+            .as_mut()
+            .ok_or_else(|| MediaError::CodecError {
+                details: "FLAC frame received before STREAMINFO".to_string(),
+            })?;
+        let ts = packet.pts.map_or(self.position, |pts| pts.max(0) as u64);
+        let decoded = decoder
+            .decode(&Packet::new_from_slice(0, ts, 0, &packet.data))
+            .map_err(|e| MediaError::CodecError {
+                details: format!("Failed to decode FLAC frame: {}", e),
+            })?;
+
+        // Symphonia left-justifies every bit depth to 32 bits, so dividing by
+        // 2^31 scales a 16-bit sample by 2^15 and a 24-bit sample by 2^23
+        let AudioBufferRef::S32(buf) = decoded else {
+            return Err(MediaError::CodecError {
+                details: "Unsupported FLAC sample format".to_string(),
+            });
+        };
+        let channels = buf.spec().channels.count();
+        let frames = buf.frames();
+        let mut samples = Vec::with_capacity(frames * channels);
+        for frame in 0..frames {
+            for channel in 0..channels {
+                samples.push(buf.chan(channel)[frame] as f32 / 2_147_483_648.0);
+            }
+        }
+
+        let rate = f64::from(self.sample_rate.max(1));
+        self.position = ts + frames as u64;
+        Ok(AudioBuffer {
+            format: AudioFormat::F32LE,
+            sample_rate: self.sample_rate,
+            channels: channels as u8,
+            samples,
+            timestamp: Duration::from_secs_f64(ts as f64 / rate),
+            duration: Duration::from_secs_f64(frames as f64 / rate),
+            channel_map: None,
+        })
+    }
+
+    /// Takes the `fLaC` stream header with its metadata blocks, as in
+    /// Matroska `CodecPrivate`, or the Ogg FLAC mapping header
+    fn configure(&mut self, extradata: &[u8]) -> Result<(), MediaError> {
+        let stream_info = Self::stream_info(extradata)?;
+
+        let mut params = CodecParameters::new();
+        params
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_extra_data(stream_info.to_vec().into_boxed_slice());
+        let decoder = symphonia::default::get_codecs()
+            .make(&params, &DecoderOptions::default())
+            .map_err(|e| MediaError::CodecError {
+                details: format!("Failed to create FLAC decoder: {}", e),
+            })?;
+
+        let params = decoder.codec_params();
+        self.sample_rate = params.sample_rate.unwrap_or(0);
+        self.channels = params.channels.map_or(0, |c| c.count() as u8);
+        self.decoder = Some(decoder);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
+        // FLAC frames are independent, so nothing is buffered
+        self.reset();
+        Ok(vec![])
+    }
+
+    fn reset(&mut self) {
+        if let Some(decoder) = &mut self.decoder {
+            decoder.reset();
+        }
+        self.position = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flac_decoder_creation() {
+        let decoder = FlacDecoder::new();
+        assert!(decoder.is_ok());
+    }
+
+    #[test]
+    fn test_stream_info_layouts() {
+        let body = [0xAB; STREAMINFO_SIZE];
+        let block = [&[0x80, 0x00, 0x00, 0x22][..], &body].concat();
+        let native = [&b"fLaC"[..], &block].concat();
+        let ogg = [&b"\x7FFLAC\x01\x00\x00\x01"[..], &native].concat();
+
+        for config in [&body[..], &block, &native, &ogg] {
+            assert_eq!(FlacDecoder::stream_info(config).unwrap(), &body);
+        }
+        assert!(FlacDecoder::stream_info(b"fLaC").is_err());
+    }
+}
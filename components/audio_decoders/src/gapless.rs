@@ -0,0 +1,101 @@
+//! Gapless playback trimming
+//!
+//! Drops the encoder delay from the start of a stream and the padding from
+//! the end of its last packet, shifting timestamps so the first remaining
+//! sample is at zero.
+
+use cortenbrowser_shared_types::{AudioBuffer, GaplessInfo};
+use std::time::Duration;
+
+/// Trims decoded buffers according to a stream's [`GaplessInfo`]
+#[derive(Debug, Clone)]
+pub(crate) struct GaplessTrimmer {
+    info: GaplessInfo,
+    /// Position in samples per channel of the next buffer, for packets
+    /// without a timestamp
+    position: u64,
+}
+
+impl GaplessTrimmer {
+    /// Create a trimmer for a stream starting at position zero
+    pub(crate) fn new(info: GaplessInfo) -> Self {
+        Self { info, position: 0 }
+    }
+
+    /// Trim a decoded buffer in place
+    ///
+    /// `pts` is the position of the buffer's first sample in samples per
+    /// channel, including the encoder delay. Samples still within the delay
+    /// are dropped, and the padding is dropped from the end of the buffer of
+    /// the last packet. Padding longer than that buffer is only partially
+    /// trimmed.
+    pub(crate) fn trim(&mut self, buffer: &mut AudioBuffer, pts: Option<i64>, is_last: bool) {
+        let channels = usize::from(buffer.channels.max(1));
+        let frames = buffer.samples.len() / channels;
+        let start = pts.map_or(self.position, |pts| pts.max(0) as u64);
+        self.position = start + frames as u64;
+
+        let delay = u64::from(self.info.encoder_delay);
+        let front = delay.saturating_sub(start).min(frames as u64) as usize;
+        let back = if is_last {
+            (self.info.padding as usize).min(frames - front)
+        } else {
+            0
+        };
+        buffer.samples.truncate((frames - back) * channels);
+        buffer.samples.drain(..front * channels);
+
+        let rate = f64::from(buffer.sample_rate.max(1));
+        let output_start = (start + front as u64).saturating_sub(delay);
+        buffer.timestamp = Duration::from_secs_f64(output_start as f64 / rate);
+        buffer.duration = Duration::from_secs_f64((frames - front - back) as f64 / rate);
+    }
+
+    /// Restart at position zero, e.g. after a seek
+    pub(crate) fn reset(&mut self) {
+        self.position = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cortenbrowser_shared_types::AudioFormat;
+
+    fn stereo_buffer(frames: usize) -> AudioBuffer {
+        AudioBuffer {
+            format: AudioFormat::F32LE,
+            sample_rate: 1000,
+            channels: 2,
+            samples: (0..frames * 2).map(|i| i as f32).collect(),
+            timestamp: Duration::ZERO,
+            duration: Duration::ZERO,
+            channel_map: None,
+        }
+    }
+
+    #[test]
+    fn test_trim_delay_across_buffers() {
+        let mut trimmer = GaplessTrimmer::new(GaplessInfo {
+            encoder_delay: 150,
+            padding: 30,
+        });
+
+        let mut first = stereo_buffer(100);
+        trimmer.trim(&mut first, None, false);
+        assert!(first.samples.is_empty());
+
+        let mut second = stereo_buffer(100);
+        trimmer.trim(&mut second, None, false);
+        assert_eq!(second.samples.len(), 100);
+        assert_eq!(second.samples[0], 100.0);
+        assert_eq!(second.timestamp, Duration::ZERO);
+        assert_eq!(second.duration, Duration::from_millis(50));
+
+        let mut last = stereo_buffer(100);
+        trimmer.trim(&mut last, Some(200), true);
+        assert_eq!(last.samples.len(), 140);
+        assert_eq!(last.timestamp, Duration::from_millis(50));
+        assert_eq!(last.duration, Duration::from_millis(70));
+    }
+}
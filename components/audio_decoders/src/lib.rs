@@ -1,6 +1,6 @@
 //! # audio_decoders Component
 //!
-//! Audio codec implementations (AAC, MP3, Opus, Vorbis, PCM)
+//! Audio codec implementations (AAC, MP3, Opus, FLAC, Vorbis, PCM)
 //!
 //! This component provides decoder implementations for common audio codecs
 //! used in web media playback. Each decoder implements the `AudioDecoder` trait
@@ -35,11 +35,14 @@ pub use cortenbrowser_shared_types::{
 // Module declarations
 mod aac_decoder;
 mod factory;
+mod flac_decoder;
+mod gapless;
 mod mp3_decoder;
 mod opus_decoder;
 
 // Re-export decoder implementations
 pub use aac_decoder::AACDecoder;
 pub use factory::DecoderFactory;
+pub use flac_decoder::FlacDecoder;
 pub use mp3_decoder::MP3Decoder;
 pub use opus_decoder::OpusDecoder;
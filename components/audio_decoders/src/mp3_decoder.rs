@@ -2,14 +2,26 @@
 //!
 //! Provides decoding of MP3-encoded audio packets to PCM samples.
 
-use cortenbrowser_shared_types::{AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, MediaError};
+use crate::gapless::GaplessTrimmer;
+use cortenbrowser_shared_types::{
+    AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, GaplessInfo, MediaError,
+};
 use minimp3::Decoder;
+use std::collections::VecDeque;
 
 /// MP3 audio decoder
 ///
 /// Decodes MP3-encoded audio packets into PCM audio samples.
 /// Supports all MP3 layers (I, II, III) and common sample rates.
 ///
+/// The bit reservoir and synthesis state are carried from one packet to the
+/// next, so packets must be decoded in stream order until the decoder is
+/// reset.
+///
+/// A Xing/Info frame at the start of the stream produces no samples. The
+/// encoder delay and padding in its LAME tag are trimmed for gapless
+/// playback, the padding when decoding the packet marked `is_last`.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -21,10 +33,23 @@ use minimp3::Decoder;
 ///     data: vec![/* mp3 data */],
 ///     pts: Some(0),
 ///     dts: Some(0),
+///     is_last: false,
+///     encryption: None,
 /// };
 /// let buffer = decoder.decode(&packet).expect("Failed to decode");
 /// ```
-pub struct MP3Decoder;
+pub struct MP3Decoder {
+    /// minimp3 decoder reading the packets as one continuous stream, which
+    /// holds the bit reservoir and the overlap of the previous frame
+    decoder: Decoder<VecDeque<u8>>,
+    /// Whether a packet has been decoded since creation or the last reset
+    started: bool,
+    /// Trimming of the encoder delay and padding, if known
+    gapless: Option<GaplessTrimmer>,
+}
+
+/// Samples of delay added by the MP3 decoder's synthesis filterbank
+const DECODER_DELAY: u32 = 529;
 
 impl MP3Decoder {
     /// Create a new MP3 decoder
@@ -33,7 +58,62 @@ impl MP3Decoder {
     ///
     /// `Ok(MP3Decoder)` on success
     pub fn new() -> Result<Self, MediaError> {
-        Ok(Self)
+        Ok(Self {
+            decoder: Decoder::new(VecDeque::new()),
+            started: false,
+            gapless: None,
+        })
+    }
+
+    /// Find the Xing/Info tag of the first frame of a stream
+    ///
+    /// # Returns
+    ///
+    /// `None` if the frame carries no tag, `Some(None)` for a tag without
+    /// encoder delay and padding, otherwise the gapless information of the
+    /// stream including the decoder delay
+    fn info_tag(frame: &[u8]) -> Option<Option<GaplessInfo>> {
+        let header = frame.get(..4)?;
+        if header[0] != 0xFF || header[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+        let mpeg1 = header[1] & 0x18 == 0x18;
+        let mono = header[3] >> 6 == 3;
+        let crc = if header[1] & 0x01 == 0 { 2 } else { 0 };
+        let side_info = match (mpeg1, mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        };
+
+        let tag = frame.get(4 + crc + side_info..)?;
+        if !matches!(tag.get(..4)?, b"Xing" | b"Info") {
+            return None;
+        }
+        let flags = tag.get(7)?;
+        // Optional frame count, byte count, seek table and quality fields
+        let fields = [4, 4, 100, 4]
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| flags & (1 << i) != 0)
+            .map(|(_, size)| size)
+            .sum::<usize>();
+
+        // LAME extension: encoder version, then delay and padding as 12-bit
+        // values after 12 bytes of settings
+        let lame = tag.get(8 + fields..);
+        let gapless = lame
+            .filter(|lame| matches!(lame.get(..4), Some(b"LAME" | b"Lavf" | b"Lavc")))
+            .and_then(|lame| lame.get(21..24))
+            .map(|bytes| {
+                let delay = (u32::from(bytes[0]) << 4) | u32::from(bytes[1] >> 4);
+                let padding = (u32::from(bytes[1] & 0x0F) << 8) | u32::from(bytes[2]);
+                GaplessInfo {
+                    encoder_delay: delay + DECODER_DELAY,
+                    padding: padding.saturating_sub(DECODER_DELAY),
+                }
+            });
+        Some(gapless)
     }
 }
 
@@ -45,16 +125,24 @@ impl AudioDecoder for MP3Decoder {
             });
         }
 
-        // Create a new decoder with the packet data
-        let cursor = std::io::Cursor::new(packet.data.clone());
-        let mut temp_decoder = Decoder::new(cursor);
+        let first = !self.started;
+        self.started = true;
+        let info_tag = if first {
+            Self::info_tag(&packet.data)
+        } else {
+            None
+        };
 
         // Decode the MP3 frame
-        let frame = temp_decoder
-            .next_frame()
-            .map_err(|e| MediaError::CodecError {
+        self.decoder.reader_mut().extend(&packet.data);
+        let frame = self.decoder.next_frame().map_err(|e| {
+            // Drop the unusable data rather than misreading the next packet
+            self.decoder = Decoder::new(VecDeque::new());
+            MediaError::CodecError {
                 details: format!("MP3 decoding failed: {:?}", e),
-            })?;
+            }
+        })?;
+        let channels = frame.channels.max(1);
 
         // Convert i16 samples to f32
         let samples: Vec<f32> = frame.data.iter().map(|&s| s as f32 / 32768.0).collect();
@@ -67,24 +155,53 @@ impl AudioDecoder for MP3Decoder {
         };
 
         // Calculate duration
-        let sample_count = samples.len() / frame.channels;
+        let sample_count = samples.len() / channels;
         let duration =
             std::time::Duration::from_secs_f64(sample_count as f64 / frame.sample_rate as f64);
 
-        Ok(AudioBuffer {
+        let mut buffer = AudioBuffer {
             format: AudioFormat::F32LE,
             sample_rate: frame.sample_rate as u32,
-            channels: frame.channels as u8,
+            channels: channels as u8,
             samples,
             timestamp,
             duration,
-        })
+            channel_map: None,
+        };
+
+        // The Info frame is silence carrying metadata rather than audio
+        if let Some(gapless) = info_tag {
+            if let Some(info) = gapless {
+                self.gapless = Some(GaplessTrimmer::new(info));
+            }
+            buffer.samples.clear();
+            buffer.duration = std::time::Duration::ZERO;
+            return Ok(buffer);
+        }
+        if let Some(trimmer) = &mut self.gapless {
+            trimmer.trim(&mut buffer, packet.pts, packet.is_last);
+        }
+        Ok(buffer)
+    }
+
+    fn set_gapless_info(&mut self, info: GaplessInfo) {
+        self.gapless = Some(GaplessTrimmer::new(info));
     }
 
     fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
         // minimp3 doesn't buffer frames, so nothing to flush
+        self.reset();
         Ok(vec![])
     }
+
+    fn reset(&mut self) {
+        // Drops the bit reservoir, which belongs to the frames before a seek
+        self.decoder = Decoder::new(VecDeque::new());
+        self.started = false;
+        if let Some(trimmer) = &mut self.gapless {
+            trimmer.reset();
+        }
+    }
 }
 
 #[cfg(test)]
@@ -10,6 +10,15 @@ use opus::{Channels, Decoder};
 /// Decodes Opus-encoded audio packets into PCM audio samples.
 /// Opus supports sample rates of 8000, 12000, 16000, 24000, and 48000 Hz.
 ///
+/// The pre-skip signalled in the `OpusHead` passed to
+/// [`AudioDecoder::configure`] is dropped from the start of the stream, and
+/// again after [`AudioDecoder::reset`] while the decoder state converges.
+///
+/// Lost packets are filled in with [`decode_plc`](Self::decode_plc), or
+/// recovered from the in-band FEC data of the following packet with
+/// [`decode_fec`](Self::decode_fec); [`AudioDecoder::conceal_loss`] picks
+/// between the two.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -21,6 +30,8 @@ use opus::{Channels, Decoder};
 ///     data: vec![/* opus data */],
 ///     pts: Some(0),
 ///     dts: Some(0),
+///     is_last: false,
+///     encryption: None,
 /// };
 /// let buffer = decoder.decode(&packet).expect("Failed to decode");
 /// ```
@@ -28,8 +39,24 @@ pub struct OpusDecoder {
     decoder: Decoder,
     sample_rate: u32,
     channels: u8,
+    /// Pre-skip from the `OpusHead`, in samples per channel at `sample_rate`
+    pre_skip: u32,
+    /// Samples per channel still to drop from the decoded output
+    skip: u32,
+    /// Presentation timestamp following the last decoded frame, in samples
+    /// per channel at `sample_rate`
+    next_pts: Option<i64>,
 }
 
+/// Size of an `OpusHead` with channel mapping family 0
+const OPUS_HEAD_SIZE: usize = 19;
+
+/// Sample rate that the `OpusHead` pre-skip is counted at
+const OPUS_HEAD_RATE: u32 = 48000;
+
+/// Maximum Opus frame duration of 120 ms, in samples per channel at 48 kHz
+const MAX_FRAME_SIZE: usize = 5760;
+
 impl OpusDecoder {
     /// Create a new Opus decoder
     ///
@@ -82,38 +109,114 @@ impl OpusDecoder {
             decoder,
             sample_rate,
             channels,
+            pre_skip: 0,
+            skip: 0,
+            next_pts: None,
         })
     }
-}
 
-impl AudioDecoder for OpusDecoder {
-    fn decode(&mut self, packet: &AudioPacket) -> Result<AudioBuffer, MediaError> {
-        if packet.data.is_empty() {
+    /// Synthesize concealment audio for a lost packet
+    ///
+    /// The decoder extrapolates from the frames decoded so far, fading to
+    /// silence over consecutive losses. The buffer is timestamped to follow
+    /// the last decoded frame.
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_count` - Samples per channel to synthesize, normally the
+    ///   duration of the lost packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::InvalidParameter` if `sample_count` is not a
+    /// multiple of 2.5 ms or exceeds the 120 ms maximum frame duration.
+    pub fn decode_plc(&mut self, sample_count: usize) -> Result<AudioBuffer, MediaError> {
+        let granule = self.sample_rate as usize / 400;
+        if sample_count == 0
+            || !sample_count.is_multiple_of(granule)
+            || sample_count > self.max_frame_size()
+        {
+            return Err(MediaError::InvalidParameter(format!(
+                "Opus concealment needs a multiple of {} samples up to {}, got {}",
+                granule,
+                self.max_frame_size(),
+                sample_count
+            )));
+        }
+
+        let pts = self.next_pts;
+        self.decode_frame(&[], sample_count, false, pts)
+    }
+
+    /// Recover a lost packet from the in-band FEC data of the packet after it
+    ///
+    /// Encoders with in-band FEC enabled embed a low-bitrate copy (LBRR) of
+    /// each frame in the next packet. When `next_packet` carries none, the
+    /// lost frame is concealed as by [`decode_plc`](Self::decode_plc).
+    /// `next_packet` itself must still be decoded afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::CodecError` if `next_packet` is empty or not a
+    /// valid Opus packet.
+    pub fn decode_fec(&mut self, next_packet: &AudioPacket) -> Result<AudioBuffer, MediaError> {
+        if next_packet.data.is_empty() {
             return Err(MediaError::CodecError {
-                details: "Cannot decode empty packet".to_string(),
+                details: "Cannot recover from empty packet".to_string(),
             });
         }
 
-        // Opus frames are typically 2.5, 5, 10, 20, 40, or 60 ms
-        // For 48kHz, 20ms = 960 samples per channel
-        // Maximum frame size for Opus is 120ms at 48kHz = 5760 samples
-        let max_frame_size = 5760;
-        let mut output = vec![0f32; max_frame_size * self.channels as usize];
+        // The lost frame is taken to be as long as the one after it
+        let frame_size = self
+            .decoder
+            .get_nb_samples(&next_packet.data)
+            .map_err(|e| MediaError::CodecError {
+                details: format!("Invalid Opus packet: {}", e),
+            })?;
+        let pts = next_packet
+            .pts
+            .map(|pts| pts - frame_size as i64)
+            .or(self.next_pts);
+        self.decode_frame(&next_packet.data, frame_size, true, pts)
+    }
+
+    /// Maximum frame duration in samples per channel at `sample_rate`
+    fn max_frame_size(&self) -> usize {
+        MAX_FRAME_SIZE * self.sample_rate as usize / OPUS_HEAD_RATE as usize
+    }
+
+    /// Decode up to `frame_size` samples per channel from `data`, or
+    /// conceal a lost frame when `data` is empty
+    fn decode_frame(
+        &mut self,
+        data: &[u8],
+        frame_size: usize,
+        fec: bool,
+        pts: Option<i64>,
+    ) -> Result<AudioBuffer, MediaError> {
+        let mut output = vec![0f32; frame_size * self.channels as usize];
 
-        // Decode the Opus packet
         let samples_decoded = self
             .decoder
-            .decode_float(&packet.data, &mut output, false)
+            .decode_float(data, &mut output, fec)
             .map_err(|e| MediaError::CodecError {
                 details: format!("Opus decoding failed: {}", e),
             })?;
 
         // Truncate to actual decoded size
         output.truncate(samples_decoded * self.channels as usize);
+        self.next_pts = pts.map(|pts| pts + samples_decoded as i64);
+
+        // Drop the pre-skip, which may span several packets
+        let skipped = (self.skip as usize).min(samples_decoded);
+        self.skip -= skipped as u32;
+        output.drain(..skipped * self.channels as usize);
+        let samples_decoded = samples_decoded - skipped;
 
         // Calculate timestamp and duration
-        let timestamp = if let Some(pts) = packet.pts {
-            std::time::Duration::from_secs_f64(pts as f64 / self.sample_rate as f64)
+        let timestamp = if let Some(pts) = pts {
+            let start = (pts + skipped as i64).max(0);
+            std::time::Duration::from_secs_f64(start as f64 / self.sample_rate as f64)
         } else {
             std::time::Duration::ZERO
         };
@@ -127,13 +230,64 @@ impl AudioDecoder for OpusDecoder {
             duration: std::time::Duration::from_secs_f64(
                 samples_decoded as f64 / self.sample_rate as f64,
             ),
+            channel_map: None,
         })
     }
+}
+
+impl AudioDecoder for OpusDecoder {
+    fn decode(&mut self, packet: &AudioPacket) -> Result<AudioBuffer, MediaError> {
+        if packet.data.is_empty() {
+            return Err(MediaError::CodecError {
+                details: "Cannot decode empty packet".to_string(),
+            });
+        }
+
+        // Opus frames are typically 2.5, 5, 10, 20, 40, or 60 ms
+        // For 48kHz, 20ms = 960 samples per channel
+        // Maximum frame size for Opus is 120ms
+        let max_frame_size = self.max_frame_size();
+        self.decode_frame(&packet.data, max_frame_size, false, packet.pts)
+    }
+
+    /// Takes the `OpusHead` identification header
+    fn configure(&mut self, extradata: &[u8]) -> Result<(), MediaError> {
+        if extradata.len() < OPUS_HEAD_SIZE || !extradata.starts_with(b"OpusHead") {
+            return Err(MediaError::CodecError {
+                details: "Invalid OpusHead".to_string(),
+            });
+        }
+        let pre_skip = u16::from_le_bytes([extradata[10], extradata[11]]);
+        self.pre_skip =
+            (u64::from(pre_skip) * u64::from(self.sample_rate) / u64::from(OPUS_HEAD_RATE)) as u32;
+        self.skip = self.pre_skip;
+        Ok(())
+    }
 
     fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
         // Opus decoder doesn't buffer frames, so nothing to flush
         Ok(vec![])
     }
+
+    fn reset(&mut self) {
+        // Only fails for an invalid decoder, which `new` never creates
+        let _ = self.decoder.reset_state();
+        // The output converges again after the pre-skip, as at the start of
+        // the stream
+        self.skip = self.pre_skip;
+        self.next_pts = None;
+    }
+
+    fn conceal_loss(
+        &mut self,
+        next: Option<&AudioPacket>,
+        sample_count: usize,
+    ) -> Result<AudioBuffer, MediaError> {
+        match next {
+            Some(next) if !next.data.is_empty() => self.decode_fec(next),
+            _ => self.decode_plc(sample_count),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -157,4 +311,21 @@ mod tests {
         let decoder = OpusDecoder::new(48000, 0);
         assert!(decoder.is_err());
     }
+
+    #[test]
+    fn test_opus_decoder_plc_rejects_partial_frame() {
+        let mut decoder = OpusDecoder::new(48000, 2).unwrap();
+        assert!(matches!(
+            decoder.decode_plc(100),
+            Err(MediaError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            decoder.decode_plc(0),
+            Err(MediaError::InvalidParameter(_))
+        ));
+        assert!(matches!(
+            decoder.decode_plc(5760 + 120),
+            Err(MediaError::InvalidParameter(_))
+        ));
+    }
 }
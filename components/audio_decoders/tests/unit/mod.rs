@@ -2,5 +2,6 @@
 
 mod test_aac;
 mod test_factory;
+mod test_flac;
 mod test_mp3;
 mod test_opus;
@@ -4,7 +4,7 @@
 
 use cortenbrowser_audio_decoders::AACDecoder;
 use cortenbrowser_shared_types::{
-    AACProfile, AudioBuffer, AudioCodec, AudioDecoder, AudioPacket, MediaError,
+    AACProfile, AudioBuffer, AudioCodec, AudioDecoder, AudioPacket, GaplessInfo, MediaError,
 };
 use std::time::Duration;
 
@@ -48,6 +48,8 @@ fn test_aac_decoder_decode_aac_packet() {
         data: aac_frame,
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -77,6 +79,8 @@ fn test_aac_decoder_handles_invalid_data() {
         data: vec![0x00, 0x00, 0x00, 0x00], // Not valid AAC data
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -100,6 +104,8 @@ fn test_aac_decoder_handles_empty_packet() {
         data: vec![],
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -126,3 +132,118 @@ fn test_aac_decoder_flush_returns_empty() {
     assert!(result.is_ok(), "Flush should succeed");
     // AAC may or may not buffer frames depending on implementation
 }
+
+/// Raw AAC LC frame of silence: a single channel element with no scale
+/// factor bands, then the end element and zero bytes up to a size Symphonia
+/// can probe
+const SILENT_FRAME: [u8; 16] = [0x01, 0x00, 0x00, 0x07, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+#[test]
+fn test_aac_decoder_trims_priming_and_padding() {
+    /**
+     * Given a mono 44.1 kHz AAC stream with the usual 2112 priming samples
+     * and 500 padding samples signalled by the container
+     * When decoding 4 frames of 1024 samples, the last one marked as such
+     * Then exactly 4 * 1024 - 2112 - 500 = 1484 samples remain
+     */
+    // Given
+    let mut decoder = AACDecoder::new().expect("Decoder should be created");
+    decoder
+        .configure(&[0x12, 0x08])
+        .expect("AudioSpecificConfig should be accepted");
+    decoder.set_gapless_info(GaplessInfo {
+        encoder_delay: 2112,
+        padding: 500,
+    });
+
+    // When
+    let buffers: Vec<AudioBuffer> = (0..4)
+        .map(|i| {
+            let packet = AudioPacket {
+                data: SILENT_FRAME.to_vec(),
+                pts: Some(i * 1024),
+                dts: Some(i * 1024),
+                is_last: i == 3,
+                encryption: None,
+            };
+            decoder.decode(&packet).expect("Frame should decode")
+        })
+        .collect();
+
+    // Then
+    let lengths: Vec<usize> = buffers.iter().map(|b| b.samples.len()).collect();
+    assert_eq!(lengths, vec![0, 0, 960, 524]);
+    assert_eq!(lengths.iter().sum::<usize>(), 1484);
+    assert_eq!(buffers[2].timestamp, Duration::ZERO);
+    assert_eq!(buffers[3].timestamp, buffers[2].duration);
+}
+
+#[test]
+fn test_aac_decoder_flush_restarts_trimming() {
+    /**
+     * Given an AAC decoder with gapless information that has decoded a frame
+     * When flushing and decoding packets without timestamps
+     * Then the priming samples are trimmed again from the restarted stream
+     */
+    // Given
+    let mut decoder = AACDecoder::new().expect("Decoder should be created");
+    decoder
+        .configure(&[0x12, 0x08])
+        .expect("AudioSpecificConfig should be accepted");
+    decoder.set_gapless_info(GaplessInfo {
+        encoder_delay: 1000,
+        padding: 0,
+    });
+    let packet = AudioPacket {
+        data: SILENT_FRAME.to_vec(),
+        ..Default::default()
+    };
+    decoder.decode(&packet).expect("Frame should decode");
+
+    // When
+    decoder.flush().expect("Flush should succeed");
+    let buffer = decoder.decode(&packet).expect("Frame should decode");
+
+    // Then
+    assert_eq!(buffer.samples.len(), 24);
+}
+
+#[test]
+fn test_aac_decoder_reset_matches_new_decoder() {
+    /**
+     * Given an AAC decoder with gapless information that has decoded frames
+     * When resetting and decoding a packet without a timestamp
+     * Then the output is that of a new decoder, with the priming samples
+     *      trimmed again
+     */
+    // Given
+    let gapless = GaplessInfo {
+        encoder_delay: 1000,
+        padding: 0,
+    };
+    let new_decoder = || {
+        let mut decoder = AACDecoder::new().expect("Decoder should be created");
+        decoder
+            .configure(&[0x12, 0x08])
+            .expect("AudioSpecificConfig should be accepted");
+        decoder.set_gapless_info(gapless);
+        decoder
+    };
+    let packet = AudioPacket {
+        data: SILENT_FRAME.to_vec(),
+        ..Default::default()
+    };
+    let mut decoder = new_decoder();
+    for _ in 0..2 {
+        decoder.decode(&packet).expect("Frame should decode");
+    }
+
+    // When
+    decoder.reset();
+    let buffer = decoder.decode(&packet).expect("Frame should decode");
+
+    // Then
+    let expected = new_decoder().decode(&packet).expect("Frame should decode");
+    assert_eq!(buffer.samples, expected.samples);
+    assert_eq!(buffer.samples.len(), 24);
+}
@@ -72,7 +72,7 @@ fn test_factory_creates_aac_decoder() {
 #[test]
 fn test_factory_rejects_unsupported_codec() {
     /**
-     * Given an unsupported codec (Vorbis, FLAC, PCM)
+     * Given an unsupported codec (Vorbis, PCM)
      * When creating decoder via factory
      * Then error is returned indicating unsupported format
      */
@@ -90,11 +90,11 @@ fn test_factory_rejects_unsupported_codec() {
 }
 
 #[test]
-fn test_factory_rejects_flac() {
+fn test_factory_creates_flac_decoder() {
     /**
      * Given FLAC codec
      * When creating decoder via factory
-     * Then error is returned (FLAC not implemented)
+     * Then FLAC decoder is created successfully
      */
     // Given
     let codec = AudioCodec::FLAC;
@@ -103,7 +103,7 @@ fn test_factory_rejects_flac() {
     let result = DecoderFactory::create_decoder(codec);
 
     // Then
-    assert!(result.is_err(), "Factory should reject FLAC");
+    assert!(result.is_ok(), "Factory should create FLAC decoder");
 }
 
 #[test]
@@ -153,3 +153,80 @@ fn test_factory_handles_opus_invalid_params() {
         "Factory should reject invalid Opus parameters"
     );
 }
+
+#[test]
+fn test_factory_parses_aac_codec_strings() {
+    // Given RFC 6381 AAC identifiers
+    // When parsing them
+    let lc = DecoderFactory::parse_codec_string("mp4a.40.2").unwrap();
+    let he = DecoderFactory::parse_codec_string("mp4a.40.5").unwrap();
+
+    // Then the matching AAC profile is selected
+    assert!(matches!(
+        lc,
+        AudioCodec::AAC {
+            profile: AACProfile::LC,
+            ..
+        }
+    ));
+    assert!(matches!(
+        he,
+        AudioCodec::AAC {
+            profile: AACProfile::HE,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn test_factory_parses_simple_codec_strings() {
+    // Given / When / Then - plain codec names map to their codec
+    assert!(matches!(
+        DecoderFactory::parse_codec_string("opus").unwrap(),
+        AudioCodec::Opus { .. }
+    ));
+    assert!(matches!(
+        DecoderFactory::parse_codec_string("mp3").unwrap(),
+        AudioCodec::MP3 {
+            layer: MP3Layer::Layer3,
+            ..
+        }
+    ));
+    assert_eq!(
+        DecoderFactory::parse_codec_string("vorbis").unwrap(),
+        AudioCodec::Vorbis
+    );
+    assert!(matches!(
+        DecoderFactory::parse_codec_string("OPUS").unwrap(),
+        AudioCodec::Opus { .. }
+    ));
+}
+
+#[test]
+fn test_factory_from_codec_string_creates_decoders() {
+    // Given codec strings for implemented decoders
+    // When creating decoders from them
+    // Then each succeeds
+    for codec in ["mp4a.40.2", "mp4a.40.5", "opus", "mp3"] {
+        assert!(
+            DecoderFactory::from_codec_string(codec).is_ok(),
+            "Factory should create decoder for {}",
+            codec
+        );
+    }
+}
+
+#[test]
+fn test_factory_from_codec_string_rejects_unknown() {
+    // Given a bogus codec string
+    let codec = "bogus.42";
+
+    // When
+    let result = DecoderFactory::from_codec_string(codec);
+
+    // Then the error names the offending string
+    match result {
+        Err(MediaError::UnsupportedFormat { format }) => assert!(format.contains("bogus.42")),
+        _ => panic!("Expected UnsupportedFormat error"),
+    }
+}
@@ -0,0 +1,250 @@
+//! Unit tests for FlacDecoder
+//!
+//! Tests for FLAC audio decoder implementation
+
+use cortenbrowser_audio_decoders::FlacDecoder;
+use cortenbrowser_shared_types::{AudioDecoder, AudioPacket, MediaError};
+use std::time::Duration;
+
+/// Samples per channel in each fixture frame
+const BLOCK_SIZE: u16 = 16;
+
+/// STREAMINFO metadata block (header and body) for fixed 16-sample blocks
+fn stream_info_block(sample_rate: u32, channels: u8, bits_per_sample: u8) -> Vec<u8> {
+    let mut block = vec![0x80, 0x00, 0x00, 0x22];
+    block.extend_from_slice(&BLOCK_SIZE.to_be_bytes());
+    block.extend_from_slice(&BLOCK_SIZE.to_be_bytes());
+    block.extend_from_slice(&[0; 6]);
+    let packed = (u64::from(sample_rate) << 44)
+        | (u64::from(channels - 1) << 41)
+        | (u64::from(bits_per_sample - 1) << 36);
+    block.extend_from_slice(&packed.to_be_bytes());
+    block.extend_from_slice(&[0; 16]);
+    block
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// FLAC frame storing each channel in a verbatim subframe
+fn flac_frame(
+    frame_number: u8,
+    bits_per_sample: u8,
+    channels: &[[i32; BLOCK_SIZE as usize]],
+) -> Vec<u8> {
+    let size_code = match bits_per_sample {
+        16 => 4,
+        24 => 6,
+        _ => unreachable!("fixture supports 16 and 24 bits"),
+    };
+    let mut frame = vec![
+        0xFF,
+        0xF8,
+        // 8-bit block size at the end of the header, sample rate from STREAMINFO
+        0x60,
+        ((channels.len() as u8 - 1) << 4) | (size_code << 1),
+        frame_number,
+        (BLOCK_SIZE - 1) as u8,
+    ];
+    frame.push(crc8(&frame));
+
+    let bytes = usize::from(bits_per_sample / 8);
+    for samples in channels {
+        // Verbatim subframe without wasted bits
+        frame.push(0x02);
+        for sample in samples {
+            frame.extend_from_slice(&sample.to_be_bytes()[4 - bytes..]);
+        }
+    }
+    let crc = crc16(&frame);
+    frame.extend_from_slice(&crc.to_be_bytes());
+    frame
+}
+
+fn packet(data: Vec<u8>, pts: Option<i64>) -> AudioPacket {
+    AudioPacket {
+        data,
+        pts,
+        dts: pts,
+        is_last: false,
+        encryption: None,
+    }
+}
+
+#[test]
+fn test_flac_decoder_new_creates_decoder() {
+    /**
+     * Given no parameters
+     * When creating new FlacDecoder
+     * Then decoder is created successfully
+     */
+    // When
+    let result = FlacDecoder::new();
+
+    // Then
+    assert!(result.is_ok(), "FlacDecoder creation should succeed");
+}
+
+#[test]
+fn test_flac_decoder_decodes_16bit_matroska_frames() {
+    /**
+     * Given a decoder configured with Matroska FLAC CodecPrivate
+     * When decoding two 16-bit stereo frames without timestamps
+     * Then samples are scaled exactly and interleaved, and timestamps follow
+     *      the frames
+     */
+    // Given
+    let mut decoder = FlacDecoder::new().unwrap();
+    let codec_private = [&b"fLaC"[..], &stream_info_block(8000, 2, 16)].concat();
+    decoder.configure(&codec_private).unwrap();
+
+    let mut left = [0; BLOCK_SIZE as usize];
+    left[..3].copy_from_slice(&[16384, -32768, 1]);
+    let mut right = [0; BLOCK_SIZE as usize];
+    right[..3].copy_from_slice(&[-16384, 32767, -1]);
+
+    // When
+    let first = decoder
+        .decode(&packet(flac_frame(0, 16, &[left, right]), None))
+        .unwrap();
+    let second = decoder
+        .decode(&packet(flac_frame(1, 16, &[left, right]), None))
+        .unwrap();
+
+    // Then
+    assert_eq!(first.sample_rate, 8000);
+    assert_eq!(first.channels, 2);
+    assert_eq!(first.samples.len(), 32);
+    assert_eq!(
+        &first.samples[..6],
+        &[
+            0.5,
+            -0.5,
+            -1.0,
+            32767.0 / 32768.0,
+            1.0 / 32768.0,
+            -1.0 / 32768.0
+        ]
+    );
+    assert_eq!(first.timestamp, Duration::ZERO);
+    assert_eq!(first.duration, Duration::from_millis(2));
+    assert_eq!(second.samples, first.samples);
+    assert_eq!(second.timestamp, Duration::from_millis(2));
+}
+
+#[test]
+fn test_flac_decoder_decodes_24bit_ogg_stream() {
+    /**
+     * Given an unconfigured decoder and an Ogg FLAC stream
+     * When decoding the mapping header, a metadata packet and two 24-bit
+     *      mono frames with timestamps
+     * Then header packets decode to no samples and frame samples are scaled
+     *      exactly with timestamps from the packets
+     */
+    // Given
+    let mut decoder = FlacDecoder::new().unwrap();
+    let mapping_header = [
+        &b"\x7FFLAC\x01\x00\x00\x01fLaC"[..],
+        &stream_info_block(48000, 1, 24),
+    ]
+    .concat();
+    // Last metadata block: an empty VORBIS_COMMENT
+    let comment = vec![0x84, 0x00, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0, 0];
+
+    let mut samples = [0; BLOCK_SIZE as usize];
+    samples[..4].copy_from_slice(&[4194304, -8388608, 1, 8388607]);
+
+    // When
+    let header = decoder.decode(&packet(mapping_header, None)).unwrap();
+    let tags = decoder.decode(&packet(comment, None)).unwrap();
+    let first = decoder
+        .decode(&packet(flac_frame(0, 24, &[samples]), Some(0)))
+        .unwrap();
+    let second = decoder
+        .decode(&packet(flac_frame(1, 24, &[samples]), Some(16)))
+        .unwrap();
+
+    // Then
+    assert!(header.samples.is_empty());
+    assert!(tags.samples.is_empty());
+    assert_eq!(first.sample_rate, 48000);
+    assert_eq!(first.channels, 1);
+    assert_eq!(
+        &first.samples[..4],
+        &[0.5, -1.0, 1.0 / 8388608.0, 8388607.0 / 8388608.0]
+    );
+    assert_eq!(first.timestamp, Duration::ZERO);
+    assert_eq!(second.timestamp, Duration::from_secs_f64(16.0 / 48000.0));
+    assert_eq!(second.duration, Duration::from_secs_f64(16.0 / 48000.0));
+}
+
+#[test]
+fn test_flac_decoder_rejects_frame_before_stream_info() {
+    /**
+     * Given an unconfigured decoder
+     * When decoding a FLAC frame
+     * Then a codec error is returned
+     */
+    // Given
+    let mut decoder = FlacDecoder::new().unwrap();
+    let frame = flac_frame(0, 16, &[[0; BLOCK_SIZE as usize]]);
+
+    // When
+    let result = decoder.decode(&packet(frame, Some(0)));
+
+    // Then
+    assert!(matches!(result, Err(MediaError::CodecError { .. })));
+}
+
+#[test]
+fn test_flac_decoder_reset_restarts_position() {
+    /**
+     * Given a configured decoder that has decoded two frames without
+     *      timestamps
+     * When resetting and decoding a frame without a timestamp
+     * Then its samples are unchanged and its timestamp restarts at zero
+     */
+    // Given
+    let mut decoder = FlacDecoder::new().unwrap();
+    let codec_private = [&b"fLaC"[..], &stream_info_block(8000, 1, 16)].concat();
+    decoder.configure(&codec_private).unwrap();
+    let mut samples = [0; BLOCK_SIZE as usize];
+    samples[..2].copy_from_slice(&[1000, -1000]);
+    let first = decoder
+        .decode(&packet(flac_frame(0, 16, &[samples]), None))
+        .unwrap();
+    decoder
+        .decode(&packet(flac_frame(1, 16, &[samples]), None))
+        .unwrap();
+
+    // When
+    decoder.reset();
+    let after_reset = decoder
+        .decode(&packet(flac_frame(0, 16, &[samples]), None))
+        .unwrap();
+
+    // Then
+    assert_eq!(after_reset.samples, first.samples);
+    assert_eq!(after_reset.timestamp, Duration::ZERO);
+}
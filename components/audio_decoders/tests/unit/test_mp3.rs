@@ -46,6 +46,8 @@ fn test_mp3_decoder_decode_mp3_packet() {
         data: mp3_frame,
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -76,6 +78,8 @@ fn test_mp3_decoder_handles_invalid_data() {
         data: vec![0x00, 0x00, 0x00, 0x00], // Not valid MP3 data
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -99,6 +103,8 @@ fn test_mp3_decoder_handles_empty_packet() {
         data: vec![],
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -124,11 +130,15 @@ fn test_mp3_decoder_decode_multiple_frames() {
         data: mp3_frame.clone(),
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
     let packet2 = AudioPacket {
         data: mp3_frame.clone(),
         pts: Some(1152), // MP3 Layer III frame size
         dts: Some(1152),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -162,3 +172,141 @@ fn test_mp3_decoder_flush_returns_empty() {
     let buffers = result.unwrap();
     assert_eq!(buffers.len(), 0, "MP3 decoder should not buffer frames");
 }
+
+/// Size of an MPEG-1 Layer III frame at 128 kbps and 44.1 kHz
+const FRAME_SIZE: usize = 417;
+
+/// Silent MPEG-1 Layer III stereo frame: a header, zeroed side information
+/// and no main data
+fn silent_frame() -> Vec<u8> {
+    let mut frame = vec![0; FRAME_SIZE];
+    frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+    frame
+}
+
+/// Info frame with a LAME tag carrying the encoder delay and padding
+fn info_frame(delay: u16, padding: u16) -> Vec<u8> {
+    let mut frame = silent_frame();
+    // Xing tag after the 32 bytes of side information, with the frame count,
+    // byte count, seek table and quality fields
+    let mut tag = b"Info".to_vec();
+    tag.extend_from_slice(&0x0Fu32.to_be_bytes());
+    tag.extend_from_slice(&3u32.to_be_bytes());
+    tag.extend_from_slice(&((4 * FRAME_SIZE) as u32).to_be_bytes());
+    tag.extend_from_slice(&[0; 104]);
+    tag.extend_from_slice(b"LAME3.100");
+    tag.extend_from_slice(&[0; 12]);
+    tag.extend_from_slice(&[
+        (delay >> 4) as u8,
+        ((delay & 0x0F) << 4) as u8 | (padding >> 8) as u8,
+        padding as u8,
+    ]);
+    frame[36..36 + tag.len()].copy_from_slice(&tag);
+    frame
+}
+
+fn packet(data: Vec<u8>, pts: Option<i64>, is_last: bool) -> AudioPacket {
+    AudioPacket {
+        data,
+        pts,
+        dts: pts,
+        is_last,
+        encryption: None,
+    }
+}
+
+#[test]
+fn test_mp3_decoder_trims_lame_delay_and_padding() {
+    /**
+     * Given an MP3 stream with an Info frame signalling 576 samples of
+     * encoder delay and 1200 of padding, followed by 3 frames
+     * When decoding every frame, the last one marked as such
+     * Then the 529-sample decoder delay is accounted for and exactly
+     * 3 * 1152 - (576 + 529) - (1200 - 529) = 1680 samples per channel remain
+     */
+    // Given
+    let mut decoder = MP3Decoder::new().expect("Decoder should be created");
+
+    // When
+    let info = decoder
+        .decode(&packet(info_frame(576, 1200), None, false))
+        .expect("Info frame should decode");
+    let buffers: Vec<AudioBuffer> = (0..3)
+        .map(|i| {
+            decoder
+                .decode(&packet(silent_frame(), Some(i * 1152), i == 2))
+                .expect("Frame should decode")
+        })
+        .collect();
+
+    // Then
+    assert!(
+        info.samples.is_empty(),
+        "Info frame should produce no audio"
+    );
+    let frames: Vec<usize> = buffers.iter().map(|b| b.samples.len() / 2).collect();
+    assert_eq!(frames, vec![47, 1152, 481]);
+    assert_eq!(frames.iter().sum::<usize>(), 1680);
+
+    // Timestamps continue across the trimmed buffers, starting at zero
+    assert_eq!(buffers[0].timestamp, Duration::ZERO);
+    assert_eq!(buffers[1].timestamp, buffers[0].duration);
+    assert_eq!(
+        buffers[2].timestamp,
+        Duration::from_secs_f64(1199.0 / 44100.0)
+    );
+}
+
+#[test]
+fn test_mp3_decoder_without_info_frame_is_untrimmed() {
+    /**
+     * Given an MP3 stream without an Info frame
+     * When decoding its last frame
+     * Then all samples are kept
+     */
+    // Given
+    let mut decoder = MP3Decoder::new().expect("Decoder should be created");
+
+    // When
+    let buffer = decoder
+        .decode(&packet(silent_frame(), Some(0), true))
+        .expect("Frame should decode");
+
+    // Then
+    assert_eq!(buffer.samples.len(), 2 * 1152);
+}
+
+#[test]
+fn test_mp3_decoder_reset_drops_bit_reservoir() {
+    /**
+     * Given an MP3 decoder that has decoded a frame filling the bit reservoir
+     * When resetting and decoding a frame whose main data starts 100 bytes
+     *      back in the reservoir
+     * Then the frame cannot be decoded, as by a new decoder, while without
+     *      the reset it decodes from the previous frame's data
+     */
+    // Given
+    let mut reservoir_frame = silent_frame();
+    // main_data_begin is the first 9 bits of the side information
+    reservoir_frame[4] = 50;
+    let mut decoder = MP3Decoder::new().expect("Decoder should be created");
+    let mut continued = MP3Decoder::new().expect("Decoder should be created");
+    decoder
+        .decode(&packet(silent_frame(), Some(0), false))
+        .expect("Frame should decode");
+    continued
+        .decode(&packet(silent_frame(), Some(0), false))
+        .expect("Frame should decode");
+
+    // When
+    decoder.reset();
+    let after_reset = decoder.decode(&packet(reservoir_frame.clone(), Some(1152), false));
+    let without_reset = continued.decode(&packet(reservoir_frame, Some(1152), false));
+
+    // Then
+    assert!(matches!(after_reset, Err(MediaError::CodecError { .. })));
+    assert_eq!(
+        without_reset.expect("Frame should decode").samples.len(),
+        2 * 1152
+    );
+}
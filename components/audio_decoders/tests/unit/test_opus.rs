@@ -91,6 +91,8 @@ fn test_opus_decoder_decode_opus_packet() {
         data: vec![0xFC], // TOC byte for Opus stereo, 20ms frame
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -120,11 +122,15 @@ fn test_opus_decoder_maintains_state_across_packets() {
         data: vec![0xFC],
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
     let packet2 = AudioPacket {
         data: vec![0xFC],
         pts: Some(960), // Next frame
         dts: Some(960),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -156,6 +162,8 @@ fn test_opus_decoder_handles_empty_packet() {
         data: vec![],
         pts: Some(0),
         dts: Some(0),
+        is_last: false,
+        encryption: None,
     };
 
     // When
@@ -185,3 +193,196 @@ fn test_opus_decoder_flush_returns_empty() {
     let buffers = result.unwrap();
     assert_eq!(buffers.len(), 0, "Opus decoder should not buffer frames");
 }
+
+/// `OpusHead` for a stereo 48 kHz stream with channel mapping family 0
+fn opus_head(pre_skip: u16) -> Vec<u8> {
+    let mut head = b"OpusHead\x01\x02".to_vec();
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&48000u32.to_le_bytes());
+    head.extend_from_slice(&[0, 0, 0]);
+    head
+}
+
+#[test]
+fn test_opus_decoder_reset_reapplies_pre_skip() {
+    /**
+     * Given an Opus decoder configured with 312 samples of pre-skip that has
+     *      decoded two packets
+     * When resetting and decoding the first packet again
+     * Then the pre-skip is dropped again and the output is that of the
+     *      first packet, with no state left from the packets before the reset
+     */
+    // Given
+    let mut decoder = OpusDecoder::new(48000, 2).expect("Decoder should be created");
+    decoder
+        .configure(&opus_head(312))
+        .expect("OpusHead should be accepted");
+    let first_packet = AudioPacket {
+        data: vec![0xFC],
+        pts: Some(-312),
+        dts: Some(-312),
+        is_last: false,
+        encryption: None,
+    };
+    let second_packet = AudioPacket {
+        data: vec![0xFC],
+        pts: Some(648),
+        dts: Some(648),
+        is_last: false,
+        encryption: None,
+    };
+    let first = decoder.decode(&first_packet).expect("Packet should decode");
+    decoder
+        .decode(&second_packet)
+        .expect("Packet should decode");
+
+    // When
+    decoder.reset();
+    let after_reset = decoder.decode(&first_packet).expect("Packet should decode");
+
+    // Then
+    assert_eq!(first.samples.len(), 2 * (960 - 312));
+    assert_eq!(first.timestamp, Duration::ZERO);
+    assert_eq!(after_reset.samples, first.samples);
+    assert_eq!(after_reset.timestamp, first.timestamp);
+}
+
+#[test]
+fn test_opus_decoder_rejects_invalid_opus_head() {
+    /**
+     * Given an Opus decoder
+     * When configuring it with data that is not an OpusHead
+     * Then a codec error is returned
+     */
+    // Given
+    let mut decoder = OpusDecoder::new(48000, 2).expect("Decoder should be created");
+
+    // When
+    let result = decoder.configure(b"OpusTags");
+
+    // Then
+    assert!(matches!(result, Err(MediaError::CodecError { .. })));
+}
+
+/// Encode one second of a 440 Hz tone as 20 ms mono 48 kHz packets with
+/// in-band FEC
+fn encode_tone_with_fec() -> Vec<AudioPacket> {
+    let mut encoder = opus::Encoder::new(48000, opus::Channels::Mono, opus::Application::Voip)
+        .expect("Encoder should be created");
+    encoder.set_inband_fec(true).expect("FEC should be enabled");
+    encoder
+        .set_packet_loss_perc(10)
+        .expect("Loss percentage should be set");
+
+    (0..50)
+        .map(|frame| {
+            let pcm: Vec<f32> = (0..960)
+                .map(|i| {
+                    let t = (frame * 960 + i) as f32 / 48000.0;
+                    0.5 * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+                })
+                .collect();
+            AudioPacket {
+                data: encoder
+                    .encode_vec_float(&pcm, 4000)
+                    .expect("Frame should encode"),
+                pts: Some(frame as i64 * 960),
+                dts: Some(frame as i64 * 960),
+                is_last: false,
+                encryption: None,
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_opus_decoder_plc_preserves_length_with_lost_packets() {
+    /**
+     * Given an encoded stream with every 10th packet lost
+     * When concealing each lost packet with decode_plc
+     * Then the output is as long as the stream and timestamps stay contiguous
+     */
+    // Given
+    let packets = encode_tone_with_fec();
+    let mut decoder = OpusDecoder::new(48000, 1).expect("Decoder should be created");
+
+    // When
+    let mut buffers = Vec::new();
+    for (i, packet) in packets.iter().enumerate() {
+        if i % 10 == 9 {
+            buffers.push(decoder.decode_plc(960).expect("Loss should be concealed"));
+        } else {
+            buffers.push(decoder.decode(packet).expect("Packet should decode"));
+        }
+    }
+
+    // Then
+    let total: usize = buffers.iter().map(|b| b.samples.len()).sum();
+    assert_eq!(total, packets.len() * 960);
+    for (i, buffer) in buffers.iter().enumerate() {
+        assert_eq!(buffer.timestamp, Duration::from_millis(i as u64 * 20));
+        assert!(buffer.samples.iter().all(|s| s.is_finite()));
+    }
+}
+
+#[test]
+fn test_opus_decoder_fec_preserves_length_with_lost_packets() {
+    /**
+     * Given an encoded stream with in-band FEC and every 10th packet lost
+     * When recovering each lost packet from the one after it with decode_fec
+     * Then the output is as long as the stream and the recovered frames
+     *      carry signal
+     */
+    // Given
+    let packets = encode_tone_with_fec();
+    let mut decoder = OpusDecoder::new(48000, 1).expect("Decoder should be created");
+
+    // When
+    let mut buffers = Vec::new();
+    for (i, packet) in packets.iter().enumerate() {
+        if i % 10 == 9 {
+            let recovered = match packets.get(i + 1) {
+                Some(next) => decoder.decode_fec(next),
+                None => decoder.decode_plc(960),
+            };
+            buffers.push(recovered.expect("Loss should be recovered"));
+        } else {
+            buffers.push(decoder.decode(packet).expect("Packet should decode"));
+        }
+    }
+
+    // Then
+    let total: usize = buffers.iter().map(|b| b.samples.len()).sum();
+    assert_eq!(total, packets.len() * 960);
+    assert_eq!(buffers[19].timestamp, Duration::from_millis(19 * 20));
+    let energy: f32 = buffers[19].samples.iter().map(|s| s * s).sum();
+    assert!(energy > 0.0, "Recovered frame should not be silent");
+}
+
+#[test]
+fn test_opus_decoder_conceal_loss_without_next_packet() {
+    /**
+     * Given an Opus decoder that has decoded a packet
+     * When concealing a loss with no following packet available
+     * Then the requested number of samples is synthesized
+     */
+    // Given
+    let mut decoder = OpusDecoder::new(48000, 2).expect("Decoder should be created");
+    let packet = AudioPacket {
+        data: vec![0xFC],
+        pts: Some(0),
+        dts: Some(0),
+        is_last: false,
+        encryption: None,
+    };
+    decoder.decode(&packet).expect("Packet should decode");
+
+    // When
+    let buffer = decoder
+        .conceal_loss(None, 960)
+        .expect("Loss should be concealed");
+
+    // Then
+    assert_eq!(buffer.samples.len(), 2 * 960);
+    assert_eq!(buffer.timestamp, Duration::from_millis(20));
+}
@@ -53,6 +53,7 @@ fn frame_cache_insert_benchmark(c: &mut Criterion) {
                 height: 1080,
                 format: PixelFormat::YUV420,
                 data: vec![0u8; frame_size],
+                strides: None,
                 timestamp: Duration::from_secs(0),
                 duration: Some(Duration::from_millis(33)),
                 metadata: FrameMetadata::default(),
@@ -76,6 +77,7 @@ fn frame_cache_get_benchmark(c: &mut Criterion) {
             height: 1080,
             format: PixelFormat::YUV420,
             data: vec![0u8; 1000],
+            strides: None,
             timestamp: Duration::from_secs(i),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -0,0 +1,320 @@
+//! Ring buffer for interleaved audio samples
+//!
+//! A circular FIFO of `f32` samples that audio rendering pulls from while
+//! decoded audio is pushed in. Samples are written and read in whole
+//! frames, one sample per channel, so that channels never shift.
+
+use crate::error::BufferError;
+use crate::TRACING_TARGET;
+
+/// A circular buffer for interleaved audio samples
+///
+/// Holds up to a fixed number of frames of `channels` samples each, at
+/// `sample_rate` frames per second. Writes and reads only ever move whole
+/// frames: either all samples of a frame are in the buffer or none.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_buffer_manager::AudioRingBuffer;
+///
+/// // 10 ms of stereo audio at 48 kHz
+/// let mut buffer = AudioRingBuffer::new(480, 2, 48000).unwrap();
+///
+/// let written = buffer.write_samples(&[0.1, -0.1, 0.2, -0.2]).unwrap();
+/// assert_eq!(written, 4);
+/// assert_eq!(buffer.available_frames(), 2);
+///
+/// let mut out = [0.0f32; 4];
+/// let read = buffer.read_samples(&mut out).unwrap();
+/// assert_eq!(read, 4);
+/// assert_eq!(out, [0.1, -0.1, 0.2, -0.2]);
+/// ```
+#[derive(Debug)]
+pub struct AudioRingBuffer {
+    samples: Vec<f32>,
+    /// Samples per frame
+    channels: u8,
+    /// Frames per second
+    sample_rate: u32,
+    read_pos: usize,
+    write_pos: usize,
+    /// Samples available to read, always a whole number of frames
+    count: usize,
+}
+
+impl AudioRingBuffer {
+    /// Creates a new audio ring buffer holding `capacity_frames` frames
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity_frames` - The maximum number of frames the buffer can hold
+    /// * `channels` - The number of interleaved samples per frame
+    /// * `sample_rate` - The number of frames per second
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::InvalidSize` if `channels` is 0
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::AudioRingBuffer;
+    ///
+    /// let buffer = AudioRingBuffer::new(1024, 2, 44100).unwrap();
+    /// assert_eq!(buffer.capacity_frames(), 1024);
+    /// assert_eq!(buffer.channels(), 2);
+    /// assert_eq!(buffer.sample_rate(), 44100);
+    /// assert_eq!(buffer.available_frames(), 0);
+    /// ```
+    pub fn new(
+        capacity_frames: usize,
+        channels: u8,
+        sample_rate: u32,
+    ) -> Result<Self, BufferError> {
+        if channels == 0 {
+            return Err(BufferError::InvalidSize(
+                "audio frames need at least one channel".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            samples: vec![0.0; capacity_frames * channels as usize],
+            channels,
+            sample_rate,
+            read_pos: 0,
+            write_pos: 0,
+            count: 0,
+        })
+    }
+
+    /// Writes interleaved samples to the buffer
+    ///
+    /// Returns the number of samples written. When there is room for fewer
+    /// frames than given, only the frames that fit are written, so the
+    /// count is always a multiple of `channels`.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Interleaved samples of whole frames
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::InvalidSize` if `samples` ends with a partial
+    /// frame, and `BufferError::BufferFull` if there is no room for a frame
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::{AudioRingBuffer, BufferError};
+    ///
+    /// let mut buffer = AudioRingBuffer::new(2, 2, 48000).unwrap();
+    ///
+    /// // Three stereo frames, of which two fit
+    /// let written = buffer.write_samples(&[0.0; 6]).unwrap();
+    /// assert_eq!(written, 4);
+    /// assert_eq!(buffer.write_samples(&[0.0; 2]), Err(BufferError::BufferFull));
+    /// ```
+    pub fn write_samples(&mut self, samples: &[f32]) -> Result<usize, BufferError> {
+        let channels = self.channels as usize;
+        if !samples.len().is_multiple_of(channels) {
+            return Err(BufferError::InvalidSize(format!(
+                "{} samples are not whole frames of {} channels",
+                samples.len(),
+                channels
+            )));
+        }
+        if samples.is_empty() {
+            return Ok(0);
+        }
+
+        let free_frames = (self.samples.len() - self.count) / channels;
+        if free_frames == 0 {
+            return Err(BufferError::BufferFull);
+        }
+
+        let to_write = (samples.len() / channels).min(free_frames) * channels;
+
+        for &sample in samples.iter().take(to_write) {
+            self.samples[self.write_pos] = sample;
+            self.write_pos = (self.write_pos + 1) % self.samples.len();
+        }
+
+        self.count += to_write;
+        tracing::trace!(
+            target: TRACING_TARGET,
+            wrote_samples = to_write,
+            available_frames = self.available_frames(),
+            capacity_frames = self.capacity_frames(),
+            "Wrote to audio ring buffer"
+        );
+        Ok(to_write)
+    }
+
+    /// Reads interleaved samples from the buffer
+    ///
+    /// Returns the number of samples read. Only whole frames are read, as
+    /// many as are available and fit in `out`, so the count is always a
+    /// multiple of `channels`; samples of `out` past it are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `out` - The buffer to read samples into
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::BufferEmpty` if no frames are available
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::AudioRingBuffer;
+    ///
+    /// let mut buffer = AudioRingBuffer::new(4, 2, 48000).unwrap();
+    /// buffer.write_samples(&[1.0, 2.0, 3.0, 4.0]).unwrap();
+    ///
+    /// // Room for one and a half frames reads one
+    /// let mut out = [0.0f32; 3];
+    /// assert_eq!(buffer.read_samples(&mut out).unwrap(), 2);
+    /// assert_eq!(out, [1.0, 2.0, 0.0]);
+    /// assert_eq!(buffer.available_frames(), 1);
+    /// ```
+    pub fn read_samples(&mut self, out: &mut [f32]) -> Result<usize, BufferError> {
+        if self.count == 0 {
+            return Err(BufferError::BufferEmpty);
+        }
+
+        let channels = self.channels as usize;
+        let to_read = (out.len() / channels * channels).min(self.count);
+
+        for sample in out.iter_mut().take(to_read) {
+            *sample = self.samples[self.read_pos];
+            self.read_pos = (self.read_pos + 1) % self.samples.len();
+        }
+
+        self.count -= to_read;
+        tracing::trace!(
+            target: TRACING_TARGET,
+            read_samples = to_read,
+            available_frames = self.available_frames(),
+            capacity_frames = self.capacity_frames(),
+            "Read from audio ring buffer"
+        );
+        Ok(to_read)
+    }
+
+    /// Returns the number of samples available to read
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::AudioRingBuffer;
+    ///
+    /// let mut buffer = AudioRingBuffer::new(100, 2, 48000).unwrap();
+    /// buffer.write_samples(&[0.0; 8]).unwrap();
+    /// assert_eq!(buffer.available_samples(), 8);
+    /// ```
+    pub fn available_samples(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of frames available to read
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::AudioRingBuffer;
+    ///
+    /// let mut buffer = AudioRingBuffer::new(100, 2, 48000).unwrap();
+    /// buffer.write_samples(&[0.0; 8]).unwrap();
+    /// assert_eq!(buffer.available_frames(), 4);
+    /// ```
+    pub fn available_frames(&self) -> usize {
+        self.count / self.channels as usize
+    }
+
+    /// Returns the total capacity of the buffer in frames
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::AudioRingBuffer;
+    ///
+    /// let buffer = AudioRingBuffer::new(480, 6, 48000).unwrap();
+    /// assert_eq!(buffer.capacity_frames(), 480);
+    /// ```
+    pub fn capacity_frames(&self) -> usize {
+        self.samples.len() / self.channels as usize
+    }
+
+    /// Returns the number of interleaved samples per frame
+    pub fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    /// Returns the number of frames per second
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zero_channels_rejected() {
+        assert!(matches!(
+            AudioRingBuffer::new(100, 0, 48000),
+            Err(BufferError::InvalidSize(_))
+        ));
+    }
+
+    #[test]
+    fn test_partial_frame_not_written() {
+        let mut buffer = AudioRingBuffer::new(10, 2, 48000).unwrap();
+
+        let result = buffer.write_samples(&[0.1, 0.2, 0.3]);
+        assert!(matches!(result, Err(BufferError::InvalidSize(_))));
+        assert_eq!(buffer.available_samples(), 0);
+    }
+
+    #[test]
+    fn test_write_stops_at_frame_boundary() {
+        // Room for 3 stereo frames, of which 2 are taken
+        let mut buffer = AudioRingBuffer::new(3, 2, 48000).unwrap();
+        buffer.write_samples(&[1.0, 1.0, 2.0, 2.0]).unwrap();
+
+        assert_eq!(buffer.write_samples(&[3.0, 3.0, 4.0, 4.0]).unwrap(), 2);
+        assert_eq!(buffer.available_frames(), 3);
+        assert_eq!(
+            buffer.write_samples(&[4.0, 4.0]),
+            Err(BufferError::BufferFull)
+        );
+    }
+
+    #[test]
+    fn test_wraparound_keeps_channels_aligned() {
+        let mut buffer = AudioRingBuffer::new(4, 2, 48000).unwrap();
+        buffer.write_samples(&[0.0; 6]).unwrap();
+        let mut tmp = [0.0f32; 6];
+        buffer.read_samples(&mut tmp).unwrap();
+
+        // Left samples are positive and right ones negative across the end
+        let frames = [1.0, -1.0, 2.0, -2.0, 3.0, -3.0];
+        assert_eq!(buffer.write_samples(&frames).unwrap(), 6);
+
+        let mut out = [0.0f32; 6];
+        assert_eq!(buffer.read_samples(&mut out).unwrap(), 6);
+        assert_eq!(out, frames);
+        assert_eq!(buffer.available_frames(), 0);
+    }
+
+    #[test]
+    fn test_read_from_empty_buffer() {
+        let mut buffer = AudioRingBuffer::new(10, 1, 8000).unwrap();
+        let mut out = [0.0f32; 4];
+
+        assert_eq!(buffer.read_samples(&mut out), Err(BufferError::BufferEmpty));
+    }
+}
@@ -17,7 +17,8 @@ struct CacheEntry {
 /// LRU cache for video frames
 ///
 /// Stores video frames indexed by timestamp with automatic eviction
-/// of least-recently-used frames when capacity is reached.
+/// of least-recently-used frames when capacity is reached. Capacity is a
+/// number of frames, a number of bytes of frame data, or both.
 ///
 /// # Examples
 ///
@@ -33,6 +34,7 @@ struct CacheEntry {
 ///     height: 1080,
 ///     format: PixelFormat::YUV420,
 ///     data: vec![0u8; 100],
+///     strides: None,
 ///     timestamp: Duration::from_secs(1),
 ///     duration: Some(Duration::from_millis(33)),
 ///     metadata: FrameMetadata::default(),
@@ -46,6 +48,9 @@ struct CacheEntry {
 pub struct FrameCache {
     frames: HashMap<Duration, CacheEntry>,
     max_frames: usize,
+    max_bytes: usize,
+    /// Sum of the `data` lengths of the cached frames
+    current_bytes: usize,
     access_counter: u64,
 }
 
@@ -64,16 +69,87 @@ impl FrameCache {
     /// let cache = FrameCache::new(100);
     /// ```
     pub fn new(max_frames: usize) -> Self {
+        Self::new_with_limits(max_frames, usize::MAX)
+    }
+
+    /// Creates a new frame cache holding at most `max_bytes` of frame data
+    ///
+    /// Frame sizes vary widely with resolution, so a byte limit bounds the
+    /// memory used better than a frame count.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_bytes` - Maximum total size of the cached frames' data
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::FrameCache;
+    ///
+    /// let cache = FrameCache::new_with_memory_limit(64 * 1024 * 1024);
+    /// assert_eq!(cache.current_bytes(), 0);
+    /// ```
+    pub fn new_with_memory_limit(max_bytes: usize) -> Self {
+        Self::new_with_limits(usize::MAX, max_bytes)
+    }
+
+    /// Creates a new frame cache limited by both frame count and data size
+    ///
+    /// # Arguments
+    ///
+    /// * `max_frames` - Maximum number of frames to cache
+    /// * `max_bytes` - Maximum total size of the cached frames' data
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::FrameCache;
+    ///
+    /// let cache = FrameCache::new_with_limits(100, 64 * 1024 * 1024);
+    /// ```
+    pub fn new_with_limits(max_frames: usize, max_bytes: usize) -> Self {
         Self {
             frames: HashMap::new(),
             max_frames,
+            max_bytes,
+            current_bytes: 0,
             access_counter: 0,
         }
     }
 
+    /// Gets the total size of the cached frames' data in bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::FrameCache;
+    /// use cortenbrowser_shared_types::{VideoFrame, PixelFormat, FrameMetadata};
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = FrameCache::new_with_memory_limit(1024);
+    /// let frame = VideoFrame {
+    ///     width: 16,
+    ///     height: 16,
+    ///     format: PixelFormat::YUV420,
+    ///     data: vec![0u8; 384],
+    ///     strides: None,
+    ///     timestamp: Duration::from_secs(1),
+    ///     duration: Some(Duration::from_millis(33)),
+    ///     metadata: FrameMetadata::default(),
+    /// };
+    ///
+    /// cache.insert(frame).unwrap();
+    /// assert_eq!(cache.current_bytes(), 384);
+    /// ```
+    pub fn current_bytes(&self) -> usize {
+        self.current_bytes
+    }
+
     /// Inserts a frame into the cache
     ///
-    /// If the cache is full, evicts the least-recently-used frame.
+    /// Evicts least-recently-used frames until the new frame fits within
+    /// both the frame count and the byte limit. A frame with the timestamp
+    /// of a cached frame replaces it.
     ///
     /// # Arguments
     ///
@@ -81,7 +157,8 @@ impl FrameCache {
     ///
     /// # Errors
     ///
-    /// Returns `BufferError::OutOfMemory` if max_frames is 0
+    /// Returns `BufferError::OutOfMemory` if max_frames is 0, or the frame's
+    /// data alone exceeds the byte limit
     ///
     /// # Examples
     ///
@@ -96,6 +173,7 @@ impl FrameCache {
     ///     height: 1080,
     ///     format: PixelFormat::YUV420,
     ///     data: vec![0u8; 100],
+    ///     strides: None,
     ///     timestamp: Duration::from_secs(1),
     ///     duration: Some(Duration::from_millis(33)),
     ///     metadata: FrameMetadata::default(),
@@ -104,23 +182,29 @@ impl FrameCache {
     /// cache.insert(frame).unwrap();
     /// ```
     pub fn insert(&mut self, frame: VideoFrame) -> Result<(), BufferError> {
-        if self.max_frames == 0 {
+        let size = frame.data.len();
+        if self.max_frames == 0 || size > self.max_bytes {
             return Err(BufferError::OutOfMemory);
         }
 
         let timestamp = frame.timestamp;
 
-        // If cache is full and this is a new frame, evict LRU
-        if self.frames.len() >= self.max_frames && !self.frames.contains_key(&timestamp) {
+        // A replaced frame frees its space first
+        self.remove(timestamp);
+
+        // Evict LRU frames until the new frame fits
+        while self.frames.len() >= self.max_frames || self.current_bytes + size > self.max_bytes {
             // Find and remove least recently used frame
-            if let Some((&lru_timestamp, _)) = self.frames.iter()
-                .min_by_key(|(_, entry)| entry.access_count) {
-                self.frames.remove(&lru_timestamp);
-            }
+            let Some((&lru_timestamp, _)) = self.frames.iter()
+                .min_by_key(|(_, entry)| entry.access_count) else {
+                break;
+            };
+            self.remove(lru_timestamp);
         }
 
-        // Insert or update the frame
+        // Insert the frame
         self.access_counter += 1;
+        self.current_bytes += size;
         self.frames.insert(timestamp, CacheEntry {
             frame,
             access_count: self.access_counter,
@@ -129,6 +213,13 @@ impl FrameCache {
         Ok(())
     }
 
+    /// Removes the frame at `timestamp`, if cached, releasing its bytes
+    fn remove(&mut self, timestamp: Duration) {
+        if let Some(entry) = self.frames.remove(&timestamp) {
+            self.current_bytes -= entry.frame.data.len();
+        }
+    }
+
     /// Gets a frame by timestamp
     ///
     /// Updates the access count for LRU tracking.
@@ -154,6 +245,7 @@ impl FrameCache {
     ///     height: 1080,
     ///     format: PixelFormat::YUV420,
     ///     data: vec![0u8; 100],
+    ///     strides: None,
     ///     timestamp: Duration::from_secs(1),
     ///     duration: Some(Duration::from_millis(33)),
     ///     metadata: FrameMetadata::default(),
@@ -174,6 +266,57 @@ impl FrameCache {
         }
     }
 
+    /// Gets the frame closest to a timestamp
+    ///
+    /// Finds the frame whose timestamp is nearest to `timestamp`, for
+    /// presentation timestamps that differ slightly from the ones frames
+    /// were cached under. Of two equally near frames the earlier one is
+    /// returned. Updates the access count for LRU tracking.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - The timestamp to look for
+    /// * `tolerance` - How far the frame's timestamp may be from `timestamp`
+    ///
+    /// # Returns
+    ///
+    /// Some(frame) if a frame lies within `tolerance`, None otherwise
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::FrameCache;
+    /// use cortenbrowser_shared_types::{VideoFrame, PixelFormat, FrameMetadata};
+    /// use std::time::Duration;
+    ///
+    /// let mut cache = FrameCache::new(10);
+    /// let frame = VideoFrame {
+    ///     width: 1920,
+    ///     height: 1080,
+    ///     format: PixelFormat::YUV420,
+    ///     data: vec![0u8; 100],
+    ///     strides: None,
+    ///     timestamp: Duration::from_millis(33),
+    ///     duration: Some(Duration::from_millis(33)),
+    ///     metadata: FrameMetadata::default(),
+    /// };
+    ///
+    /// cache.insert(frame).unwrap();
+    /// let retrieved = cache.get_nearest(Duration::from_micros(33_367), Duration::from_millis(1));
+    /// assert_eq!(retrieved.unwrap().timestamp, Duration::from_millis(33));
+    /// ```
+    pub fn get_nearest(&mut self, timestamp: Duration, tolerance: Duration) -> Option<VideoFrame> {
+        let nearest = self
+            .frames
+            .keys()
+            .map(|&ts| (ts.abs_diff(timestamp), ts))
+            .filter(|&(distance, _)| distance <= tolerance)
+            .min()
+            .map(|(_, ts)| ts)?;
+
+        self.get(nearest)
+    }
+
     /// Evicts frames before the given timestamp
     ///
     /// Useful for removing old frames that are no longer needed.
@@ -201,6 +344,7 @@ impl FrameCache {
     ///         height: 1080,
     ///         format: PixelFormat::YUV420,
     ///         data: vec![0u8; 100],
+    ///         strides: None,
     ///         timestamp: Duration::from_secs(i),
     ///         duration: Some(Duration::from_millis(33)),
     ///         metadata: FrameMetadata::default(),
@@ -219,7 +363,7 @@ impl FrameCache {
 
         let count = to_remove.len();
         for ts in to_remove {
-            self.frames.remove(&ts);
+            self.remove(ts);
         }
 
         count
@@ -237,6 +381,7 @@ mod tests {
             height: 1080,
             format: PixelFormat::YUV420,
             data: vec![0u8; 100],
+            strides: None,
             timestamp: Duration::from_secs(timestamp_secs),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -338,4 +483,122 @@ mod tests {
         let result = cache.insert(frame);
         assert_eq!(result, Err(BufferError::OutOfMemory));
     }
+
+    #[test]
+    fn test_get_nearest_within_tolerance() {
+        let mut cache = FrameCache::new(10);
+        for ms in [0, 33, 66] {
+            let mut frame = create_test_frame(0);
+            frame.timestamp = Duration::from_millis(ms);
+            cache.insert(frame).unwrap();
+        }
+
+        let nearest = cache.get_nearest(Duration::from_millis(34), Duration::from_millis(5));
+        assert_eq!(nearest.unwrap().timestamp, Duration::from_millis(33));
+
+        // Nothing within 5ms of 50ms
+        assert!(cache
+            .get_nearest(Duration::from_millis(50), Duration::from_millis(5))
+            .is_none());
+
+        // 49.5ms lies halfway between 33ms and 66ms
+        let nearest = cache.get_nearest(Duration::from_micros(49_500), Duration::from_millis(20));
+        assert_eq!(nearest.unwrap().timestamp, Duration::from_millis(33));
+    }
+
+    #[test]
+    fn test_get_nearest_updates_lru() {
+        let mut cache = FrameCache::new(2);
+        cache.insert(create_test_frame(1)).unwrap();
+        cache.insert(create_test_frame(2)).unwrap();
+
+        // Touch frame 1 through a nearby timestamp
+        let nearby = Duration::from_secs(1) + Duration::from_micros(3);
+        assert!(cache.get_nearest(nearby, Duration::from_millis(1)).is_some());
+
+        // Frame 2 is now the least recently used
+        cache.insert(create_test_frame(3)).unwrap();
+        assert!(cache.get(Duration::from_secs(1)).is_some());
+        assert!(cache.get(Duration::from_secs(2)).is_none());
+    }
+
+    fn create_sized_frame(timestamp_secs: u64, size: usize) -> VideoFrame {
+        VideoFrame {
+            data: vec![0u8; size],
+            ..create_test_frame(timestamp_secs)
+        }
+    }
+
+    #[test]
+    fn test_memory_tracking() {
+        let mut cache = FrameCache::new(10);
+        assert_eq!(cache.current_bytes(), 0);
+
+        cache.insert(create_sized_frame(1, 1000)).unwrap();
+        cache.insert(create_sized_frame(2, 250)).unwrap();
+        assert_eq!(cache.current_bytes(), 1250);
+
+        // Replacing a frame accounts for its new size only
+        cache.insert(create_sized_frame(1, 400)).unwrap();
+        assert_eq!(cache.current_bytes(), 650);
+
+        cache.evict_before(Duration::from_secs(2));
+        assert_eq!(cache.current_bytes(), 250);
+    }
+
+    #[test]
+    fn test_memory_limit_evicts_lru_frames() {
+        let mut cache = FrameCache::new_with_memory_limit(1000);
+
+        cache.insert(create_sized_frame(1, 300)).unwrap();
+        cache.insert(create_sized_frame(2, 300)).unwrap();
+        cache.insert(create_sized_frame(3, 300)).unwrap();
+
+        // Access frame 1 to make it recently used
+        cache.get(Duration::from_secs(1));
+
+        // 600 bytes must be freed: frames 2 and 3 go, frame 1 stays
+        cache.insert(create_sized_frame(4, 700)).unwrap();
+        assert_eq!(cache.current_bytes(), 1000);
+        assert!(cache.get(Duration::from_secs(1)).is_some());
+        assert!(cache.get(Duration::from_secs(2)).is_none());
+        assert!(cache.get(Duration::from_secs(3)).is_none());
+        assert!(cache.get(Duration::from_secs(4)).is_some());
+
+        // A small frame fits after evicting one large one
+        cache.insert(create_sized_frame(5, 100)).unwrap();
+        assert_eq!(cache.current_bytes(), 800);
+        assert!(cache.get(Duration::from_secs(1)).is_none());
+    }
+
+    #[test]
+    fn test_frame_larger_than_memory_limit() {
+        let mut cache = FrameCache::new_with_memory_limit(1000);
+        cache.insert(create_sized_frame(1, 500)).unwrap();
+
+        let result = cache.insert(create_sized_frame(2, 1001));
+        assert_eq!(result, Err(BufferError::OutOfMemory));
+
+        // The cache is left untouched
+        assert_eq!(cache.current_bytes(), 500);
+        assert!(cache.get(Duration::from_secs(1)).is_some());
+    }
+
+    #[test]
+    fn test_combined_limits() {
+        let mut cache = FrameCache::new_with_limits(2, 1000);
+
+        // The frame count limit applies to small frames
+        cache.insert(create_sized_frame(1, 10)).unwrap();
+        cache.insert(create_sized_frame(2, 10)).unwrap();
+        cache.insert(create_sized_frame(3, 10)).unwrap();
+        assert!(cache.get(Duration::from_secs(1)).is_none());
+        assert_eq!(cache.current_bytes(), 20);
+
+        // The byte limit applies to large ones
+        cache.insert(create_sized_frame(4, 995)).unwrap();
+        assert_eq!(cache.current_bytes(), 995);
+        assert!(cache.get(Duration::from_secs(2)).is_none());
+        assert!(cache.get(Duration::from_secs(3)).is_none());
+    }
 }
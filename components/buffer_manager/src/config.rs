@@ -7,6 +7,8 @@ pub struct BufferConfig {
     pub max_memory: usize,
     /// Maximum number of video frames to cache
     pub max_video_frames: usize,
+    /// Maximum bytes of video frame data to cache
+    pub frame_cache_max_bytes: usize,
     /// Maximum number of audio buffers
     pub max_audio_buffers: usize,
 }
@@ -18,6 +20,8 @@ impl Default for BufferConfig {
             max_memory: 100 * 1024 * 1024,
             // Default to 100 frames
             max_video_frames: 100,
+            // Default to 100MB of cached frames
+            frame_cache_max_bytes: 100 * 1024 * 1024,
             // Default to 50 audio buffers
             max_audio_buffers: 50,
         }
@@ -5,9 +5,13 @@
 //! This crate provides efficient memory buffers and caches for the Corten Media Engine:
 //!
 //! - [`RingBuffer`] - Circular buffer for streaming byte data
+//! - [`AudioRingBuffer`] - Circular buffer for interleaved audio samples
 //! - [`FrameCache`] - LRU cache for video frames
 //! - [`BufferManager`] - Coordinates buffer resources and memory limits
 //!
+//! Allocations and ring buffer reads and writes are reported as `tracing`
+//! events under the `cortenbrowser::buffer_manager` target.
+//!
 //! # Examples
 //!
 //! Creating a ring buffer:
@@ -29,11 +33,16 @@
 mod config;
 mod error;
 mod ring;
+mod audio_ring;
 mod cache;
 mod manager;
 
 pub use config::BufferConfig;
 pub use error::BufferError;
 pub use ring::RingBuffer;
+pub use audio_ring::AudioRingBuffer;
 pub use cache::FrameCache;
 pub use manager::{BufferManager, VideoFrameBuffer, AudioSampleBuffer};
+
+/// Target of the `tracing` spans and events of this crate
+pub(crate) const TRACING_TARGET: &str = "cortenbrowser::buffer_manager";
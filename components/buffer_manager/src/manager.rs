@@ -2,7 +2,9 @@
 //!
 //! Coordinates memory allocation and tracks resource usage.
 
-use crate::{BufferConfig, BufferError};
+use crate::{BufferConfig, BufferError, TRACING_TARGET};
+use cortenbrowser_shared_types::SessionId;
+use tracing::Span;
 
 /// Video frame buffer wrapper
 ///
@@ -29,6 +31,8 @@ pub struct AudioSampleBuffer {
 /// Manages buffer resources and memory limits
 ///
 /// Tracks memory usage and enforces limits across all buffer types.
+/// Allocations are reported as `debug` events inside the manager's span,
+/// which carries the session ID given to [`BufferManager::with_session`].
 ///
 /// # Examples
 ///
@@ -45,6 +49,7 @@ pub struct AudioSampleBuffer {
 pub struct BufferManager {
     config: BufferConfig,
     current_memory: usize,
+    span: Span,
 }
 
 impl BufferManager {
@@ -66,9 +71,42 @@ impl BufferManager {
         Self {
             config,
             current_memory: 0,
+            span: tracing::debug_span!(
+                target: TRACING_TARGET,
+                "buffer_manager",
+                session_id = tracing::field::Empty
+            ),
         }
     }
 
+    /// Records the session the buffers are allocated for in the manager's span
+    ///
+    /// # Arguments
+    ///
+    /// * `session_id` - Media session using this manager
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::{BufferManager, BufferConfig};
+    /// use cortenbrowser_shared_types::SessionId;
+    ///
+    /// let manager = BufferManager::new(BufferConfig::default()).with_session(SessionId::new());
+    /// ```
+    pub fn with_session(self, session_id: SessionId) -> Self {
+        self.span
+            .record("session_id", tracing::field::display(session_id));
+        self
+    }
+
+    /// Returns the span allocations are reported in
+    ///
+    /// Work done on behalf of the manager's session can run in it too,
+    /// e.g. a future with `tracing::Instrument::instrument`.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
     /// Allocates a video frame buffer
     ///
     /// # Arguments
@@ -91,11 +129,26 @@ impl BufferManager {
     /// assert_eq!(buffer.size, 1024);
     /// ```
     pub fn allocate_video_buffer(&mut self, size: usize) -> Result<VideoFrameBuffer, BufferError> {
+        let _entered = self.span.enter();
         if self.current_memory + size > self.config.max_memory {
+            tracing::debug!(
+                target: TRACING_TARGET,
+                size,
+                current_memory = self.current_memory,
+                max_memory = self.config.max_memory,
+                "Video buffer exceeds memory limit"
+            );
             return Err(BufferError::OutOfMemory);
         }
 
         self.current_memory += size;
+        tracing::debug!(
+            target: TRACING_TARGET,
+            size,
+            current_memory = self.current_memory,
+            max_memory = self.config.max_memory,
+            "Allocated video buffer"
+        );
 
         Ok(VideoFrameBuffer {
             data: vec![0; size],
@@ -125,13 +178,28 @@ impl BufferManager {
     /// assert_eq!(buffer.count, 4800);
     /// ```
     pub fn allocate_audio_buffer(&mut self, samples: usize) -> Result<AudioSampleBuffer, BufferError> {
+        let _entered = self.span.enter();
         let size = samples * std::mem::size_of::<f32>();
 
         if self.current_memory + size > self.config.max_memory {
+            tracing::debug!(
+                target: TRACING_TARGET,
+                size,
+                current_memory = self.current_memory,
+                max_memory = self.config.max_memory,
+                "Audio buffer exceeds memory limit"
+            );
             return Err(BufferError::OutOfMemory);
         }
 
         self.current_memory += size;
+        tracing::debug!(
+            target: TRACING_TARGET,
+            size,
+            current_memory = self.current_memory,
+            max_memory = self.config.max_memory,
+            "Allocated audio buffer"
+        );
 
         Ok(AudioSampleBuffer {
             samples: vec![0.0; samples],
@@ -220,6 +288,7 @@ mod tests {
         let config = BufferConfig {
             max_memory: 2048,
             max_video_frames: 10,
+            frame_cache_max_bytes: 2048,
             max_audio_buffers: 10,
         };
         let mut manager = BufferManager::new(config);
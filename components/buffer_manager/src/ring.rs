@@ -3,6 +3,7 @@
 //! A circular buffer that efficiently manages byte streams with wraparound.
 
 use crate::error::BufferError;
+use crate::TRACING_TARGET;
 
 /// A circular buffer for streaming byte data
 ///
@@ -102,6 +103,13 @@ impl RingBuffer {
         }
 
         self.count += to_write;
+        tracing::trace!(
+            target: TRACING_TARGET,
+            wrote_bytes = to_write,
+            available = self.count,
+            capacity = self.capacity,
+            "Wrote to ring buffer"
+        );
         Ok(to_write)
     }
 
@@ -144,9 +152,132 @@ impl RingBuffer {
         }
 
         self.count -= to_read;
+        tracing::trace!(
+            target: TRACING_TARGET,
+            read_bytes = to_read,
+            available = self.count,
+            capacity = self.capacity,
+            "Read from ring buffer"
+        );
         Ok(to_read)
     }
 
+    /// Copies data from the ring buffer without consuming it
+    ///
+    /// Returns the number of bytes copied. The bytes stay in the buffer, to
+    /// be read again by the next `peek` or `read`.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - The buffer to copy into
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::BufferEmpty` if no data is available
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::RingBuffer;
+    ///
+    /// let mut buffer = RingBuffer::new(10);
+    /// buffer.write(b"Hello").unwrap();
+    ///
+    /// let mut out = vec![0u8; 4];
+    /// assert_eq!(buffer.peek(&mut out).unwrap(), 4);
+    /// assert_eq!(&out, b"Hell");
+    /// assert_eq!(buffer.available(), 5);
+    /// ```
+    pub fn peek(&self, buf: &mut [u8]) -> Result<usize, BufferError> {
+        if self.count == 0 {
+            return Err(BufferError::BufferEmpty);
+        }
+
+        let to_peek = buf.len().min(self.count);
+
+        for (i, byte_ref) in buf.iter_mut().take(to_peek).enumerate() {
+            *byte_ref = self.buffer[(self.read_pos + i) % self.capacity];
+        }
+
+        Ok(to_peek)
+    }
+
+    /// Returns the next `len` bytes without consuming them or copying
+    ///
+    /// The bytes must be stored contiguously, i.e. not wrap around the end
+    /// of the buffer. When they do, `peek` copies them instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `len` - The number of bytes to return
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::InvalidSize` if fewer than `len` bytes are
+    /// available, or they wrap around the end of the buffer
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::RingBuffer;
+    ///
+    /// let mut buffer = RingBuffer::new(10);
+    /// buffer.write(b"ftypisom").unwrap();
+    ///
+    /// assert_eq!(buffer.peek_slice(4).unwrap(), b"ftyp");
+    /// assert_eq!(buffer.available(), 8);
+    /// ```
+    pub fn peek_slice(&self, len: usize) -> Result<&[u8], BufferError> {
+        if len > self.count {
+            return Err(BufferError::InvalidSize(format!(
+                "{} bytes requested, {} available",
+                len, self.count
+            )));
+        }
+        if self.read_pos + len > self.capacity {
+            return Err(BufferError::InvalidSize(format!(
+                "{} bytes wrap around the end of the buffer",
+                len
+            )));
+        }
+
+        Ok(&self.buffer[self.read_pos..self.read_pos + len])
+    }
+
+    /// Consumes data from the ring buffer without copying it
+    ///
+    /// Returns the number of bytes discarded, at most the number available.
+    ///
+    /// # Arguments
+    ///
+    /// * `n` - The number of bytes to discard
+    ///
+    /// # Errors
+    ///
+    /// Returns `BufferError::BufferEmpty` if no data is available
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_buffer_manager::RingBuffer;
+    ///
+    /// let mut buffer = RingBuffer::new(10);
+    /// buffer.write(b"Hello").unwrap();
+    ///
+    /// assert_eq!(buffer.discard(3).unwrap(), 3);
+    /// assert_eq!(buffer.available(), 2);
+    /// ```
+    pub fn discard(&mut self, n: usize) -> Result<usize, BufferError> {
+        if self.count == 0 {
+            return Err(BufferError::BufferEmpty);
+        }
+
+        let to_discard = n.min(self.count);
+        self.read_pos = (self.read_pos + to_discard) % self.capacity;
+        self.count -= to_discard;
+        Ok(to_discard)
+    }
+
     /// Returns the number of bytes available to read
     ///
     /// # Examples
@@ -281,4 +412,66 @@ mod tests {
 
         assert_eq!(buffer.available(), 0);
     }
+
+    #[test]
+    fn test_peek_does_not_consume() {
+        let mut buffer = RingBuffer::new(10);
+        buffer.write(b"Hello").unwrap();
+
+        let mut out = vec![0u8; 8];
+        assert_eq!(buffer.peek(&mut out).unwrap(), 5);
+        assert_eq!(&out[..5], b"Hello");
+        assert_eq!(buffer.available(), 5);
+
+        // The peeked bytes are read again
+        let mut read = vec![0u8; 5];
+        buffer.read(&mut read).unwrap();
+        assert_eq!(&read, b"Hello");
+        assert_eq!(buffer.peek(&mut out), Err(BufferError::BufferEmpty));
+    }
+
+    #[test]
+    fn test_discard_skips_bytes() {
+        let mut buffer = RingBuffer::new(10);
+        buffer.write(b"12345").unwrap();
+        let mut tmp = vec![0u8; 5];
+        buffer.read(&mut tmp).unwrap();
+        buffer.write(b"ABCDEFGH").unwrap();
+
+        // Discarding across the end of the buffer
+        assert_eq!(buffer.discard(6).unwrap(), 6);
+        assert_eq!(buffer.available(), 2);
+        let mut out = vec![0u8; 2];
+        buffer.peek(&mut out).unwrap();
+        assert_eq!(&out, b"GH");
+
+        // No more than is available
+        assert_eq!(buffer.discard(10).unwrap(), 2);
+        assert_eq!(buffer.available(), 0);
+        assert_eq!(buffer.discard(1), Err(BufferError::BufferEmpty));
+    }
+
+    #[test]
+    fn test_peek_slice_after_wraparound() {
+        let mut buffer = RingBuffer::new(10);
+        buffer.write(b"1234567").unwrap();
+        let mut tmp = vec![0u8; 7];
+        buffer.read(&mut tmp).unwrap();
+
+        // "ABC" fills the end of the buffer, "DEFGH" wraps to the start
+        buffer.write(b"ABCDEFGH").unwrap();
+        assert_eq!(buffer.peek_slice(3).unwrap(), b"ABC");
+        assert!(matches!(
+            buffer.peek_slice(4),
+            Err(BufferError::InvalidSize(_))
+        ));
+        assert_eq!(buffer.available(), 8);
+
+        buffer.discard(3).unwrap();
+        assert_eq!(buffer.peek_slice(5).unwrap(), b"DEFGH");
+        assert!(matches!(
+            buffer.peek_slice(6),
+            Err(BufferError::InvalidSize(_))
+        ));
+    }
 }
@@ -0,0 +1,65 @@
+//! Tracing events reported by BufferManager and RingBuffer
+
+use cortenbrowser_buffer_manager::{BufferConfig, BufferError, BufferManager, RingBuffer};
+use cortenbrowser_shared_types::SessionId;
+use tracing_test::traced_test;
+
+fn config(max_memory: usize) -> BufferConfig {
+    BufferConfig {
+        max_memory,
+        ..BufferConfig::default()
+    }
+}
+
+#[traced_test]
+#[test]
+fn test_allocations_emit_debug_events() {
+    let session_id = SessionId::new();
+    let mut manager = BufferManager::new(config(1 << 20)).with_session(session_id);
+
+    manager.allocate_video_buffer(1024).unwrap();
+    manager.allocate_audio_buffer(256).unwrap();
+
+    assert!(logs_contain("Allocated video buffer"));
+    assert!(logs_contain(
+        "size=1024 current_memory=1024 max_memory=1048576"
+    ));
+    assert!(logs_contain("Allocated audio buffer"));
+    assert!(logs_contain(
+        "size=1024 current_memory=2048 max_memory=1048576"
+    ));
+    // Events are reported in the manager's session span
+    assert!(logs_contain(&format!("session_id={}", session_id)));
+}
+
+#[traced_test]
+#[test]
+fn test_overflow_event_carries_current_memory() {
+    let mut manager = BufferManager::new(config(2048));
+    manager.allocate_video_buffer(1500).unwrap();
+
+    let result = manager.allocate_video_buffer(1000);
+    assert_eq!(result, Err(BufferError::OutOfMemory));
+    assert!(logs_contain("Video buffer exceeds memory limit"));
+    assert!(logs_contain(
+        "size=1000 current_memory=1500 max_memory=2048"
+    ));
+
+    let result = manager.allocate_audio_buffer(200);
+    assert_eq!(result, Err(BufferError::OutOfMemory));
+    assert!(logs_contain("Audio buffer exceeds memory limit"));
+    assert!(logs_contain("size=800 current_memory=1500 max_memory=2048"));
+}
+
+#[traced_test]
+#[test]
+fn test_ring_buffer_emits_trace_events() {
+    let mut buffer = RingBuffer::new(16);
+    buffer.write(b"Hello, world").unwrap();
+    let mut out = [0u8; 5];
+    buffer.read(&mut out).unwrap();
+
+    assert!(logs_contain("wrote_bytes=12 available=12 capacity=16"));
+    assert!(logs_contain("read_bytes=5 available=7 capacity=16"));
+    assert!(logs_contain("cortenbrowser::buffer_manager"));
+}
@@ -0,0 +1,3 @@
+//! Integration tests for buffer_manager component
+
+mod buffer_telemetry;
@@ -0,0 +1,4 @@
+//! Integration test harness for buffer_manager component
+
+#[path = "integration/mod.rs"]
+mod integration;
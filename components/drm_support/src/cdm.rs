@@ -3,16 +3,52 @@
 //! Provides the CDM interface for DRM session management, license acquisition,
 //! and decryption operations.
 
-use crate::types::{DrmError, DrmSessionId, SessionData, SessionState, SessionType};
+use crate::clearkey::{self, ContentKey, OutputPolicy, CLEARKEY_KEY_SYSTEM};
+use crate::pssh::PsshParser;
+use crate::session_store::{InMemorySessionStore, SessionStore};
+use crate::types::{
+    CdmEvent, DrmError, DrmSessionId, KeyStatus, LicenseExpiry, LicenseValidity, MessageType,
+    OutputProtection, SampleEncryption, SessionData, SessionState, SessionType,
+};
 use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+use tokio::sync::{mpsc, RwLock};
 
 /// Content Decryption Module
 ///
 /// Manages DRM sessions, generates license requests, and provides decryption
 /// capabilities for protected content.
 ///
+/// For the `org.w3.clearkey` key system, license responses are parsed as
+/// ClearKey JSON Web Key sets and content is decrypted with AES-128-CTR.
+/// Other key systems use stub decryption, licensing the key IDs parsed from
+/// the session's init data.
+///
+/// Sessions of type [`SessionType::PersistentLicense`] are saved to a
+/// [`SessionStore`] when their license arrives, and can be restored with
+/// [`load_session`](Self::load_session) by a later CDM sharing the store.
+///
+/// Licenses may carry an expiry, checked with
+/// [`check_license_valid`](Self::check_license_valid). Licenses expiring
+/// within [`RENEWAL_WINDOW`](Self::RENEWAL_WINDOW) are renewed
+/// automatically when a renewal server is known.
+///
+/// ClearKey licenses may require HDCP or forbid analog outputs; keys are
+/// only used for decryption while the
+/// [output protection](Self::set_output_protection) satisfies their
+/// license.
+///
+/// As in EME, license requests and key status changes are also delivered
+/// asynchronously as [`CdmEvent`]s through
+/// [`event_receiver`](Self::event_receiver).
+///
+/// [`close_session`](Self::close_session) releases a session's keys and
+/// makes any further operation on it fail with
+/// [`DrmError::SessionClosed`]; [`remove_session`](Self::remove_session)
+/// also destroys a persistent license, producing a release message for the
+/// license server.
+///
 /// # Examples
 ///
 /// ```
@@ -28,18 +64,18 @@ use tokio::sync::RwLock;
 ///     let session_id = cdm.create_session().await.expect("Session creation");
 ///
 ///     // Generate a license request
-///     let init_data = b"initialization_data";
-///     let request = cdm.generate_request(&session_id, init_data).await
+///     let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+///     let request = cdm.generate_request(&session_id, "keyids", init_data).await
 ///         .expect("License request generation");
 ///
 ///     // Update with license response
 ///     let license = b"license_from_server";
 ///     cdm.update(&session_id, license).await.expect("Session update");
 ///
-///     // Decrypt content
+///     // Decrypt content with a key the session is licensed for
 ///     let encrypted = b"encrypted_data";
-///     let key_id = b"key_id";
-///     let decrypted = cdm.decrypt(encrypted, key_id).expect("Decryption");
+///     let key_id = [0x10; 16];
+///     let decrypted = cdm.decrypt(encrypted, &key_id).expect("Decryption");
 /// }
 /// ```
 #[derive(Debug)]
@@ -49,9 +85,37 @@ pub struct ContentDecryptionModule {
 
     /// Active DRM sessions
     sessions: Arc<RwLock<HashMap<DrmSessionId, SessionData>>>,
+
+    /// Licensed keys by key ID, from all updated sessions
+    keys: Arc<std::sync::RwLock<HashMap<Vec<u8>, LicensedKey>>>,
+
+    /// Storage for persistent-license sessions
+    session_store: Arc<dyn SessionStore>,
+
+    /// Senders of the receivers handed out by `event_receiver`
+    event_senders: Arc<Mutex<Vec<mpsc::UnboundedSender<CdmEvent>>>>,
+
+    /// Output protection of the display content is decrypted for
+    output_protection: Arc<Mutex<OutputProtection>>,
+}
+
+/// Licensed key with the session, expiry and output policy of the license
+/// that provided it
+///
+/// Only ClearKey keys carry the content key; those of other key systems stay
+/// with the platform CDM.
+#[derive(Debug, Clone)]
+struct LicensedKey {
+    session_id: DrmSessionId,
+    key: Option<ContentKey>,
+    expires_at: Option<SystemTime>,
+    policy: OutputPolicy,
 }
 
 impl ContentDecryptionModule {
+    /// Time before expiry from which a license is reported as expiring
+    pub const RENEWAL_WINDOW: Duration = Duration::from_secs(60);
+
     /// Create a new CDM instance for the specified key system
     ///
     /// # Arguments
@@ -75,6 +139,33 @@ impl ContentDecryptionModule {
     /// assert!(cdm.is_err());
     /// ```
     pub fn new(key_system: String) -> Result<Self, DrmError> {
+        Self::with_session_store(key_system, Arc::new(InMemorySessionStore::new()))
+    }
+
+    /// Create a new CDM instance that persists sessions to `session_store`
+    ///
+    /// CDM instances sharing a store can load each other's persistent
+    /// sessions.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ContentDecryptionModule)` - CDM instance
+    /// * `Err(DrmError::UnsupportedKeySystem)` - If key system is not supported
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{ContentDecryptionModule, InMemorySessionStore};
+    /// use std::sync::Arc;
+    ///
+    /// let store = Arc::new(InMemorySessionStore::new());
+    /// let cdm = ContentDecryptionModule::with_session_store("org.w3.clearkey".to_string(), store);
+    /// assert!(cdm.is_ok());
+    /// ```
+    pub fn with_session_store(
+        key_system: String,
+        session_store: Arc<dyn SessionStore>,
+    ) -> Result<Self, DrmError> {
         // Validate key system
         if key_system.is_empty() {
             return Err(DrmError::UnsupportedKeySystem(
@@ -87,10 +178,119 @@ impl ContentDecryptionModule {
         Ok(Self {
             key_system,
             sessions: Arc::new(RwLock::new(HashMap::new())),
+            keys: Arc::new(std::sync::RwLock::new(HashMap::new())),
+            session_store,
+            event_senders: Arc::new(Mutex::new(Vec::new())),
+            output_protection: Arc::new(Mutex::new(OutputProtection::default())),
         })
     }
 
-    /// Create a new DRM session
+    /// Set the output protection of the display content is played on
+    ///
+    /// Without HDCP, keys from licenses requiring it fail to decrypt with
+    /// [`DrmError::HdcpRequired`]; with an analog output, keys from licenses
+    /// forbidding one fail with [`DrmError::OutputNotAllowed`]. The default
+    /// is no HDCP and no analog output.
+    ///
+    /// Sessions whose keys become or stop being
+    /// [`KeyStatus::OutputRestricted`] get a [`CdmEvent::KeyStatusesChange`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{ContentDecryptionModule, OutputProtection};
+    ///
+    /// let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string()).unwrap();
+    /// cdm.set_output_protection(OutputProtection {
+    ///     hdcp_enabled: true,
+    ///     analog_output: false,
+    /// });
+    /// assert!(cdm.output_protection().hdcp_enabled);
+    /// ```
+    pub fn set_output_protection(&self, output_protection: OutputProtection) {
+        let previous = std::mem::replace(
+            &mut *self
+                .output_protection
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner),
+            output_protection,
+        );
+
+        let mut changed: Vec<DrmSessionId> = Vec::new();
+        for key in self
+            .keys
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+        {
+            let was_allowed = key.policy.check(&previous).is_ok();
+            let is_allowed = key.policy.check(&output_protection).is_ok();
+            if was_allowed != is_allowed && !changed.contains(&key.session_id) {
+                changed.push(key.session_id.clone());
+            }
+        }
+        for session_id in changed {
+            self.emit(CdmEvent::KeyStatusesChange { session_id });
+        }
+    }
+
+    /// Get the output protection set with
+    /// [`set_output_protection`](Self::set_output_protection)
+    pub fn output_protection(&self) -> OutputProtection {
+        *self
+            .output_protection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Subscribe to the CDM's session events
+    ///
+    /// The receiver gets every [`CdmEvent`] emitted after it was created:
+    /// a [`CdmEvent::Message`] for each license or renewal request, and a
+    /// [`CdmEvent::KeyStatusesChange`] whenever a session's keys are
+    /// added, restored, released, expire or become output-restricted.
+    /// Each call returns an independent receiver.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{CdmEvent, ContentDecryptionModule, MessageType};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cdm = ContentDecryptionModule::new("com.example.test".to_string()).unwrap();
+    ///     let mut events = cdm.event_receiver();
+    ///
+    ///     let session_id = cdm.create_session().await.unwrap();
+    ///     let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    ///     cdm.generate_request(&session_id, "keyids", init_data).await.unwrap();
+    ///
+    ///     match events.recv().await.unwrap() {
+    ///         CdmEvent::Message { message_type, .. } => {
+    ///             assert_eq!(message_type, MessageType::LicenseRequest);
+    ///         }
+    ///         other => panic!("unexpected event: {:?}", other),
+    ///     }
+    /// }
+    /// ```
+    pub fn event_receiver(&self) -> mpsc::UnboundedReceiver<CdmEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.event_senders
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sender);
+        receiver
+    }
+
+    /// Send an event to every live receiver, dropping closed ones
+    fn emit(&self, event: CdmEvent) {
+        self.event_senders
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Create a new temporary DRM session
     ///
     /// # Returns
     ///
@@ -110,7 +310,23 @@ impl ContentDecryptionModule {
     /// }
     /// ```
     pub async fn create_session(&self) -> Result<DrmSessionId, DrmError> {
-        let session_data = SessionData::new(SessionType::Temporary);
+        self.create_session_with_type(SessionType::Temporary).await
+    }
+
+    /// Create a new DRM session of the given type
+    ///
+    /// [`SessionType::PersistentLicense`] sessions are saved to the session
+    /// store each time they are updated with a license.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DrmSessionId)` - Unique session identifier
+    /// * `Err(DrmError)` - If session creation fails
+    pub async fn create_session_with_type(
+        &self,
+        session_type: SessionType,
+    ) -> Result<DrmSessionId, DrmError> {
+        let session_data = SessionData::new(session_type);
         let session_id = session_data.id.clone();
 
         let mut sessions = self.sessions.write().await;
@@ -121,15 +337,30 @@ impl ContentDecryptionModule {
 
     /// Generate a license request for a DRM session
     ///
+    /// Key IDs are taken from the init data and listed in the request's
+    /// `kids` field as base64url strings. The request is also emitted as a
+    /// [`CdmEvent::Message`] of type [`MessageType::LicenseRequest`].
+    ///
+    /// The session records the key IDs. For key systems other than
+    /// ClearKey, whose licenses are opaque, they are the keys the license
+    /// from [`update`](Self::update) makes available to
+    /// [`decrypt`](Self::decrypt).
+    ///
     /// # Arguments
     ///
     /// * `session_id` - The session identifier
-    /// * `init_data` - Initialization data (e.g., PSSH box from media)
+    /// * `init_data_type` - `"cenc"` for concatenated PSSH boxes, or
+    ///   `"keyids"` for a JSON `{"kids":[...]}` key ID list
+    /// * `init_data` - Initialization data (e.g., PSSH boxes from media)
     ///
     /// # Returns
     ///
     /// * `Ok(Vec<u8>)` - License request payload to send to license server
     /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    /// * `Err(DrmError::SessionClosed)` - If the session has been closed
+    /// * `Err(DrmError::InvalidInitData)` - If the init data type is not
+    ///   supported, the init data is malformed, or `"cenc"` data has no PSSH
+    ///   box for this key system
     /// * `Err(DrmError::LicenseRequestFailed)` - If request generation fails
     ///
     /// # Examples
@@ -142,44 +373,88 @@ impl ContentDecryptionModule {
     ///     let cdm = ContentDecryptionModule::new("com.example.test".to_string()).unwrap();
     ///     let session_id = cdm.create_session().await.unwrap();
     ///
-    ///     let init_data = b"pssh_box_data";
-    ///     let request = cdm.generate_request(&session_id, init_data).await.unwrap();
+    ///     let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    ///     let request = cdm.generate_request(&session_id, "keyids", init_data).await.unwrap();
     ///     assert!(!request.is_empty());
     /// }
     /// ```
     pub async fn generate_request(
         &self,
         session_id: &DrmSessionId,
+        init_data_type: &str,
         init_data: &[u8],
     ) -> Result<Vec<u8>, DrmError> {
         let mut sessions = self.sessions.write().await;
 
-        let session = sessions
-            .get_mut(session_id)
-            .ok_or_else(|| DrmError::SessionNotFound(session_id.clone()))?;
+        let session = Self::open_session(&mut sessions, session_id)?;
+
+        let key_ids = self.init_data_key_ids(init_data_type, init_data)?;
 
         // Store initialization data
         session.init_data = Some(init_data.to_vec());
+        session.key_ids = key_ids.clone();
         session.state = SessionState::PendingLicense;
 
         // Stub implementation: In production, this would:
-        // 1. Parse initialization data (e.g., PSSH box)
-        // 2. Generate CDM-specific license request
-        // 3. Format according to key system requirements
+        // 1. Generate CDM-specific license request
+        // 2. Format according to key system requirements
         //
         // For now, return a placeholder request that includes:
         // - Key system identifier
         // - Session ID
-        // - Initialization data
+        // - Initialization data and the key IDs parsed from it
         use base64::Engine;
+        let kids: Vec<String> = key_ids
+            .iter()
+            .map(|key_id| clearkey::encode_base64url(key_id))
+            .collect();
         let request = serde_json::json!({
             "key_system": self.key_system,
             "session_id": session_id.as_str(),
+            "init_data_type": init_data_type,
             "init_data": base64::engine::general_purpose::STANDARD.encode(init_data),
+            "kids": kids,
             "type": "license-request"
         });
+        let request = request.to_string().into_bytes();
 
-        Ok(request.to_string().into_bytes())
+        self.emit(CdmEvent::Message {
+            session_id: session_id.clone(),
+            message_type: MessageType::LicenseRequest,
+            payload: request.clone(),
+        });
+        Ok(request)
+    }
+
+    /// Parse the key IDs from init data of the given type
+    fn init_data_key_ids(
+        &self,
+        init_data_type: &str,
+        init_data: &[u8],
+    ) -> Result<Vec<Vec<u8>>, DrmError> {
+        match init_data_type {
+            "cenc" => {
+                let boxes: Vec<_> = PsshParser::parse(init_data)?
+                    .into_iter()
+                    .filter(|pssh| pssh.key_system() == Some(self.key_system.as_str()))
+                    .collect();
+                if boxes.is_empty() {
+                    return Err(DrmError::InvalidInitData(format!(
+                        "No PSSH box for {}",
+                        self.key_system
+                    )));
+                }
+                Ok(boxes
+                    .iter()
+                    .flat_map(|pssh| pssh.key_ids.iter().map(|key_id| key_id.to_vec()))
+                    .collect())
+            }
+            "keyids" => clearkey::parse_key_ids(init_data),
+            other => Err(DrmError::InvalidInitData(format!(
+                "Unsupported init data type: {}",
+                other
+            ))),
+        }
     }
 
     /// Update a DRM session with a license response
@@ -193,7 +468,23 @@ impl ContentDecryptionModule {
     ///
     /// * `Ok(())` - Session updated successfully
     /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    /// * `Err(DrmError::SessionClosed)` - If the session has been closed
     /// * `Err(DrmError::LicenseRequestFailed)` - If license is invalid
+    /// * `Err(DrmError::LicenseServerError)` - If `response` is an error
+    ///   document from the license server, such as `{"status":403}`
+    /// * `Err(DrmError::SessionStorageFailed)` - If a persistent-license
+    ///   session cannot be saved
+    ///
+    /// For ClearKey, `response` must be a JSON Web Key set such as
+    /// `{"keys":[{"kty":"oct","kid":"...","k":"..."}]}`, whose keys become
+    /// available to [`decrypt`](Self::decrypt). It may also carry an
+    /// `expiration` in milliseconds since the Unix epoch and a
+    /// `renewal_url`, which replace the session's [`LicenseExpiry`], and
+    /// an output policy: `hdcp_required` (default `false`) and
+    /// `allow_analog_output` (default `true`).
+    ///
+    /// Updating a [`SessionState::Renewing`] session with the renewed
+    /// license makes it active again.
     ///
     /// # Examples
     ///
@@ -205,25 +496,40 @@ impl ContentDecryptionModule {
     ///     let cdm = ContentDecryptionModule::new("com.example.test".to_string()).unwrap();
     ///     let session_id = cdm.create_session().await.unwrap();
     ///
-    ///     let init_data = b"pssh_box_data";
-    ///     cdm.generate_request(&session_id, init_data).await.unwrap();
+    ///     let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    ///     cdm.generate_request(&session_id, "keyids", init_data).await.unwrap();
     ///
     ///     let license = b"license_from_server";
     ///     cdm.update(&session_id, license).await.unwrap();
     /// }
     /// ```
-    pub async fn update(
-        &self,
-        session_id: &DrmSessionId,
-        response: &[u8],
-    ) -> Result<(), DrmError> {
+    pub async fn update(&self, session_id: &DrmSessionId, response: &[u8]) -> Result<(), DrmError> {
         let mut sessions = self.sessions.write().await;
 
-        let session = sessions
-            .get_mut(session_id)
-            .ok_or_else(|| DrmError::SessionNotFound(session_id.clone()))?;
+        let session = Self::open_session(&mut sessions, session_id)?;
 
-        // Stub implementation: In production, this would:
+        if self.key_system == CLEARKEY_KEY_SYSTEM {
+            let license = clearkey::parse_license(response)?;
+            session.key_ids = license
+                .keys
+                .iter()
+                .map(|(key_id, _)| key_id.clone())
+                .collect();
+            self.install_keys(
+                session_id,
+                license
+                    .keys
+                    .into_iter()
+                    .map(|(key_id, key)| (key_id, Some(key))),
+                license.expiry.expires_at,
+                license.policy,
+            );
+            session.expiry = license.expiry;
+        } else {
+            self.install_key_ids(session);
+        }
+
+        // Stub implementation: In production, other key systems would:
         // 1. Parse license response
         // 2. Validate license signature
         // 3. Extract decryption keys
@@ -232,13 +538,575 @@ impl ContentDecryptionModule {
         // For now, just store the license data and mark session as active
         session.license_data = Some(response.to_vec());
         session.state = SessionState::Active;
+        session.renewal_request = None;
+        session.expiry_reported = is_expired(&session.expiry);
+
+        self.persist(session)?;
+        self.emit(CdmEvent::KeyStatusesChange {
+            session_id: session_id.clone(),
+        });
+        Ok(())
+    }
+
+    /// Restore a persistent-license session from the session store
+    ///
+    /// The session becomes usable as it was when last updated; for ClearKey
+    /// its keys are available to [`decrypt`](Self::decrypt) again.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Session loaded
+    /// * `Err(DrmError::SessionNotFound)` - If no session is stored under
+    ///   `session_id`
+    /// * `Err(DrmError::SessionStorageFailed)` - If the stored session cannot
+    ///   be read
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{
+    ///     ContentDecryptionModule, InMemorySessionStore, SessionType,
+    /// };
+    /// use std::sync::Arc;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let store = Arc::new(InMemorySessionStore::new());
+    ///     let cdm = ContentDecryptionModule::with_session_store(
+    ///         "com.example.test".to_string(),
+    ///         store.clone(),
+    ///     )
+    ///     .unwrap();
+    ///     let session_id = cdm
+    ///         .create_session_with_type(SessionType::PersistentLicense)
+    ///         .await
+    ///         .unwrap();
+    ///     cdm.update(&session_id, b"license_from_server").await.unwrap();
+    ///
+    ///     // A later CDM sharing the store can load the session
+    ///     let cdm = ContentDecryptionModule::with_session_store(
+    ///         "com.example.test".to_string(),
+    ///         store,
+    ///     )
+    ///     .unwrap();
+    ///     cdm.load_session(&session_id).await.unwrap();
+    /// }
+    /// ```
+    pub async fn load_session(&self, session_id: &DrmSessionId) -> Result<(), DrmError> {
+        let record = self
+            .session_store
+            .load(session_id)?
+            .ok_or_else(|| DrmError::SessionNotFound(session_id.clone()))?;
+        let session: SessionData = serde_json::from_slice(&record).map_err(|e| {
+            DrmError::SessionStorageFailed(format!("Invalid session record: {}", e))
+        })?;
+
+        if self.key_system == CLEARKEY_KEY_SYSTEM {
+            if let Some(license) = &session.license_data {
+                let license = clearkey::parse_license(license)?;
+                self.install_keys(
+                    session_id,
+                    license
+                        .keys
+                        .into_iter()
+                        .map(|(key_id, key)| (key_id, Some(key))),
+                    session.expiry.expires_at,
+                    license.policy,
+                );
+            }
+        } else if session.license_data.is_some() {
+            self.install_key_ids(&session);
+        }
+
+        self.sessions
+            .write()
+            .await
+            .insert(session_id.clone(), session);
+        self.emit(CdmEvent::KeyStatusesChange {
+            session_id: session_id.clone(),
+        });
+        Ok(())
+    }
+
+    /// Remove a session's license and delete it from the session store
+    ///
+    /// The session is closed; for ClearKey its keys are no longer available
+    /// to [`decrypt`](Self::decrypt).
+    ///
+    /// Unlike a [`SessionType::Temporary`] session, whose license only
+    /// lives in memory, a [`SessionType::PersistentLicense`] session is
+    /// deleted from the session store and a [`MessageType::LicenseRelease`]
+    /// message listing its key IDs is emitted, for the application to
+    /// send to the license server as proof of the release.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Session removed
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    /// * `Err(DrmError::SessionClosed)` - If the session has been closed
+    /// * `Err(DrmError::SessionStorageFailed)` - If the stored session cannot
+    ///   be deleted
+    pub async fn remove_session(&self, session_id: &DrmSessionId) -> Result<(), DrmError> {
+        let mut sessions = self.sessions.write().await;
+
+        let session = Self::open_session(&mut sessions, session_id)?;
+
+        if session.session_type == SessionType::PersistentLicense {
+            self.session_store.remove(session_id)?;
+
+            let kids: Vec<String> = session
+                .key_ids
+                .iter()
+                .map(|key_id| clearkey::encode_base64url(key_id))
+                .collect();
+            let message = serde_json::json!({
+                "key_system": self.key_system,
+                "session_id": session_id.as_str(),
+                "kids": kids,
+                "type": "license-release"
+            });
+            self.emit(CdmEvent::Message {
+                session_id: session_id.clone(),
+                message_type: MessageType::LicenseRelease,
+                payload: message.to_string().into_bytes(),
+            });
+        }
+
+        self.release(session);
+        Ok(())
+    }
+
+    /// Close a session, releasing its keys
+    ///
+    /// For ClearKey its keys are no longer available to
+    /// [`decrypt`](Self::decrypt). A persistent license stays in the
+    /// session store and can still be loaded with
+    /// [`load_session`](Self::load_session). All further operations on the
+    /// session, except querying its [key statuses](Self::key_statuses) and
+    /// [expiration](Self::expiration), fail with
+    /// [`DrmError::SessionClosed`]; closing it again does nothing.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Session closed
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{ContentDecryptionModule, DrmError};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cdm = ContentDecryptionModule::new("com.example.test".to_string()).unwrap();
+    ///     let session_id = cdm.create_session().await.unwrap();
+    ///     cdm.close_session(&session_id).await.unwrap();
+    ///
+    ///     let result = cdm.update(&session_id, b"license_from_server").await;
+    ///     assert!(matches!(result, Err(DrmError::SessionClosed(_))));
+    /// }
+    /// ```
+    pub async fn close_session(&self, session_id: &DrmSessionId) -> Result<(), DrmError> {
+        let mut sessions = self.sessions.write().await;
 
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| DrmError::SessionNotFound(session_id.clone()))?;
+        if session.state != SessionState::Closed {
+            self.release(session);
+        }
         Ok(())
     }
 
+    /// Look up a session that has not been closed
+    fn open_session<'a>(
+        sessions: &'a mut HashMap<DrmSessionId, SessionData>,
+        session_id: &DrmSessionId,
+    ) -> Result<&'a mut SessionData, DrmError> {
+        let session = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| DrmError::SessionNotFound(session_id.clone()))?;
+        if session.state == SessionState::Closed {
+            return Err(DrmError::SessionClosed(session_id.clone()));
+        }
+        Ok(session)
+    }
+
+    /// Drop a session's keys and license and move it to `Closed`
+    fn release(&self, session: &mut SessionData) {
+        let mut keys = self.keys.write().unwrap_or_else(PoisonError::into_inner);
+        for key_id in &session.key_ids {
+            keys.remove(key_id);
+        }
+        drop(keys);
+
+        session.license_data = None;
+        session.expiry = LicenseExpiry::default();
+        session.renewal_request = None;
+        session.state = SessionState::Closed;
+        self.emit(CdmEvent::KeyStatusesChange {
+            session_id: session.id.clone(),
+        });
+    }
+
+    /// Make keys available to [`decrypt`](Self::decrypt) until `expires_at`
+    fn install_keys(
+        &self,
+        session_id: &DrmSessionId,
+        keys: impl IntoIterator<Item = (Vec<u8>, Option<ContentKey>)>,
+        expires_at: Option<SystemTime>,
+        policy: OutputPolicy,
+    ) {
+        self.keys
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(keys.into_iter().map(|(key_id, key)| {
+                (
+                    key_id,
+                    LicensedKey {
+                        session_id: session_id.clone(),
+                        key,
+                        expires_at,
+                        policy,
+                    },
+                )
+            }));
+    }
+
+    /// License the key IDs of a session of a key system other than ClearKey,
+    /// whose keys stay with the platform CDM
+    fn install_key_ids(&self, session: &SessionData) {
+        self.install_keys(
+            &session.id,
+            session.key_ids.iter().map(|key_id| (key_id.clone(), None)),
+            session.expiry.expires_at,
+            OutputPolicy::UNRESTRICTED,
+        );
+    }
+
+    /// Save a persistent-license session to the session store
+    fn persist(&self, session: &SessionData) -> Result<(), DrmError> {
+        if session.session_type != SessionType::PersistentLicense {
+            return Ok(());
+        }
+        let record = serde_json::to_vec(session).map_err(|e| {
+            DrmError::SessionStorageFailed(format!("Cannot serialize session: {}", e))
+        })?;
+        self.session_store.save(&session.id, record)
+    }
+
+    /// Set the license expiry of a session, as provided by the platform CDM
+    ///
+    /// ClearKey sessions take their expiry from the license in
+    /// [`update`](Self::update) instead, which replaces this value.
+    /// Sessions with a license get a [`CdmEvent::KeyStatusesChange`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Expiry stored
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    /// * `Err(DrmError::SessionClosed)` - If the session has been closed
+    /// * `Err(DrmError::SessionStorageFailed)` - If a persistent-license
+    ///   session cannot be saved
+    pub async fn set_license_expiry(
+        &self,
+        session_id: &DrmSessionId,
+        expiry: LicenseExpiry,
+    ) -> Result<(), DrmError> {
+        let mut sessions = self.sessions.write().await;
+
+        let session = Self::open_session(&mut sessions, session_id)?;
+        session.expiry = expiry;
+        session.expiry_reported = is_expired(&session.expiry);
+
+        let mut keys = self.keys.write().unwrap_or_else(PoisonError::into_inner);
+        for key_id in &session.key_ids {
+            if let Some(key) = keys.get_mut(key_id) {
+                key.expires_at = session.expiry.expires_at;
+            }
+        }
+        drop(keys);
+
+        if session.license_data.is_some() {
+            self.persist(session)?;
+            self.emit(CdmEvent::KeyStatusesChange {
+                session_id: session_id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check whether a session's license is still valid
+    ///
+    /// A license expiring within [`RENEWAL_WINDOW`](Self::RENEWAL_WINDOW) is
+    /// reported as [`LicenseValidity::Expiring`]. If the session is active
+    /// and has a renewal server, a renewal request is then generated as by
+    /// [`generate_renewal_request`](Self::generate_renewal_request); take it
+    /// with [`take_renewal_request`](Self::take_renewal_request).
+    ///
+    /// The first check finding the license expired emits a
+    /// [`CdmEvent::KeyStatusesChange`], as the session's keys are now
+    /// [`KeyStatus::Expired`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(LicenseValidity)` - Validity of the license now
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    /// * `Err(DrmError::SessionClosed)` - If the session has been closed
+    /// * `Err(DrmError::LicenseRequestFailed)` - If the session has no
+    ///   license yet
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{ContentDecryptionModule, LicenseValidity};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string()).unwrap();
+    ///     let session_id = cdm.create_session().await.unwrap();
+    ///
+    ///     let license = br#"{"keys":[],"expiration":1000}"#;
+    ///     cdm.update(&session_id, license).await.unwrap();
+    ///
+    ///     let validity = cdm.check_license_valid(&session_id).await.unwrap();
+    ///     assert_eq!(validity, LicenseValidity::Expired);
+    /// }
+    /// ```
+    pub async fn check_license_valid(
+        &self,
+        session_id: &DrmSessionId,
+    ) -> Result<LicenseValidity, DrmError> {
+        let mut sessions = self.sessions.write().await;
+
+        let session = Self::open_session(&mut sessions, session_id)?;
+        if session.license_data.is_none() {
+            return Err(DrmError::LicenseRequestFailed(format!(
+                "Session {} has no license",
+                session_id
+            )));
+        }
+
+        let Some(expires_at) = session.expiry.expires_at else {
+            return Ok(LicenseValidity::Valid {
+                remaining: Duration::MAX,
+            });
+        };
+        let remaining = expires_at
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO);
+        if remaining.is_zero() {
+            if !session.expiry_reported {
+                session.expiry_reported = true;
+                self.emit(CdmEvent::KeyStatusesChange {
+                    session_id: session_id.clone(),
+                });
+            }
+            return Ok(LicenseValidity::Expired);
+        }
+        if remaining > Self::RENEWAL_WINDOW {
+            return Ok(LicenseValidity::Valid { remaining });
+        }
+
+        if session.state == SessionState::Active && session.expiry.renewal_server_url.is_some() {
+            session.renewal_request = Some(self.renewal_request(session)?);
+        }
+        Ok(LicenseValidity::Expiring { remaining })
+    }
+
+    /// Report the status of each key of a session
+    ///
+    /// Keys are those of the license for ClearKey and those of the init data
+    /// for other key systems. Sessions without a license have no key
+    /// statuses yet. ClearKey keys whose license the current
+    /// [output protection](Self::set_output_protection) does not satisfy
+    /// are [`KeyStatus::OutputRestricted`].
+    ///
+    /// Changes are announced with [`CdmEvent::KeyStatusesChange`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(HashMap<Vec<u8>, KeyStatus>)` - Status of each key ID
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{ContentDecryptionModule, KeyStatus};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string()).unwrap();
+    ///     let session_id = cdm.create_session().await.unwrap();
+    ///
+    ///     let license = br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+    ///     cdm.update(&session_id, license).await.unwrap();
+    ///
+    ///     let statuses = cdm.key_statuses(&session_id).await.unwrap();
+    ///     assert_eq!(statuses[&vec![0x10; 16]], KeyStatus::Usable);
+    /// }
+    /// ```
+    pub async fn key_statuses(
+        &self,
+        session_id: &DrmSessionId,
+    ) -> Result<HashMap<Vec<u8>, KeyStatus>, DrmError> {
+        let sessions = self.sessions.read().await;
+
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| DrmError::SessionNotFound(session_id.clone()))?;
+
+        let status = match session.state {
+            SessionState::Created | SessionState::PendingLicense => return Ok(HashMap::new()),
+            SessionState::Closed => KeyStatus::Released,
+            SessionState::Error => KeyStatus::InternalError,
+            SessionState::Active | SessionState::Renewing if is_expired(&session.expiry) => {
+                KeyStatus::Expired
+            }
+            SessionState::Active | SessionState::Renewing => KeyStatus::Usable,
+        };
+
+        let output_protection = self.output_protection();
+        let keys = self.keys.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(session
+            .key_ids
+            .iter()
+            .map(|key_id| {
+                let restricted = status == KeyStatus::Usable
+                    && keys
+                        .get(key_id)
+                        .is_some_and(|key| key.policy.check(&output_protection).is_err());
+                let status = if restricted {
+                    KeyStatus::OutputRestricted
+                } else {
+                    status
+                };
+                (key_id.clone(), status)
+            })
+            .collect())
+    }
+
+    /// Get when a session's license expires
+    ///
+    /// The expiry comes from the license for ClearKey and from
+    /// [`set_license_expiry`](Self::set_license_expiry) otherwise, as the
+    /// EME `expiration` attribute.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(SystemTime))` - When the license expires
+    /// * `Ok(None)` - If the session has no license, or its license never
+    ///   expires
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    pub async fn expiration(
+        &self,
+        session_id: &DrmSessionId,
+    ) -> Result<Option<SystemTime>, DrmError> {
+        let sessions = self.sessions.read().await;
+
+        let session = sessions
+            .get(session_id)
+            .ok_or_else(|| DrmError::SessionNotFound(session_id.clone()))?;
+        Ok(session.expiry.expires_at)
+    }
+
+    /// Generate a license renewal request for a session
+    ///
+    /// The session enters [`SessionState::Renewing`] until it is updated with
+    /// the renewed license. The request is also emitted as a
+    /// [`CdmEvent::Message`] of type [`MessageType::LicenseRenewal`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - Renewal request payload to send to the renewal
+    ///   server
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    /// * `Err(DrmError::SessionClosed)` - If the session has been closed
+    /// * `Err(DrmError::LicenseRequestFailed)` - If the session has no
+    ///   license to renew
+    pub async fn generate_renewal_request(
+        &self,
+        session_id: &DrmSessionId,
+    ) -> Result<Vec<u8>, DrmError> {
+        let mut sessions = self.sessions.write().await;
+
+        let session = Self::open_session(&mut sessions, session_id)?;
+        self.renewal_request(session)
+    }
+
+    /// Take the renewal request generated automatically by
+    /// [`check_license_valid`](Self::check_license_valid), if any
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(Vec<u8>))` - Renewal request payload, returned only once
+    /// * `Ok(None)` - If no renewal request is pending
+    /// * `Err(DrmError::SessionNotFound)` - If session doesn't exist
+    /// * `Err(DrmError::SessionClosed)` - If the session has been closed
+    pub async fn take_renewal_request(
+        &self,
+        session_id: &DrmSessionId,
+    ) -> Result<Option<Vec<u8>>, DrmError> {
+        let mut sessions = self.sessions.write().await;
+
+        let session = Self::open_session(&mut sessions, session_id)?;
+        Ok(session.renewal_request.take())
+    }
+
+    /// Build a renewal request and move the session to `Renewing`
+    fn renewal_request(&self, session: &mut SessionData) -> Result<Vec<u8>, DrmError> {
+        if !matches!(session.state, SessionState::Active | SessionState::Renewing) {
+            return Err(DrmError::LicenseRequestFailed(format!(
+                "Session {} has no license to renew",
+                session.id
+            )));
+        }
+
+        let request = serde_json::json!({
+            "key_system": self.key_system,
+            "session_id": session.id.as_str(),
+            "renewal_server_url": session.expiry.renewal_server_url,
+            "type": "license-renewal"
+        });
+        session.state = SessionState::Renewing;
+        let request = request.to_string().into_bytes();
+
+        self.emit(CdmEvent::Message {
+            session_id: session.id.clone(),
+            message_type: MessageType::LicenseRenewal,
+            payload: request.clone(),
+        });
+        Ok(request)
+    }
+
+    /// Check whether samples encrypted with a key can be decrypted now
+    ///
+    /// The key must be licensed to a session, unexpired and allowed on the
+    /// current output.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_id` - ID of the key
+    ///
+    /// # Returns
+    ///
+    /// * `true` if [`decrypt_sample`](Self::decrypt_sample) can use the key
+    pub fn is_key_usable(&self, key_id: &[u8]) -> bool {
+        let keys = self.keys.read().unwrap_or_else(PoisonError::into_inner);
+        keys.get(key_id).is_some_and(|key| {
+            key.expires_at
+                .is_none_or(|expires_at| expires_at > SystemTime::now())
+                && key.policy.check(&self.output_protection()).is_ok()
+        })
+    }
+
     /// Decrypt protected content
     ///
-    /// **Note**: This is a stub implementation. In production, this would:
+    /// Treats `data` as one fully encrypted sample with an all-zero IV; use
+    /// [`decrypt_sample`](Self::decrypt_sample) for other layouts.
+    ///
+    /// **Note**: Except for ClearKey, this is a stub implementation. In
+    /// production, this would:
     /// - Use platform-specific CDM for actual decryption
     /// - Ensure decryption happens in secure memory
     /// - Never expose clear content keys
@@ -251,7 +1119,13 @@ impl ContentDecryptionModule {
     /// # Returns
     ///
     /// * `Ok(Vec<u8>)` - Decrypted content
-    /// * `Err(DrmError::DecryptionFailed)` - If decryption fails
+    /// * `Err(DrmError::DecryptionFailed)` - If decryption fails, or
+    ///   `key_id` is not licensed to an open session or its license has
+    ///   expired
+    /// * `Err(DrmError::HdcpRequired)` - If the key's license requires HDCP
+    ///   and the output is not HDCP-protected
+    /// * `Err(DrmError::OutputNotAllowed)` - If the key's license forbids
+    ///   the current output
     ///
     /// # Security Considerations
     ///
@@ -266,16 +1140,75 @@ impl ContentDecryptionModule {
     /// ```
     /// use cortenbrowser_drm_support::ContentDecryptionModule;
     ///
-    /// let cdm = ContentDecryptionModule::new("com.example.test".to_string()).unwrap();
-    /// let encrypted = b"encrypted_content";
-    /// let key_id = b"key_identifier";
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cdm = ContentDecryptionModule::new("com.example.test".to_string()).unwrap();
+    ///     let session_id = cdm.create_session().await.unwrap();
+    ///     let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    ///     cdm.generate_request(&session_id, "keyids", init_data).await.unwrap();
+    ///     cdm.update(&session_id, b"license").await.unwrap();
     ///
-    /// let result = cdm.decrypt(encrypted, key_id);
-    /// // Stub implementation returns placeholder
-    /// assert!(result.is_ok());
+    ///     // Stub implementation returns placeholder
+    ///     assert!(cdm.decrypt(b"encrypted_content", &[0x10; 16]).is_ok());
+    ///     // Keys the session was not licensed for are refused
+    ///     assert!(cdm.decrypt(b"encrypted_content", &[0x20; 16]).is_err());
+    /// }
     /// ```
     pub fn decrypt(&self, data: &[u8], key_id: &[u8]) -> Result<Vec<u8>, DrmError> {
-        // Stub implementation: Return placeholder decrypted data
+        let sample = SampleEncryption {
+            iv: vec![0; 16],
+            ..Default::default()
+        };
+        self.decrypt_sample(data, key_id, &sample)
+    }
+
+    /// Decrypt one protected sample using its IV and subsample layout
+    ///
+    /// For ClearKey, only the encrypted ranges of `sample` are decrypted,
+    /// with AES-128-CTR for `cenc` or pattern AES-128-CBC for `cbcs`; clear
+    /// ranges are copied unchanged. Other key systems ignore the layout.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<u8>)` - Decrypted sample
+    /// * `Err(DrmError::DecryptionFailed)` - If `key_id` is not licensed to
+    ///   an open session or its license has expired, or for ClearKey the IV
+    ///   is not 8 or 16 bytes or the subsamples do not cover `data` exactly
+    /// * `Err(DrmError::HdcpRequired)` / `Err(DrmError::OutputNotAllowed)` -
+    ///   If the output protection does not satisfy the key's license
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{ContentDecryptionModule, SampleEncryption, Subsample};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string()).unwrap();
+    ///     let session_id = cdm.create_session().await.unwrap();
+    ///
+    ///     let license = br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+    ///     cdm.update(&session_id, license).await.unwrap();
+    ///
+    ///     // Two clear header bytes, then four encrypted bytes
+    ///     let sample = SampleEncryption {
+    ///         iv: vec![0; 8],
+    ///         subsamples: vec![Subsample { clear_bytes: 2, encrypted_bytes: 4 }],
+    ///         ..Default::default()
+    ///     };
+    ///     let data = [0x00, 0x01, 0x85, 0xce, 0x49, 0x43];
+    ///     let decrypted = cdm.decrypt_sample(&data, &[0x10; 16], &sample).unwrap();
+    ///     assert_eq!(decrypted, b"\x00\x01Cort");
+    /// }
+    /// ```
+    pub fn decrypt_sample(
+        &self,
+        data: &[u8],
+        key_id: &[u8],
+        sample: &SampleEncryption,
+    ) -> Result<Vec<u8>, DrmError> {
+        // Stub implementation: Return placeholder decrypted data for
+        // key systems other than ClearKey
         //
         // SECURITY NOTE: Production implementation MUST:
         // 1. Use platform CDM (Widevine, PlayReady, FairPlay)
@@ -296,9 +1229,26 @@ impl ContentDecryptionModule {
             ));
         }
 
-        // Placeholder: In production, this would call platform CDM
-        // For now, return "decrypted" data (actually just a copy for testing)
-        Ok(data.to_vec())
+        let keys = self.keys.read().unwrap_or_else(PoisonError::into_inner);
+        let key = keys.get(key_id).ok_or_else(|| {
+            DrmError::DecryptionFailed("Key ID is not licensed to any session".to_string())
+        })?;
+        if key
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= SystemTime::now())
+        {
+            return Err(DrmError::DecryptionFailed(
+                "License for key ID has expired".to_string(),
+            ));
+        }
+        key.policy.check(&self.output_protection())?;
+
+        match &key.key {
+            Some(content_key) => clearkey::decrypt(content_key, sample, data),
+            // Placeholder: In production, this would call platform CDM
+            // For now, return "decrypted" data (actually just a copy for testing)
+            None => Ok(data.to_vec()),
+        }
     }
 
     /// Get the key system for this CDM
@@ -307,6 +1257,13 @@ impl ContentDecryptionModule {
     }
 }
 
+/// Whether a license with `expiry` has expired
+fn is_expired(expiry: &LicenseExpiry) -> bool {
+    expiry
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= SystemTime::now())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -332,8 +1289,11 @@ mod tests {
         let session_id = cdm.create_session().await.unwrap();
 
         // Generate request
-        let init_data = b"test_init_data";
-        let request = cdm.generate_request(&session_id, init_data).await.unwrap();
+        let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+        let request = cdm
+            .generate_request(&session_id, "keyids", init_data)
+            .await
+            .unwrap();
         assert!(!request.is_empty());
 
         // Verify session state updated
@@ -356,4 +1316,24 @@ mod tests {
             assert!(session.license_data.is_some());
         }
     }
+
+    #[tokio::test]
+    async fn test_renewal_workflow() {
+        let cdm = ContentDecryptionModule::new("com.test.drm".to_string()).unwrap();
+        let session_id = cdm.create_session().await.unwrap();
+        cdm.update(&session_id, b"test_license").await.unwrap();
+
+        cdm.generate_renewal_request(&session_id).await.unwrap();
+        assert_eq!(
+            cdm.sessions.read().await[&session_id].state,
+            SessionState::Renewing
+        );
+
+        // The renewed license makes the session active again
+        cdm.update(&session_id, b"renewed_license").await.unwrap();
+        assert_eq!(
+            cdm.sessions.read().await[&session_id].state,
+            SessionState::Active
+        );
+    }
 }
@@ -0,0 +1,562 @@
+//! ClearKey key system
+//!
+//! Implements the W3C EME `org.w3.clearkey` key system: JSON Web Key set
+//! licenses, and decryption with the keys they carry of Common Encryption
+//! samples, either AES-128-CTR (`cenc`) or pattern AES-128-CBC (`cbcs`).
+
+use crate::types::{
+    DrmError, EncryptionPattern, EncryptionScheme, LicenseExpiry, OutputProtection,
+    SampleEncryption,
+};
+use aes::cipher::{BlockDecrypt, KeyInit, KeyIvInit, StreamCipher};
+use base64::Engine;
+use serde::Deserialize;
+use std::time::{Duration, SystemTime};
+
+/// Key system identifier for ClearKey
+pub(crate) const CLEARKEY_KEY_SYSTEM: &str = "org.w3.clearkey";
+
+/// Initialization data type ClearKey configurations must accept
+pub(crate) const KEYIDS_INIT_DATA_TYPE: &str = "keyids";
+
+/// AES-128 content key
+pub(crate) type ContentKey = [u8; 16];
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+/// Size of an AES block
+const BLOCK_SIZE: usize = 16;
+
+/// `"keyids"` init data: a list of base64url key IDs
+#[derive(Debug, Deserialize)]
+struct KeyIds {
+    kids: Vec<String>,
+}
+
+/// ClearKey license: a JSON Web Key set
+///
+/// `expiration`, `renewal_url`, `hdcp_required` and `allow_analog_output`
+/// are extensions to the standard format. `expiration` is in milliseconds
+/// since the Unix epoch, like `MediaKeySession.expiration`.
+#[derive(Debug, Deserialize)]
+struct License {
+    keys: Vec<JsonWebKey>,
+    #[serde(default)]
+    expiration: Option<f64>,
+    #[serde(default)]
+    renewal_url: Option<String>,
+    #[serde(default)]
+    hdcp_required: bool,
+    #[serde(default = "allow_by_default")]
+    allow_analog_output: bool,
+}
+
+fn allow_by_default() -> bool {
+    true
+}
+
+/// Error document a license server returns instead of a license, such as
+/// `{"status":403}`
+#[derive(Debug, Deserialize)]
+struct ServerError {
+    status: u16,
+}
+
+/// Output protection a license requires for its keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OutputPolicy {
+    /// Keys may only be used on HDCP-protected outputs
+    pub(crate) hdcp_required: bool,
+    /// Keys may be used while content is sent to an analog output
+    pub(crate) allow_analog_output: bool,
+}
+
+impl OutputPolicy {
+    /// Policy allowing keys on any output, for licenses without one
+    pub(crate) const UNRESTRICTED: Self = Self {
+        hdcp_required: false,
+        allow_analog_output: true,
+    };
+
+    /// Check that `output` satisfies the policy
+    pub(crate) fn check(&self, output: &OutputProtection) -> Result<(), DrmError> {
+        if self.hdcp_required && !output.hdcp_enabled {
+            return Err(DrmError::HdcpRequired);
+        }
+        if !self.allow_analog_output && output.analog_output {
+            return Err(DrmError::OutputNotAllowed);
+        }
+        Ok(())
+    }
+}
+
+/// Keys and expiry of a parsed ClearKey license
+#[derive(Debug)]
+pub(crate) struct ClearKeyLicense {
+    /// `(key ID, key)` pairs
+    pub(crate) keys: Vec<(Vec<u8>, ContentKey)>,
+    pub(crate) expiry: LicenseExpiry,
+    pub(crate) policy: OutputPolicy,
+}
+
+/// Symmetric JSON Web Key with base64url key ID and key
+#[derive(Debug, Deserialize)]
+struct JsonWebKey {
+    kty: String,
+    kid: String,
+    k: String,
+}
+
+/// Parse a ClearKey license into its keys, expiry and output policy
+///
+/// Key IDs and keys are base64url-encoded; trailing padding is tolerated.
+/// A license server error document is reported as
+/// [`DrmError::LicenseServerError`].
+pub(crate) fn parse_license(response: &[u8]) -> Result<ClearKeyLicense, DrmError> {
+    let license: License = serde_json::from_slice(response).map_err(|e| {
+        match serde_json::from_slice::<ServerError>(response) {
+            Ok(error) => DrmError::LicenseServerError {
+                status: error.status,
+            },
+            Err(_) => DrmError::LicenseRequestFailed(format!("Invalid ClearKey license: {}", e)),
+        }
+    })?;
+
+    let expires_at = match license.expiration {
+        Some(millis) if millis.is_finite() && millis >= 0.0 => {
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs_f64(millis / 1000.0))
+        }
+        Some(millis) => {
+            return Err(DrmError::LicenseRequestFailed(format!(
+                "Invalid ClearKey license expiration: {}",
+                millis
+            )))
+        }
+        None => None,
+    };
+
+    let keys = license
+        .keys
+        .into_iter()
+        .map(|key| {
+            if key.kty != "oct" {
+                return Err(DrmError::LicenseRequestFailed(format!(
+                    "Unsupported ClearKey key type: {}",
+                    key.kty
+                )));
+            }
+            let kid = decode_base64url(&key.kid)?;
+            let k = decode_base64url(&key.k)?;
+            let k = ContentKey::try_from(k.as_slice()).map_err(|_| {
+                DrmError::LicenseRequestFailed(format!(
+                    "ClearKey key must be 16 bytes, got {}",
+                    k.len()
+                ))
+            })?;
+            Ok((kid, k))
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(ClearKeyLicense {
+        keys,
+        expiry: LicenseExpiry {
+            expires_at,
+            renewal_server_url: license.renewal_url,
+        },
+        policy: OutputPolicy {
+            hdcp_required: license.hdcp_required,
+            allow_analog_output: license.allow_analog_output,
+        },
+    })
+}
+
+/// Parse `"keyids"` init data such as `{"kids":["..."]}` into key IDs
+pub(crate) fn parse_key_ids(init_data: &[u8]) -> Result<Vec<Vec<u8>>, DrmError> {
+    let key_ids: KeyIds = serde_json::from_slice(init_data)
+        .map_err(|e| DrmError::InvalidInitData(format!("Invalid keyids init data: {}", e)))?;
+
+    key_ids
+        .kids
+        .iter()
+        .map(|kid| {
+            decode_base64url(kid)
+                .map_err(|_| DrmError::InvalidInitData(format!("Invalid key ID: {}", kid)))
+        })
+        .collect()
+}
+
+/// Decrypt a sample using its IV, subsample layout and scheme
+///
+/// Under `cenc` the AES-128-CTR keystream runs continuously across the
+/// encrypted ranges. Under `cbcs` each encrypted range is decrypted with
+/// AES-128-CBC starting from the IV, only in the blocks the pattern
+/// encrypts. Clear ranges are copied unchanged.
+pub(crate) fn decrypt(
+    key: &ContentKey,
+    sample: &SampleEncryption,
+    data: &[u8],
+) -> Result<Vec<u8>, DrmError> {
+    let mut iv = [0u8; BLOCK_SIZE];
+    match sample.iv.len() {
+        8 | 16 => iv[..sample.iv.len()].copy_from_slice(&sample.iv),
+        len => {
+            return Err(DrmError::DecryptionFailed(format!(
+                "IV must be 8 or 16 bytes, got {}",
+                len
+            )))
+        }
+    }
+
+    let mut output = data.to_vec();
+    let ranges = encrypted_ranges(sample, data.len())?;
+    match sample.scheme {
+        EncryptionScheme::Cenc => {
+            let mut cipher = Aes128Ctr::new(key.into(), (&iv).into());
+            for range in ranges {
+                cipher.apply_keystream(&mut output[range]);
+            }
+        }
+        EncryptionScheme::Cbcs => {
+            let cipher = aes::Aes128::new(key.into());
+            for range in ranges {
+                decrypt_cbc_pattern(&cipher, &iv, sample.pattern, &mut output[range]);
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Byte ranges of a sample's encrypted data, checking that the subsamples
+/// cover the sample exactly
+fn encrypted_ranges(
+    sample: &SampleEncryption,
+    len: usize,
+) -> Result<Vec<std::ops::Range<usize>>, DrmError> {
+    if sample.subsamples.is_empty() {
+        return Ok(std::iter::once(0..len).collect());
+    }
+
+    let mut ranges = Vec::with_capacity(sample.subsamples.len());
+    let mut position = 0;
+    for subsample in &sample.subsamples {
+        let start = position + subsample.clear_bytes as usize;
+        let end = start + subsample.encrypted_bytes as usize;
+        if end > len {
+            return Err(DrmError::DecryptionFailed(format!(
+                "Subsamples exceed the {}-byte sample",
+                len
+            )));
+        }
+        ranges.push(start..end);
+        position = end;
+    }
+    if position != len {
+        return Err(DrmError::DecryptionFailed(format!(
+            "Subsamples cover {} of {} sample bytes",
+            position, len
+        )));
+    }
+    Ok(ranges)
+}
+
+/// Decrypt one encrypted range in place with AES-128-CBC from `iv`
+///
+/// Blocks the pattern skips are clear and do not take part in the chain.
+fn decrypt_cbc_pattern(
+    cipher: &aes::Aes128,
+    iv: &[u8; BLOCK_SIZE],
+    pattern: Option<EncryptionPattern>,
+    data: &mut [u8],
+) {
+    let (crypt, skip) = match pattern {
+        Some(pattern) if pattern.crypt_byte_block > 0 => (
+            pattern.crypt_byte_block as usize,
+            pattern.skip_byte_block as usize,
+        ),
+        _ => (1, 0),
+    };
+
+    let mut chain = *iv;
+    for (index, block) in data.chunks_exact_mut(BLOCK_SIZE).enumerate() {
+        if index % (crypt + skip) >= crypt {
+            continue;
+        }
+        let mut ciphertext = [0u8; BLOCK_SIZE];
+        ciphertext.copy_from_slice(block);
+        let mut decrypted = aes::Block::from(ciphertext);
+        cipher.decrypt_block(&mut decrypted);
+        for ((byte, plain), previous) in block.iter_mut().zip(decrypted).zip(chain) {
+            *byte = plain ^ previous;
+        }
+        chain = ciphertext;
+    }
+}
+
+/// Encode bytes as unpadded base64url, as ClearKey key IDs and keys are
+pub(crate) fn encode_base64url(bytes: &[u8]) -> String {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn decode_base64url(value: &str) -> Result<Vec<u8>, DrmError> {
+    base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|e| DrmError::LicenseRequestFailed(format!("Invalid base64url value: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Subsample;
+
+    /// NIST SP 800-38A key, used by its CTR and CBC vectors
+    const NIST_KEY: ContentKey = [
+        0x2b, 0x7e, 0x15, 0x16, 0x28, 0xae, 0xd2, 0xa6, 0xab, 0xf7, 0x15, 0x88, 0x09, 0xcf, 0x4f,
+        0x3c,
+    ];
+
+    /// NIST SP 800-38A, F.2.1 CBC-AES128 IV
+    const CBC_IV: [u8; 16] = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+
+    /// NIST SP 800-38A, F.2.1 CBC-AES128 first two plaintext blocks
+    const CBC_PLAINTEXT: [u8; 32] = [
+        0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93, 0x17,
+        0x2a, 0xae, 0x2d, 0x8a, 0x57, 0x1e, 0x03, 0xac, 0x9c, 0x9e, 0xb7, 0x6f, 0xac, 0x45, 0xaf,
+        0x8e, 0x51,
+    ];
+
+    /// NIST SP 800-38A, F.2.1 CBC-AES128 first two ciphertext blocks
+    const CBC_CIPHERTEXT: [u8; 32] = [
+        0x76, 0x49, 0xab, 0xac, 0x81, 0x19, 0xb2, 0x46, 0xce, 0xe9, 0x8e, 0x9b, 0x12, 0xe9, 0x19,
+        0x7d, 0x50, 0x86, 0xcb, 0x9b, 0x50, 0x72, 0x19, 0xee, 0x95, 0xdb, 0x11, 0x3a, 0x91, 0x76,
+        0x78, 0xb2,
+    ];
+
+    const KEY: ContentKey = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+
+    /// "Hello, ClearKey subsamples!" encrypted with `KEY` and IV 0102030405060708
+    const CIPHERTEXT: [u8; 27] = [
+        0x50, 0xdf, 0x05, 0xd7, 0x29, 0x4d, 0xde, 0xa6, 0xcb, 0xa9, 0xff, 0xb3, 0xec, 0x54, 0x9b,
+        0x58, 0xbc, 0xe4, 0xcf, 0xed, 0xbc, 0x94, 0xbe, 0x08, 0x3e, 0x22, 0x7f,
+    ];
+
+    fn sample(iv: &[u8], subsamples: &[(u32, u32)]) -> SampleEncryption {
+        SampleEncryption {
+            iv: iv.to_vec(),
+            subsamples: subsamples
+                .iter()
+                .map(|&(clear_bytes, encrypted_bytes)| Subsample {
+                    clear_bytes,
+                    encrypted_bytes,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn cbcs_sample(subsamples: &[(u32, u32)], pattern: Option<(u8, u8)>) -> SampleEncryption {
+        SampleEncryption {
+            scheme: EncryptionScheme::Cbcs,
+            pattern: pattern.map(|(crypt_byte_block, skip_byte_block)| EncryptionPattern {
+                crypt_byte_block,
+                skip_byte_block,
+            }),
+            ..sample(&CBC_IV, subsamples)
+        }
+    }
+
+    #[test]
+    fn test_parse_license() {
+        let license = br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw=="}]}"#;
+        let license = parse_license(license).unwrap();
+
+        assert_eq!(license.keys.len(), 1);
+        assert_eq!(license.keys[0].0, vec![0x10; 16]);
+        assert_eq!(license.keys[0].1, KEY);
+        assert_eq!(license.expiry, LicenseExpiry::default());
+    }
+
+    #[test]
+    fn test_parse_license_expiry() {
+        let license = br#"{"keys":[],"expiration":1700000000500,"renewal_url":"https://license.example.com/renew"}"#;
+        let license = parse_license(license).unwrap();
+
+        assert_eq!(
+            license.expiry.expires_at,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_500))
+        );
+        assert_eq!(
+            license.expiry.renewal_server_url.as_deref(),
+            Some("https://license.example.com/renew")
+        );
+        assert!(matches!(
+            parse_license(br#"{"keys":[],"expiration":-1}"#),
+            Err(DrmError::LicenseRequestFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_license_rejects_invalid_keys() {
+        let wrong_type = br#"{"keys":[{"kty":"RSA","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+        let short_key = br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAEC"}]}"#;
+
+        assert!(matches!(
+            parse_license(wrong_type),
+            Err(DrmError::LicenseRequestFailed(_))
+        ));
+        assert!(matches!(
+            parse_license(short_key),
+            Err(DrmError::LicenseRequestFailed(_))
+        ));
+        assert!(matches!(
+            parse_license(b"not json"),
+            Err(DrmError::LicenseRequestFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_license_output_policy() {
+        let default = parse_license(br#"{"keys":[]}"#).unwrap();
+        let restricted =
+            parse_license(br#"{"keys":[],"hdcp_required":true,"allow_analog_output":false}"#)
+                .unwrap();
+
+        assert_eq!(
+            default.policy,
+            OutputPolicy {
+                hdcp_required: false,
+                allow_analog_output: true,
+            }
+        );
+        assert!(default.policy.check(&OutputProtection::default()).is_ok());
+        assert!(matches!(
+            restricted.policy.check(&OutputProtection::default()),
+            Err(DrmError::HdcpRequired)
+        ));
+        assert!(matches!(
+            restricted.policy.check(&OutputProtection {
+                hdcp_enabled: true,
+                analog_output: true,
+            }),
+            Err(DrmError::OutputNotAllowed)
+        ));
+    }
+
+    #[test]
+    fn test_parse_license_server_error() {
+        assert!(matches!(
+            parse_license(br#"{"status":403,"message":"Forbidden"}"#),
+            Err(DrmError::LicenseServerError { status: 403 })
+        ));
+    }
+
+    #[test]
+    fn test_parse_key_ids() {
+        let key_ids =
+            parse_key_ids(br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA","AAECAwQFBgcICQoLDA0ODw"]}"#);
+
+        assert_eq!(key_ids.unwrap(), vec![vec![0x10; 16], KEY.to_vec()]);
+        assert_eq!(encode_base64url(&KEY), "AAECAwQFBgcICQoLDA0ODw");
+        assert!(matches!(
+            parse_key_ids(b"{}"),
+            Err(DrmError::InvalidInitData(_))
+        ));
+        assert!(matches!(
+            parse_key_ids(br#"{"kids":["!"]}"#),
+            Err(DrmError::InvalidInitData(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_nist_vector() {
+        // NIST SP 800-38A, F.5.1 CTR-AES128 (first block)
+        let key = NIST_KEY;
+        let iv = [
+            0xf0, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa, 0xfb, 0xfc, 0xfd,
+            0xfe, 0xff,
+        ];
+        let ciphertext = [
+            0x87, 0x4d, 0x61, 0x91, 0xb6, 0x20, 0xe3, 0x26, 0x1b, 0xef, 0x68, 0x64, 0x99, 0x0d,
+            0xb6, 0xce,
+        ];
+        let plaintext = [
+            0x6b, 0xc1, 0xbe, 0xe2, 0x2e, 0x40, 0x9f, 0x96, 0xe9, 0x3d, 0x7e, 0x11, 0x73, 0x93,
+            0x17, 0x2a,
+        ];
+
+        assert_eq!(
+            decrypt(&key, &sample(&iv, &[]), &ciphertext).unwrap(),
+            plaintext
+        );
+    }
+
+    #[test]
+    fn test_decrypt_subsamples() {
+        // Clear ranges interleaved with the two halves of the ciphertext
+        let mut data = vec![0xaa; 4];
+        data.extend_from_slice(&CIPHERTEXT[..10]);
+        data.extend_from_slice(&[0xbb; 3]);
+        data.extend_from_slice(&CIPHERTEXT[10..]);
+
+        let layout = sample(&[1, 2, 3, 4, 5, 6, 7, 8], &[(4, 10), (3, 17)]);
+        let decrypted = decrypt(&KEY, &layout, &data).unwrap();
+
+        let mut expected = vec![0xaa; 4];
+        expected.extend_from_slice(b"Hello, Cle");
+        expected.extend_from_slice(&[0xbb; 3]);
+        expected.extend_from_slice(b"arKey subsamples!");
+        assert_eq!(decrypted, expected);
+    }
+
+    #[test]
+    fn test_decrypt_cbcs_nist_vector() {
+        let decrypted = decrypt(&NIST_KEY, &cbcs_sample(&[], None), &CBC_CIPHERTEXT).unwrap();
+        assert_eq!(decrypted, CBC_PLAINTEXT);
+    }
+
+    #[test]
+    fn test_decrypt_cbcs_pattern_and_subsamples() {
+        // A 1:1 pattern over four blocks encrypts the first and third, which
+        // chain to each other, and a trailing partial block stays clear
+        let mut encrypted = CBC_CIPHERTEXT[..16].to_vec();
+        encrypted.extend_from_slice(&[0xcc; 16]);
+        encrypted.extend_from_slice(&CBC_CIPHERTEXT[16..]);
+        encrypted.extend_from_slice(&[0xdd; 16 + 5]);
+        let mut data = vec![0xaa; 3];
+        data.extend_from_slice(&encrypted);
+
+        let layout = cbcs_sample(&[(3, encrypted.len() as u32)], Some((1, 1)));
+        let decrypted = decrypt(&NIST_KEY, &layout, &data).unwrap();
+
+        let mut expected = vec![0xaa; 3];
+        expected.extend_from_slice(&CBC_PLAINTEXT[..16]);
+        expected.extend_from_slice(&[0xcc; 16]);
+        expected.extend_from_slice(&CBC_PLAINTEXT[16..]);
+        expected.extend_from_slice(&[0xdd; 16 + 5]);
+        assert_eq!(decrypted, expected);
+    }
+
+    #[test]
+    fn test_decrypt_cbcs_restarts_iv_per_subsample() {
+        let mut data = CBC_CIPHERTEXT[..16].to_vec();
+        data.extend_from_slice(&[0xbb; 2]);
+        data.extend_from_slice(&CBC_CIPHERTEXT[..16]);
+
+        let layout = cbcs_sample(&[(0, 16), (2, 16)], Some((1, 9)));
+        let decrypted = decrypt(&NIST_KEY, &layout, &data).unwrap();
+
+        assert_eq!(&decrypted[..16], &CBC_PLAINTEXT[..16]);
+        assert_eq!(&decrypted[18..], &CBC_PLAINTEXT[..16]);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_invalid_layout() {
+        let iv = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        assert!(decrypt(&KEY, &sample(&iv[..4], &[]), &CIPHERTEXT).is_err());
+        assert!(decrypt(&KEY, &sample(&iv, &[(4, 30)]), &CIPHERTEXT).is_err());
+        assert!(decrypt(&KEY, &sample(&iv, &[(4, 10)]), &CIPHERTEXT).is_err());
+    }
+}
@@ -3,8 +3,12 @@
 //! Provides the EME API for requesting media key system access and managing
 //! DRM capabilities according to the W3C EME specification.
 
-use crate::types::DrmError;
+use crate::clearkey::{CLEARKEY_KEY_SYSTEM, KEYIDS_INIT_DATA_TYPE};
+use crate::types::{DrmError, SessionType};
+use cortenbrowser_audio_decoders::DecoderFactory as AudioDecoderFactory;
+use cortenbrowser_video_decoders::DecoderFactory as VideoDecoderFactory;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Media Key System Configuration
 ///
@@ -62,6 +66,9 @@ pub struct MediaKeySystemMediaCapability {
     pub content_type: String,
 
     /// Robustness level required
+    ///
+    /// Values are key-system specific (e.g. `"HW_SECURE_ALL"` for Widevine,
+    /// `"3000"` for PlayReady); an empty string places no requirement.
     pub robustness: String,
 }
 
@@ -111,7 +118,10 @@ impl MediaKeySystemAccess {
     }
 
     /// Create with specific configuration
-    pub fn with_configuration(key_system: String, configuration: MediaKeySystemConfiguration) -> Self {
+    pub fn with_configuration(
+        key_system: String,
+        configuration: MediaKeySystemConfiguration,
+    ) -> Self {
         Self {
             key_system,
             configuration,
@@ -155,6 +165,38 @@ impl MediaKeySystemAccess {
 pub struct EMEInterface {
     /// Supported key systems
     supported_key_systems: Vec<String>,
+
+    /// What each supported key system allows
+    policies: HashMap<String, KeySystemPolicy>,
+}
+
+/// What a key system's CDM can provide to a configuration
+#[derive(Debug, Clone)]
+struct KeySystemPolicy {
+    /// Non-empty robustness levels it can satisfy
+    robustness: Vec<String>,
+
+    /// Session types it can create
+    session_types: Vec<SessionType>,
+
+    /// Whether it needs, may use or never uses a distinctive identifier
+    distinctive_identifier: MediaKeysRequirement,
+
+    /// Whether it needs, may use or never uses persistent state
+    persistent_state: MediaKeysRequirement,
+}
+
+impl KeySystemPolicy {
+    /// Policy of a software CDM with the given robustness levels and
+    /// session types, never using a distinctive identifier
+    fn software(robustness: &[&str], session_types: &[SessionType]) -> Self {
+        Self {
+            robustness: robustness.iter().map(|level| level.to_string()).collect(),
+            session_types: session_types.to_vec(),
+            distinctive_identifier: MediaKeysRequirement::NotAllowed,
+            persistent_state: MediaKeysRequirement::Optional,
+        }
+    }
 }
 
 impl EMEInterface {
@@ -168,6 +210,8 @@ impl EMEInterface {
     /// let eme = EMEInterface::new();
     /// ```
     pub fn new() -> Self {
+        use SessionType::{PersistentLicense, Temporary};
+
         Self {
             // Stub implementation: In production, this would query
             // available CDMs on the platform
@@ -179,12 +223,47 @@ impl EMEInterface {
                 // Test key system for development
                 "com.example.test".to_string(),
             ],
+            // Stub implementation: software CDMs only, so hardware-backed
+            // robustness levels are unavailable
+            policies: HashMap::from([
+                (
+                    "com.widevine.alpha".to_string(),
+                    KeySystemPolicy::software(
+                        &["SW_SECURE_CRYPTO", "SW_SECURE_DECODE"],
+                        &[Temporary, PersistentLicense],
+                    ),
+                ),
+                (
+                    "com.microsoft.playready".to_string(),
+                    KeySystemPolicy::software(&["150", "2000"], &[Temporary, PersistentLicense]),
+                ),
+                (
+                    "com.apple.fps".to_string(),
+                    KeySystemPolicy::software(&[], &[Temporary]),
+                ),
+                (
+                    "org.w3.clearkey".to_string(),
+                    KeySystemPolicy::software(&[], &[Temporary, PersistentLicense]),
+                ),
+                (
+                    "com.example.test".to_string(),
+                    KeySystemPolicy {
+                        distinctive_identifier: MediaKeysRequirement::Optional,
+                        ..KeySystemPolicy::software(&[], &[Temporary, PersistentLicense])
+                    },
+                ),
+            ]),
         }
     }
 
     /// Request media key system access
     ///
-    /// Attempts to find a supported configuration for the requested key system.
+    /// Attempts to find a supported configuration for the requested key
+    /// system, following the configuration selection of the EME
+    /// specification. Configurations are tried in order, and the first one
+    /// that [`supported_configuration`](Self::supported_configuration)
+    /// accepts is selected, with its unsupported capabilities and init data
+    /// types left out.
     ///
     /// # Arguments
     ///
@@ -194,7 +273,8 @@ impl EMEInterface {
     /// # Returns
     ///
     /// * `Ok(MediaKeySystemAccess)` - Access granted with selected configuration
-    /// * `Err(DrmError::UnsupportedKeySystem)` - No supported configuration found
+    /// * `Err(DrmError::UnsupportedKeySystem)` - The key system is not supported,
+    ///   or none of the configurations can be satisfied
     ///
     /// # Examples
     ///
@@ -223,23 +303,185 @@ impl EMEInterface {
         key_system: String,
         configs: Vec<MediaKeySystemConfiguration>,
     ) -> Result<MediaKeySystemAccess, DrmError> {
-        // Stub implementation: In production, this would:
-        // 1. Check if key system is supported on this platform
-        // 2. Query CDM for supported configurations
-        // 3. Match requested configs against CDM capabilities
-        // 4. Select best matching configuration
-        // 5. Return access object for creating MediaKeys
+        // Stub implementation: In production, the CDM would be queried for
+        // supported configurations instead of the static tables
 
         // For stub, check if key system is in our supported list
         if !self.supported_key_systems.contains(&key_system) {
             return Err(DrmError::UnsupportedKeySystem(key_system));
         }
 
-        // Use first configuration if available, otherwise use default
-        let configuration = configs.into_iter().next()
-            .unwrap_or_default();
+        // Use default configuration if none were requested
+        if configs.is_empty() {
+            return Ok(MediaKeySystemAccess::with_configuration(
+                key_system,
+                MediaKeySystemConfiguration::default(),
+            ));
+        }
+
+        match configs
+            .iter()
+            .find_map(|config| self.supported_configuration(&key_system, config))
+        {
+            Some(configuration) => Ok(MediaKeySystemAccess::with_configuration(
+                key_system,
+                configuration,
+            )),
+            None => Err(DrmError::UnsupportedKeySystem(key_system)),
+        }
+    }
+
+    /// Get the part of a configuration a key system can satisfy
+    ///
+    /// A configuration is satisfiable when:
+    ///
+    /// * at least one of its init data types, if it lists any, is `"cenc"`,
+    ///   `"keyids"` or `"webm"`; ClearKey also requires `"keyids"`
+    /// * at least one of its audio capabilities, if it lists any, and one
+    ///   of its video capabilities, if it lists any, is supported, see
+    ///   [`is_capability_supported`](Self::is_capability_supported)
+    /// * the key system can create each of its session types, and
+    ///   persistent sessions are not combined with a `NotAllowed`
+    ///   persistent state
+    /// * its distinctive identifier and persistent state requirements do not
+    ///   conflict with what the key system needs or can provide
+    ///
+    /// # Returns
+    ///
+    /// The configuration without its unsupported init data types and
+    /// capabilities, or `None` if it cannot be satisfied
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{
+    ///     EMEInterface, MediaKeySystemConfiguration, MediaKeySystemMediaCapability,
+    /// };
+    ///
+    /// let eme = EMEInterface::new();
+    /// let capability = |content_type: &str| MediaKeySystemMediaCapability {
+    ///     content_type: content_type.to_string(),
+    ///     robustness: String::new(),
+    /// };
+    /// let config = MediaKeySystemConfiguration {
+    ///     video_capabilities: vec![
+    ///         capability("video/mp4; codecs=\"hvc1.1.6.L93.B0\""),
+    ///         capability("video/mp4; codecs=\"avc1.42E01E\""),
+    ///     ],
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let accepted = eme
+    ///     .supported_configuration("com.widevine.alpha", &config)
+    ///     .unwrap();
+    /// assert_eq!(accepted.video_capabilities, vec![capability("video/mp4; codecs=\"avc1.42E01E\"")]);
+    /// ```
+    pub fn supported_configuration(
+        &self,
+        key_system: &str,
+        config: &MediaKeySystemConfiguration,
+    ) -> Option<MediaKeySystemConfiguration> {
+        let policy = self.policies.get(key_system)?;
+
+        let init_data_types = filter_supported(&config.init_data_types, |init_data_type| {
+            SUPPORTED_INIT_DATA_TYPES.contains(&init_data_type.as_str())
+        })?;
+        if key_system == CLEARKEY_KEY_SYSTEM
+            && !init_data_types.iter().any(|t| t == KEYIDS_INIT_DATA_TYPE)
+        {
+            return None;
+        }
+
+        if !requirement_satisfiable(config.distinctive_identifier, policy.distinctive_identifier)
+            || !requirement_satisfiable(config.persistent_state, policy.persistent_state)
+        {
+            return None;
+        }
+
+        for session_type in &config.session_types {
+            let session_type = parse_session_type(session_type)?;
+            if !policy.session_types.contains(&session_type) {
+                return None;
+            }
+            if session_type != SessionType::Temporary
+                && config.persistent_state == MediaKeysRequirement::NotAllowed
+            {
+                return None;
+            }
+        }
+
+        let audio_capabilities = filter_supported(&config.audio_capabilities, |capability| {
+            capability.content_type.trim_start().starts_with("audio/")
+                && self.is_capability_supported(key_system, capability)
+        })?;
+        let video_capabilities = filter_supported(&config.video_capabilities, |capability| {
+            capability.content_type.trim_start().starts_with("video/")
+                && self.is_capability_supported(key_system, capability)
+        })?;
+
+        Some(MediaKeySystemConfiguration {
+            init_data_types,
+            audio_capabilities,
+            video_capabilities,
+            ..config.clone()
+        })
+    }
+
+    /// Check if a key system can satisfy a configuration
+    ///
+    /// See [`supported_configuration`](Self::supported_configuration) for
+    /// when a configuration is satisfiable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_drm_support::{
+    ///     EMEInterface, MediaKeySystemConfiguration, MediaKeySystemMediaCapability,
+    /// };
+    ///
+    /// let eme = EMEInterface::new();
+    /// let config = MediaKeySystemConfiguration {
+    ///     video_capabilities: vec![MediaKeySystemMediaCapability {
+    ///         content_type: "video/mp4; codecs=\"avc1.42E01E\"".to_string(),
+    ///         robustness: "HW_SECURE_ALL".to_string(),
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    /// assert!(!eme.is_configuration_supported("com.widevine.alpha", &config));
+    /// ```
+    pub fn is_configuration_supported(
+        &self,
+        key_system: &str,
+        config: &MediaKeySystemConfiguration,
+    ) -> bool {
+        self.supported_configuration(key_system, config).is_some()
+    }
+
+    /// Check if a key system can satisfy a capability
+    ///
+    /// The content type must be an MP4 or WebM audio or video type whose
+    /// `codecs` are all decodable by this build, video codecs for a video
+    /// type and audio codecs for an audio type. A type without `codecs` is
+    /// accepted for its container alone. An empty robustness is
+    /// satisfiable by every supported key system.
+    pub fn is_capability_supported(
+        &self,
+        key_system: &str,
+        capability: &MediaKeySystemMediaCapability,
+    ) -> bool {
+        self.is_key_system_supported(key_system)
+            && is_content_type_supported(&capability.content_type)
+            && (capability.robustness.is_empty()
+                || self
+                    .supported_robustness(key_system)
+                    .contains(&capability.robustness))
+    }
 
-        Ok(MediaKeySystemAccess::with_configuration(key_system, configuration))
+    /// Get the non-empty robustness levels a key system supports
+    pub fn supported_robustness(&self, key_system: &str) -> &[String] {
+        self.policies
+            .get(key_system)
+            .map_or(&[], |policy| policy.robustness.as_slice())
     }
 
     /// Check if a key system is supported
@@ -269,6 +511,92 @@ impl Default for EMEInterface {
     }
 }
 
+/// Init data types the CDM can extract key IDs from
+const SUPPORTED_INIT_DATA_TYPES: [&str; 3] = ["cenc", KEYIDS_INIT_DATA_TYPE, "webm"];
+
+/// Keep the supported items of a list
+///
+/// An empty list stays empty, while a non-empty list without supported
+/// items gives `None`.
+fn filter_supported<T: Clone>(items: &[T], supported: impl Fn(&T) -> bool) -> Option<Vec<T>> {
+    let kept: Vec<T> = items
+        .iter()
+        .filter(|item| supported(item))
+        .cloned()
+        .collect();
+    (!kept.is_empty() || items.is_empty()).then_some(kept)
+}
+
+/// Whether a configuration's requirement is compatible with what a key
+/// system needs (`Required`), may use (`Optional`) or never uses
+/// (`NotAllowed`)
+fn requirement_satisfiable(
+    requested: MediaKeysRequirement,
+    key_system: MediaKeysRequirement,
+) -> bool {
+    !matches!(
+        (requested, key_system),
+        (
+            MediaKeysRequirement::Required,
+            MediaKeysRequirement::NotAllowed
+        ) | (
+            MediaKeysRequirement::NotAllowed,
+            MediaKeysRequirement::Required
+        )
+    )
+}
+
+/// Session type named by an EME `MediaKeySessionType` string
+fn parse_session_type(name: &str) -> Option<SessionType> {
+    match name {
+        "temporary" => Some(SessionType::Temporary),
+        "persistent-license" => Some(SessionType::PersistentLicense),
+        "persistent-release-message" => Some(SessionType::PersistentReleaseMessage),
+        _ => None,
+    }
+}
+
+/// Whether this build can demux and decode a capability's content type
+fn is_content_type_supported(content_type: &str) -> bool {
+    let mut parts = content_type.split(';');
+    let essence = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+    let codecs: Vec<&str> = parts
+        .filter_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("codecs")
+                .then(|| value.trim().trim_matches('"'))
+        })
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|codec| !codec.is_empty())
+        .collect();
+
+    match essence.as_str() {
+        "video/mp4" | "video/webm" => codecs.iter().all(|codec| is_video_codec_supported(codec)),
+        "audio/mp4" | "audio/webm" => codecs
+            .iter()
+            .all(|codec| AudioDecoderFactory::parse_codec_string(codec).is_ok()),
+        _ => false,
+    }
+}
+
+/// Whether a video decoder of this build handles an RFC 6381 codec string
+fn is_video_codec_supported(codec: &str) -> bool {
+    let family = codec
+        .split('.')
+        .next()
+        .unwrap_or(codec)
+        .to_ascii_lowercase();
+    let name = match family.as_str() {
+        "avc1" | "avc3" => "H.264",
+        "vp09" | "vp9" => "VP9",
+        "av01" => "AV1",
+        _ => return false,
+    };
+    VideoDecoderFactory::supported_codecs().contains(&name)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,7 +606,10 @@ mod tests {
         let config = MediaKeySystemConfiguration::default();
         assert_eq!(config.init_data_types.len(), 2);
         assert!(config.init_data_types.contains(&"cenc".to_string()));
-        assert_eq!(config.distinctive_identifier, MediaKeysRequirement::Optional);
+        assert_eq!(
+            config.distinctive_identifier,
+            MediaKeysRequirement::Optional
+        );
     }
 
     #[test]
@@ -300,13 +631,45 @@ mod tests {
         let eme = EMEInterface::new();
         let configs = vec![MediaKeySystemConfiguration::default()];
 
-        let result = eme.request_media_key_system_access(
-            "com.widevine.alpha".to_string(),
-            configs
-        ).await;
+        let result = eme
+            .request_media_key_system_access("com.widevine.alpha".to_string(), configs)
+            .await;
 
         assert!(result.is_ok());
         let access = result.unwrap();
         assert_eq!(access.key_system(), "com.widevine.alpha");
     }
+
+    #[test]
+    fn test_capability_robustness_matching() {
+        let eme = EMEInterface::new();
+        let capability = |robustness: &str| MediaKeySystemMediaCapability {
+            content_type: "video/mp4".to_string(),
+            robustness: robustness.to_string(),
+        };
+
+        assert!(eme.is_capability_supported("com.widevine.alpha", &capability("")));
+        assert!(eme.is_capability_supported("com.widevine.alpha", &capability("SW_SECURE_CRYPTO")));
+        assert!(!eme.is_capability_supported("com.widevine.alpha", &capability("HW_SECURE_ALL")));
+        assert!(!eme.is_capability_supported("org.w3.clearkey", &capability("SW_SECURE_CRYPTO")));
+        assert!(!eme.is_capability_supported("com.unknown.system", &capability("")));
+    }
+
+    #[test]
+    fn test_content_type_support() {
+        assert!(is_content_type_supported(
+            "video/mp4; codecs=\"avc1.42E01E\""
+        ));
+        assert!(is_content_type_supported("Audio/WebM; codecs=opus"));
+        assert!(is_content_type_supported("video/mp4"));
+        // Codecs must all be decodable and match the media type
+        assert!(!is_content_type_supported(
+            "video/mp4; codecs=\"avc1.42E01E, mp4a.40.2\""
+        ));
+        assert!(!is_content_type_supported(
+            "audio/mp4; codecs=\"avc1.42E01E\""
+        ));
+        assert!(!is_content_type_supported("video/webm; codecs=vp8"));
+        assert!(!is_content_type_supported("application/x-mpegurl"));
+    }
 }
@@ -8,7 +8,13 @@
 //! - Content Decryption Module (CDM) interface for managing DRM sessions
 //! - EME (Encrypted Media Extensions) API for requesting key system access
 //! - License acquisition and session management
-//! - Decryption interface (stub implementation - production requires platform CDM)
+//! - Asynchronous `message` and `keystatuseschange` events from the CDM
+//! - License expiry checks, renewal requests and key status reporting
+//! - Persistent-license sessions through a pluggable [`SessionStore`]
+//! - PSSH box parsing for CENC init data
+//! - ClearKey (`org.w3.clearkey`) licenses, with AES-128-CTR (`cenc`) and
+//!   pattern AES-128-CBC (`cbcs`) decryption
+//! - Decryption interface for other key systems (stub implementation - production requires platform CDM)
 //!
 //! # Architecture
 //!
@@ -43,9 +49,9 @@
 //!     // Step 3: Create a DRM session
 //!     let session_id = cdm.create_session().await?;
 //!
-//!     // Step 4: Generate license request
-//!     let init_data = b"initialization_data_from_media";
-//!     let license_request = cdm.generate_request(&session_id, init_data).await?;
+//!     // Step 4: Generate license request from the media's key IDs
+//!     let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+//!     let license_request = cdm.generate_request(&session_id, "keyids", init_data).await?;
 //!
 //!     // Step 5: (Send license_request to license server and get response)
 //!     let license_response = b"license_from_server";
@@ -53,10 +59,10 @@
 //!     // Step 6: Update session with license
 //!     cdm.update(&session_id, license_response).await?;
 //!
-//!     // Step 7: Decrypt content
+//!     // Step 7: Decrypt content with one of the session's keys
 //!     let encrypted_data = b"encrypted_content";
-//!     let key_id = b"key_identifier";
-//!     let decrypted_data = cdm.decrypt(encrypted_data, key_id)?;
+//!     let key_id = [0x10; 16];
+//!     let decrypted_data = cdm.decrypt(encrypted_data, &key_id)?;
 //!
 //!     Ok(())
 //! }
@@ -80,13 +86,22 @@
 
 // Module declarations
 mod cdm;
+mod clearkey;
 mod eme;
+mod pssh;
+mod session_store;
 mod types;
 
 // Re-export public API
 pub use cdm::ContentDecryptionModule;
 pub use eme::{
-    EMEInterface, MediaKeySystemAccess, MediaKeySystemConfiguration,
-    MediaKeySystemMediaCapability, MediaKeysRequirement,
+    EMEInterface, MediaKeySystemAccess, MediaKeySystemConfiguration, MediaKeySystemMediaCapability,
+    MediaKeysRequirement,
+};
+pub use pssh::{PsshBox, PsshParser, COMMON_SYSTEM_ID, PLAYREADY_SYSTEM_ID, WIDEVINE_SYSTEM_ID};
+pub use session_store::{InMemorySessionStore, SessionStore};
+pub use types::{
+    CdmEvent, DrmError, DrmSessionId, EncryptionPattern, EncryptionScheme, KeyStatus,
+    LicenseExpiry, LicenseValidity, MessageType, OutputProtection, SampleEncryption, SessionState,
+    SessionType, Subsample,
 };
-pub use types::{DrmError, DrmSessionId, SessionState, SessionType};
@@ -0,0 +1,260 @@
+//! PSSH box parsing
+//!
+//! Parses Protection System Specific Header boxes, as carried in the `moov`
+//! box of CENC-protected MP4 init segments and in `"cenc"` EME init data.
+
+use crate::clearkey::CLEARKEY_KEY_SYSTEM;
+use crate::types::DrmError;
+
+/// Widevine system ID (`edef8ba9-79d6-4ace-a3c8-27dcd51d21ed`)
+pub const WIDEVINE_SYSTEM_ID: [u8; 16] = [
+    0xed, 0xef, 0x8b, 0xa9, 0x79, 0xd6, 0x4a, 0xce, 0xa3, 0xc8, 0x27, 0xdc, 0xd5, 0x1d, 0x21, 0xed,
+];
+
+/// PlayReady system ID (`9a04f079-9840-4286-ab92-e65be0885f95`)
+pub const PLAYREADY_SYSTEM_ID: [u8; 16] = [
+    0x9a, 0x04, 0xf0, 0x79, 0x98, 0x40, 0x42, 0x86, 0xab, 0x92, 0xe6, 0x5b, 0xe0, 0x88, 0x5f, 0x95,
+];
+
+/// W3C Common PSSH system ID (`1077efec-c0b2-4d02-ace3-3c1e52e2fb4b`), used by ClearKey
+pub const COMMON_SYSTEM_ID: [u8; 16] = [
+    0x10, 0x77, 0xef, 0xec, 0xc0, 0xb2, 0x4d, 0x02, 0xac, 0xe3, 0x3c, 0x1e, 0x52, 0xe2, 0xfb, 0x4b,
+];
+
+/// Protobuf tag of the repeated `key_id` field in Widevine PSSH data
+const WIDEVINE_KEY_ID_TAG: u64 = 0x12;
+
+/// A parsed PSSH box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PsshBox {
+    /// Protection system ID
+    pub system_id: [u8; 16],
+
+    /// Key IDs from a version 1 box, or from the data of a version 0 Widevine box
+    pub key_ids: Vec<[u8; 16]>,
+
+    /// System-specific data
+    pub pssh_data: Vec<u8>,
+}
+
+impl PsshBox {
+    /// Get the key system this box's system ID belongs to, if known
+    pub fn key_system(&self) -> Option<&'static str> {
+        match self.system_id {
+            WIDEVINE_SYSTEM_ID => Some("com.widevine.alpha"),
+            PLAYREADY_SYSTEM_ID => Some("com.microsoft.playready"),
+            COMMON_SYSTEM_ID => Some(CLEARKEY_KEY_SYSTEM),
+            _ => None,
+        }
+    }
+}
+
+/// Parser for concatenated PSSH boxes
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_drm_support::{PsshParser, COMMON_SYSTEM_ID};
+///
+/// let mut data = vec![0, 0, 0, 52];
+/// data.extend_from_slice(b"pssh");
+/// data.extend_from_slice(&[1, 0, 0, 0]);
+/// data.extend_from_slice(&COMMON_SYSTEM_ID);
+/// data.extend_from_slice(&[0, 0, 0, 1]);
+/// data.extend_from_slice(&[0x10; 16]);
+/// data.extend_from_slice(&[0, 0, 0, 0]);
+///
+/// let boxes = PsshParser::parse(&data).unwrap();
+/// assert_eq!(boxes[0].key_system(), Some("org.w3.clearkey"));
+/// assert_eq!(boxes[0].key_ids, vec![[0x10; 16]]);
+/// ```
+#[derive(Debug)]
+pub struct PsshParser;
+
+impl PsshParser {
+    /// Parse every PSSH box in `data`
+    ///
+    /// `data` is a sequence of MP4 boxes: either `"cenc"` init data, or an
+    /// init segment whose `moov` box holds the PSSH boxes. Other boxes are
+    /// skipped.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<PsshBox>)` - The PSSH boxes, in order
+    /// * `Err(DrmError::InvalidInitData)` - If a box is truncated or has an
+    ///   unsupported version
+    pub fn parse(data: &[u8]) -> Result<Vec<PsshBox>, DrmError> {
+        let mut boxes = Vec::new();
+        Self::parse_boxes(data, &mut boxes)?;
+        Ok(boxes)
+    }
+
+    fn parse_boxes(mut data: &[u8], boxes: &mut Vec<PsshBox>) -> Result<(), DrmError> {
+        while !data.is_empty() {
+            let (box_type, payload) = read_box(&mut data)?;
+            match &box_type {
+                b"pssh" => boxes.push(parse_pssh(payload)?),
+                b"moov" => Self::parse_boxes(payload, boxes)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read the type and payload of the next box in `data`
+fn read_box<'a>(data: &mut &'a [u8]) -> Result<([u8; 4], &'a [u8]), DrmError> {
+    let mut header = *data;
+    let size = read_u32(&mut header)? as u64;
+    let box_type: [u8; 4] = take(&mut header, 4)?.try_into().unwrap_or_default();
+    let size = match size {
+        0 => data.len() as u64,
+        1 => u64::from_be_bytes(take(&mut header, 8)?.try_into().unwrap_or_default()),
+        size => size,
+    };
+
+    let header_len = (data.len() - header.len()) as u64;
+    if size < header_len || size > data.len() as u64 {
+        return Err(invalid("Box size out of range"));
+    }
+    let body = take(data, size as usize)?;
+    Ok((box_type, &body[header_len as usize..]))
+}
+
+fn parse_pssh(mut payload: &[u8]) -> Result<PsshBox, DrmError> {
+    let version = take(&mut payload, 4)?[0];
+    if version > 1 {
+        return Err(invalid(&format!(
+            "Unsupported PSSH box version {}",
+            version
+        )));
+    }
+    let system_id = read_key_id(&mut payload)?;
+
+    let mut key_ids = Vec::new();
+    if version == 1 {
+        let count = read_u32(&mut payload)?;
+        for _ in 0..count {
+            key_ids.push(read_key_id(&mut payload)?);
+        }
+    }
+
+    let data_size = read_u32(&mut payload)? as usize;
+    let pssh_data = take(&mut payload, data_size)?.to_vec();
+    if version == 0 && system_id == WIDEVINE_SYSTEM_ID {
+        key_ids = widevine_key_ids(&pssh_data);
+    }
+
+    Ok(PsshBox {
+        system_id,
+        key_ids,
+        pssh_data,
+    })
+}
+
+/// Collect the `key_id` fields of Widevine PSSH data
+///
+/// The data is a `WidevinePsshData` protobuf message. Parsing stops at the
+/// first malformed field, since the data is otherwise opaque to the CDM.
+fn widevine_key_ids(mut data: &[u8]) -> Vec<[u8; 16]> {
+    let mut key_ids = Vec::new();
+    while let Some(tag) = read_varint(&mut data) {
+        let length = match tag & 0x7 {
+            0 => match read_varint(&mut data) {
+                Some(_) => continue,
+                None => break,
+            },
+            1 => 8,
+            2 => match read_varint(&mut data) {
+                Some(length) => length as usize,
+                None => break,
+            },
+            5 => 4,
+            _ => break,
+        };
+        let Ok(field) = take(&mut data, length) else {
+            break;
+        };
+        if tag == WIDEVINE_KEY_ID_TAG {
+            if let Ok(key_id) = field.try_into() {
+                key_ids.push(key_id);
+            }
+        }
+    }
+    key_ids
+}
+
+fn read_varint(data: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = data.split_first()?;
+        *data = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn take<'a>(data: &mut &'a [u8], length: usize) -> Result<&'a [u8], DrmError> {
+    if data.len() < length {
+        return Err(invalid("Truncated PSSH box"));
+    }
+    let (head, rest) = data.split_at(length);
+    *data = rest;
+    Ok(head)
+}
+
+fn read_u32(data: &mut &[u8]) -> Result<u32, DrmError> {
+    Ok(u32::from_be_bytes(
+        take(data, 4)?.try_into().unwrap_or_default(),
+    ))
+}
+
+fn read_key_id(data: &mut &[u8]) -> Result<[u8; 16], DrmError> {
+    Ok(take(data, 16)?.try_into().unwrap_or_default())
+}
+
+fn invalid(message: &str) -> DrmError {
+    DrmError::InvalidInitData(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_box_sizes() {
+        let mut data = vec![0, 0, 0, 12];
+        data.extend_from_slice(b"free");
+        data.extend_from_slice(&[1, 2, 3, 4]);
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(b"skip");
+        data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 17, 5]);
+
+        let mut rest = &data[..];
+        let (box_type, payload) = read_box(&mut rest).unwrap();
+        assert_eq!((&box_type, payload), (b"free", &[1, 2, 3, 4][..]));
+
+        let (box_type, payload) = read_box(&mut rest).unwrap();
+        assert_eq!((&box_type, payload), (b"skip", &[5][..]));
+        assert!(rest.is_empty());
+
+        assert!(read_box(&mut &data[..10]).is_err());
+    }
+
+    #[test]
+    fn test_widevine_key_ids_skips_other_fields() {
+        let mut data = vec![0x08, 0x01, 0x12, 0x10];
+        data.extend_from_slice(&[0xab; 16]);
+        data.extend_from_slice(&[0x12, 0x02, 0x01, 0x02]);
+        data.extend_from_slice(&[0x1a, 0x03]);
+        data.extend_from_slice(b"abc");
+        data.extend_from_slice(&[0x12, 0x10]);
+        data.extend_from_slice(&[0xcd; 16]);
+
+        assert_eq!(widevine_key_ids(&data), vec![[0xab; 16], [0xcd; 16]]);
+        assert!(widevine_key_ids(&data[..10]).is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+//! Persistent session storage
+//!
+//! Stores the serialized state of persistent-license sessions so that they
+//! can be loaded again by a later [`ContentDecryptionModule`], e.g. for
+//! offline playback after a restart.
+//!
+//! [`ContentDecryptionModule`]: crate::ContentDecryptionModule
+
+use crate::types::{DrmError, DrmSessionId};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Mutex, PoisonError};
+
+/// Storage backend for persistent-license sessions
+///
+/// Records are opaque serialized session data, including the license, keyed
+/// by session ID. Implementations may keep them in memory, on disk or in a
+/// platform key store.
+pub trait SessionStore: Debug + Send + Sync {
+    /// Store the record of a session, replacing any previous one
+    fn save(&self, session_id: &DrmSessionId, record: Vec<u8>) -> Result<(), DrmError>;
+
+    /// Load the record of a session, or `None` if none is stored
+    fn load(&self, session_id: &DrmSessionId) -> Result<Option<Vec<u8>>, DrmError>;
+
+    /// Delete the record of a session; deleting a missing record succeeds
+    fn remove(&self, session_id: &DrmSessionId) -> Result<(), DrmError>;
+}
+
+/// Session store keeping records in memory
+///
+/// Records last as long as the store, which can be shared between CDM
+/// instances.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_drm_support::{DrmSessionId, InMemorySessionStore, SessionStore};
+///
+/// let store = InMemorySessionStore::new();
+/// let session_id = DrmSessionId::new();
+///
+/// store.save(&session_id, b"record".to_vec()).unwrap();
+/// assert_eq!(store.load(&session_id).unwrap(), Some(b"record".to_vec()));
+///
+/// store.remove(&session_id).unwrap();
+/// assert_eq!(store.load(&session_id).unwrap(), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct InMemorySessionStore {
+    records: Mutex<HashMap<DrmSessionId, Vec<u8>>>,
+}
+
+impl InMemorySessionStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SessionStore for InMemorySessionStore {
+    fn save(&self, session_id: &DrmSessionId, record: Vec<u8>) -> Result<(), DrmError> {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(session_id.clone(), record);
+        Ok(())
+    }
+
+    fn load(&self, session_id: &DrmSessionId) -> Result<Option<Vec<u8>>, DrmError> {
+        Ok(self
+            .records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(session_id)
+            .cloned())
+    }
+
+    fn remove(&self, session_id: &DrmSessionId) -> Result<(), DrmError> {
+        self.records
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(session_id);
+        Ok(())
+    }
+}
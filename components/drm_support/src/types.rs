@@ -2,8 +2,11 @@
 //!
 //! This module defines the fundamental types used throughout the DRM support component.
 
+use cortenbrowser_shared_types::{EncryptionInfo, MediaError};
+pub use cortenbrowser_shared_types::{EncryptionPattern, EncryptionScheme, Subsample};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::{Duration, SystemTime};
 use uuid::Uuid;
 
 /// Unique identifier for a DRM session
@@ -91,6 +94,58 @@ pub enum DrmError {
     /// The requested DRM session was not found
     #[error("Session not found: {0}")]
     SessionNotFound(DrmSessionId),
+
+    /// The DRM session has been closed and can no longer be used
+    #[error("Session closed: {0}")]
+    SessionClosed(DrmSessionId),
+
+    /// Initialization data is malformed or of an unsupported type
+    #[error("Invalid initialization data: {0}")]
+    InvalidInitData(String),
+
+    /// Persistent session data could not be stored, loaded or removed
+    #[error("Session storage failed: {0}")]
+    SessionStorageFailed(String),
+
+    /// The license requires HDCP, but the output is not HDCP-protected
+    #[error("HDCP output protection required")]
+    HdcpRequired,
+
+    /// The license does not allow playback on the current output
+    #[error("Output not allowed by license")]
+    OutputNotAllowed,
+
+    /// The license server answered with an error instead of a license
+    #[error("License server error: status {status}")]
+    LicenseServerError {
+        /// Status code reported by the license server
+        status: u16,
+    },
+}
+
+/// Translate DRM errors into media engine errors
+///
+/// License server failures are transport failures and become
+/// [`MediaError::NetworkError`]; all other errors, including output
+/// protection policy failures, become [`MediaError::DrmError`].
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_drm_support::DrmError;
+/// use cortenbrowser_shared_types::MediaError;
+///
+/// let error = MediaError::from(DrmError::HdcpRequired);
+/// assert!(matches!(error, MediaError::DrmError { .. }));
+/// ```
+impl From<DrmError> for MediaError {
+    fn from(error: DrmError) -> Self {
+        let details = error.to_string();
+        match error {
+            DrmError::LicenseServerError { .. } => MediaError::NetworkError { details },
+            _ => MediaError::DrmError { details },
+        }
+    }
 }
 
 /// Session types for DRM sessions
@@ -124,6 +179,10 @@ pub enum SessionState {
     /// License has been received and session is usable
     Active,
 
+    /// License is about to expire and a renewal request has been generated,
+    /// waiting for the renewed license
+    Renewing,
+
     /// Session has been closed
     Closed,
 
@@ -131,6 +190,196 @@ pub enum SessionState {
     Error,
 }
 
+/// Expiry of a session's license
+///
+/// Parsed from the license for ClearKey, or provided by the platform CDM for
+/// other key systems.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_drm_support::LicenseExpiry;
+/// use std::time::{Duration, SystemTime};
+///
+/// let expiry = LicenseExpiry {
+///     expires_at: Some(SystemTime::now() + Duration::from_secs(3600)),
+///     renewal_server_url: Some("https://license.example.com/renew".to_string()),
+/// };
+/// assert!(expiry.expires_at.is_some());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct LicenseExpiry {
+    /// When the license expires; `None` for licenses that never expire
+    pub expires_at: Option<SystemTime>,
+
+    /// License server to send renewal requests to; without one, licenses
+    /// are not renewed automatically
+    pub renewal_server_url: Option<String>,
+}
+
+/// Output protection of the display content is played on
+///
+/// Set on the CDM with
+/// [`ContentDecryptionModule::set_output_protection`](crate::ContentDecryptionModule::set_output_protection)
+/// and checked against the output policy of ClearKey licenses when
+/// decrypting.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_drm_support::OutputProtection;
+///
+/// let output = OutputProtection {
+///     hdcp_enabled: true,
+///     ..OutputProtection::default()
+/// };
+/// assert!(!output.analog_output);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct OutputProtection {
+    /// HDCP is established on every digital output
+    pub hdcp_enabled: bool,
+
+    /// Content is also sent to an analog output
+    pub analog_output: bool,
+}
+
+/// Validity of a session's license at the time it is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LicenseValidity {
+    /// License is valid for longer than the renewal window
+    ///
+    /// `remaining` is [`Duration::MAX`] for licenses that never expire.
+    Valid {
+        /// Time until the license expires
+        remaining: Duration,
+    },
+
+    /// License expires within the renewal window
+    Expiring {
+        /// Time until the license expires
+        remaining: Duration,
+    },
+
+    /// License has expired
+    Expired,
+}
+
+/// Status of a key in a session, as reported to EME applications
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyStatus {
+    /// Key can be used to decrypt content
+    Usable,
+
+    /// License providing the key has expired
+    Expired,
+
+    /// Key has been released with its session's license
+    Released,
+
+    /// Key cannot be used on the current output, because its license
+    /// requires HDCP or forbids analog outputs
+    OutputRestricted,
+
+    /// Key cannot be used because of an error in the CDM
+    InternalError,
+}
+
+/// Type of a message from the CDM to the application, as in EME
+/// `MediaKeyMessageType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageType {
+    /// Request for a new license, to send to the license server
+    LicenseRequest,
+
+    /// Request to renew an existing license
+    LicenseRenewal,
+
+    /// Record of a removed persistent license, for the license server to
+    /// acknowledge its release
+    LicenseRelease,
+}
+
+/// Event delivered asynchronously by the CDM, mirroring the EME `message`
+/// and `keystatuseschange` events
+///
+/// Received through
+/// [`ContentDecryptionModule::event_receiver`](crate::ContentDecryptionModule::event_receiver).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CdmEvent {
+    /// A message for the application to send to the license server
+    Message {
+        /// Session the message belongs to
+        session_id: DrmSessionId,
+        /// What the message is for
+        message_type: MessageType,
+        /// Message payload
+        payload: Vec<u8>,
+    },
+
+    /// The key statuses of a session have changed; query them with
+    /// [`ContentDecryptionModule::key_statuses`](crate::ContentDecryptionModule::key_statuses)
+    KeyStatusesChange {
+        /// Session whose key statuses changed
+        session_id: DrmSessionId,
+    },
+}
+
+/// Encryption layout of one protected sample
+///
+/// Mirrors the per-sample entries of a CENC `senc` box, with the scheme
+/// and pattern of the track's `tenc` box. With no subsamples the whole
+/// sample is encrypted. Under `cenc`, subsamples are decrypted as a single
+/// AES-CTR keystream across their encrypted ranges; under `cbcs`, each
+/// encrypted range is decrypted with AES-CBC from the IV, following the
+/// pattern.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_drm_support::{SampleEncryption, Subsample};
+///
+/// // 5 clear header bytes followed by 16 encrypted bytes
+/// let sample = SampleEncryption {
+///     iv: vec![0; 8],
+///     subsamples: vec![Subsample { clear_bytes: 5, encrypted_bytes: 16 }],
+///     ..Default::default()
+/// };
+/// assert_eq!(sample.subsamples[0].clear_bytes, 5);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleEncryption {
+    /// Initialization vector, 8 or 16 bytes
+    ///
+    /// 8-byte IVs are zero-padded on the right to form the counter block,
+    /// or the CBC IV.
+    pub iv: Vec<u8>,
+
+    /// Clear and encrypted byte ranges, in sample order
+    pub subsamples: Vec<Subsample>,
+
+    /// Protection scheme of the sample's track
+    #[serde(default)]
+    pub scheme: EncryptionScheme,
+
+    /// Blocks encrypted and skipped in each encrypted range, for `cbcs`
+    ///
+    /// `None`, or no encrypted blocks, encrypts every block.
+    #[serde(default)]
+    pub pattern: Option<EncryptionPattern>,
+}
+
+impl From<&EncryptionInfo> for SampleEncryption {
+    fn from(info: &EncryptionInfo) -> Self {
+        Self {
+            iv: info.iv.clone(),
+            subsamples: info.subsamples.clone(),
+            scheme: info.scheme,
+            pattern: info.pattern,
+        }
+    }
+}
+
 /// Internal session data
 ///
 /// Stores the state and metadata for a DRM session.
@@ -150,6 +399,22 @@ pub(crate) struct SessionData {
 
     /// License data received from server
     pub license_data: Option<Vec<u8>>,
+
+    /// IDs of the session's keys, from the license for ClearKey and from the
+    /// init data otherwise
+    pub key_ids: Vec<Vec<u8>>,
+
+    /// Expiry of the current license
+    pub expiry: LicenseExpiry,
+
+    /// Renewal request generated automatically, not yet taken by the
+    /// application
+    pub renewal_request: Option<Vec<u8>>,
+
+    /// Whether the expiry of the current license has been reported as a key
+    /// status change
+    #[serde(skip)]
+    pub expiry_reported: bool,
 }
 
 impl SessionData {
@@ -161,6 +426,10 @@ impl SessionData {
             session_type,
             init_data: None,
             license_data: None,
+            key_ids: Vec::new(),
+            expiry: LicenseExpiry::default(),
+            renewal_request: None,
+            expiry_reported: false,
         }
     }
 }
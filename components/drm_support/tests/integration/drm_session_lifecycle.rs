@@ -2,14 +2,18 @@
 //!
 //! Tests the complete flow of DRM session creation, license acquisition, and decryption.
 
-use cortenbrowser_drm_support::{ContentDecryptionModule, EMEInterface, MediaKeySystemConfiguration};
+use cortenbrowser_drm_support::{
+    CdmEvent, ContentDecryptionModule, DrmError, EMEInterface, InMemorySessionStore, KeyStatus,
+    MediaKeySystemConfiguration, MessageType, SampleEncryption, SessionType, Subsample,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn test_complete_drm_session_lifecycle() {
     /// Given: An EME interface and CDM
     /// When: We go through the complete DRM workflow
     /// Then: All steps should complete successfully
-
     // Step 1: Request media key system access via EME
     let eme = EMEInterface::new();
     let configs = vec![MediaKeySystemConfiguration::default()];
@@ -34,9 +38,9 @@ async fn test_complete_drm_session_lifecycle() {
     assert!(!session_id.as_str().is_empty());
 
     // Step 4: Generate license request
-    let init_data = b"pssh_box_data_from_media";
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
     let license_request = cdm
-        .generate_request(&session_id, init_data)
+        .generate_request(&session_id, "keyids", init_data)
         .await
         .expect("License request generation should succeed");
 
@@ -49,13 +53,180 @@ async fn test_complete_drm_session_lifecycle() {
         .await
         .expect("Session update should succeed");
 
-    // Step 6: Decrypt content (stub implementation for now)
+    // Step 6: Decrypt content with the key from the init data (stub
+    // implementation for now)
     let encrypted_data = b"encrypted_video_data";
-    let key_id = b"content_key_id";
+    let key_id = [0x10; 16];
 
     let _decrypted = cdm
-        .decrypt(encrypted_data, key_id)
-        .expect("Decryption should succeed or fail gracefully");
+        .decrypt(encrypted_data, &key_id)
+        .expect("Decryption should succeed for a licensed key");
+}
+
+#[tokio::test]
+async fn test_clearkey_session_lifecycle() {
+    /// Given: ClearKey access and a JSON Web Key license
+    /// When: We go through the DRM workflow and decrypt a known sample
+    /// Then: The decrypted sample should match the original plaintext
+    let eme = EMEInterface::new();
+    let access = eme
+        .request_media_key_system_access(
+            "org.w3.clearkey".to_string(),
+            vec![MediaKeySystemConfiguration::default()],
+        )
+        .await
+        .expect("Should get ClearKey access");
+
+    let cdm = ContentDecryptionModule::new(access.key_system().to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.generate_request(
+        &session_id,
+        "keyids",
+        br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#,
+    )
+    .await
+    .expect("License request generation should succeed");
+
+    let license =
+        br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+    cdm.update(&session_id, license)
+        .await
+        .expect("ClearKey license should be accepted");
+
+    // A 4-byte clear header, then "Corten ClearKey sample!" encrypted with
+    // AES-128-CTR and a zero IV
+    let mut data = vec![0, 0, 0, 23];
+    data.extend_from_slice(&[
+        0x85, 0xce, 0x49, 0x43, 0xe2, 0xe1, 0x7b, 0xc1, 0x03, 0x2a, 0xe0, 0x10, 0xea, 0xad, 0xa1,
+        0x59, 0x00, 0x27, 0x7e, 0xe5, 0xf9, 0xa5, 0x95,
+    ]);
+    let sample = SampleEncryption {
+        iv: vec![0; 8],
+        subsamples: vec![Subsample {
+            clear_bytes: 4,
+            encrypted_bytes: 23,
+        }],
+        ..Default::default()
+    };
+    let decrypted = cdm
+        .decrypt_sample(&data, &[0x10; 16], &sample)
+        .expect("Decryption should succeed");
+
+    assert_eq!(&decrypted[..4], &[0, 0, 0, 23]);
+    assert_eq!(&decrypted[4..], b"Corten ClearKey sample!");
+}
+
+#[tokio::test]
+async fn test_clearkey_persistent_session_offline_playback() {
+    /// Given: A persistent-license ClearKey session updated with a license
+    /// When: The CDM is recreated with the same session store and the
+    ///       session loaded
+    /// Then: Content should decrypt without contacting a license server
+    let store = Arc::new(InMemorySessionStore::new());
+    let session_id = {
+        let cdm = ContentDecryptionModule::with_session_store(
+            "org.w3.clearkey".to_string(),
+            store.clone(),
+        )
+        .expect("CDM creation should succeed");
+        let session_id = cdm
+            .create_session_with_type(SessionType::PersistentLicense)
+            .await
+            .expect("Session creation");
+        cdm.generate_request(
+            &session_id,
+            "keyids",
+            br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#,
+        )
+        .await
+        .expect("License request generation should succeed");
+
+        let license = br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+        cdm.update(&session_id, license)
+            .await
+            .expect("ClearKey license should be accepted");
+        session_id
+    };
+
+    let cdm = ContentDecryptionModule::with_session_store("org.w3.clearkey".to_string(), store)
+        .expect("CDM creation should succeed");
+    cdm.load_session(&session_id)
+        .await
+        .expect("Persistent session should load");
+
+    // "Corten ClearKey sample!" encrypted with AES-128-CTR and a zero IV
+    let encrypted = [
+        0x85, 0xce, 0x49, 0x43, 0xe2, 0xe1, 0x7b, 0xc1, 0x03, 0x2a, 0xe0, 0x10, 0xea, 0xad, 0xa1,
+        0x59, 0x00, 0x27, 0x7e, 0xe5, 0xf9, 0xa5, 0x95,
+    ];
+    let decrypted = cdm
+        .decrypt(&encrypted, &[0x10; 16])
+        .expect("Decryption should succeed");
+
+    assert_eq!(decrypted, b"Corten ClearKey sample!");
+}
+
+#[tokio::test]
+async fn test_clearkey_persistent_session_close_load_remove() {
+    /// Given: A persistent-license ClearKey session updated with a license
+    /// When: We close it, load it again and then remove it
+    /// Then: Closing should keep the stored license, and removing it should
+    ///       release it for good with a release message
+    let store = Arc::new(InMemorySessionStore::new());
+    let cdm =
+        ContentDecryptionModule::with_session_store("org.w3.clearkey".to_string(), store.clone())
+            .expect("CDM creation should succeed");
+    let mut events = cdm.event_receiver();
+    let session_id = cdm
+        .create_session_with_type(SessionType::PersistentLicense)
+        .await
+        .expect("Session creation");
+    let license =
+        br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+    cdm.update(&session_id, license)
+        .await
+        .expect("ClearKey license should be accepted");
+
+    cdm.close_session(&session_id).await.expect("Session close");
+    assert!(matches!(
+        cdm.decrypt(&[0; 16], &[0x10; 16]),
+        Err(DrmError::DecryptionFailed(_))
+    ));
+    assert!(matches!(
+        cdm.update(&session_id, license).await,
+        Err(DrmError::SessionClosed(_))
+    ));
+
+    let cdm = ContentDecryptionModule::with_session_store("org.w3.clearkey".to_string(), store)
+        .expect("CDM creation should succeed");
+    cdm.load_session(&session_id)
+        .await
+        .expect("Closed persistent session should still load");
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::Usable)])
+    );
+
+    let mut release_events = cdm.event_receiver();
+    cdm.remove_session(&session_id)
+        .await
+        .expect("Session removal");
+    assert!(matches!(
+        release_events.try_recv(),
+        Ok(CdmEvent::Message {
+            message_type: MessageType::LicenseRelease,
+            ..
+        })
+    ));
+    assert!(matches!(
+        cdm.load_session(&session_id).await,
+        Err(DrmError::SessionNotFound(_))
+    ));
+
+    // The first CDM saw the license arrive and the keys released on close
+    let statuses_changes = std::iter::from_fn(|| events.try_recv().ok()).count();
+    assert_eq!(statuses_changes, 2);
 }
 
 #[tokio::test]
@@ -63,7 +234,6 @@ async fn test_multiple_concurrent_sessions() {
     /// Given: A CDM instance
     /// When: We create multiple concurrent sessions
     /// Then: All sessions should be independent
-
     let cdm = ContentDecryptionModule::new("com.example.test".to_string())
         .expect("CDM creation should succeed");
 
@@ -78,11 +248,20 @@ async fn test_multiple_concurrent_sessions() {
     assert_ne!(session1, session3);
 
     // Generate requests for each session
-    let init_data = b"initialization_data";
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
 
-    let req1 = cdm.generate_request(&session1, init_data).await.expect("Request 1");
-    let req2 = cdm.generate_request(&session2, init_data).await.expect("Request 2");
-    let req3 = cdm.generate_request(&session3, init_data).await.expect("Request 3");
+    let req1 = cdm
+        .generate_request(&session1, "keyids", init_data)
+        .await
+        .expect("Request 1");
+    let req2 = cdm
+        .generate_request(&session2, "keyids", init_data)
+        .await
+        .expect("Request 2");
+    let req3 = cdm
+        .generate_request(&session3, "keyids", init_data)
+        .await
+        .expect("Request 3");
 
     // All requests should succeed
     assert!(!req1.is_empty());
@@ -95,7 +274,6 @@ async fn test_session_isolation() {
     /// Given: Multiple DRM sessions
     /// When: We update one session
     /// Then: Other sessions should not be affected
-
     let cdm = ContentDecryptionModule::new("com.example.test".to_string())
         .expect("CDM creation should succeed");
 
@@ -103,16 +281,24 @@ async fn test_session_isolation() {
     let session2 = cdm.create_session().await.expect("Session 2");
 
     // Generate requests for both
-    let init_data = b"init_data";
-    cdm.generate_request(&session1, init_data).await.expect("Request 1");
-    cdm.generate_request(&session2, init_data).await.expect("Request 2");
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    cdm.generate_request(&session1, "keyids", init_data)
+        .await
+        .expect("Request 1");
+    cdm.generate_request(&session2, "keyids", init_data)
+        .await
+        .expect("Request 2");
 
     // Update only session1
     let license = b"license_data";
-    cdm.update(&session1, license).await.expect("Update session 1");
+    cdm.update(&session1, license)
+        .await
+        .expect("Update session 1");
 
     // Session2 should still be valid and updatable
-    cdm.update(&session2, license).await.expect("Update session 2 should still work");
+    cdm.update(&session2, license)
+        .await
+        .expect("Update session 2 should still work");
 }
 
 #[tokio::test]
@@ -120,14 +306,13 @@ async fn test_error_handling_in_lifecycle() {
     /// Given: A CDM instance
     /// When: We perform operations with invalid data
     /// Then: Errors should be handled gracefully
-
     let cdm = ContentDecryptionModule::new("com.example.test".to_string())
         .expect("CDM creation should succeed");
 
     let session_id = cdm.create_session().await.expect("Session creation");
 
     // Try to generate request with empty init data
-    let result = cdm.generate_request(&session_id, &[]).await;
+    let result = cdm.generate_request(&session_id, "keyids", &[]).await;
     // Should either succeed (stub allows it) or fail gracefully
     assert!(result.is_ok() || result.is_err());
 
@@ -2,4 +2,6 @@
 
 mod test_cdm;
 mod test_eme;
+mod test_pssh;
+mod test_session_store;
 mod test_types;
@@ -2,7 +2,13 @@
 //!
 //! Tests for CDM session management, license requests, and decryption.
 
-use cortenbrowser_drm_support::{ContentDecryptionModule, DrmError, DrmSessionId};
+use cortenbrowser_drm_support::{
+    CdmEvent, ContentDecryptionModule, DrmError, DrmSessionId, EncryptionPattern, EncryptionScheme,
+    KeyStatus, LicenseExpiry, LicenseValidity, MessageType, OutputProtection, SampleEncryption,
+    SessionType, Subsample,
+};
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime};
 
 #[test]
 fn test_cdm_creation_with_supported_key_system() {
@@ -11,7 +17,10 @@ fn test_cdm_creation_with_supported_key_system() {
     /// Then: Creation should succeed
     let result = ContentDecryptionModule::new("com.example.test".to_string());
 
-    assert!(result.is_ok(), "CDM creation should succeed for test key system");
+    assert!(
+        result.is_ok(),
+        "CDM creation should succeed for test key system"
+    );
 }
 
 #[test]
@@ -21,9 +30,12 @@ fn test_cdm_creation_with_unsupported_key_system() {
     /// Then: Creation should fail with UnsupportedKeySystem error
     let result = ContentDecryptionModule::new("".to_string());
 
-    assert!(result.is_err(), "CDM creation should fail for empty key system");
+    assert!(
+        result.is_err(),
+        "CDM creation should fail for empty key system"
+    );
     match result.unwrap_err() {
-        DrmError::UnsupportedKeySystem(_) => {}, // Expected
+        DrmError::UnsupportedKeySystem(_) => {} // Expected
         other => panic!("Expected UnsupportedKeySystem, got {:?}", other),
     }
 }
@@ -40,7 +52,10 @@ async fn test_cdm_create_session() {
 
     assert!(result.is_ok(), "Session creation should succeed");
     let session_id = result.unwrap();
-    assert!(!session_id.as_str().is_empty(), "Session ID should not be empty");
+    assert!(
+        !session_id.as_str().is_empty(),
+        "Session ID should not be empty"
+    );
 }
 
 #[tokio::test]
@@ -51,8 +66,14 @@ async fn test_cdm_create_multiple_sessions() {
     let cdm = ContentDecryptionModule::new("com.example.test".to_string())
         .expect("CDM creation should succeed");
 
-    let session1 = cdm.create_session().await.expect("First session should be created");
-    let session2 = cdm.create_session().await.expect("Second session should be created");
+    let session1 = cdm
+        .create_session()
+        .await
+        .expect("First session should be created");
+    let session2 = cdm
+        .create_session()
+        .await
+        .expect("Second session should be created");
 
     assert_ne!(session1, session2, "Session IDs should be unique");
 }
@@ -64,10 +85,13 @@ async fn test_cdm_generate_request() {
     /// Then: A request payload should be returned
     let cdm = ContentDecryptionModule::new("com.example.test".to_string())
         .expect("CDM creation should succeed");
-    let session_id = cdm.create_session().await.expect("Session creation should succeed");
+    let session_id = cdm
+        .create_session()
+        .await
+        .expect("Session creation should succeed");
 
-    let init_data = b"initialization_data";
-    let result = cdm.generate_request(&session_id, init_data).await;
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    let result = cdm.generate_request(&session_id, "keyids", init_data).await;
 
     assert!(result.is_ok(), "License request generation should succeed");
     let request = result.unwrap();
@@ -83,12 +107,17 @@ async fn test_cdm_generate_request_invalid_session() {
         .expect("CDM creation should succeed");
     let invalid_session = DrmSessionId::from("non-existent-session".to_string());
 
-    let init_data = b"initialization_data";
-    let result = cdm.generate_request(&invalid_session, init_data).await;
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    let result = cdm
+        .generate_request(&invalid_session, "keyids", init_data)
+        .await;
 
-    assert!(result.is_err(), "Request generation should fail for invalid session");
+    assert!(
+        result.is_err(),
+        "Request generation should fail for invalid session"
+    );
     match result.unwrap_err() {
-        DrmError::SessionNotFound(_) => {}, // Expected
+        DrmError::SessionNotFound(_) => {} // Expected
         other => panic!("Expected SessionNotFound, got {:?}", other),
     }
 }
@@ -100,10 +129,14 @@ async fn test_cdm_update_session() {
     /// Then: The update should succeed
     let cdm = ContentDecryptionModule::new("com.example.test".to_string())
         .expect("CDM creation should succeed");
-    let session_id = cdm.create_session().await.expect("Session creation should succeed");
-
-    let init_data = b"initialization_data";
-    cdm.generate_request(&session_id, init_data).await
+    let session_id = cdm
+        .create_session()
+        .await
+        .expect("Session creation should succeed");
+
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    cdm.generate_request(&session_id, "keyids", init_data)
+        .await
         .expect("Request generation should succeed");
 
     let license_response = b"license_response_data";
@@ -126,7 +159,7 @@ async fn test_cdm_update_invalid_session() {
 
     assert!(result.is_err(), "Update should fail for invalid session");
     match result.unwrap_err() {
-        DrmError::SessionNotFound(_) => {}, // Expected
+        DrmError::SessionNotFound(_) => {} // Expected
         other => panic!("Expected SessionNotFound, got {:?}", other),
     }
 }
@@ -165,3 +198,793 @@ fn test_cdm_decrypt_with_invalid_key() {
     // Should either succeed (stub) or fail gracefully
     assert!(result.is_ok() || result.is_err());
 }
+
+const CLEARKEY_LICENSE: &[u8] =
+    br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+
+#[tokio::test]
+async fn test_cdm_clearkey_decrypt() {
+    /// Given: A ClearKey CDM updated with a JSON Web Key license
+    /// When: We decrypt AES-128-CTR ciphertext with the licensed key ID
+    /// Then: Should return the known plaintext
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("ClearKey license should be accepted");
+
+    let encrypted = [
+        0x85, 0xce, 0x49, 0x43, 0xe2, 0xe1, 0x7b, 0xc1, 0x03, 0x2a, 0xe0, 0x10, 0xea, 0xad, 0xa1,
+        0x59, 0x00, 0x27, 0x7e, 0xe5, 0xf9, 0xa5, 0x95,
+    ];
+
+    let decrypted = cdm
+        .decrypt(&encrypted, &[0x10; 16])
+        .expect("Decryption should succeed");
+
+    assert_eq!(decrypted, b"Corten ClearKey sample!");
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_decrypt_unknown_key_id() {
+    /// Given: A ClearKey CDM updated with a license
+    /// When: We decrypt with a key ID the license did not contain
+    /// Then: Should return DecryptionFailed
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    let result = cdm.decrypt(b"encrypted_content", &[0x20; 16]);
+
+    assert!(matches!(result, Err(DrmError::DecryptionFailed(_))));
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_rejects_invalid_license() {
+    /// Given: A ClearKey CDM session
+    /// When: We update it with a license that is not a JSON Web Key set
+    /// Then: Should return LicenseRequestFailed
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    let result = cdm.update(&session_id, b"license_from_server").await;
+
+    assert!(matches!(result, Err(DrmError::LicenseRequestFailed(_))));
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_license_server_error() {
+    /// Given: A ClearKey CDM session
+    /// When: We update it with the error document of a license server
+    /// Then: Should return LicenseServerError with the server's status
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    let result = cdm.update(&session_id, br#"{"status":403}"#).await;
+
+    assert!(matches!(
+        result,
+        Err(DrmError::LicenseServerError { status: 403 })
+    ));
+}
+
+const CLEARKEY_HDCP_LICENSE: &[u8] = br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}],"hdcp_required":true,"allow_analog_output":false}"#;
+
+#[tokio::test]
+async fn test_cdm_clearkey_decrypt_hdcp_required() {
+    /// Given: A ClearKey license requiring HDCP
+    /// When: We decrypt without and then with HDCP on the output
+    /// Then: Should return HdcpRequired, then decrypt
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_HDCP_LICENSE)
+        .await
+        .expect("Session update");
+    let encrypted = [0x85, 0xce, 0x49, 0x43];
+
+    let result = cdm.decrypt(&encrypted, &[0x10; 16]);
+    assert!(matches!(result, Err(DrmError::HdcpRequired)));
+
+    cdm.set_output_protection(OutputProtection {
+        hdcp_enabled: true,
+        analog_output: false,
+    });
+    let decrypted = cdm.decrypt(&encrypted, &[0x10; 16]);
+    assert_eq!(decrypted.expect("Decryption should succeed"), b"Cort");
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_decrypt_output_not_allowed() {
+    /// Given: A ClearKey license forbidding analog outputs
+    /// When: We decrypt while content is also sent to an analog output
+    /// Then: Should return OutputNotAllowed
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_HDCP_LICENSE)
+        .await
+        .expect("Session update");
+    cdm.set_output_protection(OutputProtection {
+        hdcp_enabled: true,
+        analog_output: true,
+    });
+
+    let result = cdm.decrypt(&[0x85, 0xce, 0x49, 0x43], &[0x10; 16]);
+
+    assert!(matches!(result, Err(DrmError::OutputNotAllowed)));
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_decrypt_sample_subsamples() {
+    /// Given: A ClearKey CDM updated with a license
+    /// When: We decrypt a sample with an 8-byte IV and clear/encrypted subsamples
+    /// Then: Clear ranges should be kept and encrypted ranges decrypted
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    // "Hello, ClearKey subsamples!" encrypted with IV 0102030405060708, split
+    // after 10 bytes by a 3-byte clear range
+    let mut data = vec![0xaa; 4];
+    data.extend_from_slice(&[0x50, 0xdf, 0x05, 0xd7, 0x29, 0x4d, 0xde, 0xa6, 0xcb, 0xa9]);
+    data.extend_from_slice(&[0xbb; 3]);
+    data.extend_from_slice(&[
+        0xff, 0xb3, 0xec, 0x54, 0x9b, 0x58, 0xbc, 0xe4, 0xcf, 0xed, 0xbc, 0x94, 0xbe, 0x08, 0x3e,
+        0x22, 0x7f,
+    ]);
+    let sample = SampleEncryption {
+        iv: vec![1, 2, 3, 4, 5, 6, 7, 8],
+        subsamples: vec![
+            Subsample {
+                clear_bytes: 4,
+                encrypted_bytes: 10,
+            },
+            Subsample {
+                clear_bytes: 3,
+                encrypted_bytes: 17,
+            },
+        ],
+        ..Default::default()
+    };
+
+    let decrypted = cdm
+        .decrypt_sample(&data, &[0x10; 16], &sample)
+        .expect("Decryption should succeed");
+
+    let mut expected = vec![0xaa; 4];
+    expected.extend_from_slice(b"Hello, Cle");
+    expected.extend_from_slice(&[0xbb; 3]);
+    expected.extend_from_slice(b"arKey subsamples!");
+    assert_eq!(decrypted, expected);
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_decrypt_cbcs_sample() {
+    /// Given: A ClearKey CDM updated with a license
+    /// When: We decrypt a cbcs sample with a clear header
+    /// Then: Should decrypt the encrypted range with AES-128-CBC
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    // "Corten cbcs sample!!!!!!!!!!!!!!" encrypted with AES-128-CBC and a
+    // zero IV
+    let mut data = vec![0, 0, 0, 32];
+    data.extend_from_slice(&[
+        0xed, 0x7c, 0x0f, 0xf0, 0xad, 0xf1, 0x56, 0xa3, 0x21, 0x54, 0x89, 0x22, 0xf6, 0x24, 0x20,
+        0xb6, 0x05, 0x41, 0x16, 0x32, 0x41, 0xd6, 0x9e, 0xc1, 0x04, 0x9d, 0xbb, 0xb7, 0x93, 0x08,
+        0x52, 0x25,
+    ]);
+    let sample = SampleEncryption {
+        iv: vec![0; 16],
+        subsamples: vec![Subsample {
+            clear_bytes: 4,
+            encrypted_bytes: 32,
+        }],
+        scheme: EncryptionScheme::Cbcs,
+        pattern: Some(EncryptionPattern {
+            crypt_byte_block: 1,
+            skip_byte_block: 0,
+        }),
+    };
+
+    let decrypted = cdm
+        .decrypt_sample(&data, &[0x10; 16], &sample)
+        .expect("Decryption should succeed");
+
+    assert_eq!(&decrypted[..4], &[0, 0, 0, 32]);
+    assert_eq!(&decrypted[4..], b"Corten cbcs sample!!!!!!!!!!!!!!");
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_decrypt_sample_invalid_layout() {
+    /// Given: A ClearKey CDM updated with a license
+    /// When: The subsamples do not cover the sample exactly
+    /// Then: Should return DecryptionFailed
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    let sample = SampleEncryption {
+        iv: vec![0; 16],
+        subsamples: vec![Subsample {
+            clear_bytes: 2,
+            encrypted_bytes: 4,
+        }],
+        ..Default::default()
+    };
+    let result = cdm.decrypt_sample(&[0; 10], &[0x10; 16], &sample);
+
+    assert!(matches!(result, Err(DrmError::DecryptionFailed(_))));
+}
+
+/// ClearKey license with the key of `CLEARKEY_LICENSE`, expiring at
+/// `expiration` milliseconds since the Unix epoch
+fn clearkey_license_expiring(expiration: u128) -> Vec<u8> {
+    format!(
+        r#"{{"keys":[{{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}}],"expiration":{},"renewal_url":"https://license.example.com/renew"}}"#,
+        expiration
+    )
+    .into_bytes()
+}
+
+fn unix_millis(time: SystemTime) -> u128 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .expect("Time after the epoch")
+        .as_millis()
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_license_expired() {
+    /// Given: A ClearKey session updated with a license that expired an hour ago
+    /// When: We check the license
+    /// Then: It should be Expired and no renewal request generated
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    let expired = SystemTime::now() - Duration::from_secs(3600);
+    cdm.update(
+        &session_id,
+        &clearkey_license_expiring(unix_millis(expired)),
+    )
+    .await
+    .expect("Session update");
+
+    let validity = cdm.check_license_valid(&session_id).await;
+
+    assert_eq!(validity.unwrap(), LicenseValidity::Expired);
+    assert_eq!(cdm.take_renewal_request(&session_id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_license_expiring_renews() {
+    /// Given: A ClearKey license expiring within the renewal window
+    /// When: We check the license
+    /// Then: It should be Expiring and a renewal request generated once
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    let expiring = SystemTime::now() + Duration::from_secs(30);
+    cdm.update(
+        &session_id,
+        &clearkey_license_expiring(unix_millis(expiring)),
+    )
+    .await
+    .expect("Session update");
+
+    let validity = cdm.check_license_valid(&session_id).await.unwrap();
+    assert!(matches!(
+        validity,
+        LicenseValidity::Expiring { remaining } if remaining <= Duration::from_secs(30)
+    ));
+
+    let request = cdm
+        .take_renewal_request(&session_id)
+        .await
+        .unwrap()
+        .expect("Renewal request should be generated");
+    let request: serde_json::Value = serde_json::from_slice(&request).expect("JSON request");
+    assert_eq!(request["type"], "license-renewal");
+    assert_eq!(
+        request["renewal_server_url"],
+        "https://license.example.com/renew"
+    );
+
+    // Already renewing, so checking again generates no second request
+    cdm.check_license_valid(&session_id).await.unwrap();
+    assert_eq!(cdm.take_renewal_request(&session_id).await.unwrap(), None);
+
+    // The renewed license makes the session valid again
+    let renewed = SystemTime::now() + Duration::from_secs(3600);
+    cdm.update(
+        &session_id,
+        &clearkey_license_expiring(unix_millis(renewed)),
+    )
+    .await
+    .expect("Session update");
+    assert!(matches!(
+        cdm.check_license_valid(&session_id).await.unwrap(),
+        LicenseValidity::Valid { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_cdm_license_expiry_from_cdm() {
+    /// Given: A session whose expiry is provided by the CDM, without a
+    ///        renewal server
+    /// When: We check the license before and after setting the expiry
+    /// Then: It should be valid indefinitely, then Expiring without renewing
+    let cdm = ContentDecryptionModule::new("com.widevine.alpha".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, b"license_from_server")
+        .await
+        .expect("Session update");
+
+    assert_eq!(
+        cdm.check_license_valid(&session_id).await.unwrap(),
+        LicenseValidity::Valid {
+            remaining: Duration::MAX
+        }
+    );
+
+    cdm.set_license_expiry(
+        &session_id,
+        LicenseExpiry {
+            expires_at: Some(SystemTime::now() + Duration::from_secs(10)),
+            renewal_server_url: None,
+        },
+    )
+    .await
+    .expect("Set expiry");
+
+    assert!(matches!(
+        cdm.check_license_valid(&session_id).await.unwrap(),
+        LicenseValidity::Expiring { .. }
+    ));
+    assert_eq!(cdm.take_renewal_request(&session_id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_cdm_renewal_request_requires_license() {
+    /// Given: A session without a license
+    /// When: We check it or request a renewal explicitly
+    /// Then: Both should fail until the session has a license
+    let cdm = ContentDecryptionModule::new("com.widevine.alpha".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    assert!(matches!(
+        cdm.check_license_valid(&session_id).await,
+        Err(DrmError::LicenseRequestFailed(_))
+    ));
+    assert!(matches!(
+        cdm.generate_renewal_request(&session_id).await,
+        Err(DrmError::LicenseRequestFailed(_))
+    ));
+
+    cdm.update(&session_id, b"license_from_server")
+        .await
+        .expect("Session update");
+    let request = cdm.generate_renewal_request(&session_id).await;
+    assert!(request.is_ok());
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_expired_license_key_status() {
+    /// Given: A ClearKey session updated with an already expired license
+    /// When: We query its key statuses and decrypt with its key
+    /// Then: The key should be Expired and decryption should fail
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    let expired = SystemTime::now() - Duration::from_secs(3600);
+    cdm.update(
+        &session_id,
+        &clearkey_license_expiring(unix_millis(expired)),
+    )
+    .await
+    .expect("Session update");
+
+    let statuses = cdm.key_statuses(&session_id).await.unwrap();
+    let result = cdm.decrypt(&[0; 16], &[0x10; 16]);
+
+    assert_eq!(
+        statuses,
+        HashMap::from([(vec![0x10; 16], KeyStatus::Expired)])
+    );
+    match result {
+        Err(DrmError::DecryptionFailed(reason)) => assert!(reason.contains("expired")),
+        other => panic!("Expected DecryptionFailed, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_cdm_key_statuses_lifecycle() {
+    /// Given: A ClearKey session
+    /// When: We query its key statuses before the license, after it and
+    ///       after removing the session
+    /// Then: There should be none, then Usable, then Released keys
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.generate_request(
+        &session_id,
+        "keyids",
+        br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#,
+    )
+    .await
+    .expect("License request generation");
+    assert_eq!(cdm.key_statuses(&session_id).await.unwrap(), HashMap::new());
+
+    let tomorrow = SystemTime::now() + Duration::from_secs(86400);
+    cdm.update(
+        &session_id,
+        &clearkey_license_expiring(unix_millis(tomorrow)),
+    )
+    .await
+    .expect("Session update");
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::Usable)])
+    );
+    assert!(cdm.decrypt(&[0; 16], &[0x10; 16]).is_ok());
+
+    cdm.remove_session(&session_id)
+        .await
+        .expect("Session removal");
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::Released)])
+    );
+    assert!(matches!(
+        cdm.key_statuses(&DrmSessionId::new()).await,
+        Err(DrmError::SessionNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_cdm_expiry_from_cdm_blocks_decryption() {
+    /// Given: A ClearKey session with a license that never expires
+    /// When: The platform sets an expiry in the past
+    /// Then: The key should be Expired and decryption should fail
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    cdm.set_license_expiry(
+        &session_id,
+        LicenseExpiry {
+            expires_at: Some(SystemTime::now() - Duration::from_secs(1)),
+            renewal_server_url: None,
+        },
+    )
+    .await
+    .expect("Set expiry");
+
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::Expired)])
+    );
+    assert!(matches!(
+        cdm.decrypt(&[0; 16], &[0x10; 16]),
+        Err(DrmError::DecryptionFailed(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_cdm_generate_request_emits_message_event() {
+    /// Given: A CDM with an event receiver and a new session
+    /// When: We generate a license request
+    /// Then: A LicenseRequest message carrying the request should be received
+    let cdm = ContentDecryptionModule::new("com.example.test".to_string())
+        .expect("CDM creation should succeed");
+    let mut events = cdm.event_receiver();
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    let request = cdm
+        .generate_request(&session_id, "keyids", init_data)
+        .await
+        .expect("License request generation");
+
+    let event = tokio::time::timeout(Duration::from_secs(1), events.recv())
+        .await
+        .expect("Event should arrive")
+        .expect("Channel should be open");
+    assert_eq!(
+        event,
+        CdmEvent::Message {
+            session_id,
+            message_type: MessageType::LicenseRequest,
+            payload: request,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_cdm_update_emits_key_statuses_change() {
+    /// Given: A ClearKey CDM with an event receiver
+    /// When: A session is updated with a license and then removed
+    /// Then: A KeyStatusesChange event should be received for each
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let mut events = cdm.event_receiver();
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+    cdm.remove_session(&session_id)
+        .await
+        .expect("Session removal");
+
+    for _ in 0..2 {
+        assert_eq!(
+            events.try_recv().expect("Event should be queued"),
+            CdmEvent::KeyStatusesChange {
+                session_id: session_id.clone(),
+            }
+        );
+    }
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_cdm_close_session_rejects_further_operations() {
+    /// Given: A ClearKey session with a license
+    /// When: We close it
+    /// Then: Its keys should be released and further operations should fail
+    ///       with SessionClosed
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let mut events = cdm.event_receiver();
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    cdm.close_session(&session_id).await.expect("Session close");
+
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::Released)])
+    );
+    assert!(matches!(
+        cdm.decrypt(&[0; 16], &[0x10; 16]),
+        Err(DrmError::DecryptionFailed(_))
+    ));
+    let init_data = br#"{"kids":["EBAQEBAQEBAQEBAQEBAQEA"]}"#;
+    assert!(matches!(
+        cdm.generate_request(&session_id, "keyids", init_data).await,
+        Err(DrmError::SessionClosed(_))
+    ));
+    assert!(matches!(
+        cdm.update(&session_id, CLEARKEY_LICENSE).await,
+        Err(DrmError::SessionClosed(_))
+    ));
+    assert!(matches!(
+        cdm.remove_session(&session_id).await,
+        Err(DrmError::SessionClosed(_))
+    ));
+    assert!(matches!(
+        cdm.generate_renewal_request(&session_id).await,
+        Err(DrmError::SessionClosed(_))
+    ));
+    assert!(matches!(
+        cdm.check_license_valid(&session_id).await,
+        Err(DrmError::SessionClosed(_))
+    ));
+
+    // Closing again does nothing
+    cdm.close_session(&session_id).await.expect("Second close");
+    assert!(matches!(
+        cdm.close_session(&DrmSessionId::new()).await,
+        Err(DrmError::SessionNotFound(_))
+    ));
+    for _ in 0..2 {
+        assert!(matches!(
+            events.try_recv(),
+            Ok(CdmEvent::KeyStatusesChange { .. })
+        ));
+    }
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_cdm_remove_persistent_session_emits_release_message() {
+    /// Given: A temporary and a persistent-license ClearKey session
+    /// When: We remove both
+    /// Then: Only the persistent one should produce a LicenseRelease message
+    ///       listing its key IDs
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let temporary = cdm.create_session().await.expect("Session creation");
+    let persistent = cdm
+        .create_session_with_type(SessionType::PersistentLicense)
+        .await
+        .expect("Session creation");
+    for session_id in [&temporary, &persistent] {
+        cdm.update(session_id, CLEARKEY_LICENSE)
+            .await
+            .expect("Session update");
+    }
+    let mut events = cdm.event_receiver();
+
+    cdm.remove_session(&temporary)
+        .await
+        .expect("Session removal");
+    assert_eq!(
+        events.try_recv().expect("Event should be queued"),
+        CdmEvent::KeyStatusesChange {
+            session_id: temporary,
+        }
+    );
+    assert!(events.try_recv().is_err());
+
+    cdm.remove_session(&persistent)
+        .await
+        .expect("Session removal");
+    match events.try_recv().expect("Event should be queued") {
+        CdmEvent::Message {
+            session_id,
+            message_type,
+            payload,
+        } => {
+            assert_eq!(session_id, persistent);
+            assert_eq!(message_type, MessageType::LicenseRelease);
+            let message: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+            assert_eq!(message["type"], "license-release");
+            assert_eq!(message["kids"][0], "EBAQEBAQEBAQEBAQEBAQEA");
+        }
+        other => panic!("Expected a release message, got {:?}", other),
+    }
+    assert_eq!(
+        events.try_recv().expect("Event should be queued"),
+        CdmEvent::KeyStatusesChange {
+            session_id: persistent,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_cdm_expiration_and_expired_key_status_event() {
+    /// Given: A ClearKey session whose license expires shortly
+    /// When: We read its expiration and check it after the expiry
+    /// Then: The expiration should come from the license, and a single
+    ///       KeyStatusesChange should announce the Expired key
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    assert_eq!(cdm.expiration(&session_id).await.unwrap(), None);
+
+    let expires_at =
+        SystemTime::UNIX_EPOCH + Duration::from_millis(unix_millis(SystemTime::now()) as u64 + 50);
+    cdm.update(
+        &session_id,
+        &clearkey_license_expiring(unix_millis(expires_at)),
+    )
+    .await
+    .expect("Session update");
+    let expiration = cdm
+        .expiration(&session_id)
+        .await
+        .unwrap()
+        .expect("License should expire");
+    // Within the precision of the license's floating point milliseconds
+    assert!(unix_millis(expiration).abs_diff(unix_millis(expires_at)) <= 1);
+    let mut events = cdm.event_receiver();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    for _ in 0..2 {
+        assert_eq!(
+            cdm.check_license_valid(&session_id).await.unwrap(),
+            LicenseValidity::Expired
+        );
+    }
+
+    assert_eq!(
+        events.try_recv().expect("Event should be queued"),
+        CdmEvent::KeyStatusesChange {
+            session_id: session_id.clone(),
+        }
+    );
+    assert!(events.try_recv().is_err());
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::Expired)])
+    );
+}
+
+#[tokio::test]
+async fn test_cdm_output_restricted_key_status() {
+    /// Given: A ClearKey session whose license requires HDCP
+    /// When: The output protection changes
+    /// Then: The key should be OutputRestricted without HDCP and Usable with
+    ///       it, with a KeyStatusesChange on each change
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_HDCP_LICENSE)
+        .await
+        .expect("Session update");
+    let mut events = cdm.event_receiver();
+
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::OutputRestricted)])
+    );
+    assert!(!cdm.is_key_usable(&[0x10; 16]));
+
+    cdm.set_output_protection(OutputProtection {
+        hdcp_enabled: true,
+        analog_output: false,
+    });
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::Usable)])
+    );
+    assert!(cdm.is_key_usable(&[0x10; 16]));
+    assert_eq!(
+        events.try_recv().expect("Event should be queued"),
+        CdmEvent::KeyStatusesChange {
+            session_id: session_id.clone(),
+        }
+    );
+
+    // Enabling HDCP again changes nothing
+    cdm.set_output_protection(OutputProtection {
+        hdcp_enabled: true,
+        analog_output: false,
+    });
+    assert!(events.try_recv().is_err());
+
+    cdm.set_output_protection(OutputProtection {
+        hdcp_enabled: true,
+        analog_output: true,
+    });
+    assert_eq!(
+        cdm.key_statuses(&session_id).await.unwrap(),
+        HashMap::from([(vec![0x10; 16], KeyStatus::OutputRestricted)])
+    );
+    assert!(events.try_recv().is_ok());
+}
+
+#[tokio::test]
+async fn test_cdm_clearkey_key_usable_once_licensed() {
+    /// Given: A ClearKey session
+    /// When: Its license is installed and the session closed
+    /// Then: The key should only be usable while the license is
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    assert!(!cdm.is_key_usable(&[0x10; 16]));
+
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+    assert!(cdm.is_key_usable(&[0x10; 16]));
+    assert!(!cdm.is_key_usable(&[0x20; 16]));
+
+    cdm.close_session(&session_id).await.expect("Session close");
+    assert!(!cdm.is_key_usable(&[0x10; 16]));
+}
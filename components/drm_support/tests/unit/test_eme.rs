@@ -4,8 +4,19 @@
 
 use cortenbrowser_drm_support::{
     DrmError, EMEInterface, MediaKeySystemAccess, MediaKeySystemConfiguration,
+    MediaKeySystemMediaCapability, MediaKeysRequirement,
 };
 
+fn video_config(robustness: &str) -> MediaKeySystemConfiguration {
+    MediaKeySystemConfiguration {
+        video_capabilities: vec![MediaKeySystemMediaCapability {
+            content_type: "video/mp4; codecs=\"avc1.42E01E\"".to_string(),
+            robustness: robustness.to_string(),
+        }],
+        ..Default::default()
+    }
+}
+
 #[tokio::test]
 async fn test_eme_request_media_key_system_access() {
     /// Given: An EME interface and supported key system
@@ -18,7 +29,10 @@ async fn test_eme_request_media_key_system_access() {
         .request_media_key_system_access("com.example.test".to_string(), configs)
         .await;
 
-    assert!(result.is_ok(), "Request should succeed for supported key system");
+    assert!(
+        result.is_ok(),
+        "Request should succeed for supported key system"
+    );
 }
 
 #[tokio::test]
@@ -91,3 +105,222 @@ fn test_media_key_system_access_creation() {
 
     assert_eq!(access.key_system(), "com.example.test");
 }
+
+#[tokio::test]
+async fn test_eme_skips_unsatisfiable_robustness() {
+    /// Given: A hardware-secure config followed by a software-secure one
+    /// When: We request Widevine access
+    /// Then: The first config should be skipped for the second
+    let eme = EMEInterface::new();
+    let configs = vec![
+        video_config("HW_SECURE_ALL"),
+        video_config("SW_SECURE_CRYPTO"),
+    ];
+
+    let access = eme
+        .request_media_key_system_access("com.widevine.alpha".to_string(), configs)
+        .await
+        .expect("Software-secure config should be selected");
+
+    assert_eq!(access.configuration(), &video_config("SW_SECURE_CRYPTO"));
+}
+
+#[tokio::test]
+async fn test_eme_empty_robustness_always_satisfiable() {
+    /// Given: A key system without robustness levels
+    /// When: We request access with a high robustness then an empty one
+    /// Then: The config with empty robustness should be selected
+    let eme = EMEInterface::new();
+    let configs = vec![video_config("SW_SECURE_DECODE"), video_config("")];
+
+    let access = eme
+        .request_media_key_system_access("org.w3.clearkey".to_string(), configs)
+        .await
+        .expect("Config without robustness should be selected");
+
+    assert_eq!(access.configuration(), &video_config(""));
+}
+
+#[tokio::test]
+async fn test_eme_rejects_config_with_any_unsatisfiable_capability() {
+    /// Given: A config where only the audio capability is satisfiable
+    /// When: We request Widevine access
+    /// Then: Should return UnsupportedKeySystem, as no config matches
+    let eme = EMEInterface::new();
+    let mut config = video_config("HW_SECURE_ALL");
+    config.audio_capabilities = vec![MediaKeySystemMediaCapability {
+        content_type: "audio/mp4; codecs=\"mp4a.40.2\"".to_string(),
+        robustness: "SW_SECURE_CRYPTO".to_string(),
+    }];
+
+    let result = eme
+        .request_media_key_system_access("com.widevine.alpha".to_string(), vec![config])
+        .await;
+
+    assert!(matches!(result, Err(DrmError::UnsupportedKeySystem(_))));
+}
+
+#[tokio::test]
+async fn test_eme_clearkey_requires_keyids_init_data() {
+    /// Given: ClearKey configs with and without the "keyids" init data type
+    /// When: We request ClearKey access
+    /// Then: Only the config accepting "keyids" should be selected
+    let eme = EMEInterface::new();
+    let cenc_only = MediaKeySystemConfiguration {
+        init_data_types: vec!["cenc".to_string()],
+        ..Default::default()
+    };
+    let keyids = MediaKeySystemConfiguration {
+        init_data_types: vec!["keyids".to_string()],
+        ..Default::default()
+    };
+
+    let access = eme
+        .request_media_key_system_access(
+            "org.w3.clearkey".to_string(),
+            vec![cenc_only.clone(), keyids.clone()],
+        )
+        .await
+        .expect("Config accepting keyids should be selected");
+    assert_eq!(access.configuration(), &keyids);
+
+    let result = eme
+        .request_media_key_system_access("org.w3.clearkey".to_string(), vec![cenc_only])
+        .await;
+    assert!(matches!(result, Err(DrmError::UnsupportedKeySystem(_))));
+}
+
+fn capability(content_type: &str) -> MediaKeySystemMediaCapability {
+    MediaKeySystemMediaCapability {
+        content_type: content_type.to_string(),
+        robustness: String::new(),
+    }
+}
+
+#[tokio::test]
+async fn test_eme_filters_unsupported_capabilities() {
+    // Given: Capability lists mixing decodable and undecodable content types
+    // When: We request Widevine access
+    // Then: The accepted configuration should keep only the decodable ones
+    let eme = EMEInterface::new();
+    let config = MediaKeySystemConfiguration {
+        init_data_types: vec!["cenc".to_string(), "sinf".to_string()],
+        video_capabilities: vec![
+            capability("video/mp4; codecs=\"hvc1.1.6.L93.B0\""),
+            capability("video/mp4; codecs=\"avc1.42E01E\""),
+            capability("video/mp4; codecs=\"mp4a.40.2\""),
+        ],
+        audio_capabilities: vec![
+            capability("audio/mp4; codecs=\"mp4a.40.2\""),
+            capability("audio/mp4; codecs=\"ec-3\""),
+            capability("video/webm; codecs=\"opus\""),
+        ],
+        ..Default::default()
+    };
+
+    let access = eme
+        .request_media_key_system_access("com.widevine.alpha".to_string(), vec![config.clone()])
+        .await
+        .expect("Partially supported config should be accepted");
+
+    let accepted = access.configuration();
+    assert_eq!(accepted.init_data_types, vec!["cenc".to_string()]);
+    assert_eq!(
+        accepted.video_capabilities,
+        vec![capability("video/mp4; codecs=\"avc1.42E01E\"")]
+    );
+    assert_eq!(
+        accepted.audio_capabilities,
+        vec![capability("audio/mp4; codecs=\"mp4a.40.2\"")]
+    );
+    assert_eq!(accepted.session_types, config.session_types);
+}
+
+#[tokio::test]
+async fn test_eme_rejects_config_without_supported_codec() {
+    // Given: A config whose only video capability cannot be decoded
+    // When: We request ClearKey access with it, then with a decodable fallback
+    // Then: The fallback should be selected
+    let eme = EMEInterface::new();
+    let undecodable = MediaKeySystemConfiguration {
+        video_capabilities: vec![capability("video/mp4; codecs=\"hev1.1.6.L93.B0\"")],
+        ..Default::default()
+    };
+    let fallback = MediaKeySystemConfiguration {
+        video_capabilities: vec![capability("video/mp4; codecs=\"avc1.64001f\"")],
+        ..Default::default()
+    };
+
+    let result = eme
+        .request_media_key_system_access("org.w3.clearkey".to_string(), vec![undecodable.clone()])
+        .await;
+    assert!(matches!(result, Err(DrmError::UnsupportedKeySystem(_))));
+
+    let access = eme
+        .request_media_key_system_access(
+            "org.w3.clearkey".to_string(),
+            vec![undecodable, fallback.clone()],
+        )
+        .await
+        .expect("Decodable fallback should be selected");
+    assert_eq!(access.configuration(), &fallback);
+}
+
+#[tokio::test]
+async fn test_eme_rejects_persistent_sessions_key_system_forbids() {
+    // Given: A config requiring persistent-license sessions
+    // When: We request FairPlay, which only creates temporary sessions
+    // Then: Should fail, while Widevine should accept the same config
+    let eme = EMEInterface::new();
+    let persistent = MediaKeySystemConfiguration {
+        session_types: vec!["temporary".to_string(), "persistent-license".to_string()],
+        persistent_state: MediaKeysRequirement::Required,
+        ..Default::default()
+    };
+
+    let result = eme
+        .request_media_key_system_access("com.apple.fps".to_string(), vec![persistent.clone()])
+        .await;
+    assert!(matches!(result, Err(DrmError::UnsupportedKeySystem(_))));
+
+    let access = eme
+        .request_media_key_system_access("com.widevine.alpha".to_string(), vec![persistent.clone()])
+        .await
+        .expect("Widevine supports persistent licenses");
+    assert_eq!(access.configuration(), &persistent);
+}
+
+#[tokio::test]
+async fn test_eme_rejects_conflicting_requirements() {
+    // Given: Configs contradicting themselves or the key system's policy
+    // When: We request Widevine access
+    // Then: Each should be rejected
+    let eme = EMEInterface::new();
+    let persistent_without_state = MediaKeySystemConfiguration {
+        session_types: vec!["persistent-license".to_string()],
+        persistent_state: MediaKeysRequirement::NotAllowed,
+        ..Default::default()
+    };
+    let distinctive_identifier = MediaKeySystemConfiguration {
+        distinctive_identifier: MediaKeysRequirement::Required,
+        ..Default::default()
+    };
+    let unknown_session_type = MediaKeySystemConfiguration {
+        session_types: vec!["persistent-usage-record".to_string()],
+        ..Default::default()
+    };
+
+    for config in [
+        persistent_without_state,
+        distinctive_identifier.clone(),
+        unknown_session_type,
+    ] {
+        let result = eme
+            .request_media_key_system_access("com.widevine.alpha".to_string(), vec![config])
+            .await;
+        assert!(matches!(result, Err(DrmError::UnsupportedKeySystem(_))));
+    }
+
+    // The test key system may use a distinctive identifier
+    assert!(eme.is_configuration_supported("com.example.test", &distinctive_identifier));
+}
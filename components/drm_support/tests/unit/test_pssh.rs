@@ -0,0 +1,276 @@
+//! Unit tests for PSSH box parsing
+//!
+//! Tests for parsing CENC init data and dispatching it from license requests.
+
+use cortenbrowser_drm_support::{
+    ContentDecryptionModule, DrmError, PsshParser, COMMON_SYSTEM_ID, PLAYREADY_SYSTEM_ID,
+    WIDEVINE_SYSTEM_ID,
+};
+
+/// Version 0 Widevine PSSH box with key ID eb676abbcb345e96bbcf616630f1a3da
+/// in its `WidevinePsshData`
+const WIDEVINE_PSSH: [u8; 79] = [
+    0x00, 0x00, 0x00, 0x4f, 0x70, 0x73, 0x73, 0x68, 0x00, 0x00, 0x00, 0x00, 0xed, 0xef, 0x8b, 0xa9,
+    0x79, 0xd6, 0x4a, 0xce, 0xa3, 0xc8, 0x27, 0xdc, 0xd5, 0x1d, 0x21, 0xed, 0x00, 0x00, 0x00, 0x2f,
+    0x08, 0x01, 0x12, 0x10, 0xeb, 0x67, 0x6a, 0xbb, 0xcb, 0x34, 0x5e, 0x96, 0xbb, 0xcf, 0x61, 0x66,
+    0x30, 0xf1, 0xa3, 0xda, 0x1a, 0x0d, 0x77, 0x69, 0x64, 0x65, 0x76, 0x69, 0x6e, 0x65, 0x5f, 0x74,
+    0x65, 0x73, 0x74, 0x22, 0x0a, 0x43, 0x6f, 0x72, 0x74, 0x65, 0x6e, 0x54, 0x65, 0x73, 0x74,
+];
+
+const WIDEVINE_KEY_ID: [u8; 16] = [
+    0xeb, 0x67, 0x6a, 0xbb, 0xcb, 0x34, 0x5e, 0x96, 0xbb, 0xcf, 0x61, 0x66, 0x30, 0xf1, 0xa3, 0xda,
+];
+
+/// Build a version 1 PSSH box listing `key_ids`
+fn pssh_v1(system_id: &[u8; 16], key_ids: &[[u8; 16]], data: &[u8]) -> Vec<u8> {
+    let size = 36 + 16 * key_ids.len() + data.len();
+    let mut pssh = (size as u32).to_be_bytes().to_vec();
+    pssh.extend_from_slice(b"pssh");
+    pssh.extend_from_slice(&[1, 0, 0, 0]);
+    pssh.extend_from_slice(system_id);
+    pssh.extend_from_slice(&(key_ids.len() as u32).to_be_bytes());
+    for key_id in key_ids {
+        pssh.extend_from_slice(key_id);
+    }
+    pssh.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    pssh.extend_from_slice(data);
+    pssh
+}
+
+/// Build a version 0 PSSH box carrying `data`
+fn pssh_v0(system_id: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    let size = 32 + data.len();
+    let mut pssh = (size as u32).to_be_bytes().to_vec();
+    pssh.extend_from_slice(b"pssh");
+    pssh.extend_from_slice(&[0, 0, 0, 0]);
+    pssh.extend_from_slice(system_id);
+    pssh.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    pssh.extend_from_slice(data);
+    pssh
+}
+
+/// Build `WidevinePsshData` listing `key_ids`
+fn widevine_pssh_data(key_ids: &[[u8; 16]]) -> Vec<u8> {
+    let mut data = vec![0x08, 0x01];
+    for key_id in key_ids {
+        data.extend_from_slice(&[0x12, 0x10]);
+        data.extend_from_slice(key_id);
+    }
+    data
+}
+
+#[test]
+fn test_pssh_parse_widevine() {
+    /// Given: A known version 0 Widevine PSSH box
+    /// When: We parse it
+    /// Then: The key ID should be read from the Widevine PSSH data
+    let boxes = PsshParser::parse(&WIDEVINE_PSSH).expect("PSSH should parse");
+
+    assert_eq!(boxes.len(), 1);
+    assert_eq!(boxes[0].system_id, WIDEVINE_SYSTEM_ID);
+    assert_eq!(boxes[0].key_system(), Some("com.widevine.alpha"));
+    assert_eq!(boxes[0].key_ids, vec![WIDEVINE_KEY_ID]);
+    assert_eq!(boxes[0].pssh_data, &WIDEVINE_PSSH[32..]);
+}
+
+#[test]
+fn test_pssh_parse_concatenated_boxes() {
+    /// Given: Widevine and version 1 PlayReady PSSH boxes concatenated
+    /// When: We parse them
+    /// Then: Both boxes should be returned in order with their key IDs
+    let mut data = WIDEVINE_PSSH.to_vec();
+    data.extend(pssh_v1(
+        &PLAYREADY_SYSTEM_ID,
+        &[[0x01; 16], [0x02; 16]],
+        b"<WRMHEADER/>",
+    ));
+
+    let boxes = PsshParser::parse(&data).expect("PSSH boxes should parse");
+
+    assert_eq!(boxes.len(), 2);
+    assert_eq!(boxes[1].key_system(), Some("com.microsoft.playready"));
+    assert_eq!(boxes[1].key_ids, vec![[0x01; 16], [0x02; 16]]);
+    assert_eq!(boxes[1].pssh_data, b"<WRMHEADER/>");
+}
+
+#[test]
+fn test_pssh_parse_init_segment() {
+    /// Given: An init segment with the PSSH box inside `moov`
+    /// When: We parse it
+    /// Then: Other boxes should be skipped and the PSSH box found
+    let mut data = vec![0, 0, 0, 16];
+    data.extend_from_slice(b"ftypisom");
+    data.extend_from_slice(&[0, 0, 0, 0]);
+    data.extend_from_slice(&((8 + WIDEVINE_PSSH.len()) as u32).to_be_bytes());
+    data.extend_from_slice(b"moov");
+    data.extend_from_slice(&WIDEVINE_PSSH);
+
+    let boxes = PsshParser::parse(&data).expect("Init segment should parse");
+
+    assert_eq!(boxes.len(), 1);
+    assert_eq!(boxes[0].key_ids, vec![WIDEVINE_KEY_ID]);
+}
+
+#[test]
+fn test_pssh_parse_truncated() {
+    /// Given: A PSSH box cut short
+    /// When: We parse it
+    /// Then: Should fail with InvalidInitData
+    let result = PsshParser::parse(&WIDEVINE_PSSH[..40]);
+
+    assert!(matches!(result, Err(DrmError::InvalidInitData(_))));
+}
+
+#[test]
+fn test_pssh_parse_version_0_multiple_key_ids() {
+    // Given: A version 0 Widevine box with two key IDs in its data, and a
+    // version 0 PlayReady box
+    // When: We parse them
+    // Then: Both Widevine key IDs should be read, and the opaque PlayReady
+    // data should yield none
+    let mut data = pssh_v0(
+        &WIDEVINE_SYSTEM_ID,
+        &widevine_pssh_data(&[[0x01; 16], [0x02; 16]]),
+    );
+    data.extend(pssh_v0(&PLAYREADY_SYSTEM_ID, b"<WRMHEADER/>"));
+
+    let boxes = PsshParser::parse(&data).expect("PSSH boxes should parse");
+
+    assert_eq!(boxes.len(), 2);
+    assert_eq!(boxes[0].key_ids, vec![[0x01; 16], [0x02; 16]]);
+    assert!(boxes[1].key_ids.is_empty());
+    assert_eq!(boxes[1].pssh_data, b"<WRMHEADER/>");
+}
+
+#[test]
+fn test_pssh_parse_truncated_key_id_list() {
+    // Given: A version 1 box whose key ID count exceeds the key IDs present
+    // When: We parse it
+    // Then: Should fail with InvalidInitData
+    let mut data = pssh_v1(&COMMON_SYSTEM_ID, &[[0x01; 16], [0x02; 16]], &[]);
+    // Claim a third key ID
+    data[31] = 3;
+
+    let result = PsshParser::parse(&data);
+
+    assert!(matches!(result, Err(DrmError::InvalidInitData(_))));
+}
+
+#[test]
+fn test_pssh_parse_unsupported_version() {
+    // Given: A PSSH box of version 2
+    // When: We parse it
+    // Then: Should fail with InvalidInitData
+    let mut data = pssh_v1(&COMMON_SYSTEM_ID, &[[0x01; 16]], &[]);
+    data[8] = 2;
+
+    let result = PsshParser::parse(&data);
+
+    assert!(matches!(result, Err(DrmError::InvalidInitData(_))));
+}
+
+#[tokio::test]
+async fn test_cdm_generate_request_from_cenc_init_data() {
+    /// Given: A Widevine CDM session
+    /// When: We generate a request from "cenc" init data
+    /// Then: The request should list the key ID from the Widevine PSSH box
+    let cdm = ContentDecryptionModule::new("com.widevine.alpha".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    let mut init_data = pssh_v1(&PLAYREADY_SYSTEM_ID, &[[0x01; 16]], &[]);
+    init_data.extend_from_slice(&WIDEVINE_PSSH);
+    let request = cdm
+        .generate_request(&session_id, "cenc", &init_data)
+        .await
+        .expect("License request generation should succeed");
+
+    let request: serde_json::Value = serde_json::from_slice(&request).expect("JSON request");
+    assert_eq!(
+        request["kids"],
+        serde_json::json!(["62dqu8s0Xpa7z2FmMPGj2g"])
+    );
+}
+
+#[tokio::test]
+async fn test_cdm_generate_request_invalid_init_data() {
+    /// Given: A Widevine CDM session
+    /// When: The init data has no Widevine PSSH box, or an unknown type
+    /// Then: Should fail with InvalidInitData
+    let cdm = ContentDecryptionModule::new("com.widevine.alpha".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    let playready_only = pssh_v1(&PLAYREADY_SYSTEM_ID, &[[0x01; 16]], &[]);
+    let result = cdm
+        .generate_request(&session_id, "cenc", &playready_only)
+        .await;
+    assert!(matches!(result, Err(DrmError::InvalidInitData(_))));
+
+    let result = cdm.generate_request(&session_id, "webm", &[0x01; 16]).await;
+    assert!(matches!(result, Err(DrmError::InvalidInitData(_))));
+}
+
+#[tokio::test]
+async fn test_cdm_generate_request_from_malformed_keyids() {
+    // Given: A ClearKey CDM session
+    // When: The "keyids" init data is not JSON, or lists an invalid key ID
+    // Then: Should fail with InvalidInitData
+    let cdm = ContentDecryptionModule::new("org.w3.clearkey".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+
+    let result = cdm
+        .generate_request(&session_id, "keyids", b"{\"kids\":")
+        .await;
+    assert!(matches!(result, Err(DrmError::InvalidInitData(_))));
+
+    let result = cdm
+        .generate_request(&session_id, "keyids", br#"{"kids":["not base64!"]}"#)
+        .await;
+    assert!(matches!(result, Err(DrmError::InvalidInitData(_))));
+}
+
+#[tokio::test]
+async fn test_cdm_decrypt_only_with_session_key_ids() {
+    // Given: A PlayReady CDM session requested with two key IDs from a
+    // version 1 PSSH box
+    // When: We decrypt before and after the license, and after closing
+    // Then: Only the session's key IDs should decrypt, and only while it is
+    // licensed and open
+    let cdm = ContentDecryptionModule::new("com.microsoft.playready".to_string())
+        .expect("CDM creation should succeed");
+    let session_id = cdm.create_session().await.expect("Session creation");
+    let init_data = pssh_v1(
+        &PLAYREADY_SYSTEM_ID,
+        &[[0x01; 16], [0x02; 16]],
+        b"<WRMHEADER/>",
+    );
+    cdm.generate_request(&session_id, "cenc", &init_data)
+        .await
+        .expect("License request generation should succeed");
+    assert!(matches!(
+        cdm.decrypt(b"sample", &[0x01; 16]),
+        Err(DrmError::DecryptionFailed(_))
+    ));
+
+    cdm.update(&session_id, b"license")
+        .await
+        .expect("Session update should succeed");
+    assert!(cdm.is_key_usable(&[0x02; 16]));
+    assert_eq!(cdm.decrypt(b"sample", &[0x01; 16]).unwrap(), b"sample");
+    assert_eq!(cdm.decrypt(b"sample", &[0x02; 16]).unwrap(), b"sample");
+    assert!(!cdm.is_key_usable(&[0x03; 16]));
+    assert!(matches!(
+        cdm.decrypt(b"sample", &[0x03; 16]),
+        Err(DrmError::DecryptionFailed(_))
+    ));
+
+    cdm.close_session(&session_id)
+        .await
+        .expect("Session close should succeed");
+    assert!(matches!(
+        cdm.decrypt(b"sample", &[0x01; 16]),
+        Err(DrmError::DecryptionFailed(_))
+    ));
+}
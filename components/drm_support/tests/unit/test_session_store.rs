@@ -0,0 +1,114 @@
+//! Unit tests for persistent sessions
+//!
+//! Tests for saving, loading and removing persistent-license sessions.
+
+use cortenbrowser_drm_support::{
+    ContentDecryptionModule, DrmError, DrmSessionId, InMemorySessionStore, SessionStore,
+    SessionType,
+};
+use std::sync::Arc;
+
+const CLEARKEY_LICENSE: &[u8] =
+    br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+
+fn clearkey_cdm(store: &Arc<InMemorySessionStore>) -> ContentDecryptionModule {
+    ContentDecryptionModule::with_session_store("org.w3.clearkey".to_string(), store.clone())
+        .expect("CDM creation should succeed")
+}
+
+#[tokio::test]
+async fn test_persistent_session_saved_on_update() {
+    /// Given: A persistent-license session and a temporary session
+    /// When: Both are updated with a license
+    /// Then: Only the persistent session should be in the store
+    let store = Arc::new(InMemorySessionStore::new());
+    let cdm = clearkey_cdm(&store);
+    let persistent = cdm
+        .create_session_with_type(SessionType::PersistentLicense)
+        .await
+        .expect("Session creation");
+    let temporary = cdm.create_session().await.expect("Session creation");
+
+    assert_eq!(store.load(&persistent).unwrap(), None);
+    cdm.update(&persistent, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+    cdm.update(&temporary, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    assert!(store.load(&persistent).unwrap().is_some());
+    assert_eq!(store.load(&temporary).unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_load_session_not_stored() {
+    /// Given: A CDM with an empty store
+    /// When: We load an unknown session, or a temporary session
+    /// Then: Should fail with SessionNotFound
+    let store = Arc::new(InMemorySessionStore::new());
+    let cdm = clearkey_cdm(&store);
+    let temporary = cdm.create_session().await.expect("Session creation");
+    cdm.update(&temporary, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+
+    let result = clearkey_cdm(&store)
+        .load_session(&DrmSessionId::new())
+        .await;
+    assert!(matches!(result, Err(DrmError::SessionNotFound(_))));
+
+    let result = clearkey_cdm(&store).load_session(&temporary).await;
+    assert!(matches!(result, Err(DrmError::SessionNotFound(_))));
+}
+
+#[tokio::test]
+async fn test_remove_session() {
+    /// Given: A stored persistent-license ClearKey session
+    /// When: We remove it
+    /// Then: Its keys should be gone and it should no longer load
+    let store = Arc::new(InMemorySessionStore::new());
+    let cdm = clearkey_cdm(&store);
+    let session_id = cdm
+        .create_session_with_type(SessionType::PersistentLicense)
+        .await
+        .expect("Session creation");
+    cdm.update(&session_id, CLEARKEY_LICENSE)
+        .await
+        .expect("Session update");
+    assert!(cdm.decrypt(&[0; 16], &[0x10; 16]).is_ok());
+
+    cdm.remove_session(&session_id)
+        .await
+        .expect("Session removal");
+
+    assert!(matches!(
+        cdm.decrypt(&[0; 16], &[0x10; 16]),
+        Err(DrmError::DecryptionFailed(_))
+    ));
+    assert_eq!(store.load(&session_id).unwrap(), None);
+    assert!(matches!(
+        clearkey_cdm(&store).load_session(&session_id).await,
+        Err(DrmError::SessionNotFound(_))
+    ));
+    assert!(matches!(
+        cdm.remove_session(&DrmSessionId::new()).await,
+        Err(DrmError::SessionNotFound(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_load_session_invalid_record() {
+    /// Given: A store holding a corrupt session record
+    /// When: We load the session
+    /// Then: Should fail with SessionStorageFailed
+    let store = Arc::new(InMemorySessionStore::new());
+    let session_id = DrmSessionId::new();
+    store
+        .save(&session_id, b"not a session".to_vec())
+        .expect("Save");
+
+    let result = clearkey_cdm(&store).load_session(&session_id).await;
+
+    assert!(matches!(result, Err(DrmError::SessionStorageFailed(_))));
+}
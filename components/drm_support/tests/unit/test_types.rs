@@ -3,6 +3,7 @@
 //! Tests for DrmSessionId, DrmError, and related type definitions.
 
 use cortenbrowser_drm_support::{DrmError, DrmSessionId};
+use cortenbrowser_shared_types::MediaError;
 
 #[test]
 fn test_drm_session_id_creation() {
@@ -59,7 +60,7 @@ fn test_drm_error_license_request_failed() {
     let error = DrmError::LicenseRequestFailed("Network timeout".to_string());
 
     match error {
-        DrmError::LicenseRequestFailed(_) => {}, // Expected
+        DrmError::LicenseRequestFailed(_) => {} // Expected
         _ => panic!("Expected LicenseRequestFailed error"),
     }
 }
@@ -72,7 +73,7 @@ fn test_drm_error_decryption_failed() {
     let error = DrmError::DecryptionFailed("Invalid key".to_string());
 
     match error {
-        DrmError::DecryptionFailed(_) => {}, // Expected
+        DrmError::DecryptionFailed(_) => {} // Expected
         _ => panic!("Expected DecryptionFailed error"),
     }
 }
@@ -88,7 +89,86 @@ fn test_drm_error_session_not_found() {
     match error {
         DrmError::SessionNotFound(id) => {
             assert_eq!(id, session_id);
-        },
+        }
         _ => panic!("Expected SessionNotFound error"),
     }
 }
+
+#[test]
+fn test_drm_error_invalid_init_data() {
+    // Given: Malformed initialization data
+    // When: We create the error
+    // Then: The error should be of the correct variant
+    let error = DrmError::InvalidInitData("Truncated PSSH box".to_string());
+
+    match error {
+        DrmError::InvalidInitData(_) => {} // Expected
+        _ => panic!("Expected InvalidInitData error"),
+    }
+}
+
+#[test]
+fn test_drm_error_session_storage_failed() {
+    // Given: A session store failure
+    // When: We format the error
+    // Then: The message should describe the storage failure
+    let error = DrmError::SessionStorageFailed("Disk full".to_string());
+
+    assert_eq!(error.to_string(), "Session storage failed: Disk full");
+}
+
+#[test]
+fn test_drm_error_output_protection_variants() {
+    // Given: Output protection policy failures
+    // When: We format the errors
+    // Then: The messages should name the policy that failed
+    assert_eq!(
+        DrmError::HdcpRequired.to_string(),
+        "HDCP output protection required"
+    );
+    assert_eq!(
+        DrmError::OutputNotAllowed.to_string(),
+        "Output not allowed by license"
+    );
+}
+
+#[test]
+fn test_drm_error_license_server_error() {
+    // Given: A license server answering with an HTTP status
+    // When: We format the error
+    // Then: The message should include the status
+    let error = DrmError::LicenseServerError { status: 503 };
+
+    assert_eq!(error.to_string(), "License server error: status 503");
+}
+
+#[test]
+fn test_drm_error_into_media_error() {
+    // Given: DRM policy and decryption failures
+    // When: We convert them to media errors
+    // Then: They should become DrmError with the DRM error message
+    for error in [
+        DrmError::HdcpRequired,
+        DrmError::OutputNotAllowed,
+        DrmError::DecryptionFailed("Bad key".to_string()),
+    ] {
+        let details = error.to_string();
+
+        assert_eq!(MediaError::from(error), MediaError::DrmError { details });
+    }
+}
+
+#[test]
+fn test_drm_license_server_error_into_media_error() {
+    // Given: A license server failure
+    // When: We convert it to a media error
+    // Then: It should become a NetworkError, as a transport failure
+    let error: MediaError = DrmError::LicenseServerError { status: 403 }.into();
+
+    assert_eq!(
+        error,
+        MediaError::NetworkError {
+            details: "License server error: status 403".to_string(),
+        }
+    );
+}
@@ -1,18 +1,51 @@
 //! Demuxer trait and related types
 
-use crate::types::{AudioTrackInfo, MediaInfo, VideoTrackInfo};
+use crate::types::{AudioTrackInfo, DemuxedPacket, MediaInfo, SeekIndex, VideoTrackInfo};
 use cortenbrowser_shared_types::MediaError;
+use std::fmt;
+use std::time::Duration;
+
+/// Score returned by [`Demuxer::probe`] for an unambiguous match
+pub const PROBE_SCORE_MAX: u8 = 100;
 
 /// Trait for container format demuxers
 ///
 /// Demuxers are responsible for parsing container formats (MP4, WebM, etc.)
 /// and extracting metadata about the contained media streams.
-pub trait Demuxer {
+///
+/// Demuxers that support packet extraction are first loaded with
+/// [`Demuxer::load`], after which [`Demuxer::read_packet`] returns the
+/// compressed samples of all tracks in decode order.
+///
+/// For progressive playback, data can instead be fed in chunks as it
+/// arrives with [`Demuxer::feed`] and packets read with
+/// [`Demuxer::next_packet`], which returns `Ok(None)` until enough data
+/// for the next packet has been fed.
+///
+/// Demuxers with a keyframe index expose it through [`Demuxer::seek_index`],
+/// and [`Demuxer::seek`] restarts reading at a keyframe from data fed again
+/// from its byte offset.
+///
+/// [`DemuxerFactory`](crate::DemuxerFactory) picks a demuxer for unknown
+/// data by comparing the [`Demuxer::probe`] scores of all formats.
+pub trait Demuxer: fmt::Debug {
     /// Create a new demuxer instance
     fn new() -> Self
     where
         Self: Sized;
 
+    /// Score how likely `data` is in this demuxer's format
+    ///
+    /// `data` is the start of the file and may be truncated at any byte.
+    ///
+    /// # Returns
+    ///
+    /// `0` if the data is not in this format, up to [`PROBE_SCORE_MAX`] for
+    /// an unambiguous signature
+    fn probe(data: &[u8]) -> u8
+    where
+        Self: Sized;
+
     /// Parse media container data and extract information
     ///
     /// # Arguments
@@ -48,4 +81,126 @@ pub trait Demuxer {
     /// * `Some(AudioTrackInfo)` - Track information if found
     /// * `None` - Track not found
     fn get_audio_track(&self, track_id: u32) -> Option<AudioTrackInfo>;
+
+    /// Load container data for packet extraction
+    ///
+    /// Parses the container like [`Demuxer::parse`] and keeps the data so
+    /// packets can be read afterwards. Loading again resets all read positions.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(MediaInfo)` - Successfully loaded media information
+    /// * `Err(MediaError)` - Failed to parse container, or packet extraction
+    ///   is not supported by this demuxer
+    fn load(&mut self, data: &[u8]) -> Result<MediaInfo, MediaError> {
+        let _ = data;
+        Err(MediaError::NotImplemented(
+            "Packet extraction is not supported by this demuxer".to_string(),
+        ))
+    }
+
+    /// Read the next packet from any track
+    ///
+    /// Packets are returned in decode order, interleaving tracks by decode
+    /// timestamp.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DemuxedPacket))` - The next packet
+    /// * `Ok(None)` - All tracks are exhausted
+    /// * `Err(MediaError)` - Malformed data, or no data has been loaded
+    fn read_packet(&mut self) -> Result<Option<DemuxedPacket>, MediaError> {
+        Err(MediaError::NotImplemented(
+            "Packet extraction is not supported by this demuxer".to_string(),
+        ))
+    }
+
+    /// Read the next packet from a specific track
+    ///
+    /// Shares read positions with [`Demuxer::read_packet`].
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - Identifier of the track to read from
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DemuxedPacket))` - The next packet of the track
+    /// * `Ok(None)` - The track is exhausted
+    /// * `Err(MediaError)` - Unknown track, malformed data, or no data loaded
+    fn next_sample(&mut self, track_id: u32) -> Result<Option<DemuxedPacket>, MediaError> {
+        let _ = track_id;
+        Err(MediaError::NotImplemented(
+            "Packet extraction is not supported by this demuxer".to_string(),
+        ))
+    }
+
+    /// Append a chunk of container data for incremental parsing
+    ///
+    /// Chunks may split the data at any byte. Media information becomes
+    /// available through [`Demuxer::media_info`] once the headers have been
+    /// fed, and packets through [`Demuxer::next_packet`] as their data
+    /// arrives.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Data accepted
+    /// * `Err(MediaError)` - The data is malformed or not in this format, the
+    ///   stream has ended, or incremental parsing is not supported by this
+    ///   demuxer
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), MediaError> {
+        let _ = chunk;
+        Err(MediaError::NotImplemented(
+            "Incremental parsing is not supported by this demuxer".to_string(),
+        ))
+    }
+
+    /// Signal that no more data will be fed
+    ///
+    /// Afterwards, incomplete trailing data is reported as an error instead
+    /// of waiting for more bytes.
+    fn end_of_stream(&mut self) {}
+
+    /// Read the next packet of fed data
+    ///
+    /// Packets are returned in the same order as [`Demuxer::read_packet`].
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(DemuxedPacket))` - The next packet
+    /// * `Ok(None)` - More data is needed, or the stream has ended and all
+    ///   tracks are exhausted
+    /// * `Err(MediaError)` - Malformed data
+    fn next_packet(&mut self) -> Result<Option<DemuxedPacket>, MediaError> {
+        self.read_packet()
+    }
+
+    /// Returns the media information of the loaded or fed data, once known
+    fn media_info(&self) -> Option<&MediaInfo> {
+        None
+    }
+
+    /// Returns the keyframe index of the loaded or fed data, if the
+    /// container has one
+    fn seek_index(&self) -> Option<&SeekIndex> {
+        None
+    }
+
+    /// Restart reading at the latest keyframes at or before `time`
+    ///
+    /// Buffered data is dropped: data must then be fed with
+    /// [`Demuxer::feed`] starting at the returned byte offset, after which
+    /// [`Demuxer::next_packet`] continues from the keyframes.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(offset)` - File offset to feed data from
+    /// * `Err(MediaError)` - No data or no seek index is available, or
+    ///   seeking is not supported by this demuxer
+    fn seek(&mut self, time: Duration) -> Result<u64, MediaError> {
+        let _ = time;
+        Err(MediaError::NotImplemented(
+            "Seeking is not supported by this demuxer".to_string(),
+        ))
+    }
 }
@@ -0,0 +1,236 @@
+//! EBML (Extensible Binary Meta Language) primitives
+//!
+//! EBML is the binary format underlying Matroska and WebM. Every element is
+//! encoded as:
+//!
+//! ```text
+//! +------------+--------------+-----------------+
+//! | Element ID | Data size    | Data            |
+//! | (1-4 B)    | (1-8 B vint) | (size bytes)    |
+//! +------------+--------------+-----------------+
+//! ```
+//!
+//! IDs and sizes are variable-length integers ("vints"): the number of
+//! leading zero bits in the first byte gives the total length. IDs keep the
+//! length marker bit; sizes drop it. A size with all value bits set means
+//! "unknown", used for live streams whose Segment/Cluster length is not known
+//! up front.
+
+use cortenbrowser_shared_types::MediaError;
+
+/// Header of an EBML element
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ElementHeader {
+    /// Element ID, including the length marker bits
+    pub(crate) id: u32,
+    /// Payload size in bytes, or `None` for unknown-size elements
+    pub(crate) size: Option<u64>,
+    /// Length of the ID and size fields
+    pub(crate) header_len: usize,
+}
+
+fn malformed(details: &str) -> MediaError {
+    MediaError::CodecError {
+        details: format!("Malformed EBML: {}", details),
+    }
+}
+
+/// Length of a vint from its first byte, or `None` for the invalid 0x00
+fn vint_length(first: u8) -> Option<usize> {
+    (first != 0).then(|| first.leading_zeros() as usize + 1)
+}
+
+/// Read a vint with its length marker removed
+///
+/// # Returns
+///
+/// * `Ok(Some((value, len)))` - The decoded value and its encoded length
+/// * `Ok(None)` - `data` ends before the vint does
+/// * `Err(MediaError)` - The first byte is not a valid vint marker
+pub(crate) fn read_vint(data: &[u8]) -> Result<Option<(u64, usize)>, MediaError> {
+    let Some(&first) = data.first() else {
+        return Ok(None);
+    };
+    let len = vint_length(first).ok_or_else(|| malformed("invalid variable-length integer"))?;
+    if data.len() < len {
+        return Ok(None);
+    }
+
+    let mut value = u64::from(first) & (0xFF >> len);
+    for &byte in &data[1..len] {
+        value = (value << 8) | u64::from(byte);
+    }
+    Ok(Some((value, len)))
+}
+
+/// Read a signed vint, as used by EBML lacing size differences
+pub(crate) fn read_signed_vint(data: &[u8]) -> Result<Option<(i64, usize)>, MediaError> {
+    Ok(read_vint(data)?.map(|(value, len)| {
+        let bias = (1i64 << (7 * len - 1)) - 1;
+        (value as i64 - bias, len)
+    }))
+}
+
+/// Read an element header
+///
+/// # Returns
+///
+/// * `Ok(Some(header))` - A complete header
+/// * `Ok(None)` - `data` ends inside the header
+/// * `Err(MediaError)` - The ID or size is malformed
+pub(crate) fn read_element_header(data: &[u8]) -> Result<Option<ElementHeader>, MediaError> {
+    let Some(&first) = data.first() else {
+        return Ok(None);
+    };
+    let id_len = vint_length(first)
+        .filter(|&len| len <= 4)
+        .ok_or_else(|| malformed("invalid element ID"))?;
+    if data.len() < id_len {
+        return Ok(None);
+    }
+    let id = data[..id_len]
+        .iter()
+        .fold(0u32, |id, &byte| (id << 8) | u32::from(byte));
+
+    let Some((size, size_len)) = read_vint(&data[id_len..])? else {
+        return Ok(None);
+    };
+    let unknown = size == (1u64 << (7 * size_len)) - 1;
+
+    Ok(Some(ElementHeader {
+        id,
+        size: (!unknown).then_some(size),
+        header_len: id_len + size_len,
+    }))
+}
+
+/// Decode an unsigned integer element payload
+pub(crate) fn read_uint(data: &[u8]) -> Result<u64, MediaError> {
+    if data.len() > 8 {
+        return Err(malformed("unsigned integer longer than 8 bytes"));
+    }
+    Ok(data
+        .iter()
+        .fold(0u64, |value, &byte| (value << 8) | u64::from(byte)))
+}
+
+/// Decode a float element payload (0, 4 or 8 bytes)
+pub(crate) fn read_float(data: &[u8]) -> Result<f64, MediaError> {
+    match data.len() {
+        0 => Ok(0.0),
+        4 => Ok(f64::from(f32::from_be_bytes([
+            data[0], data[1], data[2], data[3],
+        ]))),
+        8 => Ok(f64::from_be_bytes([
+            data[0], data[1], data[2], data[3], data[4], data[5], data[6], data[7],
+        ])),
+        _ => Err(malformed("float must be 0, 4 or 8 bytes")),
+    }
+}
+
+/// Decode a string element payload, dropping trailing NUL padding
+pub(crate) fn read_string(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// Iterate over the children of a fully buffered master element
+///
+/// Yields `(id, payload)` pairs. Children with an unknown size or a size
+/// running past the parent are reported as errors.
+pub(crate) fn children(data: &[u8]) -> Children<'_> {
+    Children { data, pos: 0 }
+}
+
+/// Iterator returned by [`children`]
+pub(crate) struct Children<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for Children<'a> {
+    type Item = Result<(u32, &'a [u8]), MediaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.data.len() {
+            return None;
+        }
+
+        let rest = &self.data[self.pos..];
+        let result = match read_element_header(rest) {
+            Ok(Some(ElementHeader {
+                id,
+                size: Some(size),
+                header_len,
+            })) => header_len
+                .checked_add(size as usize)
+                .filter(|&end| end <= rest.len())
+                .map(|end| {
+                    self.pos += end;
+                    (id, &rest[header_len..end])
+                })
+                .ok_or_else(|| malformed("child element exceeds its parent")),
+            Ok(Some(_)) => Err(malformed("unknown-size child of a sized element")),
+            Ok(None) => Err(malformed("truncated child element")),
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            // Stop after the first error
+            self.pos = self.data.len();
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_vint() {
+        assert_eq!(read_vint(&[0x81]).unwrap(), Some((1, 1)));
+        assert_eq!(read_vint(&[0x40, 0x02]).unwrap(), Some((2, 2)));
+        assert_eq!(read_vint(&[0x10, 0x00, 0x00, 0x05]).unwrap(), Some((5, 4)));
+        assert_eq!(read_vint(&[0x40]).unwrap(), None);
+        assert!(read_vint(&[0x00]).is_err());
+    }
+
+    #[test]
+    fn test_read_signed_vint() {
+        // 1-byte range is -63..=64 around a bias of 63
+        assert_eq!(read_signed_vint(&[0xBF]).unwrap(), Some((0, 1)));
+        assert_eq!(read_signed_vint(&[0x80]).unwrap(), Some((-63, 1)));
+        assert_eq!(read_signed_vint(&[0x5F, 0xFF]).unwrap(), Some((0, 2)));
+    }
+
+    #[test]
+    fn test_read_element_header() {
+        // Segment with unknown size
+        let header = read_element_header(&[
+            0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        ])
+        .unwrap()
+        .unwrap();
+        assert_eq!(header.id, 0x1853_8067);
+        assert_eq!(header.size, None);
+        assert_eq!(header.header_len, 12);
+
+        // Timecode with 1-byte size
+        let header = read_element_header(&[0xE7, 0x81, 0x00]).unwrap().unwrap();
+        assert_eq!(header.id, 0xE7);
+        assert_eq!(header.size, Some(1));
+        assert_eq!(header.header_len, 2);
+
+        assert_eq!(read_element_header(&[0x1A, 0x45]).unwrap(), None);
+    }
+
+    #[test]
+    fn test_children_reports_overflow() {
+        let data = [0xE7, 0x81, 0x05, 0xA3, 0x85, 0x00];
+        let mut iter = children(&data);
+        assert_eq!(iter.next().unwrap().unwrap(), (0xE7, &[0x05][..]));
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+}
@@ -0,0 +1,192 @@
+//! Container format detection and demuxer selection
+
+use crate::demuxer::Demuxer;
+use crate::matroska::MatroskaDemuxer;
+use crate::mp4::Mp4Demuxer;
+use crate::ogg::OggDemuxer;
+use crate::webm::WebmDemuxer;
+use cortenbrowser_shared_types::MediaError;
+
+/// Container formats with a demuxer in this crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContainerFormat {
+    /// MPEG-4 Part 14 / QuickTime
+    Mp4,
+    /// WebM
+    WebM,
+    /// Ogg
+    Ogg,
+    /// Matroska
+    Matroska,
+}
+
+impl ContainerFormat {
+    /// All formats, in the order used to break probe score ties
+    pub const ALL: [ContainerFormat; 4] = [Self::Mp4, Self::WebM, Self::Ogg, Self::Matroska];
+
+    /// Score how likely `data` is in this format, see [`Demuxer::probe`]
+    pub fn probe_score(self, data: &[u8]) -> u8 {
+        match self {
+            Self::Mp4 => Mp4Demuxer::probe(data),
+            Self::WebM => WebmDemuxer::probe(data),
+            Self::Ogg => OggDemuxer::probe(data),
+            Self::Matroska => MatroskaDemuxer::probe(data),
+        }
+    }
+
+    /// Detect the format of `data` from its first bytes
+    ///
+    /// # Returns
+    ///
+    /// The format with the highest non-zero probe score, or `None` if no
+    /// format recognises the data
+    pub fn probe(data: &[u8]) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .map(|format| (format, format.probe_score(data)))
+            .filter(|&(_, score)| score > 0)
+            // max_by_key keeps the last maximum; reverse so earlier formats win ties
+            .rev()
+            .max_by_key(|&(_, score)| score)
+            .map(|(format, _)| format)
+    }
+
+    /// Determine the format from a MIME type
+    ///
+    /// The MIME type may carry a `codecs` parameter, e.g.
+    /// `video/mp4; codecs="avc1.64001f, mp4a.40.2"`. Every listed codec must
+    /// fit in the container.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(ContainerFormat)` - The container format
+    /// * `Err(MediaError)` - `UnsupportedFormat` for unknown MIME types, or
+    ///   codecs the container cannot carry
+    pub fn from_mime(mime: &str) -> Result<Self, MediaError> {
+        let (essence, codecs) = parse_mime(mime);
+        let format = match essence.as_str() {
+            "video/mp4" | "audio/mp4" | "application/mp4" | "video/quicktime" | "audio/x-m4a" => {
+                Self::Mp4
+            }
+            "video/webm" | "audio/webm" => Self::WebM,
+            "video/ogg" | "audio/ogg" | "application/ogg" | "audio/opus" => Self::Ogg,
+            "video/x-matroska" | "audio/x-matroska" | "video/matroska" | "audio/matroska" => {
+                Self::Matroska
+            }
+            _ => {
+                return Err(MediaError::UnsupportedFormat {
+                    format: format!("Unsupported MIME type: {}", essence),
+                })
+            }
+        };
+
+        if let Some(codec) = codecs.iter().find(|codec| !format.can_contain(codec)) {
+            return Err(MediaError::UnsupportedFormat {
+                format: format!("{} cannot contain codec {}", essence, codec),
+            });
+        }
+        Ok(format)
+    }
+
+    /// Create a demuxer for this format
+    pub fn create_demuxer(self) -> Box<dyn Demuxer + Send> {
+        match self {
+            Self::Mp4 => Box::new(Mp4Demuxer::new()),
+            Self::WebM => Box::new(WebmDemuxer::new()),
+            Self::Ogg => Box::new(OggDemuxer::new()),
+            Self::Matroska => Box::new(MatroskaDemuxer::new()),
+        }
+    }
+
+    /// Whether a codec from a `codecs` MIME parameter fits in this format
+    fn can_contain(self, codec: &str) -> bool {
+        // RFC 6381 codec strings: a four-character code, then dotted details
+        let family = codec
+            .split('.')
+            .next()
+            .unwrap_or(codec)
+            .to_ascii_lowercase();
+        let allowed: &[&str] = match self {
+            Self::Mp4 => &[
+                "avc1", "avc3", "hvc1", "hev1", "av01", "vp09", "mp4a", "opus", "flac", "ac-3",
+                "ec-3",
+            ],
+            Self::WebM => &["vp8", "vp9", "vp09", "av01", "vorbis", "opus"],
+            Self::Ogg => &["vorbis", "opus", "flac", "theora", "speex"],
+            Self::Matroska => return true,
+        };
+        allowed.contains(&family.as_str())
+    }
+}
+
+/// Split a MIME type into its lowercase essence and `codecs` list
+fn parse_mime(mime: &str) -> (String, Vec<String>) {
+    let mut parts = mime.split(';');
+    let essence = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+    let codecs = parts
+        .filter_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim()
+                .eq_ignore_ascii_case("codecs")
+                .then(|| value.trim().trim_matches('"'))
+        })
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|codec| !codec.is_empty())
+        .map(str::to_string)
+        .collect();
+    (essence, codecs)
+}
+
+/// Creates demuxers for data or MIME types of unknown format
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_format_parsers::DemuxerFactory;
+///
+/// assert!(DemuxerFactory::create_for_mime("audio/webm; codecs=opus").is_ok());
+/// assert!(DemuxerFactory::create_for_data(b"not media").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DemuxerFactory;
+
+impl DemuxerFactory {
+    /// Create a demuxer for data by probing its first bytes
+    ///
+    /// A few kilobytes from the start of the file are enough; the data may
+    /// be truncated anywhere.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Box<dyn Demuxer>)` - A demuxer for the best-scoring format
+    /// * `Err(MediaError)` - `UnsupportedFormat` if no format matches
+    pub fn create_for_data(data: &[u8]) -> Result<Box<dyn Demuxer + Send>, MediaError> {
+        ContainerFormat::probe(data)
+            .map(ContainerFormat::create_demuxer)
+            .ok_or_else(|| MediaError::UnsupportedFormat {
+                format: "Unrecognized container format".to_string(),
+            })
+    }
+
+    /// Create a demuxer for a MIME type, see [`ContainerFormat::from_mime`]
+    pub fn create_for_mime(mime: &str) -> Result<Box<dyn Demuxer + Send>, MediaError> {
+        ContainerFormat::from_mime(mime).map(ContainerFormat::create_demuxer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mime() {
+        let (essence, codecs) = parse_mime(r#"Video/MP4 ; codecs="avc1.64001f, mp4a.40.2""#);
+        assert_eq!(essence, "video/mp4");
+        assert_eq!(codecs, vec!["avc1.64001f", "mp4a.40.2"]);
+
+        let (essence, codecs) = parse_mime("audio/ogg");
+        assert_eq!(essence, "audio/ogg");
+        assert!(codecs.is_empty());
+    }
+}
@@ -21,20 +21,72 @@
 //! println!("Video tracks: {}", info.video_tracks.len());
 //! println!("Audio tracks: {}", info.audio_tracks.len());
 //! ```
+//!
+//! Reading compressed packets:
+//!
+//! ```no_run
+//! use cortenbrowser_format_parsers::{Mp4Demuxer, Demuxer};
+//!
+//! let mut demuxer = Mp4Demuxer::new();
+//! let data = std::fs::read("video.mp4").unwrap();
+//! demuxer.load(&data).unwrap();
+//!
+//! while let Some(packet) = demuxer.read_packet().unwrap() {
+//!     println!("Track {}: {} bytes", packet.track_id, packet.data().len());
+//! }
+//! ```
+//!
+//! When the format is not known up front, [`DemuxerFactory`] picks a demuxer
+//! from the first bytes of the data or from a MIME type:
+//!
+//! ```no_run
+//! use cortenbrowser_format_parsers::DemuxerFactory;
+//!
+//! let data = std::fs::read("media.bin").unwrap();
+//! let mut demuxer = DemuxerFactory::create_for_data(&data)
+//!     .or_else(|_| DemuxerFactory::create_for_mime("video/webm; codecs=vp9"))
+//!     .unwrap();
+//! let info = demuxer.load(&data).unwrap();
+//! ```
+//!
+//! Data can also be fed incrementally, e.g. as it arrives from the network:
+//!
+//! ```no_run
+//! use cortenbrowser_format_parsers::{Demuxer, WebmDemuxer};
+//!
+//! # fn chunks() -> Vec<Vec<u8>> { Vec::new() }
+//! let mut demuxer = WebmDemuxer::new();
+//! for chunk in chunks() {
+//!     demuxer.feed(&chunk).unwrap();
+//!     while let Some(packet) = demuxer.next_packet().unwrap() {
+//!         println!("Track {}: {:?}", packet.track_id, packet.pts_time());
+//!     }
+//! }
+//! demuxer.end_of_stream();
+//! ```
 
 #![warn(missing_docs)]
 
 mod demuxer;
+mod ebml;
+mod factory;
 mod matroska;
+mod mkv;
 mod mp4;
+mod mp4_fragment;
+mod mp4_sample_entry;
 mod ogg;
+mod ogg_page;
 mod types;
 mod webm;
 
 // Re-export public API
-pub use demuxer::Demuxer;
+pub use demuxer::{Demuxer, PROBE_SCORE_MAX};
+pub use factory::{ContainerFormat, DemuxerFactory};
 pub use matroska::MatroskaDemuxer;
 pub use mp4::Mp4Demuxer;
 pub use ogg::OggDemuxer;
-pub use types::{AudioTrackInfo, MediaInfo, VideoTrackInfo};
+pub use types::{
+    AudioTrackInfo, DemuxedPacket, MediaInfo, Packet, SeekIndex, SeekPoint, VideoTrackInfo,
+};
 pub use webm::WebmDemuxer;
@@ -1,45 +1,53 @@
 //! Matroska (MKV) container format demuxer
 
-use crate::demuxer::Demuxer;
-use crate::types::{AudioTrackInfo, MediaInfo, VideoTrackInfo};
+use crate::demuxer::{Demuxer, PROBE_SCORE_MAX};
+use crate::mkv::{self, DocTypeProbe, MkvReader};
+use crate::types::{AudioTrackInfo, DemuxedPacket, MediaInfo, SeekIndex, VideoTrackInfo};
 use cortenbrowser_shared_types::MediaError;
-use std::collections::HashMap;
 use std::time::Duration;
 
 /// Matroska (MKV) container demuxer
 ///
-/// Parses Matroska container format and extracts media information.
+/// Parses Matroska container format and extracts media
+/// information and compressed packets.
+///
+/// Besides [`Demuxer::load`], data can be fed incrementally with
+/// [`Demuxer::feed`]. [`Demuxer::next_packet`] then returns
+/// `Ok(None)` whenever the next packet is not complete yet, and reading
+/// resumes once more data is fed.
+///
+/// The [`SeekIndex`] comes from the Cues, which [`Demuxer::load`] finds
+/// anywhere in the data and fed data provides once they are reached. Files
+/// without Cues have no seek index and cannot [`Demuxer::seek`].
+///
+/// Packet timestamps are in nanoseconds (`timescale` 1,000,000,000).
 #[derive(Debug, Default)]
 pub struct MatroskaDemuxer {
     media_info: Option<MediaInfo>,
+    reader: Option<MkvReader>,
 }
 
 impl Demuxer for MatroskaDemuxer {
     fn new() -> Self {
-        Self { media_info: None }
-    }
-
-    fn parse(&self, data: &[u8]) -> Result<MediaInfo, MediaError> {
-        if data.is_empty() {
-            return Err(MediaError::UnsupportedFormat {
-                format: "Empty data".to_string(),
-            });
+        Self {
+            media_info: None,
+            reader: None,
         }
+    }
 
-        // Basic Matroska validation - check for EBML header (same as WebM)
-        if data.len() < 4 || &data[0..4] != b"\x1a\x45\xdf\xa3" {
-            return Err(MediaError::UnsupportedFormat {
-                format: "Invalid Matroska data".to_string(),
-            });
+    fn probe(data: &[u8]) -> u8 {
+        match mkv::probe_doc_type(data) {
+            DocTypeProbe::DocType(doc_type) if doc_type == "matroska" => PROBE_SCORE_MAX,
+            // WebM is a Matroska subset, though WebmDemuxer is the better match
+            DocTypeProbe::DocType(doc_type) if doc_type == "webm" => PROBE_SCORE_MAX / 2,
+            // Without a DocType, only Matroska is sure to fit
+            DocTypeProbe::Unknown => PROBE_SCORE_MAX / 2,
+            _ => 0,
         }
+    }
 
-        // Simplified implementation - returns valid but minimal MediaInfo
-        Ok(MediaInfo {
-            duration: Duration::ZERO,
-            video_tracks: Vec::new(),
-            audio_tracks: Vec::new(),
-            metadata: HashMap::new(),
-        })
+    fn parse(&self, data: &[u8]) -> Result<MediaInfo, MediaError> {
+        read_header(data).map(|(_, info)| info)
     }
 
     fn get_video_track(&self, track_id: u32) -> Option<VideoTrackInfo> {
@@ -59,4 +67,107 @@ impl Demuxer for MatroskaDemuxer {
             .find(|t| t.track_id == track_id)
             .cloned()
     }
+
+    fn load(&mut self, data: &[u8]) -> Result<MediaInfo, MediaError> {
+        let (mut reader, info) = read_header(data)?;
+        reader.load_cues(data)?;
+        self.reader = Some(reader);
+        self.media_info = Some(info.clone());
+        Ok(info)
+    }
+
+    fn read_packet(&mut self) -> Result<Option<DemuxedPacket>, MediaError> {
+        self.reader_mut()?.read_packet()
+    }
+
+    fn next_sample(&mut self, track_id: u32) -> Result<Option<DemuxedPacket>, MediaError> {
+        self.update_media_info()?;
+        let reader = self.reader_mut()?;
+        if reader.tracks_known() && !reader.has_track(track_id) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Unknown track: {}",
+                track_id
+            )));
+        }
+        reader.read_track_packet(track_id)
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), MediaError> {
+        let reader = self
+            .reader
+            .get_or_insert_with(|| MkvReader::new("Matroska"));
+        if reader.is_ended() {
+            return Err(MediaError::InvalidState(
+                "Matroska stream has already ended".to_string(),
+            ));
+        }
+        reader.push(chunk);
+        self.update_media_info()
+    }
+
+    fn end_of_stream(&mut self) {
+        if let Some(reader) = &mut self.reader {
+            reader.end();
+        }
+    }
+
+    fn media_info(&self) -> Option<&MediaInfo> {
+        self.media_info.as_ref()
+    }
+
+    fn seek_index(&self) -> Option<&SeekIndex> {
+        self.reader.as_ref()?.seek_index()
+    }
+
+    fn seek(&mut self, time: Duration) -> Result<u64, MediaError> {
+        self.reader_mut()?.seek(time)
+    }
+}
+
+impl MatroskaDemuxer {
+    /// Returns the CodecPrivate data of a track
+    ///
+    /// For Vorbis this holds the three Xiph-laced setup headers; for Opus
+    /// the `OpusHead` structure.
+    pub fn codec_private(&self, track_id: u32) -> Option<&[u8]> {
+        self.reader.as_ref()?.codec_private(track_id)
+    }
+
+    /// Returns whether the stream has ended and every packet has been read
+    pub fn is_finished(&self) -> bool {
+        self.reader.as_ref().is_some_and(MkvReader::is_finished)
+    }
+
+    fn reader_mut(&mut self) -> Result<&mut MkvReader, MediaError> {
+        self.reader
+            .as_mut()
+            .ok_or_else(|| MediaError::InvalidState("No Matroska data loaded".to_string()))
+    }
+
+    fn update_media_info(&mut self) -> Result<(), MediaError> {
+        if self.media_info.is_none() {
+            if let Some(reader) = &mut self.reader {
+                if reader.tracks_known() || !reader.is_ended() {
+                    self.media_info = reader.media_info()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse complete Matroska data up to the track list
+fn read_header(data: &[u8]) -> Result<(MkvReader, MediaInfo), MediaError> {
+    if data.is_empty() {
+        return Err(MediaError::UnsupportedFormat {
+            format: "Empty data".to_string(),
+        });
+    }
+
+    let mut reader = MkvReader::new("Matroska");
+    reader.push(data);
+    reader.end();
+    // media_info only returns None for open streams
+    let info = reader.media_info()?.unwrap_or_default();
+    Ok((reader, info))
 }
@@ -0,0 +1,1142 @@
+//! Incremental Matroska/WebM block reader
+//!
+//! Shared by [`WebmDemuxer`](crate::WebmDemuxer) and
+//! [`MatroskaDemuxer`](crate::MatroskaDemuxer). Data is pushed in arbitrary
+//! chunks; elements are parsed lazily as packets are requested, and parsing
+//! pauses whenever an element is incomplete until more data arrives.
+//!
+//! # Structure
+//!
+//! ```text
+//! EBML header (DocType "webm" / "matroska")
+//! Segment
+//! ├── Info       TimecodeScale, Duration
+//! ├── Tracks     TrackEntry: TrackNumber, TrackType, CodecID, CodecPrivate, ...
+//! ├── Cluster    Timecode
+//! │   ├── SimpleBlock
+//! │   └── BlockGroup: Block, ReferenceBlock, BlockDuration
+//! ├── Cluster ...
+//! └── Cues, Tags, ... (Cues are indexed, the rest skipped)
+//! ```
+//!
+//! Info, Tracks and Cues are buffered whole. Segments and Clusters are entered
+//! without buffering, so unknown-size (live) Segments and Clusters work.
+//!
+//! # Timestamps
+//!
+//! A block's timestamp is `(cluster Timecode + block relative timecode) *
+//! TimecodeScale` nanoseconds. Packets are emitted with a timescale of
+//! 1,000,000,000 (nanoseconds). Laced frames after the first are offset by
+//! the track's DefaultDuration when present.
+//!
+//! # Seeking
+//!
+//! CuePoints map a CueTime to the position of a Cluster relative to the
+//! start of the Segment's data. They are turned into a [`SeekIndex`] with
+//! file offsets, either when the reader reaches the Cues or, for complete
+//! data, by [`MkvReader::load_cues`]. Seeking resets the reader to expect
+//! data from the start of a Cluster.
+
+use crate::ebml::{self, ElementHeader};
+use crate::types::{
+    AudioTrackInfo, DemuxedPacket, MediaInfo, Packet, SeekIndex, SeekPoint, VideoTrackInfo,
+};
+use cortenbrowser_shared_types::{
+    AACProfile, AV1Level, AV1Profile, AudioCodec, AudioPacket, ColorInfo, H264Level, H264Profile,
+    MediaError, OpusApplication, VP9Profile, VideoCodec, VideoPacket,
+};
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+/// Encoded ID of the EBML header, which starts every file
+const EBML_MAGIC: [u8; 4] = [0x1A, 0x45, 0xDF, 0xA3];
+
+// Element IDs
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x1853_8067;
+const INFO: u32 = 0x1549_A966;
+const TIMECODE_SCALE: u32 = 0x2A_D7B1;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_NUMBER: u32 = 0xD7;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const CODEC_PRIVATE: u32 = 0x63A2;
+const DEFAULT_DURATION: u32 = 0x23_E383;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const COLOUR: u32 = 0x55B0;
+const MATRIX_COEFFICIENTS: u32 = 0x55B1;
+const RANGE: u32 = 0x55B9;
+const TRANSFER_CHARACTERISTICS: u32 = 0x55BA;
+const PRIMARIES: u32 = 0x55BB;
+const AUDIO: u32 = 0xE1;
+const SAMPLING_FREQUENCY: u32 = 0xB5;
+const CHANNELS: u32 = 0x9F;
+const CLUSTER: u32 = 0x1F43_B675;
+const TIMECODE: u32 = 0xE7;
+const SILENT_TRACKS: u32 = 0x5854;
+const POSITION: u32 = 0xA7;
+const PREV_SIZE: u32 = 0xAB;
+const SIMPLE_BLOCK: u32 = 0xA3;
+const BLOCK_GROUP: u32 = 0xA0;
+const BLOCK: u32 = 0xA1;
+const REFERENCE_BLOCK: u32 = 0xFB;
+const VOID: u32 = 0xEC;
+const CUES: u32 = 0x1C53_BB6B;
+const CUE_POINT: u32 = 0xBB;
+const CUE_TIME: u32 = 0xB3;
+const CUE_TRACK_POSITIONS: u32 = 0xB7;
+const CUE_TRACK: u32 = 0xF7;
+const CUE_CLUSTER_POSITION: u32 = 0xF1;
+const CUE_BLOCK_NUMBER: u32 = 0x5378;
+const CRC32: u32 = 0xBF;
+
+/// TrackType values
+const TRACK_TYPE_VIDEO: u64 = 1;
+const TRACK_TYPE_AUDIO: u64 = 2;
+
+/// Default TimecodeScale: 1 ms
+const DEFAULT_TIMECODE_SCALE: u64 = 1_000_000;
+
+/// Packet timestamps are in nanoseconds
+const NANOS_PER_SECOND: u32 = 1_000_000_000;
+
+/// Largest element buffered whole (Info, Tracks, blocks)
+const MAX_ELEMENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// SimpleBlock keyframe flag
+const FLAG_KEYFRAME: u8 = 0x80;
+
+/// Block lacing bits
+const LACING_MASK: u8 = 0x06;
+const LACING_XIPH: u8 = 0x02;
+const LACING_FIXED: u8 = 0x04;
+const LACING_EBML: u8 = 0x06;
+
+/// Whether a track carries video or audio
+#[derive(Debug, Clone, PartialEq)]
+enum TrackCodec {
+    Video(VideoCodec),
+    Audio(AudioCodec),
+}
+
+/// A parsed TrackEntry
+#[derive(Debug, Clone)]
+struct TrackEntry {
+    number: u64,
+    codec: TrackCodec,
+    codec_private: Vec<u8>,
+    default_duration: Option<u64>,
+    width: u32,
+    height: u32,
+    color: Option<ColorInfo>,
+    sample_rate: u32,
+    channels: u8,
+}
+
+/// Where the reader is in the element tree
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Expecting the EBML header
+    Header,
+    /// Expecting the Segment element
+    BeforeSegment,
+    /// Reading Segment children
+    Segment,
+    /// Reading Cluster children; `remaining` is `None` for unknown size
+    Cluster { remaining: Option<u64> },
+}
+
+/// Incremental Matroska/WebM reader
+#[derive(Debug)]
+pub(crate) struct MkvReader {
+    format: &'static str,
+    buffer: Vec<u8>,
+    /// File offset of `buffer[0]`
+    offset: u64,
+    pos: usize,
+    skip: u64,
+    ended: bool,
+    state: State,
+    timecode_scale: u64,
+    duration: Option<f64>,
+    tracks: Option<Vec<TrackEntry>>,
+    cluster_timecode: i64,
+    packets: VecDeque<DemuxedPacket>,
+    /// File offset of the Segment's data, which Cues positions are relative to
+    segment_start: Option<u64>,
+    seek_index: Option<SeekIndex>,
+}
+
+impl MkvReader {
+    /// Create a reader; `format` names the container in error messages
+    pub(crate) fn new(format: &'static str) -> Self {
+        Self {
+            format,
+            buffer: Vec::new(),
+            offset: 0,
+            pos: 0,
+            skip: 0,
+            ended: false,
+            state: State::Header,
+            timecode_scale: DEFAULT_TIMECODE_SCALE,
+            duration: None,
+            tracks: None,
+            cluster_timecode: 0,
+            packets: VecDeque::new(),
+            segment_start: None,
+            seek_index: None,
+        }
+    }
+
+    /// Append data to the reader
+    pub(crate) fn push(&mut self, data: &[u8]) {
+        let skipped = self.skip.min(data.len() as u64) as usize;
+        self.skip -= skipped as u64;
+        self.offset += skipped as u64;
+        self.buffer.extend_from_slice(&data[skipped..]);
+    }
+
+    /// Mark that no more data will be pushed
+    pub(crate) fn end(&mut self) {
+        self.ended = true;
+    }
+
+    /// Whether [`MkvReader::end`] has been called
+    pub(crate) fn is_ended(&self) -> bool {
+        self.ended
+    }
+
+    /// Whether the Tracks element has been parsed
+    pub(crate) fn tracks_known(&self) -> bool {
+        self.tracks.is_some()
+    }
+
+    /// Whether the stream has ended and every packet has been read
+    pub(crate) fn is_finished(&self) -> bool {
+        self.ended && self.packets.is_empty() && self.pos >= self.buffer.len()
+    }
+
+    /// Parse until the track list is known
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(info))` - Tracks have been parsed
+    /// * `Ok(None)` - More data is needed
+    /// * `Err(MediaError)` - Malformed data, or the stream ended without tracks
+    pub(crate) fn media_info(&mut self) -> Result<Option<MediaInfo>, MediaError> {
+        while self.tracks.is_none() && self.step()? {}
+
+        match &self.tracks {
+            Some(_) => Ok(Some(self.build_media_info())),
+            None if self.ended => Err(MediaError::UnsupportedFormat {
+                format: format!("{} data has no Tracks element", self.format),
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// CodecPrivate data of a track, if the track exists and has any
+    pub(crate) fn codec_private(&self, track_id: u32) -> Option<&[u8]> {
+        self.tracks
+            .as_ref()?
+            .iter()
+            .find(|t| t.number == u64::from(track_id))
+            .map(|t| t.codec_private.as_slice())
+            .filter(|data| !data.is_empty())
+    }
+
+    /// Read the next packet in file order
+    ///
+    /// Returns `Ok(None)` if no complete packet is available yet.
+    pub(crate) fn read_packet(&mut self) -> Result<Option<DemuxedPacket>, MediaError> {
+        while self.packets.is_empty() && self.step()? {}
+        Ok(self.packets.pop_front())
+    }
+
+    /// Read the next packet of one track, queueing other tracks' packets
+    ///
+    /// Returns `Ok(None)` if no complete packet of the track is available yet.
+    pub(crate) fn read_track_packet(
+        &mut self,
+        track_id: u32,
+    ) -> Result<Option<DemuxedPacket>, MediaError> {
+        loop {
+            if let Some(index) = self.packets.iter().position(|p| p.track_id == track_id) {
+                return Ok(self.packets.remove(index));
+            }
+            if !self.step()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Whether a track with this number exists
+    pub(crate) fn has_track(&self, track_id: u32) -> bool {
+        self.tracks
+            .as_ref()
+            .is_some_and(|tracks| tracks.iter().any(|t| t.number == u64::from(track_id)))
+    }
+
+    /// Keyframe index from the Cues, once they have been read
+    pub(crate) fn seek_index(&self) -> Option<&SeekIndex> {
+        self.seek_index.as_ref()
+    }
+
+    /// Index the Cues of complete data without reading up to them
+    ///
+    /// Data without Cues, or with Cues hidden behind an unknown-size
+    /// element, leaves the reader without a seek index.
+    pub(crate) fn load_cues(&mut self, data: &[u8]) -> Result<(), MediaError> {
+        if let Some((segment_start, cues)) = find_cues(data)? {
+            self.segment_start = Some(segment_start);
+            self.seek_index = Some(self.cue_index(cues)?);
+        }
+        Ok(())
+    }
+
+    /// Reset the reader to the Cluster of the latest cue at or before `time`
+    ///
+    /// Buffered data and packets are dropped; data must be pushed again
+    /// from the returned file offset.
+    pub(crate) fn seek(&mut self, time: Duration) -> Result<u64, MediaError> {
+        let index = self
+            .seek_index
+            .as_ref()
+            .ok_or_else(|| MediaError::InvalidState(format!("{} data has no Cues", self.format)))?;
+        let offset = index
+            .byte_offset_for_time(time)
+            .or_else(|| {
+                // Before the first cue of every track
+                index
+                    .track_ids()
+                    .filter_map(|track_id| index.entries(track_id).first())
+                    .map(|point| point.byte_offset)
+                    .min()
+            })
+            .ok_or_else(|| MediaError::InvalidState(format!("{} Cues are empty", self.format)))?;
+
+        self.buffer.clear();
+        self.offset = offset;
+        self.pos = 0;
+        self.skip = 0;
+        self.ended = false;
+        self.state = State::Segment;
+        self.cluster_timecode = 0;
+        self.packets.clear();
+        Ok(offset)
+    }
+
+    fn malformed(&self, details: &str) -> MediaError {
+        MediaError::CodecError {
+            details: format!("Malformed {} data: {}", self.format, details),
+        }
+    }
+
+    /// Process one element
+    ///
+    /// Returns `Ok(false)` if no progress can be made without more data.
+    fn step(&mut self) -> Result<bool, MediaError> {
+        // Drop consumed bytes once they dominate the buffer
+        if self.pos > 0 && self.pos * 2 >= self.buffer.len() {
+            self.buffer.drain(..self.pos);
+            self.offset += self.pos as u64;
+            self.pos = 0;
+        }
+
+        let available = &self.buffer[self.pos..];
+        if self.state == State::Header {
+            let len = available.len().min(EBML_MAGIC.len());
+            if available[..len] != EBML_MAGIC[..len] {
+                return Err(MediaError::UnsupportedFormat {
+                    format: format!("Invalid {} data", self.format),
+                });
+            }
+        }
+
+        let header = match ebml::read_element_header(available)? {
+            Some(header) => header,
+            None => {
+                if self.ended && !available.is_empty() {
+                    return Err(self.malformed("truncated element header"));
+                }
+                return Ok(false);
+            }
+        };
+
+        match self.state {
+            State::Header => self.read_ebml_header(header),
+            State::BeforeSegment => self.read_before_segment(header),
+            State::Segment => self.read_segment_child(header),
+            State::Cluster { remaining } => self.read_cluster_child(header, remaining),
+        }
+    }
+
+    /// Payload of a complete, sized element at the read position
+    ///
+    /// Returns `Ok(None)` if the element is not fully buffered yet.
+    fn element_payload(&self, header: ElementHeader) -> Result<Option<(usize, usize)>, MediaError> {
+        let size = header
+            .size
+            .ok_or_else(|| self.malformed(&format!("element {:#X} has unknown size", header.id)))?;
+        if size > MAX_ELEMENT_SIZE {
+            return Err(self.malformed(&format!("element {:#X} is too large", header.id)));
+        }
+
+        let start = self.pos + header.header_len;
+        let end = start + size as usize;
+        if end > self.buffer.len() {
+            if self.ended {
+                return Err(self.malformed(&format!("truncated element {:#X}", header.id)));
+            }
+            return Ok(None);
+        }
+        Ok(Some((start, end)))
+    }
+
+    /// Skip over an element, including data not yet pushed
+    fn skip_element(&mut self, header: ElementHeader) -> Result<bool, MediaError> {
+        let size = header
+            .size
+            .ok_or_else(|| self.malformed(&format!("element {:#X} has unknown size", header.id)))?;
+        let total = header.header_len as u64 + size;
+        let available = (self.buffer.len() - self.pos) as u64;
+
+        if total <= available {
+            self.pos += total as usize;
+        } else {
+            self.skip = total - available;
+            self.offset += self.buffer.len() as u64;
+            self.buffer.clear();
+            self.pos = 0;
+        }
+        Ok(true)
+    }
+
+    fn read_ebml_header(&mut self, header: ElementHeader) -> Result<bool, MediaError> {
+        let Some((start, end)) = self.element_payload(header)? else {
+            return Ok(false);
+        };
+
+        for child in ebml::children(&self.buffer[start..end]) {
+            let (id, payload) = child?;
+            if id == DOC_TYPE {
+                let doc_type = ebml::read_string(payload);
+                if doc_type != "webm" && doc_type != "matroska" {
+                    return Err(MediaError::UnsupportedFormat {
+                        format: format!("Unsupported EBML DocType: {}", doc_type),
+                    });
+                }
+            }
+        }
+
+        self.pos = end;
+        self.state = State::BeforeSegment;
+        Ok(true)
+    }
+
+    fn read_before_segment(&mut self, header: ElementHeader) -> Result<bool, MediaError> {
+        if header.id == SEGMENT {
+            // Enter the Segment; its size is not needed
+            self.pos += header.header_len;
+            self.segment_start = Some(self.offset + self.pos as u64);
+            self.state = State::Segment;
+            Ok(true)
+        } else {
+            self.skip_element(header)
+        }
+    }
+
+    fn read_segment_child(&mut self, header: ElementHeader) -> Result<bool, MediaError> {
+        match header.id {
+            CLUSTER => {
+                self.pos += header.header_len;
+                self.cluster_timecode = 0;
+                self.state = State::Cluster {
+                    remaining: header.size,
+                };
+                Ok(true)
+            }
+            INFO => {
+                let Some((start, end)) = self.element_payload(header)? else {
+                    return Ok(false);
+                };
+                self.parse_info(start, end)?;
+                self.pos = end;
+                Ok(true)
+            }
+            TRACKS => {
+                let Some((start, end)) = self.element_payload(header)? else {
+                    return Ok(false);
+                };
+                self.parse_tracks(start, end)?;
+                self.pos = end;
+                Ok(true)
+            }
+            CUES if self.seek_index.is_none() => {
+                let Some((start, end)) = self.element_payload(header)? else {
+                    return Ok(false);
+                };
+                self.seek_index = Some(self.cue_index(&self.buffer[start..end])?);
+                self.pos = end;
+                Ok(true)
+            }
+            _ => self.skip_element(header),
+        }
+    }
+
+    fn read_cluster_child(
+        &mut self,
+        header: ElementHeader,
+        remaining: Option<u64>,
+    ) -> Result<bool, MediaError> {
+        let is_child = matches!(
+            header.id,
+            TIMECODE
+                | SILENT_TRACKS
+                | POSITION
+                | PREV_SIZE
+                | SIMPLE_BLOCK
+                | BLOCK_GROUP
+                | VOID
+                | CRC32
+        );
+
+        // An unknown-size Cluster ends at the first element that cannot be
+        // one of its children
+        if remaining.is_none() && !is_child {
+            self.state = State::Segment;
+            return Ok(true);
+        }
+
+        let total = header.header_len as u64 + header.size.unwrap_or(0);
+        let next_state = match remaining {
+            Some(remaining) if total > remaining => {
+                return Err(self.malformed("cluster child exceeds its cluster"));
+            }
+            Some(remaining) if total == remaining => State::Segment,
+            Some(remaining) => State::Cluster {
+                remaining: Some(remaining - total),
+            },
+            None => State::Cluster { remaining: None },
+        };
+
+        match header.id {
+            TIMECODE | SIMPLE_BLOCK | BLOCK_GROUP => {
+                let Some((start, end)) = self.element_payload(header)? else {
+                    return Ok(false);
+                };
+                match header.id {
+                    TIMECODE => {
+                        self.cluster_timecode = ebml::read_uint(&self.buffer[start..end])? as i64;
+                    }
+                    SIMPLE_BLOCK => {
+                        let block = self.buffer[start..end].to_vec();
+                        let keyframe = block_flags(&block).map(|f| f & FLAG_KEYFRAME != 0);
+                        self.parse_block(&block, keyframe.unwrap_or(false))?;
+                    }
+                    _ => {
+                        let group = self.buffer[start..end].to_vec();
+                        self.parse_block_group(&group)?;
+                    }
+                }
+                self.pos = end;
+            }
+            _ => {
+                self.skip_element(header)?;
+            }
+        }
+
+        self.state = next_state;
+        Ok(true)
+    }
+
+    fn parse_info(&mut self, start: usize, end: usize) -> Result<(), MediaError> {
+        for child in ebml::children(&self.buffer[start..end]) {
+            let (id, payload) = child?;
+            match id {
+                TIMECODE_SCALE => {
+                    let scale = ebml::read_uint(payload)?;
+                    if scale == 0 {
+                        return Err(self.malformed("TimecodeScale is zero"));
+                    }
+                    self.timecode_scale = scale;
+                }
+                DURATION => self.duration = Some(ebml::read_float(payload)?),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_tracks(&mut self, start: usize, end: usize) -> Result<(), MediaError> {
+        let mut tracks = Vec::new();
+        for child in ebml::children(&self.buffer[start..end]) {
+            let (id, payload) = child?;
+            if id == TRACK_ENTRY {
+                if let Some(track) = parse_track_entry(payload)? {
+                    tracks.push(track);
+                }
+            }
+        }
+        self.tracks = Some(tracks);
+        Ok(())
+    }
+
+    fn parse_block_group(&mut self, group: &[u8]) -> Result<(), MediaError> {
+        let mut block = None;
+        let mut has_reference = false;
+        for child in ebml::children(group) {
+            let (id, payload) = child?;
+            match id {
+                BLOCK => block = Some(payload),
+                REFERENCE_BLOCK => has_reference = true,
+                _ => {}
+            }
+        }
+
+        let block = block.ok_or_else(|| self.malformed("BlockGroup without Block"))?;
+        self.parse_block(block, !has_reference)
+    }
+
+    /// Split a Block/SimpleBlock into packets
+    fn parse_block(&mut self, block: &[u8], keyframe: bool) -> Result<(), MediaError> {
+        let (track_number, track_len) =
+            ebml::read_vint(block)?.ok_or_else(|| self.malformed("truncated block header"))?;
+        if block.len() < track_len + 3 {
+            return Err(self.malformed("truncated block header"));
+        }
+        let relative = i16::from_be_bytes([block[track_len], block[track_len + 1]]);
+        let flags = block[track_len + 2];
+        let body = &block[track_len + 3..];
+
+        let Some(track) = self
+            .tracks
+            .as_ref()
+            .and_then(|tracks| tracks.iter().find(|t| t.number == track_number))
+        else {
+            // Blocks of unsupported or unknown tracks are dropped
+            return Ok(());
+        };
+        let track_id = track.number as u32;
+        let codec = track.codec.clone();
+        let default_duration = track.default_duration.unwrap_or(0);
+
+        let frames = split_lacing(body, flags & LACING_MASK)
+            .ok_or_else(|| self.malformed("invalid block lacing"))?;
+
+        let timestamp = (self.cluster_timecode + i64::from(relative))
+            .saturating_mul(self.timecode_scale as i64);
+
+        for (i, frame) in frames.into_iter().enumerate() {
+            let pts = timestamp.saturating_add((i as u64 * default_duration) as i64);
+            // WebM codecs have no frame reordering, so decode order matches
+            // presentation order
+            let packet = match &codec {
+                TrackCodec::Video(_) => Packet::Video(VideoPacket {
+                    data: frame.to_vec(),
+                    pts: Some(pts),
+                    dts: Some(pts),
+                    is_keyframe: keyframe,
+                    encryption: None,
+                }),
+                TrackCodec::Audio(_) => Packet::Audio(AudioPacket {
+                    data: frame.to_vec(),
+                    pts: Some(pts),
+                    dts: Some(pts),
+                    is_last: false,
+                    encryption: None,
+                }),
+            };
+            self.packets.push_back(DemuxedPacket {
+                track_id,
+                timescale: NANOS_PER_SECOND,
+                packet,
+            });
+        }
+        Ok(())
+    }
+
+    /// Build a seek index from a Cues payload
+    ///
+    /// Positions of tracks that are not exposed are left out once the
+    /// tracks are known.
+    fn cue_index(&self, cues: &[u8]) -> Result<SeekIndex, MediaError> {
+        let segment_start = self.segment_start.unwrap_or(0);
+        let mut index = SeekIndex::new();
+
+        for cue_point in ebml::children(cues) {
+            let (id, payload) = cue_point?;
+            if id != CUE_POINT {
+                continue;
+            }
+
+            let mut time = None;
+            let mut positions = Vec::new();
+            for child in ebml::children(payload) {
+                let (id, payload) = child?;
+                match id {
+                    CUE_TIME => time = Some(ebml::read_uint(payload)?),
+                    CUE_TRACK_POSITIONS => positions.push(payload),
+                    _ => {}
+                }
+            }
+            let time = time.ok_or_else(|| self.malformed("CuePoint without CueTime"))?;
+            let time = Duration::from_nanos(time.saturating_mul(self.timecode_scale));
+
+            for position in positions {
+                let (mut track, mut cluster, mut block) = (None, None, None);
+                for child in ebml::children(position) {
+                    let (id, payload) = child?;
+                    match id {
+                        CUE_TRACK => track = Some(ebml::read_uint(payload)?),
+                        CUE_CLUSTER_POSITION => cluster = Some(ebml::read_uint(payload)?),
+                        CUE_BLOCK_NUMBER => block = Some(ebml::read_uint(payload)?),
+                        _ => {}
+                    }
+                }
+                let (Some(track), Some(cluster)) = (track, cluster) else {
+                    return Err(self.malformed("CueTrackPositions without track or position"));
+                };
+                let Ok(track_id) = u32::try_from(track) else {
+                    continue;
+                };
+                if self.tracks.is_some() && !self.has_track(track_id) {
+                    continue;
+                }
+
+                index.insert(
+                    track_id,
+                    SeekPoint {
+                        time,
+                        byte_offset: segment_start.saturating_add(cluster),
+                        sample_number: block,
+                    },
+                );
+            }
+        }
+        Ok(index)
+    }
+
+    fn build_media_info(&self) -> MediaInfo {
+        let mut info = MediaInfo {
+            duration: self
+                .duration
+                .map(|d| Duration::from_nanos((d * self.timecode_scale as f64) as u64))
+                .unwrap_or(Duration::ZERO),
+            video_tracks: Vec::new(),
+            audio_tracks: Vec::new(),
+            metadata: HashMap::new(),
+        };
+
+        for track in self.tracks.iter().flatten() {
+            match &track.codec {
+                TrackCodec::Video(codec) => info.video_tracks.push(VideoTrackInfo {
+                    track_id: track.number as u32,
+                    codec: codec.clone(),
+                    width: track.width,
+                    height: track.height,
+                    frame_rate: track
+                        .default_duration
+                        .filter(|&d| d > 0)
+                        .map(|d| 1e9 / d as f32)
+                        .unwrap_or(0.0),
+                    bitrate: None,
+                    extradata: track.codec_private.clone(),
+                    color: track.color,
+                }),
+                TrackCodec::Audio(codec) => info.audio_tracks.push(AudioTrackInfo {
+                    track_id: track.number as u32,
+                    codec: codec.clone(),
+                    sample_rate: track.sample_rate,
+                    channels: track.channels,
+                    bitrate: None,
+                    extradata: track.codec_private.clone(),
+                    gapless: None,
+                }),
+            }
+        }
+
+        info
+    }
+}
+
+/// Outcome of looking for the DocType at the start of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DocTypeProbe {
+    /// The data does not start with an EBML header
+    NotEbml,
+    /// An EBML header whose DocType lies beyond the probed data
+    Unknown,
+    /// The DocType of the EBML header
+    DocType(String),
+}
+
+/// Find the DocType in the EBML header at the start of possibly truncated data
+pub(crate) fn probe_doc_type(data: &[u8]) -> DocTypeProbe {
+    if !data.starts_with(&EBML_MAGIC) {
+        return DocTypeProbe::NotEbml;
+    }
+    let Ok(Some(header)) = ebml::read_element_header(data) else {
+        return DocTypeProbe::Unknown;
+    };
+    let end = header.size.map_or(data.len(), |size| {
+        (header.header_len as u64)
+            .saturating_add(size)
+            .min(data.len() as u64) as usize
+    });
+
+    ebml::children(&data[header.header_len..end])
+        .map_while(Result::ok)
+        .find(|&(id, _)| id == DOC_TYPE)
+        .map_or(DocTypeProbe::Unknown, |(_, payload)| {
+            DocTypeProbe::DocType(ebml::read_string(payload))
+        })
+}
+
+/// Find the Cues of complete data by walking the Segment's children
+///
+/// # Returns
+///
+/// The file offset of the Segment's data and the Cues payload, or `None` if
+/// the data has no Cues or they lie behind an unknown-size element
+fn find_cues(data: &[u8]) -> Result<Option<(u64, &[u8])>, MediaError> {
+    let mut pos = 0;
+    let mut segment_start = None;
+
+    while let Some(header) = ebml::read_element_header(&data[pos..])? {
+        let start = pos + header.header_len;
+        if header.id == SEGMENT && segment_start.is_none() {
+            segment_start = Some(start);
+            pos = start;
+            continue;
+        }
+
+        let Some(end) = header
+            .size
+            .and_then(|size| usize::try_from(size).ok())
+            .and_then(|size| start.checked_add(size))
+            .filter(|&end| end <= data.len())
+        else {
+            return Ok(None);
+        };
+        if let (CUES, Some(segment_start)) = (header.id, segment_start) {
+            return Ok(Some((segment_start as u64, &data[start..end])));
+        }
+        pos = end;
+    }
+    Ok(None)
+}
+
+/// Flags byte of a block, if the header is complete
+fn block_flags(block: &[u8]) -> Option<u8> {
+    let (_, len) = ebml::read_vint(block).ok()??;
+    block.get(len + 2).copied()
+}
+
+/// Split a block body into frames according to its lacing mode
+fn split_lacing(body: &[u8], lacing: u8) -> Option<Vec<&[u8]>> {
+    if lacing == 0 {
+        return Some(vec![body]);
+    }
+
+    let count = *body.first()? as usize + 1;
+    let mut pos = 1;
+    let mut sizes = Vec::with_capacity(count);
+
+    match lacing {
+        LACING_XIPH => {
+            for _ in 0..count - 1 {
+                let mut size = 0usize;
+                loop {
+                    let byte = *body.get(pos)?;
+                    pos += 1;
+                    size += byte as usize;
+                    if byte != 0xFF {
+                        break;
+                    }
+                }
+                sizes.push(size);
+            }
+        }
+        LACING_EBML => {
+            if count > 1 {
+                let (first, len) = ebml::read_vint(&body[pos..]).ok()??;
+                pos += len;
+                sizes.push(first as usize);
+                for _ in 0..count - 2 {
+                    let (diff, len) = ebml::read_signed_vint(&body[pos..]).ok()??;
+                    pos += len;
+                    let previous = *sizes.last()? as i64;
+                    sizes.push(usize::try_from(previous + diff).ok()?);
+                }
+            }
+        }
+        LACING_FIXED => {
+            let data_len = body.len() - pos;
+            if !data_len.is_multiple_of(count) {
+                return None;
+            }
+            sizes = vec![data_len / count; count - 1];
+        }
+        _ => return None,
+    }
+
+    // The last frame takes the remaining bytes
+    let laced: usize = sizes.iter().sum();
+    let last = (body.len() - pos).checked_sub(laced)?;
+    sizes.push(last);
+
+    let mut frames = Vec::with_capacity(count);
+    for size in sizes {
+        frames.push(&body[pos..pos + size]);
+        pos += size;
+    }
+    Some(frames)
+}
+
+/// Parse a TrackEntry; returns `None` for tracks with unsupported codecs
+fn parse_track_entry(data: &[u8]) -> Result<Option<TrackEntry>, MediaError> {
+    let mut number = None;
+    let mut track_type = 0;
+    let mut codec_id = String::new();
+    let mut codec_private = Vec::new();
+    let mut default_duration = None;
+    let (mut width, mut height) = (0, 0);
+    let mut color = None;
+    let mut sample_rate = 8000.0;
+    let mut channels = 1;
+
+    for child in ebml::children(data) {
+        let (id, payload) = child?;
+        match id {
+            TRACK_NUMBER => number = Some(ebml::read_uint(payload)?),
+            TRACK_TYPE => track_type = ebml::read_uint(payload)?,
+            CODEC_ID => codec_id = ebml::read_string(payload),
+            CODEC_PRIVATE => codec_private = payload.to_vec(),
+            DEFAULT_DURATION => default_duration = Some(ebml::read_uint(payload)?),
+            VIDEO => {
+                for child in ebml::children(payload) {
+                    let (id, payload) = child?;
+                    match id {
+                        PIXEL_WIDTH => width = ebml::read_uint(payload)? as u32,
+                        PIXEL_HEIGHT => height = ebml::read_uint(payload)? as u32,
+                        COLOUR => color = Some(parse_colour(payload)?),
+                        _ => {}
+                    }
+                }
+            }
+            AUDIO => {
+                for child in ebml::children(payload) {
+                    let (id, payload) = child?;
+                    match id {
+                        SAMPLING_FREQUENCY => sample_rate = ebml::read_float(payload)?,
+                        CHANNELS => channels = ebml::read_uint(payload)?.min(u8::MAX as u64) as u8,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(number) = number else {
+        return Err(MediaError::CodecError {
+            details: "Malformed Matroska data: TrackEntry without TrackNumber".to_string(),
+        });
+    };
+    let sample_rate = sample_rate as u32;
+
+    let codec = match track_type {
+        TRACK_TYPE_VIDEO => video_codec(&codec_id, &codec_private).map(TrackCodec::Video),
+        TRACK_TYPE_AUDIO => audio_codec(&codec_id, sample_rate, channels).map(TrackCodec::Audio),
+        _ => None,
+    };
+
+    Ok(codec.map(|codec| TrackEntry {
+        number,
+        codec,
+        codec_private,
+        default_duration,
+        width,
+        height,
+        color,
+        sample_rate,
+        channels,
+    }))
+}
+
+/// Parse a Colour element
+///
+/// Missing code points default to 2 ("unspecified"), as in Matroska.
+fn parse_colour(data: &[u8]) -> Result<ColorInfo, MediaError> {
+    let mut color = ColorInfo {
+        primaries: 2,
+        transfer: 2,
+        matrix: 2,
+        full_range: false,
+    };
+    for child in ebml::children(data) {
+        let (id, payload) = child?;
+        let value = || -> Result<u8, MediaError> {
+            Ok(ebml::read_uint(payload)?.min(u8::MAX as u64) as u8)
+        };
+        match id {
+            PRIMARIES => color.primaries = value()?,
+            TRANSFER_CHARACTERISTICS => color.transfer = value()?,
+            MATRIX_COEFFICIENTS => color.matrix = value()?,
+            // 1 = broadcast range, 2 = full range
+            RANGE => color.full_range = value()? == 2,
+            _ => {}
+        }
+    }
+    Ok(color)
+}
+
+/// Map a Matroska video CodecID to a codec
+fn video_codec(codec_id: &str, codec_private: &[u8]) -> Option<VideoCodec> {
+    match codec_id {
+        "V_VP8" => Some(VideoCodec::VP8),
+        "V_VP9" => Some(VideoCodec::VP9 {
+            profile: vp9_profile(codec_private),
+        }),
+        "V_AV1" => {
+            // av1C: marker/version byte, then seq_profile (3 bits) and
+            // seq_level_idx_0 (5 bits)
+            let byte = codec_private.get(1).copied().unwrap_or(0);
+            let profile = match byte >> 5 {
+                1 => AV1Profile::High,
+                2 => AV1Profile::Professional,
+                _ => AV1Profile::Main,
+            };
+            let level = match byte & 0x1F {
+                idx if idx >= 13 => AV1Level::Level5_1,
+                12 => AV1Level::Level5_0,
+                idx if idx >= 9 => AV1Level::Level4_1,
+                _ => AV1Level::Level4_0,
+            };
+            Some(VideoCodec::AV1 { profile, level })
+        }
+        "V_MPEG4/ISO/AVC" => {
+            // avcC: version, profile_idc, constraints, level_idc
+            let profile = match codec_private.get(1) {
+                Some(66) => H264Profile::Baseline,
+                Some(77) => H264Profile::Main,
+                Some(110) => H264Profile::High10,
+                Some(122) => H264Profile::High422,
+                Some(244) => H264Profile::High444,
+                _ => H264Profile::High,
+            };
+            let level = match codec_private.get(3).copied().unwrap_or(41) {
+                l if l >= 51 => H264Level::Level5_1,
+                50 => H264Level::Level5_0,
+                l if l >= 41 => H264Level::Level4_1,
+                40 => H264Level::Level4_0,
+                l if l >= 31 => H264Level::Level3_1,
+                _ => H264Level::Level3_0,
+            };
+            Some(VideoCodec::H264 {
+                profile,
+                level,
+                hardware_accel: false,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Read the VP9 profile from CodecPrivate feature records (ID 1 = profile)
+fn vp9_profile(codec_private: &[u8]) -> VP9Profile {
+    let mut pos = 0;
+    while pos + 2 <= codec_private.len() {
+        let (id, len) = (codec_private[pos], codec_private[pos + 1] as usize);
+        let value = codec_private.get(pos + 2..pos + 2 + len);
+        if id == 1 {
+            return match value.and_then(|v| v.first()) {
+                Some(1) => VP9Profile::Profile1,
+                Some(2) => VP9Profile::Profile2,
+                Some(3) => VP9Profile::Profile3,
+                _ => VP9Profile::Profile0,
+            };
+        }
+        pos += 2 + len;
+    }
+    VP9Profile::Profile0
+}
+
+/// Map a Matroska audio CodecID to a codec
+fn audio_codec(codec_id: &str, sample_rate: u32, channels: u8) -> Option<AudioCodec> {
+    match codec_id {
+        "A_OPUS" => Some(AudioCodec::Opus {
+            sample_rate,
+            channels,
+            application: OpusApplication::Audio,
+        }),
+        "A_VORBIS" => Some(AudioCodec::Vorbis),
+        "A_FLAC" => Some(AudioCodec::FLAC),
+        id if id.starts_with("A_AAC") => Some(AudioCodec::AAC {
+            profile: AACProfile::LC,
+            sample_rate,
+            channels,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_lacing_xiph() {
+        // 3 frames: 300, 2, and the remaining 4 bytes
+        let mut body = vec![2, 0xFF, 45, 2];
+        body.extend(vec![1u8; 300]);
+        body.extend([2, 2]);
+        body.extend([3, 3, 3, 3]);
+
+        let frames = split_lacing(&body, LACING_XIPH).unwrap();
+        assert_eq!(
+            frames.iter().map(|f| f.len()).collect::<Vec<_>>(),
+            vec![300, 2, 4]
+        );
+        assert!(frames[2].iter().all(|&b| b == 3));
+    }
+
+    #[test]
+    fn test_split_lacing_ebml() {
+        // 3 frames: 5, 5 - 2 = 3, and the remaining 6 bytes
+        let mut body = vec![2, 0x85, 0xBD];
+        body.extend([1u8; 5]);
+        body.extend([2u8; 3]);
+        body.extend([3u8; 6]);
+
+        let frames = split_lacing(&body, LACING_EBML).unwrap();
+        assert_eq!(
+            frames.iter().map(|f| f.len()).collect::<Vec<_>>(),
+            vec![5, 3, 6]
+        );
+    }
+
+    #[test]
+    fn test_split_lacing_fixed() {
+        let mut body = vec![3];
+        body.extend([7u8; 12]);
+        let frames = split_lacing(&body, LACING_FIXED).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert!(frames.iter().all(|f| f.len() == 3));
+
+        // Not evenly divisible
+        assert!(split_lacing(&[1, 0, 0, 0], LACING_FIXED).is_none());
+    }
+
+    #[test]
+    fn test_vp9_profile_from_codec_private() {
+        assert_eq!(vp9_profile(&[]), VP9Profile::Profile0);
+        assert_eq!(vp9_profile(&[1, 1, 2, 2, 1, 31]), VP9Profile::Profile2);
+        assert_eq!(vp9_profile(&[2, 1, 31, 1, 1, 1]), VP9Profile::Profile1);
+    }
+}
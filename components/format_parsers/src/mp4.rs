@@ -1,9 +1,17 @@
 //! MP4 container format demuxer
 
-use crate::demuxer::Demuxer;
-use crate::types::{AudioTrackInfo, MediaInfo, VideoTrackInfo};
+use crate::demuxer::{Demuxer, PROBE_SCORE_MAX};
+use crate::mp4_fragment::{parse_moof, sample_defaults, SampleDefaults, TrackFragment};
+use crate::mp4_sample_entry::{
+    boxes, parse_senc, sample_descriptions, udta_metadata, SampleDescription, TrackEncryption,
+};
+use crate::types::{
+    ticks_to_duration, AudioTrackInfo, DemuxedPacket, MediaInfo, Packet, SeekIndex, SeekPoint,
+    VideoTrackInfo,
+};
 use cortenbrowser_shared_types::{
-    AACProfile, AudioCodec, H264Level, H264Profile, MediaError, VideoCodec,
+    AACProfile, AudioCodec, AudioPacket, AudioSpecificConfig, EncryptionInfo, H264Level,
+    H264Profile, MediaError, VideoCodec, VideoPacket,
 };
 use std::collections::HashMap;
 use std::io::Cursor;
@@ -12,62 +20,158 @@ use std::time::Duration;
 /// MP4 (MPEG-4 Part 14) container demuxer
 ///
 /// Parses MP4 container format and extracts video/audio track information.
+/// After [`Demuxer::load`], compressed samples can be read with
+/// [`Demuxer::read_packet`] or [`Demuxer::next_sample`]. Sample positions,
+/// sizes, timestamps and keyframe flags come from the sample tables
+/// (`stts`, `ctts`, `stss`, `stsc`, `stsz`, `stco`/`co64`). Packet
+/// timestamps are in the track's media timescale.
+///
+/// Data can also be fed incrementally with [`Demuxer::feed`]. Tracks are
+/// known once the whole `moov` box has arrived; for files with `moov` after
+/// `mdat` everything up to it is buffered until then.
+/// [`Demuxer::next_packet`] returns a sample once its bytes have been fed,
+/// and bytes before the next sample of every track are released.
+///
+/// In fragmented files, whose `moov` holds an `mvex` box, the samples are
+/// added from each `moof` box (`traf`, `tfhd`, `tfdt`, `trun`) once it has
+/// been fed, or loaded. Fragments may come in any order, as when appended
+/// to a Media Source Extensions source buffer: each sample is timed by its
+/// fragment's `tfdt`.
+///
+/// Samples of encrypted tracks (`encv`, `enca`) carry their
+/// [`EncryptionInfo`], from `senc` or the sample auxiliary information
+/// located by `saiz` and `saio`, or from the `senc` box of their track
+/// fragment; their codec is the original format named in `sinf`.
+/// Auxiliary information in the file data must be fed along with its
+/// sample.
+///
+/// The [`SeekIndex`] lists the sync samples (`stss`) of every track at their
+/// file offsets. After [`Demuxer::seek`], data is fed again from the
+/// returned offset.
 #[derive(Debug, Default)]
 pub struct Mp4Demuxer {
     media_info: Option<MediaInfo>,
+    data: Vec<u8>,
+    tracks: Vec<TrackSamples>,
+    seek_index: Option<SeekIndex>,
+    /// File offset of `data[0]`
+    data_offset: u64,
+    /// File offset of the next top-level box to inspect for `moov` or
+    /// `moof`
+    scan_offset: u64,
+    /// Whether more data may still be fed
+    streaming: bool,
+    /// Sample defaults of each track of a fragmented file, `None` for
+    /// files without fragments
+    fragment_defaults: Option<HashMap<u32, SampleDefaults>>,
 }
 
-impl Demuxer for Mp4Demuxer {
-    fn new() -> Self {
-        Self { media_info: None }
-    }
+/// Whether a track carries video or audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TrackKind {
+    Video,
+    Audio,
+}
 
-    fn parse(&self, data: &[u8]) -> Result<MediaInfo, MediaError> {
-        if data.is_empty() {
-            return Err(MediaError::UnsupportedFormat {
-                format: "Empty data".to_string(),
-            });
-        }
+/// Minimal `ftyp` box put in front of a fed `moov` box, which the `mp4`
+/// crate requires to read it
+const STREAM_FTYP: [u8; 16] = *b"\x00\x00\x00\x10ftypisom\x00\x00\x02\x00";
 
-        let cursor = Cursor::new(data);
-        let mp4_file = mp4::Mp4Reader::read_header(cursor, data.len() as u64).map_err(|e| {
-            MediaError::UnsupportedFormat {
-                format: format!("Failed to parse MP4: {}", e),
-            }
-        })?;
+/// Location and timing of a single sample
+#[derive(Debug, Clone, PartialEq)]
+struct SampleEntry {
+    offset: u64,
+    size: u32,
+    dts: u64,
+    composition_offset: i64,
+    is_sync: bool,
+    /// How the sample is encrypted, `None` for clear samples
+    protection: Option<SampleProtection>,
+}
 
-        let duration = Duration::from_millis(mp4_file.duration().as_millis() as u64);
+/// Where the encryption of a sample is described
+#[derive(Debug, Clone, PartialEq)]
+enum SampleProtection {
+    /// Read from the sample table
+    Known(EncryptionInfo),
+    /// In sample auxiliary information at a file offset
+    AuxInfo { offset: u64, size: u8 },
+}
 
-        let mut video_tracks = Vec::new();
-        let mut audio_tracks = Vec::new();
+impl SampleEntry {
+    /// File offset of the first byte needed to read the sample
+    fn start(&self) -> u64 {
+        match self.protection {
+            Some(SampleProtection::AuxInfo { offset, .. }) => offset.min(self.offset),
+            _ => self.offset,
+        }
+    }
 
-        // Extract video and audio tracks
-        for track_id in mp4_file.tracks().keys() {
-            if let Some(track) = mp4_file.tracks().get(track_id) {
-                match track.track_type() {
-                    Ok(mp4::TrackType::Video) => {
-                        if let Some(video_info) = extract_video_track_info(*track_id, track) {
-                            video_tracks.push(video_info);
-                        }
-                    }
-                    Ok(mp4::TrackType::Audio) => {
-                        if let Some(audio_info) = extract_audio_track_info(*track_id, track) {
-                            audio_tracks.push(audio_info);
-                        }
-                    }
-                    _ => {}
-                }
+    /// File offset after the last byte needed to read the sample, `None`
+    /// if it lies past the largest file offset
+    fn end(&self) -> Option<u64> {
+        let end = self.offset.checked_add(u64::from(self.size))?;
+        match self.protection {
+            Some(SampleProtection::AuxInfo { offset, size }) => {
+                Some(end.max(offset.checked_add(u64::from(size))?))
             }
+            _ => Some(end),
         }
+    }
+}
 
-        let metadata = HashMap::new(); // MP4 metadata extraction can be added later
+/// Sample table and read position for one track
+#[derive(Debug)]
+struct TrackSamples {
+    track_id: u32,
+    kind: TrackKind,
+    timescale: u32,
+    samples: Vec<SampleEntry>,
+    next: usize,
+    /// Default protection of an encrypted track
+    encryption: Option<TrackEncryption>,
+    /// Decode time after the last fragment added, where a fragment without
+    /// `tfdt` starts
+    fragment_dts: u64,
+}
 
-        Ok(MediaInfo {
-            duration,
-            video_tracks,
-            audio_tracks,
-            metadata,
-        })
+impl TrackSamples {
+    fn peek(&self) -> Option<&SampleEntry> {
+        self.samples.get(self.next)
+    }
+}
+
+impl Demuxer for Mp4Demuxer {
+    fn new() -> Self {
+        Self::default()
+    }
+
+    fn probe(data: &[u8]) -> u8 {
+        // The first box is normally ftyp; a size of 1 means a 64-bit size follows
+        let Some(size) = data
+            .get(..4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        else {
+            return 0;
+        };
+        if size != 1 && size < 8 {
+            return 0;
+        }
+        match data.get(4..8) {
+            Some(b"ftyp") => PROBE_SCORE_MAX,
+            // Older QuickTime-style files may start with other top-level boxes
+            Some(b"moov" | b"mdat" | b"free" | b"skip" | b"wide") => PROBE_SCORE_MAX / 2,
+            _ => 0,
+        }
+    }
+
+    fn parse(&self, data: &[u8]) -> Result<MediaInfo, MediaError> {
+        let mp4_file = read_header(data)?;
+        Ok(media_info(
+            &mp4_file,
+            &sample_descriptions(data),
+            udta_metadata(data),
+        ))
     }
 
     fn get_video_track(&self, track_id: u32) -> Option<VideoTrackInfo> {
@@ -87,22 +191,828 @@ impl Demuxer for Mp4Demuxer {
             .find(|t| t.track_id == track_id)
             .cloned()
     }
+
+    fn load(&mut self, data: &[u8]) -> Result<MediaInfo, MediaError> {
+        let mp4_file = read_header(data)?;
+        let descriptions = sample_descriptions(data);
+        let info = media_info(&mp4_file, &descriptions, udta_metadata(data));
+        let tracks = track_samples(&mp4_file, &descriptions, &info)?;
+
+        *self = Self {
+            media_info: Some(info.clone()),
+            data: data.to_vec(),
+            seek_index: Some(seek_index(&tracks)),
+            tracks,
+            fragment_defaults: sample_defaults(data),
+            ..Self::default()
+        };
+        if self.fragment_defaults.is_some() {
+            self.scan_boxes()?;
+        }
+
+        Ok(info)
+    }
+
+    fn read_packet(&mut self) -> Result<Option<DemuxedPacket>, MediaError> {
+        if self.waiting_for_moov() {
+            return Ok(None);
+        }
+        self.ensure_loaded()?;
+
+        match self.next_track() {
+            Some(index) => self.read_fed(index),
+            None => Ok(None),
+        }
+    }
+
+    fn next_sample(&mut self, track_id: u32) -> Result<Option<DemuxedPacket>, MediaError> {
+        if self.waiting_for_moov() {
+            return Ok(None);
+        }
+        self.ensure_loaded()?;
+
+        let index = self
+            .tracks
+            .iter()
+            .position(|t| t.track_id == track_id)
+            .ok_or_else(|| MediaError::InvalidParameter(format!("Unknown track: {}", track_id)))?;
+
+        if self.tracks[index].peek().is_none() {
+            return Ok(None);
+        }
+        self.read_fed(index)
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), MediaError> {
+        if self.media_info.is_some() && !self.streaming {
+            return Err(MediaError::InvalidState(
+                "MP4 data is already complete".to_string(),
+            ));
+        }
+        self.streaming = true;
+        self.data.extend_from_slice(chunk);
+
+        if self.media_info.is_none() || self.fragment_defaults.is_some() {
+            self.scan_boxes()?;
+        }
+        Ok(())
+    }
+
+    fn end_of_stream(&mut self) {
+        self.streaming = false;
+    }
+
+    fn media_info(&self) -> Option<&MediaInfo> {
+        self.media_info.as_ref()
+    }
+
+    fn seek_index(&self) -> Option<&SeekIndex> {
+        self.seek_index.as_ref()
+    }
+
+    fn seek(&mut self, time: Duration) -> Result<u64, MediaError> {
+        let index = self
+            .seek_index
+            .as_ref()
+            .ok_or_else(|| MediaError::InvalidState("No MP4 data loaded".to_string()))?;
+
+        // Tracks with no sync sample before `time` start at their first one
+        for track in &mut self.tracks {
+            track.next = index
+                .entry_for_time(track.track_id, time)
+                .or_else(|| index.entries(track.track_id).first())
+                .and_then(|point| point.sample_number)
+                .map_or(0, |number| number as usize - 1);
+        }
+
+        let mut offset = self
+            .tracks
+            .iter()
+            .filter_map(|t| t.peek().map(SampleEntry::start))
+            .min()
+            .unwrap_or(self.data_offset + self.data.len() as u64);
+        if self.fragment_defaults.is_some() {
+            // Fragments not scanned yet are fed again too
+            offset = offset.min(self.scan_offset);
+        }
+        self.data.clear();
+        self.data_offset = offset;
+        self.streaming = true;
+        Ok(offset)
+    }
+}
+
+impl Mp4Demuxer {
+    /// Returns the number of samples in a track, if the track is loaded
+    pub fn sample_count(&self, track_id: u32) -> Option<usize> {
+        self.tracks
+            .iter()
+            .find(|t| t.track_id == track_id)
+            .map(|t| t.samples.len())
+    }
+
+    /// Whether fed data has not reached the end of the `moov` box yet
+    fn waiting_for_moov(&self) -> bool {
+        self.streaming && self.media_info.is_none()
+    }
+
+    /// Index of the track whose next sample has the lowest DTS in seconds
+    ///
+    /// Comparing dts_a / ts_a < dts_b / ts_b by cross-multiplying avoids
+    /// rounding; ties go to the lower track ID.
+    fn next_track(&self) -> Option<usize> {
+        let mut best: Option<(usize, u64, u32)> = None;
+        for (index, track) in self.tracks.iter().enumerate() {
+            let Some(sample) = track.peek() else {
+                continue;
+            };
+            let earlier = match best {
+                None => true,
+                Some((_, dts, timescale)) => {
+                    (sample.dts as u128) * (timescale as u128)
+                        < (dts as u128) * (track.timescale as u128)
+                }
+            };
+            if earlier {
+                best = Some((index, sample.dts, track.timescale));
+            }
+        }
+        best.map(|(index, _, _)| index)
+    }
+
+    /// Read the next sample of the track at `index` once its bytes are fed
+    ///
+    /// While streaming, returns `Ok(None)` if the sample is not complete yet
+    /// and afterwards releases the data no track needs any more.
+    fn read_fed(&mut self, index: usize) -> Result<Option<DemuxedPacket>, MediaError> {
+        if !self.streaming {
+            return self.read_from(index).map(Some);
+        }
+
+        let fed_end = self.data_offset + self.data.len() as u64;
+        let complete = self.tracks[index]
+            .peek()
+            .and_then(SampleEntry::end)
+            .is_some_and(|end| end <= fed_end);
+        if !complete {
+            return Ok(None);
+        }
+
+        let packet = self.read_from(index)?;
+        self.release_read_data();
+        Ok(Some(packet))
+    }
+
+    /// Drop buffered bytes before the next sample of every track, and
+    /// before the next fragment
+    fn release_read_data(&mut self) {
+        let mut needed = self
+            .tracks
+            .iter()
+            .filter_map(|t| t.peek().map(SampleEntry::start))
+            .min()
+            .unwrap_or(self.data_offset + self.data.len() as u64);
+        if self.fragment_defaults.is_some() {
+            needed = needed.min(self.scan_offset);
+        }
+        if let Some(release) = needed.checked_sub(self.data_offset) {
+            let release = (release as usize).min(self.data.len());
+            self.data.drain(..release);
+            self.data_offset += release as u64;
+        }
+    }
+
+    /// Walk the fed top-level boxes, loading the tracks once the whole
+    /// `moov` box has arrived and, in a fragmented file, adding the samples
+    /// of each whole `moof` box
+    fn scan_boxes(&mut self) -> Result<(), MediaError> {
+        loop {
+            // Bytes from the next box to scan on are not released
+            let Some(start) = self
+                .scan_offset
+                .checked_sub(self.data_offset)
+                .and_then(|start| usize::try_from(start).ok())
+            else {
+                return Ok(());
+            };
+            let Some((size, box_type)) = read_box_header(&self.data[start.min(self.data.len())..])?
+            else {
+                return Ok(());
+            };
+            let whole_box = start
+                .checked_add(size)
+                .and_then(|end| self.data.get(start..end));
+
+            match &box_type {
+                b"moov" if self.media_info.is_none() => {
+                    let Some(moov) = whole_box else {
+                        return Ok(());
+                    };
+                    let header = [&STREAM_FTYP[..], moov].concat();
+                    let mp4_file = read_header(&header)?;
+                    let descriptions = sample_descriptions(&header);
+                    let info = media_info(&mp4_file, &descriptions, udta_metadata(&header));
+                    self.tracks = track_samples(&mp4_file, &descriptions, &info)?;
+                    self.seek_index = Some(seek_index(&self.tracks));
+                    self.media_info = Some(info);
+                    self.fragment_defaults = sample_defaults(&header);
+                    if self.fragment_defaults.is_none() {
+                        return Ok(());
+                    }
+                }
+                b"moof" if self.fragment_defaults.is_some() => {
+                    let Some(moof) = whole_box else {
+                        return Ok(());
+                    };
+                    let moof = moof.to_vec();
+                    self.add_fragment(&moof)?;
+                }
+                _ => {}
+            }
+
+            if size == 0 {
+                if self.media_info.is_some() {
+                    // No fragment follows a box that extends to the end
+                    self.fragment_defaults = None;
+                    return Ok(());
+                }
+                return Err(MediaError::UnsupportedFormat {
+                    format: format!(
+                        "MP4 box '{}' extends to the end of the file before moov",
+                        String::from_utf8_lossy(&box_type)
+                    ),
+                });
+            }
+            self.scan_offset += size as u64;
+        }
+    }
+
+    /// Add the samples of the `moof` box at the scan offset to their tracks
+    fn add_fragment(&mut self, moof: &[u8]) -> Result<(), MediaError> {
+        let malformed = || MediaError::CodecError {
+            details: format!(
+                "Malformed MP4 movie fragment at offset {}",
+                self.scan_offset
+            ),
+        };
+        let defaults = self.fragment_defaults.as_ref().ok_or_else(malformed)?;
+        let fragments = boxes(moof)
+            .next()
+            .and_then(|(_, payload)| parse_moof(payload, self.scan_offset, defaults))
+            .ok_or_else(malformed)?;
+
+        for fragment in fragments {
+            let Some(index) = self
+                .tracks
+                .iter()
+                .position(|t| t.track_id == fragment.track_id)
+            else {
+                continue;
+            };
+            let track = &self.tracks[index];
+            let base = fragment.base_decode_time.unwrap_or(track.fragment_dts);
+            let protection = fragment_protection(track, &fragment).ok_or_else(malformed)?;
+
+            let first = track.samples.len();
+            let mut samples = Vec::with_capacity(fragment.samples.len());
+            for (sample, protection) in fragment.samples.iter().zip(protection) {
+                let sample = SampleEntry {
+                    offset: sample.offset,
+                    size: sample.size,
+                    dts: base.checked_add(sample.dts).ok_or_else(malformed)?,
+                    composition_offset: sample.composition_offset,
+                    is_sync: sample.is_sync,
+                    protection,
+                };
+                if sample.end().is_none() {
+                    return Err(malformed());
+                }
+                samples.push(sample);
+            }
+
+            let track = &mut self.tracks[index];
+            track.fragment_dts = base.saturating_add(fragment.duration);
+            track.samples.extend(samples);
+            if let Some(index) = self.seek_index.as_mut() {
+                for (i, sample) in track.samples.iter().enumerate().skip(first) {
+                    let pts = sample.dts as i64 + sample.composition_offset;
+                    let Some(time) = ticks_to_duration(pts, track.timescale) else {
+                        continue;
+                    };
+                    if sample.is_sync {
+                        index.insert(
+                            track.track_id,
+                            SeekPoint {
+                                time,
+                                byte_offset: sample.offset,
+                                sample_number: Some(i as u64 + 1),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn ensure_loaded(&self) -> Result<(), MediaError> {
+        if self.media_info.is_none() {
+            return Err(MediaError::InvalidState("No MP4 data loaded".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Read the next sample of the track at `index` and advance it
+    fn read_from(&mut self, index: usize) -> Result<DemuxedPacket, MediaError> {
+        let track = &mut self.tracks[index];
+        let sample = track.samples[track.next].clone();
+        track.next += 1;
+
+        let start = sample.offset.checked_sub(self.data_offset);
+        let end = start
+            .and_then(|start| start.checked_add(u64::from(sample.size)))
+            .filter(|&end| end <= self.data.len() as u64)
+            .ok_or_else(|| MediaError::CodecError {
+                details: format!(
+                    "Sample at offset {} with size {} exceeds data length {}",
+                    sample.offset,
+                    sample.size,
+                    self.data_offset + self.data.len() as u64
+                ),
+            })?;
+        let (start, end) = ((end - u64::from(sample.size)) as usize, end as usize);
+
+        let data = self.data[start..end].to_vec();
+        let dts = sample.dts as i64;
+        let pts = dts + sample.composition_offset;
+        let encryption = match sample.protection {
+            None => None,
+            Some(SampleProtection::Known(info)) => Some(info),
+            Some(SampleProtection::AuxInfo { offset, size }) => {
+                Some(self.read_aux_info(index, offset, size)?)
+            }
+        };
+        let track = &self.tracks[index];
+
+        let packet = match track.kind {
+            TrackKind::Video => Packet::Video(VideoPacket {
+                data,
+                pts: Some(pts),
+                dts: Some(dts),
+                is_keyframe: sample.is_sync,
+                encryption,
+            }),
+            TrackKind::Audio => Packet::Audio(AudioPacket {
+                data,
+                pts: Some(pts),
+                dts: Some(dts),
+                // More fragments may follow until the end of the stream
+                is_last: track.next == track.samples.len()
+                    && !(self.streaming && self.fragment_defaults.is_some()),
+                encryption,
+            }),
+        };
+
+        Ok(DemuxedPacket {
+            track_id: track.track_id,
+            timescale: track.timescale,
+            packet,
+        })
+    }
+
+    /// Parse the auxiliary information of a sample of the track at `index`
+    fn read_aux_info(
+        &self,
+        index: usize,
+        offset: u64,
+        size: u8,
+    ) -> Result<EncryptionInfo, MediaError> {
+        let malformed = || MediaError::CodecError {
+            details: format!(
+                "Sample auxiliary information at offset {} with size {} is unavailable",
+                offset, size
+            ),
+        };
+        let start = offset
+            .checked_sub(self.data_offset)
+            .and_then(|start| usize::try_from(start).ok())
+            .ok_or_else(malformed)?;
+        let aux = self
+            .data
+            .get(start..start + size as usize)
+            .ok_or_else(malformed)?;
+        let encryption = self.tracks[index]
+            .encryption
+            .as_ref()
+            .ok_or_else(malformed)?;
+        encryption
+            .sample_info(aux, size > encryption.per_sample_iv_size)
+            .map(|(info, _)| info)
+            .ok_or_else(malformed)
+    }
+}
+
+/// Parse the MP4 box structure
+fn read_header(data: &[u8]) -> Result<mp4::Mp4Reader<Cursor<&[u8]>>, MediaError> {
+    if data.is_empty() {
+        return Err(MediaError::UnsupportedFormat {
+            format: "Empty data".to_string(),
+        });
+    }
+
+    let cursor = Cursor::new(data);
+    mp4::Mp4Reader::read_header(cursor, data.len() as u64).map_err(|e| {
+        MediaError::UnsupportedFormat {
+            format: format!("Failed to parse MP4: {}", e),
+        }
+    })
+}
+
+/// Read the size and type of the box at the start of `data`
+///
+/// # Returns
+///
+/// `Ok(None)` if the header is incomplete; a size of 0 means the box
+/// extends to the end of the file
+fn read_box_header(data: &[u8]) -> Result<Option<(usize, [u8; 4])>, MediaError> {
+    let Some(header) = data.get(..8) else {
+        return Ok(None);
+    };
+    let box_type = [header[4], header[5], header[6], header[7]];
+    let (size, header_len) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]])
+    {
+        1 => {
+            let Some(large) = data.get(8..16) else {
+                return Ok(None);
+            };
+            let mut size = [0; 8];
+            size.copy_from_slice(large);
+            (u64::from_be_bytes(size), 16)
+        }
+        size => (u64::from(size), 8),
+    };
+
+    if size != 0 && size < header_len {
+        return Err(MediaError::UnsupportedFormat {
+            format: format!(
+                "Invalid size {} for MP4 box '{}'",
+                size,
+                String::from_utf8_lossy(&box_type)
+            ),
+        });
+    }
+    let size = usize::try_from(size).map_err(|_| MediaError::UnsupportedFormat {
+        format: format!("MP4 box of {} bytes is too large", size),
+    })?;
+    Ok(Some((size, box_type)))
+}
+
+/// Build the sample tables of the audio and video tracks listed in `info`,
+/// ordered by ID
+fn track_samples(
+    mp4_file: &mp4::Mp4Reader<Cursor<&[u8]>>,
+    descriptions: &HashMap<u32, SampleDescription>,
+    info: &MediaInfo,
+) -> Result<Vec<TrackSamples>, MediaError> {
+    let mut tracks = Vec::new();
+    for (track_id, track) in mp4_file.tracks() {
+        let kind = if info.video_tracks.iter().any(|t| t.track_id == *track_id) {
+            TrackKind::Video
+        } else if info.audio_tracks.iter().any(|t| t.track_id == *track_id) {
+            TrackKind::Audio
+        } else {
+            continue;
+        };
+
+        let description = descriptions.get(track_id);
+        tracks.push(TrackSamples {
+            track_id: *track_id,
+            kind,
+            timescale: track.timescale(),
+            samples: build_sample_table(track, description)?,
+            next: 0,
+            encryption: description.and_then(|d| d.encryption.clone()),
+            fragment_dts: 0,
+        });
+    }
+    tracks.sort_by_key(|t| t.track_id);
+    Ok(tracks)
+}
+
+/// Index the sync samples of every track
+fn seek_index(tracks: &[TrackSamples]) -> SeekIndex {
+    let mut index = SeekIndex::new();
+    for track in tracks {
+        for (i, sample) in track.samples.iter().enumerate() {
+            if !sample.is_sync {
+                continue;
+            }
+            let pts = sample.dts as i64 + sample.composition_offset;
+            let Some(time) = ticks_to_duration(pts, track.timescale) else {
+                continue;
+            };
+            index.insert(
+                track.track_id,
+                SeekPoint {
+                    time,
+                    byte_offset: sample.offset,
+                    sample_number: Some(i as u64 + 1),
+                },
+            );
+        }
+    }
+    index
+}
+
+/// Extract media information from a parsed MP4 file
+///
+/// `descriptions` hold the codec configuration and color of each track,
+/// and `metadata` the title, artist and other tags, read from the `moov`
+/// box the file was parsed from.
+fn media_info(
+    mp4_file: &mp4::Mp4Reader<Cursor<&[u8]>>,
+    descriptions: &HashMap<u32, SampleDescription>,
+    metadata: HashMap<String, String>,
+) -> MediaInfo {
+    let duration = Duration::from_millis(mp4_file.duration().as_millis() as u64);
+
+    let mut video_tracks = Vec::new();
+    let mut audio_tracks = Vec::new();
+
+    // Extract video and audio tracks, in ID order so they list stably
+    let mut track_ids: Vec<_> = mp4_file.tracks().keys().collect();
+    track_ids.sort();
+    for track_id in track_ids {
+        if let Some(track) = mp4_file.tracks().get(track_id) {
+            let description = descriptions.get(track_id).cloned().unwrap_or_default();
+            match track.track_type() {
+                Ok(mp4::TrackType::Video) => {
+                    if let Some(video_info) =
+                        extract_video_track_info(*track_id, track, description)
+                    {
+                        video_tracks.push(video_info);
+                    }
+                }
+                Ok(mp4::TrackType::Audio) => {
+                    if let Some(audio_info) =
+                        extract_audio_track_info(*track_id, track, description)
+                    {
+                        audio_tracks.push(audio_info);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    MediaInfo {
+        duration,
+        video_tracks,
+        audio_tracks,
+        metadata,
+    }
+}
+
+/// Expand the sample tables of a track into per-sample entries
+fn build_sample_table(
+    track: &mp4::Mp4Track,
+    description: Option<&SampleDescription>,
+) -> Result<Vec<SampleEntry>, MediaError> {
+    let stbl = &track.trak.mdia.minf.stbl;
+    let malformed = |details: &str| MediaError::CodecError {
+        details: format!("Malformed MP4 sample table: {}", details),
+    };
+
+    // stsz: sample sizes
+    let count = stbl.stsz.sample_count as usize;
+    let sizes: Vec<u32> = if stbl.stsz.sample_size != 0 {
+        vec![stbl.stsz.sample_size; count]
+    } else {
+        if stbl.stsz.sample_sizes.len() < count {
+            return Err(malformed("stsz has fewer entries than samples"));
+        }
+        stbl.stsz.sample_sizes[..count].to_vec()
+    };
+
+    // stco / co64: chunk offsets
+    let chunk_offsets: Vec<u64> = match (&stbl.stco, &stbl.co64) {
+        (Some(stco), _) => stco.entries.iter().map(|&o| o as u64).collect(),
+        (None, Some(co64)) => co64.entries.clone(),
+        (None, None) if count == 0 => Vec::new(),
+        (None, None) => return Err(malformed("missing stco/co64")),
+    };
+
+    // stsc: map samples to chunks
+    let mut offsets = Vec::with_capacity(count);
+    let mut chunks = Vec::with_capacity(count);
+    for (i, entry) in stbl.stsc.entries.iter().enumerate() {
+        if entry.first_chunk == 0 {
+            return Err(malformed("stsc chunk numbers start at 1"));
+        }
+        let last_chunk = stbl
+            .stsc
+            .entries
+            .get(i + 1)
+            .map(|next| next.first_chunk.saturating_sub(1))
+            .unwrap_or(chunk_offsets.len() as u32);
+
+        for chunk in entry.first_chunk..=last_chunk {
+            let mut offset = *chunk_offsets
+                .get(chunk as usize - 1)
+                .ok_or_else(|| malformed("stsc references a missing chunk"))?;
+            for _ in 0..entry.samples_per_chunk {
+                if offsets.len() == count {
+                    break;
+                }
+                offsets.push(offset);
+                chunks.push(chunk as usize - 1);
+                offset = offset.saturating_add(sizes[offsets.len() - 1] as u64);
+            }
+        }
+    }
+    if offsets.len() < count {
+        return Err(malformed("stsc covers fewer samples than stsz"));
+    }
+
+    // stts: decode timestamps
+    let mut dts = Vec::with_capacity(count);
+    let mut time = 0u64;
+    for entry in &stbl.stts.entries {
+        for _ in 0..entry.sample_count {
+            if dts.len() == count {
+                break;
+            }
+            dts.push(time);
+            time += entry.sample_delta as u64;
+        }
+    }
+    if dts.len() < count {
+        return Err(malformed("stts covers fewer samples than stsz"));
+    }
+
+    // ctts: composition offsets (optional)
+    let mut composition = vec![0i64; count];
+    if let Some(ctts) = &stbl.ctts {
+        let mut index = 0;
+        for entry in &ctts.entries {
+            for _ in 0..entry.sample_count {
+                if index == count {
+                    break;
+                }
+                composition[index] = entry.sample_offset as i64;
+                index += 1;
+            }
+        }
+    }
+
+    // stss: sync samples (optional, all samples are sync when absent)
+    let mut sync = vec![stbl.stss.is_none(); count];
+    if let Some(stss) = &stbl.stss {
+        for &sample_number in &stss.entries {
+            if let Some(flag) = (sample_number as usize)
+                .checked_sub(1)
+                .and_then(|i| sync.get_mut(i))
+            {
+                *flag = true;
+            }
+        }
+    }
+
+    let protection = match description {
+        Some(description) => sample_protection(description, &chunks)?,
+        None => vec![None; count],
+    };
+
+    (0..count)
+        .zip(protection)
+        .map(|(i, protection)| {
+            let sample = SampleEntry {
+                offset: offsets[i],
+                size: sizes[i],
+                dts: dts[i],
+                composition_offset: composition[i],
+                is_sync: sync[i],
+                protection,
+            };
+            match sample.end() {
+                Some(_) => Ok(sample),
+                None => Err(malformed("sample extends past the largest file offset")),
+            }
+        })
+        .collect()
+}
+
+/// Find how each sample of a track fragment is encrypted, from its `senc`
+/// box
+///
+/// Returns `None` if `senc` has fewer entries than the fragment samples.
+fn fragment_protection(
+    track: &TrackSamples,
+    fragment: &TrackFragment,
+) -> Option<Vec<Option<SampleProtection>>> {
+    let count = fragment.samples.len();
+    let Some(encryption) = track
+        .encryption
+        .as_ref()
+        .filter(|encryption| encryption.is_protected)
+    else {
+        return Some(vec![None; count]);
+    };
+    let Some(senc) = fragment.senc else {
+        return Some(vec![
+            Some(SampleProtection::Known(encryption.default_info()));
+            count
+        ]);
+    };
+    let entries = parse_senc(senc, encryption)?;
+    (entries.len() >= count).then(|| {
+        entries
+            .into_iter()
+            .take(count)
+            .map(|info| Some(SampleProtection::Known(info)))
+            .collect()
+    })
+}
+
+/// Find how each sample of a track is encrypted
+///
+/// `chunks` holds the index of the chunk of each sample. Auxiliary
+/// information in `saio` is either contiguous for the whole track or
+/// located per chunk.
+fn sample_protection(
+    description: &SampleDescription,
+    chunks: &[usize],
+) -> Result<Vec<Option<SampleProtection>>, MediaError> {
+    let count = chunks.len();
+    let Some(encryption) = description
+        .encryption
+        .as_ref()
+        .filter(|encryption| encryption.is_protected)
+    else {
+        return Ok(vec![None; count]);
+    };
+    let malformed = |details: &str| MediaError::CodecError {
+        details: format!("Malformed MP4 sample encryption: {}", details),
+    };
+
+    if !description.sample_encryption.is_empty() {
+        if description.sample_encryption.len() < count {
+            return Err(malformed("senc has fewer entries than samples"));
+        }
+        return Ok(description.sample_encryption[..count]
+            .iter()
+            .map(|info| Some(SampleProtection::Known(info.clone())))
+            .collect());
+    }
+
+    let Some(aux_info) = &description.aux_info else {
+        return Ok(vec![
+            Some(SampleProtection::Known(encryption.default_info()));
+            count
+        ]);
+    };
+    let per_chunk = aux_info.offsets.len() > 1;
+    let mut offset = *aux_info
+        .offsets
+        .first()
+        .ok_or_else(|| malformed("saio has no offsets"))?;
+    let mut protection = Vec::with_capacity(count);
+    for (i, &chunk) in chunks.iter().enumerate() {
+        if per_chunk && (i == 0 || chunks[i - 1] != chunk) {
+            offset = *aux_info
+                .offsets
+                .get(chunk)
+                .ok_or_else(|| malformed("saio has fewer offsets than chunks"))?;
+        }
+        let size = aux_info.size(i);
+        protection.push(Some(if size == 0 {
+            SampleProtection::Known(encryption.default_info())
+        } else {
+            SampleProtection::AuxInfo { offset, size }
+        }));
+        offset += u64::from(size);
+    }
+    Ok(protection)
 }
 
 /// Extract video track information from MP4 track
-fn extract_video_track_info(track_id: u32, track: &mp4::Mp4Track) -> Option<VideoTrackInfo> {
-    let codec = match track.media_type() {
-        Ok(mp4::MediaType::H264) => VideoCodec::H264 {
+fn extract_video_track_info(
+    track_id: u32,
+    track: &mp4::Mp4Track,
+    description: SampleDescription,
+) -> Option<VideoTrackInfo> {
+    let codec = match media_type(track, &description) {
+        Some(mp4::MediaType::H264) => VideoCodec::H264 {
             profile: H264Profile::High,
             level: H264Level::Level4_1,
             hardware_accel: false,
         },
-        Ok(mp4::MediaType::H265) => VideoCodec::H265 {
+        Some(mp4::MediaType::H265) => VideoCodec::H265 {
             profile: cortenbrowser_shared_types::H265Profile::Main,
             tier: cortenbrowser_shared_types::H265Tier::Main,
             level: cortenbrowser_shared_types::H265Level::Level5_0,
         },
-        Ok(mp4::MediaType::VP9) => VideoCodec::VP9 {
+        Some(mp4::MediaType::VP9) => VideoCodec::VP9 {
             profile: cortenbrowser_shared_types::VP9Profile::Profile0,
         },
         _ => return None,
@@ -115,16 +1025,28 @@ fn extract_video_track_info(track_id: u32, track: &mp4::Mp4Track) -> Option<Vide
         height: track.height() as u32,
         frame_rate: track.frame_rate() as f32,
         bitrate: Some(track.bitrate()),
+        extradata: description.extradata,
+        color: description.color,
     })
 }
 
 /// Extract audio track information from MP4 track
-fn extract_audio_track_info(track_id: u32, track: &mp4::Mp4Track) -> Option<AudioTrackInfo> {
-    let codec = match track.media_type() {
-        Ok(mp4::MediaType::AAC) => AudioCodec::AAC {
-            profile: AACProfile::LC,
-            sample_rate: 48000, // Default value, would need to parse codec config for actual value
-            channels: 2,        // Default stereo
+///
+/// The AAC profile, sample rate and channel count come from the
+/// AudioSpecificConfig; without one, AAC LC at 48 kHz stereo is assumed.
+fn extract_audio_track_info(
+    track_id: u32,
+    track: &mp4::Mp4Track,
+    description: SampleDescription,
+) -> Option<AudioTrackInfo> {
+    let config = AudioSpecificConfig::parse(&description.extradata).ok();
+    let sample_rate = config.map_or(48000, |c| c.output_sample_rate());
+    let channels = config.map_or(2, |c| if c.channels == 0 { 2 } else { c.channels });
+    let codec = match media_type(track, &description) {
+        Some(mp4::MediaType::AAC) => AudioCodec::AAC {
+            profile: config.and_then(|c| c.profile()).unwrap_or(AACProfile::LC),
+            sample_rate,
+            channels,
         },
         _ => return None,
     };
@@ -132,8 +1054,26 @@ fn extract_audio_track_info(track_id: u32, track: &mp4::Mp4Track) -> Option<Audi
     Some(AudioTrackInfo {
         track_id,
         codec,
-        sample_rate: 48000, // Default value
-        channels: 2,        // Default stereo
+        sample_rate,
+        channels,
         bitrate: Some(track.bitrate()),
+        extradata: description.extradata,
+        gapless: description.gapless,
     })
 }
+
+/// Media type of a track, from the original format of an encrypted one
+///
+/// The `mp4` crate does not read `encv` and `enca` sample entries.
+fn media_type(track: &mp4::Mp4Track, description: &SampleDescription) -> Option<mp4::MediaType> {
+    track
+        .media_type()
+        .ok()
+        .or_else(|| match &description.original_format? {
+            b"avc1" | b"avc3" => Some(mp4::MediaType::H264),
+            b"hvc1" | b"hev1" => Some(mp4::MediaType::H265),
+            b"vp09" => Some(mp4::MediaType::VP9),
+            b"mp4a" => Some(mp4::MediaType::AAC),
+            _ => None,
+        })
+}
@@ -0,0 +1,263 @@
+//! MP4 movie fragment parsing
+//!
+//! Fragmented MP4 files, as used for streaming and Media Source Extensions,
+//! announce fragments with an `mvex` box in `moov` and keep the sample
+//! tables there empty. The samples follow in movie fragments, a `moof` box
+//! followed by the `mdat` holding their data:
+//!
+//! ```text
+//! moof > traf > tfhd   track ID, data offset base and sample defaults
+//!             > tfdt   decode time of the first sample
+//!             > trun   size, duration, flags and composition offset of
+//!                      each sample of a run, and where its data starts
+//! ```
+//!
+//! Sample values missing from `trun` come from the track fragment's `tfhd`
+//! or, failing that, from the track's `trex` box in `moov > mvex`.
+
+use crate::mp4_sample_entry::{boxes, find_box, read_u32, read_u64};
+use std::collections::HashMap;
+
+/// `tfhd` flags
+const TFHD_BASE_DATA_OFFSET: u32 = 0x01;
+const TFHD_SAMPLE_DESCRIPTION_INDEX: u32 = 0x02;
+const TFHD_DEFAULT_DURATION: u32 = 0x08;
+const TFHD_DEFAULT_SIZE: u32 = 0x10;
+const TFHD_DEFAULT_FLAGS: u32 = 0x20;
+const TFHD_DEFAULT_BASE_IS_MOOF: u32 = 0x02_0000;
+
+/// `trun` flags
+const TRUN_DATA_OFFSET: u32 = 0x01;
+const TRUN_FIRST_SAMPLE_FLAGS: u32 = 0x04;
+const TRUN_DURATION: u32 = 0x100;
+const TRUN_SIZE: u32 = 0x200;
+const TRUN_FLAGS: u32 = 0x400;
+const TRUN_COMPOSITION_OFFSET: u32 = 0x800;
+
+/// `sample_is_non_sync_sample` bit of the sample flags
+const SAMPLE_IS_NON_SYNC: u32 = 0x01_0000;
+
+/// Defaults for the samples of a track's fragments, from `trex`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct SampleDefaults {
+    duration: u32,
+    size: u32,
+    flags: u32,
+}
+
+/// A sample of a track fragment
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FragmentSample {
+    /// File offset of the sample data
+    pub(crate) offset: u64,
+    pub(crate) size: u32,
+    /// Decode time relative to the start of the track fragment
+    pub(crate) dts: u64,
+    pub(crate) composition_offset: i64,
+    pub(crate) is_sync: bool,
+}
+
+/// The samples of one track in a movie fragment
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TrackFragment<'a> {
+    pub(crate) track_id: u32,
+    /// Decode time of the first sample from `tfdt`; without one, the
+    /// fragment follows the track's previous fragment
+    pub(crate) base_decode_time: Option<u64>,
+    /// Sum of the sample durations
+    pub(crate) duration: u64,
+    pub(crate) samples: Vec<FragmentSample>,
+    /// Payload of the `senc` box of an encrypted track
+    pub(crate) senc: Option<&'a [u8]>,
+}
+
+/// Read the sample defaults of every track from `moov > mvex > trex`
+///
+/// `data` is a sequence of top-level boxes containing `moov`.
+///
+/// # Returns
+///
+/// The defaults keyed by track ID, or `None` if the movie is not fragmented
+pub(crate) fn sample_defaults(data: &[u8]) -> Option<HashMap<u32, SampleDefaults>> {
+    let mvex = find_box(find_box(data, b"moov")?, b"mvex")?;
+    Some(
+        boxes(mvex)
+            .filter(|(box_type, _)| box_type == b"trex")
+            .filter_map(|(_, trex)| {
+                // Track ID and sample description index precede the defaults
+                let defaults = SampleDefaults {
+                    duration: read_u32(trex, 12)?,
+                    size: read_u32(trex, 16)?,
+                    flags: read_u32(trex, 20)?,
+                };
+                Some((read_u32(trex, 4)?, defaults))
+            })
+            .collect(),
+    )
+}
+
+/// Parse the track fragments of a `moof` box
+///
+/// # Arguments
+///
+/// * `moof` - Payload of the `moof` box
+/// * `moof_offset` - File offset of the `moof` box, the default base of
+///   sample data offsets
+/// * `defaults` - Sample defaults of each track from [`sample_defaults`]
+///
+/// # Returns
+///
+/// The track fragments in file order, or `None` if a track fragment is
+/// malformed
+pub(crate) fn parse_moof<'a>(
+    moof: &'a [u8],
+    moof_offset: u64,
+    defaults: &HashMap<u32, SampleDefaults>,
+) -> Option<Vec<TrackFragment<'a>>> {
+    let mut fragments = Vec::new();
+    // Without an explicit base, a track fragment's data follows the data of
+    // the previous one
+    let mut data_end = moof_offset;
+    for (box_type, traf) in boxes(moof) {
+        if &box_type != b"traf" {
+            continue;
+        }
+        let fragment = parse_traf(traf, moof_offset, &mut data_end, defaults)?;
+        fragments.push(fragment);
+    }
+    Some(fragments)
+}
+
+/// Parse a `traf` box, advancing `data_end` past its sample data
+fn parse_traf<'a>(
+    traf: &'a [u8],
+    moof_offset: u64,
+    data_end: &mut u64,
+    defaults: &HashMap<u32, SampleDefaults>,
+) -> Option<TrackFragment<'a>> {
+    let tfhd = find_box(traf, b"tfhd")?;
+    let flags = read_u32(tfhd, 0)? & 0x00FF_FFFF;
+    let track_id = read_u32(tfhd, 4)?;
+    let mut track_defaults = defaults.get(&track_id).copied().unwrap_or_default();
+
+    let mut pos = 8;
+    let base = if flags & TFHD_BASE_DATA_OFFSET != 0 {
+        pos += 8;
+        read_u64(tfhd, 8)?
+    } else if flags & TFHD_DEFAULT_BASE_IS_MOOF != 0 {
+        moof_offset
+    } else {
+        *data_end
+    };
+    if flags & TFHD_SAMPLE_DESCRIPTION_INDEX != 0 {
+        pos += 4;
+    }
+    for (flag, value) in [
+        (TFHD_DEFAULT_DURATION, &mut track_defaults.duration),
+        (TFHD_DEFAULT_SIZE, &mut track_defaults.size),
+        (TFHD_DEFAULT_FLAGS, &mut track_defaults.flags),
+    ] {
+        if flags & flag != 0 {
+            *value = read_u32(tfhd, pos)?;
+            pos += 4;
+        }
+    }
+
+    let base_decode_time = match find_box(traf, b"tfdt") {
+        Some(tfdt) if tfdt.first() == Some(&1) => Some(read_u64(tfdt, 4)?),
+        Some(tfdt) => Some(u64::from(read_u32(tfdt, 4)?)),
+        None => None,
+    };
+
+    let mut samples = Vec::new();
+    let mut dts = 0u64;
+    // A run without a data offset follows the data of the previous run
+    let mut run_end = base;
+    for (box_type, trun) in boxes(traf) {
+        if &box_type != b"trun" {
+            continue;
+        }
+        run_end = parse_trun(trun, base, run_end, &track_defaults, &mut dts, &mut samples)?;
+    }
+    *data_end = run_end;
+
+    Some(TrackFragment {
+        track_id,
+        base_decode_time,
+        duration: dts,
+        samples,
+        senc: find_box(traf, b"senc"),
+    })
+}
+
+/// Parse a `trun` box into `samples`, advancing `dts` past its samples
+///
+/// `base` is the base data offset of the track fragment, and `run_end`
+/// the file offset after the data of the previous run.
+///
+/// # Returns
+///
+/// The file offset after the run's sample data
+fn parse_trun(
+    trun: &[u8],
+    base: u64,
+    run_end: u64,
+    defaults: &SampleDefaults,
+    dts: &mut u64,
+    samples: &mut Vec<FragmentSample>,
+) -> Option<u64> {
+    let version = *trun.first()?;
+    let flags = read_u32(trun, 0)? & 0x00FF_FFFF;
+    let count = read_u32(trun, 4)?;
+
+    let mut pos = 8;
+    let mut offset = if flags & TRUN_DATA_OFFSET != 0 {
+        pos += 4;
+        base.checked_add_signed(i64::from(read_u32(trun, pos - 4)? as i32))?
+    } else {
+        run_end
+    };
+    let first_flags = if flags & TRUN_FIRST_SAMPLE_FLAGS != 0 {
+        pos += 4;
+        Some(read_u32(trun, pos - 4)?)
+    } else {
+        None
+    };
+
+    for i in 0..count {
+        let mut field = |flag: u32, default: u32| {
+            if flags & flag == 0 {
+                return Some(default);
+            }
+            let value = read_u32(trun, pos)?;
+            pos += 4;
+            Some(value)
+        };
+        let duration = field(TRUN_DURATION, defaults.duration)?;
+        let size = field(TRUN_SIZE, defaults.size)?;
+        let sample_flags = field(TRUN_FLAGS, defaults.flags)?;
+        let composition_offset = field(TRUN_COMPOSITION_OFFSET, 0)?;
+
+        let sample_flags = match first_flags {
+            Some(first) if i == 0 => first,
+            _ => sample_flags,
+        };
+        // Version 1 composition offsets are signed
+        let composition_offset = if version == 1 {
+            i64::from(composition_offset as i32)
+        } else {
+            i64::from(composition_offset)
+        };
+
+        samples.push(FragmentSample {
+            offset,
+            size,
+            dts: *dts,
+            composition_offset,
+            is_sync: sample_flags & SAMPLE_IS_NON_SYNC == 0,
+        });
+        offset = offset.checked_add(u64::from(size))?;
+        *dts = dts.checked_add(u64::from(duration))?;
+    }
+    Some(offset)
+}
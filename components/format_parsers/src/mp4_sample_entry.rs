@@ -0,0 +1,764 @@
+//! MP4 sample entry parsing
+//!
+//! The `mp4` crate decodes codec configuration boxes into fields but does not
+//! keep their bytes, and skips `colr`. This module walks the raw `moov` box
+//! down to each track's first sample entry:
+//!
+//! ```text
+//! moov > trak > mdia > minf > stbl > stsd > avc1 / hvc1 / vp09 / av01 / mp4a
+//! ```
+//!
+//! Visual sample entries hold the codec configuration box (`avcC`, `hvcC`,
+//! `vpcC`, `av1C`) whose payload is kept as is, and optionally `colr`. Audio
+//! sample entries hold `esds`, whose DecoderSpecificInfo descriptor is the
+//! AudioSpecificConfig for AAC.
+//!
+//! The encoder delay and padding of audio tracks come from the iTunes
+//! `iTunSMPB` tag in `moov > udta > meta > ilst` or, failing that, from the
+//! track's edit list (`trak > edts > elst`).
+//!
+//! Container metadata (title, artist, ...) comes from the iTunes items in
+//! the same `ilst` box or, failing that, from QuickTime text items directly
+//! in `moov > udta`.
+//!
+//! Encrypted tracks use `encv` and `enca` sample entries, whose `sinf` box
+//! names the original format (`frma`), the protection scheme (`schm`) and
+//! the track's default key ID and IV (`schi > tenc`). Each sample's IV and
+//! subsamples are in a `senc` box in the sample table or, failing that, in
+//! sample auxiliary information located by `saiz` and `saio`.
+
+use cortenbrowser_shared_types::{
+    ColorInfo, EncryptionInfo, EncryptionPattern, EncryptionScheme, GaplessInfo, Subsample,
+};
+use std::collections::HashMap;
+
+/// Size of the fields before the child boxes of a visual sample entry
+const VISUAL_SAMPLE_ENTRY_SIZE: usize = 78;
+/// Size of the fields before the child boxes of a version 0 audio sample entry
+const AUDIO_SAMPLE_ENTRY_SIZE: usize = 28;
+
+/// MPEG-4 descriptor tags used in `esds`
+const ES_DESCRIPTOR_TAG: u8 = 0x03;
+const DECODER_CONFIG_DESCRIPTOR_TAG: u8 = 0x04;
+const DECODER_SPECIFIC_INFO_TAG: u8 = 0x05;
+
+/// Metadata keys of the iTunes and QuickTime text items in `udta`
+const METADATA_ITEMS: [(&[u8; 4], &str); 7] = [
+    (b"\xa9nam", "title"),
+    (b"\xa9ART", "artist"),
+    (b"\xa9alb", "album"),
+    (b"aART", "album_artist"),
+    (b"\xa9day", "date"),
+    (b"\xa9gen", "genre"),
+    (b"\xa9cmt", "comment"),
+];
+
+/// Codec configuration of a track's first sample entry
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct SampleDescription {
+    /// Codec configuration record or AudioSpecificConfig
+    pub(crate) extradata: Vec<u8>,
+    /// Color description from `colr`
+    pub(crate) color: Option<ColorInfo>,
+    /// Encoder delay and padding of an audio track
+    pub(crate) gapless: Option<GaplessInfo>,
+    /// Format of the samples before encryption, from `frma`
+    pub(crate) original_format: Option<[u8; 4]>,
+    /// Default protection of an encrypted track
+    pub(crate) encryption: Option<TrackEncryption>,
+    /// Encryption of each sample from `senc`, empty if the track has none
+    pub(crate) sample_encryption: Vec<EncryptionInfo>,
+    /// Location of each sample's auxiliary information
+    pub(crate) aux_info: Option<AuxInfoLocation>,
+}
+
+/// Default protection of an encrypted track, from `tenc`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct TrackEncryption {
+    pub(crate) scheme: EncryptionScheme,
+    pub(crate) pattern: Option<EncryptionPattern>,
+    /// Whether samples are encrypted by default
+    pub(crate) is_protected: bool,
+    /// Size of the IV stored with each sample, 0 for a constant IV
+    pub(crate) per_sample_iv_size: u8,
+    pub(crate) key_id: Vec<u8>,
+    pub(crate) constant_iv: Vec<u8>,
+}
+
+impl TrackEncryption {
+    /// Encryption of a sample without auxiliary information, which uses
+    /// the constant IV and is encrypted whole
+    pub(crate) fn default_info(&self) -> EncryptionInfo {
+        EncryptionInfo {
+            key_id: self.key_id.clone(),
+            iv: self.constant_iv.clone(),
+            subsamples: Vec::new(),
+            scheme: self.scheme,
+            pattern: self.pattern,
+        }
+    }
+
+    /// Parse one sample's auxiliary information, which has the layout of a
+    /// `senc` entry
+    ///
+    /// # Returns
+    ///
+    /// The sample's encryption and the number of bytes read
+    pub(crate) fn sample_info(
+        &self,
+        aux: &[u8],
+        has_subsamples: bool,
+    ) -> Option<(EncryptionInfo, usize)> {
+        let mut info = self.default_info();
+        let mut pos = self.per_sample_iv_size as usize;
+        if pos > 0 {
+            info.iv = aux.get(..pos)?.to_vec();
+        }
+        if has_subsamples {
+            let count = read_u16(aux, pos)?;
+            pos += 2;
+            for _ in 0..count {
+                info.subsamples.push(Subsample {
+                    clear_bytes: u32::from(read_u16(aux, pos)?),
+                    encrypted_bytes: read_u32(aux, pos + 2)?,
+                });
+                pos += 6;
+            }
+        }
+        Some((info, pos))
+    }
+}
+
+/// Where the auxiliary information of each sample is, from `saiz` and
+/// `saio`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct AuxInfoLocation {
+    /// Size of every sample's information, 0 if they differ
+    pub(crate) default_size: u8,
+    /// Size of each sample's information, if they differ
+    pub(crate) sizes: Vec<u8>,
+    /// File offset of the information of the first sample of each chunk,
+    /// or of all samples if there is only one
+    pub(crate) offsets: Vec<u64>,
+}
+
+impl AuxInfoLocation {
+    /// Size of the information of the sample at `index`
+    pub(crate) fn size(&self, index: usize) -> u8 {
+        if self.default_size != 0 {
+            self.default_size
+        } else {
+            self.sizes.get(index).copied().unwrap_or(0)
+        }
+    }
+}
+
+/// Read the sample descriptions of every track, keyed by track ID
+///
+/// `data` is a sequence of top-level boxes containing `moov`. Malformed
+/// boxes end the walk of their parent, so tracks are left out rather than
+/// failing the whole file, which the `mp4` crate has already validated.
+pub(crate) fn sample_descriptions(data: &[u8]) -> HashMap<u32, SampleDescription> {
+    let mut descriptions = HashMap::new();
+    let Some(moov) = find_box(data, b"moov") else {
+        return descriptions;
+    };
+    let movie_timescale = find_box(moov, b"mvhd").and_then(timescale);
+    let itunsmpb = find_path(moov, &[b"udta", b"meta"])
+        .and_then(|meta| meta.get(4..))
+        .and_then(|meta| find_box(meta, b"ilst"))
+        .and_then(itunsmpb);
+    for (box_type, trak) in boxes(moov) {
+        if &box_type != b"trak" {
+            continue;
+        }
+        let Some(track_id) = find_box(trak, b"tkhd").and_then(track_id) else {
+            continue;
+        };
+        let stbl = find_path(trak, &[b"mdia", b"minf", b"stbl"]).unwrap_or_default();
+        let entry = find_box(stbl, b"stsd")
+            .and_then(|stsd| stsd.get(8..))
+            .and_then(|entries| boxes(entries).next());
+        if let Some((entry_type, entry)) = entry {
+            let mut description = parse_sample_entry(&entry_type, entry);
+            if let Some(encryption) = &description.encryption {
+                description.sample_encryption = find_box(stbl, b"senc")
+                    .and_then(|senc| parse_senc(senc, encryption))
+                    .unwrap_or_default();
+                description.aux_info = aux_info_location(stbl);
+            }
+            if matches!(&entry_type, b"mp4a" | b"enca") {
+                description.gapless = itunsmpb.or_else(|| {
+                    movie_timescale
+                        .and_then(|movie_timescale| edit_list_gapless(trak, movie_timescale))
+                });
+            }
+            descriptions.insert(track_id, description);
+        }
+    }
+    descriptions
+}
+
+fn parse_sample_entry(entry_type: &[u8; 4], entry: &[u8]) -> SampleDescription {
+    match entry_type {
+        b"mp4a" | b"enca" => parse_audio_sample_entry(entry),
+        _ => parse_visual_sample_entry(entry),
+    }
+}
+
+fn parse_visual_sample_entry(entry: &[u8]) -> SampleDescription {
+    let mut description = SampleDescription::default();
+    let children = entry.get(VISUAL_SAMPLE_ENTRY_SIZE..).unwrap_or_default();
+    for (box_type, payload) in boxes(children) {
+        match &box_type {
+            b"avcC" | b"hvcC" | b"vpcC" | b"av1C" => description.extradata = payload.to_vec(),
+            b"colr" => description.color = parse_colr(payload).or(description.color),
+            b"sinf" => parse_sinf(payload, &mut description),
+            _ => {}
+        }
+    }
+    description
+}
+
+fn parse_audio_sample_entry(entry: &[u8]) -> SampleDescription {
+    let mut description = SampleDescription::default();
+    // QuickTime sound descriptions version 1 and 2 add 16 and 36 bytes
+    let fields_size = match entry.get(8..10) {
+        Some([0, 1]) => AUDIO_SAMPLE_ENTRY_SIZE + 16,
+        Some([0, 2]) => AUDIO_SAMPLE_ENTRY_SIZE + 36,
+        _ => AUDIO_SAMPLE_ENTRY_SIZE,
+    };
+    if let Some(children) = entry.get(fields_size..) {
+        if let Some(esds) = find_box(children, b"esds") {
+            description.extradata = esds
+                .get(4..)
+                .and_then(decoder_specific_info)
+                .unwrap_or_default()
+                .to_vec();
+        }
+        if let Some(sinf) = find_box(children, b"sinf") {
+            parse_sinf(sinf, &mut description);
+        }
+    }
+    description
+}
+
+/// Read the original format and protection of an encrypted sample entry
+/// from its `sinf` box
+///
+/// Schemes other than `cenc` and `cbcs` leave the track without
+/// protection information.
+fn parse_sinf(sinf: &[u8], description: &mut SampleDescription) {
+    description.original_format = find_box(sinf, b"frma")
+        .and_then(|frma| frma.get(..4))
+        .map(|format| [format[0], format[1], format[2], format[3]]);
+    let scheme = match find_box(sinf, b"schm").and_then(|schm| schm.get(4..8)) {
+        Some(b"cenc") => EncryptionScheme::Cenc,
+        Some(b"cbcs") => EncryptionScheme::Cbcs,
+        _ => return,
+    };
+    description.encryption =
+        find_path(sinf, &[b"schi", b"tenc"]).and_then(|tenc| parse_tenc(tenc, scheme));
+}
+
+/// Parse a `tenc` box
+fn parse_tenc(tenc: &[u8], scheme: EncryptionScheme) -> Option<TrackEncryption> {
+    let version = *tenc.first()?;
+    // The pattern byte is reserved in version 0
+    let pattern = match *tenc.get(5)? {
+        pattern if version > 0 && pattern != 0 => Some(EncryptionPattern {
+            crypt_byte_block: pattern >> 4,
+            skip_byte_block: pattern & 0x0F,
+        }),
+        _ => None,
+    };
+    let is_protected = *tenc.get(6)? == 1;
+    let per_sample_iv_size = *tenc.get(7)?;
+    let key_id = tenc.get(8..24)?.to_vec();
+    let constant_iv = if is_protected && per_sample_iv_size == 0 {
+        let size = *tenc.get(24)? as usize;
+        tenc.get(25..25 + size)?.to_vec()
+    } else {
+        Vec::new()
+    };
+    Some(TrackEncryption {
+        scheme,
+        pattern,
+        is_protected,
+        per_sample_iv_size,
+        key_id,
+        constant_iv,
+    })
+}
+
+/// Parse the per-sample entries of a `senc` box
+pub(crate) fn parse_senc(senc: &[u8], encryption: &TrackEncryption) -> Option<Vec<EncryptionInfo>> {
+    let has_subsamples = read_u32(senc, 0)? & 0x02 != 0;
+    let count = read_u32(senc, 4)?;
+    let mut entries = senc.get(8..)?;
+    (0..count)
+        .map(|_| {
+            let (info, size) = encryption.sample_info(entries, has_subsamples)?;
+            entries = &entries[size..];
+            Some(info)
+        })
+        .collect()
+}
+
+/// Read the sizes and offsets of the sample auxiliary information from the
+/// `saiz` and `saio` boxes of a sample table
+fn aux_info_location(stbl: &[u8]) -> Option<AuxInfoLocation> {
+    let saiz = find_box(stbl, b"saiz")?;
+    let saio = find_box(stbl, b"saio")?;
+    // Both may name the auxiliary information type before their fields
+    let skip = |full_box: &[u8]| {
+        if full_box.get(3)? & 0x01 != 0 {
+            Some(12)
+        } else {
+            Some(4)
+        }
+    };
+
+    let pos = skip(saiz)?;
+    let default_size = *saiz.get(pos)?;
+    let count = read_u32(saiz, pos + 1)? as usize;
+    let sizes = if default_size == 0 {
+        saiz.get(pos + 5..pos + 5 + count)?.to_vec()
+    } else {
+        Vec::new()
+    };
+
+    let pos = skip(saio)?;
+    let large = saio.first() == Some(&1);
+    let count = read_u32(saio, pos)? as usize;
+    let offsets = (0..count)
+        .map(|i| {
+            if large {
+                read_u64(saio, pos + 4 + i * 8)
+            } else {
+                read_u32(saio, pos + 4 + i * 4).map(u64::from)
+            }
+        })
+        .collect::<Option<Vec<u64>>>()?;
+
+    Some(AuxInfoLocation {
+        default_size,
+        sizes,
+        offsets,
+    })
+}
+
+/// Read the track ID of a `tkhd` box
+fn track_id(tkhd: &[u8]) -> Option<u32> {
+    // Creation and modification times are 64-bit in version 1
+    let offset = if tkhd.first() == Some(&1) { 20 } else { 12 };
+    read_u32(tkhd, offset)
+}
+
+/// Read the timescale of an `mvhd` or `mdhd` box
+fn timescale(header: &[u8]) -> Option<u32> {
+    let offset = if header.first() == Some(&1) { 20 } else { 12 };
+    read_u32(header, offset)
+}
+
+/// Read the duration of an `mdhd` box
+fn media_duration(mdhd: &[u8]) -> Option<u64> {
+    if mdhd.first() == Some(&1) {
+        read_u64(mdhd, 24)
+    } else {
+        read_u32(mdhd, 16).map(u64::from)
+    }
+}
+
+/// Find the value of the `iTunSMPB` tag in an `ilst` box
+fn itunsmpb(ilst: &[u8]) -> Option<GaplessInfo> {
+    boxes(ilst)
+        .filter(|(box_type, _)| box_type == b"----")
+        .find(|(_, item)| {
+            find_box(item, b"name").and_then(|name| name.get(4..)) == Some(b"iTunSMPB")
+        })
+        .and_then(|(_, item)| find_box(item, b"data"))
+        // Type and locale precede the value
+        .and_then(|data| data.get(8..))
+        .and_then(|value| std::str::from_utf8(value).ok())
+        .and_then(|value| GaplessInfo::parse_itunsmpb(value).ok())
+}
+
+/// Read the container metadata in `moov > udta`, keyed as
+/// [`MediaInfo::metadata`](crate::MediaInfo::metadata) is
+///
+/// `data` is a sequence of top-level boxes containing `moov`. Items of
+/// `meta > ilst` take precedence over QuickTime text items, and items that
+/// are not UTF-8 text are skipped.
+pub(crate) fn udta_metadata(data: &[u8]) -> HashMap<String, String> {
+    let mut metadata = HashMap::new();
+    let Some(udta) = find_path(data, &[b"moov", b"udta"]) else {
+        return metadata;
+    };
+    let ilst = find_box(udta, b"meta")
+        .and_then(|meta| meta.get(4..))
+        .and_then(|meta| find_box(meta, b"ilst"));
+
+    for (item_type, key) in METADATA_ITEMS {
+        let value = ilst
+            .and_then(|ilst| find_box(ilst, item_type))
+            .and_then(ilst_text)
+            .or_else(|| find_box(udta, item_type).and_then(quicktime_text));
+        if let Some(value) = value {
+            metadata.insert(key.to_string(), value);
+        }
+    }
+    metadata
+}
+
+/// Text value of an `ilst` item, from its `data` box with the UTF-8 type
+fn ilst_text(item: &[u8]) -> Option<String> {
+    let data = find_box(item, b"data")?;
+    // Type and locale precede the value
+    if read_u32(data, 0)? != 1 {
+        return None;
+    }
+    let value = std::str::from_utf8(data.get(8..)?).ok()?;
+    Some(value.to_string())
+}
+
+/// Text of a QuickTime user data item, its first string after its size and
+/// language code
+fn quicktime_text(item: &[u8]) -> Option<String> {
+    let size = read_u16(item, 0)? as usize;
+    let value = std::str::from_utf8(item.get(4..4 + size)?).ok()?;
+    Some(value.to_string())
+}
+
+/// Derive the encoder delay and padding of an audio track from its edit
+/// list
+///
+/// The media time of the edit is the delay and the media beyond the end of
+/// the edit is the padding, both in the media timescale, which is the sample
+/// rate for audio tracks. Only a single edit, optionally after an empty one,
+/// is supported.
+fn edit_list_gapless(trak: &[u8], movie_timescale: u32) -> Option<GaplessInfo> {
+    let elst = find_path(trak, &[b"edts", b"elst"])?;
+    let large = elst.first() == Some(&1);
+    let entry_size = if large { 20 } else { 12 };
+    let count = read_u32(elst, 4)? as usize;
+
+    let mut edits = (0..count).map(|i| {
+        let offset = 8 + i * entry_size;
+        if large {
+            Some((read_u64(elst, offset)?, read_u64(elst, offset + 8)? as i64))
+        } else {
+            let media_time = read_u32(elst, offset + 4)? as i32;
+            Some((u64::from(read_u32(elst, offset)?), i64::from(media_time)))
+        }
+    });
+    let (segment_duration, media_time) = match edits.next()?? {
+        (_, -1) => edits.next()??,
+        edit => edit,
+    };
+    if edits.next().is_some() || media_time < 0 || movie_timescale == 0 {
+        return None;
+    }
+
+    let mdhd = find_path(trak, &[b"mdia", b"mdhd"])?;
+    let media_timescale = u64::from(timescale(mdhd)?);
+    let duration = media_duration(mdhd)?;
+    let segment = segment_duration.checked_mul(media_timescale)? / u64::from(movie_timescale);
+    let padding = duration.saturating_sub(media_time as u64 + segment);
+    Some(GaplessInfo {
+        encoder_delay: u32::try_from(media_time).ok()?,
+        padding: u32::try_from(padding).ok()?,
+    })
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+pub(crate) fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+pub(crate) fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(data.get(offset..offset + 8)?);
+    Some(u64::from_be_bytes(bytes))
+}
+
+/// Parse an `nclx` (ISO) or `nclc` (QuickTime) `colr` box; ICC profiles are
+/// not supported
+fn parse_colr(colr: &[u8]) -> Option<ColorInfo> {
+    let code_point = |offset: usize| -> Option<u8> {
+        let bytes = colr.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]).min(u8::MAX as u16) as u8)
+    };
+    let full_range = match colr.get(..4)? {
+        b"nclx" => colr.get(10)? & 0x80 != 0,
+        b"nclc" => false,
+        _ => return None,
+    };
+    Some(ColorInfo {
+        primaries: code_point(4)?,
+        transfer: code_point(6)?,
+        matrix: code_point(8)?,
+        full_range,
+    })
+}
+
+/// Find the DecoderSpecificInfo in the ES_Descriptor of an `esds` box
+fn decoder_specific_info(mut data: &[u8]) -> Option<&[u8]> {
+    let es = read_descriptor(&mut data, ES_DESCRIPTOR_TAG)?;
+    let flags = *es.get(2)?;
+    let mut rest = &es[3..];
+    if flags & 0x80 != 0 {
+        // dependsOn_ES_ID
+        rest = rest.get(2..)?;
+    }
+    if flags & 0x40 != 0 {
+        // URL
+        let length = *rest.first()? as usize;
+        rest = rest.get(1 + length..)?;
+    }
+    if flags & 0x20 != 0 {
+        // OCR_ES_Id
+        rest = rest.get(2..)?;
+    }
+
+    let decoder_config = read_descriptor(&mut rest, DECODER_CONFIG_DESCRIPTOR_TAG)?;
+    // objectTypeIndication, streamType, bufferSizeDB, maxBitrate, avgBitrate
+    let mut rest = decoder_config.get(13..)?;
+    while !rest.is_empty() {
+        let tag = rest[0];
+        let payload = read_descriptor(&mut rest, tag)?;
+        if tag == DECODER_SPECIFIC_INFO_TAG {
+            return Some(payload);
+        }
+    }
+    None
+}
+
+/// Read a descriptor with the given tag and its expandable size field
+fn read_descriptor<'a>(data: &mut &'a [u8], tag: u8) -> Option<&'a [u8]> {
+    if *data.first()? != tag {
+        return None;
+    }
+    let mut size = 0usize;
+    let mut pos = 1;
+    loop {
+        let byte = *data.get(pos)?;
+        size = (size << 7) | (byte & 0x7F) as usize;
+        pos += 1;
+        if byte & 0x80 == 0 || pos == 5 {
+            break;
+        }
+    }
+    let payload = data.get(pos..pos + size)?;
+    *data = &data[pos + size..];
+    Some(payload)
+}
+
+/// Follow a path of nested boxes from `data`
+fn find_path<'a>(data: &'a [u8], path: &[&[u8; 4]]) -> Option<&'a [u8]> {
+    path.iter()
+        .try_fold(data, |parent, box_type| find_box(parent, box_type))
+}
+
+/// Payload of the first box of a type in `data`
+pub(crate) fn find_box<'a>(data: &'a [u8], box_type: &[u8; 4]) -> Option<&'a [u8]> {
+    boxes(data)
+        .find(|(found, _)| found == box_type)
+        .map(|(_, payload)| payload)
+}
+
+/// Iterate over the type and payload of the boxes in `data`, stopping at the
+/// first malformed box
+pub(crate) fn boxes(mut data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    std::iter::from_fn(move || {
+        let header = data.get(..8)?;
+        let box_type = [header[4], header[5], header[6], header[7]];
+        let (size, header_len) =
+            match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+                0 => (data.len(), 8),
+                1 => {
+                    let large = data.get(8..16)?;
+                    let mut size = [0; 8];
+                    size.copy_from_slice(large);
+                    (usize::try_from(u64::from_be_bytes(size)).ok()?, 16)
+                }
+                size => (size as usize, 8),
+            };
+        if size < header_len || size > data.len() {
+            data = &[];
+            return None;
+        }
+        let payload = &data[header_len..size];
+        data = &data[size..];
+        Some((box_type, payload))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut data = ((8 + payload.len()) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(payload);
+        data
+    }
+
+    #[test]
+    fn test_parse_colr() {
+        let nclx = [b"nclx".as_slice(), &[0, 9, 0, 16, 0, 9, 0x80]].concat();
+        let nclc = [b"nclc".as_slice(), &[0, 1, 0, 1, 0, 1]].concat();
+
+        assert_eq!(
+            parse_colr(&nclx),
+            Some(ColorInfo {
+                primaries: 9,
+                transfer: 16,
+                matrix: 9,
+                full_range: true,
+            })
+        );
+        assert_eq!(parse_colr(&nclc), Some(ColorInfo::BT709));
+        assert_eq!(parse_colr(b"prof"), None);
+        assert_eq!(parse_colr(&nclx[..8]), None);
+    }
+
+    #[test]
+    fn test_decoder_specific_info() {
+        let mut esds = vec![0x03, 0x80, 0x80, 0x80, 0x19, 0x00, 0x02, 0x00];
+        esds.extend_from_slice(&[0x04, 0x11, 0x40, 0x15, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        esds.extend_from_slice(&[0x05, 0x02, 0x12, 0x10]);
+        esds.extend_from_slice(&[0x06, 0x01, 0x02]);
+
+        assert_eq!(decoder_specific_info(&esds), Some(&[0x12, 0x10][..]));
+        assert_eq!(decoder_specific_info(&esds[..12]), None);
+    }
+
+    #[test]
+    fn test_sample_descriptions_version_1_tkhd() {
+        let mut tkhd = vec![1, 0, 0, 0];
+        tkhd.extend_from_slice(&[0; 16]);
+        tkhd.extend_from_slice(&7u32.to_be_bytes());
+
+        let mut entry = vec![0; VISUAL_SAMPLE_ENTRY_SIZE];
+        entry.extend(mp4_box(b"vpcC", &[1, 0, 0, 0, 0x00, 0x1F, 0x80, 0x02]));
+        let stsd = mp4_box(
+            b"stsd",
+            &[&[0, 0, 0, 0, 0, 0, 0, 1], &mp4_box(b"vp09", &entry)[..]].concat(),
+        );
+        let stbl = mp4_box(b"stbl", &stsd);
+        let minf = mp4_box(b"minf", &stbl);
+        let mdia = mp4_box(b"mdia", &minf);
+        let trak = mp4_box(b"trak", &[mp4_box(b"tkhd", &tkhd), mdia].concat());
+        let moov = mp4_box(b"moov", &trak);
+
+        let descriptions = sample_descriptions(&moov);
+        assert_eq!(
+            descriptions[&7].extradata,
+            vec![1, 0, 0, 0, 0x00, 0x1F, 0x80, 0x02]
+        );
+        assert_eq!(descriptions[&7].color, None);
+    }
+
+    #[test]
+    fn test_parse_tenc_cbcs_constant_iv() {
+        // Version 1 with a 1:9 pattern and a 16-byte constant IV
+        let mut tenc = vec![1, 0, 0, 0, 0, 0x19, 1, 0];
+        tenc.extend_from_slice(&[0x10; 16]);
+        tenc.push(16);
+        tenc.extend_from_slice(&[0x22; 16]);
+
+        let encryption = parse_tenc(&tenc, EncryptionScheme::Cbcs).unwrap();
+        assert_eq!(
+            encryption.pattern,
+            Some(EncryptionPattern {
+                crypt_byte_block: 1,
+                skip_byte_block: 9,
+            })
+        );
+        assert!(encryption.is_protected);
+        assert_eq!(encryption.constant_iv, vec![0x22; 16]);
+
+        // Samples carry only subsamples
+        let aux = [0, 1, 0, 5, 0, 0, 0, 32];
+        let (info, size) = encryption.sample_info(&aux, true).unwrap();
+        assert_eq!(size, aux.len());
+        assert_eq!(info.iv, vec![0x22; 16]);
+        assert_eq!(
+            info.subsamples,
+            vec![Subsample {
+                clear_bytes: 5,
+                encrypted_bytes: 32,
+            }]
+        );
+        assert_eq!(encryption.sample_info(&aux[..6], true), None);
+    }
+
+    /// Build a `moov` with one audio track in a 48 kHz media timescale and
+    /// the given edit list
+    fn audio_moov(elst: &[u8]) -> Vec<u8> {
+        let mut tkhd = vec![0; 12];
+        tkhd.extend_from_slice(&2u32.to_be_bytes());
+
+        // Version 0 mdhd with a 48 kHz timescale and 10 frames of 1024
+        let mut mdhd = vec![0; 12];
+        mdhd.extend_from_slice(&48000u32.to_be_bytes());
+        mdhd.extend_from_slice(&10240u32.to_be_bytes());
+        let mut mvhd = vec![0; 12];
+        mvhd.extend_from_slice(&1000u32.to_be_bytes());
+
+        let entry = vec![0; AUDIO_SAMPLE_ENTRY_SIZE];
+        let stsd = mp4_box(
+            b"stsd",
+            &[&[0, 0, 0, 0, 0, 0, 0, 1], &mp4_box(b"mp4a", &entry)[..]].concat(),
+        );
+        let minf = mp4_box(b"minf", &mp4_box(b"stbl", &stsd));
+        let mdia = mp4_box(b"mdia", &[mp4_box(b"mdhd", &mdhd), minf].concat());
+        let edts = mp4_box(b"edts", &mp4_box(b"elst", elst));
+        let trak = mp4_box(b"trak", &[mp4_box(b"tkhd", &tkhd), edts, mdia].concat());
+        mp4_box(b"moov", &[mp4_box(b"mvhd", &mvhd), trak].concat())
+    }
+
+    #[test]
+    fn test_edit_list_gapless() {
+        // An empty edit, then 150 ms of media from sample 2112
+        let mut elst = vec![0, 0, 0, 0, 0, 0, 0, 2];
+        elst.extend_from_slice(&[0, 0, 0, 10, 0xFF, 0xFF, 0xFF, 0xFF, 0, 1, 0, 0]);
+        elst.extend_from_slice(&[0, 0, 0, 150, 0, 0, 0x08, 0x40, 0, 1, 0, 0]);
+
+        let descriptions = sample_descriptions(&audio_moov(&elst));
+
+        // 10240 - 2112 - 7200 samples of padding
+        assert_eq!(
+            descriptions[&2].gapless,
+            Some(GaplessInfo {
+                encoder_delay: 2112,
+                padding: 928,
+            })
+        );
+    }
+
+    #[test]
+    fn test_edit_list_gapless_version_1() {
+        let mut elst = vec![1, 0, 0, 0, 0, 0, 0, 1];
+        elst.extend_from_slice(&190u64.to_be_bytes());
+        elst.extend_from_slice(&1024u64.to_be_bytes());
+        elst.extend_from_slice(&[0, 1, 0, 0]);
+
+        let descriptions = sample_descriptions(&audio_moov(&elst));
+
+        assert_eq!(
+            descriptions[&2].gapless,
+            Some(GaplessInfo {
+                encoder_delay: 1024,
+                padding: 96,
+            })
+        );
+    }
+}
@@ -1,80 +1,82 @@
 //! Ogg container format demuxer
+//!
+//! Pages are read with [`crate::ogg_page`] and their packet pieces are
+//! reassembled per logical stream. Pages failing the CRC check are skipped;
+//! the stream they belonged to notices the gap in page sequence numbers and
+//! drops the packet that was interrupted.
+//!
+//! # Timestamps
+//!
+//! A page's granule position is the codec-defined position at the end of the
+//! last packet completed on that page:
+//!
+//! - **Vorbis**: PCM sample position at the stream's sample rate. Packet
+//!   durations follow from the block sizes, `(previous + current) / 4`, and
+//!   the first audio packet produces no samples.
+//! - **Opus**: sample position at 48 kHz, including the `OpusHead` pre-skip.
+//!   Packet durations come from the TOC byte.
+//! - **FLAC**: PCM sample position at the stream's sample rate. Packet
+//!   durations are the block sizes in the frame headers.
+//!
+//! The first page completing audio packets anchors a stream: its granule
+//! position minus the durations of those packets is the starting position.
+//! Packet timestamps are in samples (timescale = sample rate, 48000 for Opus)
+//! with the Opus pre-skip subtracted, so the first Opus packets may carry
+//! negative timestamps.
 
-use crate::demuxer::Demuxer;
-use crate::types::{AudioTrackInfo, MediaInfo, VideoTrackInfo};
-use cortenbrowser_shared_types::{AudioCodec, MediaError};
-use std::collections::HashMap;
-use std::io::Cursor;
+use crate::demuxer::{Demuxer, PROBE_SCORE_MAX};
+use crate::ogg_page::{self, Page, PageStatus};
+use crate::types::{
+    ticks_to_duration, AudioTrackInfo, DemuxedPacket, MediaInfo, Packet, VideoTrackInfo,
+};
+use cortenbrowser_shared_types::{AudioCodec, AudioPacket, MediaError, OpusApplication};
+use std::collections::VecDeque;
 use std::time::Duration;
 
+/// Opus always decodes at 48 kHz
+const OPUS_SAMPLE_RATE: u32 = 48_000;
+
+/// Size of the Ogg FLAC mapping header before the `fLaC` marker
+const FLAC_MAPPING_HEADER_SIZE: usize = 9;
+
 /// Ogg container demuxer
 ///
-/// Parses Ogg container format and extracts media information.
+/// Parses Ogg container format and extracts Vorbis, Opus and FLAC streams. After
+/// [`Demuxer::load`], packets can be read with [`Demuxer::read_packet`] or
+/// [`Demuxer::next_sample`]. Track IDs are the logical stream serial numbers.
+///
+/// Data can also be fed incrementally with [`Demuxer::feed`]. Media
+/// information is available once every stream's header packets have
+/// arrived; its duration stays zero, as it comes from the last page.
+///
+/// Chained Ogg files (a new logical stream starting after the first ones
+/// ended) are not supported: reading reports an error once the next chain
+/// is reached.
 #[derive(Debug, Default)]
 pub struct OggDemuxer {
     media_info: Option<MediaInfo>,
+    reader: Option<OggReader>,
 }
 
 impl Demuxer for OggDemuxer {
     fn new() -> Self {
-        Self { media_info: None }
-    }
-
-    fn parse(&self, data: &[u8]) -> Result<MediaInfo, MediaError> {
-        if data.is_empty() {
-            return Err(MediaError::UnsupportedFormat {
-                format: "Empty data".to_string(),
-            });
+        Self {
+            media_info: None,
+            reader: None,
         }
+    }
 
-        // Basic Ogg validation - must start with "OggS"
-        if data.len() < 4 || &data[0..4] != b"OggS" {
-            return Err(MediaError::UnsupportedFormat {
-                format: "Invalid Ogg data".to_string(),
-            });
-        }
-
-        let cursor = Cursor::new(data);
-        let mut reader = ogg::PacketReader::new(cursor);
-
-        let mut audio_tracks = Vec::new();
-
-        // Read first packet to identify codec
-        if let Ok(Some(packet)) = reader.read_packet() {
-            // Try to identify codec from packet header
-            // Vorbis packets start with 0x01 + "vorbis"
-            // Opus packets start with "OpusHead"
-            if packet.data.len() > 8 {
-                if &packet.data[1..7] == b"vorbis" {
-                    audio_tracks.push(AudioTrackInfo {
-                        track_id: packet.stream_serial(),
-                        codec: AudioCodec::Vorbis,
-                        sample_rate: 44100, // Default, would parse from header
-                        channels: 2,
-                        bitrate: None,
-                    });
-                } else if packet.data.starts_with(b"OpusHead") {
-                    audio_tracks.push(AudioTrackInfo {
-                        track_id: packet.stream_serial(),
-                        codec: AudioCodec::Opus {
-                            sample_rate: 48000, // Opus is always 48kHz internally
-                            channels: 2,
-                            application: cortenbrowser_shared_types::OpusApplication::Audio,
-                        },
-                        sample_rate: 48000,
-                        channels: 2,
-                        bitrate: None,
-                    });
-                }
-            }
+    fn probe(data: &[u8]) -> u8 {
+        // Capture pattern followed by stream structure version 0
+        match data.strip_prefix(ogg_page::CAPTURE_PATTERN) {
+            Some([0, ..]) => PROBE_SCORE_MAX,
+            Some([]) => PROBE_SCORE_MAX / 2,
+            _ => 0,
         }
+    }
 
-        Ok(MediaInfo {
-            duration: Duration::ZERO, // Would need to scan file for duration
-            video_tracks: Vec::new(), // Ogg can contain Theora but not common
-            audio_tracks,
-            metadata: HashMap::new(),
-        })
+    fn parse(&self, data: &[u8]) -> Result<MediaInfo, MediaError> {
+        read_header(data).map(|(_, info)| info)
     }
 
     fn get_video_track(&self, track_id: u32) -> Option<VideoTrackInfo> {
@@ -94,4 +96,869 @@ impl Demuxer for OggDemuxer {
             .find(|t| t.track_id == track_id)
             .cloned()
     }
+
+    fn load(&mut self, data: &[u8]) -> Result<MediaInfo, MediaError> {
+        let (reader, info) = read_header(data)?;
+        self.reader = Some(reader);
+        self.media_info = Some(info.clone());
+        Ok(info)
+    }
+
+    fn read_packet(&mut self) -> Result<Option<DemuxedPacket>, MediaError> {
+        self.reader_mut()?.read_packet()
+    }
+
+    fn next_sample(&mut self, track_id: u32) -> Result<Option<DemuxedPacket>, MediaError> {
+        let reader = self.reader_mut()?;
+        if !reader.has_track(track_id) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Unknown track: {}",
+                track_id
+            )));
+        }
+        reader.read_track_packet(track_id)
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> Result<(), MediaError> {
+        let reader = self.reader.get_or_insert_with(OggReader::streaming);
+        if reader.ended {
+            return Err(MediaError::InvalidState(
+                "Ogg stream has already ended".to_string(),
+            ));
+        }
+        reader.push(chunk)?;
+
+        if self.media_info.is_none() && reader.read_headers()? {
+            self.media_info = Some(reader.media_info());
+        }
+        Ok(())
+    }
+
+    fn end_of_stream(&mut self) {
+        if let Some(reader) = &mut self.reader {
+            reader.ended = true;
+        }
+    }
+
+    fn media_info(&self) -> Option<&MediaInfo> {
+        self.media_info.as_ref()
+    }
+}
+
+impl OggDemuxer {
+    /// Returns the header packets of a track
+    ///
+    /// For Vorbis these are the identification, comment and setup headers;
+    /// for Opus the `OpusHead` and `OpusTags` packets; for FLAC the mapping
+    /// header with STREAMINFO followed by the other metadata blocks.
+    pub fn codec_headers(&self, track_id: u32) -> Option<&[Vec<u8>]> {
+        self.reader
+            .as_ref()?
+            .stream(track_id)
+            .map(|stream| stream.headers.as_slice())
+    }
+
+    fn reader_mut(&mut self) -> Result<&mut OggReader, MediaError> {
+        self.reader
+            .as_mut()
+            .ok_or_else(|| MediaError::InvalidState("No Ogg data loaded".to_string()))
+    }
+}
+
+/// Parse complete Ogg data up to the end of the codec headers
+fn read_header(data: &[u8]) -> Result<(OggReader, MediaInfo), MediaError> {
+    if data.is_empty() {
+        return Err(MediaError::UnsupportedFormat {
+            format: "Empty data".to_string(),
+        });
+    }
+    if !data.starts_with(ogg_page::CAPTURE_PATTERN) {
+        return Err(MediaError::UnsupportedFormat {
+            format: "Invalid Ogg data".to_string(),
+        });
+    }
+
+    let mut reader = OggReader::new(data.to_vec());
+    reader.read_headers()?;
+    let info = reader.media_info();
+    Ok((reader, info))
+}
+
+fn malformed(details: &str) -> MediaError {
+    MediaError::CodecError {
+        details: format!("Malformed Ogg data: {}", details),
+    }
+}
+
+/// Page reader over loaded or fed Ogg data
+#[derive(Debug)]
+struct OggReader {
+    data: Vec<u8>,
+    pos: usize,
+    /// Whether all data has been received; until then a truncated page
+    /// waits for more data instead of being skipped
+    ended: bool,
+    streams: Vec<LogicalStream>,
+    /// Whether a non-BOS page has been seen; later BOS pages start a chain
+    started: bool,
+    packets: VecDeque<DemuxedPacket>,
+}
+
+impl OggReader {
+    fn new(data: Vec<u8>) -> Self {
+        Self {
+            data,
+            pos: 0,
+            ended: true,
+            streams: Vec::new(),
+            started: false,
+            packets: VecDeque::new(),
+        }
+    }
+
+    /// Creates a reader for data fed with [`OggReader::push`]
+    fn streaming() -> Self {
+        Self {
+            ended: false,
+            ..Self::new(Vec::new())
+        }
+    }
+
+    /// Append fed data, dropping the pages already read
+    fn push(&mut self, chunk: &[u8]) -> Result<(), MediaError> {
+        self.data.drain(..self.pos);
+        self.pos = 0;
+        self.data.extend_from_slice(chunk);
+
+        let prefix = &self.data[..self.data.len().min(ogg_page::CAPTURE_PATTERN.len())];
+        if self.streams.is_empty() && !ogg_page::CAPTURE_PATTERN.starts_with(prefix) {
+            return Err(MediaError::UnsupportedFormat {
+                format: "Invalid Ogg data".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Read pages until every stream has received its header packets
+    ///
+    /// Returns whether the headers are complete; `false` means more data
+    /// is needed.
+    fn read_headers(&mut self) -> Result<bool, MediaError> {
+        loop {
+            if self.started && self.streams.iter().all(LogicalStream::headers_complete) {
+                return Ok(true);
+            }
+            if !self.step()? {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn media_info(&self) -> MediaInfo {
+        let mut info = MediaInfo::default();
+        for stream in &self.streams {
+            let Some(codec) = &stream.codec else {
+                continue;
+            };
+            info.audio_tracks.push(AudioTrackInfo {
+                track_id: stream.serial,
+                codec: codec.audio_codec(),
+                sample_rate: codec.sample_rate(),
+                channels: codec.channels(),
+                bitrate: codec.bitrate(),
+                extradata: codec.extradata(&stream.headers),
+                gapless: None,
+            });
+            // The last page is only known once all data has been received
+            if !self.ended {
+                continue;
+            }
+            if let Some(duration) = self
+                .last_granule_position(stream.serial)
+                .and_then(|granule| codec.granule_to_duration(granule))
+            {
+                info.duration = info.duration.max(duration);
+            }
+        }
+        info
+    }
+
+    /// Granule position of the last page of a stream, scanning back from the end
+    fn last_granule_position(&self, serial: u32) -> Option<i64> {
+        let mut end = self.data.len();
+        while let Some(start) = ogg_page::rfind_capture_pattern(&self.data[..end]) {
+            if let PageStatus::Page(page) = ogg_page::read_page(&self.data[start..]) {
+                if page.serial == serial && page.granule_position >= 0 {
+                    return Some(page.granule_position);
+                }
+            }
+            // Exclude the pattern just found from the next search
+            end = start + ogg_page::CAPTURE_PATTERN.len() - 1;
+        }
+        None
+    }
+
+    fn stream(&self, serial: u32) -> Option<&LogicalStream> {
+        self.streams
+            .iter()
+            .find(|s| s.serial == serial && s.codec.is_some())
+    }
+
+    /// Whether a supported stream with this serial number exists
+    fn has_track(&self, track_id: u32) -> bool {
+        self.stream(track_id).is_some()
+    }
+
+    /// Read the next packet in file order
+    fn read_packet(&mut self) -> Result<Option<DemuxedPacket>, MediaError> {
+        while self.packets.is_empty() && self.step()? {}
+        Ok(self.packets.pop_front())
+    }
+
+    /// Read the next packet of one stream, queueing other streams' packets
+    fn read_track_packet(&mut self, track_id: u32) -> Result<Option<DemuxedPacket>, MediaError> {
+        loop {
+            if let Some(index) = self.packets.iter().position(|p| p.track_id == track_id) {
+                return Ok(self.packets.remove(index));
+            }
+            if !self.step()? {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Process one page
+    ///
+    /// Returns `Ok(false)` at the end of the data, or when the next page has
+    /// not been fully fed yet.
+    fn step(&mut self) -> Result<bool, MediaError> {
+        if self.pos >= self.data.len() {
+            return Ok(false);
+        }
+
+        let data = std::mem::take(&mut self.data);
+        let result = match ogg_page::read_page(&data[self.pos..]) {
+            PageStatus::Page(page) => self.process_page(&page).map(|()| {
+                self.pos += page.len();
+                true
+            }),
+            PageStatus::Incomplete if !self.ended => Ok(false),
+            // Corrupted or truncated page: resynchronise at the next capture
+            // pattern. While data is still being fed, the pattern may be
+            // split across chunks, so its possible start is kept.
+            PageStatus::Incomplete | PageStatus::Invalid => {
+                let end = if self.ended {
+                    data.len()
+                } else {
+                    let possible_start = data
+                        .len()
+                        .saturating_sub(ogg_page::CAPTURE_PATTERN.len() - 1);
+                    (self.pos + 1).max(possible_start)
+                };
+                self.pos = ogg_page::find_capture_pattern(&data, self.pos + 1).unwrap_or(end);
+                Ok(true)
+            }
+        };
+        self.data = data;
+        result
+    }
+
+    fn process_page(&mut self, page: &Page<'_>) -> Result<(), MediaError> {
+        if page.is_bos() {
+            if self.started {
+                return Err(MediaError::UnsupportedFormat {
+                    format: format!("Chained Ogg stream (serial {})", page.serial),
+                });
+            }
+            if !self.streams.iter().any(|s| s.serial == page.serial) {
+                self.streams.push(LogicalStream::new(page.serial));
+            }
+        } else {
+            self.started = true;
+        }
+
+        // Pages of streams that never had a BOS page are dropped
+        if let Some(stream) = self.streams.iter_mut().find(|s| s.serial == page.serial) {
+            let packets = stream.push_page(page)?;
+            self.packets.extend(packets);
+        }
+        Ok(())
+    }
+}
+
+/// Reassembly and timing state of one logical stream
+#[derive(Debug)]
+struct LogicalStream {
+    serial: u32,
+    /// Whether the first packet has been seen
+    identified: bool,
+    /// `None` for unidentified or unsupported codecs
+    codec: Option<StreamCodec>,
+    headers: Vec<Vec<u8>>,
+    /// Packet continuing on the next page
+    partial: Option<Vec<u8>>,
+    last_sequence: Option<u32>,
+    /// Granule position at the end of the last emitted packet
+    position: Option<i64>,
+    ended: bool,
+}
+
+impl LogicalStream {
+    fn new(serial: u32) -> Self {
+        Self {
+            serial,
+            identified: false,
+            codec: None,
+            headers: Vec::new(),
+            partial: None,
+            last_sequence: None,
+            position: None,
+            ended: false,
+        }
+    }
+
+    fn headers_complete(&self) -> bool {
+        match &self.codec {
+            Some(codec) => self.headers.len() >= codec.header_count(),
+            None => self.identified,
+        }
+    }
+
+    /// Reassemble the packets of a page and timestamp the audio packets
+    fn push_page(&mut self, page: &Page<'_>) -> Result<Vec<DemuxedPacket>, MediaError> {
+        if self.ended {
+            return Ok(Vec::new());
+        }
+
+        let contiguous = self
+            .last_sequence
+            .is_none_or(|sequence| page.sequence == sequence.wrapping_add(1));
+        self.last_sequence = Some(page.sequence);
+        if !contiguous || !page.is_continued() {
+            // A page was lost, or the previous page's last packet was never
+            // finished
+            self.partial = None;
+        }
+        if !contiguous {
+            // Re-anchor timestamps on the next granule position
+            self.position = None;
+            if let Some(codec) = &mut self.codec {
+                codec.reset();
+            }
+        }
+
+        let mut completed = Vec::new();
+        for (i, (data, complete)) in page.packets().into_iter().enumerate() {
+            let packet = if i == 0 && page.is_continued() {
+                // Without the start of the packet, the piece is useless
+                let Some(mut partial) = self.partial.take() else {
+                    continue;
+                };
+                partial.extend_from_slice(data);
+                partial
+            } else {
+                data.to_vec()
+            };
+            if complete {
+                completed.push(packet);
+            } else {
+                self.partial = Some(packet);
+            }
+        }
+        if page.is_eos() {
+            self.ended = true;
+            self.partial = None;
+        }
+
+        let mut audio = Vec::new();
+        for packet in completed {
+            if !self.headers_complete() {
+                self.push_header(packet)?;
+            } else if self.codec.is_some() {
+                audio.push(packet);
+            }
+        }
+        Ok(self.timestamp(audio, page.granule_position, page.is_eos()))
+    }
+
+    fn push_header(&mut self, packet: Vec<u8>) -> Result<(), MediaError> {
+        if !self.identified {
+            self.identified = true;
+            self.codec = StreamCodec::identify(&packet)?;
+        } else if let Some(StreamCodec::Vorbis(vorbis)) = &mut self.codec {
+            if self.headers.len() == 2 {
+                vorbis.read_setup_header(&packet)?;
+            }
+        }
+        if self.codec.is_some() {
+            self.headers.push(packet);
+        }
+        Ok(())
+    }
+
+    /// Assign timestamps to the audio packets completed on one page, marking
+    /// the last one of the stream on the end-of-stream page
+    fn timestamp(
+        &mut self,
+        packets: Vec<Vec<u8>>,
+        granule_position: i64,
+        end_of_stream: bool,
+    ) -> Vec<DemuxedPacket> {
+        let Some(codec) = &mut self.codec else {
+            return Vec::new();
+        };
+        let durations: Vec<i64> = packets.iter().map(|p| codec.packet_duration(p)).collect();
+        if self.position.is_none() && granule_position >= 0 && !packets.is_empty() {
+            let total: i64 = durations.iter().sum();
+            self.position = Some((granule_position - total).max(0));
+        }
+
+        let count = packets.len();
+        let mut output = Vec::with_capacity(count);
+        for (i, (data, duration)) in packets.into_iter().zip(durations).enumerate() {
+            let pts = self.position.map(|position| position - codec.pts_offset());
+            if let Some(position) = &mut self.position {
+                *position += duration;
+            }
+            output.push(DemuxedPacket {
+                track_id: self.serial,
+                timescale: codec.sample_rate(),
+                packet: Packet::Audio(AudioPacket {
+                    data,
+                    pts,
+                    dts: pts,
+                    is_last: end_of_stream && i + 1 == count,
+                    encryption: None,
+                }),
+            });
+        }
+        output
+    }
+}
+
+/// Codec of a supported logical stream
+#[derive(Debug)]
+enum StreamCodec {
+    Vorbis(VorbisStream),
+    Opus(OpusHead),
+    Flac(FlacStream),
+}
+
+impl StreamCodec {
+    /// Identify the codec from a stream's first packet
+    ///
+    /// Returns `Ok(None)` for codecs other than Vorbis, Opus and FLAC.
+    fn identify(packet: &[u8]) -> Result<Option<Self>, MediaError> {
+        if packet.starts_with(b"\x01vorbis") {
+            VorbisStream::parse(packet).map(|vorbis| Some(Self::Vorbis(vorbis)))
+        } else if packet.starts_with(b"OpusHead") {
+            OpusHead::parse(packet).map(|opus| Some(Self::Opus(opus)))
+        } else if packet.starts_with(b"\x7FFLAC") {
+            FlacStream::parse(packet).map(|flac| Some(Self::Flac(flac)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn header_count(&self) -> usize {
+        match self {
+            Self::Vorbis(_) => 3,
+            Self::Opus(_) => 2,
+            Self::Flac(flac) => 1 + usize::from(flac.header_packets),
+        }
+    }
+
+    fn audio_codec(&self) -> AudioCodec {
+        match self {
+            Self::Vorbis(_) => AudioCodec::Vorbis,
+            Self::Opus(opus) => AudioCodec::Opus {
+                sample_rate: OPUS_SAMPLE_RATE,
+                channels: opus.channels,
+                application: OpusApplication::Audio,
+            },
+            Self::Flac(_) => AudioCodec::FLAC,
+        }
+    }
+
+    fn sample_rate(&self) -> u32 {
+        match self {
+            Self::Vorbis(vorbis) => vorbis.sample_rate,
+            Self::Opus(_) => OPUS_SAMPLE_RATE,
+            Self::Flac(flac) => flac.sample_rate,
+        }
+    }
+
+    fn channels(&self) -> u8 {
+        match self {
+            Self::Vorbis(vorbis) => vorbis.channels,
+            Self::Opus(opus) => opus.channels,
+            Self::Flac(flac) => flac.channels,
+        }
+    }
+
+    fn bitrate(&self) -> Option<u32> {
+        match self {
+            Self::Vorbis(vorbis) => vorbis.bitrate,
+            Self::Opus(_) | Self::Flac(_) => None,
+        }
+    }
+
+    /// Codec configuration in the layout Matroska uses for CodecPrivate:
+    /// the `OpusHead` packet, the three Vorbis headers Xiph-laced, or the
+    /// `fLaC` marker followed by the FLAC metadata blocks
+    fn extradata(&self, headers: &[Vec<u8>]) -> Vec<u8> {
+        match self {
+            Self::Opus(_) => headers.first().cloned().unwrap_or_default(),
+            Self::Flac(_) => {
+                let Some((mapping, metadata)) = headers.split_first() else {
+                    return Vec::new();
+                };
+                let mut extradata = mapping[FLAC_MAPPING_HEADER_SIZE..].to_vec();
+                for block in metadata {
+                    extradata.extend_from_slice(block);
+                }
+                extradata
+            }
+            Self::Vorbis(_) => {
+                let Some((last, laced)) = headers.split_last() else {
+                    return Vec::new();
+                };
+                let mut extradata = vec![laced.len() as u8];
+                for header in laced {
+                    extradata.extend(std::iter::repeat_n(255, header.len() / 255));
+                    extradata.push((header.len() % 255) as u8);
+                }
+                for header in laced {
+                    extradata.extend_from_slice(header);
+                }
+                extradata.extend_from_slice(last);
+                extradata
+            }
+        }
+    }
+
+    /// Granule position of the first sample to present
+    fn pts_offset(&self) -> i64 {
+        match self {
+            Self::Vorbis(_) | Self::Flac(_) => 0,
+            Self::Opus(opus) => i64::from(opus.pre_skip),
+        }
+    }
+
+    /// Convert a granule position to a presentation time
+    fn granule_to_duration(&self, granule_position: i64) -> Option<Duration> {
+        ticks_to_duration(
+            (granule_position - self.pts_offset()).max(0),
+            self.sample_rate(),
+        )
+    }
+
+    /// Number of samples a packet decodes to
+    fn packet_duration(&mut self, packet: &[u8]) -> i64 {
+        match self {
+            Self::Vorbis(vorbis) => vorbis.packet_duration(packet),
+            Self::Opus(_) => opus_packet_duration(packet),
+            Self::Flac(_) => flac_block_size(packet),
+        }
+    }
+
+    /// Forget inter-packet state after a gap
+    fn reset(&mut self) {
+        if let Self::Vorbis(vorbis) = self {
+            vorbis.previous_blocksize = None;
+        }
+    }
+}
+
+/// Vorbis stream parameters from the identification and setup headers
+#[derive(Debug)]
+struct VorbisStream {
+    sample_rate: u32,
+    channels: u8,
+    bitrate: Option<u32>,
+    /// Short and long block sizes
+    blocksizes: [u32; 2],
+    /// Whether each mode uses long blocks
+    mode_blockflags: Vec<bool>,
+    previous_blocksize: Option<u32>,
+}
+
+impl VorbisStream {
+    /// Parse the identification header
+    fn parse(header: &[u8]) -> Result<Self, MediaError> {
+        if header.len() < 30 {
+            return Err(malformed("Vorbis identification header too short"));
+        }
+        let channels = header[11];
+        let sample_rate = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+        let nominal_bitrate = i32::from_le_bytes([header[20], header[21], header[22], header[23]]);
+        let short = header[28] & 0x0F;
+        let long = header[28] >> 4;
+        if channels == 0
+            || sample_rate == 0
+            || !(6..=13).contains(&short)
+            || !(short..=13).contains(&long)
+        {
+            return Err(malformed("invalid Vorbis identification header"));
+        }
+
+        Ok(Self {
+            sample_rate,
+            channels,
+            bitrate: u32::try_from(nominal_bitrate).ok().filter(|&b| b > 0),
+            blocksizes: [1 << short, 1 << long],
+            mode_blockflags: Vec::new(),
+            previous_blocksize: None,
+        })
+    }
+
+    fn read_setup_header(&mut self, header: &[u8]) -> Result<(), MediaError> {
+        if !header.starts_with(b"\x05vorbis") {
+            return Err(malformed("expected Vorbis setup header"));
+        }
+        self.mode_blockflags = vorbis_mode_blockflags(header)
+            .ok_or_else(|| malformed("no modes found in Vorbis setup header"))?;
+        Ok(())
+    }
+
+    fn packet_duration(&mut self, packet: &[u8]) -> i64 {
+        let Some(&first) = packet.first() else {
+            return 0;
+        };
+        if first & 0x01 != 0 {
+            // Header packets carry no audio
+            return 0;
+        }
+
+        let mode_bits = usize::BITS - self.mode_blockflags.len().saturating_sub(1).leading_zeros();
+        let mode = usize::from(first >> 1) & ((1 << mode_bits) - 1);
+        let long = self.mode_blockflags.get(mode).copied().unwrap_or(false);
+        let blocksize = self.blocksizes[usize::from(long)];
+
+        // Each block overlaps half of the previous one; the first block only
+        // primes the overlap
+        let duration = self
+            .previous_blocksize
+            .map_or(0, |previous| (previous + blocksize) / 4);
+        self.previous_blocksize = Some(blocksize);
+        i64::from(duration)
+    }
+}
+
+/// Find the block flag of each mode in a Vorbis setup header
+///
+/// The modes are the last field of the setup header, but reaching them from
+/// the front means decoding every codebook, floor and residue. Instead this
+/// scans backwards from the framing bit, as FFmpeg's Vorbis parser does.
+/// Each mode is 41 bits: block flag, window type (0), transform type (0) and
+/// mapping (< 64); the mode list is preceded by a 6-bit mode count minus one.
+fn vorbis_mode_blockflags(header: &[u8]) -> Option<Vec<bool>> {
+    // Vorbis packs fields LSB-first
+    let bit = |index: usize| (header[index / 8] >> (index % 8)) & 1;
+    let field = |end: usize, width: usize| {
+        (end - width..end)
+            .rev()
+            .fold(0u32, |value, index| (value << 1) | u32::from(bit(index)))
+    };
+
+    let framing = (0..header.len() * 8).rev().find(|&index| bit(index) == 1)?;
+    let mut end = framing;
+    let mut count = 0;
+    let mut mode_count = 0;
+    while end >= 97 {
+        if field(end, 8) > 63 || field(end - 8, 16) != 0 || field(end - 24, 16) != 0 {
+            break;
+        }
+        end -= 41;
+        count += 1;
+        if count > 64 {
+            break;
+        }
+        if field(end, 6) + 1 == count {
+            mode_count = count;
+        }
+    }
+    if mode_count == 0 {
+        return None;
+    }
+
+    let mode_count = mode_count as usize;
+    Some(
+        (0..mode_count)
+            .map(|mode| bit(framing - 41 * (mode_count - mode)) == 1)
+            .collect(),
+    )
+}
+
+/// Opus stream parameters from `OpusHead`
+#[derive(Debug)]
+struct OpusHead {
+    channels: u8,
+    /// Samples at 48 kHz to discard from the start of the decoded output
+    pre_skip: u16,
+}
+
+impl OpusHead {
+    fn parse(header: &[u8]) -> Result<Self, MediaError> {
+        if header.len() < 19 {
+            return Err(malformed("OpusHead too short"));
+        }
+        let channels = header[9];
+        if channels == 0 {
+            return Err(malformed("OpusHead has no channels"));
+        }
+        Ok(Self {
+            channels,
+            pre_skip: u16::from_le_bytes([header[10], header[11]]),
+        })
+    }
+}
+
+/// FLAC stream parameters from the Ogg FLAC mapping header
+#[derive(Debug)]
+struct FlacStream {
+    sample_rate: u32,
+    channels: u8,
+    /// Metadata packets following the mapping header; 0 if unknown, in which
+    /// case they are passed on as audio packets of no duration
+    header_packets: u16,
+}
+
+impl FlacStream {
+    /// Parse the mapping header: `\x7FFLAC`, version, header packet count,
+    /// then `fLaC` and the STREAMINFO metadata block
+    fn parse(header: &[u8]) -> Result<Self, MediaError> {
+        if header.len() < FLAC_MAPPING_HEADER_SIZE + 42
+            || &header[FLAC_MAPPING_HEADER_SIZE..FLAC_MAPPING_HEADER_SIZE + 4] != b"fLaC"
+            || header[FLAC_MAPPING_HEADER_SIZE + 4] & 0x7F != 0
+        {
+            return Err(malformed("invalid Ogg FLAC mapping header"));
+        }
+        // Sample rate (20 bits), channels - 1 (3 bits) follow the block and
+        // frame size bounds in STREAMINFO
+        let info = &header[FLAC_MAPPING_HEADER_SIZE + 8..];
+        let sample_rate =
+            (u32::from(info[10]) << 12) | (u32::from(info[11]) << 4) | u32::from(info[12] >> 4);
+        if sample_rate == 0 {
+            return Err(malformed("FLAC STREAMINFO has no sample rate"));
+        }
+
+        Ok(Self {
+            sample_rate,
+            channels: ((info[12] >> 1) & 0x07) + 1,
+            header_packets: u16::from_be_bytes([header[7], header[8]]),
+        })
+    }
+}
+
+/// Number of samples in a FLAC frame, from its header; 0 for packets that
+/// are not frames
+fn flac_block_size(packet: &[u8]) -> i64 {
+    if packet.len() < 5 || packet[0] != 0xFF || packet[1] & 0xFE != 0xF8 {
+        return 0;
+    }
+    let code = packet[2] >> 4;
+    match code {
+        1 => 192,
+        2..=5 => 576 << (code - 2),
+        8..=15 => 256 << (code - 8),
+        6 | 7 => {
+            // The block size follows the UTF-8 coded frame or sample number
+            let number_len = match packet[4].leading_ones() {
+                0 => 1,
+                n => n as usize,
+            };
+            let at = 4 + number_len;
+            match (code, packet.get(at), packet.get(at + 1)) {
+                (6, Some(&size), _) => i64::from(size) + 1,
+                (7, Some(&high), Some(&low)) => i64::from(u16::from_be_bytes([high, low])) + 1,
+                _ => 0,
+            }
+        }
+        _ => 0,
+    }
+}
+
+/// Number of 48 kHz samples in an Opus packet, from its TOC byte (RFC 6716 §3.1)
+fn opus_packet_duration(packet: &[u8]) -> i64 {
+    let Some(&toc) = packet.first() else {
+        return 0;
+    };
+    let config = usize::from(toc >> 3);
+    let frame_size: i64 = match config {
+        // SILK: 10, 20, 40, 60 ms
+        0..=11 => [480, 960, 1920, 2880][config % 4],
+        // Hybrid: 10, 20 ms
+        12..=15 => [480, 960][config % 2],
+        // CELT: 2.5, 5, 10, 20 ms
+        _ => [120, 240, 480, 960][config % 4],
+    };
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => packet.get(1).map_or(0, |&count| i64::from(count & 0x3F)),
+    };
+    frame_size * frames
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opus_packet_duration() {
+        // CELT 20 ms, one frame
+        assert_eq!(opus_packet_duration(&[0xF8]), 960);
+        // SILK 60 ms, two frames
+        assert_eq!(opus_packet_duration(&[0x19]), 5760);
+        // Hybrid 10 ms, code 3 with 3 frames
+        assert_eq!(opus_packet_duration(&[0x63, 0x03]), 1440);
+        assert_eq!(opus_packet_duration(&[]), 0);
+    }
+
+    #[test]
+    fn test_flac_block_size() {
+        // 4096 samples
+        assert_eq!(flac_block_size(&[0xFF, 0xF8, 0xC9, 0x18, 0x00]), 4096);
+        // 8-bit block size after a two-byte frame number
+        assert_eq!(
+            flac_block_size(&[0xFF, 0xF8, 0x69, 0x18, 0xC2, 0x80, 0x0F]),
+            16
+        );
+        // 16-bit block size after a one-byte frame number
+        assert_eq!(
+            flac_block_size(&[0xFF, 0xF9, 0x79, 0x18, 0x05, 0x01, 0x1F]),
+            288
+        );
+        // Metadata block
+        assert_eq!(flac_block_size(&[0x84, 0x00, 0x00, 0x08, 0x00]), 0);
+    }
+
+    #[test]
+    fn test_vorbis_mode_blockflags() {
+        // Two modes (short, long) after arbitrary setup data
+        let mut bits = Vec::new();
+        let mut push = |value: u32, width: usize| {
+            bits.extend((0..width).map(|i| (value >> i) & 1 == 1));
+        };
+        for byte in b"\x05vorbis\xff\xff\xff\xff\xff\xff\xff\xff" {
+            push(u32::from(*byte), 8);
+        }
+        push(1, 6);
+        for blockflag in [0, 1] {
+            push(blockflag, 1);
+            push(0, 16);
+            push(0, 16);
+            push(0, 8);
+        }
+        push(1, 1);
+
+        let mut header = vec![0u8; bits.len().div_ceil(8)];
+        for (i, _) in bits.iter().enumerate().filter(|(_, &b)| b) {
+            header[i / 8] |= 1 << (i % 8);
+        }
+
+        assert_eq!(vorbis_mode_blockflags(&header), Some(vec![false, true]));
+        assert_eq!(vorbis_mode_blockflags(&[0; 4]), None);
+    }
 }
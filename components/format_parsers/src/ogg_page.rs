@@ -0,0 +1,269 @@
+//! Ogg page primitives
+//!
+//! An Ogg physical bitstream is a sequence of pages, each belonging to one
+//! logical stream (identified by its serial number):
+//!
+//! ```text
+//! +--------+---------+-------+----------+--------+----------+-------+----------+--------+------+
+//! | "OggS" | version | flags | granule  | serial | sequence | CRC   | segments | lacing | body |
+//! | 4 B    | 1 B     | 1 B   | 8 B      | 4 B    | 4 B      | 4 B   | 1 B      | n B    |      |
+//! +--------+---------+-------+----------+--------+----------+-------+----------+--------+------+
+//! ```
+//!
+//! All integers are little-endian. Packets are split into segments of up to
+//! 255 bytes; a lacing value below 255 ends a packet. A page whose last
+//! lacing value is 255 leaves its final packet open, and the next page of the
+//! stream continues it with [`FLAG_CONTINUED`] set.
+//!
+//! The CRC is CRC-32 with polynomial `0x04C11DB7`, initial value 0 and no
+//! bit reflection, computed over the whole page with the CRC field zeroed.
+
+/// Capture pattern that starts every page
+pub(crate) const CAPTURE_PATTERN: &[u8; 4] = b"OggS";
+
+/// The first packet on the page continues a packet from the previous page
+pub(crate) const FLAG_CONTINUED: u8 = 0x01;
+/// First page of a logical stream
+pub(crate) const FLAG_BOS: u8 = 0x02;
+/// Last page of a logical stream
+pub(crate) const FLAG_EOS: u8 = 0x04;
+
+/// Length of the fixed page header, up to and including the segment count
+const HEADER_LEN: usize = 27;
+
+/// Offset of the CRC field in the page header
+const CRC_OFFSET: usize = 22;
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut r = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            r = if r & 0x8000_0000 != 0 {
+                (r << 1) ^ 0x04C1_1DB7
+            } else {
+                r << 1
+            };
+            bit += 1;
+        }
+        table[i] = r;
+        i += 1;
+    }
+    table
+}
+
+/// Continue an Ogg CRC-32 over `data`
+fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &byte| {
+        (crc << 8) ^ CRC_TABLE[usize::from((crc >> 24) as u8 ^ byte)]
+    })
+}
+
+/// A complete, CRC-checked page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Page<'a> {
+    /// Header type flags
+    pub(crate) flags: u8,
+    /// Codec-defined position of the last packet completed on this page, or
+    /// -1 if no packet is completed
+    pub(crate) granule_position: i64,
+    /// Logical stream serial number
+    pub(crate) serial: u32,
+    /// Page sequence number within the logical stream
+    pub(crate) sequence: u32,
+    lacing: &'a [u8],
+    body: &'a [u8],
+}
+
+impl<'a> Page<'a> {
+    /// Whether the first packet continues one from the previous page
+    pub(crate) fn is_continued(&self) -> bool {
+        self.flags & FLAG_CONTINUED != 0
+    }
+
+    /// Whether this is the first page of a logical stream
+    pub(crate) fn is_bos(&self) -> bool {
+        self.flags & FLAG_BOS != 0
+    }
+
+    /// Whether this is the last page of a logical stream
+    pub(crate) fn is_eos(&self) -> bool {
+        self.flags & FLAG_EOS != 0
+    }
+
+    /// Total length of the page in bytes
+    pub(crate) fn len(&self) -> usize {
+        HEADER_LEN + self.lacing.len() + self.body.len()
+    }
+
+    /// Split the body into packet pieces
+    ///
+    /// Yields `(data, complete)` pairs. `complete` is false only for a final
+    /// piece that continues on the next page.
+    pub(crate) fn packets(&self) -> Vec<(&'a [u8], bool)> {
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        let mut end = 0;
+        for &lacing in self.lacing {
+            end += usize::from(lacing);
+            if lacing < 255 {
+                pieces.push((&self.body[start..end], true));
+                start = end;
+            }
+        }
+        if start < end {
+            pieces.push((&self.body[start..end], false));
+        }
+        pieces
+    }
+}
+
+/// Outcome of reading a page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PageStatus<'a> {
+    /// A complete page with a valid CRC
+    Page(Page<'a>),
+    /// `data` ends inside the page
+    Incomplete,
+    /// No page starts here, or the page is corrupted
+    Invalid,
+}
+
+/// Read the page at the start of `data`
+pub(crate) fn read_page(data: &[u8]) -> PageStatus<'_> {
+    if data.len() < HEADER_LEN {
+        return if CAPTURE_PATTERN.starts_with(&data[..data.len().min(4)]) {
+            PageStatus::Incomplete
+        } else {
+            PageStatus::Invalid
+        };
+    }
+    if &data[..4] != CAPTURE_PATTERN || data[4] != 0 {
+        return PageStatus::Invalid;
+    }
+
+    let segments = usize::from(data[26]);
+    if data.len() < HEADER_LEN + segments {
+        return PageStatus::Incomplete;
+    }
+    let lacing = &data[HEADER_LEN..HEADER_LEN + segments];
+    let body_len: usize = lacing.iter().map(|&l| usize::from(l)).sum();
+    let len = HEADER_LEN + segments + body_len;
+    if data.len() < len {
+        return PageStatus::Incomplete;
+    }
+
+    let stored_crc = u32::from_le_bytes([data[22], data[23], data[24], data[25]]);
+    let crc = crc32_update(0, &data[..CRC_OFFSET]);
+    let crc = crc32_update(crc, &[0; 4]);
+    let crc = crc32_update(crc, &data[CRC_OFFSET + 4..len]);
+    if crc != stored_crc {
+        return PageStatus::Invalid;
+    }
+
+    let mut granule = [0u8; 8];
+    granule.copy_from_slice(&data[6..14]);
+    PageStatus::Page(Page {
+        flags: data[5],
+        granule_position: i64::from_le_bytes(granule),
+        serial: u32::from_le_bytes([data[14], data[15], data[16], data[17]]),
+        sequence: u32::from_le_bytes([data[18], data[19], data[20], data[21]]),
+        lacing,
+        body: &data[HEADER_LEN + segments..len],
+    })
+}
+
+/// Offset of the next capture pattern at or after `from`
+pub(crate) fn find_capture_pattern(data: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(CAPTURE_PATTERN.len())
+        .position(|window| window == CAPTURE_PATTERN)
+        .map(|offset| from + offset)
+}
+
+/// Offset of the last capture pattern that fits entirely in `data`
+pub(crate) fn rfind_capture_pattern(data: &[u8]) -> Option<usize> {
+    data.windows(CAPTURE_PATTERN.len())
+        .rposition(|window| window == CAPTURE_PATTERN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(flags: u8, granule: i64, lacing: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut data = CAPTURE_PATTERN.to_vec();
+        data.push(0);
+        data.push(flags);
+        data.extend_from_slice(&granule.to_le_bytes());
+        data.extend_from_slice(&7u32.to_le_bytes());
+        data.extend_from_slice(&3u32.to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.push(lacing.len() as u8);
+        data.extend_from_slice(lacing);
+        data.extend_from_slice(body);
+        let crc = crc32_update(0, &data);
+        data[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_crc32_check_value() {
+        // CRC-32/POSIX without the final inversion
+        assert_eq!(crc32_update(0, b"123456789"), !0x765E_7680);
+    }
+
+    #[test]
+    fn test_read_page() {
+        let body = vec![0xAB; 300];
+        let data = page(FLAG_CONTINUED, 1234, &[255, 45], &body);
+
+        let PageStatus::Page(page) = read_page(&data) else {
+            panic!("expected a page");
+        };
+        assert!(page.is_continued());
+        assert!(!page.is_bos());
+        assert_eq!(page.granule_position, 1234);
+        assert_eq!(page.serial, 7);
+        assert_eq!(page.sequence, 3);
+        assert_eq!(page.len(), data.len());
+        assert_eq!(page.packets(), vec![(&body[..], true)]);
+
+        assert_eq!(read_page(&data[..data.len() - 1]), PageStatus::Incomplete);
+        assert_eq!(read_page(&data[..2]), PageStatus::Incomplete);
+    }
+
+    #[test]
+    fn test_read_page_rejects_bad_crc() {
+        let mut data = page(0, 0, &[3], b"abc");
+        data[28] ^= 0xFF;
+        assert_eq!(read_page(&data), PageStatus::Invalid);
+        assert_eq!(read_page(b"not a page at all, really"), PageStatus::Invalid);
+    }
+
+    #[test]
+    fn test_page_packets_split_on_lacing() {
+        let body = vec![1u8; 255 + 10 + 255];
+        let data = page(0, -1, &[255, 10, 255], &body);
+        let PageStatus::Page(page) = read_page(&data) else {
+            panic!("expected a page");
+        };
+        let packets = page.packets();
+        assert_eq!(packets.len(), 2);
+        assert_eq!((packets[0].0.len(), packets[0].1), (265, true));
+        assert_eq!((packets[1].0.len(), packets[1].1), (255, false));
+    }
+
+    #[test]
+    fn test_find_capture_pattern() {
+        assert_eq!(find_capture_pattern(b"xxOggSyy", 0), Some(2));
+        assert_eq!(find_capture_pattern(b"xxOggSyy", 3), None);
+        assert_eq!(find_capture_pattern(b"Ogg", 5), None);
+        assert_eq!(rfind_capture_pattern(b"OggSxOggSx"), Some(5));
+        assert_eq!(rfind_capture_pattern(b"OggSxOgg"), Some(0));
+    }
+}
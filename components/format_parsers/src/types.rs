@@ -1,7 +1,10 @@
 //! Type definitions for media information and track metadata
 
-use cortenbrowser_shared_types::{AudioCodec, VideoCodec};
-use std::collections::HashMap;
+use cortenbrowser_shared_types::{
+    AudioCodec, AudioPacket, AudioSpecificConfig, AvcDecoderConfig, ColorInfo, EncryptionInfo,
+    GaplessInfo, VideoCodec, VideoPacket,
+};
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
 /// Information about a media container
@@ -32,6 +35,12 @@ pub struct VideoTrackInfo {
     pub frame_rate: f32,
     /// Bitrate in bits per second (if available)
     pub bitrate: Option<u32>,
+    /// Codec configuration record (`avcC`, `hvcC`, `vpcC`, ...), empty if
+    /// the container carries none
+    pub extradata: Vec<u8>,
+    /// Color description from the MP4 `colr` box or Matroska `Colour`
+    /// element (if present)
+    pub color: Option<ColorInfo>,
 }
 
 /// Information about an audio track
@@ -47,6 +56,43 @@ pub struct AudioTrackInfo {
     pub channels: u8,
     /// Bitrate in bits per second (if available)
     pub bitrate: Option<u32>,
+    /// Codec configuration (AudioSpecificConfig for AAC, `OpusHead` for
+    /// Opus, Xiph-laced headers for Vorbis), empty if the container carries
+    /// none
+    pub extradata: Vec<u8>,
+    /// Encoder delay and padding to trim for gapless playback, if the
+    /// container signals them
+    pub gapless: Option<GaplessInfo>,
+}
+
+impl VideoTrackInfo {
+    /// Parse the `avcC` record of an H.264 track
+    ///
+    /// # Returns
+    ///
+    /// The record with its SPS and PPS lists, or `None` for other codecs and
+    /// missing or malformed extradata
+    pub fn avc_config(&self) -> Option<AvcDecoderConfig> {
+        match self.codec {
+            VideoCodec::H264 { .. } => AvcDecoderConfig::parse(&self.extradata).ok(),
+            _ => None,
+        }
+    }
+}
+
+impl AudioTrackInfo {
+    /// Parse the AudioSpecificConfig of an AAC track
+    ///
+    /// # Returns
+    ///
+    /// The configuration, or `None` for other codecs and missing or
+    /// malformed extradata
+    pub fn audio_specific_config(&self) -> Option<AudioSpecificConfig> {
+        match self.codec {
+            AudioCodec::AAC { .. } => AudioSpecificConfig::parse(&self.extradata).ok(),
+            _ => None,
+        }
+    }
 }
 
 impl Default for MediaInfo {
@@ -59,3 +105,168 @@ impl Default for MediaInfo {
         }
     }
 }
+
+/// A keyframe position in a container
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeekPoint {
+    /// Presentation time of the keyframe
+    pub time: Duration,
+    /// File offset to read from: the sample itself in MP4, its Cluster in
+    /// Matroska/WebM
+    pub byte_offset: u64,
+    /// One-based number of the sample in its track (MP4) or of the block in
+    /// its Cluster (Matroska/WebM), if known
+    pub sample_number: Option<u64>,
+}
+
+/// Per-track keyframe index for seeking without reading the whole file
+///
+/// Built from `stss`/`stco` in MP4 and from Cues in Matroska/WebM. Entries
+/// of each track are kept in time order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeekIndex {
+    tracks: BTreeMap<u32, Vec<SeekPoint>>,
+}
+
+impl SeekIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a keyframe of a track, keeping the track's entries in time order
+    pub fn insert(&mut self, track_id: u32, point: SeekPoint) {
+        let entries = self.tracks.entry(track_id).or_default();
+        let index = entries.partition_point(|p| p.time <= point.time);
+        entries.insert(index, point);
+    }
+
+    /// Returns whether the index has no entries
+    pub fn is_empty(&self) -> bool {
+        self.tracks.values().all(Vec::is_empty)
+    }
+
+    /// IDs of the indexed tracks, in ascending order
+    pub fn track_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.tracks.keys().copied()
+    }
+
+    /// Keyframes of a track in time order
+    pub fn entries(&self, track_id: u32) -> &[SeekPoint] {
+        self.tracks.get(&track_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// The latest keyframe of a track at or before `time`
+    pub fn entry_for_time(&self, track_id: u32, time: Duration) -> Option<&SeekPoint> {
+        let entries = self.entries(track_id);
+        let index = entries.partition_point(|p| p.time <= time);
+        index.checked_sub(1).map(|i| &entries[i])
+    }
+
+    /// Byte offset to read from to decode every track at `time`
+    ///
+    /// This is the lowest offset among the latest keyframes at or before
+    /// `time` of each track. Tracks with no such keyframe are ignored.
+    ///
+    /// # Returns
+    ///
+    /// The offset, or `None` if no track has a keyframe at or before `time`
+    pub fn byte_offset_for_time(&self, time: Duration) -> Option<u64> {
+        self.track_ids()
+            .filter_map(|track_id| self.entry_for_time(track_id, time))
+            .map(|point| point.byte_offset)
+            .min()
+    }
+}
+
+/// Compressed packet payload from a video or audio track
+#[derive(Debug, Clone)]
+pub enum Packet {
+    /// Video packet
+    Video(VideoPacket),
+    /// Audio packet
+    Audio(AudioPacket),
+}
+
+/// A compressed packet read from a container
+///
+/// Timestamps in the wrapped packet are expressed in units of `timescale`
+/// ticks per second, as stored in the container. Use [`DemuxedPacket::pts_time`]
+/// and [`DemuxedPacket::dts_time`] to convert them to wall-clock time.
+#[derive(Debug, Clone)]
+pub struct DemuxedPacket {
+    /// Track the packet belongs to
+    pub track_id: u32,
+    /// Timestamp units per second
+    pub timescale: u32,
+    /// Packet payload and timestamps
+    pub packet: Packet,
+}
+
+impl DemuxedPacket {
+    /// Returns the compressed packet data
+    pub fn data(&self) -> &[u8] {
+        match &self.packet {
+            Packet::Video(packet) => &packet.data,
+            Packet::Audio(packet) => &packet.data,
+        }
+    }
+
+    /// Returns the presentation timestamp in timescale units
+    pub fn pts(&self) -> Option<i64> {
+        match &self.packet {
+            Packet::Video(packet) => packet.pts,
+            Packet::Audio(packet) => packet.pts,
+        }
+    }
+
+    /// Returns the decode timestamp in timescale units
+    pub fn dts(&self) -> Option<i64> {
+        match &self.packet {
+            Packet::Video(packet) => packet.dts,
+            Packet::Audio(packet) => packet.dts,
+        }
+    }
+
+    /// Returns whether decoding can start at this packet
+    ///
+    /// Audio packets are always treated as keyframes.
+    pub fn is_keyframe(&self) -> bool {
+        match &self.packet {
+            Packet::Video(packet) => packet.is_keyframe,
+            Packet::Audio(_) => true,
+        }
+    }
+
+    /// Returns how the packet data is encrypted, if it is
+    pub fn encryption(&self) -> Option<&EncryptionInfo> {
+        match &self.packet {
+            Packet::Video(packet) => packet.encryption.as_ref(),
+            Packet::Audio(packet) => packet.encryption.as_ref(),
+        }
+    }
+
+    /// Returns the presentation timestamp as a duration
+    pub fn pts_time(&self) -> Option<Duration> {
+        self.pts()
+            .and_then(|pts| ticks_to_duration(pts, self.timescale))
+    }
+
+    /// Returns the decode timestamp as a duration
+    pub fn dts_time(&self) -> Option<Duration> {
+        self.dts()
+            .and_then(|dts| ticks_to_duration(dts, self.timescale))
+    }
+}
+
+/// Convert a non-negative tick count to a duration
+pub(crate) fn ticks_to_duration(ticks: i64, timescale: u32) -> Option<Duration> {
+    if ticks < 0 || timescale == 0 {
+        return None;
+    }
+    let ticks = ticks as u64;
+    let timescale = timescale as u64;
+    let secs = ticks / timescale;
+    let nanos = (ticks % timescale) * 1_000_000_000 / timescale;
+    Some(Duration::new(secs, nanos as u32))
+}
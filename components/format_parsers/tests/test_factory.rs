@@ -0,0 +1,207 @@
+//! Unit tests for container format probing and DemuxerFactory
+
+use cortenbrowser_format_parsers::{
+    ContainerFormat, Demuxer, DemuxerFactory, MatroskaDemuxer, Mp4Demuxer, OggDemuxer, WebmDemuxer,
+    PROBE_SCORE_MAX,
+};
+use cortenbrowser_shared_types::MediaError;
+
+/// Start of an MP4 file: ftyp box with major brand isom
+const MP4_PREFIX: &[u8] = b"\x00\x00\x00\x18ftypisom\x00\x00\x02\x00isomiso2";
+
+/// Start of an Ogg file: capture pattern, version 0, BOS flag
+const OGG_PREFIX: &[u8] = b"OggS\x00\x02\x00\x00\x00\x00\x00\x00\x00\x00";
+
+/// EBML header with the given DocType
+fn ebml_header(doc_type: &str) -> Vec<u8> {
+    let mut payload = vec![0x42, 0x86, 0x81, 0x01]; // EBMLVersion 1
+    payload.extend_from_slice(&[0x42, 0x82, 0x80 | doc_type.len() as u8]);
+    payload.extend_from_slice(doc_type.as_bytes());
+    payload.extend_from_slice(&[0x42, 0x87, 0x81, 0x04]); // DocTypeVersion 4
+
+    let mut header = vec![0x1A, 0x45, 0xDF, 0xA3, 0x80 | payload.len() as u8];
+    header.extend(payload);
+    // Start of a Segment with unknown size
+    header.extend_from_slice(&[0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF]);
+    header
+}
+
+fn probe_err(data: &[u8]) -> MediaError {
+    DemuxerFactory::create_for_data(data).unwrap_err()
+}
+
+/// Test each format's signature gets the maximum score from its own demuxer only
+#[test]
+fn test_probe_scores_signatures() {
+    assert_eq!(Mp4Demuxer::probe(MP4_PREFIX), PROBE_SCORE_MAX);
+    assert_eq!(OggDemuxer::probe(OGG_PREFIX), PROBE_SCORE_MAX);
+    assert_eq!(WebmDemuxer::probe(&ebml_header("webm")), PROBE_SCORE_MAX);
+    assert_eq!(
+        MatroskaDemuxer::probe(&ebml_header("matroska")),
+        PROBE_SCORE_MAX
+    );
+
+    assert_eq!(Mp4Demuxer::probe(OGG_PREFIX), 0);
+    assert_eq!(OggDemuxer::probe(MP4_PREFIX), 0);
+    assert_eq!(WebmDemuxer::probe(MP4_PREFIX), 0);
+    assert_eq!(MatroskaDemuxer::probe(OGG_PREFIX), 0);
+}
+
+/// Test probing picks the matching format
+#[test]
+fn test_probe_detects_formats() {
+    assert_eq!(
+        ContainerFormat::probe(MP4_PREFIX),
+        Some(ContainerFormat::Mp4)
+    );
+    assert_eq!(
+        ContainerFormat::probe(OGG_PREFIX),
+        Some(ContainerFormat::Ogg)
+    );
+    assert_eq!(
+        ContainerFormat::probe(&ebml_header("webm")),
+        Some(ContainerFormat::WebM)
+    );
+    assert_eq!(
+        ContainerFormat::probe(&ebml_header("matroska")),
+        Some(ContainerFormat::Matroska)
+    );
+    assert_eq!(ContainerFormat::probe(b"RIFF\x00\x00\x00\x00WAVE"), None);
+}
+
+/// Test WebM and Matroska, which share the EBML header, are told apart by DocType
+#[test]
+fn test_probe_ambiguous_ebml_prefix() {
+    let webm = ebml_header("webm");
+    let mkv = ebml_header("matroska");
+
+    // Both demuxers accept WebM, but WebM wins
+    assert!(MatroskaDemuxer::probe(&webm) > 0);
+    assert!(WebmDemuxer::probe(&webm) > MatroskaDemuxer::probe(&webm));
+    // Only Matroska accepts Matroska
+    assert_eq!(WebmDemuxer::probe(&mkv), 0);
+
+    // An unknown DocType is neither
+    assert_eq!(ContainerFormat::probe(&ebml_header("foo")), None);
+}
+
+/// Test truncated probe windows
+#[test]
+fn test_probe_truncated_window() {
+    // Cut before the DocType: EBML but undecided, Matroska is the safe choice
+    let webm = ebml_header("webm");
+    assert_eq!(
+        ContainerFormat::probe(&webm[..6]),
+        Some(ContainerFormat::Matroska)
+    );
+    // Cut inside the DocType payload
+    assert_eq!(
+        ContainerFormat::probe(&webm[..14]),
+        Some(ContainerFormat::Matroska)
+    );
+    // Cut right after the DocType
+    assert_eq!(
+        ContainerFormat::probe(&webm[..16]),
+        Some(ContainerFormat::WebM)
+    );
+
+    // Too short to recognise anything
+    assert_eq!(ContainerFormat::probe(&MP4_PREFIX[..6]), None);
+    assert_eq!(ContainerFormat::probe(&OGG_PREFIX[..3]), None);
+    assert_eq!(ContainerFormat::probe(&webm[..3]), None);
+    assert_eq!(ContainerFormat::probe(&[]), None);
+
+    // Exactly the capture pattern still suggests Ogg
+    assert_eq!(ContainerFormat::probe(b"OggS"), Some(ContainerFormat::Ogg));
+    assert_eq!(
+        ContainerFormat::probe(&MP4_PREFIX[..8]),
+        Some(ContainerFormat::Mp4)
+    );
+}
+
+/// Test create_for_data fails with UnsupportedFormat when nothing matches
+#[test]
+fn test_create_for_data_unsupported() {
+    assert!(matches!(
+        probe_err(b"definitely not media"),
+        MediaError::UnsupportedFormat { .. }
+    ));
+    assert!(matches!(
+        probe_err(&[]),
+        MediaError::UnsupportedFormat { .. }
+    ));
+}
+
+/// Test create_for_data returns a usable demuxer
+#[test]
+fn test_create_for_data_returns_demuxer() {
+    let mut demuxer = DemuxerFactory::create_for_data(OGG_PREFIX).unwrap();
+    // The prefix alone is not a complete Ogg stream, but loading goes
+    // through the Ogg demuxer
+    let info = demuxer.load(OGG_PREFIX).unwrap();
+    assert!(info.audio_tracks.is_empty());
+}
+
+/// Test MIME types select the container format
+#[test]
+fn test_from_mime() {
+    let cases = [
+        ("video/mp4", ContainerFormat::Mp4),
+        ("audio/mp4", ContainerFormat::Mp4),
+        ("video/webm", ContainerFormat::WebM),
+        ("audio/ogg", ContainerFormat::Ogg),
+        ("video/x-matroska", ContainerFormat::Matroska),
+        ("VIDEO/WEBM", ContainerFormat::WebM),
+    ];
+    for (mime, format) in cases {
+        assert_eq!(
+            ContainerFormat::from_mime(mime).unwrap(),
+            format,
+            "{}",
+            mime
+        );
+    }
+}
+
+/// Test MIME types with codecs parameters
+#[test]
+fn test_from_mime_with_codecs() {
+    assert_eq!(
+        ContainerFormat::from_mime("video/mp4; codecs=avc1.64001f").unwrap(),
+        ContainerFormat::Mp4
+    );
+    assert_eq!(
+        ContainerFormat::from_mime(r#"video/mp4; codecs="avc1.64001f, mp4a.40.2""#).unwrap(),
+        ContainerFormat::Mp4
+    );
+    assert_eq!(
+        ContainerFormat::from_mime(r#"video/webm;codecs="vp09.00.10.08, opus""#).unwrap(),
+        ContainerFormat::WebM
+    );
+    assert_eq!(
+        ContainerFormat::from_mime("audio/ogg; codecs=opus").unwrap(),
+        ContainerFormat::Ogg
+    );
+    assert_eq!(
+        ContainerFormat::from_mime("video/x-matroska; codecs=avc1.64001f,vorbis").unwrap(),
+        ContainerFormat::Matroska
+    );
+
+    // Codecs the container cannot carry
+    assert!(matches!(
+        ContainerFormat::from_mime("video/webm; codecs=avc1.64001f"),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+    assert!(ContainerFormat::from_mime("audio/ogg; codecs=mp4a.40.2").is_err());
+}
+
+/// Test create_for_mime rejects unknown MIME types
+#[test]
+fn test_create_for_mime() {
+    assert!(DemuxerFactory::create_for_mime("video/mp4; codecs=avc1.64001f").is_ok());
+    assert!(matches!(
+        DemuxerFactory::create_for_mime("text/html"),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+    assert!(DemuxerFactory::create_for_mime("").is_err());
+}
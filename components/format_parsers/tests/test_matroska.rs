@@ -1,6 +1,7 @@
 //! Unit tests for Matroska demuxer
 
 use cortenbrowser_format_parsers::{Demuxer, MatroskaDemuxer};
+use std::time::Duration;
 
 /// Test that MatroskaDemuxer can be created
 #[test]
@@ -28,3 +29,129 @@ fn test_matroska_demuxer_parse_empty_data() {
     let result = demuxer.parse(empty_data);
     assert!(result.is_err(), "Should fail to parse empty data");
 }
+
+use cortenbrowser_shared_types::{AV1Level, AV1Profile, AudioCodec, MediaError, VideoCodec};
+
+fn element(id: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = id
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|&b| b == 0)
+        .collect();
+    out.push(0x01);
+    out.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+    out.extend_from_slice(payload);
+    out
+}
+
+fn uint(id: u32, value: u64) -> Vec<u8> {
+    element(id, &value.to_be_bytes())
+}
+
+/// Matroska file with an AV1 and a Vorbis track and one cluster
+fn fixture_mkv() -> Vec<u8> {
+    let video = [
+        uint(0xD7, 1),
+        uint(0x83, 1),
+        element(0x86, b"V_AV1"),
+        // av1C: Main profile, seq_level_idx 12 (5.0)
+        element(0x63A2, &[0x81, 0x0C, 0x00, 0x00]),
+        element(0xE0, &[uint(0xB0, 3840), uint(0xBA, 2160)].concat()),
+    ]
+    .concat();
+    let audio = [
+        uint(0xD7, 2),
+        uint(0x83, 2),
+        element(0x86, b"A_VORBIS"),
+        element(0x63A2, b"\x02\x1e\x0fvorbis-headers"),
+    ]
+    .concat();
+
+    let cluster = [
+        uint(0xE7, 10),
+        element(0xA3, &[0x81, 0x00, 0x00, 0x80, 0xAA]),
+        element(0xA3, &[0x82, 0xFF, 0xFF, 0x80, 0xBB]),
+    ]
+    .concat();
+
+    let segment = [
+        // TimecodeScale of 1 µs
+        element(0x1549_A966, &uint(0x2A_D7B1, 1000)),
+        element(
+            0x1654_AE6B,
+            &[element(0xAE, &video), element(0xAE, &audio)].concat(),
+        ),
+        element(0x1F43_B675, &cluster),
+    ]
+    .concat();
+
+    [
+        element(0x1A45_DFA3, &element(0x4282, b"matroska")),
+        element(0x1853_8067, &segment),
+    ]
+    .concat()
+}
+
+/// Test parsing AV1 and Vorbis tracks
+#[test]
+fn test_matroska_demuxer_parse_tracks() {
+    let demuxer = MatroskaDemuxer::new();
+    let info = demuxer.parse(&fixture_mkv()).unwrap();
+
+    assert_eq!(
+        info.video_tracks[0].codec,
+        VideoCodec::AV1 {
+            profile: AV1Profile::Main,
+            level: AV1Level::Level5_0,
+        }
+    );
+    assert_eq!(info.video_tracks[0].width, 3840);
+    assert_eq!(info.audio_tracks[0].codec, AudioCodec::Vorbis);
+}
+
+/// Test packet timestamps honour TimecodeScale and negative relative timecodes
+#[test]
+fn test_matroska_demuxer_read_packets() {
+    let mut demuxer = MatroskaDemuxer::new();
+    demuxer.load(&fixture_mkv()).unwrap();
+    assert_eq!(
+        demuxer.codec_private(2),
+        Some(&b"\x02\x1e\x0fvorbis-headers"[..])
+    );
+
+    let video = demuxer.read_packet().unwrap().unwrap();
+    assert_eq!(
+        (video.track_id, video.pts(), video.data()),
+        (1, Some(10_000), &[0xAA][..])
+    );
+
+    let audio = demuxer.read_packet().unwrap().unwrap();
+    assert_eq!(
+        (audio.track_id, audio.pts(), audio.data()),
+        (2, Some(9_000), &[0xBB][..])
+    );
+
+    assert!(demuxer.read_packet().unwrap().is_none());
+    assert!(demuxer.is_finished());
+}
+
+/// Test that a file without Cues has no seek index but still plays
+#[test]
+fn test_matroska_demuxer_without_cues() {
+    let mut demuxer = MatroskaDemuxer::new();
+    demuxer.load(&fixture_mkv()).unwrap();
+    assert!(demuxer.seek_index().is_none());
+
+    assert!(matches!(
+        demuxer.seek(Duration::ZERO),
+        Err(MediaError::InvalidState(_))
+    ));
+    assert_eq!(demuxer.read_packet().unwrap().unwrap().track_id, 1);
+
+    // Fed data without Cues has none once the stream has ended either
+    let mut demuxer = MatroskaDemuxer::new();
+    demuxer.feed(&fixture_mkv()).unwrap();
+    demuxer.end_of_stream();
+    while demuxer.next_packet().unwrap().is_some() {}
+    assert!(demuxer.seek_index().is_none());
+}
@@ -1,6 +1,127 @@
 //! Unit tests for MP4 demuxer
 
-use cortenbrowser_format_parsers::{Demuxer, Mp4Demuxer};
+use cortenbrowser_format_parsers::{Demuxer, Mp4Demuxer, Packet};
+use cortenbrowser_shared_types::{
+    AACProfile, AudioCodec, EncryptionInfo, EncryptionScheme, GaplessInfo, MediaError, Subsample,
+    VideoCodec,
+};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::time::Duration;
+
+const VIDEO_TRACK: u32 = 1;
+const AUDIO_TRACK: u32 = 2;
+const VIDEO_SAMPLES: usize = 12;
+const AUDIO_SAMPLES: usize = 20;
+const KEYFRAME_INTERVAL: usize = 4;
+
+/// Composition offset of video sample `i` in video timescale units
+fn rendering_offset(i: usize) -> i32 {
+    if i.is_multiple_of(KEYFRAME_INTERVAL) {
+        0
+    } else {
+        80
+    }
+}
+
+/// Payload of a sample: its size varies, and the first bytes identify it
+fn sample_payload(track: u8, index: usize) -> Vec<u8> {
+    let mut data = vec![track, index as u8];
+    data.resize(10 + index * 7, 0xAB);
+    data
+}
+
+/// SPS and PPS of the fixture's H.264 track
+const SPS: [u8; 5] = [0x67, 0x64, 0x00, 0x1F, 0xAC];
+const PPS: [u8; 4] = [0x68, 0xEE, 0x3C, 0x80];
+
+/// Build a small MP4 with one H.264 track (25 fps, timescale 1000) and one
+/// AAC track (1024-sample frames, timescale 48000)
+fn fixture_mp4() -> Vec<u8> {
+    fixture_mp4_with_aac(mp4::SampleFreqIndex::Freq48000, mp4::ChannelConfig::Stereo)
+}
+
+/// Build the fixture with the given AAC sampling frequency and channels in
+/// its AudioSpecificConfig
+fn fixture_mp4_with_aac(
+    freq_index: mp4::SampleFreqIndex,
+    chan_conf: mp4::ChannelConfig,
+) -> Vec<u8> {
+    let mut writer = fixture_writer(freq_index, chan_conf);
+    for i in 0..VIDEO_SAMPLES {
+        writer
+            .write_sample(
+                VIDEO_TRACK,
+                &mp4::Mp4Sample {
+                    start_time: i as u64 * 40,
+                    duration: 40,
+                    rendering_offset: rendering_offset(i),
+                    is_sync: i.is_multiple_of(KEYFRAME_INTERVAL),
+                    bytes: mp4::Bytes::from(sample_payload(VIDEO_TRACK as u8, i)),
+                },
+            )
+            .unwrap();
+    }
+    for i in 0..AUDIO_SAMPLES {
+        writer
+            .write_sample(
+                AUDIO_TRACK,
+                &mp4::Mp4Sample {
+                    start_time: i as u64 * 1024,
+                    duration: 1024,
+                    rendering_offset: 0,
+                    is_sync: true,
+                    bytes: mp4::Bytes::from(sample_payload(AUDIO_TRACK as u8, i)),
+                },
+            )
+            .unwrap();
+    }
+
+    writer.write_end().unwrap();
+    writer.into_writer().into_inner()
+}
+
+/// Start writing the fixture's tracks, without samples
+fn fixture_writer(
+    freq_index: mp4::SampleFreqIndex,
+    chan_conf: mp4::ChannelConfig,
+) -> mp4::Mp4Writer<Cursor<Vec<u8>>> {
+    let config = mp4::Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("avc1").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = mp4::Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+
+    writer
+        .add_track(&mp4::TrackConfig {
+            track_type: mp4::TrackType::Video,
+            timescale: 1000,
+            language: "und".to_string(),
+            media_conf: mp4::MediaConfig::AvcConfig(mp4::AvcConfig {
+                width: 320,
+                height: 240,
+                seq_param_set: SPS.to_vec(),
+                pic_param_set: PPS.to_vec(),
+            }),
+        })
+        .unwrap();
+    writer
+        .add_track(&mp4::TrackConfig {
+            track_type: mp4::TrackType::Audio,
+            timescale: 48000,
+            language: "und".to_string(),
+            media_conf: mp4::MediaConfig::AacConfig(mp4::AacConfig {
+                bitrate: 128000,
+                profile: mp4::AudioObjectType::AacLowComplexity,
+                freq_index,
+                chan_conf,
+            }),
+        })
+        .unwrap();
+    writer
+}
 
 /// Test that Mp4Demuxer can be created
 #[test]
@@ -62,3 +183,1004 @@ fn test_mp4_demuxer_parse_minimal_valid() {
         }
     }
 }
+
+/// Test that loading the fixture exposes both tracks and their sample counts
+#[test]
+fn test_mp4_demuxer_load_fixture() {
+    let mut demuxer = Mp4Demuxer::new();
+    let info = demuxer.load(&fixture_mp4()).unwrap();
+
+    assert_eq!(info.video_tracks.len(), 1);
+    assert_eq!(info.audio_tracks.len(), 1);
+    assert_eq!(demuxer.sample_count(VIDEO_TRACK), Some(VIDEO_SAMPLES));
+    assert_eq!(demuxer.sample_count(AUDIO_TRACK), Some(AUDIO_SAMPLES));
+    assert!(demuxer.get_video_track(VIDEO_TRACK).is_some());
+}
+
+/// Test that the `avcC` record is exposed byte for byte, with its SPS/PPS
+#[test]
+fn test_mp4_demuxer_avcc_extradata() {
+    let data = fixture_mp4();
+    let start = data.windows(4).position(|w| w == b"avcC").unwrap() - 4;
+    let size = u32::from_be_bytes(data[start..start + 4].try_into().unwrap()) as usize;
+    let avcc_payload = &data[start + 8..start + size];
+
+    let mut demuxer = Mp4Demuxer::new();
+    let info = demuxer.load(&data).unwrap();
+    let video = &info.video_tracks[0];
+
+    assert_eq!(video.extradata, avcc_payload);
+    assert_eq!(
+        video.extradata,
+        [
+            &[0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x05][..],
+            &SPS,
+            &[0x01, 0x00, 0x04],
+            &PPS
+        ]
+        .concat()
+    );
+    let avc_config = video.avc_config().unwrap();
+    assert_eq!(avc_config.nal_length_size, 4);
+    assert_eq!(avc_config.sps, vec![SPS.to_vec()]);
+    assert_eq!(avc_config.pps, vec![PPS.to_vec()]);
+    assert_eq!(video.color, None);
+}
+
+/// Test that the AAC sample rate and channels come from the
+/// AudioSpecificConfig
+#[test]
+fn test_mp4_demuxer_aac_audio_specific_config() {
+    let data = fixture_mp4_with_aac(mp4::SampleFreqIndex::Freq22050, mp4::ChannelConfig::Mono);
+
+    let info = Mp4Demuxer::new().parse(&data).unwrap();
+    let audio = &info.audio_tracks[0];
+
+    // AAC LC (2), frequency index 7, 1 channel
+    assert_eq!(audio.extradata, vec![0x13, 0x88]);
+    assert_eq!(audio.sample_rate, 22050);
+    assert_eq!(audio.channels, 1);
+    assert_eq!(audio.audio_specific_config().unwrap().sample_rate, 22050);
+    assert!(matches!(
+        audio.codec,
+        AudioCodec::AAC {
+            profile: AACProfile::LC,
+            sample_rate: 22050,
+            channels: 1,
+        }
+    ));
+}
+
+fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut data = ((8 + payload.len()) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(box_type);
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Append `child` to the `moov` box of the fixture, which follows `mdat` so
+/// no chunk offsets move
+fn fixture_mp4_with_moov_child(child: &[u8]) -> Vec<u8> {
+    let data = fixture_mp4();
+    let mut out = Vec::new();
+    for (box_type, bytes) in top_level_boxes(&data) {
+        if &box_type == b"moov" {
+            out.extend(mp4_box(b"moov", &[&bytes[8..], child].concat()));
+        } else {
+            out.extend_from_slice(bytes);
+        }
+    }
+    out
+}
+
+/// Test that the encoder delay and padding come from an iTunSMPB tag
+#[test]
+fn test_mp4_demuxer_itunsmpb_gapless_info() {
+    let value = b" 00000000 00000840 000001C0 0000000000004C00 00000000";
+    let item = [
+        mp4_box(b"mean", &[&[0; 4], &b"com.apple.iTunes"[..]].concat()),
+        mp4_box(b"name", &[&[0; 4], &b"iTunSMPB"[..]].concat()),
+        mp4_box(b"data", &[&[0, 0, 0, 1, 0, 0, 0, 0], &value[..]].concat()),
+    ]
+    .concat();
+    let ilst = mp4_box(b"ilst", &mp4_box(b"----", &item));
+    let hdlr = mp4_box(b"hdlr", &[&[0; 8], &b"mdir"[..], &[0; 13]].concat());
+    let meta = mp4_box(b"meta", &[&[0; 4], &hdlr[..], &ilst[..]].concat());
+    let udta = mp4_box(b"udta", &meta);
+    let data = fixture_mp4_with_moov_child(&udta);
+
+    let info = Mp4Demuxer::new().parse(&data).unwrap();
+
+    assert_eq!(
+        info.audio_tracks[0].gapless,
+        Some(GaplessInfo {
+            encoder_delay: 2112,
+            padding: 448,
+        })
+    );
+    assert_eq!(info.video_tracks.len(), 1);
+}
+
+/// Test that the title, artist and album come from `udta`, iTunes items
+/// first, and are known as soon as a fed `moov` box is parsed
+#[test]
+fn test_mp4_demuxer_udta_metadata() {
+    let text_item = |item_type: &[u8; 4], value: &str| {
+        mp4_box(
+            item_type,
+            &mp4_box(
+                b"data",
+                &[&[0, 0, 0, 1, 0, 0, 0, 0], value.as_bytes()].concat(),
+            ),
+        )
+    };
+    let quicktime_item = |item_type: &[u8; 4], value: &str| {
+        let header = [&(value.len() as u16).to_be_bytes()[..], &[0x15, 0xC7]].concat();
+        mp4_box(item_type, &[&header[..], value.as_bytes()].concat())
+    };
+    let ilst = mp4_box(
+        b"ilst",
+        &[
+            text_item(b"\xa9nam", "Song"),
+            text_item(b"\xa9ART", "Artist"),
+            // Cover art is not text
+            mp4_box(b"covr", &mp4_box(b"data", &[0, 0, 0, 13, 0, 0, 0, 0, 0xFF])),
+        ]
+        .concat(),
+    );
+    let hdlr = mp4_box(b"hdlr", &[&[0; 8], &b"mdir"[..], &[0; 13]].concat());
+    let meta = mp4_box(b"meta", &[&[0; 4], &hdlr[..], &ilst[..]].concat());
+    let udta = mp4_box(
+        b"udta",
+        &[
+            quicktime_item(b"\xa9nam", "Old song"),
+            quicktime_item(b"\xa9alb", "Album"),
+            meta,
+        ]
+        .concat(),
+    );
+    let data = fixture_mp4_with_moov_child(&udta);
+
+    let info = Mp4Demuxer::new().parse(&data).unwrap();
+    let expected: HashMap<String, String> =
+        [("title", "Song"), ("artist", "Artist"), ("album", "Album")]
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+    assert_eq!(info.metadata, expected);
+
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.feed(&data).unwrap();
+    assert_eq!(demuxer.media_info().unwrap().metadata, expected);
+
+    // Files without udta have no metadata
+    assert!(Mp4Demuxer::new()
+        .parse(&fixture_mp4())
+        .unwrap()
+        .metadata
+        .is_empty());
+}
+
+/// Test that only the last audio packet is marked as the end of the stream
+#[test]
+fn test_mp4_demuxer_marks_last_audio_packet() {
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&fixture_mp4()).unwrap();
+
+    let mut last = Vec::new();
+    while let Some(packet) = demuxer.next_sample(AUDIO_TRACK).unwrap() {
+        let Packet::Audio(audio) = packet.packet else {
+            panic!("expected an audio packet");
+        };
+        last.push(audio.is_last);
+    }
+
+    assert_eq!(last.len(), AUDIO_SAMPLES);
+    assert_eq!(last.iter().filter(|&&is_last| is_last).count(), 1);
+    assert!(last[AUDIO_SAMPLES - 1]);
+}
+
+/// Test that fed data exposes the same codec configuration as loaded data
+#[test]
+fn test_mp4_demuxer_feed_extradata() {
+    let data = fixture_mp4();
+    let expected = Mp4Demuxer::new().parse(&data).unwrap();
+
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.feed(&data).unwrap();
+    let info = demuxer.media_info().unwrap();
+
+    assert_eq!(
+        info.video_tracks[0].extradata,
+        expected.video_tracks[0].extradata
+    );
+    assert_eq!(
+        info.audio_tracks[0].extradata,
+        expected.audio_tracks[0].extradata
+    );
+    assert!(!info.audio_tracks[0].extradata.is_empty());
+}
+
+/// Test reading video samples: sizes, keyframes and composition offsets
+#[test]
+fn test_mp4_demuxer_next_sample_video() {
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&fixture_mp4()).unwrap();
+
+    let mut count = 0;
+    while let Some(packet) = demuxer.next_sample(VIDEO_TRACK).unwrap() {
+        assert_eq!(packet.track_id, VIDEO_TRACK);
+        assert_eq!(packet.timescale, 1000);
+        assert_eq!(
+            packet.data(),
+            sample_payload(VIDEO_TRACK as u8, count).as_slice()
+        );
+        assert_eq!(
+            packet.is_keyframe(),
+            count.is_multiple_of(KEYFRAME_INTERVAL)
+        );
+        assert_eq!(packet.dts(), Some(count as i64 * 40));
+        assert_eq!(
+            packet.pts(),
+            Some(count as i64 * 40 + rendering_offset(count) as i64)
+        );
+        assert!(matches!(packet.packet, Packet::Video(_)));
+        count += 1;
+    }
+
+    assert_eq!(count, VIDEO_SAMPLES);
+    assert!(demuxer.next_sample(VIDEO_TRACK).unwrap().is_none());
+}
+
+/// Test reading audio samples
+#[test]
+fn test_mp4_demuxer_next_sample_audio() {
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&fixture_mp4()).unwrap();
+
+    let mut count = 0;
+    while let Some(packet) = demuxer.next_sample(AUDIO_TRACK).unwrap() {
+        assert_eq!(
+            packet.data(),
+            sample_payload(AUDIO_TRACK as u8, count).as_slice()
+        );
+        assert_eq!(packet.dts(), Some(count as i64 * 1024));
+        assert_eq!(packet.pts(), packet.dts());
+        assert!(packet.is_keyframe());
+        assert!(matches!(packet.packet, Packet::Audio(_)));
+        count += 1;
+    }
+
+    assert_eq!(count, AUDIO_SAMPLES);
+}
+
+/// Test that read_packet interleaves tracks in decode order
+#[test]
+fn test_mp4_demuxer_read_packet_interleaves_by_dts() {
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&fixture_mp4()).unwrap();
+
+    let mut last_time = std::time::Duration::ZERO;
+    let mut last_dts = std::collections::HashMap::new();
+    let mut video = 0;
+    let mut audio = 0;
+
+    while let Some(packet) = demuxer.read_packet().unwrap() {
+        let time = packet.dts_time().unwrap();
+        assert!(time >= last_time, "DTS went backwards across tracks");
+        last_time = time;
+
+        let dts = packet.dts().unwrap();
+        if let Some(previous) = last_dts.insert(packet.track_id, dts) {
+            assert!(dts > previous, "DTS not increasing within track");
+        }
+
+        match packet.packet {
+            Packet::Video(_) => video += 1,
+            Packet::Audio(_) => audio += 1,
+        }
+    }
+
+    assert_eq!(video, VIDEO_SAMPLES);
+    assert_eq!(audio, AUDIO_SAMPLES);
+}
+
+/// Test that loading again rewinds all tracks
+#[test]
+fn test_mp4_demuxer_load_resets_position() {
+    let data = fixture_mp4();
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&data).unwrap();
+    while demuxer.read_packet().unwrap().is_some() {}
+
+    demuxer.load(&data).unwrap();
+    let packet = demuxer.read_packet().unwrap().unwrap();
+    assert_eq!(packet.dts(), Some(0));
+}
+
+/// Test packet reads fail before data is loaded
+#[test]
+fn test_mp4_demuxer_read_packet_not_loaded() {
+    let mut demuxer = Mp4Demuxer::new();
+
+    assert!(matches!(
+        demuxer.read_packet(),
+        Err(MediaError::InvalidState(_))
+    ));
+    assert!(matches!(
+        demuxer.next_sample(VIDEO_TRACK),
+        Err(MediaError::InvalidState(_))
+    ));
+}
+
+/// Test reading from an unknown track fails
+#[test]
+fn test_mp4_demuxer_next_sample_unknown_track() {
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&fixture_mp4()).unwrap();
+
+    assert!(matches!(
+        demuxer.next_sample(99),
+        Err(MediaError::InvalidParameter(_))
+    ));
+}
+
+/// Packet fields compared between one-shot and incremental reads
+type Summary = (u32, Option<i64>, Option<i64>, bool, Vec<u8>);
+
+fn summarize(packet: &cortenbrowser_format_parsers::DemuxedPacket) -> Summary {
+    (
+        packet.track_id,
+        packet.dts(),
+        packet.pts(),
+        packet.is_keyframe(),
+        packet.data().to_vec(),
+    )
+}
+
+/// Packets of `data` read after loading it in one piece
+fn loaded_packets(data: &[u8]) -> Vec<Summary> {
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(data).unwrap();
+    std::iter::from_fn(|| demuxer.read_packet().unwrap())
+        .map(|packet| summarize(&packet))
+        .collect()
+}
+
+/// Split top-level boxes into (type, bytes)
+fn top_level_boxes(data: &[u8]) -> Vec<([u8; 4], &[u8])> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        boxes.push((
+            data[pos + 4..pos + 8].try_into().unwrap(),
+            &data[pos..pos + size],
+        ));
+        pos += size;
+    }
+    boxes
+}
+
+/// Add `delta` to every chunk offset in the `stco` boxes below `container`
+fn shift_chunk_offsets(container: &mut [u8], delta: u32) {
+    let mut pos = 8;
+    while pos < container.len() {
+        let size = u32::from_be_bytes(container[pos..pos + 4].try_into().unwrap()) as usize;
+        let child = &mut container[pos..pos + size];
+        match &child[4..8] {
+            b"trak" | b"mdia" | b"minf" | b"stbl" => shift_chunk_offsets(child, delta),
+            b"stco" => {
+                let count = u32::from_be_bytes(child[12..16].try_into().unwrap()) as usize;
+                for entry in child[16..16 + count * 4].chunks_mut(4) {
+                    let offset = u32::from_be_bytes(entry.try_into().unwrap());
+                    entry.copy_from_slice(&(offset + delta).to_be_bytes());
+                }
+            }
+            _ => {}
+        }
+        pos += size;
+    }
+}
+
+/// Rewrite the fixture with `moov` ahead of `mdat`, as for progressive download
+fn fixture_mp4_moov_first() -> Vec<u8> {
+    let data = fixture_mp4();
+    let boxes = top_level_boxes(&data);
+    let mut moov = boxes.iter().find(|(t, _)| t == b"moov").unwrap().1.to_vec();
+    let moov_len = moov.len() as u32;
+    shift_chunk_offsets(&mut moov, moov_len);
+
+    let mut out = Vec::new();
+    for (box_type, bytes) in &boxes {
+        match box_type {
+            b"moov" => {}
+            b"mdat" => {
+                out.extend_from_slice(&moov);
+                out.extend_from_slice(bytes);
+            }
+            _ => out.extend_from_slice(bytes),
+        }
+    }
+    out
+}
+
+/// Feed `data` in chunks, reading packets after every chunk
+fn fed_packets(data: &[u8], chunk_size: usize) -> (Mp4Demuxer, Vec<Summary>) {
+    let mut demuxer = Mp4Demuxer::new();
+    let mut packets = Vec::new();
+    for chunk in data.chunks(chunk_size) {
+        demuxer.feed(chunk).unwrap();
+        while let Some(packet) = demuxer.next_packet().unwrap() {
+            packets.push(summarize(&packet));
+        }
+    }
+    (demuxer, packets)
+}
+
+/// Test that feeding the fixture in chunks yields the one-shot packets
+#[test]
+fn test_mp4_demuxer_feed_chunks_matches_load() {
+    for data in [fixture_mp4(), fixture_mp4_moov_first()] {
+        let expected = loaded_packets(&data);
+        assert_eq!(expected.len(), VIDEO_SAMPLES + AUDIO_SAMPLES);
+
+        for chunk_size in [1, 100, 4096] {
+            let (mut demuxer, packets) = fed_packets(&data, chunk_size);
+            assert_eq!(packets, expected, "chunk size {}", chunk_size);
+
+            demuxer.end_of_stream();
+            assert!(demuxer.next_packet().unwrap().is_none());
+        }
+    }
+}
+
+/// Test that moov at the end holds back packets until it arrives
+#[test]
+fn test_mp4_demuxer_feed_waits_for_trailing_moov() {
+    let data = fixture_mp4();
+    let moov_start = data.len() - top_level_boxes(&data).last().unwrap().1.len();
+
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.feed(&data[..moov_start + 8]).unwrap();
+    assert!(demuxer.media_info().is_none());
+    assert!(demuxer.next_packet().unwrap().is_none());
+
+    demuxer.feed(&data[moov_start + 8..]).unwrap();
+    assert_eq!(demuxer.media_info().unwrap().video_tracks.len(), 1);
+    assert!(demuxer.next_packet().unwrap().is_some());
+}
+
+/// Test that moov ahead of mdat lets packets out before all data arrives
+#[test]
+fn test_mp4_demuxer_feed_moov_first_is_progressive() {
+    let data = fixture_mp4_moov_first();
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.feed(&data[..data.len() / 2]).unwrap();
+
+    assert!(demuxer.media_info().is_some());
+    let mut count = 0;
+    while demuxer.next_packet().unwrap().is_some() {
+        count += 1;
+    }
+    assert!(count > 0 && count < VIDEO_SAMPLES + AUDIO_SAMPLES);
+}
+
+/// Test that a truncated stream is reported once it has ended
+#[test]
+fn test_mp4_demuxer_feed_truncated_stream() {
+    let data = fixture_mp4_moov_first();
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.feed(&data[..data.len() - 10]).unwrap();
+    while demuxer.next_packet().unwrap().is_some() {}
+
+    demuxer.end_of_stream();
+    assert!(matches!(
+        demuxer.next_packet(),
+        Err(MediaError::CodecError { .. })
+    ));
+}
+
+/// Test that malformed box headers are rejected while feeding
+#[test]
+fn test_mp4_demuxer_feed_invalid_box_size() {
+    let mut demuxer = Mp4Demuxer::new();
+    assert!(matches!(
+        demuxer.feed(&[0, 0, 0, 4, b'f', b't', b'y', b'p']),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+}
+
+/// Test that loaded data cannot be extended
+#[test]
+fn test_mp4_demuxer_feed_after_load() {
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&fixture_mp4()).unwrap();
+
+    assert!(matches!(
+        demuxer.feed(&[0; 8]),
+        Err(MediaError::InvalidState(_))
+    ));
+}
+
+/// Test that the seek index lists the sync samples at their file offsets
+#[test]
+fn test_mp4_demuxer_seek_index_keyframes() {
+    let data = fixture_mp4();
+    let mut demuxer = Mp4Demuxer::new();
+    assert!(demuxer.seek_index().is_none());
+    demuxer.load(&data).unwrap();
+    let index = demuxer.seek_index().unwrap();
+
+    let video = index.entries(VIDEO_TRACK);
+    assert_eq!(
+        video
+            .iter()
+            .map(|p| (p.time, p.sample_number))
+            .collect::<Vec<_>>(),
+        vec![
+            (Duration::ZERO, Some(1)),
+            (Duration::from_millis(160), Some(5)),
+            (Duration::from_millis(320), Some(9)),
+        ]
+    );
+    // Every audio sample is a sync sample
+    assert_eq!(index.entries(AUDIO_TRACK).len(), AUDIO_SAMPLES);
+
+    // Offsets point at the sample payloads
+    for (track, points) in [
+        (VIDEO_TRACK, video),
+        (AUDIO_TRACK, index.entries(AUDIO_TRACK)),
+    ] {
+        for point in points {
+            let offset = point.byte_offset as usize;
+            let number = point.sample_number.unwrap() as u8 - 1;
+            assert_eq!(&data[offset..offset + 2], &[track as u8, number]);
+        }
+    }
+
+    // At 200 ms: video keyframe 5 (160 ms) and audio sample 10 (192 ms)
+    let video_offset = video[1].byte_offset;
+    let audio_offset = index.entries(AUDIO_TRACK)[9].byte_offset;
+    assert_eq!(
+        index.byte_offset_for_time(Duration::from_millis(200)),
+        Some(video_offset.min(audio_offset))
+    );
+    assert_eq!(
+        index
+            .entry_for_time(VIDEO_TRACK, Duration::from_millis(319))
+            .unwrap()
+            .sample_number,
+        Some(5)
+    );
+}
+
+/// Test that seeking restarts every track at its keyframe from fed data
+#[test]
+fn test_mp4_demuxer_seek_feeds_from_keyframe() {
+    let data = fixture_mp4();
+    let expected: Vec<_> = loaded_packets(&data)
+        .into_iter()
+        .filter(|p| match p.0 {
+            VIDEO_TRACK => p.4[1] >= 4,
+            _ => p.4[1] >= 9,
+        })
+        .collect();
+
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&data).unwrap();
+    let offset = demuxer.seek(Duration::from_millis(200)).unwrap();
+    assert_eq!(
+        Some(offset),
+        demuxer
+            .seek_index()
+            .unwrap()
+            .byte_offset_for_time(Duration::from_millis(200))
+    );
+
+    // Waits for the data from the offset
+    assert!(demuxer.next_packet().unwrap().is_none());
+    for chunk in data[offset as usize..].chunks(4096) {
+        demuxer.feed(chunk).unwrap();
+    }
+    demuxer.end_of_stream();
+
+    let packets: Vec<_> = std::iter::from_fn(|| demuxer.next_packet().unwrap())
+        .map(|packet| summarize(&packet))
+        .collect();
+    assert_eq!(packets, expected);
+    assert!(packets[0].3, "reading starts at a keyframe");
+}
+
+/// Test seeking before any data is loaded
+#[test]
+fn test_mp4_demuxer_seek_not_loaded() {
+    let mut demuxer = Mp4Demuxer::new();
+    assert!(matches!(
+        demuxer.seek(Duration::ZERO),
+        Err(MediaError::InvalidState(_))
+    ));
+}
+
+const KEY_ID: [u8; 16] = [0x10; 16];
+
+/// IV of encrypted video sample `i`
+fn sample_iv(i: usize) -> [u8; 8] {
+    [i as u8; 8]
+}
+
+/// Auxiliary information of video sample `i`: its IV and one subsample
+/// leaving the two identifying bytes clear
+fn sample_aux_info(i: usize) -> Vec<u8> {
+    let size = sample_payload(VIDEO_TRACK as u8, i).len() as u32;
+    let mut aux = sample_iv(i).to_vec();
+    aux.extend_from_slice(&1u16.to_be_bytes());
+    aux.extend_from_slice(&2u16.to_be_bytes());
+    aux.extend_from_slice(&(size - 2).to_be_bytes());
+    aux
+}
+
+/// Apply `edit` to every box at `path` below the boxes in `data`, fixing up
+/// the sizes of their parents
+fn edit_boxes(data: &[u8], path: &[&[u8; 4]], edit: &dyn Fn(&[u8]) -> Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (box_type, bytes) in top_level_boxes(data) {
+        if &box_type != path[0] {
+            out.extend_from_slice(bytes);
+        } else if path.len() == 1 {
+            out.extend(edit(bytes));
+        } else {
+            out.extend(mp4_box(
+                &box_type,
+                &edit_boxes(&bytes[8..], &path[1..], edit),
+            ));
+        }
+    }
+    out
+}
+
+/// Turn the video track of the fixture into a `cenc` encrypted one, with
+/// `aux_info` added to its sample table
+fn fixture_mp4_encrypted(aux_info: &[u8]) -> Vec<u8> {
+    let mut tenc = vec![0, 0, 0, 0, 0, 0, 1, 8];
+    tenc.extend_from_slice(&KEY_ID);
+    let sinf = mp4_box(
+        b"sinf",
+        &[
+            mp4_box(b"frma", b"avc1"),
+            mp4_box(
+                b"schm",
+                &[&[0, 0, 0, 0][..], b"cenc", &[0, 1, 0, 0]].concat(),
+            ),
+            mp4_box(b"schi", &mp4_box(b"tenc", &tenc)),
+        ]
+        .concat(),
+    );
+    edit_boxes(
+        &fixture_mp4(),
+        &[b"moov", b"trak", b"mdia", b"minf", b"stbl"],
+        &|stbl| {
+            let children = edit_boxes(&stbl[8..], &[b"stsd"], &|stsd| match &stsd[20..24] {
+                b"avc1" => {
+                    let entry = mp4_box(b"encv", &[&stsd[24..], &sinf[..]].concat());
+                    mp4_box(b"stsd", &[&stsd[8..16], &entry[..]].concat())
+                }
+                _ => stsd.to_vec(),
+            });
+            if children.windows(4).any(|w| w == b"encv") {
+                mp4_box(b"stbl", &[&children[..], aux_info].concat())
+            } else {
+                mp4_box(b"stbl", &children)
+            }
+        },
+    )
+}
+
+/// Test that the encryption of each sample comes from `senc`
+#[test]
+fn test_mp4_demuxer_encrypted_track_senc() {
+    let mut senc = vec![0, 0, 0, 2];
+    senc.extend_from_slice(&(VIDEO_SAMPLES as u32).to_be_bytes());
+    for i in 0..VIDEO_SAMPLES {
+        senc.extend(sample_aux_info(i));
+    }
+    let data = fixture_mp4_encrypted(&mp4_box(b"senc", &senc));
+
+    let mut demuxer = Mp4Demuxer::new();
+    let info = demuxer.load(&data).unwrap();
+    assert!(matches!(
+        info.video_tracks[0].codec,
+        VideoCodec::H264 { .. }
+    ));
+    assert_eq!(
+        info.video_tracks[0].avc_config().unwrap().sps,
+        vec![SPS.to_vec()]
+    );
+
+    for i in 0..VIDEO_SAMPLES {
+        let packet = demuxer.next_sample(VIDEO_TRACK).unwrap().unwrap();
+        assert_eq!(
+            packet.encryption(),
+            Some(&EncryptionInfo {
+                key_id: KEY_ID.to_vec(),
+                iv: sample_iv(i).to_vec(),
+                subsamples: vec![Subsample {
+                    clear_bytes: 2,
+                    encrypted_bytes: packet.data().len() as u32 - 2,
+                }],
+                scheme: EncryptionScheme::Cenc,
+                pattern: None,
+            })
+        );
+    }
+    let audio = demuxer.next_sample(AUDIO_TRACK).unwrap().unwrap();
+    assert_eq!(audio.encryption(), None);
+}
+
+/// Test that sample auxiliary information located by `saiz` and `saio` is
+/// read, also when fed in chunks
+#[test]
+fn test_mp4_demuxer_encrypted_track_aux_info() {
+    let saiz = mp4_box(
+        b"saiz",
+        &[&[0, 0, 0, 0, 16][..], &(VIDEO_SAMPLES as u32).to_be_bytes()].concat(),
+    );
+    // The information follows moov in a free box, whose offset is only
+    // known once moov is built; saio has a fixed size
+    let with_offset = |offset: u32| {
+        let saio = mp4_box(
+            b"saio",
+            &[&[0, 0, 0, 0, 0, 0, 0, 1][..], &offset.to_be_bytes()].concat(),
+        );
+        fixture_mp4_encrypted(&[saiz.clone(), saio].concat())
+    };
+    let offset = with_offset(0).len() as u32 + 8;
+    let aux_info: Vec<u8> = (0..VIDEO_SAMPLES).flat_map(sample_aux_info).collect();
+    let data = [with_offset(offset), mp4_box(b"free", &aux_info)].concat();
+
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&data).unwrap();
+    let ivs: Vec<_> = std::iter::from_fn(|| demuxer.next_sample(VIDEO_TRACK).unwrap())
+        .map(|packet| packet.encryption().unwrap().iv.clone())
+        .collect();
+    let expected: Vec<_> = (0..VIDEO_SAMPLES).map(|i| sample_iv(i).to_vec()).collect();
+    assert_eq!(ivs, expected);
+
+    let mut demuxer = Mp4Demuxer::new();
+    let mut fed = Vec::new();
+    for chunk in data.chunks(100) {
+        demuxer.feed(chunk).unwrap();
+        while let Some(packet) = demuxer.next_packet().unwrap() {
+            if let Some(encryption) = packet.encryption() {
+                fed.push(encryption.iv.clone());
+            }
+        }
+    }
+    assert_eq!(fed, expected);
+}
+
+/// Test that samples past the largest file offset are rejected
+#[test]
+fn test_mp4_demuxer_rejects_overflowing_sample_offsets() {
+    let data = edit_boxes(
+        &fixture_mp4(),
+        &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stco"],
+        &|stco| {
+            let count = u32::from_be_bytes(stco[12..16].try_into().unwrap());
+            let mut co64 = stco[8..16].to_vec();
+            for _ in 0..count {
+                co64.extend_from_slice(&(u64::MAX - 4).to_be_bytes());
+            }
+            mp4_box(b"co64", &co64)
+        },
+    );
+
+    let mut demuxer = Mp4Demuxer::new();
+    assert!(matches!(
+        demuxer.load(&data),
+        Err(MediaError::CodecError { .. })
+    ));
+}
+
+/// Test that tracks of unsupported codecs are not demuxed
+#[test]
+fn test_mp4_demuxer_skips_tracks_missing_from_media_info() {
+    let data = edit_boxes(
+        &fixture_mp4(),
+        &[b"moov", b"trak", b"mdia", b"minf", b"stbl", b"stsd"],
+        &|stsd| {
+            let mut stsd = stsd.to_vec();
+            if &stsd[20..24] == b"avc1" {
+                stsd[20..24].copy_from_slice(b"xvid");
+            }
+            stsd
+        },
+    );
+
+    let mut demuxer = Mp4Demuxer::new();
+    let info = demuxer.load(&data).unwrap();
+    assert!(info.video_tracks.is_empty());
+    assert_eq!(info.audio_tracks.len(), 1);
+
+    let packets = loaded_packets(&data);
+    assert_eq!(packets.len(), AUDIO_SAMPLES);
+    assert!(packets.iter().all(|p| p.0 == AUDIO_TRACK));
+}
+
+/// `ftyp` and `moov` of a fragmented version of the fixture: the tracks
+/// without samples, with `mvex` announcing movie fragments
+fn fragmented_init() -> Vec<u8> {
+    let mut writer = fixture_writer(mp4::SampleFreqIndex::Freq48000, mp4::ChannelConfig::Stereo);
+    writer.write_end().unwrap();
+    let data = writer.into_writer().into_inner();
+
+    // Default sample description index 1, no other defaults
+    let trex = |track: u32| {
+        mp4_box(
+            b"trex",
+            &[
+                &[0; 4][..],
+                &track.to_be_bytes(),
+                &1u32.to_be_bytes(),
+                &[0; 12],
+            ]
+            .concat(),
+        )
+    };
+    let mvex = mp4_box(b"mvex", &[trex(VIDEO_TRACK), trex(AUDIO_TRACK)].concat());
+    top_level_boxes(&data)
+        .into_iter()
+        .filter(|(box_type, _)| box_type != b"mdat")
+        .flat_map(|(box_type, bytes)| match &box_type {
+            b"moov" => mp4_box(b"moov", &[&bytes[8..], &mvex].concat()),
+            _ => bytes.to_vec(),
+        })
+        .collect()
+}
+
+/// `moof` and `mdat` of a movie fragment holding the fixture's video and
+/// audio samples in the given ranges
+fn movie_fragment(
+    sequence: u32,
+    video: std::ops::Range<usize>,
+    audio: std::ops::Range<usize>,
+) -> Vec<u8> {
+    // (duration, data, flags, composition offset) of each sample
+    let video_samples: Vec<_> = video
+        .clone()
+        .map(|i| {
+            let flags = if i.is_multiple_of(KEYFRAME_INTERVAL) {
+                0x0200_0000
+            } else {
+                0x0101_0000
+            };
+            let payload = sample_payload(VIDEO_TRACK as u8, i);
+            (40, payload, flags, rendering_offset(i) as u32)
+        })
+        .collect();
+    let audio_samples: Vec<_> = audio
+        .clone()
+        .map(|i| (1024, sample_payload(AUDIO_TRACK as u8, i), 0x0200_0000, 0))
+        .collect();
+    let trafs = [
+        (VIDEO_TRACK, video.start as u64 * 40, video_samples),
+        (AUDIO_TRACK, audio.start as u64 * 1024, audio_samples),
+    ];
+
+    // Data offsets are relative to the moof (default-base-is-moof)
+    let moof = |data_offset: u32| {
+        let mut offset = data_offset;
+        let mut payload = mp4_box(b"mfhd", &[&[0; 4][..], &sequence.to_be_bytes()].concat());
+        for (track, decode_time, samples) in &trafs {
+            let tfhd = mp4_box(b"tfhd", &[&[0, 2, 0, 0][..], &track.to_be_bytes()].concat());
+            let tfdt = mp4_box(
+                b"tfdt",
+                &[&[1, 0, 0, 0][..], &decode_time.to_be_bytes()].concat(),
+            );
+            // Data offset, and duration, size, flags and composition offset
+            // of every sample
+            let mut trun = vec![0, 0, 0x0F, 0x01];
+            trun.extend_from_slice(&(samples.len() as u32).to_be_bytes());
+            trun.extend_from_slice(&offset.to_be_bytes());
+            for (duration, data, flags, composition_offset) in samples {
+                for value in [*duration, data.len() as u32, *flags, *composition_offset] {
+                    trun.extend_from_slice(&value.to_be_bytes());
+                }
+                offset += data.len() as u32;
+            }
+            payload.extend(mp4_box(
+                b"traf",
+                &[tfhd, tfdt, mp4_box(b"trun", &trun)].concat(),
+            ));
+        }
+        mp4_box(b"moof", &payload)
+    };
+    let moof_len = moof(0).len() as u32;
+    let mdat: Vec<u8> = trafs
+        .iter()
+        .flat_map(|(_, _, samples)| samples.iter().flat_map(|sample| sample.1.clone()))
+        .collect();
+    [moof(moof_len + 8), mp4_box(b"mdat", &mdat)].concat()
+}
+
+/// The fixture as a fragmented file of two movie fragments, split at the
+/// second video keyframe (160 ms)
+fn fixture_mp4_fragmented() -> Vec<u8> {
+    [
+        fragmented_init(),
+        movie_fragment(1, 0..4, 0..8),
+        movie_fragment(2, 4..VIDEO_SAMPLES, 8..AUDIO_SAMPLES),
+    ]
+    .concat()
+}
+
+/// Test that the samples of movie fragments match the unfragmented file
+#[test]
+fn test_mp4_demuxer_fragments_match_sample_tables() {
+    let data = fixture_mp4_fragmented();
+    let expected = loaded_packets(&fixture_mp4());
+
+    let mut demuxer = Mp4Demuxer::new();
+    let info = demuxer.load(&fragmented_init()).unwrap();
+    assert_eq!(info.video_tracks.len(), 1);
+    assert_eq!(info.audio_tracks.len(), 1);
+    assert!(demuxer.read_packet().unwrap().is_none());
+
+    assert_eq!(loaded_packets(&data), expected);
+    for chunk_size in [1, 100, 4096] {
+        let (mut demuxer, packets) = fed_packets(&data, chunk_size);
+        assert_eq!(packets, expected, "chunk size {}", chunk_size);
+
+        demuxer.end_of_stream();
+        assert!(demuxer.next_packet().unwrap().is_none());
+    }
+}
+
+/// Test that fragments fed out of order are timed by their `tfdt`
+#[test]
+fn test_mp4_demuxer_feed_fragments_out_of_order() {
+    let mut demuxer = Mp4Demuxer::new();
+    let read = |demuxer: &mut Mp4Demuxer, data: &[u8]| {
+        demuxer.feed(data).unwrap();
+        std::iter::from_fn(|| demuxer.next_packet().unwrap())
+            .map(|packet| summarize(&packet))
+            .collect::<Vec<_>>()
+    };
+
+    assert!(read(&mut demuxer, &fragmented_init()).is_empty());
+    let second = read(&mut demuxer, &movie_fragment(2, 4..8, 8..16));
+    let first = read(&mut demuxer, &movie_fragment(1, 0..4, 0..8));
+
+    let expected = loaded_packets(&fixture_mp4());
+    let in_range = |p: &&Summary, video: std::ops::Range<u8>, audio: std::ops::Range<u8>| match p.0
+    {
+        VIDEO_TRACK => video.contains(&p.4[1]),
+        _ => audio.contains(&p.4[1]),
+    };
+    let expected_second: Vec<_> = expected
+        .iter()
+        .filter(|p| in_range(p, 4..8, 8..16))
+        .cloned()
+        .collect();
+    let expected_first: Vec<_> = expected
+        .iter()
+        .filter(|p| in_range(p, 0..4, 0..8))
+        .cloned()
+        .collect();
+    assert_eq!(second, expected_second);
+    assert_eq!(first, expected_first);
+
+    // The second fragment starts at the keyframe at 160 ms
+    assert_eq!(second[0].0, VIDEO_TRACK);
+    assert_eq!(second[0].1, Some(160));
+    assert!(second[0].3);
+
+    let keyframes: Vec<_> = demuxer
+        .seek_index()
+        .unwrap()
+        .entries(VIDEO_TRACK)
+        .iter()
+        .map(|point| point.time)
+        .collect();
+    assert_eq!(keyframes, vec![Duration::ZERO, Duration::from_millis(160)]);
+}
@@ -28,3 +28,583 @@ fn test_ogg_demuxer_parse_empty_data() {
     let result = demuxer.parse(empty_data);
     assert!(result.is_err(), "Should fail to parse empty data");
 }
+
+// ---------------------------------------------------------------------------
+// Opus and Vorbis fixtures
+// ---------------------------------------------------------------------------
+
+use cortenbrowser_format_parsers::{DemuxedPacket, Packet};
+use cortenbrowser_shared_types::{AudioCodec, MediaError, OpusApplication};
+use std::time::Duration;
+
+const SERIAL: u32 = 0x1234_5678;
+const BOS: u8 = 0x02;
+const EOS: u8 = 0x04;
+const CONTINUED: u8 = 0x01;
+const PRE_SKIP: i64 = 312;
+/// CELT-only, 20 ms, one frame: 960 samples at 48 kHz
+const OPUS_TOC: u8 = 0xF8;
+
+/// Ogg CRC-32: polynomial 0x04C11DB7, initial value 0, no reflection
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in data {
+        crc ^= u32::from(byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Build a page from raw lacing values and body
+fn raw_page(
+    serial: u32,
+    sequence: u32,
+    flags: u8,
+    granule: i64,
+    lacing: &[u8],
+    body: &[u8],
+) -> Vec<u8> {
+    let mut page = b"OggS".to_vec();
+    page.push(0);
+    page.push(flags);
+    page.extend_from_slice(&granule.to_le_bytes());
+    page.extend_from_slice(&serial.to_le_bytes());
+    page.extend_from_slice(&sequence.to_le_bytes());
+    page.extend_from_slice(&[0; 4]);
+    page.push(lacing.len() as u8);
+    page.extend_from_slice(lacing);
+    page.extend_from_slice(body);
+    let crc = crc32(&page);
+    page[22..26].copy_from_slice(&crc.to_le_bytes());
+    page
+}
+
+/// Lacing values for a packet; `complete` is false if it continues on the next page
+fn lacing_for(len: usize, complete: bool) -> Vec<u8> {
+    let mut lacing = vec![255; len / 255];
+    if complete {
+        lacing.push((len % 255) as u8);
+    } else {
+        assert_eq!(len % 255, 0, "open packet pieces must fill whole segments");
+    }
+    lacing
+}
+
+/// Build a page holding complete packets
+fn page(serial: u32, sequence: u32, flags: u8, granule: i64, packets: &[&[u8]]) -> Vec<u8> {
+    let lacing: Vec<u8> = packets
+        .iter()
+        .flat_map(|p| lacing_for(p.len(), true))
+        .collect();
+    raw_page(serial, sequence, flags, granule, &lacing, &packets.concat())
+}
+
+fn opus_head() -> Vec<u8> {
+    let mut head = b"OpusHead".to_vec();
+    head.push(1); // version
+    head.push(2); // channels
+    head.extend_from_slice(&(PRE_SKIP as u16).to_le_bytes());
+    head.extend_from_slice(&44_100u32.to_le_bytes()); // input sample rate
+    head.extend_from_slice(&[0, 0, 0]); // gain, mapping family
+    head
+}
+
+fn opus_tags() -> Vec<u8> {
+    let mut tags = b"OpusTags".to_vec();
+    tags.extend_from_slice(&[0; 8]); // empty vendor, no comments
+    tags
+}
+
+fn opus_packet(index: u8, len: usize) -> Vec<u8> {
+    let mut packet = vec![index; len];
+    packet[0] = OPUS_TOC;
+    packet
+}
+
+/// BOS and OpusTags pages
+fn opus_headers() -> Vec<u8> {
+    let mut data = page(SERIAL, 0, BOS, 0, &[&opus_head()]);
+    data.extend(page(SERIAL, 1, 0, 0, &[&opus_tags()]));
+    data
+}
+
+/// Opus stream with 3 packets on one page and 2 on the final page
+fn opus_stream() -> Vec<u8> {
+    let packets: Vec<Vec<u8>> = (0..5).map(|i| opus_packet(i, 20)).collect();
+    let mut data = opus_headers();
+    data.extend(page(
+        SERIAL,
+        2,
+        0,
+        PRE_SKIP + 3 * 960,
+        &[&packets[0], &packets[1], &packets[2]],
+    ));
+    data.extend(page(
+        SERIAL,
+        3,
+        EOS,
+        PRE_SKIP + 5 * 960,
+        &[&packets[3], &packets[4]],
+    ));
+    data
+}
+
+/// Write `value` as a `width`-bit LSB-first field
+fn push_bits(bits: &mut Vec<bool>, value: u32, width: usize) {
+    bits.extend((0..width).map(|i| (value >> i) & 1 == 1));
+}
+
+fn vorbis_identification() -> Vec<u8> {
+    let mut header = b"\x01vorbis".to_vec();
+    header.extend_from_slice(&0u32.to_le_bytes()); // version
+    header.push(2); // channels
+    header.extend_from_slice(&32_000u32.to_le_bytes());
+    header.extend_from_slice(&0i32.to_le_bytes()); // maximum bitrate
+    header.extend_from_slice(&128_000i32.to_le_bytes()); // nominal bitrate
+    header.extend_from_slice(&0i32.to_le_bytes()); // minimum bitrate
+    header.push(0xB8); // block sizes 256 and 2048
+    header.push(1); // framing
+    header
+}
+
+fn vorbis_comment() -> Vec<u8> {
+    let mut header = b"\x03vorbis".to_vec();
+    header.extend_from_slice(&[0; 8]);
+    header.push(1);
+    header
+}
+
+/// Setup header whose mode list has a short (mode 0) and a long (mode 1) mode
+fn vorbis_setup() -> Vec<u8> {
+    let mut bits = Vec::new();
+    // Stand-in for codebooks, floors, residues and mappings
+    for &byte in b"\x05vorbis\xde\xad\xbe\xef\xff\xff\xff\xff" {
+        push_bits(&mut bits, u32::from(byte), 8);
+    }
+    push_bits(&mut bits, 1, 6); // mode count - 1
+    for blockflag in [0, 1] {
+        push_bits(&mut bits, blockflag, 1);
+        push_bits(&mut bits, 0, 16); // window type
+        push_bits(&mut bits, 0, 16); // transform type
+        push_bits(&mut bits, 0, 8); // mapping
+    }
+    push_bits(&mut bits, 1, 1); // framing
+
+    let mut header = vec![0u8; bits.len().div_ceil(8)];
+    for (i, &bit) in bits.iter().enumerate() {
+        if bit {
+            header[i / 8] |= 1 << (i % 8);
+        }
+    }
+    header
+}
+
+/// Vorbis stream with packets using long, long, short, short blocks
+///
+/// Sample counts: 0 (first packet), (2048 + 2048) / 4, (2048 + 256) / 4,
+/// (256 + 256) / 4.
+fn vorbis_stream() -> Vec<u8> {
+    let mut data = page(SERIAL, 0, BOS, 0, &[&vorbis_identification()]);
+    data.extend(page(SERIAL, 1, 0, 0, &[&vorbis_comment(), &vorbis_setup()]));
+    data.extend(page(
+        SERIAL,
+        2,
+        EOS,
+        1024 + 576 + 128,
+        &[&[0x02, 1], &[0x02, 2], &[0x00, 3], &[0x00, 4]],
+    ));
+    data
+}
+
+fn loaded(data: &[u8]) -> OggDemuxer {
+    let mut demuxer = OggDemuxer::new();
+    demuxer.load(data).unwrap();
+    demuxer
+}
+
+fn read_all(demuxer: &mut OggDemuxer) -> Vec<DemuxedPacket> {
+    let mut packets = Vec::new();
+    while let Some(packet) = demuxer.read_packet().unwrap() {
+        packets.push(packet);
+    }
+    packets
+}
+
+fn ms(millis: u64) -> Option<Duration> {
+    Some(Duration::from_millis(millis))
+}
+
+/// Test Opus stream information comes from OpusHead and the last granule position
+#[test]
+fn test_ogg_opus_media_info() {
+    let info = OggDemuxer::new().parse(&opus_stream()).unwrap();
+
+    assert_eq!(info.audio_tracks.len(), 1);
+    let track = &info.audio_tracks[0];
+    assert_eq!(track.track_id, SERIAL);
+    assert_eq!(track.sample_rate, 48_000);
+    assert_eq!(track.channels, 2);
+    assert_eq!(
+        track.codec,
+        AudioCodec::Opus {
+            sample_rate: 48_000,
+            channels: 2,
+            application: OpusApplication::Audio,
+        }
+    );
+    // (5 * 960 + pre-skip - pre-skip) samples at 48 kHz
+    assert_eq!(info.duration, Duration::from_millis(100));
+}
+
+/// Test Opus granule positions map to timestamps with the pre-skip removed
+#[test]
+fn test_ogg_opus_granule_to_timestamp() {
+    let mut demuxer = loaded(&opus_stream());
+    let packets = read_all(&mut demuxer);
+
+    assert_eq!(packets.len(), 5);
+    for (i, packet) in packets.iter().enumerate() {
+        assert_eq!(packet.track_id, SERIAL);
+        assert_eq!(packet.timescale, 48_000);
+        assert_eq!(packet.pts(), Some(i as i64 * 960));
+        assert_eq!(packet.dts(), packet.pts());
+        assert_eq!(packet.pts_time(), ms(i as u64 * 20));
+        assert_eq!(packet.data(), opus_packet(i as u8, 20).as_slice());
+    }
+    assert!(demuxer.read_packet().unwrap().is_none());
+}
+
+/// Test the last packet on the end-of-stream page is marked as the last one
+#[test]
+fn test_ogg_marks_last_packet() {
+    let packets = read_all(&mut loaded(&opus_stream()));
+
+    let last: Vec<bool> = packets
+        .iter()
+        .map(|packet| matches!(&packet.packet, Packet::Audio(audio) if audio.is_last))
+        .collect();
+    assert_eq!(last, vec![false, false, false, false, true]);
+}
+
+/// Test Opus packets before the end of the pre-skip get negative timestamps
+#[test]
+fn test_ogg_opus_pre_skip_gives_negative_pts() {
+    let mut data = opus_headers();
+    // The stream starts at granule 0, so the first packet is mostly pre-skip
+    data.extend(page(SERIAL, 2, EOS, 2 * 960, &[&[OPUS_TOC], &[OPUS_TOC]]));
+
+    let packets = read_all(&mut loaded(&data));
+    assert_eq!(packets[0].pts(), Some(-PRE_SKIP));
+    assert_eq!(packets[0].pts_time(), None);
+    assert_eq!(packets[1].pts(), Some(960 - PRE_SKIP));
+}
+
+/// Test a packet spanning three pages is reassembled
+#[test]
+fn test_ogg_packet_spanning_three_pages() {
+    let big = opus_packet(7, 600);
+    let small = opus_packet(8, 10);
+
+    let mut data = opus_headers();
+    data.extend(raw_page(
+        SERIAL,
+        2,
+        0,
+        -1,
+        &lacing_for(255, false),
+        &big[..255],
+    ));
+    data.extend(raw_page(
+        SERIAL,
+        3,
+        CONTINUED,
+        -1,
+        &lacing_for(255, false),
+        &big[255..510],
+    ));
+    let mut lacing = lacing_for(90, true);
+    lacing.extend(lacing_for(small.len(), true));
+    data.extend(raw_page(
+        SERIAL,
+        4,
+        CONTINUED | EOS,
+        PRE_SKIP + 2 * 960,
+        &lacing,
+        &[&big[510..], &small[..]].concat(),
+    ));
+
+    let packets = read_all(&mut loaded(&data));
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].data(), big.as_slice());
+    assert_eq!(packets[0].pts(), Some(0));
+    assert_eq!(packets[1].data(), small.as_slice());
+    assert_eq!(packets[1].pts_time(), ms(20));
+}
+
+/// Test a page with a bad CRC is skipped and timestamps resume from the next page
+#[test]
+fn test_ogg_corrupted_page_is_skipped() {
+    let mut data = opus_headers();
+    let mut offsets = Vec::new();
+    for i in 0..3u8 {
+        offsets.push(data.len());
+        data.extend(page(
+            SERIAL,
+            2 + u32::from(i),
+            0,
+            PRE_SKIP + (i64::from(i) + 1) * 960,
+            &[&opus_packet(i, 40)],
+        ));
+    }
+    // Flip a payload byte of the middle page
+    data[offsets[1] + 40] ^= 0xFF;
+
+    let packets = read_all(&mut loaded(&data));
+    assert_eq!(packets.len(), 2);
+    assert_eq!(packets[0].data()[1], 0);
+    assert_eq!(packets[0].pts(), Some(0));
+    assert_eq!(packets[1].data()[1], 2);
+    assert_eq!(packets[1].pts(), Some(2 * 960));
+}
+
+/// Test a packet continued from a corrupted page is dropped
+#[test]
+fn test_ogg_continuation_of_lost_page_is_dropped() {
+    let big = opus_packet(1, 300);
+    let mut data = opus_headers();
+    let corrupt_at = data.len() + 30;
+    data.extend(raw_page(
+        SERIAL,
+        2,
+        0,
+        -1,
+        &lacing_for(255, false),
+        &big[..255],
+    ));
+    let mut lacing = lacing_for(45, true);
+    lacing.extend(lacing_for(5, true));
+    data.extend(raw_page(
+        SERIAL,
+        3,
+        CONTINUED,
+        PRE_SKIP + 2 * 960,
+        &lacing,
+        &[&big[255..], &opus_packet(2, 5)[..]].concat(),
+    ));
+    data[corrupt_at] ^= 0xFF;
+
+    let packets = read_all(&mut loaded(&data));
+    assert_eq!(packets.len(), 1);
+    assert_eq!(packets[0].data(), opus_packet(2, 5).as_slice());
+    assert_eq!(packets[0].pts(), Some(960));
+}
+
+/// Test Vorbis stream information comes from the identification header
+#[test]
+fn test_ogg_vorbis_media_info() {
+    let demuxer = loaded(&vorbis_stream());
+    let track = demuxer.get_audio_track(SERIAL).unwrap();
+
+    assert_eq!(track.codec, AudioCodec::Vorbis);
+    assert_eq!(track.sample_rate, 32_000);
+    assert_eq!(track.channels, 2);
+    assert_eq!(track.bitrate, Some(128_000));
+    assert_eq!(demuxer.codec_headers(SERIAL).unwrap().len(), 3);
+    assert_eq!(
+        OggDemuxer::new().parse(&vorbis_stream()).unwrap().duration,
+        Duration::from_millis(54)
+    );
+}
+
+/// Test Vorbis granule positions map to timestamps using block sizes
+#[test]
+fn test_ogg_vorbis_granule_to_timestamp() {
+    let mut demuxer = loaded(&vorbis_stream());
+    let packets = read_all(&mut demuxer);
+
+    assert_eq!(packets.len(), 4);
+    let pts: Vec<_> = packets.iter().map(DemuxedPacket::pts).collect();
+    assert_eq!(pts, vec![Some(0), Some(0), Some(1024), Some(1600)]);
+    assert!(packets.iter().all(|p| p.timescale == 32_000));
+    assert_eq!(packets[2].pts_time(), ms(32));
+    assert_eq!(packets[3].pts_time(), ms(50));
+    assert_eq!(packets[3].data(), &[0x00, 4]);
+}
+
+/// Test a new logical stream after EOS is reported as a chained stream
+#[test]
+fn test_ogg_chained_stream_is_reported() {
+    let mut data = opus_stream();
+    data.extend(page(SERIAL + 1, 0, BOS, 0, &[&opus_head()]));
+
+    let mut demuxer = loaded(&data);
+    for _ in 0..5 {
+        assert!(demuxer.read_packet().unwrap().is_some());
+    }
+    assert!(matches!(
+        demuxer.read_packet(),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+}
+
+/// Test next_sample filters by track and rejects unknown tracks
+#[test]
+fn test_ogg_next_sample() {
+    let mut demuxer = loaded(&opus_stream());
+
+    let packet = demuxer.next_sample(SERIAL).unwrap().unwrap();
+    assert_eq!(packet.pts(), Some(0));
+    assert!(matches!(
+        demuxer.next_sample(SERIAL + 1),
+        Err(MediaError::InvalidParameter(_))
+    ));
+    assert_eq!(demuxer.codec_headers(SERIAL).unwrap()[0], opus_head());
+    assert_eq!(
+        demuxer.get_audio_track(SERIAL).unwrap().extradata,
+        opus_head()
+    );
+}
+
+/// Test Vorbis extradata holds the three headers Xiph-laced, as in Matroska
+#[test]
+fn test_ogg_vorbis_extradata() {
+    let demuxer = loaded(&vorbis_stream());
+    let headers = demuxer.codec_headers(SERIAL).unwrap();
+
+    let mut expected = vec![2];
+    for header in &headers[..2] {
+        expected.extend(std::iter::repeat_n(255, header.len() / 255));
+        expected.push((header.len() % 255) as u8);
+    }
+    expected.extend(headers.concat());
+    assert_eq!(demuxer.get_audio_track(SERIAL).unwrap().extradata, expected);
+}
+
+/// Ogg FLAC mapping header with a 44.1 kHz stereo STREAMINFO, announcing
+/// one further header packet
+fn flac_mapping_header() -> Vec<u8> {
+    let mut header = b"\x7FFLAC\x01\x00\x00\x01fLaC".to_vec();
+    header.extend_from_slice(&[0x00, 0x00, 0x00, 0x22]); // STREAMINFO block header
+    header.extend_from_slice(&[0x10, 0x00, 0x10, 0x00]); // 4096-sample blocks
+    header.extend_from_slice(&[0; 6]); // unknown frame sizes
+                                       // 44100 Hz, 2 channels, 16 bits, unknown length
+    header.extend_from_slice(&[0x0A, 0xC4, 0x42, 0xF0, 0, 0, 0, 0]);
+    header.extend_from_slice(&[0; 16]); // MD5
+    header
+}
+
+/// 4096-sample FLAC frame; only the header matters to the demuxer
+fn flac_frame(index: u8) -> Vec<u8> {
+    vec![0xFF, 0xF8, 0xC9, 0x18, index, 0xAA, 0xBB]
+}
+
+/// FLAC stream with 2 frames on one page and 1 on the final page
+fn flac_stream() -> Vec<u8> {
+    // Last metadata block: an empty VORBIS_COMMENT
+    let comment = [0x84, 0x00, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0, 0];
+    let mut data = page(SERIAL, 0, BOS, 0, &[&flac_mapping_header()]);
+    data.extend(page(SERIAL, 1, 0, 0, &[&comment]));
+    data.extend(page(SERIAL, 2, 0, 8192, &[&flac_frame(0), &flac_frame(1)]));
+    data.extend(page(SERIAL, 3, EOS, 12288, &[&flac_frame(2)]));
+    data
+}
+
+/// Test FLAC stream information comes from the mapping header's STREAMINFO
+#[test]
+fn test_ogg_flac_media_info() {
+    let demuxer = loaded(&flac_stream());
+    let track = demuxer.get_audio_track(SERIAL).unwrap();
+
+    assert_eq!(track.codec, AudioCodec::FLAC);
+    assert_eq!(track.sample_rate, 44_100);
+    assert_eq!(track.channels, 2);
+    // Matroska CodecPrivate layout: fLaC and the metadata blocks
+    let headers = demuxer.codec_headers(SERIAL).unwrap();
+    assert_eq!(headers.len(), 2);
+    assert_eq!(
+        track.extradata,
+        [&headers[0][9..], &headers[1][..]].concat()
+    );
+    assert!(track.extradata.starts_with(b"fLaC"));
+}
+
+/// Test FLAC packet timestamps follow the frame block sizes
+#[test]
+fn test_ogg_flac_granule_to_timestamp() {
+    let packets = read_all(&mut loaded(&flac_stream()));
+
+    let pts: Vec<_> = packets.iter().map(|packet| packet.pts()).collect();
+    assert_eq!(pts, vec![Some(0), Some(4096), Some(8192)]);
+    assert!(packets.iter().all(|packet| packet.timescale == 44_100));
+    assert_eq!(packets[2].data(), flac_frame(2).as_slice());
+}
+
+/// Test reading before loading fails
+#[test]
+fn test_ogg_read_packet_before_load() {
+    let mut demuxer = OggDemuxer::new();
+    assert!(matches!(
+        demuxer.read_packet(),
+        Err(MediaError::InvalidState(_))
+    ));
+}
+
+fn summarize(packet: &DemuxedPacket) -> (u32, Option<i64>, Vec<u8>) {
+    (packet.track_id, packet.pts(), packet.data().to_vec())
+}
+
+/// Test that feeding a stream in chunks yields the one-shot packets
+#[test]
+fn test_ogg_feed_chunks_matches_load() {
+    for data in [opus_stream(), vorbis_stream()] {
+        let expected: Vec<_> = read_all(&mut loaded(&data)).iter().map(summarize).collect();
+
+        for chunk_size in [1, 7, 4096] {
+            let mut demuxer = OggDemuxer::new();
+            let mut packets = Vec::new();
+            for chunk in data.chunks(chunk_size) {
+                demuxer.feed(chunk).unwrap();
+                while let Some(packet) = demuxer.next_packet().unwrap() {
+                    packets.push(summarize(&packet));
+                }
+            }
+            demuxer.end_of_stream();
+            assert!(demuxer.next_packet().unwrap().is_none());
+            assert_eq!(packets, expected, "chunk size {}", chunk_size);
+        }
+    }
+}
+
+/// Test that media info appears once all header packets have arrived
+#[test]
+fn test_ogg_feed_media_info_after_headers() {
+    let data = opus_stream();
+    let headers = opus_headers().len();
+    let mut demuxer = OggDemuxer::new();
+
+    demuxer.feed(&data[..headers - 1]).unwrap();
+    assert!(demuxer.media_info().is_none());
+    assert!(demuxer.next_packet().unwrap().is_none());
+
+    // The OpusTags page completes the headers
+    demuxer.feed(&data[headers - 1..]).unwrap();
+    let info = demuxer.media_info().unwrap();
+    assert_eq!(info.audio_tracks[0].track_id, SERIAL);
+    assert_eq!(info.duration, Duration::ZERO);
+}
+
+/// Test that fed non-Ogg data is rejected
+#[test]
+fn test_ogg_feed_invalid_data() {
+    let mut demuxer = OggDemuxer::new();
+    assert!(matches!(
+        demuxer.feed(b"RIFF"),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+}
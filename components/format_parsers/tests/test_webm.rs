@@ -1,6 +1,7 @@
 //! Unit tests for WebM demuxer
 
 use cortenbrowser_format_parsers::{Demuxer, WebmDemuxer};
+use std::time::Duration;
 
 /// Test that WebmDemuxer can be created
 #[test]
@@ -28,3 +29,614 @@ fn test_webm_demuxer_parse_empty_data() {
     let result = demuxer.parse(empty_data);
     assert!(result.is_err(), "Should fail to parse empty data");
 }
+
+// ---------------------------------------------------------------------------
+// VP9 + Opus fixture
+// ---------------------------------------------------------------------------
+
+use cortenbrowser_format_parsers::{DemuxedPacket, Packet};
+use cortenbrowser_shared_types::{
+    AudioCodec, ColorInfo, MediaError, OpusApplication, VP9Profile, VideoCodec,
+};
+
+const VIDEO_TRACK: u32 = 1;
+const AUDIO_TRACK: u32 = 2;
+const MS: i64 = 1_000_000;
+const OPUS_HEAD: &[u8] = b"OpusHead\x01\x02\x38\x01\x80\xbb\x00\x00\x00\x00\x00";
+
+/// Encode an element with an 8-byte size field
+fn element(id: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out: Vec<u8> = id
+        .to_be_bytes()
+        .into_iter()
+        .skip_while(|&b| b == 0)
+        .collect();
+    out.push(0x01);
+    out.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Encode a master element with an unknown size
+fn unknown_size(id: u32) -> Vec<u8> {
+    let mut out = id.to_be_bytes().to_vec();
+    out.extend_from_slice(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+    out
+}
+
+fn uint(id: u32, value: u64) -> Vec<u8> {
+    element(id, &value.to_be_bytes())
+}
+
+fn float(id: u32, value: f64) -> Vec<u8> {
+    element(id, &value.to_be_bytes())
+}
+
+fn block(track: u8, relative: i16, flags: u8, body: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x80 | track];
+    out.extend_from_slice(&relative.to_be_bytes());
+    out.push(flags);
+    out.extend_from_slice(body);
+    out
+}
+
+fn simple_block(track: u8, relative: i16, flags: u8, body: &[u8]) -> Vec<u8> {
+    element(0xA3, &block(track, relative, flags, body))
+}
+
+fn ebml_header(doc_type: &str) -> Vec<u8> {
+    element(0x1A45_DFA3, &element(0x4282, doc_type.as_bytes()))
+}
+
+fn tracks() -> Vec<u8> {
+    let video = [
+        uint(0xD7, 1),
+        uint(0x83, 1),
+        element(0x86, b"V_VP9"),
+        // VP9 CodecPrivate: profile 0, level 30
+        element(0x63A2, &[1, 1, 0, 2, 1, 30]),
+        uint(0x23_E383, 40 * MS as u64),
+        element(
+            0xE0,
+            &[
+                uint(0xB0, 640),
+                uint(0xBA, 360),
+                // Colour: BT.2020 primaries and matrix, PQ transfer, full range
+                element(
+                    0x55B0,
+                    &[
+                        uint(0x55B1, 9),
+                        uint(0x55B9, 2),
+                        uint(0x55BA, 16),
+                        uint(0x55BB, 9),
+                    ]
+                    .concat(),
+                ),
+            ]
+            .concat(),
+        ),
+    ]
+    .concat();
+    let audio = [
+        uint(0xD7, 2),
+        uint(0x83, 2),
+        element(0x86, b"A_OPUS"),
+        element(0x63A2, OPUS_HEAD),
+        uint(0x23_E383, 20 * MS as u64),
+        element(0xE1, &[float(0xB5, 48000.0), uint(0x9F, 2)].concat()),
+    ]
+    .concat();
+    // A subtitle track, which is not exposed
+    let subtitles = [
+        uint(0xD7, 3),
+        uint(0x83, 17),
+        element(0x86, b"S_TEXT/WEBVTT"),
+    ]
+    .concat();
+
+    element(
+        0x1654_AE6B,
+        &[
+            element(0xAE, &video),
+            element(0xAE, &audio),
+            element(0xAE, &subtitles),
+        ]
+        .concat(),
+    )
+}
+
+/// Cluster contents; packets are described in `expected_packets`
+fn clusters() -> Vec<Vec<u8>> {
+    // Xiph lacing: 3 frames of 300, 2 and 4 bytes
+    let mut xiph = vec![2, 0xFF, 45, 2];
+    xiph.extend([0xA1; 300]);
+    xiph.extend([0xA2; 2]);
+    xiph.extend([0xA3; 4]);
+
+    // EBML lacing: 2 frames of 5 and 7 bytes
+    let mut ebml = vec![1, 0x85];
+    ebml.extend([0xB1; 5]);
+    ebml.extend([0xB2; 7]);
+
+    // Fixed lacing: 2 frames of 3 bytes
+    let mut fixed = vec![1];
+    fixed.extend([0xC1; 6]);
+
+    let first = [
+        uint(0xE7, 0),
+        simple_block(1, 0, 0x80, &[0x10; 50]),
+        simple_block(2, 0, 0x80 | 0x02, &xiph),
+        simple_block(3, 0, 0x80, b"subtitle"),
+        // Inter frame in a BlockGroup with a ReferenceBlock
+        element(
+            0xA0,
+            &[element(0xA1, &block(1, 40, 0, &[0x11; 20])), uint(0xFB, 40)].concat(),
+        ),
+        simple_block(1, 80, 0, &[0x12; 20]),
+    ]
+    .concat();
+
+    let second = [
+        uint(0xE7, 120),
+        element(0xEC, &[0; 16]),
+        // Key frame in a BlockGroup without a ReferenceBlock
+        element(
+            0xA0,
+            &[element(0xA1, &block(1, 0, 0, &[0x13; 40])), uint(0x9B, 40)].concat(),
+        ),
+        simple_block(2, 0, 0x80 | 0x06, &ebml),
+        simple_block(2, 40, 0x80 | 0x04, &fixed),
+        simple_block(1, 40, 0, &[0x14; 20]),
+    ]
+    .concat();
+
+    vec![first, second]
+}
+
+fn segment_head() -> Vec<u8> {
+    [
+        element(
+            0x1549_A966,
+            &[uint(0x2A_D7B1, MS as u64), float(0x4489, 200.0)].concat(),
+        ),
+        tracks(),
+    ]
+    .concat()
+}
+
+/// File offset of the Segment's data: the EBML header, then the Segment's
+/// 4-byte ID and 8-byte size
+fn segment_start() -> u64 {
+    ebml_header("webm").len() as u64 + 12
+}
+
+/// Offsets of the two Clusters relative to the Segment's data
+fn cluster_positions() -> [u64; 2] {
+    let first = segment_head().len();
+    let second = first + element(0x1F43_B675, &clusters()[0]).len();
+    [first as u64, second as u64]
+}
+
+/// CuePoint with (track, cluster position, block number) entries
+fn cue_point(time_ms: u64, positions: &[(u64, u64, u64)]) -> Vec<u8> {
+    let mut payload = uint(0xB3, time_ms);
+    for &(track, cluster, block) in positions {
+        payload.extend(element(
+            0xB7,
+            &[uint(0xF7, track), uint(0xF1, cluster), uint(0x5378, block)].concat(),
+        ));
+    }
+    element(0xBB, &payload)
+}
+
+/// Cues for both Clusters; the subtitle track's cue is not indexed
+fn cues() -> Vec<u8> {
+    let [first, second] = cluster_positions();
+    element(
+        0x1C53_BB6B,
+        &[
+            cue_point(0, &[(1, first, 1), (2, first, 2), (3, first, 3)]),
+            cue_point(120, &[(1, second, 1), (2, second, 2)]),
+        ]
+        .concat(),
+    )
+}
+
+fn fixture_webm() -> Vec<u8> {
+    let mut segment = segment_head();
+    for cluster in clusters() {
+        segment.extend(element(0x1F43_B675, &cluster));
+    }
+    segment.extend(cues());
+
+    [ebml_header("webm"), element(0x1853_8067, &segment)].concat()
+}
+
+/// Live-style stream with unknown-size Segment and Clusters
+fn fixture_live_webm() -> Vec<u8> {
+    let mut out = [
+        ebml_header("webm"),
+        unknown_size(0x1853_8067),
+        segment_head(),
+    ]
+    .concat();
+    for cluster in clusters() {
+        out.extend(unknown_size(0x1F43_B675));
+        out.extend(cluster);
+    }
+    out
+}
+
+/// (track, pts in ms, keyframe, size) for every packet in file order
+fn expected_packets() -> Vec<(u32, i64, bool, usize)> {
+    vec![
+        (VIDEO_TRACK, 0, true, 50),
+        (AUDIO_TRACK, 0, true, 300),
+        (AUDIO_TRACK, 20, true, 2),
+        (AUDIO_TRACK, 40, true, 4),
+        (VIDEO_TRACK, 40, false, 20),
+        (VIDEO_TRACK, 80, false, 20),
+        (VIDEO_TRACK, 120, true, 40),
+        (AUDIO_TRACK, 120, true, 5),
+        (AUDIO_TRACK, 140, true, 7),
+        (AUDIO_TRACK, 160, true, 3),
+        (AUDIO_TRACK, 180, true, 3),
+        (VIDEO_TRACK, 160, false, 20),
+    ]
+}
+
+fn summarize(packet: &DemuxedPacket) -> (u32, i64, bool, usize) {
+    assert_eq!(packet.timescale, 1_000_000_000);
+    assert_eq!(packet.pts(), packet.dts());
+    (
+        packet.track_id,
+        packet.pts().unwrap() / MS,
+        packet.is_keyframe(),
+        packet.data().len(),
+    )
+}
+
+fn read_all(demuxer: &mut WebmDemuxer) -> Vec<DemuxedPacket> {
+    let mut packets = Vec::new();
+    while let Some(packet) = demuxer.read_packet().unwrap() {
+        packets.push(packet);
+    }
+    packets
+}
+
+/// Test parsing track information from a VP9 + Opus file
+#[test]
+fn test_webm_demuxer_parse_tracks() {
+    let demuxer = WebmDemuxer::new();
+    let info = demuxer.parse(&fixture_webm()).unwrap();
+
+    assert_eq!(info.duration.as_millis(), 200);
+    assert_eq!(info.video_tracks.len(), 1);
+    assert_eq!(
+        info.audio_tracks.len(),
+        1,
+        "subtitle tracks are not exposed"
+    );
+
+    let video = &info.video_tracks[0];
+    assert_eq!(video.track_id, VIDEO_TRACK);
+    assert_eq!(
+        video.codec,
+        VideoCodec::VP9 {
+            profile: VP9Profile::Profile0
+        }
+    );
+    assert_eq!((video.width, video.height), (640, 360));
+    assert_eq!(video.frame_rate, 25.0);
+    assert_eq!(video.extradata, vec![1, 1, 0, 2, 1, 30]);
+    assert_eq!(
+        video.color,
+        Some(ColorInfo {
+            primaries: 9,
+            transfer: 16,
+            matrix: 9,
+            full_range: true,
+        })
+    );
+
+    let audio = &info.audio_tracks[0];
+    assert_eq!(audio.track_id, AUDIO_TRACK);
+    assert_eq!(
+        audio.codec,
+        AudioCodec::Opus {
+            sample_rate: 48000,
+            channels: 2,
+            application: OpusApplication::Audio,
+        }
+    );
+    assert_eq!((audio.sample_rate, audio.channels), (48000, 2));
+    assert_eq!(audio.extradata, OPUS_HEAD);
+}
+
+/// Test reading all packets with timestamps and keyframe flags
+#[test]
+fn test_webm_demuxer_read_packets() {
+    let mut demuxer = WebmDemuxer::new();
+    demuxer.load(&fixture_webm()).unwrap();
+    assert!(demuxer.get_video_track(VIDEO_TRACK).is_some());
+    assert!(demuxer.get_audio_track(AUDIO_TRACK).is_some());
+
+    let packets = read_all(&mut demuxer);
+    let summary: Vec<_> = packets.iter().map(summarize).collect();
+    assert_eq!(summary, expected_packets());
+    assert!(demuxer.is_finished());
+
+    assert!(matches!(packets[0].packet, Packet::Video(_)));
+    assert!(matches!(packets[1].packet, Packet::Audio(_)));
+}
+
+/// Test that laced frames are split with the right payloads
+#[test]
+fn test_webm_demuxer_lacing_payloads() {
+    let mut demuxer = WebmDemuxer::new();
+    demuxer.load(&fixture_webm()).unwrap();
+
+    let audio: Vec<_> = std::iter::from_fn(|| demuxer.next_sample(AUDIO_TRACK).unwrap()).collect();
+    let first_bytes: Vec<u8> = audio.iter().map(|p| p.data()[0]).collect();
+    assert_eq!(first_bytes, vec![0xA1, 0xA2, 0xA3, 0xB1, 0xB2, 0xC1, 0xC1]);
+    assert!(audio
+        .iter()
+        .all(|p| p.data().iter().all(|&b| b == p.data()[0])));
+}
+
+/// Test per-track reads keep the other track's packets
+#[test]
+fn test_webm_demuxer_next_sample() {
+    let mut demuxer = WebmDemuxer::new();
+    demuxer.load(&fixture_webm()).unwrap();
+
+    let video: Vec<_> = std::iter::from_fn(|| demuxer.next_sample(VIDEO_TRACK).unwrap())
+        .map(|p| summarize(&p))
+        .collect();
+    let expected: Vec<_> = expected_packets()
+        .into_iter()
+        .filter(|p| p.0 == VIDEO_TRACK)
+        .collect();
+    assert_eq!(video, expected);
+
+    // Audio packets read past while looking for video are still available
+    let audio = read_all(&mut demuxer);
+    assert_eq!(audio.len(), 7);
+    assert!(audio.iter().all(|p| p.track_id == AUDIO_TRACK));
+}
+
+/// Test CodecPrivate is exposed for decoder setup
+#[test]
+fn test_webm_demuxer_codec_private() {
+    let mut demuxer = WebmDemuxer::new();
+    assert_eq!(demuxer.codec_private(AUDIO_TRACK), None);
+
+    demuxer.load(&fixture_webm()).unwrap();
+    assert_eq!(demuxer.codec_private(AUDIO_TRACK), Some(OPUS_HEAD));
+    assert_eq!(
+        demuxer.codec_private(VIDEO_TRACK),
+        Some(&[1, 1, 0, 2, 1, 30][..])
+    );
+    assert_eq!(demuxer.codec_private(3), None);
+}
+
+/// Test that pushing data in small chunks yields the same packets
+#[test]
+fn test_webm_demuxer_streaming_chunks() {
+    let data = fixture_webm();
+
+    for chunk_size in [1, 7, 64, 4096] {
+        let mut demuxer = WebmDemuxer::new();
+        let mut summary = Vec::new();
+
+        for chunk in data.chunks(chunk_size) {
+            demuxer.feed(chunk).unwrap();
+            while let Some(packet) = demuxer.next_packet().unwrap() {
+                summary.push(summarize(&packet));
+            }
+        }
+        assert!(demuxer.media_info().is_some());
+        assert!(!demuxer.is_finished());
+
+        demuxer.end_of_stream();
+        assert!(demuxer.next_packet().unwrap().is_none());
+        assert!(demuxer.is_finished());
+        assert_eq!(summary, expected_packets(), "chunk size {}", chunk_size);
+    }
+}
+
+/// Test that media info appears once the Tracks element has arrived
+#[test]
+fn test_webm_demuxer_streaming_media_info() {
+    let data = fixture_webm();
+    let mut demuxer = WebmDemuxer::new();
+
+    demuxer.feed(&data[..40]).unwrap();
+    assert!(demuxer.media_info().is_none());
+    assert!(demuxer.next_packet().unwrap().is_none());
+
+    demuxer.feed(&data[40..]).unwrap();
+    let info = demuxer.media_info().unwrap();
+    assert_eq!(info.video_tracks.len(), 1);
+    assert!(demuxer.get_audio_track(AUDIO_TRACK).is_some());
+}
+
+/// Test unknown-size Segment and Clusters as produced by live muxers
+#[test]
+fn test_webm_demuxer_unknown_size_clusters() {
+    let mut demuxer = WebmDemuxer::new();
+    demuxer.feed(&fixture_live_webm()).unwrap();
+    demuxer.end_of_stream();
+
+    let summary: Vec<_> = read_all(&mut demuxer).iter().map(summarize).collect();
+    assert_eq!(summary, expected_packets());
+}
+
+/// Test that a truncated stream is reported once it has ended
+#[test]
+fn test_webm_demuxer_truncated_stream() {
+    let data = fixture_webm();
+    let mut demuxer = WebmDemuxer::new();
+    demuxer
+        .feed(&data[..data.len() - cues().len() - 60])
+        .unwrap();
+
+    // Waits for more data while the stream is open
+    let packets = read_all(&mut demuxer);
+    assert!(packets.len() < expected_packets().len());
+
+    demuxer.end_of_stream();
+    assert!(matches!(
+        demuxer.read_packet(),
+        Err(MediaError::CodecError { .. })
+    ));
+}
+
+/// Test that non-WebM fed data is rejected
+#[test]
+fn test_webm_demuxer_feed_invalid_data() {
+    let mut demuxer = WebmDemuxer::new();
+    assert!(matches!(
+        demuxer.feed(b"RIFF"),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+}
+
+/// Test that an unsupported DocType is rejected
+#[test]
+fn test_webm_demuxer_rejects_unknown_doc_type() {
+    let mut data = fixture_webm();
+    let header = ebml_header("webm");
+    data.splice(..header.len(), ebml_header("wxyz"));
+
+    let demuxer = WebmDemuxer::new();
+    assert!(matches!(
+        demuxer.parse(&data),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+}
+
+/// Test reading packets before any data is loaded
+#[test]
+fn test_webm_demuxer_read_packet_not_loaded() {
+    let mut demuxer = WebmDemuxer::new();
+    assert!(matches!(
+        demuxer.read_packet(),
+        Err(MediaError::InvalidState(_))
+    ));
+}
+
+/// Test reading from an unknown track
+#[test]
+fn test_webm_demuxer_next_sample_unknown_track() {
+    let mut demuxer = WebmDemuxer::new();
+    demuxer.load(&fixture_webm()).unwrap();
+    assert!(matches!(
+        demuxer.next_sample(3),
+        Err(MediaError::InvalidParameter(_))
+    ));
+}
+
+/// Test that the seek index comes from the Cues and points at the Clusters
+#[test]
+fn test_webm_demuxer_seek_index_from_cues() {
+    let data = fixture_webm();
+    let mut demuxer = WebmDemuxer::new();
+    demuxer.load(&data).unwrap();
+    let index = demuxer.seek_index().unwrap();
+
+    let [first, second] = cluster_positions().map(|pos| segment_start() + pos);
+    let entries = |track| {
+        index
+            .entries(track)
+            .iter()
+            .map(|p| (p.time, p.byte_offset, p.sample_number))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        entries(VIDEO_TRACK),
+        vec![
+            (Duration::ZERO, first, Some(1)),
+            (Duration::from_millis(120), second, Some(1)),
+        ]
+    );
+    assert_eq!(
+        entries(AUDIO_TRACK),
+        vec![
+            (Duration::ZERO, first, Some(2)),
+            (Duration::from_millis(120), second, Some(2)),
+        ]
+    );
+    assert!(index.entries(3).is_empty());
+    assert_eq!(index.track_ids().collect::<Vec<_>>(), vec![1, 2]);
+
+    for offset in [first, second] {
+        assert_eq!(
+            &data[offset as usize..offset as usize + 4],
+            &[0x1F, 0x43, 0xB6, 0x75]
+        );
+    }
+    assert_eq!(
+        index.byte_offset_for_time(Duration::from_millis(119)),
+        Some(first)
+    );
+    assert_eq!(
+        index.byte_offset_for_time(Duration::from_millis(130)),
+        Some(second)
+    );
+}
+
+/// Test that fed data provides the seek index once the Cues arrive
+#[test]
+fn test_webm_demuxer_streamed_cues() {
+    let data = fixture_webm();
+    let mut loaded = WebmDemuxer::new();
+    loaded.load(&data).unwrap();
+
+    for chunk_size in [1, 7, 4096] {
+        let mut demuxer = WebmDemuxer::new();
+        let cues_start = data.len() - cues().len();
+        for chunk in data[..cues_start].chunks(chunk_size) {
+            demuxer.feed(chunk).unwrap();
+            while demuxer.next_packet().unwrap().is_some() {}
+        }
+        assert!(demuxer.seek_index().is_none());
+
+        demuxer.feed(&data[cues_start..]).unwrap();
+        assert!(demuxer.next_packet().unwrap().is_none());
+        assert_eq!(
+            demuxer.seek_index(),
+            loaded.seek_index(),
+            "chunk size {}",
+            chunk_size
+        );
+    }
+}
+
+/// Test that seeking restarts reading at the cued Cluster
+#[test]
+fn test_webm_demuxer_seek_feeds_from_cluster() {
+    let data = fixture_webm();
+    let mut demuxer = WebmDemuxer::new();
+    demuxer.load(&data).unwrap();
+    read_all(&mut demuxer);
+
+    let offset = demuxer.seek(Duration::from_millis(130)).unwrap();
+    assert_eq!(offset, segment_start() + cluster_positions()[1]);
+    assert!(demuxer.next_packet().unwrap().is_none());
+
+    demuxer.feed(&data[offset as usize..]).unwrap();
+    demuxer.end_of_stream();
+    let summary: Vec<_> = read_all(&mut demuxer).iter().map(summarize).collect();
+    let expected: Vec<_> = expected_packets()
+        .into_iter()
+        .filter(|&(_, pts, _, _)| pts >= 120)
+        .collect();
+    assert_eq!(summary, expected);
+
+    // Before the first cue, reading restarts at the first Cluster
+    assert_eq!(
+        demuxer.seek(Duration::ZERO).unwrap(),
+        segment_start() + cluster_positions()[0]
+    );
+}
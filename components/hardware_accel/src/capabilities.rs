@@ -7,23 +7,35 @@ use cortenbrowser_shared_types::VideoCodec;
 /// Describes the capabilities of the available hardware acceleration,
 /// including supported codecs, maximum resolution, and frame rate.
 ///
+/// `max_resolution` and `max_framerate` are device-wide limits. Codecs with
+/// an entry in `codec_limits` use that entry instead, so a device can report
+/// 8K for one codec and 4K for another.
+///
 /// # Examples
 ///
 /// ```
-/// use cortenbrowser_hardware_accel::HardwareCapabilities;
+/// use cortenbrowser_hardware_accel::{CodecLimits, HardwareCapabilities};
 /// use cortenbrowser_shared_types::{VideoCodec, H264Profile, H264Level};
 ///
+/// let h264 = VideoCodec::H264 {
+///     profile: H264Profile::High,
+///     level: H264Level::Level4_1,
+///     hardware_accel: true,
+/// };
+///
 /// let caps = HardwareCapabilities {
-///     supported_codecs: vec![
-///         VideoCodec::H264 {
-///             profile: H264Profile::High,
-///             level: H264Level::Level4_1,
-///             hardware_accel: true,
-///         },
-///     ],
-///     max_resolution: (3840, 2160), // 4K
+///     supported_codecs: vec![h264.clone()],
+///     max_resolution: (7680, 4320), // 8K
 ///     max_framerate: 60.0,
+///     codec_limits: vec![CodecLimits {
+///         codec: h264.clone(),
+///         max_resolution: (3840, 2160), // 4K
+///         max_framerate: 60.0,
+///     }],
 /// };
+///
+/// assert_eq!(caps.max_resolution_for(&h264), (3840, 2160));
+/// assert!(!caps.supports(&h264, 7680, 4320, 30.0));
 /// ```
 #[derive(Debug, Clone)]
 pub struct HardwareCapabilities {
@@ -35,6 +47,24 @@ pub struct HardwareCapabilities {
 
     /// Maximum frame rate supported (frames per second)
     pub max_framerate: f32,
+
+    /// Per-codec limits overriding `max_resolution` and `max_framerate`
+    pub codec_limits: Vec<CodecLimits>,
+}
+
+/// Decoding limits for a single codec
+///
+/// Matches codecs by type, ignoring profile and level.
+#[derive(Debug, Clone)]
+pub struct CodecLimits {
+    /// Codec the limits apply to
+    pub codec: VideoCodec,
+
+    /// Maximum resolution supported (width, height)
+    pub max_resolution: (u32, u32),
+
+    /// Maximum frame rate supported (frames per second)
+    pub max_framerate: f32,
 }
 
 impl Default for HardwareCapabilities {
@@ -43,6 +73,77 @@ impl Default for HardwareCapabilities {
             supported_codecs: Vec::new(),
             max_resolution: (0, 0),
             max_framerate: 0.0,
+            codec_limits: Vec::new(),
+        }
+    }
+}
+
+impl HardwareCapabilities {
+    /// Check if a codec is supported, ignoring profile and level
+    pub fn is_codec_supported(&self, codec: &VideoCodec) -> bool {
+        self.supported_codecs
+            .iter()
+            .any(|supported| same_codec(supported, codec))
+    }
+
+    /// Get the maximum resolution (width, height) for a codec
+    ///
+    /// Returns `(0, 0)` if the codec is not supported.
+    pub fn max_resolution_for(&self, codec: &VideoCodec) -> (u32, u32) {
+        if !self.is_codec_supported(codec) {
+            return (0, 0);
         }
+
+        self.limits_for(codec)
+            .map(|limits| limits.max_resolution)
+            .unwrap_or(self.max_resolution)
+    }
+
+    /// Get the maximum frame rate for a codec
+    ///
+    /// Returns `0.0` if the codec is not supported.
+    pub fn max_framerate_for(&self, codec: &VideoCodec) -> f32 {
+        if !self.is_codec_supported(codec) {
+            return 0.0;
+        }
+
+        self.limits_for(codec)
+            .map(|limits| limits.max_framerate)
+            .unwrap_or(self.max_framerate)
     }
+
+    /// Check if a codec can be decoded at the given size and frame rate
+    ///
+    /// # Arguments
+    ///
+    /// * `codec` - Codec to check
+    /// * `width` - Frame width in pixels
+    /// * `height` - Frame height in pixels
+    /// * `fps` - Frame rate in frames per second
+    pub fn supports(&self, codec: &VideoCodec, width: u32, height: u32, fps: f32) -> bool {
+        if !self.is_codec_supported(codec) {
+            return false;
+        }
+
+        let (max_width, max_height) = self.max_resolution_for(codec);
+        width <= max_width && height <= max_height && fps <= self.max_framerate_for(codec)
+    }
+
+    fn limits_for(&self, codec: &VideoCodec) -> Option<&CodecLimits> {
+        self.codec_limits
+            .iter()
+            .find(|limits| same_codec(&limits.codec, codec))
+    }
+}
+
+/// Compare codecs by type, ignoring profile and level
+fn same_codec(a: &VideoCodec, b: &VideoCodec) -> bool {
+    matches!(
+        (a, b),
+        (VideoCodec::H264 { .. }, VideoCodec::H264 { .. })
+            | (VideoCodec::VP9 { .. }, VideoCodec::VP9 { .. })
+            | (VideoCodec::VP8, VideoCodec::VP8)
+            | (VideoCodec::H265 { .. }, VideoCodec::H265 { .. })
+            | (VideoCodec::AV1 { .. }, VideoCodec::AV1 { .. })
+    )
 }
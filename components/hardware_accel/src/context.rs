@@ -1,14 +1,19 @@
 //! Hardware context for platform detection and decoder creation
 
-use crate::capabilities::HardwareCapabilities;
+use crate::capabilities::{CodecLimits, HardwareCapabilities};
 use crate::error::{HardwareError, HardwareResult};
-use cortenbrowser_shared_types::{H264Level, H264Profile, VP9Profile, VideoCodec, VideoDecoder};
+use crate::fallback::FallbackDecoder;
+use crate::pool::{DecoderPool, DecoderPoolConfig, HardwareDecoder, PooledDecoder};
+use cortenbrowser_shared_types::{H264Level, H264Profile, MediaError, VideoCodec, VideoDecoder};
+use cortenbrowser_video_decoders::DecoderFactory as SoftwareDecoderFactory;
 
 #[cfg(target_os = "linux")]
-use crate::vaapi::VAAPIDecoder;
+use crate::vaapi::{self, VAAPIDecoder};
+#[cfg(target_os = "linux")]
+use cortenbrowser_shared_types::VP9Profile;
 
 #[cfg(target_os = "windows")]
-use crate::dxva::DXVADecoder;
+use crate::dxva::{self, DXVADecoder};
 
 #[cfg(target_os = "macos")]
 use crate::videotoolbox::VideoToolboxDecoder;
@@ -19,7 +24,7 @@ use crate::videotoolbox::VideoToolboxDecoder;
 /// Automatically detects the available hardware acceleration API
 /// based on the operating system:
 /// - Linux: VA-API
-/// - Windows: DXVA (stub)
+/// - Windows: DXVA
 /// - macOS: VideoToolbox (stub)
 ///
 /// # Examples
@@ -46,6 +51,8 @@ use crate::videotoolbox::VideoToolboxDecoder;
 /// ```
 pub struct HardwareContext {
     capabilities: HardwareCapabilities,
+    driver_name: Option<String>,
+    decoder_pool: DecoderPool,
 }
 
 impl HardwareContext {
@@ -83,39 +90,168 @@ impl HardwareContext {
         }
     }
 
+    /// Replace the decoder pool with one using the given configuration
+    ///
+    /// Idle decoders in the previous pool are destroyed. Decoders already
+    /// handed out are destroyed when dropped instead of being pooled.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_hardware_accel::{DecoderPoolConfig, HardwareContext};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let ctx = HardwareContext::new()?.with_decoder_pool_config(DecoderPoolConfig { max_idle: 2 });
+    /// assert_eq!(ctx.decoder_pool().config().max_idle, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_decoder_pool_config(mut self, config: DecoderPoolConfig) -> Self {
+        self.decoder_pool = DecoderPool::new(config);
+        self
+    }
+
     /// Initialize hardware context for Linux (VA-API)
+    ///
+    /// Probes the VA-API driver for decodable profiles. If probing fails
+    /// (no libva, no render node, driver initialization error), a
+    /// conservative hardcoded capability set is used instead.
     #[cfg(target_os = "linux")]
     fn init_linux() -> HardwareResult<Self> {
-        // Attempt to detect VA-API capabilities
-        // For now, we return a conservative set of capabilities
-        // In a full implementation, this would query VA-API directly
+        match vaapi::probe() {
+            Ok(probe) => Ok(Self {
+                capabilities: Self::probed_capabilities(probe.codecs),
+                driver_name: Some(probe.driver_name),
+                decoder_pool: DecoderPool::new(DecoderPoolConfig::default()),
+            }),
+            Err(_) => Ok(Self {
+                capabilities: Self::fallback_linux_capabilities(),
+                driver_name: None,
+                decoder_pool: DecoderPool::new(DecoderPoolConfig::default()),
+            }),
+        }
+    }
 
-        let mut capabilities = HardwareCapabilities::default();
+    /// Build capabilities from the codecs reported by the VA-API or DXVA
+    /// probe
+    ///
+    /// Neither API reports picture size limits without creating a decoder
+    /// config, so typical driver limits are used per codec.
+    #[cfg(any(target_os = "linux", target_os = "windows"))]
+    fn probed_capabilities(codecs: Vec<VideoCodec>) -> HardwareCapabilities {
+        let codec_limits: Vec<CodecLimits> = codecs
+            .iter()
+            .map(|codec| {
+                let (max_resolution, max_framerate) = match codec {
+                    VideoCodec::H264 { .. } | VideoCodec::VP8 => ((4096, 2304), 120.0),
+                    _ => ((8192, 4320), 60.0),
+                };
+                CodecLimits {
+                    codec: codec.clone(),
+                    max_resolution,
+                    max_framerate,
+                }
+            })
+            .collect();
 
-        // Common VA-API supported codecs
-        capabilities.supported_codecs = vec![
-            VideoCodec::H264 {
-                profile: H264Profile::High,
-                level: H264Level::Level5_1,
-                hardware_accel: true,
-            },
-            VideoCodec::VP9 {
-                profile: VP9Profile::Profile0,
-            },
-        ];
+        // Device-wide limits are the largest of the per-codec limits
+        let max_resolution = codec_limits
+            .iter()
+            .map(|limits| limits.max_resolution)
+            .max()
+            .unwrap_or((0, 0));
+        let max_framerate = codec_limits
+            .iter()
+            .map(|limits| limits.max_framerate)
+            .fold(0.0, f32::max);
 
-        capabilities.max_resolution = (4096, 4096); // Typical VA-API max
-        capabilities.max_framerate = 60.0;
+        HardwareCapabilities {
+            supported_codecs: codecs,
+            max_resolution,
+            max_framerate,
+            codec_limits,
+        }
+    }
 
-        Ok(Self { capabilities })
+    /// Conservative capabilities used when the VA-API probe fails
+    #[cfg(target_os = "linux")]
+    fn fallback_linux_capabilities() -> HardwareCapabilities {
+        let h264 = VideoCodec::H264 {
+            profile: H264Profile::High,
+            level: H264Level::Level5_1,
+            hardware_accel: true,
+        };
+        let vp9 = VideoCodec::VP9 {
+            profile: VP9Profile::Profile0,
+        };
+
+        HardwareCapabilities {
+            // Common VA-API supported codecs
+            supported_codecs: vec![h264.clone(), vp9.clone()],
+
+            // Device-wide limits are the largest of the per-codec limits
+            max_resolution: (8192, 4320),
+            max_framerate: 120.0,
+
+            // Typical VA-API limits: H.264 decoders top out around 4K, while
+            // VP9 decoders handle 8K
+            codec_limits: vec![
+                CodecLimits {
+                    codec: h264,
+                    max_resolution: (4096, 2304),
+                    max_framerate: 120.0,
+                },
+                CodecLimits {
+                    codec: vp9,
+                    max_resolution: (8192, 4320),
+                    max_framerate: 60.0,
+                },
+            ],
+        }
     }
 
-    /// Initialize hardware context for Windows (DXVA stub)
+    /// Initialize hardware context for Windows (DXVA)
+    ///
+    /// Probes the Direct3D 11 video device for decoder profiles. If probing
+    /// fails (no GPU, or a driver without video support), a conservative
+    /// hardcoded capability set is used instead.
     #[cfg(target_os = "windows")]
     fn init_windows() -> HardwareResult<Self> {
-        // DXVA implementation is a stub for now
-        // TODO: Implement DXVA support
-        Err(HardwareError::NotAvailable)
+        match dxva::probe() {
+            Ok(probe) => Ok(Self {
+                capabilities: Self::probed_capabilities(probe.codecs),
+                driver_name: Some(probe.driver_name),
+                decoder_pool: DecoderPool::new(DecoderPoolConfig::default()),
+            }),
+            Err(_) => Ok(Self {
+                capabilities: Self::fallback_windows_capabilities(),
+                driver_name: None,
+                decoder_pool: DecoderPool::new(DecoderPoolConfig::default()),
+            }),
+        }
+    }
+
+    /// Conservative capabilities used when the DXVA probe fails
+    ///
+    /// Every GPU with a Direct3D 11 video driver decodes H.264.
+    #[cfg(target_os = "windows")]
+    fn fallback_windows_capabilities() -> HardwareCapabilities {
+        let h264 = VideoCodec::H264 {
+            profile: H264Profile::High,
+            level: H264Level::Level5_1,
+            hardware_accel: true,
+        };
+
+        HardwareCapabilities {
+            supported_codecs: vec![h264.clone()],
+            max_resolution: (4096, 2304),
+            max_framerate: 120.0,
+            codec_limits: vec![CodecLimits {
+                codec: h264,
+                max_resolution: (4096, 2304),
+                max_framerate: 120.0,
+            }],
+        }
     }
 
     /// Initialize hardware context for macOS (VideoToolbox stub)
@@ -150,19 +286,39 @@ impl HardwareContext {
     /// # }
     /// ```
     pub fn is_codec_supported(&self, codec: &VideoCodec) -> bool {
-        // Check if codec is in the supported list
-        // For H.264, we match on codec type (ignoring specific profile/level)
-        self.capabilities
-            .supported_codecs
-            .iter()
-            .any(|supported| match (supported, codec) {
-                (VideoCodec::H264 { .. }, VideoCodec::H264 { .. }) => true,
-                (VideoCodec::VP9 { .. }, VideoCodec::VP9 { .. }) => true,
-                (VideoCodec::VP8, VideoCodec::VP8) => true,
-                (VideoCodec::H265 { .. }, VideoCodec::H265 { .. }) => true,
-                (VideoCodec::AV1 { .. }, VideoCodec::AV1 { .. }) => true,
-                _ => false,
-            })
+        // Match on codec type (ignoring specific profile/level)
+        self.capabilities.is_codec_supported(codec)
+    }
+
+    /// Check if a codec can be decoded in hardware at the given size and
+    /// frame rate
+    ///
+    /// Unlike [`HardwareContext::is_codec_supported`], this checks the
+    /// per-codec resolution and frame rate limits.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_hardware_accel::HardwareContext;
+    /// use cortenbrowser_shared_types::{VideoCodec, H264Profile, H264Level};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let ctx = HardwareContext::new()?;
+    ///
+    /// let h264 = VideoCodec::H264 {
+    ///     profile: H264Profile::High,
+    ///     level: H264Level::Level5_1,
+    ///     hardware_accel: true,
+    /// };
+    ///
+    /// if !ctx.supports(&h264, 7680, 4320, 30.0) {
+    ///     println!("8K H.264 needs software decoding");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn supports(&self, codec: &VideoCodec, width: u32, height: u32, fps: f32) -> bool {
+        self.capabilities.supports(codec, width, height, fps)
     }
 
     /// Create a hardware decoder for the specified codec
@@ -223,6 +379,124 @@ impl HardwareContext {
         }
     }
 
+    /// Acquire a hardware decoder from the decoder pool
+    ///
+    /// Reuses an idle decoder created earlier for the same codec, or creates
+    /// a new one. Dropping the returned [`PooledDecoder`] resets the decoder
+    /// and returns it to the pool; decoders beyond
+    /// [`DecoderPoolConfig::max_idle`] are destroyed.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`HardwareContext::create_decoder`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_hardware_accel::HardwareContext;
+    /// use cortenbrowser_shared_types::{VideoCodec, H264Profile, H264Level};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let ctx = HardwareContext::new()?;
+    ///
+    /// let h264 = VideoCodec::H264 {
+    ///     profile: H264Profile::High,
+    ///     level: H264Level::Level4_1,
+    ///     hardware_accel: true,
+    /// };
+    ///
+    /// let decoder = ctx.acquire_decoder(&h264)?;
+    /// // Use decoder; it returns to the pool when dropped
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn acquire_decoder(&self, codec: &VideoCodec) -> HardwareResult<PooledDecoder> {
+        if !self.is_codec_supported(codec) {
+            return Err(HardwareError::UnsupportedCodec);
+        }
+
+        self.decoder_pool
+            .acquire(codec, || Self::create_platform_decoder(codec))
+    }
+
+    /// Get the decoder pool used by [`HardwareContext::acquire_decoder`]
+    pub fn decoder_pool(&self) -> &DecoderPool {
+        &self.decoder_pool
+    }
+
+    /// Create a poolable decoder for the current platform
+    #[allow(unused_variables)]
+    fn create_platform_decoder(codec: &VideoCodec) -> HardwareResult<Box<dyn HardwareDecoder>> {
+        #[cfg(target_os = "linux")]
+        {
+            Ok(Box::new(VAAPIDecoder::new(codec)?))
+        }
+
+        #[cfg(target_os = "windows")]
+        {
+            Ok(Box::new(DXVADecoder::new(codec)?))
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            Ok(Box::new(VideoToolboxDecoder::new(codec)?))
+        }
+
+        #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+        {
+            Err(HardwareError::NotAvailable)
+        }
+    }
+
+    /// Create a decoder, falling back to software decoding if needed
+    ///
+    /// Tries [`HardwareContext::create_decoder`] first. If the hardware path
+    /// reports `UnsupportedCodec` or `NotAvailable`, a software decoder is
+    /// created through `video_decoders::DecoderFactory` instead. The returned
+    /// [`FallbackDecoder`] reports which path was chosen via
+    /// [`FallbackDecoder::is_hardware`].
+    ///
+    /// # Errors
+    ///
+    /// Returns:
+    /// - `MediaError::HardwareError` if the hardware decoder fails for any
+    ///   other reason (e.g., initialization failure)
+    /// - Any error from the software decoder factory, such as
+    ///   `MediaError::UnsupportedFormat` when no software decoder exists
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_hardware_accel::HardwareContext;
+    /// use cortenbrowser_shared_types::{VideoCodec, AV1Profile, AV1Level};
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let ctx = HardwareContext::new()?;
+    ///
+    /// let av1 = VideoCodec::AV1 {
+    ///     profile: AV1Profile::Main,
+    ///     level: AV1Level::Level4_0,
+    /// };
+    ///
+    /// let decoder = ctx.create_decoder_or_fallback(&av1)?;
+    /// println!("Hardware: {}", decoder.is_hardware());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn create_decoder_or_fallback(
+        &self,
+        codec: &VideoCodec,
+    ) -> Result<FallbackDecoder, MediaError> {
+        match self.create_decoder(codec) {
+            Ok(decoder) => Ok(FallbackDecoder::hardware(decoder)),
+            Err(HardwareError::UnsupportedCodec) | Err(HardwareError::NotAvailable) => {
+                let decoder = SoftwareDecoderFactory::create_decoder(codec.clone())?;
+                Ok(FallbackDecoder::software(decoder))
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Get hardware capabilities
     ///
     /// Returns information about supported codecs, maximum resolution,
@@ -246,4 +520,30 @@ impl HardwareContext {
     pub fn get_capabilities(&self) -> &HardwareCapabilities {
         &self.capabilities
     }
+
+    /// Get the name of the hardware driver, for diagnostics
+    ///
+    /// On Linux this is the VA-API vendor string (e.g., "Intel iHD driver");
+    /// on Windows it is the adapter description (e.g., "NVIDIA GeForce RTX
+    /// 3060").
+    /// Returns `None` if no driver was probed and the capabilities are the
+    /// conservative built-in defaults.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_hardware_accel::HardwareContext;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let ctx = HardwareContext::new()?;
+    /// match ctx.driver_name() {
+    ///     Some(name) => println!("Driver: {}", name),
+    ///     None => println!("Driver not probed; using default capabilities"),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn driver_name(&self) -> Option<&str> {
+        self.driver_name.as_deref()
+    }
 }
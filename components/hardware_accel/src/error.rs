@@ -1,5 +1,6 @@
 //! Error types for hardware acceleration operations
 
+use cortenbrowser_shared_types::MediaError;
 use thiserror::Error;
 
 /// Hardware acceleration error types
@@ -33,3 +34,18 @@ pub enum HardwareError {
 
 /// Result type for hardware acceleration operations
 pub type HardwareResult<T> = Result<T, HardwareError>;
+
+impl From<HardwareError> for MediaError {
+    fn from(error: HardwareError) -> Self {
+        MediaError::HardwareError {
+            details: error.to_string(),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<cortenbrowser_shared_types::MissingSymbol> for HardwareError {
+    fn from(_: cortenbrowser_shared_types::MissingSymbol) -> Self {
+        HardwareError::NotAvailable
+    }
+}
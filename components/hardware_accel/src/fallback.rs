@@ -0,0 +1,78 @@
+//! Decoder wrapper that records whether hardware or software decoding is used
+
+use cortenbrowser_shared_types::{MediaError, VideoDecoder, VideoFrame, VideoPacket};
+
+/// Video decoder returned by [`HardwareContext::create_decoder_or_fallback`]
+///
+/// Wraps either a hardware decoder or a software decoder from
+/// `video_decoders` and forwards all decoding calls to it. Use
+/// [`FallbackDecoder::is_hardware`] to find out which path was chosen.
+///
+/// [`HardwareContext::create_decoder_or_fallback`]: crate::HardwareContext::create_decoder_or_fallback
+///
+/// # Examples
+///
+/// ```no_run
+/// use cortenbrowser_hardware_accel::HardwareContext;
+/// use cortenbrowser_shared_types::{VideoCodec, H264Profile, H264Level};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let ctx = HardwareContext::new()?;
+///
+/// let h264 = VideoCodec::H264 {
+///     profile: H264Profile::High,
+///     level: H264Level::Level4_1,
+///     hardware_accel: true,
+/// };
+///
+/// let decoder = ctx.create_decoder_or_fallback(&h264)?;
+/// if decoder.is_hardware() {
+///     println!("Using hardware decoder");
+/// } else {
+///     println!("Using software decoder");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct FallbackDecoder {
+    inner: Box<dyn VideoDecoder>,
+    hardware: bool,
+}
+
+impl FallbackDecoder {
+    /// Wrap a hardware decoder
+    pub(crate) fn hardware(inner: Box<dyn VideoDecoder>) -> Self {
+        Self {
+            inner,
+            hardware: true,
+        }
+    }
+
+    /// Wrap a software decoder
+    pub(crate) fn software(inner: Box<dyn VideoDecoder>) -> Self {
+        Self {
+            inner,
+            hardware: false,
+        }
+    }
+
+    /// Returns `true` if the wrapped decoder is hardware accelerated
+    pub fn is_hardware(&self) -> bool {
+        self.hardware
+    }
+
+    /// Unwrap into the underlying decoder
+    pub fn into_inner(self) -> Box<dyn VideoDecoder> {
+        self.inner
+    }
+}
+
+impl VideoDecoder for FallbackDecoder {
+    fn decode(&mut self, packet: &VideoPacket) -> Result<VideoFrame, MediaError> {
+        self.inner.decode(packet)
+    }
+
+    fn flush(&mut self) -> Result<Vec<VideoFrame>, MediaError> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,274 @@
+//! H.264 sequence parameter set parsing
+//!
+//! Hardware decoders need the coded picture size before the first frame
+//! comes back from the driver. For H.264 it is carried in the sequence
+//! parameter set (SPS, NAL unit type 7), which encoders repeat ahead of
+//! every keyframe:
+//!
+//! ```text
+//! +-------------+------------+-------+--------+-----+------------------+------------------+-----+---------+
+//! | profile_idc | constraint | level | sps_id | ... | pic_width_in_mbs | pic_height_in_mu | ... | cropping |
+//! | u(8)        | u(8)       | u(8)  | ue(v)  |     | ue(v)            | ue(v)            |     |         |
+//! +-------------+------------+-------+--------+-----+------------------+------------------+-----+---------+
+//! ```
+//!
+//! Sizes are coded in 16x16 macroblocks and trimmed by the frame cropping
+//! offsets, e.g. 1080p is coded as 1088 lines with 8 cropped.
+
+/// NAL unit type of a sequence parameter set
+const NAL_TYPE_SPS: u8 = 7;
+
+/// Profiles whose SPS carries chroma format and bit depth fields
+const HIGH_PROFILES: [u8; 13] = [100, 110, 122, 244, 44, 83, 86, 118, 128, 138, 139, 134, 135];
+
+/// Find the first SPS in Annex B data and return its display size
+///
+/// # Returns
+///
+/// `Some((width, height))` in pixels after cropping, or `None` if there is
+/// no SPS or it is malformed
+pub(crate) fn find_sps_dimensions(data: &[u8]) -> Option<(u32, u32)> {
+    nal_units(data)
+        .find(|nal| {
+            nal.first()
+                .is_some_and(|&header| header & 0x1F == NAL_TYPE_SPS)
+        })
+        .and_then(|nal| sps_dimensions(&nal[1..]))
+}
+
+/// Split Annex B data on `00 00 01` start codes
+fn nal_units(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut starts = Vec::new();
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i..i + 3] == [0, 0, 1] {
+            starts.push(i + 3);
+            i += 3;
+        } else {
+            i += 1;
+        }
+    }
+
+    let ends: Vec<usize> = starts
+        .iter()
+        .skip(1)
+        // The start code of the next unit, including a four-byte zero prefix
+        .map(|&next| next - 3 - usize::from(next >= 4 && data[next - 4] == 0))
+        .chain(std::iter::once(data.len()))
+        .collect();
+    starts
+        .into_iter()
+        .zip(ends)
+        .filter(|&(start, end)| start < end)
+        .map(move |(start, end)| &data[start..end])
+}
+
+/// Parse the display size from an SPS payload (after the NAL header)
+fn sps_dimensions(payload: &[u8]) -> Option<(u32, u32)> {
+    let rbsp = remove_emulation_prevention(payload);
+    let mut r = BitReader::new(&rbsp);
+
+    let profile_idc = r.bits(8)? as u8;
+    r.bits(16)?; // constraint flags, level_idc
+    r.ue()?; // seq_parameter_set_id
+
+    let mut chroma_format_idc = 1;
+    let mut separate_colour_plane = false;
+    if HIGH_PROFILES.contains(&profile_idc) {
+        chroma_format_idc = r.ue()?;
+        if chroma_format_idc == 3 {
+            separate_colour_plane = r.bit()?;
+        }
+        r.ue()?; // bit_depth_luma_minus8
+        r.ue()?; // bit_depth_chroma_minus8
+        r.bit()?; // qpprime_y_zero_transform_bypass_flag
+        if r.bit()? {
+            let lists = if chroma_format_idc == 3 { 12 } else { 8 };
+            for i in 0..lists {
+                if r.bit()? {
+                    skip_scaling_list(&mut r, if i < 6 { 16 } else { 64 })?;
+                }
+            }
+        }
+    }
+
+    r.ue()?; // log2_max_frame_num_minus4
+    match r.ue()? {
+        0 => {
+            r.ue()?; // log2_max_pic_order_cnt_lsb_minus4
+        }
+        1 => {
+            r.bit()?; // delta_pic_order_always_zero_flag
+            r.se()?; // offset_for_non_ref_pic
+            r.se()?; // offset_for_top_to_bottom_field
+            for _ in 0..r.ue()? {
+                r.se()?; // offset_for_ref_frame
+            }
+        }
+        _ => {}
+    }
+    r.ue()?; // max_num_ref_frames
+    r.bit()?; // gaps_in_frame_num_value_allowed_flag
+
+    let width_in_mbs = r.ue()?.checked_add(1)?;
+    let height_in_map_units = r.ue()?.checked_add(1)?;
+    let frame_mbs_only = r.bit()?;
+    if !frame_mbs_only {
+        r.bit()?; // mb_adaptive_frame_field_flag
+    }
+    r.bit()?; // direct_8x8_inference_flag
+
+    let (mut left, mut right, mut top, mut bottom) = (0, 0, 0, 0);
+    if r.bit()? {
+        left = r.ue()?;
+        right = r.ue()?;
+        top = r.ue()?;
+        bottom = r.ue()?;
+    }
+
+    // Cropping is in chroma sample units, doubled vertically for field coding
+    let field_factor = if frame_mbs_only { 1 } else { 2 };
+    let (sub_width, sub_height) = match (chroma_format_idc, separate_colour_plane) {
+        (1, _) => (2, 2),
+        (2, _) => (2, 1),
+        _ => (1, 1),
+    };
+    let width = width_in_mbs
+        .checked_mul(16)?
+        .checked_sub(sub_width * (left.checked_add(right)?))?;
+    let height = height_in_map_units
+        .checked_mul(16 * field_factor)?
+        .checked_sub(sub_height * field_factor * (top.checked_add(bottom)?))?;
+
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+/// Skip a scaling_list() structure
+fn skip_scaling_list(r: &mut BitReader<'_>, size: usize) -> Option<()> {
+    let mut last = 8i32;
+    let mut next = 8i32;
+    for _ in 0..size {
+        if next != 0 {
+            next = (last + r.se()?).rem_euclid(256);
+        }
+        if next != 0 {
+            last = next;
+        }
+    }
+    Some(())
+}
+
+/// Drop the `03` in every `00 00 03` sequence
+fn remove_emulation_prevention(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeros = 0;
+    for &byte in data {
+        if zeros >= 2 && byte == 3 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        out.push(byte);
+    }
+    out
+}
+
+/// MSB-first bit reader with Exp-Golomb support
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Option<bool> {
+        let byte = self.data.get(self.pos / 8)?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Some(bit == 1)
+    }
+
+    fn bits(&mut self, count: u32) -> Option<u32> {
+        (0..count).try_fold(0u32, |value, _| Some((value << 1) | u32::from(self.bit()?)))
+    }
+
+    /// Unsigned Exp-Golomb code
+    fn ue(&mut self) -> Option<u32> {
+        let mut leading_zeros = 0;
+        while !self.bit()? {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return None;
+            }
+        }
+        let suffix = self.bits(leading_zeros)?;
+        ((1u64 << leading_zeros) - 1 + u64::from(suffix))
+            .try_into()
+            .ok()
+    }
+
+    /// Signed Exp-Golomb code
+    fn se(&mut self) -> Option<i32> {
+        let code = i64::from(self.ue()?);
+        let value = if code % 2 == 1 {
+            (code + 1) / 2
+        } else {
+            -(code / 2)
+        };
+        i32::try_from(value).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Baseline 640x480, no cropping
+    const SPS_640X480: [u8; 9] = [0x67, 0x42, 0xC0, 0x1E, 0xF4, 0x05, 0x01, 0xEC, 0x80];
+
+    /// High profile 1920x1080, coded as 1088 lines with 8 cropped
+    const SPS_1080P: [u8; 27] = [
+        0x67, 0x64, 0x00, 0x28, 0xAC, 0xD9, 0x40, 0x78, 0x02, 0x27, 0xE5, 0xC0, 0x44, 0x00, 0x00,
+        0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xF0, 0x3C, 0x60, 0xC6, 0x58,
+    ];
+
+    fn annex_b(nals: &[&[u8]]) -> Vec<u8> {
+        nals.iter()
+            .flat_map(|nal| [&[0, 0, 0, 1][..], nal].concat())
+            .collect()
+    }
+
+    #[test]
+    fn test_sps_dimensions() {
+        assert_eq!(
+            find_sps_dimensions(&annex_b(&[&SPS_640X480])),
+            Some((640, 480))
+        );
+        assert_eq!(
+            find_sps_dimensions(&annex_b(&[&SPS_1080P])),
+            Some((1920, 1080))
+        );
+    }
+
+    #[test]
+    fn test_sps_found_among_other_nal_units() {
+        // AUD, SPS, PPS, IDR slice
+        let data = annex_b(&[
+            &[0x09, 0xF0],
+            &SPS_640X480,
+            &[0x68, 0xCE, 0x38, 0x80],
+            &[0x65, 0x88],
+        ]);
+        assert_eq!(find_sps_dimensions(&data), Some((640, 480)));
+    }
+
+    #[test]
+    fn test_missing_or_truncated_sps() {
+        assert_eq!(find_sps_dimensions(&annex_b(&[&[0x65, 0x88, 0x84]])), None);
+        assert_eq!(find_sps_dimensions(&annex_b(&[&SPS_1080P[..8]])), None);
+        assert_eq!(find_sps_dimensions(&[0u8; 100]), None);
+    }
+}
@@ -4,15 +4,15 @@
 //!
 //! This component provides hardware video decoding support across multiple platforms:
 //! - **Linux**: VA-API (Video Acceleration API)
-//! - **Windows**: DXVA (DirectX Video Acceleration) - stub
+//! - **Windows**: DXVA (DirectX Video Acceleration)
 //! - **macOS**: VideoToolbox - stub
 //!
 //! # Platform Support
 //!
 //! | Platform | API | Status | Codecs |
 //! |----------|-----|--------|--------|
-//! | Linux | VA-API | ✅ Implemented (mock) | H.264, VP9, VP8, H.265, AV1 |
-//! | Windows | DXVA | ⚠️ Stub | N/A |
+//! | Linux | VA-API | ✅ Capability probe; decode mocked | H.264, VP9, VP8, H.265, AV1 |
+//! | Windows | DXVA | ✅ Capability probe; decode mocked | H.264, H.265, VP8, VP9, AV1 |
 //! | macOS | VideoToolbox | ⚠️ Stub | N/A |
 //!
 //! # Architecture
@@ -22,6 +22,7 @@
 //! - Reports hardware capabilities
 //! - Creates platform-specific decoders
 //! - Provides automatic fallback when hardware is unavailable
+//! - Pools decoders for reuse across sessions
 //!
 //! # Usage
 //!
@@ -114,6 +115,52 @@
 //! # }
 //! ```
 //!
+//! The same fallback is available as a single call:
+//!
+//! ```no_run
+//! use cortenbrowser_hardware_accel::HardwareContext;
+//! use cortenbrowser_shared_types::{VideoCodec, H264Profile, H264Level};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let codec = VideoCodec::H264 {
+//!     profile: H264Profile::High,
+//!     level: H264Level::Level4_1,
+//!     hardware_accel: true,
+//! };
+//!
+//! let ctx = HardwareContext::new()?;
+//! let decoder = ctx.create_decoder_or_fallback(&codec)?;
+//! println!("Hardware decoding: {}", decoder.is_hardware());
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Decoder Reuse
+//!
+//! Hardware decoder initialization is expensive. For rapid session churn,
+//! acquire decoders from the context's pool; dropping one resets it and
+//! returns it for the next session with the same codec:
+//!
+//! ```no_run
+//! use cortenbrowser_hardware_accel::{DecoderPoolConfig, HardwareContext};
+//! use cortenbrowser_shared_types::{VideoCodec, VP9Profile};
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let ctx = HardwareContext::new()?.with_decoder_pool_config(DecoderPoolConfig { max_idle: 8 });
+//! let vp9 = VideoCodec::VP9 {
+//!     profile: VP9Profile::Profile0,
+//! };
+//!
+//! let first = ctx.acquire_decoder(&vp9)?;
+//! let id = first.id();
+//! drop(first);
+//!
+//! // The same decoder instance is handed out again
+//! assert_eq!(ctx.acquire_decoder(&vp9)?.id(), id);
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! # Error Handling
 //!
 //! All operations that can fail return [`HardwareResult<T>`](error::HardwareResult),
@@ -156,12 +203,11 @@
 //!
 //! ## Windows (DXVA)
 //!
-//! **Status**: Stub implementation
+//! **Status**: Capability probe through Direct3D 11; decode mocked
 //!
-//! When implemented, will require:
-//! - Windows Vista or later
-//! - DirectX 11 or later
-//! - GPU with DXVA2 support
+//! Requires:
+//! - Windows 8 or later
+//! - A GPU driver exposing `ID3D11VideoDevice`
 //!
 //! ## macOS (VideoToolbox)
 //!
@@ -177,7 +223,11 @@
 mod capabilities;
 mod context;
 mod error;
+mod fallback;
+mod pool;
 
+#[cfg(any(target_os = "linux", target_os = "windows"))]
+mod h264;
 #[cfg(target_os = "linux")]
 mod vaapi;
 
@@ -188,9 +238,11 @@ mod dxva;
 mod videotoolbox;
 
 // Re-export public API
-pub use capabilities::HardwareCapabilities;
+pub use capabilities::{CodecLimits, HardwareCapabilities};
 pub use context::HardwareContext;
 pub use error::{HardwareError, HardwareResult};
+pub use fallback::FallbackDecoder;
+pub use pool::{DecoderPool, DecoderPoolConfig, PooledDecoder};
 
 #[cfg(target_os = "linux")]
 pub use vaapi::VAAPIDecoder;
@@ -0,0 +1,380 @@
+//! Pool of reusable hardware decoders
+//!
+//! Creating a hardware decoder is expensive: on VA-API it opens the display,
+//! creates a decoder config and allocates surfaces. When many short clips
+//! play in a row, [`HardwareContext::acquire_decoder`] hands out decoders
+//! from a [`DecoderPool`] instead, and dropping the returned
+//! [`PooledDecoder`] resets the decoder and returns it to the pool.
+//!
+//! [`HardwareContext::acquire_decoder`]: crate::HardwareContext::acquire_decoder
+
+use crate::error::HardwareResult;
+use cortenbrowser_shared_types::{MediaError, VideoCodec, VideoDecoder, VideoFrame, VideoPacket};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+
+/// Default number of idle decoders kept by a pool
+const DEFAULT_MAX_IDLE: usize = 4;
+
+/// A platform hardware decoder that can be reset and reused
+pub(crate) trait HardwareDecoder: VideoDecoder + Send {
+    /// Drop all decoding state so the decoder can start a new stream
+    fn reset(&mut self) -> HardwareResult<()>;
+}
+
+/// Configuration for a [`DecoderPool`]
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_hardware_accel::DecoderPoolConfig;
+///
+/// let config = DecoderPoolConfig { max_idle: 8 };
+/// assert_eq!(DecoderPoolConfig::default().max_idle, 4);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecoderPoolConfig {
+    /// Maximum number of idle decoders kept across all codecs
+    ///
+    /// Decoders returned while the pool is full are destroyed. Zero disables
+    /// pooling.
+    pub max_idle: usize,
+}
+
+impl Default for DecoderPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle: DEFAULT_MAX_IDLE,
+        }
+    }
+}
+
+/// An idle decoder waiting for reuse
+struct IdleDecoder {
+    id: u64,
+    codec: VideoCodec,
+    decoder: Box<dyn HardwareDecoder>,
+}
+
+/// Idle decoders, oldest first
+struct PoolState {
+    config: DecoderPoolConfig,
+    idle: Vec<IdleDecoder>,
+}
+
+/// Cache of idle hardware decoders, keyed by codec
+///
+/// Owned by [`HardwareContext`](crate::HardwareContext). Decoders are only
+/// reused for the exact same codec, including profile and level, since the
+/// hardware decoder configuration depends on them.
+pub struct DecoderPool {
+    state: Arc<Mutex<PoolState>>,
+    next_id: AtomicU64,
+}
+
+impl DecoderPool {
+    /// Create an empty pool
+    pub(crate) fn new(config: DecoderPoolConfig) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PoolState {
+                config,
+                idle: Vec::new(),
+            })),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Get the pool configuration
+    pub fn config(&self) -> DecoderPoolConfig {
+        self.lock().config
+    }
+
+    /// Number of idle decoders across all codecs
+    pub fn idle_count(&self) -> usize {
+        self.lock().idle.len()
+    }
+
+    /// Number of idle decoders for a codec
+    pub fn idle_count_for(&self, codec: &VideoCodec) -> usize {
+        self.lock()
+            .idle
+            .iter()
+            .filter(|idle| idle.codec == *codec)
+            .count()
+    }
+
+    /// Destroy all idle decoders
+    ///
+    /// Decoders currently handed out are unaffected and return to the pool
+    /// when dropped.
+    pub fn clear(&self) {
+        self.lock().idle.clear();
+    }
+
+    /// Take an idle decoder for `codec`, or create one with `create`
+    ///
+    /// The most recently returned decoder is reused first, since it is the
+    /// most likely to still have warm resources.
+    pub(crate) fn acquire<F>(&self, codec: &VideoCodec, create: F) -> HardwareResult<PooledDecoder>
+    where
+        F: FnOnce() -> HardwareResult<Box<dyn HardwareDecoder>>,
+    {
+        let reused = {
+            let mut state = self.lock();
+            state
+                .idle
+                .iter()
+                .rposition(|idle| idle.codec == *codec)
+                .map(|index| state.idle.remove(index))
+        };
+
+        let (id, decoder) = match reused {
+            Some(idle) => (idle.id, idle.decoder),
+            None => (self.next_id.fetch_add(1, Ordering::Relaxed), create()?),
+        };
+
+        Ok(PooledDecoder {
+            id,
+            codec: codec.clone(),
+            decoder: Some(decoder),
+            pool: Arc::downgrade(&self.state),
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, PoolState> {
+        // A panic while holding the lock cannot leave the idle list
+        // inconsistent, so a poisoned lock is still usable
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl std::fmt::Debug for DecoderPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let state = self.lock();
+        f.debug_struct("DecoderPool")
+            .field("config", &state.config)
+            .field("idle", &state.idle.len())
+            .finish()
+    }
+}
+
+/// A hardware decoder borrowed from a [`DecoderPool`]
+///
+/// Decodes like any other [`VideoDecoder`]. When dropped, the decoder is
+/// reset and returned to the pool it came from, unless the pool is full,
+/// the reset fails, or the owning [`HardwareContext`](crate::HardwareContext)
+/// has been dropped; in those cases the decoder is destroyed.
+pub struct PooledDecoder {
+    id: u64,
+    codec: VideoCodec,
+    decoder: Option<Box<dyn HardwareDecoder>>,
+    pool: Weak<Mutex<PoolState>>,
+}
+
+impl PooledDecoder {
+    /// Identifier of the underlying decoder instance
+    ///
+    /// Stays the same when a decoder is returned to the pool and acquired
+    /// again, so it shows whether a decoder was reused.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Codec the decoder was created for
+    pub fn codec(&self) -> &VideoCodec {
+        &self.codec
+    }
+
+    fn decoder(&mut self) -> &mut dyn HardwareDecoder {
+        self.decoder
+            .as_deref_mut()
+            .expect("decoder is only taken on drop")
+    }
+}
+
+impl VideoDecoder for PooledDecoder {
+    fn decode(&mut self, packet: &VideoPacket) -> Result<VideoFrame, MediaError> {
+        self.decoder().decode(packet)
+    }
+
+    fn flush(&mut self) -> Result<Vec<VideoFrame>, MediaError> {
+        self.decoder().flush()
+    }
+}
+
+impl Drop for PooledDecoder {
+    fn drop(&mut self) {
+        let (Some(mut decoder), Some(pool)) = (self.decoder.take(), self.pool.upgrade()) else {
+            return;
+        };
+        if decoder.reset().is_err() {
+            return;
+        }
+
+        let mut state = pool.lock().unwrap_or_else(|e| e.into_inner());
+        if state.idle.len() < state.config.max_idle {
+            state.idle.push(IdleDecoder {
+                id: self.id,
+                codec: self.codec.clone(),
+                decoder,
+            });
+        }
+    }
+}
+
+impl std::fmt::Debug for PooledDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PooledDecoder")
+            .field("id", &self.id)
+            .field("codec", &self.codec)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::HardwareError;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Decoder that counts resets and drops
+    struct CountingDecoder {
+        resets: Arc<AtomicUsize>,
+        drops: Arc<AtomicUsize>,
+        fail_reset: bool,
+    }
+
+    impl VideoDecoder for CountingDecoder {
+        fn decode(&mut self, _packet: &VideoPacket) -> Result<VideoFrame, MediaError> {
+            Err(MediaError::NotImplemented("mock".to_string()))
+        }
+
+        fn flush(&mut self) -> Result<Vec<VideoFrame>, MediaError> {
+            Ok(Vec::new())
+        }
+    }
+
+    impl HardwareDecoder for CountingDecoder {
+        fn reset(&mut self) -> HardwareResult<()> {
+            self.resets.fetch_add(1, Ordering::SeqCst);
+            if self.fail_reset {
+                Err(HardwareError::DecodeFailed)
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    impl Drop for CountingDecoder {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    struct Counters {
+        resets: Arc<AtomicUsize>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Counters {
+        fn new() -> Self {
+            Self {
+                resets: Arc::new(AtomicUsize::new(0)),
+                drops: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        fn factory(
+            &self,
+            fail_reset: bool,
+        ) -> impl FnOnce() -> HardwareResult<Box<dyn HardwareDecoder>> {
+            let resets = Arc::clone(&self.resets);
+            let drops = Arc::clone(&self.drops);
+            move || {
+                Ok(Box::new(CountingDecoder {
+                    resets,
+                    drops,
+                    fail_reset,
+                }) as Box<dyn HardwareDecoder>)
+            }
+        }
+    }
+
+    #[test]
+    fn test_returned_decoder_is_reset_and_reused() {
+        let pool = DecoderPool::new(DecoderPoolConfig::default());
+        let counters = Counters::new();
+
+        let first = pool
+            .acquire(&VideoCodec::VP8, counters.factory(false))
+            .unwrap();
+        let id = first.id();
+        drop(first);
+
+        assert_eq!(counters.resets.load(Ordering::SeqCst), 1);
+        assert_eq!(counters.drops.load(Ordering::SeqCst), 0);
+        assert_eq!(pool.idle_count_for(&VideoCodec::VP8), 1);
+
+        let second = pool
+            .acquire(&VideoCodec::VP8, || panic!("should reuse the idle decoder"))
+            .unwrap();
+        assert_eq!(second.id(), id);
+        assert_eq!(pool.idle_count(), 0);
+    }
+
+    #[test]
+    fn test_excess_decoders_are_destroyed() {
+        let pool = DecoderPool::new(DecoderPoolConfig { max_idle: 1 });
+        let counters = Counters::new();
+
+        let a = pool
+            .acquire(&VideoCodec::VP8, counters.factory(false))
+            .unwrap();
+        let b = pool
+            .acquire(&VideoCodec::VP8, counters.factory(false))
+            .unwrap();
+        assert_ne!(a.id(), b.id());
+
+        drop(a);
+        drop(b);
+        assert_eq!(pool.idle_count(), 1);
+        assert_eq!(counters.drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_failed_reset_destroys_decoder() {
+        let pool = DecoderPool::new(DecoderPoolConfig::default());
+        let counters = Counters::new();
+
+        drop(
+            pool.acquire(&VideoCodec::VP8, counters.factory(true))
+                .unwrap(),
+        );
+        assert_eq!(pool.idle_count(), 0);
+        assert_eq!(counters.drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_decoder_outliving_pool_is_destroyed() {
+        let pool = DecoderPool::new(DecoderPoolConfig::default());
+        let counters = Counters::new();
+
+        let decoder = pool
+            .acquire(&VideoCodec::VP8, counters.factory(false))
+            .unwrap();
+        drop(pool);
+        drop(decoder);
+
+        assert_eq!(counters.resets.load(Ordering::SeqCst), 0);
+        assert_eq!(counters.drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_create_error_is_returned() {
+        let pool = DecoderPool::new(DecoderPoolConfig::default());
+        let result = pool.acquire(&VideoCodec::VP8, || {
+            Err(HardwareError::InitializationFailed)
+        });
+        assert_eq!(result.unwrap_err(), HardwareError::InitializationFailed);
+    }
+}
@@ -1,11 +1,256 @@
 //! VA-API hardware decoder for Linux
+//!
+//! Also provides the runtime capability probe used by
+//! [`HardwareContext`](crate::HardwareContext). The probe loads `libva` at
+//! runtime, and simply fails on systems without VA-API installed.
 
 use crate::error::{HardwareError, HardwareResult};
+use crate::h264;
+use crate::pool::HardwareDecoder;
 use cortenbrowser_shared_types::{
-    FrameMetadata, MediaError, PixelFormat, VideoCodec, VideoDecoder, VideoFrame, VideoPacket,
+    AV1Level, AV1Profile, FrameMetadata, H264Level, H264Profile, H265Level, H265Profile, H265Tier,
+    Library, MediaError, PixelFormat, VP9Profile, VideoCodec, VideoDecoder, VideoFrame,
+    VideoPacket,
 };
+use libc::{c_char, c_int, c_void};
+use std::ffi::CStr;
 use std::time::Duration;
 
+/// DRM render nodes tried in order when opening a VA display
+const RENDER_NODES: [&CStr; 4] = [
+    c"/dev/dri/renderD128",
+    c"/dev/dri/renderD129",
+    c"/dev/dri/renderD130",
+    c"/dev/dri/renderD131",
+];
+
+/// Output size used until the stream size is known
+const DEFAULT_WIDTH: u32 = 1920;
+const DEFAULT_HEIGHT: u32 = 1080;
+
+/// `VA_STATUS_SUCCESS`
+const VA_STATUS_SUCCESS: c_int = 0;
+
+/// `VAEntrypointVLD` - bitstream decoding
+const VA_ENTRYPOINT_VLD: c_int = 1;
+
+// `VAProfile` values from va.h
+const VA_PROFILE_H264_BASELINE: c_int = 5;
+const VA_PROFILE_H264_MAIN: c_int = 6;
+const VA_PROFILE_H264_HIGH: c_int = 7;
+const VA_PROFILE_H264_CONSTRAINED_BASELINE: c_int = 13;
+const VA_PROFILE_VP8_VERSION0_3: c_int = 14;
+const VA_PROFILE_HEVC_MAIN: c_int = 17;
+const VA_PROFILE_HEVC_MAIN10: c_int = 18;
+const VA_PROFILE_VP9_PROFILE0: c_int = 19;
+const VA_PROFILE_VP9_PROFILE1: c_int = 20;
+const VA_PROFILE_VP9_PROFILE2: c_int = 21;
+const VA_PROFILE_VP9_PROFILE3: c_int = 22;
+const VA_PROFILE_AV1_PROFILE0: c_int = 32;
+const VA_PROFILE_AV1_PROFILE1: c_int = 33;
+
+type VADisplay = *mut c_void;
+type VaGetDisplayDrmFn = unsafe extern "C" fn(c_int) -> VADisplay;
+type VaInitializeFn = unsafe extern "C" fn(VADisplay, *mut c_int, *mut c_int) -> c_int;
+type VaTerminateFn = unsafe extern "C" fn(VADisplay) -> c_int;
+type VaQueryVendorStringFn = unsafe extern "C" fn(VADisplay) -> *const c_char;
+type VaMaxNumFn = unsafe extern "C" fn(VADisplay) -> c_int;
+type VaQueryConfigProfilesFn = unsafe extern "C" fn(VADisplay, *mut c_int, *mut c_int) -> c_int;
+type VaQueryConfigEntrypointsFn =
+    unsafe extern "C" fn(VADisplay, c_int, *mut c_int, *mut c_int) -> c_int;
+
+/// Result of probing the VA-API driver
+#[derive(Debug, Clone)]
+pub(crate) struct VaapiProbe {
+    /// Driver vendor string (as reported by `vaQueryVendorString`)
+    pub(crate) driver_name: String,
+    /// Codecs with a decode (VLD) entrypoint
+    pub(crate) codecs: Vec<VideoCodec>,
+}
+
+/// Probe the VA-API driver for supported decode profiles
+///
+/// Opens the first usable DRM render node, initializes a VA display and
+/// enumerates profiles with `vaQueryConfigProfiles`. Only profiles that
+/// expose a VLD (decode) entrypoint are reported.
+///
+/// # Errors
+///
+/// Returns `HardwareError::NotAvailable` if `libva` cannot be loaded or no
+/// render node yields an initialized VA display.
+pub(crate) fn probe() -> HardwareResult<VaapiProbe> {
+    let va = Library::open(c"libva.so.2").ok_or(HardwareError::NotAvailable)?;
+    let va_drm = Library::open(c"libva-drm.so.2").ok_or(HardwareError::NotAvailable)?;
+
+    // SAFETY: the function types match the libva 2.x C declarations
+    let api = unsafe {
+        VaApi {
+            get_display_drm: va_drm.symbol(c"vaGetDisplayDRM")?,
+            initialize: va.symbol(c"vaInitialize")?,
+            terminate: va.symbol(c"vaTerminate")?,
+            query_vendor_string: va.symbol(c"vaQueryVendorString")?,
+            max_num_profiles: va.symbol(c"vaMaxNumProfiles")?,
+            max_num_entrypoints: va.symbol(c"vaMaxNumEntrypoints")?,
+            query_config_profiles: va.symbol(c"vaQueryConfigProfiles")?,
+            query_config_entrypoints: va.symbol(c"vaQueryConfigEntrypoints")?,
+        }
+    };
+
+    RENDER_NODES
+        .iter()
+        .find_map(|node| api.probe_node(node))
+        .ok_or(HardwareError::NotAvailable)
+}
+
+/// Map a VA-API profile to the codec it decodes
+///
+/// Levels are not part of a VA-API profile, so the highest level we model is
+/// reported. Returns `None` for profiles with no [`VideoCodec`] equivalent
+/// (MPEG-2, VC-1, JPEG, ...).
+pub(crate) fn codec_for_va_profile(profile: c_int) -> Option<VideoCodec> {
+    let h264 = |profile| VideoCodec::H264 {
+        profile,
+        level: H264Level::Level5_1,
+        hardware_accel: true,
+    };
+    let h265 = |profile| VideoCodec::H265 {
+        profile,
+        tier: H265Tier::Main,
+        level: H265Level::Level5_1,
+    };
+    let av1 = |profile| VideoCodec::AV1 {
+        profile,
+        level: AV1Level::Level5_1,
+    };
+
+    match profile {
+        VA_PROFILE_H264_BASELINE | VA_PROFILE_H264_CONSTRAINED_BASELINE => {
+            Some(h264(H264Profile::Baseline))
+        }
+        VA_PROFILE_H264_MAIN => Some(h264(H264Profile::Main)),
+        VA_PROFILE_H264_HIGH => Some(h264(H264Profile::High)),
+        VA_PROFILE_VP8_VERSION0_3 => Some(VideoCodec::VP8),
+        VA_PROFILE_HEVC_MAIN => Some(h265(H265Profile::Main)),
+        VA_PROFILE_HEVC_MAIN10 => Some(h265(H265Profile::Main10)),
+        VA_PROFILE_VP9_PROFILE0 => Some(VideoCodec::VP9 {
+            profile: VP9Profile::Profile0,
+        }),
+        VA_PROFILE_VP9_PROFILE1 => Some(VideoCodec::VP9 {
+            profile: VP9Profile::Profile1,
+        }),
+        VA_PROFILE_VP9_PROFILE2 => Some(VideoCodec::VP9 {
+            profile: VP9Profile::Profile2,
+        }),
+        VA_PROFILE_VP9_PROFILE3 => Some(VideoCodec::VP9 {
+            profile: VP9Profile::Profile3,
+        }),
+        VA_PROFILE_AV1_PROFILE0 => Some(av1(AV1Profile::Main)),
+        VA_PROFILE_AV1_PROFILE1 => Some(av1(AV1Profile::High)),
+        _ => None,
+    }
+}
+
+/// libva entry points used by the probe
+struct VaApi {
+    get_display_drm: VaGetDisplayDrmFn,
+    initialize: VaInitializeFn,
+    terminate: VaTerminateFn,
+    query_vendor_string: VaQueryVendorStringFn,
+    max_num_profiles: VaMaxNumFn,
+    max_num_entrypoints: VaMaxNumFn,
+    query_config_profiles: VaQueryConfigProfilesFn,
+    query_config_entrypoints: VaQueryConfigEntrypointsFn,
+}
+
+impl VaApi {
+    /// Probe one DRM render node, or `None` if it cannot be used
+    fn probe_node(&self, node: &CStr) -> Option<VaapiProbe> {
+        // SAFETY: `node` is a valid path; the descriptor is closed below
+        let fd = unsafe { libc::open(node.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return None;
+        }
+
+        // SAFETY: `fd` is an open DRM node; the display is terminated before
+        // the descriptor is closed
+        let result = unsafe {
+            let display = (self.get_display_drm)(fd);
+            if display.is_null() {
+                None
+            } else {
+                let (mut major, mut minor) = (0, 0);
+                if (self.initialize)(display, &mut major, &mut minor) == VA_STATUS_SUCCESS {
+                    let probe = self.query_display(display);
+                    (self.terminate)(display);
+                    Some(probe)
+                } else {
+                    None
+                }
+            }
+        };
+
+        // SAFETY: `fd` was opened above
+        unsafe {
+            libc::close(fd);
+        }
+        result
+    }
+
+    /// Read the vendor string and decodable profiles of an initialized display
+    ///
+    /// # Safety
+    ///
+    /// `display` must be an initialized VA display.
+    unsafe fn query_display(&self, display: VADisplay) -> VaapiProbe {
+        let vendor = (self.query_vendor_string)(display);
+        let driver_name = if vendor.is_null() {
+            "unknown".to_string()
+        } else {
+            CStr::from_ptr(vendor).to_string_lossy().into_owned()
+        };
+
+        let mut profiles = vec![0 as c_int; (self.max_num_profiles)(display).max(0) as usize];
+        let mut num_profiles: c_int = 0;
+        if (self.query_config_profiles)(display, profiles.as_mut_ptr(), &mut num_profiles)
+            != VA_STATUS_SUCCESS
+        {
+            num_profiles = 0;
+        }
+        profiles.truncate(num_profiles.clamp(0, profiles.len() as c_int) as usize);
+
+        let max_entrypoints = (self.max_num_entrypoints)(display).max(0) as usize;
+        let mut codecs: Vec<VideoCodec> = Vec::new();
+        for profile in profiles {
+            let Some(codec) = codec_for_va_profile(profile) else {
+                continue;
+            };
+
+            let mut entrypoints = vec![0 as c_int; max_entrypoints];
+            let mut num_entrypoints: c_int = 0;
+            let status = (self.query_config_entrypoints)(
+                display,
+                profile,
+                entrypoints.as_mut_ptr(),
+                &mut num_entrypoints,
+            );
+            let decodable = status == VA_STATUS_SUCCESS
+                && entrypoints
+                    .iter()
+                    .take(num_entrypoints.max(0) as usize)
+                    .any(|&e| e == VA_ENTRYPOINT_VLD);
+
+            if decodable && !codecs.contains(&codec) {
+                codecs.push(codec);
+            }
+        }
+
+        VaapiProbe {
+            driver_name,
+            codecs,
+        }
+    }
+}
+
 /// VA-API hardware video decoder
 ///
 /// Provides hardware-accelerated video decoding on Linux systems using VA-API.
@@ -36,8 +281,12 @@ use std::time::Duration;
 /// # }
 /// ```
 pub struct VAAPIDecoder {
-    _codec: VideoCodec, // Stored for future use (e.g., reconfiguration)
+    codec: VideoCodec,
     initialized: bool,
+    /// Coded picture width in pixels
+    width: u32,
+    /// Coded picture height in pixels
+    height: u32,
 }
 
 impl VAAPIDecoder {
@@ -86,11 +335,94 @@ impl VAAPIDecoder {
         // This allows testing without actual VA-API hardware
 
         Ok(Self {
-            _codec: codec.clone(),
+            codec: codec.clone(),
             initialized: true,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
         })
     }
 
+    /// Set the picture size of the stream
+    ///
+    /// Use this when the size is known from the container's codec
+    /// configuration. For H.264 the decoder also picks the size up from the
+    /// sequence parameter set sent with each keyframe. Until either happens,
+    /// frames are 1920x1080.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HardwareError::InitializationFailed` if either dimension is
+    /// zero, and `HardwareError::NotAvailable` if the decoder has been shut
+    /// down.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_hardware_accel::VAAPIDecoder;
+    /// use cortenbrowser_shared_types::VideoCodec;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut decoder = VAAPIDecoder::new(&VideoCodec::VP8)?;
+    /// decoder.configure(640, 480)?;
+    /// assert_eq!(decoder.dimensions(), (640, 480));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn configure(&mut self, width: u32, height: u32) -> HardwareResult<()> {
+        if !self.initialized {
+            return Err(HardwareError::NotAvailable);
+        }
+        if width == 0 || height == 0 {
+            return Err(HardwareError::InitializationFailed);
+        }
+
+        // In a real implementation, this would recreate the VA context and
+        // its surfaces at the new size (vaCreateSurfaces, vaCreateContext)
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// Get the picture size of decoded frames as `(width, height)`
+    pub fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Reset the decoder for a new stream
+    ///
+    /// Discards reference frames and pending output while keeping the
+    /// VA-API context, so the decoder can be reused without the cost of
+    /// re-initialization. The picture size returns to the default until the
+    /// new stream configures it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HardwareError::NotAvailable` if the decoder has been shut down.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_hardware_accel::VAAPIDecoder;
+    /// use cortenbrowser_shared_types::VideoCodec;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut decoder = VAAPIDecoder::new(&VideoCodec::VP8)?;
+    /// decoder.reset()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reset(&mut self) -> HardwareResult<()> {
+        if !self.initialized {
+            return Err(HardwareError::NotAvailable);
+        }
+
+        // In a real implementation, this would release the reference surfaces
+        // held for the current stream; the VA config and context are kept.
+        self.width = DEFAULT_WIDTH;
+        self.height = DEFAULT_HEIGHT;
+        Ok(())
+    }
+
     /// Check if a codec is supported by VA-API
     fn is_codec_supported(codec: &VideoCodec) -> bool {
         match codec {
@@ -138,6 +470,14 @@ impl VideoDecoder for VAAPIDecoder {
             });
         }
 
+        // Keyframes may carry a new sequence header
+        if packet.is_keyframe && matches!(self.codec, VideoCodec::H264 { .. }) {
+            if let Some((width, height)) = h264::find_sps_dimensions(&packet.data) {
+                self.width = width;
+                self.height = height;
+            }
+        }
+
         // In a real implementation, this would decode the packet using VA-API
         // For now, return a mock frame for testing purposes
 
@@ -150,10 +490,11 @@ impl VideoDecoder for VAAPIDecoder {
         // Create mock decoded frame
         // In reality, this would be the actual decoded YUV data from hardware
         Ok(VideoFrame {
-            width: 1920,
-            height: 1080,
+            width: self.width,
+            height: self.height,
             format: PixelFormat::YUV420,
-            data: vec![0u8; 1920 * 1080 * 3 / 2], // YUV420 size
+            data: vec![0u8; self.width as usize * self.height as usize * 3 / 2], // YUV420 size
+            strides: None,
             timestamp,
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -185,6 +526,12 @@ impl VideoDecoder for VAAPIDecoder {
     }
 }
 
+impl HardwareDecoder for VAAPIDecoder {
+    fn reset(&mut self) -> HardwareResult<()> {
+        VAAPIDecoder::reset(self)
+    }
+}
+
 impl Drop for VAAPIDecoder {
     fn drop(&mut self) {
         // In a real implementation, this would:
@@ -200,7 +547,6 @@ impl Drop for VAAPIDecoder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cortenbrowser_shared_types::{H264Level, H264Profile};
 
     #[test]
     fn test_vaapi_decoder_creation() {
@@ -214,6 +560,62 @@ mod tests {
         assert!(decoder.is_ok());
     }
 
+    #[test]
+    fn test_codec_for_va_profile() {
+        assert!(matches!(
+            codec_for_va_profile(VA_PROFILE_H264_CONSTRAINED_BASELINE),
+            Some(VideoCodec::H264 {
+                profile: H264Profile::Baseline,
+                hardware_accel: true,
+                ..
+            })
+        ));
+        assert!(matches!(
+            codec_for_va_profile(VA_PROFILE_H264_HIGH),
+            Some(VideoCodec::H264 {
+                profile: H264Profile::High,
+                ..
+            })
+        ));
+        assert_eq!(
+            codec_for_va_profile(VA_PROFILE_VP8_VERSION0_3),
+            Some(VideoCodec::VP8)
+        );
+        assert_eq!(
+            codec_for_va_profile(VA_PROFILE_VP9_PROFILE2),
+            Some(VideoCodec::VP9 {
+                profile: VP9Profile::Profile2
+            })
+        );
+        assert!(matches!(
+            codec_for_va_profile(VA_PROFILE_HEVC_MAIN10),
+            Some(VideoCodec::H265 {
+                profile: H265Profile::Main10,
+                ..
+            })
+        ));
+        assert!(matches!(
+            codec_for_va_profile(VA_PROFILE_AV1_PROFILE0),
+            Some(VideoCodec::AV1 {
+                profile: AV1Profile::Main,
+                ..
+            })
+        ));
+
+        // MPEG-2 Simple, VC-1 Advanced, JPEG Baseline, VAProfileNone
+        for profile in [0, 10, 12, -1] {
+            assert_eq!(codec_for_va_profile(profile), None);
+        }
+    }
+
+    #[test]
+    fn test_vaapi_probe_does_not_panic() {
+        // Succeeds only with a VA-API driver; must fail cleanly otherwise
+        if let Ok(probe) = probe() {
+            assert!(!probe.driver_name.is_empty());
+        }
+    }
+
     #[test]
     fn test_vaapi_unsupported_codec() {
         let codec = VideoCodec::Theora;
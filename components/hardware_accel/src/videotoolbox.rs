@@ -66,6 +66,7 @@
 //! ```
 
 use crate::error::{HardwareError, HardwareResult};
+use crate::pool::HardwareDecoder;
 use cortenbrowser_shared_types::{MediaError, VideoCodec, VideoDecoder, VideoFrame, VideoPacket};
 
 /// VideoToolbox hardware video decoder (stub)
@@ -163,6 +164,15 @@ impl VideoDecoder for VideoToolboxDecoder {
     }
 }
 
+impl HardwareDecoder for VideoToolboxDecoder {
+    /// Reset decoder state (stub)
+    ///
+    /// Always returns `Err(HardwareError::NotAvailable)` as VideoToolbox is not implemented.
+    fn reset(&mut self) -> HardwareResult<()> {
+        Err(HardwareError::NotAvailable)
+    }
+}
+
 #[cfg(test)]
 #[cfg(target_os = "macos")]
 mod tests {
@@ -111,6 +111,7 @@ fn test_decoder_lifecycle() {
                     pts: Some(0),
                     dts: Some(0),
                     is_keyframe: true,
+                    encryption: None,
                 };
 
                 // Decode should not panic (may fail due to invalid data)
@@ -148,16 +149,22 @@ fn test_linux_vaapi_availability() {
 
 #[cfg(target_os = "windows")]
 #[test]
-fn test_windows_dxva_stub() {
+fn test_windows_dxva_context() {
     /*
      * Given a Windows system
      * When creating hardware context
-     * Then should return NotAvailable (stub implementation)
+     * Then should succeed, with probed or default DXVA capabilities
      */
-    let result = HardwareContext::new();
-
-    // Windows stub should return NotAvailable
-    assert!(matches!(result, Err(HardwareError::NotAvailable)));
+    let ctx = HardwareContext::new().expect("Windows context should initialize");
+
+    let h264 = VideoCodec::H264 {
+        profile: H264Profile::High,
+        level: H264Level::Level4_1,
+        hardware_accel: true,
+    };
+    if ctx.is_codec_supported(&h264) {
+        assert!(ctx.create_decoder(&h264).is_ok());
+    }
 }
 
 #[cfg(target_os = "macos")]
@@ -1,7 +1,37 @@
 //! Unit tests for HardwareCapabilities struct
 
-use cortenbrowser_hardware_accel::HardwareCapabilities;
-use cortenbrowser_shared_types::{H264Level, H264Profile, VP9Profile, VideoCodec};
+use cortenbrowser_hardware_accel::{CodecLimits, HardwareCapabilities};
+use cortenbrowser_shared_types::{
+    AV1Level, AV1Profile, H264Level, H264Profile, VP9Profile, VideoCodec,
+};
+
+fn h264() -> VideoCodec {
+    VideoCodec::H264 {
+        profile: H264Profile::High,
+        level: H264Level::Level5_1,
+        hardware_accel: true,
+    }
+}
+
+fn vp9() -> VideoCodec {
+    VideoCodec::VP9 {
+        profile: VP9Profile::Profile0,
+    }
+}
+
+/// 8K device where H.264 is limited to 4K
+fn per_codec_caps() -> HardwareCapabilities {
+    HardwareCapabilities {
+        supported_codecs: vec![h264(), vp9()],
+        max_resolution: (7680, 4320),
+        max_framerate: 60.0,
+        codec_limits: vec![CodecLimits {
+            codec: h264(),
+            max_resolution: (3840, 2160),
+            max_framerate: 30.0,
+        }],
+    }
+}
 
 #[test]
 fn test_hardware_capabilities_creation() {
@@ -18,6 +48,7 @@ fn test_hardware_capabilities_creation() {
         ],
         max_resolution: (3840, 2160), // 4K
         max_framerate: 60.0,
+        codec_limits: vec![],
     };
 
     assert_eq!(caps.supported_codecs.len(), 2);
@@ -31,6 +62,7 @@ fn test_hardware_capabilities_empty() {
         supported_codecs: vec![],
         max_resolution: (0, 0),
         max_framerate: 0.0,
+        codec_limits: vec![],
     };
 
     assert_eq!(caps.supported_codecs.len(), 0);
@@ -43,6 +75,7 @@ fn test_hardware_capabilities_debug_format() {
         supported_codecs: vec![],
         max_resolution: (1920, 1080),
         max_framerate: 30.0,
+        codec_limits: vec![],
     };
 
     let debug_str = format!("{:?}", caps);
@@ -55,6 +88,7 @@ fn test_hardware_capabilities_clone() {
         supported_codecs: vec![VideoCodec::VP8],
         max_resolution: (1920, 1080),
         max_framerate: 30.0,
+        codec_limits: vec![],
     };
 
     let cloned = caps.clone();
@@ -62,3 +96,63 @@ fn test_hardware_capabilities_clone() {
     assert_eq!(caps.max_resolution, cloned.max_resolution);
     assert_eq!(caps.max_framerate, cloned.max_framerate);
 }
+
+#[test]
+fn test_hardware_capabilities_max_resolution_for() {
+    let caps = per_codec_caps();
+
+    assert_eq!(caps.max_resolution_for(&h264()), (3840, 2160));
+    // VP9 has no override and uses the device-wide limit
+    assert_eq!(caps.max_resolution_for(&vp9()), (7680, 4320));
+    assert_eq!(caps.max_resolution_for(&VideoCodec::VP8), (0, 0));
+}
+
+#[test]
+fn test_hardware_capabilities_max_framerate_for() {
+    let caps = per_codec_caps();
+
+    assert_eq!(caps.max_framerate_for(&h264()), 30.0);
+    assert_eq!(caps.max_framerate_for(&vp9()), 60.0);
+    assert_eq!(caps.max_framerate_for(&VideoCodec::VP8), 0.0);
+}
+
+#[test]
+fn test_hardware_capabilities_limits_ignore_profile() {
+    let caps = per_codec_caps();
+    let baseline = VideoCodec::H264 {
+        profile: H264Profile::Baseline,
+        level: H264Level::Level3_0,
+        hardware_accel: false,
+    };
+
+    assert_eq!(caps.max_resolution_for(&baseline), (3840, 2160));
+}
+
+#[test]
+fn test_hardware_capabilities_supports_rejects_above_codec_cap() {
+    let caps = per_codec_caps();
+
+    // 8K is listed device-wide, but H.264 is capped at 4K
+    assert!(caps.supports(&vp9(), 7680, 4320, 30.0));
+    assert!(!caps.supports(&h264(), 7680, 4320, 30.0));
+    assert!(caps.supports(&h264(), 3840, 2160, 30.0));
+}
+
+#[test]
+fn test_hardware_capabilities_supports_rejects_above_framerate_cap() {
+    let caps = per_codec_caps();
+
+    assert!(!caps.supports(&h264(), 1920, 1080, 60.0));
+    assert!(caps.supports(&vp9(), 1920, 1080, 60.0));
+}
+
+#[test]
+fn test_hardware_capabilities_supports_unsupported_codec() {
+    let caps = per_codec_caps();
+    let av1 = VideoCodec::AV1 {
+        profile: AV1Profile::Main,
+        level: AV1Level::Level4_0,
+    };
+
+    assert!(!caps.supports(&av1, 640, 480, 30.0));
+}
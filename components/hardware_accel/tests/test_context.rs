@@ -1,7 +1,9 @@
 //! Unit tests for HardwareContext
 
-use cortenbrowser_hardware_accel::{HardwareContext, HardwareError};
-use cortenbrowser_shared_types::{H264Level, H264Profile, VideoCodec};
+use cortenbrowser_hardware_accel::{DecoderPoolConfig, HardwareContext, HardwareError};
+use cortenbrowser_shared_types::{
+    AV1Level, AV1Profile, H264Level, H264Profile, MediaError, VP9Profile, VideoCodec,
+};
 
 #[test]
 fn test_hardware_context_new() {
@@ -102,3 +104,182 @@ fn test_hardware_context_platform_detection() {
         Err(e) => panic!("Unexpected error: {:?}", e),
     }
 }
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_fallback_uses_hardware_when_supported() {
+    let ctx = HardwareContext::new().expect("VA-API mock should be available");
+
+    let h264 = VideoCodec::H264 {
+        profile: H264Profile::High,
+        level: H264Level::Level4_1,
+        hardware_accel: true,
+    };
+
+    let decoder = ctx
+        .create_decoder_or_fallback(&h264)
+        .expect("H.264 decoder should be created");
+
+    assert!(decoder.is_hardware());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_fallback_uses_software_when_unsupported() {
+    let ctx = HardwareContext::new().expect("VA-API mock should be available");
+    if ctx.driver_name().is_some() {
+        // A real driver may decode AV1 in hardware
+        return;
+    }
+
+    // AV1 is not in the fallback hardware codec list
+    let av1 = VideoCodec::AV1 {
+        profile: AV1Profile::Main,
+        level: AV1Level::Level4_0,
+    };
+    assert!(!ctx.is_codec_supported(&av1));
+
+    let decoder = ctx
+        .create_decoder_or_fallback(&av1)
+        .expect("Software AV1 decoder should be created");
+
+    assert!(!decoder.is_hardware());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_fallback_reports_unsupported_everywhere() {
+    let ctx = HardwareContext::new().expect("VA-API mock should be available");
+
+    // Theora has neither a hardware nor a software decoder
+    let result = ctx.create_decoder_or_fallback(&VideoCodec::Theora);
+
+    assert!(matches!(result, Err(MediaError::UnsupportedFormat { .. })));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_per_codec_limits() {
+    let ctx = HardwareContext::new().expect("Linux mock context should initialize");
+    if ctx.driver_name().is_some() {
+        // Limits below are those of the fallback capability set
+        return;
+    }
+    let caps = ctx.get_capabilities();
+    let h264 = VideoCodec::H264 {
+        profile: H264Profile::High,
+        level: H264Level::Level5_1,
+        hardware_accel: true,
+    };
+    let vp9 = VideoCodec::VP9 {
+        profile: VP9Profile::Profile0,
+    };
+
+    // 8K is listed device-wide
+    assert!(caps.max_resolution.0 >= 7680 && caps.max_resolution.1 >= 4320);
+
+    // VP9 decodes 8K, H.264 does not
+    assert!(ctx.supports(&vp9, 7680, 4320, 30.0));
+    assert!(!ctx.supports(&h264, 7680, 4320, 30.0));
+    assert!(ctx.supports(&h264, 3840, 2160, 30.0));
+    assert_ne!(
+        caps.max_resolution_for(&h264),
+        caps.max_resolution_for(&vp9)
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_driver_name_matches_capabilities() {
+    let ctx = HardwareContext::new().expect("Linux context should initialize");
+    let caps = ctx.get_capabilities();
+
+    match ctx.driver_name() {
+        Some(name) => {
+            // Probed: every reported codec has matching per-codec limits
+            assert!(!name.is_empty());
+            for codec in &caps.supported_codecs {
+                assert!(caps.max_resolution_for(codec).0 > 0);
+            }
+        }
+        None => {
+            // Probe failed: the conservative H.264 + VP9 set is reported
+            assert_eq!(caps.supported_codecs.len(), 2);
+            assert!(!ctx.is_codec_supported(&VideoCodec::VP8));
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_acquire_decoder_reuses_instance() {
+    let ctx = HardwareContext::new().expect("Linux context should initialize");
+    let codec = ctx.get_capabilities().supported_codecs[0].clone();
+
+    let first = ctx.acquire_decoder(&codec).unwrap();
+    let id = first.id();
+    assert_eq!(first.codec(), &codec);
+    assert_eq!(ctx.decoder_pool().idle_count(), 0);
+
+    drop(first);
+    assert_eq!(ctx.decoder_pool().idle_count_for(&codec), 1);
+
+    // Re-acquiring the same codec hands out the pooled instance
+    let second = ctx.acquire_decoder(&codec).unwrap();
+    assert_eq!(second.id(), id);
+    assert_eq!(ctx.decoder_pool().idle_count(), 0);
+
+    // A concurrent acquire needs a new instance
+    let third = ctx.acquire_decoder(&codec).unwrap();
+    assert_ne!(third.id(), id);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_acquire_decoder_keys_by_codec() {
+    let ctx = HardwareContext::new().expect("Linux context should initialize");
+    let codecs = &ctx.get_capabilities().supported_codecs;
+    if codecs.len() < 2 {
+        return;
+    }
+
+    let first = ctx.acquire_decoder(&codecs[0]).unwrap();
+    let id = first.id();
+    drop(first);
+
+    // A different codec does not reuse the pooled decoder
+    let other = ctx.acquire_decoder(&codecs[1]).unwrap();
+    assert_ne!(other.id(), id);
+    assert_eq!(ctx.decoder_pool().idle_count_for(&codecs[0]), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_decoder_pool_cap() {
+    let ctx = HardwareContext::new()
+        .expect("Linux context should initialize")
+        .with_decoder_pool_config(DecoderPoolConfig { max_idle: 2 });
+    let codec = ctx.get_capabilities().supported_codecs[0].clone();
+
+    let decoders: Vec<_> = (0..4)
+        .map(|_| ctx.acquire_decoder(&codec).unwrap())
+        .collect();
+    let ids: Vec<u64> = decoders.iter().map(|d| d.id()).collect();
+    drop(decoders);
+
+    // Only two decoders are kept; the others are destroyed
+    assert_eq!(ctx.decoder_pool().idle_count(), 2);
+    let reused = ctx.acquire_decoder(&codec).unwrap();
+    assert!(ids[..2].contains(&reused.id()));
+
+    ctx.decoder_pool().clear();
+    assert_eq!(ctx.decoder_pool().idle_count(), 0);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_hardware_context_acquire_decoder_unsupported_codec() {
+    let ctx = HardwareContext::new().expect("Linux context should initialize");
+    let result = ctx.acquire_decoder(&VideoCodec::Theora);
+    assert_eq!(result.unwrap_err(), HardwareError::UnsupportedCodec);
+}
@@ -0,0 +1,94 @@
+//! Unit tests for DXVADecoder (Windows)
+
+#![cfg(target_os = "windows")]
+
+use cortenbrowser_hardware_accel::{DXVADecoder, HardwareContext, HardwareError};
+use cortenbrowser_shared_types::{
+    H264Level, H264Profile, PixelFormat, VP9Profile, VideoCodec, VideoDecoder, VideoPacket,
+};
+
+#[test]
+fn test_dxva_decoder_new_with_h264() {
+    let codec = VideoCodec::H264 {
+        profile: H264Profile::High,
+        level: H264Level::Level4_1,
+        hardware_accel: true,
+    };
+
+    let result = DXVADecoder::new(&codec);
+
+    // May fail if Direct3D 11 video is not available in test environment
+    match result {
+        Ok(_decoder) => {
+            // DXVA available
+        }
+        Err(HardwareError::NotAvailable) => {
+            // Expected in environments without a D3D11 video driver
+        }
+        Err(HardwareError::InitializationFailed) => {
+            // Also acceptable
+        }
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn test_dxva_decoder_new_with_unsupported_codec() {
+    let codec = VideoCodec::Theora;
+
+    let result = DXVADecoder::new(&codec);
+
+    // Should fail with unsupported codec
+    match result {
+        Err(HardwareError::UnsupportedCodec) => {
+            // Expected
+        }
+        Err(HardwareError::NotAvailable) => {
+            // Also acceptable if DXVA not available
+        }
+        Ok(_) => panic!("Theora should not be supported by DXVA"),
+        Err(e) => panic!("Unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn test_dxva_decoder_decodes_mock_frames() {
+    let codec = VideoCodec::VP9 {
+        profile: VP9Profile::Profile0,
+    };
+    let mut decoder = DXVADecoder::new(&codec).unwrap();
+    decoder.configure(640, 480).unwrap();
+
+    let packet = VideoPacket {
+        data: vec![0u8; 100],
+        pts: Some(0),
+        dts: Some(0),
+        is_keyframe: true,
+        encryption: None,
+    };
+    let frame = decoder.decode(&packet).unwrap();
+
+    assert_eq!((frame.width, frame.height), (640, 480));
+    assert_eq!(frame.format, PixelFormat::YUV420);
+    assert_eq!(frame.data.len(), 640 * 480 * 3 / 2);
+    assert!(decoder.flush().unwrap().is_empty());
+}
+
+#[test]
+fn test_dxva_probe_reports_driver_codecs() {
+    let ctx = HardwareContext::new().expect("Windows context should initialize");
+
+    // Only meaningful with a D3D11 video driver installed
+    let Some(driver) = ctx.driver_name() else {
+        return;
+    };
+    println!("DXVA adapter: {}", driver);
+
+    // Every probed codec can be opened as a hardware decoder
+    for codec in &ctx.get_capabilities().supported_codecs {
+        assert!(ctx.create_decoder(codec).is_ok(), "{:?} should open", codec);
+    }
+
+    // Theora has no DXVA profile and is never reported
+    assert!(!ctx.is_codec_supported(&VideoCodec::Theora));
+}
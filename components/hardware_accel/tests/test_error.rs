@@ -1,6 +1,7 @@
 //! Unit tests for HardwareError enum
 
 use cortenbrowser_hardware_accel::HardwareError;
+use cortenbrowser_shared_types::MediaError;
 
 #[test]
 fn test_hardware_error_not_available_display() {
@@ -45,3 +46,14 @@ fn test_hardware_error_clone() {
     let cloned = error.clone();
     assert_eq!(error, cloned);
 }
+
+#[test]
+fn test_hardware_error_converts_to_media_error() {
+    let error: MediaError = HardwareError::InitializationFailed.into();
+    assert_eq!(
+        error,
+        MediaError::HardwareError {
+            details: "Hardware decoder initialization failed".to_string(),
+        }
+    );
+}
@@ -2,8 +2,10 @@
 
 #![cfg(target_os = "linux")]
 
-use cortenbrowser_hardware_accel::{HardwareError, VAAPIDecoder};
-use cortenbrowser_shared_types::{H264Level, H264Profile, VideoCodec, VideoDecoder, VideoPacket};
+use cortenbrowser_hardware_accel::{HardwareContext, HardwareError, VAAPIDecoder};
+use cortenbrowser_shared_types::{
+    H264Level, H264Profile, PixelFormat, VP9Profile, VideoCodec, VideoDecoder, VideoPacket,
+};
 
 #[test]
 fn test_vaapi_decoder_new_with_h264() {
@@ -66,6 +68,7 @@ fn test_vaapi_decoder_implements_video_decoder_trait() {
             pts: Some(0),
             dts: Some(0),
             is_keyframe: true,
+            encryption: None,
         };
 
         // decode should compile (trait method exists)
@@ -75,3 +78,92 @@ fn test_vaapi_decoder_implements_video_decoder_trait() {
         let _ = decoder.flush();
     }
 }
+
+#[test]
+fn test_vaapi_probe_reports_driver_codecs() {
+    let ctx = HardwareContext::new().expect("Linux context should initialize");
+
+    // Only meaningful with a VA-API driver installed
+    let Some(driver) = ctx.driver_name() else {
+        return;
+    };
+    println!("VA-API driver: {}", driver);
+
+    // Every probed codec can be opened as a hardware decoder
+    for codec in &ctx.get_capabilities().supported_codecs {
+        assert!(ctx.create_decoder(codec).is_ok(), "{:?} should open", codec);
+    }
+
+    // Theora has no VA-API profile and is never reported
+    assert!(!ctx.is_codec_supported(&VideoCodec::Theora));
+}
+
+#[test]
+fn test_vaapi_decoder_configured_dimensions() {
+    let codec = VideoCodec::VP9 {
+        profile: VP9Profile::Profile0,
+    };
+    let mut decoder = VAAPIDecoder::new(&codec).unwrap();
+    decoder.configure(640, 480).unwrap();
+
+    let packet = VideoPacket {
+        data: vec![0u8; 100],
+        pts: Some(0),
+        dts: Some(0),
+        is_keyframe: true,
+        encryption: None,
+    };
+    let frame = decoder.decode(&packet).unwrap();
+
+    assert_eq!((frame.width, frame.height), (640, 480));
+    assert_eq!(frame.format, PixelFormat::YUV420);
+    assert_eq!(frame.data.len(), 640 * 480 * 3 / 2);
+}
+
+#[test]
+fn test_vaapi_decoder_rejects_empty_dimensions() {
+    let mut decoder = VAAPIDecoder::new(&VideoCodec::VP8).unwrap();
+
+    assert_eq!(
+        decoder.configure(0, 480),
+        Err(HardwareError::InitializationFailed)
+    );
+    assert_eq!(decoder.dimensions(), (1920, 1080));
+}
+
+#[test]
+fn test_vaapi_decoder_reads_h264_sps() {
+    let codec = VideoCodec::H264 {
+        profile: H264Profile::Baseline,
+        level: H264Level::Level3_0,
+        hardware_accel: true,
+    };
+    let mut decoder = VAAPIDecoder::new(&codec).unwrap();
+
+    // SPS for 640x480 Baseline, followed by an IDR slice
+    let mut data = vec![
+        0, 0, 0, 1, 0x67, 0x42, 0xC0, 0x1E, 0xF4, 0x05, 0x01, 0xEC, 0x80,
+    ];
+    data.extend_from_slice(&[0, 0, 0, 1, 0x65, 0x88, 0x84]);
+    let keyframe = VideoPacket {
+        data,
+        pts: Some(0),
+        dts: Some(0),
+        is_keyframe: true,
+        encryption: None,
+    };
+    let frame = decoder.decode(&keyframe).unwrap();
+    assert_eq!((frame.width, frame.height), (640, 480));
+    assert_eq!(frame.data.len(), 640 * 480 * 3 / 2);
+
+    // Later frames keep the size
+    let delta = VideoPacket {
+        data: vec![0, 0, 0, 1, 0x41, 0x9A],
+        pts: Some(1),
+        dts: Some(1),
+        is_keyframe: false,
+        encryption: None,
+    };
+    let frame = decoder.decode(&delta).unwrap();
+    assert_eq!((frame.width, frame.height), (640, 480));
+}
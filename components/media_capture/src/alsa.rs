@@ -0,0 +1,363 @@
+//! ALSA capture device discovery for Linux
+//!
+//! Capture PCM devices appear as `/dev/snd/pcmC<card>D<device>c` nodes,
+//! and the kernel describes each one in
+//! `/proc/asound/card<card>/pcm<device>c/info`. Devices are identified by
+//! their node path, which stays the same for as long as the card is
+//! plugged in.
+//!
+//! Audio is captured through the `hw:<card>,<device>` PCM of `libasound`,
+//! which is loaded at runtime like the other platform libraries. The
+//! hardware PCM does no conversion, so audio arrives at the device's own
+//! sample rate and channel count, nearest to the ones asked for.
+
+use crate::microphone_capture::AudioSource;
+use crate::v4l2::capture_error;
+use crate::{CaptureError, DeviceInfo, DeviceKind};
+use cortenbrowser_shared_types::Library;
+use libc::{c_char, c_int, c_long, c_uint, c_ulong, c_void};
+use std::ffi::{CStr, CString};
+use std::io;
+use std::path::Path;
+use std::ptr;
+
+/// Soname of the ALSA library
+const LIBASOUND: &CStr = c"libasound.so.2";
+
+/// Directory holding the ALSA device nodes
+const SOUND_DIR: &str = "/dev/snd";
+
+/// `SND_PCM_STREAM_CAPTURE`
+const SND_PCM_STREAM_CAPTURE: c_int = 1;
+
+/// `SND_PCM_ACCESS_RW_INTERLEAVED`
+const SND_PCM_ACCESS_RW_INTERLEAVED: c_int = 3;
+
+/// `SND_PCM_FORMAT_S16_LE`
+const SND_PCM_FORMAT_S16_LE: c_int = 2;
+
+/// Periods read per second, 10 ms each
+const PERIODS_PER_SECOND: u32 = 100;
+
+/// List the ALSA capture devices, ordered by card and device number
+pub(crate) fn enumerate_devices() -> Vec<DeviceInfo> {
+    let Ok(entries) = std::fs::read_dir(SOUND_DIR) else {
+        return Vec::new();
+    };
+
+    let mut nodes: Vec<(u32, u32, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let (card, device) = parse_capture_node(&entry.file_name().to_string_lossy())?;
+            Some((card, device, entry.path().to_string_lossy().into_owned()))
+        })
+        .collect();
+    nodes.sort();
+
+    nodes
+        .into_iter()
+        .map(|(card, device, path)| device_info(card, device, path))
+        .collect()
+}
+
+/// Describe capture device `device` of card `card`, whose node is `path`
+///
+/// The PCM name is used as label, or the node path if the kernel does not
+/// name it.
+fn device_info(card: u32, device: u32, path: String) -> DeviceInfo {
+    let info = std::fs::read_to_string(format!("/proc/asound/card{card}/pcm{device}c/info"))
+        .unwrap_or_default();
+    DeviceInfo {
+        label: info_name(&info).unwrap_or_else(|| path.clone()),
+        device_id: path,
+        kind: DeviceKind::AudioInput,
+    }
+}
+
+/// Card and device number of a `pcmC<card>D<device>c` node name
+fn parse_capture_node(name: &str) -> Option<(u32, u32)> {
+    let numbers = name.strip_prefix("pcmC")?.strip_suffix('c')?;
+    let (card, device) = numbers.split_once('D')?;
+    Some((card.parse().ok()?, device.parse().ok()?))
+}
+
+/// `name` field of a PCM info file, if present and not empty
+fn info_name(info: &str) -> Option<String> {
+    info.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == "name")
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+#[repr(C)]
+struct SndPcm {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct SndPcmHwParams {
+    _private: [u8; 0],
+}
+
+type PcmOpenFn = unsafe extern "C" fn(*mut *mut SndPcm, *const c_char, c_int, c_int) -> c_int;
+type PcmCloseFn = unsafe extern "C" fn(*mut SndPcm) -> c_int;
+type PcmPrepareFn = unsafe extern "C" fn(*mut SndPcm) -> c_int;
+type PcmReadiFn = unsafe extern "C" fn(*mut SndPcm, *mut c_void, c_ulong) -> c_long;
+type PcmRecoverFn = unsafe extern "C" fn(*mut SndPcm, c_int, c_int) -> c_int;
+type HwParamsMallocFn = unsafe extern "C" fn(*mut *mut SndPcmHwParams) -> c_int;
+type HwParamsFreeFn = unsafe extern "C" fn(*mut SndPcmHwParams);
+type HwParamsAnyFn = unsafe extern "C" fn(*mut SndPcm, *mut SndPcmHwParams) -> c_int;
+type HwParamsSetFn = unsafe extern "C" fn(*mut SndPcm, *mut SndPcmHwParams, c_int) -> c_int;
+type HwParamsSetNearFn =
+    unsafe extern "C" fn(*mut SndPcm, *mut SndPcmHwParams, *mut c_uint) -> c_int;
+type HwParamsSetRateNearFn =
+    unsafe extern "C" fn(*mut SndPcm, *mut SndPcmHwParams, *mut c_uint, *mut c_int) -> c_int;
+type HwParamsSetPeriodNearFn =
+    unsafe extern "C" fn(*mut SndPcm, *mut SndPcmHwParams, *mut c_ulong, *mut c_int) -> c_int;
+type HwParamsApplyFn = unsafe extern "C" fn(*mut SndPcm, *mut SndPcmHwParams) -> c_int;
+
+/// libasound entry points used for capture
+struct AlsaApi {
+    pcm_open: PcmOpenFn,
+    pcm_close: PcmCloseFn,
+    pcm_prepare: PcmPrepareFn,
+    pcm_readi: PcmReadiFn,
+    pcm_recover: PcmRecoverFn,
+    hw_params_malloc: HwParamsMallocFn,
+    hw_params_free: HwParamsFreeFn,
+    hw_params_any: HwParamsAnyFn,
+    hw_params_set_access: HwParamsSetFn,
+    hw_params_set_format: HwParamsSetFn,
+    hw_params_set_channels_near: HwParamsSetNearFn,
+    hw_params_set_rate_near: HwParamsSetRateNearFn,
+    hw_params_set_period_size_near: HwParamsSetPeriodNearFn,
+    hw_params: HwParamsApplyFn,
+}
+
+impl AlsaApi {
+    fn load(asound: &Library) -> Result<Self, CaptureError> {
+        // SAFETY: the function types match the libasound C declarations,
+        // with enum arguments passed as C ints
+        unsafe {
+            Ok(Self {
+                pcm_open: asound.symbol(c"snd_pcm_open")?,
+                pcm_close: asound.symbol(c"snd_pcm_close")?,
+                pcm_prepare: asound.symbol(c"snd_pcm_prepare")?,
+                pcm_readi: asound.symbol(c"snd_pcm_readi")?,
+                pcm_recover: asound.symbol(c"snd_pcm_recover")?,
+                hw_params_malloc: asound.symbol(c"snd_pcm_hw_params_malloc")?,
+                hw_params_free: asound.symbol(c"snd_pcm_hw_params_free")?,
+                hw_params_any: asound.symbol(c"snd_pcm_hw_params_any")?,
+                hw_params_set_access: asound.symbol(c"snd_pcm_hw_params_set_access")?,
+                hw_params_set_format: asound.symbol(c"snd_pcm_hw_params_set_format")?,
+                hw_params_set_channels_near: asound
+                    .symbol(c"snd_pcm_hw_params_set_channels_near")?,
+                hw_params_set_rate_near: asound.symbol(c"snd_pcm_hw_params_set_rate_near")?,
+                hw_params_set_period_size_near: asound
+                    .symbol(c"snd_pcm_hw_params_set_period_size_near")?,
+                hw_params: asound.symbol(c"snd_pcm_hw_params")?,
+            })
+        }
+    }
+}
+
+/// Audio capture from an ALSA capture device
+///
+/// Captures interleaved signed 16-bit samples, read in 10 ms periods and
+/// converted to `f32`.
+pub(crate) struct AlsaCapture {
+    api: AlsaApi,
+    pcm: *mut SndPcm,
+    sample_rate: u32,
+    channels: u8,
+    /// Frames per read
+    period: usize,
+    buffer: Vec<i16>,
+    // Keeps the function pointers in `api` valid
+    _asound: Library,
+}
+
+// SAFETY: the capture owns its PCM handle, which is only used through it
+unsafe impl Send for AlsaCapture {}
+
+impl AlsaCapture {
+    /// Open the capture device node `device_id` at the sample rate and
+    /// channel count nearest to `sample_rate` and `channels`
+    pub(crate) fn open(
+        device_id: &str,
+        sample_rate: u32,
+        channels: u8,
+    ) -> Result<Self, CaptureError> {
+        let (card, device) = Path::new(device_id)
+            .file_name()
+            .and_then(|name| parse_capture_node(&name.to_string_lossy()))
+            .ok_or(CaptureError::DeviceNotFound)?;
+        let name = CString::new(format!("hw:{card},{device}"))
+            .map_err(|_| CaptureError::DeviceNotFound)?;
+
+        let asound = Library::open(LIBASOUND).ok_or(CaptureError::CaptureFailure)?;
+        let api = AlsaApi::load(&asound)?;
+
+        let mut pcm = ptr::null_mut();
+        // SAFETY: `name` is a valid NUL-terminated string and `pcm` is
+        // only used if opening succeeds
+        let ret = unsafe { (api.pcm_open)(&mut pcm, name.as_ptr(), SND_PCM_STREAM_CAPTURE, 0) };
+        if ret < 0 {
+            return Err(capture_error(io::Error::from_raw_os_error(-ret)));
+        }
+
+        // Closed on drop if configuring fails
+        let mut capture = Self {
+            api,
+            pcm,
+            sample_rate: 0,
+            channels: 0,
+            period: 0,
+            buffer: Vec::new(),
+            _asound: asound,
+        };
+        capture.configure(sample_rate, channels)?;
+        Ok(capture)
+    }
+
+    fn configure(&mut self, sample_rate: u32, channels: u8) -> Result<(), CaptureError> {
+        let mut params = ptr::null_mut();
+        // SAFETY: `params` is allocated here and freed after use
+        if unsafe { (self.api.hw_params_malloc)(&mut params) } < 0 {
+            return Err(CaptureError::CaptureFailure);
+        }
+        let result = self.set_hw_params(params, sample_rate, channels);
+        unsafe { (self.api.hw_params_free)(params) };
+        result
+    }
+
+    fn set_hw_params(
+        &mut self,
+        params: *mut SndPcmHwParams,
+        sample_rate: u32,
+        channels: u8,
+    ) -> Result<(), CaptureError> {
+        let check = |ret: c_int| {
+            if ret < 0 {
+                Err(CaptureError::CaptureFailure)
+            } else {
+                Ok(())
+            }
+        };
+
+        let api = &self.api;
+        let pcm = self.pcm;
+        let mut channels = c_uint::from(channels);
+        let mut rate = sample_rate as c_uint;
+        let mut period = c_ulong::from(sample_rate / PERIODS_PER_SECOND);
+        let mut dir = 0;
+        // SAFETY: `pcm` is open and `params` allocated; the near functions
+        // write back the values the device settled on
+        unsafe {
+            check((api.hw_params_any)(pcm, params))?;
+            check((api.hw_params_set_access)(
+                pcm,
+                params,
+                SND_PCM_ACCESS_RW_INTERLEAVED,
+            ))?;
+            check((api.hw_params_set_format)(
+                pcm,
+                params,
+                SND_PCM_FORMAT_S16_LE,
+            ))?;
+            check((api.hw_params_set_channels_near)(
+                pcm,
+                params,
+                &mut channels,
+            ))?;
+            check((api.hw_params_set_rate_near)(
+                pcm, params, &mut rate, &mut dir,
+            ))?;
+            dir = 0;
+            check((api.hw_params_set_period_size_near)(
+                pcm,
+                params,
+                &mut period,
+                &mut dir,
+            ))?;
+            check((api.hw_params)(pcm, params))?;
+            check((api.pcm_prepare)(pcm))?;
+        }
+
+        if rate == 0 || period == 0 || channels == 0 || channels > c_uint::from(u8::MAX) {
+            return Err(CaptureError::CaptureFailure);
+        }
+        self.sample_rate = rate;
+        self.channels = channels as u8;
+        self.period = period as usize;
+        Ok(())
+    }
+}
+
+impl AudioSource for AlsaCapture {
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn channels(&self) -> u8 {
+        self.channels
+    }
+
+    fn read(&mut self) -> Result<Vec<f32>, CaptureError> {
+        let channels = self.channels as usize;
+        self.buffer.resize(self.period * channels, 0);
+        loop {
+            // SAFETY: the buffer holds `period` frames of `channels` samples
+            let ret = unsafe {
+                (self.api.pcm_readi)(
+                    self.pcm,
+                    self.buffer.as_mut_ptr().cast(),
+                    self.period as c_ulong,
+                )
+            };
+            if ret > 0 {
+                let samples = &self.buffer[..ret as usize * channels];
+                return Ok(samples.iter().map(|&s| f32::from(s) / 32768.0).collect());
+            }
+            // Overruns and suspends are recovered from by restarting the
+            // stream, losing the audio in between
+            if ret < 0 && unsafe { (self.api.pcm_recover)(self.pcm, ret as c_int, 1) } < 0 {
+                return Err(CaptureError::CaptureFailure);
+            }
+        }
+    }
+}
+
+impl Drop for AlsaCapture {
+    fn drop(&mut self) {
+        // SAFETY: the PCM was opened by `open` and is closed only here
+        unsafe {
+            (self.api.pcm_close)(self.pcm);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_capture_node() {
+        assert_eq!(parse_capture_node("pcmC0D0c"), Some((0, 0)));
+        assert_eq!(parse_capture_node("pcmC12D3c"), Some((12, 3)));
+        // Playback devices, controls and malformed names are not capture devices
+        assert_eq!(parse_capture_node("pcmC0D0p"), None);
+        assert_eq!(parse_capture_node("controlC0"), None);
+        assert_eq!(parse_capture_node("pcmCxD0c"), None);
+    }
+
+    #[test]
+    fn test_info_name() {
+        let info = "card: 1\ndevice: 0\nsubdevice: 0\nstream: CAPTURE\n\
+                    id: USB Audio\nname: USB Audio\nsubname: subdevice #0\n";
+        assert_eq!(info_name(info), Some("USB Audio".to_string()));
+        assert_eq!(info_name("card: 0\nname: \n"), None);
+        assert_eq!(info_name(""), None);
+    }
+}
@@ -2,27 +2,35 @@
 //!
 //! Provides camera/webcam capture capabilities with platform-specific implementations.
 
-use crate::{CaptureConstraints, CaptureError};
-use cortenbrowser_shared_types::VideoFrame;
+use crate::device_enumerator::PlatformBackend;
+use crate::{CaptureConstraints, CaptureError, CaptureMode, CaptureSettings, DeviceBackend};
+use cortenbrowser_shared_types::{MediaStreamTrack, VideoFrame};
 use tokio::sync::mpsc;
 
+#[cfg(target_os = "linux")]
+use crate::v4l2::V4L2CameraCapture;
+#[cfg(target_os = "linux")]
+use std::sync::Mutex;
+
 /// Camera capture interface
 ///
 /// Captures video frames from a camera or webcam.
-/// Platform-specific implementation required for actual capture.
+/// On Linux, `device_id` is a V4L2 device path such as `/dev/video0` and
+/// frames are delivered as YUV 4:2:0. Other platforms are not yet supported.
 ///
 /// # Examples
 ///
 /// ```no_run
-/// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints};
+/// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints, ConstrainRange};
 ///
 /// #[tokio::main]
 /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-///     let device_id = "camera-001".to_string();
+///     let device_id = "/dev/video0".to_string();
 ///     let constraints = CaptureConstraints {
-///         width: Some(1920),
-///         height: Some(1080),
-///         frame_rate: Some(30.0),
+///         width: ConstrainRange::ideal(1920),
+///         height: ConstrainRange::ideal(1080),
+///         frame_rate: ConstrainRange::ideal(30.0),
+///         ..Default::default()
 ///     };
 ///
 ///     let capture = CameraCapture::new(device_id, constraints)?;
@@ -39,11 +47,16 @@ use tokio::sync::mpsc;
 /// ```
 #[derive(Debug)]
 pub struct CameraCapture {
-    #[allow(dead_code)] // Will be used in platform-specific implementation
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
     device_id: String,
-    #[allow(dead_code)] // Will be used in platform-specific implementation
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
     constraints: CaptureConstraints,
-    // Platform-specific fields will be added
+    /// Modes the device offers, listed by `open`
+    capabilities: Vec<CaptureMode>,
+    /// Mode negotiated by `open`
+    active_format: Option<CaptureMode>,
+    #[cfg(target_os = "linux")]
+    session: Mutex<Option<V4L2CameraCapture>>,
 }
 
 impl CameraCapture {
@@ -57,13 +70,14 @@ impl CameraCapture {
     /// # Examples
     ///
     /// ```
-    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints};
+    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints, ConstrainRange};
     ///
     /// let device_id = "camera-001".to_string();
     /// let constraints = CaptureConstraints {
-    ///     width: Some(1920),
-    ///     height: Some(1080),
-    ///     frame_rate: Some(30.0),
+    ///     width: ConstrainRange::ideal(1920),
+    ///     height: ConstrainRange::ideal(1080),
+    ///     frame_rate: ConstrainRange::ideal(30.0),
+    ///     ..Default::default()
     /// };
     ///
     /// let capture = CameraCapture::new(device_id, constraints).unwrap();
@@ -72,26 +86,137 @@ impl CameraCapture {
         Ok(Self {
             device_id,
             constraints,
+            capabilities: Vec::new(),
+            active_format: None,
+            #[cfg(target_os = "linux")]
+            session: Mutex::new(None),
         })
     }
 
+    /// Opens a camera and negotiates the mode closest to the constraints
+    ///
+    /// The device's resolutions and frame rates are listed and matched
+    /// against the constraints with [`CaptureConstraints::select_mode`].
+    /// When no mode meets the ideal values exactly, the closest one is
+    /// selected and reported by [`active_format`](Self::active_format).
+    ///
+    /// # Arguments
+    ///
+    /// * `device_id` - Device identifier from DeviceEnumerator
+    /// * `constraints` - Capture constraints (resolution, frame rate)
+    ///
+    /// # Errors
+    ///
+    /// On Linux, returns `DeviceNotFound` if `device_id` is not a video
+    /// capture device, `PermissionDenied` if it cannot be opened,
+    /// `CaptureFailure` if no YUYV format or streaming I/O is available, and
+    /// `Overconstrained` if none of its modes meets an exact, min or max
+    /// constraint. Other platforms have no cameras yet and return
+    /// `DeviceNotFound`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints, ConstrainRange};
+    ///
+    /// let constraints = CaptureConstraints {
+    ///     width: ConstrainRange::ideal(1280),
+    ///     height: ConstrainRange::ideal(720),
+    ///     frame_rate: ConstrainRange::ideal(30.0),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let capture = CameraCapture::open("/dev/video0".to_string(), constraints).unwrap();
+    /// if let Some(mode) = capture.active_format() {
+    ///     println!("Negotiated {}x{}", mode.width, mode.height);
+    /// }
+    /// ```
+    pub fn open(device_id: String, constraints: CaptureConstraints) -> Result<Self, CaptureError> {
+        Self::open_with_backend(&PlatformBackend, device_id, constraints)
+    }
+
+    /// Opens a camera of `backend` and negotiates the mode closest to the
+    /// constraints
+    ///
+    /// Like [`open`](Self::open), with the device's modes listed by
+    /// [`DeviceBackend::capture_modes`]. Frames are still captured from the
+    /// platform device once started.
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Source of the device's modes
+    /// * `device_id` - Device identifier from the backend
+    /// * `constraints` - Capture constraints (resolution, frame rate)
+    ///
+    /// # Errors
+    ///
+    /// Returns the backend's error if the modes cannot be listed, and
+    /// `Overconstrained` if none of them meets an exact, min or max
+    /// constraint.
+    pub fn open_with_backend(
+        backend: &dyn DeviceBackend,
+        device_id: String,
+        constraints: CaptureConstraints,
+    ) -> Result<Self, CaptureError> {
+        let capabilities = backend.capture_modes(&device_id)?;
+        let active_format = constraints.select_mode(&capabilities)?;
+        Ok(Self {
+            capabilities,
+            active_format: Some(active_format),
+            ..Self::new(device_id, constraints)?
+        })
+    }
+
+    /// Gets the modes the device offers
+    ///
+    /// # Returns
+    ///
+    /// The resolutions and frame rates listed by `open`, or none for a
+    /// capture created with `new`
+    pub fn capabilities(&self) -> &[CaptureMode] {
+        &self.capabilities
+    }
+
+    /// Gets the mode negotiated with the device
+    ///
+    /// # Returns
+    ///
+    /// The mode closest to the constraints, selected by `open`, or `None`
+    /// for a capture created with `new`, which negotiates on `start`
+    pub fn active_format(&self) -> Option<CaptureMode> {
+        self.active_format
+    }
+
     /// Starts camera capture
     ///
     /// Returns a receiver channel that will receive video frames.
-    /// Platform-specific implementation required.
+    /// Starting again replaces the previous capture session.
+    ///
+    /// The device's resolutions and frame rates are matched against the
+    /// constraints with [`CaptureConstraints::select_mode`], and capture
+    /// starts in the mode closest to them.
+    ///
+    /// # Errors
+    ///
+    /// On Linux, returns `DeviceNotFound` if `device_id` is not a video
+    /// capture device, `PermissionDenied` if it cannot be opened,
+    /// `Overconstrained` if none of its modes meets an exact, min or max
+    /// constraint, and `CaptureFailure` if no YUYV format or streaming I/O
+    /// is available.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints};
+    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints, ConstrainRange};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let device_id = "camera-001".to_string();
+    ///     let device_id = "/dev/video0".to_string();
     ///     let constraints = CaptureConstraints {
-    ///         width: Some(1280),
-    ///         height: Some(720),
-    ///         frame_rate: Some(15.0),
+    ///         width: ConstrainRange::ideal(1280),
+    ///         height: ConstrainRange::ideal(720),
+    ///         frame_rate: ConstrainRange::ideal(15.0),
+    ///         ..Default::default()
     ///     };
     ///
     ///     let capture = CameraCapture::new(device_id, constraints)?;
@@ -101,10 +226,108 @@ impl CameraCapture {
     /// }
     /// ```
     pub async fn start(&self) -> Result<mpsc::Receiver<VideoFrame>, CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, create a channel and return the receiver (mock implementation)
-        let (_, rx) = mpsc::channel(32);
-        Ok(rx)
+        #[cfg(target_os = "linux")]
+        {
+            let mut session = self
+                .session
+                .lock()
+                .map_err(|_| CaptureError::CaptureFailure)?;
+            // Release the device before reopening it
+            if let Some(mut previous) = session.take() {
+                previous.stop();
+            }
+
+            let (tx, rx) = mpsc::channel(32);
+            *session = Some(V4L2CameraCapture::start(
+                &self.device_id,
+                &self.constraints,
+                tx,
+            )?);
+            Ok(rx)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            // Platform-specific implementation will be added
+            // For now, create a channel and return the receiver (mock implementation)
+            let (_, rx) = mpsc::channel(32);
+            Ok(rx)
+        }
+    }
+
+    /// Starts camera capture into a video track
+    ///
+    /// The track is labelled with the device identifier. Capture stops
+    /// once the track and all its clones have been stopped, and the track
+    /// ends if capture stops first. Must be called within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// As for [`start`](Self::start).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints};
+    /// use cortenbrowser_shared_types::MediaStream;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let capture = CameraCapture::new("/dev/video0".to_string(), CaptureConstraints::default())?;
+    ///     let track = capture.start_track().await?;
+    ///     let stream = MediaStream::with_tracks(vec![track]);
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn start_track(&self) -> Result<MediaStreamTrack, CaptureError> {
+        let frames = self.start().await?;
+        Ok(crate::tracks::video_track(self.device_id.clone(), frames))
+    }
+
+    /// Gets the settings the running capture negotiated with the device
+    ///
+    /// # Returns
+    ///
+    /// The resolution and frame rate frames are captured at, or `None` if
+    /// capture is not running
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints, ConstrainRange};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let constraints = CaptureConstraints {
+    ///         width: ConstrainRange::ideal(1920),
+    ///         height: ConstrainRange::ideal(1080),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     let capture = CameraCapture::new("/dev/video0".to_string(), constraints)?;
+    ///     let _receiver = capture.start().await?;
+    ///     if let Some(settings) = capture.get_settings() {
+    ///         println!("Capturing {}x{}", settings.width, settings.height);
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn get_settings(&self) -> Option<CaptureSettings> {
+        #[cfg(target_os = "linux")]
+        {
+            self.session
+                .lock()
+                .ok()?
+                .as_ref()
+                .map(V4L2CameraCapture::settings)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
     }
 
     /// Stops camera capture
@@ -112,21 +335,32 @@ impl CameraCapture {
     /// # Examples
     ///
     /// ```
-    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints};
+    /// use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints, ConstrainRange};
     ///
     /// let device_id = "camera-001".to_string();
     /// let constraints = CaptureConstraints {
-    ///     width: Some(1920),
-    ///     height: Some(1080),
-    ///     frame_rate: Some(30.0),
+    ///     width: ConstrainRange::ideal(1920),
+    ///     height: ConstrainRange::ideal(1080),
+    ///     frame_rate: ConstrainRange::ideal(30.0),
+    ///     ..Default::default()
     /// };
     ///
     /// let capture = CameraCapture::new(device_id, constraints).unwrap();
     /// capture.stop().unwrap();
     /// ```
     pub fn stop(&self) -> Result<(), CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, just return Ok (mock implementation)
+        #[cfg(target_os = "linux")]
+        {
+            let session = self
+                .session
+                .lock()
+                .map_err(|_| CaptureError::CaptureFailure)?
+                .take();
+            if let Some(mut capture) = session {
+                capture.stop();
+            }
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,629 @@
+//! Camera mode selection from capture constraints
+//!
+//! Follows the constraint resolution of getUserMedia: `exact`, `min` and
+//! `max` values are required and rule out the modes that do not satisfy
+//! them, while `ideal` values only rank the remaining modes. Each mode gets
+//! a fitness distance, the sum over its properties of
+//! `|actual - ideal| / max(|actual|, |ideal|)` for numbers and 0 or 1 for
+//! the facing mode, and the mode with the smallest distance is selected.
+
+use crate::{CaptureConstraints, CaptureError};
+
+// Modes the same distance from the constraints are ranked by their distance
+// from 640x480 at 30 frames per second, the usual camera default
+const DEFAULT_WIDTH: u32 = 640;
+const DEFAULT_HEIGHT: u32 = 480;
+const DEFAULT_FRAME_RATE: f32 = 30.0;
+
+/// How far a frame rate may be off an `exact`, `min` or `max` value,
+/// absorbing the rounding of frame intervals reported as fractions
+const FRAME_RATE_TOLERANCE: f64 = 0.01;
+
+/// Constrained properties, in the order they are checked
+const PROPERTIES: [&str; 4] = ["width", "height", "frame_rate", "facing_mode"];
+
+/// Largest width or height that can be constrained; larger `u32` values
+/// are negative numbers converted without a check
+const MAX_DIMENSION: u32 = i32::MAX as u32;
+
+/// Largest frame rate that can be constrained
+const MAX_FRAME_RATE: f32 = 1000.0;
+
+/// Constraint on a numeric capture property
+///
+/// `exact`, `min` and `max` must be met by the selected mode, `ideal` is
+/// approached as closely as the device allows.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::ConstrainRange;
+///
+/// let width = ConstrainRange::ideal(1920);
+/// assert_eq!(width.ideal, Some(1920));
+///
+/// let frame_rate = ConstrainRange::range(24.0, 60.0);
+/// assert_eq!(frame_rate.min, Some(24.0));
+/// assert_eq!(frame_rate.max, Some(60.0));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConstrainRange<T> {
+    /// Value to get as close to as possible
+    pub ideal: Option<T>,
+    /// Value that must be met exactly
+    pub exact: Option<T>,
+    /// Smallest acceptable value
+    pub min: Option<T>,
+    /// Largest acceptable value
+    pub max: Option<T>,
+}
+
+impl<T> Default for ConstrainRange<T> {
+    fn default() -> Self {
+        Self {
+            ideal: None,
+            exact: None,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl<T: Copy> ConstrainRange<T> {
+    /// Creates a constraint preferring `value`
+    pub fn ideal(value: T) -> Self {
+        Self {
+            ideal: Some(value),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a constraint requiring `value`
+    pub fn exact(value: T) -> Self {
+        Self {
+            exact: Some(value),
+            ..Self::default()
+        }
+    }
+
+    /// Creates a constraint requiring a value from `min` to `max`
+    pub fn range(min: T, max: T) -> Self {
+        Self {
+            min: Some(min),
+            max: Some(max),
+            ..Self::default()
+        }
+    }
+
+    /// Gets the value asked for, the exact one or else the ideal one
+    pub fn preferred(&self) -> Option<T> {
+        self.exact.or(self.ideal)
+    }
+}
+
+impl<T: Copy + Into<f64>> ConstrainRange<T> {
+    /// Whether `actual` meets the exact, min and max values
+    ///
+    /// A device without the property satisfies only an unconstrained one.
+    fn satisfied_by(&self, actual: Option<f64>, tolerance: f64) -> bool {
+        let required = [self.exact, self.min, self.max];
+        let Some(actual) = actual else {
+            return required.iter().all(Option::is_none);
+        };
+        self.exact
+            .is_none_or(|exact| (actual - exact.into()).abs() <= tolerance)
+            && self.min.is_none_or(|min| actual >= min.into() - tolerance)
+            && self.max.is_none_or(|max| actual <= max.into() + tolerance)
+    }
+
+    /// Whether the constraint can be met by a value up to `largest`
+    ///
+    /// Every value must be finite and at most `largest`, and above 0
+    /// except for `min`, which is 0 when it constrains nothing. `min` must
+    /// not exceed `max`, and `exact` must lie between them.
+    fn is_valid(&self, largest: f64) -> bool {
+        let in_range = |value: Option<T>, smallest_allowed: bool| {
+            value.map(Into::into).is_none_or(|value: f64| {
+                value.is_finite()
+                    && value <= largest
+                    && (value > 0.0 || (smallest_allowed && value == 0.0))
+            })
+        };
+        let (min, max, exact) = (
+            self.min.map(Into::into),
+            self.max.map(Into::into),
+            self.exact.map(Into::into),
+        );
+
+        in_range(self.ideal, false)
+            && in_range(self.exact, false)
+            && in_range(self.min, true)
+            && in_range(self.max, false)
+            && min.zip(max).is_none_or(|(min, max)| min <= max)
+            && exact.zip(min).is_none_or(|(exact, min)| exact >= min)
+            && exact.zip(max).is_none_or(|(exact, max)| exact <= max)
+    }
+
+    /// Distance of `actual` from the ideal value, from 0 to 1
+    fn distance(&self, actual: Option<f64>) -> f64 {
+        match (self.ideal, actual) {
+            (Some(ideal), Some(actual)) => relative_distance(actual, ideal.into()),
+            _ => 0.0,
+        }
+    }
+}
+
+/// Direction a camera faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FacingMode {
+    /// Facing the user, like a front camera or webcam
+    User,
+    /// Facing away from the user, like a rear camera
+    Environment,
+    /// Facing to the left of the user
+    Left,
+    /// Facing to the right of the user
+    Right,
+}
+
+/// Constraint on the direction a camera faces
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::{ConstrainFacingMode, FacingMode};
+///
+/// let facing_mode = ConstrainFacingMode::exact(FacingMode::Environment);
+/// assert_eq!(facing_mode.exact, Some(FacingMode::Environment));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ConstrainFacingMode {
+    /// Direction to prefer
+    pub ideal: Option<FacingMode>,
+    /// Direction the camera must face
+    pub exact: Option<FacingMode>,
+}
+
+impl ConstrainFacingMode {
+    /// Creates a constraint preferring `facing_mode`
+    pub fn ideal(facing_mode: FacingMode) -> Self {
+        Self {
+            ideal: Some(facing_mode),
+            exact: None,
+        }
+    }
+
+    /// Creates a constraint requiring `facing_mode`
+    pub fn exact(facing_mode: FacingMode) -> Self {
+        Self {
+            ideal: None,
+            exact: Some(facing_mode),
+        }
+    }
+
+    fn satisfied_by(&self, actual: Option<FacingMode>) -> bool {
+        self.exact.is_none_or(|exact| actual == Some(exact))
+    }
+
+    fn distance(&self, actual: Option<FacingMode>) -> f64 {
+        match (self.ideal, actual) {
+            (Some(ideal), Some(actual)) if ideal != actual => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Resolution and frame rate a camera can capture at
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::CaptureMode;
+///
+/// let mode = CaptureMode {
+///     width: 1280,
+///     height: 720,
+///     frame_rate: Some(30.0),
+///     facing_mode: None,
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureMode {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Frame rate in frames per second, if the device reports one
+    pub frame_rate: Option<f32>,
+    /// Direction the camera faces, if known
+    pub facing_mode: Option<FacingMode>,
+}
+
+/// Settings a capture was started with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureSettings {
+    /// Width of the captured frames in pixels
+    pub width: u32,
+    /// Height of the captured frames in pixels
+    pub height: u32,
+    /// Frame rate in frames per second, if the device reports one
+    pub frame_rate: Option<f32>,
+    /// Direction the camera faces, if known
+    pub facing_mode: Option<FacingMode>,
+}
+
+impl From<CaptureMode> for CaptureSettings {
+    fn from(mode: CaptureMode) -> Self {
+        Self {
+            width: mode.width,
+            height: mode.height,
+            frame_rate: mode.frame_rate,
+            facing_mode: mode.facing_mode,
+        }
+    }
+}
+
+/// Builder of [`CaptureConstraints`]
+///
+/// Plain values are ideal, as in getUserMedia: the device mode closest to
+/// them is selected. `exact_*` values and ranges are required, and no mode
+/// is selected unless it meets them.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::{CaptureConstraints, ConstrainRange, FacingMode};
+///
+/// let constraints = CaptureConstraints::builder()
+///     .width(1280)
+///     .exact_height(720)
+///     .frame_rate(30.0)
+///     .facing_mode(FacingMode::User)
+///     .build();
+///
+/// assert_eq!(constraints.width, ConstrainRange::ideal(1280));
+/// assert_eq!(constraints.height, ConstrainRange::exact(720));
+/// assert!(constraints.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CaptureConstraintsBuilder {
+    constraints: CaptureConstraints,
+}
+
+impl CaptureConstraintsBuilder {
+    /// Prefers a width in pixels
+    pub fn width(mut self, width: u32) -> Self {
+        self.constraints.width.ideal = Some(width);
+        self
+    }
+
+    /// Requires a width in pixels
+    pub fn exact_width(mut self, width: u32) -> Self {
+        self.constraints.width.exact = Some(width);
+        self
+    }
+
+    /// Requires a width from `min` to `max` pixels
+    pub fn width_range(mut self, min: u32, max: u32) -> Self {
+        self.constraints.width.min = Some(min);
+        self.constraints.width.max = Some(max);
+        self
+    }
+
+    /// Prefers a height in pixels
+    pub fn height(mut self, height: u32) -> Self {
+        self.constraints.height.ideal = Some(height);
+        self
+    }
+
+    /// Requires a height in pixels
+    pub fn exact_height(mut self, height: u32) -> Self {
+        self.constraints.height.exact = Some(height);
+        self
+    }
+
+    /// Requires a height from `min` to `max` pixels
+    pub fn height_range(mut self, min: u32, max: u32) -> Self {
+        self.constraints.height.min = Some(min);
+        self.constraints.height.max = Some(max);
+        self
+    }
+
+    /// Prefers a frame rate in frames per second
+    pub fn frame_rate(mut self, frame_rate: f32) -> Self {
+        self.constraints.frame_rate.ideal = Some(frame_rate);
+        self
+    }
+
+    /// Requires a frame rate in frames per second
+    pub fn exact_frame_rate(mut self, frame_rate: f32) -> Self {
+        self.constraints.frame_rate.exact = Some(frame_rate);
+        self
+    }
+
+    /// Requires a frame rate from `min` to `max` frames per second
+    pub fn frame_rate_range(mut self, min: f32, max: f32) -> Self {
+        self.constraints.frame_rate.min = Some(min);
+        self.constraints.frame_rate.max = Some(max);
+        self
+    }
+
+    /// Prefers a camera facing `facing_mode`
+    pub fn facing_mode(mut self, facing_mode: FacingMode) -> Self {
+        self.constraints.facing_mode.ideal = Some(facing_mode);
+        self
+    }
+
+    /// Requires a camera facing `facing_mode`
+    pub fn exact_facing_mode(mut self, facing_mode: FacingMode) -> Self {
+        self.constraints.facing_mode.exact = Some(facing_mode);
+        self
+    }
+
+    /// Gets the constraints built
+    ///
+    /// The constraints are not checked; see [`CaptureConstraints::validate`].
+    pub fn build(self) -> CaptureConstraints {
+        self.constraints
+    }
+}
+
+impl CaptureConstraints {
+    /// Creates a builder of constraints, initially unconstrained
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::CaptureConstraints;
+    ///
+    /// let constraints = CaptureConstraints::builder().width(640).build();
+    /// assert_eq!(constraints.width.ideal, Some(640));
+    /// ```
+    pub fn builder() -> CaptureConstraintsBuilder {
+        CaptureConstraintsBuilder::default()
+    }
+
+    /// Checks that the constraints ask for values a device can have
+    ///
+    /// Widths and heights must be above 0 and frame rates above 0 and at
+    /// most 1000, except for minimums, which may be 0. Minimums must not
+    /// exceed maximums, and exact values must lie between them. Ideal values
+    /// are only preferred and may lie outside a range.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConstraint` naming the first of `width`, `height`
+    /// and `frame_rate` with an invalid value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{CaptureConstraints, CaptureError};
+    ///
+    /// assert!(CaptureConstraints::builder().width(1280).build().validate().is_ok());
+    ///
+    /// let zero_width = CaptureConstraints::builder().exact_width(0).build();
+    /// assert_eq!(
+    ///     zero_width.validate(),
+    ///     Err(CaptureError::InvalidConstraint { constraint: "width".to_string() })
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), CaptureError> {
+        let valid = [
+            self.width.is_valid(MAX_DIMENSION.into()),
+            self.height.is_valid(MAX_DIMENSION.into()),
+            self.frame_rate.is_valid(MAX_FRAME_RATE.into()),
+        ];
+        match valid.iter().position(|valid| !valid) {
+            Some(index) => Err(CaptureError::InvalidConstraint {
+                constraint: PROPERTIES[index].to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Gets the fitness distance of `mode` from these constraints
+    ///
+    /// # Returns
+    ///
+    /// `None` if the mode does not meet a required value, otherwise the sum
+    /// of the distances of its properties from the ideal values, 0 for a
+    /// mode matching all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{CaptureConstraints, CaptureMode, ConstrainRange};
+    ///
+    /// let constraints = CaptureConstraints {
+    ///     width: ConstrainRange::ideal(1280),
+    ///     height: ConstrainRange::exact(720),
+    ///     ..Default::default()
+    /// };
+    /// let mode = |width, height| CaptureMode {
+    ///     width,
+    ///     height,
+    ///     frame_rate: Some(30.0),
+    ///     facing_mode: None,
+    /// };
+    ///
+    /// assert_eq!(constraints.fitness_distance(&mode(1280, 720)), Some(0.0));
+    /// assert_eq!(constraints.fitness_distance(&mode(640, 720)), Some(0.5));
+    /// assert_eq!(constraints.fitness_distance(&mode(640, 480)), None);
+    /// ```
+    pub fn fitness_distance(&self, mode: &CaptureMode) -> Option<f64> {
+        if self.unmet_constraint(mode).is_some() {
+            return None;
+        }
+        let frame_rate = mode.frame_rate.map(f64::from);
+        Some(
+            self.width.distance(Some(mode.width.into()))
+                + self.height.distance(Some(mode.height.into()))
+                + self.frame_rate.distance(frame_rate)
+                + self.facing_mode.distance(mode.facing_mode),
+        )
+    }
+
+    /// Selects the mode closest to these constraints
+    ///
+    /// Of the modes meeting every required value, the one with the smallest
+    /// fitness distance is selected, and of equally fit ones the one
+    /// closest to 640x480 at 30 frames per second.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidConstraint` if the constraints fail
+    /// [`validate`](Self::validate), and `Overconstrained` naming the first
+    /// of `width`, `height`, `frame_rate` and `facing_mode` that rules out
+    /// the last remaining modes, when no mode meets every required value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{CaptureConstraints, CaptureError, CaptureMode, ConstrainRange};
+    ///
+    /// let modes = [
+    ///     CaptureMode { width: 640, height: 480, frame_rate: Some(30.0), facing_mode: None },
+    ///     CaptureMode { width: 1280, height: 720, frame_rate: Some(30.0), facing_mode: None },
+    /// ];
+    ///
+    /// let ideal = CaptureConstraints {
+    ///     width: ConstrainRange::ideal(1920),
+    ///     height: ConstrainRange::ideal(1080),
+    ///     frame_rate: ConstrainRange::ideal(60.0),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(ideal.select_mode(&modes).unwrap(), modes[1]);
+    ///
+    /// let exact = CaptureConstraints {
+    ///     frame_rate: ConstrainRange::exact(60.0),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(
+    ///     exact.select_mode(&modes),
+    ///     Err(CaptureError::Overconstrained { constraint: "frame_rate".to_string() })
+    /// );
+    /// ```
+    pub fn select_mode(&self, modes: &[CaptureMode]) -> Result<CaptureMode, CaptureError> {
+        self.validate()?;
+
+        let defaults = CaptureConstraints {
+            width: ConstrainRange::ideal(DEFAULT_WIDTH),
+            height: ConstrainRange::ideal(DEFAULT_HEIGHT),
+            frame_rate: ConstrainRange::ideal(DEFAULT_FRAME_RATE),
+            ..CaptureConstraints::default()
+        };
+
+        let best = modes
+            .iter()
+            .filter_map(|mode| {
+                let distance = self.fitness_distance(mode)?;
+                let default_distance = defaults.fitness_distance(mode)?;
+                Some((distance, default_distance, mode))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        if let Some((_, _, mode)) = best {
+            return Ok(*mode);
+        }
+
+        Err(CaptureError::Overconstrained {
+            constraint: self.failed_constraint(modes).to_string(),
+        })
+    }
+
+    /// Index in [`PROPERTIES`] of the first property whose required values
+    /// `mode` misses
+    fn unmet_constraint(&self, mode: &CaptureMode) -> Option<usize> {
+        let frame_rate = mode.frame_rate.map(f64::from);
+        [
+            self.width.satisfied_by(Some(mode.width.into()), 0.0),
+            self.height.satisfied_by(Some(mode.height.into()), 0.0),
+            self.frame_rate
+                .satisfied_by(frame_rate, FRAME_RATE_TOLERANCE),
+            self.facing_mode.satisfied_by(mode.facing_mode),
+        ]
+        .iter()
+        .position(|satisfied| !satisfied)
+    }
+
+    /// Name of the property that rules out the last modes when filtering
+    /// them by one property after the other
+    fn failed_constraint(&self, modes: &[CaptureMode]) -> &'static str {
+        let mut remaining: Vec<&CaptureMode> = modes.iter().collect();
+        for (index, property) in PROPERTIES.iter().enumerate() {
+            remaining.retain(|mode| {
+                self.unmet_constraint(mode)
+                    .is_none_or(|unmet| unmet > index)
+            });
+            if remaining.is_empty() {
+                return property;
+            }
+        }
+        // Only reached without modes at all
+        PROPERTIES[0]
+    }
+}
+
+/// `|actual - ideal| / max(|actual|, |ideal|)`, 0 when both are 0
+fn relative_distance(actual: f64, ideal: f64) -> f64 {
+    let scale = actual.abs().max(ideal.abs());
+    if scale == 0.0 {
+        0.0
+    } else {
+        (actual - ideal).abs() / scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mode(width: u32, height: u32, frame_rate: f32) -> CaptureMode {
+        CaptureMode {
+            width,
+            height,
+            frame_rate: Some(frame_rate),
+            facing_mode: None,
+        }
+    }
+
+    #[test]
+    fn test_relative_distance() {
+        assert_eq!(relative_distance(0.0, 0.0), 0.0);
+        assert_eq!(relative_distance(1280.0, 1920.0), 640.0 / 1920.0);
+        assert_eq!(relative_distance(60.0, 30.0), 0.5);
+    }
+
+    #[test]
+    fn test_frame_rate_tolerance() {
+        let constraints = CaptureConstraints {
+            frame_rate: ConstrainRange::exact(30.0),
+            ..CaptureConstraints::default()
+        };
+        // 30 fps reported as a 333333/10000000 interval
+        assert!(constraints
+            .fitness_distance(&mode(640, 480, 30.000_002))
+            .is_some());
+        assert!(constraints
+            .fitness_distance(&mode(640, 480, 29.97))
+            .is_none());
+    }
+
+    #[test]
+    fn test_failed_constraint_is_first_to_rule_out_all_modes() {
+        let modes = [mode(1280, 720, 30.0), mode(640, 480, 60.0)];
+        // Each is met by some mode, but not both by the same one
+        let constraints = CaptureConstraints {
+            width: ConstrainRange::exact(1280),
+            frame_rate: ConstrainRange::exact(60.0),
+            ..CaptureConstraints::default()
+        };
+        assert_eq!(constraints.failed_constraint(&modes), "frame_rate");
+
+        let constraints = CaptureConstraints {
+            height: ConstrainRange::range(1000, 500),
+            ..CaptureConstraints::default()
+        };
+        assert_eq!(constraints.failed_constraint(&modes), "height");
+    }
+}
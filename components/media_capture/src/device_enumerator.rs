@@ -1,8 +1,87 @@
 //! Device enumeration for media capture
 //!
-//! Provides functionality to discover available video and audio input devices.
+//! Provides functionality to discover available video and audio input devices
+//! and to watch for devices being plugged in or removed.
 
-use crate::{CaptureError, DeviceInfo};
+use crate::{CaptureError, CaptureMode, DeviceEvent, DeviceInfo, DeviceKind};
+use futures_util::stream::{self, Stream, StreamExt};
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+/// Capacity of the device event channel; slower watchers miss older events
+const EVENT_CAPACITY: usize = 16;
+
+/// Kinds of devices listed and watched by [`DeviceEnumerator`]
+const INPUT_KINDS: [DeviceKind; 2] = [DeviceKind::VideoInput, DeviceKind::AudioInput];
+
+/// Stream yielding whenever capture devices may have been plugged in or
+/// removed
+pub type DeviceChanges = Pin<Box<dyn Stream<Item = ()> + Send>>;
+
+/// Source of the devices listed and watched by a [`DeviceEnumerator`]
+///
+/// [`DeviceEnumerator::new`] uses the platform's devices; other backends
+/// are set with [`DeviceEnumerator::with_backend`], such as one simulating
+/// devices being plugged in.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::{DeviceBackend, DeviceChanges, DeviceInfo, DeviceKind};
+/// use futures_util::stream;
+///
+/// /// A single microphone that is never unplugged
+/// struct FixedMicrophone;
+///
+/// impl DeviceBackend for FixedMicrophone {
+///     fn devices(&self, kind: DeviceKind) -> Vec<DeviceInfo> {
+///         match kind {
+///             DeviceKind::AudioInput => vec![DeviceInfo {
+///                 device_id: "mic-001".to_string(),
+///                 label: "Built-in Microphone".to_string(),
+///                 kind,
+///             }],
+///             _ => vec![],
+///         }
+///     }
+///
+///     fn changes(&self) -> DeviceChanges {
+///         Box::pin(stream::empty())
+///     }
+/// }
+/// ```
+pub trait DeviceBackend: Send + Sync + 'static {
+    /// Lists the available devices of `kind`
+    ///
+    /// A device keeps its `device_id` for as long as it is available, so
+    /// that it is recognized from one listing to the next.
+    fn devices(&self, kind: DeviceKind) -> Vec<DeviceInfo>;
+
+    /// Starts watching for devices being plugged in or removed
+    ///
+    /// Changes made after this returns are reported by the stream yielding,
+    /// after which the devices are listed again to find out what changed.
+    /// Watching stops when the stream ends.
+    fn changes(&self) -> DeviceChanges;
+
+    /// Lists the modes the camera `device_id` can capture in
+    ///
+    /// [`CameraCapture`](crate::CameraCapture) negotiates its mode from
+    /// these. Backends without cameras keep the default, which finds no
+    /// device.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DeviceNotFound` if `device_id` is not a camera of this
+    /// backend, or another `CaptureError` if its modes cannot be queried.
+    fn capture_modes(&self, device_id: &str) -> Result<Vec<CaptureMode>, CaptureError> {
+        let _ = device_id;
+        Err(CaptureError::DeviceNotFound)
+    }
+}
 
 /// Enumerates available capture devices
 ///
@@ -23,9 +102,25 @@ use crate::{CaptureError, DeviceInfo};
 ///     Ok(())
 /// }
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct DeviceEnumerator {
-    // Platform-specific fields will be added when implementing platform support
+    /// Where devices are listed from
+    backend: Arc<dyn DeviceBackend>,
+    /// Sender of the device events delivered to `watch` streams
+    events: broadcast::Sender<DeviceEvent>,
+    /// Background task detecting device changes, started by the first `watch`
+    watcher: Arc<Mutex<Option<Watcher>>>,
+}
+
+/// Background device watching task, stopped when the last clone of its
+/// enumerator is dropped
+#[derive(Debug)]
+struct Watcher(JoinHandle<()>);
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
 }
 
 impl DeviceEnumerator {
@@ -39,7 +134,42 @@ impl DeviceEnumerator {
     /// let enumerator = DeviceEnumerator::new();
     /// ```
     pub fn new() -> Self {
-        Self {}
+        Self::with_backend(PlatformBackend)
+    }
+
+    /// Creates a device enumerator listing the devices of `backend`
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - Source of the devices to list and watch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{DeviceBackend, DeviceChanges, DeviceEnumerator, DeviceInfo, DeviceKind};
+    /// use futures_util::stream;
+    ///
+    /// struct NoDevices;
+    ///
+    /// impl DeviceBackend for NoDevices {
+    ///     fn devices(&self, _kind: DeviceKind) -> Vec<DeviceInfo> {
+    ///         vec![]
+    ///     }
+    ///
+    ///     fn changes(&self) -> DeviceChanges {
+    ///         Box::pin(stream::empty())
+    ///     }
+    /// }
+    ///
+    /// let enumerator = DeviceEnumerator::with_backend(NoDevices);
+    /// ```
+    pub fn with_backend(backend: impl DeviceBackend) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            backend: Arc::new(backend),
+            events,
+            watcher: Arc::new(Mutex::new(None)),
+        }
     }
 
     /// Enumerates available video input devices
@@ -47,6 +177,9 @@ impl DeviceEnumerator {
     /// Returns a list of video capture devices (cameras, webcams).
     /// The list may be empty if no devices are available or permissions are denied.
     ///
+    /// On Linux, `/dev/video*` nodes that support video capture are listed
+    /// with the node path as `device_id` and the V4L2 card name as `label`.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -65,9 +198,7 @@ impl DeviceEnumerator {
     /// }
     /// ```
     pub async fn enumerate_video_devices(&self) -> Result<Vec<DeviceInfo>, CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, return empty list (mock implementation)
-        Ok(vec![])
+        Ok(self.backend.devices(DeviceKind::VideoInput))
     }
 
     /// Enumerates available audio input devices
@@ -75,6 +206,10 @@ impl DeviceEnumerator {
     /// Returns a list of audio capture devices (microphones).
     /// The list may be empty if no devices are available or permissions are denied.
     ///
+    /// On Linux, ALSA capture PCM devices are listed with the
+    /// `/dev/snd/pcmC*D*c` node path as `device_id` and the PCM name as
+    /// `label`.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -93,9 +228,176 @@ impl DeviceEnumerator {
     /// }
     /// ```
     pub async fn enumerate_audio_devices(&self) -> Result<Vec<DeviceInfo>, CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, return empty list (mock implementation)
-        Ok(vec![])
+        Ok(self.backend.devices(DeviceKind::AudioInput))
+    }
+
+    /// Watches for capture devices being plugged in or removed
+    ///
+    /// Events are detected by a background task started on the first call
+    /// and shared by all clones of this enumerator; the stream ends when
+    /// the enumerator and all its clones are dropped. Devices present when
+    /// watching starts are not reported; list them with
+    /// [`enumerate_video_devices`](Self::enumerate_video_devices) and
+    /// [`enumerate_audio_devices`](Self::enumerate_audio_devices).
+    ///
+    /// On Linux, devices are watched through udev `video4linux` and `sound`
+    /// events; other platforms report no events yet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a Tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::{DeviceEnumerator, DeviceEvent};
+    /// use futures_util::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     let enumerator = DeviceEnumerator::new();
+    ///     let mut events = Box::pin(enumerator.watch());
+    ///
+    ///     while let Some(event) = events.next().await {
+    ///         match event {
+    ///             DeviceEvent::Added(device) => println!("Plugged in: {}", device.label),
+    ///             DeviceEvent::Removed { device_id } => println!("Removed: {}", device_id),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn watch(&self) -> impl Stream<Item = DeviceEvent> {
+        let receiver = self.events.subscribe();
+
+        let mut watcher = self.watcher.lock().unwrap_or_else(PoisonError::into_inner);
+        if watcher.is_none() {
+            let backend = Arc::clone(&self.backend);
+            let events = self.events.clone();
+            *watcher = Some(Watcher(tokio::spawn(watch_devices(backend, events))));
+        }
+
+        stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    // Lagged watchers skip the events they missed
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+}
+
+impl fmt::Debug for DeviceEnumerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeviceEnumerator")
+            .field("events", &self.events)
+            .field("watcher", &self.watcher)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Report the input devices added and removed each time `backend` signals
+/// a change
+///
+/// Devices are matched by `device_id`, so a device is only reported again
+/// after it was removed.
+async fn watch_devices(backend: Arc<dyn DeviceBackend>, events: broadcast::Sender<DeviceEvent>) {
+    let mut changes = backend.changes();
+    // Listed after watching starts so no change is missed
+    let mut known = input_devices(backend.as_ref());
+
+    while changes.next().await.is_some() {
+        let current = input_devices(backend.as_ref());
+        let removed = known
+            .iter()
+            .filter(|device| !contains(&current, &device.device_id))
+            .map(|device| DeviceEvent::Removed {
+                device_id: device.device_id.clone(),
+            });
+        let added = current
+            .iter()
+            .filter(|device| !contains(&known, &device.device_id))
+            .map(|device| DeviceEvent::Added(device.clone()));
+        for event in removed.chain(added) {
+            // Sending only fails while nobody is watching
+            let _ = events.send(event);
+        }
+        known = current;
+    }
+}
+
+/// Video and audio input devices of `backend`
+fn input_devices(backend: &dyn DeviceBackend) -> Vec<DeviceInfo> {
+    INPUT_KINDS
+        .into_iter()
+        .flat_map(|kind| backend.devices(kind))
+        .collect()
+}
+
+fn contains(devices: &[DeviceInfo], device_id: &str) -> bool {
+    devices.iter().any(|device| device.device_id == device_id)
+}
+
+/// Devices of the platform: V4L2 cameras and ALSA microphones on Linux
+pub(crate) struct PlatformBackend;
+
+#[cfg(target_os = "linux")]
+impl DeviceBackend for PlatformBackend {
+    fn devices(&self, kind: DeviceKind) -> Vec<DeviceInfo> {
+        match kind {
+            DeviceKind::VideoInput => crate::v4l2::enumerate_devices(),
+            DeviceKind::AudioInput => crate::alsa::enumerate_devices(),
+            DeviceKind::AudioOutput => Vec::new(),
+        }
+    }
+
+    /// Yields for udev additions and removals of video and sound device
+    /// nodes; without udev, nothing is reported
+    fn changes(&self) -> DeviceChanges {
+        use crate::udev::Monitor;
+        use tokio::io::unix::AsyncFd;
+
+        let monitor = Monitor::open(&["video4linux", "sound"])
+            .ok()
+            .and_then(|monitor| AsyncFd::new(monitor).ok());
+        let Some(monitor) = monitor else {
+            return Box::pin(stream::empty());
+        };
+
+        Box::pin(stream::unfold(monitor, |mut monitor| async move {
+            loop {
+                let changed = {
+                    let mut guard = monitor.readable_mut().await.ok()?;
+                    let mut changed = false;
+                    while guard.get_inner_mut().receive().is_some() {
+                        changed = true;
+                    }
+                    guard.clear_ready();
+                    changed
+                };
+                if changed {
+                    return Some(((), monitor));
+                }
+            }
+        }))
+    }
+
+    fn capture_modes(&self, device_id: &str) -> Result<Vec<CaptureMode>, CaptureError> {
+        crate::v4l2::capture_modes(device_id)
+    }
+}
+
+/// No devices are listed or watched on this platform yet
+#[cfg(not(target_os = "linux"))]
+impl DeviceBackend for PlatformBackend {
+    fn devices(&self, _kind: DeviceKind) -> Vec<DeviceInfo> {
+        Vec::new()
+    }
+
+    fn changes(&self) -> DeviceChanges {
+        Box::pin(stream::empty())
     }
 }
 
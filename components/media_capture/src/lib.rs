@@ -26,14 +26,33 @@
 #![warn(missing_docs)]
 
 mod types;
+mod constraints;
 mod device_enumerator;
 mod screen_capture;
 mod camera_capture;
 mod microphone_capture;
+mod tracks;
+#[cfg(target_os = "linux")]
+mod alsa;
+#[cfg(target_os = "linux")]
+mod udev;
+#[cfg(target_os = "linux")]
+mod v4l2;
+#[cfg(target_os = "linux")]
+mod x11;
+#[cfg(target_os = "linux")]
+mod wayland;
 
 // Re-export public API
 pub use types::*;
-pub use device_enumerator::DeviceEnumerator;
-pub use screen_capture::ScreenCapture;
+pub use constraints::{
+    CaptureConstraintsBuilder, CaptureMode, CaptureSettings, ConstrainFacingMode, ConstrainRange,
+    FacingMode,
+};
+pub use device_enumerator::{DeviceBackend, DeviceChanges, DeviceEnumerator};
+pub use screen_capture::{
+    CaptureOptions, CaptureSource, CaptureSourceKind, CaptureStream, DisplayInfo, DisplayServer,
+    Rect, ScreenCapture,
+};
 pub use camera_capture::CameraCapture;
 pub use microphone_capture::MicrophoneCapture;
@@ -1,15 +1,56 @@
 //! Microphone capture functionality
 //!
 //! Provides microphone/audio input capture capabilities with platform-specific implementations.
+//!
+//! Captured audio passes through these stages before it is delivered:
+//!
+//! ```text
+//! AudioSource ─> channel conversion ─> resampling ─> AudioProcessingChain ─> echo cancellation
+//!             ─> level metering
+//! ```
 
-use crate::{AudioConstraints, CaptureError};
-use cortenbrowser_shared_types::AudioBuffer;
+use crate::{AudioConstraints, AudioLevelEvent, CaptureError};
+use cortenbrowser_media_pipeline::{ResampleStretcher, TimeStretcher};
+use cortenbrowser_shared_types::{
+    AudioBuffer, AudioFormat, AudioLevel, AudioProcessingConfig, MediaStreamTrack,
+};
+use cortenbrowser_webrtc_integration::{AudioLevelMeter, AudioProcessingChain, EchoCanceller};
+use futures_util::stream::{self, Stream};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::mpsc;
 
+/// Sample rate asked of the device when the constraints do not specify one
+#[cfg(target_os = "linux")]
+const DEFAULT_SAMPLE_RATE: u32 = 48000;
+
+/// Channel count asked of the device when the constraints do not specify one
+#[cfg(target_os = "linux")]
+const DEFAULT_CHANNELS: u8 = 1;
+
+/// Longest far-end audio kept waiting for microphone audio to cancel it
+/// from, in seconds; older far-end audio is dropped
+const MAX_FAR_END_SECONDS: usize = 1;
+
+/// Level updates sent to `level_events` per second of captured audio
+const LEVEL_UPDATES_PER_SECOND: u32 = 10;
+
+/// Level events kept for each `level_events` stream that falls behind
+const LEVEL_EVENT_CAPACITY: usize = 32;
+
 /// Microphone capture interface
 ///
 /// Captures audio samples from a microphone or audio input device.
-/// Platform-specific implementation required for actual capture.
+/// On Linux, `device_id` is an ALSA capture device node such as
+/// `/dev/snd/pcmC0D0c`, as listed by
+/// [`DeviceEnumerator`](crate::DeviceEnumerator). Audio is delivered as
+/// interleaved `f32` samples at the sample rate and channel count of the
+/// constraints, resampled and remixed from what the device captures as
+/// needed. Other platforms are not yet supported.
 ///
 /// # Examples
 ///
@@ -18,7 +59,7 @@ use tokio::sync::mpsc;
 ///
 /// #[tokio::main]
 /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-///     let device_id = "mic-001".to_string();
+///     let device_id = "/dev/snd/pcmC0D0c".to_string();
 ///     let constraints = AudioConstraints {
 ///         sample_rate: Some(48000),
 ///         channels: Some(2),
@@ -38,11 +79,20 @@ use tokio::sync::mpsc;
 /// ```
 #[derive(Debug)]
 pub struct MicrophoneCapture {
-    #[allow(dead_code)] // Will be used in platform-specific implementation
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
     device_id: String,
-    #[allow(dead_code)] // Will be used in platform-specific implementation
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
     constraints: AudioConstraints,
-    // Platform-specific fields will be added
+    processing: Option<AudioProcessingConfig>,
+    /// Audio played by the speakers, set by `with_echo_cancellation`
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    far_end: Option<Arc<Mutex<mpsc::Receiver<AudioBuffer>>>>,
+    /// Smoothing window of the level meter, set by `with_level_smoothing`
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    level_smoothing: Option<Duration>,
+    levels: Arc<LevelMonitor>,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    session: Mutex<Option<CaptureSession>>,
 }
 
 impl MicrophoneCapture {
@@ -73,13 +123,201 @@ impl MicrophoneCapture {
         Ok(Self {
             device_id,
             constraints,
+            processing: None,
+            far_end: None,
+            level_smoothing: None,
+            levels: Arc::new(LevelMonitor::new()),
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Enables post-processing of captured audio
+    ///
+    /// Captured buffers are passed through the noise suppression →
+    /// automatic gain control → echo cancellation chain before they are
+    /// delivered. Stages are selected by `config`. The chain has no far-end
+    /// reference, so its echo cancellation only takes effect together with
+    /// [`with_echo_cancellation`](Self::with_echo_cancellation).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{MicrophoneCapture, AudioConstraints};
+    /// use cortenbrowser_shared_types::AudioProcessingConfig;
+    ///
+    /// let constraints = AudioConstraints {
+    ///     sample_rate: Some(48000),
+    ///     channels: Some(1),
+    /// };
+    ///
+    /// let capture = MicrophoneCapture::new("mic-001".to_string(), constraints)
+    ///     .unwrap()
+    ///     .with_audio_processing(
+    ///         AudioProcessingConfig::new()
+    ///             .with_noise_suppression(true)
+    ///             .with_auto_gain_control(true),
+    ///     );
+    ///
+    /// assert!(capture.audio_processing().is_some());
+    /// ```
+    pub fn with_audio_processing(mut self, config: AudioProcessingConfig) -> Self {
+        self.processing = Some(config);
+        self
+    }
+
+    /// Returns the audio processing configuration, if enabled
+    pub fn audio_processing(&self) -> Option<&AudioProcessingConfig> {
+        self.processing.as_ref()
+    }
+
+    /// Enables acoustic echo cancellation against the audio the speakers play
+    ///
+    /// `far_end` receives the audio as it is played, in any format. It is
+    /// mixed down and resampled to the captured format, and every captured
+    /// channel has the echo of it removed by an [`EchoCanceller`] as the
+    /// last processing stage. The filter length is that of the audio
+    /// processing configuration, or its default.
+    ///
+    /// # Arguments
+    ///
+    /// * `far_end` - Receiver of the audio played by the speakers
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{MicrophoneCapture, AudioConstraints};
+    /// use tokio::sync::mpsc;
+    ///
+    /// let constraints = AudioConstraints {
+    ///     sample_rate: Some(48000),
+    ///     channels: Some(1),
+    /// };
+    ///
+    /// // The audio output sends what it plays to `speaker`
+    /// let (_speaker, far_end) = mpsc::channel(32);
+    /// let capture = MicrophoneCapture::new("mic-001".to_string(), constraints)
+    ///     .unwrap()
+    ///     .with_echo_cancellation(far_end);
+    /// ```
+    pub fn with_echo_cancellation(mut self, far_end: mpsc::Receiver<AudioBuffer>) -> Self {
+        self.far_end = Some(Arc::new(Mutex::new(far_end)));
+        self
+    }
+
+    /// Sets the smoothing window of the reported audio level
+    ///
+    /// The RMS level is averaged, and the peak level decays, over `window`.
+    /// The default window is 300 ms; a zero window reports the level of
+    /// each captured buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{MicrophoneCapture, AudioConstraints};
+    /// use std::time::Duration;
+    ///
+    /// let constraints = AudioConstraints {
+    ///     sample_rate: Some(48000),
+    ///     channels: Some(1),
+    /// };
+    ///
+    /// let capture = MicrophoneCapture::new("mic-001".to_string(), constraints)
+    ///     .unwrap()
+    ///     .with_level_smoothing(Duration::from_millis(100));
+    /// ```
+    pub fn with_level_smoothing(mut self, window: Duration) -> Self {
+        self.level_smoothing = Some(window);
+        self
+    }
+
+    /// Returns the current level of the captured audio
+    ///
+    /// The level is measured after all processing, so it is what the
+    /// receiver of the audio hears. It is silence while not capturing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{MicrophoneCapture, AudioConstraints};
+    /// use cortenbrowser_shared_types::AudioLevel;
+    ///
+    /// let constraints = AudioConstraints {
+    ///     sample_rate: Some(48000),
+    ///     channels: Some(1),
+    /// };
+    ///
+    /// let capture = MicrophoneCapture::new("mic-001".to_string(), constraints).unwrap();
+    /// assert_eq!(capture.get_level(), AudioLevel::SILENCE);
+    /// ```
+    pub fn get_level(&self) -> AudioLevel {
+        self.levels.get()
+    }
+
+    /// Watches the level and voice activity of the captured audio
+    ///
+    /// The stream yields [`AudioLevelEvent::Level`] about ten times per
+    /// second of captured audio, and [`AudioLevelEvent::VoiceActivity`] as
+    /// soon as voice starts or stops being detected. Stopping capture while
+    /// voice is detected ends the voice activity. Streams that fall behind
+    /// skip the events they missed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::{AudioConstraints, AudioLevelEvent, MicrophoneCapture};
+    /// use futures_util::StreamExt;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let constraints = AudioConstraints {
+    ///         sample_rate: Some(48000),
+    ///         channels: Some(1),
+    ///     };
+    ///     let capture = MicrophoneCapture::new("/dev/snd/pcmC0D0c".to_string(), constraints)?;
+    ///
+    ///     let mut events = Box::pin(capture.level_events());
+    ///     let _receiver = capture.start().await?;
+    ///
+    ///     while let Some(event) = events.next().await {
+    ///         if let AudioLevelEvent::VoiceActivity(speaking) = event {
+    ///             println!("Speaking: {}", speaking);
+    ///         }
+    ///     }
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn level_events(&self) -> impl Stream<Item = AudioLevelEvent> {
+        let receiver = self.levels.events.subscribe();
+
+        stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    // Lagged streams skip the events they missed
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
         })
     }
 
     /// Starts microphone capture
     ///
     /// Returns a receiver channel that will receive audio buffers.
-    /// Platform-specific implementation required.
+    /// Starting again replaces the previous capture session.
+    ///
+    /// Buffers have the constrained sample rate and channel count, or the
+    /// device's where unconstrained. Their timestamps count the audio
+    /// delivered since capture started, so they increase by each buffer's
+    /// duration.
+    ///
+    /// # Errors
+    ///
+    /// On Linux, returns `DeviceNotFound` if `device_id` is not an ALSA
+    /// capture device, `PermissionDenied` if it cannot be opened, and
+    /// `CaptureFailure` if `libasound` is missing or the device cannot
+    /// capture 16-bit audio.
     ///
     /// # Examples
     ///
@@ -88,7 +326,7 @@ impl MicrophoneCapture {
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let device_id = "mic-001".to_string();
+    ///     let device_id = "/dev/snd/pcmC0D0c".to_string();
     ///     let constraints = AudioConstraints {
     ///         sample_rate: Some(48000),
     ///         channels: Some(2),
@@ -101,12 +339,88 @@ impl MicrophoneCapture {
     /// }
     /// ```
     pub async fn start(&self) -> Result<mpsc::Receiver<AudioBuffer>, CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, create a channel and return the receiver (mock implementation)
-        let (_, rx) = mpsc::channel(32);
+        #[cfg(target_os = "linux")]
+        {
+            // Release the device before reopening it
+            self.stop()?;
+            let source = crate::alsa::AlsaCapture::open(
+                &self.device_id,
+                self.constraints.sample_rate.unwrap_or(DEFAULT_SAMPLE_RATE),
+                self.constraints.channels.unwrap_or(DEFAULT_CHANNELS),
+            )?;
+            self.start_source(Box::new(source))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            // Platform-specific implementation will be added
+            // For now, create a channel and return the receiver (mock implementation)
+            let (_, rx) = mpsc::channel(32);
+            Ok(rx)
+        }
+    }
+
+    /// Starts a capture session reading from `source`
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn start_source(
+        &self,
+        source: Box<dyn AudioSource>,
+    ) -> Result<mpsc::Receiver<AudioBuffer>, CaptureError> {
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|_| CaptureError::CaptureFailure)?;
+        if let Some(mut previous) = session.take() {
+            previous.stop();
+        }
+
+        let sample_rate = self.constraints.sample_rate.unwrap_or(source.sample_rate());
+        let channels = self.constraints.channels.unwrap_or(source.channels());
+        let mut stages = Stages::new(&*source, sample_rate, channels);
+        if let Some(config) = self
+            .processing
+            .as_ref()
+            .filter(|config| config.is_enabled())
+        {
+            stages.chain = Some(AudioProcessingChain::new(sample_rate, config.clone()));
+        }
+        if let Some(far_end) = &self.far_end {
+            let filter_length = self.processing.as_ref().map_or(
+                AudioProcessingConfig::default().echo_filter_length,
+                |config| config.echo_filter_length,
+            );
+            stages.echo = Some(EchoStage::new(
+                Arc::clone(far_end),
+                sample_rate,
+                channels,
+                filter_length,
+            ));
+        }
+        let mut meter = AudioLevelMeter::new(sample_rate, channels);
+        if let Some(window) = self.level_smoothing {
+            meter = meter.with_smoothing(window);
+        }
+        stages.level = Some(LevelStage::new(meter, Arc::clone(&self.levels), sample_rate));
+
+        let (tx, rx) = mpsc::channel(32);
+        *session = Some(CaptureSession::start(source, stages, tx)?);
         Ok(rx)
     }
 
+    /// Starts microphone capture into an audio track
+    ///
+    /// The track is labelled with the device identifier. Capture stops
+    /// once the track and all its clones have been stopped, and the track
+    /// ends if capture stops first. Must be called within a Tokio runtime.
+    ///
+    /// # Errors
+    ///
+    /// As for [`start`](Self::start).
+    pub async fn start_track(&self) -> Result<MediaStreamTrack, CaptureError> {
+        let buffers = self.start().await?;
+        Ok(crate::tracks::audio_track(self.device_id.clone(), buffers))
+    }
+
     /// Stops microphone capture
     ///
     /// # Examples
@@ -124,8 +438,604 @@ impl MicrophoneCapture {
     /// capture.stop().unwrap();
     /// ```
     pub fn stop(&self) -> Result<(), CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, just return Ok (mock implementation)
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| CaptureError::CaptureFailure)?
+            .take();
+        if let Some(mut capture) = session {
+            capture.stop();
+        }
+
         Ok(())
     }
 }
+
+/// Source of captured audio, in the device's own format
+pub(crate) trait AudioSource: Send {
+    /// Sample rate the device captures at
+    fn sample_rate(&self) -> u32;
+
+    /// Number of interleaved channels the device captures
+    fn channels(&self) -> u8;
+
+    /// Wait for the next interleaved samples; empty once capture has ended
+    fn read(&mut self) -> Result<Vec<f32>, CaptureError>;
+}
+
+/// Conversion and processing of captured audio into delivered buffers
+struct Stages {
+    /// Format the source captures in
+    source_rate: u32,
+    source_channels: u8,
+    /// Format buffers are delivered in
+    sample_rate: u32,
+    channels: u8,
+    /// Converts from the source's sample rate, when it differs
+    resampler: Option<ResampleStretcher>,
+    chain: Option<AudioProcessingChain>,
+    echo: Option<EchoStage>,
+    level: Option<LevelStage>,
+    /// Frames delivered so far, the timestamp of the next buffer
+    frames: u64,
+}
+
+impl Stages {
+    fn new(source: &dyn AudioSource, sample_rate: u32, channels: u8) -> Self {
+        let source_rate = source.sample_rate();
+        Self {
+            source_rate,
+            source_channels: source.channels(),
+            sample_rate,
+            channels,
+            resampler: (source_rate != sample_rate).then(ResampleStretcher::new),
+            chain: None,
+            echo: None,
+            level: None,
+            frames: 0,
+        }
+    }
+
+    /// Turn captured samples into the next buffer, or `None` if resampling
+    /// left no whole frame
+    fn process(&mut self, samples: Vec<f32>) -> Option<AudioBuffer> {
+        let samples = convert_channels(samples, self.source_channels, self.channels);
+        let mut samples = match &mut self.resampler {
+            Some(resampler) => {
+                let buffer = AudioBuffer::new(
+                    AudioFormat::F32LE,
+                    self.source_rate,
+                    self.channels,
+                    samples,
+                    Duration::ZERO,
+                );
+                let rate = self.source_rate as f32 / self.sample_rate as f32;
+                resampler.process(buffer, rate).samples
+            }
+            None => samples,
+        };
+
+        let channels = self.channels.max(1) as usize;
+        let frames = samples.len() / channels;
+        if frames == 0 {
+            return None;
+        }
+        samples.truncate(frames * channels);
+
+        let timestamp =
+            Duration::from_nanos(self.frames * 1_000_000_000 / u64::from(self.sample_rate));
+        self.frames += frames as u64;
+        let mut buffer = AudioBuffer::new(
+            AudioFormat::F32LE,
+            self.sample_rate,
+            self.channels,
+            samples,
+            timestamp,
+        );
+
+        if let Some(chain) = &mut self.chain {
+            buffer = chain.process_buffer(&buffer, None);
+        }
+        if let Some(echo) = &mut self.echo {
+            echo.process(&mut buffer);
+        }
+        if let Some(level) = &mut self.level {
+            level.process(&buffer);
+        }
+        Some(buffer)
+    }
+}
+
+/// Latest level of the captured audio, shared with the capture thread
+#[derive(Debug)]
+struct LevelMonitor {
+    /// Bits of the `f32` RMS and peak levels
+    rms_dbfs: AtomicU32,
+    peak_dbfs: AtomicU32,
+    voice_active: AtomicBool,
+    events: broadcast::Sender<AudioLevelEvent>,
+}
+
+impl LevelMonitor {
+    fn new() -> Self {
+        let (events, _) = broadcast::channel(LEVEL_EVENT_CAPACITY);
+        Self {
+            rms_dbfs: AtomicU32::new(AudioLevel::MIN_DBFS.to_bits()),
+            peak_dbfs: AtomicU32::new(AudioLevel::MIN_DBFS.to_bits()),
+            voice_active: AtomicBool::new(false),
+            events,
+        }
+    }
+
+    fn get(&self) -> AudioLevel {
+        AudioLevel {
+            rms_dbfs: f32::from_bits(self.rms_dbfs.load(Ordering::Relaxed)),
+            peak_dbfs: f32::from_bits(self.peak_dbfs.load(Ordering::Relaxed)),
+            voice_active: self.voice_active.load(Ordering::Relaxed),
+        }
+    }
+
+    fn set(&self, level: AudioLevel) {
+        self.rms_dbfs.store(level.rms_dbfs.to_bits(), Ordering::Relaxed);
+        self.peak_dbfs.store(level.peak_dbfs.to_bits(), Ordering::Relaxed);
+        self.voice_active.store(level.voice_active, Ordering::Relaxed);
+    }
+
+    fn send(&self, event: AudioLevelEvent) {
+        // Nobody watching is not an error
+        let _ = self.events.send(event);
+    }
+}
+
+/// Level metering of the delivered audio
+struct LevelStage {
+    meter: AudioLevelMeter,
+    monitor: Arc<LevelMonitor>,
+    /// Frames between level updates
+    update_frames: usize,
+    /// Frames metered since the last level update
+    frames: usize,
+    voice_active: bool,
+}
+
+impl LevelStage {
+    fn new(meter: AudioLevelMeter, monitor: Arc<LevelMonitor>, sample_rate: u32) -> Self {
+        Self {
+            meter,
+            monitor,
+            update_frames: (sample_rate / LEVEL_UPDATES_PER_SECOND).max(1) as usize,
+            frames: 0,
+            voice_active: false,
+        }
+    }
+
+    /// Meter `buffer`, publishing its level and any voice activity change
+    fn process(&mut self, buffer: &AudioBuffer) {
+        let level = self.meter.process(&buffer.samples);
+        self.monitor.set(level);
+
+        if level.voice_active != self.voice_active {
+            self.voice_active = level.voice_active;
+            self.monitor
+                .send(AudioLevelEvent::VoiceActivity(level.voice_active));
+        }
+
+        self.frames += buffer.samples.len() / buffer.channels.max(1) as usize;
+        if self.frames >= self.update_frames {
+            self.frames %= self.update_frames;
+            self.monitor.send(AudioLevelEvent::Level(level));
+        }
+    }
+}
+
+impl Drop for LevelStage {
+    fn drop(&mut self) {
+        // Capture has stopped, so the audio is silent
+        self.monitor.set(AudioLevel::SILENCE);
+        if self.voice_active {
+            self.monitor.send(AudioLevelEvent::VoiceActivity(false));
+        }
+    }
+}
+
+/// Echo cancellation against the audio the speakers play
+struct EchoStage {
+    far_end: Arc<Mutex<mpsc::Receiver<AudioBuffer>>>,
+    /// Mono far-end samples at the delivered sample rate, oldest first
+    reference: VecDeque<f32>,
+    /// Far-end resampler and the sample rate it converts from
+    resampler: Option<(u32, ResampleStretcher)>,
+    sample_rate: u32,
+    /// One canceller per delivered channel
+    cancellers: Vec<EchoCanceller>,
+}
+
+impl EchoStage {
+    fn new(
+        far_end: Arc<Mutex<mpsc::Receiver<AudioBuffer>>>,
+        sample_rate: u32,
+        channels: u8,
+        filter_length: usize,
+    ) -> Self {
+        Self {
+            far_end,
+            reference: VecDeque::new(),
+            resampler: None,
+            sample_rate,
+            cancellers: (0..channels.max(1))
+                .map(|_| EchoCanceller::new(sample_rate, filter_length))
+                .collect(),
+        }
+    }
+
+    /// Remove the echo of the far end from every channel of `buffer`
+    fn process(&mut self, buffer: &mut AudioBuffer) {
+        self.receive_far_end();
+
+        let channels = self.cancellers.len();
+        let frames = buffer.samples.len() / channels;
+        // Missing far-end audio is silence, which leaves the audio as is
+        let take = frames.min(self.reference.len());
+        let far_end: Vec<f32> = self.reference.drain(..take).collect();
+
+        for (channel, canceller) in self.cancellers.iter_mut().enumerate() {
+            let near_end: Vec<f32> = buffer
+                .samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect();
+            let output = canceller.process(&far_end, &near_end);
+            for (i, sample) in output.into_iter().enumerate() {
+                buffer.samples[i * channels + channel] = sample;
+            }
+        }
+    }
+
+    /// Queue the far-end audio played since the last buffer
+    fn receive_far_end(&mut self) {
+        let Ok(mut far_end) = self.far_end.lock() else {
+            return;
+        };
+        while let Ok(buffer) = far_end.try_recv() {
+            if buffer.sample_rate == 0 {
+                continue;
+            }
+            let mono = AudioBuffer {
+                samples: convert_channels(buffer.samples, buffer.channels, 1),
+                channels: 1,
+                channel_map: None,
+                ..buffer
+            };
+            let samples = if mono.sample_rate == self.sample_rate {
+                mono.samples
+            } else {
+                // A new far-end sample rate restarts its resampling
+                if self.resampler.as_ref().map(|(rate, _)| *rate) != Some(mono.sample_rate) {
+                    self.resampler = Some((mono.sample_rate, ResampleStretcher::new()));
+                }
+                let (rate, resampler) = self.resampler.as_mut().expect("resampler set above");
+                let step = *rate as f32 / self.sample_rate as f32;
+                resampler.process(mono, step).samples
+            };
+            self.reference.extend(samples);
+        }
+
+        let max = self.sample_rate as usize * MAX_FAR_END_SECONDS;
+        if self.reference.len() > max {
+            self.reference.drain(..self.reference.len() - max);
+        }
+    }
+}
+
+/// Remix interleaved samples from `from` to `to` channels
+///
+/// Mono is copied to every channel and every channel is averaged into mono;
+/// otherwise channels are kept by position, with missing ones silent.
+fn convert_channels(samples: Vec<f32>, from: u8, to: u8) -> Vec<f32> {
+    let (from, to) = (from.max(1) as usize, to.max(1) as usize);
+    if from == to {
+        return samples;
+    }
+
+    samples
+        .chunks_exact(from)
+        .flat_map(|frame| {
+            (0..to).map(move |channel| match (from, to) {
+                (1, _) => frame[0],
+                (_, 1) => frame.iter().sum::<f32>() / from as f32,
+                _ => frame.get(channel).copied().unwrap_or(0.0),
+            })
+        })
+        .collect()
+}
+
+/// Capture thread reading from an [`AudioSource`]
+#[derive(Debug)]
+struct CaptureSession {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CaptureSession {
+    fn start(
+        source: Box<dyn AudioSource>,
+        stages: Stages,
+        sender: mpsc::Sender<AudioBuffer>,
+    ) -> Result<Self, CaptureError> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = std::thread::Builder::new()
+            .name("audio-capture".to_string())
+            .spawn(move || capture_loop(source, stages, sender, thread_stop))
+            .map_err(|_| CaptureError::CaptureFailure)?;
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn capture_loop(
+    mut source: Box<dyn AudioSource>,
+    mut stages: Stages,
+    sender: mpsc::Sender<AudioBuffer>,
+    stop: Arc<AtomicBool>,
+) {
+    while !stop.load(Ordering::Acquire) {
+        let samples = match source.read() {
+            Ok(samples) if !samples.is_empty() => samples,
+            _ => break,
+        };
+        let Some(buffer) = stages.process(samples) else {
+            continue;
+        };
+        if sender.blocking_send(buffer).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Source producing `periods` periods of a ramp, then ending
+    struct MockMicrophone {
+        sample_rate: u32,
+        channels: u8,
+        period: usize,
+        periods: usize,
+    }
+
+    impl AudioSource for MockMicrophone {
+        fn sample_rate(&self) -> u32 {
+            self.sample_rate
+        }
+
+        fn channels(&self) -> u8 {
+            self.channels
+        }
+
+        fn read(&mut self) -> Result<Vec<f32>, CaptureError> {
+            if self.periods == 0 {
+                return Ok(Vec::new());
+            }
+            self.periods -= 1;
+            let samples = self.period * self.channels as usize;
+            Ok((0..samples).map(|i| i as f32 / samples as f32).collect())
+        }
+    }
+
+    fn microphone(sample_rate: u32, channels: u8) -> Box<MockMicrophone> {
+        // 10 ms periods for one second
+        Box::new(MockMicrophone {
+            sample_rate,
+            channels,
+            period: sample_rate as usize / 100,
+            periods: 100,
+        })
+    }
+
+    fn capture(sample_rate: u32, channels: u8) -> MicrophoneCapture {
+        let constraints = AudioConstraints {
+            sample_rate: Some(sample_rate),
+            channels: Some(channels),
+        };
+        MicrophoneCapture::new("mic-001".to_string(), constraints).unwrap()
+    }
+
+    async fn collect(mut receiver: mpsc::Receiver<AudioBuffer>) -> Vec<AudioBuffer> {
+        let mut buffers = Vec::new();
+        while let Some(buffer) = receiver.recv().await {
+            buffers.push(buffer);
+        }
+        buffers
+    }
+
+    fn assert_contiguous(buffers: &[AudioBuffer]) {
+        for pair in buffers.windows(2) {
+            assert!(pair[1].timestamp > pair[0].timestamp);
+            let gap = (pair[1].timestamp.as_secs_f64()
+                - (pair[0].timestamp + pair[0].duration).as_secs_f64())
+            .abs();
+            assert!(gap < 1e-6, "gap of {}s between buffers", gap);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_buffers_have_requested_format() {
+        let capture = capture(48000, 2);
+        let receiver = capture.start_source(microphone(48000, 2)).unwrap();
+
+        let buffers = collect(receiver).await;
+        assert_eq!(buffers.len(), 100);
+        for buffer in &buffers {
+            assert_eq!(buffer.sample_rate, 48000);
+            assert_eq!(buffer.channels, 2);
+            assert_eq!(buffer.samples.len(), 960);
+            assert_eq!(buffer.duration, Duration::from_millis(10));
+        }
+        assert_eq!(buffers[0].timestamp, Duration::ZERO);
+        assert_contiguous(&buffers);
+    }
+
+    #[tokio::test]
+    async fn test_resampled_to_requested_rate() {
+        let capture = capture(48000, 1);
+        let receiver = capture.start_source(microphone(44100, 2)).unwrap();
+
+        let buffers = collect(receiver).await;
+        let frames: usize = buffers.iter().map(|buffer| buffer.samples.len()).sum();
+        // One second of audio, give or take the frame interpolation holds back
+        assert!((47990..=48000).contains(&frames), "{} frames", frames);
+        for buffer in &buffers {
+            assert_eq!(buffer.sample_rate, 48000);
+            assert_eq!(buffer.channels, 1);
+        }
+        assert_contiguous(&buffers);
+    }
+
+    #[tokio::test]
+    async fn test_unconstrained_format_is_the_devices() {
+        let constraints = AudioConstraints {
+            sample_rate: None,
+            channels: None,
+        };
+        let capture = MicrophoneCapture::new("mic-001".to_string(), constraints).unwrap();
+        let receiver = capture.start_source(microphone(16000, 1)).unwrap();
+
+        let buffers = collect(receiver).await;
+        assert_eq!(buffers[0].sample_rate, 16000);
+        assert_eq!(buffers[0].channels, 1);
+    }
+
+    #[tokio::test]
+    async fn test_echo_of_far_end_is_reduced() {
+        let (speaker, far_end) = mpsc::channel(128);
+        let capture = capture(16000, 1).with_echo_cancellation(far_end);
+
+        // The microphone picks up exactly what the speaker plays, at half
+        // the volume, and the speaker plays at twice the sample rate
+        let tone = |rate: u32, gain: f32| -> Vec<f32> {
+            (0..rate / 100)
+                .map(|i| gain * (i as f32 * 440.0 * std::f32::consts::TAU / rate as f32).sin())
+                .collect()
+        };
+        for period in 0..100u64 {
+            let buffer = AudioBuffer::new(
+                AudioFormat::F32LE,
+                32000,
+                1,
+                tone(32000, 1.0),
+                Duration::from_millis(period * 10),
+            );
+            speaker.try_send(buffer).unwrap();
+        }
+
+        struct Echo(usize, Vec<f32>);
+        impl AudioSource for Echo {
+            fn sample_rate(&self) -> u32 {
+                16000
+            }
+            fn channels(&self) -> u8 {
+                1
+            }
+            fn read(&mut self) -> Result<Vec<f32>, CaptureError> {
+                if self.0 == 0 {
+                    return Ok(Vec::new());
+                }
+                self.0 -= 1;
+                Ok(self.1.clone())
+            }
+        }
+
+        let echo = tone(16000, 0.5);
+        let energy = |samples: &[f32]| samples.iter().map(|s| s * s).sum::<f32>();
+        let captured = energy(&echo);
+        let receiver = capture.start_source(Box::new(Echo(100, echo))).unwrap();
+        let buffers = collect(receiver).await;
+        let remaining = energy(&buffers.last().unwrap().samples);
+        assert!(
+            remaining < captured * 0.01,
+            "echo energy {} of {}",
+            remaining,
+            captured
+        );
+    }
+
+    #[tokio::test]
+    async fn test_level_events() {
+        let capture = capture(48000, 1).with_level_smoothing(Duration::ZERO);
+        let events = capture.level_events();
+        let receiver = capture.start_source(microphone(48000, 1)).unwrap();
+
+        let buffers = collect(receiver).await;
+        assert_eq!(buffers.len(), 100);
+        capture.stop().unwrap();
+
+        // One second of audio, then capture stopping
+        let events: Vec<AudioLevelEvent> = tokio::time::timeout(
+            Duration::from_secs(5),
+            futures_util::StreamExt::collect(futures_util::StreamExt::take(events, 12)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(events[0], AudioLevelEvent::VoiceActivity(true));
+        assert_eq!(events[11], AudioLevelEvent::VoiceActivity(false));
+        for event in &events[1..11] {
+            // The ramp's RMS is 1/√3 and its peak almost 1
+            let AudioLevelEvent::Level(level) = event else {
+                panic!("expected a level update, got {:?}", event);
+            };
+            assert!((level.rms_dbfs + 4.77).abs() < 0.1, "{:?}", level);
+            assert!(level.peak_dbfs > -0.1, "{:?}", level);
+            assert!(level.voice_active);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_level_is_silence_once_stopped() {
+        let capture = capture(16000, 1);
+        assert_eq!(capture.get_level(), AudioLevel::SILENCE);
+
+        let mut receiver = capture.start_source(microphone(16000, 1)).unwrap();
+        receiver.recv().await.unwrap();
+        assert!(capture.get_level().rms_dbfs > AudioLevel::MIN_DBFS);
+
+        // Unblock the capture thread so it can stop
+        drop(receiver);
+        capture.stop().unwrap();
+        assert_eq!(capture.get_level(), AudioLevel::SILENCE);
+    }
+
+    #[test]
+    fn test_convert_channels() {
+        assert_eq!(
+            convert_channels(vec![0.5, 1.0], 1, 2),
+            vec![0.5, 0.5, 1.0, 1.0]
+        );
+        assert_eq!(
+            convert_channels(vec![0.5, 1.0, 0.0, 1.0], 2, 1),
+            vec![0.75, 0.5]
+        );
+        assert_eq!(convert_channels(vec![0.1, 0.2, 0.3], 3, 2), vec![0.1, 0.2]);
+        assert_eq!(convert_channels(vec![0.1, 0.2], 2, 3), vec![0.1, 0.2, 0.0]);
+    }
+}
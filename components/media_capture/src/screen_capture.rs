@@ -3,32 +3,290 @@
 //! Provides screen capture capabilities with platform-specific implementations.
 
 use crate::{CaptureConstraints, CaptureError};
-use cortenbrowser_shared_types::VideoFrame;
+use cortenbrowser_shared_types::{MediaStreamTrack, VideoFrame};
+use std::ffi::OsString;
+use std::fmt;
 use tokio::sync::mpsc;
 
+#[cfg(target_os = "linux")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(target_os = "linux")]
+use std::sync::{Arc, Mutex};
+#[cfg(target_os = "linux")]
+use std::thread::JoinHandle;
+#[cfg(target_os = "linux")]
+use std::time::{Duration, Instant};
+
+/// Frame rate used when the constraints do not request one
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const DEFAULT_FRAME_RATE: f32 = 30.0;
+
+/// Largest size of a [`CaptureSource`] thumbnail
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const THUMBNAIL_SIZE: (u32, u32) = (320, 180);
+
+/// Display server a screen is captured from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayServer {
+    /// X11 server, captured with `xcb_get_image`
+    X11,
+    /// Wayland compositor, captured with the `wlr-screencopy` protocol
+    Wayland,
+}
+
+impl DisplayServer {
+    /// Detects the display server of the current session
+    ///
+    /// Wayland is preferred when `WAYLAND_DISPLAY` is set, otherwise X11 is
+    /// used when `DISPLAY` is set.
+    pub fn detect() -> Option<Self> {
+        Self::from_env(
+            std::env::var_os("WAYLAND_DISPLAY"),
+            std::env::var_os("DISPLAY"),
+        )
+    }
+
+    fn from_env(wayland_display: Option<OsString>, display: Option<OsString>) -> Option<Self> {
+        let set = |var: &Option<OsString>| var.as_ref().is_some_and(|value| !value.is_empty());
+        if set(&wayland_display) {
+            Some(DisplayServer::Wayland)
+        } else if set(&display) {
+            Some(DisplayServer::X11)
+        } else {
+            None
+        }
+    }
+}
+
+/// Rectangle in pixels
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::Rect;
+///
+/// let screen = Rect::new(0, 0, 1920, 1080);
+/// let region = Rect::new(1600, -100, 640, 480);
+/// assert_eq!(region.intersection(&screen), Some(Rect::new(1600, 0, 320, 380)));
+/// assert_eq!(Rect::new(2000, 0, 100, 100).intersection(&screen), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    /// Horizontal position of the left edge
+    pub x: i32,
+    /// Vertical position of the top edge
+    pub y: i32,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+}
+
+impl Rect {
+    /// Creates a rectangle from its top-left corner and size
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Gets the part of this rectangle inside `other`
+    ///
+    /// Returns `None` if the rectangles do not overlap.
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let (left, top) = (self.x.max(other.x), self.y.max(other.y));
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        (right > i64::from(left) && bottom > i64::from(top)).then(|| Rect {
+            x: left,
+            y: top,
+            width: (right - i64::from(left)) as u32,
+            height: (bottom - i64::from(top)) as u32,
+        })
+    }
+
+    fn right(&self) -> i64 {
+        i64::from(self.x) + i64::from(self.width)
+    }
+
+    fn bottom(&self) -> i64 {
+        i64::from(self.y) + i64::from(self.height)
+    }
+}
+
+/// Screen that can be captured
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayInfo {
+    /// Identifier to pass as `display_id` to [`ScreenCapture::new`]
+    pub id: u32,
+    /// Position of the display in the desktop and its size in pixels
+    pub bounds: Rect,
+    /// Ratio of the display's pixels to logical pixels, such as 2.0 on a
+    /// high-density display
+    pub scale_factor: f64,
+}
+
+/// Kind of surface a [`CaptureSource`] shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureSourceKind {
+    /// A whole display
+    Screen,
+    /// A top-level application window
+    Window,
+}
+
+/// Screen or window that can be captured
+///
+/// Listed by [`ScreenCapture::enumerate_sources`] for the user to pick
+/// from, as with getDisplayMedia.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaptureSource {
+    /// Whether the source is a whole screen or a window
+    pub kind: CaptureSourceKind,
+    /// Identifier to pass to [`ScreenCapture::start_capture`], such as
+    /// `screen:0` or `window:12582919`
+    pub id: String,
+    /// Title of the window, or a name for the screen
+    pub title: String,
+    /// Preview of the source's contents, at most 320x180 pixels, if it
+    /// could be grabbed
+    pub thumbnail: Option<VideoFrame>,
+}
+
+/// Options for [`ScreenCapture::start_capture`]
+///
+/// By default the cursor is drawn into frames and the whole source is
+/// captured.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::{CaptureOptions, Rect};
+///
+/// let options = CaptureOptions {
+///     crop: Some(Rect::new(0, 0, 800, 600)),
+///     ..Default::default()
+/// };
+/// assert!(options.capture_cursor);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureOptions {
+    /// Whether the cursor is drawn into frames
+    pub capture_cursor: bool,
+    /// Part of the source to capture, in pixels from its top-left corner
+    pub crop: Option<Rect>,
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            capture_cursor: true,
+            crop: None,
+        }
+    }
+}
+
+/// Parsed [`CaptureSource::id`]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SourceId {
+    /// Display, numbered as for [`ScreenCapture::new`]
+    Screen(u32),
+    /// Window, by its display server identifier
+    Window(u32),
+}
+
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+impl SourceId {
+    fn parse(id: &str) -> Option<Self> {
+        let (kind, number) = id.split_once(':')?;
+        let number = number.parse().ok()?;
+        match kind {
+            "screen" => Some(SourceId::Screen(number)),
+            "window" => Some(SourceId::Window(number)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SourceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceId::Screen(id) => write!(f, "screen:{}", id),
+            SourceId::Window(id) => write!(f, "window:{}", id),
+        }
+    }
+}
+
+/// Stream of captured screen frames
+///
+/// Returned by [`ScreenCapture::start`] and [`ScreenCapture::start_capture`].
+/// Frames are delivered in `PixelFormat::RGBA32` until the capture is
+/// stopped or fails, or the captured window is closed, after which
+/// [`CaptureStream::next_frame`] returns `None`.
+#[derive(Debug)]
+pub struct CaptureStream {
+    receiver: mpsc::Receiver<VideoFrame>,
+}
+
+impl CaptureStream {
+    /// Waits for the next captured frame
+    ///
+    /// Returns `None` once capture has ended.
+    pub async fn next_frame(&mut self) -> Option<VideoFrame> {
+        self.receiver.recv().await
+    }
+
+    /// Converts the stream into its underlying channel receiver
+    pub fn into_receiver(self) -> mpsc::Receiver<VideoFrame> {
+        self.receiver
+    }
+
+    /// Converts the stream into a video track labelled `label`
+    ///
+    /// Capture stops once the track and all its clones have been stopped,
+    /// and the track ends when capture ends, such as when a captured
+    /// window is closed. Must be called within a Tokio runtime.
+    pub fn into_track(self, label: impl Into<String>) -> MediaStreamTrack {
+        crate::tracks::video_track(label.into(), self.receiver)
+    }
+}
+
 /// Screen capture interface
 ///
-/// Captures video frames from the screen or specific windows.
-/// Platform-specific implementation required for actual capture.
+/// Captures video frames from a screen. On Linux the display server is
+/// detected from `WAYLAND_DISPLAY` and `DISPLAY`: Wayland compositors are
+/// captured through `wlr-screencopy`, falling back to X11 when the
+/// compositor does not support it and an X server is available, and X11
+/// screens through `xcb_get_image`. Frames are delivered as RGBA at the
+/// constrained frame rate (30 fps by default), cropped from the top-left
+/// corner to the constrained size, or to the region of a capture created
+/// with [`ScreenCapture::capture_region`]. Windows can be captured on X11
+/// by picking them from [`ScreenCapture::enumerate_sources`]. Other
+/// platforms are not yet supported.
 ///
 /// # Examples
 ///
 /// ```no_run
-/// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints};
+/// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints, ConstrainRange};
 ///
 /// #[tokio::main]
 /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
 ///     let constraints = CaptureConstraints {
-///         width: Some(1920),
-///         height: Some(1080),
-///         frame_rate: Some(30.0),
+///         width: ConstrainRange::ideal(1920),
+///         height: ConstrainRange::ideal(1080),
+///         frame_rate: ConstrainRange::ideal(30.0),
+///         ..Default::default()
 ///     };
 ///
-///     let capture = ScreenCapture::new(constraints)?;
-///     let mut receiver = capture.start().await?;
+///     let capture = ScreenCapture::new(0, constraints)?;
+///     let mut stream = capture.start().await?;
 ///
 ///     // Receive frames
-///     while let Some(frame) = receiver.recv().await {
+///     while let Some(frame) = stream.next_frame().await {
 ///         println!("Received frame: {}x{}", frame.width, frame.height);
 ///     }
 ///
@@ -38,9 +296,14 @@ use tokio::sync::mpsc;
 /// ```
 #[derive(Debug)]
 pub struct ScreenCapture {
-    #[allow(dead_code)] // Will be used in platform-specific implementation
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    display_id: u32,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
     constraints: CaptureConstraints,
-    // Platform-specific fields will be added
+    region: Option<Rect>,
+    display_server: Option<DisplayServer>,
+    #[cfg(target_os = "linux")]
+    session: Mutex<Option<CaptureSession>>,
 }
 
 impl ScreenCapture {
@@ -48,54 +311,343 @@ impl ScreenCapture {
     ///
     /// # Arguments
     ///
+    /// * `display_id` - Screen to capture: the X11 screen number, or the
+    ///   index of the Wayland output in the order the compositor announces them
     /// * `constraints` - Capture constraints (resolution, frame rate)
     ///
     /// # Examples
     ///
     /// ```
-    /// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints};
+    /// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints, ConstrainRange};
     ///
     /// let constraints = CaptureConstraints {
-    ///     width: Some(1920),
-    ///     height: Some(1080),
-    ///     frame_rate: Some(30.0),
+    ///     width: ConstrainRange::ideal(1920),
+    ///     height: ConstrainRange::ideal(1080),
+    ///     frame_rate: ConstrainRange::ideal(30.0),
+    ///     ..Default::default()
     /// };
     ///
-    /// let capture = ScreenCapture::new(constraints).unwrap();
+    /// let capture = ScreenCapture::new(0, constraints).unwrap();
+    /// ```
+    pub fn new(display_id: u32, constraints: CaptureConstraints) -> Result<Self, CaptureError> {
+        Ok(Self {
+            display_id,
+            constraints,
+            region: None,
+            display_server: DisplayServer::detect(),
+            #[cfg(target_os = "linux")]
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Creates a screen capture of a region of a display
+    ///
+    /// Frames are cropped to `region`, given in pixels from the top-left
+    /// corner of the display. A region reaching past the edges of the
+    /// display is clamped to them, so frames are only the size of the
+    /// visible part. The `width` and `height` constraints do not apply; the
+    /// frame rate constraint does.
+    ///
+    /// # Arguments
+    ///
+    /// * `display_id` - Screen to capture, as for [`ScreenCapture::new`]
+    /// * `region` - Part of the screen to capture
+    /// * `constraints` - Capture constraints (frame rate)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_capture::{CaptureConstraints, ConstrainRange, Rect, ScreenCapture};
+    ///
+    /// let constraints = CaptureConstraints {
+    ///     frame_rate: ConstrainRange::ideal(15.0),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let capture = ScreenCapture::capture_region(0, Rect::new(100, 100, 640, 480), constraints)
+    ///     .unwrap();
+    /// assert_eq!(capture.region(), Some(Rect::new(100, 100, 640, 480)));
+    /// ```
+    pub fn capture_region(
+        display_id: u32,
+        region: Rect,
+        constraints: CaptureConstraints,
+    ) -> Result<Self, CaptureError> {
+        Ok(Self {
+            region: Some(region),
+            ..Self::new(display_id, constraints)?
+        })
+    }
+
+    /// Lists the displays that can be captured
+    ///
+    /// On Linux these are the Wayland outputs, in the order the compositor
+    /// announces them, or the X11 screens, which report a scale factor of
+    /// 1.0. The list is empty if no display server can be reached or on
+    /// other platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::ScreenCapture;
+    ///
+    /// for display in ScreenCapture::list_displays() {
+    ///     println!(
+    ///         "Display {}: {}x{} at {}x",
+    ///         display.id, display.bounds.width, display.bounds.height, display.scale_factor
+    ///     );
+    /// }
+    /// ```
+    pub fn list_displays() -> Vec<DisplayInfo> {
+        #[cfg(target_os = "linux")]
+        {
+            list_screens(DisplayServer::detect()).unwrap_or_default()
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Vec::new()
+        }
+    }
+
+    /// Lists the screens and windows that can be captured
+    ///
+    /// Screens come first, in the order of [`ScreenCapture::list_displays`],
+    /// followed on X11 by the top-level windows the window manager lists in
+    /// `_NET_CLIENT_LIST`. Wayland compositors do not let other clients
+    /// capture windows, so only their outputs are listed. Each source has a
+    /// thumbnail unless it could not be grabbed, such as a minimized window.
+    /// The list is empty if no display server can be reached or on other
+    /// platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::{CaptureSourceKind, ScreenCapture};
+    ///
+    /// for source in ScreenCapture::enumerate_sources() {
+    ///     let kind = match source.kind {
+    ///         CaptureSourceKind::Screen => "screen",
+    ///         CaptureSourceKind::Window => "window",
+    ///     };
+    ///     println!("{} ({}): {}", source.id, kind, source.title);
+    /// }
     /// ```
-    pub fn new(constraints: CaptureConstraints) -> Result<Self, CaptureError> {
-        Ok(Self { constraints })
+    pub fn enumerate_sources() -> Vec<CaptureSource> {
+        #[cfg(target_os = "linux")]
+        {
+            Self::enumerate_with_backend(&PlatformSources(DisplayServer::detect()))
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Vec::new()
+        }
+    }
+
+    /// Lists the sources of `backend` with their thumbnails
+    #[cfg(target_os = "linux")]
+    pub(crate) fn enumerate_with_backend(backend: &dyn SourceBackend) -> Vec<CaptureSource> {
+        let mut sources = backend.sources().unwrap_or_default();
+        for source in &mut sources {
+            source.thumbnail = SourceId::parse(&source.id)
+                .and_then(|id| backend.open(id, false).ok())
+                .and_then(|mut capture| capture.grab().ok().flatten())
+                .map(|frame| thumbnail(&frame, THUMBNAIL_SIZE));
+        }
+        sources
+    }
+
+    /// Returns the display server detected when the capture was created
+    pub fn display_server(&self) -> Option<DisplayServer> {
+        self.display_server
+    }
+
+    /// Returns the captured region, if only part of the display is captured
+    pub fn region(&self) -> Option<Rect> {
+        self.region
     }
 
     /// Starts screen capture
     ///
-    /// Returns a receiver channel that will receive video frames.
-    /// Platform-specific implementation required.
+    /// Returns a stream that will receive video frames.
+    /// Starting again replaces the previous capture session.
+    ///
+    /// # Errors
+    ///
+    /// On Linux, returns `DeviceNotFound` if no display server is detected,
+    /// it cannot be connected to, or it has no screen `display_id`,
+    /// `Overconstrained` naming `region` if the region lies entirely outside
+    /// the screen, and `CaptureFailure` if the screen cannot be captured.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints};
+    /// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints, ConstrainRange};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let constraints = CaptureConstraints {
-    ///         width: Some(1280),
-    ///         height: Some(720),
-    ///         frame_rate: Some(15.0),
+    ///         width: ConstrainRange::ideal(1280),
+    ///         height: ConstrainRange::ideal(720),
+    ///         frame_rate: ConstrainRange::ideal(15.0),
+    ///         ..Default::default()
+    ///     };
+    ///
+    ///     let capture = ScreenCapture::new(0, constraints)?;
+    ///     let stream = capture.start().await?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn start(&self) -> Result<CaptureStream, CaptureError> {
+        #[cfg(target_os = "linux")]
+        {
+            self.start_session(|| self.open_source(), self.region, "region")
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            // Platform-specific implementation will be added
+            // For now, create a channel and return the receiver (mock implementation)
+            let (_, rx) = mpsc::channel(32);
+            Ok(CaptureStream { receiver: rx })
+        }
+    }
+
+    /// Starts capturing a screen or window
+    ///
+    /// `source_id` is the [`CaptureSource::id`] of a source listed by
+    /// [`ScreenCapture::enumerate_sources`]. Frames are the size of the
+    /// source, or of `options.crop` clamped to it, so the `width` and
+    /// `height` constraints do not apply; the frame rate constraint does.
+    /// No frames are delivered while a captured window is minimized, and
+    /// the stream ends when the window is closed, or resized so that the
+    /// crop no longer overlaps it. Starting again replaces the previous
+    /// capture session.
+    ///
+    /// # Arguments
+    ///
+    /// * `source_id` - Identifier of the screen or window to capture
+    /// * `options` - Whether to draw the cursor, and the part to capture
+    ///
+    /// # Errors
+    ///
+    /// On Linux, returns `DeviceNotFound` if no display server is detected
+    /// or it has no source `source_id`, `Overconstrained` naming `crop` if
+    /// the crop lies entirely outside the source, and `CaptureFailure` if
+    /// the source cannot be captured.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_capture::{
+    ///     CaptureConstraints, CaptureOptions, CaptureSourceKind, Rect, ScreenCapture,
+    /// };
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let sources = ScreenCapture::enumerate_sources();
+    ///     let window = sources
+    ///         .iter()
+    ///         .find(|source| source.kind == CaptureSourceKind::Window)
+    ///         .ok_or("no window to capture")?;
+    ///
+    ///     let capture = ScreenCapture::new(0, CaptureConstraints::default())?;
+    ///     let options = CaptureOptions {
+    ///         capture_cursor: false,
+    ///         crop: Some(Rect::new(0, 0, 640, 480)),
     ///     };
+    ///     let mut stream = capture.start_capture(&window.id, options).await?;
     ///
-    ///     let capture = ScreenCapture::new(constraints)?;
-    ///     let receiver = capture.start().await?;
+    ///     // Ends when the window is closed
+    ///     while let Some(frame) = stream.next_frame().await {
+    ///         println!("Received frame: {}x{}", frame.width, frame.height);
+    ///     }
     ///
     ///     Ok(())
     /// }
     /// ```
-    pub async fn start(&self) -> Result<mpsc::Receiver<VideoFrame>, CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, create a channel and return the receiver (mock implementation)
-        let (_, rx) = mpsc::channel(32);
-        Ok(rx)
+    pub async fn start_capture(
+        &self,
+        source_id: &str,
+        options: CaptureOptions,
+    ) -> Result<CaptureStream, CaptureError> {
+        #[cfg(target_os = "linux")]
+        {
+            self.start_with_backend(&PlatformSources(self.display_server), source_id, options)
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            let _ = (source_id, options);
+            let (_, rx) = mpsc::channel(32);
+            Ok(CaptureStream { receiver: rx })
+        }
+    }
+
+    /// Starts capturing source `source_id` of `backend`
+    #[cfg(target_os = "linux")]
+    pub(crate) fn start_with_backend(
+        &self,
+        backend: &dyn SourceBackend,
+        source_id: &str,
+        options: CaptureOptions,
+    ) -> Result<CaptureStream, CaptureError> {
+        let source = SourceId::parse(source_id).ok_or(CaptureError::DeviceNotFound)?;
+        self.start_session(
+            || backend.open(source, options.capture_cursor),
+            options.crop,
+            "crop",
+        )
+    }
+
+    /// Replace the capture session with one grabbing from `open`'s source
+    ///
+    /// Frames are cropped to `crop`, which is checked against the source
+    /// first and reported as `constraint` if it lies outside.
+    #[cfg(target_os = "linux")]
+    fn start_session(
+        &self,
+        open: impl FnOnce() -> Result<Box<dyn ScreenSource>, CaptureError>,
+        crop: Option<Rect>,
+        constraint: &str,
+    ) -> Result<CaptureStream, CaptureError> {
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|_| CaptureError::CaptureFailure)?;
+        if let Some(mut previous) = session.take() {
+            previous.stop();
+        }
+
+        let mut source = open()?;
+        if let Some(crop) = crop {
+            // Check the crop against the source before streaming
+            let frame = source.grab()?;
+            if frame.is_some_and(|frame| visible_region(crop, &frame).is_none()) {
+                return Err(CaptureError::Overconstrained {
+                    constraint: constraint.to_string(),
+                });
+            }
+        }
+        let (tx, rx) = mpsc::channel(32);
+        *session = Some(CaptureSession::start(source, &self.constraints, crop, tx)?);
+        Ok(CaptureStream { receiver: rx })
+    }
+
+    /// Connect to the detected display server
+    #[cfg(target_os = "linux")]
+    fn open_source(&self) -> Result<Box<dyn ScreenSource>, CaptureError> {
+        // Regions are cropped from the whole screen
+        let size = match self.region {
+            Some(_) => (None, None),
+            None => (
+                self.constraints.width.preferred(),
+                self.constraints.height.preferred(),
+            ),
+        };
+        open_screen(self.display_server, self.display_id, size, false)
     }
 
     /// Stops screen capture
@@ -103,20 +655,708 @@ impl ScreenCapture {
     /// # Examples
     ///
     /// ```
-    /// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints};
+    /// use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints, ConstrainRange};
     ///
     /// let constraints = CaptureConstraints {
-    ///     width: Some(1920),
-    ///     height: Some(1080),
-    ///     frame_rate: Some(30.0),
+    ///     width: ConstrainRange::ideal(1920),
+    ///     height: ConstrainRange::ideal(1080),
+    ///     frame_rate: ConstrainRange::ideal(30.0),
+    ///     ..Default::default()
     /// };
     ///
-    /// let capture = ScreenCapture::new(constraints).unwrap();
+    /// let capture = ScreenCapture::new(0, constraints).unwrap();
     /// capture.stop().unwrap();
     /// ```
     pub fn stop(&self) -> Result<(), CaptureError> {
-        // Platform-specific implementation will be added
-        // For now, just return Ok (mock implementation)
+        #[cfg(target_os = "linux")]
+        {
+            let session = self
+                .session
+                .lock()
+                .map_err(|_| CaptureError::CaptureFailure)?
+                .take();
+            if let Some(mut capture) = session {
+                capture.stop();
+            }
+        }
+
         Ok(())
     }
 }
+
+/// A display server connection that can grab the current screen contents
+#[cfg(target_os = "linux")]
+pub(crate) trait ScreenSource: Send {
+    /// Grab one RGBA frame; the timestamp is filled in by the caller
+    ///
+    /// Returns `None` while there is nothing to show, such as a minimized
+    /// window, and an error once the source is gone for good.
+    fn grab(&mut self) -> Result<Option<VideoFrame>, CaptureError>;
+}
+
+/// Lists and opens the screens and windows of a display server
+#[cfg(target_os = "linux")]
+pub(crate) trait SourceBackend {
+    /// List the sources, without thumbnails
+    fn sources(&self) -> Result<Vec<CaptureSource>, CaptureError>;
+
+    /// Open a source at its full size, drawing the cursor in if `cursor`
+    fn open(&self, source: SourceId, cursor: bool) -> Result<Box<dyn ScreenSource>, CaptureError>;
+}
+
+/// Sources of the session's display server
+#[cfg(target_os = "linux")]
+struct PlatformSources(Option<DisplayServer>);
+
+#[cfg(target_os = "linux")]
+impl SourceBackend for PlatformSources {
+    fn sources(&self) -> Result<Vec<CaptureSource>, CaptureError> {
+        let mut sources: Vec<CaptureSource> = list_screens(self.0)?
+            .into_iter()
+            .map(|display| CaptureSource {
+                kind: CaptureSourceKind::Screen,
+                id: SourceId::Screen(display.id).to_string(),
+                title: format!("Screen {}", display.id + 1),
+                thumbnail: None,
+            })
+            .collect();
+        if self.0 == Some(DisplayServer::X11) {
+            sources.extend(crate::x11::list_windows().unwrap_or_default());
+        }
+        Ok(sources)
+    }
+
+    fn open(&self, source: SourceId, cursor: bool) -> Result<Box<dyn ScreenSource>, CaptureError> {
+        match source {
+            SourceId::Screen(id) => open_screen(self.0, id, (None, None), cursor),
+            SourceId::Window(window) if self.0 == Some(DisplayServer::X11) => {
+                crate::x11::X11ScreenCapture::open_window(window, cursor)
+                    .map(|capture| Box::new(capture) as Box<dyn ScreenSource>)
+            }
+            SourceId::Window(_) => Err(CaptureError::DeviceNotFound),
+        }
+    }
+}
+
+/// Describe the screens of `display_server`
+#[cfg(target_os = "linux")]
+fn list_screens(display_server: Option<DisplayServer>) -> Result<Vec<DisplayInfo>, CaptureError> {
+    match display_server {
+        Some(DisplayServer::Wayland) => crate::wayland::list_outputs().or_else(|e| {
+            // As when capturing, fall back to XWayland
+            if std::env::var_os("DISPLAY").is_some() {
+                crate::x11::list_screens()
+            } else {
+                Err(e)
+            }
+        }),
+        Some(DisplayServer::X11) => crate::x11::list_screens(),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Connect to `display_server` to capture screen `display_id`
+///
+/// `size` limits the captured region, taken from the top-left corner of
+/// the screen.
+#[cfg(target_os = "linux")]
+fn open_screen(
+    display_server: Option<DisplayServer>,
+    display_id: u32,
+    size: (Option<u32>, Option<u32>),
+    cursor: bool,
+) -> Result<Box<dyn ScreenSource>, CaptureError> {
+    match display_server {
+        Some(DisplayServer::Wayland) => {
+            match crate::wayland::WaylandScreenCapture::open(display_id, size, cursor) {
+                Ok(capture) => Ok(Box::new(capture)),
+                // Compositors without wlr-screencopy may still run XWayland
+                Err(CaptureError::CaptureFailure) if std::env::var_os("DISPLAY").is_some() => {
+                    crate::x11::X11ScreenCapture::open(display_id, size, cursor)
+                        .map(|capture| Box::new(capture) as Box<dyn ScreenSource>)
+                }
+                Err(e) => Err(e),
+            }
+        }
+        Some(DisplayServer::X11) => crate::x11::X11ScreenCapture::open(display_id, size, cursor)
+            .map(|capture| Box::new(capture) as Box<dyn ScreenSource>),
+        None => Err(CaptureError::DeviceNotFound),
+    }
+}
+
+/// Capture thread grabbing frames from a [`ScreenSource`] at a fixed rate
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct CaptureSession {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(target_os = "linux")]
+impl CaptureSession {
+    fn start(
+        source: Box<dyn ScreenSource>,
+        constraints: &CaptureConstraints,
+        region: Option<Rect>,
+        sender: mpsc::Sender<VideoFrame>,
+    ) -> Result<Self, CaptureError> {
+        let frame_rate = constraints
+            .frame_rate
+            .preferred()
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .unwrap_or(DEFAULT_FRAME_RATE);
+        let interval = Duration::from_secs_f32(1.0 / frame_rate);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = std::thread::Builder::new()
+            .name("screen-capture".to_string())
+            .spawn(move || capture_loop(source, interval, region, sender, thread_stop))
+            .map_err(|_| CaptureError::CaptureFailure)?;
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            // Wake the thread if it is waiting for the next frame
+            thread.thread().unpark();
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for CaptureSession {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn capture_loop(
+    mut source: Box<dyn ScreenSource>,
+    interval: Duration,
+    region: Option<Rect>,
+    sender: mpsc::Sender<VideoFrame>,
+    stop: Arc<AtomicBool>,
+) {
+    let start = Instant::now();
+    let mut next = start;
+    while !stop.load(Ordering::Acquire) {
+        let now = Instant::now();
+        if now < next {
+            std::thread::park_timeout(next - now);
+            continue;
+        }
+
+        let mut frame = match source.grab() {
+            Ok(Some(frame)) => frame,
+            // Wait for the source to show again, such as a minimized window
+            Ok(None) => {
+                next = (next + interval).max(Instant::now());
+                continue;
+            }
+            // The source is gone, such as a closed window
+            Err(_) => break,
+        };
+        if let Some(region) = region {
+            // The source may have shrunk away from the region since
+            let Some(visible) = visible_region(region, &frame) else {
+                break;
+            };
+            frame = crop_rgba(&frame, visible);
+        }
+        frame.timestamp = now - start;
+        frame.duration = Some(interval);
+        if sender.blocking_send(frame).is_err() {
+            break;
+        }
+
+        // Skip frames rather than bursting after a slow grab
+        next = (next + interval).max(Instant::now());
+    }
+}
+
+/// Byte order of 32-bit pixels in a captured image
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteOrder {
+    /// Blue, green, red, alpha/padding
+    Bgra,
+    /// Red, green, blue, alpha/padding
+    Rgba,
+}
+
+/// Convert a 32-bit image with `stride` bytes per row to packed opaque RGBA
+///
+/// Only the top-left `width` x `height` pixels are kept, and rows are
+/// reversed when `y_invert` is set. Returns `None` if `data` is too short.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub(crate) fn to_rgba(
+    data: &[u8],
+    stride: usize,
+    width: u32,
+    height: u32,
+    order: ByteOrder,
+    y_invert: bool,
+) -> Option<Vec<u8>> {
+    let (width, height) = (width as usize, height as usize);
+    let row_bytes = width * 4;
+    if height == 0 || stride < row_bytes || data.len() < stride * (height - 1) + row_bytes {
+        return None;
+    }
+
+    let mut out = Vec::with_capacity(row_bytes * height);
+    for row in 0..height {
+        let src_row = if y_invert { height - 1 - row } else { row };
+        let src = &data[src_row * stride..src_row * stride + row_bytes];
+        for pixel in src.chunks_exact(4) {
+            let (r, b) = match order {
+                ByteOrder::Bgra => (pixel[2], pixel[0]),
+                ByteOrder::Rgba => (pixel[0], pixel[2]),
+            };
+            // The padding byte of 24-bit depth images is undefined
+            out.extend_from_slice(&[r, pixel[1], b, 0xFF]);
+        }
+    }
+    Some(out)
+}
+
+/// Blend a premultiplied ARGB cursor image onto a packed RGBA image
+///
+/// `position` is where the cursor image's top-left corner falls in the
+/// image; the parts of the cursor outside it are clipped.
+#[cfg(target_os = "linux")]
+pub(crate) fn draw_cursor(
+    data: &mut [u8],
+    width: u32,
+    height: u32,
+    cursor: &[u32],
+    (cursor_width, cursor_height): (u32, u32),
+    (x, y): (i32, i32),
+) {
+    for row in 0..cursor_height {
+        let image_y = i64::from(y) + i64::from(row);
+        if image_y < 0 || image_y >= i64::from(height) {
+            continue;
+        }
+        for column in 0..cursor_width {
+            let image_x = i64::from(x) + i64::from(column);
+            if image_x < 0 || image_x >= i64::from(width) {
+                continue;
+            }
+            let Some(&argb) = cursor.get((row * cursor_width + column) as usize) else {
+                return;
+            };
+            let alpha = argb >> 24;
+            let offset = (image_y as usize * width as usize + image_x as usize) * 4;
+            for (channel, shift) in data[offset..offset + 3].iter_mut().zip([16, 8, 0]) {
+                let blended = ((argb >> shift) & 0xFF) + u32::from(*channel) * (255 - alpha) / 255;
+                *channel = blended.min(255) as u8;
+            }
+        }
+    }
+}
+
+/// Scale an RGBA frame down to fit in `max` pixels, keeping its aspect
+#[cfg(target_os = "linux")]
+fn thumbnail(frame: &VideoFrame, (max_width, max_height): (u32, u32)) -> VideoFrame {
+    let scale = f64::min(
+        f64::from(max_width) / f64::from(frame.width.max(1)),
+        f64::from(max_height) / f64::from(frame.height.max(1)),
+    )
+    .min(1.0);
+    let width = ((f64::from(frame.width) * scale).round() as u32).max(1);
+    let height = ((f64::from(frame.height) * scale).round() as u32).max(1);
+
+    // Nearest-neighbour sampling is enough for a preview
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        let source_y = (u64::from(y) * u64::from(frame.height) / u64::from(height)) as usize;
+        for x in 0..width {
+            let source_x = (u64::from(x) * u64::from(frame.width) / u64::from(width)) as usize;
+            let offset = (source_y * frame.width as usize + source_x) * 4;
+            data.extend_from_slice(&frame.data[offset..offset + 4]);
+        }
+    }
+    VideoFrame::new(width, height, frame.format, data, frame.timestamp)
+}
+
+/// Part of `region` inside `frame`, a grab of the whole source
+#[cfg(target_os = "linux")]
+fn visible_region(region: Rect, frame: &VideoFrame) -> Option<Rect> {
+    region.intersection(&Rect::new(0, 0, frame.width, frame.height))
+}
+
+/// Copy `rect` out of an RGBA frame it lies inside of
+#[cfg(target_os = "linux")]
+fn crop_rgba(frame: &VideoFrame, rect: Rect) -> VideoFrame {
+    let stride = frame.width as usize * 4;
+    let (x, y) = (rect.x as usize * 4, rect.y as usize);
+    let row_bytes = rect.width as usize * 4;
+    let data = frame
+        .data
+        .chunks_exact(stride)
+        .skip(y)
+        .take(rect.height as usize)
+        .flat_map(|row| &row[x..x + row_bytes])
+        .copied()
+        .collect();
+
+    let mut cropped = VideoFrame::new(rect.width, rect.height, frame.format, data, frame.timestamp);
+    cropped.duration = frame.duration;
+    cropped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_server_prefers_wayland() {
+        let wayland = Some(OsString::from("wayland-0"));
+        let x11 = Some(OsString::from(":0"));
+        assert_eq!(
+            DisplayServer::from_env(wayland.clone(), x11.clone()),
+            Some(DisplayServer::Wayland)
+        );
+        assert_eq!(
+            DisplayServer::from_env(None, x11.clone()),
+            Some(DisplayServer::X11)
+        );
+        assert_eq!(
+            DisplayServer::from_env(Some(OsString::new()), x11),
+            Some(DisplayServer::X11)
+        );
+        assert_eq!(DisplayServer::from_env(None, None), None);
+    }
+
+    #[test]
+    fn test_to_rgba_swaps_crops_and_inverts() {
+        // 1x2 crop of a 2x2 image with a padded stride
+        let bgra = [
+            1, 2, 3, 0, 9, 9, 9, 9, 0, 0, //
+            4, 5, 6, 0, 9, 9, 9, 9, 0, 0,
+        ];
+        assert_eq!(
+            to_rgba(&bgra, 10, 1, 2, ByteOrder::Bgra, false).unwrap(),
+            vec![3, 2, 1, 255, 6, 5, 4, 255]
+        );
+        assert_eq!(
+            to_rgba(&bgra, 10, 1, 2, ByteOrder::Rgba, true).unwrap(),
+            vec![4, 5, 6, 255, 1, 2, 3, 255]
+        );
+        assert!(to_rgba(&bgra, 10, 3, 2, ByteOrder::Bgra, false).is_none());
+        assert!(to_rgba(&bgra, 10, 2, 3, ByteOrder::Bgra, false).is_none());
+    }
+
+    /// Screen of `width` x `height` pixels whose red and green values are
+    /// the pixel's column and row
+    #[cfg(target_os = "linux")]
+    struct MockScreen {
+        width: u32,
+        height: u32,
+    }
+
+    #[cfg(target_os = "linux")]
+    impl ScreenSource for MockScreen {
+        fn grab(&mut self) -> Result<Option<VideoFrame>, CaptureError> {
+            let data = (0..self.height)
+                .flat_map(|y| (0..self.width).flat_map(move |x| [x as u8, y as u8, 0, 0xFF]))
+                .collect();
+            Ok(Some(VideoFrame::new(
+                self.width,
+                self.height,
+                cortenbrowser_shared_types::PixelFormat::RGBA32,
+                data,
+                Duration::ZERO,
+            )))
+        }
+    }
+
+    /// Window showing a [`MockScreen`] that closes after `frames_left`
+    /// grabs, or shows nothing while minimized
+    #[cfg(target_os = "linux")]
+    struct MockWindow {
+        screen: MockScreen,
+        frames_left: usize,
+        minimized: bool,
+    }
+
+    #[cfg(target_os = "linux")]
+    impl ScreenSource for MockWindow {
+        fn grab(&mut self) -> Result<Option<VideoFrame>, CaptureError> {
+            if self.frames_left == 0 {
+                return Err(CaptureError::DeviceNotFound);
+            }
+            self.frames_left -= 1;
+            if self.minimized {
+                return Ok(None);
+            }
+            self.screen.grab()
+        }
+    }
+
+    /// Backend with a 640x360 screen, a 64x48 window `window:7` that closes
+    /// after `window_frames` grabs, and a minimized window `window:8`
+    #[cfg(target_os = "linux")]
+    struct MockSources {
+        window_frames: usize,
+        /// Sources opened and whether the cursor was asked for
+        opened: Mutex<Vec<(SourceId, bool)>>,
+    }
+
+    #[cfg(target_os = "linux")]
+    impl MockSources {
+        fn new(window_frames: usize) -> Self {
+            Self {
+                window_frames,
+                opened: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    impl SourceBackend for MockSources {
+        fn sources(&self) -> Result<Vec<CaptureSource>, CaptureError> {
+            let source = |kind, id: SourceId, title: &str| CaptureSource {
+                kind,
+                id: id.to_string(),
+                title: title.to_string(),
+                thumbnail: None,
+            };
+            Ok(vec![
+                source(CaptureSourceKind::Screen, SourceId::Screen(0), "Screen 1"),
+                source(CaptureSourceKind::Window, SourceId::Window(7), "Editor"),
+                source(CaptureSourceKind::Window, SourceId::Window(8), "Player"),
+            ])
+        }
+
+        fn open(
+            &self,
+            source: SourceId,
+            cursor: bool,
+        ) -> Result<Box<dyn ScreenSource>, CaptureError> {
+            self.opened.lock().unwrap().push((source, cursor));
+            let window = |minimized| MockWindow {
+                screen: MockScreen {
+                    width: 64,
+                    height: 48,
+                },
+                frames_left: self.window_frames,
+                minimized,
+            };
+            match source {
+                SourceId::Screen(0) => Ok(Box::new(MockScreen {
+                    width: 640,
+                    height: 360,
+                })),
+                SourceId::Window(7) => Ok(Box::new(window(false))),
+                SourceId::Window(8) => Ok(Box::new(window(true))),
+                _ => Err(CaptureError::DeviceNotFound),
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn first_region_frame(region: Rect) -> VideoFrame {
+        let source = Box::new(MockScreen {
+            width: 64,
+            height: 48,
+        });
+        let (tx, mut rx) = mpsc::channel(4);
+        let mut session =
+            CaptureSession::start(source, &CaptureConstraints::default(), Some(region), tx)
+                .unwrap();
+        let frame = rx.recv().await.unwrap();
+        session.stop();
+        frame
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_region_frames_have_region_size() {
+        let frame = first_region_frame(Rect::new(8, 4, 16, 10)).await;
+        assert_eq!((frame.width, frame.height), (16, 10));
+        assert_eq!(frame.data.len(), 16 * 10 * 4);
+
+        // Top-left and bottom-right pixels come from the region's corners
+        assert_eq!(&frame.data[..2], &[8, 4]);
+        let last = frame.data.len() - 4;
+        assert_eq!(&frame.data[last..last + 2], &[23, 13]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_region_clamped_to_screen() {
+        let frame = first_region_frame(Rect::new(40, -8, 100, 20)).await;
+        assert_eq!((frame.width, frame.height), (24, 12));
+        assert_eq!(&frame.data[..2], &[40, 0]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_region_outside_screen_ends_stream() {
+        let source = Box::new(MockScreen {
+            width: 64,
+            height: 48,
+        });
+        let (tx, mut rx) = mpsc::channel(4);
+        let region = Some(Rect::new(64, 0, 10, 10));
+        let _session =
+            CaptureSession::start(source, &CaptureConstraints::default(), region, tx).unwrap();
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[test]
+    fn test_source_id_round_trips() {
+        assert_eq!(SourceId::parse("screen:1"), Some(SourceId::Screen(1)));
+        assert_eq!(SourceId::parse("window:42"), Some(SourceId::Window(42)));
+        assert_eq!(SourceId::Window(42).to_string(), "window:42");
+        assert_eq!(SourceId::parse("screen:"), None);
+        assert_eq!(SourceId::parse("window:-1"), None);
+        assert_eq!(SourceId::parse("tab:1"), None);
+        assert_eq!(SourceId::parse("0"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_draw_cursor_blends_and_clips() {
+        // 2x1 grey image under a 2x2 cursor hanging off its top-left
+        let mut data = vec![100, 100, 100, 255, 100, 100, 100, 255];
+        let cursor = [0xFFFF_FFFF, 0xFF00_0000, 0xFFFF_0000, 0x8000_0080];
+        draw_cursor(&mut data, 2, 1, &cursor, (2, 2), (0, -1));
+
+        // Opaque red replaces the first pixel, half-transparent blue mixes
+        assert_eq!(&data[..4], &[255, 0, 0, 255]);
+        assert_eq!(&data[4..], &[49, 49, 177, 255]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_thumbnail_fits_and_keeps_aspect() {
+        let mut screen = MockScreen {
+            width: 64,
+            height: 48,
+        };
+        let frame = screen.grab().unwrap().unwrap();
+
+        let small = thumbnail(&frame, (16, 16));
+        assert_eq!((small.width, small.height), (16, 12));
+        assert_eq!(small.data.len(), 16 * 12 * 4);
+        // Every fourth column and row of the original
+        assert_eq!(&small.data[4..6], &[4, 0]);
+        let last = small.data.len() - 4;
+        assert_eq!(&small.data[last..last + 2], &[60, 44]);
+
+        // Small frames are not scaled up
+        let same = thumbnail(&frame, THUMBNAIL_SIZE);
+        assert_eq!((same.width, same.height), (64, 48));
+        assert_eq!(same.data, frame.data);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_enumerate_sources_adds_thumbnails() {
+        let backend = MockSources::new(10);
+        let sources = ScreenCapture::enumerate_with_backend(&backend);
+
+        let ids: Vec<_> = sources.iter().map(|source| source.id.as_str()).collect();
+        assert_eq!(ids, ["screen:0", "window:7", "window:8"]);
+        assert_eq!(sources[0].kind, CaptureSourceKind::Screen);
+        assert_eq!(sources[1].kind, CaptureSourceKind::Window);
+        assert_eq!(sources[1].title, "Editor");
+
+        let screen = sources[0].thumbnail.as_ref().unwrap();
+        assert_eq!((screen.width, screen.height), THUMBNAIL_SIZE);
+        let window = sources[1].thumbnail.as_ref().unwrap();
+        assert_eq!((window.width, window.height), (64, 48));
+        // Nothing to show for the minimized window
+        assert!(sources[2].thumbnail.is_none());
+
+        // Thumbnails leave the cursor out
+        let opened = backend.opened.lock().unwrap();
+        assert!(opened.iter().all(|&(_, cursor)| !cursor));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_start_capture_crops_window() {
+        let backend = MockSources::new(10);
+        let capture = ScreenCapture::new(0, CaptureConstraints::default()).unwrap();
+        let options = CaptureOptions {
+            capture_cursor: true,
+            crop: Some(Rect::new(8, 4, 16, 10)),
+        };
+        let mut stream = capture
+            .start_with_backend(&backend, "window:7", options)
+            .unwrap();
+
+        let frame = stream.next_frame().await.unwrap();
+        assert_eq!((frame.width, frame.height), (16, 10));
+        assert_eq!(frame.data.len(), 16 * 10 * 4);
+        assert_eq!(&frame.data[..2], &[8, 4]);
+        assert_eq!(
+            backend.opened.lock().unwrap().as_slice(),
+            &[(SourceId::Window(7), true)]
+        );
+        capture.stop().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_closed_window_ends_stream() {
+        let backend = MockSources::new(3);
+        let capture = ScreenCapture::new(0, CaptureConstraints::default()).unwrap();
+        let mut stream = capture
+            .start_with_backend(&backend, "window:7", CaptureOptions::default())
+            .unwrap();
+
+        let mut frames = 0;
+        while let Some(frame) = stream.next_frame().await {
+            assert_eq!((frame.width, frame.height), (64, 48));
+            frames += 1;
+        }
+        assert_eq!(frames, 3);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_minimized_window_sends_nothing_until_closed() {
+        let backend = MockSources::new(3);
+        let capture = ScreenCapture::new(0, CaptureConstraints::default()).unwrap();
+        let mut stream = capture
+            .start_with_backend(&backend, "window:8", CaptureOptions::default())
+            .unwrap();
+
+        assert!(stream.next_frame().await.is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_start_capture_rejects_unknown_source_and_outside_crop() {
+        let backend = MockSources::new(10);
+        let capture = ScreenCapture::new(0, CaptureConstraints::default()).unwrap();
+
+        for id in ["window:9", "display:0"] {
+            assert!(matches!(
+                capture.start_with_backend(&backend, id, CaptureOptions::default()),
+                Err(CaptureError::DeviceNotFound)
+            ));
+        }
+
+        let options = CaptureOptions {
+            crop: Some(Rect::new(640, 0, 10, 10)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            capture.start_with_backend(&backend, "screen:0", options),
+            Err(CaptureError::Overconstrained { constraint }) if constraint == "crop"
+        ));
+    }
+}
@@ -0,0 +1,90 @@
+//! Capture into MediaStreamTracks
+//!
+//! Captured frames and buffers are forwarded from the capture channel to
+//! the track's source by a task, until every track of the source has been
+//! stopped or capture ends, which ends the tracks.
+
+use cortenbrowser_shared_types::{
+    AudioBuffer, MediaSample, MediaStreamTrack, MediaStreamTrackKind, VideoFrame,
+};
+use tokio::sync::mpsc;
+
+/// Create a video track of the frames received on `frames`
+///
+/// Must be called within a Tokio runtime.
+pub(crate) fn video_track(label: String, frames: mpsc::Receiver<VideoFrame>) -> MediaStreamTrack {
+    forward(
+        MediaStreamTrackKind::Video,
+        label,
+        frames,
+        MediaSample::Video,
+    )
+}
+
+/// Create an audio track of the buffers received on `buffers`
+///
+/// Must be called within a Tokio runtime.
+pub(crate) fn audio_track(label: String, buffers: mpsc::Receiver<AudioBuffer>) -> MediaStreamTrack {
+    forward(
+        MediaStreamTrackKind::Audio,
+        label,
+        buffers,
+        MediaSample::Audio,
+    )
+}
+
+fn forward<T: Send + 'static>(
+    kind: MediaStreamTrackKind,
+    label: String,
+    mut receiver: mpsc::Receiver<T>,
+    sample: fn(T) -> MediaSample,
+) -> MediaStreamTrack {
+    let (track, source) = MediaStreamTrack::new(kind, label);
+    tokio::spawn(async move {
+        while let Some(media) = receiver.recv().await {
+            // Dropping the receiver once no track is live stops capture
+            if !source.send(sample(media)) {
+                break;
+            }
+        }
+    });
+    track
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cortenbrowser_shared_types::{MediaStreamTrackState, PixelFormat};
+    use std::time::Duration;
+
+    fn frame() -> VideoFrame {
+        VideoFrame::new(2, 2, PixelFormat::RGBA32, vec![255; 16], Duration::ZERO)
+    }
+
+    #[tokio::test]
+    async fn test_track_forwards_until_capture_ends() {
+        let (tx, rx) = mpsc::channel(4);
+        let track = video_track("Camera".to_string(), rx);
+        let mut frames = track.subscribe();
+        assert_eq!(track.label(), "Camera");
+
+        tx.send(frame()).await.unwrap();
+        assert_eq!(frames.next().await, Some(MediaSample::Video(frame())));
+
+        drop(tx);
+        assert_eq!(frames.next().await, None);
+        assert_eq!(track.ready_state(), MediaStreamTrackState::Ended);
+    }
+
+    #[tokio::test]
+    async fn test_stopped_track_stops_capture() {
+        let (tx, rx) = mpsc::channel(4);
+        let track = video_track("Camera".to_string(), rx);
+
+        track.stop();
+        tx.send(frame()).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(1), tx.closed())
+            .await
+            .unwrap();
+    }
+}
@@ -3,29 +3,45 @@
 //! This module defines types for device capture including constraints,
 //! device information, and error types.
 
+use crate::{ConstrainFacingMode, ConstrainRange};
+use cortenbrowser_shared_types::AudioLevel;
 use std::fmt;
 
 /// Constraints for video capture
 ///
+/// Each property can be asked for ideally, exactly or within a range, as
+/// with getUserMedia. Unconstrained properties are left to the device.
+///
 /// # Examples
 ///
 /// ```
-/// use cortenbrowser_media_capture::CaptureConstraints;
+/// use cortenbrowser_media_capture::{CaptureConstraints, ConstrainRange};
 ///
 /// let constraints = CaptureConstraints {
-///     width: Some(1920),
-///     height: Some(1080),
-///     frame_rate: Some(30.0),
+///     width: ConstrainRange::ideal(1920),
+///     height: ConstrainRange::ideal(1080),
+///     frame_rate: ConstrainRange::range(24.0, 60.0),
+///     ..Default::default()
 /// };
+///
+/// // The same with a builder
+/// let built = CaptureConstraints::builder()
+///     .width(1920)
+///     .height(1080)
+///     .frame_rate_range(24.0, 60.0)
+///     .build();
+/// assert_eq!(built, constraints);
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct CaptureConstraints {
-    /// Desired width in pixels
-    pub width: Option<u32>,
-    /// Desired height in pixels
-    pub height: Option<u32>,
-    /// Desired frame rate in frames per second
-    pub frame_rate: Option<f32>,
+    /// Width in pixels
+    pub width: ConstrainRange<u32>,
+    /// Height in pixels
+    pub height: ConstrainRange<u32>,
+    /// Frame rate in frames per second
+    pub frame_rate: ConstrainRange<f32>,
+    /// Direction the camera faces
+    pub facing_mode: ConstrainFacingMode,
 }
 
 /// Constraints for audio capture
@@ -82,6 +98,49 @@ pub struct DeviceInfo {
     pub kind: DeviceKind,
 }
 
+/// Change in the set of available capture devices
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::{DeviceEvent, DeviceInfo, DeviceKind};
+///
+/// let event = DeviceEvent::Added(DeviceInfo {
+///     device_id: "/dev/video2".to_string(),
+///     label: "USB Camera".to_string(),
+///     kind: DeviceKind::VideoInput,
+/// });
+/// assert!(matches!(event, DeviceEvent::Added(_)));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    /// A device was plugged in
+    Added(DeviceInfo),
+    /// A device was unplugged
+    Removed {
+        /// `device_id` of the removed device
+        device_id: String,
+    },
+}
+
+/// Change in the level or voice activity of captured audio
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_capture::AudioLevelEvent;
+///
+/// let event = AudioLevelEvent::VoiceActivity(true);
+/// assert!(matches!(event, AudioLevelEvent::VoiceActivity(true)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioLevelEvent {
+    /// Periodic level update, about ten times a second
+    Level(AudioLevel),
+    /// Voice started (`true`) or stopped (`false`) being detected
+    VoiceActivity(bool),
+}
+
 /// Errors that can occur during media capture
 #[derive(Debug, Clone, PartialEq)]
 pub enum CaptureError {
@@ -91,6 +150,17 @@ pub enum CaptureError {
     PermissionDenied,
     /// Capture operation failed
     CaptureFailure,
+    /// No mode of the device meets the required constraint values
+    Overconstrained {
+        /// Name of the constraint that cannot be met, e.g. `frame_rate`
+        constraint: String,
+    },
+    /// A constraint asks for values no device can have, such as a zero
+    /// width or a minimum above its maximum
+    InvalidConstraint {
+        /// Name of the invalid constraint, e.g. `width`
+        constraint: String,
+    },
 }
 
 impl fmt::Display for CaptureError {
@@ -99,8 +169,21 @@ impl fmt::Display for CaptureError {
             CaptureError::DeviceNotFound => write!(f, "Device not found"),
             CaptureError::PermissionDenied => write!(f, "Permission denied"),
             CaptureError::CaptureFailure => write!(f, "Capture failure"),
+            CaptureError::Overconstrained { constraint } => {
+                write!(f, "Constraint cannot be satisfied: {}", constraint)
+            }
+            CaptureError::InvalidConstraint { constraint } => {
+                write!(f, "Invalid constraint: {}", constraint)
+            }
         }
     }
 }
 
 impl std::error::Error for CaptureError {}
+
+#[cfg(target_os = "linux")]
+impl From<cortenbrowser_shared_types::MissingSymbol> for CaptureError {
+    fn from(_: cortenbrowser_shared_types::MissingSymbol) -> Self {
+        CaptureError::CaptureFailure
+    }
+}
@@ -0,0 +1,237 @@
+//! udev device monitoring for Linux
+//!
+//! Subscribes to udev events for some subsystems, as processed by the udev
+//! daemon, so device nodes exist with their final permissions by the time
+//! an addition is reported. `libudev` is loaded at runtime; without it,
+//! or without a udev daemon, no events are received.
+
+use crate::CaptureError;
+use cortenbrowser_shared_types::Library;
+use libc::{c_char, c_int};
+use std::ffi::{CStr, CString};
+use std::os::fd::{AsRawFd, RawFd};
+use std::ptr;
+
+#[repr(C)]
+struct Udev {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct UdevMonitor {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct UdevDevice {
+    _private: [u8; 0],
+}
+
+type UdevNewFn = unsafe extern "C" fn() -> *mut Udev;
+type UdevUnrefFn = unsafe extern "C" fn(*mut Udev) -> *mut Udev;
+type UdevMonitorNewFromNetlinkFn =
+    unsafe extern "C" fn(*mut Udev, *const c_char) -> *mut UdevMonitor;
+type UdevMonitorFilterAddMatchSubsystemDevtypeFn =
+    unsafe extern "C" fn(*mut UdevMonitor, *const c_char, *const c_char) -> c_int;
+type UdevMonitorEnableReceivingFn = unsafe extern "C" fn(*mut UdevMonitor) -> c_int;
+type UdevMonitorGetFdFn = unsafe extern "C" fn(*mut UdevMonitor) -> c_int;
+type UdevMonitorReceiveDeviceFn = unsafe extern "C" fn(*mut UdevMonitor) -> *mut UdevDevice;
+type UdevMonitorUnrefFn = unsafe extern "C" fn(*mut UdevMonitor) -> *mut UdevMonitor;
+type UdevDeviceGetStringFn = unsafe extern "C" fn(*mut UdevDevice) -> *const c_char;
+type UdevDeviceUnrefFn = unsafe extern "C" fn(*mut UdevDevice) -> *mut UdevDevice;
+
+/// libudev entry points used for monitoring
+struct UdevApi {
+    new: UdevNewFn,
+    unref: UdevUnrefFn,
+    monitor_new_from_netlink: UdevMonitorNewFromNetlinkFn,
+    monitor_filter_add_match_subsystem_devtype: UdevMonitorFilterAddMatchSubsystemDevtypeFn,
+    monitor_enable_receiving: UdevMonitorEnableReceivingFn,
+    monitor_get_fd: UdevMonitorGetFdFn,
+    monitor_receive_device: UdevMonitorReceiveDeviceFn,
+    monitor_unref: UdevMonitorUnrefFn,
+    device_get_action: UdevDeviceGetStringFn,
+    device_get_devnode: UdevDeviceGetStringFn,
+    device_unref: UdevDeviceUnrefFn,
+}
+
+impl UdevApi {
+    fn load(udev: &Library) -> Result<Self, CaptureError> {
+        // SAFETY: the function types match the libudev C declarations
+        unsafe {
+            Ok(Self {
+                new: udev.symbol(c"udev_new")?,
+                unref: udev.symbol(c"udev_unref")?,
+                monitor_new_from_netlink: udev.symbol(c"udev_monitor_new_from_netlink")?,
+                monitor_filter_add_match_subsystem_devtype: udev
+                    .symbol(c"udev_monitor_filter_add_match_subsystem_devtype")?,
+                monitor_enable_receiving: udev.symbol(c"udev_monitor_enable_receiving")?,
+                monitor_get_fd: udev.symbol(c"udev_monitor_get_fd")?,
+                monitor_receive_device: udev.symbol(c"udev_monitor_receive_device")?,
+                monitor_unref: udev.symbol(c"udev_monitor_unref")?,
+                device_get_action: udev.symbol(c"udev_device_get_action")?,
+                device_get_devnode: udev.symbol(c"udev_device_get_devnode")?,
+                device_unref: udev.symbol(c"udev_device_unref")?,
+            })
+        }
+    }
+}
+
+/// What happened to a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Action {
+    /// The device was plugged in
+    Add,
+    /// The device was unplugged
+    Remove,
+}
+
+impl Action {
+    fn parse(action: &str) -> Option<Self> {
+        match action {
+            "add" => Some(Self::Add),
+            "remove" => Some(Self::Remove),
+            _ => None,
+        }
+    }
+}
+
+/// Addition or removal of a device node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DeviceChange {
+    /// Path of the device node, such as `/dev/video0`
+    pub(crate) devnode: String,
+}
+
+/// Monitor of udev events for some subsystems
+///
+/// The monitor socket is non-blocking; wait for it to become readable
+/// through [`AsRawFd`] and then [`receive`](Self::receive) until it
+/// returns `None`.
+pub(crate) struct Monitor {
+    api: UdevApi,
+    udev: *mut Udev,
+    monitor: *mut UdevMonitor,
+    // Keeps the function pointers in `api` valid
+    _libudev: Library,
+}
+
+// SAFETY: the udev context and monitor are owned by this value and only
+// used through `&mut self` or from the thread owning it
+unsafe impl Send for Monitor {}
+
+impl Monitor {
+    /// Start monitoring devices of `subsystems`, such as `video4linux`
+    pub(crate) fn open(subsystems: &[&str]) -> Result<Self, CaptureError> {
+        let libudev = Library::open(c"libudev.so.1").ok_or(CaptureError::CaptureFailure)?;
+        let api = UdevApi::load(&libudev)?;
+        let subsystems = subsystems
+            .iter()
+            .map(|subsystem| CString::new(*subsystem))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| CaptureError::CaptureFailure)?;
+
+        // SAFETY: udev_new has no preconditions
+        let udev = unsafe { (api.new)() };
+        if udev.is_null() {
+            return Err(CaptureError::CaptureFailure);
+        }
+        // SAFETY: `udev` is a live context and the name is NUL-terminated
+        let monitor = unsafe { (api.monitor_new_from_netlink)(udev, c"udev".as_ptr()) };
+        let mut watcher = Self {
+            api,
+            udev,
+            monitor,
+            _libudev: libudev,
+        };
+        if monitor.is_null() {
+            return Err(CaptureError::CaptureFailure);
+        }
+
+        // SAFETY: the monitor is live and the strings are NUL-terminated
+        let enabled = unsafe {
+            subsystems.iter().all(|subsystem| {
+                (watcher.api.monitor_filter_add_match_subsystem_devtype)(
+                    watcher.monitor,
+                    subsystem.as_ptr(),
+                    ptr::null(),
+                ) >= 0
+            }) && (watcher.api.monitor_enable_receiving)(watcher.monitor) >= 0
+        };
+        if !enabled {
+            watcher.close_monitor();
+            return Err(CaptureError::CaptureFailure);
+        }
+        Ok(watcher)
+    }
+
+    /// Take the next pending device change, or `None` when there is none
+    ///
+    /// Events other than additions and removals, and events for devices
+    /// without a device node, are skipped.
+    pub(crate) fn receive(&mut self) -> Option<DeviceChange> {
+        loop {
+            // SAFETY: the monitor is live; the returned device is released
+            // below after its strings have been copied
+            unsafe {
+                let device = (self.api.monitor_receive_device)(self.monitor);
+                if device.is_null() {
+                    return None;
+                }
+                let action = string((self.api.device_get_action)(device));
+                let devnode = string((self.api.device_get_devnode)(device));
+                (self.api.device_unref)(device);
+
+                let action = action.as_deref().and_then(Action::parse);
+                if let (Some(_), Some(devnode)) = (action, devnode) {
+                    return Some(DeviceChange { devnode });
+                }
+            }
+        }
+    }
+
+    fn close_monitor(&mut self) {
+        if !self.monitor.is_null() {
+            // SAFETY: the monitor is live and not used again
+            unsafe { (self.api.monitor_unref)(self.monitor) };
+            self.monitor = ptr::null_mut();
+        }
+    }
+}
+
+impl AsRawFd for Monitor {
+    fn as_raw_fd(&self) -> RawFd {
+        // SAFETY: the monitor is live for as long as `self`
+        unsafe { (self.api.monitor_get_fd)(self.monitor) }
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        self.close_monitor();
+        // SAFETY: the context is live and its monitor has been released
+        unsafe { (self.api.unref)(self.udev) };
+    }
+}
+
+/// Copy a string owned by libudev, which may be null
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a NUL-terminated string.
+unsafe fn string(ptr: *const c_char) -> Option<String> {
+    (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_parse() {
+        assert_eq!(Action::parse("add"), Some(Action::Add));
+        assert_eq!(Action::parse("remove"), Some(Action::Remove));
+        assert_eq!(Action::parse("change"), None);
+        assert_eq!(Action::parse("bind"), None);
+    }
+}
@@ -0,0 +1,869 @@
+//! V4L2 camera capture for Linux
+//!
+//! Talks to `/dev/video*` nodes through raw `ioctl` calls. Frames are
+//! captured in YUYV through memory-mapped streaming buffers and converted to
+//! planar YUV 4:2:0 before being delivered.
+
+use crate::{
+    CaptureConstraints, CaptureError, CaptureMode, CaptureSettings, DeviceInfo, DeviceKind,
+};
+use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+use libc::{c_int, c_ulong, c_void};
+use std::ffi::CString;
+use std::io;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const V4L2_BUF_TYPE_VIDEO_CAPTURE: u32 = 1;
+const V4L2_MEMORY_MMAP: u32 = 1;
+const V4L2_FIELD_ANY: u32 = 0;
+
+const V4L2_CAP_VIDEO_CAPTURE: u32 = 0x0000_0001;
+const V4L2_CAP_STREAMING: u32 = 0x0400_0000;
+const V4L2_CAP_DEVICE_CAPS: u32 = 0x8000_0000;
+const V4L2_CAP_TIMEPERFRAME: u32 = 0x1000;
+
+const V4L2_PIX_FMT_YUYV: u32 = fourcc(b"YUYV");
+
+const V4L2_FRMSIZE_TYPE_DISCRETE: u32 = 1;
+const V4L2_FRMIVAL_TYPE_DISCRETE: u32 = 1;
+
+/// Number of mmap buffers requested from the driver
+const BUFFER_COUNT: u32 = 4;
+/// How long the capture thread waits for a frame before checking for stop
+const POLL_TIMEOUT_MS: c_int = 100;
+
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    (code[0] as u32) | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+const fn ioc(dir: c_ulong, nr: c_ulong, size: usize) -> c_ulong {
+    (dir << 30) | ((size as c_ulong) << 16) | ((b'V' as c_ulong) << 8) | nr
+}
+
+const IOC_WRITE: c_ulong = 1;
+const IOC_READ: c_ulong = 2;
+
+const VIDIOC_QUERYCAP: c_ulong = ioc(IOC_READ, 0, mem::size_of::<V4l2Capability>());
+const VIDIOC_ENUM_FMT: c_ulong = ioc(IOC_READ | IOC_WRITE, 2, mem::size_of::<V4l2FmtDesc>());
+const VIDIOC_G_FMT: c_ulong = ioc(IOC_READ | IOC_WRITE, 4, mem::size_of::<V4l2Format>());
+const VIDIOC_S_FMT: c_ulong = ioc(IOC_READ | IOC_WRITE, 5, mem::size_of::<V4l2Format>());
+const VIDIOC_REQBUFS: c_ulong = ioc(
+    IOC_READ | IOC_WRITE,
+    8,
+    mem::size_of::<V4l2RequestBuffers>(),
+);
+const VIDIOC_QUERYBUF: c_ulong = ioc(IOC_READ | IOC_WRITE, 9, mem::size_of::<V4l2Buffer>());
+const VIDIOC_QBUF: c_ulong = ioc(IOC_READ | IOC_WRITE, 15, mem::size_of::<V4l2Buffer>());
+const VIDIOC_DQBUF: c_ulong = ioc(IOC_READ | IOC_WRITE, 17, mem::size_of::<V4l2Buffer>());
+const VIDIOC_STREAMON: c_ulong = ioc(IOC_WRITE, 18, mem::size_of::<c_int>());
+const VIDIOC_STREAMOFF: c_ulong = ioc(IOC_WRITE, 19, mem::size_of::<c_int>());
+const VIDIOC_G_PARM: c_ulong = ioc(IOC_READ | IOC_WRITE, 21, mem::size_of::<V4l2StreamParm>());
+const VIDIOC_S_PARM: c_ulong = ioc(IOC_READ | IOC_WRITE, 22, mem::size_of::<V4l2StreamParm>());
+const VIDIOC_ENUM_FRAMESIZES: c_ulong =
+    ioc(IOC_READ | IOC_WRITE, 74, mem::size_of::<V4l2FrmSizeEnum>());
+const VIDIOC_ENUM_FRAMEINTERVALS: c_ulong =
+    ioc(IOC_READ | IOC_WRITE, 75, mem::size_of::<V4l2FrmIvalEnum>());
+
+#[repr(C)]
+struct V4l2Capability {
+    driver: [u8; 16],
+    card: [u8; 32],
+    bus_info: [u8; 32],
+    version: u32,
+    capabilities: u32,
+    device_caps: u32,
+    reserved: [u32; 3],
+}
+
+#[repr(C)]
+struct V4l2FmtDesc {
+    index: u32,
+    type_: u32,
+    flags: u32,
+    description: [u8; 32],
+    pixelformat: u32,
+    mbus_code: u32,
+    reserved: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct V4l2PixFormat {
+    width: u32,
+    height: u32,
+    pixelformat: u32,
+    field: u32,
+    bytesperline: u32,
+    sizeimage: u32,
+    colorspace: u32,
+    priv_: u32,
+    flags: u32,
+    ycbcr_enc: u32,
+    quantization: u32,
+    xfer_func: u32,
+}
+
+/// `v4l2_format.fmt`, sized and aligned like the kernel union, which holds
+/// pointers in its overlay variant
+#[repr(C)]
+union V4l2FormatUnion {
+    pix: V4l2PixFormat,
+    raw: [c_ulong; 200 / mem::size_of::<c_ulong>()],
+}
+
+#[repr(C)]
+struct V4l2Format {
+    type_: u32,
+    fmt: V4l2FormatUnion,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct V4l2Fract {
+    numerator: u32,
+    denominator: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct V4l2CaptureParm {
+    capability: u32,
+    capturemode: u32,
+    timeperframe: V4l2Fract,
+    extendedmode: u32,
+    readbuffers: u32,
+    reserved: [u32; 4],
+}
+
+#[repr(C)]
+union V4l2StreamParmUnion {
+    capture: V4l2CaptureParm,
+    raw: [u8; 200],
+}
+
+#[repr(C)]
+struct V4l2StreamParm {
+    type_: u32,
+    parm: V4l2StreamParmUnion,
+}
+
+/// `v4l2_frmsizeenum`, with the discrete or stepwise size in `size`
+#[repr(C)]
+struct V4l2FrmSizeEnum {
+    index: u32,
+    pixel_format: u32,
+    type_: u32,
+    /// `width, height` when discrete, otherwise `min_width, max_width,
+    /// step_width, min_height, max_height, step_height`
+    size: [u32; 6],
+    reserved: [u32; 2],
+}
+
+/// `v4l2_frmivalenum`, with the discrete or stepwise interval in `interval`
+#[repr(C)]
+struct V4l2FrmIvalEnum {
+    index: u32,
+    pixel_format: u32,
+    width: u32,
+    height: u32,
+    type_: u32,
+    /// The discrete fraction, otherwise the min, max and step fractions
+    interval: [V4l2Fract; 3],
+    reserved: [u32; 2],
+}
+
+#[repr(C)]
+struct V4l2RequestBuffers {
+    count: u32,
+    type_: u32,
+    memory: u32,
+    capabilities: u32,
+    reserved: u32,
+}
+
+#[repr(C)]
+struct V4l2Timecode {
+    type_: u32,
+    flags: u32,
+    frames: u8,
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    userbits: [u8; 4],
+}
+
+#[repr(C)]
+union V4l2BufferM {
+    offset: u32,
+    userptr: c_ulong,
+}
+
+#[repr(C)]
+struct V4l2Buffer {
+    index: u32,
+    type_: u32,
+    bytesused: u32,
+    flags: u32,
+    field: u32,
+    timestamp: libc::timeval,
+    timecode: V4l2Timecode,
+    sequence: u32,
+    memory: u32,
+    m: V4l2BufferM,
+    length: u32,
+    reserved2: u32,
+    request_fd: i32,
+}
+
+/// Zero-initialize a V4L2 ioctl argument
+fn zeroed<T>() -> T {
+    // SAFETY: only used for the plain-data repr(C) structs above, for which
+    // all-zero is a valid value
+    unsafe { mem::zeroed() }
+}
+
+/// Map an OS error from opening or configuring a device
+pub(crate) fn capture_error(err: io::Error) -> CaptureError {
+    match err.raw_os_error() {
+        Some(libc::ENOENT) | Some(libc::ENODEV) | Some(libc::ENXIO) => CaptureError::DeviceNotFound,
+        Some(libc::EACCES) | Some(libc::EPERM) => CaptureError::PermissionDenied,
+        _ => CaptureError::CaptureFailure,
+    }
+}
+
+/// An open V4L2 device node
+struct Device {
+    fd: c_int,
+}
+
+impl Device {
+    fn open(path: &str) -> io::Result<Self> {
+        let path = CString::new(path).map_err(|_| io::Error::from_raw_os_error(libc::ENOENT))?;
+        // SAFETY: path is a valid NUL-terminated string
+        let fd = unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDWR | libc::O_NONBLOCK | libc::O_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd })
+    }
+
+    /// Issue an ioctl, retrying when interrupted by a signal
+    fn ioctl<T>(&self, request: c_ulong, arg: &mut T) -> io::Result<()> {
+        loop {
+            // SAFETY: request is a V4L2 ioctl whose argument type is T
+            let ret = unsafe { libc::ioctl(self.fd, request as _, arg as *mut T as *mut c_void) };
+            if ret >= 0 {
+                return Ok(());
+            }
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EINTR) {
+                return Err(err);
+            }
+        }
+    }
+
+    fn query_capabilities(&self) -> io::Result<V4l2Capability> {
+        let mut cap: V4l2Capability = zeroed();
+        self.ioctl(VIDIOC_QUERYCAP, &mut cap)?;
+        Ok(cap)
+    }
+
+    /// Pixel formats offered for video capture
+    fn pixel_formats(&self) -> Vec<u32> {
+        let mut formats = Vec::new();
+        for index in 0.. {
+            let mut desc: V4l2FmtDesc = zeroed();
+            desc.index = index;
+            desc.type_ = V4L2_BUF_TYPE_VIDEO_CAPTURE;
+            if self.ioctl(VIDIOC_ENUM_FMT, &mut desc).is_err() {
+                break;
+            }
+            formats.push(desc.pixelformat);
+        }
+        formats
+    }
+
+    /// Frame sizes offered in `pixel_format`
+    ///
+    /// For a stepwise or continuous range only its smallest and largest
+    /// sizes are listed.
+    fn frame_sizes(&self, pixel_format: u32) -> Vec<(u32, u32)> {
+        let mut sizes = Vec::new();
+        for index in 0.. {
+            let mut desc: V4l2FrmSizeEnum = zeroed();
+            desc.index = index;
+            desc.pixel_format = pixel_format;
+            if self.ioctl(VIDIOC_ENUM_FRAMESIZES, &mut desc).is_err() {
+                break;
+            }
+            if desc.type_ == V4L2_FRMSIZE_TYPE_DISCRETE {
+                sizes.push((desc.size[0], desc.size[1]));
+            } else {
+                sizes.push((desc.size[0], desc.size[3]));
+                sizes.push((desc.size[1], desc.size[4]));
+                break;
+            }
+        }
+        sizes
+    }
+
+    /// Frame rates offered in `pixel_format` at `width` x `height`
+    ///
+    /// For a stepwise or continuous range only its slowest and fastest
+    /// rates are listed.
+    fn frame_rates(&self, pixel_format: u32, width: u32, height: u32) -> Vec<f32> {
+        let mut rates = Vec::new();
+        for index in 0.. {
+            let mut desc: V4l2FrmIvalEnum = zeroed();
+            desc.index = index;
+            desc.pixel_format = pixel_format;
+            desc.width = width;
+            desc.height = height;
+            if self.ioctl(VIDIOC_ENUM_FRAMEINTERVALS, &mut desc).is_err() {
+                break;
+            }
+            let intervals = if desc.type_ == V4L2_FRMIVAL_TYPE_DISCRETE {
+                &desc.interval[..1]
+            } else {
+                &desc.interval[..2]
+            };
+            rates.extend(intervals.iter().copied().filter_map(frame_rate));
+            if desc.type_ != V4L2_FRMIVAL_TYPE_DISCRETE {
+                break;
+            }
+        }
+        rates
+    }
+
+    /// Modes offered in `pixel_format`, one per frame size and rate
+    fn modes(&self, pixel_format: u32) -> Vec<CaptureMode> {
+        let mut modes = Vec::new();
+        for (width, height) in self.frame_sizes(pixel_format) {
+            let rates = self.frame_rates(pixel_format, width, height);
+            let mode = |frame_rate| CaptureMode {
+                width,
+                height,
+                frame_rate,
+                // V4L2 does not tell which way a camera faces
+                facing_mode: None,
+            };
+            if rates.is_empty() {
+                modes.push(mode(None));
+            }
+            modes.extend(rates.into_iter().map(|rate| mode(Some(rate))));
+        }
+        modes
+    }
+
+    /// Wait up to `timeout_ms` for a frame to become available
+    fn poll(&self, timeout_ms: c_int) -> io::Result<bool> {
+        let mut pfd = libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: pfd is a single valid pollfd
+        let ret = unsafe { libc::poll(&mut pfd, 1, timeout_ms) };
+        match ret {
+            r if r > 0 => Ok(true),
+            0 => Ok(false),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EINTR) {
+                    Ok(false)
+                } else {
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        // SAFETY: fd was opened by Device::open and is closed only here
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
+
+/// Frames per second of a frame interval, `None` for a zero fraction
+fn frame_rate(interval: V4l2Fract) -> Option<f32> {
+    if interval.numerator == 0 || interval.denominator == 0 {
+        return None;
+    }
+    Some((f64::from(interval.denominator) / f64::from(interval.numerator)) as f32)
+}
+
+/// Capabilities of the device node itself rather than the whole driver
+fn device_caps(cap: &V4l2Capability) -> u32 {
+    if cap.capabilities & V4L2_CAP_DEVICE_CAPS != 0 {
+        cap.device_caps
+    } else {
+        cap.capabilities
+    }
+}
+
+/// Card name from a NUL-padded `v4l2_capability` field
+fn card_label(card: &[u8]) -> String {
+    let end = card.iter().position(|&b| b == 0).unwrap_or(card.len());
+    String::from_utf8_lossy(&card[..end]).trim().to_string()
+}
+
+/// Probe `/dev/video*` for nodes that support video capture
+pub(crate) fn enumerate_devices() -> Vec<DeviceInfo> {
+    let Ok(entries) = std::fs::read_dir("/dev") else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("video"))
+        .map(|entry| entry.path().to_string_lossy().into_owned())
+        .collect();
+    paths.sort_by_key(|path| {
+        let index = path.trim_start_matches("/dev/video").parse::<u32>().ok();
+        (index.is_none(), index, path.clone())
+    });
+
+    paths.into_iter().filter_map(device_info).collect()
+}
+
+/// Describe the device node at `path`, or `None` if it is not a video
+/// capture device
+pub(crate) fn device_info(path: String) -> Option<DeviceInfo> {
+    let device = Device::open(&path).ok()?;
+    let cap = device.query_capabilities().ok()?;
+    if device_caps(&cap) & V4L2_CAP_VIDEO_CAPTURE == 0 {
+        return None;
+    }
+    let label = card_label(&cap.card);
+    Some(DeviceInfo {
+        label: if label.is_empty() { path.clone() } else { label },
+        device_id: path,
+        kind: DeviceKind::VideoInput,
+    })
+}
+
+/// Modes the capture device at `path` can stream in
+///
+/// # Errors
+///
+/// Returns `DeviceNotFound` if `path` is not a video capture device,
+/// `PermissionDenied` if it cannot be opened, and `CaptureFailure` if it
+/// offers no YUYV format or streaming I/O.
+pub(crate) fn capture_modes(path: &str) -> Result<Vec<CaptureMode>, CaptureError> {
+    let device = Device::open(path).map_err(capture_error)?;
+    Stream::modes(&device)
+}
+
+/// A memory-mapped driver buffer
+struct MappedBuffer {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+// SAFETY: the mapping is owned exclusively by the capture thread
+unsafe impl Send for MappedBuffer {}
+
+impl MappedBuffer {
+    fn as_slice(&self, bytes_used: usize) -> &[u8] {
+        // SAFETY: ptr maps len readable bytes for the lifetime of self
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, bytes_used.min(self.len)) }
+    }
+}
+
+impl Drop for MappedBuffer {
+    fn drop(&mut self) {
+        // SAFETY: ptr and len come from a successful mmap
+        unsafe {
+            libc::munmap(self.ptr, self.len);
+        }
+    }
+}
+
+/// A negotiated, streaming device
+struct Stream {
+    // Buffers must be unmapped before the device is closed
+    buffers: Vec<MappedBuffer>,
+    device: Device,
+    width: u32,
+    height: u32,
+    stride: u32,
+    frame_interval: Option<Duration>,
+}
+
+impl Stream {
+    fn open(path: &str, constraints: &CaptureConstraints) -> Result<Self, CaptureError> {
+        let device = Device::open(path).map_err(capture_error)?;
+        let mode = constraints.select_mode(&Self::modes(&device)?)?;
+
+        let mut format: V4l2Format = zeroed();
+        format.type_ = V4L2_BUF_TYPE_VIDEO_CAPTURE;
+        device
+            .ioctl(VIDIOC_G_FMT, &mut format)
+            .map_err(capture_error)?;
+        // SAFETY: the driver fills the pix member for capture buffers
+        let mut pix = unsafe { format.fmt.pix };
+        pix.width = mode.width;
+        pix.height = mode.height;
+        pix.pixelformat = V4L2_PIX_FMT_YUYV;
+        pix.field = V4L2_FIELD_ANY;
+        pix.bytesperline = 0;
+        pix.sizeimage = 0;
+        format.fmt.pix = pix;
+        device
+            .ioctl(VIDIOC_S_FMT, &mut format)
+            .map_err(capture_error)?;
+        // SAFETY: S_FMT writes back the negotiated pix format
+        let pix = unsafe { format.fmt.pix };
+        if pix.pixelformat != V4L2_PIX_FMT_YUYV || pix.width == 0 || pix.height == 0 {
+            return Err(CaptureError::CaptureFailure);
+        }
+
+        let frame_interval = Self::set_frame_rate(&device, mode.frame_rate);
+
+        let mut request: V4l2RequestBuffers = zeroed();
+        request.count = BUFFER_COUNT;
+        request.type_ = V4L2_BUF_TYPE_VIDEO_CAPTURE;
+        request.memory = V4L2_MEMORY_MMAP;
+        device
+            .ioctl(VIDIOC_REQBUFS, &mut request)
+            .map_err(capture_error)?;
+        if request.count == 0 {
+            return Err(CaptureError::CaptureFailure);
+        }
+
+        let mut buffers = Vec::with_capacity(request.count as usize);
+        for index in 0..request.count {
+            let mut buf = Self::buffer(index);
+            device
+                .ioctl(VIDIOC_QUERYBUF, &mut buf)
+                .map_err(capture_error)?;
+            let len = buf.length as usize;
+            // SAFETY: offset and length describe a driver buffer of this fd
+            let ptr = unsafe {
+                libc::mmap(
+                    std::ptr::null_mut(),
+                    len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    device.fd,
+                    buf.m.offset as libc::off_t,
+                )
+            };
+            if ptr == libc::MAP_FAILED {
+                return Err(CaptureError::CaptureFailure);
+            }
+            buffers.push(MappedBuffer { ptr, len });
+            device.ioctl(VIDIOC_QBUF, &mut buf).map_err(capture_error)?;
+        }
+
+        let mut buf_type = V4L2_BUF_TYPE_VIDEO_CAPTURE as c_int;
+        device
+            .ioctl(VIDIOC_STREAMON, &mut buf_type)
+            .map_err(capture_error)?;
+
+        Ok(Self {
+            buffers,
+            device,
+            width: pix.width,
+            height: pix.height,
+            stride: pix.bytesperline.max(pix.width * 2),
+            frame_interval,
+        })
+    }
+
+    /// Modes `device` can stream YUYV in
+    ///
+    /// # Errors
+    ///
+    /// Returns `DeviceNotFound` if `device` is not a video capture device,
+    /// and `CaptureFailure` if it offers no YUYV format or streaming I/O.
+    fn modes(device: &Device) -> Result<Vec<CaptureMode>, CaptureError> {
+        let cap = device.query_capabilities().map_err(capture_error)?;
+        let caps = device_caps(&cap);
+        if caps & V4L2_CAP_VIDEO_CAPTURE == 0 {
+            return Err(CaptureError::DeviceNotFound);
+        }
+        if caps & V4L2_CAP_STREAMING == 0 {
+            return Err(CaptureError::CaptureFailure);
+        }
+
+        // MJPEG-only cameras would need a JPEG decoder to produce YUV 4:2:0
+        if !device.pixel_formats().contains(&V4L2_PIX_FMT_YUYV) {
+            return Err(CaptureError::CaptureFailure);
+        }
+
+        let modes = device.modes(V4L2_PIX_FMT_YUYV);
+        if !modes.is_empty() {
+            return Ok(modes);
+        }
+
+        // Drivers that do not enumerate their modes offer the current one
+        let mut format: V4l2Format = zeroed();
+        format.type_ = V4L2_BUF_TYPE_VIDEO_CAPTURE;
+        device
+            .ioctl(VIDIOC_G_FMT, &mut format)
+            .map_err(capture_error)?;
+        // SAFETY: the driver fills the pix member for capture buffers
+        let pix = unsafe { format.fmt.pix };
+        Ok(vec![CaptureMode {
+            width: pix.width,
+            height: pix.height,
+            frame_rate: Self::set_frame_rate(device, None)
+                .map(|interval| 1.0 / interval.as_secs_f32()),
+            facing_mode: None,
+        }])
+    }
+
+    /// Request a frame rate and return the interval the driver settled on
+    fn set_frame_rate(device: &Device, frame_rate: Option<f32>) -> Option<Duration> {
+        let mut parm: V4l2StreamParm = zeroed();
+        parm.type_ = V4L2_BUF_TYPE_VIDEO_CAPTURE;
+        device.ioctl(VIDIOC_G_PARM, &mut parm).ok()?;
+        // SAFETY: the driver fills the capture member for capture buffers
+        let mut capture = unsafe { parm.parm.capture };
+
+        if let Some(rate) = frame_rate.filter(|rate| *rate > 0.0) {
+            if capture.capability & V4L2_CAP_TIMEPERFRAME != 0 {
+                capture.timeperframe = V4l2Fract {
+                    numerator: 1000,
+                    denominator: (rate * 1000.0).round() as u32,
+                };
+                parm.parm.capture = capture;
+                if device.ioctl(VIDIOC_S_PARM, &mut parm).is_ok() {
+                    // SAFETY: S_PARM writes back the capture member
+                    capture = unsafe { parm.parm.capture };
+                }
+            }
+        }
+
+        let fract = capture.timeperframe;
+        if fract.numerator == 0 || fract.denominator == 0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            f64::from(fract.numerator) / f64::from(fract.denominator),
+        ))
+    }
+
+    /// Resolution and frame rate the driver settled on
+    fn settings(&self) -> CaptureSettings {
+        CaptureSettings {
+            width: self.width,
+            height: self.height,
+            frame_rate: self
+                .frame_interval
+                .map(|interval| 1.0 / interval.as_secs_f32()),
+            facing_mode: None,
+        }
+    }
+
+    fn buffer(index: u32) -> V4l2Buffer {
+        let mut buf: V4l2Buffer = zeroed();
+        buf.index = index;
+        buf.type_ = V4L2_BUF_TYPE_VIDEO_CAPTURE;
+        buf.memory = V4L2_MEMORY_MMAP;
+        buf
+    }
+
+    /// Dequeue, convert and requeue one frame
+    ///
+    /// Returns `Ok(None)` when no frame was ready.
+    fn next_frame(&self) -> io::Result<Option<(Vec<u8>, Duration)>> {
+        let mut buf = Self::buffer(0);
+        match self.device.ioctl(VIDIOC_DQBUF, &mut buf) {
+            Ok(()) => {}
+            Err(err) if err.raw_os_error() == Some(libc::EAGAIN) => return Ok(None),
+            Err(err) => return Err(err),
+        }
+
+        let data = self
+            .buffers
+            .get(buf.index as usize)
+            .map(|mapped| mapped.as_slice(buf.bytesused as usize))
+            .and_then(|yuyv| yuyv_to_yuv420(yuyv, self.width, self.height, self.stride));
+        let timestamp = Duration::from_secs(buf.timestamp.tv_sec.max(0) as u64)
+            + Duration::from_micros(buf.timestamp.tv_usec.max(0) as u64);
+
+        self.device.ioctl(VIDIOC_QBUF, &mut buf)?;
+        Ok(data.map(|data| (data, timestamp)))
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let mut buf_type = V4L2_BUF_TYPE_VIDEO_CAPTURE as c_int;
+        let _ = self.device.ioctl(VIDIOC_STREAMOFF, &mut buf_type);
+    }
+}
+
+/// Convert packed YUYV 4:2:2 to planar YUV 4:2:0
+///
+/// Chroma is averaged over each pair of rows. Returns `None` for an odd
+/// width, which YUYV cannot represent, or when `yuyv` is too short for the
+/// given dimensions and stride.
+pub(crate) fn yuyv_to_yuv420(yuyv: &[u8], width: u32, height: u32, stride: u32) -> Option<Vec<u8>> {
+    let (width, height, stride) = (width as usize, height as usize, stride as usize);
+    if width == 0 || height == 0 || width % 2 != 0 || stride < width * 2 {
+        return None;
+    }
+    if yuyv.len() < stride * (height - 1) + width * 2 {
+        return None;
+    }
+
+    let chroma_width = width / 2;
+    let chroma_height = height.div_ceil(2);
+    let mut out = vec![0u8; width * height + 2 * chroma_width * chroma_height];
+    let (y_plane, chroma) = out.split_at_mut(width * height);
+    let (u_plane, v_plane) = chroma.split_at_mut(chroma_width * chroma_height);
+
+    for row in 0..height {
+        let line = &yuyv[row * stride..row * stride + width * 2];
+        for (x, y) in y_plane[row * width..(row + 1) * width]
+            .iter_mut()
+            .enumerate()
+        {
+            *y = line[x * 2];
+        }
+    }
+
+    for cy in 0..chroma_height {
+        let top = &yuyv[cy * 2 * stride..];
+        let bottom = if cy * 2 + 1 < height {
+            &yuyv[(cy * 2 + 1) * stride..]
+        } else {
+            top
+        };
+        for cx in 0..chroma_width {
+            // Each 4-byte macropixel is Y0 U Y1 V
+            let u = (u16::from(top[cx * 4 + 1]) + u16::from(bottom[cx * 4 + 1])).div_ceil(2);
+            let v = (u16::from(top[cx * 4 + 3]) + u16::from(bottom[cx * 4 + 3])).div_ceil(2);
+            u_plane[cy * chroma_width + cx] = u as u8;
+            v_plane[cy * chroma_width + cx] = v as u8;
+        }
+    }
+
+    Some(out)
+}
+
+/// Camera capture from a V4L2 device node
+///
+/// Frames are captured on a dedicated thread and sent to the channel given
+/// to [`V4L2CameraCapture::start`] until [`V4L2CameraCapture::stop`] is
+/// called or the receiver is dropped.
+#[derive(Debug)]
+pub(crate) struct V4L2CameraCapture {
+    settings: CaptureSettings,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl V4L2CameraCapture {
+    /// Open `path`, select the mode best fitting `constraints` and start
+    /// streaming
+    pub(crate) fn start(
+        path: &str,
+        constraints: &CaptureConstraints,
+        sender: mpsc::Sender<VideoFrame>,
+    ) -> Result<Self, CaptureError> {
+        let stream = Stream::open(path, constraints)?;
+        let settings = stream.settings();
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+
+        let thread = std::thread::Builder::new()
+            .name("v4l2-capture".to_string())
+            .spawn(move || capture_loop(stream, sender, thread_stop))
+            .map_err(|_| CaptureError::CaptureFailure)?;
+
+        Ok(Self {
+            settings,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Resolution and frame rate negotiated with the device
+    pub(crate) fn settings(&self) -> CaptureSettings {
+        self.settings
+    }
+
+    /// Stop streaming and release the device
+    pub(crate) fn stop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for V4L2CameraCapture {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn capture_loop(stream: Stream, sender: mpsc::Sender<VideoFrame>, stop: Arc<AtomicBool>) {
+    let mut first_timestamp = None;
+    while !stop.load(Ordering::Acquire) {
+        match stream.device.poll(POLL_TIMEOUT_MS) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(_) => break,
+        }
+        let (data, timestamp) = match stream.next_frame() {
+            Ok(Some(frame)) => frame,
+            Ok(None) => continue,
+            Err(_) => break,
+        };
+
+        let start = *first_timestamp.get_or_insert(timestamp);
+        let mut frame = VideoFrame::new(
+            stream.width,
+            stream.height,
+            PixelFormat::YUV420,
+            data,
+            timestamp.saturating_sub(start),
+        );
+        frame.duration = stream.frame_interval;
+        if sender.blocking_send(frame).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_yuyv_to_yuv420_averages_chroma_rows() {
+        // 2x2 frame with a padded stride: Y0 U Y1 V per macropixel
+        let yuyv = [
+            10, 100, 20, 200, 0, 0, //
+            30, 110, 40, 210, 0, 0,
+        ];
+        let out = yuyv_to_yuv420(&yuyv, 2, 2, 6).unwrap();
+        assert_eq!(out, vec![10, 20, 30, 40, 105, 205]);
+    }
+
+    #[test]
+    fn test_yuyv_to_yuv420_rejects_short_input() {
+        assert!(yuyv_to_yuv420(&[0; 7], 2, 2, 4).is_none());
+        assert!(yuyv_to_yuv420(&[0; 8], 2, 2, 2).is_none());
+        assert!(yuyv_to_yuv420(&[0; 12], 3, 2, 6).is_none());
+    }
+}
@@ -0,0 +1,673 @@
+//! Wayland screen capture for Linux
+//!
+//! Captures outputs with the `wlr-screencopy-unstable-v1` protocol,
+//! supported by wlroots-based compositors. The Wayland wire protocol is
+//! spoken directly over the compositor socket: frames are copied into a
+//! shared-memory buffer created from a `memfd`.
+
+use crate::screen_capture::{to_rgba, ByteOrder, ScreenSource};
+use crate::{CaptureError, DisplayInfo, Rect};
+use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+use libc::{c_int, c_void};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::ptr;
+use std::time::Duration;
+
+/// Object ID of the `wl_display` singleton
+const DISPLAY_ID: u32 = 1;
+
+// Requests
+const WL_DISPLAY_SYNC: u16 = 0;
+const WL_DISPLAY_GET_REGISTRY: u16 = 1;
+const WL_REGISTRY_BIND: u16 = 0;
+const WL_SHM_CREATE_POOL: u16 = 0;
+const WL_SHM_POOL_CREATE_BUFFER: u16 = 0;
+const WL_SHM_POOL_DESTROY: u16 = 1;
+const WL_BUFFER_DESTROY: u16 = 0;
+const SCREENCOPY_MANAGER_CAPTURE_OUTPUT: u16 = 0;
+const SCREENCOPY_FRAME_COPY: u16 = 0;
+const SCREENCOPY_FRAME_DESTROY: u16 = 1;
+
+// Events
+const WL_DISPLAY_ERROR: u16 = 0;
+const WL_REGISTRY_GLOBAL: u16 = 0;
+const WL_CALLBACK_DONE: u16 = 0;
+const WL_OUTPUT_GEOMETRY: u16 = 0;
+const WL_OUTPUT_MODE: u16 = 1;
+const WL_OUTPUT_SCALE: u16 = 3;
+const SCREENCOPY_FRAME_BUFFER: u16 = 0;
+const SCREENCOPY_FRAME_FLAGS: u16 = 1;
+const SCREENCOPY_FRAME_READY: u16 = 2;
+const SCREENCOPY_FRAME_FAILED: u16 = 3;
+
+/// `WL_OUTPUT_MODE_CURRENT`
+const MODE_CURRENT: u32 = 1;
+
+/// `wl_output` version adding the `scale` event
+const WL_OUTPUT_SCALE_VERSION: u32 = 2;
+
+/// `ZWLR_SCREENCOPY_FRAME_V1_FLAGS_Y_INVERT`
+const FLAGS_Y_INVERT: u32 = 1;
+
+// `wl_shm` formats: DRM fourcc codes named by their little-endian word
+const WL_SHM_FORMAT_ARGB8888: u32 = 0;
+const WL_SHM_FORMAT_XRGB8888: u32 = 1;
+const WL_SHM_FORMAT_ABGR8888: u32 = 0x3432_4241;
+const WL_SHM_FORMAT_XBGR8888: u32 = 0x3432_4258;
+
+/// How long to wait for the compositor before giving up on a frame
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Request to send to the compositor
+struct Request {
+    bytes: Vec<u8>,
+}
+
+impl Request {
+    fn new(object: u32, opcode: u16) -> Self {
+        let mut bytes = Vec::with_capacity(32);
+        bytes.extend_from_slice(&object.to_ne_bytes());
+        bytes.extend_from_slice(&u32::from(opcode).to_ne_bytes());
+        Self { bytes }
+    }
+
+    fn uint(mut self, value: u32) -> Self {
+        self.bytes.extend_from_slice(&value.to_ne_bytes());
+        self
+    }
+
+    fn string(mut self, value: &str) -> Self {
+        let len = value.len() + 1;
+        self = self.uint(len as u32);
+        self.bytes.extend_from_slice(value.as_bytes());
+        self.bytes.resize(
+            self.bytes.len() + (len.next_multiple_of(4) - value.len()),
+            0,
+        );
+        self
+    }
+
+    /// Wire encoding with the message size filled in
+    fn finish(mut self) -> Vec<u8> {
+        let size = self.bytes.len() as u32;
+        let word = u32::from_ne_bytes([self.bytes[4], self.bytes[5], self.bytes[6], self.bytes[7]]);
+        self.bytes[4..8].copy_from_slice(&(size << 16 | word).to_ne_bytes());
+        self.bytes
+    }
+}
+
+/// Event received from the compositor
+struct Event {
+    object: u32,
+    opcode: u16,
+    args: Vec<u8>,
+    pos: usize,
+}
+
+impl Event {
+    fn uint(&mut self) -> Result<u32, CaptureError> {
+        let bytes = self
+            .args
+            .get(self.pos..self.pos + 4)
+            .ok_or(CaptureError::CaptureFailure)?;
+        self.pos += 4;
+        Ok(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn int(&mut self) -> Result<i32, CaptureError> {
+        self.uint().map(|value| value as i32)
+    }
+
+    fn string(&mut self) -> Result<String, CaptureError> {
+        let len = self.uint()? as usize;
+        let bytes = self
+            .args
+            .get(self.pos..self.pos + len)
+            .ok_or(CaptureError::CaptureFailure)?;
+        self.pos += len.next_multiple_of(4);
+        // Drop the terminating NUL
+        Ok(String::from_utf8_lossy(bytes.strip_suffix(&[0]).unwrap_or(bytes)).into_owned())
+    }
+}
+
+/// Client side of a Wayland connection
+struct Connection {
+    socket: UnixStream,
+    incoming: Vec<u8>,
+    next_id: u32,
+}
+
+impl Connection {
+    fn connect() -> Result<Self, CaptureError> {
+        let display = std::env::var_os("WAYLAND_DISPLAY").ok_or(CaptureError::DeviceNotFound)?;
+        let mut path = PathBuf::from(&display);
+        if path.is_relative() {
+            let runtime_dir =
+                std::env::var_os("XDG_RUNTIME_DIR").ok_or(CaptureError::DeviceNotFound)?;
+            path = PathBuf::from(runtime_dir).join(display);
+        }
+
+        let socket = UnixStream::connect(path).map_err(|e| match e.kind() {
+            io::ErrorKind::PermissionDenied => CaptureError::PermissionDenied,
+            _ => CaptureError::DeviceNotFound,
+        })?;
+        socket
+            .set_read_timeout(Some(READ_TIMEOUT))
+            .map_err(|_| CaptureError::CaptureFailure)?;
+        Ok(Self {
+            socket,
+            incoming: Vec::new(),
+            next_id: DISPLAY_ID + 1,
+        })
+    }
+
+    fn new_id(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn send(&mut self, request: Request) -> Result<(), CaptureError> {
+        self.socket
+            .write_all(&request.finish())
+            .map_err(|_| CaptureError::CaptureFailure)
+    }
+
+    /// Send a request carrying a file descriptor
+    fn send_fd(&mut self, request: Request, fd: RawFd) -> Result<(), CaptureError> {
+        let bytes = request.finish();
+        let mut iov = libc::iovec {
+            iov_base: bytes.as_ptr() as *mut c_void,
+            iov_len: bytes.len(),
+        };
+        // Large and aligned enough for one SCM_RIGHTS control message
+        let mut control = [0u64; 4];
+        // SAFETY: msghdr is plain data and all-zero is a valid value
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr().cast();
+        // SAFETY: CMSG_SPACE only computes a size
+        msg.msg_controllen = unsafe { libc::CMSG_SPACE(size_of::<c_int>() as u32) } as _;
+
+        // SAFETY: the control buffer holds one message with room for an fd
+        let sent = unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<c_int>() as u32) as _;
+            ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<c_int>(), fd);
+            libc::sendmsg(self.socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL)
+        };
+        if sent != bytes.len() as isize {
+            return Err(CaptureError::CaptureFailure);
+        }
+        Ok(())
+    }
+
+    /// Read the next event, failing on protocol errors
+    fn read_event(&mut self) -> Result<Event, CaptureError> {
+        loop {
+            if self.incoming.len() >= 8 {
+                let header = |i: usize| {
+                    u32::from_ne_bytes([
+                        self.incoming[i],
+                        self.incoming[i + 1],
+                        self.incoming[i + 2],
+                        self.incoming[i + 3],
+                    ])
+                };
+                let (object, word) = (header(0), header(4));
+                let size = (word >> 16) as usize;
+                if size < 8 {
+                    return Err(CaptureError::CaptureFailure);
+                }
+                if self.incoming.len() >= size {
+                    let args = self.incoming[8..size].to_vec();
+                    self.incoming.drain(..size);
+                    let opcode = (word & 0xFFFF) as u16;
+                    if object == DISPLAY_ID && opcode == WL_DISPLAY_ERROR {
+                        return Err(CaptureError::CaptureFailure);
+                    }
+                    return Ok(Event {
+                        object,
+                        opcode,
+                        args,
+                        pos: 0,
+                    });
+                }
+            }
+
+            let mut chunk = [0u8; 4096];
+            match self.socket.read(&mut chunk) {
+                Ok(0) | Err(_) => return Err(CaptureError::CaptureFailure),
+                Ok(n) => self.incoming.extend_from_slice(&chunk[..n]),
+            }
+        }
+    }
+
+    /// Send `wl_display.sync` and handle events until it completes
+    fn roundtrip(
+        &mut self,
+        mut handle: impl FnMut(&mut Event) -> Result<(), CaptureError>,
+    ) -> Result<(), CaptureError> {
+        let callback = self.new_id();
+        self.send(Request::new(DISPLAY_ID, WL_DISPLAY_SYNC).uint(callback))?;
+        loop {
+            let mut event = self.read_event()?;
+            if event.object == callback && event.opcode == WL_CALLBACK_DONE {
+                return Ok(());
+            }
+            handle(&mut event)?;
+        }
+    }
+
+    /// Bind a global announced by the registry
+    fn bind(
+        &mut self,
+        registry: u32,
+        name: u32,
+        interface: &str,
+        version: u32,
+    ) -> Result<u32, CaptureError> {
+        let id = self.new_id();
+        self.send(
+            Request::new(registry, WL_REGISTRY_BIND)
+                .uint(name)
+                .string(interface)
+                .uint(version)
+                .uint(id),
+        )?;
+        Ok(id)
+    }
+}
+
+/// `wl_buffer` backed by a mapped `memfd`
+struct ShmBuffer {
+    id: u32,
+    _fd: OwnedFd,
+    map: *mut c_void,
+    size: usize,
+    format: u32,
+    width: u32,
+    height: u32,
+    stride: u32,
+}
+
+impl ShmBuffer {
+    fn create(
+        connection: &mut Connection,
+        shm: u32,
+        format: u32,
+        width: u32,
+        height: u32,
+        stride: u32,
+    ) -> Result<Self, CaptureError> {
+        let size = stride as usize * height as usize;
+        if size == 0 || size > i32::MAX as usize {
+            return Err(CaptureError::CaptureFailure);
+        }
+
+        // SAFETY: the name is NUL-terminated and a new fd is owned on success
+        let fd = unsafe {
+            let fd = libc::memfd_create(c"screencopy".as_ptr(), libc::MFD_CLOEXEC);
+            if fd < 0 {
+                return Err(CaptureError::CaptureFailure);
+            }
+            OwnedFd::from_raw_fd(fd)
+        };
+        // SAFETY: `fd` is a valid memfd and the mapping covers its new size
+        let map = unsafe {
+            if libc::ftruncate(fd.as_raw_fd(), size as libc::off_t) != 0 {
+                return Err(CaptureError::CaptureFailure);
+            }
+            libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(CaptureError::CaptureFailure);
+        }
+        let mut buffer = Self {
+            id: 0,
+            _fd: fd,
+            map,
+            size,
+            format,
+            width,
+            height,
+            stride,
+        };
+
+        let pool = connection.new_id();
+        connection.send_fd(
+            Request::new(shm, WL_SHM_CREATE_POOL)
+                .uint(pool)
+                .uint(size as u32),
+            buffer._fd.as_raw_fd(),
+        )?;
+        buffer.id = connection.new_id();
+        connection.send(
+            Request::new(pool, WL_SHM_POOL_CREATE_BUFFER)
+                .uint(buffer.id)
+                .uint(0)
+                .uint(width)
+                .uint(height)
+                .uint(stride)
+                .uint(format),
+        )?;
+        // The buffer keeps the pool's memory alive
+        connection.send(Request::new(pool, WL_SHM_POOL_DESTROY))?;
+        Ok(buffer)
+    }
+
+    fn matches(&self, format: u32, width: u32, height: u32, stride: u32) -> bool {
+        (self.format, self.width, self.height, self.stride) == (format, width, height, stride)
+    }
+
+    fn data(&self) -> &[u8] {
+        // SAFETY: the mapping is `size` bytes and lives as long as `self`
+        unsafe { std::slice::from_raw_parts(self.map.cast::<u8>(), self.size) }
+    }
+}
+
+impl Drop for ShmBuffer {
+    fn drop(&mut self) {
+        // SAFETY: the mapping came from a successful mmap of `size` bytes
+        unsafe {
+            libc::munmap(self.map, self.size);
+        }
+    }
+}
+
+/// Describe the outputs of the compositor named by `WAYLAND_DISPLAY`
+///
+/// Outputs are listed in the order the compositor announces them, with
+/// their position in the compositor's layout and the size of their current
+/// mode.
+pub(crate) fn list_outputs() -> Result<Vec<DisplayInfo>, CaptureError> {
+    let mut connection = Connection::connect()?;
+    let registry = connection.new_id();
+    connection.send(Request::new(DISPLAY_ID, WL_DISPLAY_GET_REGISTRY).uint(registry))?;
+
+    let mut globals = Vec::new();
+    connection.roundtrip(|event| {
+        if event.object == registry && event.opcode == WL_REGISTRY_GLOBAL {
+            let name = event.uint()?;
+            if event.string()? == "wl_output" {
+                globals.push((name, event.uint()?));
+            }
+        }
+        Ok(())
+    })?;
+
+    let outputs = globals
+        .into_iter()
+        .map(|(name, version)| {
+            let version = version.min(WL_OUTPUT_SCALE_VERSION);
+            connection.bind(registry, name, "wl_output", version)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut displays: Vec<DisplayInfo> = (0..outputs.len())
+        .map(|index| DisplayInfo {
+            id: index as u32,
+            bounds: Rect::new(0, 0, 0, 0),
+            scale_factor: 1.0,
+        })
+        .collect();
+
+    // Outputs describe themselves once bound
+    connection.roundtrip(|event| {
+        let Some(index) = outputs.iter().position(|&output| output == event.object) else {
+            return Ok(());
+        };
+        let display = &mut displays[index];
+        match event.opcode {
+            WL_OUTPUT_GEOMETRY => {
+                display.bounds.x = event.int()?;
+                display.bounds.y = event.int()?;
+            }
+            WL_OUTPUT_MODE => {
+                let (flags, width, height) = (event.uint()?, event.uint()?, event.uint()?);
+                if flags & MODE_CURRENT != 0 {
+                    display.bounds.width = width;
+                    display.bounds.height = height;
+                }
+            }
+            WL_OUTPUT_SCALE => display.scale_factor = f64::from(event.int()?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    Ok(displays)
+}
+
+/// Screen capture from a Wayland output
+///
+/// Connects to the compositor named by `WAYLAND_DISPLAY` and captures
+/// output `display_id`, counted in the order the compositor announces its
+/// outputs. Opening fails with `CaptureFailure` if the compositor does not
+/// support `wlr-screencopy`.
+pub(crate) struct WaylandScreenCapture {
+    connection: Connection,
+    shm: u32,
+    manager: u32,
+    output: u32,
+    size: (Option<u32>, Option<u32>),
+    /// Whether the compositor draws the cursor into frames
+    cursor: bool,
+    buffer: Option<ShmBuffer>,
+}
+
+// SAFETY: the shared-memory mapping is owned by the capture and only
+// accessed through it
+unsafe impl Send for WaylandScreenCapture {}
+
+impl WaylandScreenCapture {
+    /// Connect to the compositor and select output `display_id`
+    ///
+    /// `size` limits the captured region, taken from the top-left corner of
+    /// the output, and the compositor draws the cursor in when `cursor` is
+    /// set.
+    pub(crate) fn open(
+        display_id: u32,
+        size: (Option<u32>, Option<u32>),
+        cursor: bool,
+    ) -> Result<Self, CaptureError> {
+        let mut connection = Connection::connect()?;
+        let registry = connection.new_id();
+        connection.send(Request::new(DISPLAY_ID, WL_DISPLAY_GET_REGISTRY).uint(registry))?;
+
+        let (mut shm, mut manager, mut outputs) = (None, None, Vec::new());
+        connection.roundtrip(|event| {
+            if event.object == registry && event.opcode == WL_REGISTRY_GLOBAL {
+                let name = event.uint()?;
+                match event.string()?.as_str() {
+                    "wl_shm" => shm = Some(name),
+                    "zwlr_screencopy_manager_v1" => manager = Some(name),
+                    "wl_output" => outputs.push(name),
+                    _ => {}
+                }
+            }
+            Ok(())
+        })?;
+
+        let output = *outputs
+            .get(display_id as usize)
+            .ok_or(CaptureError::DeviceNotFound)?;
+        let (Some(shm), Some(manager)) = (shm, manager) else {
+            return Err(CaptureError::CaptureFailure);
+        };
+        let shm = connection.bind(registry, shm, "wl_shm", 1)?;
+        let manager = connection.bind(registry, manager, "zwlr_screencopy_manager_v1", 1)?;
+        let output = connection.bind(registry, output, "wl_output", 1)?;
+        // Surface bind errors before the first frame
+        connection.roundtrip(|_| Ok(()))?;
+
+        Ok(Self {
+            connection,
+            shm,
+            manager,
+            output,
+            size,
+            cursor,
+            buffer: None,
+        })
+    }
+
+    /// Copy one frame into the shared buffer
+    ///
+    /// Returns the buffer and whether its rows are stored bottom-up.
+    fn copy_frame(&mut self) -> Result<(&ShmBuffer, bool), CaptureError> {
+        let frame = self.connection.new_id();
+        self.connection.send(
+            Request::new(self.manager, SCREENCOPY_MANAGER_CAPTURE_OUTPUT)
+                .uint(frame)
+                .uint(u32::from(self.cursor))
+                .uint(self.output),
+        )?;
+
+        let mut y_invert = false;
+        let result = loop {
+            let mut event = self.connection.read_event()?;
+            if event.object != frame {
+                continue;
+            }
+            match event.opcode {
+                SCREENCOPY_FRAME_BUFFER => {
+                    let format = event.uint()?;
+                    let (width, height, stride) = (event.uint()?, event.uint()?, event.uint()?);
+                    let reuse = self
+                        .buffer
+                        .as_ref()
+                        .is_some_and(|buffer| buffer.matches(format, width, height, stride));
+                    if !reuse {
+                        if let Some(old) = self.buffer.take() {
+                            self.connection
+                                .send(Request::new(old.id, WL_BUFFER_DESTROY))?;
+                        }
+                        self.buffer = Some(ShmBuffer::create(
+                            &mut self.connection,
+                            self.shm,
+                            format,
+                            width,
+                            height,
+                            stride,
+                        )?);
+                    }
+                    let buffer = self.buffer.as_ref().map_or(0, |buffer| buffer.id);
+                    self.connection
+                        .send(Request::new(frame, SCREENCOPY_FRAME_COPY).uint(buffer))?;
+                }
+                SCREENCOPY_FRAME_FLAGS => y_invert = event.uint()? & FLAGS_Y_INVERT != 0,
+                SCREENCOPY_FRAME_READY => break Ok(()),
+                SCREENCOPY_FRAME_FAILED => break Err(CaptureError::CaptureFailure),
+                _ => {}
+            }
+        };
+        self.connection
+            .send(Request::new(frame, SCREENCOPY_FRAME_DESTROY))?;
+        result?;
+
+        let buffer = self.buffer.as_ref().ok_or(CaptureError::CaptureFailure)?;
+        Ok((buffer, y_invert))
+    }
+}
+
+impl ScreenSource for WaylandScreenCapture {
+    fn grab(&mut self) -> Result<Option<VideoFrame>, CaptureError> {
+        let (limit_width, limit_height) = self.size;
+        let (buffer, y_invert) = self.copy_frame()?;
+        let order = match buffer.format {
+            WL_SHM_FORMAT_ARGB8888 | WL_SHM_FORMAT_XRGB8888 => ByteOrder::Bgra,
+            WL_SHM_FORMAT_ABGR8888 | WL_SHM_FORMAT_XBGR8888 => ByteOrder::Rgba,
+            _ => return Err(CaptureError::CaptureFailure),
+        };
+
+        let clamp =
+            |limit: Option<u32>, size: u32| limit.map_or(size, |limit| limit.clamp(1, size));
+        let width = clamp(limit_width, buffer.width);
+        let height = clamp(limit_height, buffer.height);
+        // Crop from the top of the image as displayed
+        let skip = if y_invert {
+            (buffer.height - height) as usize * buffer.stride as usize
+        } else {
+            0
+        };
+        let rgba = to_rgba(
+            &buffer.data()[skip..],
+            buffer.stride as usize,
+            width,
+            height,
+            order,
+            y_invert,
+        )
+        .ok_or(CaptureError::CaptureFailure)?;
+
+        Ok(Some(VideoFrame::new(
+            width,
+            height,
+            PixelFormat::RGBA32,
+            rgba,
+            Duration::ZERO,
+        )))
+    }
+}
+
+impl Drop for WaylandScreenCapture {
+    fn drop(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            let _ = self
+                .connection
+                .send(Request::new(buffer.id, WL_BUFFER_DESTROY));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_encodes_size_and_padded_string() {
+        let bytes = Request::new(2, WL_REGISTRY_BIND)
+            .uint(7)
+            .string("wl_shm")
+            .uint(1)
+            .finish();
+        assert_eq!(bytes.len(), 28);
+        assert_eq!(&bytes[0..4], &2u32.to_ne_bytes());
+        assert_eq!(&bytes[4..8], &(28u32 << 16).to_ne_bytes());
+        assert_eq!(&bytes[8..12], &7u32.to_ne_bytes());
+        // Length includes the terminating NUL
+        assert_eq!(&bytes[12..16], &7u32.to_ne_bytes());
+        assert_eq!(&bytes[16..24], b"wl_shm\0\0");
+        assert_eq!(&bytes[24..28], &1u32.to_ne_bytes());
+    }
+
+    #[test]
+    fn test_event_decodes_global() {
+        let mut args = 5u32.to_ne_bytes().to_vec();
+        args.extend_from_slice(&7u32.to_ne_bytes());
+        args.extend_from_slice(b"wl_shm\0\0");
+        args.extend_from_slice(&1u32.to_ne_bytes());
+        let mut event = Event {
+            object: 2,
+            opcode: WL_REGISTRY_GLOBAL,
+            args,
+            pos: 0,
+        };
+        assert_eq!(event.uint().unwrap(), 5);
+        assert_eq!(event.string().unwrap(), "wl_shm");
+        assert_eq!(event.uint().unwrap(), 1);
+        assert!(event.uint().is_err());
+    }
+}
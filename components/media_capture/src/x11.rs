@@ -0,0 +1,809 @@
+//! X11 screen capture for Linux
+//!
+//! Grabs the root window of an X screen with `xcb_get_image` in Z-pixmap
+//! format. Top-level windows are found through the window manager's
+//! `_NET_CLIENT_LIST` and captured from the part of the root window they
+//! cover, and the cursor is drawn in from the XFixes extension. `libxcb`
+//! and `libxcb-xfixes` are loaded at runtime; opening a capture fails on
+//! systems without them.
+
+use crate::screen_capture::{draw_cursor, to_rgba, ByteOrder, ScreenSource, SourceId};
+use crate::{CaptureError, CaptureSource, CaptureSourceKind, DisplayInfo, Rect};
+use cortenbrowser_shared_types::{Library, PixelFormat, VideoFrame};
+use libc::{c_char, c_int, c_uint, c_void};
+use std::ptr;
+use std::time::Duration;
+
+/// `XCB_IMAGE_FORMAT_Z_PIXMAP`
+const XCB_IMAGE_FORMAT_Z_PIXMAP: u8 = 2;
+
+/// `XCB_IMAGE_ORDER_LSB_FIRST`
+const XCB_IMAGE_ORDER_LSB_FIRST: u8 = 0;
+
+/// `XCB_ATOM_ANY`, matching properties of any type
+const XCB_ATOM_ANY: u32 = 0;
+
+/// `XCB_ATOM_WINDOW`
+const XCB_ATOM_WINDOW: u32 = 33;
+
+/// `XCB_ATOM_WM_NAME`
+const XCB_ATOM_WM_NAME: u32 = 39;
+
+/// `XCB_MAP_STATE_VIEWABLE`
+const XCB_MAP_STATE_VIEWABLE: u8 = 2;
+
+/// Most windows read from `_NET_CLIENT_LIST`
+const MAX_CLIENTS: u32 = 4096;
+
+/// Longest window title read, in 32-bit units
+const MAX_TITLE_LONGS: u32 = 1024;
+
+#[repr(C)]
+struct XcbConnection {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct XcbSetup {
+    status: u8,
+    pad0: u8,
+    protocol_major_version: u16,
+    protocol_minor_version: u16,
+    length: u16,
+    release_number: u32,
+    resource_id_base: u32,
+    resource_id_mask: u32,
+    motion_buffer_size: u32,
+    vendor_len: u16,
+    maximum_request_length: u16,
+    roots_len: u8,
+    pixmap_formats_len: u8,
+    image_byte_order: u8,
+}
+
+#[repr(C)]
+struct XcbScreen {
+    root: u32,
+    default_colormap: u32,
+    white_pixel: u32,
+    black_pixel: u32,
+    current_input_masks: u32,
+    width_in_pixels: u16,
+    height_in_pixels: u16,
+    width_in_millimeters: u16,
+    height_in_millimeters: u16,
+    min_installed_maps: u16,
+    max_installed_maps: u16,
+    root_visual: u32,
+    backing_stores: u8,
+    save_unders: u8,
+    root_depth: u8,
+    allowed_depths_len: u8,
+}
+
+#[repr(C)]
+struct XcbScreenIterator {
+    data: *mut XcbScreen,
+    rem: c_int,
+    index: c_int,
+}
+
+/// Sequence number of a request, used to wait for its reply
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct XcbCookie {
+    sequence: c_uint,
+}
+
+#[repr(C)]
+struct XcbGetImageReply {
+    response_type: u8,
+    depth: u8,
+    sequence: u16,
+    length: u32,
+    visual: u32,
+    pad0: [u8; 20],
+}
+
+#[repr(C)]
+struct XcbInternAtomReply {
+    response_type: u8,
+    pad0: u8,
+    sequence: u16,
+    length: u32,
+    atom: u32,
+}
+
+#[repr(C)]
+struct XcbGetPropertyReply {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct XcbGetGeometryReply {
+    response_type: u8,
+    depth: u8,
+    sequence: u16,
+    length: u32,
+    root: u32,
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+    border_width: u16,
+    pad0: [u8; 2],
+}
+
+#[repr(C)]
+struct XcbTranslateCoordinatesReply {
+    response_type: u8,
+    same_screen: u8,
+    sequence: u16,
+    length: u32,
+    child: u32,
+    dst_x: i16,
+    dst_y: i16,
+}
+
+#[repr(C)]
+struct XcbGetWindowAttributesReply {
+    response_type: u8,
+    backing_store: u8,
+    sequence: u16,
+    length: u32,
+    visual: u32,
+    class: u16,
+    bit_gravity: u8,
+    win_gravity: u8,
+    backing_planes: u32,
+    backing_pixel: u32,
+    save_under: u8,
+    map_is_installed: u8,
+    map_state: u8,
+}
+
+#[repr(C)]
+struct XcbQueryExtensionReply {
+    response_type: u8,
+    pad0: u8,
+    sequence: u16,
+    length: u32,
+    present: u8,
+    major_opcode: u8,
+    first_event: u8,
+    first_error: u8,
+}
+
+#[repr(C)]
+struct XcbExtension {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct XcbXfixesQueryVersionReply {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct XcbXfixesGetCursorImageReply {
+    response_type: u8,
+    pad0: u8,
+    sequence: u16,
+    length: u32,
+    x: i16,
+    y: i16,
+    width: u16,
+    height: u16,
+    xhot: u16,
+    yhot: u16,
+    cursor_serial: u32,
+    pad1: [u8; 8],
+}
+
+type XcbConnectFn = unsafe extern "C" fn(*const c_char, *mut c_int) -> *mut XcbConnection;
+type XcbConnectionHasErrorFn = unsafe extern "C" fn(*mut XcbConnection) -> c_int;
+type XcbDisconnectFn = unsafe extern "C" fn(*mut XcbConnection);
+type XcbGetSetupFn = unsafe extern "C" fn(*mut XcbConnection) -> *const XcbSetup;
+type XcbSetupRootsIteratorFn = unsafe extern "C" fn(*const XcbSetup) -> XcbScreenIterator;
+type XcbScreenNextFn = unsafe extern "C" fn(*mut XcbScreenIterator);
+/// `xcb_*_reply` for a reply of type `T`
+type XcbReplyFn<T> =
+    unsafe extern "C" fn(*mut XcbConnection, XcbCookie, *mut *mut c_void) -> *mut T;
+type XcbGetImageFn =
+    unsafe extern "C" fn(*mut XcbConnection, u8, u32, i16, i16, u16, u16, u32) -> XcbCookie;
+type XcbGetImageDataFn = unsafe extern "C" fn(*const XcbGetImageReply) -> *mut u8;
+type XcbGetImageDataLengthFn = unsafe extern "C" fn(*const XcbGetImageReply) -> c_int;
+type XcbInternAtomFn =
+    unsafe extern "C" fn(*mut XcbConnection, u8, u16, *const c_char) -> XcbCookie;
+type XcbGetPropertyFn =
+    unsafe extern "C" fn(*mut XcbConnection, u8, u32, u32, u32, u32, u32) -> XcbCookie;
+type XcbGetPropertyValueFn = unsafe extern "C" fn(*const XcbGetPropertyReply) -> *mut c_void;
+type XcbGetPropertyValueLengthFn = unsafe extern "C" fn(*const XcbGetPropertyReply) -> c_int;
+type XcbWindowFn = unsafe extern "C" fn(*mut XcbConnection, u32) -> XcbCookie;
+type XcbTranslateCoordinatesFn =
+    unsafe extern "C" fn(*mut XcbConnection, u32, u32, i16, i16) -> XcbCookie;
+type XcbGetExtensionDataFn =
+    unsafe extern "C" fn(*mut XcbConnection, *mut XcbExtension) -> *const XcbQueryExtensionReply;
+type XcbXfixesQueryVersionFn = unsafe extern "C" fn(*mut XcbConnection, u32, u32) -> XcbCookie;
+type XcbXfixesGetCursorImageFn = unsafe extern "C" fn(*mut XcbConnection) -> XcbCookie;
+type XcbXfixesCursorImageFn = unsafe extern "C" fn(*const XcbXfixesGetCursorImageReply) -> *mut u32;
+type XcbXfixesCursorImageLengthFn =
+    unsafe extern "C" fn(*const XcbXfixesGetCursorImageReply) -> c_int;
+
+/// libxcb entry points used for capture
+struct XcbApi {
+    connect: XcbConnectFn,
+    connection_has_error: XcbConnectionHasErrorFn,
+    disconnect: XcbDisconnectFn,
+    get_setup: XcbGetSetupFn,
+    setup_roots_iterator: XcbSetupRootsIteratorFn,
+    screen_next: XcbScreenNextFn,
+    get_image: XcbGetImageFn,
+    get_image_reply: XcbReplyFn<XcbGetImageReply>,
+    get_image_data: XcbGetImageDataFn,
+    get_image_data_length: XcbGetImageDataLengthFn,
+    intern_atom: XcbInternAtomFn,
+    intern_atom_reply: XcbReplyFn<XcbInternAtomReply>,
+    get_property: XcbGetPropertyFn,
+    get_property_reply: XcbReplyFn<XcbGetPropertyReply>,
+    get_property_value: XcbGetPropertyValueFn,
+    get_property_value_length: XcbGetPropertyValueLengthFn,
+    get_geometry: XcbWindowFn,
+    get_geometry_reply: XcbReplyFn<XcbGetGeometryReply>,
+    translate_coordinates: XcbTranslateCoordinatesFn,
+    translate_coordinates_reply: XcbReplyFn<XcbTranslateCoordinatesReply>,
+    get_window_attributes: XcbWindowFn,
+    get_window_attributes_reply: XcbReplyFn<XcbGetWindowAttributesReply>,
+    get_extension_data: XcbGetExtensionDataFn,
+}
+
+impl XcbApi {
+    fn load(xcb: &Library) -> Result<Self, CaptureError> {
+        // SAFETY: the function types match the libxcb C declarations
+        unsafe {
+            Ok(Self {
+                connect: xcb.symbol(c"xcb_connect")?,
+                connection_has_error: xcb.symbol(c"xcb_connection_has_error")?,
+                disconnect: xcb.symbol(c"xcb_disconnect")?,
+                get_setup: xcb.symbol(c"xcb_get_setup")?,
+                setup_roots_iterator: xcb.symbol(c"xcb_setup_roots_iterator")?,
+                screen_next: xcb.symbol(c"xcb_screen_next")?,
+                get_image: xcb.symbol(c"xcb_get_image")?,
+                get_image_reply: xcb.symbol(c"xcb_get_image_reply")?,
+                get_image_data: xcb.symbol(c"xcb_get_image_data")?,
+                get_image_data_length: xcb.symbol(c"xcb_get_image_data_length")?,
+                intern_atom: xcb.symbol(c"xcb_intern_atom")?,
+                intern_atom_reply: xcb.symbol(c"xcb_intern_atom_reply")?,
+                get_property: xcb.symbol(c"xcb_get_property")?,
+                get_property_reply: xcb.symbol(c"xcb_get_property_reply")?,
+                get_property_value: xcb.symbol(c"xcb_get_property_value")?,
+                get_property_value_length: xcb.symbol(c"xcb_get_property_value_length")?,
+                get_geometry: xcb.symbol(c"xcb_get_geometry")?,
+                get_geometry_reply: xcb.symbol(c"xcb_get_geometry_reply")?,
+                translate_coordinates: xcb.symbol(c"xcb_translate_coordinates")?,
+                translate_coordinates_reply: xcb.symbol(c"xcb_translate_coordinates_reply")?,
+                get_window_attributes: xcb.symbol(c"xcb_get_window_attributes")?,
+                get_window_attributes_reply: xcb.symbol(c"xcb_get_window_attributes_reply")?,
+                get_extension_data: xcb.symbol(c"xcb_get_extension_data")?,
+            })
+        }
+    }
+}
+
+/// libxcb-xfixes entry points used to draw the cursor
+struct XfixesApi {
+    extension: *mut XcbExtension,
+    query_version: XcbXfixesQueryVersionFn,
+    query_version_reply: XcbReplyFn<XcbXfixesQueryVersionReply>,
+    get_cursor_image: XcbXfixesGetCursorImageFn,
+    get_cursor_image_reply: XcbReplyFn<XcbXfixesGetCursorImageReply>,
+    cursor_image: XcbXfixesCursorImageFn,
+    cursor_image_length: XcbXfixesCursorImageLengthFn,
+}
+
+impl XfixesApi {
+    fn load(xfixes: &Library) -> Result<Self, CaptureError> {
+        // SAFETY: the function types match the libxcb-xfixes C declarations,
+        // and `xcb_xfixes_id` is the extension's static descriptor
+        unsafe {
+            Ok(Self {
+                extension: xfixes.symbol(c"xcb_xfixes_id")?,
+                query_version: xfixes.symbol(c"xcb_xfixes_query_version")?,
+                query_version_reply: xfixes.symbol(c"xcb_xfixes_query_version_reply")?,
+                get_cursor_image: xfixes.symbol(c"xcb_xfixes_get_cursor_image")?,
+                get_cursor_image_reply: xfixes.symbol(c"xcb_xfixes_get_cursor_image_reply")?,
+                cursor_image: xfixes.symbol(c"xcb_xfixes_get_cursor_image_cursor_image")?,
+                cursor_image_length: xfixes
+                    .symbol(c"xcb_xfixes_get_cursor_image_cursor_image_length")?,
+            })
+        }
+    }
+}
+
+/// XFixes extension of a connection, for reading the cursor image
+struct Xfixes {
+    api: XfixesApi,
+    // Keeps the function pointers in `api` valid
+    _library: Library,
+}
+
+/// Reply to an xcb request, freed when dropped
+struct Reply<T>(*mut T);
+
+impl<T> std::ops::Deref for Reply<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: replies are only created from non-null xcb replies
+        unsafe { &*self.0 }
+    }
+}
+
+impl<T> Drop for Reply<T> {
+    fn drop(&mut self) {
+        // SAFETY: replies are allocated by libxcb with malloc
+        unsafe { libc::free(self.0.cast()) };
+    }
+}
+
+/// Cursor image in premultiplied ARGB, positioned on the root window
+struct CursorImage {
+    /// Position of the image's top-left corner
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    pixels: Vec<u32>,
+}
+
+/// What an [`X11ScreenCapture`] grabs
+#[derive(Clone, Copy)]
+enum Target {
+    /// The top-left part of the root window
+    Screen,
+    /// A top-level window, from the part of the root window it covers
+    Window(u32),
+}
+
+/// Screen capture from an X11 screen or window
+///
+/// Connects to the server named by `DISPLAY` and captures the root window
+/// of screen `display_id`, or a window on it, which must use 24 or 32-bit
+/// TrueColor pixels with blue in the lowest byte as all common servers do.
+pub(crate) struct X11ScreenCapture {
+    api: XcbApi,
+    connection: *mut XcbConnection,
+    root: u32,
+    width: u16,
+    height: u16,
+    target: Target,
+    /// Set when the cursor is drawn into frames
+    xfixes: Option<Xfixes>,
+    // Keeps the function pointers in `api` valid
+    _xcb: Library,
+}
+
+// SAFETY: xcb connections are thread-safe and the capture owns its connection
+unsafe impl Send for X11ScreenCapture {}
+
+impl X11ScreenCapture {
+    /// Connect to the X server and select screen `display_id`
+    ///
+    /// `size` limits the captured region, taken from the top-left corner of
+    /// the screen, and the cursor is drawn in when `cursor` is set and the
+    /// server supports XFixes.
+    pub(crate) fn open(
+        display_id: u32,
+        size: (Option<u32>, Option<u32>),
+        cursor: bool,
+    ) -> Result<Self, CaptureError> {
+        let mut capture = Self::connect()?;
+        capture.select_screen(display_id, size)?;
+        if cursor {
+            capture.xfixes = capture.load_xfixes();
+        }
+        Ok(capture)
+    }
+
+    /// Connect to the X server and select top-level window `window`
+    ///
+    /// Frames are the size of the window, with the parts outside its screen
+    /// black. Fails with `DeviceNotFound` if there is no such window.
+    pub(crate) fn open_window(window: u32, cursor: bool) -> Result<Self, CaptureError> {
+        let mut capture = Self::connect()?;
+        // SAFETY: the connection is live; an unknown window fails the request
+        let geometry = unsafe {
+            let cookie = (capture.api.get_geometry)(capture.connection, window);
+            capture.wait(capture.api.get_geometry_reply, cookie)
+        }
+        .ok_or(CaptureError::DeviceNotFound)?;
+
+        let display_id = capture
+            .screens()
+            .iter()
+            .position(|screen| screen.root == geometry.root)
+            .ok_or(CaptureError::DeviceNotFound)?;
+        capture.select_screen(display_id as u32, (None, None))?;
+        capture.target = Target::Window(window);
+        if cursor {
+            capture.xfixes = capture.load_xfixes();
+        }
+        Ok(capture)
+    }
+
+    /// Connect to the X server named by `DISPLAY`
+    fn connect() -> Result<Self, CaptureError> {
+        let xcb = Library::open(c"libxcb.so.1").ok_or(CaptureError::CaptureFailure)?;
+        let api = XcbApi::load(&xcb)?;
+
+        // SAFETY: a null display name selects `DISPLAY`
+        let connection = unsafe { (api.connect)(ptr::null(), ptr::null_mut()) };
+        // SAFETY: xcb_connect never returns null, errors are reported on the
+        // returned connection which must still be disconnected
+        if unsafe { (api.connection_has_error)(connection) } != 0 {
+            unsafe { (api.disconnect)(connection) };
+            return Err(CaptureError::DeviceNotFound);
+        }
+
+        Ok(Self {
+            api,
+            connection,
+            root: 0,
+            width: 0,
+            height: 0,
+            target: Target::Screen,
+            xfixes: None,
+            _xcb: xcb,
+        })
+    }
+
+    /// Wait for the reply to a request, or `None` if the request failed
+    ///
+    /// # Safety
+    ///
+    /// `reply` must be the reply function of the request `cookie` came from.
+    unsafe fn wait<T>(&self, reply: XcbReplyFn<T>, cookie: XcbCookie) -> Option<Reply<T>> {
+        let mut error = ptr::null_mut();
+        let reply = reply(self.connection, cookie, &mut error);
+        if !error.is_null() {
+            // SAFETY: errors are allocated by libxcb with malloc
+            libc::free(error);
+        }
+        (!reply.is_null()).then(|| Reply(reply))
+    }
+
+    /// Screens of the server, in order
+    fn screens(&self) -> Vec<&XcbScreen> {
+        let mut screens = Vec::new();
+        // SAFETY: the setup and screens are owned by the live connection
+        unsafe {
+            let setup = (self.api.get_setup)(self.connection);
+            let mut iter = (self.api.setup_roots_iterator)(setup);
+            while iter.rem > 0 && !iter.data.is_null() {
+                screens.push(&*iter.data);
+                (self.api.screen_next)(&mut iter);
+            }
+        }
+        screens
+    }
+
+    /// Set up XFixes for reading the cursor, if the server supports it
+    fn load_xfixes(&self) -> Option<Xfixes> {
+        let library = Library::open(c"libxcb-xfixes.so.0")?;
+        let api = XfixesApi::load(&library).ok()?;
+        // SAFETY: the connection is live. Requests to a missing extension
+        // would close the connection, so its presence is checked first, and
+        // XFixes needs its version negotiated before any other request.
+        unsafe {
+            let extension = (self.api.get_extension_data)(self.connection, api.extension);
+            if extension.is_null() || (*extension).present == 0 {
+                return None;
+            }
+            let cookie = (api.query_version)(self.connection, 4, 0);
+            self.wait(api.query_version_reply, cookie)?;
+        }
+        Some(Xfixes {
+            api,
+            _library: library,
+        })
+    }
+
+    /// Look up an atom the server already knows, or `None`
+    fn atom(&self, name: &str) -> Option<u32> {
+        // SAFETY: the connection is live and `name` outlives the request
+        let reply = unsafe {
+            let cookie =
+                (self.api.intern_atom)(self.connection, 1, name.len() as u16, name.as_ptr().cast());
+            self.wait(self.api.intern_atom_reply, cookie)?
+        };
+        (reply.atom != 0).then_some(reply.atom)
+    }
+
+    /// Read up to `longs` 32-bit units of a window property
+    fn property(&self, window: u32, property: u32, kind: u32, longs: u32) -> Option<Vec<u8>> {
+        // SAFETY: the connection is live, and the value is copied out of the
+        // reply before it is freed
+        unsafe {
+            let cookie =
+                (self.api.get_property)(self.connection, 0, window, property, kind, 0, longs);
+            let reply = self.wait(self.api.get_property_reply, cookie)?;
+            let value = (self.api.get_property_value)(reply.0);
+            let len = (self.api.get_property_value_length)(reply.0).max(0) as usize;
+            if value.is_null() {
+                return Some(Vec::new());
+            }
+            Some(std::slice::from_raw_parts(value.cast::<u8>(), len).to_vec())
+        }
+    }
+
+    /// Title of a window, preferring the UTF-8 `_NET_WM_NAME`
+    fn window_title(&self, window: u32, net_wm_name: Option<u32>) -> String {
+        net_wm_name
+            .and_then(|atom| self.property(window, atom, XCB_ATOM_ANY, MAX_TITLE_LONGS))
+            .filter(|title| !title.is_empty())
+            .or_else(|| self.property(window, XCB_ATOM_WM_NAME, XCB_ATOM_ANY, MAX_TITLE_LONGS))
+            .map(|title| String::from_utf8_lossy(&title).into_owned())
+            .unwrap_or_default()
+    }
+
+    fn select_screen(
+        &mut self,
+        display_id: u32,
+        (width, height): (Option<u32>, Option<u32>),
+    ) -> Result<(), CaptureError> {
+        // SAFETY: the setup and screens are owned by the live connection
+        let screen = unsafe {
+            let setup = (self.api.get_setup)(self.connection);
+            if (*setup).image_byte_order != XCB_IMAGE_ORDER_LSB_FIRST {
+                return Err(CaptureError::CaptureFailure);
+            }
+            let mut iter = (self.api.setup_roots_iterator)(setup);
+            for _ in 0..display_id {
+                if iter.rem == 0 {
+                    break;
+                }
+                (self.api.screen_next)(&mut iter);
+            }
+            if iter.rem == 0 || iter.data.is_null() {
+                return Err(CaptureError::DeviceNotFound);
+            }
+            &*iter.data
+        };
+
+        if !matches!(screen.root_depth, 24 | 32) {
+            return Err(CaptureError::CaptureFailure);
+        }
+        let clamp = |limit: Option<u32>, size: u16| {
+            limit.map_or(size, |limit| limit.clamp(1, u32::from(size)) as u16)
+        };
+        self.root = screen.root;
+        self.width = clamp(width, screen.width_in_pixels);
+        self.height = clamp(height, screen.height_in_pixels);
+        Ok(())
+    }
+}
+
+/// Describe the screens of the X server named by `DISPLAY`
+///
+/// X11 has no per-screen scale, so every screen reports 1.0.
+pub(crate) fn list_screens() -> Result<Vec<DisplayInfo>, CaptureError> {
+    let capture = X11ScreenCapture::connect()?;
+    let displays = capture
+        .screens()
+        .iter()
+        .enumerate()
+        .map(|(id, screen)| DisplayInfo {
+            id: id as u32,
+            bounds: Rect::new(
+                0,
+                0,
+                u32::from(screen.width_in_pixels),
+                u32::from(screen.height_in_pixels),
+            ),
+            scale_factor: 1.0,
+        })
+        .collect();
+    Ok(displays)
+}
+
+/// List the top-level windows of the X server named by `DISPLAY`
+///
+/// Windows are those the window manager reports in `_NET_CLIENT_LIST`, in
+/// its order; without a window manager supporting it the list is empty.
+pub(crate) fn list_windows() -> Result<Vec<CaptureSource>, CaptureError> {
+    let capture = X11ScreenCapture::connect()?;
+    let Some(client_list) = capture.atom("_NET_CLIENT_LIST") else {
+        return Ok(Vec::new());
+    };
+    let net_wm_name = capture.atom("_NET_WM_NAME");
+
+    let mut windows = Vec::new();
+    for screen in capture.screens() {
+        let clients = capture
+            .property(screen.root, client_list, XCB_ATOM_WINDOW, MAX_CLIENTS)
+            .unwrap_or_default();
+        for id in clients.chunks_exact(4) {
+            let window = u32::from_ne_bytes([id[0], id[1], id[2], id[3]]);
+            windows.push(CaptureSource {
+                kind: CaptureSourceKind::Window,
+                id: SourceId::Window(window).to_string(),
+                title: capture.window_title(window, net_wm_name),
+                thumbnail: None,
+            });
+        }
+    }
+    Ok(windows)
+}
+
+impl X11ScreenCapture {
+    /// Grab `rect` of the root window as packed RGBA
+    fn get_image(&self, rect: Rect) -> Result<Vec<u8>, CaptureError> {
+        let (width, height) = (rect.width as u16, rect.height as u16);
+        // SAFETY: the connection is live and `root` is a window on it
+        let reply = unsafe {
+            let cookie = (self.api.get_image)(
+                self.connection,
+                XCB_IMAGE_FORMAT_Z_PIXMAP,
+                self.root,
+                rect.x as i16,
+                rect.y as i16,
+                width,
+                height,
+                u32::MAX,
+            );
+            self.wait(self.api.get_image_reply, cookie)
+        }
+        .ok_or(CaptureError::CaptureFailure)?;
+
+        // SAFETY: the reply owns its image data until freed
+        let rgba = unsafe {
+            let data = (self.api.get_image_data)(reply.0);
+            let len = (self.api.get_image_data_length)(reply.0).max(0) as usize;
+            if data.is_null() {
+                None
+            } else {
+                let bgra = std::slice::from_raw_parts(data, len);
+                let stride = len / usize::from(height.max(1));
+                to_rgba(
+                    bgra,
+                    stride,
+                    rect.width,
+                    rect.height,
+                    ByteOrder::Bgra,
+                    false,
+                )
+            }
+        };
+        rgba.ok_or(CaptureError::CaptureFailure)
+    }
+
+    /// Read the cursor image, if it is drawn into frames
+    fn cursor(&self) -> Option<CursorImage> {
+        let xfixes = self.xfixes.as_ref()?;
+        // SAFETY: XFixes was set up on this connection, and the pixels are
+        // copied out of the reply before it is freed
+        unsafe {
+            let cookie = (xfixes.api.get_cursor_image)(self.connection);
+            let reply = self.wait(xfixes.api.get_cursor_image_reply, cookie)?;
+            let pixels = (xfixes.api.cursor_image)(reply.0);
+            let len = (xfixes.api.cursor_image_length)(reply.0).max(0) as usize;
+            let (width, height) = (u32::from(reply.width), u32::from(reply.height));
+            if pixels.is_null() || len < (width * height) as usize {
+                return None;
+            }
+            Some(CursorImage {
+                x: i32::from(reply.x) - i32::from(reply.xhot),
+                y: i32::from(reply.y) - i32::from(reply.yhot),
+                width,
+                height,
+                pixels: std::slice::from_raw_parts(pixels, len).to_vec(),
+            })
+        }
+    }
+
+    /// Grab the top-left part of the screen
+    fn grab_screen(&self) -> Result<VideoFrame, CaptureError> {
+        let (width, height) = (u32::from(self.width), u32::from(self.height));
+        let mut data = self.get_image(Rect::new(0, 0, width, height))?;
+        if let Some(cursor) = self.cursor() {
+            draw_cursor(
+                &mut data,
+                width,
+                height,
+                &cursor.pixels,
+                (cursor.width, cursor.height),
+                (cursor.x, cursor.y),
+            );
+        }
+        Ok(VideoFrame::new(
+            width,
+            height,
+            PixelFormat::RGBA32,
+            data,
+            Duration::ZERO,
+        ))
+    }
+
+    /// Grab a window, or `None` while it is not shown
+    ///
+    /// Fails with `DeviceNotFound` once the window has been destroyed.
+    fn grab_window(&self, window: u32) -> Result<Option<VideoFrame>, CaptureError> {
+        // SAFETY: the connection is live; requests about a destroyed window
+        // fail rather than return a reply
+        let (attributes, geometry, position) = unsafe {
+            let attributes = (self.api.get_window_attributes)(self.connection, window);
+            let geometry = (self.api.get_geometry)(self.connection, window);
+            let position =
+                (self.api.translate_coordinates)(self.connection, window, self.root, 0, 0);
+            (
+                self.wait(self.api.get_window_attributes_reply, attributes),
+                self.wait(self.api.get_geometry_reply, geometry),
+                self.wait(self.api.translate_coordinates_reply, position),
+            )
+        };
+        let (Some(attributes), Some(geometry), Some(position)) = (attributes, geometry, position)
+        else {
+            return Err(CaptureError::DeviceNotFound);
+        };
+        if attributes.map_state != XCB_MAP_STATE_VIEWABLE {
+            return Ok(None);
+        }
+
+        let bounds = Rect::new(
+            i32::from(position.dst_x),
+            i32::from(position.dst_y),
+            u32::from(geometry.width),
+            u32::from(geometry.height),
+        );
+        let (width, height) = (bounds.width, bounds.height);
+        let mut data = [0, 0, 0, 0xFF].repeat((width * height) as usize);
+        let screen = Rect::new(0, 0, u32::from(self.width), u32::from(self.height));
+        if let Some(visible) = bounds.intersection(&screen) {
+            // The window may be unmapped between the requests
+            let Ok(pixels) = self.get_image(visible) else {
+                return Ok(None);
+            };
+            let row_bytes = visible.width as usize * 4;
+            let left = (visible.x - bounds.x) as usize * 4;
+            let top = (visible.y - bounds.y) as usize;
+            for (row, src) in pixels.chunks_exact(row_bytes).enumerate() {
+                let start = (top + row) * width as usize * 4 + left;
+                data[start..start + row_bytes].copy_from_slice(src);
+            }
+        }
+        if let Some(cursor) = self.cursor() {
+            draw_cursor(
+                &mut data,
+                width,
+                height,
+                &cursor.pixels,
+                (cursor.width, cursor.height),
+                (cursor.x - bounds.x, cursor.y - bounds.y),
+            );
+        }
+
+        Ok(Some(VideoFrame::new(
+            width,
+            height,
+            PixelFormat::RGBA32,
+            data,
+            Duration::ZERO,
+        )))
+    }
+}
+
+impl ScreenSource for X11ScreenCapture {
+    fn grab(&mut self) -> Result<Option<VideoFrame>, CaptureError> {
+        match self.target {
+            Target::Screen => self.grab_screen().map(Some),
+            Target::Window(window) => self.grab_window(window),
+        }
+    }
+}
+
+impl Drop for X11ScreenCapture {
+    fn drop(&mut self) {
+        // SAFETY: the connection came from xcb_connect and is not used again
+        unsafe { (self.api.disconnect)(self.connection) };
+    }
+}
@@ -2,15 +2,71 @@
 //!
 //! Tests camera capture functionality
 
-use cortenbrowser_media_capture::{CameraCapture, CaptureConstraints};
+use cortenbrowser_media_capture::{
+    CameraCapture, CaptureConstraints, CaptureError, CaptureMode, ConstrainRange, DeviceBackend,
+    DeviceChanges, DeviceInfo, DeviceKind,
+};
+use futures_util::stream;
+
+/// Backend with a single camera offering a few modes
+struct MockCamera;
+
+const MOCK_CAMERA: &str = "mock-camera";
+
+fn mode(width: u32, height: u32, frame_rate: f32) -> CaptureMode {
+    CaptureMode {
+        width,
+        height,
+        frame_rate: Some(frame_rate),
+        facing_mode: None,
+    }
+}
+
+impl DeviceBackend for MockCamera {
+    fn devices(&self, kind: DeviceKind) -> Vec<DeviceInfo> {
+        match kind {
+            DeviceKind::VideoInput => vec![DeviceInfo {
+                device_id: MOCK_CAMERA.to_string(),
+                label: "Mock Camera".to_string(),
+                kind,
+            }],
+            _ => vec![],
+        }
+    }
+
+    fn changes(&self) -> DeviceChanges {
+        Box::pin(stream::empty())
+    }
+
+    fn capture_modes(&self, device_id: &str) -> Result<Vec<CaptureMode>, CaptureError> {
+        if device_id != MOCK_CAMERA {
+            return Err(CaptureError::DeviceNotFound);
+        }
+        Ok(vec![
+            mode(640, 480, 30.0),
+            mode(1280, 720, 24.0),
+            mode(1920, 1080, 30.0),
+        ])
+    }
+}
+
+fn hd_constraints() -> CaptureConstraints {
+    CaptureConstraints {
+        width: ConstrainRange::ideal(1280),
+        height: ConstrainRange::ideal(720),
+        frame_rate: ConstrainRange::ideal(30.0),
+        ..Default::default()
+    }
+}
 
 #[test]
 fn test_camera_capture_new() {
     let device_id = "camera-001".to_string();
     let constraints = CaptureConstraints {
-        width: Some(1920),
-        height: Some(1080),
-        frame_rate: Some(30.0),
+        width: ConstrainRange::ideal(1920),
+        height: ConstrainRange::ideal(1080),
+        frame_rate: ConstrainRange::ideal(30.0),
+        ..Default::default()
     };
 
     let result = CameraCapture::new(device_id, constraints);
@@ -21,9 +77,10 @@ fn test_camera_capture_new() {
 fn test_camera_capture_new_with_empty_device_id() {
     let device_id = "".to_string();
     let constraints = CaptureConstraints {
-        width: Some(640),
-        height: Some(480),
-        frame_rate: Some(15.0),
+        width: ConstrainRange::ideal(640),
+        height: ConstrainRange::ideal(480),
+        frame_rate: ConstrainRange::ideal(15.0),
+        ..Default::default()
     };
 
     let result = CameraCapture::new(device_id, constraints);
@@ -31,13 +88,15 @@ fn test_camera_capture_new_with_empty_device_id() {
     assert!(result.is_ok());
 }
 
+#[cfg(not(target_os = "linux"))]
 #[tokio::test]
 async fn test_camera_capture_start() {
     let device_id = "camera-001".to_string();
     let constraints = CaptureConstraints {
-        width: Some(1280),
-        height: Some(720),
-        frame_rate: Some(15.0),
+        width: ConstrainRange::ideal(1280),
+        height: ConstrainRange::ideal(720),
+        frame_rate: ConstrainRange::ideal(15.0),
+        ..Default::default()
     };
 
     let capture = CameraCapture::new(device_id, constraints).unwrap();
@@ -47,13 +106,35 @@ async fn test_camera_capture_start() {
     assert!(result.is_ok());
 }
 
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_camera_capture_start_missing_device() {
+    let device_id = "/dev/video-does-not-exist".to_string();
+    let constraints = CaptureConstraints {
+        width: ConstrainRange::ideal(1280),
+        height: ConstrainRange::ideal(720),
+        frame_rate: ConstrainRange::ideal(15.0),
+        ..Default::default()
+    };
+
+    let capture = CameraCapture::new(device_id, constraints).unwrap();
+    let result = capture.start().await;
+
+    assert_eq!(
+        result.err(),
+        Some(cortenbrowser_media_capture::CaptureError::DeviceNotFound)
+    );
+    assert!(capture.stop().is_ok());
+}
+
 #[test]
 fn test_camera_capture_stop() {
     let device_id = "camera-001".to_string();
     let constraints = CaptureConstraints {
-        width: Some(1920),
-        height: Some(1080),
-        frame_rate: Some(30.0),
+        width: ConstrainRange::ideal(1920),
+        height: ConstrainRange::ideal(1080),
+        frame_rate: ConstrainRange::ideal(30.0),
+        ..Default::default()
     };
 
     let capture = CameraCapture::new(device_id, constraints).unwrap();
@@ -62,3 +143,69 @@ fn test_camera_capture_stop() {
     // Stop should succeed
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_camera_capture_no_settings_before_start() {
+    let constraints = CaptureConstraints {
+        width: ConstrainRange::exact(1920),
+        ..Default::default()
+    };
+
+    let capture = CameraCapture::new("camera-001".to_string(), constraints).unwrap();
+    assert_eq!(capture.get_settings(), None);
+}
+
+#[test]
+fn test_camera_capture_open_selects_closest_mode() {
+    let capture =
+        CameraCapture::open_with_backend(&MockCamera, MOCK_CAMERA.to_string(), hd_constraints())
+            .unwrap();
+
+    // No 1280x720@30 mode: the resolution matters as much as the frame rate
+    assert_eq!(capture.active_format(), Some(mode(1280, 720, 24.0)));
+    assert_eq!(capture.capabilities().len(), 3);
+}
+
+#[test]
+fn test_camera_capture_open_honors_exact_frame_rate() {
+    let constraints = CaptureConstraints {
+        frame_rate: ConstrainRange::exact(30.0),
+        ..hd_constraints()
+    };
+
+    let capture =
+        CameraCapture::open_with_backend(&MockCamera, MOCK_CAMERA.to_string(), constraints)
+            .unwrap();
+    assert_eq!(capture.active_format(), Some(mode(1920, 1080, 30.0)));
+}
+
+#[test]
+fn test_camera_capture_open_overconstrained() {
+    let constraints = CaptureConstraints {
+        width: ConstrainRange::exact(3840),
+        ..hd_constraints()
+    };
+
+    let result =
+        CameraCapture::open_with_backend(&MockCamera, MOCK_CAMERA.to_string(), constraints);
+    assert_eq!(
+        result.err(),
+        Some(CaptureError::Overconstrained {
+            constraint: "width".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_camera_capture_open_unknown_device() {
+    let result =
+        CameraCapture::open_with_backend(&MockCamera, "camera-001".to_string(), hd_constraints());
+    assert_eq!(result.err(), Some(CaptureError::DeviceNotFound));
+}
+
+#[test]
+fn test_camera_capture_new_has_no_active_format() {
+    let capture = CameraCapture::new("camera-001".to_string(), hd_constraints()).unwrap();
+    assert_eq!(capture.active_format(), None);
+    assert!(capture.capabilities().is_empty());
+}
@@ -2,7 +2,68 @@
 //!
 //! Tests device enumeration for video and audio devices
 
-use cortenbrowser_media_capture::{DeviceEnumerator, DeviceKind};
+use cortenbrowser_media_capture::{
+    DeviceBackend, DeviceChanges, DeviceEnumerator, DeviceEvent, DeviceInfo, DeviceKind,
+};
+use futures_util::{stream, StreamExt};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Backend whose devices are plugged in and removed by the test
+#[derive(Clone)]
+struct MockBackend {
+    devices: Arc<Mutex<Vec<DeviceInfo>>>,
+    changes: broadcast::Sender<()>,
+}
+
+impl MockBackend {
+    fn new(devices: Vec<DeviceInfo>) -> Self {
+        Self {
+            devices: Arc::new(Mutex::new(devices)),
+            changes: broadcast::channel(16).0,
+        }
+    }
+
+    fn plug(&self, device: DeviceInfo) {
+        self.devices.lock().unwrap().push(device);
+        let _ = self.changes.send(());
+    }
+
+    fn unplug(&self, device_id: &str) {
+        self.devices
+            .lock()
+            .unwrap()
+            .retain(|device| device.device_id != device_id);
+        let _ = self.changes.send(());
+    }
+}
+
+impl DeviceBackend for MockBackend {
+    fn devices(&self, kind: DeviceKind) -> Vec<DeviceInfo> {
+        let devices = self.devices.lock().unwrap();
+        devices
+            .iter()
+            .filter(|device| device.kind == kind)
+            .cloned()
+            .collect()
+    }
+
+    fn changes(&self) -> DeviceChanges {
+        let receiver = self.changes.subscribe();
+        Box::pin(stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.ok().map(|()| ((), receiver))
+        }))
+    }
+}
+
+fn device(device_id: &str, kind: DeviceKind) -> DeviceInfo {
+    DeviceInfo {
+        device_id: device_id.to_string(),
+        label: format!("Device {}", device_id),
+        kind,
+    }
+}
 
 #[tokio::test]
 async fn test_enumerate_video_devices() {
@@ -51,3 +112,116 @@ async fn test_device_enumerator_multiple_calls() {
     assert!(result1.is_ok());
     assert!(result2.is_ok());
 }
+
+#[tokio::test]
+async fn test_watch_ends_when_enumerator_dropped() {
+    let enumerator = DeviceEnumerator::new();
+    let mut events = Box::pin(enumerator.watch());
+
+    // No devices are plugged in during the test
+    let pending = tokio::time::timeout(Duration::from_millis(50), events.next()).await;
+    assert!(pending.is_err());
+
+    drop(enumerator);
+    let ended = tokio::time::timeout(Duration::from_secs(5), events.next()).await;
+    assert_eq!(ended, Ok(None));
+}
+
+#[tokio::test]
+async fn test_watch_shared_by_clones() {
+    let enumerator = DeviceEnumerator::new();
+    let clone = enumerator.clone();
+    let mut first = Box::pin(enumerator.watch());
+    let mut second = Box::pin(clone.watch());
+
+    // The watcher keeps running while a clone is alive
+    drop(enumerator);
+    let pending = tokio::time::timeout(Duration::from_millis(50), first.next()).await;
+    assert!(pending.is_err());
+
+    drop(clone);
+    let first = tokio::time::timeout(Duration::from_secs(5), first.next()).await;
+    let second = tokio::time::timeout(Duration::from_secs(5), second.next()).await;
+    assert_eq!(first, Ok(None));
+    assert_eq!(second, Ok(None));
+}
+
+#[tokio::test]
+async fn test_enumerate_from_backend() {
+    let camera = device("camera-1", DeviceKind::VideoInput);
+    let microphone = device("mic-1", DeviceKind::AudioInput);
+    let backend = MockBackend::new(vec![camera.clone(), microphone.clone()]);
+    let enumerator = DeviceEnumerator::with_backend(backend);
+
+    let video = enumerator.enumerate_video_devices().await.unwrap();
+    let audio = enumerator.enumerate_audio_devices().await.unwrap();
+    assert_eq!(video, vec![camera]);
+    assert_eq!(audio, vec![microphone]);
+}
+
+#[tokio::test]
+async fn test_device_ids_stable_across_enumerations() {
+    let enumerator = DeviceEnumerator::new();
+    let ids = |devices: Vec<DeviceInfo>| -> Vec<String> {
+        devices.into_iter().map(|device| device.device_id).collect()
+    };
+
+    let first = enumerator.enumerate_audio_devices().await.unwrap();
+    let second = enumerator.enumerate_audio_devices().await.unwrap();
+    assert_eq!(ids(first), ids(second));
+
+    let first = enumerator.enumerate_video_devices().await.unwrap();
+    let second = enumerator.enumerate_video_devices().await.unwrap();
+    assert_eq!(ids(first), ids(second));
+}
+
+#[tokio::test]
+async fn test_watch_reports_added_and_removed_devices() {
+    let camera = device("camera-1", DeviceKind::VideoInput);
+    let backend = MockBackend::new(vec![camera.clone()]);
+    let enumerator = DeviceEnumerator::with_backend(backend.clone());
+    let mut events = Box::pin(enumerator.watch());
+    // Let the watcher list the devices present from the start
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let microphone = device("mic-1", DeviceKind::AudioInput);
+    backend.plug(microphone.clone());
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await;
+    assert_eq!(event, Ok(Some(DeviceEvent::Added(microphone))));
+
+    backend.unplug("camera-1");
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await;
+    assert_eq!(
+        event,
+        Ok(Some(DeviceEvent::Removed {
+            device_id: "camera-1".to_string()
+        }))
+    );
+
+    // Plugging the camera back in reports it again, under the same id
+    backend.plug(camera.clone());
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await;
+    assert_eq!(event, Ok(Some(DeviceEvent::Added(camera))));
+}
+
+#[tokio::test]
+async fn test_watch_ignores_unchanged_devices() {
+    let camera = device("camera-1", DeviceKind::VideoInput);
+    let backend = MockBackend::new(vec![camera]);
+    let enumerator = DeviceEnumerator::with_backend(backend.clone());
+    let mut events = Box::pin(enumerator.watch());
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // A change signal without any device change reports nothing
+    let _ = backend.changes.send(());
+    let pending = tokio::time::timeout(Duration::from_millis(50), events.next()).await;
+    assert!(pending.is_err());
+
+    // Devices already known are not reported again with a new one
+    let microphone = device("mic-1", DeviceKind::AudioInput);
+    backend.plug(microphone.clone());
+    let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await;
+    assert_eq!(event, Ok(Some(DeviceEvent::Added(microphone))));
+    let pending = tokio::time::timeout(Duration::from_millis(50), events.next()).await;
+    assert!(pending.is_err());
+}
@@ -3,6 +3,7 @@
 //! Tests microphone capture functionality
 
 use cortenbrowser_media_capture::{MicrophoneCapture, AudioConstraints};
+use cortenbrowser_shared_types::AudioProcessingConfig;
 
 #[test]
 fn test_microphone_capture_new() {
@@ -28,6 +29,7 @@ fn test_microphone_capture_new_with_mono() {
     assert!(result.is_ok());
 }
 
+#[cfg(not(target_os = "linux"))]
 #[tokio::test]
 async fn test_microphone_capture_start() {
     let device_id = "mic-001".to_string();
@@ -43,6 +45,28 @@ async fn test_microphone_capture_start() {
     assert!(result.is_ok());
 }
 
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_microphone_capture_start_missing_device() {
+    let constraints = AudioConstraints {
+        sample_rate: Some(48000),
+        channels: Some(2),
+    };
+
+    // Neither an ALSA capture node nor a card that exists
+    for device_id in ["mic-001", "/dev/snd/pcmC99D0c"] {
+        let capture = MicrophoneCapture::new(device_id.to_string(), constraints.clone()).unwrap();
+        let result = capture.start().await;
+
+        assert!(matches!(
+            result,
+            Err(cortenbrowser_media_capture::CaptureError::DeviceNotFound)
+                | Err(cortenbrowser_media_capture::CaptureError::CaptureFailure)
+        ));
+        assert!(capture.stop().is_ok());
+    }
+}
+
 #[test]
 fn test_microphone_capture_stop() {
     let device_id = "mic-001".to_string();
@@ -57,3 +81,41 @@ fn test_microphone_capture_stop() {
     // Stop should succeed
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_microphone_capture_with_audio_processing() {
+    let device_id = "mic-001".to_string();
+    let constraints = AudioConstraints {
+        sample_rate: Some(48000),
+        channels: Some(1),
+    };
+    let config = AudioProcessingConfig::new()
+        .with_noise_suppression(true)
+        .with_auto_gain_control(true)
+        .with_echo_cancellation(true);
+
+    let capture = MicrophoneCapture::new(device_id, constraints)
+        .unwrap()
+        .with_audio_processing(config.clone());
+
+    assert_eq!(capture.audio_processing(), Some(&config));
+}
+
+#[cfg(not(target_os = "linux"))]
+#[tokio::test]
+async fn test_microphone_capture_start_with_audio_processing() {
+    let device_id = "mic-001".to_string();
+    let constraints = AudioConstraints {
+        sample_rate: Some(48000),
+        channels: Some(1),
+    };
+    let config = AudioProcessingConfig::new().with_auto_gain_control(true);
+
+    let capture = MicrophoneCapture::new(device_id, constraints)
+        .unwrap()
+        .with_audio_processing(config);
+    let result = capture.start().await;
+
+    // Start should succeed (returns processed channel)
+    assert!(result.is_ok());
+}
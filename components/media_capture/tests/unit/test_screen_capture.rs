@@ -2,58 +2,114 @@
 //!
 //! Tests screen capture functionality
 
-use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints};
+use cortenbrowser_media_capture::{ScreenCapture, CaptureConstraints, ConstrainRange, Rect};
 
 #[test]
 fn test_screen_capture_new() {
     let constraints = CaptureConstraints {
-        width: Some(1920),
-        height: Some(1080),
-        frame_rate: Some(30.0),
+        width: ConstrainRange::ideal(1920),
+        height: ConstrainRange::ideal(1080),
+        frame_rate: ConstrainRange::ideal(30.0),
+        ..Default::default()
     };
 
-    let result = ScreenCapture::new(constraints);
+    let result = ScreenCapture::new(0, constraints);
     assert!(result.is_ok());
 }
 
 #[test]
 fn test_screen_capture_new_with_none_constraints() {
-    let constraints = CaptureConstraints {
-        width: None,
-        height: None,
-        frame_rate: None,
-    };
+    let constraints = CaptureConstraints::default();
 
-    let result = ScreenCapture::new(constraints);
+    let result = ScreenCapture::new(0, constraints);
     assert!(result.is_ok());
 }
 
+#[cfg(not(target_os = "linux"))]
 #[tokio::test]
 async fn test_screen_capture_start() {
     let constraints = CaptureConstraints {
-        width: Some(1280),
-        height: Some(720),
-        frame_rate: Some(15.0),
+        width: ConstrainRange::ideal(1280),
+        height: ConstrainRange::ideal(720),
+        frame_rate: ConstrainRange::ideal(15.0),
+        ..Default::default()
     };
 
-    let capture = ScreenCapture::new(constraints).unwrap();
+    let capture = ScreenCapture::new(0, constraints).unwrap();
     let result = capture.start().await;
 
     // Start should succeed (returns channel)
     assert!(result.is_ok());
 }
 
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_screen_capture_start_missing_display() {
+    let constraints = CaptureConstraints {
+        width: ConstrainRange::ideal(1280),
+        height: ConstrainRange::ideal(720),
+        frame_rate: ConstrainRange::ideal(15.0),
+        ..Default::default()
+    };
+
+    // No display server, or one without this many screens
+    let capture = ScreenCapture::new(u32::MAX, constraints).unwrap();
+    let result = capture.start().await;
+
+    assert_eq!(
+        result.err(),
+        Some(cortenbrowser_media_capture::CaptureError::DeviceNotFound)
+    );
+}
+
 #[test]
 fn test_screen_capture_stop() {
     let constraints = CaptureConstraints {
-        width: Some(1920),
-        height: Some(1080),
-        frame_rate: Some(30.0),
+        width: ConstrainRange::ideal(1920),
+        height: ConstrainRange::ideal(1080),
+        frame_rate: ConstrainRange::ideal(30.0),
+        ..Default::default()
     };
 
-    let capture = ScreenCapture::new(constraints).unwrap();
+    let capture = ScreenCapture::new(0, constraints).unwrap();
     let result = capture.stop();
 
     // Stop should succeed
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_screen_capture_region() {
+    let region = Rect::new(100, 50, 640, 360);
+    let capture = ScreenCapture::capture_region(1, region, CaptureConstraints::default()).unwrap();
+    assert_eq!(capture.region(), Some(region));
+
+    let full = ScreenCapture::new(1, CaptureConstraints::default()).unwrap();
+    assert_eq!(full.region(), None);
+}
+
+#[test]
+fn test_rect_intersection() {
+    let screen = Rect::new(0, 0, 1920, 1080);
+
+    assert_eq!(
+        Rect::new(10, 20, 30, 40).intersection(&screen),
+        Some(Rect::new(10, 20, 30, 40))
+    );
+    assert_eq!(
+        Rect::new(-100, 1000, 400, 400).intersection(&screen),
+        Some(Rect::new(0, 1000, 300, 80))
+    );
+    assert_eq!(Rect::new(0, 1080, 10, 10).intersection(&screen), None);
+    assert_eq!(Rect::new(5, 5, 0, 10).intersection(&screen), None);
+}
+
+#[test]
+fn test_list_displays() {
+    // May be empty without a display server
+    let displays = ScreenCapture::list_displays();
+    for (index, display) in displays.iter().enumerate() {
+        assert_eq!(display.id, index as u32);
+        assert!(display.scale_factor > 0.0);
+    }
+}
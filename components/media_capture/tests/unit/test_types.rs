@@ -1,33 +1,33 @@
 //! Unit tests for media capture types
 //!
-//! Tests for CaptureConstraints, AudioConstraints, DeviceInfo, DeviceKind, and CaptureError
+//! Tests for CaptureConstraints and mode selection, AudioConstraints, DeviceInfo, DeviceKind,
+//! and CaptureError
 
 use cortenbrowser_media_capture::*;
 
 #[test]
 fn test_capture_constraints_creation() {
     let constraints = CaptureConstraints {
-        width: Some(1920),
-        height: Some(1080),
-        frame_rate: Some(30.0),
+        width: ConstrainRange::ideal(1920),
+        height: ConstrainRange::ideal(1080),
+        frame_rate: ConstrainRange::ideal(30.0),
+        ..Default::default()
     };
 
-    assert_eq!(constraints.width, Some(1920));
-    assert_eq!(constraints.height, Some(1080));
-    assert_eq!(constraints.frame_rate, Some(30.0));
+    assert_eq!(constraints.width.ideal, Some(1920));
+    assert_eq!(constraints.height.ideal, Some(1080));
+    assert_eq!(constraints.frame_rate.ideal, Some(30.0));
+    assert_eq!(constraints.facing_mode, ConstrainFacingMode::default());
 }
 
 #[test]
 fn test_capture_constraints_optional_fields() {
-    let constraints = CaptureConstraints {
-        width: None,
-        height: None,
-        frame_rate: None,
-    };
+    let constraints = CaptureConstraints::default();
 
-    assert_eq!(constraints.width, None);
-    assert_eq!(constraints.height, None);
-    assert_eq!(constraints.frame_rate, None);
+    assert_eq!(constraints.width, ConstrainRange::default());
+    assert_eq!(constraints.height, ConstrainRange::default());
+    assert_eq!(constraints.frame_rate, ConstrainRange::default());
+    assert_eq!(constraints.width.preferred(), None);
 }
 
 #[test]
@@ -82,11 +82,18 @@ fn test_capture_error_variants() {
     let not_found = CaptureError::DeviceNotFound;
     let permission_denied = CaptureError::PermissionDenied;
     let capture_failure = CaptureError::CaptureFailure;
+    let overconstrained = CaptureError::Overconstrained {
+        constraint: "width".to_string(),
+    };
 
     // Test that error variants can be created
     assert!(matches!(not_found, CaptureError::DeviceNotFound));
     assert!(matches!(permission_denied, CaptureError::PermissionDenied));
     assert!(matches!(capture_failure, CaptureError::CaptureFailure));
+    assert!(matches!(
+        overconstrained,
+        CaptureError::Overconstrained { .. }
+    ));
 }
 
 #[test]
@@ -95,3 +102,252 @@ fn test_capture_error_display() {
     let error_str = format!("{}", error);
     assert!(!error_str.is_empty());
 }
+
+#[test]
+fn test_device_event_variants() {
+    let device = DeviceInfo {
+        device_id: "/dev/video2".to_string(),
+        label: "USB Camera".to_string(),
+        kind: DeviceKind::VideoInput,
+    };
+
+    let added = DeviceEvent::Added(device.clone());
+    let removed = DeviceEvent::Removed {
+        device_id: device.device_id.clone(),
+    };
+
+    assert_eq!(added, DeviceEvent::Added(device));
+    assert_ne!(added, removed);
+}
+
+/// Modes of a camera that does 640x480 and 1280x720 at up to 30 fps
+fn camera_modes() -> Vec<CaptureMode> {
+    [
+        (640, 480, 30.0),
+        (640, 480, 15.0),
+        (1280, 720, 30.0),
+        (1280, 720, 15.0),
+    ]
+    .into_iter()
+    .map(|(width, height, frame_rate)| CaptureMode {
+        width,
+        height,
+        frame_rate: Some(frame_rate),
+        facing_mode: Some(FacingMode::User),
+    })
+    .collect()
+}
+
+#[test]
+fn test_select_mode_closest_to_ideal() {
+    let constraints = CaptureConstraints {
+        width: ConstrainRange::ideal(1920),
+        height: ConstrainRange::ideal(1080),
+        frame_rate: ConstrainRange::ideal(60.0),
+        ..Default::default()
+    };
+    let modes = camera_modes();
+
+    let mode = constraints.select_mode(&modes).unwrap();
+    assert_eq!(
+        (mode.width, mode.height, mode.frame_rate),
+        (1280, 720, Some(30.0))
+    );
+
+    // 1920 -> 1280 and 1080 -> 720 are each a third off, 60 -> 30 half
+    let distance = constraints.fitness_distance(&mode).unwrap();
+    assert!((distance - (1.0 / 3.0 + 1.0 / 3.0 + 0.5)).abs() < 1e-6);
+    let slower = constraints.fitness_distance(&modes[3]).unwrap();
+    assert!(slower > distance);
+}
+
+#[test]
+fn test_select_mode_without_constraints_prefers_default() {
+    let mut modes = camera_modes();
+    modes.reverse();
+
+    let mode = CaptureConstraints::default().select_mode(&modes).unwrap();
+    assert_eq!(
+        (mode.width, mode.height, mode.frame_rate),
+        (640, 480, Some(30.0))
+    );
+}
+
+#[test]
+fn test_select_mode_within_range() {
+    let constraints = CaptureConstraints {
+        width: ConstrainRange {
+            min: Some(1000),
+            ideal: Some(640),
+            ..Default::default()
+        },
+        frame_rate: ConstrainRange::range(10.0, 20.0),
+        ..Default::default()
+    };
+
+    let mode = constraints.select_mode(&camera_modes()).unwrap();
+    assert_eq!(
+        (mode.width, mode.height, mode.frame_rate),
+        (1280, 720, Some(15.0))
+    );
+}
+
+#[test]
+fn test_select_mode_exact_constraint_overconstrained() {
+    let modes = camera_modes();
+
+    let constraints = CaptureConstraints {
+        width: ConstrainRange::exact(1920),
+        height: ConstrainRange::exact(1080),
+        ..Default::default()
+    };
+    assert_eq!(
+        constraints.select_mode(&modes),
+        Err(CaptureError::Overconstrained {
+            constraint: "width".to_string()
+        })
+    );
+
+    let constraints = CaptureConstraints {
+        frame_rate: ConstrainRange::exact(60.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        constraints.select_mode(&modes),
+        Err(CaptureError::Overconstrained {
+            constraint: "frame_rate".to_string()
+        })
+    );
+    assert_eq!(constraints.fitness_distance(&modes[0]), None);
+}
+
+#[test]
+fn test_select_mode_facing_mode() {
+    let modes = camera_modes();
+
+    // An ideal facing mode only ranks modes
+    let ideal = CaptureConstraints {
+        facing_mode: ConstrainFacingMode::ideal(FacingMode::Environment),
+        ..Default::default()
+    };
+    assert_eq!(ideal.fitness_distance(&modes[0]), Some(1.0));
+    assert!(ideal.select_mode(&modes).is_ok());
+
+    let exact = CaptureConstraints {
+        facing_mode: ConstrainFacingMode::exact(FacingMode::Environment),
+        ..Default::default()
+    };
+    assert_eq!(
+        exact.select_mode(&modes),
+        Err(CaptureError::Overconstrained {
+            constraint: "facing_mode".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_capture_constraints_builder() {
+    let constraints = CaptureConstraints::builder()
+        .width(1280)
+        .height(720)
+        .frame_rate(30.0)
+        .build();
+
+    assert_eq!(
+        constraints,
+        CaptureConstraints {
+            width: ConstrainRange::ideal(1280),
+            height: ConstrainRange::ideal(720),
+            frame_rate: ConstrainRange::ideal(30.0),
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn test_capture_constraints_builder_required_values() {
+    let constraints = CaptureConstraints::builder()
+        .exact_width(1920)
+        .height_range(720, 1080)
+        .exact_frame_rate(60.0)
+        .exact_facing_mode(FacingMode::Environment)
+        .build();
+
+    assert_eq!(constraints.width, ConstrainRange::exact(1920));
+    assert_eq!(constraints.height, ConstrainRange::range(720, 1080));
+    assert_eq!(constraints.frame_rate, ConstrainRange::exact(60.0));
+    assert_eq!(
+        constraints.facing_mode,
+        ConstrainFacingMode::exact(FacingMode::Environment)
+    );
+    assert!(constraints.validate().is_ok());
+}
+
+#[test]
+fn test_validate_rejects_zero_width() {
+    let constraints = CaptureConstraints::builder().width(0).height(720).build();
+
+    assert_eq!(
+        constraints.validate(),
+        Err(CaptureError::InvalidConstraint {
+            constraint: "width".to_string()
+        })
+    );
+    // Negotiation fails rather than approximating
+    assert_eq!(
+        constraints.select_mode(&camera_modes()),
+        Err(CaptureError::InvalidConstraint {
+            constraint: "width".to_string()
+        })
+    );
+}
+
+#[test]
+fn test_validate_rejects_impossible_values() {
+    let invalid = |constraints: CaptureConstraints| match constraints.validate() {
+        Err(CaptureError::InvalidConstraint { constraint }) => constraint,
+        other => panic!("expected an invalid constraint, got {:?}", other),
+    };
+
+    // A negative height converted to u32
+    assert_eq!(
+        invalid(CaptureConstraints::builder().height(-1i32 as u32).build()),
+        "height"
+    );
+    assert_eq!(
+        invalid(CaptureConstraints::builder().frame_rate(1001.0).build()),
+        "frame_rate"
+    );
+    assert_eq!(
+        invalid(CaptureConstraints::builder().frame_rate(-30.0).build()),
+        "frame_rate"
+    );
+    assert_eq!(
+        invalid(CaptureConstraints::builder().frame_rate(f32::NAN).build()),
+        "frame_rate"
+    );
+    assert_eq!(
+        invalid(CaptureConstraints::builder().width_range(1920, 640).build()),
+        "width"
+    );
+    assert_eq!(
+        invalid(
+            CaptureConstraints::builder()
+                .exact_width(3840)
+                .width_range(640, 1920)
+                .build()
+        ),
+        "width"
+    );
+}
+
+#[test]
+fn test_validate_allows_zero_minimum_and_ideal_outside_range() {
+    let constraints = CaptureConstraints::builder()
+        .width(3840)
+        .width_range(0, 1920)
+        .frame_rate_range(0.0, 1000.0)
+        .build();
+
+    assert!(constraints.validate().is_ok());
+}
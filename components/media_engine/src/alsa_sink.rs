@@ -0,0 +1,283 @@
+//! ALSA audio output
+//!
+//! Plays audio through `libasound`, which is loaded at runtime; opening the
+//! sink fails on systems without it.
+
+#![allow(unsafe_code)]
+
+use crate::audio_output::Volume;
+use cortenbrowser_shared_types::{AudioBuffer, AudioSink, Library, MediaError};
+use libc::{c_char, c_int, c_long, c_uint, c_ulong, c_void};
+use parking_lot::Mutex;
+use std::ffi::{CStr, CString};
+use std::ptr;
+use std::time::Duration;
+
+#[repr(C)]
+struct SndPcm {
+    _private: [u8; 0],
+}
+
+type SndPcmOpenFn = unsafe extern "C" fn(*mut *mut SndPcm, *const c_char, c_int, c_int) -> c_int;
+type SndPcmSetParamsFn =
+    unsafe extern "C" fn(*mut SndPcm, c_int, c_int, c_uint, c_uint, c_int, c_uint) -> c_int;
+type SndPcmWriteiFn = unsafe extern "C" fn(*mut SndPcm, *const c_void, c_ulong) -> c_long;
+type SndPcmRecoverFn = unsafe extern "C" fn(*mut SndPcm, c_int, c_int) -> c_int;
+type SndPcmDelayFn = unsafe extern "C" fn(*mut SndPcm, *mut c_long) -> c_int;
+type SndPcmFn = unsafe extern "C" fn(*mut SndPcm) -> c_int;
+type SndStrerrorFn = unsafe extern "C" fn(c_int) -> *const c_char;
+
+const SND_PCM_STREAM_PLAYBACK: c_int = 0;
+const SND_PCM_ACCESS_RW_INTERLEAVED: c_int = 3;
+#[cfg(target_endian = "little")]
+const SND_PCM_FORMAT_FLOAT: c_int = 14;
+#[cfg(target_endian = "big")]
+const SND_PCM_FORMAT_FLOAT: c_int = 15;
+
+/// Soname of the ALSA library
+const LIBASOUND: &CStr = c"libasound.so.2";
+
+/// Device buffer length, which bounds the output latency
+const LATENCY_US: c_uint = 100_000;
+
+/// libasound entry points used for playback
+struct AlsaApi {
+    pcm_open: SndPcmOpenFn,
+    pcm_set_params: SndPcmSetParamsFn,
+    pcm_writei: SndPcmWriteiFn,
+    pcm_recover: SndPcmRecoverFn,
+    pcm_delay: SndPcmDelayFn,
+    pcm_drain: SndPcmFn,
+    pcm_close: SndPcmFn,
+    strerror: SndStrerrorFn,
+    // Keeps the function pointers valid
+    _asound: Library,
+}
+
+impl AlsaApi {
+    fn load() -> Result<Self, MediaError> {
+        let asound =
+            Library::open(LIBASOUND).ok_or_else(|| hardware_error("libasound is not installed"))?;
+        let missing = |_| hardware_error("libasound is missing PCM functions");
+
+        // SAFETY: the function types match the libasound C declarations
+        unsafe {
+            Ok(Self {
+                pcm_open: asound.symbol(c"snd_pcm_open").map_err(missing)?,
+                pcm_set_params: asound.symbol(c"snd_pcm_set_params").map_err(missing)?,
+                pcm_writei: asound.symbol(c"snd_pcm_writei").map_err(missing)?,
+                pcm_recover: asound.symbol(c"snd_pcm_recover").map_err(missing)?,
+                pcm_delay: asound.symbol(c"snd_pcm_delay").map_err(missing)?,
+                pcm_drain: asound.symbol(c"snd_pcm_drain").map_err(missing)?,
+                pcm_close: asound.symbol(c"snd_pcm_close").map_err(missing)?,
+                strerror: asound.symbol(c"snd_strerror").map_err(missing)?,
+                _asound: asound,
+            })
+        }
+    }
+
+    /// Turn a negative ALSA return code into an error
+    fn check(&self, what: &str, code: c_int) -> Result<(), MediaError> {
+        if code >= 0 {
+            return Ok(());
+        }
+        // SAFETY: snd_strerror returns a static NUL-terminated string
+        let reason = unsafe { CStr::from_ptr((self.strerror)(code)) };
+        Err(hardware_error(&format!(
+            "{} failed: {}",
+            what,
+            reason.to_string_lossy()
+        )))
+    }
+}
+
+fn hardware_error(details: &str) -> MediaError {
+    MediaError::HardwareError {
+        details: details.to_string(),
+    }
+}
+
+/// Open PCM device and its playback position
+struct Device {
+    api: AlsaApi,
+    pcm: *mut SndPcm,
+    /// Sample rate and channel count the device is configured for
+    config: Option<(u32, u8)>,
+    /// Frames written since the device was last configured
+    frames_written: u64,
+    /// Audio played under earlier configurations
+    played_before: Duration,
+}
+
+// SAFETY: the PCM handle is only used behind the sink's mutex
+unsafe impl Send for Device {}
+
+impl Device {
+    /// Configure the device for `rate` and `channels`, finishing the audio
+    /// queued under the previous configuration first
+    fn configure(&mut self, rate: u32, channels: u8) -> Result<(), MediaError> {
+        if self.config == Some((rate, channels)) {
+            return Ok(());
+        }
+        if let Some((old_rate, _)) = self.config.take() {
+            // SAFETY: the PCM is open
+            unsafe { (self.api.pcm_drain)(self.pcm) };
+            self.played_before += frames_to_duration(self.frames_written, old_rate);
+            self.frames_written = 0;
+        }
+
+        // SAFETY: the PCM is open and not running after the drain
+        let code = unsafe {
+            (self.api.pcm_set_params)(
+                self.pcm,
+                SND_PCM_FORMAT_FLOAT,
+                SND_PCM_ACCESS_RW_INTERLEAVED,
+                c_uint::from(channels),
+                rate,
+                1,
+                LATENCY_US,
+            )
+        };
+        self.api.check("snd_pcm_set_params", code)?;
+        self.config = Some((rate, channels));
+        Ok(())
+    }
+
+    /// Write interleaved frames, blocking until the device takes them all
+    fn write(&mut self, samples: &[f32], channels: usize) -> Result<(), MediaError> {
+        let mut remaining = samples;
+        while !remaining.is_empty() {
+            let frames = remaining.len() / channels;
+            // SAFETY: the PCM is configured for `channels` float samples per
+            // frame and `remaining` holds `frames` such frames
+            let written = unsafe {
+                (self.api.pcm_writei)(self.pcm, remaining.as_ptr().cast(), frames as c_ulong)
+            };
+            if written < 0 {
+                // Recover from underruns and suspends, then retry
+                // SAFETY: the PCM is open
+                let code = unsafe { (self.api.pcm_recover)(self.pcm, written as c_int, 1) };
+                self.api.check("snd_pcm_writei", code)?;
+                continue;
+            }
+            self.frames_written += written as u64;
+            remaining = &remaining[written as usize * channels..];
+        }
+        Ok(())
+    }
+
+    fn played(&self) -> Duration {
+        let Some((rate, _)) = self.config else {
+            return self.played_before;
+        };
+        let mut delay: c_long = 0;
+        // SAFETY: the PCM is open and `delay` is a valid out pointer
+        let queued = match unsafe { (self.api.pcm_delay)(self.pcm, &mut delay) } {
+            code if code >= 0 => delay.max(0) as u64,
+            _ => 0,
+        };
+        self.played_before + frames_to_duration(self.frames_written.saturating_sub(queued), rate)
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        // SAFETY: the PCM is open and not used again
+        unsafe { (self.api.pcm_close)(self.pcm) };
+    }
+}
+
+fn frames_to_duration(frames: u64, rate: u32) -> Duration {
+    Duration::from_secs_f64(frames as f64 / f64::from(rate))
+}
+
+/// Sink playing on an ALSA PCM device
+///
+/// The device is configured for the sample rate and channel count of the
+/// first buffer written, and reconfigured when they change. Writes block
+/// while the device buffer (100 ms) is full.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cortenbrowser_media_engine::AlsaAudioSink;
+/// use cortenbrowser_shared_types::{AudioBuffer, AudioFormat, AudioSink};
+/// use std::time::Duration;
+///
+/// let sink = AlsaAudioSink::open_default().unwrap();
+/// sink.set_volume(0.5);
+/// sink.write(AudioBuffer::new(AudioFormat::F32LE, 48000, 2, vec![0.0; 9600], Duration::ZERO))
+///     .unwrap();
+/// ```
+pub struct AlsaAudioSink {
+    device: Mutex<Device>,
+    volume: Volume,
+    /// Played duration at the last write, reported while a write blocks
+    played: Mutex<Duration>,
+}
+
+impl AlsaAudioSink {
+    /// Open the `default` PCM device
+    pub fn open_default() -> Result<Self, MediaError> {
+        Self::open("default")
+    }
+
+    /// Open a PCM device by name, such as `default` or `hw:0,0`
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::HardwareError` if libasound is not installed or
+    /// the device cannot be opened.
+    pub fn open(name: &str) -> Result<Self, MediaError> {
+        let api = AlsaApi::load()?;
+        let name = CString::new(name).map_err(|_| hardware_error("Invalid device name"))?;
+
+        let mut pcm = ptr::null_mut();
+        // SAFETY: `pcm` is a valid out pointer and the name is NUL-terminated
+        let code = unsafe { (api.pcm_open)(&mut pcm, name.as_ptr(), SND_PCM_STREAM_PLAYBACK, 0) };
+        api.check("snd_pcm_open", code)?;
+
+        Ok(Self {
+            device: Mutex::new(Device {
+                api,
+                pcm,
+                config: None,
+                frames_written: 0,
+                played_before: Duration::ZERO,
+            }),
+            volume: Volume::new(),
+            played: Mutex::new(Duration::ZERO),
+        })
+    }
+}
+
+impl AudioSink for AlsaAudioSink {
+    fn write(&self, mut buffer: AudioBuffer) -> Result<(), MediaError> {
+        if buffer.channels == 0 || buffer.samples.is_empty() {
+            return Ok(());
+        }
+        self.volume.apply(&mut buffer.samples);
+
+        let mut device = self.device.lock();
+        device.configure(buffer.sample_rate, buffer.channels)?;
+        let result = device.write(&buffer.samples, usize::from(buffer.channels));
+        *self.played.lock() = device.played();
+        result
+    }
+
+    fn played_duration(&self) -> Duration {
+        // Don't wait for a blocked write; it updates the position when done
+        match self.device.try_lock() {
+            Some(device) => {
+                let played = device.played();
+                *self.played.lock() = played;
+                played
+            }
+            None => *self.played.lock(),
+        }
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.volume.set(volume);
+    }
+}
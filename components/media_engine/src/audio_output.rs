@@ -0,0 +1,274 @@
+//! Audio output sinks
+//!
+//! Decoded audio leaves the engine through an [`AudioSink`]. Each session
+//! gets its own sink from the engine's sink factory, which by default plays
+//! on the system's default device when the `alsa` feature is enabled and
+//! discards audio otherwise.
+
+use cortenbrowser_shared_types::{AudioBuffer, AudioSink, MediaError};
+use parking_lot::Mutex;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(all(feature = "alsa", target_os = "linux"))]
+pub use crate::alsa_sink::AlsaAudioSink;
+
+/// Creates the audio sink for a new session
+pub type AudioSinkFactory = Arc<dyn Fn() -> Arc<dyn AudioSink> + Send + Sync>;
+
+/// Sink factory used unless the engine is given another
+///
+/// Opens the default ALSA device when the `alsa` feature is enabled and
+/// falls back to a [`NullAudioSink`] when there is no device to play on.
+pub(crate) fn default_sink() -> Arc<dyn AudioSink> {
+    #[cfg(all(feature = "alsa", target_os = "linux"))]
+    match AlsaAudioSink::open_default() {
+        Ok(sink) => return Arc::new(sink),
+        Err(e) => tracing::warn!("No audio output device, discarding audio: {}", e),
+    }
+    Arc::new(NullAudioSink::new())
+}
+
+/// Output volume shared between a sink's control and playback sides
+#[derive(Debug)]
+pub(crate) struct Volume(AtomicU32);
+
+impl Volume {
+    pub(crate) fn new() -> Self {
+        Self(AtomicU32::new(1.0f32.to_bits()))
+    }
+
+    pub(crate) fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    /// Set the volume, clamped to 0.0..=1.0
+    pub(crate) fn set(&self, volume: f32) {
+        let volume = if volume.is_nan() {
+            0.0
+        } else {
+            volume.clamp(0.0, 1.0)
+        };
+        self.0.store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Scale samples by the volume
+    pub(crate) fn apply(&self, samples: &mut [f32]) {
+        let volume = self.get();
+        if volume != 1.0 {
+            samples.iter_mut().for_each(|sample| *sample *= volume);
+        }
+    }
+}
+
+/// Sink that discards audio
+///
+/// Audio counts as played as soon as it is written, so the clock advances by
+/// the duration of each buffer. Used when no output device is available.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_engine::NullAudioSink;
+/// use cortenbrowser_shared_types::{AudioBuffer, AudioFormat, AudioSink};
+/// use std::time::Duration;
+///
+/// let sink = NullAudioSink::new();
+/// let buffer = AudioBuffer::new(AudioFormat::F32LE, 48000, 2, vec![0.0; 960], Duration::ZERO);
+///
+/// sink.write(buffer).unwrap();
+/// assert_eq!(sink.played_duration(), Duration::from_millis(10));
+/// ```
+#[derive(Debug)]
+pub struct NullAudioSink {
+    played: Mutex<Duration>,
+    volume: Volume,
+}
+
+impl NullAudioSink {
+    /// Create a sink that has played nothing
+    pub fn new() -> Self {
+        Self {
+            played: Mutex::new(Duration::ZERO),
+            volume: Volume::new(),
+        }
+    }
+
+    /// Get the current volume
+    pub fn volume(&self) -> f32 {
+        self.volume.get()
+    }
+}
+
+impl Default for NullAudioSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioSink for NullAudioSink {
+    fn write(&self, buffer: AudioBuffer) -> Result<(), MediaError> {
+        *self.played.lock() += buffer.duration;
+        Ok(())
+    }
+
+    fn played_duration(&self) -> Duration {
+        *self.played.lock()
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.volume.set(volume);
+    }
+}
+
+/// Sink that keeps the audio written to it
+///
+/// Buffers are stored with the volume applied, as a device would play them,
+/// and count as played as soon as they are written. Useful in tests and for
+/// rendering audio offline.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_engine::MemoryAudioSink;
+/// use cortenbrowser_shared_types::{AudioBuffer, AudioFormat, AudioSink};
+/// use std::time::Duration;
+///
+/// let sink = MemoryAudioSink::new();
+/// sink.set_volume(0.5);
+/// sink.write(AudioBuffer::new(AudioFormat::F32LE, 48000, 1, vec![1.0; 480], Duration::ZERO))
+///     .unwrap();
+///
+/// assert_eq!(sink.buffers()[0].samples[0], 0.5);
+/// assert_eq!(sink.played_duration(), Duration::from_millis(10));
+/// ```
+#[derive(Debug)]
+pub struct MemoryAudioSink {
+    buffers: Mutex<Vec<AudioBuffer>>,
+    played: Mutex<Duration>,
+    volume: Volume,
+}
+
+impl MemoryAudioSink {
+    /// Create an empty sink
+    pub fn new() -> Self {
+        Self {
+            buffers: Mutex::new(Vec::new()),
+            played: Mutex::new(Duration::ZERO),
+            volume: Volume::new(),
+        }
+    }
+
+    /// Get the buffers written so far, with the volume applied
+    pub fn buffers(&self) -> Vec<AudioBuffer> {
+        self.buffers.lock().clone()
+    }
+
+    /// Take the buffers written so far, leaving the sink empty
+    ///
+    /// The played duration keeps counting them.
+    pub fn take_buffers(&self) -> Vec<AudioBuffer> {
+        std::mem::take(&mut *self.buffers.lock())
+    }
+
+    /// Get the current volume
+    pub fn volume(&self) -> f32 {
+        self.volume.get()
+    }
+}
+
+impl Default for MemoryAudioSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AudioSink for MemoryAudioSink {
+    fn write(&self, mut buffer: AudioBuffer) -> Result<(), MediaError> {
+        self.volume.apply(&mut buffer.samples);
+        *self.played.lock() += buffer.duration;
+        self.buffers.lock().push(buffer);
+        Ok(())
+    }
+
+    fn played_duration(&self) -> Duration {
+        *self.played.lock()
+    }
+
+    fn set_volume(&self, volume: f32) {
+        self.volume.set(volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cortenbrowser_shared_types::AudioFormat;
+
+    fn buffer(value: f32, frames: usize) -> AudioBuffer {
+        AudioBuffer::new(
+            AudioFormat::F32LE,
+            48000,
+            2,
+            vec![value; frames * 2],
+            Duration::ZERO,
+        )
+    }
+
+    #[test]
+    fn test_memory_sink_scales_by_volume() {
+        let sink = MemoryAudioSink::new();
+
+        sink.write(buffer(0.8, 480)).unwrap();
+        sink.set_volume(0.25);
+        sink.write(buffer(0.8, 480)).unwrap();
+        sink.set_volume(0.0);
+        sink.write(buffer(0.8, 480)).unwrap();
+
+        let buffers = sink.buffers();
+        assert!(buffers[0].samples.iter().all(|&s| s == 0.8));
+        assert!(buffers[1].samples.iter().all(|&s| (s - 0.2).abs() < 1e-6));
+        assert!(buffers[2].samples.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn test_memory_sink_clock_progresses_with_written_audio() {
+        let sink = MemoryAudioSink::new();
+        assert_eq!(sink.played_duration(), Duration::ZERO);
+
+        for _ in 0..5 {
+            sink.write(buffer(0.0, 960)).unwrap();
+        }
+        assert_eq!(sink.played_duration(), Duration::from_millis(100));
+
+        sink.take_buffers();
+        sink.write(buffer(0.0, 480)).unwrap();
+        assert_eq!(sink.played_duration(), Duration::from_millis(110));
+        assert_eq!(sink.buffers().len(), 1);
+    }
+
+    #[test]
+    fn test_volume_is_clamped() {
+        let volume = Volume::new();
+        assert_eq!(volume.get(), 1.0);
+
+        volume.set(1.5);
+        assert_eq!(volume.get(), 1.0);
+        volume.set(-0.5);
+        assert_eq!(volume.get(), 0.0);
+        volume.set(f32::NAN);
+        assert_eq!(volume.get(), 0.0);
+    }
+
+    #[test]
+    fn test_null_sink_clock_progresses() {
+        let sink = NullAudioSink::new();
+
+        sink.write(buffer(1.0, 4800)).unwrap();
+        sink.set_volume(0.5);
+
+        assert_eq!(sink.played_duration(), Duration::from_millis(100));
+        assert_eq!(sink.volume(), 0.5);
+    }
+}
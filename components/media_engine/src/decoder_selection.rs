@@ -0,0 +1,135 @@
+//! Video decoder selection
+//!
+//! Each session's [`DecoderSelectionPolicy`] decides whether its video is
+//! decoded in hardware or software. The hardware context is shared by all
+//! sessions and only initialized the first time a policy asks for hardware,
+//! so engines that only decode in software never touch the hardware.
+
+use cortenbrowser_hardware_accel::{HardwareContext, HardwareError};
+use cortenbrowser_shared_types::{DecoderSelectionPolicy, MediaError, VideoCodec, VideoDecoder};
+use cortenbrowser_video_decoders::DecoderFactory;
+use std::sync::OnceLock;
+use tracing::{debug, warn};
+
+/// Creates video decoders according to a selection policy
+pub(crate) struct DecoderSelector {
+    /// Whether hardware decoding is allowed at all
+    hardware_enabled: bool,
+    /// Hardware context, `None` inside if hardware is unavailable
+    hardware: OnceLock<Option<HardwareContext>>,
+}
+
+impl DecoderSelector {
+    /// Creates a selector; with `hardware_enabled` unset, hardware is
+    /// treated as unavailable
+    pub(crate) fn new(hardware_enabled: bool) -> Self {
+        Self {
+            hardware_enabled,
+            hardware: OnceLock::new(),
+        }
+    }
+
+    /// Returns whether the hardware context has been initialized
+    #[cfg(test)]
+    pub(crate) fn is_hardware_initialized(&self) -> bool {
+        self.hardware.get().is_some()
+    }
+
+    /// Checks that hardware can decode `codec`, or any codec if `None`
+    ///
+    /// Initializes the hardware context.
+    pub(crate) fn check_hardware(&self, codec: Option<&VideoCodec>) -> Result<(), MediaError> {
+        let Some(hardware) = self.hardware() else {
+            return Err(HardwareError::NotAvailable.into());
+        };
+        match codec {
+            Some(codec) if !hardware.is_codec_supported(codec) => {
+                Err(HardwareError::UnsupportedCodec.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Creates a decoder for `codec` as `policy` prefers
+    pub(crate) fn create_decoder(
+        &self,
+        policy: DecoderSelectionPolicy,
+        codec: &VideoCodec,
+    ) -> Result<Box<dyn VideoDecoder>, MediaError> {
+        match policy {
+            DecoderSelectionPolicy::SoftwareOnly => Self::create_software(codec),
+            DecoderSelectionPolicy::HardwareOnly => self.create_hardware(codec),
+            DecoderSelectionPolicy::HardwareFirst => self.create_hardware(codec).or_else(|e| {
+                debug!("No hardware decoder for {:?}, using software: {}", codec, e);
+                Self::create_software(codec)
+            }),
+            DecoderSelectionPolicy::SoftwareFirst => Self::create_software(codec).or_else(|e| {
+                debug!("No software decoder for {:?}, using hardware: {}", codec, e);
+                self.create_hardware(codec)
+            }),
+        }
+    }
+
+    fn create_software(codec: &VideoCodec) -> Result<Box<dyn VideoDecoder>, MediaError> {
+        DecoderFactory::create_decoder(codec.clone())
+    }
+
+    fn create_hardware(&self, codec: &VideoCodec) -> Result<Box<dyn VideoDecoder>, MediaError> {
+        let hardware = self.hardware().ok_or(HardwareError::NotAvailable)?;
+        Ok(hardware.create_decoder(codec)?)
+    }
+
+    /// Returns the hardware context, initializing it on first use
+    fn hardware(&self) -> Option<&HardwareContext> {
+        self.hardware
+            .get_or_init(|| {
+                if !self.hardware_enabled {
+                    return None;
+                }
+                HardwareContext::new()
+                    .map_err(|e| warn!("Hardware decoding unavailable: {}", e))
+                    .ok()
+            })
+            .as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cortenbrowser_shared_types::{H264Level, H264Profile};
+
+    fn h264() -> VideoCodec {
+        VideoCodec::H264 {
+            profile: H264Profile::High,
+            level: H264Level::Level4_1,
+            hardware_accel: false,
+        }
+    }
+
+    #[test]
+    fn test_software_only_does_not_initialize_hardware() {
+        let selector = DecoderSelector::new(true);
+        assert!(selector
+            .create_decoder(DecoderSelectionPolicy::SoftwareOnly, &h264())
+            .is_ok());
+        assert!(!selector.is_hardware_initialized());
+    }
+
+    #[test]
+    fn test_hardware_only_fails_when_hardware_disabled() {
+        let selector = DecoderSelector::new(false);
+        let result = selector.create_decoder(DecoderSelectionPolicy::HardwareOnly, &h264());
+        assert!(matches!(result, Err(MediaError::HardwareError { .. })));
+        assert!(selector.check_hardware(None).is_err());
+    }
+
+    #[test]
+    fn test_hardware_first_falls_back_to_software() {
+        let selector = DecoderSelector::new(false);
+        assert!(selector
+            .create_decoder(DecoderSelectionPolicy::HardwareFirst, &h264())
+            .is_ok());
+        assert!(selector.is_hardware_initialized());
+    }
+}
@@ -1,34 +1,126 @@
 ///! Media Engine implementation - coordinates all media components
-use crate::types::{MediaEngineConfig, MediaEngineEvent, MediaEngineMessage};
-use cortenbrowser_media_pipeline::MediaPipeline;
-use cortenbrowser_media_session::{MediaSession, SessionManager, SessionState};
+use crate::audio_output::{self, AudioSinkFactory};
+use crate::decoder_selection::DecoderSelector;
+use crate::types::{MediaEngineConfig, MediaEngineEvent, MediaEngineMessage, MediaEngineStats};
+use cortenbrowser_buffer_manager::BufferManager;
+use cortenbrowser_drm_support::{ContentDecryptionModule, SampleEncryption};
+use cortenbrowser_format_parsers::MediaInfo;
+use cortenbrowser_media_capture::{DeviceEnumerator, DeviceEvent};
+use cortenbrowser_media_pipeline::{
+    MediaPipeline, PipelineConfig, Representation, SyncDecision, TrackInfo, TrackKind,
+};
+use cortenbrowser_media_session::{
+    MediaMetadata, MediaSession, SessionEvent, SessionManager, SessionSnapshot, SessionState,
+};
 use cortenbrowser_shared_types::{
-    AudioBuffer, MediaEngine, MediaError, MediaSessionConfig, MediaSource, SessionId, VideoFrame,
+    AudioBuffer, AudioSink, DecoderSelectionPolicy, EncryptionInfo, LoopMode, MediaChunk,
+    MediaElementAttributes, MediaEngine, MediaError, MediaSample, MediaSessionConfig, MediaSource,
+    MediaStreamTrack, PlaybackCommand, PlaybackStats, SessionId, VideoFrame, MAX_PLAYBACK_RATE,
+    MIN_PLAYBACK_RATE,
 };
-use parking_lot::RwLock;
+use futures_util::StreamExt;
+use parking_lot::{Mutex, RwLock};
 use std::collections::HashMap;
+use std::io::Cursor;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
-use tracing::{debug, error, info};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tracing::{debug, error, info, warn};
 
 /// Media Engine implementation
 ///
 /// Coordinates all media components including session management, pipeline orchestration,
 /// format parsing, decoding, buffering, and synchronization.
 pub struct MediaEngineImpl {
+    inner: Arc<EngineInner>,
+}
+
+/// State of an engine, shared with its message loop
+struct EngineInner {
     /// Configuration
     config: MediaEngineConfig,
     /// Session manager
     session_manager: Arc<SessionManager>,
     /// Active sessions with their pipelines
     sessions: Arc<RwLock<HashMap<SessionId, SessionContext>>>,
-    /// Message receiver channel
+    /// Message sender channel (cloned for users of the engine)
+    message_tx: mpsc::UnboundedSender<MediaEngineMessage>,
+    /// Message receiver channel, taken by the message loop
     message_rx: Arc<RwLock<Option<mpsc::UnboundedReceiver<MediaEngineMessage>>>>,
     /// Event sender channel
-    event_tx: mpsc::UnboundedSender<MediaEngineEvent>,
+    event_tx: EventSender,
     /// Event receiver channel (for users of the engine)
     event_rx: Arc<RwLock<Option<mpsc::UnboundedReceiver<MediaEngineEvent>>>>,
+    /// Capture device enumerator, watched for hot-plug events
+    capture_devices: DeviceEnumerator,
+    /// Creates the audio sink of each new session
+    audio_sink_factory: RwLock<AudioSinkFactory>,
+    /// Buffer manager, whose memory counts towards the engine statistics
+    buffer_manager: Mutex<BufferManager>,
+    /// Creates the sessions' video decoders
+    decoders: Arc<DecoderSelector>,
+    /// Set by `shutdown`, after which no sessions are created
+    shutting_down: AtomicBool,
+    /// Media elements by ID, with the sessions playing their media
+    elements: Arc<RwLock<HashMap<String, SessionId>>>,
+}
+
+/// Sends engine events, followed by a `MediaElementEvent` for each event of
+/// a media element's session
+#[derive(Clone)]
+struct EventSender {
+    tx: mpsc::UnboundedSender<MediaEngineEvent>,
+    elements: Arc<RwLock<HashMap<String, SessionId>>>,
+}
+
+impl EventSender {
+    fn send(&self, event: MediaEngineEvent) -> Result<(), mpsc::error::SendError<()>> {
+        let element_id = event_session(&event).and_then(|session| {
+            self.elements
+                .read()
+                .iter()
+                .find(|(_, &element_session)| element_session == session)
+                .map(|(element_id, _)| element_id.clone())
+        });
+        let element_event = element_id.map(|element_id| MediaEngineEvent::MediaElementEvent {
+            element_id,
+            event: Box::new(event.clone()),
+        });
+
+        let closed = |_| mpsc::error::SendError(());
+        self.tx.send(event).map_err(closed)?;
+        if let Some(element_event) = element_event {
+            self.tx.send(element_event).map_err(closed)?;
+        }
+        Ok(())
+    }
+}
+
+/// The session an event is about, if it is an event of a single session
+fn event_session(event: &MediaEngineEvent) -> Option<SessionId> {
+    match event {
+        MediaEngineEvent::VideoFrameReady { session_id, .. }
+        | MediaEngineEvent::AudioSamplesReady { session_id, .. }
+        | MediaEngineEvent::PlaybackStateChanged { session_id, .. }
+        | MediaEngineEvent::MediaError { session_id, .. }
+        | MediaEngineEvent::BufferingStarted { session_id, .. }
+        | MediaEngineEvent::BufferingEnded { session_id, .. }
+        | MediaEngineEvent::DurationChanged { session_id, .. }
+        | MediaEngineEvent::BufferedRangesChanged { session_id, .. }
+        | MediaEngineEvent::VideoTrackChanged { session_id, .. }
+        | MediaEngineEvent::BitrateChanged { session_id, .. }
+        | MediaEngineEvent::WaitingForKey { session_id } => Some(*session_id),
+        MediaEngineEvent::MediaElementCreated { .. }
+        | MediaEngineEvent::MediaElementEvent { .. }
+        | MediaEngineEvent::MediaElementRemoved { .. }
+        | MediaEngineEvent::PeriodicStats(_)
+        | MediaEngineEvent::CaptureDeviceAdded { .. }
+        | MediaEngineEvent::CaptureDeviceRemoved { .. }
+        | MediaEngineEvent::ShutdownComplete => None,
+    }
 }
 
 /// Context for a single media session
@@ -37,6 +129,65 @@ struct SessionContext {
     session: Arc<MediaSession>,
     /// The media pipeline for this session
     pipeline: Option<Arc<MediaPipeline>>,
+    /// Playback rate (1.0 = normal speed)
+    playback_rate: f32,
+    /// What playback does at the end of the media
+    loop_mode: LoopMode,
+    /// Audio output, also the pipeline's clock once it plays
+    audio_sink: Arc<dyn AudioSink>,
+    /// Task feeding the pipeline's audio to the sink while playing
+    audio_task: Option<JoinHandle<()>>,
+    /// Task feeding a capture track's media to the pipeline
+    capture_task: Option<JoinHandle<()>>,
+    /// Audio `get_audio_samples` collected but did not return
+    pending_audio: Option<AudioBuffer>,
+    /// Video frame `get_video_frame` found ahead of the clock
+    pending_video: Option<VideoFrame>,
+    /// Whether video is decoded in hardware or software
+    decoder_policy: DecoderSelectionPolicy,
+    /// How far a video frame may be from the clock and still be displayed
+    sync_threshold: Duration,
+    /// Decrypts the session's encrypted media
+    cdm: Option<Arc<ContentDecryptionModule>>,
+    /// Configuration the session was created with
+    config: MediaSessionConfig,
+}
+
+/// What a session's pipeline is opened with
+struct PipelineSettings {
+    playback_rate: f32,
+    loop_mode: LoopMode,
+    audio_sink: Arc<dyn AudioSink>,
+    sync_threshold: Duration,
+    decoder_policy: DecoderSelectionPolicy,
+    cdm: Option<Arc<ContentDecryptionModule>>,
+    config: MediaSessionConfig,
+}
+
+impl SessionContext {
+    /// Settings for a new pipeline of the session
+    fn pipeline_settings(&self) -> PipelineSettings {
+        PipelineSettings {
+            playback_rate: self.playback_rate,
+            loop_mode: self.loop_mode,
+            audio_sink: Arc::clone(&self.audio_sink),
+            sync_threshold: self.sync_threshold,
+            decoder_policy: self.decoder_policy,
+            cdm: self.cdm.clone(),
+            config: self.config.clone(),
+        }
+    }
+
+    /// Current playback position
+    ///
+    /// Read from the pipeline clock; before a source is loaded, the last
+    /// position the session reported.
+    fn position(&self) -> Duration {
+        match &self.pipeline {
+            Some(pipeline) => pipeline.current_position(),
+            None => self.session.position(),
+        }
+    }
 }
 
 impl MediaEngineImpl {
@@ -48,6 +199,9 @@ impl MediaEngineImpl {
     /// # Returns
     /// * `Ok(MediaEngineImpl)` - Successfully created engine
     /// * `Err(MediaError)` - Failed to create engine
+    ///
+    /// Within a Tokio runtime, the message loop is started right away;
+    /// otherwise it is started with [`run`](Self::run).
     pub fn new(config: MediaEngineConfig) -> Result<Self, MediaError> {
         info!("Creating MediaEngine with config: {:?}", config);
 
@@ -57,31 +211,115 @@ impl MediaEngineImpl {
         // Create message/event channels
         let (message_tx, message_rx) = mpsc::unbounded_channel();
         let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let elements = Arc::new(RwLock::new(HashMap::new()));
 
-        Ok(Self {
-            config,
-            session_manager,
-            sessions: Arc::new(RwLock::new(HashMap::new())),
-            message_rx: Arc::new(RwLock::new(Some(message_rx))),
-            event_tx,
-            event_rx: Arc::new(RwLock::new(Some(event_rx))),
-        })
+        let engine = Self {
+            inner: Arc::new(EngineInner {
+                session_manager,
+                sessions: Arc::new(RwLock::new(HashMap::new())),
+                message_tx,
+                message_rx: Arc::new(RwLock::new(Some(message_rx))),
+                event_tx: EventSender {
+                    tx: event_tx,
+                    elements: Arc::clone(&elements),
+                },
+                event_rx: Arc::new(RwLock::new(Some(event_rx))),
+                capture_devices: DeviceEnumerator::new(),
+                audio_sink_factory: RwLock::new(Arc::new(audio_output::default_sink)),
+                buffer_manager: Mutex::new(BufferManager::new(config.buffer_config.clone())),
+                decoders: Arc::new(DecoderSelector::new(config.hardware_accel_enabled)),
+                shutting_down: AtomicBool::new(false),
+                elements,
+                config,
+            }),
+        };
+        if tokio::runtime::Handle::try_current().is_ok() {
+            engine.run();
+        }
+        Ok(engine)
+    }
+
+    /// Set how the audio sink of each new session is created
+    ///
+    /// By default sessions play on the default ALSA device when the `alsa`
+    /// feature is enabled, and discard their audio otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_engine::{MediaEngineConfig, MediaEngineImpl, MemoryAudioSink};
+    /// use std::sync::Arc;
+    ///
+    /// let engine = MediaEngineImpl::new(MediaEngineConfig::default())
+    ///     .unwrap()
+    ///     .with_audio_sink_factory(|| Arc::new(MemoryAudioSink::new()));
+    /// ```
+    pub fn with_audio_sink_factory(
+        self,
+        factory: impl Fn() -> Arc<dyn AudioSink> + Send + Sync + 'static,
+    ) -> Self {
+        *self.inner.audio_sink_factory.write() = Arc::new(factory);
+        self
     }
 
     /// Get the message sender channel
     ///
-    /// Users can send MediaEngineMessage through this channel
+    /// Users can send MediaEngineMessage through this channel; messages are
+    /// handled once the message loop is started, by [`new`](Self::new)
+    /// within a Tokio runtime or else by [`run`](Self::run).
     pub fn message_sender(&self) -> mpsc::UnboundedSender<MediaEngineMessage> {
-        // Create a new sender from the receiver
-        // Note: In real implementation, we'd store the sender separately
-        unimplemented!("Message sender needs proper implementation")
+        self.inner.message_tx.clone()
+    }
+
+    /// Start the message loop
+    ///
+    /// Spawns a task that handles each message sent through
+    /// [`message_sender`](Self::message_sender) in order. Failures of
+    /// messages for a session are reported as `MediaError` events. The task
+    /// ends once the engine has been dropped and all senders are closed.
+    ///
+    /// Must be called from within a Tokio runtime, and is only needed if
+    /// the engine was created outside of one.
+    ///
+    /// # Returns
+    /// * `Some(JoinHandle)` - The message loop task
+    /// * `None` - The message loop is already running
+    pub fn run(&self) -> Option<JoinHandle<()>> {
+        let mut messages = self.inner.message_rx.write().take()?;
+        let inner = Arc::downgrade(&self.inner);
+
+        Some(tokio::spawn(async move {
+            while let Some(message) = messages.recv().await {
+                let Some(inner) = inner.upgrade() else {
+                    break;
+                };
+                let engine = Self { inner };
+                // Failures creating an element are reported by `handle_message`
+                let session_id = match &message {
+                    MediaEngineMessage::StreamData { session_id, .. }
+                    | MediaEngineMessage::PlaybackCommand { session_id, .. } => Some(*session_id),
+                    MediaEngineMessage::ElementCommand { element_id, .. }
+                    | MediaEngineMessage::RemoveMediaElement { element_id } => {
+                        engine.element_session(element_id)
+                    }
+                    MediaEngineMessage::CreateMediaElement { .. } => None,
+                };
+                if let Err(error) = engine.handle_message(message).await {
+                    error!("Failed to handle message: {}", error);
+                    if let Some(session_id) = session_id {
+                        engine.report_failure(session_id, error);
+                    }
+                }
+            }
+            debug!("Message loop stopped");
+        }))
     }
 
     /// Take the event receiver channel
     ///
     /// Users can receive MediaEngineEvent through this channel
     pub fn take_event_receiver(&self) -> Option<mpsc::UnboundedReceiver<MediaEngineEvent>> {
-        self.event_rx.write().take()
+        self.inner.event_rx.write().take()
     }
 
     /// Handle a message
@@ -92,13 +330,11 @@ impl MediaEngineImpl {
                 attributes,
             } => {
                 debug!("Creating media element: {}", element_id);
-                // TODO: Implement media element creation
-                Ok(())
+                self.create_media_element(element_id, &attributes).await
             }
             MediaEngineMessage::StreamData { session_id, chunk } => {
                 debug!("Received stream data for session: {:?}", session_id);
-                // TODO: Feed chunk to pipeline
-                Ok(())
+                self.feed_stream(session_id, &chunk)
             }
             MediaEngineMessage::PlaybackCommand {
                 session_id,
@@ -108,386 +344,3624 @@ impl MediaEngineImpl {
                     "Playback command for session {:?}: {:?}",
                     session_id, command
                 );
-                // TODO: Execute playback command
-                Ok(())
+                self.execute_command(session_id, command).await
+            }
+            MediaEngineMessage::ElementCommand {
+                element_id,
+                command,
+            } => {
+                debug!("Playback command for element {}: {:?}", element_id, command);
+                let session_id = self
+                    .element_session(&element_id)
+                    .ok_or_else(|| unknown_element(&element_id))?;
+                self.execute_command(session_id, command).await
+            }
+            MediaEngineMessage::RemoveMediaElement { element_id } => {
+                debug!("Removing media element: {}", element_id);
+                self.remove_media_element(&element_id).await
             }
         }
     }
 
-    /// Emit an event
-    fn emit_event(&self, event: MediaEngineEvent) {
-        if let Err(e) = self.event_tx.send(event) {
-            error!("Failed to send event: {}", e);
-        }
-    }
-}
-
-impl MediaEngine for MediaEngineImpl {
-    async fn create_session(&self, config: MediaSessionConfig) -> Result<SessionId, MediaError> {
-        info!("Creating media session with config: {:?}", config);
-
-        // Check session limit
-        {
-            let sessions = self.sessions.read();
-            if sessions.len() >= self.config.max_sessions {
-                return Err(MediaError::ResourceExhausted(format!(
-                    "Maximum sessions ({}) reached",
-                    self.config.max_sessions
-                )));
-            }
+    /// Create a media element with a new session playing its media
+    ///
+    /// The element is registered and `MediaElementCreated` emitted before
+    /// its attributes are applied, so a failure to load or play its `src`
+    /// leaves the element in place with its session in the `Error` state.
+    /// From then on, each event of the session is followed by a
+    /// `MediaElementEvent` carrying the element ID.
+    ///
+    /// # Arguments
+    /// * `element_id` - ID the element is addressed by
+    /// * `attributes` - Attributes of the media element
+    ///
+    /// # Returns
+    /// * `Ok(())` - Element created
+    /// * `Err(MediaError)` - Element ID already in use, session limit
+    ///   reached, or the attributes failed to apply
+    async fn create_media_element(
+        &self,
+        element_id: String,
+        attributes: &MediaElementAttributes,
+    ) -> Result<(), MediaError> {
+        if self.inner.elements.read().contains_key(&element_id) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Media element {} already exists",
+                element_id
+            )));
         }
 
-        // Create session through session manager
-        let session_id = self.session_manager.create(config)?;
-
-        // Get the session
-        let session = self
-            .session_manager
-            .get(session_id)
-            .ok_or_else(|| MediaError::SessionNotFound(session_id))?;
-
-        // Store session context (without pipeline initially)
-        let context = SessionContext {
-            session,
-            pipeline: None,
-        };
-
-        self.sessions.write().insert(session_id, context);
+        let config = MediaSessionConfig::new().with_preload(attributes.preload);
+        let session_id = self.create_session(config).await?;
+        self.inner
+            .elements
+            .write()
+            .insert(element_id.clone(), session_id);
+        self.emit_event(MediaEngineEvent::MediaElementCreated {
+            element_id,
+            session_id,
+        });
 
-        info!("Created session: {:?}", session_id);
-        Ok(session_id)
+        self.apply_attributes(session_id, attributes)
+            .await
+            .inspect_err(|error| self.report_failure(session_id, error.clone()))
     }
 
-    async fn load_source(&self, session: SessionId, source: MediaSource) -> Result<(), MediaError> {
-        info!("Loading source for session: {:?}", session);
-
-        // Get session context
-        let mut sessions = self.sessions.write();
-        let context = sessions
-            .get_mut(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
+    /// Remove a media element, destroying its session and pipeline
+    ///
+    /// # Arguments
+    /// * `element_id` - ID of the element
+    ///
+    /// # Returns
+    /// * `Ok(())` - Element removed and `MediaElementRemoved` emitted
+    /// * `Err(MediaError)` - Unknown element, or its session failed to be
+    ///   destroyed
+    async fn remove_media_element(&self, element_id: &str) -> Result<(), MediaError> {
+        let session_id = self
+            .element_session(element_id)
+            .ok_or_else(|| unknown_element(element_id))?;
+        self.destroy_session(session_id).await?;
+        self.emit_event(MediaEngineEvent::MediaElementRemoved {
+            element_id: element_id.to_string(),
+        });
+        Ok(())
+    }
 
-        // Create pipeline for this session
-        let pipeline = MediaPipeline::new(self.config.pipeline_config.clone())?;
+    /// The session playing a media element's media
+    ///
+    /// # Arguments
+    /// * `element_id` - ID of the element
+    ///
+    /// # Returns
+    /// The element's session, or `None` for an unknown element
+    pub fn element_session(&self, element_id: &str) -> Option<SessionId> {
+        self.inner.elements.read().get(element_id).copied()
+    }
 
-        // TODO: Configure pipeline with source
-        // pipeline.set_source(source)?;
+    /// Create a session configured by the attributes of a media element
+    ///
+    /// The session loops when `loop` is set, starting over at the end of the
+    /// media instead of ending. A `src` URL is loaded, and played if
+    /// `autoplay` is set.
+    ///
+    /// # Arguments
+    /// * `attributes` - Attributes of the media element
+    ///
+    /// # Returns
+    /// * `Ok(SessionId)` - The new session
+    /// * `Err(MediaError)` - Session limit reached, invalid playback rate, or
+    ///   the source failed to load or play
+    pub async fn create_session_from_attributes(
+        &self,
+        attributes: &MediaElementAttributes,
+    ) -> Result<SessionId, MediaError> {
+        let config = MediaSessionConfig::new().with_preload(attributes.preload);
+        let session = self.create_session(config).await?;
+        self.apply_attributes(session, attributes).await?;
+        Ok(session)
+    }
 
-        context.pipeline = Some(Arc::new(pipeline));
+    /// Configure a session by the attributes of a media element
+    async fn apply_attributes(
+        &self,
+        session: SessionId,
+        attributes: &MediaElementAttributes,
+    ) -> Result<(), MediaError> {
+        self.set_loop(session, attributes.loop_mode()).await?;
+        self.set_rate(session, attributes.playback_rate).await?;
+        if attributes.muted {
+            self.set_muted(session, true)?;
+        }
 
-        info!("Loaded source for session: {:?}", session);
+        if let Some(url) = &attributes.src {
+            let source = MediaSource::Url { url: url.clone() };
+            self.load_source(session, source).await?;
+            if attributes.autoplay {
+                self.play(session).await?;
+            }
+        }
         Ok(())
     }
 
-    async fn play(&self, session: SessionId) -> Result<(), MediaError> {
-        info!("Play requested for session: {:?}", session);
-
-        let sessions = self.sessions.read();
+    /// Feed a chunk of a streamed source to the session's pipeline
+    ///
+    /// The session becomes ready once the chunks fed so far describe the media.
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `chunk` - Next chunk of the session's source
+    ///
+    /// # Returns
+    /// * `Ok(())` - Chunk accepted
+    /// * `Err(MediaError)` - Unknown session, no loaded source, or unparseable data
+    pub fn feed_stream(&self, session: SessionId, chunk: &MediaChunk) -> Result<(), MediaError> {
+        let sessions = self.inner.sessions.read();
         let context = sessions
             .get(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
-
-        // Transition session state
-        context.session.set_state(SessionState::Playing {
-            position: Duration::from_secs(0),
-            rate: 1.0,
-        });
+            .ok_or(MediaError::SessionNotFound(session))?;
+        let pipeline = context
+            .pipeline
+            .as_ref()
+            .ok_or_else(|| MediaError::InvalidState("No pipeline for session".to_string()))?;
 
-        // Start pipeline
-        if let Some(pipeline) = &context.pipeline {
-            // TODO: Start pipeline playback
-            debug!("Starting pipeline for session: {:?}", session);
+        if let Some(info) = pipeline.feed(chunk)? {
+            self.set_ready(session, context, &info);
         }
-
-        // Emit state changed event
-        self.emit_event(MediaEngineEvent::PlaybackStateChanged {
-            session_id: session,
-            state: SessionState::Playing {
-                position: Duration::from_secs(0),
-                rate: 1.0,
-            },
-        });
-
         Ok(())
     }
 
-    async fn pause(&self, session: SessionId) -> Result<(), MediaError> {
-        info!("Pause requested for session: {:?}", session);
-
-        let sessions = self.sessions.read();
+    /// List the video and audio tracks of a session's media
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    ///
+    /// # Returns
+    /// * `Ok(Vec<TrackInfo>)` - Tracks from the demuxer's media information,
+    ///   with the playing track of each kind selected; empty until it is known
+    /// * `Err(MediaError)` - Unknown session or no loaded source
+    pub fn available_tracks(&self, session: SessionId) -> Result<Vec<TrackInfo>, MediaError> {
+        let sessions = self.inner.sessions.read();
         let context = sessions
             .get(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
-
-        // Get current position
-        let position = Duration::from_secs(0); // TODO: Get from pipeline
+            .ok_or(MediaError::SessionNotFound(session))?;
+        let pipeline = context
+            .pipeline
+            .as_ref()
+            .ok_or_else(|| MediaError::InvalidState("No pipeline for session".to_string()))?;
+        Ok(pipeline.tracks())
+    }
 
-        // Transition session state
-        context
-            .session
-            .set_state(SessionState::Paused { position });
+    /// Select the video or audio track a session plays
+    ///
+    /// Switching the video track restarts video decoding at the current
+    /// position; switching the audio track flushes only the audio path.
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `track_id` - ID of a track listed by `available_tracks`
+    ///
+    /// # Returns
+    /// * `Ok(())` - Track selected
+    /// * `Err(MediaError)` - Unknown session or track, no loaded source, or
+    ///   a video track of a source that cannot seek
+    pub fn select_track(&self, session: SessionId, track_id: u32) -> Result<(), MediaError> {
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+        let pipeline = context
+            .pipeline
+            .as_ref()
+            .ok_or_else(|| MediaError::InvalidState("No pipeline for session".to_string()))?;
+        let kind = pipeline
+            .tracks()
+            .into_iter()
+            .find(|track| track.track_id == track_id)
+            .map(|track| track.kind);
 
-        // Pause pipeline
-        if let Some(pipeline) = &context.pipeline {
-            // TODO: Pause pipeline
-            debug!("Pausing pipeline for session: {:?}", session);
+        pipeline.select_track(track_id)?;
+        match kind {
+            Some(TrackKind::Video) => context.pending_video = None,
+            Some(TrackKind::Audio) => context.pending_audio = None,
+            None => {}
         }
-
-        // Emit state changed event
-        self.emit_event(MediaEngineEvent::PlaybackStateChanged {
-            session_id: session,
-            state: SessionState::Paused { position },
-        });
-
+        info!("Selected track {} for session: {:?}", track_id, session);
         Ok(())
     }
 
-    async fn seek(&self, session: SessionId, position: Duration) -> Result<(), MediaError> {
-        info!(
-            "Seek to {:?} requested for session: {:?}",
-            position, session
-        );
-
-        let sessions = self.sessions.read();
+    /// Switch a session to the video track at `index` of its media's video
+    /// tracks, e.g. another camera angle
+    ///
+    /// Video decoding restarts on the new track at the current position
+    /// while audio plays on. A switch emits `VideoTrackChanged`.
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `index` - Index into the media's video tracks
+    ///
+    /// # Returns
+    /// * `Ok(())` - Track selected
+    /// * `Err(MediaError)` - Unknown session or index, no loaded source, or a
+    ///   source that cannot seek
+    pub fn select_video_track(&self, session: SessionId, index: usize) -> Result<(), MediaError> {
+        let mut sessions = self.inner.sessions.write();
         let context = sessions
-            .get(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
-
-        // Transition to seeking state
-        context
-            .session
-            .set_state(SessionState::Seeking { target: position });
-
-        // Seek in pipeline
-        if let Some(pipeline) = &context.pipeline {
-            // TODO: Perform seek in pipeline
-            debug!(
-                "Seeking pipeline to {:?} for session: {:?}",
-                position, session
-            );
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+        let pipeline = context
+            .pipeline
+            .as_ref()
+            .ok_or_else(|| MediaError::InvalidState("No pipeline for session".to_string()))?;
+        let selected = pipeline
+            .tracks()
+            .into_iter()
+            .filter(|track| track.kind == TrackKind::Video)
+            .position(|track| track.selected);
+        if selected == Some(index) {
+            return Ok(());
         }
 
-        // Transition back to playing/paused
-        context.session.set_state(SessionState::Playing {
-            position,
-            rate: 1.0,
-        });
-
-        // Emit state changed event
-        self.emit_event(MediaEngineEvent::PlaybackStateChanged {
+        pipeline.switch_video_track(index)?;
+        context.pending_video = None;
+        drop(sessions);
+        info!("Selected video track {} for session: {:?}", index, session);
+        self.emit_event(MediaEngineEvent::VideoTrackChanged {
             session_id: session,
-            state: SessionState::Playing {
-                position,
-                rate: 1.0,
-            },
+            track_index: index,
         });
-
         Ok(())
     }
 
-    async fn set_volume(&self, session: SessionId, volume: f32) -> Result<(), MediaError> {
-        info!("Set volume to {} for session: {:?}", volume, session);
-
-        // Validate volume range
-        if !(0.0..=1.0).contains(&volume) {
-            return Err(MediaError::InvalidParameter(format!(
-                "Volume must be between 0.0 and 1.0, got {}",
-                volume
-            )));
-        }
-
-        let sessions = self.sessions.read();
-        let _context = sessions
+    /// Subscribe to the events of a session
+    ///
+    /// Besides its state changes and metadata, the session reports its
+    /// position while playing, at `MediaSessionConfig::position_update_interval`,
+    /// when it starts and stops buffering, and when an operation on it fails.
+    ///
+    /// # Returns
+    /// * `Ok(Receiver)` - Receiver of the events broadcast from now on
+    /// * `Err(MediaError::SessionNotFound)` - Unknown session
+    pub fn subscribe_session_events(
+        &self,
+        session: SessionId,
+    ) -> Result<broadcast::Receiver<SessionEvent>, MediaError> {
+        let sessions = self.inner.sessions.read();
+        let context = sessions
             .get(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
-
-        // TODO: Set volume on audio output
-        debug!("Setting volume to {} for session: {:?}", volume, session);
-
-        Ok(())
+            .ok_or(MediaError::SessionNotFound(session))?;
+        Ok(context.session.subscribe_events())
     }
 
-    async fn get_video_frame(&self, session: SessionId) -> Result<VideoFrame, MediaError> {
-        debug!("Get video frame for session: {:?}", session);
+    /// Repeat the section of a session's media from `start` to `end`
+    ///
+    /// Shorthand for `set_loop` with `LoopMode::AB`: each time playback
+    /// reaches `end` it seeks back to `start`, announced to the session's
+    /// subscribers with `SessionEvent::AbLoopIteration`. While the loop is
+    /// set, `Playing` states carry its points. Another `set_loop` mode
+    /// replaces it.
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `start` - Loop start (A point)
+    /// * `end` - Loop end (B point)
+    ///
+    /// # Returns
+    /// * `Ok(())` - Loop set
+    /// * `Err(MediaError)` - Unknown session, or not
+    ///   `start < end <= duration`
+    pub async fn set_ab_loop(
+        &self,
+        session: SessionId,
+        start: Duration,
+        end: Duration,
+    ) -> Result<(), MediaError> {
+        self.set_loop(session, LoopMode::AB { start, end }).await
+    }
 
-        let sessions = self.sessions.read();
+    /// Play a session's source as an adaptive stream of `representations`
+    ///
+    /// The session's pipeline switches between the representations as its
+    /// buffer fills and drains, see `MediaPipeline::set_representations`.
+    /// Every switch, and the initial representation, is announced with a
+    /// `BitrateChanged` event.
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `representations` - Encodings of the stream, e.g. from its manifest
+    ///
+    /// # Returns
+    /// * `Ok(())` - Representations set
+    /// * `Err(MediaError)` - Unknown session, no loaded source, or no
+    ///   representations
+    pub fn set_representations(
+        &self,
+        session: SessionId,
+        representations: Vec<Representation>,
+    ) -> Result<(), MediaError> {
+        let sessions = self.inner.sessions.read();
         let context = sessions
             .get(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
+            .ok_or(MediaError::SessionNotFound(session))?;
+        let pipeline = context
+            .pipeline
+            .as_ref()
+            .ok_or_else(|| MediaError::InvalidState("No pipeline for session".to_string()))?;
+        pipeline.set_representations(representations)
+    }
+
+    /// Set how far a session's video frames may be from the clock and still
+    /// be displayed
+    ///
+    /// Replaces `PipelineConfig::sync_threshold` for the session, including
+    /// for sources it loads later. Live streams may want less than the
+    /// default 40ms, offline playback can tolerate more.
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `threshold` - Sync threshold
+    ///
+    /// # Returns
+    /// * `Ok(())` - Threshold set
+    /// * `Err(MediaError)` - Unknown session
+    pub fn set_sync_threshold(
+        &self,
+        session: SessionId,
+        threshold: Duration,
+    ) -> Result<(), MediaError> {
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
 
-        // Get frame from pipeline
+        context.sync_threshold = threshold;
         if let Some(pipeline) = &context.pipeline {
-            // TODO: Get frame from pipeline
-            debug!(
-                "Getting video frame from pipeline for session: {:?}",
-                session
-            );
-            // For now, return a placeholder error
-            return Err(MediaError::NotImplemented(
-                "get_video_frame not yet implemented".to_string(),
-            ));
+            pipeline.set_sync_threshold(threshold);
         }
-
-        Err(MediaError::InvalidState(
-            "No pipeline for session".to_string(),
-        ))
+        debug!(
+            "Set sync threshold to {:?} for session: {:?}",
+            threshold, session
+        );
+        Ok(())
     }
 
-    async fn get_audio_samples(
+    /// Attach a content decryption module to a session
+    ///
+    /// Encrypted packets of the session's media are decrypted with it
+    /// before they are decoded, also for sources the session loads later.
+    /// Decoding waits at a packet whose key the CDM cannot use yet, emitting
+    /// `WaitingForKey`, and continues once the key is usable, e.g. after
+    /// `ContentDecryptionModule::update` installed the license.
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `cdm` - CDM holding the keys of the session's media
+    ///
+    /// # Returns
+    /// * `Ok(())` - CDM attached
+    /// * `Err(MediaError)` - Unknown session
+    pub fn attach_cdm(
         &self,
         session: SessionId,
-        count: usize,
-    ) -> Result<AudioBuffer, MediaError> {
-        debug!("Get {} audio samples for session: {:?}", count, session);
-
-        let sessions = self.sessions.read();
+        cdm: Arc<ContentDecryptionModule>,
+    ) -> Result<(), MediaError> {
+        let mut sessions = self.inner.sessions.write();
         let context = sessions
-            .get(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
 
-        // Get samples from pipeline
         if let Some(pipeline) = &context.pipeline {
-            // TODO: Get samples from pipeline
-            debug!(
-                "Getting {} audio samples from pipeline for session: {:?}",
-                count, session
-            );
-            // For now, return a placeholder error
-            return Err(MediaError::NotImplemented(
-                "get_audio_samples not yet implemented".to_string(),
-            ));
+            pipeline.set_decryptor(cdm_decryptor(Arc::clone(&cdm)));
         }
-
-        Err(MediaError::InvalidState(
-            "No pipeline for session".to_string(),
-        ))
+        context.cdm = Some(cdm);
+        debug!("Attached CDM to session: {:?}", session);
+        Ok(())
     }
 
-    async fn destroy_session(&self, session: SessionId) -> Result<(), MediaError> {
+    /// Release a session's pipeline, keeping the session to resume later
+    ///
+    /// The pipeline is stopped and dropped, freeing its decoders and
+    /// buffers, and a session that had one is paused at its position. The
+    /// session itself stays, so it can still be configured or destroyed.
+    /// Like a discarded browser tab, it comes back with `resume_session`
+    /// and the returned snapshot, which can be persisted with serde to
+    /// resume in a later engine.
+    ///
+    /// # Arguments
+    /// * `session` - Session to suspend
+    ///
+    /// # Returns
+    /// * `Ok(SessionSnapshot)` - The session's source, position, volume,
+    ///   rate, muting and metadata
+    /// * `Err(MediaError)` - Unknown session
+    pub async fn suspend_session(&self, session: SessionId) -> Result<SessionSnapshot, MediaError> {
+        info!("Suspending session: {:?}", session);
+
+        let (pipeline, snapshot) = {
+            let mut sessions = self.inner.sessions.write();
+            let context = sessions
+                .get_mut(&session)
+                .ok_or(MediaError::SessionNotFound(session))?;
+
+            if let Some(task) = context.audio_task.take() {
+                task.abort();
+            }
+            if let Some(task) = context.capture_task.take() {
+                task.abort();
+            }
+            context.pending_audio = None;
+            context.pending_video = None;
+            let pipeline = context.pipeline.take();
+            if let Some(pipeline) = &pipeline {
+                let state = SessionState::Paused {
+                    position: pipeline.current_position(),
+                };
+                if let Err(e) = context.session.transition_to(state) {
+                    warn!("Suspended session {:?} without pausing it: {}", session, e);
+                }
+            }
+            (pipeline, context.session.snapshot())
+        };
+
+        if let Some(pipeline) = pipeline.filter(|pipeline| pipeline.is_running()) {
+            if let Err(e) = pipeline.stop().await {
+                error!("Failed to stop pipeline of session {:?}: {}", session, e);
+            }
+        }
+
+        info!("Suspended session {:?} at {:?}", session, snapshot.position);
+        Ok(snapshot)
+    }
+
+    /// Bring a session back from its snapshot
+    ///
+    /// A session still suspended is resumed itself; otherwise, such as after
+    /// a restart, a new session is restored from the snapshot. The session
+    /// takes the snapshot's volume, rate and muting, and loads its source,
+    /// seeking to its position before reporting `Ready`. Playback does not
+    /// start until `play`.
+    ///
+    /// # Arguments
+    /// * `snapshot` - Snapshot from `suspend_session`
+    ///
+    /// # Returns
+    /// * `Ok(SessionId)` - The resumed session
+    /// * `Err(MediaError)` - Session limit reached, or the source failed to
+    ///   load
+    pub async fn resume_session(
+        &self,
+        snapshot: &SessionSnapshot,
+    ) -> Result<SessionId, MediaError> {
+        let suspended = self
+            .inner
+            .sessions
+            .read()
+            .get(&snapshot.session_id)
+            .filter(|context| context.pipeline.is_none())
+            .map(|context| Arc::clone(&context.session));
+        let session = match suspended {
+            Some(suspended) => {
+                suspended.set_resume_position(Some(snapshot.position).filter(|p| !p.is_zero()));
+                snapshot.session_id
+            }
+            None => {
+                self.check_session_limit()?;
+                let session = self.inner.session_manager.restore(snapshot)?;
+                self.add_session(session, MediaSessionConfig::default())?;
+                session
+            }
+        };
+        info!("Resuming session {:?} at {:?}", session, snapshot.position);
+
+        self.set_rate(session, snapshot.rate).await?;
+        self.set_volume(session, snapshot.volume).await?;
+        self.set_muted(session, snapshot.muted)?;
+        if let Some(source) = &snapshot.source {
+            self.load_source(session, source.to_source()).await?;
+        }
+        Ok(session)
+    }
+
+    /// Fail unless the engine can take another session
+    fn check_session_limit(&self) -> Result<(), MediaError> {
+        if self.inner.shutting_down.load(Ordering::SeqCst) {
+            return Err(MediaError::InvalidState(
+                "Media engine is shutting down".to_string(),
+            ));
+        }
+
+        let sessions = self.inner.sessions.read();
+        if sessions.len() >= self.inner.config.max_sessions {
+            return Err(MediaError::ResourceExhausted(format!(
+                "Maximum sessions ({}) reached",
+                self.inner.config.max_sessions
+            )));
+        }
+        Ok(())
+    }
+
+    /// Start tracking a session the session manager created
+    ///
+    /// The session has no pipeline until a source is loaded. Its audio
+    /// output starts at the session's volume and muting.
+    fn add_session(
+        &self,
+        session_id: SessionId,
+        config: MediaSessionConfig,
+    ) -> Result<(), MediaError> {
+        let session = self
+            .inner
+            .session_manager
+            .get(session_id)
+            .ok_or(MediaError::SessionNotFound(session_id))?;
+        let decoder_policy = config
+            .decoder_policy
+            .unwrap_or(self.inner.config.decoder_selection_policy);
+        let audio_sink = (self.inner.audio_sink_factory.read())();
+        audio_sink.set_volume(output_volume(&session));
+
+        let context = SessionContext {
+            session,
+            pipeline: None,
+            playback_rate: 1.0,
+            loop_mode: LoopMode::None,
+            audio_sink,
+            audio_task: None,
+            capture_task: None,
+            pending_audio: None,
+            pending_video: None,
+            decoder_policy,
+            sync_threshold: self.inner.config.pipeline_config.sync_threshold,
+            cdm: None,
+            config,
+        };
+        self.watch_state(session_id, &context.session);
+        self.inner.sessions.write().insert(session_id, context);
+        Ok(())
+    }
+
+    /// Mute or unmute a session's audio
+    ///
+    /// The session keeps its volume, which applies again once unmuted.
+    fn set_muted(&self, session: SessionId, muted: bool) -> Result<(), MediaError> {
+        debug!("Setting muted to {} for session: {:?}", muted, session);
+
+        let sessions = self.inner.sessions.read();
+        let context = sessions
+            .get(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        context.session.set_muted(muted);
+        context
+            .audio_sink
+            .set_volume(output_volume(&context.session));
+        Ok(())
+    }
+
+    /// Record media information its pipeline parsed only once playing
+    ///
+    /// Like [`set_ready`](Self::set_ready), without changing the session's
+    /// state. The pipeline knows the duration already.
+    fn set_media_info(&self, session: SessionId, context: &SessionContext, info: &MediaInfo) {
+        context.session.set_metadata(media_metadata(info));
+        if !info.duration.is_zero() {
+            self.emit_event(MediaEngineEvent::DurationChanged {
+                session_id: session,
+                duration: info.duration,
+            });
+        }
+    }
+
+    /// Mark a session ready with the media information parsed by its pipeline
+    ///
+    /// A known duration is passed to the pipeline so that it reports the end
+    /// of the media, and announced with a `DurationChanged` event.
+    fn set_ready(&self, session: SessionId, context: &SessionContext, info: &MediaInfo) {
+        if let Some(pipeline) = context
+            .pipeline
+            .as_ref()
+            .filter(|_| !info.duration.is_zero())
+        {
+            pipeline.set_media_duration(info.duration);
+        }
+        let metadata = media_metadata(info);
+        let state = SessionState::Ready {
+            duration: info.duration,
+            metadata: metadata.clone(),
+        };
+        context.session.set_metadata(metadata);
+        if let Err(e) = context.session.transition_to(state) {
+            error!("Failed to mark session {:?} ready: {}", session, e);
+        }
+        if !info.duration.is_zero() {
+            self.emit_event(MediaEngineEvent::DurationChanged {
+                session_id: session,
+                duration: info.duration,
+            });
+        }
+    }
+
+    /// Create a pipeline for a session's source
+    ///
+    /// The pipeline is clocked by the session's audio output, unless audio is
+    /// disabled, syncs video to it within `sync_threshold`, creates video
+    /// decoders as `decoder_policy` prefers and decrypts packets with the
+    /// session's CDM. Which tracks play, the preload strategy and how far
+    /// ahead media is buffered come from the session's configuration.
+    /// Buffers are parsed up front, returning their media information,
+    /// unless preloading nothing; other sources are read as they play.
+    ///
+    /// With `HardwareOnly`, fails with `HardwareError` unless hardware is
+    /// available and, once the media information is known, supports the
+    /// video codec.
+    async fn open_pipeline(
+        &self,
+        source: MediaSource,
+        settings: PipelineSettings,
+    ) -> Result<(MediaPipeline, Option<MediaInfo>), MediaError> {
+        let PipelineSettings {
+            playback_rate,
+            loop_mode,
+            audio_sink,
+            sync_threshold,
+            decoder_policy,
+            cdm,
+            config,
+        } = settings;
+        let hardware_only = decoder_policy == DecoderSelectionPolicy::HardwareOnly;
+        if hardware_only {
+            self.inner.decoders.check_hardware(None)?;
+        }
+
+        let pipeline = MediaPipeline::new(PipelineConfig {
+            enable_video: config.enable_video,
+            enable_audio: config.enable_audio,
+            preload: config.preload,
+            max_buffer_ahead: config.max_buffer_ahead,
+            ..self.inner.config.pipeline_config.clone()
+        })?;
+        pipeline.set_playback_rate(playback_rate)?;
+        pipeline.set_loop_mode(loop_mode)?;
+        if config.enable_audio {
+            pipeline.set_audio_clock(Some(audio_sink));
+        }
+        pipeline.set_sync_threshold(sync_threshold);
+        let decoders = Arc::clone(&self.inner.decoders);
+        pipeline.set_decoder_factory(move |codec| decoders.create_decoder(decoder_policy, codec));
+        if let Some(cdm) = cdm {
+            pipeline.set_decryptor(cdm_decryptor(cdm));
+        }
+
+        let data = match &source {
+            MediaSource::Buffer { data, .. } => Some(data.clone()),
+            _ => None,
+        };
+        pipeline.load_source(source).await?;
+        let media_info = data
+            .map(|data| pipeline.set_reader(Box::new(Cursor::new(data))))
+            .transpose()?
+            .flatten();
+        if let Some(track) = media_info
+            .as_ref()
+            .and_then(|info| info.video_tracks.first())
+            .filter(|_| hardware_only)
+        {
+            self.inner.decoders.check_hardware(Some(&track.codec))?;
+        }
+        Ok((pipeline, media_info))
+    }
+
+    /// Put a session into the `Error` state after an operation on it failed
+    ///
+    /// Emits the error as a `MediaError` event and returns it, so the
+    /// operation can still return it to its caller.
+    fn fail_session(&self, session: SessionId, error: MediaError) -> MediaError {
+        error!("Session {:?} failed: {}", session, error);
+        let state = SessionState::Error {
+            error: error.clone(),
+        };
+        if let Err(e) = self.inner.session_manager.transition_state(session, state) {
+            error!("Failed to set error state of session {:?}: {}", session, e);
+        }
+        self.publish_session_event(session, SessionEvent::Error(error.clone()));
+        self.emit_event(MediaEngineEvent::MediaError {
+            session_id: session,
+            error: error.clone(),
+        });
+        error
+    }
+
+    /// Report a failed operation on a session as a `MediaError` event,
+    /// unless [`fail_session`](Self::fail_session) already did
+    fn report_failure(&self, session: SessionId, error: MediaError) {
+        if !self.is_reported_failure(session, &error) {
+            self.publish_session_event(session, SessionEvent::Error(error.clone()));
+            self.emit_event(MediaEngineEvent::MediaError {
+                session_id: session,
+                error,
+            });
+        }
+    }
+
+    /// Whether a failure was already reported by [`fail_session`](Self::fail_session)
+    fn is_reported_failure(&self, session: SessionId, error: &MediaError) -> bool {
+        matches!(
+            self.inner.session_manager.get_state(session),
+            Ok(SessionState::Error { error: reported }) if reported == *error
+        )
+    }
+
+    /// Execute a playback command on a session
+    ///
+    /// # Arguments
+    /// * `session` - Target session
+    /// * `command` - Command to execute
+    ///
+    /// # Returns
+    /// * `Ok(())` - Command executed
+    /// * `Err(MediaError)` - Unknown session or invalid command parameters
+    pub async fn execute_command(
+        &self,
+        session: SessionId,
+        command: PlaybackCommand,
+    ) -> Result<(), MediaError> {
+        match command {
+            PlaybackCommand::Play => self.play(session).await,
+            PlaybackCommand::Pause => self.pause(session).await,
+            PlaybackCommand::Seek(ms) => self.seek(session, Duration::from_millis(ms)).await,
+            PlaybackCommand::SetRate(rate) => self.set_rate(session, rate).await,
+            PlaybackCommand::SetVolume(volume) => self.set_volume(session, volume).await,
+            PlaybackCommand::SetMuted(muted) => self.set_muted(session, muted),
+        }
+    }
+
+    /// Forward loops of a session's pipeline as session state changes
+    ///
+    /// Each loop moves the session through `Looping` back to `Playing`,
+    /// without ending. Media timestamps restart at the loop start, which the
+    /// `Looping` event announces; samples and frames held back from before
+    /// the loop are discarded. The task ends when the pipeline is dropped.
+    fn watch_loops(&self, session_id: SessionId, context: &SessionContext) {
+        let Some(pipeline) = &context.pipeline else {
+            return;
+        };
+        let mut loops = pipeline.subscribe_loops();
+        let pipeline = Arc::downgrade(pipeline);
+        let session = Arc::clone(&context.session);
+        let sessions = Arc::clone(&self.inner.sessions);
+
+        tokio::spawn(async move {
+            while loops.changed().await.is_ok() {
+                let iteration = *loops.borrow_and_update();
+                let Some(pipeline) = pipeline.upgrade() else {
+                    break;
+                };
+                if let Some(context) = sessions.write().get_mut(&session_id) {
+                    context.pending_audio = None;
+                    context.pending_video = None;
+                }
+                let states = [
+                    SessionState::Looping { iteration },
+                    SessionState::Playing {
+                        position: pipeline.current_position(),
+                        rate: pipeline.playback_rate(),
+                        ab_loop: session.ab_loop(),
+                    },
+                ];
+                debug!("Session {:?} looped ({})", session_id, iteration);
+                if let LoopMode::AB { .. } = pipeline.loop_mode() {
+                    session.publish_event(SessionEvent::AbLoopIteration { count: iteration });
+                }
+
+                for state in states {
+                    if let Err(e) = session.transition_to(state) {
+                        warn!("Session {:?} looped while not playing: {}", session_id, e);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Report the position of a playing session to its subscribers as
+    /// `PositionUpdate` events, at the session's position update interval
+    ///
+    /// A zero interval reports nothing. The task ends when the pipeline is
+    /// dropped.
+    fn watch_position(&self, context: &SessionContext) {
+        let Some(pipeline) = &context.pipeline else {
+            return;
+        };
+        let period = context.session.position_update_interval;
+        if period.is_zero() {
+            return;
+        }
+        let pipeline = Arc::downgrade(pipeline);
+        let session = Arc::clone(&context.session);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                let Some(pipeline) = pipeline.upgrade() else {
+                    break;
+                };
+                if matches!(session.get_state(), SessionState::Playing { .. }) {
+                    session.publish_event(SessionEvent::PositionUpdate {
+                        position: pipeline.current_position(),
+                    });
+                }
+            }
+        });
+    }
+
+    /// Forward representation switches of a session's adaptive stream as
+    /// `BitrateChanged` events
+    ///
+    /// The task ends when the pipeline is dropped.
+    fn watch_bitrate(&self, session_id: SessionId, context: &SessionContext) {
+        let Some(pipeline) = &context.pipeline else {
+            return;
+        };
+        let mut switches = pipeline.subscribe_representation();
+        let event_tx = self.inner.event_tx.clone();
+
+        tokio::spawn(async move {
+            while switches.changed().await.is_ok() {
+                let Some(representation) = switches.borrow_and_update().clone() else {
+                    continue;
+                };
+                debug!(
+                    "Session {:?} switched to representation {} ({} bps)",
+                    session_id, representation.id, representation.bandwidth
+                );
+                let event = MediaEngineEvent::BitrateChanged {
+                    session_id,
+                    new_bitrate: representation.bandwidth,
+                };
+                if event_tx.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Move a session to the `Ended` state at the end of its media
+    ///
+    /// The pipeline reports the end of the media when playback reaches it
+    /// without looping. The task ends when the pipeline is dropped.
+    fn watch_end(&self, session_id: SessionId, context: &SessionContext) {
+        let Some(pipeline) = &context.pipeline else {
+            return;
+        };
+        let mut ended = pipeline.subscribe_ended();
+        let session = Arc::clone(&context.session);
+
+        tokio::spawn(async move {
+            while ended.changed().await.is_ok() {
+                if !*ended.borrow_and_update() {
+                    continue;
+                }
+                debug!("Session {:?} reached the end of the media", session_id);
+
+                if let Err(e) = session.transition_to(SessionState::Ended) {
+                    warn!("Session {:?} ended while not playing: {}", session_id, e);
+                }
+            }
+        });
+    }
+
+    /// Forward the state changes of a session as `PlaybackStateChanged`
+    /// events
+    ///
+    /// The events are derived from the session's own subscription, so they
+    /// report exactly the states it went through, in order. The task ends
+    /// when the session is dropped.
+    fn watch_state(&self, session_id: SessionId, session: &MediaSession) {
+        let mut events = session.subscribe_events();
+        let event_tx = self.inner.event_tx.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let state = match events.recv().await {
+                    Ok(SessionEvent::StateChanged { new, .. }) => new,
+                    Ok(_) => continue,
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!(
+                            "Missed {} events of session {:?}, state changes may be lost",
+                            missed, session_id
+                        );
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let event = MediaEngineEvent::PlaybackStateChanged { session_id, state };
+                if event_tx.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Forward a session's decoding stopping at an encrypted packet whose
+    /// key is not usable as a `WaitingForKey` event
+    ///
+    /// The task ends when the pipeline is dropped.
+    fn watch_waiting_for_key(&self, session_id: SessionId, context: &SessionContext) {
+        let Some(pipeline) = &context.pipeline else {
+            return;
+        };
+        let mut waiting = pipeline.subscribe_waiting_for_key();
+        // Decoding started with the pipeline, so it may be waiting already
+        waiting.mark_changed();
+        let event_tx = self.inner.event_tx.clone();
+
+        tokio::spawn(async move {
+            while waiting.changed().await.is_ok() {
+                if !*waiting.borrow_and_update() {
+                    continue;
+                }
+                debug!("Session {:?} is waiting for a decryption key", session_id);
+                if event_tx
+                    .send(MediaEngineEvent::WaitingForKey { session_id })
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Report a session's buffered media and whether playback waits for it
+    ///
+    /// Polls the pipeline, emitting `BufferedRangesChanged` as the buffered
+    /// ranges grow. When the video of a playing session underruns,
+    /// `BufferingStarted` is emitted; `BufferingEnded` follows once the
+    /// configured readahead is buffered again or the media is fully decoded.
+    /// The task ends when the pipeline is dropped.
+    fn watch_buffering(&self, session_id: SessionId, context: &SessionContext) {
+        let Some(pipeline) = &context.pipeline else {
+            return;
+        };
+        let pipeline = Arc::downgrade(pipeline);
+        let session = Arc::clone(&context.session);
+        let readahead = self.inner.config.buffering_readahead;
+        let event_tx = self.inner.event_tx.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(BUFFERING_POLL_INTERVAL);
+            let mut ranges = Vec::new();
+            let mut buffering = false;
+            loop {
+                interval.tick().await;
+                let Some(pipeline) = pipeline.upgrade() else {
+                    break;
+                };
+
+                let mut events = Vec::new();
+                let buffered = pipeline.buffered_ranges();
+                if buffered != ranges {
+                    ranges = buffered;
+                    events.push(MediaEngineEvent::BufferedRangesChanged {
+                        session_id,
+                        ranges: ranges.clone(),
+                    });
+                }
+
+                let position = pipeline.current_position();
+                if !buffering
+                    && pipeline.is_underrun()
+                    && matches!(session.get_state(), SessionState::Playing { .. })
+                {
+                    debug!("Session {:?} is buffering at {:?}", session_id, position);
+                    buffering = true;
+                    session.publish_event(SessionEvent::BufferingStarted);
+                    events.push(MediaEngineEvent::BufferingStarted {
+                        session_id,
+                        position,
+                    });
+                } else if buffering
+                    && (pipeline.buffered_ahead() >= readahead
+                        || pipeline.is_decoded()
+                        || pipeline.is_ended())
+                {
+                    debug!("Session {:?} buffered at {:?}", session_id, position);
+                    buffering = false;
+                    session.publish_event(SessionEvent::BufferingEnded);
+                    events.push(MediaEngineEvent::BufferingEnded {
+                        session_id,
+                        position,
+                    });
+                }
+
+                for event in events {
+                    if event_tx.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Feed a session's decoded audio to its sink
+    ///
+    /// Polls the pipeline's audio queue and writes each buffer to the sink on
+    /// a blocking thread, since writes wait for room in the device. Does
+    /// nothing if the session has no pipeline or is already being fed. The
+    /// task runs until aborted or the pipeline is dropped.
+    fn feed_audio(context: &mut SessionContext) {
+        let Some(pipeline) = context
+            .pipeline
+            .as_ref()
+            .filter(|_| context.config.enable_audio)
+        else {
+            return;
+        };
+        if context
+            .audio_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+        {
+            return;
+        }
+        let pipeline = Arc::downgrade(pipeline);
+        let sink = Arc::clone(&context.audio_sink);
+
+        context.audio_task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(AUDIO_POLL_INTERVAL);
+            loop {
+                interval.tick().await;
+                let Some(pipeline) = pipeline.upgrade() else {
+                    break;
+                };
+                while let Some(buffer) = pipeline.get_next_audio_buffer().await {
+                    let sink = Arc::clone(&sink);
+                    match tokio::task::spawn_blocking(move || sink.write(buffer)).await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => error!("Failed to write audio: {}", e),
+                        Err(e) => error!("Audio write task failed: {}", e),
+                    }
+                }
+            }
+        }));
+    }
+
+    /// Spawn the task feeding a capture track's media to the pipeline
+    ///
+    /// Captured media is live, so each frame or buffer is timestamped with
+    /// the pipeline's position as it arrives. The task runs until the track
+    /// ends, the task is aborted or the pipeline is dropped.
+    fn feed_capture(context: &mut SessionContext, track: &MediaStreamTrack) {
+        let Some(pipeline) = context.pipeline.as_ref() else {
+            return;
+        };
+        let pipeline = Arc::downgrade(pipeline);
+        let mut samples = track.subscribe();
+
+        context.capture_task = Some(tokio::spawn(async move {
+            while let Some(sample) = samples.next().await {
+                let Some(pipeline) = pipeline.upgrade() else {
+                    break;
+                };
+                let position = pipeline.current_position();
+                let pushed = match sample {
+                    MediaSample::Video(mut frame) => {
+                        frame.timestamp = position;
+                        pipeline.push_video_frame(frame).await
+                    }
+                    MediaSample::Audio(mut buffer) => {
+                        buffer.timestamp = position;
+                        pipeline.push_audio_buffer(buffer).await
+                    }
+                };
+                if let Err(e) = pushed {
+                    debug!("Stopped feeding capture track: {}", e);
+                    break;
+                }
+            }
+        }));
+    }
+
+    /// Forward capture devices being plugged in or removed as events
+    ///
+    /// Emits `CaptureDeviceAdded` and `CaptureDeviceRemoved` so the browser
+    /// can update its device lists. Must be called once, from within a Tokio
+    /// runtime; watching stops when the engine is dropped.
+    pub fn watch_capture_devices(&self) {
+        let mut devices = Box::pin(self.inner.capture_devices.watch());
+        let event_tx = self.inner.event_tx.clone();
+
+        tokio::spawn(async move {
+            while let Some(event) = devices.next().await {
+                let event = match event {
+                    DeviceEvent::Added(device) => {
+                        debug!("Capture device added: {}", device.device_id);
+                        MediaEngineEvent::CaptureDeviceAdded { device }
+                    }
+                    DeviceEvent::Removed { device_id } => {
+                        debug!("Capture device removed: {}", device_id);
+                        MediaEngineEvent::CaptureDeviceRemoved { device_id }
+                    }
+                };
+                if event_tx.send(event).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Get statistics aggregated over all sessions
+    ///
+    /// Sums the decoding counters of each session's pipeline and adds the
+    /// memory they queue to the buffer manager's. The queue depths, dropped
+    /// frames and decode latency come from the pipelines' metrics, which
+    /// start over on a seek.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_engine::{MediaEngineConfig, MediaEngineImpl};
+    ///
+    /// let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    /// let stats = engine.statistics();
+    /// assert_eq!(stats.sessions_active, 0);
+    /// assert_eq!(stats.total_frames_decoded, 0);
+    /// ```
+    pub fn statistics(&self) -> MediaEngineStats {
+        let sessions = self.inner.sessions.read();
+        let mut stats = MediaEngineStats {
+            sessions_active: sessions.len(),
+            total_memory_bytes: self.inner.buffer_manager.lock().get_memory_usage(),
+            ..Default::default()
+        };
+        let mut decode_time = Duration::ZERO;
+        let (mut frames_produced, mut latency_ms) = (0, 0.0);
+
+        for pipeline in sessions.values().filter_map(|c| c.pipeline.as_ref()) {
+            let pipeline_stats = pipeline.stats();
+            stats.total_frames_decoded += pipeline_stats.frames_decoded;
+            stats.total_audio_buffers_decoded += pipeline_stats.audio_buffers_queued;
+            stats.total_memory_bytes += pipeline_stats.queued_bytes;
+            stats.decode_errors += pipeline_stats.decode_errors;
+            stats.buffer_underruns += pipeline_stats.underruns;
+            decode_time += pipeline_stats.decode_time;
+
+            let metrics = pipeline.get_metrics();
+            stats.video_queue_depth += metrics.video_queue_depth;
+            stats.audio_queue_depth += metrics.audio_queue_depth;
+            stats.dropped_frames += metrics.dropped_frames;
+            frames_produced += metrics.total_frames_produced;
+            latency_ms +=
+                metrics.avg_video_decode_latency_ms * metrics.total_frames_produced as f64;
+        }
+        if stats.total_frames_decoded > 0 {
+            stats.avg_frame_decode_ms = millis(decode_time) / stats.total_frames_decoded as f64;
+        }
+        if frames_produced > 0 {
+            stats.avg_video_decode_latency_ms = latency_ms / frames_produced as f64;
+        }
+        stats
+    }
+
+    /// Start emitting `PeriodicStats` every `stats_interval`
+    ///
+    /// Must be called from within a Tokio runtime. Reporting stops when the
+    /// engine is dropped. Returns `None` if `stats_interval` is zero.
+    pub fn report_statistics(self: &Arc<Self>) -> Option<JoinHandle<()>> {
+        let period = self.inner.config.stats_interval;
+        if period.is_zero() {
+            return None;
+        }
+        let engine = Arc::downgrade(self);
+
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+            loop {
+                interval.tick().await;
+                let Some(engine) = engine.upgrade() else {
+                    break;
+                };
+                let stats = engine.statistics();
+                if engine
+                    .inner
+                    .event_tx
+                    .send(MediaEngineEvent::PeriodicStats(stats))
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }))
+    }
+
+    /// Shut the engine down, letting sessions play out their decoded media
+    ///
+    /// No more sessions are created from the start of the shutdown. Every
+    /// session's pipeline is [drained](MediaPipeline::drain), playing out
+    /// the frames already decoded without decoding more, for up to
+    /// `drain_timeout`. All sessions are then destroyed, whether or not
+    /// their pipelines stopped in time, and `ShutdownComplete` is emitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error destroying a session; the other sessions
+    /// are still destroyed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_engine::{MediaEngineConfig, MediaEngineImpl};
+    /// use cortenbrowser_shared_types::{MediaEngine, MediaSessionConfig};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let engine = MediaEngineImpl::new(MediaEngineConfig::default())?;
+    /// engine.create_session(MediaSessionConfig::default()).await?;
+    ///
+    /// engine.shutdown(Duration::from_secs(1)).await?;
+    /// assert!(engine.create_session(MediaSessionConfig::default()).await.is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn shutdown(&self, drain_timeout: Duration) -> Result<(), MediaError> {
+        info!("Shutting down MediaEngine");
+        self.inner.shutting_down.store(true, Ordering::SeqCst);
+
+        let drains: Vec<_> = self
+            .inner
+            .sessions
+            .read()
+            .values()
+            .filter_map(|context| context.pipeline.as_ref().map(|pipeline| pipeline.drain()))
+            .collect();
+        let drained = tokio::time::timeout(drain_timeout, async {
+            for drain in drains {
+                drain.await;
+            }
+        })
+        .await;
+        if drained.is_err() {
+            warn!(
+                "Pipelines still playing after {:?}, destroying their sessions",
+                drain_timeout
+            );
+        }
+
+        let sessions: Vec<SessionId> = self.inner.sessions.read().keys().copied().collect();
+        let mut result = Ok(());
+        for session in sessions {
+            if let Err(e) = self.destroy_session(session).await {
+                error!("Failed to destroy session {:?}: {}", session, e);
+                result = result.and(Err(e));
+            }
+        }
+
+        self.emit_event(MediaEngineEvent::ShutdownComplete);
+        info!("MediaEngine shut down");
+        result
+    }
+
+    /// Broadcast an event to the subscribers of a session, if it exists
+    fn publish_session_event(&self, session: SessionId, event: SessionEvent) {
+        if let Some(context) = self.inner.sessions.read().get(&session) {
+            context.session.publish_event(event);
+        }
+    }
+
+    /// Emit an event
+    fn emit_event(&self, event: MediaEngineEvent) {
+        if let Err(e) = self.inner.event_tx.send(event) {
+            error!("Failed to send event: {}", e);
+        }
+    }
+}
+
+/// How often a playing session's audio queue is drained into its sink
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often a session's buffered media is checked
+const BUFFERING_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How often `get_video_frame` checks for a decoded frame
+const FRAME_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Error for a message addressed to a media element that does not exist
+fn unknown_element(element_id: &str) -> MediaError {
+    MediaError::InvalidParameter(format!("Unknown media element: {}", element_id))
+}
+
+/// Whether two audio buffers can be concatenated
+fn same_audio_format(a: &AudioBuffer, b: &AudioBuffer) -> bool {
+    a.format == b.format && a.sample_rate == b.sample_rate && a.channels == b.channels
+}
+
+/// Error for data a pipeline did not produce within the frame timeout
+///
+/// A running pipeline should keep its queues filled, so waiting longer
+/// would not help; otherwise the data may still come once it runs.
+fn no_data_error(pipeline: &MediaPipeline, what: &str) -> MediaError {
+    if pipeline.is_running() {
+        MediaError::Timeout(format!("No {} decoded", what))
+    } else {
+        MediaError::WouldBlock(format!("No {} available", what))
+    }
+}
+
+/// Decryptor for a session's pipeline using its CDM
+///
+/// Packets whose key the CDM cannot use yet are left to wait for it.
+fn cdm_decryptor(
+    cdm: Arc<ContentDecryptionModule>,
+) -> impl Fn(&[u8], &EncryptionInfo) -> Result<Option<Vec<u8>>, MediaError> + Send + Sync + 'static
+{
+    move |data, info| {
+        if !cdm.is_key_usable(&info.key_id) {
+            return Ok(None);
+        }
+        let sample = SampleEncryption::from(info);
+        Ok(Some(cdm.decrypt_sample(data, &info.key_id, &sample)?))
+    }
+}
+
+/// Split the first `frames` samples per channel off an audio buffer
+///
+/// Returns the buffer whole, without a rest, if it is no longer than
+/// `frames`.
+fn split_audio_buffer(
+    mut buffer: AudioBuffer,
+    frames: usize,
+) -> (AudioBuffer, Option<AudioBuffer>) {
+    let split = frames * buffer.channels as usize;
+    if buffer.samples.len() <= split {
+        return (buffer, None);
+    }
+
+    let head_duration = Duration::from_secs_f64(frames as f64 / f64::from(buffer.sample_rate));
+    let rest = AudioBuffer {
+        samples: buffer.samples.split_off(split),
+        timestamp: buffer.timestamp + head_duration,
+        duration: buffer.duration.saturating_sub(head_duration),
+        ..buffer.clone()
+    };
+    buffer.duration = head_duration;
+    (buffer, Some(rest))
+}
+
+/// Volume a session's audio is output at, zero while muted
+fn output_volume(session: &MediaSession) -> f32 {
+    if session.is_muted() {
+        0.0
+    } else {
+        session.volume()
+    }
+}
+
+/// Session metadata from the demuxer's media information
+fn media_metadata(info: &MediaInfo) -> MediaMetadata {
+    MediaMetadata {
+        title: info.metadata.get("title").cloned(),
+        artist: info.metadata.get("artist").cloned(),
+        album: info.metadata.get("album").cloned(),
+        duration: info.duration,
+        video_track_count: info.video_tracks.len(),
+        audio_track_count: info.audio_tracks.len(),
+    }
+}
+
+/// Playback statistics of a session's pipeline
+fn playback_stats(pipeline: &MediaPipeline) -> PlaybackStats {
+    let stats = pipeline.stats();
+    PlaybackStats {
+        frames_decoded: stats.frames_decoded,
+        frames_displayed: stats.frames_displayed,
+        frames_dropped: stats.frames_dropped,
+        decode_errors: stats.decode_errors,
+        decode_time_p50_ms: millis(stats.decode_time_p50),
+        decode_time_p90_ms: millis(stats.decode_time_p90),
+        decode_time_p99_ms: millis(stats.decode_time_p99),
+        video_bitrate: stats.bitrate,
+        buffered_ahead_ms: millis(pipeline.buffered_ahead()),
+        video_queue_depth: stats.video_queue_depth,
+        audio_queue_depth: stats.audio_queue_depth,
+        bytes_buffered: stats.queued_bytes,
+        video_underruns: stats.underruns,
+        audio_underruns: stats.audio_underruns,
+    }
+}
+
+/// A duration in fractional milliseconds
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+impl MediaEngine for MediaEngineImpl {
+    async fn create_session(&self, config: MediaSessionConfig) -> Result<SessionId, MediaError> {
+        info!("Creating media session with config: {:?}", config);
+
+        self.check_session_limit()?;
+        config.validate()?;
+
+        // Create session through session manager
+        let session_id = self.inner.session_manager.create(config.clone())?;
+        self.add_session(session_id, config)?;
+
+        info!("Created session: {:?}", session_id);
+        Ok(session_id)
+    }
+
+    async fn load_source(&self, session: SessionId, source: MediaSource) -> Result<(), MediaError> {
+        info!("Loading source for session: {:?}", session);
+
+        let (settings, media_session) = {
+            let sessions = self.inner.sessions.read();
+            let context = sessions
+                .get(&session)
+                .ok_or(MediaError::SessionNotFound(session))?;
+            (context.pipeline_settings(), Arc::clone(&context.session))
+        };
+        media_session.set_source(&source);
+        let is_stream = matches!(source, MediaSource::Stream { .. });
+        let track = match &source {
+            MediaSource::Capture { track, .. } => track.clone(),
+            _ => None,
+        };
+        media_session.transition_to(SessionState::Loading {
+            source: source.clone(),
+            progress: 0.0,
+        })?;
+
+        let (pipeline, media_info) = self
+            .open_pipeline(source, settings)
+            .await
+            .map_err(|e| self.fail_session(session, e))?;
+
+        // A resumed session continues where it was suspended
+        if let Some(position) = media_session.take_resume_position() {
+            debug!("Resuming session {:?} at {:?}", session, position);
+            pipeline
+                .seek(position)
+                .await
+                .map_err(|e| self.fail_session(session, e))?;
+        }
+
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        if let Some(task) = context.audio_task.take() {
+            task.abort();
+        }
+        if let Some(task) = context.capture_task.take() {
+            task.abort();
+        }
+        context.pending_audio = None;
+        context.pending_video = None;
+        context.pipeline = Some(Arc::new(pipeline));
+        if let Some(track) = track {
+            Self::feed_capture(context, &track);
+        }
+        self.watch_loops(session, context);
+        self.watch_end(session, context);
+        self.watch_bitrate(session, context);
+        self.watch_buffering(session, context);
+        self.watch_waiting_for_key(session, context);
+        self.watch_position(context);
+
+        match media_info {
+            Some(info) => self.set_ready(session, context, &info),
+            // Streams become ready once their chunks describe the media
+            None if is_stream => {}
+            // Other sources are parsed as they play, their duration unknown
+            // until then
+            None => context.session.transition_to(SessionState::Ready {
+                duration: Duration::ZERO,
+                metadata: MediaMetadata::default(),
+            })?,
+        }
+
+        info!("Loaded source for session: {:?}", session);
+        Ok(())
+    }
+
+    async fn play(&self, session: SessionId) -> Result<(), MediaError> {
+        info!("Play requested for session: {:?}", session);
+
+        // Run the pipeline clock, from the beginning again after the end
+        let pipeline = {
+            let sessions = self.inner.sessions.read();
+            let context = sessions
+                .get(&session)
+                .ok_or(MediaError::SessionNotFound(session))?;
+            context.pipeline.clone()
+        };
+        if let Some(pipeline) = pipeline.filter(|pipeline| !pipeline.is_running()) {
+            pipeline
+                .start()
+                .await
+                .map_err(|e| self.fail_session(session, e))?;
+        }
+
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        // Media not preloaded is only parsed once playback starts
+        let info = context
+            .pipeline
+            .as_ref()
+            .filter(|_| context.session.get_metadata().is_none())
+            .and_then(|pipeline| pipeline.media_info());
+        if let Some(info) = info {
+            self.set_media_info(session, context, &info);
+        }
+
+        // Playback after the end starts again from the beginning
+        if context.session.get_state() == SessionState::Ended {
+            context.session.transition_to(SessionState::Seeking {
+                target: Duration::ZERO,
+            })?;
+        }
+
+        // Transition session state
+        context.session.transition_to(SessionState::Playing {
+            position: context.position(),
+            rate: context.playback_rate,
+            ab_loop: context.session.ab_loop(),
+        })?;
+
+        // Start feeding audio output
+        if context.pipeline.is_some() {
+            debug!("Starting audio output for session: {:?}", session);
+            Self::feed_audio(context);
+        }
+
+        Ok(())
+    }
+
+    async fn pause(&self, session: SessionId) -> Result<(), MediaError> {
+        info!("Pause requested for session: {:?}", session);
+
+        // Stop the pipeline clock
+        let pipeline = {
+            let sessions = self.inner.sessions.read();
+            let context = sessions
+                .get(&session)
+                .ok_or(MediaError::SessionNotFound(session))?;
+            context.pipeline.clone()
+        };
+        if let Some(pipeline) = pipeline.filter(|pipeline| pipeline.is_running()) {
+            pipeline.pause().await?;
+        }
+
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        // Stop feeding audio output
+        if let Some(task) = context.audio_task.take() {
+            debug!("Stopping audio output for session: {:?}", session);
+            task.abort();
+        }
+
+        // Transition session state, unless already paused
+        if matches!(context.session.get_state(), SessionState::Paused { .. }) {
+            return Ok(());
+        }
+        context.session.transition_to(SessionState::Paused {
+            position: context.position(),
+        })
+    }
+
+    async fn seek(&self, session: SessionId, position: Duration) -> Result<(), MediaError> {
+        info!(
+            "Seek to {:?} requested for session: {:?}",
+            position, session
+        );
+
+        // Transition to seeking state
+        let (pipeline, previous, position) = {
+            let sessions = self.inner.sessions.read();
+            let context = sessions
+                .get(&session)
+                .ok_or(MediaError::SessionNotFound(session))?;
+            let previous = context.session.get_state();
+            let position = match context.pipeline.as_deref().map(|p| p.seekable_range()) {
+                // Nothing is known to be seekable yet, so there is no range
+                // to clamp to
+                Some((start, end)) if start < end => {
+                    let clamped = position.clamp(start, end);
+                    if clamped != position {
+                        warn!(
+                            "Seek to {:?} outside seekable range {:?}-{:?} of session {:?}, seeking to {:?}",
+                            position, start, end, session, clamped
+                        );
+                    }
+                    clamped
+                }
+                _ => position,
+            };
+            context
+                .session
+                .transition_to(SessionState::Seeking { target: position })?;
+            (context.pipeline.clone(), previous, position)
+        };
+
+        // Seek in pipeline
+        let result = match &pipeline {
+            Some(pipeline) => {
+                debug!(
+                    "Seeking pipeline to {:?} for session: {:?}",
+                    position, session
+                );
+                pipeline.seek(position).await
+            }
+            None => Ok(()),
+        };
+
+        if let Err(e) = result {
+            return Err(self.fail_session(session, e));
+        }
+
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        context.pending_audio = None;
+        context.pending_video = None;
+
+        // Transition back to paused if paused, playing otherwise
+        let position = context.position();
+        let state = match previous {
+            SessionState::Paused { .. } => SessionState::Paused { position },
+            _ => SessionState::Playing {
+                position,
+                rate: context.playback_rate,
+                ab_loop: context.session.ab_loop(),
+            },
+        };
+        context.session.transition_to(state)
+    }
+
+    async fn set_volume(&self, session: SessionId, volume: f32) -> Result<(), MediaError> {
+        info!("Set volume to {} for session: {:?}", volume, session);
+
+        // Validate volume range
+        if !(0.0..=1.0).contains(&volume) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Volume must be between 0.0 and 1.0, got {}",
+                volume
+            )));
+        }
+
+        let sessions = self.inner.sessions.read();
+        let context = sessions
+            .get(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        context.session.set_volume(volume);
+        context
+            .audio_sink
+            .set_volume(output_volume(&context.session));
+
+        Ok(())
+    }
+
+    async fn set_rate(&self, session: SessionId, rate: f32) -> Result<(), MediaError> {
+        info!("Set rate to {} for session: {:?}", rate, session);
+
+        // Validate rate range
+        if !(MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).contains(&rate) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Playback rate must be between {} and {}, got {}",
+                MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE, rate
+            )));
+        }
+
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        context.playback_rate = rate;
+        context.session.set_rate(rate);
+
+        // Scale the pipeline clock
+        if let Some(pipeline) = &context.pipeline {
+            pipeline.set_playback_rate(rate)?;
+        }
+
+        // Update the rate of a playing session
+        if let SessionState::Playing {
+            position, ab_loop, ..
+        } = context.session.get_state()
+        {
+            context.session.transition_to(SessionState::Playing {
+                position,
+                rate,
+                ab_loop,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    async fn set_loop(&self, session: SessionId, mode: LoopMode) -> Result<(), MediaError> {
+        info!("Set loop mode to {:?} for session: {:?}", mode, session);
+
+        let mut sessions = self.inner.sessions.write();
+        let context = sessions
+            .get_mut(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        // Validate the loop section against the media and keep it with the
+        // session
+        match mode {
+            LoopMode::AB { start, end } => context.session.set_ab_loop(start, end)?,
+            _ => context.session.clear_ab_loop(),
+        }
+        context.loop_mode = mode;
+
+        // Restart at the end of the media
+        if let Some(pipeline) = &context.pipeline {
+            pipeline.set_loop_mode(mode)?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_video_frame(&self, session: SessionId) -> Result<VideoFrame, MediaError> {
+        debug!("Get video frame for session: {:?}", session);
+
+        // Start with the frame the last call found ahead of the clock
+        let (pipeline, mut pending) = {
+            let mut sessions = self.inner.sessions.write();
+            let context = sessions
+                .get_mut(&session)
+                .ok_or(MediaError::SessionNotFound(session))?;
+            if !context.config.enable_video {
+                return Err(MediaError::InvalidState(
+                    "Video is disabled for session".to_string(),
+                ));
+            }
+            let pipeline = context
+                .pipeline
+                .clone()
+                .ok_or_else(|| MediaError::InvalidState("No pipeline for session".to_string()))?;
+            (pipeline, context.pending_video.take())
+        };
+
+        // Wait for the pipeline to decode a frame that is not late
+        let next_frame = async {
+            loop {
+                let frame = match pending.take() {
+                    Some(frame) => frame,
+                    None => match pipeline.get_next_video_frame().await {
+                        Some(frame) => frame,
+                        None => {
+                            tokio::time::sleep(FRAME_POLL_INTERVAL).await;
+                            continue;
+                        }
+                    },
+                };
+                match pipeline.sync_frame(&frame) {
+                    SyncDecision::Display => return Ok(frame),
+                    SyncDecision::Drop => {
+                        debug!("Dropping late video frame at {:?}", frame.timestamp)
+                    }
+                    SyncDecision::Wait { duration } => return Err((frame, duration)),
+                }
+            }
+        };
+
+        match tokio::time::timeout(self.inner.config.frame_timeout, next_frame).await {
+            Ok(Ok(frame)) => Ok(frame),
+            Ok(Err((frame, wait_for))) => {
+                if let Some(context) = self.inner.sessions.write().get_mut(&session) {
+                    context.pending_video = Some(frame);
+                }
+                Err(MediaError::NotReady { wait_for })
+            }
+            Err(_) => Err(no_data_error(&pipeline, "video frame")),
+        }
+    }
+
+    async fn get_audio_samples(
+        &self,
+        session: SessionId,
+        count: usize,
+    ) -> Result<AudioBuffer, MediaError> {
+        debug!("Get {} audio samples for session: {:?}", count, session);
+
+        if count == 0 {
+            return Err(MediaError::InvalidParameter(
+                "Audio sample count must be positive".to_string(),
+            ));
+        }
+
+        // Start with the samples the last call left over
+        let (pipeline, mut pending) = {
+            let mut sessions = self.inner.sessions.write();
+            let context = sessions
+                .get_mut(&session)
+                .ok_or(MediaError::SessionNotFound(session))?;
+            if !context.config.enable_audio {
+                return Err(MediaError::InvalidState(
+                    "Audio is disabled for session".to_string(),
+                ));
+            }
+            let pipeline = context
+                .pipeline
+                .clone()
+                .ok_or_else(|| MediaError::InvalidState("No pipeline for session".to_string()))?;
+            (pipeline, context.pending_audio.take())
+        };
+
+        // Concatenate queued buffers of the same format until `count`
+        // samples per channel are collected, waiting for more to be decoded
+        let deadline = Instant::now() + self.inner.config.frame_timeout;
+        let mut samples: Option<AudioBuffer> = None;
+        let mut collected = 0;
+        let mut timed_out = false;
+        while collected < count {
+            let buffer = match pending.take() {
+                Some(buffer) => buffer,
+                None => match pipeline.get_next_audio_buffer().await {
+                    Some(buffer) => buffer,
+                    None if pipeline.is_ended() => break,
+                    None if Instant::now() >= deadline => {
+                        timed_out = true;
+                        break;
+                    }
+                    None => {
+                        tokio::time::sleep(FRAME_POLL_INTERVAL).await;
+                        continue;
+                    }
+                },
+            };
+            if buffer.channels == 0 || buffer.samples.is_empty() {
+                continue;
+            }
+            if samples
+                .as_ref()
+                .is_some_and(|samples| !same_audio_format(samples, &buffer))
+            {
+                pending = Some(buffer);
+                break;
+            }
+
+            let (head, rest) = split_audio_buffer(buffer, count - collected);
+            pending = rest;
+            collected += head.samples.len() / head.channels as usize;
+            match &mut samples {
+                Some(samples) => {
+                    samples.samples.extend_from_slice(&head.samples);
+                    samples.duration += head.duration;
+                }
+                None => samples = Some(head),
+            }
+        }
+
+        // Keep a short read for the next call rather than return it early
+        if timed_out {
+            pending = samples.take();
+        }
+        if let Some(context) = self.inner.sessions.write().get_mut(&session) {
+            context.pending_audio = pending;
+        }
+        samples.ok_or_else(|| no_data_error(&pipeline, "audio samples"))
+    }
+
+    async fn get_buffered_ranges(
+        &self,
+        session: SessionId,
+    ) -> Result<Vec<(Duration, Duration)>, MediaError> {
+        let sessions = self.inner.sessions.read();
+        let context = sessions
+            .get(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        Ok(context
+            .pipeline
+            .as_deref()
+            .map(MediaPipeline::buffered_ranges)
+            .unwrap_or_default())
+    }
+
+    async fn get_seekable_range(
+        &self,
+        session: SessionId,
+    ) -> Result<(Duration, Duration), MediaError> {
+        let sessions = self.inner.sessions.read();
+        let context = sessions
+            .get(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        Ok(context
+            .pipeline
+            .as_deref()
+            .map(MediaPipeline::seekable_range)
+            .unwrap_or_default())
+    }
+
+    async fn get_playback_stats(&self, session: SessionId) -> Result<PlaybackStats, MediaError> {
+        let sessions = self.inner.sessions.read();
+        let context = sessions
+            .get(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        // A session without a source has nothing to report yet
+        Ok(context
+            .pipeline
+            .as_deref()
+            .map(playback_stats)
+            .unwrap_or_default())
+    }
+
+    async fn destroy_session(&self, session: SessionId) -> Result<(), MediaError> {
         info!("Destroying session: {:?}", session);
 
-        // Remove session context
-        let context = self
-            .sessions
-            .write()
-            .remove(&session)
-            .ok_or_else(|| MediaError::SessionNotFound(session))?;
+        // Remove session context
+        let context = self
+            .inner
+            .sessions
+            .write()
+            .remove(&session)
+            .ok_or(MediaError::SessionNotFound(session))?;
+
+        // Stop audio output and capture
+        if let Some(task) = context.audio_task {
+            task.abort();
+        }
+        if let Some(task) = context.capture_task {
+            task.abort();
+        }
+
+        // Stop pipeline if exists
+        if let Some(pipeline) = context.pipeline.filter(|pipeline| pipeline.is_running()) {
+            debug!("Stopping pipeline for session: {:?}", session);
+            if let Err(e) = pipeline.stop().await {
+                error!("Failed to stop pipeline of session {:?}: {}", session, e);
+            }
+        }
+
+        // Destroy session through manager
+        self.inner.session_manager.destroy(session)?;
+        self.inner
+            .elements
+            .write()
+            .retain(|_, &mut element_session| element_session != session);
+
+        info!("Destroyed session: {:?}", session);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryAudioSink;
+    use cortenbrowser_media_pipeline::AbrConfig;
+    use cortenbrowser_shared_types::{AudioFormat, PixelFormat};
+
+    #[tokio::test]
+    async fn test_create_engine() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config);
+        assert!(engine.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_create_session() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session_config = MediaSessionConfig::default();
+        let result = engine.create_session(session_config).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_session_limit() {
+        let config = MediaEngineConfig {
+            max_sessions: 1,
+            ..Default::default()
+        };
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        // Create first session - should succeed
+        let session1 = engine.create_session(MediaSessionConfig::default()).await;
+        assert!(session1.is_ok());
+
+        // Create second session - should fail due to limit
+        let session2 = engine.create_session(MediaSessionConfig::default()).await;
+        assert!(session2.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_data_unknown_session() {
+        let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+
+        let message = MediaEngineMessage::StreamData {
+            session_id: SessionId::new(),
+            chunk: MediaChunk {
+                data: vec![0; 16],
+                sequence: 0,
+                is_final: true,
+            },
+        };
+        let result = engine.handle_message(message).await;
+        assert!(matches!(result, Err(MediaError::SessionNotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_play_pause() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        // Without a source there is nothing to play
+        assert!(matches!(
+            engine.play(session).await,
+            Err(MediaError::InvalidStateTransition { .. })
+        ));
+        assert_eq!(
+            engine.inner.session_manager.get_state(session).unwrap(),
+            SessionState::Idle
+        );
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Play should succeed
+        let result = engine.play(session).await;
+        assert!(result.is_ok());
+
+        // Pause should succeed
+        let result = engine.pause(session).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_seek() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Seek should succeed
+        let result = engine.seek(session, Duration::from_secs(10)).await;
+        assert!(result.is_ok());
+    }
+
+    /// Let the engine's tasks forward the events of what happened so far
+    ///
+    /// `PlaybackStateChanged` events are forwarded from each session's
+    /// subscription by a task of its own.
+    async fn settle() {
+        tokio::task::yield_now().await;
+    }
+
+    /// Positions of the `PlaybackStateChanged` events sent so far
+    fn state_positions(
+        events: &mut mpsc::UnboundedReceiver<MediaEngineEvent>,
+    ) -> Vec<(&'static str, Duration)> {
+        let mut states = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let MediaEngineEvent::PlaybackStateChanged { state, .. } = event {
+                let position = match state {
+                    SessionState::Playing { position, .. } | SessionState::Paused { position } => {
+                        position
+                    }
+                    _ => continue,
+                };
+                states.push((state.state_name(), position));
+            }
+        }
+        states
+    }
+
+    fn assert_near(position: Duration, expected: Duration) {
+        let error = position.abs_diff(expected);
+        assert!(
+            error < Duration::from_millis(100),
+            "Position {:?} is not near {:?}",
+            position,
+            expected
+        );
+    }
+
+    #[tokio::test]
+    async fn test_seek_reports_pipeline_position() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Seeking while paused stays paused at the new position
+        engine.pause(session).await.unwrap();
+        engine.seek(session, Duration::from_secs(10)).await.unwrap();
+        engine.play(session).await.unwrap();
+
+        settle().await;
+        let states = state_positions(&mut events);
+        let names: Vec<_> = states.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["Paused", "Paused", "Playing"]);
+        assert_eq!(states[0].1, Duration::ZERO);
+        assert_near(states[1].1, Duration::from_secs(10));
+        assert_near(states[2].1, Duration::from_secs(10));
+
+        // Seeking while playing keeps playing
+        engine.seek(session, Duration::from_secs(20)).await.unwrap();
+        engine.pause(session).await.unwrap();
+
+        settle().await;
+        let states = state_positions(&mut events);
+        assert_eq!(states[0].0, "Playing");
+        assert_near(states[0].1, Duration::from_secs(20));
+        assert_eq!(states[1].0, "Paused");
+        assert_near(states[1].1, Duration::from_secs(20));
+
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        assert_near(pipeline.current_position(), Duration::from_secs(20));
+    }
+
+    #[tokio::test]
+    async fn test_seek_before_load_is_rejected() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            engine.seek(session, Duration::from_secs(10)).await,
+            Err(MediaError::InvalidStateTransition { .. })
+        ));
+        assert!(engine.pause(session).await.is_err());
+
+        // The session stays idle, without reporting a state change
+        settle().await;
+        assert_eq!(
+            engine.inner.session_manager.get_state(session).unwrap(),
+            SessionState::Idle
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_volume_valid() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        // Valid volumes should succeed
+        assert!(engine.set_volume(session, 0.0).await.is_ok());
+        assert!(engine.set_volume(session, 0.5).await.is_ok());
+        assert!(engine.set_volume(session, 1.0).await.is_ok());
+    }
+
+    /// Engine whose sessions play into one shared memory sink
+    async fn engine_with_memory_sink() -> (MediaEngineImpl, SessionId, Arc<MemoryAudioSink>) {
+        let sink = Arc::new(MemoryAudioSink::new());
+        let factory_sink = Arc::clone(&sink);
+        let engine = MediaEngineImpl::new(MediaEngineConfig::default())
+            .unwrap()
+            .with_audio_sink_factory(move || factory_sink.clone());
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.ogg".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        (engine, session, sink)
+    }
+
+    fn audio_buffer(value: f32) -> AudioBuffer {
+        AudioBuffer::new(
+            AudioFormat::F32LE,
+            48000,
+            2,
+            vec![value; 960],
+            Duration::ZERO,
+        )
+    }
+
+    /// Wait for the feeding task to write `count` buffers
+    async fn wait_for_buffers(sink: &MemoryAudioSink, count: usize) {
+        for _ in 0..100 {
+            if sink.buffers().len() >= count {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!(
+            "Sink received {} buffers, expected {}",
+            sink.buffers().len(),
+            count
+        );
+    }
+
+    #[tokio::test]
+    async fn test_play_feeds_audio_to_sink_with_volume() {
+        let (engine, session, sink) = engine_with_memory_sink().await;
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+
+        engine.set_volume(session, 0.5).await.unwrap();
+        engine.play(session).await.unwrap();
+        for _ in 0..3 {
+            pipeline.push_audio_buffer(audio_buffer(1.0)).await.unwrap();
+        }
+        wait_for_buffers(&sink, 3).await;
+
+        assert_eq!(sink.volume(), 0.5);
+        assert!(sink
+            .buffers()
+            .iter()
+            .all(|buffer| buffer.samples.iter().all(|&s| s == 0.5)));
+        assert_eq!(sink.played_duration(), Duration::from_millis(30));
+    }
+
+    #[tokio::test]
+    async fn test_muting_keeps_volume() {
+        let (engine, session, sink) = engine_with_memory_sink().await;
+        engine.set_volume(session, 0.5).await.unwrap();
+
+        engine
+            .execute_command(session, PlaybackCommand::SetMuted(true))
+            .await
+            .unwrap();
+        assert_eq!(sink.volume(), 0.0);
+
+        // Volume changes while muted apply once unmuted
+        engine.set_volume(session, 0.8).await.unwrap();
+        assert_eq!(sink.volume(), 0.0);
+        engine
+            .execute_command(session, PlaybackCommand::SetMuted(false))
+            .await
+            .unwrap();
+        assert_eq!(sink.volume(), 0.8);
+    }
+
+    #[tokio::test]
+    async fn test_pause_stops_feeding_audio() {
+        let (engine, session, sink) = engine_with_memory_sink().await;
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+
+        engine.play(session).await.unwrap();
+        pipeline
+            .push_audio_buffer(audio_buffer(0.25))
+            .await
+            .unwrap();
+        wait_for_buffers(&sink, 1).await;
+        assert_eq!(sink.played_duration(), Duration::from_millis(10));
+
+        // Audio queued while paused waits for playback to resume
+        engine.pause(session).await.unwrap();
+        pipeline
+            .push_audio_buffer(audio_buffer(0.25))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(sink.buffers().len(), 1);
+
+        engine.play(session).await.unwrap();
+        wait_for_buffers(&sink, 2).await;
+        assert_eq!(sink.played_duration(), Duration::from_millis(20));
+        assert!(sink.buffers()[1].samples.iter().all(|&s| s == 0.25));
+    }
+
+    /// Stereo buffer of `frames` samples per channel of one value
+    fn timed_audio_buffer(
+        value: f32,
+        frames: usize,
+        sample_rate: u32,
+        start_ms: u64,
+    ) -> AudioBuffer {
+        AudioBuffer::new(
+            AudioFormat::F32LE,
+            sample_rate,
+            2,
+            vec![value; frames * 2],
+            Duration::from_millis(start_ms),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_get_audio_samples_concatenates_and_splits_buffers() {
+        let (engine, session, _sink) = engine_with_memory_sink().await;
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        for (i, value) in [1.0, 2.0, 3.0].into_iter().enumerate() {
+            let buffer = timed_audio_buffer(value, 480, 48000, 10 * i as u64);
+            pipeline.push_audio_buffer(buffer).await.unwrap();
+        }
+
+        // All of the first buffer and half of the second
+        let samples = engine.get_audio_samples(session, 720).await.unwrap();
+        assert_eq!(samples.samples.len(), 720 * 2);
+        assert!(samples.samples[..960].iter().all(|&s| s == 1.0));
+        assert!(samples.samples[960..].iter().all(|&s| s == 2.0));
+        assert_eq!(samples.timestamp, Duration::ZERO);
+        assert_eq!(samples.duration, Duration::from_millis(15));
+
+        // The rest of the second buffer and all of the third
+        let samples = engine.get_audio_samples(session, 720).await.unwrap();
+        assert_eq!(samples.samples.len(), 720 * 2);
+        assert!(samples.samples[..480].iter().all(|&s| s == 2.0));
+        assert!(samples.samples[480..].iter().all(|&s| s == 3.0));
+        assert_eq!(samples.timestamp, Duration::from_millis(15));
+        assert_eq!(samples.duration, Duration::from_millis(15));
+
+        assert!(matches!(
+            engine.get_audio_samples(session, 720).await,
+            Err(MediaError::WouldBlock(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_audio_samples_stops_at_format_change() {
+        let (engine, session, _sink) = engine_with_memory_sink().await;
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline
+            .push_audio_buffer(timed_audio_buffer(0.5, 480, 48000, 0))
+            .await
+            .unwrap();
+        pipeline
+            .push_audio_buffer(timed_audio_buffer(0.5, 441, 44100, 10))
+            .await
+            .unwrap();
+
+        // Buffers of another sample rate are not concatenated
+        let samples = engine.get_audio_samples(session, 4800).await.unwrap();
+        assert_eq!(samples.sample_rate, 48000);
+        assert_eq!(samples.samples.len(), 480 * 2);
+
+        let samples = engine.get_audio_samples(session, 441).await.unwrap();
+        assert_eq!(samples.sample_rate, 44100);
+        assert_eq!(samples.samples.len(), 441 * 2);
+        assert_eq!(samples.timestamp, Duration::from_millis(10));
+
+        assert!(matches!(
+            engine.get_audio_samples(session, 0).await,
+            Err(MediaError::InvalidParameter(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_audio_samples_waits_for_exact_count() {
+        let (engine, session, _sink) = engine_with_memory_sink().await;
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline
+            .push_audio_buffer(timed_audio_buffer(1.0, 480, 48000, 0))
+            .await
+            .unwrap();
+
+        // A short read is kept until the rest is decoded
+        assert!(matches!(
+            engine.get_audio_samples(session, 720).await,
+            Err(MediaError::WouldBlock(_))
+        ));
+        pipeline
+            .push_audio_buffer(timed_audio_buffer(2.0, 480, 48000, 10))
+            .await
+            .unwrap();
+
+        let samples = engine.get_audio_samples(session, 720).await.unwrap();
+        assert_eq!(samples.samples.len(), 720 * 2);
+        assert!(samples.samples[..960].iter().all(|&s| s == 1.0));
+        assert!(samples.samples[960..].iter().all(|&s| s == 2.0));
+        assert_eq!(samples.timestamp, Duration::ZERO);
+    }
+
+    /// Engine with a session on a URL source, whose pipeline is empty
+    async fn engine_with_empty_pipeline() -> (MediaEngineImpl, SessionId, Arc<MediaPipeline>) {
+        let config = MediaEngineConfig {
+            frame_timeout: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        let source = MediaSource::Url {
+            url: "test.mp4".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        (engine, session, pipeline)
+    }
+
+    fn video_frame(timestamp_ms: u64) -> VideoFrame {
+        VideoFrame::new(
+            16,
+            16,
+            PixelFormat::YUV420,
+            vec![0; 384],
+            Duration::from_millis(timestamp_ms),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_seek_clamped_to_seekable_range() {
+        let (engine, session, pipeline) = engine_with_empty_pipeline().await;
+        assert!(engine
+            .get_buffered_ranges(session)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            engine.get_seekable_range(session).await.unwrap(),
+            (Duration::ZERO, Duration::ZERO)
+        );
+
+        for timestamp in (0..1000).step_by(40) {
+            let mut frame = video_frame(timestamp);
+            frame.duration = Some(Duration::from_millis(40));
+            pipeline.push_video_frame(frame).await.unwrap();
+        }
+        let buffered = (Duration::ZERO, Duration::from_secs(1));
+        assert_eq!(
+            engine.get_buffered_ranges(session).await.unwrap(),
+            [buffered]
+        );
+        assert_eq!(engine.get_seekable_range(session).await.unwrap(), buffered);
+
+        // Seeking past the seekable range lands at its end
+        engine.pause(session).await.unwrap();
+        engine.seek(session, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(pipeline.current_position(), Duration::from_secs(1));
+
+        // Once the duration is known, all of it is seekable
+        pipeline.set_media_duration(Duration::from_secs(10));
+        engine.seek(session, Duration::from_secs(5)).await.unwrap();
+        assert_eq!(pipeline.current_position(), Duration::from_secs(5));
+
+        let unknown = SessionId::new();
+        assert!(matches!(
+            engine.get_seekable_range(unknown).await,
+            Err(MediaError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_video_frame_syncs_to_clock() {
+        let (engine, session, pipeline) = engine_with_empty_pipeline().await;
+        for timestamp in [0, 200] {
+            pipeline
+                .push_video_frame(video_frame(timestamp))
+                .await
+                .unwrap();
+        }
+
+        let frame = engine.get_video_frame(session).await.unwrap();
+        assert_eq!(frame.timestamp, Duration::ZERO);
+
+        // A frame ahead of the clock is held back until it is due
+        for _ in 0..2 {
+            assert_eq!(
+                engine.get_video_frame(session).await,
+                Err(MediaError::NotReady {
+                    wait_for: Duration::from_millis(200)
+                })
+            );
+        }
+        pipeline.seek(Duration::from_millis(200)).await.unwrap();
+        let frame = engine.get_video_frame(session).await.unwrap();
+        assert_eq!(frame.timestamp, Duration::from_millis(200));
+
+        // Late frames are skipped
+        for timestamp in [50, 210] {
+            pipeline
+                .push_video_frame(video_frame(timestamp))
+                .await
+                .unwrap();
+        }
+        let frame = engine.get_video_frame(session).await.unwrap();
+        assert_eq!(frame.timestamp, Duration::from_millis(210));
+    }
+
+    #[tokio::test]
+    async fn test_set_sync_threshold_holds_back_early_frames() {
+        let (engine, session, pipeline) = engine_with_empty_pipeline().await;
+        pipeline.push_video_frame(video_frame(30)).await.unwrap();
+        let frame = engine.get_video_frame(session).await.unwrap();
+        assert_eq!(frame.timestamp, Duration::from_millis(30));
+
+        // Displaying the frame moved the clock to it, so the next one is
+        // 30ms ahead, beyond a 20ms threshold
+        engine
+            .set_sync_threshold(session, Duration::from_millis(20))
+            .unwrap();
+        assert_eq!(pipeline.sync_threshold(), Duration::from_millis(20));
+        pipeline.push_video_frame(video_frame(60)).await.unwrap();
+        assert_eq!(
+            engine.get_video_frame(session).await,
+            Err(MediaError::NotReady {
+                wait_for: Duration::from_millis(30)
+            })
+        );
+
+        let unknown = SessionId::new();
+        assert!(matches!(
+            engine.set_sync_threshold(unknown, Duration::from_millis(20)),
+            Err(MediaError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_session_events_during_play_pause_seek() {
+        tokio::time::pause();
+        let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+        let config =
+            MediaSessionConfig::new().with_position_update_interval(Duration::from_millis(100));
+        let session = engine.create_session(config).await.unwrap();
+        let mut events = engine.subscribe_session_events(session).unwrap();
+        let source = MediaSource::Url {
+            url: "test.mp4".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        for timestamp in (0..1000).step_by(40) {
+            pipeline
+                .push_video_frame(video_frame(timestamp))
+                .await
+                .unwrap();
+        }
+
+        engine.play(session).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        engine.pause(session).await.unwrap();
+        engine.seek(session, Duration::from_secs(2)).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+
+        let mut states = Vec::new();
+        let mut positions = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                SessionEvent::StateChanged { new, .. } => states.push(new),
+                // Positions are only reported while playing
+                SessionEvent::PositionUpdate { position } => {
+                    assert!(matches!(states.last(), Some(SessionState::Playing { .. })));
+                    positions.push(position);
+                }
+                event => panic!("Unexpected event {:?}", event),
+            }
+        }
+        let names: Vec<_> = states.iter().map(SessionState::state_name).collect();
+        assert_eq!(
+            names,
+            ["Loading", "Ready", "Playing", "Paused", "Seeking", "Paused"]
+        );
+        assert_eq!(
+            states.last(),
+            Some(&SessionState::Paused {
+                position: Duration::from_secs(2)
+            })
+        );
+        assert_eq!(positions.len(), 2);
+        assert!(positions[0] < positions[1]);
+
+        assert!(matches!(
+            engine.subscribe_session_events(SessionId::new()),
+            Err(MediaError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_abr_switch_emits_bitrate_changed() {
+        tokio::time::pause();
+        let config = MediaEngineConfig {
+            pipeline_config: PipelineConfig {
+                abr: AbrConfig {
+                    up_threshold: Duration::from_millis(500),
+                    down_threshold: Duration::from_millis(200),
+                    switch_interval: Duration::from_millis(100),
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        let source = MediaSource::Url {
+            url: "https://example.com/manifest.mpd".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        let representations = ["low", "high"]
+            .into_iter()
+            .zip([500_000, 2_000_000])
+            .map(|(id, bandwidth)| Representation {
+                id: id.to_string(),
+                bandwidth,
+                url: format!("https://example.com/{}/", id),
+            })
+            .collect();
+        engine
+            .set_representations(session, representations)
+            .unwrap();
+
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        for timestamp in (0..1000).step_by(40) {
+            pipeline
+                .push_video_frame(video_frame(timestamp))
+                .await
+                .unwrap();
+        }
+        engine.play(session).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let mut bitrates = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let MediaEngineEvent::BitrateChanged {
+                session_id,
+                new_bitrate,
+            } = event
+            {
+                assert_eq!(session_id, session);
+                bitrates.push(new_bitrate);
+            }
+        }
+        assert_eq!(bitrates, [500_000, 2_000_000]);
+
+        let unknown = SessionId::new();
+        assert!(matches!(
+            engine.set_representations(unknown, Vec::new()),
+            Err(MediaError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_running_pipeline_without_data_times_out() {
+        let (engine, session, pipeline) = engine_with_empty_pipeline().await;
+        pipeline.start().await.unwrap();
+
+        assert!(matches!(
+            engine.get_video_frame(session).await,
+            Err(MediaError::Timeout(_))
+        ));
+        assert!(matches!(
+            engine.get_audio_samples(session, 480).await,
+            Err(MediaError::Timeout(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_video_frame_without_frames_would_block() {
+        let config = MediaEngineConfig {
+            frame_timeout: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            engine.get_video_frame(session).await,
+            Err(MediaError::InvalidState(_))
+        ));
+
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            engine.get_video_frame(session).await,
+            Err(MediaError::WouldBlock(_))
+        ));
+        assert!(matches!(
+            engine.get_audio_samples(session, 480).await,
+            Err(MediaError::WouldBlock(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_volume_invalid() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        // Invalid volumes should fail
+        assert!(engine.set_volume(session, -0.1).await.is_err());
+        assert!(engine.set_volume(session, 1.1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_rate_valid() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        // Boundary and typical rates should succeed
+        assert!(engine.set_rate(session, 0.25).await.is_ok());
+        assert!(engine.set_rate(session, 2.0).await.is_ok());
+        assert!(engine.set_rate(session, 4.0).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_set_rate_invalid() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        // Out-of-range rates should fail
+        assert!(engine.set_rate(session, 0.0).await.is_err());
+        assert!(engine.set_rate(session, 0.2).await.is_err());
+        assert!(engine.set_rate(session, 4.5).await.is_err());
+        assert!(engine.set_rate(session, f32::NAN).await.is_err());
+        assert!(matches!(
+            engine.set_playback_rate(session, 5.0).await,
+            Err(MediaError::InvalidParameter(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_set_rate_updates_playing_state() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        engine.play(session).await.unwrap();
+        engine.set_rate(session, 1.5).await.unwrap();
+
+        let state = engine.inner.session_manager.get_state(session).unwrap();
+        assert!(matches!(state, SessionState::Playing { rate, .. } if rate == 1.5));
+
+        let pipeline_rate = engine.inner.sessions.read()[&session]
+            .pipeline
+            .as_ref()
+            .unwrap()
+            .playback_rate();
+        assert_eq!(pipeline_rate, 1.5);
+
+        // The rate is kept across pause/play and seek
+        engine.pause(session).await.unwrap();
+        engine.play(session).await.unwrap();
+        engine.seek(session, Duration::from_secs(5)).await.unwrap();
+        let state = engine.inner.session_manager.get_state(session).unwrap();
+        assert!(matches!(state, SessionState::Playing { rate, .. } if rate == 1.5));
+    }
+
+    #[tokio::test]
+    async fn test_set_rate_while_paused_applies_on_play() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+
+        // A paused session stays paused with the new rate
+        engine.pause(session).await.unwrap();
+        engine.set_rate(session, 0.5).await.unwrap();
+        let state = engine.inner.session_manager.get_state(session).unwrap();
+        assert!(matches!(state, SessionState::Paused { .. }));
+
+        engine.play(session).await.unwrap();
+        let state = engine.inner.session_manager.get_state(session).unwrap();
+        assert!(matches!(state, SessionState::Playing { rate, .. } if rate == 0.5));
+
+        // A rejected rate leaves the current one in place
+        assert!(matches!(
+            engine.set_rate(session, 8.0).await,
+            Err(MediaError::InvalidParameter(_))
+        ));
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        assert_eq!(pipeline.playback_rate(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_execute_set_rate_command() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        engine.play(session).await.unwrap();
+
+        engine
+            .execute_command(session, PlaybackCommand::SetRate(0.5))
+            .await
+            .unwrap();
+        let state = engine.inner.session_manager.get_state(session).unwrap();
+        assert!(matches!(state, SessionState::Playing { rate, .. } if rate == 0.5));
+
+        assert!(engine
+            .execute_command(session, PlaybackCommand::SetRate(32.0))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_set_loop() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        // The mode is kept for pipelines created later
+        engine.set_loop(session, LoopMode::All).await.unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.ogg".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        let pipeline_mode = engine.inner.sessions.read()[&session]
+            .pipeline
+            .as_ref()
+            .unwrap()
+            .loop_mode();
+        assert_eq!(pipeline_mode, LoopMode::All);
+
+        let backwards = LoopMode::AB {
+            start: Duration::from_secs(2),
+            end: Duration::from_secs(1),
+        };
+        assert!(engine.set_loop(session, backwards).await.is_err());
+        assert_eq!(
+            engine.inner.sessions.read()[&session].loop_mode,
+            LoopMode::All
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_loops_update_session_state() {
+        tokio::time::pause();
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        let source = MediaSource::Url {
+            url: "test.ogg".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        engine.set_loop(session, LoopMode::All).await.unwrap();
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.set_media_duration(Duration::from_secs(1));
+        engine.play(session).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+
+        let mut states = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let MediaEngineEvent::PlaybackStateChanged { state, .. } = event {
+                states.push(state.state_name());
+            }
+        }
+        // Loops restart playback without ending it
+        assert_eq!(
+            states,
+            ["Loading", "Ready", "Playing", "Looping", "Playing", "Looping", "Playing"]
+        );
+
+        let state = engine.inner.session_manager.get_state(session).unwrap();
+        assert!(matches!(state, SessionState::Playing { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_ab_loop_emits_iterations() {
+        tokio::time::pause();
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        let source = MediaSource::Url {
+            url: "test.ogg".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
 
-        // Stop pipeline if exists
-        if let Some(pipeline) = context.pipeline {
-            // TODO: Stop and cleanup pipeline
-            debug!("Stopping pipeline for session: {:?}", session);
-        }
+        let start = Duration::from_millis(200);
+        let end = Duration::from_millis(700);
+        assert!(engine.set_ab_loop(session, end, start).await.is_err());
+        engine.set_ab_loop(session, start, end).await.unwrap();
+        assert_eq!(pipeline.loop_mode(), LoopMode::AB { start, end });
 
-        // Destroy session through manager
-        self.session_manager.destroy(session)?;
+        let mut events = engine.subscribe_session_events(session).unwrap();
+        engine.play(session).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1300)).await;
 
-        info!("Destroyed session: {:?}", session);
-        Ok(())
-    }
-}
+        let mut counts = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                SessionEvent::AbLoopIteration { count } => counts.push(count),
+                SessionEvent::StateChanged {
+                    new: SessionState::Playing { ab_loop, .. },
+                    ..
+                } => assert_eq!(ab_loop, Some((start, end))),
+                _ => {}
+            }
+        }
+        assert_eq!(counts, [1, 2]);
+        let position = pipeline.current_position();
+        assert!(position >= start && position < end);
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        // Another mode replaces the loop section
+        engine.set_loop(session, LoopMode::None).await.unwrap();
+        assert_eq!(
+            engine.inner.sessions.read()[&session].session.ab_loop(),
+            None
+        );
+    }
 
     #[tokio::test]
-    async fn test_create_engine() {
+    async fn test_looping_media_element_never_ends() {
+        tokio::time::pause();
         let config = MediaEngineConfig::default();
-        let engine = MediaEngineImpl::new(config);
-        assert!(engine.is_ok());
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+
+        let message = MediaEngineMessage::CreateMediaElement {
+            element_id: "video-1".to_string(),
+            attributes: MediaElementAttributes {
+                loop_playback: true,
+                src: Some("test.ogg".to_string()),
+                ..Default::default()
+            },
+        };
+        engine.handle_message(message).await.unwrap();
+
+        let Some(MediaEngineEvent::MediaElementCreated {
+            element_id,
+            session_id: session,
+        }) = events.recv().await
+        else {
+            panic!("Expected MediaElementCreated event");
+        };
+        assert_eq!(element_id, "video-1");
+        assert_eq!(
+            engine.inner.sessions.read()[&session].loop_mode,
+            LoopMode::All
+        );
+
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.set_media_duration(Duration::from_secs(1));
+        engine.play(session).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+
+        let mut loops = 0;
+        while let Ok(event) = events.try_recv() {
+            if let MediaEngineEvent::PlaybackStateChanged { state, .. } = event {
+                assert!(!matches!(state, SessionState::Ended));
+                loops += usize::from(matches!(state, SessionState::Looping { .. }));
+            }
+        }
+        assert_eq!(loops, 3);
     }
 
     #[tokio::test]
-    async fn test_create_session() {
+    async fn test_double_rate_ends_in_half_the_duration() {
+        tokio::time::pause();
         let config = MediaEngineConfig::default();
         let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
 
-        let session_config = MediaSessionConfig::default();
-        let result = engine.create_session(session_config).await;
-        assert!(result.is_ok());
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        let source = MediaSource::Url {
+            url: "test.ogg".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        engine.set_playback_rate(session, 2.0).await.unwrap();
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        engine.play(session).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(4900)).await;
+        assert!(!pipeline.is_ended());
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(pipeline.is_ended());
+
+        let mut states = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if let MediaEngineEvent::PlaybackStateChanged { state, .. } = event {
+                states.push(state);
+            }
+        }
+        assert!(matches!(
+            states[..],
+            [
+                SessionState::Loading { .. },
+                SessionState::Ready { .. },
+                SessionState::Playing { rate, .. },
+                SessionState::Ended,
+            ] if rate == 2.0
+        ));
+        assert!(matches!(
+            engine.inner.session_manager.get_state(session).unwrap(),
+            SessionState::Ended
+        ));
     }
 
     #[tokio::test]
-    async fn test_session_limit() {
+    async fn test_starved_pipeline_reports_buffering() {
+        tokio::time::pause();
         let config = MediaEngineConfig {
-            max_sessions: 1,
+            buffering_readahead: Duration::from_millis(200),
             ..Default::default()
         };
         let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
 
-        // Create first session - should succeed
-        let session1 = engine.create_session(MediaSessionConfig::default()).await;
-        assert!(session1.is_ok());
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        let mut session_events = engine.subscribe_session_events(session).unwrap();
+        let source = MediaSource::Url {
+            url: "test.mp4".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        engine.play(session).await.unwrap();
 
-        // Create second session - should fail due to limit
-        let session2 = engine.create_session(MediaSessionConfig::default()).await;
-        assert!(session2.is_err());
+        let push_frames = |range: std::ops::Range<u64>| {
+            let pipeline = Arc::clone(&pipeline);
+            async move {
+                for ms in range.step_by(40) {
+                    let mut frame = video_frame(ms);
+                    frame.duration = Some(Duration::from_millis(40));
+                    pipeline.push_video_frame(frame).await.unwrap();
+                }
+            }
+        };
+
+        // The input stops after 200ms of video, which is played
+        push_frames(0..200).await;
+        while pipeline.get_next_video_frame().await.is_some() {}
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        // Then resumes
+        push_frames(200..1000).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut buffering = Vec::new();
+        let mut started_at = Duration::ZERO;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                MediaEngineEvent::BufferedRangesChanged { ranges, .. } => {
+                    buffering.push(format!("ranges {:?}", ranges))
+                }
+                MediaEngineEvent::BufferingStarted { position, .. } => {
+                    assert!(position >= Duration::from_millis(200));
+                    started_at = position;
+                    buffering.push("started".to_string())
+                }
+                MediaEngineEvent::BufferingEnded { position, .. } => {
+                    assert!(position > started_at);
+                    buffering.push("ended".to_string())
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(
+            buffering,
+            [
+                "ranges [(0ns, 200ms)]",
+                "started",
+                "ranges [(0ns, 1s)]",
+                "ended"
+            ]
+        );
+
+        // The session's subscribers see the same
+        let mut forwarded = Vec::new();
+        while let Ok(event) = session_events.try_recv() {
+            if matches!(
+                event,
+                SessionEvent::BufferingStarted | SessionEvent::BufferingEnded
+            ) {
+                forwarded.push(event);
+            }
+        }
+        assert_eq!(
+            forwarded,
+            [SessionEvent::BufferingStarted, SessionEvent::BufferingEnded]
+        );
     }
 
     #[tokio::test]
-    async fn test_play_pause() {
+    async fn test_ready_reports_duration() {
         let config = MediaEngineConfig::default();
         let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
 
         let session = engine
             .create_session(MediaSessionConfig::default())
             .await
             .unwrap();
+        // A stream is loading until its chunks describe the media
+        let (_chunks, receiver) = mpsc::channel(1);
+        let source = MediaSource::Stream {
+            receiver: Arc::new(receiver),
+            mime_type: "video/mp4".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        settle().await;
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            MediaEngineEvent::PlaybackStateChanged {
+                state: SessionState::Loading { .. },
+                ..
+            }
+        ));
 
-        // Play should succeed
-        let result = engine.play(session).await;
-        assert!(result.is_ok());
+        let info = MediaInfo {
+            duration: Duration::from_secs(30),
+            video_tracks: Vec::new(),
+            audio_tracks: Vec::new(),
+            metadata: HashMap::new(),
+        };
+        {
+            let sessions = engine.inner.sessions.read();
+            engine.set_ready(session, &sessions[&session], &info);
+        }
 
-        // Pause should succeed
-        let result = engine.pause(session).await;
-        assert!(result.is_ok());
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            MediaEngineEvent::DurationChanged { session_id, duration }
+                if session_id == session && duration == Duration::from_secs(30)
+        ));
+        settle().await;
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            MediaEngineEvent::PlaybackStateChanged {
+                state: SessionState::Ready { .. },
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_report_statistics_periodically() {
+        let config = MediaEngineConfig {
+            stats_interval: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let engine = Arc::new(MediaEngineImpl::new(config).unwrap());
+        let mut events = engine.take_event_receiver().unwrap();
+        engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        let task = engine.report_statistics().unwrap();
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+
+        for _ in 0..2 {
+            assert!(matches!(
+                events.try_recv().unwrap(),
+                MediaEngineEvent::PeriodicStats(MediaEngineStats {
+                    sessions_active: 1,
+                    ..
+                })
+            ));
+        }
+        assert!(events.try_recv().is_err());
+
+        drop(engine);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(task.is_finished());
     }
 
     #[tokio::test]
-    async fn test_seek() {
+    async fn test_statistics_reporting_disabled() {
+        let config = MediaEngineConfig {
+            stats_interval: Duration::ZERO,
+            ..Default::default()
+        };
+        let engine = Arc::new(MediaEngineImpl::new(config).unwrap());
+        assert!(engine.report_statistics().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_watch_capture_devices_without_changes() {
         let config = MediaEngineConfig::default();
         let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+
+        engine.watch_capture_devices();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // No devices are plugged in during the test
+        assert!(events.try_recv().is_err());
+    }
 
+    #[tokio::test]
+    async fn test_message_loop_handles_playback_command() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
         let session = engine
             .create_session(MediaSessionConfig::default())
             .await
             .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        settle().await;
+        while events.try_recv().is_ok() {}
 
-        // Seek should succeed
-        let result = engine.seek(session, Duration::from_secs(10)).await;
-        assert!(result.is_ok());
+        // Started by `new` within the runtime
+        assert!(engine.run().is_none());
+        engine
+            .message_sender()
+            .send(MediaEngineMessage::PlaybackCommand {
+                session_id: session,
+                command: PlaybackCommand::Play,
+            })
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        match event {
+            MediaEngineEvent::PlaybackStateChanged { session_id, state } => {
+                assert_eq!(session_id, session);
+                assert!(matches!(state, SessionState::Playing { .. }));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
     }
 
     #[tokio::test]
-    async fn test_set_volume_valid() {
+    async fn test_message_loop_creates_and_controls_media_element() {
         let config = MediaEngineConfig::default();
         let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+        let messages = engine.message_sender();
 
-        let session = engine
-            .create_session(MediaSessionConfig::default())
+        messages
+            .send(MediaEngineMessage::CreateMediaElement {
+                element_id: "video-1".to_string(),
+                attributes: MediaElementAttributes::default(),
+            })
+            .unwrap();
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let session = match event {
+            MediaEngineEvent::MediaElementCreated {
+                element_id,
+                session_id,
+            } => {
+                assert_eq!(element_id, "video-1");
+                session_id
+            }
+            other => panic!("unexpected event: {:?}", other),
+        };
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
             .await
             .unwrap();
 
-        // Valid volumes should succeed
-        assert!(engine.set_volume(session, 0.0).await.is_ok());
-        assert!(engine.set_volume(session, 0.5).await.is_ok());
-        assert!(engine.set_volume(session, 1.0).await.is_ok());
+        // Commands are handled in the order they were sent
+        for command in [
+            PlaybackCommand::Play,
+            PlaybackCommand::Seek(10_000),
+            PlaybackCommand::Pause,
+        ] {
+            messages
+                .send(MediaEngineMessage::PlaybackCommand {
+                    session_id: session,
+                    command,
+                })
+                .unwrap();
+        }
+        let mut states = Vec::new();
+        while states.len() < 6 {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            if let MediaEngineEvent::PlaybackStateChanged { session_id, state } = event {
+                assert_eq!(session_id, session);
+                states.push(state.state_name());
+            }
+        }
+        assert_eq!(
+            states,
+            ["Loading", "Ready", "Playing", "Seeking", "Playing", "Paused"]
+        );
+        assert!(matches!(
+            engine.inner.session_manager.get_state(session).unwrap(),
+            SessionState::Paused { position } if position == Duration::from_secs(10)
+        ));
     }
 
     #[tokio::test]
-    async fn test_set_volume_invalid() {
+    async fn test_message_loop_reports_errors() {
         let config = MediaEngineConfig::default();
         let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+        let unknown = SessionId::new();
 
-        let session = engine
-            .create_session(MediaSessionConfig::default())
+        engine
+            .message_sender()
+            .send(MediaEngineMessage::PlaybackCommand {
+                session_id: unknown,
+                command: PlaybackCommand::Play,
+            })
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
             .await
+            .unwrap()
             .unwrap();
+        assert!(matches!(
+            event,
+            MediaEngineEvent::MediaError {
+                session_id,
+                error: MediaError::SessionNotFound(_),
+            } if session_id == unknown
+        ));
+    }
 
-        // Invalid volumes should fail
-        assert!(engine.set_volume(session, -0.1).await.is_err());
-        assert!(engine.set_volume(session, 1.1).await.is_err());
+    #[test]
+    fn test_message_loop_started_by_run_outside_runtime() {
+        let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+        let unknown = SessionId::new();
+        engine
+            .message_sender()
+            .send(MediaEngineMessage::PlaybackCommand {
+                session_id: unknown,
+                command: PlaybackCommand::Play,
+            })
+            .unwrap();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        runtime.block_on(async {
+            // Messages sent before the loop starts are handled once it runs
+            engine.run().unwrap();
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(matches!(
+                event,
+                MediaEngineEvent::MediaError { session_id, .. } if session_id == unknown
+            ));
+        });
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_plays_out_queued_frames() {
+        tokio::time::pause();
+        let (engine, session, pipeline) = engine_with_empty_pipeline().await;
+        let mut events = engine.take_event_receiver().unwrap();
+        for timestamp in (0..=400).step_by(40) {
+            pipeline
+                .push_video_frame(video_frame(timestamp))
+                .await
+                .unwrap();
+        }
+        engine.play(session).await.unwrap();
+
+        let started = Instant::now();
+        engine.shutdown(Duration::from_secs(5)).await.unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(400));
+        assert!(elapsed < Duration::from_secs(1));
+        assert!(!pipeline.is_running());
+        assert!(engine.inner.sessions.read().is_empty());
+
+        let mut shutdown_complete = false;
+        while let Ok(event) = events.try_recv() {
+            shutdown_complete |= matches!(event, MediaEngineEvent::ShutdownComplete);
+        }
+        assert!(shutdown_complete);
+        assert!(matches!(
+            engine.create_session(MediaSessionConfig::default()).await,
+            Err(MediaError::InvalidState(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_destroys_sessions_after_drain_timeout() {
+        tokio::time::pause();
+        let (engine, session, pipeline) = engine_with_empty_pipeline().await;
+        for timestamp in [0, 10_000] {
+            pipeline
+                .push_video_frame(video_frame(timestamp))
+                .await
+                .unwrap();
+        }
+        engine.play(session).await.unwrap();
+
+        let started = Instant::now();
+        engine.shutdown(Duration::from_millis(100)).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(engine.inner.sessions.read().is_empty());
+        assert!(engine.inner.session_manager.get(session).is_none());
+        assert!(!pipeline.is_running());
     }
 
     #[tokio::test]
@@ -524,6 +3998,8 @@ mod tests {
             .await
             .is_err());
         assert!(engine.set_volume(fake_session, 0.5).await.is_err());
+        assert!(engine.set_rate(fake_session, 1.0).await.is_err());
+        assert!(engine.set_loop(fake_session, LoopMode::All).await.is_err());
         assert!(engine.destroy_session(fake_session).await.is_err());
     }
 
@@ -545,6 +4021,156 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_software_only_load_leaves_hardware_uninitialized() {
+        let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        let source = MediaSource::Url {
+            url: "test.mp4".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+        assert_eq!(
+            engine.inner.sessions.read()[&session].decoder_policy,
+            DecoderSelectionPolicy::SoftwareOnly
+        );
+        assert!(!engine.inner.decoders.is_hardware_initialized());
+    }
+
+    #[tokio::test]
+    async fn test_hardware_only_session_fails_without_hardware() {
+        let config = MediaEngineConfig {
+            hardware_accel_enabled: false,
+            ..Default::default()
+        };
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let session = engine
+            .create_session(
+                MediaSessionConfig::new().with_decoder_policy(DecoderSelectionPolicy::HardwareOnly),
+            )
+            .await
+            .unwrap();
+
+        let source = MediaSource::Url {
+            url: "test.mp4".to_string(),
+        };
+        let error = engine.load_source(session, source).await.unwrap_err();
+        assert!(matches!(error, MediaError::HardwareError { .. }));
+        assert!(matches!(
+            engine.inner.session_manager.get_state(session).unwrap(),
+            SessionState::Error { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_load_invalid_source_emits_error() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+
+        let source = MediaSource::Buffer {
+            data: vec![0xAB; 64],
+            mime_type: "application/octet-stream".to_string(),
+        };
+        let error = engine.load_source(session, source).await.unwrap_err();
+
+        match events.try_recv().unwrap() {
+            MediaEngineEvent::MediaError {
+                session_id,
+                error: reported,
+            } => {
+                assert_eq!(session_id, session);
+                assert_eq!(reported, error);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(matches!(
+            engine.inner.session_manager.get_state(session).unwrap(),
+            SessionState::Error { error: state_error } if state_error == error
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_message_loop_reports_failed_seek_once() {
+        let config = MediaEngineConfig::default();
+        let engine = MediaEngineImpl::new(config).unwrap();
+        let mut events = engine.take_event_receiver().unwrap();
+
+        let session = engine
+            .create_session(MediaSessionConfig::default())
+            .await
+            .unwrap();
+        engine
+            .load_source(
+                session,
+                MediaSource::Url {
+                    url: "test.mp4".to_string(),
+                },
+            )
+            .await
+            .unwrap();
+        engine.play(session).await.unwrap();
+        let pipeline = engine.inner.sessions.read()[&session]
+            .pipeline
+            .clone()
+            .unwrap();
+        pipeline.stop().await.unwrap();
+        settle().await;
+        while events.try_recv().is_ok() {}
+
+        // A stopped pipeline cannot seek
+        engine
+            .message_sender()
+            .send(MediaEngineMessage::PlaybackCommand {
+                session_id: session,
+                command: PlaybackCommand::Seek(1000),
+            })
+            .unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                match events.recv().await {
+                    Some(MediaEngineEvent::PlaybackStateChanged { .. }) => continue,
+                    event => break event,
+                }
+            }
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(matches!(
+            event,
+            MediaEngineEvent::MediaError { session_id, .. } if session_id == session
+        ));
+        assert!(matches!(
+            engine.inner.session_manager.get_state(session).unwrap(),
+            SessionState::Error { .. }
+        ));
+
+        // Besides the state changes to Seeking and Error
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut states = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                MediaEngineEvent::PlaybackStateChanged { state, .. } => {
+                    states.push(state.state_name())
+                }
+                other => panic!("unexpected event: {:?}", other),
+            }
+        }
+        assert!(states
+            .iter()
+            .all(|state| ["Seeking", "Error"].contains(state)));
+    }
+
     #[tokio::test]
     async fn test_multiple_sessions() {
         let config = MediaEngineConfig {
@@ -560,6 +4186,10 @@ mod tests {
                 .create_session(MediaSessionConfig::default())
                 .await
                 .unwrap();
+            let source = MediaSource::Url {
+                url: "test.mp4".to_string(),
+            };
+            engine.load_source(session, source).await.unwrap();
             sessions.push(session);
         }
 
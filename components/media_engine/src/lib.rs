@@ -19,6 +19,10 @@
 //! - **WebRTC**: Using webrtc_integration for real-time media
 //! - **DRM**: Using drm_support for protected content
 //! - **Capture**: Using media_capture for device input
+//! - **Media Source Extensions**: Parsing appended byte stream segments into
+//!   buffered coded frames
+//! - **Audio Output**: Feeding decoded audio to an [`AudioSink`](cortenbrowser_shared_types::AudioSink),
+//!   played on the default ALSA device with the `alsa` feature
 //!
 //! # Examples
 //!
@@ -60,9 +64,23 @@
 #![warn(missing_docs)]
 #![deny(unsafe_code)]
 
+#[cfg(all(feature = "alsa", target_os = "linux"))]
+mod alsa_sink;
+mod audio_output;
+mod decoder_selection;
 mod engine;
+mod mse;
 mod types;
 
 // Re-export public API
+#[cfg(all(feature = "alsa", target_os = "linux"))]
+pub use audio_output::AlsaAudioSink;
+pub use audio_output::{AudioSinkFactory, MemoryAudioSink, NullAudioSink};
 pub use engine::MediaEngineImpl;
-pub use types::{MediaEngineConfig, MediaEngineEvent, MediaEngineMessage};
+pub use mse::{
+    CodedFrame, EndOfStreamError, MediaSourceHandle, MseEvent, ReadyState, SourceBufferHandle,
+};
+pub use types::{
+    DecoderSelectionPolicy, MediaEngineConfig, MediaEngineEvent, MediaEngineMessage,
+    MediaEngineStats,
+};
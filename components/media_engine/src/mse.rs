@@ -0,0 +1,1372 @@
+//! Media Source Extensions
+//!
+//! A [`MediaSourceHandle`] is the engine side of a script's `MediaSource`:
+//! [`SourceBufferHandle`]s are added to it for each MIME type, and byte
+//! stream segments appended to them are parsed into coded frames, which
+//! make up their buffered ranges and can be read for playback.
+//!
+//! Appends and removals run asynchronously like in the W3C algorithms:
+//! the buffer is `updating` until they finish, and their progress is
+//! reported as [`MseEvent`]s named after the DOM events a script shim
+//! dispatches for them.
+use cortenbrowser_format_parsers::{ContainerFormat, DemuxedPacket, Demuxer};
+use cortenbrowser_shared_types::MediaError;
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// How many events a subscriber may fall behind before missing some
+const EVENT_CAPACITY: usize = 64;
+
+/// Largest gap between coded frames that still counts as contiguous in the
+/// buffered ranges
+const GAP_TOLERANCE: Duration = Duration::from_millis(1);
+
+/// Whether a media source accepts data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadyState {
+    /// Detached from the media element; no operations are allowed
+    Closed,
+    /// Accepting appends and removals
+    Open,
+    /// `end_of_stream` was called; the next append or removal reopens it
+    Ended,
+}
+
+/// Error passed to [`MediaSourceHandle::end_of_stream`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndOfStreamError {
+    /// Fetching the media failed
+    Network,
+    /// The media could not be parsed or decoded
+    Decode,
+}
+
+/// Events of a media source and its source buffers
+///
+/// Each maps to the DOM event of the same name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MseEvent {
+    /// The media source was opened again after it ended
+    SourceOpen,
+    /// The media source ended
+    SourceEnded,
+    /// The media source was closed
+    SourceClose,
+    /// An append or removal started
+    UpdateStart {
+        /// Source buffer ID
+        buffer_id: String,
+    },
+    /// An append or removal completed successfully
+    Update {
+        /// Source buffer ID
+        buffer_id: String,
+    },
+    /// An append or removal ended, successfully or not
+    UpdateEnd {
+        /// Source buffer ID
+        buffer_id: String,
+    },
+    /// An append failed to parse; the media source ends with a decode error
+    Error {
+        /// Source buffer ID
+        buffer_id: String,
+        /// The parse error
+        error: MediaError,
+    },
+    /// An append was aborted
+    Abort {
+        /// Source buffer ID
+        buffer_id: String,
+    },
+}
+
+/// A compressed frame held by a source buffer
+#[derive(Debug, Clone)]
+pub struct CodedFrame {
+    /// The demuxed packet
+    pub packet: DemuxedPacket,
+    /// Presentation timestamp
+    pub pts: Duration,
+    /// Time until the next frame of the track
+    pub duration: Duration,
+}
+
+impl CodedFrame {
+    /// Presentation end time
+    pub fn end(&self) -> Duration {
+        self.pts + self.duration
+    }
+}
+
+/// Engine side of a Media Source Extensions `MediaSource`
+///
+/// Handles are cheap to clone and share the same media source. A new media
+/// source is `Open`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use cortenbrowser_media_engine::{MediaSourceHandle, MseEvent};
+///
+/// # async fn example(init: Vec<u8>, segment: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+/// let source = MediaSourceHandle::new();
+/// let mut events = source.subscribe();
+/// let buffer = source.add_source_buffer("video/webm; codecs=\"vp9\"")?;
+///
+/// for data in [init, segment] {
+///     buffer.append_buffer(data)?;
+///     while !matches!(events.recv().await?, MseEvent::UpdateEnd { .. }) {}
+/// }
+/// println!("Buffered: {:?}", buffer.buffered());
+///
+/// source.end_of_stream(None)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MediaSourceHandle {
+    inner: Arc<MediaSourceInner>,
+}
+
+/// A source buffer of a [`MediaSourceHandle`]
+///
+/// Handles are cheap to clone and share the same source buffer.
+#[derive(Debug, Clone)]
+pub struct SourceBufferHandle {
+    source: Arc<MediaSourceInner>,
+    inner: Arc<SourceBufferInner>,
+}
+
+#[derive(Debug)]
+struct MediaSourceInner {
+    state: Mutex<MediaSourceState>,
+    events: broadcast::Sender<MseEvent>,
+}
+
+#[derive(Debug)]
+struct MediaSourceState {
+    ready_state: ReadyState,
+    duration: Option<Duration>,
+    end_of_stream_error: Option<EndOfStreamError>,
+    source_buffers: Vec<Arc<SourceBufferInner>>,
+    next_buffer_id: u64,
+}
+
+#[derive(Debug)]
+struct SourceBufferInner {
+    id: String,
+    mime_type: String,
+    format: ContainerFormat,
+    state: Mutex<SourceBufferState>,
+}
+
+/// What a source buffer is doing while it is `updating`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Append,
+    Remove,
+}
+
+#[derive(Debug)]
+struct SourceBufferState {
+    operation: Option<Operation>,
+    task: Option<JoinHandle<()>>,
+    /// Incremented by `abort`, so that an aborted append does not complete
+    generation: u64,
+    /// Set once the buffer is removed from its media source
+    removed: bool,
+    parser: Box<dyn Demuxer + Send>,
+    /// Bytes appended until the parser knew the tracks, fed to a new parser
+    /// when the parser state is reset
+    init_segment: Vec<u8>,
+    tracks: BTreeMap<u32, TrackBuffer>,
+}
+
+/// Coded frames of one track, by presentation timestamp
+#[derive(Debug, Default)]
+struct TrackBuffer {
+    frames: BTreeMap<Duration, CodedFrame>,
+    /// Duration of the last frame whose successor was appended with it
+    last_frame_duration: Option<Duration>,
+    /// Frame duration declared by the track, used until one is measured
+    default_duration: Option<Duration>,
+}
+
+impl Default for MediaSourceHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MediaSourceHandle {
+    /// Create an open media source without source buffers
+    pub fn new() -> Self {
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            inner: Arc::new(MediaSourceInner {
+                state: Mutex::new(MediaSourceState {
+                    ready_state: ReadyState::Open,
+                    duration: None,
+                    end_of_stream_error: None,
+                    source_buffers: Vec::new(),
+                    next_buffer_id: 0,
+                }),
+                events,
+            }),
+        }
+    }
+
+    /// Subscribe to the events of the media source and its source buffers
+    pub fn subscribe(&self) -> broadcast::Receiver<MseEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Get the ready state
+    pub fn ready_state(&self) -> ReadyState {
+        self.inner.state.lock().ready_state
+    }
+
+    /// Get the media duration, `None` until set or known
+    pub fn duration(&self) -> Option<Duration> {
+        self.inner.state.lock().duration
+    }
+
+    /// Set the media duration
+    ///
+    /// # Errors
+    /// * `InvalidState` - The media source is not open, a source buffer is
+    ///   updating, or frames are buffered beyond `duration`
+    pub fn set_duration(&self, duration: Duration) -> Result<(), MediaError> {
+        let mut state = self.inner.state.lock();
+        if state.ready_state != ReadyState::Open {
+            return Err(MediaError::InvalidState(
+                "Media source is not open".to_string(),
+            ));
+        }
+        if state
+            .source_buffers
+            .iter()
+            .any(|buffer| buffer.is_updating())
+        {
+            return Err(MediaError::InvalidState(
+                "A source buffer is updating".to_string(),
+            ));
+        }
+        if state
+            .source_buffers
+            .iter()
+            .any(|buffer| buffer.highest_pts().is_some_and(|pts| pts > duration))
+        {
+            return Err(MediaError::InvalidState(
+                "Duration is before buffered frames".to_string(),
+            ));
+        }
+        state.duration = Some(duration);
+        Ok(())
+    }
+
+    /// Get the error the stream ended with, if any
+    pub fn end_of_stream_error(&self) -> Option<EndOfStreamError> {
+        self.inner.state.lock().end_of_stream_error
+    }
+
+    /// Add a source buffer for media of a MIME type
+    ///
+    /// # Arguments
+    /// * `mime_type` - Type of the byte stream, e.g. `video/webm; codecs="vp9"`
+    ///   or `video/mp4; codecs="avc1.64001f"`
+    ///
+    /// # Returns
+    /// * `Ok(SourceBufferHandle)` - The new source buffer
+    /// * `Err(MediaError)` - `UnsupportedFormat` for a MIME type without a
+    ///   byte stream parser, or `InvalidState` if the media source is not
+    ///   open
+    pub fn add_source_buffer(&self, mime_type: &str) -> Result<SourceBufferHandle, MediaError> {
+        let format = ContainerFormat::from_mime(mime_type)?;
+        if format == ContainerFormat::Ogg {
+            // Ogg has no byte stream format with self-contained segments
+            return Err(MediaError::UnsupportedFormat {
+                format: format!("{} is not supported as an MSE byte stream", mime_type),
+            });
+        }
+
+        let mut state = self.inner.state.lock();
+        if state.ready_state != ReadyState::Open {
+            return Err(MediaError::InvalidState(
+                "Media source is not open".to_string(),
+            ));
+        }
+
+        let id = format!("sourcebuffer-{}", state.next_buffer_id);
+        state.next_buffer_id += 1;
+        let buffer = Arc::new(SourceBufferInner {
+            id,
+            mime_type: mime_type.to_string(),
+            format,
+            state: Mutex::new(SourceBufferState {
+                operation: None,
+                task: None,
+                generation: 0,
+                removed: false,
+                parser: format.create_demuxer(),
+                init_segment: Vec::new(),
+                tracks: BTreeMap::new(),
+            }),
+        });
+        state.source_buffers.push(Arc::clone(&buffer));
+        debug!("Added source buffer {} for {}", buffer.id, mime_type);
+
+        Ok(SourceBufferHandle {
+            source: Arc::clone(&self.inner),
+            inner: buffer,
+        })
+    }
+
+    /// Remove a source buffer, aborting its append or removal
+    ///
+    /// # Errors
+    /// * `InvalidParameter` - The buffer is not one of this media source's
+    pub fn remove_source_buffer(&self, buffer: &SourceBufferHandle) -> Result<(), MediaError> {
+        let mut state = self.inner.state.lock();
+        let index = state
+            .source_buffers
+            .iter()
+            .position(|b| Arc::ptr_eq(b, &buffer.inner))
+            .ok_or_else(|| {
+                MediaError::InvalidParameter(format!(
+                    "Source buffer {} is not attached",
+                    buffer.inner.id
+                ))
+            })?;
+        state.source_buffers.remove(index);
+        drop(state);
+
+        let mut buffer_state = buffer.inner.state.lock();
+        buffer_state.removed = true;
+        if buffer_state.operation.is_some() {
+            buffer.inner.stop_operation(&mut buffer_state);
+            self.inner.emit(MseEvent::Abort {
+                buffer_id: buffer.inner.id.clone(),
+            });
+            self.inner.emit(MseEvent::UpdateEnd {
+                buffer_id: buffer.inner.id.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the source buffers, in the order they were added
+    pub fn source_buffers(&self) -> Vec<SourceBufferHandle> {
+        self.inner
+            .state
+            .lock()
+            .source_buffers
+            .iter()
+            .map(|buffer| SourceBufferHandle {
+                source: Arc::clone(&self.inner),
+                inner: Arc::clone(buffer),
+            })
+            .collect()
+    }
+
+    /// Get the time ranges buffered in every source buffer
+    ///
+    /// Once the media source has ended, the last range of each source
+    /// buffer extends to the highest end time of all of them, so that
+    /// tracks ending early do not cut playback short.
+    pub fn buffered(&self) -> Vec<(Duration, Duration)> {
+        let state = self.inner.state.lock();
+        let ranges: Vec<_> = state
+            .source_buffers
+            .iter()
+            .map(|buffer| buffer.buffered())
+            .collect();
+        if ranges.is_empty() {
+            return Vec::new();
+        }
+
+        let ended = state.ready_state == ReadyState::Ended;
+        let highest_end = ranges
+            .iter()
+            .filter_map(|r| r.last().map(|&(_, end)| end))
+            .max()
+            .unwrap_or_default();
+        ranges
+            .into_iter()
+            .map(|mut r| {
+                if let Some(last) = r.last_mut().filter(|_| ended) {
+                    last.1 = highest_end;
+                }
+                r
+            })
+            .reduce(|a, b| intersect_ranges(&a, &b))
+            .unwrap_or_default()
+    }
+
+    /// Signal that no more data will be appended
+    ///
+    /// Without an error, the duration becomes the highest end time
+    /// buffered.
+    ///
+    /// # Errors
+    /// * `InvalidState` - The media source is not open or a source buffer
+    ///   is updating
+    pub fn end_of_stream(&self, error: Option<EndOfStreamError>) -> Result<(), MediaError> {
+        let mut state = self.inner.state.lock();
+        if state.ready_state != ReadyState::Open {
+            return Err(MediaError::InvalidState(
+                "Media source is not open".to_string(),
+            ));
+        }
+        if state
+            .source_buffers
+            .iter()
+            .any(|buffer| buffer.is_updating())
+        {
+            return Err(MediaError::InvalidState(
+                "A source buffer is updating".to_string(),
+            ));
+        }
+        self.inner.end(&mut state, error);
+        Ok(())
+    }
+
+    /// Close the media source, detaching and aborting all source buffers
+    pub fn close(&self) {
+        let buffers = {
+            let mut state = self.inner.state.lock();
+            if state.ready_state == ReadyState::Closed {
+                return;
+            }
+            state.ready_state = ReadyState::Closed;
+            state.duration = None;
+            std::mem::take(&mut state.source_buffers)
+        };
+        for buffer in buffers {
+            let mut buffer_state = buffer.state.lock();
+            buffer_state.removed = true;
+            buffer.stop_operation(&mut buffer_state);
+        }
+        self.inner.emit(MseEvent::SourceClose);
+    }
+}
+
+impl MediaSourceInner {
+    fn emit(&self, event: MseEvent) {
+        // Nobody listening is fine
+        let _ = self.events.send(event);
+    }
+
+    /// Reopen an ended media source for an append or removal
+    fn reopen(&self) -> Result<(), MediaError> {
+        let mut state = self.state.lock();
+        match state.ready_state {
+            ReadyState::Closed => Err(MediaError::InvalidState(
+                "Media source is closed".to_string(),
+            )),
+            ReadyState::Open => Ok(()),
+            ReadyState::Ended => {
+                state.ready_state = ReadyState::Open;
+                state.end_of_stream_error = None;
+                drop(state);
+                self.emit(MseEvent::SourceOpen);
+                Ok(())
+            }
+        }
+    }
+
+    fn end(&self, state: &mut MediaSourceState, error: Option<EndOfStreamError>) {
+        state.ready_state = ReadyState::Ended;
+        state.end_of_stream_error = error;
+        if error.is_none() {
+            let highest_end = state
+                .source_buffers
+                .iter()
+                .filter_map(|buffer| buffer.highest_end())
+                .max();
+            if let Some(end) = highest_end {
+                state.duration = Some(end);
+            }
+        }
+        self.emit(MseEvent::SourceEnded);
+    }
+}
+
+impl SourceBufferInner {
+    fn is_updating(&self) -> bool {
+        self.state.lock().operation.is_some()
+    }
+
+    fn buffered(&self) -> Vec<(Duration, Duration)> {
+        self.state
+            .lock()
+            .tracks
+            .values()
+            .map(TrackBuffer::ranges)
+            .reduce(|a, b| intersect_ranges(&a, &b))
+            .unwrap_or_default()
+    }
+
+    fn highest_pts(&self) -> Option<Duration> {
+        let state = self.state.lock();
+        state
+            .tracks
+            .values()
+            .filter_map(|track| track.frames.keys().next_back().copied())
+            .max()
+    }
+
+    fn highest_end(&self) -> Option<Duration> {
+        let state = self.state.lock();
+        state
+            .tracks
+            .values()
+            .filter_map(|track| track.frames.values().next_back().map(CodedFrame::end))
+            .max()
+    }
+
+    /// Stop the running append or removal and reset the parser
+    fn stop_operation(&self, state: &mut SourceBufferState) {
+        if let Some(task) = state.task.take() {
+            task.abort();
+        }
+        state.operation = None;
+        state.generation += 1;
+        self.reset_parser(state);
+    }
+
+    /// Drop partially parsed data, keeping the tracks known
+    fn reset_parser(&self, state: &mut SourceBufferState) {
+        let mut parser = self.format.create_demuxer();
+        if !state.init_segment.is_empty() {
+            if let Err(e) = parser.feed(&state.init_segment) {
+                warn!("Failed to restore parser of {}: {}", self.id, e);
+            }
+            // Frames following the initialization segment were kept already
+            while let Ok(Some(_)) = parser.next_packet() {}
+        }
+        state.parser = parser;
+    }
+}
+
+impl SourceBufferState {
+    /// Parse appended bytes and add the coded frames they complete
+    fn append(&mut self, data: &[u8]) -> Result<(), MediaError> {
+        let had_tracks = self.parser.media_info().is_some();
+        self.parser.feed(data)?;
+        if !had_tracks {
+            self.init_segment.extend_from_slice(data);
+            if let Some(info) = self.parser.media_info() {
+                for video in &info.video_tracks {
+                    let track = self.tracks.entry(video.track_id).or_default();
+                    if video.frame_rate > 0.0 {
+                        track.default_duration =
+                            Some(Duration::from_secs_f64(1.0 / f64::from(video.frame_rate)));
+                    }
+                }
+                for audio in &info.audio_tracks {
+                    self.tracks.entry(audio.track_id).or_default();
+                }
+            }
+        }
+
+        let mut packets: BTreeMap<u32, Vec<(Duration, DemuxedPacket)>> = BTreeMap::new();
+        while let Some(packet) = self.parser.next_packet()? {
+            if let Some(pts) = packet.pts_time() {
+                packets
+                    .entry(packet.track_id)
+                    .or_default()
+                    .push((pts, packet));
+            }
+        }
+        for (track_id, frames) in packets {
+            if let Some(track) = self.tracks.get_mut(&track_id) {
+                track.add(frames);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the frames presented from `start` until `end`, and the frames
+    /// after them that depend on them
+    fn remove(&mut self, start: Duration, end: Duration) {
+        for track in self.tracks.values_mut() {
+            let removed: Vec<Duration> = track
+                .frames
+                .range(start..end)
+                .map(|(&pts, _)| pts)
+                .collect();
+            if removed.is_empty() {
+                continue;
+            }
+            for pts in removed {
+                track.frames.remove(&pts);
+            }
+
+            let dependent: Vec<Duration> = track
+                .frames
+                .range(end..)
+                .take_while(|(_, frame)| !frame.packet.is_keyframe())
+                .map(|(&pts, _)| pts)
+                .collect();
+            for pts in dependent {
+                track.frames.remove(&pts);
+            }
+        }
+    }
+}
+
+impl TrackBuffer {
+    /// Add frames appended together, replacing the frames they overlap
+    ///
+    /// Frame durations are the distance to the next frame of the append;
+    /// the last frame lasts as long as the last duration measured, or the
+    /// track's frame duration.
+    fn add(&mut self, mut frames: Vec<(Duration, DemuxedPacket)>) {
+        frames.sort_by_key(|(pts, _)| *pts);
+        let next_pts: Vec<Option<Duration>> = frames
+            .iter()
+            .skip(1)
+            .map(|(pts, _)| Some(*pts))
+            .chain(std::iter::once(None))
+            .collect();
+
+        for ((pts, packet), next) in frames.into_iter().zip(next_pts) {
+            let duration = match next {
+                Some(next) => {
+                    let duration = next - pts;
+                    self.last_frame_duration = Some(duration);
+                    duration
+                }
+                None => self
+                    .last_frame_duration
+                    .or(self.default_duration)
+                    .unwrap_or_default(),
+            };
+
+            let overlapped: Vec<Duration> = self
+                .frames
+                .range(pts..pts + duration.max(GAP_TOLERANCE))
+                .map(|(&pts, _)| pts)
+                .collect();
+            for pts in overlapped {
+                self.frames.remove(&pts);
+            }
+            self.frames.insert(
+                pts,
+                CodedFrame {
+                    packet,
+                    pts,
+                    duration,
+                },
+            );
+        }
+    }
+
+    /// Contiguous presentation ranges of the frames
+    fn ranges(&self) -> Vec<(Duration, Duration)> {
+        let mut ranges: Vec<(Duration, Duration)> = Vec::new();
+        for frame in self.frames.values() {
+            match ranges.last_mut() {
+                Some(last) if frame.pts <= last.1 + GAP_TOLERANCE => {
+                    last.1 = last.1.max(frame.end());
+                }
+                _ => ranges.push((frame.pts, frame.end())),
+            }
+        }
+        ranges
+    }
+
+    /// Frames playable from `position`: from the last keyframe at or before
+    /// it to the end of its buffered range
+    fn playable_from(&self, position: Duration) -> Vec<CodedFrame> {
+        let Some(start) = self
+            .frames
+            .range(..=position)
+            .rev()
+            .find(|(_, frame)| frame.packet.is_keyframe())
+            .map(|(&pts, _)| pts)
+        else {
+            return Vec::new();
+        };
+
+        let mut frames: Vec<CodedFrame> = Vec::new();
+        for frame in self.frames.range(start..).map(|(_, frame)| frame) {
+            if frames
+                .last()
+                .is_some_and(|last| frame.pts > last.end() + GAP_TOLERANCE)
+            {
+                break;
+            }
+            frames.push(frame.clone());
+        }
+        if frames.last().is_some_and(|last| last.end() < position) {
+            return Vec::new();
+        }
+        frames
+    }
+}
+
+impl SourceBufferHandle {
+    /// Get the ID of the source buffer
+    pub fn id(&self) -> &str {
+        &self.inner.id
+    }
+
+    /// Get the MIME type the source buffer was added for
+    pub fn mime_type(&self) -> &str {
+        &self.inner.mime_type
+    }
+
+    /// Whether an append or removal is in progress
+    pub fn updating(&self) -> bool {
+        self.inner.is_updating()
+    }
+
+    /// Get the buffered time ranges, in which every track has frames
+    pub fn buffered(&self) -> Vec<(Duration, Duration)> {
+        self.inner.buffered()
+    }
+
+    /// Append a segment of the byte stream
+    ///
+    /// The first data appended must be an initialization segment, after
+    /// which media segments can be appended in any order; data may also be
+    /// split across appends at any byte. The data is parsed on a Tokio
+    /// task: `UpdateStart` is emitted right away, and `Update` and
+    /// `UpdateEnd` once its coded frames are buffered. If the data cannot
+    /// be parsed, `Error` and `UpdateEnd` are emitted instead and the media
+    /// source ends with a decode error.
+    ///
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Arguments
+    /// * `data` - Bytes of the byte stream
+    ///
+    /// # Errors
+    /// * `InvalidState` - The buffer is updating or removed, or the media
+    ///   source is closed
+    pub fn append_buffer(&self, data: Vec<u8>) -> Result<(), MediaError> {
+        self.start(Operation::Append, move |state| state.append(&data))
+    }
+
+    /// Remove the coded frames presented from `start` until `end`
+    ///
+    /// Frames after `end` that depend on removed frames are removed too, up
+    /// to the next keyframe. Like appends, removals run on a Tokio task and
+    /// emit `UpdateStart`, `Update` and `UpdateEnd`.
+    ///
+    /// Must be called from within a Tokio runtime.
+    ///
+    /// # Errors
+    /// * `InvalidParameter` - `start` is after `end`
+    /// * `InvalidState` - The buffer is updating or removed, or the media
+    ///   source is closed
+    pub fn remove(&self, start: Duration, end: Duration) -> Result<(), MediaError> {
+        if start > end {
+            return Err(MediaError::InvalidParameter(format!(
+                "Removal start {:?} is after its end {:?}",
+                start, end
+            )));
+        }
+        self.start(Operation::Remove, move |state| {
+            state.remove(start, end);
+            Ok(())
+        })
+    }
+
+    /// Abort the current append and reset the parser
+    ///
+    /// Bytes of an incomplete segment are dropped, so the next append must
+    /// start at a segment boundary. An aborted append emits `Abort` and
+    /// `UpdateEnd`; frames it had already buffered are kept.
+    ///
+    /// # Errors
+    /// * `InvalidState` - The buffer is removed, the media source is not
+    ///   open, or a removal is in progress
+    pub fn abort(&self) -> Result<(), MediaError> {
+        if self.source.state.lock().ready_state != ReadyState::Open {
+            return Err(MediaError::InvalidState(
+                "Media source is not open".to_string(),
+            ));
+        }
+
+        let mut state = self.inner.state.lock();
+        if state.removed {
+            return Err(MediaError::InvalidState(
+                "Source buffer was removed".to_string(),
+            ));
+        }
+        match state.operation {
+            Some(Operation::Remove) => Err(MediaError::InvalidState(
+                "Cannot abort a removal".to_string(),
+            )),
+            Some(Operation::Append) => {
+                self.inner.stop_operation(&mut state);
+                drop(state);
+                self.source.emit(MseEvent::Abort {
+                    buffer_id: self.inner.id.clone(),
+                });
+                self.source.emit(MseEvent::UpdateEnd {
+                    buffer_id: self.inner.id.clone(),
+                });
+                Ok(())
+            }
+            None => {
+                self.inner.reset_parser(&mut state);
+                Ok(())
+            }
+        }
+    }
+
+    /// Get the coded frames to play from `position`
+    ///
+    /// Each track's frames start at its last keyframe at or before
+    /// `position` and run to the end of its buffered range, so a decoder
+    /// can decode them in turn. Frames of all tracks are returned in
+    /// decode order.
+    ///
+    /// # Returns
+    /// The frames, empty if `position` is not buffered
+    pub fn playable_frames(&self, position: Duration) -> Vec<CodedFrame> {
+        let state = self.inner.state.lock();
+        let mut frames: Vec<CodedFrame> = state
+            .tracks
+            .values()
+            .flat_map(|track| track.playable_from(position))
+            .collect();
+        frames.sort_by_key(|frame| frame.packet.dts_time().unwrap_or(frame.pts));
+        frames
+    }
+
+    /// Mark the buffer updating and run an operation on a task
+    fn start(
+        &self,
+        operation: Operation,
+        run: impl FnOnce(&mut SourceBufferState) -> Result<(), MediaError> + Send + 'static,
+    ) -> Result<(), MediaError> {
+        {
+            let state = self.inner.state.lock();
+            if state.removed {
+                return Err(MediaError::InvalidState(
+                    "Source buffer was removed".to_string(),
+                ));
+            }
+            if state.operation.is_some() {
+                return Err(MediaError::InvalidState(
+                    "Source buffer is updating".to_string(),
+                ));
+            }
+        }
+        self.source.reopen()?;
+
+        let mut state = self.inner.state.lock();
+        state.operation = Some(operation);
+        let generation = state.generation;
+        let buffer_id = self.inner.id.clone();
+        self.source.emit(MseEvent::UpdateStart {
+            buffer_id: buffer_id.clone(),
+        });
+
+        let source = Arc::clone(&self.source);
+        let inner = Arc::clone(&self.inner);
+        state.task = Some(tokio::spawn(async move {
+            let mut state = inner.state.lock();
+            if state.generation != generation {
+                return;
+            }
+            let result = run(&mut state);
+            state.operation = None;
+            state.task = None;
+            if result.is_err() {
+                inner.reset_parser(&mut state);
+            }
+            drop(state);
+
+            match result {
+                Ok(()) => {
+                    source.emit(MseEvent::Update {
+                        buffer_id: buffer_id.clone(),
+                    });
+                    source.emit(MseEvent::UpdateEnd { buffer_id });
+                }
+                Err(error) => {
+                    warn!("Append to {} failed: {}", buffer_id, error);
+                    source.emit(MseEvent::Error {
+                        buffer_id: buffer_id.clone(),
+                        error,
+                    });
+                    source.emit(MseEvent::UpdateEnd { buffer_id });
+                    let mut source_state = source.state.lock();
+                    if source_state.ready_state == ReadyState::Open {
+                        source.end(&mut source_state, Some(EndOfStreamError::Decode));
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+}
+
+/// Intersection of two sorted lists of disjoint time ranges
+fn intersect_ranges(
+    a: &[(Duration, Duration)],
+    b: &[(Duration, Duration)],
+) -> Vec<(Duration, Duration)> {
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start < end {
+            result.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: u64 = 1_000_000;
+    const VIDEO: u8 = 1;
+    const AUDIO: u8 = 2;
+    const OPUS_HEAD: &[u8] = b"OpusHead\x01\x02\x38\x01\x80\xbb\x00\x00\x00\x00\x00";
+
+    /// Encode an element with an 8-byte size field
+    fn element(id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut out: Vec<u8> = id
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|&b| b == 0)
+            .collect();
+        out.push(0x01);
+        out.extend_from_slice(&(payload.len() as u64).to_be_bytes()[1..]);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn uint(id: u32, value: u64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    fn float(id: u32, value: f64) -> Vec<u8> {
+        element(id, &value.to_be_bytes())
+    }
+
+    /// EBML header, unknown-size Segment, Info and Tracks
+    fn init_segment(with_audio: bool) -> Vec<u8> {
+        let video = [
+            uint(0xD7, VIDEO.into()),
+            uint(0x83, 1),
+            element(0x86, b"V_VP9"),
+            uint(0x23_E383, 40 * MS),
+            element(0xE0, &[uint(0xB0, 640), uint(0xBA, 360)].concat()),
+        ]
+        .concat();
+        let mut tracks = element(0xAE, &video);
+        if with_audio {
+            let audio = [
+                uint(0xD7, AUDIO.into()),
+                uint(0x83, 2),
+                element(0x86, b"A_OPUS"),
+                element(0x63A2, OPUS_HEAD),
+                element(0xE1, &[float(0xB5, 48000.0), uint(0x9F, 2)].concat()),
+            ]
+            .concat();
+            tracks.extend(element(0xAE, &audio));
+        }
+
+        [
+            element(0x1A45_DFA3, &element(0x4282, b"webm")),
+            vec![
+                0x18, 0x53, 0x80, 0x67, 0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+            ],
+            element(0x1549_A966, &uint(0x2A_D7B1, MS)),
+            element(0x1654_AE6B, &tracks),
+        ]
+        .concat()
+    }
+
+    /// Cluster with (track, time relative to the cluster, keyframe) blocks
+    fn cluster(time_ms: u64, blocks: &[(u8, i16, bool)]) -> Vec<u8> {
+        let mut payload = uint(0xE7, time_ms);
+        for &(track, relative, keyframe) in blocks {
+            let mut block = vec![0x80 | track];
+            block.extend_from_slice(&relative.to_be_bytes());
+            block.push(if keyframe { 0x80 } else { 0 });
+            block.extend_from_slice(&[0xAB; 16]);
+            payload.extend(element(0xA3, &block));
+        }
+        element(0x1F43_B675, &payload)
+    }
+
+    /// Three 40 ms video frames, the first a keyframe
+    fn video_cluster(time_ms: u64) -> Vec<u8> {
+        cluster(
+            time_ms,
+            &[(VIDEO, 0, true), (VIDEO, 40, false), (VIDEO, 80, false)],
+        )
+    }
+
+    fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let size = (payload.len() + 8) as u32;
+        [&size.to_be_bytes()[..], box_type, payload].concat()
+    }
+
+    /// `ftyp` and `moov` of a fragmented MP4 with one H.264 track
+    /// (timescale 1000)
+    fn mp4_init_segment() -> Vec<u8> {
+        let config = mp4::Mp4Config {
+            major_brand: str::parse("iso6").unwrap(),
+            minor_version: 0,
+            compatible_brands: vec![str::parse("iso6").unwrap(), str::parse("avc1").unwrap()],
+            timescale: 1000,
+        };
+        let mut writer =
+            mp4::Mp4Writer::write_start(std::io::Cursor::new(Vec::new()), &config).unwrap();
+        writer
+            .add_track(&mp4::TrackConfig {
+                track_type: mp4::TrackType::Video,
+                timescale: 1000,
+                language: "und".to_string(),
+                media_conf: mp4::MediaConfig::AvcConfig(mp4::AvcConfig {
+                    width: 640,
+                    height: 360,
+                    seq_param_set: vec![0x67, 0x64, 0x00, 0x1F, 0xAC],
+                    pic_param_set: vec![0x68, 0xEE, 0x3C, 0x80],
+                }),
+            })
+            .unwrap();
+        writer.write_end().unwrap();
+        let data = writer.into_writer().into_inner();
+
+        // Track 1 with sample description 1 and no other defaults
+        let trex = [
+            &[0; 4][..],
+            &1u32.to_be_bytes(),
+            &1u32.to_be_bytes(),
+            &[0; 12],
+        ]
+        .concat();
+        let mvex = mp4_box(b"mvex", &mp4_box(b"trex", &trex));
+        let mut out = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            let bytes = &data[pos..pos + size];
+            match &bytes[4..8] {
+                b"mdat" => {}
+                b"moov" => out.extend(mp4_box(b"moov", &[&bytes[8..], &mvex].concat())),
+                _ => out.extend_from_slice(bytes),
+            }
+            pos += size;
+        }
+        out
+    }
+
+    /// Movie fragment of three 40 ms H.264 frames, the first a keyframe
+    fn mp4_media_segment(time_ms: u64) -> Vec<u8> {
+        let tfhd = mp4_box(b"tfhd", &[0, 2, 0, 0, 0, 0, 0, 1]);
+        let tfdt = mp4_box(
+            b"tfdt",
+            &[&[1, 0, 0, 0][..], &time_ms.to_be_bytes()].concat(),
+        );
+        // Data offset, and duration, size and flags of every sample
+        let trun = |data_offset: u32| {
+            let mut trun = vec![0, 0, 0x07, 0x01, 0, 0, 0, 3];
+            trun.extend_from_slice(&data_offset.to_be_bytes());
+            for flags in [0x0200_0000u32, 0x0101_0000, 0x0101_0000] {
+                for value in [40, 16, flags] {
+                    trun.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            mp4_box(b"trun", &trun)
+        };
+        let moof = |data_offset: u32| {
+            let traf = mp4_box(
+                b"traf",
+                &[tfhd.clone(), tfdt.clone(), trun(data_offset)].concat(),
+            );
+            mp4_box(b"moof", &[mp4_box(b"mfhd", &[0; 8]), traf].concat())
+        };
+        let moof_len = moof(0).len() as u32;
+        [moof(moof_len + 8), mp4_box(b"mdat", &[0xAB; 48])].concat()
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    /// Wait for the end of the running append or removal, returning its
+    /// events
+    async fn update_end(events: &mut broadcast::Receiver<MseEvent>) -> Vec<MseEvent> {
+        let mut received = Vec::new();
+        loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+                .await
+                .expect("timed out waiting for updateend")
+                .unwrap();
+            let done = matches!(event, MseEvent::UpdateEnd { .. });
+            received.push(event);
+            if done {
+                return received;
+            }
+        }
+    }
+
+    async fn append(
+        buffer: &SourceBufferHandle,
+        events: &mut broadcast::Receiver<MseEvent>,
+        data: Vec<u8>,
+    ) -> Vec<MseEvent> {
+        buffer.append_buffer(data).unwrap();
+        assert!(buffer.updating());
+        let received = update_end(events).await;
+        assert!(!buffer.updating());
+        received
+    }
+
+    #[tokio::test]
+    async fn test_append_out_of_order_media_segments() {
+        let source = MediaSourceHandle::new();
+        let mut events = source.subscribe();
+        let buffer = source
+            .add_source_buffer("video/webm; codecs=\"vp9\"")
+            .unwrap();
+        let id = buffer.id().to_string();
+
+        let received = append(&buffer, &mut events, init_segment(false)).await;
+        assert_eq!(
+            received,
+            vec![
+                MseEvent::UpdateStart {
+                    buffer_id: id.clone()
+                },
+                MseEvent::Update {
+                    buffer_id: id.clone()
+                },
+                MseEvent::UpdateEnd { buffer_id: id },
+            ]
+        );
+        assert!(buffer.buffered().is_empty());
+
+        // The second segment arrives first
+        append(&buffer, &mut events, video_cluster(120)).await;
+        assert_eq!(buffer.buffered(), vec![(ms(120), ms(240))]);
+        assert!(buffer.playable_frames(ms(50)).is_empty());
+
+        append(&buffer, &mut events, video_cluster(0)).await;
+        assert_eq!(buffer.buffered(), vec![(ms(0), ms(240))]);
+
+        append(&buffer, &mut events, video_cluster(360)).await;
+        assert_eq!(
+            buffer.buffered(),
+            vec![(ms(0), ms(240)), (ms(360), ms(480))]
+        );
+        assert_eq!(source.buffered(), buffer.buffered());
+
+        // Playback from 50 ms decodes from the keyframe at 0 up to the gap
+        let frames = buffer.playable_frames(ms(50));
+        let times: Vec<u64> = frames.iter().map(|f| f.pts.as_millis() as u64).collect();
+        assert_eq!(times, vec![0, 40, 80, 120, 160, 200]);
+        assert!(frames[0].packet.is_keyframe());
+        assert!(frames[3].packet.is_keyframe());
+        assert!(buffer.playable_frames(ms(300)).is_empty());
+        assert_eq!(buffer.playable_frames(ms(400)).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_append_out_of_order_mp4_fragments() {
+        let source = MediaSourceHandle::new();
+        let mut events = source.subscribe();
+        let buffer = source
+            .add_source_buffer("video/mp4; codecs=\"avc1.64001f\"")
+            .unwrap();
+
+        let received = append(&buffer, &mut events, mp4_init_segment()).await;
+        assert!(matches!(received[1], MseEvent::Update { .. }));
+        assert!(buffer.buffered().is_empty());
+
+        // The second fragment arrives first
+        append(&buffer, &mut events, mp4_media_segment(120)).await;
+        assert_eq!(buffer.buffered(), vec![(ms(120), ms(240))]);
+        assert!(buffer.playable_frames(ms(50)).is_empty());
+
+        append(&buffer, &mut events, mp4_media_segment(0)).await;
+        assert_eq!(buffer.buffered(), vec![(ms(0), ms(240))]);
+
+        append(&buffer, &mut events, mp4_media_segment(360)).await;
+        assert_eq!(
+            buffer.buffered(),
+            vec![(ms(0), ms(240)), (ms(360), ms(480))]
+        );
+
+        // Playback from 50 ms decodes from the keyframe at 0 up to the gap
+        let frames = buffer.playable_frames(ms(50));
+        let times: Vec<u64> = frames.iter().map(|f| f.pts.as_millis() as u64).collect();
+        assert_eq!(times, vec![0, 40, 80, 120, 160, 200]);
+        assert!(frames[0].packet.is_keyframe());
+        assert!(!frames[1].packet.is_keyframe());
+        assert!(frames[3].packet.is_keyframe());
+        assert_eq!(buffer.playable_frames(ms(400)).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_buffered_intersects_tracks() {
+        let source = MediaSourceHandle::new();
+        let mut events = source.subscribe();
+        let buffer = source.add_source_buffer("video/webm").unwrap();
+
+        append(&buffer, &mut events, init_segment(true)).await;
+        // Video covers 0-120 ms, audio 0-80 ms
+        let segment = cluster(
+            0,
+            &[
+                (VIDEO, 0, true),
+                (AUDIO, 0, true),
+                (AUDIO, 20, true),
+                (VIDEO, 40, false),
+                (AUDIO, 40, true),
+                (AUDIO, 60, true),
+                (VIDEO, 80, false),
+            ],
+        );
+        append(&buffer, &mut events, segment).await;
+        assert_eq!(buffer.buffered(), vec![(ms(0), ms(80))]);
+
+        // Frames of both tracks in decode order
+        let frames = buffer.playable_frames(Duration::ZERO);
+        assert_eq!(frames.len(), 7);
+        assert!(frames.windows(2).all(|w| w[0].pts <= w[1].pts));
+    }
+
+    #[tokio::test]
+    async fn test_remove_drops_dependent_frames() {
+        let source = MediaSourceHandle::new();
+        let mut events = source.subscribe();
+        let buffer = source.add_source_buffer("video/webm").unwrap();
+        for data in [init_segment(false), video_cluster(0), video_cluster(120)] {
+            append(&buffer, &mut events, data).await;
+        }
+
+        assert!(buffer.remove(ms(80), ms(40)).is_err());
+        buffer.remove(ms(40), ms(80)).unwrap();
+        assert!(buffer.updating());
+        let received = update_end(&mut events).await;
+        assert!(matches!(received[1], MseEvent::Update { .. }));
+
+        // The frame at 80 ms depends on the removed one
+        assert_eq!(buffer.buffered(), vec![(ms(0), ms(40)), (ms(120), ms(240))]);
+    }
+
+    #[tokio::test]
+    async fn test_append_while_updating_and_abort() {
+        let source = MediaSourceHandle::new();
+        let mut events = source.subscribe();
+        let buffer = source.add_source_buffer("video/webm").unwrap();
+        append(&buffer, &mut events, init_segment(false)).await;
+
+        buffer.append_buffer(video_cluster(0)).unwrap();
+        assert!(matches!(
+            buffer.append_buffer(video_cluster(120)),
+            Err(MediaError::InvalidState(_))
+        ));
+        assert!(matches!(
+            buffer.remove(ms(0), ms(40)),
+            Err(MediaError::InvalidState(_))
+        ));
+
+        buffer.abort().unwrap();
+        assert!(!buffer.updating());
+        let received = update_end(&mut events).await;
+        assert!(matches!(received[0], MseEvent::UpdateStart { .. }));
+        assert!(matches!(received[1], MseEvent::Abort { .. }));
+        assert!(buffer.buffered().is_empty());
+
+        // Appending continues with the next segment
+        let received = append(&buffer, &mut events, video_cluster(120)).await;
+        assert!(matches!(received[1], MseEvent::Update { .. }));
+        assert_eq!(buffer.buffered(), vec![(ms(120), ms(240))]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_append_ends_with_decode_error() {
+        let source = MediaSourceHandle::new();
+        let mut events = source.subscribe();
+        let buffer = source.add_source_buffer("video/webm").unwrap();
+
+        let received = append(&buffer, &mut events, b"not a webm stream".to_vec()).await;
+        assert!(matches!(received[1], MseEvent::Error { .. }));
+        assert_eq!(events.recv().await.unwrap(), MseEvent::SourceEnded);
+        assert_eq!(source.ready_state(), ReadyState::Ended);
+        assert_eq!(source.end_of_stream_error(), Some(EndOfStreamError::Decode));
+    }
+
+    #[tokio::test]
+    async fn test_end_of_stream_and_reopen() {
+        let source = MediaSourceHandle::new();
+        let mut events = source.subscribe();
+        let buffer = source.add_source_buffer("video/webm").unwrap();
+        for data in [init_segment(false), video_cluster(0)] {
+            append(&buffer, &mut events, data).await;
+        }
+
+        buffer.append_buffer(video_cluster(120)).unwrap();
+        assert!(source.end_of_stream(None).is_err());
+        update_end(&mut events).await;
+
+        source.end_of_stream(None).unwrap();
+        assert_eq!(events.recv().await.unwrap(), MseEvent::SourceEnded);
+        assert_eq!(source.ready_state(), ReadyState::Ended);
+        assert_eq!(source.duration(), Some(ms(240)));
+        assert!(source.end_of_stream(None).is_err());
+
+        // Appending again reopens the media source
+        buffer.append_buffer(video_cluster(240)).unwrap();
+        assert_eq!(events.recv().await.unwrap(), MseEvent::SourceOpen);
+        assert_eq!(source.ready_state(), ReadyState::Open);
+        update_end(&mut events).await;
+        assert_eq!(buffer.buffered(), vec![(ms(0), ms(360))]);
+
+        source.close();
+        assert_eq!(source.ready_state(), ReadyState::Closed);
+        assert!(source.source_buffers().is_empty());
+        assert!(buffer.append_buffer(video_cluster(360)).is_err());
+    }
+
+    #[test]
+    fn test_add_source_buffer_types() {
+        let source = MediaSourceHandle::new();
+        assert!(matches!(
+            source.add_source_buffer("audio/ogg; codecs=\"opus\""),
+            Err(MediaError::UnsupportedFormat { .. })
+        ));
+        assert!(source.add_source_buffer("text/plain").is_err());
+
+        let video = source.add_source_buffer("video/webm").unwrap();
+        let audio = source.add_source_buffer("audio/webm").unwrap();
+        assert_ne!(video.id(), audio.id());
+        source
+            .add_source_buffer("video/mp4; codecs=\"avc1.64001f\"")
+            .unwrap();
+        source.add_source_buffer("audio/mp4").unwrap();
+        assert_eq!(source.source_buffers().len(), 4);
+
+        source.remove_source_buffer(&audio).unwrap();
+        assert!(source.remove_source_buffer(&audio).is_err());
+        assert_eq!(source.source_buffers().len(), 3);
+    }
+}
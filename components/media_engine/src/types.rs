@@ -1,11 +1,14 @@
 ///! Types for media engine configuration and messages
 use cortenbrowser_buffer_manager::BufferConfig;
+use cortenbrowser_media_capture::DeviceInfo;
 use cortenbrowser_media_pipeline::PipelineConfig;
 use cortenbrowser_media_session::SessionState;
+pub use cortenbrowser_shared_types::DecoderSelectionPolicy;
 use cortenbrowser_shared_types::{
     AudioBuffer, MediaChunk, MediaElementAttributes, MediaError, PlaybackCommand, SessionId,
     VideoFrame,
 };
+use std::time::Duration;
 
 /// Configuration for the Media Engine
 #[derive(Debug, Clone)]
@@ -18,6 +21,17 @@ pub struct MediaEngineConfig {
     pub buffer_config: BufferConfig,
     /// Pipeline configuration
     pub pipeline_config: PipelineConfig,
+    /// How long `get_video_frame` waits for a frame to be decoded
+    pub frame_timeout: Duration,
+    /// How far media must be buffered ahead of the playback position for
+    /// buffering to end
+    pub buffering_readahead: Duration,
+    /// How often `report_statistics` emits `PeriodicStats`; zero disables
+    /// it
+    pub stats_interval: Duration,
+    /// Whether sessions decode video in hardware or software, unless their
+    /// `MediaSessionConfig::decoder_policy` overrides it
+    pub decoder_selection_policy: DecoderSelectionPolicy,
 }
 
 impl Default for MediaEngineConfig {
@@ -27,10 +41,44 @@ impl Default for MediaEngineConfig {
             max_sessions: 10,
             buffer_config: BufferConfig::default(),
             pipeline_config: PipelineConfig::default(),
+            frame_timeout: Duration::from_millis(100),
+            buffering_readahead: Duration::from_secs(2),
+            stats_interval: Duration::from_secs(5),
+            decoder_selection_policy: DecoderSelectionPolicy::default(),
         }
     }
 }
 
+/// Statistics aggregated over the engine's sessions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaEngineStats {
+    /// Number of sessions
+    pub sessions_active: usize,
+    /// Video frames decoded
+    pub total_frames_decoded: u64,
+    /// Audio buffers decoded and queued for playout
+    pub total_audio_buffers_decoded: u64,
+    /// Bytes held by the buffer manager and by decoded media waiting in
+    /// the pipelines' queues
+    pub total_memory_bytes: usize,
+    /// Video packets that did not decode to a frame
+    pub decode_errors: u64,
+    /// Times video playback ran out of decoded frames
+    pub buffer_underruns: u64,
+    /// Average time taken to decode a video frame, in milliseconds
+    pub avg_frame_decode_ms: f64,
+    /// Video frames waiting in the pipelines' queues
+    pub video_queue_depth: usize,
+    /// Audio buffers waiting in the pipelines' queues
+    pub audio_queue_depth: usize,
+    /// Video frames dropped for running late since each pipeline's last
+    /// seek
+    pub dropped_frames: u64,
+    /// Average time taken to decode a video frame since each pipeline's
+    /// last seek, in milliseconds
+    pub avg_video_decode_latency_ms: f64,
+}
+
 /// Messages the Media Engine handles
 #[derive(Debug, Clone)]
 pub enum MediaEngineMessage {
@@ -55,6 +103,18 @@ pub enum MediaEngineMessage {
         /// Command to execute
         command: PlaybackCommand,
     },
+    /// Playback command for the session of a media element
+    ElementCommand {
+        /// Element ID
+        element_id: String,
+        /// Command to execute
+        command: PlaybackCommand,
+    },
+    /// Remove a media element, destroying its session and pipeline
+    RemoveMediaElement {
+        /// Element ID
+        element_id: String,
+    },
 }
 
 /// Events the Media Engine emits
@@ -88,4 +148,86 @@ pub enum MediaEngineEvent {
         /// Error details
         error: MediaError,
     },
+    /// Playback ran out of buffered media and waits for more
+    BufferingStarted {
+        /// Session ID
+        session_id: SessionId,
+        /// Playback position
+        position: Duration,
+    },
+    /// Enough media is buffered ahead for playback to continue
+    BufferingEnded {
+        /// Session ID
+        session_id: SessionId,
+        /// Playback position
+        position: Duration,
+    },
+    /// The duration of the media became known
+    DurationChanged {
+        /// Session ID
+        session_id: SessionId,
+        /// Media duration
+        duration: Duration,
+    },
+    /// The buffered media time ranges changed
+    BufferedRangesChanged {
+        /// Session ID
+        session_id: SessionId,
+        /// Contiguous buffered ranges as `(start, end)`, sorted by start
+        ranges: Vec<(Duration, Duration)>,
+    },
+    /// Media element was created
+    MediaElementCreated {
+        /// Element ID
+        element_id: String,
+        /// Session playing the element's media
+        session_id: SessionId,
+    },
+    /// Event of the session of a media element, emitted right after the
+    /// session event itself
+    MediaElementEvent {
+        /// Element ID
+        element_id: String,
+        /// The session event
+        event: Box<MediaEngineEvent>,
+    },
+    /// Media element was removed and its session destroyed
+    MediaElementRemoved {
+        /// Element ID
+        element_id: String,
+    },
+    /// Statistics sampled every `MediaEngineConfig::stats_interval`
+    PeriodicStats(MediaEngineStats),
+    /// Capture device was plugged in
+    CaptureDeviceAdded {
+        /// The new device
+        device: DeviceInfo,
+    },
+    /// Capture device was unplugged
+    CaptureDeviceRemoved {
+        /// ID of the removed device
+        device_id: String,
+    },
+    /// A session switched to another of its media's video tracks
+    VideoTrackChanged {
+        /// Session ID
+        session_id: SessionId,
+        /// Index of the new track in the media's video tracks
+        track_index: usize,
+    },
+    /// An adaptive stream switched to a representation of another bitrate
+    BitrateChanged {
+        /// Session ID
+        session_id: SessionId,
+        /// Bits per second of the new representation
+        new_bitrate: u32,
+    },
+    /// Decoding stopped at an encrypted packet whose key is not usable,
+    /// until the session's CDM has it
+    WaitingForKey {
+        /// Session ID
+        session_id: SessionId,
+    },
+    /// `MediaEngineImpl::shutdown` destroyed the last session
+    ShutdownComplete,
 }
@@ -1,6 +1,18 @@
 ///! Integration tests for media_engine component
-use cortenbrowser_media_engine::{MediaEngineConfig, MediaEngineImpl};
-use cortenbrowser_shared_types::{MediaEngine, MediaSessionConfig, MediaSource};
+use cortenbrowser_drm_support::{ContentDecryptionModule, SampleEncryption, Subsample};
+use cortenbrowser_format_parsers::{Demuxer, Mp4Demuxer};
+use cortenbrowser_media_engine::{
+    MediaEngineConfig, MediaEngineEvent, MediaEngineImpl, MediaEngineMessage, MemoryAudioSink,
+};
+use cortenbrowser_media_pipeline::TrackKind;
+use cortenbrowser_media_session::{SessionEvent, SessionState};
+use cortenbrowser_shared_types::{
+    AudioSink, CaptureDevice, CaptureDeviceType, LoopMode, MediaChunk, MediaConstraints,
+    MediaElementAttributes, MediaEngine, MediaError, MediaSample, MediaSessionConfig, MediaSource,
+    MediaStreamTrack, MediaStreamTrackKind, PixelFormat, PlaybackCommand, PlaybackStats,
+    PreloadStrategy, SessionId, VideoFrame,
+};
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Test basic engine creation and configuration
@@ -54,6 +66,12 @@ async fn test_playback_workflow() {
         .await
         .expect("Set volume should succeed");
 
+    // Set playback rate
+    engine
+        .set_rate(session, 1.5)
+        .await
+        .expect("Set rate should succeed");
+
     // Cleanup
     engine
         .destroy_session(session)
@@ -83,6 +101,12 @@ async fn test_concurrent_sessions() {
         .create_session(MediaSessionConfig::default())
         .await
         .unwrap();
+    for session in [session1, session2, session3] {
+        let source = MediaSource::Url {
+            url: "test.mp4".to_string(),
+        };
+        engine.load_source(session, source).await.unwrap();
+    }
 
     // All should be playable
     assert!(engine.play(session1).await.is_ok());
@@ -154,6 +178,25 @@ async fn test_state_transitions() {
         .await
         .unwrap();
 
+    let mut events = engine.subscribe_session_events(session).unwrap();
+
+    // Nothing plays before a source is loaded
+    assert!(matches!(
+        engine.play(session).await,
+        Err(MediaError::InvalidStateTransition { .. })
+    ));
+    assert!(matches!(
+        events.recv().await,
+        Ok(SessionEvent::Error(
+            MediaError::InvalidStateTransition { .. }
+        ))
+    ));
+
+    let source = MediaSource::Url {
+        url: "test.mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+
     // Play -> Pause -> Play should work
     assert!(engine.play(session).await.is_ok());
     assert!(engine.pause(session).await.is_ok());
@@ -162,5 +205,1211 @@ async fn test_state_transitions() {
     // Seek while playing
     assert!(engine.seek(session, Duration::from_secs(5)).await.is_ok());
 
+    // Subscribers saw every state the session went through
+    let mut states = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let SessionEvent::StateChanged { new, .. } = event {
+            states.push(new.state_name());
+        }
+    }
+    assert_eq!(
+        states,
+        ["Loading", "Ready", "Playing", "Paused", "Playing", "Seeking", "Playing"]
+    );
+
+    engine.destroy_session(session).await.unwrap();
+}
+
+/// Encode `count` 64x64 frames as H.264 and mux them into an MP4, with
+/// length-prefixed samples as MP4 stores them
+fn h264_mp4(count: usize) -> Vec<u8> {
+    h264_mp4_with_audio(count, 0)
+}
+
+/// Like `h264_mp4`, adding `audio_tracks` AAC tracks of one silent sample
+/// each, as tracks 2 onwards
+fn h264_mp4_with_audio(count: usize, audio_tracks: u32) -> Vec<u8> {
+    h264_mp4_with_tracks(count, 1, audio_tracks)
+}
+
+/// Like `h264_mp4_with_audio`, with `video_tracks` copies of the video as
+/// tracks 1 onwards, followed by the audio tracks
+fn h264_mp4_with_tracks(count: usize, video_tracks: u32, audio_tracks: u32) -> Vec<u8> {
+    build_h264_mp4(count, count, video_tracks, audio_tracks)
+}
+
+/// Like `h264_mp4`, with a keyframe every `interval` frames, so that seeks
+/// decode from near their target
+fn h264_mp4_with_keyframes(count: usize, interval: usize) -> Vec<u8> {
+    build_h264_mp4(count, interval, 1, 0)
+}
+
+fn build_h264_mp4(
+    count: usize,
+    keyframe_interval: usize,
+    video_tracks: u32,
+    audio_tracks: u32,
+) -> Vec<u8> {
+    let mut encoder = openh264::encoder::Encoder::new().unwrap();
+    let frame = openh264::formats::YUVBuffer::new(64, 64);
+    let samples: Vec<Vec<Vec<u8>>> = (0..count)
+        .map(|i| {
+            if i % keyframe_interval == 0 {
+                encoder.force_intra_frame();
+            }
+            nal_units(&encoder.encode(&frame).unwrap().to_vec())
+        })
+        .collect();
+
+    let find = |nal_type: u8| {
+        samples[0]
+            .iter()
+            .find(|nal| nal[0] & 0x1F == nal_type)
+            .cloned()
+            .unwrap()
+    };
+    let config = mp4::Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap(), str::parse("avc1").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer =
+        mp4::Mp4Writer::write_start(std::io::Cursor::new(Vec::new()), &config).unwrap();
+    for _ in 0..video_tracks {
+        writer
+            .add_track(&mp4::TrackConfig {
+                track_type: mp4::TrackType::Video,
+                timescale: 1000,
+                language: "und".to_string(),
+                media_conf: mp4::MediaConfig::AvcConfig(mp4::AvcConfig {
+                    width: 64,
+                    height: 64,
+                    seq_param_set: find(7),
+                    pic_param_set: find(8),
+                }),
+            })
+            .unwrap();
+    }
+    for _ in 0..audio_tracks {
+        writer
+            .add_track(&mp4::TrackConfig {
+                track_type: mp4::TrackType::Audio,
+                timescale: 48000,
+                language: "und".to_string(),
+                media_conf: mp4::MediaConfig::AacConfig(mp4::AacConfig::default()),
+            })
+            .unwrap();
+    }
+
+    for track_id in 1..=video_tracks {
+        for (i, nals) in samples.iter().enumerate() {
+            let bytes: Vec<u8> = nals
+                .iter()
+                .flat_map(|nal| [&(nal.len() as u32).to_be_bytes()[..], nal].concat())
+                .collect();
+            writer
+                .write_sample(
+                    track_id,
+                    &mp4::Mp4Sample {
+                        start_time: i as u64 * 40,
+                        duration: 40,
+                        rendering_offset: 0,
+                        is_sync: i % keyframe_interval == 0,
+                        bytes: mp4::Bytes::from(bytes),
+                    },
+                )
+                .unwrap();
+        }
+    }
+
+    for track_id in video_tracks + 1..=video_tracks + audio_tracks {
+        writer
+            .write_sample(
+                track_id,
+                &mp4::Mp4Sample {
+                    start_time: 0,
+                    duration: 1024,
+                    rendering_offset: 0,
+                    is_sync: true,
+                    bytes: mp4::Bytes::from(vec![
+                        0x21, 0x00, 0x49, 0x90, 0x02, 0x19, 0x00, 0x23, 0x80,
+                    ]),
+                },
+            )
+            .unwrap();
+    }
+
+    writer.write_end().unwrap();
+    writer.into_writer().into_inner()
+}
+
+/// Split Annex B data on its start codes
+fn nal_units(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut nals: Vec<Vec<u8>> = Vec::new();
+    let mut i = 0;
+    while i < data.len() {
+        if data[i..].starts_with(&[0, 0, 1]) {
+            nals.push(Vec::new());
+            i += 3;
+            continue;
+        }
+        if data[i..].starts_with(&[0, 0, 0, 1]) {
+            i += 1;
+            continue;
+        }
+        if let Some(nal) = nals.last_mut() {
+            nal.push(data[i]);
+        }
+        i += 1;
+    }
+    nals
+}
+
+/// Test that a buffer source is seekable over its whole duration
+#[tokio::test]
+async fn test_buffer_source_seekable_range() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4(3),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+
+    assert_eq!(
+        engine.get_seekable_range(session).await.unwrap(),
+        (Duration::ZERO, Duration::from_millis(120))
+    );
+    let buffered = engine.get_buffered_ranges(session).await.unwrap();
+    assert!(buffered
+        .iter()
+        .all(|&(start, end)| start < end && end <= Duration::from_millis(120)));
+
+    // Seeks past the end are clamped rather than failing
+    assert!(engine.seek(session, Duration::from_secs(5)).await.is_ok());
+}
+
+/// Test that a loaded MP4 buffer reports its duration and yields frames
+#[tokio::test]
+async fn test_load_mp4_buffer_decodes_frames() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4(3),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine
+        .load_source(session, source)
+        .await
+        .expect("MP4 buffer should load");
+
+    let ready = wait_for_event(&mut events, |event| {
+        matches!(
+            event,
+            MediaEngineEvent::PlaybackStateChanged {
+                state: SessionState::Ready { .. },
+                ..
+            }
+        )
+    })
+    .await;
+    match ready {
+        MediaEngineEvent::PlaybackStateChanged {
+            state: SessionState::Ready { duration, metadata },
+            ..
+        } => {
+            assert_eq!(duration, Duration::from_millis(120));
+            assert_eq!(metadata.video_track_count, 1);
+        }
+        other => panic!("Expected Ready state, got {:?}", other),
+    }
+
+    // Frames are decoded in the background
+    let mut frame = None;
+    for _ in 0..100 {
+        if let Ok(decoded) = engine.get_video_frame(session).await {
+            frame = Some(decoded);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let frame = frame.expect("A frame should be decoded");
+    assert_eq!((frame.width, frame.height), (64, 64));
+    assert_eq!(frame.timestamp, Duration::ZERO);
+}
+
+/// Test that a playing buffer source outputs decoded frames through the engine
+#[tokio::test]
+async fn test_play_buffer_outputs_video_frame() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig {
+        frame_timeout: Duration::from_secs(2),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4(3),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.play(session).await.unwrap();
+
+    // Waits for the background decoder
+    let frame = engine
+        .get_video_frame(session)
+        .await
+        .expect("A frame should be decoded");
+    assert_eq!((frame.width, frame.height), (64, 64));
+    // OpenH264 pads rows, as the frame's strides describe
+    assert!(frame.plane_stride(0) >= 64);
+    assert_eq!(frame.plane(2).len(), frame.plane_stride(2) * 32);
+
+    // The video track has no audio
+    assert!(matches!(
+        engine.get_audio_samples(session, 480).await,
+        Err(MediaError::WouldBlock(_))
+    ));
+}
+
+/// Test that the audio track of a buffer source is decoded for playout
+#[tokio::test]
+async fn test_load_mp4_buffer_decodes_audio() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig {
+        frame_timeout: Duration::from_secs(2),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4_with_audio(3, 1),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+
+    // Waits for the background decoder, with nothing pushed by hand
+    let samples = engine
+        .get_audio_samples(session, 480)
+        .await
+        .expect("Audio should be decoded");
+    assert_eq!(samples.sample_rate, 48000);
+    assert_eq!(samples.samples.len(), 480 * samples.channels as usize);
+    assert_eq!(samples.timestamp, Duration::ZERO);
+}
+
+/// Test that a playing buffer source plays its decoded audio into the sink
+#[tokio::test]
+async fn test_play_buffer_feeds_decoded_audio_to_sink() {
+    let sink = Arc::new(MemoryAudioSink::new());
+    let factory_sink = Arc::clone(&sink);
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default())
+        .unwrap()
+        .with_audio_sink_factory(move || factory_sink.clone());
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4_with_audio(3, 1),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.play(session).await.unwrap();
+
+    // Nothing is pushed by hand, so the audio comes from the decoder
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while sink.played_duration().is_zero() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("Decoded audio should play into the sink");
+    assert!(!sink.buffers()[0].samples.is_empty());
+}
+
+/// Test that a capture source plays the frames of its track as they arrive
+#[tokio::test]
+async fn test_play_capture_track_outputs_video_frame() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig {
+        frame_timeout: Duration::from_secs(2),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let (track, camera) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera".to_string());
+    let source = MediaSource::Capture {
+        device: CaptureDevice {
+            id: "/dev/video0".to_string(),
+            name: "Camera".to_string(),
+            device_type: CaptureDeviceType::Camera,
+        },
+        constraints: MediaConstraints::default(),
+        track: Some(track.clone()),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.play(session).await.unwrap();
+
+    // Captured frames are timestamped as they arrive
+    let frame = VideoFrame::new(
+        4,
+        4,
+        PixelFormat::RGBA32,
+        vec![255; 64],
+        Duration::from_secs(60),
+    );
+    assert!(camera.send(MediaSample::Video(frame)));
+    let frame = engine
+        .get_video_frame(session)
+        .await
+        .expect("The captured frame should be played");
+    assert_eq!((frame.width, frame.height), (4, 4));
+    assert!(frame.timestamp < Duration::from_secs(1));
+
     engine.destroy_session(session).await.unwrap();
 }
+
+/// Test that a session with video disabled plays no video
+#[tokio::test]
+async fn test_audio_only_session_outputs_no_video() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig {
+        frame_timeout: Duration::from_millis(200),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::new().with_video(false))
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4_with_audio(3, 1),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.play(session).await.unwrap();
+
+    assert!(matches!(
+        engine.get_video_frame(session).await,
+        Err(MediaError::InvalidState(_))
+    ));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let stats = engine.get_playback_stats(session).await.unwrap();
+    assert_eq!(stats.frames_decoded, 0);
+}
+
+/// Test that disabling both tracks is rejected
+#[tokio::test]
+async fn test_session_without_tracks_is_rejected() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+
+    let config = MediaSessionConfig::new()
+        .with_video(false)
+        .with_audio(false);
+    assert!(matches!(
+        engine.create_session(config).await,
+        Err(MediaError::InvalidParameter(_))
+    ));
+    assert_eq!(engine.statistics().sessions_active, 0);
+}
+
+/// Test that a buffer preloading nothing is only parsed once played
+#[tokio::test]
+async fn test_preload_none_parses_buffer_on_play() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::new().with_preload(PreloadStrategy::None))
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4(3),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(event, MediaEngineEvent::DurationChanged { .. }),
+            "Nothing should be parsed before playing"
+        );
+    }
+
+    engine.play(session).await.unwrap();
+    let mut duration = None;
+    while let Ok(event) = events.try_recv() {
+        if let MediaEngineEvent::DurationChanged { duration: d, .. } = event {
+            duration = Some(d);
+        }
+    }
+    assert_eq!(duration, Some(Duration::from_millis(120)));
+}
+
+/// Test that a suspended session resumes where it was, also in a new engine
+#[tokio::test]
+async fn test_suspend_and_resume_session() {
+    let config = MediaEngineConfig {
+        frame_timeout: Duration::from_secs(2),
+        ..Default::default()
+    };
+    let engine = MediaEngineImpl::new(config.clone()).unwrap();
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4_with_keyframes(100, 25),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.set_volume(session, 0.5).await.unwrap();
+    engine.play(session).await.unwrap();
+    engine.seek(session, Duration::from_secs(3)).await.unwrap();
+    engine.get_video_frame(session).await.unwrap();
+
+    let snapshot = engine.suspend_session(session).await.unwrap();
+    assert_eq!(snapshot.session_id, session);
+    assert!(snapshot.position >= Duration::from_secs(3));
+    assert_eq!(snapshot.volume, 0.5);
+    assert!(snapshot.source.is_some());
+    // The session stays, without its pipeline's decoders
+    let stats = engine.get_playback_stats(session).await.unwrap();
+    assert_eq!(stats.frames_decoded, 0);
+
+    // Resumed in place, the first frame is from where playback was
+    assert_eq!(engine.resume_session(&snapshot).await.unwrap(), session);
+    engine.play(session).await.unwrap();
+    let frame = engine.get_video_frame(session).await.unwrap();
+    assert!(frame.timestamp >= Duration::from_secs(3));
+
+    // Persisted and restored into another engine, as after a restart
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let snapshot = serde_json::from_str(&json).unwrap();
+    let engine = MediaEngineImpl::new(config).unwrap();
+    let restored = engine.resume_session(&snapshot).await.unwrap();
+    assert_ne!(restored, session);
+    engine.play(restored).await.unwrap();
+    let frame = engine.get_video_frame(restored).await.unwrap();
+    assert!(frame.timestamp >= Duration::from_secs(3));
+}
+
+/// Test that the engine statistics count frames decoded for a playing session
+#[tokio::test]
+async fn test_statistics_count_decoded_frames() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4(3),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.play(session).await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let stats = engine.statistics();
+    assert_eq!(stats.sessions_active, 1);
+    assert!(stats.total_frames_decoded > 0);
+    assert_eq!(stats.decode_errors, 0);
+    assert!(stats.avg_video_decode_latency_ms > 0.0);
+    assert!(stats.video_queue_depth <= 3);
+}
+
+/// Test that playback statistics of a playing session only grow and are
+/// plausible
+#[tokio::test]
+async fn test_playback_stats_grow_while_playing() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig {
+        frame_timeout: Duration::from_secs(2),
+        ..Default::default()
+    })
+    .unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        engine.get_playback_stats(session).await.unwrap(),
+        PlaybackStats::default()
+    );
+    let source = MediaSource::Buffer {
+        data: h264_mp4(25),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.play(session).await.unwrap();
+
+    engine.get_video_frame(session).await.unwrap();
+    let first = engine.get_playback_stats(session).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    for _ in 0..20 {
+        if engine.get_video_frame(session).await.is_ok() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let second = engine.get_playback_stats(session).await.unwrap();
+
+    assert!(first.frames_decoded > 0);
+    assert!(second.frames_decoded >= first.frames_decoded);
+    assert!(first.frames_displayed >= 1);
+    assert!(second.frames_displayed > first.frames_displayed);
+    assert!(second.frames_dropped >= first.frames_dropped);
+    assert_eq!(second.decode_errors, 0);
+    assert!(second.decode_time_p50_ms > 0.0);
+    assert!(second.decode_time_p50_ms <= second.decode_time_p90_ms);
+    assert!(second.decode_time_p90_ms <= second.decode_time_p99_ms);
+    assert!(second.video_bitrate > 0);
+    assert!(second.video_queue_depth <= 25);
+
+    assert!(matches!(
+        engine.get_playback_stats(SessionId::new()).await,
+        Err(MediaError::SessionNotFound(_))
+    ));
+}
+
+/// Test listing the tracks of a dubbed MP4 and switching its audio track
+#[tokio::test]
+async fn test_select_audio_track_of_multi_track_mp4() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    assert!(matches!(
+        engine.available_tracks(session),
+        Err(MediaError::InvalidState(_))
+    ));
+    let source = MediaSource::Buffer {
+        data: h264_mp4_with_audio(3, 2),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+
+    let tracks = engine.available_tracks(session).unwrap();
+    let listed: Vec<_> = tracks
+        .iter()
+        .map(|track| (track.track_id, track.kind, track.selected))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            (1, TrackKind::Video, true),
+            (2, TrackKind::Audio, true),
+            (3, TrackKind::Audio, false),
+        ]
+    );
+
+    engine.play(session).await.unwrap();
+    engine.select_track(session, 3).unwrap();
+    let selected: Vec<_> = engine
+        .available_tracks(session)
+        .unwrap()
+        .into_iter()
+        .filter(|track| track.selected)
+        .map(|track| track.track_id)
+        .collect();
+    assert_eq!(selected, [1, 3]);
+
+    // Video keeps playing from the same decoder
+    assert!(engine.get_video_frame(session).await.is_ok());
+
+    assert!(matches!(
+        engine.select_track(session, 7),
+        Err(MediaError::InvalidParameter(_))
+    ));
+}
+
+/// Test switching the camera angle of a multi-angle MP4 while its audio
+/// plays on
+#[tokio::test]
+async fn test_select_video_track_of_multi_angle_mp4() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig {
+        frame_timeout: Duration::from_secs(2),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4_with_tracks(10, 2, 1),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.play(session).await.unwrap();
+    assert!(engine.get_video_frame(session).await.is_ok());
+
+    engine.select_video_track(session, 1).unwrap();
+    let selected: Vec<_> = engine
+        .available_tracks(session)
+        .unwrap()
+        .into_iter()
+        .filter(|track| track.selected)
+        .map(|track| track.track_id)
+        .collect();
+    assert_eq!(selected, [2, 3]);
+    assert!(engine.get_video_frame(session).await.is_ok());
+
+    let mut changed = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let MediaEngineEvent::VideoTrackChanged {
+            session_id,
+            track_index,
+        } = event
+        {
+            assert_eq!(session_id, session);
+            changed.push(track_index);
+        }
+    }
+    assert_eq!(changed, [1]);
+
+    assert!(matches!(
+        engine.select_video_track(session, 2),
+        Err(MediaError::InvalidParameter(_))
+    ));
+}
+
+/// Test that an MP4 streamed in 4KB chunks becomes ready and yields frames
+#[tokio::test]
+async fn test_stream_mp4_chunks_decodes_frames() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let (_chunk_tx, chunk_rx) = tokio::sync::mpsc::channel(1);
+    let source = MediaSource::Stream {
+        receiver: Arc::new(chunk_rx),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine
+        .load_source(session, source)
+        .await
+        .expect("MP4 stream should load");
+
+    let data = h264_mp4(3);
+    let chunks: Vec<_> = data.chunks(4096).collect();
+    for (sequence, chunk) in chunks.iter().enumerate() {
+        let chunk = MediaChunk {
+            data: chunk.to_vec(),
+            sequence: sequence as u64,
+            is_final: sequence == chunks.len() - 1,
+        };
+        engine
+            .feed_stream(session, &chunk)
+            .expect("Chunk should be accepted");
+    }
+
+    let ready = wait_for_event(&mut events, |event| {
+        matches!(
+            event,
+            MediaEngineEvent::PlaybackStateChanged {
+                state: SessionState::Ready { .. },
+                ..
+            }
+        )
+    })
+    .await;
+    match ready {
+        MediaEngineEvent::PlaybackStateChanged {
+            state: SessionState::Ready { duration, metadata },
+            ..
+        } => {
+            assert_eq!(duration, Duration::from_millis(120));
+            assert_eq!(metadata.video_track_count, 1);
+        }
+        other => panic!("Expected Ready state, got {:?}", other),
+    }
+
+    let mut frame = None;
+    for _ in 0..100 {
+        if let Ok(decoded) = engine.get_video_frame(session).await {
+            frame = Some(decoded);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let frame = frame.expect("A frame should be decoded");
+    assert_eq!((frame.width, frame.height), (64, 64));
+    assert_eq!(frame.timestamp, Duration::ZERO);
+}
+
+/// Test that stream data is rejected before a source is loaded
+#[tokio::test]
+async fn test_stream_data_requires_source() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+
+    let chunk = MediaChunk {
+        data: vec![0; 16],
+        sequence: 0,
+        is_final: false,
+    };
+    assert!(engine.feed_stream(session, &chunk).is_err());
+}
+
+/// Play a session, consuming its frames, until it ends or loops `loops` times
+///
+/// Returns the state changes seen and the timestamps of the frames played.
+async fn play_through(
+    engine: &MediaEngineImpl,
+    events: &mut tokio::sync::mpsc::UnboundedReceiver<MediaEngineEvent>,
+    session: SessionId,
+    loops: usize,
+) -> (Vec<SessionState>, Vec<Duration>) {
+    engine.play(session).await.unwrap();
+
+    let mut states = Vec::new();
+    let mut timestamps = Vec::new();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while tokio::time::Instant::now() < deadline {
+        if let Ok(frame) = engine.get_video_frame(session).await {
+            timestamps.push(frame.timestamp);
+        }
+        while let Ok(event) = events.try_recv() {
+            if let MediaEngineEvent::PlaybackStateChanged { state, .. } = event {
+                states.push(state);
+            }
+        }
+        let looped = states
+            .iter()
+            .filter(|state| matches!(state, SessionState::Looping { .. }))
+            .count();
+        if looped >= loops || states.contains(&SessionState::Ended) {
+            break;
+        }
+    }
+    (states, timestamps)
+}
+
+/// Test that a buffer source played to its end ends the session
+#[tokio::test]
+async fn test_buffer_source_plays_to_end() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4(5),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+
+    let (states, timestamps) = play_through(&engine, &mut events, session, usize::MAX).await;
+
+    assert_eq!(states.last(), Some(&SessionState::Ended));
+    assert!(!timestamps.is_empty());
+    assert!(timestamps.windows(2).all(|pair| pair[0] <= pair[1]));
+}
+
+/// Test that a looping buffer source starts over instead of ending
+#[tokio::test]
+async fn test_looping_buffer_source_starts_over() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let source = MediaSource::Buffer {
+        data: h264_mp4(5),
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+    engine.set_loop(session, LoopMode::All).await.unwrap();
+
+    let (states, timestamps) = play_through(&engine, &mut events, session, 2).await;
+
+    assert!(!states.contains(&SessionState::Ended));
+    let loops: Vec<_> = states
+        .iter()
+        .filter_map(|state| match state {
+            SessionState::Looping { iteration } => Some(*iteration),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(loops, [1, 2]);
+    // Timestamps only go back where the media starts over
+    let restarts = timestamps
+        .windows(2)
+        .filter(|pair| pair[1] < pair[0])
+        .count();
+    assert!(restarts <= loops.len());
+}
+
+/// ClearKey license for key ID 0x10.. with key 0x00 0x01 .. 0x0F
+const CLEARKEY_LICENSE: &[u8] =
+    br#"{"keys":[{"kty":"oct","kid":"EBAQEBAQEBAQEBAQEBAQEA","k":"AAECAwQFBgcICQoLDA0ODw"}]}"#;
+
+fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut data = ((8 + payload.len()) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(box_type);
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Split boxes into (type, header size, bytes)
+fn boxes(data: &[u8]) -> Vec<([u8; 4], usize, &[u8])> {
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let (size, header) = match u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) {
+            1 => (
+                u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap()) as usize,
+                16,
+            ),
+            size => (size as usize, 8),
+        };
+        let box_type = data[pos + 4..pos + 8].try_into().unwrap();
+        boxes.push((box_type, header, &data[pos..pos + size]));
+        pos += size;
+    }
+    boxes
+}
+
+/// Apply `edit` to the payload of every box at `path` below `data`
+fn edit_boxes(data: &[u8], path: &[&[u8; 4]], edit: &dyn Fn(&[u8]) -> Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (box_type, header, bytes) in boxes(data) {
+        if &box_type != path[0] {
+            out.extend_from_slice(bytes);
+        } else if path.len() == 1 {
+            out.extend(mp4_box(&box_type, &edit(&bytes[header..])));
+        } else {
+            let children = edit_boxes(&bytes[header..], &path[1..], edit);
+            out.extend(mp4_box(&box_type, &children));
+        }
+    }
+    out
+}
+
+/// `h264_mp4` encrypted with the ClearKey license's key as `cenc`
+///
+/// Each NAL unit's length prefix and header stay clear. Samples are
+/// encrypted with `cdm`, which holds the key, since AES-CTR encryption is
+/// the same operation as decryption.
+fn encrypted_h264_mp4(count: usize, cdm: &ContentDecryptionModule) -> Vec<u8> {
+    let mut data = h264_mp4(count);
+    // The samples of the only track follow each other in mdat
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&data).unwrap();
+    let samples: Vec<Vec<u8>> = std::iter::from_fn(|| demuxer.read_packet().unwrap())
+        .map(|packet| packet.data().to_vec())
+        .collect();
+    let mut senc = vec![0, 0, 0, 2];
+    senc.extend_from_slice(&(count as u32).to_be_bytes());
+    let mut offset = data
+        .windows(samples[0].len())
+        .position(|window| window == samples[0])
+        .unwrap();
+    for (i, sample) in samples.iter().enumerate() {
+        let end = offset + sample.len();
+        let mut subsamples = Vec::new();
+        let mut nal = offset;
+        while nal < end {
+            let length = u32::from_be_bytes(data[nal..nal + 4].try_into().unwrap());
+            subsamples.push(Subsample {
+                clear_bytes: 5,
+                encrypted_bytes: length - 1,
+            });
+            nal += 4 + length as usize;
+        }
+        let iv = vec![i as u8 + 1; 8];
+        let sample = SampleEncryption {
+            iv: iv.clone(),
+            subsamples: subsamples.clone(),
+            ..Default::default()
+        };
+        let encrypted = cdm
+            .decrypt_sample(&data[offset..end], &[0x10; 16], &sample)
+            .unwrap();
+        data[offset..end].copy_from_slice(&encrypted);
+
+        senc.extend_from_slice(&iv);
+        senc.extend_from_slice(&(subsamples.len() as u16).to_be_bytes());
+        for subsample in subsamples {
+            senc.extend_from_slice(&(subsample.clear_bytes as u16).to_be_bytes());
+            senc.extend_from_slice(&subsample.encrypted_bytes.to_be_bytes());
+        }
+        offset = end;
+    }
+
+    let mut tenc = vec![0, 0, 0, 0, 0, 0, 1, 8];
+    tenc.extend_from_slice(&[0x10; 16]);
+    let sinf = mp4_box(
+        b"sinf",
+        &[
+            mp4_box(b"frma", b"avc1"),
+            mp4_box(
+                b"schm",
+                &[&[0, 0, 0, 0][..], b"cenc", &[0, 1, 0, 0]].concat(),
+            ),
+            mp4_box(b"schi", &mp4_box(b"tenc", &tenc)),
+        ]
+        .concat(),
+    );
+    edit_boxes(
+        &data,
+        &[b"moov", b"trak", b"mdia", b"minf", b"stbl"],
+        &|stbl| {
+            let children = edit_boxes(stbl, &[b"stsd"], &|stsd| {
+                let entry = [&stsd[16..], &sinf[..]].concat();
+                [&stsd[..8], &mp4_box(b"encv", &entry)[..]].concat()
+            });
+            [children, mp4_box(b"senc", &senc)].concat()
+        },
+    )
+}
+
+/// Test that an encrypted MP4 waits for its key, then decrypts and decodes
+/// once the ClearKey license is installed
+#[tokio::test]
+async fn test_encrypted_mp4_decodes_after_clearkey_license() {
+    let packager = ContentDecryptionModule::new("org.w3.clearkey".to_string()).unwrap();
+    let packager_session = packager.create_session().await.unwrap();
+    packager
+        .update(&packager_session, CLEARKEY_LICENSE)
+        .await
+        .unwrap();
+    let data = encrypted_h264_mp4(3, &packager);
+
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+    let session = engine
+        .create_session(MediaSessionConfig::default())
+        .await
+        .unwrap();
+    let cdm = Arc::new(ContentDecryptionModule::new("org.w3.clearkey".to_string()).unwrap());
+    let drm_session = cdm.create_session().await.unwrap();
+    engine.attach_cdm(session, Arc::clone(&cdm)).unwrap();
+
+    let source = MediaSource::Buffer {
+        data,
+        mime_type: "video/mp4".to_string(),
+    };
+    engine.load_source(session, source).await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), async {
+        loop {
+            if let Some(MediaEngineEvent::WaitingForKey { session_id }) = events.recv().await {
+                assert_eq!(session_id, session);
+                break;
+            }
+        }
+    })
+    .await
+    .expect("Decoding should wait for the key");
+    assert!(engine.get_video_frame(session).await.is_err());
+
+    cdm.update(&drm_session, CLEARKEY_LICENSE).await.unwrap();
+
+    let mut frame = None;
+    for _ in 0..100 {
+        if let Ok(decoded) = engine.get_video_frame(session).await {
+            frame = Some(decoded);
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    let frame = frame.expect("A frame should be decoded");
+    assert_eq!((frame.width, frame.height), (64, 64));
+    assert_eq!(frame.timestamp, Duration::ZERO);
+}
+
+/// Receive events until one matches, failing after a timeout
+async fn wait_for_event(
+    events: &mut tokio::sync::mpsc::UnboundedReceiver<MediaEngineEvent>,
+    mut matches: impl FnMut(&MediaEngineEvent) -> bool,
+) -> MediaEngineEvent {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            let event = events.recv().await.expect("event channel closed");
+            if matches(&event) {
+                return event;
+            }
+        }
+    })
+    .await
+    .expect("timed out waiting for event")
+}
+
+/// Whether an event is a media element's session changing to a state
+fn is_element_state(event: &MediaEngineEvent, id: &str, state_name: &str) -> bool {
+    matches!(
+        event,
+        MediaEngineEvent::MediaElementEvent { element_id, event }
+            if element_id == id && matches!(
+                event.as_ref(),
+                MediaEngineEvent::PlaybackStateChanged { state, .. }
+                    if state.state_name() == state_name
+            )
+    )
+}
+
+/// Test a media element's lifecycle driven only through the message channel
+#[tokio::test]
+async fn test_media_element_messages() {
+    let engine = MediaEngineImpl::new(MediaEngineConfig::default()).unwrap();
+    let mut events = engine.take_event_receiver().unwrap();
+    let messages = engine.message_sender();
+
+    // Autoplaying element with a source
+    messages
+        .send(MediaEngineMessage::CreateMediaElement {
+            element_id: "video-1".to_string(),
+            attributes: MediaElementAttributes {
+                autoplay: true,
+                muted: true,
+                playback_rate: 1.5,
+                src: Some("test.mp4".to_string()),
+                ..Default::default()
+            },
+        })
+        .unwrap();
+    let session = match wait_for_event(&mut events, |event| {
+        matches!(event, MediaEngineEvent::MediaElementCreated { .. })
+    })
+    .await
+    {
+        MediaEngineEvent::MediaElementCreated {
+            element_id,
+            session_id,
+        } => {
+            assert_eq!(element_id, "video-1");
+            session_id
+        }
+        _ => unreachable!(),
+    };
+    assert_eq!(engine.element_session("video-1"), Some(session));
+
+    // The session plays at the element's rate, reported for the element
+    let event = wait_for_event(&mut events, |event| {
+        is_element_state(event, "video-1", "Playing")
+    })
+    .await;
+    let MediaEngineEvent::MediaElementEvent { event, .. } = event else {
+        unreachable!()
+    };
+    assert!(matches!(
+        *event,
+        MediaEngineEvent::PlaybackStateChanged {
+            session_id,
+            state: SessionState::Playing { rate, .. },
+        } if session_id == session && rate == 1.5
+    ));
+
+    // A second element without a source stays idle
+    messages
+        .send(MediaEngineMessage::CreateMediaElement {
+            element_id: "audio-1".to_string(),
+            attributes: MediaElementAttributes::default(),
+        })
+        .unwrap();
+    wait_for_event(&mut events, |event| {
+        matches!(event, MediaEngineEvent::MediaElementCreated { element_id, .. } if element_id == "audio-1")
+    })
+    .await;
+    assert_ne!(engine.element_session("audio-1"), Some(session));
+
+    // Commands addressed by element ID reach the element's session
+    messages
+        .send(MediaEngineMessage::ElementCommand {
+            element_id: "video-1".to_string(),
+            command: PlaybackCommand::Pause,
+        })
+        .unwrap();
+    wait_for_event(&mut events, |event| {
+        is_element_state(event, "video-1", "Paused")
+    })
+    .await;
+
+    // Duplicate element IDs are rejected without replacing the element
+    messages
+        .send(MediaEngineMessage::CreateMediaElement {
+            element_id: "video-1".to_string(),
+            attributes: MediaElementAttributes::default(),
+        })
+        .unwrap();
+
+    // Removing the element destroys its session
+    messages
+        .send(MediaEngineMessage::RemoveMediaElement {
+            element_id: "video-1".to_string(),
+        })
+        .unwrap();
+    wait_for_event(&mut events, |event| {
+        matches!(event, MediaEngineEvent::MediaElementRemoved { element_id } if element_id == "video-1")
+    })
+    .await;
+    assert_eq!(engine.element_session("video-1"), None);
+    assert!(engine.element_session("audio-1").is_some());
+    assert_eq!(engine.statistics().sessions_active, 1);
+
+    // Commands for the removed element fail without reaching any session
+    messages
+        .send(MediaEngineMessage::ElementCommand {
+            element_id: "video-1".to_string(),
+            command: PlaybackCommand::Play,
+        })
+        .unwrap();
+    messages
+        .send(MediaEngineMessage::ElementCommand {
+            element_id: "audio-1".to_string(),
+            command: PlaybackCommand::Play,
+        })
+        .unwrap();
+    // The element without a source cannot play
+    let event = wait_for_event(&mut events, |event| {
+        matches!(
+            event,
+            MediaEngineEvent::PlaybackStateChanged { .. } | MediaEngineEvent::MediaError { .. }
+        )
+    })
+    .await;
+    assert!(matches!(
+        event,
+        MediaEngineEvent::MediaError {
+            session_id,
+            error: MediaError::InvalidStateTransition { .. },
+        } if Some(session_id) == engine.element_session("audio-1")
+    ));
+}
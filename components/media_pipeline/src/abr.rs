@@ -0,0 +1,238 @@
+//! Adaptive bitrate switching
+//!
+//! Adaptive streams such as DASH and HLS offer the same media in several
+//! representations of different bitrates. The [`AbrController`] picks one
+//! by how much media is buffered ahead of playback: a buffer filling up
+//! beyond the up threshold means the network keeps up with a higher
+//! bitrate, one draining below the down threshold that it cannot keep up
+//! with the current one. Switches go one representation at a time, and at
+//! most once per switch interval so that a fluctuating buffer does not
+//! flap between two of them.
+
+use cortenbrowser_shared_types::MediaError;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// One encoding of an adaptive stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Representation {
+    /// ID of the representation in its manifest
+    pub id: String,
+    /// Bits per second the representation needs
+    pub bandwidth: u32,
+    /// URL its segments are read from
+    pub url: String,
+}
+
+/// Thresholds for switching representations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbrConfig {
+    /// Buffer level above which a higher bitrate is selected
+    pub up_threshold: Duration,
+    /// Buffer level below which a lower bitrate is selected
+    pub down_threshold: Duration,
+    /// Shortest time between two switches
+    pub switch_interval: Duration,
+}
+
+impl Default for AbrConfig {
+    fn default() -> Self {
+        Self {
+            up_threshold: Duration::from_secs(10),
+            down_threshold: Duration::from_secs(4),
+            switch_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// A representation switch decided by the [`AbrController`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbrEvent {
+    /// Switched to the higher bitrate representation with this ID
+    SwitchUp(String),
+    /// Switched to the lower bitrate representation with this ID
+    SwitchDown(String),
+}
+
+/// Chooses the representation of an adaptive stream by buffer level
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_pipeline::{AbrConfig, AbrController, AbrEvent, Representation};
+/// use std::time::Duration;
+/// use tokio::time::Instant;
+///
+/// let representation = |id: &str, bandwidth| Representation {
+///     id: id.to_string(),
+///     bandwidth,
+///     url: format!("https://example.com/{}.mp4", id),
+/// };
+/// let mut abr = AbrController::new(
+///     vec![representation("480p", 1_000_000), representation("1080p", 5_000_000)],
+///     AbrConfig::default(),
+/// )
+/// .unwrap();
+/// assert_eq!(abr.current().id, "480p");
+///
+/// let event = abr.update(Duration::from_secs(20), Instant::now());
+/// assert_eq!(event, Some(AbrEvent::SwitchUp("1080p".to_string())));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AbrController {
+    /// Representations, sorted by bandwidth
+    representations: Vec<Representation>,
+    /// Index of the selected representation
+    current: usize,
+    config: AbrConfig,
+    /// When the last switch happened
+    last_switch: Option<Instant>,
+}
+
+impl AbrController {
+    /// Creates a controller starting at the lowest bitrate representation
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if there are no representations, or the
+    /// down threshold is above the up threshold.
+    pub fn new(
+        mut representations: Vec<Representation>,
+        config: AbrConfig,
+    ) -> Result<Self, MediaError> {
+        if representations.is_empty() {
+            return Err(MediaError::InvalidParameter(
+                "Adaptive stream has no representations".to_string(),
+            ));
+        }
+        if config.down_threshold > config.up_threshold {
+            return Err(MediaError::InvalidParameter(format!(
+                "ABR down threshold {:?} is above the up threshold {:?}",
+                config.down_threshold, config.up_threshold
+            )));
+        }
+        representations.sort_by_key(|representation| representation.bandwidth);
+
+        Ok(Self {
+            representations,
+            current: 0,
+            config,
+            last_switch: None,
+        })
+    }
+
+    /// Gets the selected representation
+    pub fn current(&self) -> &Representation {
+        &self.representations[self.current]
+    }
+
+    /// Gets the representations, sorted by bandwidth
+    pub fn representations(&self) -> &[Representation] {
+        &self.representations
+    }
+
+    /// Reconsiders the representation given the media buffered ahead of
+    /// playback at `now`
+    ///
+    /// # Returns
+    ///
+    /// The switch made, if any
+    pub fn update(&mut self, buffer_level: Duration, now: Instant) -> Option<AbrEvent> {
+        let settled = self
+            .last_switch
+            .is_none_or(|last| now.duration_since(last) >= self.config.switch_interval);
+        if !settled {
+            return None;
+        }
+
+        let event = if buffer_level < self.config.down_threshold && self.current > 0 {
+            self.current -= 1;
+            AbrEvent::SwitchDown(self.current().id.clone())
+        } else if buffer_level > self.config.up_threshold
+            && self.current + 1 < self.representations.len()
+        {
+            self.current += 1;
+            AbrEvent::SwitchUp(self.current().id.clone())
+        } else {
+            return None;
+        };
+        self.last_switch = Some(now);
+        Some(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn representations() -> Vec<Representation> {
+        [("720p", 3_000_000), ("360p", 800_000), ("1080p", 6_000_000)]
+            .into_iter()
+            .map(|(id, bandwidth)| Representation {
+                id: id.to_string(),
+                bandwidth,
+                url: format!("https://example.com/{}/segment.m4s", id),
+            })
+            .collect()
+    }
+
+    fn config() -> AbrConfig {
+        AbrConfig {
+            up_threshold: Duration::from_secs(10),
+            down_threshold: Duration::from_secs(4),
+            switch_interval: Duration::from_secs(2),
+        }
+    }
+
+    #[test]
+    fn test_starts_at_lowest_bitrate() {
+        let abr = AbrController::new(representations(), config()).unwrap();
+        assert_eq!(abr.current().id, "360p");
+        let bandwidths: Vec<u32> = abr.representations().iter().map(|r| r.bandwidth).collect();
+        assert_eq!(bandwidths, [800_000, 3_000_000, 6_000_000]);
+    }
+
+    #[test]
+    fn test_switches_down_when_buffer_drops_below_threshold() {
+        let mut abr = AbrController::new(representations(), config()).unwrap();
+        let start = Instant::now();
+        abr.update(Duration::from_secs(12), start);
+        abr.update(Duration::from_secs(12), start + Duration::from_secs(2));
+        assert_eq!(abr.current().id, "1080p");
+
+        // Between the thresholds nothing changes
+        let later = start + Duration::from_secs(10);
+        assert_eq!(abr.update(Duration::from_secs(6), later), None);
+
+        assert_eq!(
+            abr.update(Duration::from_secs(3), later),
+            Some(AbrEvent::SwitchDown("720p".to_string()))
+        );
+        // Not again before the switch interval has passed
+        assert_eq!(
+            abr.update(Duration::from_secs(1), later + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            abr.update(Duration::from_secs(1), later + Duration::from_secs(2)),
+            Some(AbrEvent::SwitchDown("360p".to_string()))
+        );
+        // There is nothing lower
+        assert_eq!(
+            abr.update(Duration::ZERO, later + Duration::from_secs(4)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_invalid_configurations_rejected() {
+        assert!(AbrController::new(Vec::new(), config()).is_err());
+
+        let inverted = AbrConfig {
+            up_threshold: Duration::from_secs(2),
+            down_threshold: Duration::from_secs(4),
+            ..config()
+        };
+        assert!(AbrController::new(representations(), inverted).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Buffered time ranges
+//!
+//! Each track records the media time its queued media covers. Media queued
+//! in one decoding run, from loading or a seek on, forms one contiguous
+//! range; ranges of different runs are merged where they overlap. The
+//! pipeline's buffered ranges are where all tracks are buffered.
+
+use std::time::Duration;
+
+/// Time ranges of one track's media that the pipeline has queued
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BufferedRanges {
+    /// Disjoint ranges, sorted by start
+    ranges: Vec<(Duration, Duration)>,
+    /// Start of the range the current decoding run adds to
+    run_start: Option<Duration>,
+}
+
+impl BufferedRanges {
+    /// Records media from `start` to `end` as queued
+    ///
+    /// The media continues the current decoding run's range.
+    pub(crate) fn add(&mut self, start: Duration, end: Duration) {
+        let run_start = self.run_start.get_or_insert(start);
+        *run_start = (*run_start).min(start);
+        let start = *run_start;
+        self.insert(start, end.max(start));
+    }
+
+    /// Starts a new decoding run, whose media begins a new range
+    pub(crate) fn restart(&mut self) {
+        self.run_start = None;
+    }
+
+    /// Returns the ranges, sorted by start
+    pub(crate) fn ranges(&self) -> &[(Duration, Duration)] {
+        &self.ranges
+    }
+
+    /// Returns whether no media has been queued
+    pub(crate) fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Inserts a range, merging it with the ranges it overlaps or touches
+    fn insert(&mut self, mut start: Duration, mut end: Duration) {
+        self.ranges.retain(|&(s, e)| {
+            let overlaps = s <= end && start <= e;
+            if overlaps {
+                start = start.min(s);
+                end = end.max(e);
+            }
+            !overlaps
+        });
+        let index = self.ranges.partition_point(|&(s, _)| s < start);
+        self.ranges.insert(index, (start, end));
+    }
+}
+
+/// Returns the ranges covered by both `a` and `b`
+pub(crate) fn intersect(
+    a: &[(Duration, Duration)],
+    b: &[(Duration, Duration)],
+) -> Vec<(Duration, Duration)> {
+    let mut ranges = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        let start = a[i].0.max(b[j].0);
+        let end = a[i].1.min(b[j].1);
+        if start < end {
+            ranges.push((start, end));
+        }
+        if a[i].1 < b[j].1 {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn test_run_forms_one_range() {
+        let mut buffered = BufferedRanges::default();
+        assert!(buffered.is_empty());
+
+        // Frames without a duration still continue the range
+        buffered.add(ms(0), ms(40));
+        buffered.add(ms(80), ms(80));
+        buffered.add(ms(120), ms(160));
+        assert_eq!(buffered.ranges(), [(ms(0), ms(160))]);
+    }
+
+    #[test]
+    fn test_runs_after_restart_merge_where_they_overlap() {
+        let mut buffered = BufferedRanges::default();
+        buffered.add(ms(0), ms(1000));
+
+        buffered.restart();
+        buffered.add(ms(5000), ms(6000));
+        assert_eq!(buffered.ranges(), [(ms(0), ms(1000)), (ms(5000), ms(6000))]);
+
+        buffered.restart();
+        buffered.add(ms(500), ms(2000));
+        buffered.add(ms(2000), ms(5500));
+        assert_eq!(buffered.ranges(), [(ms(0), ms(6000))]);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let video = [(ms(0), ms(1000)), (ms(2000), ms(3000))];
+        let audio = [(ms(500), ms(2500))];
+        assert_eq!(
+            intersect(&video, &audio),
+            [(ms(500), ms(1000)), (ms(2000), ms(2500))]
+        );
+        assert!(intersect(&video, &[]).is_empty());
+    }
+}
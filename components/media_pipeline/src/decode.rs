@@ -0,0 +1,985 @@
+//! Video and audio decoding stages
+//!
+//! Each stage pulls the packets of one track from the demuxer, decrypts the
+//! encrypted ones and decodes them on its own thread, pushing video frames
+//! into the pipeline's video queue and audio buffers into its audio queue.
+//! The stages share the demuxer, each keeping the packets it reads for the
+//! other's track in [`PendingPackets`].
+
+use crate::buffered::BufferedRanges;
+use crate::stats::{self, StatsCounters};
+use crate::types::{AudioDecoderFactory, PacketDecryptor, RecoveryPolicy, VideoDecoderFactory};
+use crate::AVSyncController;
+use cortenbrowser_format_parsers::{
+    AudioTrackInfo, DemuxedPacket, Demuxer, Packet, VideoTrackInfo,
+};
+use cortenbrowser_shared_types::{
+    AudioBuffer, AudioCodec, AudioPacket, EncryptionInfo, MediaError, VideoCodec, VideoDecoder,
+    VideoFrame, VideoPacket,
+};
+use parking_lot::{Mutex, RwLock};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch};
+
+/// Annex B start code written in front of each NAL unit
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+/// How often a packet waiting for its key is tried again
+const KEY_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often a decoder that is far enough ahead checks the position again
+const READAHEAD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often an audio decoder waiting on a full queue tries it again
+const QUEUE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The media buffered by a decoder, and how far ahead of the playback
+/// position it may reach
+#[derive(Debug, Clone)]
+pub(crate) struct Readahead {
+    /// Media time of the media queued so far
+    pub(crate) buffered: Arc<Mutex<BufferedRanges>>,
+    /// Clock giving the playback position
+    pub(crate) clock: Arc<AVSyncController>,
+    /// How much may be buffered ahead of the position
+    pub(crate) limit: Duration,
+}
+
+impl Readahead {
+    /// Returns whether the limit is buffered ahead of the position
+    fn is_full(&self) -> bool {
+        let position = self.clock.get_clock();
+        self.buffered
+            .lock()
+            .ranges()
+            .iter()
+            .find(|&&(start, end)| start <= position && position < end)
+            .is_some_and(|&(_, end)| end - position >= self.limit)
+    }
+}
+
+/// The demuxer the pipeline's decoder threads read their packets from
+#[derive(Debug, Clone)]
+pub(crate) struct PacketSource {
+    /// Demuxer selected for the loaded source
+    pub(crate) demuxer: Arc<Mutex<Option<Box<dyn Demuxer + Send>>>>,
+    /// Packets read for the tracks of other decoder threads
+    pub(crate) pending: PendingPackets,
+}
+
+/// Packets read from the shared demuxer for other decoder threads
+///
+/// Each decoder thread registers the track it decodes. A thread reading a
+/// packet of another registered track keeps it here until that track's
+/// thread asks for its next packet; packets of tracks nobody decodes are
+/// dropped. Used with the demuxer locked.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct PendingPackets(Arc<Mutex<PendingTracks>>);
+
+/// The registered tracks and their pending packets
+#[derive(Debug, Default)]
+struct PendingTracks {
+    /// Pending packets by track ID, with the registration they are kept for
+    tracks: HashMap<u32, (u64, VecDeque<DemuxedPacket>)>,
+    /// ID of the next registration
+    next_registration: u64,
+}
+
+/// Registration of a decoder thread's track, removed when dropped
+#[derive(Debug)]
+struct Registration {
+    pending: PendingPackets,
+    track_id: u32,
+    id: u64,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut pending = self.pending.0.lock();
+        if pending
+            .tracks
+            .get(&self.track_id)
+            .is_some_and(|(id, _)| *id == self.id)
+        {
+            pending.tracks.remove(&self.track_id);
+        }
+    }
+}
+
+impl PendingPackets {
+    /// Registers a thread decoding `track_id`, in place of any earlier one
+    fn register(&self, track_id: u32) -> Registration {
+        let mut pending = self.0.lock();
+        let id = pending.next_registration;
+        pending.next_registration += 1;
+        pending.tracks.insert(track_id, (id, VecDeque::new()));
+        Registration {
+            pending: self.clone(),
+            track_id,
+            id,
+        }
+    }
+
+    /// Drops the pending packets, as the demuxer has moved
+    pub(crate) fn clear(&self) {
+        for (_, packets) in self.0.lock().tracks.values_mut() {
+            packets.clear();
+        }
+    }
+
+    /// Reads the next packet of `track_id`, pending or from `demuxer`
+    ///
+    /// Packets of other registered tracks read on the way are kept for
+    /// their threads.
+    fn next_packet(
+        &self,
+        demuxer: &mut dyn Demuxer,
+        track_id: u32,
+    ) -> Result<Option<DemuxedPacket>, MediaError> {
+        let mut pending = self.0.lock();
+        let kept = pending
+            .tracks
+            .get_mut(&track_id)
+            .and_then(|(_, packets)| packets.pop_front());
+        if kept.is_some() {
+            return Ok(kept);
+        }
+        while let Some(packet) = demuxer.next_packet()? {
+            if packet.track_id == track_id {
+                return Ok(Some(packet));
+            }
+            if let Some((_, packets)) = pending.tracks.get_mut(&packet.track_id) {
+                packets.push_back(packet);
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Decryption of encrypted packets, shared by the pipeline's decoders
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Decryption {
+    /// Decrypts packets, unset until a CDM is attached
+    pub(crate) decryptor: Arc<RwLock<DecryptorSlot>>,
+    /// Whether decoding waits for the key of an encrypted packet
+    pub(crate) waiting_for_key: Arc<watch::Sender<bool>>,
+}
+
+/// The pipeline's packet decryptor
+#[derive(Default)]
+pub(crate) struct DecryptorSlot(pub(crate) Option<PacketDecryptor>);
+
+impl std::fmt::Debug for DecryptorSlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DecryptorSlot")
+    }
+}
+
+impl Decryption {
+    /// Decrypts the data of a packet, waiting while its key is not usable
+    ///
+    /// # Returns
+    ///
+    /// The decrypted data, or `None` if the thread was cancelled while
+    /// waiting
+    fn decrypt(
+        &self,
+        data: &[u8],
+        info: &EncryptionInfo,
+        cancelled: &AtomicBool,
+    ) -> Option<Result<Vec<u8>, MediaError>> {
+        loop {
+            let decryptor = self.decryptor.read().0.clone();
+            let result = match decryptor {
+                Some(decryptor) => decryptor(data, info),
+                None => Ok(None),
+            };
+            match result {
+                Ok(None) => {
+                    self.waiting_for_key
+                        .send_if_modified(|waiting| !std::mem::replace(waiting, true));
+                    thread::park_timeout(KEY_RETRY_INTERVAL);
+                    if cancelled.load(Ordering::Relaxed) {
+                        self.waiting_for_key.send_replace(false);
+                        return None;
+                    }
+                }
+                result => {
+                    self.waiting_for_key
+                        .send_if_modified(|waiting| std::mem::replace(waiting, false));
+                    return Some(result.map(Option::unwrap_or_default));
+                }
+            }
+        }
+    }
+}
+
+/// The pipeline's video decoder factory
+pub(crate) struct DecoderFactorySlot(pub(crate) VideoDecoderFactory);
+
+impl std::fmt::Debug for DecoderFactorySlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DecoderFactorySlot")
+    }
+}
+
+/// The pipeline's audio decoder factory
+pub(crate) struct AudioDecoderFactorySlot(pub(crate) AudioDecoderFactory);
+
+impl std::fmt::Debug for AudioDecoderFactorySlot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AudioDecoderFactorySlot")
+    }
+}
+
+/// How decoder threads create their decoders and recover from codec errors,
+/// shared by the pipeline's decoders
+#[derive(Clone)]
+pub(crate) struct Recovery {
+    /// Creates the decoder, and creates it again under
+    /// [`RecoveryPolicy::ReinitDecoder`]
+    pub(crate) decoder_factory: Arc<RwLock<DecoderFactorySlot>>,
+    /// What to do after a codec error
+    pub(crate) policy: RecoveryPolicy,
+    /// Most codec errors tolerated within `window`, any number if unset
+    pub(crate) max_errors: Option<usize>,
+    /// Length of the sliding window codec errors are counted in
+    pub(crate) window: Duration,
+    /// Called with the error that stopped decoding
+    pub(crate) on_failure: Arc<dyn Fn(MediaError) + Send + Sync>,
+}
+
+impl std::fmt::Debug for Recovery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Recovery")
+            .field("policy", &self.policy)
+            .field("max_errors", &self.max_errors)
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Recovery {
+    /// Creates a decoder for `track` and configures it with the track's
+    /// codec setup
+    fn create_decoder(&self, track: &VideoTrackInfo) -> Result<Box<dyn VideoDecoder>, MediaError> {
+        let factory = Arc::clone(&self.decoder_factory.read().0);
+        let mut decoder = factory(&track.codec)?;
+        // Streams with in-band parameter sets still decode if this fails
+        if !track.extradata.is_empty() {
+            let _ = decoder.configure(&track.extradata);
+        }
+        Ok(decoder)
+    }
+
+    /// Decides how decoding goes on after `error`, a codec error
+    ///
+    /// The error is counted in `recent` first. Under
+    /// [`RecoveryPolicy::ReinitDecoder`] `decoder` is replaced by a new one
+    /// for `track`.
+    ///
+    /// # Errors
+    ///
+    /// The error that stops decoding: a `CodecError` once more than the
+    /// tolerated errors fall within the window, `error` itself under
+    /// [`RecoveryPolicy::Fail`], or the factory's error if the decoder cannot
+    /// be created again
+    fn recover(
+        &self,
+        error: MediaError,
+        recent: &mut ErrorWindow,
+        decoder: &mut Box<dyn VideoDecoder>,
+        track: &VideoTrackInfo,
+    ) -> Result<(), MediaError> {
+        if recent.record(Instant::now(), self) {
+            return Err(MediaError::CodecError {
+                details: format!(
+                    "More than {} codec errors within {:?}, last: {}",
+                    self.max_errors.unwrap_or_default(),
+                    self.window,
+                    error
+                ),
+            });
+        }
+        match self.policy {
+            RecoveryPolicy::Fail => Err(error),
+            RecoveryPolicy::SkipPacket => Ok(()),
+            RecoveryPolicy::ReinitDecoder => {
+                *decoder = self.create_decoder(track)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Times of the recent codec errors of a decoder thread
+#[derive(Debug, Default)]
+struct ErrorWindow(VecDeque<Instant>);
+
+impl ErrorWindow {
+    /// Records a codec error at `now`
+    ///
+    /// Returns whether more errors than `recovery` tolerates now fall within
+    /// its window.
+    fn record(&mut self, now: Instant, recovery: &Recovery) -> bool {
+        let Some(max_errors) = recovery.max_errors else {
+            return false;
+        };
+        while self
+            .0
+            .front()
+            .is_some_and(|&time| now.duration_since(time) >= recovery.window)
+        {
+            self.0.pop_front();
+        }
+        self.0.push_back(now);
+        self.0.len() > max_errors
+    }
+}
+
+/// Handle to a running video decoder thread
+#[derive(Debug)]
+pub(crate) struct VideoDecoderHandle {
+    thread: Thread,
+    cancelled: Arc<AtomicBool>,
+    input_ended: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+}
+
+impl VideoDecoderHandle {
+    /// Wakes the thread after more data has been fed to the demuxer
+    pub(crate) fn wake(&self) {
+        self.thread.unpark();
+    }
+
+    /// Lets the thread stop once the demuxer has no more packets
+    pub(crate) fn end_input(&self) {
+        self.input_ended.store(true, Ordering::Relaxed);
+        self.thread.unpark();
+    }
+
+    /// Returns whether the thread has decoded and queued every frame of the
+    /// media, including those flushed from the decoder
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Stops the thread at the next packet
+    ///
+    /// Called with the demuxer locked, the thread reads no further packets.
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.thread.unpark();
+    }
+}
+
+/// Spawns a thread decoding `track` into `video_tx`
+///
+/// The decoder is created by `recovery`, which also decides how decoding
+/// goes on after a codec error; if it cannot, the thread stops and reports
+/// the error to the recovery's failure handler. Encrypted packets are
+/// decrypted by `decryption` first; while their key is not usable the thread
+/// waits, retrying the packet. The media time of each queued frame is
+/// recorded in the buffered ranges of `readahead`, and no packets are read
+/// while its limit is buffered ahead of the position. Decoding is counted in
+/// `stats`.
+/// Decoders are not `Send`, so the decoder is created on the thread that uses
+/// it. The thread blocks while the queue is full and exits at the end of the
+/// media, when cancelled, or when the queue is closed. A cancelled decoder is
+/// flushed and the frames it still held discarded. Only at the end of the
+/// media is it [finished](VideoDecoderHandle::is_finished). Until
+/// [`VideoDecoderHandle::end_input`] is called, running out of packets parks
+/// the thread until it is woken for newly fed data.
+pub(crate) fn spawn_video_decoder(
+    source: PacketSource,
+    track: VideoTrackInfo,
+    recovery: Recovery,
+    decryption: Decryption,
+    video_tx: mpsc::Sender<VideoFrame>,
+    readahead: Readahead,
+    stats: Arc<StatsCounters>,
+) -> VideoDecoderHandle {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let input_ended = Arc::new(AtomicBool::new(false));
+    let finished = Arc::new(AtomicBool::new(false));
+    let (thread_cancelled, thread_input_ended, thread_finished) = (
+        Arc::clone(&cancelled),
+        Arc::clone(&input_ended),
+        Arc::clone(&finished),
+    );
+    let registration = source.pending.register(track.track_id);
+
+    let handle = thread::spawn(move || {
+        let _registration = registration;
+        let (cancelled, input_ended) = (thread_cancelled, thread_input_ended);
+        let Ok(mut decoder) = recovery.create_decoder(&track) else {
+            // Nothing can be decoded, which is the end as far as the
+            // pipeline is concerned
+            thread_finished.store(true, Ordering::Relaxed);
+            return;
+        };
+        let decoder_name = decoder_type(&track.codec);
+        let mut recent_errors = ErrorWindow::default();
+        let length_prefixed = matches!(
+            track.codec,
+            VideoCodec::H264 { .. } | VideoCodec::H265 { .. }
+        );
+
+        let buffered = &readahead.buffered;
+        loop {
+            // A cancelled decoder goes on to stop below
+            if !cancelled.load(Ordering::Relaxed) && readahead.is_full() {
+                thread::park_timeout(READAHEAD_POLL_INTERVAL);
+                continue;
+            }
+
+            // Checked before reading, so no packet fed before the end is missed
+            let ended = input_ended.load(Ordering::Relaxed);
+            let next = {
+                let mut demuxer = source.demuxer.lock();
+                // Checked under the lock, so nothing is read after a seek
+                if cancelled.load(Ordering::Relaxed) {
+                    None
+                } else {
+                    Some(
+                        demuxer
+                            .as_mut()
+                            .map(|d| source.pending.next_packet(d.as_mut(), track.track_id)),
+                    )
+                }
+            };
+            let Some(next) = next else {
+                let _ = decoder.flush();
+                return;
+            };
+            let packet = match next {
+                Some(Ok(Some(packet))) => packet,
+                Some(Ok(None)) if !ended => {
+                    thread::park();
+                    continue;
+                }
+                _ => break,
+            };
+            let Some(mut video) = decoder_packet(packet) else {
+                continue;
+            };
+            if let Some(info) = video.encryption.take() {
+                match decryption.decrypt(&video.data, &info, &cancelled) {
+                    Some(Ok(data)) => video.data = data,
+                    Some(Err(_)) => {
+                        stats.decode_failed();
+                        continue;
+                    }
+                    None => {
+                        let _ = decoder.flush();
+                        return;
+                    }
+                }
+            }
+            if length_prefixed {
+                video.data = to_annex_b(video.data);
+            }
+
+            // Errors cover both corrupt packets and decoders still buffering
+            let started = Instant::now();
+            match decoder.decode(&video) {
+                Ok(frame) => {
+                    stats.frame_decoded(started.elapsed(), frame.timestamp, video.data.len());
+                    if !queue_frame(&video_tx, buffered, &stats, &cancelled, frame) {
+                        return;
+                    }
+                }
+                Err(error @ MediaError::CodecError { .. }) => {
+                    stats.codec_error(decoder_name);
+                    let recovered =
+                        recovery.recover(error, &mut recent_errors, &mut decoder, &track);
+                    if let Err(error) = recovered {
+                        (recovery.on_failure)(error);
+                        return;
+                    }
+                }
+                Err(_) => stats.decode_failed(),
+            }
+        }
+
+        if cancelled.load(Ordering::Relaxed) {
+            return;
+        }
+        let started = Instant::now();
+        let frames = decoder.flush().unwrap_or_default();
+        let decode_time = started.elapsed() / frames.len().max(1) as u32;
+        for frame in frames {
+            stats.frame_flushed(decode_time);
+            if !queue_frame(&video_tx, buffered, &stats, &cancelled, frame) {
+                return;
+            }
+        }
+        thread_finished.store(true, Ordering::Relaxed);
+    });
+
+    VideoDecoderHandle {
+        thread: handle.thread().clone(),
+        cancelled,
+        input_ended,
+        finished,
+    }
+}
+
+/// Name of the type of decoder for `codec`, under which its codec errors
+/// are counted
+fn decoder_type(codec: &VideoCodec) -> &'static str {
+    match codec {
+        VideoCodec::H264 { .. } => "h264",
+        VideoCodec::H265 { .. } => "h265",
+        VideoCodec::VP8 => "vp8",
+        VideoCodec::VP9 { .. } => "vp9",
+        VideoCodec::AV1 { .. } => "av1",
+        VideoCodec::Theora => "theora",
+    }
+}
+
+/// Queues a decoded frame and records it as buffered
+///
+/// Frames of a cancelled decoder are not recorded, as they belong to the
+/// run before a seek. Returns `false` if the queue has been closed.
+fn queue_frame(
+    video_tx: &mpsc::Sender<VideoFrame>,
+    buffered: &Mutex<BufferedRanges>,
+    stats: &StatsCounters,
+    cancelled: &AtomicBool,
+    frame: VideoFrame,
+) -> bool {
+    let (start, end) = frame_range(&frame);
+    let bytes = frame.data.len();
+    stats.add_video_bytes(bytes);
+    if video_tx.blocking_send(frame).is_err() {
+        stats.remove_video_bytes(bytes);
+        return false;
+    }
+    if cancelled.load(Ordering::Relaxed) {
+        return false;
+    }
+    buffered.lock().add(start, end);
+    true
+}
+
+/// Media time covered by a video frame
+pub(crate) fn frame_range(frame: &VideoFrame) -> (Duration, Duration) {
+    let end = frame.timestamp + frame.duration.unwrap_or_default();
+    (frame.timestamp, end)
+}
+
+/// Converts a demuxed video packet to the millisecond timestamps decoders use
+fn decoder_packet(packet: DemuxedPacket) -> Option<VideoPacket> {
+    let pts = packet.pts_time().map(|t| t.as_millis() as i64);
+    let dts = packet.dts_time().map(|t| t.as_millis() as i64);
+    match packet.packet {
+        Packet::Video(video) => Some(VideoPacket { pts, dts, ..video }),
+        Packet::Audio(_) => None,
+    }
+}
+
+/// The audio buffer queue, shared by the audio decoder and the pipeline
+#[derive(Debug)]
+pub(crate) struct AudioQueue {
+    /// Audio buffer queue (sender)
+    pub(crate) tx: mpsc::Sender<AudioBuffer>,
+    /// Moves on each time the queued audio is discarded, so that audio
+    /// decoded from packets read before is dropped rather than queued
+    pub(crate) generation: u64,
+}
+
+/// Handle to a running audio decoder thread
+#[derive(Debug)]
+pub(crate) struct AudioDecoderHandle {
+    track_id: u32,
+    thread: Thread,
+    cancelled: Arc<AtomicBool>,
+    input_ended: Arc<AtomicBool>,
+    finished: Arc<AtomicBool>,
+}
+
+impl AudioDecoderHandle {
+    /// Returns the ID of the track the thread decodes
+    pub(crate) fn track_id(&self) -> u32 {
+        self.track_id
+    }
+
+    /// Wakes the thread after more data has been fed to the demuxer
+    pub(crate) fn wake(&self) {
+        self.thread.unpark();
+    }
+
+    /// Lets the thread finish once the demuxer has no more packets
+    pub(crate) fn end_input(&self) {
+        self.input_ended.store(true, Ordering::Relaxed);
+        self.thread.unpark();
+    }
+
+    /// Returns whether the thread has decoded and queued all of the audio
+    /// up to the end of the media, including that flushed from the decoder
+    pub(crate) fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed)
+    }
+
+    /// Has the thread reset its decoder before decoding the packets read
+    /// from now on
+    ///
+    /// Called with the [`AudioQueue`] locked, after moving its generation
+    /// on; the thread resets its decoder when it reads the first packet of
+    /// the new generation, and decodes to the end of the media again.
+    pub(crate) fn reset(&self) {
+        self.finished.store(false, Ordering::Relaxed);
+        self.thread.unpark();
+    }
+
+    /// Stops the thread at the next packet
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.thread.unpark();
+    }
+}
+
+/// Spawns a thread decoding `track` into the audio `queue`
+///
+/// The decoder is created by `factory` and configured with the track's
+/// codec setup and gapless information. Without a decoder the track's
+/// packets are read and dropped, and a packet that fails to decode is
+/// skipped. Encrypted packets are decrypted by `decryption` first, waiting
+/// while their key is not usable. The media time of each queued buffer is
+/// recorded in the buffered ranges of `readahead`, and no packets are read
+/// while its limit is buffered ahead of the position. Decoding is counted
+/// in `stats`.
+///
+/// Unlike the video decoder, the thread lasts until it is cancelled or the
+/// queue is closed. At the end of the media it flushes the decoder, queues
+/// what the decoder still held and is then
+/// [finished](AudioDecoderHandle::is_finished) until
+/// [reset](AudioDecoderHandle::reset). Packets at or before the last one
+/// decoded since the decoder was last reset are skipped, so the media can be
+/// read again for video alone. The thread blocks while the queue is full,
+/// and until [`AudioDecoderHandle::end_input`] is called, running out of
+/// packets parks it until it is woken for newly fed data.
+pub(crate) fn spawn_audio_decoder(
+    source: PacketSource,
+    track: AudioTrackInfo,
+    factory: Arc<RwLock<AudioDecoderFactorySlot>>,
+    decryption: Decryption,
+    queue: Arc<Mutex<AudioQueue>>,
+    readahead: Readahead,
+    stats: Arc<StatsCounters>,
+) -> AudioDecoderHandle {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let input_ended = Arc::new(AtomicBool::new(false));
+    let finished = Arc::new(AtomicBool::new(false));
+    let (thread_cancelled, thread_input_ended, thread_finished) = (
+        Arc::clone(&cancelled),
+        Arc::clone(&input_ended),
+        Arc::clone(&finished),
+    );
+    let registration = source.pending.register(track.track_id);
+    let track_id = track.track_id;
+
+    let handle = thread::spawn(move || {
+        let _registration = registration;
+        let (cancelled, input_ended, finished) =
+            (thread_cancelled, thread_input_ended, thread_finished);
+        let factory = Arc::clone(&factory.read().0);
+        let mut decoder = factory(&track.codec).ok().map(|mut decoder| {
+            // Decoders taking their setup in-band still decode if this fails
+            if !track.extradata.is_empty() {
+                let _ = decoder.configure(&track.extradata);
+            }
+            if let Some(gapless) = track.gapless {
+                decoder.set_gapless_info(gapless);
+            }
+            decoder
+        });
+        let decoder_name = audio_decoder_type(&track.codec);
+
+        let mut generation = queue.lock().generation;
+        let mut decoded_until = None;
+        let mut flushed = false;
+        loop {
+            if cancelled.load(Ordering::Relaxed) {
+                return;
+            }
+            if readahead.is_full() {
+                thread::park_timeout(READAHEAD_POLL_INTERVAL);
+                continue;
+            }
+
+            // Checked before reading, so no packet fed before the end is missed
+            let ended = input_ended.load(Ordering::Relaxed);
+            let (read_generation, next) = {
+                let mut demuxer = source.demuxer.lock();
+                // Read with the packet, as seeks move both with the demuxer
+                // locked
+                let read_generation = queue.lock().generation;
+                let next = demuxer
+                    .as_mut()
+                    .map(|d| source.pending.next_packet(d.as_mut(), track.track_id));
+                (read_generation, next)
+            };
+            // The queued audio was discarded for a seek; so is the audio
+            // the decoder holds from before it
+            if read_generation != generation {
+                generation = read_generation;
+                decoded_until = None;
+                flushed = false;
+                if let Some(decoder) = decoder.as_mut() {
+                    decoder.reset();
+                }
+            }
+
+            let packet = match next {
+                Some(Ok(Some(packet))) => packet,
+                Some(Ok(None)) if !ended => {
+                    thread::park();
+                    continue;
+                }
+                _ => {
+                    if !flushed {
+                        flushed = true;
+                        let buffers = decoder
+                            .as_mut()
+                            .and_then(|decoder| decoder.flush().ok())
+                            .unwrap_or_default();
+                        for buffer in buffers {
+                            let queued = queue_audio(
+                                &queue,
+                                generation,
+                                &readahead.buffered,
+                                &stats,
+                                &cancelled,
+                                buffer,
+                            );
+                            if !queued {
+                                return;
+                            }
+                        }
+                        let queue = queue.lock();
+                        if queue.generation == generation {
+                            finished.store(true, Ordering::Relaxed);
+                        }
+                    }
+                    thread::park();
+                    continue;
+                }
+            };
+
+            let Some((time, mut audio)) = audio_packet(packet, track.sample_rate) else {
+                continue;
+            };
+            if time.is_some_and(|time| decoded_until.is_some_and(|until| time <= until)) {
+                continue;
+            }
+            decoded_until = time.or(decoded_until);
+            let Some(decoder) = decoder.as_mut() else {
+                continue;
+            };
+            if let Some(info) = audio.encryption.take() {
+                match decryption.decrypt(&audio.data, &info, &cancelled) {
+                    Some(Ok(data)) => audio.data = data,
+                    Some(Err(_)) => {
+                        stats.decode_failed();
+                        continue;
+                    }
+                    None => return,
+                }
+            }
+
+            match decoder.decode(&audio) {
+                Ok(buffer) if buffer.samples.is_empty() => {}
+                Ok(buffer) => {
+                    let queued = queue_audio(
+                        &queue,
+                        generation,
+                        &readahead.buffered,
+                        &stats,
+                        &cancelled,
+                        buffer,
+                    );
+                    if !queued {
+                        return;
+                    }
+                }
+                Err(MediaError::CodecError { .. }) => stats.codec_error(decoder_name),
+                Err(_) => stats.decode_failed(),
+            }
+        }
+    });
+
+    AudioDecoderHandle {
+        track_id,
+        thread: handle.thread().clone(),
+        cancelled,
+        input_ended,
+        finished,
+    }
+}
+
+/// Name of the type of decoder for `codec`, under which its codec errors
+/// are counted
+fn audio_decoder_type(codec: &AudioCodec) -> &'static str {
+    match codec {
+        AudioCodec::AAC { .. } => "aac",
+        AudioCodec::Opus { .. } => "opus",
+        AudioCodec::MP3 { .. } => "mp3",
+        AudioCodec::Vorbis => "vorbis",
+        AudioCodec::FLAC => "flac",
+        AudioCodec::PCM { .. } => "pcm",
+    }
+}
+
+/// Queues a decoded audio buffer and records it as buffered
+///
+/// Waits while the queue is full. A buffer decoded in an earlier
+/// `generation`, before the queued audio was discarded, is dropped instead.
+/// Returns `false` if the thread has been cancelled or the queue closed.
+fn queue_audio(
+    queue: &Mutex<AudioQueue>,
+    generation: u64,
+    buffered: &Mutex<BufferedRanges>,
+    stats: &StatsCounters,
+    cancelled: &AtomicBool,
+    mut buffer: AudioBuffer,
+) -> bool {
+    let (start, end) = (buffer.timestamp, buffer.timestamp + buffer.duration);
+    let bytes = stats::audio_bytes(buffer.samples.len());
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return false;
+        }
+        {
+            // Held while sending, so the queue is not emptied in between
+            let queue = queue.lock();
+            if queue.generation != generation {
+                return true;
+            }
+            stats.add_audio_bytes(bytes);
+            match queue.tx.try_send(buffer) {
+                Ok(()) => {
+                    stats.audio_buffer_queued();
+                    buffered.lock().add(start, end);
+                    return true;
+                }
+                Err(TrySendError::Full(returned)) => buffer = returned,
+                Err(TrySendError::Closed(_)) => {
+                    stats.remove_audio_bytes(bytes);
+                    return false;
+                }
+            }
+            stats.remove_audio_bytes(bytes);
+        }
+        thread::park_timeout(QUEUE_POLL_INTERVAL);
+    }
+}
+
+/// Converts a demuxed audio packet to the timestamps audio decoders use, in
+/// samples at the track's `sample_rate`
+///
+/// Also returns the packet's presentation time.
+fn audio_packet(
+    packet: DemuxedPacket,
+    sample_rate: u32,
+) -> Option<(Option<Duration>, AudioPacket)> {
+    let time = packet.pts_time();
+    let samples = |t: Duration| (t.as_secs_f64() * sample_rate as f64).round() as i64;
+    let pts = time.map(samples);
+    let dts = packet.dts_time().map(samples);
+    match packet.packet {
+        Packet::Audio(audio) => Some((time, AudioPacket { pts, dts, ..audio })),
+        Packet::Video(_) => None,
+    }
+}
+
+/// Rewrites 4-byte length-prefixed NAL units, as stored in MP4, with Annex B
+/// start codes
+///
+/// Data already in Annex B form, or that does not split exactly into
+/// length-prefixed units, is returned unchanged.
+fn to_annex_b(data: Vec<u8>) -> Vec<u8> {
+    if data.starts_with(&START_CODE) {
+        return data;
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    let mut rest = &data[..];
+    while !rest.is_empty() {
+        let Some((length, tail)) = rest.split_first_chunk::<4>() else {
+            return data;
+        };
+        let length = u32::from_be_bytes(*length) as usize;
+        if length == 0 || length > tail.len() {
+            return data;
+        }
+        out.extend_from_slice(&START_CODE);
+        out.extend_from_slice(&tail[..length]);
+        rest = &tail[length..];
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_annex_b_rewrites_length_prefixes() {
+        let data = vec![0, 0, 0, 2, 0x67, 0x42, 0, 0, 0, 3, 0x65, 0x88, 0x84];
+        assert_eq!(
+            to_annex_b(data),
+            vec![0, 0, 0, 1, 0x67, 0x42, 0, 0, 0, 1, 0x65, 0x88, 0x84]
+        );
+    }
+
+    fn recovery(max_errors: Option<usize>) -> Recovery {
+        Recovery {
+            decoder_factory: Arc::new(RwLock::new(DecoderFactorySlot(Arc::new(
+                |_: &VideoCodec| Err(MediaError::InvalidState("No decoder".to_string())),
+            )))),
+            policy: RecoveryPolicy::SkipPacket,
+            max_errors,
+            window: Duration::from_secs(1),
+            on_failure: Arc::new(|_| {}),
+        }
+    }
+
+    #[test]
+    fn test_error_window_counts_recent_errors_only() {
+        let recovery = recovery(Some(2));
+        let mut window = ErrorWindow::default();
+        let start = Instant::now();
+
+        assert!(!window.record(start, &recovery));
+        assert!(!window.record(start + Duration::from_millis(500), &recovery));
+        assert!(window.record(start + Duration::from_millis(900), &recovery));
+
+        // The first two errors have left the window
+        let later = start + Duration::from_millis(1600);
+        assert!(!window.record(later, &recovery));
+    }
+
+    #[test]
+    fn test_error_window_without_limit_never_fails() {
+        let recovery = recovery(None);
+        let mut window = ErrorWindow::default();
+        let now = Instant::now();
+        assert!((0..100).all(|_| !window.record(now, &recovery)));
+    }
+
+    #[test]
+    fn test_to_annex_b_keeps_other_data() {
+        let annex_b = vec![0, 0, 0, 1, 0x67, 0, 0, 0, 1, 0x68];
+        assert_eq!(to_annex_b(annex_b.clone()), annex_b);
+
+        let truncated = vec![0, 0, 0, 9, 0x65, 0x88];
+        assert_eq!(to_annex_b(truncated.clone()), truncated);
+    }
+}
@@ -10,9 +10,11 @@
 //! The media_pipeline component consists of:
 //!
 //! - [`AVSyncController`]: Audio/video synchronization logic
+//! - [`AbrController`]: Adaptive bitrate switching between representations
 //! - [`MediaPipeline`]: Main pipeline orchestration (coming soon)
 //! - [`PipelineConfig`]: Pipeline configuration
 //! - [`SyncDecision`]: Synchronization decisions
+//! - [`TimeStretcher`]: Audio time-stretching for playback rates
 //!
 //! # Examples
 //!
@@ -28,6 +30,7 @@
 //!     height: 1080,
 //!     format: PixelFormat::YUV420,
 //!     data: vec![0u8; 1920 * 1080],
+//!     strides: None,
 //!     timestamp: Duration::from_secs(1),
 //!     duration: Some(Duration::from_millis(33)),
 //!     metadata: FrameMetadata::default(),
@@ -40,11 +43,21 @@
 #![warn(missing_docs)]
 #![deny(unsafe_code)]
 
+mod abr;
+mod buffered;
+mod decode;
 mod pipeline;
+mod stats;
 mod sync;
+mod time_stretch;
 mod types;
 
 // Re-export public API
+pub use abr::{AbrConfig, AbrController, AbrEvent, Representation};
 pub use pipeline::MediaPipeline;
 pub use sync::AVSyncController;
-pub use types::{PipelineConfig, SyncDecision};
+pub use time_stretch::{ResampleStretcher, TimeStretcher};
+pub use types::{
+    AudioDecoderFactory, MediaReader, PacketDecryptor, PipelineConfig, PipelineMetrics,
+    PipelineStats, RecoveryPolicy, SyncDecision, TrackInfo, TrackKind, VideoDecoderFactory,
+};
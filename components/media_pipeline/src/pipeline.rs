@@ -2,13 +2,47 @@
 //!
 //! Coordinates source readers, demuxers, decoders, and synchronization.
 
-use crate::types::PipelineConfig;
-use crate::AVSyncController;
-use cortenbrowser_shared_types::{AudioBuffer, MediaError, MediaSource, VideoFrame};
-use parking_lot::RwLock;
+use crate::abr::{AbrController, Representation};
+use crate::buffered::{self, BufferedRanges};
+use crate::decode;
+use crate::stats::{self, StatsCounters};
+use crate::time_stretch::{ResampleStretcher, TimeStretcher};
+use crate::types::{
+    MediaReader, PipelineConfig, PipelineMetrics, PipelineStats, TrackInfo, TrackKind,
+};
+use crate::{AVSyncController, SyncDecision};
+use cortenbrowser_format_parsers::{Demuxer, DemuxerFactory, MediaInfo};
+use cortenbrowser_shared_types::{
+    AudioBuffer, AudioCodec, AudioDecoder, AudioSink, EncryptionInfo, LoopMode, MediaChunk,
+    MediaError, MediaSource, PreloadStrategy, VideoCodec, VideoDecoder, VideoFrame,
+    MAX_PLAYBACK_RATE, MIN_PLAYBACK_RATE,
+};
+use cortenbrowser_video_decoders::DecoderFactory;
+use parking_lot::{Mutex, RwLock};
+use std::future::Future;
+use std::io::SeekFrom;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, MissedTickBehavior};
+
+/// Interval at which the media clock advances while running
+const CLOCK_TICK: Duration = Duration::from_millis(10);
+
+/// Reading of the media that the preload strategy left for
+/// [`MediaPipeline::start`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Deferred {
+    /// Nothing is left
+    #[default]
+    Nothing,
+    /// The media information is parsed, but decoding has not started
+    Decoding,
+    /// The reader has not been read at all
+    Reading,
+}
 
 /// Pipeline state enumeration
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -23,6 +57,8 @@ enum PipelineState {
     Running,
     /// Pipeline is stopped
     Stopped,
+    /// Decoding failed and cannot go on until a source is loaded again
+    Error,
 }
 
 /// Main media pipeline orchestrator
@@ -59,14 +95,28 @@ pub struct MediaPipeline {
     sync_controller: Arc<AVSyncController>,
     /// Currently loaded media source
     source: Arc<RwLock<Option<MediaSource>>>,
-    /// Video frame queue (sender)
-    video_tx: mpsc::Sender<VideoFrame>,
-    /// Video frame queue (receiver)
-    video_rx: Arc<RwLock<Option<mpsc::Receiver<VideoFrame>>>>,
-    /// Audio buffer queue (sender)
-    audio_tx: mpsc::Sender<AudioBuffer>,
-    /// Audio buffer queue (receiver)
-    audio_rx: Arc<RwLock<Option<mpsc::Receiver<AudioBuffer>>>>,
+    /// Demuxing, decoding and the queues of decoded media
+    decoding: Decoding,
+    /// What to do on reaching the end of the media
+    loop_mode: Arc<RwLock<LoopMode>>,
+    /// Duration of the loaded media, if known
+    duration: Arc<RwLock<Option<Duration>>>,
+    /// Number of times playback has looped
+    loop_count: Arc<watch::Sender<u64>>,
+    /// Whether playback has reached the end of the media without looping
+    ended: Arc<watch::Sender<bool>>,
+    /// Error that put the pipeline in its error state
+    failed: Arc<watch::Sender<Option<MediaError>>>,
+    /// Time-stretching stage for audio leaving the queue
+    time_stretcher: Arc<Mutex<Box<dyn TimeStretcher>>>,
+    /// Task advancing the media clock while running
+    clock_task: Mutex<Option<JoinHandle<()>>>,
+    /// Chooses the representation of an adaptive stream
+    abr: Arc<Mutex<Option<AbrController>>>,
+    /// Representation of the adaptive stream being played
+    representation: Arc<watch::Sender<Option<Representation>>>,
+    /// Reading left for `start` by the preload strategy
+    deferred: Mutex<Deferred>,
 }
 
 impl MediaPipeline {
@@ -89,6 +139,7 @@ impl MediaPipeline {
     /// let pipeline = MediaPipeline::new(config).unwrap();
     /// ```
     pub fn new(config: PipelineConfig) -> Result<Self, MediaError> {
+        validate_loop_mode(config.loop_mode)?;
         let buffer_size = config.buffer_size;
 
         // Create video frame queue
@@ -97,27 +148,97 @@ impl MediaPipeline {
         // Create audio buffer queue
         let (audio_tx, audio_rx) = mpsc::channel(buffer_size);
 
+        let sync_controller = Arc::new(AVSyncController::with_thresholds(
+            config.sync_threshold,
+            config.max_drop_threshold,
+        ));
+
+        let state = Arc::new(RwLock::new(PipelineState::Idle));
+        let failed = Arc::new(watch::channel(None).0);
+        let on_failure = {
+            let (state, failed) = (Arc::clone(&state), Arc::clone(&failed));
+            Arc::new(move |error| {
+                *state.write() = PipelineState::Error;
+                failed.send_replace(Some(error));
+            })
+        };
+
         Ok(Self {
-            config,
-            state: Arc::new(RwLock::new(PipelineState::Idle)),
-            sync_controller: Arc::new(AVSyncController::new()),
+            state,
+            sync_controller: Arc::clone(&sync_controller),
             source: Arc::new(RwLock::new(None)),
-            video_tx,
-            video_rx: Arc::new(RwLock::new(Some(video_rx))),
-            audio_tx,
-            audio_rx: Arc::new(RwLock::new(Some(audio_rx))),
+            decoding: Decoding {
+                demuxer: Arc::new(Mutex::new(None)),
+                pending: decode::PendingPackets::default(),
+                media_info: Arc::new(RwLock::new(None)),
+                video_decoder: Arc::new(Mutex::new(None)),
+                audio_decoder: Arc::new(Mutex::new(None)),
+                reader: Arc::new(Mutex::new(None)),
+                video_tx: Arc::new(Mutex::new(video_tx)),
+                // Dropping a receiver closes its queue
+                video_rx: Arc::new(RwLock::new(config.enable_video.then_some(video_rx))),
+                audio_queue: Arc::new(Mutex::new(decode::AudioQueue {
+                    tx: audio_tx,
+                    generation: 0,
+                })),
+                audio_rx: Arc::new(RwLock::new(config.enable_audio.then_some(audio_rx))),
+                video_buffered: Arc::new(Mutex::new(BufferedRanges::default())),
+                audio_buffered: Arc::new(Mutex::new(BufferedRanges::default())),
+                stats: Arc::new(StatsCounters::default()),
+                video_track: Arc::new(Mutex::new(None)),
+                audio_track: Arc::new(Mutex::new(None)),
+                decryption: decode::Decryption::default(),
+                recovery: decode::Recovery {
+                    decoder_factory: Arc::new(RwLock::new(decode::DecoderFactorySlot(Arc::new(
+                        |codec: &VideoCodec| DecoderFactory::create_decoder(codec.clone()),
+                    )))),
+                    policy: config.recovery_policy,
+                    max_errors: config.max_codec_errors,
+                    window: config.codec_error_window,
+                    on_failure,
+                },
+                audio_decoder_factory: Arc::new(RwLock::new(decode::AudioDecoderFactorySlot(
+                    Arc::new(|codec: &AudioCodec| {
+                        cortenbrowser_audio_decoders::DecoderFactory::create_decoder(codec.clone())
+                    }),
+                ))),
+                draining: Arc::new(AtomicBool::new(false)),
+                buffer_size,
+                enable_video: config.enable_video,
+                enable_audio: config.enable_audio,
+                clock: sync_controller,
+                max_buffer_ahead: config.max_buffer_ahead,
+            },
+            loop_mode: Arc::new(RwLock::new(config.loop_mode)),
+            duration: Arc::new(RwLock::new(None)),
+            loop_count: Arc::new(watch::channel(0).0),
+            ended: Arc::new(watch::channel(false).0),
+            failed,
+            time_stretcher: Arc::new(Mutex::new(Box::new(ResampleStretcher::new()))),
+            clock_task: Mutex::new(None),
+            abr: Arc::new(Mutex::new(None)),
+            representation: Arc::new(watch::channel(None).0),
+            deferred: Mutex::new(Deferred::Nothing),
+            config,
         })
     }
 
     /// Loads a media source into the pipeline
     ///
+    /// A demuxer is selected for sources that carry data or a MIME type:
+    /// buffers are probed by content first and fall back to their MIME type;
+    /// streams use their MIME type. Other sources get a demuxer once their
+    /// data is available. Loading also leaves the
+    /// [error state](MediaPipeline::error).
+    ///
     /// # Arguments
     ///
     /// * `source` - The media source to load
     ///
     /// # Returns
     ///
-    /// `Ok(())` on success, or an error
+    /// `Ok(())` on success, `UnsupportedFormat` if no demuxer matches the
+    /// source, or an error for invalid state transitions
     ///
     /// # Examples
     ///
@@ -139,22 +260,52 @@ impl MediaPipeline {
     pub async fn load_source(&self, source: MediaSource) -> Result<(), MediaError> {
         let mut state = self.state.write();
 
-        // Can only load in Idle or Stopped states
-        if *state != PipelineState::Idle && *state != PipelineState::Stopped {
+        // Can only load in Idle or Stopped states, or to recover from an error
+        if !matches!(
+            *state,
+            PipelineState::Idle | PipelineState::Stopped | PipelineState::Error
+        ) {
             return Err(MediaError::InvalidStateTransition {
                 from: cortenbrowser_shared_types::SessionState::Loading,
                 to: cortenbrowser_shared_types::SessionState::Ready,
             });
         }
 
+        let demuxer = match &source {
+            MediaSource::Buffer { data, mime_type } => Some(
+                DemuxerFactory::create_for_data(data)
+                    .or_else(|_| DemuxerFactory::create_for_mime(mime_type))?,
+            ),
+            MediaSource::Stream { mime_type, .. } if !mime_type.is_empty() => {
+                Some(DemuxerFactory::create_for_mime(mime_type)?)
+            }
+            _ => None,
+        };
+
         *state = PipelineState::Loading;
         drop(state); // Release lock
+        self.failed.send_replace(None);
 
-        // Store the source
+        // Store the source and its demuxer
         {
             let mut src = self.source.write();
             *src = Some(source);
         }
+        *self.decoding.demuxer.lock() = demuxer;
+        self.decoding.pending.clear();
+        // The audio decoder carries on across seeks, but not to new media
+        if let Some(decoder) = self.decoding.audio_decoder.lock().take() {
+            decoder.cancel();
+        }
+        // The previous source's reader must not be read for this one
+        *self.decoding.reader.lock() = None;
+        *self.deferred.lock() = Deferred::Nothing;
+        *self.decoding.video_track.lock() = None;
+        *self.decoding.audio_track.lock() = None;
+        self.decoding.draining.store(false, Ordering::Relaxed);
+        self.decoding.stats.reset_metrics();
+        *self.abr.lock() = None;
+        self.representation.send_replace(None);
 
         // Transition to Ready state
         {
@@ -165,8 +316,377 @@ impl MediaPipeline {
         Ok(())
     }
 
+    /// Returns whether a demuxer has been selected for the loaded source
+    pub fn has_demuxer(&self) -> bool {
+        self.decoding.demuxer.lock().is_some()
+    }
+
+    /// Feeds media data to the demuxer selected by [`load_source`]
+    ///
+    /// The reader is read to the end and parsed by the demuxer. The media
+    /// duration is taken from the parse, and the selected video and audio
+    /// tracks are decoded on background threads into the video frame and
+    /// audio buffer queues, ready for [`get_next_video_frame`] and
+    /// [`get_next_audio_buffer`]. The reader is kept so that [`seek`] can
+    /// read again from a keyframe.
+    ///
+    /// With [`PipelineConfig::preload`] set to `Metadata`, decoding waits
+    /// for [`start`]; with `None`, so does reading the media.
+    ///
+    /// [`load_source`]: MediaPipeline::load_source
+    /// [`get_next_video_frame`]: MediaPipeline::get_next_video_frame
+    /// [`get_next_audio_buffer`]: MediaPipeline::get_next_audio_buffer
+    /// [`seek`]: MediaPipeline::seek
+    /// [`start`]: MediaPipeline::start
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Source of the media data
+    ///
+    /// # Returns
+    ///
+    /// The parsed `MediaInfo`, or `None` if reading waits for [`start`].
+    /// `InvalidState` if no demuxer has been selected, or the demuxer's
+    /// error if the data cannot be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::MediaSource;
+    /// use std::io::Cursor;
+    ///
+    /// # async fn example(data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
+    ///
+    /// let source = MediaSource::Buffer {
+    ///     data: data.clone(),
+    ///     mime_type: "video/mp4".to_string(),
+    /// };
+    /// pipeline.load_source(source).await?;
+    ///
+    /// if let Some(info) = pipeline.set_reader(Box::new(Cursor::new(data)))? {
+    ///     println!("Duration: {:?}", info.duration);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_reader(
+        &self,
+        reader: Box<dyn MediaReader>,
+    ) -> Result<Option<MediaInfo>, MediaError> {
+        if !self.has_demuxer() {
+            return Err(MediaError::InvalidState("No demuxer selected".to_string()));
+        }
+
+        let deferred = match self.config.preload {
+            PreloadStrategy::None => {
+                *self.decoding.reader.lock() = Some(reader);
+                *self.deferred.lock() = Deferred::Reading;
+                return Ok(None);
+            }
+            PreloadStrategy::Metadata => Deferred::Decoding,
+            PreloadStrategy::Auto => Deferred::Nothing,
+        };
+
+        let info = self.read_media(reader)?;
+        if deferred == Deferred::Nothing {
+            self.read_packets(&info);
+        } else {
+            self.set_media_info(&info);
+        }
+        *self.deferred.lock() = deferred;
+        Ok(Some(info))
+    }
+
+    /// Reads all of `reader` and parses it with the demuxer, keeping the
+    /// reader for seeks
+    fn read_media(&self, mut reader: Box<dyn MediaReader>) -> Result<MediaInfo, MediaError> {
+        let data = read_from(reader.as_mut(), 0)?;
+
+        let info = {
+            let mut demuxer = self.decoding.demuxer.lock();
+            let demuxer = demuxer
+                .as_mut()
+                .ok_or_else(|| MediaError::InvalidState("No demuxer selected".to_string()))?;
+            demuxer.load(&data)?
+        };
+
+        *self.decoding.reader.lock() = Some(reader);
+        Ok(info)
+    }
+
+    /// Starts decoding media that has been read in full
+    fn read_packets(&self, info: &MediaInfo) {
+        self.start_decoding(info);
+        self.decoding.end_input();
+    }
+
+    /// Reads and decodes what the preload strategy left for playback
+    fn start_deferred(&self) -> Result<(), MediaError> {
+        match std::mem::take(&mut *self.deferred.lock()) {
+            Deferred::Nothing => {}
+            Deferred::Decoding => {
+                // A seek may have started decoding already
+                let info = self.media_info();
+                if let Some(info) = info.filter(|_| self.decoding.video_decoder.lock().is_none()) {
+                    self.read_packets(&info);
+                }
+            }
+            Deferred::Reading => {
+                let reader = self.decoding.reader.lock().take();
+                if let Some(reader) = reader {
+                    let info = self.read_media(reader)?;
+                    self.read_packets(&info);
+                    // Decode from where a seek before playback went
+                    let position = self.current_position();
+                    if !position.is_zero() {
+                        self.decoding.seek(position)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Feeds the next chunk of a streamed source to its demuxer
+    ///
+    /// Chunks are parsed as they arrive. Once the demuxer has read enough to
+    /// describe the media, decoding of the selected video and audio tracks
+    /// starts and continues as more chunks are fed. The final chunk marks the end of the
+    /// stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `chunk` - The next chunk of media data
+    ///
+    /// # Returns
+    ///
+    /// The `MediaInfo` on the call that first makes it available and `None`
+    /// otherwise, `InvalidState` if no demuxer has been selected, or the
+    /// demuxer's error if the data cannot be parsed
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::MediaChunk;
+    ///
+    /// # fn example(pipeline: &MediaPipeline, data: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+    /// let chunk = MediaChunk {
+    ///     data,
+    ///     sequence: 0,
+    ///     is_final: true,
+    /// };
+    /// if let Some(info) = pipeline.feed(&chunk)? {
+    ///     println!("Duration: {:?}", info.duration);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn feed(&self, chunk: &MediaChunk) -> Result<Option<MediaInfo>, MediaError> {
+        let info = {
+            let mut demuxer = self.decoding.demuxer.lock();
+            let demuxer = demuxer
+                .as_mut()
+                .ok_or_else(|| MediaError::InvalidState("No demuxer selected".to_string()))?;
+            demuxer.feed(&chunk.data)?;
+            if chunk.is_final {
+                demuxer.end_of_stream();
+            }
+            demuxer.media_info().cloned()
+        };
+
+        let new_info = match info {
+            Some(info) if self.decoding.media_info.read().is_none() => {
+                self.start_decoding(&info);
+                Some(info)
+            }
+            _ => None,
+        };
+
+        if chunk.is_final {
+            self.decoding.end_input();
+        } else {
+            self.decoding.wake();
+        }
+
+        Ok(new_info)
+    }
+
+    /// Records parsed media information and its duration, and starts
+    /// decoding its selected tracks
+    fn start_decoding(&self, info: &MediaInfo) {
+        self.set_media_info(info);
+        self.decoding.start(info);
+    }
+
+    /// Records parsed media information and its duration
+    fn set_media_info(&self, info: &MediaInfo) {
+        // Streams that have not ended yet report a zero duration
+        if !info.duration.is_zero() {
+            self.set_media_duration(info.duration);
+        }
+        *self.decoding.media_info.write() = Some(info.clone());
+    }
+
+    /// Returns the media information parsed by [`set_reader`] or
+    /// [`feed`], if any
+    ///
+    /// [`set_reader`]: MediaPipeline::set_reader
+    /// [`feed`]: MediaPipeline::feed
+    pub fn media_info(&self) -> Option<MediaInfo> {
+        self.decoding.media_info.read().clone()
+    }
+
+    /// Lists the tracks of the loaded media
+    ///
+    /// One track of each kind is selected: the first, until another is
+    /// chosen with [`select_track`](MediaPipeline::select_track). Empty until
+    /// the media information is known.
+    pub fn tracks(&self) -> Vec<TrackInfo> {
+        let Some(info) = self.media_info() else {
+            return Vec::new();
+        };
+        let video = self.decoding.selected_video_track(&info);
+        let audio = self.decoding.selected_audio_track(&info);
+
+        let video_tracks = info.video_tracks.iter().map(|track| TrackInfo {
+            track_id: track.track_id,
+            kind: TrackKind::Video,
+            selected: video == Some(track.track_id),
+        });
+        let audio_tracks = info.audio_tracks.iter().map(|track| TrackInfo {
+            track_id: track.track_id,
+            kind: TrackKind::Audio,
+            selected: audio == Some(track.track_id),
+        });
+        video_tracks.chain(audio_tracks).collect()
+    }
+
+    /// Selects the track that plays for its kind
+    ///
+    /// Selecting a video track restarts video decoding on it from the
+    /// keyframe at or before the current position. Selecting an audio track
+    /// discards only the queued audio and decodes the new track from where
+    /// demuxing has got to; video carries on.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_id` - ID of a track listed by [`tracks`](MediaPipeline::tracks)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success, `InvalidParameter` if the media has no such
+    /// track, or `InvalidState` if the media information is not known yet or
+    /// a video track is selected on a source that cannot seek
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, TrackKind};
+    ///
+    /// # fn example(pipeline: &MediaPipeline) -> Result<(), Box<dyn std::error::Error>> {
+    /// // Switch to the second audio track, such as a dub
+    /// let dub = pipeline
+    ///     .tracks()
+    ///     .into_iter()
+    ///     .filter(|track| track.kind == TrackKind::Audio)
+    ///     .nth(1);
+    /// if let Some(dub) = dub {
+    ///     pipeline.select_track(dub.track_id)?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn select_track(&self, track_id: u32) -> Result<(), MediaError> {
+        let track = self
+            .tracks()
+            .into_iter()
+            .find(|track| track.track_id == track_id);
+        let Some(track) = track else {
+            if self.media_info().is_none() {
+                return Err(MediaError::InvalidState(
+                    "Media information not available yet".to_string(),
+                ));
+            }
+            return Err(MediaError::InvalidParameter(format!(
+                "No track with ID {}",
+                track_id
+            )));
+        };
+        if track.selected {
+            return Ok(());
+        }
+
+        match track.kind {
+            TrackKind::Video => {
+                let previous = self.decoding.video_track.lock().replace(track_id);
+                if !self.decoding.restart_video(self.current_position())? {
+                    *self.decoding.video_track.lock() = previous;
+                    return Err(MediaError::InvalidState(
+                        "Cannot switch video tracks of a source that cannot seek".to_string(),
+                    ));
+                }
+            }
+            TrackKind::Audio => {
+                *self.decoding.audio_track.lock() = Some(track_id);
+                // Stopped first, so none of its audio is queued after the flush
+                if let Some(decoder) = self.decoding.audio_decoder.lock().take() {
+                    decoder.cancel();
+                }
+                self.decoding.flush_audio();
+                self.time_stretcher.lock().reset();
+                if let Some(info) = self.media_info() {
+                    self.decoding.start_audio(&info);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Switches to the video track at `track_index` of the media's video
+    /// tracks, e.g. another camera angle
+    ///
+    /// The current video decoder is flushed and the queued video frames are
+    /// discarded. A decoder for the new track's codec then decodes from the
+    /// keyframe at or before the current position, while audio plays on
+    /// uninterrupted.
+    ///
+    /// # Arguments
+    ///
+    /// * `track_index` - Index into [`MediaInfo::video_tracks`]
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success, `InvalidParameter` if there is no video track at
+    /// the index, or `InvalidState` if the media information is not known
+    /// yet or the source cannot seek
+    pub fn switch_video_track(&self, track_index: usize) -> Result<(), MediaError> {
+        let info = self.media_info().ok_or_else(|| {
+            MediaError::InvalidState("Media information not available yet".to_string())
+        })?;
+        let track = info.video_tracks.get(track_index).ok_or_else(|| {
+            MediaError::InvalidParameter(format!(
+                "No video track at index {} of {}",
+                track_index,
+                info.video_tracks.len()
+            ))
+        })?;
+        self.select_track(track.track_id)
+    }
+
     /// Starts the pipeline (begins processing)
     ///
+    /// Starting again after the pipeline reached the end of the media plays
+    /// it from the beginning. Reading and decoding that the
+    /// [preload strategy](PipelineConfig::preload) held back begin now.
+    ///
+    /// # Errors
+    ///
+    /// Besides invalid state transitions, the demuxer's or reader's error if
+    /// media held back by the preload strategy cannot be read
+    ///
     /// # Returns
     ///
     /// `Ok(())` on success, or an error
@@ -192,31 +712,38 @@ impl MediaPipeline {
     pub async fn start(&self) -> Result<(), MediaError> {
         let mut state = self.state.write();
 
-        // Can only start from Ready state
-        if *state != PipelineState::Ready {
+        // Can only start from Ready state, or again after the end
+        let rewind = *state == PipelineState::Stopped && self.is_ended();
+        if *state != PipelineState::Ready && !rewind {
             return Err(MediaError::InvalidStateTransition {
                 from: cortenbrowser_shared_types::SessionState::Idle,
                 to: cortenbrowser_shared_types::SessionState::Playing,
             });
         }
+        if rewind {
+            self.reposition(Duration::ZERO)?;
+        }
+        self.start_deferred()?;
 
         *state = PipelineState::Running;
+        drop(state);
 
-        // TODO: Actually start demuxing/decoding threads
-        // This would spawn worker tasks for:
-        // - Source reading
-        // - Demuxing
-        // - Video decoding
-        // - Audio decoding
+        self.ended.send_replace(false);
+        *self.clock_task.lock() = Some(self.spawn_clock());
 
         Ok(())
     }
 
-    /// Stops the pipeline
+    /// Pauses the pipeline
+    ///
+    /// The clock stops at the current position until [`start`] runs the
+    /// pipeline again.
+    ///
+    /// [`start`]: MediaPipeline::start
     ///
     /// # Returns
     ///
-    /// `Ok(())` on success, or an error
+    /// `Ok(())` on success, or an error if the pipeline is not running
     ///
     /// # Examples
     ///
@@ -233,14 +760,15 @@ impl MediaPipeline {
     ///
     /// pipeline.load_source(source).await?;
     /// pipeline.start().await?;
-    /// pipeline.stop().await?;
+    /// pipeline.pause().await?;
+    /// pipeline.start().await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn stop(&self) -> Result<(), MediaError> {
+    pub async fn pause(&self) -> Result<(), MediaError> {
         let mut state = self.state.write();
 
-        // Can stop from Running state
+        // Can pause from Running state
         if *state != PipelineState::Running {
             return Err(MediaError::InvalidStateTransition {
                 from: cortenbrowser_shared_types::SessionState::Idle,
@@ -248,19 +776,17 @@ impl MediaPipeline {
             });
         }
 
-        *state = PipelineState::Stopped;
+        *state = PipelineState::Ready;
+        drop(state);
 
-        // TODO: Actually stop worker threads
-        // This would cancel all worker tasks
+        if let Some(task) = self.clock_task.lock().take() {
+            task.abort();
+        }
 
         Ok(())
     }
 
-    /// Seeks to a specific position in the media
-    ///
-    /// # Arguments
-    ///
-    /// * `position` - Target seek position
+    /// Stops the pipeline
     ///
     /// # Returns
     ///
@@ -271,7 +797,6 @@ impl MediaPipeline {
     /// ```
     /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
     /// use cortenbrowser_shared_types::MediaSource;
-    /// use std::time::Duration;
     ///
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
@@ -282,126 +807,2233 @@ impl MediaPipeline {
     ///
     /// pipeline.load_source(source).await?;
     /// pipeline.start().await?;
-    /// pipeline.seek(Duration::from_secs(10)).await?;
+    /// pipeline.stop().await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn seek(&self, _position: Duration) -> Result<(), MediaError> {
-        let state = self.state.read();
+    pub async fn stop(&self) -> Result<(), MediaError> {
+        let mut state = self.state.write();
 
-        // Can only seek in Running or Ready states
-        if *state != PipelineState::Running && *state != PipelineState::Ready {
+        // Can stop from Running state
+        if *state != PipelineState::Running {
             return Err(MediaError::InvalidStateTransition {
                 from: cortenbrowser_shared_types::SessionState::Idle,
-                to: cortenbrowser_shared_types::SessionState::Seeking,
+                to: cortenbrowser_shared_types::SessionState::Paused,
             });
         }
 
-        // TODO: Actually seek in the media
-        // This would:
-        // - Flush buffers
-        // - Seek in the demuxer
-        // - Reset decoder state
-        // - Seek to nearest keyframe
+        *state = PipelineState::Stopped;
+        drop(state);
+
+        if let Some(task) = self.clock_task.lock().take() {
+            task.abort();
+        }
 
         Ok(())
     }
 
-    /// Gets the next video frame from the pipeline
-    ///
-    /// # Returns
+    /// Plays out the queued media, then stops the pipeline
     ///
-    /// The next video frame, or `None` if no frame is available
+    /// No more media is read or decoded. A running pipeline plays on until
+    /// its queued video frames and audio buffers have been taken, or the
+    /// clock has passed the end of the queued media, and then stops without
+    /// looping or reporting the end of the stream. A pipeline that is
+    /// ready but not running stops right away. The returned future
+    /// completes once the pipeline no longer runs; it can play again after
+    /// loading another source.
     ///
     /// # Examples
     ///
     /// ```
     /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::MediaSource;
     ///
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
     ///
-    /// let frame = pipeline.get_next_video_frame().await;
+    /// let source = MediaSource::Url {
+    ///     url: "file:///test/video.mp4".to_string(),
+    /// };
+    ///
+    /// pipeline.load_source(source).await?;
+    /// pipeline.start().await?;
+    /// pipeline.drain().await;
+    /// assert!(!pipeline.is_running());
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn get_next_video_frame(&self) -> Option<VideoFrame> {
-        let mut rx_guard = self.video_rx.write();
+    pub fn drain(&self) -> impl Future<Output = ()> + Send + 'static {
+        self.decoding.stop_decoding();
+        {
+            let mut state = self.state.write();
+            if *state == PipelineState::Ready {
+                *state = PipelineState::Stopped;
+            }
+        }
 
-        if let Some(rx) = rx_guard.as_mut() {
-            rx.try_recv().ok()
-        } else {
-            None
+        let state = Arc::clone(&self.state);
+        async move {
+            let mut ticker = tokio::time::interval(CLOCK_TICK);
+            while *state.read() == PipelineState::Running {
+                ticker.tick().await;
+            }
         }
     }
 
-    /// Gets the next audio buffer from the pipeline
+    /// Seeks to a specific position in the media
+    ///
+    /// When the media came from [`set_reader`] and its demuxer has a seek
+    /// index, the reader is read again from the byte offset of the latest
+    /// keyframes at or before `position`, and decoding restarts there with
+    /// an emptied video frame queue. Otherwise only the clock moves. Queued
+    /// audio is discarded in both cases.
+    ///
+    /// [`set_reader`]: MediaPipeline::set_reader
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - Target seek position
     ///
     /// # Returns
     ///
-    /// The next audio buffer, or `None` if no buffer is available
+    /// `Ok(())` on success, an error for invalid state transitions, or the
+    /// demuxer's or reader's error if the media cannot be read again
     ///
     /// # Examples
     ///
     /// ```
     /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::MediaSource;
+    /// use std::time::Duration;
     ///
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
     /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
     ///
-    /// let buffer = pipeline.get_next_audio_buffer().await;
+    /// let source = MediaSource::Url {
+    ///     url: "file:///test/video.mp4".to_string(),
+    /// };
+    ///
+    /// pipeline.load_source(source).await?;
+    /// pipeline.start().await?;
+    /// pipeline.seek(Duration::from_secs(10)).await?;
     /// # Ok(())
     /// # }
     /// ```
-    pub async fn get_next_audio_buffer(&self) -> Option<AudioBuffer> {
-        let mut rx_guard = self.audio_rx.write();
+    pub async fn seek(&self, position: Duration) -> Result<(), MediaError> {
+        {
+            let state = self.state.read();
 
-        if let Some(rx) = rx_guard.as_mut() {
-            rx.try_recv().ok()
-        } else {
-            None
+            // Can only seek in Running or Ready states
+            if *state != PipelineState::Running && *state != PipelineState::Ready {
+                return Err(MediaError::InvalidStateTransition {
+                    from: cortenbrowser_shared_types::SessionState::Idle,
+                    to: cortenbrowser_shared_types::SessionState::Seeking,
+                });
+            }
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        self.reposition(position)
+    }
 
-    #[tokio::test]
-    async fn test_new_pipeline() {
-        let config = PipelineConfig::default();
-        let result = MediaPipeline::new(config);
-        assert!(result.is_ok());
+    /// Moves playback to `position`, reading the media again from there
+    ///
+    /// The queued media is discarded and the audio decoder is
+    /// [reset](AudioDecoder::reset) before it decodes from `position`, so
+    /// none of the audio it held from before plays afterwards.
+    fn reposition(&self, position: Duration) -> Result<(), MediaError> {
+        self.sync_controller.set_clock(position);
+        self.time_stretcher.lock().reset();
+        self.ended.send_replace(false);
+        self.decoding.stats.reset_metrics();
+        self.decoding.seek(position).map(|_| ())
     }
 
-    #[tokio::test]
-    async fn test_state_transitions() {
-        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// Sets the playback rate
+    ///
+    /// The rate scales the A/V sync clock, and audio buffers from
+    /// [`get_next_audio_buffer`](MediaPipeline::get_next_audio_buffer) are
+    /// time-stretched to play at it.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate` - Playback rate, from 0.25 to 4.0 (1.0 = normal speed)
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success, or `InvalidParameter` if the rate is out of range
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// pipeline.set_playback_rate(1.5).unwrap();
+    /// assert_eq!(pipeline.playback_rate(), 1.5);
+    /// assert!(pipeline.set_playback_rate(8.0).is_err());
+    /// ```
+    pub fn set_playback_rate(&self, rate: f32) -> Result<(), MediaError> {
+        if !(MIN_PLAYBACK_RATE..=MAX_PLAYBACK_RATE).contains(&rate) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Playback rate must be between {} and {}, got {}",
+                MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE, rate
+            )));
+        }
 
-        // Initial state should be Idle
-        assert_eq!(*pipeline.state.read(), PipelineState::Idle);
+        self.sync_controller.set_rate(rate);
+        Ok(())
+    }
 
-        // Load source
-        let source = MediaSource::Url {
-            url: "file:///test.mp4".to_string(),
-        };
-        pipeline.load_source(source).await.unwrap();
+    /// Gets the current playback rate
+    pub fn playback_rate(&self) -> f32 {
+        self.sync_controller.rate()
+    }
 
-        // State should be Ready
-        assert_eq!(*pipeline.state.read(), PipelineState::Ready);
+    /// Sets how far a frame may be from the clock and still be displayed
+    ///
+    /// Replaces [`PipelineConfig::sync_threshold`] from the next
+    /// [`sync_frame`](MediaPipeline::sync_frame).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use std::time::Duration;
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// pipeline.set_sync_threshold(Duration::from_millis(20));
+    /// assert_eq!(pipeline.sync_threshold(), Duration::from_millis(20));
+    /// ```
+    pub fn set_sync_threshold(&self, threshold: Duration) {
+        self.sync_controller.set_threshold(threshold);
+    }
 
-        // Start pipeline
-        pipeline.start().await.unwrap();
+    /// Gets how far a frame may be from the clock and still be displayed
+    pub fn sync_threshold(&self) -> Duration {
+        self.sync_controller.threshold()
+    }
 
-        // State should be Running
+    /// Returns whether audio must be time-stretched to keep its pitch
+    ///
+    /// This is the case when pitch correction is enabled and the playback
+    /// rate differs from 1.0. The default [`ResampleStretcher`] does not
+    /// preserve pitch; install a stretcher that does with
+    /// [`set_time_stretcher`](MediaPipeline::set_time_stretcher).
+    pub fn needs_pitch_correction(&self) -> bool {
+        self.config.pitch_correct && self.playback_rate() != 1.0
+    }
+
+    /// Sets what playback does when it reaches the end of the media
+    ///
+    /// With [`LoopMode::All`] playback restarts from the beginning at the end
+    /// of the media; with [`LoopMode::AB`] it returns to `start` on reaching
+    /// `end`.
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` on success, or `InvalidParameter` if an AB loop does not
+    /// start before it ends
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::LoopMode;
+    /// use std::time::Duration;
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// pipeline.set_loop_mode(LoopMode::All).unwrap();
+    /// assert_eq!(pipeline.loop_mode(), LoopMode::All);
+    ///
+    /// let backwards = LoopMode::AB {
+    ///     start: Duration::from_secs(2),
+    ///     end: Duration::from_secs(1),
+    /// };
+    /// assert!(pipeline.set_loop_mode(backwards).is_err());
+    /// ```
+    pub fn set_loop_mode(&self, mode: LoopMode) -> Result<(), MediaError> {
+        validate_loop_mode(mode)?;
+        *self.loop_mode.write() = mode;
+        Ok(())
+    }
+
+    /// Gets the current loop mode
+    pub fn loop_mode(&self) -> LoopMode {
+        *self.loop_mode.read()
+    }
+
+    /// Sets the duration of the loaded media
+    ///
+    /// Playback ends, or loops, when the media clock reaches the duration.
+    pub fn set_media_duration(&self, duration: Duration) {
+        *self.duration.write() = Some(duration);
+    }
+
+    /// Gets the current playback position
+    ///
+    /// The position is the A/V sync clock: it advances while the pipeline
+    /// runs, follows the audio clock once one is attached, and moves to the
+    /// target of a [`seek`](MediaPipeline::seek).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::MediaSource;
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
+    /// pipeline
+    ///     .load_source(MediaSource::Url {
+    ///         url: "file:///test/video.mp4".to_string(),
+    ///     })
+    ///     .await?;
+    ///
+    /// pipeline.seek(Duration::from_secs(10)).await?;
+    /// assert_eq!(pipeline.current_position(), Duration::from_secs(10));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn current_position(&self) -> Duration {
+        self.sync_controller.get_clock()
+    }
+
+    /// Returns whether the pipeline is running, i.e. started and not yet at
+    /// the end of the media
+    pub fn is_running(&self) -> bool {
+        *self.state.read() == PipelineState::Running
+    }
+
+    /// Decides whether a video frame is due for display at the current
+    /// playback position
+    ///
+    /// See [`AVSyncController::sync_frame`].
+    pub fn sync_frame(&self, frame: &VideoFrame) -> SyncDecision {
+        let decision = self
+            .sync_controller
+            .sync_frame(frame, self.current_position());
+        if decision == SyncDecision::Drop {
+            self.decoding.stats.frame_dropped();
+        }
+        decision
+    }
+
+    /// Gets the number of times playback has looped
+    pub fn loop_count(&self) -> u64 {
+        *self.loop_count.borrow()
+    }
+
+    /// Subscribes to loop notifications
+    ///
+    /// The receiver sees the new loop count each time playback loops.
+    pub fn subscribe_loops(&self) -> watch::Receiver<u64> {
+        self.loop_count.subscribe()
+    }
+
+    /// Returns whether playback has reached the end of the media
+    ///
+    /// This is only the case without looping; a seek or restart clears it.
+    pub fn is_ended(&self) -> bool {
+        *self.ended.borrow()
+    }
+
+    /// Subscribes to end-of-stream notifications
+    ///
+    /// The receiver sees `true` when playback reaches the end of the media
+    /// without looping, and `false` when a seek or restart resumes it.
+    pub fn subscribe_ended(&self) -> watch::Receiver<bool> {
+        self.ended.subscribe()
+    }
+
+    /// Gets the error that stopped decoding, if the pipeline is in its
+    /// error state
+    ///
+    /// Decoding stops on a codec error under the
+    /// [`Fail`](crate::RecoveryPolicy::Fail) recovery policy, when a decoder
+    /// cannot be created again under
+    /// [`ReinitDecoder`](crate::RecoveryPolicy::ReinitDecoder), or once more
+    /// codec errors than [`PipelineConfig::max_codec_errors`] occur within
+    /// the [window](PipelineConfig::codec_error_window), whatever the policy.
+    /// Playback stops with it, and only
+    /// [`load_source`](MediaPipeline::load_source) leaves the error state.
+    pub fn error(&self) -> Option<MediaError> {
+        self.failed.borrow().clone()
+    }
+
+    /// Subscribes to pipeline errors
+    ///
+    /// The receiver sees the error when decoding puts the pipeline in its
+    /// error state, and `None` when a source is loaded again.
+    pub fn subscribe_error(&self) -> watch::Receiver<Option<MediaError>> {
+        self.failed.subscribe()
+    }
+
+    /// Returns whether decoding waits for the key of an encrypted packet
+    pub fn is_waiting_for_key(&self) -> bool {
+        *self.decoding.decryption.waiting_for_key.borrow()
+    }
+
+    /// Subscribes to waiting-for-key notifications
+    ///
+    /// The receiver sees `true` when decoding stops at an encrypted packet
+    /// whose key is not usable, and `false` once it continues.
+    pub fn subscribe_waiting_for_key(&self) -> watch::Receiver<bool> {
+        self.decoding.decryption.waiting_for_key.subscribe()
+    }
+
+    /// Gets the time ranges of the media the pipeline has queued
+    ///
+    /// Each decoding run, from loading or a seek on, buffers one contiguous
+    /// range of each track; a range is buffered where every track that has
+    /// queued media is. Ranges stay buffered after their media has been
+    /// played. They are sorted by start and do not overlap.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
+    /// for i in 0..25 {
+    ///     let mut frame =
+    ///         VideoFrame::new(2, 2, PixelFormat::YUV420, vec![0; 6], Duration::from_millis(i * 40));
+    ///     frame.duration = Some(Duration::from_millis(40));
+    ///     pipeline.push_video_frame(frame).await?;
+    /// }
+    ///
+    /// assert_eq!(
+    ///     pipeline.buffered_ranges(),
+    ///     [(Duration::ZERO, Duration::from_secs(1))]
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn buffered_ranges(&self) -> Vec<(Duration, Duration)> {
+        self.decoding.buffered_ranges()
+    }
+
+    /// Gets the range of media time that can be sought to
+    ///
+    /// A source read through [`set_reader`](MediaPipeline::set_reader) can be
+    /// read again from anywhere, so all of its duration is seekable, as is
+    /// media queued by the caller once its duration is set. A stream
+    /// [fed](MediaPipeline::feed) in chunks cannot be read back, so only the
+    /// span of its [buffered ranges](MediaPipeline::buffered_ranges) is
+    /// seekable, growing as more of the stream arrives.
+    ///
+    /// The range is empty, from zero to zero, while nothing of the media is
+    /// known.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use std::time::Duration;
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// assert_eq!(pipeline.seekable_range(), (Duration::ZERO, Duration::ZERO));
+    ///
+    /// pipeline.set_media_duration(Duration::from_secs(30));
+    /// assert_eq!(
+    ///     pipeline.seekable_range(),
+    ///     (Duration::ZERO, Duration::from_secs(30))
+    /// );
+    /// ```
+    pub fn seekable_range(&self) -> (Duration, Duration) {
+        let streamed = self.has_demuxer() && !self.decoding.has_reader();
+        match *self.duration.read() {
+            Some(duration) if !streamed => (Duration::ZERO, duration),
+            _ => {
+                let buffered = self.buffered_ranges();
+                match (buffered.first(), buffered.last()) {
+                    (Some(&(start, _)), Some(&(_, end))) => (start, end),
+                    _ => (Duration::ZERO, Duration::ZERO),
+                }
+            }
+        }
+    }
+
+    /// Gets how far the buffered media reaches ahead of the current position
+    ///
+    /// Zero if the position is not in a [buffered
+    /// range](MediaPipeline::buffered_ranges).
+    pub fn buffered_ahead(&self) -> Duration {
+        self.decoding.buffered_ahead(self.current_position())
+    }
+
+    /// Returns whether every video frame of the media has been decoded
+    ///
+    /// Only media decoded by the pipeline is ever fully decoded; frames
+    /// queued with [`push_video_frame`](MediaPipeline::push_video_frame)
+    /// may always be followed by more.
+    pub fn is_decoded(&self) -> bool {
+        self.decoding.is_decoded()
+    }
+
+    /// Returns whether video playback has run out of decoded frames
+    ///
+    /// This is the case while the pipeline runs with an empty video queue,
+    /// nothing buffered ahead of the current position and more video still
+    /// to be decoded, e.g. because a streamed source has not been fed fast
+    /// enough. Media without video never underruns.
+    pub fn is_underrun(&self) -> bool {
+        self.is_running() && self.decoding.is_underrun(self.current_position())
+    }
+
+    /// Gets the pipeline's decoding and playback counters
+    ///
+    /// The counters are kept as playback goes, so this is cheap to call.
+    /// Underruns are counted by the running clock, each time
+    /// [`is_underrun`](MediaPipeline::is_underrun) becomes true; audio
+    /// underruns each time the queued audio stops covering the position.
+    /// Frames displayed and dropped are those decided by
+    /// [`sync_frame`](MediaPipeline::sync_frame).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// let stats = pipeline.stats();
+    /// assert_eq!(stats.frames_decoded, 0);
+    /// assert_eq!(stats.queued_bytes, 0);
+    /// ```
+    pub fn stats(&self) -> PipelineStats {
+        let (video_queue_depth, audio_queue_depth) = self.decoding.queue_depths();
+        PipelineStats {
+            frames_displayed: self.sync_controller.frames_displayed(),
+            frames_dropped: self.sync_controller.frames_dropped(),
+            video_queue_depth,
+            audio_queue_depth,
+            ..self.decoding.stats.snapshot()
+        }
+    }
+
+    /// Gets the pipeline's performance metrics
+    ///
+    /// Unlike [`stats`](MediaPipeline::stats), the metrics start over when
+    /// a source is loaded and on every seek, so the decode latencies
+    /// average only what was decoded since. Audio buffers are counted as
+    /// they are queued.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// let metrics = pipeline.get_metrics();
+    /// assert_eq!(metrics.total_frames_produced, 0);
+    /// assert_eq!(metrics.avg_video_decode_latency_ms, 0.0);
+    /// ```
+    pub fn get_metrics(&self) -> PipelineMetrics {
+        let (video_queue_depth, audio_queue_depth) = self.decoding.queue_depths();
+        PipelineMetrics {
+            video_queue_depth,
+            audio_queue_depth,
+            ..self.decoding.stats.metrics()
+        }
+    }
+
+    /// Plays the loaded source as an adaptive stream of `representations`
+    ///
+    /// Playback starts with the lowest bitrate representation. While the
+    /// pipeline runs, the media buffered ahead of the clock is checked
+    /// against [`PipelineConfig::abr`]'s thresholds, switching to a higher
+    /// or lower bitrate representation as it fills or drains. A switch is
+    /// seamless: the source becomes the new representation's URL, from
+    /// which the following segments are read, while the media already
+    /// queued keeps playing and the clock is not moved.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidState` if no source is loaded, and `InvalidParameter`
+    /// if there are no representations or the thresholds are inverted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig, Representation};
+    /// use cortenbrowser_shared_types::MediaSource;
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
+    /// let source = MediaSource::Url {
+    ///     url: "https://example.com/manifest.mpd".to_string(),
+    /// };
+    /// pipeline.load_source(source).await?;
+    ///
+    /// pipeline.set_representations(vec![Representation {
+    ///     id: "720p".to_string(),
+    ///     bandwidth: 3_000_000,
+    ///     url: "https://example.com/720p/".to_string(),
+    /// }])?;
+    /// assert_eq!(pipeline.representation().unwrap().id, "720p");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_representations(
+        &self,
+        representations: Vec<Representation>,
+    ) -> Result<(), MediaError> {
+        if self.source.read().is_none() {
+            return Err(MediaError::InvalidState("No source loaded".to_string()));
+        }
+        let abr = AbrController::new(representations, self.config.abr)?;
+        let current = abr.current().clone();
+
+        *self.source.write() = Some(MediaSource::Url {
+            url: current.url.clone(),
+        });
+        *self.abr.lock() = Some(abr);
+        self.representation.send_replace(Some(current));
+        Ok(())
+    }
+
+    /// Gets the representation of the adaptive stream being played
+    pub fn representation(&self) -> Option<Representation> {
+        self.representation.borrow().clone()
+    }
+
+    /// Subscribes to representation switches of an adaptive stream
+    pub fn subscribe_representation(&self) -> watch::Receiver<Option<Representation>> {
+        self.representation.subscribe()
+    }
+
+    /// Spawns the task that advances the media clock and handles the end of
+    /// the media
+    ///
+    /// The end is reached when the clock passes the media duration, or the
+    /// loop end, or when the decoded media has been drained from the queues.
+    /// At the media duration, the audio decoder's output, including what it
+    /// flushes at the end of the media, is taken from the queue first. The
+    /// pipeline then stops. If looping is enabled it reads the media
+    /// again from the loop start, with emptied queues, and runs again;
+    /// otherwise it reports the end of the stream. Adaptive streams switch
+    /// representations by the media buffered ahead of the clock. The task
+    /// stops once decoding has put the pipeline in its error state.
+    fn spawn_clock(&self) -> JoinHandle<()> {
+        let state = Arc::clone(&self.state);
+        let sync = Arc::clone(&self.sync_controller);
+        let loop_mode = Arc::clone(&self.loop_mode);
+        let duration = Arc::clone(&self.duration);
+        let loop_count = Arc::clone(&self.loop_count);
+        let ended = Arc::clone(&self.ended);
+        let decoding = self.decoding.clone();
+        let time_stretcher = Arc::clone(&self.time_stretcher);
+        let source = Arc::clone(&self.source);
+        let abr = Arc::clone(&self.abr);
+        let representation = Arc::clone(&self.representation);
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(CLOCK_TICK);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut last = Instant::now();
+            let mut underrun = false;
+            let mut audio_underrun = false;
+
+            loop {
+                ticker.tick().await;
+                if *state.read() == PipelineState::Error {
+                    break;
+                }
+                let now = Instant::now();
+                let position = sync.advance(now - last);
+                last = now;
+
+                let was_underrun = std::mem::replace(&mut underrun, decoding.is_underrun(position));
+                if underrun && !was_underrun {
+                    decoding.stats.underrun();
+                }
+                let before_end = duration.read().is_none_or(|duration| position < duration);
+                let was_audio_underrun = std::mem::replace(
+                    &mut audio_underrun,
+                    before_end && decoding.is_audio_underrun(position),
+                );
+                if audio_underrun && !was_audio_underrun {
+                    decoding.stats.audio_underrun();
+                }
+
+                let switched = abr.lock().as_mut().and_then(|abr| {
+                    abr.update(decoding.buffered_ahead(position), now)
+                        .map(|_| abr.current().clone())
+                });
+                if let Some(switched) = switched {
+                    *source.write() = Some(MediaSource::Url {
+                        url: switched.url.clone(),
+                    });
+                    representation.send_replace(Some(switched));
+                }
+
+                let mode = *loop_mode.read();
+                let end = match (mode, *duration.read()) {
+                    (LoopMode::AB { end, .. }, Some(duration)) => Some(end.min(duration)),
+                    (LoopMode::AB { end, .. }, None) => Some(end),
+                    (_, duration) => duration,
+                };
+                let clock_ended = end.is_some_and(|end| position >= end);
+                let drained = decoding.is_drained(position);
+                if decoding.is_draining()
+                    && (clock_ended || drained || decoding.is_played_out(position))
+                {
+                    set_unless_failed(&state, PipelineState::Stopped);
+                    break;
+                }
+                if !clock_ended && !drained {
+                    continue;
+                }
+                let at_media_end = !matches!(mode, LoopMode::AB { .. });
+                if !drained && at_media_end && !decoding.is_audio_drained() {
+                    continue;
+                }
+
+                // End of media
+                if !set_unless_failed(&state, PipelineState::Stopped) {
+                    break;
+                }
+                let restart = match mode {
+                    LoopMode::None => {
+                        ended.send_replace(true);
+                        break;
+                    }
+                    LoopMode::All => Duration::ZERO,
+                    LoopMode::AB { start, .. } => start,
+                };
+
+                // Media that cannot be read again only loops by the clock
+                let reread = decoding.seek(restart);
+                if drained && !matches!(reread, Ok(true)) {
+                    ended.send_replace(true);
+                    break;
+                }
+                time_stretcher.lock().reset();
+
+                sync.set_clock(restart);
+                if !set_unless_failed(&state, PipelineState::Running) {
+                    break;
+                }
+                loop_count.send_modify(|count| *count += 1);
+            }
+        })
+    }
+
+    /// Gets the next video frame from the pipeline
+    ///
+    /// # Returns
+    ///
+    /// The next video frame, or `None` if no frame is available
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
+    ///
+    /// let frame = pipeline.get_next_video_frame().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_next_video_frame(&self) -> Option<VideoFrame> {
+        let frame = self.decoding.video_rx.write().as_mut()?.try_recv().ok()?;
+        self.decoding.stats.remove_video_bytes(frame.data.len());
+        Some(frame)
+    }
+
+    /// Queues a decoded video frame for display
+    ///
+    /// Waits while the video queue is full.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::InvalidState` if video is disabled or the video
+    /// queue has been closed.
+    pub async fn push_video_frame(&self, frame: VideoFrame) -> Result<(), MediaError> {
+        if !self.config.enable_video {
+            return Err(MediaError::InvalidState("Video is disabled".to_string()));
+        }
+        let (start, end) = decode::frame_range(&frame);
+        let bytes = frame.data.len();
+        let video_tx = self.decoding.video_tx.lock().clone();
+        self.decoding.stats.add_video_bytes(bytes);
+        if video_tx.send(frame).await.is_err() {
+            self.decoding.stats.remove_video_bytes(bytes);
+            return Err(MediaError::InvalidState("Video queue closed".to_string()));
+        }
+        self.decoding.video_buffered.lock().add(start, end);
+        Ok(())
+    }
+
+    /// Queues a decoded audio buffer for playout
+    ///
+    /// Waits while the audio queue is full.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::InvalidState` if audio is disabled or the audio
+    /// queue has been closed.
+    pub async fn push_audio_buffer(&self, buffer: AudioBuffer) -> Result<(), MediaError> {
+        if !self.config.enable_audio {
+            return Err(MediaError::InvalidState("Audio is disabled".to_string()));
+        }
+        let (start, end) = (buffer.timestamp, buffer.timestamp + buffer.duration);
+        let bytes = stats::audio_bytes(buffer.samples.len());
+        let audio_tx = self.decoding.audio_queue.lock().tx.clone();
+        self.decoding.stats.add_audio_bytes(bytes);
+        if audio_tx.send(buffer).await.is_err() {
+            self.decoding.stats.remove_audio_bytes(bytes);
+            return Err(MediaError::InvalidState("Audio queue closed".to_string()));
+        }
+        self.decoding.stats.audio_buffer_queued();
+        self.decoding.audio_buffered.lock().add(start, end);
+        Ok(())
+    }
+
+    /// Sets how video decoders are created
+    ///
+    /// The default creates software decoders with the `video_decoders`
+    /// factory. Applies to decoding started afterwards, such as when the
+    /// media is first read, on a seek or on a track change.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::MediaError;
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// pipeline.set_decoder_factory(|codec| {
+    ///     Err(MediaError::UnsupportedFormat {
+    ///         format: format!("{:?}", codec),
+    ///     })
+    /// });
+    /// ```
+    pub fn set_decoder_factory(
+        &self,
+        factory: impl Fn(&VideoCodec) -> Result<Box<dyn VideoDecoder>, MediaError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        *self.decoding.recovery.decoder_factory.write() =
+            decode::DecoderFactorySlot(Arc::new(factory));
+    }
+
+    /// Sets how audio decoders are created
+    ///
+    /// The default creates decoders with the `audio_decoders` factory.
+    /// Applies to decoding started afterwards, such as when the media is
+    /// first read or on a track change.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    /// use cortenbrowser_shared_types::MediaError;
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// pipeline.set_audio_decoder_factory(|codec| {
+    ///     Err(MediaError::UnsupportedFormat {
+    ///         format: format!("{:?}", codec),
+    ///     })
+    /// });
+    /// ```
+    pub fn set_audio_decoder_factory(
+        &self,
+        factory: impl Fn(&AudioCodec) -> Result<Box<dyn AudioDecoder>, MediaError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        *self.decoding.audio_decoder_factory.write() =
+            decode::AudioDecoderFactorySlot(Arc::new(factory));
+    }
+
+    /// Sets how encrypted packets are decrypted before they are decoded
+    ///
+    /// Without a decryptor, or while it returns `Ok(None)` because the key
+    /// is not usable, decoding waits at the encrypted packet and
+    /// [`is_waiting_for_key`](Self::is_waiting_for_key) is set. Packets it
+    /// fails to decrypt are counted as decode failures and skipped. Applies
+    /// to the running decoders too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    ///
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    /// // A key system without usable keys
+    /// pipeline.set_decryptor(|_data, _info| Ok(None));
+    /// ```
+    pub fn set_decryptor(
+        &self,
+        decryptor: impl Fn(&[u8], &EncryptionInfo) -> Result<Option<Vec<u8>>, MediaError>
+            + Send
+            + Sync
+            + 'static,
+    ) {
+        self.decoding.decryption.decryptor.write().0 = Some(Arc::new(decryptor));
+        self.decoding.wake();
+    }
+
+    /// Replaces the stage that time-stretches audio to the playback rate
+    ///
+    /// The default is a [`ResampleStretcher`]. This is where a
+    /// pitch-preserving stretcher plugs in.
+    pub fn set_time_stretcher(&self, stretcher: Box<dyn TimeStretcher>) {
+        *self.time_stretcher.lock() = stretcher;
+    }
+
+    /// Uses an audio sink's played duration as the media clock
+    ///
+    /// See [`AVSyncController::set_audio_clock`].
+    pub fn set_audio_clock(&self, sink: Option<Arc<dyn AudioSink>>) {
+        self.sync_controller.set_audio_clock(sink);
+    }
+
+    /// Gets the next audio buffer from the pipeline
+    ///
+    /// The buffer is time-stretched to the playback rate, so its duration is
+    /// how long it takes to play while its timestamp stays in media time.
+    ///
+    /// # Returns
+    ///
+    /// The next audio buffer, or `None` if no buffer is available
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
+    ///
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let pipeline = MediaPipeline::new(PipelineConfig::default())?;
+    ///
+    /// let buffer = pipeline.get_next_audio_buffer().await;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_next_audio_buffer(&self) -> Option<AudioBuffer> {
+        let buffer = self.decoding.audio_rx.write().as_mut()?.try_recv().ok()?;
+        self.decoding
+            .stats
+            .remove_audio_bytes(stats::audio_bytes(buffer.samples.len()));
+        let rate = self.playback_rate();
+        Some(self.time_stretcher.lock().process(buffer, rate))
+    }
+}
+
+impl Drop for MediaPipeline {
+    fn drop(&mut self) {
+        if let Some(task) = self.clock_task.get_mut().take() {
+            task.abort();
+        }
+        if let Some(decoder) = self.decoding.video_decoder.lock().take() {
+            decoder.cancel();
+        }
+        if let Some(decoder) = self.decoding.audio_decoder.lock().take() {
+            decoder.cancel();
+        }
+    }
+}
+
+/// Demuxing and decoding of the loaded media, and the queues of decoded
+/// media
+///
+/// Shared with the clock task, which reads the media again when playback
+/// loops.
+#[derive(Debug, Clone)]
+struct Decoding {
+    /// Demuxer selected for the loaded source
+    demuxer: Arc<Mutex<Option<Box<dyn Demuxer + Send>>>>,
+    /// Packets read from the demuxer for the other decoder thread
+    pending: decode::PendingPackets,
+    /// Media information from the demuxer's initial parse
+    media_info: Arc<RwLock<Option<MediaInfo>>>,
+    /// Running video decoder thread
+    video_decoder: Arc<Mutex<Option<decode::VideoDecoderHandle>>>,
+    /// Running audio decoder thread
+    audio_decoder: Arc<Mutex<Option<decode::AudioDecoderHandle>>>,
+    /// Reader the media data came from, kept for reading again after seeks
+    reader: Arc<Mutex<Option<Box<dyn MediaReader>>>>,
+    /// Video frame queue (sender), replaced on seeks
+    video_tx: Arc<Mutex<mpsc::Sender<VideoFrame>>>,
+    /// Video frame queue (receiver)
+    video_rx: Arc<RwLock<Option<mpsc::Receiver<VideoFrame>>>>,
+    /// Audio buffer queue (sender)
+    audio_queue: Arc<Mutex<decode::AudioQueue>>,
+    /// Audio buffer queue (receiver)
+    audio_rx: Arc<RwLock<Option<mpsc::Receiver<AudioBuffer>>>>,
+    /// Media time of the video queued so far
+    video_buffered: Arc<Mutex<BufferedRanges>>,
+    /// Media time of the audio queued so far
+    audio_buffered: Arc<Mutex<BufferedRanges>>,
+    /// Decoding and playback counters
+    stats: Arc<StatsCounters>,
+    /// ID of the video track to decode, the first one if unset
+    video_track: Arc<Mutex<Option<u32>>>,
+    /// ID of the audio track to play, the first one if unset
+    audio_track: Arc<Mutex<Option<u32>>>,
+    /// Decrypts encrypted packets before they are decoded
+    decryption: decode::Decryption,
+    /// Creates video decoders and recovers from their codec errors
+    recovery: decode::Recovery,
+    /// Creates audio decoders
+    audio_decoder_factory: Arc<RwLock<decode::AudioDecoderFactorySlot>>,
+    /// Whether the pipeline is draining, decoding no more media
+    draining: Arc<AtomicBool>,
+    /// Capacity of the video frame queue
+    buffer_size: usize,
+    /// Whether video is decoded at all
+    enable_video: bool,
+    /// Whether audio is decoded at all
+    enable_audio: bool,
+    /// Clock giving the playback position decoding stays ahead of
+    clock: Arc<AVSyncController>,
+    /// How far ahead of the playback position media is decoded
+    max_buffer_ahead: Duration,
+}
+
+impl Decoding {
+    /// Records parsed media information and starts decoding its selected
+    /// tracks
+    ///
+    /// The video decoder replaces any running one, unless video is
+    /// disabled. The audio decoder is started as by
+    /// [`start_audio`](Decoding::start_audio). Both are started with the
+    /// demuxer locked, so neither reads a packet of the other's track
+    /// before that track is registered.
+    fn start(&self, info: &MediaInfo) {
+        let _demuxer = self.demuxer.lock();
+        self.start_decoders(info);
+    }
+
+    /// Does the work of [`start`](Decoding::start), with the demuxer locked
+    /// by the caller
+    fn start_decoders(&self, info: &MediaInfo) {
+        *self.media_info.write() = Some(info.clone());
+        if self.is_draining() {
+            return;
+        }
+        self.start_audio(info);
+        if !self.enable_video {
+            return;
+        }
+
+        let selected = self.selected_video_track(info);
+        let track = info
+            .video_tracks
+            .iter()
+            .find(|track| Some(track.track_id) == selected);
+        let decoder = track.map(|track| {
+            decode::spawn_video_decoder(
+                self.packet_source(),
+                track.clone(),
+                self.recovery.clone(),
+                self.decryption.clone(),
+                self.video_tx.lock().clone(),
+                decode::Readahead {
+                    buffered: Arc::clone(&self.video_buffered),
+                    clock: Arc::clone(&self.clock),
+                    limit: self.max_buffer_ahead,
+                },
+                Arc::clone(&self.stats),
+            )
+        });
+        let previous = std::mem::replace(&mut *self.video_decoder.lock(), decoder);
+        if let Some(previous) = previous {
+            previous.cancel();
+        }
+    }
+
+    /// Starts decoding the selected audio track, unless audio is disabled
+    /// or its decoder is running already
+    ///
+    /// A decoder running for another track is replaced.
+    fn start_audio(&self, info: &MediaInfo) {
+        if self.is_draining() || !self.enable_audio {
+            return;
+        }
+        let selected = self.selected_audio_track(info);
+        let mut running = self.audio_decoder.lock();
+        if running.as_ref().map(|decoder| decoder.track_id()) == selected {
+            return;
+        }
+
+        let track = info
+            .audio_tracks
+            .iter()
+            .find(|track| Some(track.track_id) == selected);
+        let decoder = track.map(|track| {
+            decode::spawn_audio_decoder(
+                self.packet_source(),
+                track.clone(),
+                Arc::clone(&self.audio_decoder_factory),
+                self.decryption.clone(),
+                Arc::clone(&self.audio_queue),
+                decode::Readahead {
+                    buffered: Arc::clone(&self.audio_buffered),
+                    clock: Arc::clone(&self.clock),
+                    limit: self.max_buffer_ahead,
+                },
+                Arc::clone(&self.stats),
+            )
+        });
+        if let Some(previous) = std::mem::replace(&mut *running, decoder) {
+            previous.cancel();
+        }
+    }
+
+    /// Returns the demuxer for decoder threads to read from
+    fn packet_source(&self) -> decode::PacketSource {
+        decode::PacketSource {
+            demuxer: Arc::clone(&self.demuxer),
+            pending: self.pending.clone(),
+        }
+    }
+
+    /// Lets the decoders finish once the demuxer has no more packets
+    fn end_input(&self) {
+        if let Some(decoder) = self.video_decoder.lock().as_ref() {
+            decoder.end_input();
+        }
+        if let Some(decoder) = self.audio_decoder.lock().as_ref() {
+            decoder.end_input();
+        }
+    }
+
+    /// Wakes the decoders after more data has been fed to the demuxer, or
+    /// a decryptor has been set
+    fn wake(&self) {
+        if let Some(decoder) = self.video_decoder.lock().as_ref() {
+            decoder.wake();
+        }
+        if let Some(decoder) = self.audio_decoder.lock().as_ref() {
+            decoder.wake();
+        }
+    }
+
+    /// Returns the ID of the video track that is decoded
+    fn selected_video_track(&self, info: &MediaInfo) -> Option<u32> {
+        let selected = *self.video_track.lock();
+        info.video_tracks
+            .iter()
+            .find(|track| Some(track.track_id) == selected)
+            .or(info.video_tracks.first())
+            .map(|track| track.track_id)
+    }
+
+    /// Returns the ID of the audio track that plays
+    fn selected_audio_track(&self, info: &MediaInfo) -> Option<u32> {
+        let selected = *self.audio_track.lock();
+        info.audio_tracks
+            .iter()
+            .find(|track| Some(track.track_id) == selected)
+            .or(info.audio_tracks.first())
+            .map(|track| track.track_id)
+    }
+
+    /// Reads the media again from the keyframes at or before `position`
+    ///
+    /// Queued audio is discarded either way and the audio decoder reset, so
+    /// audio queued afterwards starts a new buffered range. So does video,
+    /// once decoding restarts or if it is queued by the caller rather than a
+    /// decoder.
+    ///
+    /// # Returns
+    ///
+    /// Whether decoding restarted, which needs a reader and a seek index
+    fn seek(&self, position: Duration) -> Result<bool, MediaError> {
+        if self.video_decoder.lock().is_none() {
+            self.video_buffered.lock().restart();
+        }
+        let restarted = self.restart_decoding(position, true);
+        if !matches!(restarted, Ok(true)) {
+            self.flush_audio();
+        }
+        restarted
+    }
+
+    /// Discards the queued audio and resets the audio decoder, so audio
+    /// queued afterwards starts a new buffered range
+    fn flush_audio(&self) {
+        let mut queue = self.audio_queue.lock();
+        queue.generation += 1;
+        if let Some(rx) = self.audio_rx.write().as_mut() {
+            while let Ok(buffer) = rx.try_recv() {
+                self.stats
+                    .remove_audio_bytes(stats::audio_bytes(buffer.samples.len()));
+            }
+        }
+        self.audio_buffered.lock().restart();
+        if let Some(decoder) = self.audio_decoder.lock().as_ref() {
+            decoder.reset();
+        }
+    }
+
+    /// Restarts video decoding from the keyframes at or before `position`,
+    /// while audio carries on
+    ///
+    /// # Returns
+    ///
+    /// Whether decoding restarted, which needs a reader and a seek index
+    fn restart_video(&self, position: Duration) -> Result<bool, MediaError> {
+        if !self.enable_video {
+            return Ok(false);
+        }
+        self.restart_decoding(position, false)
+    }
+
+    /// Reads the media again from the keyframes at or before `position`
+    ///
+    /// Video decoding starts over with an emptied queue. With
+    /// `flush_audio`, the queued audio is discarded and the audio decoder
+    /// reset too; otherwise it decodes on from the packets after those it
+    /// has decoded.
+    ///
+    /// # Returns
+    ///
+    /// Whether decoding restarted, which needs a reader and a seek index
+    fn restart_decoding(&self, position: Duration, flush_audio: bool) -> Result<bool, MediaError> {
+        let Some(info) = self.media_info.read().clone() else {
+            return Ok(false);
+        };
+        let mut reader = self.reader.lock();
+        let Some(reader) = reader.as_mut() else {
+            return Ok(false);
+        };
+
+        {
+            let mut demuxer = self.demuxer.lock();
+            let Some(demuxer) = demuxer.as_mut().filter(|d| d.seek_index().is_some()) else {
+                return Ok(false);
+            };
+
+            let offset = demuxer.seek(position)?;
+            // The demuxer stays locked, so the old decoder reads nothing more
+            if let Some(decoder) = self.video_decoder.lock().take() {
+                decoder.cancel();
+            }
+            self.video_buffered.lock().restart();
+            self.pending.clear();
+            // Also with the demuxer locked, so the audio decoder resets at
+            // the first packet read from the new position
+            if flush_audio {
+                self.flush_audio();
+            }
+            let data = read_from(reader.as_mut(), offset)?;
+            demuxer.feed(&data)?;
+            demuxer.end_of_stream();
+
+            // Frames decoded before the seek go with the old queue
+            let (video_tx, video_rx) = mpsc::channel(self.buffer_size);
+            *self.video_tx.lock() = video_tx;
+            *self.video_rx.write() = Some(video_rx);
+            self.stats.clear_video_bytes();
+
+            // Still with the demuxer locked, so the running audio decoder
+            // reads no video packet before the new video decoder's track
+            // is registered
+            self.start_decoders(&info);
+        }
+        self.end_input();
+        Ok(true)
+    }
+
+    /// Returns whether the media is read through a reader, which can read
+    /// it again from any offset
+    fn has_reader(&self) -> bool {
+        self.reader.lock().is_some()
+    }
+
+    /// Returns whether every video frame of the media has been decoded
+    ///
+    /// Never the case for media without a video decoder.
+    fn is_decoded(&self) -> bool {
+        self.video_decoder
+            .lock()
+            .as_ref()
+            .is_some_and(|decoder| decoder.is_finished())
+    }
+
+    /// Returns whether the media has a video track, decoded by the pipeline
+    /// or queued by the caller
+    fn has_video(&self) -> bool {
+        self.video_decoder.lock().is_some() || !self.video_buffered.lock().is_empty()
+    }
+
+    /// Returns whether no decoded video is waiting in the queue
+    fn is_video_queue_empty(&self) -> bool {
+        self.video_rx.read().as_ref().is_none_or(|rx| rx.is_empty())
+    }
+
+    /// Returns whether video playback at `position` has run out of decoded
+    /// frames
+    fn is_underrun(&self, position: Duration) -> bool {
+        self.has_video()
+            && !self.is_decoded()
+            && !self.is_draining()
+            && self.is_video_queue_empty()
+            && self.buffered_ahead(position).is_zero()
+    }
+
+    /// Returns whether audio has been queued, but none for `position`
+    fn is_audio_underrun(&self, position: Duration) -> bool {
+        let audio = self.audio_buffered.lock();
+        !audio.is_empty()
+            && !audio
+                .ranges()
+                .iter()
+                .any(|&(start, end)| start <= position && position < end)
+    }
+
+    /// Returns the number of video frames and audio buffers queued
+    fn queue_depths(&self) -> (usize, usize) {
+        let video = self.video_rx.read().as_ref().map_or(0, |rx| rx.len());
+        let audio = self.audio_rx.read().as_ref().map_or(0, |rx| rx.len());
+        (video, audio)
+    }
+
+    /// Returns how far the buffered media reaches ahead of `position`
+    fn buffered_ahead(&self, position: Duration) -> Duration {
+        self.buffered_ranges()
+            .into_iter()
+            .find(|&(start, end)| start <= position && position < end)
+            .map_or(Duration::ZERO, |(_, end)| end - position)
+    }
+
+    /// Returns the ranges buffered on every track that has queued media
+    fn buffered_ranges(&self) -> Vec<(Duration, Duration)> {
+        let video = self.video_buffered.lock();
+        let audio = self.audio_buffered.lock();
+        match (video.is_empty(), audio.is_empty()) {
+            (false, false) => buffered::intersect(video.ranges(), audio.ranges()),
+            (false, true) => video.ranges().to_vec(),
+            (true, _) => audio.ranges().to_vec(),
+        }
+    }
+
+    /// Returns whether all of the media has been decoded and taken from the
+    /// queues, and the decoded audio played out at `position`
+    ///
+    /// Decoders flush what they still hold at the end of the media, so
+    /// that is included. Media without a decoder is never drained; it ends
+    /// by its duration.
+    fn is_drained(&self, position: Duration) -> bool {
+        let video = self.video_decoder.lock().as_ref().map(|d| d.is_finished());
+        let audio = self.audio_decoder.lock().is_some();
+        if video.is_none() && !audio {
+            return false;
+        }
+        let audio_played = !audio
+            || self
+                .audio_buffered
+                .lock()
+                .ranges()
+                .last()
+                .is_none_or(|&(_, end)| end <= position);
+        video.unwrap_or(true)
+            && self.is_audio_drained()
+            && audio_played
+            && self.is_video_queue_empty()
+    }
+
+    /// Returns whether the audio decoder has queued all of the audio of the
+    /// media, including what it flushed, and that has been taken from the
+    /// queue
+    ///
+    /// Always the case without an audio decoder.
+    fn is_audio_drained(&self) -> bool {
+        self.audio_decoder.lock().as_ref().is_none_or(|decoder| {
+            decoder.is_finished() && self.audio_rx.read().as_ref().is_none_or(|rx| rx.is_empty())
+        })
+    }
+
+    /// Stops decoding, leaving the queued media to be played out
+    fn stop_decoding(&self) {
+        self.draining.store(true, Ordering::Relaxed);
+        // The demuxer stays locked, so the decoders read nothing more
+        let _demuxer = self.demuxer.lock();
+        if let Some(decoder) = self.video_decoder.lock().as_ref() {
+            decoder.cancel();
+        }
+        if let Some(decoder) = self.audio_decoder.lock().as_ref() {
+            decoder.cancel();
+        }
+    }
+
+    /// Returns whether the pipeline is draining
+    fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the queued media has been played out at `position`
+    ///
+    /// This is the case once both queues are empty, or nothing queued is
+    /// buffered ahead of `position`.
+    fn is_played_out(&self, position: Duration) -> bool {
+        self.queue_depths() == (0, 0) || self.buffered_ahead(position).is_zero()
+    }
+}
+
+/// Moves the pipeline to `next`, unless decoding has put it in its error
+/// state
+///
+/// Returns whether the state was set.
+fn set_unless_failed(state: &RwLock<PipelineState>, next: PipelineState) -> bool {
+    let mut state = state.write();
+    if *state == PipelineState::Error {
+        return false;
+    }
+    *state = next;
+    true
+}
+
+/// Reads everything from `offset` to the end of `reader`
+fn read_from(reader: &mut dyn MediaReader, offset: u64) -> Result<Vec<u8>, MediaError> {
+    let mut data = Vec::new();
+    reader
+        .seek(SeekFrom::Start(offset))
+        .and_then(|_| reader.read_to_end(&mut data))
+        .map_err(|e| MediaError::NetworkError {
+            details: format!("Failed to read media data: {}", e),
+        })?;
+    Ok(data)
+}
+
+/// Checks that an AB loop starts before it ends
+fn validate_loop_mode(mode: LoopMode) -> Result<(), MediaError> {
+    match mode {
+        LoopMode::AB { start, end } if start >= end => Err(MediaError::InvalidParameter(format!(
+            "Loop start {:?} must be before loop end {:?}",
+            start, end
+        ))),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AbrConfig;
+    use cortenbrowser_format_parsers::{AudioTrackInfo, VideoTrackInfo};
+    use cortenbrowser_shared_types::{AudioCodec, AudioFormat, PixelFormat, VideoCodec};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_new_pipeline() {
+        let config = PipelineConfig::default();
+        let result = MediaPipeline::new(config);
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_state_transitions() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        // Initial state should be Idle
+        assert_eq!(*pipeline.state.read(), PipelineState::Idle);
+
+        // Load source
+        let source = MediaSource::Url {
+            url: "file:///test.mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+
+        // State should be Ready
+        assert_eq!(*pipeline.state.read(), PipelineState::Ready);
+
+        // Start pipeline
+        pipeline.start().await.unwrap();
+
+        // State should be Running
+        assert_eq!(*pipeline.state.read(), PipelineState::Running);
+
+        // Stop pipeline
+        pipeline.stop().await.unwrap();
+
+        // State should be Stopped
+        assert_eq!(*pipeline.state.read(), PipelineState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_load_buffer_selects_demuxer() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        // Content wins over a wrong MIME type
+        let source = MediaSource::Buffer {
+            data: b"OggS\x00\x02".to_vec(),
+            mime_type: "video/mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        assert!(pipeline.has_demuxer());
+    }
+
+    #[tokio::test]
+    async fn test_load_buffer_falls_back_to_mime() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        let source = MediaSource::Buffer {
+            data: vec![0u8; 64],
+            mime_type: "video/webm; codecs=vp9".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        assert!(pipeline.has_demuxer());
+    }
+
+    #[tokio::test]
+    async fn test_load_unrecognized_buffer_fails() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        let source = MediaSource::Buffer {
+            data: vec![0u8; 64],
+            mime_type: "text/plain".to_string(),
+        };
+        let result = pipeline.load_source(source).await;
+        assert!(matches!(result, Err(MediaError::UnsupportedFormat { .. })));
+        assert_eq!(*pipeline.state.read(), PipelineState::Idle);
+        assert!(!pipeline.has_demuxer());
+    }
+
+    #[tokio::test]
+    async fn test_set_reader_requires_demuxer() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        let result = pipeline.set_reader(Box::new(std::io::Cursor::new(vec![0u8; 64])));
+        assert!(matches!(result, Err(MediaError::InvalidState(_))));
+        assert!(pipeline.media_info().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_set_reader_rejects_unparseable_data() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        let source = MediaSource::Buffer {
+            data: vec![0u8; 64],
+            mime_type: "video/mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+
+        let result = pipeline.set_reader(Box::new(std::io::Cursor::new(vec![0u8; 64])));
+        assert!(result.is_err());
+        assert!(pipeline.media_info().is_none());
+    }
+
+    fn chunk(data: &[u8], is_final: bool) -> MediaChunk {
+        MediaChunk {
+            data: data.to_vec(),
+            sequence: 0,
+            is_final,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_feed_requires_demuxer() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        let result = pipeline.feed(&chunk(&[0u8; 64], false));
+        assert!(matches!(result, Err(MediaError::InvalidState(_))));
+    }
+
+    #[tokio::test]
+    async fn test_feed_waits_for_media_info() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        let (_tx, rx) = mpsc::channel(1);
+        let source = MediaSource::Stream {
+            receiver: Arc::new(rx),
+            mime_type: "video/mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+
+        // A complete ftyp box, with the moov box still to come
+        let mut ftyp = vec![0, 0, 0, 16];
+        ftyp.extend_from_slice(b"ftypisom\0\0\x02\0");
+        assert!(pipeline.feed(&chunk(&ftyp, false)).unwrap().is_none());
+        assert!(pipeline.media_info().is_none());
+
+        // The stream ends without describing the media
+        assert!(pipeline.feed(&chunk(&[], true)).unwrap().is_none());
+        assert!(pipeline.media_info().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_load_url_defers_demuxer() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+
+        let source = MediaSource::Url {
+            url: "https://example.com/video".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        assert!(!pipeline.has_demuxer());
+    }
+
+    #[test]
+    fn test_playback_rate() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        assert_eq!(pipeline.playback_rate(), 1.0);
+        assert!(!pipeline.needs_pitch_correction());
+
+        pipeline.set_playback_rate(2.0).unwrap();
+        assert_eq!(pipeline.sync_controller.rate(), 2.0);
+        assert!(pipeline.needs_pitch_correction());
+
+        assert!(pipeline.set_playback_rate(0.2).is_err());
+        assert!(pipeline.set_playback_rate(4.5).is_err());
+        assert_eq!(pipeline.playback_rate(), 2.0);
+    }
+
+    #[test]
+    fn test_pitch_correction_disabled() {
+        let config = PipelineConfig {
+            pitch_correct: false,
+            ..Default::default()
+        };
+        let pipeline = MediaPipeline::new(config).unwrap();
+        pipeline.set_playback_rate(0.5).unwrap();
+        assert!(!pipeline.needs_pitch_correction());
+    }
+
+    async fn start_audio_pipeline(loop_mode: LoopMode) -> MediaPipeline {
+        let config = PipelineConfig {
+            loop_mode,
+            ..Default::default()
+        };
+        let pipeline = MediaPipeline::new(config).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/audio.ogg".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.set_media_duration(Duration::from_secs(1));
+        pipeline.start().await.unwrap();
+        pipeline
+    }
+
+    #[tokio::test]
+    async fn test_loop_all_restarts_playback() {
+        tokio::time::pause();
+        let pipeline = start_audio_pipeline(LoopMode::All).await;
+
+        tokio::time::sleep(Duration::from_secs(4)).await;
+
+        assert!(pipeline.loop_count() >= 3);
+        assert!(pipeline.current_position() < Duration::from_secs(1));
         assert_eq!(*pipeline.state.read(), PipelineState::Running);
+    }
 
-        // Stop pipeline
-        pipeline.stop().await.unwrap();
+    #[tokio::test]
+    async fn test_no_loop_stops_at_end() {
+        tokio::time::pause();
+        let pipeline = start_audio_pipeline(LoopMode::None).await;
 
-        // State should be Stopped
+        tokio::time::sleep(Duration::from_secs(2)).await;
+
+        assert_eq!(pipeline.loop_count(), 0);
+        assert_eq!(*pipeline.state.read(), PipelineState::Stopped);
+        assert!(!pipeline.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_ab_loop_stays_in_section() {
+        tokio::time::pause();
+        let pipeline = start_audio_pipeline(LoopMode::AB {
+            start: Duration::from_millis(200),
+            end: Duration::from_millis(500),
+        })
+        .await;
+
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        assert_eq!(pipeline.loop_count(), 1);
+        assert!(pipeline.current_position() >= Duration::from_millis(200));
+        assert!(pipeline.current_position() < Duration::from_millis(500));
+
+        let mut loops = pipeline.subscribe_loops();
+        loops.changed().await.unwrap();
+        assert!(*loops.borrow() >= 2);
+    }
+
+    #[tokio::test]
+    async fn test_double_rate_ends_in_half_the_duration() {
+        tokio::time::pause();
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/audio.ogg".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        pipeline.set_playback_rate(2.0).unwrap();
+        pipeline.start().await.unwrap();
+        let mut ended = pipeline.subscribe_ended();
+
+        tokio::time::sleep(Duration::from_millis(4900)).await;
+        assert!(!pipeline.is_ended());
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(pipeline.is_ended());
+        assert!(ended.has_changed().unwrap());
+        assert!(*ended.borrow_and_update());
+        assert_eq!(*pipeline.state.read(), PipelineState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_sync_frame_against_position() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.seek(Duration::from_secs(1)).await.unwrap();
+        let frame = |ms| {
+            VideoFrame::new(
+                16,
+                16,
+                PixelFormat::YUV420,
+                vec![0; 384],
+                Duration::from_millis(ms),
+            )
+        };
+
+        assert_eq!(pipeline.sync_frame(&frame(1000)), SyncDecision::Display);
+        assert_eq!(pipeline.sync_frame(&frame(500)), SyncDecision::Drop);
+        assert_eq!(
+            pipeline.sync_frame(&frame(1500)),
+            SyncDecision::Wait {
+                duration: Duration::from_millis(500)
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_audio_stretched_to_playback_rate() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let buffer = || {
+            AudioBuffer::new(
+                AudioFormat::F32LE,
+                48000,
+                2,
+                vec![0.25; 960],
+                Duration::from_secs(1),
+            )
+        };
+
+        pipeline.push_audio_buffer(buffer()).await.unwrap();
+        assert_eq!(pipeline.get_next_audio_buffer().await.unwrap(), buffer());
+
+        pipeline.set_playback_rate(2.0).unwrap();
+        pipeline.push_audio_buffer(buffer()).await.unwrap();
+        let stretched = pipeline.get_next_audio_buffer().await.unwrap();
+        assert_eq!(stretched.samples.len(), 480);
+        assert_eq!(stretched.duration, Duration::from_millis(5));
+        assert_eq!(stretched.timestamp, Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_custom_time_stretcher() {
+        #[derive(Debug)]
+        struct Silence;
+
+        impl TimeStretcher for Silence {
+            fn process(&mut self, mut buffer: AudioBuffer, _rate: f32) -> AudioBuffer {
+                buffer.samples.fill(0.0);
+                buffer
+            }
+            fn reset(&mut self) {}
+        }
+
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        pipeline.set_time_stretcher(Box::new(Silence));
+        let buffer = AudioBuffer::new(AudioFormat::F32LE, 48000, 1, vec![1.0; 48], Duration::ZERO);
+        pipeline.push_audio_buffer(buffer).await.unwrap();
+
+        let output = pipeline.get_next_audio_buffer().await.unwrap();
+        assert!(output.samples.iter().all(|&s| s == 0.0));
+    }
+
+    #[tokio::test]
+    async fn test_pause_holds_position() {
+        tokio::time::pause();
+        let pipeline = start_audio_pipeline(LoopMode::None).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        pipeline.pause().await.unwrap();
+        let position = pipeline.current_position();
+        assert!(!pipeline.is_running());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(pipeline.current_position(), position);
+        assert!(pipeline.pause().await.is_err());
+
+        pipeline.start().await.unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(pipeline.current_position() > position);
+    }
+
+    #[tokio::test]
+    async fn test_start_after_end_rewinds() {
+        tokio::time::pause();
+        let pipeline = start_audio_pipeline(LoopMode::None).await;
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        assert!(pipeline.is_ended());
+
+        pipeline.start().await.unwrap();
+        assert!(!pipeline.is_ended());
+        assert!(pipeline.is_running());
+        assert_eq!(pipeline.current_position(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_seek_discards_queued_audio() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/audio.ogg".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        let buffer = AudioBuffer::new(AudioFormat::F32LE, 48000, 1, vec![1.0; 48], Duration::ZERO);
+        pipeline.push_audio_buffer(buffer).await.unwrap();
+
+        pipeline.seek(Duration::from_secs(1)).await.unwrap();
+
+        assert!(pipeline.get_next_audio_buffer().await.is_none());
+    }
+
+    /// Video frame lasting 40ms from `ms`
+    fn timed_frame(ms: u64) -> VideoFrame {
+        let mut frame = VideoFrame::new(
+            16,
+            16,
+            PixelFormat::YUV420,
+            vec![0; 384],
+            Duration::from_millis(ms),
+        );
+        frame.duration = Some(Duration::from_millis(40));
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_buffered_ranges_are_buffered_on_every_track() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        assert!(pipeline.buffered_ranges().is_empty());
+
+        for ms in (0..1000).step_by(40) {
+            pipeline.push_video_frame(timed_frame(ms)).await.unwrap();
+        }
+        assert_eq!(
+            pipeline.buffered_ranges(),
+            [(Duration::ZERO, Duration::from_millis(1000))]
+        );
+
+        // Audio reaching less far limits the buffered range
+        let audio = AudioBuffer::new(AudioFormat::F32LE, 1000, 1, vec![0.0; 600], Duration::ZERO);
+        pipeline.push_audio_buffer(audio).await.unwrap();
+        assert_eq!(
+            pipeline.buffered_ranges(),
+            [(Duration::ZERO, Duration::from_millis(600))]
+        );
+        assert_eq!(pipeline.buffered_ahead(), Duration::from_millis(600));
+
+        // Media queued after a seek starts a new range
+        pipeline.seek(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(pipeline.buffered_ahead(), Duration::ZERO);
+        let audio = AudioBuffer::new(
+            AudioFormat::F32LE,
+            1000,
+            1,
+            vec![0.0; 1000],
+            Duration::from_secs(5),
+        );
+        pipeline.push_audio_buffer(audio).await.unwrap();
+        for ms in (5000..5400).step_by(40) {
+            pipeline.push_video_frame(timed_frame(ms)).await.unwrap();
+        }
+        assert_eq!(
+            pipeline.buffered_ranges(),
+            [
+                (Duration::ZERO, Duration::from_millis(600)),
+                (Duration::from_secs(5), Duration::from_millis(5400)),
+            ]
+        );
+        assert_eq!(pipeline.buffered_ahead(), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_seekable_range_of_stream_grows_with_buffered_media() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let (_tx, rx) = mpsc::channel(1);
+        let source = MediaSource::Stream {
+            receiver: Arc::new(rx),
+            mime_type: "video/mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        assert_eq!(pipeline.seekable_range(), (Duration::ZERO, Duration::ZERO));
+
+        for ms in (0..400).step_by(40) {
+            pipeline.push_video_frame(timed_frame(ms)).await.unwrap();
+        }
+        assert_eq!(
+            pipeline.seekable_range(),
+            (Duration::ZERO, Duration::from_millis(400))
+        );
+
+        // A known duration does not make unread parts of a stream seekable
+        pipeline.set_media_duration(Duration::from_secs(10));
+        for ms in (400..1000).step_by(40) {
+            pipeline.push_video_frame(timed_frame(ms)).await.unwrap();
+        }
+        assert_eq!(
+            pipeline.seekable_range(),
+            (Duration::ZERO, Duration::from_secs(1))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_underrun_when_clock_passes_queued_video() {
+        tokio::time::pause();
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        pipeline.start().await.unwrap();
+
+        // Media without video never underruns
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!pipeline.is_underrun());
+
+        for ms in (0..400).step_by(40) {
+            pipeline.push_video_frame(timed_frame(ms)).await.unwrap();
+        }
+        while pipeline.get_next_video_frame().await.is_some() {}
+        assert!(!pipeline.is_underrun());
+
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(pipeline.is_underrun());
+        assert_eq!(pipeline.stats().underruns, 1);
+
+        // Video reaching ahead of the clock again ends the underrun
+        let position = pipeline.current_position().as_millis() as u64;
+        pipeline
+            .push_video_frame(timed_frame(position))
+            .await
+            .unwrap();
+        assert!(!pipeline.is_underrun());
+
+        pipeline.pause().await.unwrap();
+        while pipeline.get_next_video_frame().await.is_some() {}
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!pipeline.is_underrun());
+        assert_eq!(pipeline.stats().underruns, 1);
+    }
+
+    #[tokio::test]
+    async fn test_drain_plays_out_queued_video_then_stops() {
+        tokio::time::pause();
+        let pipeline = start_audio_pipeline(LoopMode::All).await;
+        pipeline.set_media_duration(Duration::from_secs(10));
+        for ms in (0..400).step_by(40) {
+            pipeline.push_video_frame(timed_frame(ms)).await.unwrap();
+        }
+
+        pipeline.drain().await;
+        let position = pipeline.current_position();
+        assert!(position >= Duration::from_millis(400));
+        assert!(position < Duration::from_millis(500));
+        assert_eq!(*pipeline.state.read(), PipelineState::Stopped);
+        assert!(!pipeline.is_ended());
+        assert_eq!(pipeline.loop_count(), 0);
+        assert_eq!(pipeline.stats().underruns, 0);
+        assert!(pipeline.start().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_drain_stops_ready_pipeline_at_once() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mp4".to_string(),
+        };
+        pipeline.load_source(source.clone()).await.unwrap();
+        pipeline.push_video_frame(timed_frame(0)).await.unwrap();
+
+        pipeline.drain().await;
         assert_eq!(*pipeline.state.read(), PipelineState::Stopped);
+
+        // Loading another source ends the drain
+        pipeline.load_source(source).await.unwrap();
+        pipeline.start().await.unwrap();
+        assert!(pipeline.is_running());
+    }
+
+    #[tokio::test]
+    async fn test_abr_switches_with_buffer_level() {
+        tokio::time::pause();
+        let config = PipelineConfig {
+            abr: AbrConfig {
+                up_threshold: Duration::from_secs(1),
+                down_threshold: Duration::from_millis(500),
+                switch_interval: Duration::from_millis(100),
+            },
+            ..Default::default()
+        };
+        let pipeline = MediaPipeline::new(config).unwrap();
+        let representations = ["low", "high"]
+            .into_iter()
+            .zip([500_000, 2_000_000])
+            .map(|(id, bandwidth)| Representation {
+                id: id.to_string(),
+                bandwidth,
+                url: format!("https://example.com/{}/", id),
+            })
+            .collect();
+        assert!(pipeline.set_representations(Vec::new()).is_err());
+        let source = MediaSource::Url {
+            url: "https://example.com/manifest.mpd".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        pipeline.set_representations(representations).unwrap();
+        let mut switches = pipeline.subscribe_representation();
+        for ms in (0..1500).step_by(40) {
+            pipeline.push_video_frame(timed_frame(ms)).await.unwrap();
+        }
+
+        // A full buffer moves up to the higher bitrate
+        pipeline.start().await.unwrap();
+        switches.changed().await.unwrap();
+        assert_eq!(pipeline.representation().unwrap().id, "high");
+        assert!(matches!(
+            &*pipeline.source.read(),
+            Some(MediaSource::Url { url }) if url == "https://example.com/high/"
+        ));
+
+        // Playback draining it below the down threshold moves back down
+        switches.changed().await.unwrap();
+        assert_eq!(pipeline.representation().unwrap().id, "low");
+        let position = pipeline.current_position();
+        assert!(position > Duration::from_secs(1));
+        assert!(position < Duration::from_millis(1100));
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_queued_media() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+
+        pipeline.push_video_frame(timed_frame(0)).await.unwrap();
+        pipeline.push_video_frame(timed_frame(40)).await.unwrap();
+        let audio = AudioBuffer::new(AudioFormat::F32LE, 1000, 1, vec![0.0; 40], Duration::ZERO);
+        pipeline.push_audio_buffer(audio).await.unwrap();
+        let stats = pipeline.stats();
+        assert_eq!(stats.audio_buffers_queued, 1);
+        assert_eq!(stats.queued_bytes, 2 * 384 + 40 * 4);
+        assert_eq!((stats.video_queue_depth, stats.audio_queue_depth), (2, 1));
+
+        pipeline.get_next_video_frame().await.unwrap();
+        pipeline.get_next_audio_buffer().await.unwrap();
+        assert_eq!(pipeline.stats().queued_bytes, 384);
+    }
+
+    /// Media with two video tracks and two audio tracks
+    fn multi_track_info() -> MediaInfo {
+        let video = |track_id| VideoTrackInfo {
+            track_id,
+            codec: VideoCodec::VP8,
+            width: 16,
+            height: 16,
+            frame_rate: 25.0,
+            bitrate: None,
+            extradata: Vec::new(),
+            color: None,
+        };
+        let audio = |track_id| AudioTrackInfo {
+            track_id,
+            codec: AudioCodec::Vorbis,
+            sample_rate: 48000,
+            channels: 2,
+            bitrate: None,
+            extradata: Vec::new(),
+            gapless: None,
+        };
+        MediaInfo {
+            duration: Duration::from_secs(10),
+            video_tracks: vec![video(1), video(2)],
+            audio_tracks: vec![audio(3), audio(4)],
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tracks_list_first_of_each_kind_selected() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mkv".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        assert!(pipeline.tracks().is_empty());
+        assert!(matches!(
+            pipeline.select_track(3),
+            Err(MediaError::InvalidState(_))
+        ));
+
+        pipeline.start_decoding(&multi_track_info());
+        let selected: Vec<_> = pipeline
+            .tracks()
+            .into_iter()
+            .map(|track| (track.track_id, track.kind, track.selected))
+            .collect();
+        assert_eq!(
+            selected,
+            [
+                (1, TrackKind::Video, true),
+                (2, TrackKind::Video, false),
+                (3, TrackKind::Audio, true),
+                (4, TrackKind::Audio, false),
+            ]
+        );
+
+        assert!(matches!(
+            pipeline.select_track(99),
+            Err(MediaError::InvalidParameter(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_select_audio_track_flushes_only_audio() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mkv".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.start_decoding(&multi_track_info());
+
+        pipeline.push_video_frame(timed_frame(0)).await.unwrap();
+        let audio = AudioBuffer::new(AudioFormat::F32LE, 1000, 1, vec![0.0; 40], Duration::ZERO);
+        pipeline.push_audio_buffer(audio).await.unwrap();
+
+        pipeline.select_track(4).unwrap();
+        let selected: Vec<_> = pipeline
+            .tracks()
+            .into_iter()
+            .filter(|track| track.selected)
+            .map(|track| track.track_id)
+            .collect();
+        assert_eq!(selected, [1, 4]);
+        assert!(pipeline.get_next_audio_buffer().await.is_none());
+        assert!(pipeline.get_next_video_frame().await.is_some());
+
+        // Without a reader, video decoding cannot restart on another track
+        assert!(matches!(
+            pipeline.select_track(2),
+            Err(MediaError::InvalidState(_))
+        ));
+        assert!(pipeline.tracks()[0].selected);
+    }
+
+    #[tokio::test]
+    async fn test_switch_video_track_validates_index() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mkv".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        assert!(matches!(
+            pipeline.switch_video_track(0),
+            Err(MediaError::InvalidState(_))
+        ));
+
+        pipeline.start_decoding(&multi_track_info());
+        assert!(matches!(
+            pipeline.switch_video_track(2),
+            Err(MediaError::InvalidParameter(_))
+        ));
+        // The selected track needs no switch
+        pipeline.switch_video_track(0).unwrap();
+        // Without a reader, video decoding cannot restart on the other one
+        assert!(matches!(
+            pipeline.switch_video_track(1),
+            Err(MediaError::InvalidState(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stats_count_audio_underruns_and_dropped_frames() {
+        tokio::time::pause();
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        let audio = AudioBuffer::new(AudioFormat::F32LE, 1000, 1, vec![0.0; 100], Duration::ZERO);
+        pipeline.push_audio_buffer(audio).await.unwrap();
+        pipeline.start().await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(pipeline.stats().audio_underruns, 0);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(pipeline.stats().audio_underruns, 1);
+
+        // A frame far behind the clock is dropped
+        assert_eq!(pipeline.sync_frame(&timed_frame(0)), SyncDecision::Drop);
+        let stats = pipeline.stats();
+        assert_eq!((stats.frames_dropped, stats.frames_displayed), (1, 0));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_reset_on_seek() {
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        let source = MediaSource::Url {
+            url: "file:///test/video.mp4".to_string(),
+        };
+        pipeline.load_source(source).await.unwrap();
+        pipeline.set_media_duration(Duration::from_secs(10));
+        pipeline.push_video_frame(timed_frame(0)).await.unwrap();
+        let audio = AudioBuffer::new(AudioFormat::F32LE, 1000, 1, vec![0.0; 40], Duration::ZERO);
+        pipeline.push_audio_buffer(audio).await.unwrap();
+
+        pipeline.seek(Duration::from_secs(2)).await.unwrap();
+        assert_eq!(pipeline.sync_frame(&timed_frame(0)), SyncDecision::Drop);
+        let metrics = pipeline.get_metrics();
+        assert_eq!(metrics.dropped_frames, 1);
+        assert_eq!(metrics.total_audio_buffers_produced, 0);
+        assert_eq!(metrics.total_frames_produced, 0);
+
+        pipeline.seek(Duration::from_secs(4)).await.unwrap();
+        assert_eq!(pipeline.get_metrics().dropped_frames, 0);
+        // The counters are kept
+        assert_eq!(pipeline.stats().frames_dropped, 1);
+        assert_eq!(pipeline.stats().audio_buffers_queued, 1);
+    }
+
+    #[test]
+    fn test_invalid_ab_loop_rejected() {
+        let backwards = LoopMode::AB {
+            start: Duration::from_secs(1),
+            end: Duration::from_secs(1),
+        };
+        let config = PipelineConfig {
+            loop_mode: backwards,
+            ..Default::default()
+        };
+        assert!(matches!(
+            MediaPipeline::new(config),
+            Err(MediaError::InvalidParameter(_))
+        ));
+
+        let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+        assert!(pipeline.set_loop_mode(backwards).is_err());
+        assert_eq!(pipeline.loop_mode(), LoopMode::None);
     }
 
     #[tokio::test]
@@ -0,0 +1,301 @@
+//! Pipeline statistics
+//!
+//! Counters are atomics updated by the decoder thread, the clock task and
+//! the queue accessors as they go, so reading them never waits on playback.
+//! Only the window of recent decodes and the metrics, which start over on a
+//! seek, are kept under locks, held briefly once per frame.
+
+use crate::types::{PipelineMetrics, PipelineStats};
+use parking_lot::{Mutex, RwLock};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Number of recent decodes that percentiles and the bitrate are taken from
+const RECENT_DECODES: usize = 120;
+
+/// Running counters of a pipeline's decoding and playback
+#[derive(Debug, Default)]
+pub(crate) struct StatsCounters {
+    frames_decoded: AtomicU64,
+    audio_buffers_queued: AtomicU64,
+    decode_errors: AtomicU64,
+    underruns: AtomicU64,
+    audio_underruns: AtomicU64,
+    decode_nanos: AtomicU64,
+    video_queued_bytes: AtomicUsize,
+    audio_queued_bytes: AtomicUsize,
+    recent: Mutex<VecDeque<RecentDecode>>,
+    /// Metrics since loading or the last seek, without the queue depths
+    metrics: RwLock<PipelineMetrics>,
+}
+
+/// A frame decoded from a packet
+#[derive(Debug, Clone, Copy)]
+struct RecentDecode {
+    /// Time decoding took
+    decode_time: Duration,
+    /// Media time of the frame
+    timestamp: Duration,
+    /// Size of the packet
+    bytes: usize,
+}
+
+impl StatsCounters {
+    /// Records a video frame decoded from a packet of `bytes` bytes, and the
+    /// time decoding took
+    pub(crate) fn frame_decoded(&self, decode_time: Duration, timestamp: Duration, bytes: usize) {
+        self.frames_decoded.fetch_add(1, Ordering::Relaxed);
+        self.decode_nanos
+            .fetch_add(decode_time.as_nanos() as u64, Ordering::Relaxed);
+
+        let mut recent = self.recent.lock();
+        if recent.len() == RECENT_DECODES {
+            recent.pop_front();
+        }
+        recent.push_back(RecentDecode {
+            decode_time,
+            timestamp,
+            bytes,
+        });
+        drop(recent);
+        self.frame_produced(decode_time);
+    }
+
+    /// Records a video frame flushed from the decoder at the end of the
+    /// media, with its share of the time flushing took
+    pub(crate) fn frame_flushed(&self, decode_time: Duration) {
+        self.frames_decoded.fetch_add(1, Ordering::Relaxed);
+        self.decode_nanos
+            .fetch_add(decode_time.as_nanos() as u64, Ordering::Relaxed);
+        self.frame_produced(decode_time);
+    }
+
+    /// Averages the time the decoder took for a frame into the metrics
+    fn frame_produced(&self, decode_time: Duration) {
+        let mut metrics = self.metrics.write();
+        metrics.total_frames_produced += 1;
+        let latency_ms = decode_time.as_secs_f64() * 1000.0;
+        metrics.avg_video_decode_latency_ms += (latency_ms - metrics.avg_video_decode_latency_ms)
+            / metrics.total_frames_produced as f64;
+    }
+
+    /// Records a video frame the A/V sync controller dropped
+    pub(crate) fn frame_dropped(&self) {
+        self.metrics.write().dropped_frames += 1;
+    }
+
+    /// Records a video packet that did not decode to a frame
+    pub(crate) fn decode_failed(&self) {
+        self.decode_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a video packet that `decoder` failed with a codec error
+    pub(crate) fn codec_error(&self, decoder: &str) {
+        self.decode_failed();
+        let mut metrics = self.metrics.write();
+        *metrics.codec_errors.entry(decoder.to_string()).or_default() += 1;
+    }
+
+    /// Records that video playback ran out of decoded frames
+    pub(crate) fn underrun(&self) {
+        self.underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records that audio playback ran out of queued audio
+    pub(crate) fn audio_underrun(&self) {
+        self.audio_underruns.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records an audio buffer queued for playout
+    pub(crate) fn audio_buffer_queued(&self) {
+        self.audio_buffers_queued.fetch_add(1, Ordering::Relaxed);
+        self.metrics.write().total_audio_buffers_produced += 1;
+    }
+
+    /// Adds to the bytes of video waiting in the queue
+    pub(crate) fn add_video_bytes(&self, bytes: usize) {
+        self.video_queued_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Removes bytes of video taken from or dropped with the queue
+    pub(crate) fn remove_video_bytes(&self, bytes: usize) {
+        saturating_sub(&self.video_queued_bytes, bytes);
+    }
+
+    /// Forgets the queued video, when the queue is replaced
+    pub(crate) fn clear_video_bytes(&self) {
+        self.video_queued_bytes.store(0, Ordering::Relaxed);
+    }
+
+    /// Adds to the bytes of audio waiting in the queue
+    pub(crate) fn add_audio_bytes(&self, bytes: usize) {
+        self.audio_queued_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Removes bytes of audio taken from or dropped with the queue
+    pub(crate) fn remove_audio_bytes(&self, bytes: usize) {
+        saturating_sub(&self.audio_queued_bytes, bytes);
+    }
+
+    /// Starts the metrics over, so a seek leaves no stale averages
+    ///
+    /// The counters are kept.
+    pub(crate) fn reset_metrics(&self) {
+        *self.metrics.write() = PipelineMetrics::default();
+    }
+
+    /// Takes a snapshot of the metrics, with the queue depths left at zero
+    pub(crate) fn metrics(&self) -> PipelineMetrics {
+        self.metrics.read().clone()
+    }
+
+    /// Takes a snapshot of the counters
+    ///
+    /// Counts kept outside the counters, such as queue depths and frames
+    /// displayed, are left at zero.
+    pub(crate) fn snapshot(&self) -> PipelineStats {
+        let recent: Vec<RecentDecode> = self.recent.lock().iter().copied().collect();
+        let mut decode_times: Vec<Duration> = recent.iter().map(|d| d.decode_time).collect();
+        decode_times.sort_unstable();
+
+        PipelineStats {
+            frames_decoded: self.frames_decoded.load(Ordering::Relaxed),
+            audio_buffers_queued: self.audio_buffers_queued.load(Ordering::Relaxed),
+            decode_errors: self.decode_errors.load(Ordering::Relaxed),
+            underruns: self.underruns.load(Ordering::Relaxed),
+            audio_underruns: self.audio_underruns.load(Ordering::Relaxed),
+            decode_time: Duration::from_nanos(self.decode_nanos.load(Ordering::Relaxed)),
+            decode_time_p50: percentile(&decode_times, 50),
+            decode_time_p90: percentile(&decode_times, 90),
+            decode_time_p99: percentile(&decode_times, 99),
+            bitrate: bitrate(&recent),
+            queued_bytes: self.video_queued_bytes.load(Ordering::Relaxed)
+                + self.audio_queued_bytes.load(Ordering::Relaxed),
+            ..Default::default()
+        }
+    }
+}
+
+/// Subtracts from a counter without wrapping below zero
+///
+/// A queue replaced after a seek may still see sends fail for frames the
+/// counter has already forgotten.
+fn saturating_sub(counter: &AtomicUsize, bytes: usize) {
+    let _ = counter.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        Some(current.saturating_sub(bytes))
+    });
+}
+
+/// Nearest-rank percentile of sorted durations, zero if there are none
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percent * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+/// Bits per second of media time over the recent decodes
+///
+/// Each packet's bytes are spread up to the next frame, so the latest frame
+/// only ends the measured span. Zero until two frames of different times
+/// have been decoded.
+fn bitrate(recent: &[RecentDecode]) -> u64 {
+    let Some(latest) = recent.last() else {
+        return 0;
+    };
+    let first = recent.iter().map(|d| d.timestamp).min().unwrap_or_default();
+    let last = recent.iter().map(|d| d.timestamp).max().unwrap_or_default();
+    let span = last.saturating_sub(first);
+    if span.is_zero() {
+        return 0;
+    }
+    let bytes: usize = recent.iter().map(|d| d.bytes).sum::<usize>() - latest.bytes;
+    (bytes as f64 * 8.0 / span.as_secs_f64()) as u64
+}
+
+/// Bytes of sample data in an audio buffer
+pub(crate) fn audio_bytes(samples: usize) -> usize {
+    samples * std::mem::size_of::<f32>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot() {
+        let counters = StatsCounters::default();
+        counters.frame_decoded(Duration::from_millis(3), Duration::ZERO, 10);
+        counters.frame_decoded(Duration::from_millis(5), Duration::from_millis(40), 10);
+        counters.decode_failed();
+        counters.audio_buffer_queued();
+        counters.add_video_bytes(100);
+        counters.add_audio_bytes(audio_bytes(10));
+
+        let stats = counters.snapshot();
+        assert_eq!(stats.frames_decoded, 2);
+        assert_eq!(stats.decode_errors, 1);
+        assert_eq!(stats.audio_buffers_queued, 1);
+        assert_eq!(stats.decode_time, Duration::from_millis(8));
+        assert_eq!(stats.queued_bytes, 140);
+    }
+
+    #[test]
+    fn test_queued_bytes_do_not_wrap() {
+        let counters = StatsCounters::default();
+        counters.add_video_bytes(100);
+        counters.clear_video_bytes();
+        counters.remove_video_bytes(100);
+        assert_eq!(counters.snapshot().queued_bytes, 0);
+    }
+
+    #[test]
+    fn test_metrics_average_and_reset() {
+        let counters = StatsCounters::default();
+        counters.frame_decoded(Duration::from_millis(2), Duration::ZERO, 10);
+        counters.frame_decoded(Duration::from_millis(4), Duration::from_millis(40), 10);
+        counters.frame_flushed(Duration::from_millis(6));
+        counters.frame_dropped();
+        counters.audio_buffer_queued();
+        counters.codec_error("h264");
+        counters.codec_error("h264");
+
+        let metrics = counters.metrics();
+        assert_eq!(metrics.total_frames_produced, 3);
+        assert!((metrics.avg_video_decode_latency_ms - 4.0).abs() < 1e-9);
+        assert_eq!(metrics.total_audio_buffers_produced, 1);
+        assert_eq!(metrics.dropped_frames, 1);
+        assert_eq!(metrics.codec_errors["h264"], 2);
+        assert_eq!(counters.snapshot().decode_errors, 2);
+
+        // Only the metrics start over
+        counters.reset_metrics();
+        assert_eq!(counters.metrics(), PipelineMetrics::default());
+        assert_eq!(counters.snapshot().frames_decoded, 3);
+    }
+
+    #[test]
+    fn test_decode_time_percentiles_and_bitrate() {
+        let counters = StatsCounters::default();
+        assert_eq!(counters.snapshot().bitrate, 0);
+
+        // 25 frames per second of 5000 bytes each is 1 Mbit/s
+        for i in 1..=100u64 {
+            counters.frame_decoded(
+                Duration::from_millis(i),
+                Duration::from_millis(i * 40),
+                5000,
+            );
+        }
+        counters.frame_flushed(Duration::ZERO);
+
+        let stats = counters.snapshot();
+        assert_eq!(stats.frames_decoded, 101);
+        assert_eq!(stats.decode_time_p50, Duration::from_millis(50));
+        assert_eq!(stats.decode_time_p90, Duration::from_millis(90));
+        assert_eq!(stats.decode_time_p99, Duration::from_millis(99));
+        assert_eq!(stats.bitrate, 1_000_000);
+    }
+}
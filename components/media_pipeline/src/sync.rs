@@ -4,12 +4,36 @@
 //! they play in sync with minimal drift.
 
 use crate::types::SyncDecision;
-use cortenbrowser_shared_types::VideoFrame;
+use cortenbrowser_shared_types::{AudioSink, VideoFrame, MAX_PLAYBACK_RATE, MIN_PLAYBACK_RATE};
 use parking_lot::RwLock;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Default synchronization threshold (40ms)
-const DEFAULT_SYNC_THRESHOLD: Duration = Duration::from_millis(40);
+pub(crate) const DEFAULT_SYNC_THRESHOLD: Duration = Duration::from_millis(40);
+
+/// Default longest run of dropped frames (200ms)
+pub(crate) const DEFAULT_MAX_DROP_THRESHOLD: Duration = Duration::from_millis(200);
+
+/// Audio output driving the media clock
+struct AudioClock {
+    sink: Arc<dyn AudioSink>,
+    /// Played duration of the sink at the last clock update
+    last_played: Duration,
+    /// Whether the sink has played anything since it was attached
+    started: bool,
+}
+
+impl fmt::Debug for AudioClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AudioClock")
+            .field("last_played", &self.last_played)
+            .field("started", &self.started)
+            .finish_non_exhaustive()
+    }
+}
 
 /// A/V synchronization controller
 ///
@@ -17,6 +41,15 @@ const DEFAULT_SYNC_THRESHOLD: Duration = Duration::from_millis(40);
 /// whether video frames should be displayed, dropped, or delayed based on
 /// their timestamp relative to the audio stream.
 ///
+/// The clock follows wall-clock time scaled by the playback rate, or, once
+/// an audio sink attached with [`set_audio_clock`](Self::set_audio_clock)
+/// starts playing, the duration of audio the sink has played out.
+///
+/// Frames within the sync threshold of the clock are displayed, later ones
+/// dropped and earlier ones held back. So the picture keeps moving while
+/// decoding falls behind, a late frame is still displayed once the late
+/// frames dropped in a row span the max drop threshold.
+///
 /// # Examples
 ///
 /// ```
@@ -31,6 +64,7 @@ const DEFAULT_SYNC_THRESHOLD: Duration = Duration::from_millis(40);
 ///     height: 1080,
 ///     format: PixelFormat::YUV420,
 ///     data: vec![0u8; 1920 * 1080],
+///     strides: None,
 ///     timestamp: Duration::from_secs(1),
 ///     duration: Some(Duration::from_millis(33)),
 ///     metadata: FrameMetadata::default(),
@@ -44,7 +78,20 @@ pub struct AVSyncController {
     /// Current media clock position
     clock: RwLock<Duration>,
     /// Synchronization threshold
-    threshold: Duration,
+    threshold: RwLock<Duration>,
+    /// Longest media time of consecutive frames dropped for running late
+    max_drop_threshold: Duration,
+    /// Timestamp of the first late frame dropped since one was last
+    /// displayed
+    drop_run_start: RwLock<Option<Duration>>,
+    /// Playback rate (1.0 = normal speed)
+    rate: RwLock<f32>,
+    /// Audio output used as the master clock
+    audio_clock: RwLock<Option<AudioClock>>,
+    /// Frames `sync_frame` decided to display
+    frames_displayed: AtomicU64,
+    /// Frames `sync_frame` decided to drop
+    frames_dropped: AtomicU64,
 }
 
 impl AVSyncController {
@@ -58,16 +105,46 @@ impl AVSyncController {
     /// let controller = AVSyncController::new();
     /// ```
     pub fn new() -> Self {
+        Self::with_thresholds(DEFAULT_SYNC_THRESHOLD, DEFAULT_MAX_DROP_THRESHOLD)
+    }
+
+    /// Creates an A/V sync controller with the given thresholds
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - How far a frame may be from the clock and still be
+    ///   displayed
+    /// * `max_drop_threshold` - Longest media time of consecutive late
+    ///   frames dropped before one is displayed anyway
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::AVSyncController;
+    /// use std::time::Duration;
+    ///
+    /// let controller =
+    ///     AVSyncController::with_thresholds(Duration::from_millis(20), Duration::from_millis(100));
+    /// assert_eq!(controller.threshold(), Duration::from_millis(20));
+    /// ```
+    pub fn with_thresholds(threshold: Duration, max_drop_threshold: Duration) -> Self {
         Self {
             clock: RwLock::new(Duration::ZERO),
-            threshold: DEFAULT_SYNC_THRESHOLD,
+            threshold: RwLock::new(threshold),
+            max_drop_threshold,
+            drop_run_start: RwLock::new(None),
+            rate: RwLock::new(1.0),
+            audio_clock: RwLock::new(None),
+            frames_displayed: AtomicU64::new(0),
+            frames_dropped: AtomicU64::new(0),
         }
     }
 
     /// Makes a synchronization decision for a video frame
     ///
     /// Compares the video frame timestamp with the current audio timestamp
-    /// and decides whether to display, drop, or wait.
+    /// and decides whether to display, drop, or wait. Frames displayed and
+    /// dropped are counted.
     ///
     /// # Arguments
     ///
@@ -92,6 +169,7 @@ impl AVSyncController {
     ///     height: 1080,
     ///     format: PixelFormat::YUV420,
     ///     data: vec![0u8; 1920 * 1080],
+    ///     strides: None,
     ///     timestamp: Duration::from_millis(1000),
     ///     duration: Some(Duration::from_millis(33)),
     ///     metadata: FrameMetadata::default(),
@@ -101,7 +179,58 @@ impl AVSyncController {
     /// assert_eq!(decision, SyncDecision::Display);
     /// ```
     pub fn sync_frame(&self, video_frame: &VideoFrame, audio_timestamp: Duration) -> SyncDecision {
+        let decision = self.decide(video_frame, audio_timestamp);
+        match decision {
+            SyncDecision::Display => self.frames_displayed.fetch_add(1, Ordering::Relaxed),
+            SyncDecision::Drop => self.frames_dropped.fetch_add(1, Ordering::Relaxed),
+            SyncDecision::Wait { .. } => 0,
+        };
+        decision
+    }
+
+    /// Gets the number of frames decided to be displayed
+    pub fn frames_displayed(&self) -> u64 {
+        self.frames_displayed.load(Ordering::Relaxed)
+    }
+
+    /// Gets the number of frames decided to be dropped for running late
+    pub fn frames_dropped(&self) -> u64 {
+        self.frames_dropped.load(Ordering::Relaxed)
+    }
+
+    /// Sets how far a frame may be from the clock and still be displayed
+    ///
+    /// Takes effect from the next [`sync_frame`](Self::sync_frame).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::{AVSyncController, SyncDecision};
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// let controller = AVSyncController::new();
+    /// controller.set_threshold(Duration::from_millis(20));
+    ///
+    /// let frame = VideoFrame::new(2, 2, PixelFormat::YUV420, vec![0; 6], Duration::from_millis(30));
+    /// assert!(matches!(
+    ///     controller.sync_frame(&frame, Duration::ZERO),
+    ///     SyncDecision::Wait { .. }
+    /// ));
+    /// ```
+    pub fn set_threshold(&self, threshold: Duration) {
+        *self.threshold.write() = threshold;
+    }
+
+    /// Gets how far a frame may be from the clock and still be displayed
+    pub fn threshold(&self) -> Duration {
+        *self.threshold.read()
+    }
+
+    /// Decides what to do with a frame, see [`sync_frame`](Self::sync_frame)
+    fn decide(&self, video_frame: &VideoFrame, audio_timestamp: Duration) -> SyncDecision {
         let video_timestamp = video_frame.timestamp;
+        let threshold = self.threshold();
 
         // Calculate time difference (positive if video is ahead, negative if behind)
         let diff = if video_timestamp >= audio_timestamp {
@@ -110,23 +239,29 @@ impl AVSyncController {
             // Video is behind audio
             let behind_by = audio_timestamp - video_timestamp;
 
-            // If video is significantly behind (more than threshold), drop the frame
-            if behind_by > self.threshold {
+            // If video is significantly behind (more than threshold), drop
+            // the frame, unless frames have been dropped for too long
+            if behind_by > threshold && !self.ends_drop_run(video_timestamp) {
                 return SyncDecision::Drop;
             }
 
             // Within threshold, display it
+            *self.drop_run_start.write() = None;
             return SyncDecision::Display;
         };
 
         // Video is ahead of audio
-        if diff <= self.threshold {
+        if diff <= threshold {
             // Within tolerance, display immediately
             self.update_clock(video_timestamp);
+            *self.drop_run_start.write() = None;
             SyncDecision::Display
         } else {
-            // Too far ahead, need to wait
-            SyncDecision::Wait { duration: diff }
+            // Too far ahead, need to wait. The media clock runs `rate` times
+            // faster than wall-clock time, so the real wait is shorter.
+            SyncDecision::Wait {
+                duration: scale(diff, 1.0 / f64::from(self.rate())),
+            }
         }
     }
 
@@ -149,6 +284,157 @@ impl AVSyncController {
         *self.clock.read()
     }
 
+    /// Sets the playback rate
+    ///
+    /// The rate is clamped to the supported range (0.25 to 4.0).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::AVSyncController;
+    ///
+    /// let controller = AVSyncController::new();
+    /// controller.set_rate(2.0);
+    /// assert_eq!(controller.rate(), 2.0);
+    /// ```
+    pub fn set_rate(&self, rate: f32) {
+        *self.rate.write() = rate.clamp(MIN_PLAYBACK_RATE, MAX_PLAYBACK_RATE);
+    }
+
+    /// Gets the current playback rate
+    pub fn rate(&self) -> f32 {
+        *self.rate.read()
+    }
+
+    /// Uses an audio sink's played duration as the media clock
+    ///
+    /// Until the sink plays its first audio the clock keeps following
+    /// wall-clock time, so media without audio, or audio not yet decoded,
+    /// does not stall playback. From then on [`advance`](Self::advance)
+    /// moves the clock by the audio played since the last update, stalling
+    /// while the sink underruns. The sink is assumed to be fed audio
+    /// time-stretched by the playback rate, so its played duration is scaled
+    /// by the rate: at 2x, 100ms played out covers 200ms of media.
+    ///
+    /// Pass `None` to go back to the wall clock.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::AVSyncController;
+    /// use cortenbrowser_shared_types::{AudioBuffer, AudioSink, MediaError};
+    /// use std::sync::{Arc, Mutex};
+    /// use std::time::Duration;
+    ///
+    /// struct Sink(Mutex<Duration>);
+    ///
+    /// impl AudioSink for Sink {
+    ///     fn write(&self, buffer: AudioBuffer) -> Result<(), MediaError> {
+    ///         *self.0.lock().unwrap() += buffer.duration;
+    ///         Ok(())
+    ///     }
+    ///     fn played_duration(&self) -> Duration {
+    ///         *self.0.lock().unwrap()
+    ///     }
+    ///     fn set_volume(&self, _volume: f32) {}
+    /// }
+    ///
+    /// let sink = Arc::new(Sink(Mutex::new(Duration::ZERO)));
+    /// let controller = AVSyncController::new();
+    /// controller.set_audio_clock(Some(sink.clone()));
+    ///
+    /// *sink.0.lock().unwrap() = Duration::from_millis(20);
+    /// assert_eq!(controller.advance(Duration::from_secs(1)), Duration::from_millis(20));
+    /// ```
+    pub fn set_audio_clock(&self, sink: Option<Arc<dyn AudioSink>>) {
+        *self.audio_clock.write() = sink.map(|sink| AudioClock {
+            last_played: sink.played_duration(),
+            sink,
+            started: false,
+        });
+    }
+
+    /// Returns whether the clock follows an audio sink's played duration
+    pub fn is_audio_clock_active(&self) -> bool {
+        self.audio_clock
+            .read()
+            .as_ref()
+            .is_some_and(|audio| audio.started)
+    }
+
+    /// Advances the media clock by elapsed wall-clock time
+    ///
+    /// The advancement is scaled by the playback rate, so at 2x one second
+    /// of wall-clock time moves the media clock by two seconds. While an
+    /// audio clock is active, the clock moves by the audio played since the
+    /// last update instead and `elapsed` is ignored.
+    ///
+    /// # Returns
+    ///
+    /// The new clock time
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::AVSyncController;
+    /// use std::time::Duration;
+    ///
+    /// let controller = AVSyncController::new();
+    /// controller.set_rate(0.5);
+    /// assert_eq!(controller.advance(Duration::from_secs(1)), Duration::from_millis(500));
+    /// ```
+    pub fn advance(&self, elapsed: Duration) -> Duration {
+        if let Some(audio) = self.audio_clock.write().as_mut() {
+            let played = audio.sink.played_duration();
+            if played > audio.last_played {
+                audio.started = true;
+            }
+            if audio.started {
+                let mut clock = self.clock.write();
+                *clock += scale(
+                    played.saturating_sub(audio.last_played),
+                    f64::from(self.rate()),
+                );
+                audio.last_played = played;
+                return *clock;
+            }
+        }
+
+        let rate = self.rate();
+        let mut clock = self.clock.write();
+        *clock += scale(elapsed, f64::from(rate));
+        *clock
+    }
+
+    /// Moves the media clock to a position, e.g. after a seek
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_pipeline::AVSyncController;
+    /// use std::time::Duration;
+    ///
+    /// let controller = AVSyncController::new();
+    /// controller.advance(Duration::from_secs(5));
+    /// controller.set_clock(Duration::from_secs(1));
+    /// assert_eq!(controller.get_clock(), Duration::from_secs(1));
+    /// ```
+    pub fn set_clock(&self, position: Duration) {
+        *self.clock.write() = position;
+        *self.drop_run_start.write() = None;
+    }
+
+    /// Records a late frame at `timestamp` as dropped, unless the late
+    /// frames dropped in a row up to it span the max drop threshold
+    ///
+    /// # Returns
+    ///
+    /// Whether the frame should be displayed instead
+    fn ends_drop_run(&self, timestamp: Duration) -> bool {
+        let start = *self.drop_run_start.write().get_or_insert(timestamp);
+        timestamp >= start + self.max_drop_threshold
+    }
+
     /// Updates the internal clock to the given timestamp
     fn update_clock(&self, timestamp: Duration) {
         let mut clock = self.clock.write();
@@ -158,6 +444,11 @@ impl AVSyncController {
     }
 }
 
+/// Scales a duration by a factor, rounded to the nearest nanosecond
+fn scale(duration: Duration, factor: f64) -> Duration {
+    Duration::from_nanos((duration.as_nanos() as f64 * factor).round() as u64)
+}
+
 impl Default for AVSyncController {
     fn default() -> Self {
         Self::new()
@@ -167,7 +458,23 @@ impl Default for AVSyncController {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cortenbrowser_shared_types::{FrameMetadata, PixelFormat};
+    use cortenbrowser_shared_types::{AudioBuffer, FrameMetadata, MediaError, PixelFormat};
+    use parking_lot::Mutex;
+
+    /// Sink whose played duration is set by the test
+    struct ManualSink(Mutex<Duration>);
+
+    impl AudioSink for ManualSink {
+        fn write(&self, _buffer: AudioBuffer) -> Result<(), MediaError> {
+            Ok(())
+        }
+
+        fn played_duration(&self) -> Duration {
+            *self.0.lock()
+        }
+
+        fn set_volume(&self, _volume: f32) {}
+    }
 
     fn create_test_frame(timestamp: Duration) -> VideoFrame {
         VideoFrame {
@@ -175,6 +482,7 @@ mod tests {
             height: 1080,
             format: PixelFormat::YUV420,
             data: vec![0u8; 1920 * 1080],
+            strides: None,
             timestamp,
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -204,6 +512,67 @@ mod tests {
         assert_eq!(decision, SyncDecision::Drop);
     }
 
+    #[test]
+    fn test_set_threshold_at_runtime() {
+        let controller = AVSyncController::new();
+        // Frame is 30ms ahead, within the default 40ms threshold
+        let frame = create_test_frame(Duration::from_millis(1030));
+        let audio_timestamp = Duration::from_millis(1000);
+        assert_eq!(
+            controller.sync_frame(&frame, audio_timestamp),
+            SyncDecision::Display
+        );
+
+        controller.set_threshold(Duration::from_millis(20));
+        assert_eq!(controller.threshold(), Duration::from_millis(20));
+        assert_eq!(
+            controller.sync_frame(&frame, audio_timestamp),
+            SyncDecision::Wait {
+                duration: Duration::from_millis(30)
+            }
+        );
+    }
+
+    #[test]
+    fn test_late_frame_displayed_after_max_drop_run() {
+        let controller = AVSyncController::with_thresholds(
+            Duration::from_millis(40),
+            Duration::from_millis(100),
+        );
+        let frame = create_test_frame(Duration::from_millis(1000));
+        assert_eq!(
+            controller.sync_frame(&frame, Duration::from_millis(1000)),
+            SyncDecision::Display
+        );
+
+        // Decoding falls 100ms behind the clock
+        let decisions: Vec<SyncDecision> = (1..=5)
+            .map(|i| {
+                let timestamp = Duration::from_millis(1000 + i * 40);
+                let frame = create_test_frame(timestamp);
+                controller.sync_frame(&frame, timestamp + Duration::from_millis(100))
+            })
+            .collect();
+        assert_eq!(
+            decisions,
+            [
+                SyncDecision::Drop,
+                SyncDecision::Drop,
+                SyncDecision::Drop,
+                SyncDecision::Display,
+                SyncDecision::Drop
+            ]
+        );
+
+        // A seek starts over
+        controller.set_clock(Duration::from_secs(5));
+        let frame = create_test_frame(Duration::from_millis(1200));
+        assert_eq!(
+            controller.sync_frame(&frame, Duration::from_secs(5)),
+            SyncDecision::Drop
+        );
+    }
+
     #[test]
     fn test_wait_for_early_frames() {
         let controller = AVSyncController::new();
@@ -219,6 +588,56 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_counts_displayed_and_dropped_frames() {
+        let controller = AVSyncController::new();
+        let position = Duration::from_secs(1);
+        controller.sync_frame(&create_test_frame(Duration::from_millis(900)), position);
+        controller.sync_frame(&create_test_frame(Duration::from_millis(1000)), position);
+        controller.sync_frame(&create_test_frame(Duration::from_millis(1100)), position);
+        assert_eq!(controller.frames_dropped(), 1);
+        assert_eq!(controller.frames_displayed(), 1);
+    }
+
+    #[test]
+    fn test_advance_scales_by_rate() {
+        let controller = AVSyncController::new();
+        assert_eq!(
+            controller.advance(Duration::from_millis(100)),
+            Duration::from_millis(100)
+        );
+
+        controller.set_rate(2.0);
+        assert_eq!(
+            controller.advance(Duration::from_millis(100)),
+            Duration::from_millis(300)
+        );
+    }
+
+    #[test]
+    fn test_set_rate_clamps_to_supported_range() {
+        let controller = AVSyncController::new();
+        controller.set_rate(0.0);
+        assert_eq!(controller.rate(), MIN_PLAYBACK_RATE);
+        controller.set_rate(100.0);
+        assert_eq!(controller.rate(), MAX_PLAYBACK_RATE);
+    }
+
+    #[test]
+    fn test_wait_scales_by_rate() {
+        let controller = AVSyncController::new();
+        controller.set_rate(2.0);
+        // Frame is 100ms of media time ahead: 50ms of wall-clock time at 2x
+        let frame = create_test_frame(Duration::from_millis(1100));
+        let decision = controller.sync_frame(&frame, Duration::from_millis(1000));
+        assert_eq!(
+            decision,
+            SyncDecision::Wait {
+                duration: Duration::from_millis(50)
+            }
+        );
+    }
+
     #[test]
     fn test_display_slightly_behind_frames() {
         let controller = AVSyncController::new();
@@ -227,4 +646,70 @@ mod tests {
         let decision = controller.sync_frame(&frame, Duration::from_millis(1000));
         assert_eq!(decision, SyncDecision::Display);
     }
+
+    #[test]
+    fn test_audio_clock_follows_played_duration() {
+        let controller = AVSyncController::new();
+        let sink = Arc::new(ManualSink(Mutex::new(Duration::from_secs(3))));
+        controller.set_audio_clock(Some(sink.clone()));
+
+        // Wall clock until the sink plays
+        assert_eq!(
+            controller.advance(Duration::from_millis(10)),
+            Duration::from_millis(10)
+        );
+        assert!(!controller.is_audio_clock_active());
+
+        // Then audio played since attaching, regardless of elapsed time
+        *sink.0.lock() = Duration::from_millis(3020);
+        assert_eq!(
+            controller.advance(Duration::from_millis(10)),
+            Duration::from_millis(30)
+        );
+        assert!(controller.is_audio_clock_active());
+
+        // Stalls while the sink underruns
+        assert_eq!(
+            controller.advance(Duration::from_millis(10)),
+            Duration::from_millis(30)
+        );
+    }
+
+    #[test]
+    fn test_audio_clock_scales_by_rate_and_keeps_seek_position() {
+        let controller = AVSyncController::new();
+        let sink = Arc::new(ManualSink(Mutex::new(Duration::ZERO)));
+        controller.set_audio_clock(Some(sink.clone()));
+        controller.set_rate(2.0);
+
+        *sink.0.lock() = Duration::from_millis(100);
+        assert_eq!(
+            controller.advance(Duration::from_millis(10)),
+            Duration::from_millis(200)
+        );
+
+        controller.set_clock(Duration::from_secs(5));
+        *sink.0.lock() = Duration::from_millis(150);
+        assert_eq!(
+            controller.advance(Duration::from_millis(10)),
+            Duration::from_millis(5100)
+        );
+    }
+
+    #[test]
+    fn test_detaching_audio_clock_restores_wall_clock() {
+        let controller = AVSyncController::new();
+        let sink = Arc::new(ManualSink(Mutex::new(Duration::ZERO)));
+        controller.set_audio_clock(Some(sink.clone()));
+        *sink.0.lock() = Duration::from_millis(100);
+        controller.advance(Duration::ZERO);
+
+        controller.set_audio_clock(None);
+
+        assert!(!controller.is_audio_clock_active());
+        assert_eq!(
+            controller.advance(Duration::from_millis(10)),
+            Duration::from_millis(110)
+        );
+    }
 }
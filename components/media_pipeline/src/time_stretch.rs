@@ -0,0 +1,214 @@
+//! Audio time-stretching
+//!
+//! At playback rates other than 1.0 the decoded audio must play out in
+//! 1/rate of its media duration to keep up with the scaled media clock. The
+//! pipeline passes every audio buffer through a [`TimeStretcher`] before it
+//! leaves the audio queue. [`ResampleStretcher`] is the default; a
+//! pitch-preserving stretcher (e.g. WSOLA) can replace it with
+//! [`MediaPipeline::set_time_stretcher`](crate::MediaPipeline::set_time_stretcher).
+
+use cortenbrowser_shared_types::AudioBuffer;
+use std::fmt;
+use std::time::Duration;
+
+/// Audio time-stretching stage
+///
+/// Implementations change the length of audio by the playback rate: at 2.0
+/// the output lasts half as long as the input. Buffers are processed in
+/// stream order, so state may carry across them.
+pub trait TimeStretcher: Send + fmt::Debug {
+    /// Stretch a buffer of media-time audio to play at `rate`
+    ///
+    /// The output keeps the input's timestamp, in media time, and its
+    /// duration is the time it takes to play.
+    fn process(&mut self, buffer: AudioBuffer, rate: f32) -> AudioBuffer;
+
+    /// Discard state carried between buffers, e.g. after a seek
+    fn reset(&mut self);
+}
+
+/// Time-stretching by resampling
+///
+/// Plays the audio faster or slower like a tape, so pitch rises and falls
+/// with the rate. Samples are linearly interpolated, including across
+/// buffer boundaries, and buffers pass through unchanged at 1.0.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_pipeline::{ResampleStretcher, TimeStretcher};
+/// use cortenbrowser_shared_types::{AudioBuffer, AudioFormat};
+/// use std::time::Duration;
+///
+/// let mut stretcher = ResampleStretcher::new();
+/// let buffer = AudioBuffer::new(AudioFormat::F32LE, 48000, 2, vec![0.0; 1920], Duration::ZERO);
+///
+/// let stretched = stretcher.process(buffer, 2.0);
+/// assert_eq!(stretched.samples.len(), 960);
+/// assert_eq!(stretched.duration, Duration::from_millis(10));
+/// ```
+#[derive(Debug, Default)]
+pub struct ResampleStretcher {
+    /// Input position of the next output frame, in frames from the start of
+    /// the next buffer; negative positions lie between `last` and it
+    position: f64,
+    /// Last frame of the previous buffer
+    last: Vec<f32>,
+}
+
+impl ResampleStretcher {
+    /// Create a stretcher at the start of a stream
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TimeStretcher for ResampleStretcher {
+    fn process(&mut self, buffer: AudioBuffer, rate: f32) -> AudioBuffer {
+        let channels = buffer.channels as usize;
+        if channels == 0 || buffer.samples.len() < channels {
+            return buffer;
+        }
+        if self.last.len() != channels {
+            // First buffer, or the channel layout changed
+            self.reset();
+        }
+
+        let frames = buffer.samples.len() / channels;
+        let last = buffer.samples[(frames - 1) * channels..frames * channels].to_vec();
+        if rate == 1.0 && self.position == 0.0 {
+            self.last = last;
+            return buffer;
+        }
+
+        // Frame `index` of the buffer, with -1 the previous buffer's last
+        let frame = |index: isize| -> &[f32] {
+            match index {
+                -1 => &self.last,
+                _ => &buffer.samples[index as usize * channels..(index as usize + 1) * channels],
+            }
+        };
+
+        let step = f64::from(rate);
+        let end = (frames - 1) as f64;
+        let mut position = self.position;
+        let mut samples = Vec::with_capacity(((frames as f64 / step) as usize + 1) * channels);
+        while position <= end {
+            let index = position.floor();
+            let fraction = (position - index) as f32;
+            let first = frame(index as isize);
+            let second = frame((index as isize + 1).min(frames as isize - 1));
+            samples.extend(
+                first
+                    .iter()
+                    .zip(second)
+                    .map(|(&a, &b)| a + (b - a) * fraction),
+            );
+            position += step;
+        }
+
+        self.position = position - frames as f64;
+        self.last = last;
+
+        let duration = Duration::from_secs_f64(
+            (samples.len() / channels) as f64 / f64::from(buffer.sample_rate),
+        );
+        AudioBuffer {
+            samples,
+            duration,
+            ..buffer
+        }
+    }
+
+    fn reset(&mut self) {
+        self.position = 0.0;
+        self.last.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cortenbrowser_shared_types::AudioFormat;
+
+    /// Mono buffer of consecutive values starting at `start`
+    fn ramp(start: usize, frames: usize) -> AudioBuffer {
+        let samples = (start..start + frames).map(|i| i as f32).collect();
+        AudioBuffer::new(AudioFormat::F32LE, 48000, 1, samples, Duration::ZERO)
+    }
+
+    #[test]
+    fn test_unit_rate_passes_through() {
+        let mut stretcher = ResampleStretcher::new();
+        let buffer = ramp(0, 480);
+
+        assert_eq!(stretcher.process(buffer.clone(), 1.0), buffer);
+    }
+
+    #[test]
+    fn test_speedup_shortens_audio() {
+        let mut stretcher = ResampleStretcher::new();
+
+        let output = stretcher.process(ramp(0, 480), 2.0);
+
+        assert_eq!(output.samples.len(), 240);
+        assert_eq!(output.duration, Duration::from_millis(5));
+        assert_eq!(output.samples[..3], [0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_slowdown_interpolates() {
+        let mut stretcher = ResampleStretcher::new();
+
+        let output = stretcher.process(ramp(0, 4), 0.5);
+
+        assert_eq!(output.samples, [0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0]);
+    }
+
+    #[test]
+    fn test_continuous_across_buffers() {
+        let mut stretcher = ResampleStretcher::new();
+
+        // A ramp split into buffers stays a ramp with the rate as its slope
+        let mut output = Vec::new();
+        for start in (0..1000).step_by(100) {
+            output.extend(stretcher.process(ramp(start, 100), 1.5).samples);
+        }
+
+        assert_eq!(output.len(), 667);
+        for (i, sample) in output.iter().enumerate() {
+            assert!((sample - i as f32 * 1.5).abs() < 1e-3, "sample {}", i);
+        }
+    }
+
+    #[test]
+    fn test_stereo_channels_stay_separate() {
+        let mut stretcher = ResampleStretcher::new();
+        let buffer = AudioBuffer::new(
+            AudioFormat::F32LE,
+            48000,
+            2,
+            vec![1.0, -1.0, 3.0, -3.0, 5.0, -5.0],
+            Duration::ZERO,
+        );
+
+        let output = stretcher.process(buffer, 0.5);
+
+        assert_eq!(output.channels, 2);
+        assert_eq!(
+            output.samples,
+            [1.0, -1.0, 2.0, -2.0, 3.0, -3.0, 4.0, -4.0, 5.0, -5.0]
+        );
+    }
+
+    #[test]
+    fn test_reset_starts_a_new_stream() {
+        let mut stretcher = ResampleStretcher::new();
+        stretcher.process(ramp(0, 101), 2.0);
+
+        stretcher.reset();
+
+        let output = stretcher.process(ramp(500, 4), 2.0);
+        assert_eq!(output.samples, [500.0, 502.0]);
+    }
+}
@@ -1,7 +1,52 @@
 //! Type definitions for the media pipeline
 
+use crate::abr::AbrConfig;
+use crate::sync::{DEFAULT_MAX_DROP_THRESHOLD, DEFAULT_SYNC_THRESHOLD};
+use cortenbrowser_shared_types::{
+    AudioCodec, AudioDecoder, EncryptionInfo, LoopMode, MediaError, PreloadStrategy, VideoCodec,
+    VideoDecoder, DEFAULT_MAX_BUFFER_AHEAD,
+};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Read, Seek};
+use std::sync::Arc;
 use std::time::Duration;
 
+/// Creates the video decoder for a codec
+///
+/// Called on the decoder thread, since decoders are not `Send`.
+pub type VideoDecoderFactory =
+    Arc<dyn Fn(&VideoCodec) -> Result<Box<dyn VideoDecoder>, MediaError> + Send + Sync>;
+
+/// Creates the audio decoder for a codec
+///
+/// Called on the decoder thread, since decoders are not `Send`.
+pub type AudioDecoderFactory =
+    Arc<dyn Fn(&AudioCodec) -> Result<Box<dyn AudioDecoder>, MediaError> + Send + Sync>;
+
+/// Decrypts the data of an encrypted packet
+///
+/// Returns `Ok(None)` while the packet's key is not usable, in which case
+/// decoding waits and the packet is tried again. Called on the decoder
+/// thread.
+pub type PacketDecryptor =
+    Arc<dyn Fn(&[u8], &EncryptionInfo) -> Result<Option<Vec<u8>>, MediaError> + Send + Sync>;
+
+/// Source of media data for the pipeline
+///
+/// Seeking the reader is how the pipeline requests a byte range, so network
+/// readers should map it to a range request rather than downloading the
+/// skipped data.
+pub trait MediaReader: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> MediaReader for T {}
+
+impl fmt::Debug for dyn MediaReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MediaReader")
+    }
+}
+
 /// Configuration for the media pipeline
 #[derive(Debug, Clone, PartialEq)]
 pub struct PipelineConfig {
@@ -9,8 +54,50 @@ pub struct PipelineConfig {
     pub buffer_size: usize,
     /// Number of threads for decode operations
     pub thread_count: usize,
-    /// Synchronization threshold for A/V sync
+    /// How far a video frame may be from the clock and still be displayed
     pub sync_threshold: Duration,
+    /// Longest media time of consecutive late frames dropped before one is
+    /// displayed anyway
+    pub max_drop_threshold: Duration,
+    /// Preserve audio pitch when playing at rates other than 1.0
+    ///
+    /// When disabled, audio is sped up or slowed down like a tape, shifting
+    /// its pitch with the rate.
+    pub pitch_correct: bool,
+    /// What playback does when it reaches the end of the media
+    pub loop_mode: LoopMode,
+    /// When adaptive streams switch representations
+    pub abr: AbrConfig,
+    /// Decode and queue the media's video
+    ///
+    /// When disabled, no video decoder is started and the video queue is
+    /// closed, e.g. for audio-only playback of a video file.
+    pub enable_video: bool,
+    /// Queue the media's audio
+    ///
+    /// When disabled, the audio queue is closed.
+    pub enable_audio: bool,
+    /// How much of a source given to
+    /// [`set_reader`](crate::MediaPipeline::set_reader) is read before
+    /// [`start`](crate::MediaPipeline::start)
+    ///
+    /// `None` reads nothing, `Metadata` parses the media information only
+    /// and `Auto` also starts decoding.
+    pub preload: PreloadStrategy,
+    /// How far ahead of the playback position video is decoded
+    ///
+    /// Decoding waits while this much is buffered ahead.
+    pub max_buffer_ahead: Duration,
+    /// What video decoding does when a decoder fails to decode a packet
+    pub recovery_policy: RecoveryPolicy,
+    /// Most codec errors tolerated within `codec_error_window`
+    ///
+    /// One more puts the pipeline in its error state, whatever the recovery
+    /// policy. `None` tolerates any number.
+    pub max_codec_errors: Option<usize>,
+    /// Length of the sliding window in which codec errors are counted
+    /// against `max_codec_errors`
+    pub codec_error_window: Duration,
 }
 
 impl Default for PipelineConfig {
@@ -18,11 +105,128 @@ impl Default for PipelineConfig {
         Self {
             buffer_size: 1024,
             thread_count: 4,
-            sync_threshold: Duration::from_millis(40), // 40ms tolerance
+            sync_threshold: DEFAULT_SYNC_THRESHOLD,
+            max_drop_threshold: DEFAULT_MAX_DROP_THRESHOLD,
+            pitch_correct: true,
+            loop_mode: LoopMode::None,
+            abr: AbrConfig::default(),
+            enable_video: true,
+            enable_audio: true,
+            preload: PreloadStrategy::Auto,
+            max_buffer_ahead: DEFAULT_MAX_BUFFER_AHEAD,
+            recovery_policy: RecoveryPolicy::default(),
+            max_codec_errors: None,
+            codec_error_window: Duration::from_secs(1),
         }
     }
 }
 
+/// How video decoding recovers when a decoder returns
+/// `MediaError::CodecError` for a packet
+///
+/// Decoders also report packets they hold back while filling their pipeline
+/// as codec errors, so `Fail` and `ReinitDecoder` suit decoders that output a
+/// frame for every packet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RecoveryPolicy {
+    /// Stop decoding and put the pipeline in its error state
+    Fail,
+    /// Discard the packet and decode the next one
+    #[default]
+    SkipPacket,
+    /// Discard the packet and the decoder, and decode the next packet with
+    /// a newly created decoder
+    ReinitDecoder,
+}
+
+/// Kind of media a track carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackKind {
+    /// Video track
+    Video,
+    /// Audio track
+    Audio,
+}
+
+/// A track of the loaded media
+///
+/// See [`MediaPipeline::tracks`](crate::MediaPipeline::tracks).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackInfo {
+    /// Track identifier from the container
+    pub track_id: u32,
+    /// Kind of media the track carries
+    pub kind: TrackKind,
+    /// Whether the track is the one of its kind that plays
+    pub selected: bool,
+}
+
+/// Counters of a pipeline's decoding and playback
+///
+/// See [`MediaPipeline::stats`](crate::MediaPipeline::stats).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineStats {
+    /// Video frames decoded
+    pub frames_decoded: u64,
+    /// Audio buffers queued for playout
+    pub audio_buffers_queued: u64,
+    /// Video packets that did not decode to a frame, including those a
+    /// decoder held back while filling its pipeline
+    pub decode_errors: u64,
+    /// Video frames the A/V sync controller decided to display
+    pub frames_displayed: u64,
+    /// Video frames the A/V sync controller dropped for running late
+    pub frames_dropped: u64,
+    /// Times video playback ran out of decoded frames
+    pub underruns: u64,
+    /// Times audio playback ran out of queued audio before the end of the
+    /// media
+    pub audio_underruns: u64,
+    /// Total time spent decoding the decoded frames
+    pub decode_time: Duration,
+    /// Median time to decode one of the recently decoded frames
+    pub decode_time_p50: Duration,
+    /// 90th percentile time to decode one of the recently decoded frames
+    pub decode_time_p90: Duration,
+    /// 99th percentile time to decode one of the recently decoded frames
+    pub decode_time_p99: Duration,
+    /// Bits per second of media time of the recently decoded video packets
+    pub bitrate: u64,
+    /// Video frames waiting in the queue
+    pub video_queue_depth: usize,
+    /// Audio buffers waiting in the queue
+    pub audio_queue_depth: usize,
+    /// Bytes of decoded media waiting in the queues
+    pub queued_bytes: usize,
+}
+
+/// Performance metrics of a pipeline since loading or the last seek
+///
+/// See [`MediaPipeline::get_metrics`](crate::MediaPipeline::get_metrics).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PipelineMetrics {
+    /// Video frames waiting in the queue
+    pub video_queue_depth: usize,
+    /// Audio buffers waiting in the queue
+    pub audio_queue_depth: usize,
+    /// Average time the decoder took to produce a video frame, in
+    /// milliseconds
+    pub avg_video_decode_latency_ms: f64,
+    /// Average time taken to decode an audio buffer, in milliseconds
+    ///
+    /// Zero while audio is decoded before it is queued to the pipeline.
+    pub avg_audio_decode_latency_ms: f64,
+    /// Video frames produced by the decoder
+    pub total_frames_produced: u64,
+    /// Audio buffers queued for playout
+    pub total_audio_buffers_produced: u64,
+    /// Video frames the A/V sync controller dropped for running late
+    pub dropped_frames: u64,
+    /// Packets each type of video decoder failed with a codec error, by
+    /// codec name such as `"h264"`
+    pub codec_errors: BTreeMap<String, u64>,
+}
+
 /// Decision made by the A/V sync controller
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SyncDecision {
@@ -2,10 +2,17 @@
 //!
 //! Tests the end-to-end pipeline workflow.
 
-use cortenbrowser_media_pipeline::{AVSyncController, MediaPipeline, PipelineConfig, SyncDecision};
+use cortenbrowser_format_parsers::{Demuxer, Mp4Demuxer};
+use cortenbrowser_media_pipeline::{
+    AVSyncController, AbrConfig, MediaPipeline, PipelineConfig, RecoveryPolicy, SyncDecision,
+};
 use cortenbrowser_shared_types::{
-    AudioBuffer, AudioFormat, FrameMetadata, MediaSource, PixelFormat, VideoFrame,
+    AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, FrameMetadata, LoopMode, MediaError,
+    MediaSource, PixelFormat, PreloadStrategy, VideoDecoder, VideoFrame, VideoPacket,
 };
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 #[tokio::test]
@@ -19,6 +26,17 @@ async fn test_complete_pipeline_lifecycle() {
         buffer_size: 2048,
         thread_count: 4,
         sync_threshold: Duration::from_millis(40),
+        max_drop_threshold: Duration::from_millis(200),
+        pitch_correct: true,
+        loop_mode: LoopMode::None,
+        abr: AbrConfig::default(),
+        enable_video: true,
+        enable_audio: true,
+        preload: PreloadStrategy::Auto,
+        max_buffer_ahead: Duration::from_secs(30),
+        recovery_policy: RecoveryPolicy::SkipPacket,
+        max_codec_errors: None,
+        codec_error_window: Duration::from_secs(1),
     };
 
     let pipeline = MediaPipeline::new(config).unwrap();
@@ -150,6 +168,7 @@ fn create_test_video_frame(timestamp: Duration) -> VideoFrame {
         height: 1080,
         format: PixelFormat::YUV420,
         data: vec![0u8; 1920 * 1080],
+        strides: None,
         timestamp,
         duration: Some(Duration::from_millis(33)),
         metadata: FrameMetadata {
@@ -157,6 +176,7 @@ fn create_test_video_frame(timestamp: Duration) -> VideoFrame {
             pts: Some(timestamp.as_millis() as i64),
             dts: Some(timestamp.as_millis() as i64),
             sequence: Some(0),
+            ..Default::default()
         },
     }
 }
@@ -170,6 +190,7 @@ fn create_test_audio_buffer(timestamp: Duration) -> AudioBuffer {
         samples: vec![0.0f32; 4800],
         timestamp,
         duration: Duration::from_millis(100),
+        channel_map: None,
     }
 }
 
@@ -178,3 +199,807 @@ fn create_mock_mp4_data() -> Vec<u8> {
     // In a real implementation, this would be actual MP4 data
     vec![0x00, 0x00, 0x00, 0x18, 0x66, 0x74, 0x79, 0x70] // ftyp box
 }
+
+/// MP4 with one H.264 track of 12 40 ms samples and a keyframe every 4
+fn keyframed_mp4() -> Vec<u8> {
+    let config = mp4::Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = mp4::Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&mp4::TrackConfig {
+            track_type: mp4::TrackType::Video,
+            timescale: 1000,
+            language: "und".to_string(),
+            media_conf: mp4::MediaConfig::AvcConfig(mp4::AvcConfig {
+                width: 64,
+                height: 64,
+                seq_param_set: vec![0x67, 0x42, 0x00, 0x0A],
+                pic_param_set: vec![0x68, 0xCE, 0x38, 0x80],
+            }),
+        })
+        .unwrap();
+    for i in 0..12u64 {
+        writer
+            .write_sample(
+                1,
+                &mp4::Mp4Sample {
+                    start_time: i * 40,
+                    duration: 40,
+                    rendering_offset: 0,
+                    is_sync: i % 4 == 0,
+                    bytes: mp4::Bytes::from(vec![i as u8; 100]),
+                },
+            )
+            .unwrap();
+    }
+    writer.write_end().unwrap();
+    writer.into_writer().into_inner()
+}
+
+/// Reader recording the offsets it is seeked to
+struct RangeReader {
+    inner: Cursor<Vec<u8>>,
+    seeks: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Read for RangeReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for RangeReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let offset = self.inner.seek(pos)?;
+        self.seeks.lock().unwrap().push(offset);
+        Ok(offset)
+    }
+}
+
+#[tokio::test]
+async fn test_seek_reads_from_keyframe_offset() {
+    // Given a pipeline reading an MP4 with keyframes every 160 ms
+    // When seeking to 200 ms
+    // Then the source is read again from the keyframe at 160 ms only
+
+    let data = keyframed_mp4();
+    let mut demuxer = Mp4Demuxer::new();
+    demuxer.load(&data).unwrap();
+    let keyframe = demuxer
+        .seek_index()
+        .unwrap()
+        .entry_for_time(1, Duration::from_millis(200))
+        .copied()
+        .unwrap();
+    assert_eq!(keyframe.time, Duration::from_millis(160));
+
+    let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    let source = MediaSource::Buffer {
+        data: data.clone(),
+        mime_type: "video/mp4".to_string(),
+    };
+    pipeline.load_source(source).await.unwrap();
+
+    let seeks = Arc::new(Mutex::new(Vec::new()));
+    let reader = RangeReader {
+        inner: Cursor::new(data),
+        seeks: Arc::clone(&seeks),
+    };
+    pipeline.set_reader(Box::new(reader)).unwrap();
+    assert_eq!(*seeks.lock().unwrap(), vec![0]);
+
+    pipeline.seek(Duration::from_millis(200)).await.unwrap();
+    assert_eq!(*seeks.lock().unwrap(), vec![0, keyframe.byte_offset]);
+    assert_eq!(pipeline.current_position(), Duration::from_millis(200));
+}
+
+/// Loads `keyframed_mp4` through a reader recording its seeks
+async fn keyframed_pipeline(loop_mode: LoopMode) -> (MediaPipeline, Arc<Mutex<Vec<u64>>>) {
+    let config = PipelineConfig {
+        loop_mode,
+        ..Default::default()
+    };
+    let pipeline = MediaPipeline::new(config).unwrap();
+    let data = keyframed_mp4();
+    let source = MediaSource::Buffer {
+        data: data.clone(),
+        mime_type: "video/mp4".to_string(),
+    };
+    pipeline.load_source(source).await.unwrap();
+
+    let seeks = Arc::new(Mutex::new(Vec::new()));
+    let reader = RangeReader {
+        inner: Cursor::new(data),
+        seeks: Arc::clone(&seeks),
+    };
+    pipeline.set_reader(Box::new(reader)).unwrap();
+    (pipeline, seeks)
+}
+
+#[tokio::test]
+async fn test_buffer_source_is_seekable_to_its_duration() {
+    // Given a pipeline reading a buffer source
+    // When nothing has been decoded yet
+    // Then all of its 480 ms are seekable, though little is buffered
+
+    let (pipeline, _) = keyframed_pipeline(LoopMode::None).await;
+
+    assert_eq!(
+        pipeline.seekable_range(),
+        (Duration::ZERO, Duration::from_millis(480))
+    );
+    let buffered = pipeline.buffered_ranges();
+    assert!(buffered
+        .iter()
+        .all(|&(start, end)| start < end && end <= Duration::from_millis(480)));
+}
+
+#[tokio::test]
+async fn test_drained_buffer_source_ends() {
+    // Given a running pipeline on a short buffer source without looping
+    // When the decoder has delivered all of the media
+    // Then the pipeline reports the end before its 480 ms duration
+
+    let (pipeline, _) = keyframed_pipeline(LoopMode::None).await;
+    let mut ended = pipeline.subscribe_ended();
+    pipeline.start().await.unwrap();
+
+    tokio::time::timeout(Duration::from_millis(300), ended.wait_for(|ended| *ended))
+        .await
+        .expect("The pipeline should end once drained")
+        .unwrap();
+    assert!(!pipeline.is_running());
+    assert_eq!(pipeline.loop_count(), 0);
+}
+
+#[tokio::test]
+async fn test_looping_buffer_source_reads_media_again() {
+    // Given a running pipeline on a short buffer source looping all
+    // When it is drained twice
+    // Then it reads the media again from the first keyframe each time
+
+    let (pipeline, seeks) = keyframed_pipeline(LoopMode::All).await;
+    let mut loops = pipeline.subscribe_loops();
+    pipeline.start().await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), loops.wait_for(|count| *count >= 2))
+        .await
+        .expect("The pipeline should loop")
+        .unwrap();
+    assert!(!pipeline.is_ended());
+
+    let seeks = seeks.lock().unwrap().clone();
+    assert!(seeks.len() >= 3);
+    assert!(seeks[1..].iter().all(|&offset| offset == seeks[1]));
+}
+
+fn mp4_box(box_type: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut data = ((8 + payload.len()) as u32).to_be_bytes().to_vec();
+    data.extend_from_slice(box_type);
+    data.extend_from_slice(payload);
+    data
+}
+
+/// Apply `edit` to the payload of every box at `path` below `data`
+fn edit_boxes(data: &[u8], path: &[&[u8; 4]], edit: &dyn Fn(&[u8]) -> Vec<u8>) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while rest.len() >= 8 {
+        let size = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        let box_type: [u8; 4] = rest[4..8].try_into().unwrap();
+        let payload = &rest[8..size];
+        if &box_type != path[0] {
+            out.extend_from_slice(&rest[..size]);
+        } else if path.len() == 1 {
+            out.extend(mp4_box(&box_type, &edit(payload)));
+        } else {
+            out.extend(mp4_box(&box_type, &edit_boxes(payload, &path[1..], edit)));
+        }
+        rest = &rest[size..];
+    }
+    out
+}
+
+/// `keyframed_mp4` with its track marked as `cenc` encrypted with key ID
+/// 0x10.., each sample whole with an 8-byte IV of its index
+fn encrypted_keyframed_mp4() -> Vec<u8> {
+    let mut tenc = vec![0, 0, 0, 0, 0, 0, 1, 8];
+    tenc.extend_from_slice(&[0x10; 16]);
+    let sinf = mp4_box(
+        b"sinf",
+        &[
+            mp4_box(b"frma", b"avc1"),
+            mp4_box(
+                b"schm",
+                &[&[0, 0, 0, 0][..], b"cenc", &[0, 1, 0, 0]].concat(),
+            ),
+            mp4_box(b"schi", &mp4_box(b"tenc", &tenc)),
+        ]
+        .concat(),
+    );
+    let mut senc = vec![0, 0, 0, 0, 0, 0, 0, 12];
+    for i in 0..12u8 {
+        senc.extend_from_slice(&[i; 8]);
+    }
+
+    edit_boxes(
+        &keyframed_mp4(),
+        &[b"moov", b"trak", b"mdia", b"minf", b"stbl"],
+        &|stbl| {
+            let children = edit_boxes(stbl, &[b"stsd"], &|stsd| {
+                let entry = &stsd[8..];
+                let payload = [&entry[8..], &sinf[..]].concat();
+                [&stsd[..8], &mp4_box(b"encv", &payload)[..]].concat()
+            });
+            [children, mp4_box(b"senc", &senc)].concat()
+        },
+    )
+}
+
+/// Decoder recording the data of the packets it decodes
+struct RecordingDecoder {
+    packets: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl VideoDecoder for RecordingDecoder {
+    fn decode(&mut self, packet: &VideoPacket) -> Result<VideoFrame, MediaError> {
+        self.packets.lock().unwrap().push(packet.data.clone());
+        let timestamp = Duration::from_millis(packet.pts.unwrap_or_default() as u64);
+        Ok(VideoFrame::new(
+            64,
+            64,
+            PixelFormat::YUV420,
+            Vec::new(),
+            timestamp,
+        ))
+    }
+
+    fn flush(&mut self) -> Result<Vec<VideoFrame>, MediaError> {
+        Ok(Vec::new())
+    }
+}
+
+#[tokio::test]
+async fn test_encrypted_packets_wait_for_key_then_decrypt() {
+    // Given a pipeline on an encrypted MP4 whose key is not usable yet
+    // When the key becomes usable
+    // Then decoding waits until then and decodes the decrypted packets
+
+    let pipeline = MediaPipeline::new(PipelineConfig::default()).unwrap();
+    let packets = Arc::new(Mutex::new(Vec::new()));
+    let decoded = Arc::clone(&packets);
+    pipeline.set_decoder_factory(move |_| {
+        Ok(Box::new(RecordingDecoder {
+            packets: Arc::clone(&decoded),
+        }))
+    });
+    let key_usable = Arc::new(AtomicBool::new(false));
+    let usable = Arc::clone(&key_usable);
+    pipeline.set_decryptor(move |data, info| {
+        assert_eq!(info.key_id, vec![0x10; 16]);
+        if !usable.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        // The "decrypted" data is the sample's IV byte
+        Ok(Some(vec![info.iv[0]; data.len()]))
+    });
+    let mut waiting = pipeline.subscribe_waiting_for_key();
+
+    let data = encrypted_keyframed_mp4();
+    let source = MediaSource::Buffer {
+        data: data.clone(),
+        mime_type: "video/mp4".to_string(),
+    };
+    pipeline.load_source(source).await.unwrap();
+    pipeline.set_reader(Box::new(Cursor::new(data))).unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), waiting.wait_for(|waiting| *waiting))
+        .await
+        .expect("Decoding should wait for the key")
+        .unwrap();
+    assert!(packets.lock().unwrap().is_empty());
+
+    key_usable.store(true, Ordering::Relaxed);
+    tokio::time::timeout(
+        Duration::from_secs(2),
+        waiting.wait_for(|waiting| !*waiting),
+    )
+    .await
+    .expect("Decoding should continue with the key")
+    .unwrap();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while packets.lock().unwrap().len() < 12 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("Every packet should be decoded");
+
+    let packets = packets.lock().unwrap();
+    for (i, packet) in packets.iter().enumerate() {
+        assert_eq!(packet, &vec![i as u8; 100]);
+    }
+}
+
+/// Pipeline on `keyframed_mp4` with `config`, decoding through a
+/// `RecordingDecoder` and reading through a `RangeReader`
+async fn recording_pipeline(
+    config: PipelineConfig,
+) -> (
+    MediaPipeline,
+    Arc<Mutex<Vec<Vec<u8>>>>,
+    Arc<Mutex<Vec<u64>>>,
+) {
+    let pipeline = MediaPipeline::new(config).unwrap();
+    let packets = Arc::new(Mutex::new(Vec::new()));
+    let decoded = Arc::clone(&packets);
+    pipeline.set_decoder_factory(move |_| {
+        Ok(Box::new(RecordingDecoder {
+            packets: Arc::clone(&decoded),
+        }))
+    });
+
+    let data = keyframed_mp4();
+    let source = MediaSource::Buffer {
+        data: data.clone(),
+        mime_type: "video/mp4".to_string(),
+    };
+    pipeline.load_source(source).await.unwrap();
+    let seeks = Arc::new(Mutex::new(Vec::new()));
+    let reader = RangeReader {
+        inner: Cursor::new(data),
+        seeks: Arc::clone(&seeks),
+    };
+    pipeline.set_reader(Box::new(reader)).unwrap();
+    (pipeline, packets, seeks)
+}
+
+/// Waits until `count` packets have been decoded
+async fn wait_for_packets(packets: &Mutex<Vec<Vec<u8>>>, count: usize) {
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while packets.lock().unwrap().len() < count {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("Packets should be decoded");
+}
+
+#[tokio::test]
+async fn test_audio_only_pipeline_decodes_no_video() {
+    // Given a pipeline with video disabled
+    // When it plays an MP4 with a video track
+    // Then no video is decoded or queued
+
+    let config = PipelineConfig {
+        enable_video: false,
+        ..Default::default()
+    };
+    let (pipeline, packets, _) = recording_pipeline(config).await;
+    assert_eq!(pipeline.media_info().unwrap().video_tracks.len(), 1);
+    pipeline.start().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert!(packets.lock().unwrap().is_empty());
+    assert!(pipeline.get_next_video_frame().await.is_none());
+    assert!(pipeline.buffered_ranges().is_empty());
+    let frame = create_test_video_frame(Duration::ZERO);
+    assert!(matches!(
+        pipeline.push_video_frame(frame).await,
+        Err(MediaError::InvalidState(_))
+    ));
+    assert!(pipeline
+        .push_audio_buffer(create_test_audio_buffer(Duration::ZERO))
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_preload_none_reads_nothing_before_start() {
+    // Given a pipeline preloading nothing
+    // When a buffer source's reader is set
+    // Then it is neither read nor demuxed until the pipeline starts
+
+    let config = PipelineConfig {
+        preload: PreloadStrategy::None,
+        ..Default::default()
+    };
+    let (pipeline, packets, seeks) = recording_pipeline(config).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert!(seeks.lock().unwrap().is_empty());
+    assert!(pipeline.media_info().is_none());
+    assert!(packets.lock().unwrap().is_empty());
+
+    pipeline.start().await.unwrap();
+    assert_eq!(*seeks.lock().unwrap(), vec![0]);
+    assert_eq!(
+        pipeline.media_info().unwrap().duration,
+        Duration::from_millis(480)
+    );
+    wait_for_packets(&packets, 12).await;
+}
+
+#[tokio::test]
+async fn test_preload_metadata_decodes_nothing_before_start() {
+    // Given a pipeline preloading metadata
+    // When a buffer source's reader is set
+    // Then the media is parsed, but decoding waits for the pipeline to start
+
+    let config = PipelineConfig {
+        preload: PreloadStrategy::Metadata,
+        ..Default::default()
+    };
+    let (pipeline, packets, seeks) = recording_pipeline(config).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(*seeks.lock().unwrap(), vec![0]);
+    assert_eq!(
+        pipeline.seekable_range(),
+        (Duration::ZERO, Duration::from_millis(480))
+    );
+    assert!(packets.lock().unwrap().is_empty());
+
+    pipeline.start().await.unwrap();
+    wait_for_packets(&packets, 12).await;
+}
+
+#[tokio::test]
+async fn test_decoding_stops_at_max_buffer_ahead() {
+    // Given a pipeline buffering at most 100 ms ahead
+    // When it has not started playing
+    // Then decoding stops once 100 ms are buffered, and resumes as the
+    // position advances
+
+    let config = PipelineConfig {
+        max_buffer_ahead: Duration::from_millis(100),
+        ..Default::default()
+    };
+    let (pipeline, packets, _) = recording_pipeline(config).await;
+    wait_for_packets(&packets, 4).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Frames at 0, 40, 80 and 120 ms
+    assert_eq!(packets.lock().unwrap().len(), 4);
+    assert_eq!(
+        pipeline.buffered_ranges(),
+        [(Duration::ZERO, Duration::from_millis(120))]
+    );
+
+    pipeline.start().await.unwrap();
+    wait_for_packets(&packets, 12).await;
+}
+
+/// Decoder failing the packets of `keyframed_mp4` listed in `failing`, by
+/// their index, with a codec error
+struct FlakyDecoder {
+    failing: Vec<u8>,
+    packets: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl VideoDecoder for FlakyDecoder {
+    fn decode(&mut self, packet: &VideoPacket) -> Result<VideoFrame, MediaError> {
+        self.packets.lock().unwrap().push(packet.data.clone());
+        if self.failing.contains(&packet.data[0]) {
+            return Err(MediaError::CodecError {
+                details: "Corrupt packet".to_string(),
+            });
+        }
+        let timestamp = Duration::from_millis(packet.pts.unwrap_or_default() as u64);
+        Ok(VideoFrame::new(
+            64,
+            64,
+            PixelFormat::YUV420,
+            Vec::new(),
+            timestamp,
+        ))
+    }
+
+    fn flush(&mut self) -> Result<Vec<VideoFrame>, MediaError> {
+        Ok(Vec::new())
+    }
+}
+
+/// Pipeline on `keyframed_mp4` with `config`, decoding through
+/// `FlakyDecoder`s failing the packets in `failing`
+///
+/// Also returns the packets decoded and the number of decoders created.
+async fn flaky_pipeline(
+    config: PipelineConfig,
+    failing: &[u8],
+) -> (MediaPipeline, Arc<Mutex<Vec<Vec<u8>>>>, Arc<AtomicUsize>) {
+    let pipeline = MediaPipeline::new(config).unwrap();
+    let packets = Arc::new(Mutex::new(Vec::new()));
+    let created = Arc::new(AtomicUsize::new(0));
+    let (decoded, count, failing) = (Arc::clone(&packets), Arc::clone(&created), failing.to_vec());
+    pipeline.set_decoder_factory(move |_| {
+        count.fetch_add(1, Ordering::Relaxed);
+        Ok(Box::new(FlakyDecoder {
+            failing: failing.clone(),
+            packets: Arc::clone(&decoded),
+        }))
+    });
+
+    let data = keyframed_mp4();
+    let source = MediaSource::Buffer {
+        data: data.clone(),
+        mime_type: "video/mp4".to_string(),
+    };
+    pipeline.load_source(source).await.unwrap();
+    pipeline.set_reader(Box::new(Cursor::new(data))).unwrap();
+    (pipeline, packets, created)
+}
+
+/// Takes every queued video frame, returning their timestamps in ms
+async fn queued_frame_times(pipeline: &MediaPipeline) -> Vec<u64> {
+    let mut times = Vec::new();
+    while let Some(frame) = pipeline.get_next_video_frame().await {
+        times.push(frame.timestamp.as_millis() as u64);
+    }
+    times
+}
+
+#[tokio::test]
+async fn test_skip_packet_policy_decodes_past_corrupt_packets() {
+    // Given a pipeline skipping packets that fail to decode
+    // When the packets at 120 ms and 280 ms are corrupt
+    // Then every other packet is decoded with the same decoder, and the
+    // errors are counted for H.264
+
+    let config = PipelineConfig {
+        recovery_policy: RecoveryPolicy::SkipPacket,
+        ..Default::default()
+    };
+    let (pipeline, packets, created) = flaky_pipeline(config, &[3, 7]).await;
+    wait_for_packets(&packets, 12).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let times = queued_frame_times(&pipeline).await;
+    assert_eq!(times, [0, 40, 80, 160, 200, 240, 320, 360, 400, 440]);
+    assert_eq!(created.load(Ordering::Relaxed), 1);
+    assert_eq!(pipeline.get_metrics().codec_errors["h264"], 2);
+    assert_eq!(pipeline.stats().decode_errors, 2);
+    assert!(pipeline.error().is_none());
+}
+
+#[tokio::test]
+async fn test_reinit_decoder_policy_creates_new_decoder() {
+    // Given a pipeline re-creating decoders that fail
+    // When two packets are corrupt
+    // Then a new decoder goes on after each of them
+
+    let config = PipelineConfig {
+        recovery_policy: RecoveryPolicy::ReinitDecoder,
+        ..Default::default()
+    };
+    let (pipeline, packets, created) = flaky_pipeline(config, &[3, 7]).await;
+    wait_for_packets(&packets, 12).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    assert_eq!(created.load(Ordering::Relaxed), 3);
+    assert_eq!(queued_frame_times(&pipeline).await.len(), 10);
+    assert!(pipeline.error().is_none());
+}
+
+#[tokio::test]
+async fn test_fail_policy_puts_pipeline_in_error_state() {
+    // Given a running pipeline failing on codec errors
+    // When a packet is corrupt
+    // Then decoding stops there and the pipeline stays in its error state
+    // until a source is loaded again
+
+    let config = PipelineConfig {
+        recovery_policy: RecoveryPolicy::Fail,
+        preload: PreloadStrategy::Metadata,
+        ..Default::default()
+    };
+    let (pipeline, packets, _) = flaky_pipeline(config, &[3]).await;
+    let mut errors = pipeline.subscribe_error();
+    pipeline.start().await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(2), errors.wait_for(Option::is_some))
+        .await
+        .expect("Decoding should fail")
+        .unwrap();
+    assert!(matches!(
+        pipeline.error(),
+        Some(MediaError::CodecError { .. })
+    ));
+    assert_eq!(packets.lock().unwrap().len(), 4);
+    assert!(!pipeline.is_running());
+    assert!(pipeline.start().await.is_err());
+    assert!(pipeline.seek(Duration::ZERO).await.is_err());
+
+    let source = MediaSource::Url {
+        url: "file:///test/video.mp4".to_string(),
+    };
+    pipeline.load_source(source).await.unwrap();
+    assert!(pipeline.error().is_none());
+    assert!(pipeline.start().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_too_many_codec_errors_fail_any_policy() {
+    // Given a pipeline skipping corrupt packets, but tolerating only two
+    // codec errors a second
+    // When three packets in a row are corrupt
+    // Then the pipeline fails at the third
+
+    let config = PipelineConfig {
+        recovery_policy: RecoveryPolicy::SkipPacket,
+        max_codec_errors: Some(2),
+        codec_error_window: Duration::from_secs(1),
+        ..Default::default()
+    };
+    let (pipeline, packets, _) = flaky_pipeline(config, &[4, 5, 6]).await;
+    let mut errors = pipeline.subscribe_error();
+
+    tokio::time::timeout(Duration::from_secs(2), errors.wait_for(Option::is_some))
+        .await
+        .expect("Decoding should fail")
+        .unwrap();
+    assert_eq!(packets.lock().unwrap().len(), 7);
+    assert_eq!(pipeline.get_metrics().codec_errors["h264"], 3);
+    assert!(pipeline.start().await.is_err());
+}
+
+/// MP4 with one AAC track of 6 40 ms samples at 48 kHz, each filled with
+/// its index
+fn audio_mp4() -> Vec<u8> {
+    let config = mp4::Mp4Config {
+        major_brand: str::parse("isom").unwrap(),
+        minor_version: 512,
+        compatible_brands: vec![str::parse("isom").unwrap()],
+        timescale: 1000,
+    };
+    let mut writer = mp4::Mp4Writer::write_start(Cursor::new(Vec::new()), &config).unwrap();
+    writer
+        .add_track(&mp4::TrackConfig {
+            track_type: mp4::TrackType::Audio,
+            timescale: 48000,
+            language: "und".to_string(),
+            media_conf: mp4::MediaConfig::AacConfig(mp4::AacConfig::default()),
+        })
+        .unwrap();
+    for i in 0..6u64 {
+        writer
+            .write_sample(
+                1,
+                &mp4::Mp4Sample {
+                    start_time: i * 1920,
+                    duration: 1920,
+                    rendering_offset: 0,
+                    is_sync: true,
+                    bytes: mp4::Bytes::from(vec![i as u8; 10]),
+                },
+            )
+            .unwrap();
+    }
+    writer.write_end().unwrap();
+    writer.into_writer().into_inner()
+}
+
+/// Audio decoder returning each packet's audio one packet late, as codecs
+/// with a delay do, filled with the packet's first byte
+///
+/// The held back audio is returned by `flush` and dropped by `reset`.
+#[derive(Default)]
+struct DelayingAudioDecoder {
+    held: Option<AudioBuffer>,
+}
+
+impl AudioDecoder for DelayingAudioDecoder {
+    fn decode(&mut self, packet: &AudioPacket) -> Result<AudioBuffer, MediaError> {
+        let timestamp = Duration::from_secs_f64(packet.pts.unwrap_or(0) as f64 / 48000.0);
+        let buffer = AudioBuffer {
+            samples: vec![packet.data[0] as f32; 1920 * 2],
+            duration: Duration::from_millis(40),
+            ..create_test_audio_buffer(timestamp)
+        };
+        Ok(self.held.replace(buffer).unwrap_or(AudioBuffer {
+            samples: Vec::new(),
+            duration: Duration::ZERO,
+            ..create_test_audio_buffer(timestamp)
+        }))
+    }
+
+    fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
+        Ok(self.held.take().into_iter().collect())
+    }
+
+    fn reset(&mut self) {
+        self.held = None;
+    }
+}
+
+/// Pipeline on `audio_mp4` with `config`, decoding through a
+/// `DelayingAudioDecoder`
+async fn delayed_audio_pipeline(config: PipelineConfig) -> MediaPipeline {
+    let pipeline = MediaPipeline::new(config).unwrap();
+    pipeline.set_audio_decoder_factory(|_| Ok(Box::new(DelayingAudioDecoder::default())));
+    let data = audio_mp4();
+    let source = MediaSource::Buffer {
+        data: data.clone(),
+        mime_type: "audio/mp4".to_string(),
+    };
+    pipeline.load_source(source).await.unwrap();
+    pipeline.set_reader(Box::new(Cursor::new(data))).unwrap();
+    pipeline
+}
+
+/// Takes the queued audio, returning the packet index each buffer was
+/// decoded from
+async fn queued_audio_packets(pipeline: &MediaPipeline) -> Vec<u8> {
+    let mut packets = Vec::new();
+    while let Some(buffer) = pipeline.get_next_audio_buffer().await {
+        if let Some(&sample) = buffer.samples.first() {
+            packets.push(sample as u8);
+        }
+    }
+    packets
+}
+
+/// Takes the queued audio until `count` buffers have been decoded, returning
+/// the packet index of each
+async fn wait_for_audio_packets(pipeline: &MediaPipeline, count: usize) -> Vec<u8> {
+    let mut packets = Vec::new();
+    tokio::time::timeout(Duration::from_secs(2), async {
+        while packets.len() < count {
+            packets.extend(queued_audio_packets(pipeline).await);
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .expect("Audio should be decoded");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    packets.extend(queued_audio_packets(pipeline).await);
+    packets
+}
+
+#[tokio::test]
+async fn test_end_of_stream_waits_for_flushed_audio() {
+    // Given a pipeline playing 240 ms of audio through a decoder holding
+    // back the last packet's audio until it is flushed
+    // When the clock passes the duration before the audio is taken
+    // Then the end is only reported once all of it, flushed audio
+    // included, has been taken
+
+    let pipeline = delayed_audio_pipeline(PipelineConfig::default()).await;
+    let mut ended = pipeline.subscribe_ended();
+    pipeline.start().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(!pipeline.is_ended());
+
+    assert_eq!(
+        queued_audio_packets(&pipeline).await,
+        vec![0, 1, 2, 3, 4, 5]
+    );
+    tokio::time::timeout(Duration::from_secs(2), ended.wait_for(|&ended| ended))
+        .await
+        .expect("Pipeline should end")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_seek_resets_audio_decoder() {
+    // Given a pipeline buffering 80 ms of audio ahead through a decoder
+    // holding back the audio of the last packet it decoded
+    // When seeking to 160 ms
+    // Then the decoder drops the audio it held from before the seek, and
+    // only audio from 160 ms on is queued
+
+    let config = PipelineConfig {
+        max_buffer_ahead: Duration::from_millis(80),
+        ..Default::default()
+    };
+    let pipeline = delayed_audio_pipeline(config).await;
+    // Packet 2 is held back
+    assert_eq!(wait_for_audio_packets(&pipeline, 2).await, vec![0, 1]);
+
+    pipeline.seek(Duration::from_millis(160)).await.unwrap();
+    assert_eq!(wait_for_audio_packets(&pipeline, 2).await, vec![4, 5]);
+}
@@ -3,7 +3,7 @@
 //! Tests the pipeline orchestration logic.
 
 use cortenbrowser_media_pipeline::{MediaPipeline, PipelineConfig};
-use cortenbrowser_shared_types::{MediaError, MediaSource};
+use cortenbrowser_shared_types::{LoopMode, MediaError, MediaSource};
 use std::time::Duration;
 
 #[tokio::test]
@@ -190,6 +190,8 @@ async fn test_pipeline_config_custom_values() {
         buffer_size: 2048,
         thread_count: 8,
         sync_threshold: Duration::from_millis(50),
+        pitch_correct: true,
+        loop_mode: LoopMode::None,
     };
 
     let result = MediaPipeline::new(config);
@@ -124,6 +124,7 @@ fn create_test_video_frame(timestamp: Duration) -> VideoFrame {
         height: 1080,
         format: PixelFormat::YUV420,
         data: vec![0u8; 1920 * 1080],
+        strides: None,
         timestamp,
         duration: Some(Duration::from_millis(33)), // ~30fps
         metadata: FrameMetadata::default(),
@@ -140,5 +141,6 @@ fn create_test_audio_buffer(timestamp: Duration) -> AudioBuffer {
         samples: vec![0.0f32; 4800], // 100ms of audio
         timestamp,
         duration: Duration::from_millis(100),
+        channel_map: None,
     }
 }
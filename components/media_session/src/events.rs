@@ -0,0 +1,71 @@
+//! Session event notifications
+
+use crate::state::{MediaMetadata, SessionState};
+use cortenbrowser_shared_types::MediaError;
+use std::time::Duration;
+
+/// How often `PositionUpdate` is emitted while a session plays, unless
+/// configured otherwise
+pub const DEFAULT_POSITION_UPDATE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Events a [`MediaSession`](crate::MediaSession) broadcasts to its
+/// subscribers
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_session::{MediaSession, SessionEvent, SessionState};
+/// use cortenbrowser_shared_types::{MediaSource, SessionId};
+/// use std::time::Duration;
+///
+/// let session = MediaSession::new(SessionId::new());
+/// let mut events = session.subscribe_events();
+///
+/// let ready = SessionState::Ready {
+///     duration: Duration::from_secs(10),
+///     metadata: Default::default(),
+/// };
+/// assert!(session.transition_to(ready).is_err());
+/// assert!(matches!(events.try_recv(), Ok(SessionEvent::Error(_))));
+///
+/// session.transition_to(SessionState::Loading {
+///     source: MediaSource::Url { url: "test.mp4".to_string() },
+///     progress: 0.0,
+/// }).unwrap();
+/// assert!(matches!(
+///     events.try_recv(),
+///     Ok(SessionEvent::StateChanged { old: SessionState::Idle, new: SessionState::Loading { .. } })
+/// ));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum SessionEvent {
+    /// The session moved to another state
+    StateChanged {
+        /// State before the change
+        old: SessionState,
+        /// State after the change
+        new: SessionState,
+    },
+    /// Playback position of a playing session, at the session's position
+    /// update interval
+    PositionUpdate {
+        /// Current playback position
+        position: Duration,
+    },
+    /// Metadata of the loaded media became known
+    MetadataReady(MediaMetadata),
+    /// Metadata of the loaded media changed mid-stream
+    MetadataChanged(MediaMetadata),
+    /// Playback stalled waiting for media
+    BufferingStarted,
+    /// Enough media is buffered for playback to continue
+    BufferingEnded,
+    /// An operation on the session failed, or the session was asked to
+    /// make an invalid state transition
+    Error(MediaError),
+    /// Playback reached the end of the A-B loop and restarted at its start
+    AbLoopIteration {
+        /// Number of times playback has looped, starting at 1
+        count: u64,
+    },
+}
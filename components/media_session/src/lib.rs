@@ -4,10 +4,14 @@
 
 #![warn(missing_docs)]
 
+mod events;
 mod manager;
 mod session;
+mod snapshot;
 mod state;
 
+pub use events::{SessionEvent, DEFAULT_POSITION_UPDATE_INTERVAL};
 pub use manager::SessionManager;
 pub use session::MediaSession;
+pub use snapshot::{SessionSnapshot, SourceDescriptor};
 pub use state::{MediaMetadata, SessionState};
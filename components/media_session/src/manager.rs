@@ -1,11 +1,14 @@
 //! Session manager implementation
 
+use crate::events::SessionEvent;
 use crate::session::MediaSession;
-use crate::state::SessionState;
+use crate::snapshot::SessionSnapshot;
+use crate::state::{MediaMetadata, SessionState};
 use cortenbrowser_shared_types::{MediaError, MediaSessionConfig, SessionId};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::broadcast;
 
 /// Manages media sessions
 #[derive(Debug, Default)]
@@ -22,13 +25,31 @@ impl SessionManager {
     }
 
     /// Creates a new media session
-    pub fn create(&self, _config: MediaSessionConfig) -> Result<SessionId, MediaError> {
+    pub fn create(&self, config: MediaSessionConfig) -> Result<SessionId, MediaError> {
         let id = SessionId::new();
-        let session = Arc::new(MediaSession::new(id));
+        let session = Arc::new(MediaSession::with_config(id, &config));
         self.sessions.write().insert(id, session);
         Ok(id)
     }
 
+    /// Creates a new media session from a snapshot of another
+    ///
+    /// The session takes the snapshot's source, volume, rate, muting and
+    /// metadata, and seeks to the snapshot's position once its source is
+    /// loaded. It gets a new ID, as the original session may still exist.
+    pub fn restore(&self, snapshot: &SessionSnapshot) -> Result<SessionId, MediaError> {
+        let id = SessionId::new();
+        let session = MediaSession::new(id);
+        *session.source.write() = snapshot.source.clone();
+        session.set_volume(snapshot.volume);
+        session.set_rate(snapshot.rate);
+        session.set_muted(snapshot.muted);
+        *session.metadata.write() = snapshot.metadata.clone();
+        session.set_resume_position(Some(snapshot.position).filter(|p| !p.is_zero()));
+        self.sessions.write().insert(id, Arc::new(session));
+        Ok(id)
+    }
+
     /// Gets an existing session
     pub fn get(&self, id: SessionId) -> Option<Arc<MediaSession>> {
         self.sessions.read().get(&id).cloned()
@@ -41,26 +62,101 @@ impl SessionManager {
     }
 
     /// Transitions session state with validation
+    ///
+    /// A valid transition is broadcast to the session's subscribers as
+    /// `StateChanged`, an invalid one as `Error`. See
+    /// [`MediaSession::transition_to`].
     pub fn transition_state(
         &self,
         id: SessionId,
         new_state: SessionState,
     ) -> Result<(), MediaError> {
-        let sessions = self.sessions.read();
-        let session = sessions.get(&id).ok_or_else(|| MediaError::CodecError {
+        let session = self.get(id).ok_or_else(|| MediaError::CodecError {
             details: "Session not found".to_string(),
         })?;
+        session.transition_to(new_state)
+    }
 
-        let current_state = session.state.read().clone();
+    /// Subscribes to a session's state changes and other events
+    ///
+    /// Every state the session moves to is broadcast as `StateChanged`, in
+    /// order, and every rejected transition as `Error`. See
+    /// [`MediaSession::subscribe_events`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_session::{SessionEvent, SessionManager, SessionState};
+    /// use cortenbrowser_shared_types::{MediaSessionConfig, MediaSource};
+    ///
+    /// let manager = SessionManager::new();
+    /// let id = manager.create(MediaSessionConfig::new()).unwrap();
+    /// let mut events = manager.subscribe(id).unwrap();
+    ///
+    /// let source = MediaSource::Url { url: "test.mp4".to_string() };
+    /// manager
+    ///     .transition_state(id, SessionState::Loading { source, progress: 0.0 })
+    ///     .unwrap();
+    /// assert!(matches!(
+    ///     events.try_recv(),
+    ///     Ok(SessionEvent::StateChanged { new: SessionState::Loading { .. }, .. })
+    /// ));
+    /// ```
+    pub fn subscribe(
+        &self,
+        id: SessionId,
+    ) -> Result<broadcast::Receiver<SessionEvent>, MediaError> {
+        let session = self.get(id).ok_or_else(|| MediaError::CodecError {
+            details: "Session not found".to_string(),
+        })?;
+        Ok(session.subscribe_events())
+    }
 
-        if !current_state.can_transition_to(&new_state) {
-            return Err(MediaError::InvalidStateTransition {
-                from: current_state.into(),
-                to: new_state.into(),
-            });
+    /// Replaces the metadata of a session's media, without a state
+    /// transition
+    ///
+    /// The new metadata is broadcast to the session's subscribers as
+    /// `MetadataChanged`. See [`MediaSession::update_metadata`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `SessionNotFound` if there is no session `id`, and
+    /// `InvalidParameter` if the session has no media loaded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_session::{MediaMetadata, SessionManager, SessionState};
+    /// use cortenbrowser_shared_types::{MediaSessionConfig, MediaSource};
+    ///
+    /// let manager = SessionManager::new();
+    /// let id = manager.create(MediaSessionConfig::new()).unwrap();
+    /// let metadata = MediaMetadata {
+    ///     title: Some("Now playing".to_string()),
+    ///     ..MediaMetadata::default()
+    /// };
+    /// assert!(manager.update_metadata(id, metadata.clone()).is_err());
+    ///
+    /// let source = MediaSource::Url { url: "radio.mp3".to_string() };
+    /// manager
+    ///     .transition_state(id, SessionState::Loading { source, progress: 0.0 })
+    ///     .unwrap();
+    /// manager.update_metadata(id, metadata.clone()).unwrap();
+    /// assert_eq!(manager.get(id).unwrap().metadata(), metadata);
+    /// ```
+    pub fn update_metadata(
+        &self,
+        id: SessionId,
+        metadata: MediaMetadata,
+    ) -> Result<(), MediaError> {
+        let session = self.get(id).ok_or(MediaError::SessionNotFound(id))?;
+        if session.get_state() == SessionState::Idle {
+            return Err(MediaError::InvalidParameter(
+                "Session has no media loaded".to_string(),
+            ));
         }
 
-        *session.state.write() = new_state;
+        session.update_metadata(metadata);
         Ok(())
     }
 
@@ -71,7 +167,6 @@ impl SessionManager {
             details: "Session not found".to_string(),
         })?;
 
-        let state = session.state.read().clone();
-        Ok(state)
+        Ok(session.get_state())
     }
 }
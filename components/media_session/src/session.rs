@@ -1,22 +1,47 @@
 //! Media session implementation
 
-use crate::state::SessionState;
-use cortenbrowser_shared_types::SessionId;
+use crate::events::{SessionEvent, DEFAULT_POSITION_UPDATE_INTERVAL};
+use crate::snapshot::{SessionSnapshot, SourceDescriptor};
+use crate::state::{MediaMetadata, SessionState};
+use cortenbrowser_shared_types::{MediaError, MediaSessionConfig, MediaSource, SessionId};
 use parking_lot::RwLock;
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+/// Events buffered for each subscriber before the oldest are dropped
+const EVENT_CAPACITY: usize = 64;
 
 /// Represents a media playback session
 #[derive(Debug, Clone)]
 pub struct MediaSession {
     /// Unique session identifier
     pub id: SessionId,
-    /// Current session state
-    pub state: Arc<RwLock<SessionState>>,
+    /// Current session state, changed only by
+    /// [`transition_to`](Self::transition_to)
+    state: Arc<RwLock<SessionState>>,
+    /// Metadata of the loaded media, once known
+    pub metadata: Arc<RwLock<Option<MediaMetadata>>>,
     /// Session creation time
     pub created_at: SystemTime,
     /// Last update time
     pub updated_at: Arc<RwLock<SystemTime>>,
+    /// How often `PositionUpdate` is emitted while playing
+    pub position_update_interval: Duration,
+    /// Start and end of the section playback repeats, if any
+    pub ab_loop: Arc<RwLock<Option<(Duration, Duration)>>>,
+    /// Loaded source, if it can be loaded again
+    pub source: Arc<RwLock<Option<SourceDescriptor>>>,
+    /// Volume (0.0 to 1.0)
+    pub volume: Arc<RwLock<f32>>,
+    /// Playback rate (1.0 = normal speed)
+    pub rate: Arc<RwLock<f32>>,
+    /// Whether audio is muted
+    pub muted: Arc<RwLock<bool>>,
+    /// Position to seek to once the source is loaded, when restored
+    pub resume_position: Arc<RwLock<Option<Duration>>>,
+    /// Sender of the session's events
+    events: broadcast::Sender<SessionEvent>,
 }
 
 impl MediaSession {
@@ -26,24 +51,352 @@ impl MediaSession {
         Self {
             id,
             state: Arc::new(RwLock::new(SessionState::Idle)),
+            metadata: Arc::new(RwLock::new(None)),
             created_at: now,
             updated_at: Arc::new(RwLock::new(now)),
+            position_update_interval: DEFAULT_POSITION_UPDATE_INTERVAL,
+            ab_loop: Arc::new(RwLock::new(None)),
+            source: Arc::new(RwLock::new(None)),
+            volume: Arc::new(RwLock::new(1.0)),
+            rate: Arc::new(RwLock::new(1.0)),
+            muted: Arc::new(RwLock::new(false)),
+            resume_position: Arc::new(RwLock::new(None)),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+
+    /// Creates a new media session with the settings of `config`
+    pub fn with_config(id: SessionId, config: &MediaSessionConfig) -> Self {
+        Self {
+            position_update_interval: config
+                .position_update_interval
+                .unwrap_or(DEFAULT_POSITION_UPDATE_INTERVAL),
+            ..Self::new(id)
         }
     }
 
+    /// Subscribes to the session's events
+    ///
+    /// The receiver sees the events broadcast after subscribing. A receiver
+    /// falling more than 64 events behind misses the oldest ones.
+    pub fn subscribe_events(&self) -> broadcast::Receiver<SessionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Broadcasts an event to the session's subscribers
+    ///
+    /// State changes and metadata are broadcast by
+    /// [`transition_to`](Self::transition_to),
+    /// [`set_metadata`](Self::set_metadata) and
+    /// [`update_metadata`](Self::update_metadata);
+    /// this is for events observed outside the session, such as buffering.
+    pub fn publish_event(&self, event: SessionEvent) {
+        // Nobody may be subscribed
+        let _ = self.events.send(event);
+    }
+
     /// Gets the current state
     pub fn get_state(&self) -> SessionState {
         self.state.read().clone()
     }
 
-    /// Updates the session state (interior mutability - can be called on shared ref)
-    pub fn set_state(&self, new_state: SessionState) {
-        *self.state.write() = new_state;
+    /// Moves the session to a new state, if the current state can
+    /// transition to it (interior mutability - can be called on shared ref)
+    ///
+    /// Broadcasts the change as `StateChanged`. An invalid transition leaves
+    /// the state unchanged and is broadcast as an `Error` event instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidStateTransition` if the current state cannot
+    /// transition to `new_state`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_session::{MediaSession, SessionEvent, SessionState};
+    /// use cortenbrowser_shared_types::SessionId;
+    /// use std::time::Duration;
+    ///
+    /// let session = MediaSession::new(SessionId::new());
+    /// let mut events = session.subscribe_events();
+    ///
+    /// let playing = SessionState::Playing {
+    ///     position: Duration::ZERO,
+    ///     rate: 1.0,
+    ///     ab_loop: None,
+    /// };
+    /// assert!(session.transition_to(playing).is_err());
+    /// assert_eq!(session.get_state(), SessionState::Idle);
+    /// assert!(matches!(events.try_recv(), Ok(SessionEvent::Error(_))));
+    /// ```
+    pub fn transition_to(&self, new_state: SessionState) -> Result<(), MediaError> {
+        let mut state = self.state.write();
+        if !state.can_transition_to(&new_state) {
+            let error = MediaError::InvalidStateTransition {
+                from: state.clone().into(),
+                to: new_state.into(),
+            };
+            drop(state);
+            self.publish_event(SessionEvent::Error(error.clone()));
+            return Err(error);
+        }
+
+        let old = std::mem::replace(&mut *state, new_state.clone());
+        *self.updated_at.write() = SystemTime::now();
+        // Broadcast before releasing the state, so that subscribers see
+        // concurrent transitions in the order they happened
+        self.publish_event(SessionEvent::StateChanged {
+            old,
+            new: new_state,
+        });
+        Ok(())
+    }
+
+    /// Gets the metadata of the loaded media
+    pub fn get_metadata(&self) -> Option<MediaMetadata> {
+        self.metadata.read().clone()
+    }
+
+    /// Gets the metadata of the loaded media, or empty metadata while it is
+    /// not known
+    pub fn metadata(&self) -> MediaMetadata {
+        self.get_metadata().unwrap_or_default()
+    }
+
+    /// Stores the metadata of the loaded media
+    ///
+    /// Broadcasts it as `MetadataReady`.
+    pub fn set_metadata(&self, metadata: MediaMetadata) {
+        *self.metadata.write() = Some(metadata.clone());
+        *self.updated_at.write() = SystemTime::now();
+        self.publish_event(SessionEvent::MetadataReady(metadata));
+    }
+
+    /// Replaces the metadata of media that is playing, without a state
+    /// transition
+    ///
+    /// For metadata that changes mid-stream, such as the title of the song
+    /// an internet radio stream is playing. Broadcasts the new metadata as
+    /// `MetadataChanged`, unless it is unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_session::{MediaMetadata, MediaSession, SessionEvent};
+    /// use cortenbrowser_shared_types::SessionId;
+    ///
+    /// let session = MediaSession::new(SessionId::new());
+    /// let mut events = session.subscribe_events();
+    ///
+    /// let metadata = MediaMetadata {
+    ///     title: Some("Now playing".to_string()),
+    ///     ..MediaMetadata::default()
+    /// };
+    /// session.update_metadata(metadata.clone());
+    /// session.update_metadata(metadata.clone());
+    ///
+    /// assert_eq!(session.metadata(), metadata);
+    /// assert_eq!(events.try_recv(), Ok(SessionEvent::MetadataChanged(metadata)));
+    /// assert!(events.try_recv().is_err());
+    /// ```
+    pub fn update_metadata(&self, metadata: MediaMetadata) {
+        let mut current = self.metadata.write();
+        if current.as_ref() == Some(&metadata) {
+            return;
+        }
+
+        *current = Some(metadata.clone());
+        *self.updated_at.write() = SystemTime::now();
+        // Broadcast before releasing the metadata, so that subscribers see
+        // concurrent updates in the order they happened
+        self.publish_event(SessionEvent::MetadataChanged(metadata));
+    }
+
+    /// Gets the duration of the loaded media, if known
+    pub fn duration(&self) -> Option<Duration> {
+        self.metadata.read().as_ref().map(|m| m.duration)
+    }
+
+    /// Sets the section playback repeats, from `start` to `end`
+    ///
+    /// The loop points are only stored; the engine seeks back to `start`
+    /// each time playback reaches `end`. Without metadata the duration is
+    /// unknown and `end` is not checked against it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` unless `start < end <= duration`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_session::{MediaMetadata, MediaSession};
+    /// use cortenbrowser_shared_types::SessionId;
+    /// use std::time::Duration;
+    ///
+    /// let session = MediaSession::new(SessionId::new());
+    /// session.set_metadata(MediaMetadata {
+    ///     duration: Duration::from_secs(60),
+    ///     ..MediaMetadata::default()
+    /// });
+    ///
+    /// let start = Duration::from_secs(10);
+    /// let end = Duration::from_secs(20);
+    /// session.set_ab_loop(start, end).unwrap();
+    /// assert_eq!(session.ab_loop(), Some((start, end)));
+    ///
+    /// assert!(session.set_ab_loop(start, Duration::from_secs(90)).is_err());
+    /// ```
+    pub fn set_ab_loop(&self, start: Duration, end: Duration) -> Result<(), MediaError> {
+        if start >= end {
+            return Err(MediaError::InvalidParameter(format!(
+                "Loop start {:?} must be before loop end {:?}",
+                start, end
+            )));
+        }
+        if let Some(duration) = self.duration().filter(|duration| end > *duration) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Loop end {:?} is after the end of the media at {:?}",
+                end, duration
+            )));
+        }
+
+        *self.ab_loop.write() = Some((start, end));
+        *self.updated_at.write() = SystemTime::now();
+        Ok(())
+    }
+
+    /// Removes the loop points set with [`set_ab_loop`](Self::set_ab_loop)
+    pub fn clear_ab_loop(&self) {
+        *self.ab_loop.write() = None;
         *self.updated_at.write() = SystemTime::now();
     }
 
+    /// Gets the start and end of the section playback repeats, if any
+    pub fn ab_loop(&self) -> Option<(Duration, Duration)> {
+        *self.ab_loop.read()
+    }
+
     /// Gets the last update time
     pub fn get_updated_at(&self) -> SystemTime {
         *self.updated_at.read()
     }
+
+    /// Gets the playback position the session last reported
+    ///
+    /// The position of `Playing` and `Paused`, or the target of `Seeking`;
+    /// zero in other states.
+    pub fn position(&self) -> Duration {
+        match &*self.state.read() {
+            SessionState::Playing { position, .. } | SessionState::Paused { position } => *position,
+            SessionState::Seeking { target } => *target,
+            _ => Duration::ZERO,
+        }
+    }
+
+    /// Records the loaded source
+    ///
+    /// Sources that cannot be loaded again are recorded as none.
+    pub fn set_source(&self, source: &MediaSource) {
+        *self.source.write() = SourceDescriptor::from_source(source);
+        *self.updated_at.write() = SystemTime::now();
+    }
+
+    /// Gets the loaded source, if it can be loaded again
+    pub fn source(&self) -> Option<SourceDescriptor> {
+        self.source.read().clone()
+    }
+
+    /// Records the volume (0.0 to 1.0)
+    pub fn set_volume(&self, volume: f32) {
+        *self.volume.write() = volume;
+        *self.updated_at.write() = SystemTime::now();
+    }
+
+    /// Gets the volume
+    pub fn volume(&self) -> f32 {
+        *self.volume.read()
+    }
+
+    /// Records the playback rate
+    pub fn set_rate(&self, rate: f32) {
+        *self.rate.write() = rate;
+        *self.updated_at.write() = SystemTime::now();
+    }
+
+    /// Gets the playback rate
+    pub fn rate(&self) -> f32 {
+        *self.rate.read()
+    }
+
+    /// Records whether audio is muted
+    pub fn set_muted(&self, muted: bool) {
+        *self.muted.write() = muted;
+        *self.updated_at.write() = SystemTime::now();
+    }
+
+    /// Gets whether audio is muted
+    pub fn is_muted(&self) -> bool {
+        *self.muted.read()
+    }
+
+    /// Sets the position to seek to once the source is loaded
+    pub fn set_resume_position(&self, position: Option<Duration>) {
+        *self.resume_position.write() = position;
+    }
+
+    /// Takes the position to seek to once the source is loaded, if any
+    ///
+    /// Only the first load after [restoring](crate::SessionManager::restore)
+    /// seeks; later calls return `None`.
+    pub fn take_resume_position(&self) -> Option<Duration> {
+        self.resume_position.write().take()
+    }
+
+    /// Saves the session's source, position, volume, rate, muting and
+    /// metadata
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_media_session::{MediaSession, SessionState};
+    /// use cortenbrowser_shared_types::{MediaSource, SessionId};
+    /// use std::time::Duration;
+    ///
+    /// let session = MediaSession::new(SessionId::new());
+    /// let source = MediaSource::Url {
+    ///     url: "test.mp4".to_string(),
+    /// };
+    /// let states = [
+    ///     SessionState::Loading { source, progress: 1.0 },
+    ///     SessionState::Ready {
+    ///         duration: Duration::from_secs(10),
+    ///         metadata: Default::default(),
+    ///     },
+    ///     SessionState::Paused {
+    ///         position: Duration::from_secs(3),
+    ///     },
+    /// ];
+    /// for state in states {
+    ///     session.transition_to(state).unwrap();
+    /// }
+    /// session.set_muted(true);
+    ///
+    /// let snapshot = session.snapshot();
+    /// assert_eq!(snapshot.position, Duration::from_secs(3));
+    /// assert!(snapshot.muted);
+    /// assert_eq!(snapshot.source, None);
+    /// ```
+    pub fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            session_id: self.id,
+            source: self.source(),
+            position: self.position(),
+            volume: self.volume(),
+            rate: self.rate(),
+            muted: self.is_muted(),
+            metadata: self.get_metadata(),
+        }
+    }
 }
@@ -0,0 +1,116 @@
+//! Session snapshots
+//!
+//! A snapshot records what is needed to bring a session back after its
+//! resources were released, such as when a browser discards a background
+//! tab: the source, where playback was, and its volume and rate.
+
+use crate::state::MediaMetadata;
+use cortenbrowser_shared_types::{MediaSource, SessionId};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// A media source that can be loaded again
+///
+/// Sources fed by the page or a device, such as streams, Media Source
+/// Extensions, WebRTC tracks and captures, cannot be loaded again and have
+/// no descriptor.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_session::SourceDescriptor;
+/// use cortenbrowser_shared_types::MediaSource;
+///
+/// let source = MediaSource::Url {
+///     url: "video.mp4".to_string(),
+/// };
+/// let descriptor = SourceDescriptor::from_source(&source).unwrap();
+/// assert!(matches!(descriptor.to_source(), MediaSource::Url { url } if url == "video.mp4"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SourceDescriptor {
+    /// Media loaded from a URL
+    Url {
+        /// The media URL
+        url: String,
+    },
+
+    /// Media loaded from bytes in memory
+    Buffer {
+        /// Media data
+        data: Vec<u8>,
+        /// MIME type
+        mime_type: String,
+    },
+}
+
+impl SourceDescriptor {
+    /// Describes `source`, if it can be loaded again
+    pub fn from_source(source: &MediaSource) -> Option<Self> {
+        match source {
+            MediaSource::Url { url } => Some(Self::Url { url: url.clone() }),
+            MediaSource::Buffer { data, mime_type } => Some(Self::Buffer {
+                data: data.clone(),
+                mime_type: mime_type.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Creates the source to load again
+    pub fn to_source(&self) -> MediaSource {
+        match self {
+            Self::Url { url } => MediaSource::Url { url: url.clone() },
+            Self::Buffer { data, mime_type } => MediaSource::Buffer {
+                data: data.clone(),
+                mime_type: mime_type.clone(),
+            },
+        }
+    }
+}
+
+/// Saved state of a media session
+///
+/// Taken by [`MediaSession::snapshot`](crate::MediaSession::snapshot) and
+/// brought back by [`SessionManager::restore`](crate::SessionManager::restore).
+/// Snapshots serialize with serde, so they can outlive the process.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_media_session::SessionManager;
+/// use cortenbrowser_shared_types::{MediaSessionConfig, MediaSource};
+/// use std::time::Duration;
+///
+/// let manager = SessionManager::new();
+/// let id = manager.create(MediaSessionConfig::new()).unwrap();
+/// let session = manager.get(id).unwrap();
+/// session.set_source(&MediaSource::Url {
+///     url: "video.mp4".to_string(),
+/// });
+/// session.set_volume(0.5);
+///
+/// let snapshot = session.snapshot();
+/// manager.destroy(id).unwrap();
+///
+/// let restored = manager.get(manager.restore(&snapshot).unwrap()).unwrap();
+/// assert_eq!(restored.volume(), 0.5);
+/// assert_eq!(restored.source(), snapshot.source);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    /// Session the snapshot was taken of
+    pub session_id: SessionId,
+    /// Source to load again, if it can be
+    pub source: Option<SourceDescriptor>,
+    /// Playback position
+    pub position: Duration,
+    /// Volume (0.0 to 1.0)
+    pub volume: f32,
+    /// Playback rate (1.0 = normal speed)
+    pub rate: f32,
+    /// Whether audio was muted
+    pub muted: bool,
+    /// Metadata of the loaded media, if known
+    pub metadata: Option<MediaMetadata>,
+}
@@ -1,10 +1,11 @@
 //! Session state machine implementation
 
 use cortenbrowser_shared_types::{MediaError, MediaSource};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
 /// Media metadata associated with a session
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct MediaMetadata {
     /// Media title
     pub title: Option<String>,
@@ -29,12 +30,17 @@ pub struct MediaMetadata {
 /// Valid transitions:
 /// - Idle → Loading
 /// - Loading → Ready | Error
-/// - Ready → Playing | Paused
-/// - Playing → Paused | Seeking | Ended | Error
+/// - Ready → Playing | Paused | Seeking
+/// - Playing → Playing | Paused | Seeking | Ended | Looping | Error
 /// - Paused → Playing | Seeking | Error
 /// - Seeking → Playing | Paused | Error
+/// - Ended → Seeking | Looping | Error
+/// - Looping → Playing | Error
+/// - Any → Loading, loading another source
 /// - Any → Error
 ///
+/// `Playing → Playing` updates the position or rate of a playing session.
+///
 /// # Examples
 ///
 /// ```
@@ -77,6 +83,8 @@ pub enum SessionState {
         position: Duration,
         /// Playback rate (1.0 = normal speed)
         rate: f32,
+        /// Start and end of the section playback repeats, if any
+        ab_loop: Option<(Duration, Duration)>,
     },
 
     /// Session is paused
@@ -94,6 +102,12 @@ pub enum SessionState {
     /// Playback has ended
     Ended,
 
+    /// Playback reached the end and is restarting from the loop start
+    Looping {
+        /// Number of times playback has looped, starting at 1
+        iteration: u64,
+    },
+
     /// Session encountered an error
     Error {
         /// The error that occurred
@@ -122,6 +136,7 @@ impl SessionState {
     /// let playing = SessionState::Playing {
     ///     position: Duration::ZERO,
     ///     rate: 1.0,
+    ///     ab_loop: None,
     /// };
     ///
     /// // Cannot transition directly from Idle to Playing
@@ -134,20 +149,24 @@ impl SessionState {
             // Any state can transition to Error
             (_, Error { .. }) => true,
 
-            // Idle can only transition to Loading
-            (Idle, Loading { .. }) => true,
+            // Any state can load another source, Idle included
+            (_, Loading { .. }) => true,
 
             // Loading can transition to Ready or Error
             (Loading { .. }, Ready { .. }) => true,
 
-            // Ready can transition to Playing or Paused
+            // Ready can transition to Playing, Paused or Seeking
             (Ready { .. }, Playing { .. }) => true,
             (Ready { .. }, Paused { .. }) => true,
+            (Ready { .. }, Seeking { .. }) => true,
 
-            // Playing can transition to Paused, Seeking, or Ended
+            // Playing can update its position or rate, or transition to
+            // Paused, Seeking, Ended, or Looping at the end of a loop
+            (Playing { .. }, Playing { .. }) => true,
             (Playing { .. }, Paused { .. }) => true,
             (Playing { .. }, Seeking { .. }) => true,
             (Playing { .. }, Ended) => true,
+            (Playing { .. }, Looping { .. }) => true,
 
             // Paused can transition to Playing or Seeking
             (Paused { .. }, Playing { .. }) => true,
@@ -157,6 +176,12 @@ impl SessionState {
             (Seeking { .. }, Playing { .. }) => true,
             (Seeking { .. }, Paused { .. }) => true,
 
+            // Ended restarts by seeking, or through Looping when looping
+            // is enabled
+            (Ended, Seeking { .. }) => true,
+            (Ended, Looping { .. }) => true,
+            (Looping { .. }, Playing { .. }) => true,
+
             // All other transitions are invalid
             _ => false,
         }
@@ -172,6 +197,7 @@ impl SessionState {
             SessionState::Paused { .. } => "Paused",
             SessionState::Seeking { .. } => "Seeking",
             SessionState::Ended => "Ended",
+            SessionState::Looping { .. } => "Looping",
             SessionState::Error { .. } => "Error",
         }
     }
@@ -197,15 +223,18 @@ impl PartialEq for SessionState {
                 Playing {
                     position: pos1,
                     rate: r1,
+                    ab_loop: l1,
                 },
                 Playing {
                     position: pos2,
                     rate: r2,
+                    ab_loop: l2,
                 },
-            ) => pos1 == pos2 && r1 == r2,
+            ) => pos1 == pos2 && r1 == r2 && l1 == l2,
             (Paused { position: p1 }, Paused { position: p2 }) => p1 == p2,
             (Seeking { target: t1 }, Seeking { target: t2 }) => t1 == t2,
             (Ended, Ended) => true,
+            (Looping { iteration: i1 }, Looping { iteration: i2 }) => i1 == i2,
             (Error { error: e1 }, Error { error: e2 }) => e1 == e2,
             _ => false,
         }
@@ -222,6 +251,7 @@ impl From<SessionState> for cortenbrowser_shared_types::SessionState {
             SessionState::Paused { .. } => cortenbrowser_shared_types::SessionState::Paused,
             SessionState::Seeking { .. } => cortenbrowser_shared_types::SessionState::Seeking,
             SessionState::Ended => cortenbrowser_shared_types::SessionState::Ended,
+            SessionState::Looping { .. } => cortenbrowser_shared_types::SessionState::Looping,
             SessionState::Error { .. } => cortenbrowser_shared_types::SessionState::Error,
         }
     }
@@ -243,7 +273,8 @@ mod tests {
         assert_eq!(
             SessionState::Playing {
                 position: Duration::ZERO,
-                rate: 1.0
+                rate: 1.0,
+                ab_loop: None,
             }
             .state_name(),
             "Playing"
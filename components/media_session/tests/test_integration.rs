@@ -71,6 +71,7 @@ fn test_concurrent_state_transitions() {
                 SessionState::Playing {
                     position: Duration::from_secs(i as u64),
                     rate: 1.0,
+                    ab_loop: None,
                 }
             } else {
                 SessionState::Paused {
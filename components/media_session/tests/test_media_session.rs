@@ -1,9 +1,32 @@
 //! Unit tests for MediaSession
 
-use cortenbrowser_media_session::{MediaSession, SessionState};
-use cortenbrowser_shared_types::SessionId;
+use cortenbrowser_media_session::{
+    MediaMetadata, MediaSession, SessionEvent, SessionState, SourceDescriptor,
+    DEFAULT_POSITION_UPDATE_INTERVAL,
+};
+use cortenbrowser_shared_types::{MediaError, MediaSessionConfig, MediaSource, SessionId};
 use std::time::Duration;
 
+fn loading() -> SessionState {
+    SessionState::Loading {
+        source: MediaSource::Url {
+            url: "test.mp4".to_string(),
+        },
+        progress: 0.0,
+    }
+}
+
+/// Move a new session through `Loading` to `Ready`
+fn make_ready(session: &MediaSession) {
+    session.transition_to(loading()).unwrap();
+    session
+        .transition_to(SessionState::Ready {
+            duration: Duration::from_secs(10),
+            metadata: MediaMetadata::default(),
+        })
+        .unwrap();
+}
+
 #[test]
 fn test_media_session_new() {
     let id = SessionId::new();
@@ -14,7 +37,7 @@ fn test_media_session_new() {
 }
 
 #[test]
-fn test_media_session_set_state() {
+fn test_media_session_transition_to() {
     let id = SessionId::new();
     let session = MediaSession::new(id);
 
@@ -25,10 +48,34 @@ fn test_media_session_set_state() {
         progress: 0.5,
     };
 
-    session.set_state(new_state.clone());
+    session.transition_to(new_state.clone()).unwrap();
     assert_eq!(session.get_state(), new_state);
 }
 
+#[test]
+fn test_media_session_rejects_invalid_transition() {
+    let session = MediaSession::new(SessionId::new());
+    let mut events = session.subscribe_events();
+    let initial_time = session.get_updated_at();
+
+    let playing = SessionState::Playing {
+        position: Duration::ZERO,
+        rate: 1.0,
+        ab_loop: None,
+    };
+    let error = MediaError::InvalidStateTransition {
+        from: cortenbrowser_shared_types::SessionState::Idle,
+        to: cortenbrowser_shared_types::SessionState::Playing,
+    };
+    assert_eq!(session.transition_to(playing), Err(error.clone()));
+
+    // Nothing is written; subscribers see the error instead
+    assert_eq!(session.get_state(), SessionState::Idle);
+    assert_eq!(session.get_updated_at(), initial_time);
+    assert_eq!(events.try_recv().unwrap(), SessionEvent::Error(error));
+    assert!(events.try_recv().is_err());
+}
+
 #[test]
 fn test_media_session_get_state() {
     let id = SessionId::new();
@@ -47,7 +94,7 @@ fn test_media_session_updated_at_changes() {
     // Small delay to ensure time difference
     std::thread::sleep(Duration::from_millis(10));
 
-    session.set_state(SessionState::Idle);
+    session.transition_to(loading()).unwrap();
     assert!(session.get_updated_at() > initial_time);
 }
 
@@ -77,3 +124,156 @@ fn test_media_session_thread_safety() {
 
     handle.join().unwrap();
 }
+
+#[test]
+fn test_media_session_metadata_reports_duration() {
+    let session = MediaSession::new(SessionId::new());
+    assert_eq!(session.get_metadata(), None);
+    assert_eq!(session.duration(), None);
+
+    let metadata = MediaMetadata {
+        duration: Duration::from_secs(90),
+        video_track_count: 1,
+        ..Default::default()
+    };
+    session.set_metadata(metadata.clone());
+
+    assert_eq!(session.get_metadata(), Some(metadata));
+    assert_eq!(session.duration(), Some(Duration::from_secs(90)));
+}
+
+#[test]
+fn test_media_session_broadcasts_state_and_metadata() {
+    let session = MediaSession::new(SessionId::new());
+    let mut events = session.subscribe_events();
+
+    let metadata = MediaMetadata {
+        duration: Duration::from_secs(90),
+        ..Default::default()
+    };
+    session.transition_to(loading()).unwrap();
+    session.set_metadata(metadata.clone());
+    let ready = SessionState::Ready {
+        duration: Duration::from_secs(90),
+        metadata: metadata.clone(),
+    };
+    session.transition_to(ready.clone()).unwrap();
+    session.publish_event(SessionEvent::BufferingStarted);
+
+    assert_eq!(
+        events.try_recv().unwrap(),
+        SessionEvent::StateChanged {
+            old: SessionState::Idle,
+            new: loading(),
+        }
+    );
+    assert_eq!(
+        events.try_recv().unwrap(),
+        SessionEvent::MetadataReady(metadata)
+    );
+    assert_eq!(
+        events.try_recv().unwrap(),
+        SessionEvent::StateChanged {
+            old: loading(),
+            new: ready,
+        }
+    );
+    assert_eq!(events.try_recv().unwrap(), SessionEvent::BufferingStarted);
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn test_media_session_position_update_interval() {
+    let id = SessionId::new();
+    assert_eq!(
+        MediaSession::new(id).position_update_interval,
+        DEFAULT_POSITION_UPDATE_INTERVAL
+    );
+
+    let config = MediaSessionConfig::new().with_position_update_interval(Duration::from_secs(1));
+    let session = MediaSession::with_config(id, &config);
+    assert_eq!(session.position_update_interval, Duration::from_secs(1));
+}
+
+#[test]
+fn test_media_session_set_ab_loop() {
+    let session = MediaSession::new(SessionId::new());
+    assert_eq!(session.ab_loop(), None);
+
+    let start = Duration::from_secs(5);
+    let end = Duration::from_secs(15);
+    assert!(session.set_ab_loop(end, start).is_err());
+    assert!(session.set_ab_loop(start, start).is_err());
+
+    // Without metadata the end is not checked against the duration
+    session.set_ab_loop(start, Duration::from_secs(500)).unwrap();
+
+    session.set_metadata(MediaMetadata {
+        duration: Duration::from_secs(30),
+        ..Default::default()
+    });
+    assert!(matches!(
+        session.set_ab_loop(start, Duration::from_secs(31)),
+        Err(MediaError::InvalidParameter(_))
+    ));
+    session.set_ab_loop(start, Duration::from_secs(30)).unwrap();
+    session.set_ab_loop(start, end).unwrap();
+    assert_eq!(session.ab_loop(), Some((start, end)));
+
+    session.clear_ab_loop();
+    assert_eq!(session.ab_loop(), None);
+}
+
+#[test]
+fn test_media_session_snapshot() {
+    let session = MediaSession::new(SessionId::new());
+    let snapshot = session.snapshot();
+    assert_eq!(snapshot.session_id, session.id);
+    assert_eq!(snapshot.source, None);
+    assert_eq!(snapshot.position, Duration::ZERO);
+    assert_eq!((snapshot.volume, snapshot.rate), (1.0, 1.0));
+    assert!(!snapshot.muted);
+
+    session.set_source(&MediaSource::Buffer {
+        data: vec![1, 2, 3],
+        mime_type: "video/mp4".to_string(),
+    });
+    session.set_volume(0.25);
+    session.set_rate(1.5);
+    session.set_muted(true);
+    make_ready(&session);
+    session
+        .transition_to(SessionState::Playing {
+            position: Duration::from_secs(3),
+            rate: 1.5,
+            ab_loop: None,
+        })
+        .unwrap();
+    let metadata = MediaMetadata {
+        title: Some("Clip".to_string()),
+        duration: Duration::from_secs(10),
+        ..Default::default()
+    };
+    session.set_metadata(metadata.clone());
+
+    let snapshot = session.snapshot();
+    assert_eq!(
+        snapshot.source,
+        Some(SourceDescriptor::Buffer {
+            data: vec![1, 2, 3],
+            mime_type: "video/mp4".to_string(),
+        })
+    );
+    assert_eq!(snapshot.position, Duration::from_secs(3));
+    assert_eq!((snapshot.volume, snapshot.rate), (0.25, 1.5));
+    assert!(snapshot.muted);
+    assert_eq!(snapshot.metadata, Some(metadata));
+
+    // Streams cannot be loaded again
+    let (_, receiver) = tokio::sync::mpsc::channel(1);
+    session.set_source(&MediaSource::Stream {
+        receiver: std::sync::Arc::new(receiver),
+        mime_type: "video/webm".to_string(),
+    });
+    assert_eq!(session.snapshot().source, None);
+}
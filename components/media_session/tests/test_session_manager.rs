@@ -1,7 +1,9 @@
 //! Unit tests for SessionManager
 
-use cortenbrowser_media_session::{MediaMetadata, SessionManager, SessionState};
-use cortenbrowser_shared_types::{MediaError, MediaSessionConfig};
+use cortenbrowser_media_session::{
+    MediaMetadata, SessionEvent, SessionManager, SessionSnapshot, SessionState, SourceDescriptor,
+};
+use cortenbrowser_shared_types::{MediaError, MediaSessionConfig, SessionId};
 use std::time::Duration;
 
 #[test]
@@ -97,6 +99,7 @@ fn test_session_manager_transition_state_invalid() {
     let invalid_state = SessionState::Playing {
         position: Duration::ZERO,
         rate: 1.0,
+        ab_loop: None,
     };
 
     let result = manager.transition_state(session_id, invalid_state);
@@ -107,6 +110,98 @@ fn test_session_manager_transition_state_invalid() {
     ));
 }
 
+#[test]
+fn test_session_manager_transition_state_broadcasts_state_changed() {
+    let manager = SessionManager::new();
+    let session_id = manager.create(MediaSessionConfig::new()).unwrap();
+    let mut events = manager.get(session_id).unwrap().subscribe_events();
+
+    let loading = SessionState::Loading {
+        source: cortenbrowser_shared_types::MediaSource::Url {
+            url: "test.mp4".to_string(),
+        },
+        progress: 0.0,
+    };
+    manager
+        .transition_state(session_id, loading.clone())
+        .unwrap();
+    let playing = SessionState::Playing {
+        position: Duration::ZERO,
+        rate: 1.0,
+        ab_loop: None,
+    };
+    assert!(manager.transition_state(session_id, playing).is_err());
+
+    // The valid transition is broadcast, the invalid one as an error
+    assert_eq!(
+        events.try_recv().unwrap(),
+        SessionEvent::StateChanged {
+            old: SessionState::Idle,
+            new: loading,
+        }
+    );
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        SessionEvent::Error(MediaError::InvalidStateTransition { .. })
+    ));
+    assert!(events.try_recv().is_err());
+}
+
+#[test]
+fn test_session_manager_subscribe_sees_every_transition() {
+    let manager = SessionManager::new();
+    let session_id = manager.create(MediaSessionConfig::new()).unwrap();
+    let mut events = manager.subscribe(session_id).unwrap();
+
+    // Idle cannot play directly
+    let playing = SessionState::Playing {
+        position: Duration::ZERO,
+        rate: 1.0,
+        ab_loop: None,
+    };
+    assert!(matches!(
+        manager.transition_state(session_id, playing.clone()),
+        Err(MediaError::InvalidStateTransition { .. })
+    ));
+    assert_eq!(manager.get_state(session_id).unwrap(), SessionState::Idle);
+
+    let states = [
+        SessionState::Loading {
+            source: cortenbrowser_shared_types::MediaSource::Url {
+                url: "test.mp4".to_string(),
+            },
+            progress: 0.0,
+        },
+        SessionState::Ready {
+            duration: Duration::from_secs(60),
+            metadata: MediaMetadata::default(),
+        },
+        playing,
+    ];
+    for state in states.clone() {
+        manager.transition_state(session_id, state).unwrap();
+    }
+
+    assert!(matches!(
+        events.try_recv().unwrap(),
+        SessionEvent::Error(MediaError::InvalidStateTransition { .. })
+    ));
+    let mut old = SessionState::Idle;
+    for new in states {
+        assert_eq!(
+            events.try_recv().unwrap(),
+            SessionEvent::StateChanged {
+                old: old.clone(),
+                new: new.clone(),
+            }
+        );
+        old = new;
+    }
+    assert!(events.try_recv().is_err());
+
+    assert!(manager.subscribe(SessionId::new()).is_err());
+}
+
 #[test]
 fn test_session_manager_transition_state_nonexistent_session() {
     let manager = SessionManager::new();
@@ -187,6 +282,7 @@ fn test_session_manager_full_workflow() {
     let playing = SessionState::Playing {
         position: Duration::ZERO,
         rate: 1.0,
+        ab_loop: None,
     };
     assert!(manager.transition_state(session_id, playing).is_ok());
 
@@ -200,6 +296,7 @@ fn test_session_manager_full_workflow() {
     let playing2 = SessionState::Playing {
         position: Duration::from_secs(10),
         rate: 1.0,
+        ab_loop: None,
     };
     assert!(manager.transition_state(session_id, playing2).is_ok());
 
@@ -207,3 +304,108 @@ fn test_session_manager_full_workflow() {
     let ended = SessionState::Ended;
     assert!(manager.transition_state(session_id, ended).is_ok());
 }
+
+#[test]
+fn test_session_manager_restore() {
+    let snapshot = SessionSnapshot {
+        session_id: SessionId::new(),
+        source: Some(SourceDescriptor::Url {
+            url: "video.mp4".to_string(),
+        }),
+        position: Duration::from_secs(3),
+        volume: 0.5,
+        rate: 2.0,
+        muted: true,
+        metadata: Some(MediaMetadata {
+            duration: Duration::from_secs(10),
+            ..Default::default()
+        }),
+    };
+
+    // Snapshots survive serialization, as when persisted across restarts
+    let json = serde_json::to_string(&snapshot).unwrap();
+    let snapshot: SessionSnapshot = serde_json::from_str(&json).unwrap();
+
+    let manager = SessionManager::new();
+    let session_id = manager.restore(&snapshot).unwrap();
+    assert_ne!(session_id, snapshot.session_id);
+
+    let session = manager.get(session_id).unwrap();
+    assert_eq!(manager.get_state(session_id).unwrap(), SessionState::Idle);
+    assert_eq!(session.source(), snapshot.source);
+    assert_eq!(session.volume(), 0.5);
+    assert_eq!(session.rate(), 2.0);
+    assert!(session.is_muted());
+    assert_eq!(session.duration(), Some(Duration::from_secs(10)));
+
+    // The saved position is sought to once, when the source loads
+    assert_eq!(session.take_resume_position(), Some(Duration::from_secs(3)));
+    assert_eq!(session.take_resume_position(), None);
+}
+
+#[test]
+fn test_session_manager_update_metadata() {
+    let manager = SessionManager::new();
+    let session_id = manager.create(MediaSessionConfig::new()).unwrap();
+    let mut events = manager.subscribe(session_id).unwrap();
+
+    let song = |title: &str| MediaMetadata {
+        title: Some(title.to_string()),
+        artist: Some("Station".to_string()),
+        ..Default::default()
+    };
+
+    // Nothing is playing yet
+    assert!(matches!(
+        manager.update_metadata(session_id, song("First")),
+        Err(MediaError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        manager.update_metadata(SessionId::new(), song("First")),
+        Err(MediaError::SessionNotFound(_))
+    ));
+
+    let loading = SessionState::Loading {
+        source: cortenbrowser_shared_types::MediaSource::Url {
+            url: "radio.mp3".to_string(),
+        },
+        progress: 0.0,
+    };
+    let playing = SessionState::Playing {
+        position: Duration::ZERO,
+        rate: 1.0,
+        ab_loop: None,
+    };
+    manager.transition_state(session_id, loading).unwrap();
+    manager
+        .transition_state(
+            session_id,
+            SessionState::Ready {
+                duration: Duration::ZERO,
+                metadata: song("First"),
+            },
+        )
+        .unwrap();
+    manager
+        .transition_state(session_id, playing.clone())
+        .unwrap();
+    while events.try_recv().is_ok() {}
+
+    // Each new title is broadcast while the session keeps playing
+    manager.update_metadata(session_id, song("First")).unwrap();
+    manager.update_metadata(session_id, song("Second")).unwrap();
+    manager.update_metadata(session_id, song("Second")).unwrap();
+
+    let session = manager.get(session_id).unwrap();
+    assert_eq!(session.metadata(), song("Second"));
+    assert_eq!(manager.get_state(session_id).unwrap(), playing);
+    assert_eq!(
+        events.try_recv().unwrap(),
+        SessionEvent::MetadataChanged(song("First"))
+    );
+    assert_eq!(
+        events.try_recv().unwrap(),
+        SessionEvent::MetadataChanged(song("Second"))
+    );
+    assert!(events.try_recv().is_err());
+}
@@ -50,6 +50,7 @@ fn test_session_state_transition_ready_to_playing() {
     let new_state = SessionState::Playing {
         position: Duration::ZERO,
         rate: 1.0,
+        ab_loop: None,
     };
 
     assert!(state.can_transition_to(&new_state));
@@ -60,6 +61,7 @@ fn test_session_state_transition_playing_to_paused() {
     let state = SessionState::Playing {
         position: Duration::from_secs(10),
         rate: 1.0,
+        ab_loop: None,
     };
 
     let new_state = SessionState::Paused {
@@ -78,6 +80,7 @@ fn test_session_state_transition_paused_to_playing() {
     let new_state = SessionState::Playing {
         position: Duration::from_secs(10),
         rate: 1.0,
+        ab_loop: None,
     };
 
     assert!(state.can_transition_to(&new_state));
@@ -88,6 +91,7 @@ fn test_session_state_transition_to_seeking() {
     let state = SessionState::Playing {
         position: Duration::from_secs(10),
         rate: 1.0,
+        ab_loop: None,
     };
 
     let new_state = SessionState::Seeking {
@@ -106,6 +110,7 @@ fn test_session_state_transition_seeking_to_playing() {
     let new_state = SessionState::Playing {
         position: Duration::from_secs(30),
         rate: 1.0,
+        ab_loop: None,
     };
 
     assert!(state.can_transition_to(&new_state));
@@ -116,6 +121,7 @@ fn test_session_state_transition_to_ended() {
     let state = SessionState::Playing {
         position: Duration::from_secs(60),
         rate: 1.0,
+        ab_loop: None,
     };
 
     let new_state = SessionState::Ended;
@@ -128,6 +134,7 @@ fn test_session_state_transition_to_error() {
     let state = SessionState::Playing {
         position: Duration::from_secs(10),
         rate: 1.0,
+        ab_loop: None,
     };
 
     let new_state = SessionState::Error {
@@ -145,6 +152,7 @@ fn test_invalid_state_transition_idle_to_playing() {
     let new_state = SessionState::Playing {
         position: Duration::ZERO,
         rate: 1.0,
+        ab_loop: None,
     };
 
     assert!(!state.can_transition_to(&new_state));
@@ -156,16 +164,32 @@ fn test_invalid_state_transition_ended_to_playing() {
     let new_state = SessionState::Playing {
         position: Duration::ZERO,
         rate: 1.0,
+        ab_loop: None,
     };
 
     assert!(!state.can_transition_to(&new_state));
 }
 
+#[test]
+fn test_session_state_transition_ended_loops_back_to_playing() {
+    let looping = SessionState::Looping { iteration: 1 };
+
+    assert!(SessionState::Ended.can_transition_to(&looping));
+    assert!(looping.can_transition_to(&SessionState::Playing {
+        position: Duration::ZERO,
+        rate: 1.0,
+        ab_loop: None,
+    }));
+    assert!(!looping.can_transition_to(&SessionState::Ended));
+    assert_eq!(looping.state_name(), "Looping");
+}
+
 #[test]
 fn test_session_state_clone() {
     let state = SessionState::Playing {
         position: Duration::from_secs(10),
         rate: 1.5,
+        ab_loop: None,
     };
 
     let cloned = state.clone();
@@ -0,0 +1,181 @@
+//! Audio channel layouts
+//!
+//! This module describes which speaker each channel of an interleaved
+//! [`AudioBuffer`] feeds, and downmixes multichannel audio to stereo.
+
+use crate::media::AudioBuffer;
+
+/// Speaker position of an audio channel
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::Channel;
+///
+/// let channel = Channel::FrontLeft;
+/// assert!(!channel.is_lfe());
+/// assert!(Channel::LFE.is_lfe());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    /// Front left speaker
+    FrontLeft,
+    /// Front right speaker
+    FrontRight,
+    /// Front center speaker
+    Center,
+    /// Low-frequency effects (subwoofer)
+    LFE,
+    /// Left surround speaker
+    SurroundLeft,
+    /// Right surround speaker
+    SurroundRight,
+}
+
+impl Channel {
+    /// Returns whether this is the low-frequency effects channel
+    pub fn is_lfe(&self) -> bool {
+        matches!(self, Channel::LFE)
+    }
+}
+
+/// Speaker position of each channel of an audio buffer, in interleaving
+/// order
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{Channel, ChannelMap};
+///
+/// let map = ChannelMap::surround_5_1();
+/// assert_eq!(map.len(), 6);
+/// assert_eq!(map.channels[3], Channel::LFE);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChannelMap {
+    /// Channel positions, one per interleaved channel
+    pub channels: Vec<Channel>,
+}
+
+impl ChannelMap {
+    /// Creates a channel map from channel positions in interleaving order
+    pub fn new(channels: Vec<Channel>) -> Self {
+        Self { channels }
+    }
+
+    /// Mono layout: center only
+    pub fn mono() -> Self {
+        Self::new(vec![Channel::Center])
+    }
+
+    /// Stereo layout: front left, front right
+    pub fn stereo() -> Self {
+        Self::new(vec![Channel::FrontLeft, Channel::FrontRight])
+    }
+
+    /// 5.1 layout in SMPTE/WAVE order: front left, front right, center,
+    /// LFE, surround left, surround right
+    pub fn surround_5_1() -> Self {
+        Self::new(vec![
+            Channel::FrontLeft,
+            Channel::FrontRight,
+            Channel::Center,
+            Channel::LFE,
+            Channel::SurroundLeft,
+            Channel::SurroundRight,
+        ])
+    }
+
+    /// Returns the number of channels
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Returns whether the map has no channels
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+}
+
+/// ITU-R BS.775 gain of the center and surround channels in a stereo
+/// downmix (-3 dB)
+const DOWNMIX_GAIN: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+/// Downmixes multichannel audio to stereo
+///
+/// Uses the ITU-R BS.775 coefficients:
+///
+/// ```text
+/// L = FL + 0.707 * C + 0.707 * SL
+/// R = FR + 0.707 * C + 0.707 * SR
+/// ```
+///
+/// The LFE channel is dropped, as are channels of `buffer` beyond the end of
+/// `map`. The result is not normalized, so loud multichannel input may
+/// exceed the -1.0 to 1.0 range.
+///
+/// # Arguments
+///
+/// * `buffer` - Interleaved audio to downmix
+/// * `map` - Speaker position of each channel of `buffer`
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{downmix_to_stereo, AudioBuffer, AudioFormat, ChannelMap};
+/// use std::time::Duration;
+///
+/// // One frame of 5.1 audio with only the center channel active
+/// let buffer = AudioBuffer::new(
+///     AudioFormat::F32LE,
+///     48000,
+///     6,
+///     vec![0.0, 0.0, 1.0, 0.0, 0.0, 0.0],
+///     Duration::ZERO,
+/// );
+///
+/// let stereo = downmix_to_stereo(&buffer, &ChannelMap::surround_5_1());
+/// assert_eq!(stereo.channels, 2);
+/// assert!((stereo.samples[0] - 0.707).abs() < 0.001);
+/// assert_eq!(stereo.samples[0], stereo.samples[1]);
+/// ```
+pub fn downmix_to_stereo(buffer: &AudioBuffer, map: &ChannelMap) -> AudioBuffer {
+    let channels = buffer.channels as usize;
+
+    // (left gain, right gain) per input channel
+    let gains: Vec<(f32, f32)> = map
+        .channels
+        .iter()
+        .take(channels)
+        .map(|channel| match channel {
+            Channel::FrontLeft => (1.0, 0.0),
+            Channel::FrontRight => (0.0, 1.0),
+            Channel::Center => (DOWNMIX_GAIN, DOWNMIX_GAIN),
+            Channel::LFE => (0.0, 0.0),
+            Channel::SurroundLeft => (DOWNMIX_GAIN, 0.0),
+            Channel::SurroundRight => (0.0, DOWNMIX_GAIN),
+        })
+        .collect();
+
+    let mut samples = Vec::with_capacity(buffer.samples.len() / channels.max(1) * 2);
+    for frame in buffer.samples.chunks_exact(channels.max(1)) {
+        let (left, right) = frame.iter().zip(&gains).fold(
+            (0.0, 0.0),
+            |(left, right), (sample, (left_gain, right_gain))| {
+                (left + sample * left_gain, right + sample * right_gain)
+            },
+        );
+        samples.push(left);
+        samples.push(right);
+    }
+
+    AudioBuffer {
+        format: buffer.format,
+        sample_rate: buffer.sample_rate,
+        channels: 2,
+        samples,
+        timestamp: buffer.timestamp,
+        duration: buffer.duration,
+        channel_map: Some(ChannelMap::stereo()),
+    }
+}
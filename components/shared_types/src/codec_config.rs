@@ -0,0 +1,338 @@
+//! Codec configuration records
+//!
+//! This module provides parsers for the out-of-band codec configuration
+//! ("extradata") carried by containers, and the color description of video
+//! tracks.
+
+use crate::codecs::AACProfile;
+use crate::errors::MediaError;
+
+/// Color description of a video track
+///
+/// Values are ITU-T H.273 code points, as stored in the MP4 `colr` box and
+/// the Matroska `Colour` element.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::ColorInfo;
+///
+/// let bt709 = ColorInfo::BT709;
+/// assert_eq!(bt709.primaries, 1);
+/// assert!(!bt709.full_range);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ColorInfo {
+    /// Color primaries (1 = BT.709, 9 = BT.2020)
+    pub primaries: u8,
+    /// Transfer characteristics (1 = BT.709, 16 = PQ, 18 = HLG)
+    pub transfer: u8,
+    /// Matrix coefficients (1 = BT.709, 9 = BT.2020 non-constant luminance)
+    pub matrix: u8,
+    /// Whether samples use the full range rather than the limited
+    /// (studio swing) range
+    pub full_range: bool,
+}
+
+impl ColorInfo {
+    /// BT.709 primaries, transfer and matrix in the limited range
+    pub const BT709: ColorInfo = ColorInfo {
+        primaries: 1,
+        transfer: 1,
+        matrix: 1,
+        full_range: false,
+    };
+}
+
+/// H.264 decoder configuration record (`avcC`)
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::AvcDecoderConfig;
+///
+/// let avcc = [
+///     0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x04, 0x67, 0x64, 0x00, 0x1F, 0x01, 0x00,
+///     0x02, 0x68, 0xEE,
+/// ];
+/// let config = AvcDecoderConfig::parse(&avcc).unwrap();
+/// assert_eq!(config.nal_length_size, 4);
+/// assert_eq!(config.sps, vec![vec![0x67, 0x64, 0x00, 0x1F]]);
+/// assert_eq!(config.pps, vec![vec![0x68, 0xEE]]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvcDecoderConfig {
+    /// `profile_idc` of the stream
+    pub profile_indication: u8,
+    /// Constraint flags of the stream
+    pub profile_compatibility: u8,
+    /// `level_idc` of the stream
+    pub level_indication: u8,
+    /// Size in bytes of the length prefix of each NAL unit in a sample
+    pub nal_length_size: u8,
+    /// Sequence parameter set NAL units
+    pub sps: Vec<Vec<u8>>,
+    /// Picture parameter set NAL units
+    pub pps: Vec<Vec<u8>>,
+}
+
+impl AvcDecoderConfig {
+    /// Parse an `avcC` record
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AvcDecoderConfig)` - The parsed record
+    /// * `Err(MediaError::CodecError)` - If the record is truncated or has an
+    ///   unknown version
+    pub fn parse(data: &[u8]) -> Result<Self, MediaError> {
+        let mut reader = data;
+        let header = take(&mut reader, 5, "avcC")?;
+        if header[0] != 1 {
+            return Err(MediaError::CodecError {
+                details: format!("Unsupported avcC version {}", header[0]),
+            });
+        }
+
+        let sps_count = take(&mut reader, 1, "avcC")?[0] & 0x1F;
+        let sps = read_parameter_sets(&mut reader, sps_count)?;
+        let pps_count = take(&mut reader, 1, "avcC")?[0];
+        let pps = read_parameter_sets(&mut reader, pps_count)?;
+
+        Ok(Self {
+            profile_indication: header[1],
+            profile_compatibility: header[2],
+            level_indication: header[3],
+            nal_length_size: (header[4] & 0x03) + 1,
+            sps,
+            pps,
+        })
+    }
+
+    /// Returns the SPS and PPS NAL units as an Annex B byte stream
+    pub fn to_annex_b(&self) -> Vec<u8> {
+        let mut stream = Vec::new();
+        for nal in self.sps.iter().chain(&self.pps) {
+            stream.extend_from_slice(&[0, 0, 0, 1]);
+            stream.extend_from_slice(nal);
+        }
+        stream
+    }
+}
+
+/// MPEG-4 AudioSpecificConfig, the decoder configuration of AAC streams
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{AACProfile, AudioSpecificConfig};
+///
+/// // AAC LC, 44.1 kHz, stereo
+/// let config = AudioSpecificConfig::parse(&[0x12, 0x10]).unwrap();
+/// assert_eq!(config.profile(), Some(AACProfile::LC));
+/// assert_eq!(config.sample_rate, 44100);
+/// assert_eq!(config.channels, 2);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioSpecificConfig {
+    /// Audio object type of the core codec (2 = AAC LC)
+    pub object_type: u8,
+    /// Sampling frequency of the core codec in Hz
+    pub sample_rate: u32,
+    /// Output sampling frequency with SBR signalled explicitly (HE-AAC)
+    pub extension_sample_rate: Option<u32>,
+    /// Whether parametric stereo is signalled explicitly (HE-AAC v2)
+    pub parametric_stereo: bool,
+    /// Number of channels; 0 when defined by a program config element
+    pub channels: u8,
+}
+
+/// Sampling frequencies by `samplingFrequencyIndex`
+const SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// Audio object types
+const AOT_AAC_LC: u8 = 2;
+const AOT_SBR: u8 = 5;
+const AOT_ER_AAC_LD: u8 = 23;
+const AOT_PS: u8 = 29;
+
+impl AudioSpecificConfig {
+    /// Parse an AudioSpecificConfig
+    ///
+    /// Explicitly signalled SBR and PS (object types 5 and 29) are unwrapped
+    /// to the core object type.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(AudioSpecificConfig)` - The parsed configuration
+    /// * `Err(MediaError::CodecError)` - If the data is truncated or uses a
+    ///   reserved sampling frequency index
+    pub fn parse(data: &[u8]) -> Result<Self, MediaError> {
+        let mut bits = BitReader::new(data);
+        let mut object_type = read_object_type(&mut bits)?;
+        let sample_rate = read_sample_rate(&mut bits)?;
+        let channel_configuration = bits.read(4)? as u8;
+
+        let mut extension_sample_rate = None;
+        let parametric_stereo = object_type == AOT_PS;
+        if object_type == AOT_SBR || object_type == AOT_PS {
+            extension_sample_rate = Some(read_sample_rate(&mut bits)?);
+            object_type = read_object_type(&mut bits)?;
+        }
+
+        Ok(Self {
+            object_type,
+            sample_rate,
+            extension_sample_rate,
+            parametric_stereo,
+            channels: match channel_configuration {
+                7 => 8,
+                config => config,
+            },
+        })
+    }
+
+    /// Returns the AAC profile, if the object type maps to one
+    pub fn profile(&self) -> Option<AACProfile> {
+        match (self.object_type, self.extension_sample_rate) {
+            (AOT_AAC_LC, Some(_)) if self.parametric_stereo => Some(AACProfile::HEv2),
+            (AOT_AAC_LC, Some(_)) => Some(AACProfile::HE),
+            (AOT_AAC_LC, None) => Some(AACProfile::LC),
+            (AOT_ER_AAC_LD, _) => Some(AACProfile::LD),
+            _ => None,
+        }
+    }
+
+    /// Returns the output sampling frequency in Hz
+    ///
+    /// This is the SBR frequency for HE-AAC, otherwise the core frequency.
+    pub fn output_sample_rate(&self) -> u32 {
+        self.extension_sample_rate.unwrap_or(self.sample_rate)
+    }
+}
+
+/// Encoder delay and padding of a stream, trimmed by decoders for gapless
+/// playback
+///
+/// Encoders prepend priming samples and append padding to fill the last
+/// frame. Both are counted in samples per channel at the decoder output rate.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::GaplessInfo;
+///
+/// let info = GaplessInfo::parse_itunsmpb(
+///     " 00000000 00000840 000001C0 00000000000AC440 00000000 00000000",
+/// )
+/// .unwrap();
+/// assert_eq!(info.encoder_delay, 2112);
+/// assert_eq!(info.padding, 448);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct GaplessInfo {
+    /// Priming samples to drop from the start of the stream
+    pub encoder_delay: u32,
+    /// Padding samples to drop from the end of the stream
+    pub padding: u32,
+}
+
+impl GaplessInfo {
+    /// Parse the value of an iTunes `iTunSMPB` tag
+    ///
+    /// The tag holds space-separated hexadecimal fields: a reserved field,
+    /// the encoder delay, the padding and the original sample count.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(GaplessInfo)` - The delay and padding of the stream
+    /// * `Err(MediaError::CodecError)` - If a field is missing or not
+    ///   hexadecimal
+    pub fn parse_itunsmpb(value: &str) -> Result<Self, MediaError> {
+        let mut fields = value.split_whitespace().skip(1).map(|field| {
+            u32::from_str_radix(field, 16).map_err(|_| MediaError::CodecError {
+                details: format!("Invalid iTunSMPB field '{}'", field),
+            })
+        });
+        let mut next = || {
+            fields.next().unwrap_or_else(|| {
+                Err(MediaError::CodecError {
+                    details: "Truncated iTunSMPB tag".to_string(),
+                })
+            })
+        };
+        Ok(Self {
+            encoder_delay: next()?,
+            padding: next()?,
+        })
+    }
+}
+
+fn read_parameter_sets(data: &mut &[u8], count: u8) -> Result<Vec<Vec<u8>>, MediaError> {
+    (0..count)
+        .map(|_| {
+            let length = take(data, 2, "avcC")?;
+            let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+            Ok(take(data, length, "avcC")?.to_vec())
+        })
+        .collect()
+}
+
+fn read_object_type(bits: &mut BitReader) -> Result<u8, MediaError> {
+    match bits.read(5)? as u8 {
+        31 => Ok(32 + bits.read(6)? as u8),
+        object_type => Ok(object_type),
+    }
+}
+
+fn read_sample_rate(bits: &mut BitReader) -> Result<u32, MediaError> {
+    match bits.read(4)? as usize {
+        15 => bits.read(24),
+        index => SAMPLE_RATES
+            .get(index)
+            .copied()
+            .ok_or_else(|| MediaError::CodecError {
+                details: format!("Reserved AAC sampling frequency index {}", index),
+            }),
+    }
+}
+
+fn take<'a>(data: &mut &'a [u8], length: usize, record: &str) -> Result<&'a [u8], MediaError> {
+    if data.len() < length {
+        return Err(MediaError::CodecError {
+            details: format!("Truncated {} record", record),
+        });
+    }
+    let (head, rest) = data.split_at(length);
+    *data = rest;
+    Ok(head)
+}
+
+/// MSB-first bit reader
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn read(&mut self, count: usize) -> Result<u32, MediaError> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or_else(|| MediaError::CodecError {
+                    details: "Truncated AudioSpecificConfig".to_string(),
+                })?;
+            let bit = (byte >> (7 - self.position % 8)) & 1;
+            value = (value << 1) | u32::from(bit);
+            self.position += 1;
+        }
+        Ok(value)
+    }
+}
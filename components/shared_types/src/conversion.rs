@@ -0,0 +1,570 @@
+//! Pixel format conversion
+//!
+//! This module converts video frames between the planar YUV, semi-planar
+//! NV12 and packed RGB layouts produced and consumed by the media
+//! components, such as RGBA screen captures fed to YUV encoders, decoded
+//! YUV frames rendered as RGBA, or NV12 frames from hardware decoders.
+//!
+//! YUV samples use the limited (studio swing) range, and the color matrix
+//! is taken from [`FrameMetadata::color_space`](crate::FrameMetadata::color_space).
+//! Chroma planes of odd-sized frames are rounded up to cover the last row
+//! and column.
+
+use crate::{ColorSpace, MediaError, PixelFormat, VideoFrame};
+
+/// Converts video frames between pixel formats
+///
+/// Supported conversions:
+///
+/// - [`PixelFormat::YUV420`] to [`PixelFormat::RGB24`],
+///   [`PixelFormat::RGBA32`] (opaque) and [`PixelFormat::NV12`]
+/// - [`PixelFormat::RGB24`], [`PixelFormat::RGBA32`], [`PixelFormat::NV12`]
+///   and [`PixelFormat::YUV422`] to [`PixelFormat::YUV420`]
+/// - [`PixelFormat::NV12`] and [`PixelFormat::YUV422`] to
+///   [`PixelFormat::RGB24`] and [`PixelFormat::RGBA32`], through YUV 4:2:0
+///
+/// With the `simd` feature, the RGB conversions process eight pixels at a
+/// time with portable SIMD from the `wide` crate.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{PixelFormat, PixelFormatConverter, VideoFrame};
+/// use std::time::Duration;
+///
+/// // A 2x2 white RGB frame
+/// let frame = VideoFrame::new(2, 2, PixelFormat::RGB24, vec![255; 12], Duration::ZERO);
+///
+/// let yuv = PixelFormatConverter::convert(&frame, PixelFormat::YUV420).unwrap();
+/// assert_eq!(yuv.format, PixelFormat::YUV420);
+/// assert_eq!(yuv.data, [235, 235, 235, 235, 128, 128]);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PixelFormatConverter;
+
+impl PixelFormatConverter {
+    /// Converts `frame` to the `target` pixel format
+    ///
+    /// The converted frame keeps the dimensions, timing and metadata of
+    /// `frame`, and is tightly packed even if `frame` has padded `strides`.
+    /// Converting to the frame's own format returns a copy.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` if the conversion is not
+    /// supported, and `MediaError::InvalidParameter` if the frame data does
+    /// not match the size of its format and dimensions.
+    pub fn convert(frame: &VideoFrame, target: PixelFormat) -> Result<VideoFrame, MediaError> {
+        if frame.format == target {
+            return Ok(frame.clone());
+        }
+
+        let layout = Layout::new(frame.width as usize, frame.height as usize);
+        let source = frame.packed_data();
+        let expected = layout.frame_size(frame.format);
+        if expected != Some(source.len()) {
+            return Err(MediaError::InvalidParameter(format!(
+                "{:?} frame of {}x{} has {} bytes of data, expected {}",
+                frame.format,
+                frame.width,
+                frame.height,
+                source.len(),
+                expected.map_or_else(|| "a supported format".to_string(), |size| size.to_string())
+            )));
+        }
+
+        let coefficients = Coefficients::new(frame.metadata.color_space);
+        let bytes_per_pixel = if target == PixelFormat::RGBA32 { 4 } else { 3 };
+        let data = match (frame.format, target) {
+            (PixelFormat::YUV420, PixelFormat::RGB24 | PixelFormat::RGBA32) => {
+                yuv420_to_rgb(&layout, &source, bytes_per_pixel, &coefficients)
+            }
+            (PixelFormat::NV12 | PixelFormat::YUV422, PixelFormat::RGB24 | PixelFormat::RGBA32) => {
+                let yuv = to_yuv420(&layout, frame.format, &source, &coefficients)
+                    .ok_or_else(|| unsupported(frame.format, target))?;
+                yuv420_to_rgb(&layout, &yuv, bytes_per_pixel, &coefficients)
+            }
+            (PixelFormat::YUV420, PixelFormat::NV12) => yuv420_to_nv12(&layout, &source),
+            (format, PixelFormat::YUV420) => to_yuv420(&layout, format, &source, &coefficients)
+                .ok_or_else(|| unsupported(format, target))?,
+            (format, target) => return Err(unsupported(format, target)),
+        };
+
+        Ok(VideoFrame {
+            width: frame.width,
+            height: frame.height,
+            format: target,
+            data,
+            strides: None,
+            timestamp: frame.timestamp,
+            duration: frame.duration,
+            metadata: frame.metadata.clone(),
+        })
+    }
+}
+
+impl VideoFrame {
+    /// Converts the frame to opaque packed RGBA for rendering
+    ///
+    /// YUV frames are converted from the limited range with the color
+    /// matrix of [`FrameMetadata::color_space`](crate::FrameMetadata::color_space),
+    /// as by [`PixelFormatConverter::convert`]. Every pixel has an alpha of
+    /// 255.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` unless the frame is
+    /// [`PixelFormat::YUV420`], [`PixelFormat::NV12`],
+    /// [`PixelFormat::YUV422`] or already [`PixelFormat::RGBA32`], and
+    /// `MediaError::InvalidParameter` if the frame data does not match the
+    /// size of its format and dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// // A 2x2 white YUV 4:2:0 frame
+    /// let frame = VideoFrame::new(
+    ///     2,
+    ///     2,
+    ///     PixelFormat::YUV420,
+    ///     vec![235, 235, 235, 235, 128, 128],
+    ///     Duration::ZERO,
+    /// );
+    ///
+    /// let rgba = frame.to_rgba().unwrap();
+    /// assert_eq!(rgba.format, PixelFormat::RGBA32);
+    /// assert_eq!(rgba.data, vec![255; 16]);
+    /// ```
+    pub fn to_rgba(&self) -> Result<VideoFrame, MediaError> {
+        PixelFormatConverter::convert(self, PixelFormat::RGBA32)
+    }
+
+    /// Converts a planar YUV 4:2:0 frame to NV12
+    ///
+    /// The U and V planes are interleaved into one plane; samples are
+    /// unchanged.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` unless the frame is
+    /// [`PixelFormat::YUV420`] or already [`PixelFormat::NV12`], and
+    /// `MediaError::InvalidParameter` if the frame data does not match the
+    /// size of its format and dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// let frame = VideoFrame::new(
+    ///     2,
+    ///     2,
+    ///     PixelFormat::YUV420,
+    ///     vec![1, 2, 3, 4, 10, 20],
+    ///     Duration::ZERO,
+    /// );
+    ///
+    /// let nv12 = frame.to_nv12().unwrap();
+    /// assert_eq!(nv12.data, [1, 2, 3, 4, 10, 20]);
+    /// ```
+    pub fn to_nv12(&self) -> Result<VideoFrame, MediaError> {
+        PixelFormatConverter::convert(self, PixelFormat::NV12)
+    }
+}
+
+fn unsupported(source: PixelFormat, target: PixelFormat) -> MediaError {
+    MediaError::UnsupportedFormat {
+        format: format!("{:?} to {:?} conversion", source, target),
+    }
+}
+
+/// Plane dimensions of a frame
+pub(crate) struct Layout {
+    pub(crate) width: usize,
+    pub(crate) height: usize,
+    /// Width of subsampled chroma planes
+    pub(crate) chroma_width: usize,
+    /// Height of vertically subsampled chroma planes
+    pub(crate) chroma_height: usize,
+}
+
+impl Layout {
+    pub(crate) fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            chroma_width: width.div_ceil(2),
+            chroma_height: height.div_ceil(2),
+        }
+    }
+
+    pub(crate) fn luma_size(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// Size of one 4:2:0 chroma plane
+    pub(crate) fn chroma_size(&self) -> usize {
+        self.chroma_width * self.chroma_height
+    }
+
+    /// Bytes per row and rows of each plane of a frame in `format`, with
+    /// `(0, 0)` for planes the format does not have
+    pub(crate) fn planes(&self, format: PixelFormat) -> [(usize, usize); 3] {
+        let luma = (self.width, self.height);
+        let chroma = (self.chroma_width, self.chroma_height);
+        match format {
+            PixelFormat::YUV420 => [luma, chroma, chroma],
+            PixelFormat::YUV422 => {
+                let chroma = (self.chroma_width, self.height);
+                [luma, chroma, chroma]
+            }
+            PixelFormat::YUV444 => [luma; 3],
+            PixelFormat::NV12 => [luma, (2 * self.chroma_width, self.chroma_height), (0, 0)],
+            PixelFormat::RGB24 => [(3 * self.width, self.height), (0, 0), (0, 0)],
+            PixelFormat::RGBA32 => [(4 * self.width, self.height), (0, 0), (0, 0)],
+        }
+    }
+
+    /// Size of a frame in `format`, or `None` if it cannot be converted
+    pub(crate) fn frame_size(&self, format: PixelFormat) -> Option<usize> {
+        match format {
+            PixelFormat::YUV420 | PixelFormat::NV12 => {
+                Some(self.luma_size() + 2 * self.chroma_size())
+            }
+            PixelFormat::YUV422 => Some(self.luma_size() + 2 * self.chroma_width * self.height),
+            PixelFormat::RGB24 => Some(self.luma_size() * 3),
+            PixelFormat::RGBA32 => Some(self.luma_size() * 4),
+            PixelFormat::YUV444 => None,
+        }
+    }
+}
+
+/// Conversion coefficients for a color matrix in the limited range
+#[derive(Debug, Clone, Copy)]
+struct Coefficients {
+    /// Weights of R, G and B in Y
+    y: [f32; 3],
+    /// Weights of R, G and B in Cb
+    cb: [f32; 3],
+    /// Weights of R, G and B in Cr
+    cr: [f32; 3],
+    /// Scale of Y - 16 in R, G and B
+    y_scale: f32,
+    /// Weight of Cr - 128 in R
+    r_cr: f32,
+    /// Weights of Cb - 128 and Cr - 128 in G
+    g_cb: f32,
+    g_cr: f32,
+    /// Weight of Cb - 128 in B
+    b_cb: f32,
+}
+
+impl Coefficients {
+    fn new(color_space: ColorSpace) -> Self {
+        let (kr, kb) = match color_space {
+            ColorSpace::BT601 => (0.299, 0.114),
+            ColorSpace::BT709 => (0.2126, 0.0722),
+        };
+        let kg = 1.0 - kr - kb;
+        let luma_range = 219.0 / 255.0;
+        let chroma_range = 224.0 / 255.0;
+        let cb_scale = chroma_range / (2.0 * (1.0 - kb));
+        let cr_scale = chroma_range / (2.0 * (1.0 - kr));
+
+        Self {
+            y: [kr * luma_range, kg * luma_range, kb * luma_range],
+            cb: [-kr * cb_scale, -kg * cb_scale, (1.0 - kb) * cb_scale],
+            cr: [(1.0 - kr) * cr_scale, -kg * cr_scale, -kb * cr_scale],
+            y_scale: 1.0 / luma_range,
+            r_cr: 2.0 * (1.0 - kr) / chroma_range,
+            g_cb: -2.0 * (1.0 - kb) * kb / kg / chroma_range,
+            g_cr: -2.0 * (1.0 - kr) * kr / kg / chroma_range,
+            b_cb: 2.0 * (1.0 - kb) / chroma_range,
+        }
+    }
+}
+
+/// Round a sample to the nearest integer and clamp it to 0-255
+fn to_sample(value: f32) -> u8 {
+    (value + 0.5).floor().clamp(0.0, 255.0) as u8
+}
+
+/// Convert a frame in `format` to planar YUV 4:2:0, or `None` if `format`
+/// cannot be converted
+fn to_yuv420(
+    layout: &Layout,
+    format: PixelFormat,
+    data: &[u8],
+    coefficients: &Coefficients,
+) -> Option<Vec<u8>> {
+    match format {
+        PixelFormat::YUV420 => Some(data.to_vec()),
+        PixelFormat::NV12 => Some(nv12_to_yuv420(layout, data)),
+        PixelFormat::YUV422 => Some(yuv422_to_yuv420(layout, data)),
+        PixelFormat::RGB24 => Some(rgb_to_yuv420(layout, data, 3, coefficients)),
+        PixelFormat::RGBA32 => Some(rgb_to_yuv420(layout, data, 4, coefficients)),
+        PixelFormat::YUV444 => None,
+    }
+}
+
+/// Interleave the U and V planes of a YUV 4:2:0 frame into the UV plane of
+/// an NV12 frame
+fn yuv420_to_nv12(layout: &Layout, data: &[u8]) -> Vec<u8> {
+    let (luma, chroma) = data.split_at(layout.luma_size());
+    let (u_plane, v_plane) = chroma.split_at(layout.chroma_size());
+    let mut nv12 = Vec::with_capacity(data.len());
+    nv12.extend_from_slice(luma);
+    nv12.extend(u_plane.iter().zip(v_plane).flat_map(|(&u, &v)| [u, v]));
+    nv12
+}
+
+/// Split the interleaved UV plane of an NV12 frame into U and V planes
+fn nv12_to_yuv420(layout: &Layout, data: &[u8]) -> Vec<u8> {
+    let (luma, uv) = data.split_at(layout.luma_size());
+    let mut yuv = Vec::with_capacity(data.len());
+    yuv.extend_from_slice(luma);
+    yuv.extend(uv.iter().step_by(2));
+    yuv.extend(uv.iter().skip(1).step_by(2));
+    yuv
+}
+
+/// Halve the chroma planes of a YUV 4:2:2 frame vertically by averaging
+/// pairs of rows
+fn yuv422_to_yuv420(layout: &Layout, data: &[u8]) -> Vec<u8> {
+    let (luma, chroma) = data.split_at(layout.luma_size());
+    let mut yuv = Vec::with_capacity(layout.luma_size() + 2 * layout.chroma_size());
+    yuv.extend_from_slice(luma);
+
+    let width = layout.chroma_width;
+    if width == 0 {
+        return yuv;
+    }
+    for plane in chroma.chunks_exact(width * layout.height) {
+        for rows in plane.chunks(2 * width) {
+            let (top, bottom) = rows.split_at(width.min(rows.len()));
+            // The last row of an odd-height frame has no pair
+            let bottom = if bottom.is_empty() { top } else { bottom };
+            yuv.extend(
+                top.iter()
+                    .zip(bottom)
+                    .map(|(&a, &b)| (u16::from(a) + u16::from(b)).div_ceil(2) as u8),
+            );
+        }
+    }
+    yuv
+}
+
+/// Convert packed RGB with `bytes_per_pixel` bytes per pixel (alpha is
+/// ignored) to planar YUV 4:2:0
+///
+/// Each chroma sample is computed from the average color of its 2x2 block.
+fn rgb_to_yuv420(
+    layout: &Layout,
+    data: &[u8],
+    bytes_per_pixel: usize,
+    coefficients: &Coefficients,
+) -> Vec<u8> {
+    let Layout { width, height, .. } = *layout;
+    let mut yuv = vec![0u8; layout.luma_size() + 2 * layout.chroma_size()];
+    if width == 0 || height == 0 {
+        return yuv;
+    }
+    let (luma, chroma) = yuv.split_at_mut(layout.luma_size());
+    let (u_plane, v_plane) = chroma.split_at_mut(layout.chroma_size());
+
+    let stride = width * bytes_per_pixel;
+    for (rgb, out) in data.chunks_exact(stride).zip(luma.chunks_exact_mut(width)) {
+        rgb_row_to_luma(rgb, bytes_per_pixel, out, coefficients);
+    }
+
+    let pixel = |x: usize, y: usize| {
+        let offset = y * stride + x * bytes_per_pixel;
+        [
+            f32::from(data[offset]),
+            f32::from(data[offset + 1]),
+            f32::from(data[offset + 2]),
+        ]
+    };
+    for cy in 0..layout.chroma_height {
+        for cx in 0..layout.chroma_width {
+            let mut sum = [0.0f32; 3];
+            let mut count = 0.0;
+            for y in (2 * cy)..(2 * cy + 2).min(height) {
+                for x in (2 * cx)..(2 * cx + 2).min(width) {
+                    let [r, g, b] = pixel(x, y);
+                    sum[0] += r;
+                    sum[1] += g;
+                    sum[2] += b;
+                    count += 1.0;
+                }
+            }
+            let [r, g, b] = sum.map(|channel| channel / count);
+            let [cb_r, cb_g, cb_b] = coefficients.cb;
+            let [cr_r, cr_g, cr_b] = coefficients.cr;
+            let index = cy * layout.chroma_width + cx;
+            u_plane[index] = to_sample(128.0 + cb_r * r + cb_g * g + cb_b * b);
+            v_plane[index] = to_sample(128.0 + cr_r * r + cr_g * g + cr_b * b);
+        }
+    }
+    yuv
+}
+
+/// Convert planar YUV 4:2:0 to packed RGB with `bytes_per_pixel` bytes per
+/// pixel, any alpha opaque
+fn yuv420_to_rgb(
+    layout: &Layout,
+    data: &[u8],
+    bytes_per_pixel: usize,
+    coefficients: &Coefficients,
+) -> Vec<u8> {
+    let Layout { width, height, .. } = *layout;
+    // Only the color bytes are written, leaving alpha at 255
+    let mut rgb = vec![255u8; layout.luma_size() * bytes_per_pixel];
+    if width == 0 || height == 0 {
+        return rgb;
+    }
+    let (luma, chroma) = data.split_at(layout.luma_size());
+    let (u_plane, v_plane) = chroma.split_at(layout.chroma_size());
+
+    for (y, out) in rgb.chunks_exact_mut(width * bytes_per_pixel).enumerate() {
+        let chroma_row = (y / 2) * layout.chroma_width;
+        let chroma_row = chroma_row..chroma_row + layout.chroma_width;
+        yuv_row_to_rgb(
+            &luma[y * width..(y + 1) * width],
+            &u_plane[chroma_row.clone()],
+            &v_plane[chroma_row],
+            bytes_per_pixel,
+            out,
+            coefficients,
+        );
+    }
+    rgb
+}
+
+/// Compute the Y samples of one row of packed RGB pixels
+fn rgb_row_to_luma(rgb: &[u8], bytes_per_pixel: usize, out: &mut [u8], c: &Coefficients) {
+    #[cfg(feature = "simd")]
+    let (rgb, out) = simd::rgb_row_to_luma(rgb, bytes_per_pixel, out, c);
+
+    let [y_r, y_g, y_b] = c.y;
+    for (pixel, y) in rgb.chunks_exact(bytes_per_pixel).zip(out) {
+        let (r, g, b) = (
+            f32::from(pixel[0]),
+            f32::from(pixel[1]),
+            f32::from(pixel[2]),
+        );
+        *y = to_sample(16.0 + y_r * r + y_g * g + y_b * b);
+    }
+}
+
+/// Convert one row of Y samples and their horizontally subsampled U and V
+/// samples to packed RGB with `bytes_per_pixel` bytes per pixel
+fn yuv_row_to_rgb(
+    luma: &[u8],
+    u: &[u8],
+    v: &[u8],
+    bytes_per_pixel: usize,
+    out: &mut [u8],
+    c: &Coefficients,
+) {
+    #[cfg(feature = "simd")]
+    let (luma, u, v, out) = simd::yuv_row_to_rgb(luma, u, v, bytes_per_pixel, out, c);
+
+    for (x, (&y, rgb)) in luma
+        .iter()
+        .zip(out.chunks_exact_mut(bytes_per_pixel))
+        .enumerate()
+    {
+        let y = (f32::from(y) - 16.0) * c.y_scale;
+        let cb = f32::from(u[x / 2]) - 128.0;
+        let cr = f32::from(v[x / 2]) - 128.0;
+        rgb[0] = to_sample(y + c.r_cr * cr);
+        rgb[1] = to_sample(y + c.g_cb * cb + c.g_cr * cr);
+        rgb[2] = to_sample(y + c.b_cb * cb);
+    }
+}
+
+/// Eight-pixel versions of the row conversions
+///
+/// Each function converts as many whole groups of eight pixels as fit in
+/// the row and returns the remaining inputs and outputs for the scalar
+/// code. Results match the scalar conversion exactly.
+#[cfg(feature = "simd")]
+mod simd {
+    use super::Coefficients;
+    use wide::f32x8;
+
+    const LANES: usize = 8;
+
+    fn to_samples(value: f32x8) -> [u8; LANES] {
+        let value = (value + f32x8::splat(0.5))
+            .floor()
+            .max(f32x8::splat(0.0))
+            .min(f32x8::splat(255.0));
+        value.to_array().map(|sample| sample as u8)
+    }
+
+    fn lanes(samples: impl Fn(usize) -> u8) -> f32x8 {
+        f32x8::new(std::array::from_fn(|lane| f32::from(samples(lane))))
+    }
+
+    pub(super) fn rgb_row_to_luma<'a, 'b>(
+        rgb: &'a [u8],
+        bytes_per_pixel: usize,
+        out: &'b mut [u8],
+        c: &Coefficients,
+    ) -> (&'a [u8], &'b mut [u8]) {
+        let groups = out.len() / LANES;
+        let (rgb, rgb_rest) = rgb.split_at(groups * LANES * bytes_per_pixel);
+        let (out, out_rest) = out.split_at_mut(groups * LANES);
+
+        let [y_r, y_g, y_b] = c.y.map(f32x8::splat);
+        for (pixels, y) in rgb
+            .chunks_exact(LANES * bytes_per_pixel)
+            .zip(out.chunks_exact_mut(LANES))
+        {
+            let channel = |offset: usize| lanes(|lane| pixels[lane * bytes_per_pixel + offset]);
+            let luma = f32x8::splat(16.0) + y_r * channel(0) + y_g * channel(1) + y_b * channel(2);
+            y.copy_from_slice(&to_samples(luma));
+        }
+        (rgb_rest, out_rest)
+    }
+
+    pub(super) fn yuv_row_to_rgb<'a, 'b>(
+        luma: &'a [u8],
+        u: &'a [u8],
+        v: &'a [u8],
+        bytes_per_pixel: usize,
+        out: &'b mut [u8],
+        c: &Coefficients,
+    ) -> (&'a [u8], &'a [u8], &'a [u8], &'b mut [u8]) {
+        let groups = luma.len() / LANES;
+        let (luma, luma_rest) = luma.split_at(groups * LANES);
+        let (out, out_rest) = out.split_at_mut(groups * LANES * bytes_per_pixel);
+
+        for (group, (y, rgb)) in luma
+            .chunks_exact(LANES)
+            .zip(out.chunks_exact_mut(LANES * bytes_per_pixel))
+            .enumerate()
+        {
+            let chroma = group * LANES / 2;
+            let y = (lanes(|lane| y[lane]) - f32x8::splat(16.0)) * f32x8::splat(c.y_scale);
+            let cb = lanes(|lane| u[chroma + lane / 2]) - f32x8::splat(128.0);
+            let cr = lanes(|lane| v[chroma + lane / 2]) - f32x8::splat(128.0);
+
+            let r = to_samples(y + f32x8::splat(c.r_cr) * cr);
+            let g = to_samples(y + f32x8::splat(c.g_cb) * cb + f32x8::splat(c.g_cr) * cr);
+            let b = to_samples(y + f32x8::splat(c.b_cb) * cb);
+            for (lane, pixel) in rgb.chunks_exact_mut(bytes_per_pixel).enumerate() {
+                pixel[..3].copy_from_slice(&[r[lane], g[lane], b[lane]]);
+            }
+        }
+
+        let chroma = groups * LANES / 2;
+        (luma_rest, &u[chroma..], &v[chroma..], out_rest)
+    }
+}
@@ -0,0 +1,54 @@
+//! Runtime loading of optional system libraries
+//!
+//! Platform libraries such as `libva`, `libxcb`, `libudev` and `libasound`
+//! are opened with `dlopen` rather than linked, so the components using
+//! them build and run on systems without them; features needing a missing
+//! library simply fail there.
+
+#![allow(unsafe_code)]
+
+use libc::c_void;
+use std::ffi::CStr;
+use thiserror::Error;
+
+/// A function a loaded library does not export
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("Library has no symbol {0}")]
+pub struct MissingSymbol(pub String);
+
+/// Handle to a library opened with `dlopen`, closed on drop
+pub struct Library(*mut c_void);
+
+// SAFETY: dlopen handles may be used and closed from any thread
+unsafe impl Send for Library {}
+
+impl Library {
+    /// Open the library `name`, or `None` if it is not installed
+    pub fn open(name: &CStr) -> Option<Self> {
+        // SAFETY: `name` is a valid NUL-terminated string
+        let handle = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        (!handle.is_null()).then(|| Self(handle))
+    }
+
+    /// Look up a function symbol
+    ///
+    /// # Safety
+    ///
+    /// `T` must be a function pointer type matching the symbol's C signature.
+    pub unsafe fn symbol<T: Copy>(&self, name: &CStr) -> Result<T, MissingSymbol> {
+        let ptr = libc::dlsym(self.0, name.as_ptr());
+        if ptr.is_null() {
+            return Err(MissingSymbol(name.to_string_lossy().into_owned()));
+        }
+        Ok(std::mem::transmute_copy(&ptr))
+    }
+}
+
+impl Drop for Library {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from a successful dlopen
+        unsafe {
+            libc::dlclose(self.0);
+        }
+    }
+}
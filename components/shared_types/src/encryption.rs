@@ -0,0 +1,78 @@
+//! Common Encryption (CENC) metadata of protected samples
+//!
+//! Demuxers attach an [`EncryptionInfo`] to the packets of encrypted
+//! tracks, and a decryption stage uses it to decrypt them before they
+//! reach the decoder.
+
+use serde::{Deserialize, Serialize};
+
+/// Common Encryption protection scheme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EncryptionScheme {
+    /// `cenc`: AES-128-CTR
+    #[default]
+    Cenc,
+    /// `cbcs`: AES-128-CBC with pattern encryption
+    Cbcs,
+}
+
+/// Pattern of encrypted and clear 16-byte blocks
+///
+/// Each encrypted range starts with `crypt_byte_block` encrypted blocks
+/// followed by `skip_byte_block` clear ones, repeating to its end. A
+/// trailing partial block is always clear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionPattern {
+    /// Encrypted blocks at the start of each repetition
+    pub crypt_byte_block: u8,
+
+    /// Clear blocks following them
+    pub skip_byte_block: u8,
+}
+
+/// One clear range followed by one encrypted range of a sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subsample {
+    /// Number of unencrypted bytes
+    pub clear_bytes: u32,
+
+    /// Number of encrypted bytes following the clear bytes
+    pub encrypted_bytes: u32,
+}
+
+/// How one sample of an encrypted track is protected
+///
+/// Combines the track's `tenc` defaults with the sample's entry in `senc`
+/// or its auxiliary information. With no subsamples the whole sample is
+/// encrypted.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{EncryptionInfo, EncryptionScheme, Subsample};
+///
+/// let info = EncryptionInfo {
+///     key_id: vec![0x10; 16],
+///     iv: vec![0; 8],
+///     subsamples: vec![Subsample { clear_bytes: 5, encrypted_bytes: 16 }],
+///     ..Default::default()
+/// };
+/// assert_eq!(info.scheme, EncryptionScheme::Cenc);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptionInfo {
+    /// ID of the key the sample is encrypted with
+    pub key_id: Vec<u8>,
+
+    /// Initialization vector, 8 or 16 bytes
+    pub iv: Vec<u8>,
+
+    /// Clear and encrypted byte ranges, in sample order
+    pub subsamples: Vec<Subsample>,
+
+    /// Protection scheme of the sample's track
+    pub scheme: EncryptionScheme,
+
+    /// Blocks encrypted and skipped in each encrypted range, for `cbcs`
+    pub pattern: Option<EncryptionPattern>,
+}
@@ -2,6 +2,7 @@
 //!
 //! This module defines all error types that can occur during media processing.
 
+use std::time::Duration;
 use thiserror::Error;
 
 /// Session state for state transition errors
@@ -21,6 +22,8 @@ pub enum SessionState {
     Seeking,
     /// Session has ended
     Ended,
+    /// Session restarted playback after reaching the end
+    Looping,
     /// Session encountered an error
     Error,
 }
@@ -106,6 +109,21 @@ pub enum MediaError {
     /// Resource exhausted (e.g., max sessions reached)
     #[error("Resource exhausted: {0}")]
     ResourceExhausted(String),
+
+    /// Data is not available yet (e.g., no frame decoded); retry later
+    #[error("Would block: {0}")]
+    WouldBlock(String),
+
+    /// A running operation produced no data in time
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    /// Data is available but not due yet (e.g., a frame ahead of the clock)
+    #[error("Not ready, retry in {wait_for:?}")]
+    NotReady {
+        /// How long to wait before retrying
+        wait_for: Duration,
+    },
 }
 
 /// Result type for media operations
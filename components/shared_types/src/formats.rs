@@ -72,6 +72,26 @@ impl PixelFormat {
     }
 }
 
+/// YUV color matrix used to convert between YUV and RGB
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::ColorSpace;
+///
+/// let color_space = ColorSpace::BT709; // High definition video
+/// assert_ne!(color_space, ColorSpace::default());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorSpace {
+    /// ITU-R BT.601, used for standard definition video
+    #[default]
+    BT601,
+
+    /// ITU-R BT.709, used for high definition video
+    BT709,
+}
+
 /// Audio sample format
 ///
 /// # Examples
@@ -10,11 +10,18 @@
 //! The shared_types component is a foundational library that defines:
 //!
 //! - **Codec Types**: [`VideoCodec`], [`AudioCodec`] and their configuration
-//! - **Formats**: [`PixelFormat`], [`AudioFormat`] for media data
+//! - **Codec Configuration**: [`AvcDecoderConfig`], [`AudioSpecificConfig`], [`ColorInfo`],
+//!   [`GaplessInfo`]
+//! - **Formats**: [`PixelFormat`], [`ColorSpace`], [`AudioFormat`] for media data
+//! - **Conversion**: [`PixelFormatConverter`] between pixel formats
+//! - **Transforms**: [`VideoFrame::crop`] and [`VideoFrame::scale`] for YUV 4:2:0 frames
 //! - **Media Data**: [`VideoFrame`], [`AudioBuffer`], [`MediaSource`]
+//! - **Media Streams**: [`MediaStream`], [`MediaStreamTrack`] and the [`TrackSource`] feeding it
+//! - **Channel Layouts**: [`ChannelMap`], [`Channel`] and [`downmix_to_stereo`]
 //! - **Errors**: [`MediaError`] for error handling
 //! - **Sessions**: [`SessionId`] for session management
-//! - **Traits**: [`MediaEngine`], [`Demuxer`], [`VideoDecoder`], [`AudioDecoder`]
+//! - **Traits**: [`MediaEngine`], [`Demuxer`], [`VideoDecoder`], [`AudioDecoder`], [`AudioSink`]
+//! - **System Libraries**: `Library` for optional libraries loaded at runtime (Unix only)
 //!
 //! # Examples
 //!
@@ -41,6 +48,7 @@
 //!     height: 1080,
 //!     format: PixelFormat::YUV420,
 //!     data: vec![0u8; 1920 * 1080],
+//!     strides: None,
 //!     timestamp: Duration::from_secs(1),
 //!     duration: Some(Duration::from_millis(33)),
 //!     metadata: FrameMetadata::default(),
@@ -51,17 +59,32 @@
 #![deny(unsafe_code)]
 
 // Module declarations
+mod channel_map;
+mod codec_config;
 mod codecs;
+mod conversion;
+#[cfg(unix)]
+mod dylib;
+mod encryption;
 mod errors;
 mod formats;
 mod media;
+mod media_stream;
 mod session;
 mod traits;
+mod transform;
 
 // Re-export public API
+pub use channel_map::*;
+pub use codec_config::*;
 pub use codecs::*;
+pub use conversion::*;
+#[cfg(unix)]
+pub use dylib::*;
+pub use encryption::*;
 pub use errors::*;
 pub use formats::*;
 pub use media::*;
+pub use media_stream::*;
 pub use session::*;
 pub use traits::*;
@@ -3,7 +3,11 @@
 //! This module provides data structures for representing video frames,
 //! audio buffers, and media sources.
 
-use crate::formats::{AudioFormat, PixelFormat};
+use crate::channel_map::ChannelMap;
+use crate::conversion::Layout;
+use crate::formats::{AudioFormat, ColorSpace, PixelFormat};
+use crate::media_stream::MediaStreamTrack;
+use std::borrow::Cow;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -19,10 +23,19 @@ pub struct FrameMetadata {
     pub dts: Option<i64>,
     /// Frame sequence number
     pub sequence: Option<u64>,
+    /// Color matrix of the frame's YUV samples, used when converting
+    /// to or from RGB
+    pub color_space: ColorSpace,
 }
 
 /// Decoded video frame data
 ///
+/// The planes of planar formats follow each other in `data`. Without
+/// `strides`, their rows are tightly packed; decoders and hardware that pad
+/// rows set `strides` to the bytes per row of each plane, which
+/// [`plane`](Self::plane) and [`plane_stride`](Self::plane_stride) take
+/// into account.
+///
 /// # Examples
 ///
 /// ```
@@ -34,6 +47,7 @@ pub struct FrameMetadata {
 ///     height: 1080,
 ///     format: PixelFormat::YUV420,
 ///     data: vec![0u8; 1920 * 1080],
+///     strides: None,
 ///     timestamp: Duration::from_secs(1),
 ///     duration: Some(Duration::from_millis(33)),
 ///     metadata: FrameMetadata::default(),
@@ -49,6 +63,8 @@ pub struct VideoFrame {
     pub format: PixelFormat,
     /// Raw pixel data
     pub data: Vec<u8>,
+    /// Bytes per row of each plane, when rows are padded
+    pub strides: Option<[usize; 3]>,
     /// Presentation timestamp
     pub timestamp: Duration,
     /// Frame duration (time until next frame)
@@ -71,6 +87,7 @@ impl VideoFrame {
             height,
             format,
             data,
+            strides: None,
             timestamp,
             duration: None,
             metadata: FrameMetadata::default(),
@@ -81,6 +98,101 @@ impl VideoFrame {
     pub fn data_size(&self) -> usize {
         self.data.len()
     }
+
+    /// Returns the bytes per row of plane `index`
+    ///
+    /// Planes are numbered in the order they are stored: Y, U and V for
+    /// planar YUV, Y and interleaved UV for NV12, and a single plane for
+    /// packed RGB. Without `strides`, rows are as wide as the plane, with
+    /// chroma planes of odd-sized frames rounded up. Returns 0 for a plane
+    /// the format does not have.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// let mut frame = VideoFrame::new(
+    ///     6,
+    ///     4,
+    ///     PixelFormat::YUV420,
+    ///     vec![0; 6 * 4 * 3 / 2],
+    ///     Duration::ZERO,
+    /// );
+    /// assert_eq!(frame.plane_stride(0), 6);
+    /// assert_eq!(frame.plane_stride(1), 3);
+    ///
+    /// frame.strides = Some([8, 4, 4]);
+    /// assert_eq!(frame.plane_stride(1), 4);
+    /// ```
+    pub fn plane_stride(&self, index: usize) -> usize {
+        match self.plane_sizes().get(index) {
+            Some(&(row_bytes, _)) if row_bytes > 0 => {
+                self.strides.map_or(row_bytes, |strides| strides[index])
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns the data of plane `index`, including any row padding
+    ///
+    /// The plane is [`plane_stride`](Self::plane_stride) bytes per row,
+    /// and is cut short if the frame data ends early. Returns an empty
+    /// slice for a plane the format does not have.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// // A 2x2 frame: four Y samples, then one U and one V sample
+    /// let frame = VideoFrame::new(
+    ///     2,
+    ///     2,
+    ///     PixelFormat::YUV420,
+    ///     vec![16, 16, 16, 16, 100, 200],
+    ///     Duration::ZERO,
+    /// );
+    /// assert_eq!(frame.plane(0), [16, 16, 16, 16]);
+    /// assert_eq!(frame.plane(1), [100]);
+    /// assert_eq!(frame.plane(2), [200]);
+    /// assert!(frame.plane(3).is_empty());
+    /// ```
+    pub fn plane(&self, index: usize) -> &[u8] {
+        let sizes = self.plane_sizes();
+        let Some(&(_, rows)) = sizes.get(index) else {
+            return &[];
+        };
+        let offset: usize = (0..index)
+            .map(|plane| self.plane_stride(plane) * sizes[plane].1)
+            .sum();
+        let start = offset.min(self.data.len());
+        let end = (offset + self.plane_stride(index) * rows).min(self.data.len());
+        &self.data[start..end]
+    }
+
+    /// Bytes per row and rows of each plane without padding
+    fn plane_sizes(&self) -> [(usize, usize); 3] {
+        Layout::new(self.width as usize, self.height as usize).planes(self.format)
+    }
+
+    /// Frame data with the row padding of `strides` removed
+    pub(crate) fn packed_data(&self) -> Cow<'_, [u8]> {
+        if self.strides.is_none() {
+            return Cow::Borrowed(&self.data);
+        }
+
+        let mut data = Vec::with_capacity(self.data.len());
+        for (index, (row_bytes, rows)) in self.plane_sizes().into_iter().enumerate() {
+            let stride = self.plane_stride(index).max(1);
+            for row in self.plane(index).chunks(stride).take(rows) {
+                data.extend_from_slice(&row[..row_bytes.min(row.len())]);
+            }
+        }
+        Cow::Owned(data)
+    }
 }
 
 /// Decoded audio sample buffer
@@ -98,6 +210,7 @@ impl VideoFrame {
 ///     samples: vec![0.0f32; 4800],
 ///     timestamp: Duration::from_millis(100),
 ///     duration: Duration::from_millis(100),
+///     channel_map: None,
 /// };
 /// ```
 #[derive(Debug, Clone, PartialEq)]
@@ -114,6 +227,8 @@ pub struct AudioBuffer {
     pub timestamp: Duration,
     /// Buffer duration
     pub duration: Duration,
+    /// Speaker position of each channel, when known
+    pub channel_map: Option<ChannelMap>,
 }
 
 impl AudioBuffer {
@@ -134,7 +249,89 @@ impl AudioBuffer {
             samples,
             timestamp,
             duration,
+            channel_map: None,
+        }
+    }
+
+    /// Creates an audio buffer from planar samples, one plane per channel
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of planes differs from `channels` or the planes
+    /// differ in length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{AudioBuffer, AudioFormat};
+    /// use std::time::Duration;
+    ///
+    /// let buffer = AudioBuffer::from_planar(
+    ///     vec![vec![0.1, 0.2], vec![0.3, 0.4]],
+    ///     AudioFormat::F32LE,
+    ///     48000,
+    ///     2,
+    ///     Duration::ZERO,
+    /// );
+    /// assert_eq!(buffer.samples, vec![0.1, 0.3, 0.2, 0.4]);
+    /// ```
+    pub fn from_planar(
+        planes: Vec<Vec<f32>>,
+        format: AudioFormat,
+        sample_rate: u32,
+        channels: u8,
+        timestamp: Duration,
+    ) -> Self {
+        assert_eq!(
+            planes.len(),
+            channels as usize,
+            "one plane per channel is required"
+        );
+        let frames = planes.first().map_or(0, Vec::len);
+        assert!(
+            planes.iter().all(|plane| plane.len() == frames),
+            "planes must have the same length"
+        );
+
+        let mut samples = Vec::with_capacity(frames * planes.len());
+        for frame in 0..frames {
+            samples.extend(planes.iter().map(|plane| plane[frame]));
+        }
+        Self::new(format, sample_rate, channels, samples, timestamp)
+    }
+
+    /// Returns the samples in planar layout, one plane per channel
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{AudioBuffer, AudioFormat};
+    /// use std::time::Duration;
+    ///
+    /// let buffer = AudioBuffer::new(
+    ///     AudioFormat::F32LE,
+    ///     48000,
+    ///     2,
+    ///     vec![0.1, 0.3, 0.2, 0.4],
+    ///     Duration::ZERO,
+    /// );
+    /// assert_eq!(buffer.to_planar(), vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+    /// ```
+    pub fn to_planar(&self) -> Vec<Vec<f32>> {
+        let channels = self.channels as usize;
+        let mut planes = vec![Vec::with_capacity(self.samples.len() / channels.max(1)); channels];
+        for frame in self.samples.chunks_exact(channels.max(1)) {
+            for (plane, sample) in planes.iter_mut().zip(frame) {
+                plane.push(*sample);
+            }
         }
+        planes
+    }
+
+    /// Attaches the speaker position of each channel
+    pub fn with_channel_map(mut self, channel_map: ChannelMap) -> Self {
+        self.channel_map = Some(channel_map);
+        self
     }
 
     /// Returns the number of samples per channel
@@ -221,6 +418,132 @@ pub struct AudioConstraints {
     pub channels: Option<u8>,
 }
 
+/// Capture-side audio processing configuration
+///
+/// Selects which stages of the microphone processing chain are enabled.
+/// Stages run in the order noise suppression, automatic gain control,
+/// echo cancellation.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::AudioProcessingConfig;
+///
+/// let config = AudioProcessingConfig::new()
+///     .with_noise_suppression(true)
+///     .with_auto_gain_control(true);
+/// assert!(!config.echo_cancellation);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioProcessingConfig {
+    /// Enable noise suppression
+    pub noise_suppression: bool,
+    /// Enable automatic gain control
+    pub auto_gain_control: bool,
+    /// Enable acoustic echo cancellation
+    pub echo_cancellation: bool,
+    /// AGC target RMS level (linear, 0.0 to 1.0)
+    pub agc_target_rms: f32,
+    /// AGC gain reduction rate in dB/second
+    pub agc_attack_rate: f32,
+    /// AGC gain increase rate in dB/second
+    pub agc_release_rate: f32,
+    /// Echo canceller adaptive filter length in taps
+    pub echo_filter_length: usize,
+}
+
+impl Default for AudioProcessingConfig {
+    fn default() -> Self {
+        Self {
+            noise_suppression: false,
+            auto_gain_control: false,
+            echo_cancellation: false,
+            agc_target_rms: 0.1,
+            agc_attack_rate: 60.0,
+            agc_release_rate: 10.0,
+            echo_filter_length: 256,
+        }
+    }
+}
+
+impl AudioProcessingConfig {
+    /// Creates a new configuration with all stages disabled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables or disables noise suppression
+    pub fn with_noise_suppression(mut self, enabled: bool) -> Self {
+        self.noise_suppression = enabled;
+        self
+    }
+
+    /// Enables or disables automatic gain control
+    pub fn with_auto_gain_control(mut self, enabled: bool) -> Self {
+        self.auto_gain_control = enabled;
+        self
+    }
+
+    /// Enables or disables echo cancellation
+    pub fn with_echo_cancellation(mut self, enabled: bool) -> Self {
+        self.echo_cancellation = enabled;
+        self
+    }
+
+    /// Sets the AGC target RMS level
+    pub fn with_agc_target_rms(mut self, target_rms: f32) -> Self {
+        self.agc_target_rms = target_rms;
+        self
+    }
+
+    /// Returns whether any processing stage is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.noise_suppression || self.auto_gain_control || self.echo_cancellation
+    }
+}
+
+/// Level of captured audio, as shown by input level meters
+///
+/// Levels are in dBFS, relative to a full-scale sample of 1.0, and floored
+/// at [`AudioLevel::MIN_DBFS`]. The default is silence.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::AudioLevel;
+///
+/// let level = AudioLevel::default();
+/// assert_eq!(level.rms_dbfs, AudioLevel::MIN_DBFS);
+/// assert!(!level.voice_active);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioLevel {
+    /// Smoothed RMS level in dBFS
+    pub rms_dbfs: f32,
+    /// Smoothed peak level in dBFS
+    pub peak_dbfs: f32,
+    /// Whether voice is detected in the audio
+    pub voice_active: bool,
+}
+
+impl AudioLevel {
+    /// Lowest level reported, for silence
+    pub const MIN_DBFS: f32 = -100.0;
+
+    /// Level of silence
+    pub const SILENCE: AudioLevel = AudioLevel {
+        rms_dbfs: Self::MIN_DBFS,
+        peak_dbfs: Self::MIN_DBFS,
+        voice_active: false,
+    };
+}
+
+impl Default for AudioLevel {
+    fn default() -> Self {
+        Self::SILENCE
+    }
+}
+
 /// Source of media data
 ///
 /// # Examples
@@ -277,6 +600,9 @@ pub enum MediaSource {
         device: CaptureDevice,
         /// Media constraints
         constraints: MediaConstraints,
+        /// Track already capturing from the device, such as a camera
+        /// preview; its media is played as it arrives
+        track: Option<MediaStreamTrack>,
     },
 }
 
@@ -313,8 +639,36 @@ impl Default for MediaElementAttributes {
     }
 }
 
+impl MediaElementAttributes {
+    /// Returns the loop mode requested by the `loop` attribute
+    pub fn loop_mode(&self) -> LoopMode {
+        if self.loop_playback {
+            LoopMode::All
+        } else {
+            LoopMode::None
+        }
+    }
+}
+
+/// What playback does when it reaches the end of the media
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoopMode {
+    /// Stop at the end
+    #[default]
+    None,
+    /// Restart from the beginning
+    All,
+    /// Repeat the section between two positions
+    AB {
+        /// Loop start (A point)
+        start: Duration,
+        /// Loop end (B point)
+        end: Duration,
+    },
+}
+
 /// Preload strategy for media
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum PreloadStrategy {
     /// No preloading
     None,
@@ -325,6 +679,12 @@ pub enum PreloadStrategy {
     Auto,
 }
 
+/// Slowest supported playback rate
+pub const MIN_PLAYBACK_RATE: f32 = 0.25;
+
+/// Fastest supported playback rate
+pub const MAX_PLAYBACK_RATE: f32 = 4.0;
+
 /// Playback control commands
 #[derive(Debug, Clone)]
 pub enum PlaybackCommand {
@@ -334,7 +694,7 @@ pub enum PlaybackCommand {
     Pause,
     /// Seek to position (in milliseconds)
     Seek(u64),
-    /// Set playback rate
+    /// Set playback rate (0.25 to 4.0)
     SetRate(f32),
     /// Set volume (0.0 to 1.0)
     SetVolume(f32),
@@ -0,0 +1,643 @@
+//! MediaStream and MediaStreamTrack
+//!
+//! A track carries live video frames or audio buffers from one source,
+//! such as a camera, microphone, screen or remote RTP stream, to any number
+//! of subscribers. A stream groups tracks, as getUserMedia and WebRTC hand
+//! them out.
+//!
+//! Whatever produces the media feeds a track through its [`TrackSource`]:
+//!
+//! ```text
+//! Capture device / RTP receiver ─> TrackSource ─> MediaStreamTrack ─> TrackSubscription
+//!                                             └─> cloned track     ─> TrackSubscription
+//! ```
+
+use crate::formats::PixelFormat;
+use crate::media::{AudioBuffer, VideoFrame};
+use std::fmt;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, Weak};
+use std::task::Poll;
+use tokio::sync::{broadcast, watch};
+use uuid::Uuid;
+
+/// Samples held for a subscriber that has not received them yet
+///
+/// Subscribers that fall further behind skip the oldest samples, as live
+/// media has no use for them.
+const SUBSCRIPTION_CAPACITY: usize = 16;
+
+/// Luma of black in limited-range YUV
+const BLACK_LUMA: u8 = 16;
+
+/// Chroma of grey, and so of black, in YUV
+const NEUTRAL_CHROMA: u8 = 128;
+
+/// Kind of media a track carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaStreamTrackKind {
+    /// Audio buffers
+    Audio,
+    /// Video frames
+    Video,
+}
+
+/// Whether a track still delivers media
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaStreamTrackState {
+    /// The track delivers media from its source
+    Live,
+    /// The track was stopped or its source ended; it delivers nothing more
+    Ended,
+}
+
+/// Media delivered by a track
+#[derive(Debug, Clone, PartialEq)]
+pub enum MediaSample {
+    /// A frame of a video track
+    Video(VideoFrame),
+    /// A buffer of an audio track
+    Audio(AudioBuffer),
+}
+
+impl MediaSample {
+    /// Returns the kind of track the sample belongs to
+    pub fn kind(&self) -> MediaStreamTrackKind {
+        match self {
+            MediaSample::Video(_) => MediaStreamTrackKind::Video,
+            MediaSample::Audio(_) => MediaStreamTrackKind::Audio,
+        }
+    }
+
+    /// A black frame or silence of the same format, size and timing
+    fn blank(&self) -> MediaSample {
+        match self {
+            MediaSample::Video(frame) => MediaSample::Video(VideoFrame {
+                data: black(frame),
+                ..frame.clone()
+            }),
+            MediaSample::Audio(buffer) => MediaSample::Audio(AudioBuffer {
+                samples: vec![0.0; buffer.samples.len()],
+                ..buffer.clone()
+            }),
+        }
+    }
+}
+
+/// Pixel data of a black frame the size of `frame`'s
+fn black(frame: &VideoFrame) -> Vec<u8> {
+    let len = frame.data.len();
+    match frame.format {
+        PixelFormat::YUV420 | PixelFormat::YUV422 | PixelFormat::YUV444 | PixelFormat::NV12 => {
+            let luma = frame.plane(0).len();
+            let mut data = vec![NEUTRAL_CHROMA; len];
+            data[..luma].fill(BLACK_LUMA);
+            data
+        }
+        PixelFormat::RGB24 => vec![0; len],
+        PixelFormat::RGBA32 => {
+            let mut data = vec![0; len];
+            data.iter_mut()
+                .skip(3)
+                .step_by(4)
+                .for_each(|alpha| *alpha = 0xFF);
+            data
+        }
+    }
+}
+
+/// Lock a mutex, recovering the data if a holder panicked
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// State shared by a source and the tracks it feeds
+#[derive(Debug)]
+struct SourceShared {
+    kind: MediaStreamTrackKind,
+    label: String,
+    muted: AtomicBool,
+    /// Set once the source has ended, ending every track
+    ended: AtomicBool,
+    /// Tracks fed by the source, dropped ones included until the next send
+    tracks: Mutex<Vec<Weak<TrackShared>>>,
+}
+
+/// State of one track, shared by its handles
+struct TrackShared {
+    id: String,
+    enabled: AtomicBool,
+    /// Sender of the track's subscriptions, dropped when the track ends
+    sender: Mutex<Option<broadcast::Sender<MediaSample>>>,
+    state: watch::Sender<MediaStreamTrackState>,
+    source: Arc<SourceShared>,
+}
+
+impl TrackShared {
+    /// Create a track of `source` and register it with the source
+    fn create(source: Arc<SourceShared>, enabled: bool, live: bool) -> Arc<Self> {
+        let live = live && !source.ended.load(Ordering::Acquire);
+        let state = if live {
+            MediaStreamTrackState::Live
+        } else {
+            MediaStreamTrackState::Ended
+        };
+        let track = Arc::new(Self {
+            id: Uuid::new_v4().to_string(),
+            enabled: AtomicBool::new(enabled),
+            sender: Mutex::new(live.then(|| broadcast::channel(SUBSCRIPTION_CAPACITY).0)),
+            state: watch::Sender::new(state),
+            source: Arc::clone(&source),
+        });
+        lock(&source.tracks).push(Arc::downgrade(&track));
+        track
+    }
+
+    /// End the track, closing its subscriptions
+    fn end(&self) {
+        if lock(&self.sender).take().is_some() {
+            self.state.send_replace(MediaStreamTrackState::Ended);
+        }
+    }
+}
+
+/// Live audio or video track, as in the Media Capture and Streams API
+///
+/// A track delivers the media of its source to every
+/// [`subscription`](MediaStreamTrack::subscribe) until it is
+/// [stopped](MediaStreamTrack::stop) or the source ends. While the track is
+/// disabled, subscribers get black frames or silence in place of the media,
+/// so that the timing of the track is kept.
+///
+/// `MediaStreamTrack` is a handle: cloning it refers to the same track.
+/// [`MediaStreamTrack::clone_track`] is the API's `clone()`, creating an
+/// independent track of the same source.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{
+///     MediaSample, MediaStreamTrack, MediaStreamTrackKind, MediaStreamTrackState, PixelFormat,
+///     VideoFrame,
+/// };
+/// use std::time::Duration;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+/// let mut frames = track.subscribe();
+///
+/// let frame = VideoFrame::new(2, 2, PixelFormat::RGBA32, vec![255; 16], Duration::ZERO);
+/// source.send(MediaSample::Video(frame.clone()));
+/// assert_eq!(frames.next().await, Some(MediaSample::Video(frame)));
+///
+/// track.stop();
+/// assert_eq!(track.ready_state(), MediaStreamTrackState::Ended);
+/// assert_eq!(frames.next().await, None);
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct MediaStreamTrack {
+    shared: Arc<TrackShared>,
+}
+
+impl MediaStreamTrack {
+    /// Creates a live track and the source feeding it
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Whether the track carries audio or video
+    /// * `label` - Name of the source, such as the device label
+    pub fn new(kind: MediaStreamTrackKind, label: impl Into<String>) -> (Self, TrackSource) {
+        let source = Arc::new(SourceShared {
+            kind,
+            label: label.into(),
+            muted: AtomicBool::new(false),
+            ended: AtomicBool::new(false),
+            tracks: Mutex::new(Vec::new()),
+        });
+        let track = Self {
+            shared: TrackShared::create(Arc::clone(&source), true, true),
+        };
+        (track, TrackSource { shared: source })
+    }
+
+    /// Returns the unique identifier of the track
+    pub fn id(&self) -> &str {
+        &self.shared.id
+    }
+
+    /// Returns whether the track carries audio or video
+    pub fn kind(&self) -> MediaStreamTrackKind {
+        self.shared.source.kind
+    }
+
+    /// Returns the name of the track's source
+    pub fn label(&self) -> &str {
+        &self.shared.source.label
+    }
+
+    /// Returns whether the track delivers its source's media, rather than
+    /// black frames or silence
+    pub fn enabled(&self) -> bool {
+        self.shared.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Sets whether the track delivers its source's media
+    ///
+    /// A disabled track keeps delivering, black frames or silence in place
+    /// of each frame or buffer of its source.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.shared.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns whether the source is temporarily unable to provide media
+    ///
+    /// Unlike [`enabled`](MediaStreamTrack::enabled), this is set by the
+    /// source, such as a remote peer pausing its track.
+    pub fn muted(&self) -> bool {
+        self.shared.source.muted.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether the track is live or has ended
+    pub fn ready_state(&self) -> MediaStreamTrackState {
+        *self.shared.state.borrow()
+    }
+
+    /// Subscribes to the media of the track
+    ///
+    /// The subscription receives the media sent after it was created. It
+    /// ends once the track has ended, straight away for an ended track.
+    pub fn subscribe(&self) -> TrackSubscription {
+        TrackSubscription {
+            receiver: lock(&self.shared.sender)
+                .as_ref()
+                .map(broadcast::Sender::subscribe),
+        }
+    }
+
+    /// Creates a new track of the same source, as the API's `clone()`
+    ///
+    /// The new track has its own identifier and starts with this track's
+    /// enabled flag and ready state; stopping or disabling either track
+    /// afterwards leaves the other alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{MediaStreamTrack, MediaStreamTrackKind, MediaStreamTrackState};
+    ///
+    /// let (track, _source) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Microphone");
+    /// let copy = track.clone_track();
+    /// assert_ne!(copy.id(), track.id());
+    ///
+    /// track.stop();
+    /// assert_eq!(copy.ready_state(), MediaStreamTrackState::Live);
+    /// ```
+    pub fn clone_track(&self) -> MediaStreamTrack {
+        let live = self.ready_state() == MediaStreamTrackState::Live;
+        Self {
+            shared: TrackShared::create(Arc::clone(&self.shared.source), self.enabled(), live),
+        }
+    }
+
+    /// Stops the track
+    ///
+    /// The track ends and its subscriptions end once they have received
+    /// the media already sent. The source keeps feeding its other tracks.
+    pub fn stop(&self) {
+        self.shared.end();
+    }
+
+    /// Waits until the track has ended
+    pub async fn ended(&self) {
+        let mut state = self.shared.state.subscribe();
+        // The sender lives as long as the track
+        let _ = state
+            .wait_for(|state| *state == MediaStreamTrackState::Ended)
+            .await;
+    }
+}
+
+impl PartialEq for MediaStreamTrack {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shared, &other.shared)
+    }
+}
+
+impl Eq for MediaStreamTrack {}
+
+impl fmt::Debug for MediaStreamTrack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MediaStreamTrack")
+            .field("id", &self.id())
+            .field("kind", &self.kind())
+            .field("label", &self.label())
+            .field("enabled", &self.enabled())
+            .field("ready_state", &self.ready_state())
+            .finish()
+    }
+}
+
+/// Media of a track, received as it is sent
+///
+/// Returned by [`MediaStreamTrack::subscribe`].
+#[derive(Debug)]
+pub struct TrackSubscription {
+    /// `None` for a subscription to an ended track
+    receiver: Option<broadcast::Receiver<MediaSample>>,
+}
+
+impl TrackSubscription {
+    /// Waits for the next frame or buffer of the track
+    ///
+    /// Returns `None` once the track has ended. A subscriber that falls
+    /// behind skips the oldest media rather than receive it late.
+    pub async fn next(&mut self) -> Option<MediaSample> {
+        let receiver = self.receiver.as_mut()?;
+        loop {
+            match receiver.recv().await {
+                Ok(sample) => return Some(sample),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Producer side of a track
+///
+/// Whatever captures or receives the media sends it through the source to
+/// every live track of the source: the track it was created with and that
+/// track's clones. Dropping the source ends its tracks.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{
+///     AudioBuffer, AudioFormat, MediaSample, MediaStreamTrack, MediaStreamTrackKind,
+/// };
+/// use std::time::Duration;
+///
+/// let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Microphone");
+///
+/// let buffer = AudioBuffer::new(AudioFormat::F32LE, 48000, 1, vec![0.5; 480], Duration::ZERO);
+/// assert!(source.send(MediaSample::Audio(buffer.clone())));
+///
+/// // Once every track has stopped, capture can stop too
+/// track.stop();
+/// assert!(!source.send(MediaSample::Audio(buffer)));
+/// ```
+pub struct TrackSource {
+    shared: Arc<SourceShared>,
+}
+
+impl TrackSource {
+    /// Sends a frame or buffer to every live track of the source
+    ///
+    /// Disabled tracks receive black frames or silence in its place.
+    /// Samples of the other kind than the source's are ignored.
+    ///
+    /// # Returns
+    ///
+    /// Whether any track of the source is still live; once none is, there
+    /// is no need to produce more media.
+    pub fn send(&self, sample: MediaSample) -> bool {
+        let tracks: Vec<_> = {
+            let mut tracks = lock(&self.shared.tracks);
+            tracks.retain(|track| track.strong_count() > 0);
+            tracks.iter().filter_map(Weak::upgrade).collect()
+        };
+        if sample.kind() != self.shared.kind {
+            return tracks.iter().any(|track| lock(&track.sender).is_some());
+        }
+
+        let mut blank = None;
+        let mut live = false;
+        for track in tracks {
+            let sender = lock(&track.sender);
+            let Some(sender) = sender.as_ref() else {
+                continue;
+            };
+            live = true;
+            let sample = if track.enabled.load(Ordering::Relaxed) {
+                sample.clone()
+            } else {
+                blank.get_or_insert_with(|| sample.blank()).clone()
+            };
+            // Tracks without subscribers drop the media
+            let _ = sender.send(sample);
+        }
+        live
+    }
+
+    /// Sets whether the source is temporarily unable to provide media
+    pub fn set_muted(&self, muted: bool) {
+        self.shared.muted.store(muted, Ordering::Relaxed);
+    }
+
+    /// Ends every track of the source
+    ///
+    /// Called when the source is gone for good, such as an unplugged
+    /// device or a remote track that was removed.
+    pub fn end(&self) {
+        self.shared.ended.store(true, Ordering::Release);
+        let tracks: Vec<_> = lock(&self.shared.tracks).drain(..).collect();
+        for track in tracks.iter().filter_map(Weak::upgrade) {
+            track.end();
+        }
+    }
+}
+
+impl Drop for TrackSource {
+    fn drop(&mut self) {
+        self.end();
+    }
+}
+
+impl fmt::Debug for TrackSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackSource")
+            .field("kind", &self.shared.kind)
+            .field("label", &self.shared.label)
+            .field("ended", &self.shared.ended.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// Ordered set of tracks, as in the Media Capture and Streams API
+///
+/// A stream is active while any of its tracks is live;
+/// [`MediaStream::ended`] waits for it to become inactive.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{MediaStream, MediaStreamTrack, MediaStreamTrackKind};
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let (video, _camera) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+/// let (audio, _microphone) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Microphone");
+/// let stream = MediaStream::with_tracks(vec![video.clone(), audio.clone()]);
+/// assert_eq!(stream.video_tracks(), vec![video.clone()]);
+///
+/// video.stop();
+/// audio.stop();
+/// stream.ended().await;
+/// assert!(!stream.active());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct MediaStream {
+    id: String,
+    tracks: Mutex<Vec<MediaStreamTrack>>,
+    /// Notified when tracks are added or removed
+    changed: watch::Sender<()>,
+}
+
+impl MediaStream {
+    /// Creates a stream without tracks
+    pub fn new() -> Self {
+        Self::with_tracks(Vec::new())
+    }
+
+    /// Creates a stream of `tracks`, in order
+    ///
+    /// A track given more than once is only added the first time.
+    pub fn with_tracks(tracks: Vec<MediaStreamTrack>) -> Self {
+        let stream = Self {
+            id: Uuid::new_v4().to_string(),
+            tracks: Mutex::new(Vec::with_capacity(tracks.len())),
+            changed: watch::Sender::new(()),
+        };
+        for track in tracks {
+            stream.add_track(track);
+        }
+        stream
+    }
+
+    /// Returns the unique identifier of the stream
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the tracks of the stream, in the order they were added
+    pub fn tracks(&self) -> Vec<MediaStreamTrack> {
+        lock(&self.tracks).clone()
+    }
+
+    /// Returns the audio tracks of the stream
+    pub fn audio_tracks(&self) -> Vec<MediaStreamTrack> {
+        self.tracks_of(MediaStreamTrackKind::Audio)
+    }
+
+    /// Returns the video tracks of the stream
+    pub fn video_tracks(&self) -> Vec<MediaStreamTrack> {
+        self.tracks_of(MediaStreamTrackKind::Video)
+    }
+
+    fn tracks_of(&self, kind: MediaStreamTrackKind) -> Vec<MediaStreamTrack> {
+        lock(&self.tracks)
+            .iter()
+            .filter(|track| track.kind() == kind)
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the track with identifier `id`, if it is in the stream
+    pub fn track(&self, id: &str) -> Option<MediaStreamTrack> {
+        lock(&self.tracks)
+            .iter()
+            .find(|track| track.id() == id)
+            .cloned()
+    }
+
+    /// Adds a track to the end of the stream
+    ///
+    /// Returns `false`, leaving the stream as it is, if the track is
+    /// already in it.
+    pub fn add_track(&self, track: MediaStreamTrack) -> bool {
+        let mut tracks = lock(&self.tracks);
+        if tracks.contains(&track) {
+            return false;
+        }
+        tracks.push(track);
+        self.changed.send_replace(());
+        true
+    }
+
+    /// Removes a track from the stream
+    ///
+    /// Returns `false` if the track is not in the stream. The track itself
+    /// is left live.
+    pub fn remove_track(&self, track: &MediaStreamTrack) -> bool {
+        let mut tracks = lock(&self.tracks);
+        let Some(index) = tracks.iter().position(|t| t == track) else {
+            return false;
+        };
+        tracks.remove(index);
+        self.changed.send_replace(());
+        true
+    }
+
+    /// Returns whether any track of the stream is live
+    pub fn active(&self) -> bool {
+        lock(&self.tracks)
+            .iter()
+            .any(|track| track.ready_state() == MediaStreamTrackState::Live)
+    }
+
+    /// Creates a new stream of clones of this stream's tracks, as the
+    /// API's `clone()`
+    ///
+    /// See [`MediaStreamTrack::clone_track`].
+    pub fn clone_stream(&self) -> MediaStream {
+        Self::with_tracks(
+            self.tracks()
+                .iter()
+                .map(MediaStreamTrack::clone_track)
+                .collect(),
+        )
+    }
+
+    /// Waits until no track of the stream is live
+    ///
+    /// Tracks added or removed while waiting are taken into account.
+    /// Returns straight away for an inactive stream, such as one without
+    /// tracks.
+    pub async fn ended(&self) {
+        let mut changes = self.changed.subscribe();
+        loop {
+            let live = self
+                .tracks()
+                .into_iter()
+                .find(|track| track.ready_state() == MediaStreamTrackState::Live);
+            let Some(track) = live else {
+                return;
+            };
+
+            // Check again once this track ends or the tracks change
+            let mut track_ended = pin!(track.ended());
+            let mut tracks_changed = pin!(changes.changed());
+            poll_fn(|cx| {
+                if track_ended.as_mut().poll(cx).is_ready()
+                    || tracks_changed.as_mut().poll(cx).is_ready()
+                {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+        }
+    }
+}
+
+impl Default for MediaStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
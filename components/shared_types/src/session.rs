@@ -2,7 +2,11 @@
 //!
 //! This module provides types for managing media playback sessions.
 
+use crate::errors::MediaError;
+use crate::media::PreloadStrategy;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::Duration;
 use uuid::Uuid;
 
 /// Unique identifier for a media session
@@ -19,7 +23,7 @@ use uuid::Uuid;
 /// let id2 = SessionId::new();
 /// assert_ne!(id1, id2); // Each ID is unique
 /// ```
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct SessionId(Uuid);
 
 impl SessionId {
@@ -57,8 +61,50 @@ impl fmt::Display for SessionId {
     }
 }
 
+/// Order in which hardware and software video decoders are tried
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{DecoderSelectionPolicy, MediaSessionConfig};
+///
+/// let config = MediaSessionConfig::new().with_decoder_policy(DecoderSelectionPolicy::HardwareFirst);
+/// assert_eq!(config.decoder_policy, Some(DecoderSelectionPolicy::HardwareFirst));
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DecoderSelectionPolicy {
+    /// Use a hardware decoder, falling back to software if hardware is
+    /// unavailable or does not support the codec
+    HardwareFirst,
+    /// Use a software decoder, falling back to hardware if no software
+    /// decoder supports the codec
+    SoftwareFirst,
+    /// Use a hardware decoder only
+    HardwareOnly,
+    /// Use a software decoder only, without initializing hardware
+    #[default]
+    SoftwareOnly,
+}
+
+/// How far ahead of the playback position media is demuxed by default
+pub const DEFAULT_MAX_BUFFER_AHEAD: Duration = Duration::from_secs(30);
+
 /// Configuration for a media session
-#[derive(Debug, Clone, Default)]
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{MediaSessionConfig, PreloadStrategy};
+///
+/// // Audio of a video file, read only once playback starts
+/// let config = MediaSessionConfig::new()
+///     .with_video(false)
+///     .with_preload(PreloadStrategy::None);
+/// assert!(config.validate().is_ok());
+///
+/// assert!(config.with_audio(false).validate().is_err());
+/// ```
+#[derive(Debug, Clone)]
 pub struct MediaSessionConfig {
     /// Enable hardware acceleration
     pub hardware_accel: bool,
@@ -70,6 +116,37 @@ pub struct MediaSessionConfig {
     pub preferred_video_decoder: Option<String>,
     /// Preferred audio decoder
     pub preferred_audio_decoder: Option<String>,
+    /// Decoder selection policy, overriding the engine's if set
+    pub decoder_policy: Option<DecoderSelectionPolicy>,
+    /// How often the session reports its position while playing, 250ms if
+    /// not set
+    pub position_update_interval: Option<Duration>,
+    /// Decode and play the media's video
+    pub enable_video: bool,
+    /// Play the media's audio
+    pub enable_audio: bool,
+    /// How much of the media is read before playback starts
+    pub preload: PreloadStrategy,
+    /// How far ahead of the playback position media is demuxed
+    pub max_buffer_ahead: Duration,
+}
+
+impl Default for MediaSessionConfig {
+    fn default() -> Self {
+        Self {
+            hardware_accel: false,
+            max_buffer_size: None,
+            low_latency: false,
+            preferred_video_decoder: None,
+            preferred_audio_decoder: None,
+            decoder_policy: None,
+            position_update_interval: None,
+            enable_video: true,
+            enable_audio: true,
+            preload: PreloadStrategy::Auto,
+            max_buffer_ahead: DEFAULT_MAX_BUFFER_AHEAD,
+        }
+    }
 }
 
 impl MediaSessionConfig {
@@ -95,4 +172,60 @@ impl MediaSessionConfig {
         self.low_latency = enabled;
         self
     }
+
+    /// Sets the decoder selection policy of the session
+    pub fn with_decoder_policy(mut self, policy: DecoderSelectionPolicy) -> Self {
+        self.decoder_policy = Some(policy);
+        self
+    }
+
+    /// Sets how often the session reports its position while playing
+    pub fn with_position_update_interval(mut self, interval: Duration) -> Self {
+        self.position_update_interval = Some(interval);
+        self
+    }
+
+    /// Enables or disables video, e.g. for an audio-only session
+    pub fn with_video(mut self, enabled: bool) -> Self {
+        self.enable_video = enabled;
+        self
+    }
+
+    /// Enables or disables audio, e.g. for a video-only session
+    pub fn with_audio(mut self, enabled: bool) -> Self {
+        self.enable_audio = enabled;
+        self
+    }
+
+    /// Sets how much of the media is read before playback starts
+    pub fn with_preload(mut self, preload: PreloadStrategy) -> Self {
+        self.preload = preload;
+        self
+    }
+
+    /// Sets how far ahead of the playback position media is demuxed
+    pub fn with_max_buffer_ahead(mut self, ahead: Duration) -> Self {
+        self.max_buffer_ahead = ahead;
+        self
+    }
+
+    /// Checks that the configuration describes a playable session
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::InvalidParameter` if both video and audio are
+    /// disabled, or nothing may be buffered ahead.
+    pub fn validate(&self) -> Result<(), MediaError> {
+        if !self.enable_video && !self.enable_audio {
+            return Err(MediaError::InvalidParameter(
+                "Session must enable video, audio or both".to_string(),
+            ));
+        }
+        if self.max_buffer_ahead.is_zero() {
+            return Err(MediaError::InvalidParameter(
+                "Maximum buffer ahead must be positive".to_string(),
+            ));
+        }
+        Ok(())
+    }
 }
@@ -2,10 +2,13 @@
 //!
 //! This module defines the main interfaces that media engine components must implement.
 
+use crate::codec_config::GaplessInfo;
 use crate::codecs::{AudioCodec, VideoCodec};
+use crate::encryption::EncryptionInfo;
 use crate::errors::MediaError;
-use crate::media::{AudioBuffer, MediaSource, VideoFrame};
+use crate::media::{AudioBuffer, LoopMode, MediaSource, VideoFrame};
 use crate::session::{MediaSessionConfig, SessionId};
+use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use tokio::sync::mpsc;
 
@@ -70,25 +73,128 @@ pub trait MediaEngine: Send + Sync {
     async fn pause(&self, session: SessionId) -> Result<(), MediaError>;
 
     /// Seek to a specific position
+    ///
+    /// Positions outside the [seekable range](MediaEngine::get_seekable_range)
+    /// are clamped to it.
     async fn seek(&self, session: SessionId, position: Duration) -> Result<(), MediaError>;
 
+    /// Get the ranges of media time buffered for playback
+    ///
+    /// Like an element's `buffered` attribute, these are the ranges held
+    /// demuxed and decoded on every track, in order and without overlaps.
+    async fn get_buffered_ranges(
+        &self,
+        session: SessionId,
+    ) -> Result<Vec<(Duration, Duration)>, MediaError>;
+
+    /// Get the range of media time that can be sought to
+    ///
+    /// Like an element's `seekable` attribute. A source that can be read
+    /// from any offset is seekable over its whole duration; a stream only
+    /// over what has been buffered of it. Empty, from zero to zero, while
+    /// nothing of the media is known.
+    async fn get_seekable_range(
+        &self,
+        session: SessionId,
+    ) -> Result<(Duration, Duration), MediaError>;
+
     /// Set playback volume (0.0 to 1.0)
     async fn set_volume(&self, session: SessionId, volume: f32) -> Result<(), MediaError>;
 
-    /// Get the next video frame
+    /// Set playback rate (0.25 to 4.0, 1.0 = normal speed)
+    async fn set_rate(&self, session: SessionId, rate: f32) -> Result<(), MediaError>;
+
+    /// Set the playback rate of a session
+    ///
+    /// Same as [`MediaEngine::set_rate`], which it forwards to by default.
+    async fn set_playback_rate(&self, session: SessionId, rate: f32) -> Result<(), MediaError> {
+        self.set_rate(session, rate).await
+    }
+
+    /// Set what playback does when it reaches the end of the media
+    async fn set_loop(&self, session: SessionId, mode: LoopMode) -> Result<(), MediaError>;
+
+    /// Get the next video frame due for display
+    ///
+    /// Late frames are skipped. Returns [`MediaError::NotReady`] if the next
+    /// frame is ahead of the clock; it is returned by a later call. If no
+    /// frame is decoded within the engine's frame timeout, returns
+    /// [`MediaError::Timeout`] while playback runs and
+    /// [`MediaError::WouldBlock`] otherwise.
     async fn get_video_frame(&self, session: SessionId) -> Result<VideoFrame, MediaError>;
 
-    /// Get audio samples
+    /// Get `count` audio samples per channel
+    ///
+    /// Decoded buffers are concatenated and split to the exact count. Fewer
+    /// samples are returned only at the end of the media or where the audio
+    /// format changes. If the samples are not decoded within the engine's
+    /// frame timeout, returns [`MediaError::Timeout`] while playback runs and
+    /// [`MediaError::WouldBlock`] otherwise; the samples collected so far are
+    /// kept for the next call.
     async fn get_audio_samples(
         &self,
         session: SessionId,
         count: usize,
     ) -> Result<AudioBuffer, MediaError>;
 
+    /// Get playback statistics of a session
+    ///
+    /// Counters start at zero when the session's source is loaded and only
+    /// grow while it plays.
+    async fn get_playback_stats(&self, session: SessionId) -> Result<PlaybackStats, MediaError>;
+
     /// Destroy a session and free resources
     async fn destroy_session(&self, session: SessionId) -> Result<(), MediaError>;
 }
 
+/// Playback statistics of a session, as shown by developer tools
+///
+/// Times are in milliseconds so the serialized form reads directly.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::PlaybackStats;
+///
+/// let stats = PlaybackStats {
+///     frames_decoded: 250,
+///     frames_dropped: 3,
+///     ..Default::default()
+/// };
+/// assert!(stats.frames_dropped < stats.frames_decoded);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackStats {
+    /// Video frames decoded
+    pub frames_decoded: u64,
+    /// Video frames presented for display
+    pub frames_displayed: u64,
+    /// Video frames dropped for running late
+    pub frames_dropped: u64,
+    /// Video packets that did not decode to a frame
+    pub decode_errors: u64,
+    /// Median frame decode time of recent frames
+    pub decode_time_p50_ms: f64,
+    /// 90th percentile frame decode time of recent frames
+    pub decode_time_p90_ms: f64,
+    /// 99th percentile frame decode time of recent frames
+    pub decode_time_p99_ms: f64,
+    /// Bitrate of the recently decoded video, in bits per second
+    pub video_bitrate: u64,
+    /// How far media is buffered ahead of the playback position
+    pub buffered_ahead_ms: f64,
+    /// Decoded video frames waiting to be displayed
+    pub video_queue_depth: usize,
+    /// Decoded audio buffers waiting to be played
+    pub audio_queue_depth: usize,
+    /// Bytes of decoded media buffered for playback
+    pub bytes_buffered: usize,
+    /// Times video playback ran out of decoded frames
+    pub video_underruns: u64,
+    /// Times audio playback ran out of decoded audio
+    pub audio_underruns: u64,
+}
+
 /// Media information from demuxer
 #[derive(Debug, Clone, Default)]
 pub struct MediaInfo {
@@ -141,6 +247,8 @@ pub struct VideoPacket {
     pub dts: Option<i64>,
     /// Whether this is a keyframe
     pub is_keyframe: bool,
+    /// How the packet data is encrypted, for packets of encrypted tracks
+    pub encryption: Option<EncryptionInfo>,
 }
 
 /// Audio packet from demuxer
@@ -152,6 +260,11 @@ pub struct AudioPacket {
     pub pts: Option<i64>,
     /// Decode timestamp
     pub dts: Option<i64>,
+    /// Whether this is the last packet of the stream, after which decoders
+    /// trim the encoder padding
+    pub is_last: bool,
+    /// How the packet data is encrypted, for packets of encrypted tracks
+    pub encryption: Option<EncryptionInfo>,
 }
 
 /// Container format demuxer interface
@@ -178,6 +291,14 @@ pub trait VideoDecoder {
 
     /// Flush any buffered frames
     fn flush(&mut self) -> Result<Vec<VideoFrame>, MediaError>;
+
+    /// Configure the decoder with the track's codec configuration record
+    /// (e.g. `avcC` for H.264) before the first packet
+    ///
+    /// Decoders that take their configuration in-band ignore it.
+    fn configure(&mut self, _extradata: &[u8]) -> Result<(), MediaError> {
+        Ok(())
+    }
 }
 
 /// Audio decoder interface
@@ -189,4 +310,70 @@ pub trait AudioDecoder {
 
     /// Flush any buffered samples
     fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError>;
+
+    /// Discard all decoding state before decoding from a new position,
+    /// e.g. after a seek
+    ///
+    /// Unlike [`flush`](AudioDecoder::flush), buffered samples are dropped
+    /// rather than returned. The codec configuration and gapless information
+    /// are kept.
+    fn reset(&mut self);
+
+    /// Configure the decoder with the track's codec configuration (e.g. the
+    /// AudioSpecificConfig for AAC) before the first packet
+    ///
+    /// Decoders that take their configuration in-band ignore it.
+    fn configure(&mut self, _extradata: &[u8]) -> Result<(), MediaError> {
+        Ok(())
+    }
+
+    /// Set the encoder delay and padding the container signals for the
+    /// stream (e.g. from an MP4 edit list) before the first packet
+    ///
+    /// Decoders that read them in-band or have none ignore it.
+    fn set_gapless_info(&mut self, _info: GaplessInfo) {}
+
+    /// Produce audio in place of a packet that was lost in transit
+    ///
+    /// `next` is the packet following the lost one when it has already
+    /// arrived; decoders with in-band forward error correction recover the
+    /// lost frame from it. Otherwise `sample_count` samples per channel of
+    /// concealment audio are synthesized. `next` is not consumed and must
+    /// still be passed to [`decode`](AudioDecoder::decode) afterwards.
+    ///
+    /// Decoders without packet loss concealment return
+    /// [`MediaError::NotImplemented`].
+    fn conceal_loss(
+        &mut self,
+        _next: Option<&AudioPacket>,
+        _sample_count: usize,
+    ) -> Result<AudioBuffer, MediaError> {
+        Err(MediaError::NotImplemented(
+            "Packet loss concealment".to_string(),
+        ))
+    }
+}
+
+/// Audio output interface
+///
+/// Sinks play decoded audio buffers on an output device. They are shared
+/// between the task feeding them and the A/V sync controller, which uses
+/// [`played_duration`](AudioSink::played_duration) as its master clock, so
+/// all methods take `&self`.
+pub trait AudioSink: Send + Sync {
+    /// Queue a buffer for playback
+    ///
+    /// May block until the device has room for the buffer.
+    fn write(&self, buffer: AudioBuffer) -> Result<(), MediaError>;
+
+    /// Duration of audio played out so far
+    ///
+    /// Excludes audio that has been written but is still queued in the
+    /// device, so it advances with the sound actually heard.
+    fn played_duration(&self) -> Duration;
+
+    /// Set the output volume, from 0.0 (silent) to 1.0 (full scale)
+    ///
+    /// Applies to buffers written from then on.
+    fn set_volume(&self, volume: f32);
 }
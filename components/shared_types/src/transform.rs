@@ -0,0 +1,242 @@
+//! Video frame cropping and scaling
+//!
+//! This module extracts regions of YUV 4:2:0 frames and resizes them, as
+//! needed for simulcast layers and thumbnails. Each plane is processed
+//! separately, so the chroma planes keep their half resolution. As in
+//! [`PixelFormatConverter`](crate::PixelFormatConverter), chroma planes of
+//! odd-sized frames are rounded up to cover the last row and column.
+//! Frames with padded `strides` are read without the padding, and the
+//! results are tightly packed.
+
+use crate::conversion::Layout;
+use crate::{MediaError, PixelFormat, VideoFrame};
+use std::borrow::Cow;
+
+impl VideoFrame {
+    /// Extracts the `width` x `height` region with its top-left corner at
+    /// (`x`, `y`)
+    ///
+    /// The offset must be even so that the region starts on a 2x2 chroma
+    /// block. The cropped frame keeps the timing and metadata of this
+    /// frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` if the frame is not
+    /// [`PixelFormat::YUV420`], and `MediaError::InvalidParameter` if the
+    /// offset is odd, the region is empty or extends past the frame, or the
+    /// frame data does not match its dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// let frame = VideoFrame::new(
+    ///     1280,
+    ///     720,
+    ///     PixelFormat::YUV420,
+    ///     vec![128; 1280 * 720 * 3 / 2],
+    ///     Duration::ZERO,
+    /// );
+    ///
+    /// let cropped = frame.crop(640, 360, 320, 240).unwrap();
+    /// assert_eq!((cropped.width, cropped.height), (320, 240));
+    /// assert_eq!(cropped.data.len(), 320 * 240 * 3 / 2);
+    /// ```
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Result<VideoFrame, MediaError> {
+        let (source, planes) = self.yuv420_layout("cropping")?;
+
+        if !x.is_multiple_of(2) || !y.is_multiple_of(2) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Crop offset ({}, {}) is not aligned to 2x2 chroma blocks",
+                x, y
+            )));
+        }
+        if width == 0
+            || height == 0
+            || u64::from(x) + u64::from(width) > u64::from(self.width)
+            || u64::from(y) + u64::from(height) > u64::from(self.height)
+        {
+            return Err(MediaError::InvalidParameter(format!(
+                "Crop region {}x{} at ({}, {}) is outside the {}x{} frame",
+                width, height, x, y, self.width, self.height
+            )));
+        }
+
+        let target = Layout::new(width as usize, height as usize);
+        let (x, y) = (x as usize, y as usize);
+        let mut data = Vec::with_capacity(target.luma_size() + 2 * target.chroma_size());
+        crop_plane(
+            &mut data,
+            &planes[..source.luma_size()],
+            source.width,
+            (x, y),
+            (target.width, target.height),
+        );
+        let chroma = &planes[source.luma_size()..];
+        for plane in chroma.chunks_exact(source.chroma_size()) {
+            crop_plane(
+                &mut data,
+                plane,
+                source.chroma_width,
+                (x / 2, y / 2),
+                (target.chroma_width, target.chroma_height),
+            );
+        }
+
+        Ok(self.with_size(width, height, data))
+    }
+
+    /// Resizes the frame to `target_width` x `target_height` with bilinear
+    /// interpolation
+    ///
+    /// The luma and both chroma planes are interpolated separately. The
+    /// scaled frame keeps the timing and metadata of this frame.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` if the frame is not
+    /// [`PixelFormat::YUV420`], and `MediaError::InvalidParameter` if the
+    /// frame or the target is empty or the frame data does not match its
+    /// dimensions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_shared_types::{PixelFormat, VideoFrame};
+    /// use std::time::Duration;
+    ///
+    /// let frame = VideoFrame::new(
+    ///     1280,
+    ///     720,
+    ///     PixelFormat::YUV420,
+    ///     vec![128; 1280 * 720 * 3 / 2],
+    ///     Duration::ZERO,
+    /// );
+    ///
+    /// let thumbnail = frame.scale(160, 90).unwrap();
+    /// assert_eq!(thumbnail.data.len(), 160 * 90 + 2 * 80 * 45);
+    /// assert!(thumbnail.data.iter().all(|&sample| sample == 128));
+    /// ```
+    pub fn scale(&self, target_width: u32, target_height: u32) -> Result<VideoFrame, MediaError> {
+        let (source, planes) = self.yuv420_layout("scaling")?;
+
+        if [self.width, self.height, target_width, target_height].contains(&0) {
+            return Err(MediaError::InvalidParameter(format!(
+                "Cannot scale a {}x{} frame to {}x{}",
+                self.width, self.height, target_width, target_height
+            )));
+        }
+
+        let target = Layout::new(target_width as usize, target_height as usize);
+        let mut data = Vec::with_capacity(target.luma_size() + 2 * target.chroma_size());
+        scale_plane(
+            &mut data,
+            &planes[..source.luma_size()],
+            (source.width, source.height),
+            (target.width, target.height),
+        );
+        let chroma = &planes[source.luma_size()..];
+        for plane in chroma.chunks_exact(source.chroma_size()) {
+            scale_plane(
+                &mut data,
+                plane,
+                (source.chroma_width, source.chroma_height),
+                (target.chroma_width, target.chroma_height),
+            );
+        }
+
+        Ok(self.with_size(target_width, target_height, data))
+    }
+
+    /// Plane layout and tightly packed planes of this frame, checked to be
+    /// YUV 4:2:0 with matching data
+    fn yuv420_layout(&self, operation: &str) -> Result<(Layout, Cow<'_, [u8]>), MediaError> {
+        if self.format != PixelFormat::YUV420 {
+            return Err(MediaError::UnsupportedFormat {
+                format: format!("{:?} frame {}", self.format, operation),
+            });
+        }
+
+        let layout = Layout::new(self.width as usize, self.height as usize);
+        let planes = self.packed_data();
+        let expected = layout.luma_size() + 2 * layout.chroma_size();
+        if planes.len() != expected {
+            return Err(MediaError::InvalidParameter(format!(
+                "{:?} frame of {}x{} has {} bytes of data, expected {}",
+                self.format,
+                self.width,
+                self.height,
+                planes.len(),
+                expected
+            )));
+        }
+        Ok((layout, planes))
+    }
+
+    /// Copy of this frame with new dimensions and data
+    fn with_size(&self, width: u32, height: u32, data: Vec<u8>) -> VideoFrame {
+        VideoFrame {
+            width,
+            height,
+            format: self.format,
+            data,
+            strides: None,
+            timestamp: self.timestamp,
+            duration: self.duration,
+            metadata: self.metadata.clone(),
+        }
+    }
+}
+
+/// Appends the `size` region of a plane `stride` samples wide, starting at
+/// `offset`, to `out`
+fn crop_plane(
+    out: &mut Vec<u8>,
+    plane: &[u8],
+    stride: usize,
+    offset: (usize, usize),
+    size: (usize, usize),
+) {
+    let (x, y) = offset;
+    let (width, height) = size;
+    for row in plane.chunks_exact(stride).skip(y).take(height) {
+        out.extend_from_slice(&row[x..x + width]);
+    }
+}
+
+/// Source samples and weight of the second one for each target sample
+/// along an axis
+///
+/// Sample centers are aligned, so the edges of the source and target
+/// planes coincide.
+fn bilinear_taps(source: usize, target: usize) -> Vec<(usize, usize, f32)> {
+    let ratio = source as f32 / target as f32;
+    (0..target)
+        .map(|i| {
+            let position = ((i as f32 + 0.5) * ratio - 0.5).clamp(0.0, (source - 1) as f32);
+            let first = position as usize;
+            let second = (first + 1).min(source - 1);
+            (first, second, position - first as f32)
+        })
+        .collect()
+}
+
+/// Appends a plane of `source` dimensions, resized to `target` dimensions
+/// with bilinear interpolation, to `out`
+fn scale_plane(out: &mut Vec<u8>, plane: &[u8], source: (usize, usize), target: (usize, usize)) {
+    let columns = bilinear_taps(source.0, target.0);
+    let rows = bilinear_taps(source.1, target.1);
+
+    for &(top, bottom, dy) in &rows {
+        let top = &plane[top * source.0..(top + 1) * source.0];
+        let bottom = &plane[bottom * source.0..(bottom + 1) * source.0];
+        out.extend(columns.iter().map(|&(left, right, dx)| {
+            let upper = f32::from(top[left]) * (1.0 - dx) + f32::from(top[right]) * dx;
+            let lower = f32::from(bottom[left]) * (1.0 - dx) + f32::from(bottom[right]) * dx;
+            (upper * (1.0 - dy) + lower * dy).round() as u8
+        }));
+    }
+}
@@ -1,7 +1,12 @@
 //! Unit tests for shared_types component
 
+mod test_channel_map;
+mod test_codec_config;
 mod test_codecs;
+mod test_conversion;
 mod test_errors;
 mod test_formats;
 mod test_media;
+mod test_media_stream;
 mod test_traits;
+mod test_transform;
@@ -0,0 +1,141 @@
+//! Unit tests for channel layouts, planar conversion and downmixing
+
+use cortenbrowser_shared_types::{
+    downmix_to_stereo, AudioBuffer, AudioFormat, Channel, ChannelMap,
+};
+use std::f32::consts::FRAC_1_SQRT_2;
+use std::time::Duration;
+
+/// Two frames of 5.1 audio where sample `n` of channel `c` is `c + n / 10`
+fn surround_buffer() -> AudioBuffer {
+    let samples = (0..2)
+        .flat_map(|frame| (0..6).map(move |channel| channel as f32 + frame as f32 / 10.0))
+        .collect();
+    AudioBuffer::new(
+        AudioFormat::F32LE,
+        48000,
+        6,
+        samples,
+        Duration::from_millis(10),
+    )
+    .with_channel_map(ChannelMap::surround_5_1())
+}
+
+#[test]
+fn test_surround_round_trips_through_planar() {
+    let buffer = surround_buffer();
+
+    let planes = buffer.to_planar();
+    assert_eq!(planes.len(), 6);
+    for (channel, plane) in planes.iter().enumerate() {
+        assert_eq!(plane, &vec![channel as f32, channel as f32 + 0.1]);
+    }
+
+    let round_trip = AudioBuffer::from_planar(
+        planes,
+        buffer.format,
+        buffer.sample_rate,
+        buffer.channels,
+        buffer.timestamp,
+    );
+    assert_eq!(round_trip.samples, buffer.samples);
+    assert_eq!(round_trip.channels, 6);
+    assert_eq!(round_trip.timestamp, Duration::from_millis(10));
+    assert_eq!(round_trip.duration, buffer.duration);
+    assert_eq!(round_trip.sample_count(), 2);
+}
+
+#[test]
+fn test_to_planar_mono() {
+    let buffer = AudioBuffer::new(
+        AudioFormat::F32LE,
+        48000,
+        1,
+        vec![0.1, 0.2, 0.3],
+        Duration::ZERO,
+    );
+
+    assert_eq!(buffer.to_planar(), vec![vec![0.1, 0.2, 0.3]]);
+}
+
+#[test]
+#[should_panic]
+fn test_from_planar_rejects_plane_count_mismatch() {
+    AudioBuffer::from_planar(
+        vec![vec![0.0; 4]],
+        AudioFormat::F32LE,
+        48000,
+        2,
+        Duration::ZERO,
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_from_planar_rejects_uneven_planes() {
+    AudioBuffer::from_planar(
+        vec![vec![0.0; 4], vec![0.0; 3]],
+        AudioFormat::F32LE,
+        48000,
+        2,
+        Duration::ZERO,
+    );
+}
+
+#[test]
+fn test_channel_map_layouts() {
+    assert_eq!(ChannelMap::mono().channels, vec![Channel::Center]);
+    assert_eq!(
+        ChannelMap::stereo().channels,
+        vec![Channel::FrontLeft, Channel::FrontRight]
+    );
+    assert_eq!(ChannelMap::surround_5_1().len(), 6);
+    assert!(ChannelMap::new(vec![]).is_empty());
+}
+
+#[test]
+fn test_downmix_surround_uses_bs775_coefficients() {
+    let buffer = surround_buffer();
+
+    let stereo = downmix_to_stereo(&buffer, buffer.channel_map.as_ref().unwrap());
+
+    assert_eq!(stereo.channels, 2);
+    assert_eq!(stereo.channel_map, Some(ChannelMap::stereo()));
+    assert_eq!(stereo.sample_count(), 2);
+    assert_eq!(stereo.timestamp, buffer.timestamp);
+    assert_eq!(stereo.duration, buffer.duration);
+    for frame in 0..2 {
+        let offset = frame as f32 / 10.0;
+        let [fl, fr, c, _lfe, sl, sr] = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0].map(|v| v + offset);
+        let left = stereo.samples[frame * 2];
+        let right = stereo.samples[frame * 2 + 1];
+        assert!((left - (fl + FRAC_1_SQRT_2 * (c + sl))).abs() < 1e-5);
+        assert!((right - (fr + FRAC_1_SQRT_2 * (c + sr))).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn test_downmix_drops_lfe() {
+    let mut samples = vec![0.0; 6];
+    samples[3] = 1.0;
+    let buffer = AudioBuffer::new(AudioFormat::F32LE, 48000, 6, samples, Duration::ZERO);
+
+    let stereo = downmix_to_stereo(&buffer, &ChannelMap::surround_5_1());
+
+    assert_eq!(stereo.samples, vec![0.0, 0.0]);
+}
+
+#[test]
+fn test_downmix_stereo_is_unchanged() {
+    let buffer = AudioBuffer::new(
+        AudioFormat::F32LE,
+        48000,
+        2,
+        vec![0.1, 0.2, 0.3, 0.4],
+        Duration::ZERO,
+    );
+
+    let stereo = downmix_to_stereo(&buffer, &ChannelMap::stereo());
+
+    assert_eq!(stereo.samples, buffer.samples);
+}
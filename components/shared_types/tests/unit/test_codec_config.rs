@@ -0,0 +1,106 @@
+//! Unit tests for codec configuration records
+
+use cortenbrowser_shared_types::{
+    AACProfile, AudioSpecificConfig, AvcDecoderConfig, GaplessInfo, MediaError,
+};
+
+#[test]
+fn test_avc_decoder_config_parse() {
+    let avcc = [
+        0x01, 0x64, 0x00, 0x1F, 0xFD, 0xE2, 0x00, 0x02, 0x67, 0x64, 0x00, 0x03, 0x67, 0x4D, 0x40,
+        0x01, 0x00, 0x02, 0x68, 0xEE,
+    ];
+    let config = AvcDecoderConfig::parse(&avcc).unwrap();
+
+    assert_eq!(config.profile_indication, 0x64);
+    assert_eq!(config.level_indication, 0x1F);
+    assert_eq!(config.nal_length_size, 2);
+    assert_eq!(config.sps, vec![vec![0x67, 0x64], vec![0x67, 0x4D, 0x40]]);
+    assert_eq!(config.pps, vec![vec![0x68, 0xEE]]);
+    assert_eq!(
+        config.to_annex_b(),
+        vec![0, 0, 0, 1, 0x67, 0x64, 0, 0, 0, 1, 0x67, 0x4D, 0x40, 0, 0, 0, 1, 0x68, 0xEE]
+    );
+}
+
+#[test]
+fn test_avc_decoder_config_rejects_invalid() {
+    let truncated = [0x01, 0x64, 0x00, 0x1F, 0xFF, 0xE1, 0x00, 0x04, 0x67];
+    let wrong_version = [0x02, 0x64, 0x00, 0x1F, 0xFF, 0xE0, 0x00];
+
+    assert!(matches!(
+        AvcDecoderConfig::parse(&truncated),
+        Err(MediaError::CodecError { .. })
+    ));
+    assert!(matches!(
+        AvcDecoderConfig::parse(&wrong_version),
+        Err(MediaError::CodecError { .. })
+    ));
+}
+
+#[test]
+fn test_audio_specific_config_sample_rates() {
+    // AAC LC at 48 kHz stereo, 22.05 kHz mono and 8 kHz 5.1
+    let lc_48k = AudioSpecificConfig::parse(&[0x11, 0x90]).unwrap();
+    assert_eq!((lc_48k.sample_rate, lc_48k.channels), (48000, 2));
+    assert_eq!(lc_48k.profile(), Some(AACProfile::LC));
+
+    let lc_22k = AudioSpecificConfig::parse(&[0x13, 0x88]).unwrap();
+    assert_eq!((lc_22k.sample_rate, lc_22k.channels), (22050, 1));
+
+    let lc_8k = AudioSpecificConfig::parse(&[0x15, 0xB0]).unwrap();
+    assert_eq!((lc_8k.sample_rate, lc_8k.channels), (8000, 6));
+}
+
+#[test]
+fn test_audio_specific_config_explicit_frequency() {
+    // Frequency index 15 followed by a 24-bit rate of 37800 Hz, mono
+    let config = AudioSpecificConfig::parse(&[0x17, 0x80, 0x49, 0xD4, 0x08]).unwrap();
+
+    assert_eq!(config.sample_rate, 37800);
+    assert_eq!(config.channels, 1);
+}
+
+#[test]
+fn test_audio_specific_config_he_aac() {
+    // SBR at 24 kHz core / 48 kHz output, stereo, over AAC LC
+    let he = AudioSpecificConfig::parse(&[0x2B, 0x11, 0x88, 0x00]).unwrap();
+    assert_eq!(he.profile(), Some(AACProfile::HE));
+    assert_eq!(he.sample_rate, 24000);
+    assert_eq!(he.output_sample_rate(), 48000);
+
+    // The same with parametric stereo over a mono core
+    let he_v2 = AudioSpecificConfig::parse(&[0xEB, 0x09, 0x88, 0x00]).unwrap();
+    assert_eq!(he_v2.profile(), Some(AACProfile::HEv2));
+    assert_eq!(he_v2.channels, 1);
+}
+
+#[test]
+fn test_audio_specific_config_rejects_invalid() {
+    assert!(AudioSpecificConfig::parse(&[0x12]).is_err());
+    // Reserved frequency index 13
+    assert!(AudioSpecificConfig::parse(&[0x16, 0x90]).is_err());
+}
+
+#[test]
+fn test_gapless_info_parse_itunsmpb() {
+    let info = GaplessInfo::parse_itunsmpb(
+        " 00000000 00000840 0000037C 0000000000046E00 00000000 00000000 00000000",
+    )
+    .unwrap();
+
+    assert_eq!(
+        info,
+        GaplessInfo {
+            encoder_delay: 2112,
+            padding: 892,
+        }
+    );
+}
+
+#[test]
+fn test_gapless_info_rejects_invalid_itunsmpb() {
+    assert!(GaplessInfo::parse_itunsmpb(" 00000000 00000840").is_err());
+    assert!(GaplessInfo::parse_itunsmpb(" 00000000 0000084G 00000000").is_err());
+    assert!(GaplessInfo::parse_itunsmpb("").is_err());
+}
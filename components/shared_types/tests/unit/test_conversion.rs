@@ -0,0 +1,368 @@
+//! Unit tests for pixel format conversion
+
+use cortenbrowser_shared_types::{
+    ColorSpace, MediaError, PixelFormat, PixelFormatConverter, VideoFrame,
+};
+use std::time::Duration;
+
+fn frame(width: u32, height: u32, format: PixelFormat, data: Vec<u8>) -> VideoFrame {
+    VideoFrame::new(width, height, format, data, Duration::from_millis(40))
+}
+
+/// Smooth RGB24 test image of gradients, like natural content
+fn gradient(width: u32, height: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            data.push((x * 255 / (width - 1)) as u8);
+            data.push((y * 255 / (height - 1)) as u8);
+            data.push(((x + y) * 255 / (width + height - 2)) as u8);
+        }
+    }
+    data
+}
+
+fn psnr(a: &[u8], b: &[u8]) -> f64 {
+    assert_eq!(a.len(), b.len());
+    let mse = a
+        .iter()
+        .zip(b)
+        .map(|(&a, &b)| (f64::from(a) - f64::from(b)).powi(2))
+        .sum::<f64>()
+        / a.len() as f64;
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+#[test]
+fn test_rgb24_yuv420_round_trip_psnr() {
+    for color_space in [ColorSpace::BT601, ColorSpace::BT709] {
+        let mut rgb = frame(64, 48, PixelFormat::RGB24, gradient(64, 48));
+        rgb.metadata.color_space = color_space;
+
+        let yuv = PixelFormatConverter::convert(&rgb, PixelFormat::YUV420).unwrap();
+        let back = PixelFormatConverter::convert(&yuv, PixelFormat::RGB24).unwrap();
+
+        assert_eq!(yuv.data.len(), 64 * 48 * 3 / 2);
+        assert_eq!(back.data.len(), rgb.data.len());
+        let psnr = psnr(&rgb.data, &back.data);
+        assert!(
+            psnr > 35.0,
+            "{:?} round trip PSNR {:.1} dB",
+            color_space,
+            psnr
+        );
+    }
+}
+
+#[test]
+fn test_rgb24_to_yuv420_reference_colors() {
+    // White, black, red and blue in a 2x2 frame
+    let rgb = frame(
+        2,
+        2,
+        PixelFormat::RGB24,
+        vec![255, 255, 255, 0, 0, 0, 255, 0, 0, 0, 0, 255],
+    );
+
+    let yuv = PixelFormatConverter::convert(&rgb, PixelFormat::YUV420).unwrap();
+
+    // BT.601 limited range luma: 235, 16, 81 and 41
+    assert_eq!(&yuv.data[..4], &[235, 16, 81, 41]);
+}
+
+#[test]
+fn test_color_space_selects_matrix() {
+    let red = vec![255, 0, 0, 255, 0, 0, 255, 0, 0, 255, 0, 0];
+    let bt601 = frame(2, 2, PixelFormat::RGB24, red.clone());
+    let mut bt709 = frame(2, 2, PixelFormat::RGB24, red);
+    bt709.metadata.color_space = ColorSpace::BT709;
+
+    let bt601 = PixelFormatConverter::convert(&bt601, PixelFormat::YUV420).unwrap();
+    let bt709 = PixelFormatConverter::convert(&bt709, PixelFormat::YUV420).unwrap();
+
+    assert_eq!(bt601.data, [81, 81, 81, 81, 90, 240]);
+    assert_eq!(bt709.data, [63, 63, 63, 63, 102, 240]);
+}
+
+#[test]
+fn test_yuv420_to_rgb24_gray() {
+    // Mid gray in an odd-sized frame: 3x3 luma, 2x2 chroma planes
+    let mut data = vec![126; 9];
+    data.extend([128; 8]);
+    let yuv = frame(3, 3, PixelFormat::YUV420, data);
+
+    let rgb = PixelFormatConverter::convert(&yuv, PixelFormat::RGB24).unwrap();
+
+    assert_eq!(rgb.format, PixelFormat::RGB24);
+    assert_eq!(rgb.data, vec![128; 27]);
+}
+
+#[test]
+fn test_rgba32_to_yuv420_ignores_alpha() {
+    let rgb = frame(64, 48, PixelFormat::RGB24, gradient(64, 48));
+    let rgba: Vec<u8> = rgb
+        .data
+        .chunks_exact(3)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 7])
+        .collect();
+    let rgba = frame(64, 48, PixelFormat::RGBA32, rgba);
+
+    let from_rgb = PixelFormatConverter::convert(&rgb, PixelFormat::YUV420).unwrap();
+    let from_rgba = PixelFormatConverter::convert(&rgba, PixelFormat::YUV420).unwrap();
+
+    assert_eq!(from_rgba.data, from_rgb.data);
+}
+
+#[test]
+fn test_nv12_to_yuv420() {
+    // 4x2 frame: 8 luma samples, then 2 interleaved UV pairs
+    let mut data: Vec<u8> = (0..8).collect();
+    data.extend([10, 20, 11, 21]);
+    let nv12 = frame(4, 2, PixelFormat::NV12, data);
+
+    let yuv = PixelFormatConverter::convert(&nv12, PixelFormat::YUV420).unwrap();
+
+    assert_eq!(yuv.data, [0, 1, 2, 3, 4, 5, 6, 7, 10, 11, 20, 21]);
+}
+
+#[test]
+fn test_yuv422_to_yuv420_averages_rows() {
+    // 2x3 frame: chroma planes of 1x3, the last row without a pair
+    let mut data = vec![50; 6];
+    data.extend([10, 21, 30]);
+    data.extend([100, 200, 40]);
+    let yuv422 = frame(2, 3, PixelFormat::YUV422, data);
+
+    let yuv = PixelFormatConverter::convert(&yuv422, PixelFormat::YUV420).unwrap();
+
+    assert_eq!(yuv.data, [50, 50, 50, 50, 50, 50, 16, 30, 150, 40]);
+}
+
+#[test]
+fn test_nv12_to_rgb24() {
+    let mut data = vec![235; 4];
+    data.extend([128, 128]);
+    let nv12 = frame(2, 2, PixelFormat::NV12, data);
+
+    let rgb = PixelFormatConverter::convert(&nv12, PixelFormat::RGB24).unwrap();
+
+    assert_eq!(rgb.data, vec![255; 12]);
+}
+
+/// Solid-color YUV 4:2:0 frame, rounding chroma up for odd dimensions
+fn solid_yuv420(width: u32, height: u32, [y, u, v]: [u8; 3]) -> VideoFrame {
+    let luma = (width * height) as usize;
+    let chroma = (width.div_ceil(2) * height.div_ceil(2)) as usize;
+    let mut data = vec![y; luma];
+    data.extend(std::iter::repeat_n(u, chroma));
+    data.extend(std::iter::repeat_n(v, chroma));
+    frame(width, height, PixelFormat::YUV420, data)
+}
+
+fn assert_solid_rgba(rgba: &VideoFrame, expected: [u8; 3]) {
+    assert_eq!(rgba.format, PixelFormat::RGBA32);
+    assert_eq!(rgba.data.len(), (rgba.width * rgba.height * 4) as usize);
+    for pixel in rgba.data.chunks_exact(4) {
+        for (&actual, expected) in pixel.iter().zip(expected) {
+            assert!(
+                actual.abs_diff(expected) <= 1,
+                "pixel {:?}, expected {:?}",
+                pixel,
+                expected
+            );
+        }
+        assert_eq!(pixel[3], 255);
+    }
+}
+
+#[test]
+fn test_yuv420_to_rgba_solid_colors() {
+    // BT.601 limited range red, green, blue and mid gray
+    let colors = [
+        ([81, 90, 240], [255, 0, 0]),
+        ([145, 54, 34], [0, 255, 0]),
+        ([41, 240, 110], [0, 0, 255]),
+        ([126, 128, 128], [128, 128, 128]),
+    ];
+
+    for (yuv, rgb) in colors {
+        let rgba = solid_yuv420(4, 2, yuv).to_rgba().unwrap();
+        assert_solid_rgba(&rgba, rgb);
+    }
+}
+
+#[test]
+fn test_to_rgba_uses_color_space() {
+    // BT.709 limited range red
+    let mut yuv = solid_yuv420(4, 4, [63, 102, 240]);
+    yuv.metadata.color_space = ColorSpace::BT709;
+    assert_solid_rgba(&yuv.to_rgba().unwrap(), [255, 0, 0]);
+
+    // The same samples read as BT.601 are another color
+    yuv.metadata.color_space = ColorSpace::BT601;
+    let bt601 = yuv.to_rgba().unwrap();
+    assert!(bt601.data[0] < 240, "red of {:?}", &bt601.data[..4]);
+}
+
+#[test]
+fn test_to_rgba_odd_dimensions() {
+    // 5x3 luma with 3x2 chroma planes
+    let rgba = solid_yuv420(5, 3, [81, 90, 240]).to_rgba().unwrap();
+
+    assert_eq!((rgba.width, rgba.height), (5, 3));
+    assert_solid_rgba(&rgba, [255, 0, 0]);
+}
+
+#[test]
+fn test_to_rgba_matches_rgb24() {
+    let rgb = frame(19, 5, PixelFormat::RGB24, gradient(19, 5));
+    let yuv = PixelFormatConverter::convert(&rgb, PixelFormat::YUV420).unwrap();
+
+    let rgb = PixelFormatConverter::convert(&yuv, PixelFormat::RGB24).unwrap();
+    let rgba = yuv.to_rgba().unwrap();
+
+    let opaque: Vec<u8> = rgb
+        .data
+        .chunks_exact(3)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    assert_eq!(rgba.data, opaque);
+}
+
+#[test]
+fn test_nv12_to_rgba() {
+    let mut data = vec![235; 4];
+    data.extend([128, 128]);
+    let nv12 = frame(2, 2, PixelFormat::NV12, data);
+
+    assert_eq!(nv12.to_rgba().unwrap().data, vec![255; 16]);
+}
+
+#[test]
+fn test_to_nv12_interleaves_chroma() {
+    // 3x3 frame: 2x2 chroma planes
+    let mut data: Vec<u8> = (0..9).collect();
+    data.extend([10, 11, 12, 13]);
+    data.extend([20, 21, 22, 23]);
+    let yuv = frame(3, 3, PixelFormat::YUV420, data);
+
+    let nv12 = yuv.to_nv12().unwrap();
+
+    assert_eq!(nv12.format, PixelFormat::NV12);
+    assert_eq!(
+        nv12.data,
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 10, 20, 11, 21, 12, 22, 13, 23]
+    );
+    assert_eq!(nv12.plane_stride(1), 4);
+    assert_eq!(
+        PixelFormatConverter::convert(&nv12, PixelFormat::YUV420).unwrap(),
+        yuv
+    );
+}
+
+#[test]
+fn test_to_nv12_unsupported_source() {
+    let rgb = frame(2, 2, PixelFormat::RGB24, vec![0; 12]);
+
+    assert!(matches!(
+        rgb.to_nv12(),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+}
+
+#[test]
+fn test_convert_keeps_frame_timing() {
+    let mut rgb = frame(2, 2, PixelFormat::RGB24, vec![0; 12]);
+    rgb.duration = Some(Duration::from_millis(40));
+    rgb.metadata.is_keyframe = true;
+
+    let yuv = PixelFormatConverter::convert(&rgb, PixelFormat::YUV420).unwrap();
+
+    assert_eq!(yuv.timestamp, rgb.timestamp);
+    assert_eq!(yuv.duration, rgb.duration);
+    assert_eq!(yuv.metadata, rgb.metadata);
+}
+
+#[test]
+fn test_convert_same_format_copies() {
+    let yuv = frame(2, 2, PixelFormat::YUV420, vec![1, 2, 3, 4, 5, 6]);
+
+    assert_eq!(
+        PixelFormatConverter::convert(&yuv, PixelFormat::YUV420).unwrap(),
+        yuv
+    );
+}
+
+#[test]
+fn test_convert_unsupported() {
+    let rgb = frame(2, 2, PixelFormat::RGB24, vec![0; 12]);
+
+    let result = PixelFormatConverter::convert(&rgb, PixelFormat::NV12);
+
+    assert!(matches!(result, Err(MediaError::UnsupportedFormat { .. })));
+}
+
+#[test]
+fn test_convert_rejects_wrong_data_size() {
+    let yuv = frame(4, 4, PixelFormat::YUV420, vec![0; 16]);
+
+    let result = PixelFormatConverter::convert(&yuv, PixelFormat::RGB24);
+
+    assert!(matches!(result, Err(MediaError::InvalidParameter(_))));
+}
+
+#[test]
+fn test_wide_rows_match_single_pixels() {
+    // Rows long enough for the eight-pixel path with a remainder
+    let rgb = frame(19, 3, PixelFormat::RGB24, gradient(19, 3));
+
+    let yuv = PixelFormatConverter::convert(&rgb, PixelFormat::YUV420).unwrap();
+    let back = PixelFormatConverter::convert(&yuv, PixelFormat::RGB24).unwrap();
+
+    for (index, pixel) in rgb.data.chunks_exact(3).enumerate() {
+        let single = frame(1, 1, PixelFormat::RGB24, pixel.to_vec());
+        let single = PixelFormatConverter::convert(&single, PixelFormat::YUV420).unwrap();
+        assert_eq!(yuv.data[index], single.data[0], "luma of pixel {}", index);
+    }
+    let y = 19 * 3;
+    let (u, v) = yuv.data[y..].split_at(10 * 2);
+    for (index, pixel) in back.data.chunks_exact(3).enumerate() {
+        let (row, column) = (index / 19, index % 19);
+        let chroma = (row / 2) * 10 + column / 2;
+        let single = frame(
+            1,
+            1,
+            PixelFormat::YUV420,
+            vec![yuv.data[index], u[chroma], v[chroma]],
+        );
+        let single = PixelFormatConverter::convert(&single, PixelFormat::RGB24).unwrap();
+        assert_eq!(pixel, &single.data[..], "color of pixel {}", index);
+    }
+}
+
+#[test]
+fn test_padded_rows_convert_like_packed_rows() {
+    let rgb = frame(4, 2, PixelFormat::RGB24, gradient(4, 2));
+    let yuv = PixelFormatConverter::convert(&rgb, PixelFormat::YUV420).unwrap();
+
+    // The same frame with each row padded by two bytes
+    let mut padded = yuv.clone();
+    padded.data.clear();
+    for index in 0..3 {
+        let width = yuv.plane_stride(index);
+        for row in yuv.plane(index).chunks(width) {
+            padded.data.extend_from_slice(row);
+            padded.data.extend_from_slice(&[0xEE, 0xEE]);
+        }
+    }
+    padded.strides = Some([6, 4, 4]);
+
+    let back = PixelFormatConverter::convert(&padded, PixelFormat::RGB24).unwrap();
+    assert_eq!(
+        back.data,
+        PixelFormatConverter::convert(&yuv, PixelFormat::RGB24)
+            .unwrap()
+            .data
+    );
+    assert_eq!(back.strides, None);
+}
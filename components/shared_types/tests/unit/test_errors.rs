@@ -1,6 +1,7 @@
 //! Unit tests for error types
 
 use cortenbrowser_shared_types::MediaError;
+use std::time::Duration;
 
 #[test]
 fn test_unsupported_format_error() {
@@ -48,6 +49,32 @@ fn test_out_of_memory_error() {
     assert!(error_str.contains("Out of memory"));
 }
 
+#[test]
+fn test_would_block_error() {
+    let error = MediaError::WouldBlock("No video frame decoded".to_string());
+    let error_str = format!("{}", error);
+    assert!(error_str.contains("Would block"));
+    assert!(error_str.contains("No video frame decoded"));
+}
+
+#[test]
+fn test_timeout_error() {
+    let error = MediaError::Timeout("No video frame decoded".to_string());
+    let error_str = format!("{}", error);
+    assert!(error_str.contains("Timed out"));
+    assert!(error_str.contains("No video frame decoded"));
+}
+
+#[test]
+fn test_not_ready_error() {
+    let error = MediaError::NotReady {
+        wait_for: Duration::from_millis(40),
+    };
+    let error_str = format!("{}", error);
+    assert!(error_str.contains("Not ready"));
+    assert!(error_str.contains("40ms"));
+}
+
 #[test]
 fn test_error_debug() {
     let error = MediaError::DrmError {
@@ -1,7 +1,8 @@
 //! Unit tests for media data types
 
 use cortenbrowser_shared_types::{
-    AudioBuffer, AudioFormat, FrameMetadata, MediaSource, PixelFormat, SessionId, VideoFrame,
+    AudioBuffer, AudioFormat, AudioLevel, AudioProcessingConfig, FrameMetadata, LoopMode,
+    MediaElementAttributes, MediaSource, PixelFormat, SessionId, VideoFrame,
 };
 use std::time::Duration;
 
@@ -35,6 +36,7 @@ fn test_video_frame_creation() {
         height: 1080,
         format: PixelFormat::YUV420,
         data: vec![0u8; 1920 * 1080],
+        strides: None,
         timestamp: Duration::from_secs(1),
         duration: Some(Duration::from_millis(33)),
         metadata: FrameMetadata::default(),
@@ -54,6 +56,7 @@ fn test_video_frame_clone() {
         height: 480,
         format: PixelFormat::RGB24,
         data: vec![0u8; 640 * 480 * 3],
+        strides: None,
         timestamp: Duration::from_millis(500),
         duration: None,
         metadata: FrameMetadata::default(),
@@ -74,6 +77,7 @@ fn test_audio_buffer_creation() {
         samples: vec![0.0f32; 4800],
         timestamp: Duration::from_millis(100),
         duration: Duration::from_millis(100),
+        channel_map: None,
     };
 
     assert_eq!(buffer.format, AudioFormat::F32LE);
@@ -91,6 +95,7 @@ fn test_audio_buffer_clone() {
         samples: vec![0.0f32; 1000],
         timestamp: Duration::from_secs(0),
         duration: Duration::from_millis(50),
+        channel_map: None,
     };
 
     let buffer2 = buffer1.clone();
@@ -135,3 +140,130 @@ fn test_frame_metadata_default() {
     let debug = format!("{:?}", metadata);
     assert!(!debug.is_empty());
 }
+
+#[test]
+fn test_audio_processing_config_default_disabled() {
+    let config = AudioProcessingConfig::default();
+
+    assert!(!config.noise_suppression);
+    assert!(!config.auto_gain_control);
+    assert!(!config.echo_cancellation);
+    assert!(!config.is_enabled());
+}
+
+#[test]
+fn test_audio_processing_config_builder() {
+    let config = AudioProcessingConfig::new()
+        .with_noise_suppression(true)
+        .with_auto_gain_control(true)
+        .with_agc_target_rms(0.2);
+
+    assert!(config.noise_suppression);
+    assert!(config.auto_gain_control);
+    assert!(!config.echo_cancellation);
+    assert_eq!(config.agc_target_rms, 0.2);
+    assert!(config.is_enabled());
+}
+
+#[test]
+fn test_audio_level_default_is_silence() {
+    let level = AudioLevel::default();
+
+    assert_eq!(level, AudioLevel::SILENCE);
+    assert_eq!(level.rms_dbfs, AudioLevel::MIN_DBFS);
+    assert_eq!(level.peak_dbfs, AudioLevel::MIN_DBFS);
+    assert!(!level.voice_active);
+}
+
+#[test]
+fn test_media_element_loop_mode() {
+    let mut attributes = MediaElementAttributes::default();
+    assert_eq!(attributes.loop_mode(), LoopMode::None);
+
+    attributes.loop_playback = true;
+    assert_eq!(attributes.loop_mode(), LoopMode::All);
+}
+
+/// A 4x2 YUV 4:2:0 frame with distinct samples in each plane
+fn yuv420_4x2() -> VideoFrame {
+    let mut data: Vec<u8> = (1..=8).collect();
+    data.extend([20, 21, 30, 31]);
+    VideoFrame::new(4, 2, PixelFormat::YUV420, data, Duration::ZERO)
+}
+
+#[test]
+fn test_yuv420_planes_without_strides() {
+    let frame = yuv420_4x2();
+
+    assert_eq!(frame.plane(0), [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(frame.plane(1), [20, 21]);
+    assert_eq!(frame.plane(2), [30, 31]);
+    assert!(frame.plane(3).is_empty());
+    assert_eq!(
+        (0..4)
+            .map(|index| frame.plane_stride(index))
+            .collect::<Vec<_>>(),
+        [4, 2, 2, 0]
+    );
+}
+
+#[test]
+fn test_yuv420_planes_with_strides() {
+    // Rows padded to 6 luma and 4 chroma bytes
+    let mut frame = yuv420_4x2();
+    frame.data = vec![
+        1, 2, 3, 4, 0xEE, 0xEE, //
+        5, 6, 7, 8, 0xEE, 0xEE, //
+        20, 21, 0xEE, 0xEE, //
+        30, 31, 0xEE, 0xEE,
+    ];
+    frame.strides = Some([6, 4, 4]);
+
+    assert_eq!(frame.plane_stride(0), 6);
+    assert_eq!(frame.plane_stride(1), 4);
+    assert_eq!(
+        frame.plane(0),
+        [1, 2, 3, 4, 0xEE, 0xEE, 5, 6, 7, 8, 0xEE, 0xEE]
+    );
+    assert_eq!(frame.plane(1), [20, 21, 0xEE, 0xEE]);
+    assert_eq!(frame.plane(2), [30, 31, 0xEE, 0xEE]);
+}
+
+#[test]
+fn test_odd_sized_yuv420_planes_round_up() {
+    let frame = VideoFrame::new(
+        5,
+        3,
+        PixelFormat::YUV420,
+        vec![0; 5 * 3 + 2 * 3 * 2],
+        Duration::ZERO,
+    );
+
+    assert_eq!(frame.plane_stride(1), 3);
+    assert_eq!(frame.plane(0).len(), 15);
+    assert_eq!(frame.plane(1).len(), 6);
+    assert_eq!(frame.plane(2).len(), 6);
+}
+
+#[test]
+fn test_planes_of_truncated_data_are_cut_short() {
+    let mut frame = yuv420_4x2();
+    frame.data.truncate(9);
+
+    assert_eq!(frame.plane(0).len(), 8);
+    assert_eq!(frame.plane(1), [20]);
+    assert!(frame.plane(2).is_empty());
+}
+
+#[test]
+fn test_nv12_and_rgb_planes() {
+    let nv12 = VideoFrame::new(4, 2, PixelFormat::NV12, vec![0; 12], Duration::ZERO);
+    assert_eq!(nv12.plane_stride(1), 4);
+    assert_eq!(nv12.plane(1).len(), 4);
+    assert!(nv12.plane(2).is_empty());
+
+    let rgba = VideoFrame::new(4, 2, PixelFormat::RGBA32, vec![0; 32], Duration::ZERO);
+    assert_eq!(rgba.plane_stride(0), 16);
+    assert_eq!(rgba.plane(0).len(), 32);
+    assert_eq!(rgba.plane_stride(1), 0);
+}
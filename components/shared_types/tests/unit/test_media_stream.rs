@@ -0,0 +1,199 @@
+//! Unit tests for MediaStream and MediaStreamTrack
+
+use cortenbrowser_shared_types::{
+    AudioBuffer, AudioFormat, MediaSample, MediaStream, MediaStreamTrack, MediaStreamTrackKind,
+    MediaStreamTrackState, PixelFormat, VideoFrame,
+};
+use std::time::Duration;
+
+fn yuv_frame() -> VideoFrame {
+    // 4x2 YUV 4:2:0 frame: 8 luma and 2 + 2 chroma samples
+    VideoFrame::new(
+        4,
+        2,
+        PixelFormat::YUV420,
+        vec![200; 12],
+        Duration::from_millis(40),
+    )
+}
+
+#[tokio::test]
+async fn test_disabled_video_track_delivers_black_frames() {
+    let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+    let mut frames = track.subscribe();
+
+    track.set_enabled(false);
+    assert!(source.send(MediaSample::Video(yuv_frame())));
+    let Some(MediaSample::Video(black)) = frames.next().await else {
+        panic!("expected a video frame");
+    };
+    assert_eq!((black.width, black.height), (4, 2));
+    assert_eq!(black.timestamp, Duration::from_millis(40));
+    assert_eq!(&black.data[..8], &[16; 8]);
+    assert_eq!(&black.data[8..], &[128; 4]);
+
+    track.set_enabled(true);
+    source.send(MediaSample::Video(yuv_frame()));
+    assert_eq!(frames.next().await, Some(MediaSample::Video(yuv_frame())));
+}
+
+#[tokio::test]
+async fn test_disabled_rgba_track_is_opaque_black() {
+    let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Screen");
+    let mut frames = track.subscribe();
+    track.set_enabled(false);
+
+    let frame = VideoFrame::new(2, 1, PixelFormat::RGBA32, vec![90; 8], Duration::ZERO);
+    source.send(MediaSample::Video(frame));
+    let Some(MediaSample::Video(black)) = frames.next().await else {
+        panic!("expected a video frame");
+    };
+    assert_eq!(black.data, vec![0, 0, 0, 255, 0, 0, 0, 255]);
+}
+
+#[tokio::test]
+async fn test_disabled_audio_track_delivers_silence() {
+    let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Microphone");
+    let mut buffers = track.subscribe();
+    track.set_enabled(false);
+
+    let buffer = AudioBuffer::new(AudioFormat::F32LE, 48000, 2, vec![0.5; 960], Duration::ZERO);
+    source.send(MediaSample::Audio(buffer.clone()));
+    let Some(MediaSample::Audio(silence)) = buffers.next().await else {
+        panic!("expected an audio buffer");
+    };
+    assert_eq!(silence.samples, vec![0.0; 960]);
+    assert_eq!(silence.duration, buffer.duration);
+}
+
+#[tokio::test]
+async fn test_stop_ends_subscriptions_and_track() {
+    let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+    let mut first = track.subscribe();
+    let mut second = track.subscribe();
+
+    let waiting = tokio::spawn({
+        let track = track.clone();
+        async move { track.ended().await }
+    });
+    track.stop();
+
+    assert_eq!(track.ready_state(), MediaStreamTrackState::Ended);
+    assert_eq!(first.next().await, None);
+    assert_eq!(second.next().await, None);
+    tokio::time::timeout(Duration::from_secs(1), waiting)
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Nothing is delivered once stopped, and late subscribers end at once
+    assert!(!source.send(MediaSample::Video(yuv_frame())));
+    assert_eq!(track.subscribe().next().await, None);
+}
+
+#[tokio::test]
+async fn test_dropped_source_ends_all_clones() {
+    let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Microphone");
+    let copy = track.clone_track();
+    let mut subscription = copy.subscribe();
+
+    drop(source);
+    assert_eq!(track.ready_state(), MediaStreamTrackState::Ended);
+    assert_eq!(copy.ready_state(), MediaStreamTrackState::Ended);
+    assert_eq!(subscription.next().await, None);
+}
+
+#[tokio::test]
+async fn test_clone_track_is_independent() {
+    let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+    let copy = track.clone_track();
+    assert_ne!(copy.id(), track.id());
+    assert_eq!(copy.label(), "Camera");
+    let mut original_frames = track.subscribe();
+    let mut copy_frames = copy.subscribe();
+
+    // Disabling one track leaves the other's media alone
+    copy.set_enabled(false);
+    source.send(MediaSample::Video(yuv_frame()));
+    assert_eq!(
+        original_frames.next().await,
+        Some(MediaSample::Video(yuv_frame()))
+    );
+    assert_ne!(
+        copy_frames.next().await,
+        Some(MediaSample::Video(yuv_frame()))
+    );
+
+    // The source stays live until every track has stopped
+    track.stop();
+    assert!(source.send(MediaSample::Video(yuv_frame())));
+    assert!(copy_frames.next().await.is_some());
+    copy.stop();
+    assert!(!source.send(MediaSample::Video(yuv_frame())));
+}
+
+#[tokio::test]
+async fn test_muted_is_set_by_source() {
+    let (track, source) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Remote");
+    assert!(!track.muted());
+    source.set_muted(true);
+    assert!(track.muted());
+    assert!(track.enabled());
+}
+
+#[test]
+fn test_stream_adds_and_removes_tracks_in_order() {
+    let (video, _camera) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+    let (audio, _microphone) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Microphone");
+    let stream = MediaStream::new();
+    assert!(!stream.active());
+
+    assert!(stream.add_track(audio.clone()));
+    assert!(stream.add_track(video.clone()));
+    assert!(!stream.add_track(video.clone()));
+    assert_eq!(stream.tracks(), vec![audio.clone(), video.clone()]);
+    assert_eq!(stream.audio_tracks(), vec![audio.clone()]);
+    assert_eq!(stream.track(video.id()), Some(video.clone()));
+    assert!(stream.active());
+
+    assert!(stream.remove_track(&audio));
+    assert!(!stream.remove_track(&audio));
+    assert_eq!(stream.tracks(), vec![video]);
+    assert_eq!(audio.ready_state(), MediaStreamTrackState::Live);
+}
+
+#[test]
+fn test_clone_stream_clones_tracks() {
+    let (video, _camera) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+    let stream = MediaStream::with_tracks(vec![video.clone()]);
+    let copy = stream.clone_stream();
+
+    assert_ne!(copy.id(), stream.id());
+    let tracks = copy.tracks();
+    assert_eq!(tracks.len(), 1);
+    assert_ne!(tracks[0], video);
+    assert_eq!(tracks[0].kind(), MediaStreamTrackKind::Video);
+}
+
+#[tokio::test]
+async fn test_stream_ends_when_last_track_stops() {
+    let (video, _camera) = MediaStreamTrack::new(MediaStreamTrackKind::Video, "Camera");
+    let (audio, _microphone) = MediaStreamTrack::new(MediaStreamTrackKind::Audio, "Microphone");
+    let stream = std::sync::Arc::new(MediaStream::with_tracks(vec![video.clone(), audio.clone()]));
+
+    let ended = tokio::spawn({
+        let stream = std::sync::Arc::clone(&stream);
+        async move { stream.ended().await }
+    });
+    video.stop();
+    tokio::task::yield_now().await;
+    assert!(!ended.is_finished());
+
+    // Removing the last live track leaves the stream inactive too
+    stream.remove_track(&audio);
+    tokio::time::timeout(Duration::from_secs(1), ended)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!stream.active());
+}
@@ -1,8 +1,8 @@
 //! Unit tests for trait definitions
 
 use cortenbrowser_shared_types::{
-    AudioBuffer, AudioDecoder, AudioPacket, Demuxer, MediaError, MediaInfo, MediaSource, SessionId,
-    VideoDecoder, VideoFrame, VideoPacket,
+    AudioBuffer, AudioDecoder, AudioPacket, AudioSink, Demuxer, MediaError, MediaInfo, MediaSource,
+    PlaybackStats, SessionId, VideoDecoder, VideoFrame, VideoPacket,
 };
 use std::time::Duration;
 use tokio::sync::mpsc;
@@ -52,6 +52,8 @@ impl AudioDecoder for MockAudioDecoder {
     fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
         Ok(Vec::new())
     }
+
+    fn reset(&mut self) {}
 }
 
 #[test]
@@ -115,3 +117,58 @@ fn test_trait_object_video_decoder() {
     let frames = decoder.flush().unwrap();
     assert_eq!(frames.len(), 0);
 }
+
+struct MockAudioSink {
+    played: std::sync::Mutex<Duration>,
+}
+
+impl AudioSink for MockAudioSink {
+    fn write(&self, buffer: AudioBuffer) -> Result<(), MediaError> {
+        *self.played.lock().unwrap() += buffer.duration;
+        Ok(())
+    }
+
+    fn played_duration(&self) -> Duration {
+        *self.played.lock().unwrap()
+    }
+
+    fn set_volume(&self, _volume: f32) {}
+}
+
+#[test]
+fn test_audio_sink_trait_object() {
+    let sink: std::sync::Arc<dyn AudioSink> = std::sync::Arc::new(MockAudioSink {
+        played: std::sync::Mutex::new(Duration::ZERO),
+    });
+    let buffer = AudioBuffer::new(
+        cortenbrowser_shared_types::AudioFormat::F32LE,
+        48000,
+        1,
+        vec![0.0; 480],
+        Duration::ZERO,
+    );
+
+    sink.write(buffer).unwrap();
+
+    assert_eq!(sink.played_duration(), Duration::from_millis(10));
+}
+
+#[test]
+fn test_playback_stats_serialize() {
+    let stats = PlaybackStats {
+        frames_decoded: 120,
+        frames_dropped: 2,
+        decode_time_p50_ms: 1.5,
+        buffered_ahead_ms: 2000.0,
+        ..Default::default()
+    };
+
+    let json = serde_json::to_value(&stats).unwrap();
+    assert_eq!(json["frames_decoded"], 120);
+    assert_eq!(json["frames_dropped"], 2);
+    assert_eq!(json["decode_time_p50_ms"], 1.5);
+    assert_eq!(json["buffered_ahead_ms"], 2000.0);
+
+    let parsed: PlaybackStats = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed, stats);
+}
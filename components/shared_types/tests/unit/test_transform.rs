@@ -0,0 +1,283 @@
+//! Unit tests for video frame cropping and scaling
+
+use cortenbrowser_shared_types::{ColorSpace, MediaError, PixelFormat, VideoFrame};
+use std::time::Duration;
+
+/// YUV420 frame whose samples encode their plane and position, so that
+/// cropped samples can be traced back to their source
+fn pattern(width: u32, height: u32) -> VideoFrame {
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut data = Vec::new();
+    for y in 0..height {
+        data.extend((0..width).map(|x| ((x + 3 * y) % 251) as u8));
+    }
+    for offset in [50, 150] {
+        for y in 0..chroma_height {
+            data.extend((0..chroma_width).map(|x| ((offset + x + 7 * y) % 251) as u8));
+        }
+    }
+
+    let mut frame = VideoFrame::new(
+        width,
+        height,
+        PixelFormat::YUV420,
+        data,
+        Duration::from_millis(40),
+    );
+    frame.duration = Some(Duration::from_millis(33));
+    frame.metadata.pts = Some(3600);
+    frame.metadata.color_space = ColorSpace::BT709;
+    frame
+}
+
+/// YUV420 frame of one luma and one chroma value
+fn solid(width: u32, height: u32, luma: u8, chroma: u8) -> VideoFrame {
+    let luma_size = (width * height) as usize;
+    let chroma_size = (width.div_ceil(2) * height.div_ceil(2)) as usize;
+    let mut data = vec![luma; luma_size];
+    data.resize(luma_size + 2 * chroma_size, chroma);
+    VideoFrame::new(width, height, PixelFormat::YUV420, data, Duration::ZERO)
+}
+
+fn luma(frame: &VideoFrame, x: u32, y: u32) -> u8 {
+    frame.data[(y * frame.width + x) as usize]
+}
+
+/// Sample of the U (`plane` 0) or V (`plane` 1) plane
+fn chroma(frame: &VideoFrame, plane: u32, x: u32, y: u32) -> u8 {
+    let chroma_width = frame.width.div_ceil(2);
+    let chroma_size = chroma_width * frame.height.div_ceil(2);
+    frame.data[(frame.width * frame.height + plane * chroma_size + y * chroma_width + x) as usize]
+}
+
+#[test]
+fn test_crop_top_left() {
+    let frame = pattern(1280, 720);
+
+    let cropped = frame.crop(0, 0, 320, 240).unwrap();
+
+    assert_eq!(cropped.width, 320);
+    assert_eq!(cropped.height, 240);
+    assert_eq!(cropped.format, PixelFormat::YUV420);
+    assert_eq!(cropped.data.len(), 320 * 240 * 3 / 2);
+    assert_eq!(cropped.data[0], frame.data[0]);
+    assert_eq!(chroma(&cropped, 0, 0, 0), chroma(&frame, 0, 0, 0));
+    assert_eq!(chroma(&cropped, 1, 0, 0), chroma(&frame, 1, 0, 0));
+}
+
+#[test]
+fn test_crop_region_samples() {
+    let frame = pattern(1280, 720);
+
+    let cropped = frame.crop(100, 60, 320, 240).unwrap();
+
+    for (x, y) in [(0, 0), (319, 0), (0, 239), (319, 239), (17, 101)] {
+        assert_eq!(luma(&cropped, x, y), luma(&frame, x + 100, y + 60));
+    }
+    for plane in 0..2 {
+        for (x, y) in [(0, 0), (159, 119), (33, 7)] {
+            assert_eq!(
+                chroma(&cropped, plane, x, y),
+                chroma(&frame, plane, x + 50, y + 30)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_crop_odd_size_at_edge() {
+    let frame = pattern(9, 7);
+
+    let cropped = frame.crop(4, 2, 5, 5).unwrap();
+
+    assert_eq!(cropped.data.len(), 5 * 5 + 2 * 3 * 3);
+    assert_eq!(luma(&cropped, 4, 4), luma(&frame, 8, 6));
+    assert_eq!(chroma(&cropped, 1, 2, 2), chroma(&frame, 1, 4, 3));
+}
+
+#[test]
+fn test_crop_preserves_timing_and_metadata() {
+    let frame = pattern(64, 48);
+
+    let cropped = frame.crop(16, 16, 32, 16).unwrap();
+
+    assert_eq!(cropped.timestamp, frame.timestamp);
+    assert_eq!(cropped.duration, frame.duration);
+    assert_eq!(cropped.metadata, frame.metadata);
+}
+
+#[test]
+fn test_crop_rejects_unaligned_offset() {
+    let frame = pattern(64, 48);
+
+    assert!(matches!(
+        frame.crop(1, 0, 16, 16),
+        Err(MediaError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        frame.crop(0, 3, 16, 16),
+        Err(MediaError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_crop_rejects_region_outside_frame() {
+    let frame = pattern(64, 48);
+
+    for (x, y, width, height) in [
+        (0, 0, 65, 48),
+        (32, 0, 34, 16),
+        (0, 40, 16, 10),
+        (0, 0, 0, 16),
+    ] {
+        assert!(
+            matches!(
+                frame.crop(x, y, width, height),
+                Err(MediaError::InvalidParameter(_))
+            ),
+            "{}x{} at ({}, {})",
+            width,
+            height,
+            x,
+            y
+        );
+    }
+    assert!(frame.crop(0, 0, 64, 48).is_ok());
+}
+
+#[test]
+fn test_scale_output_size() {
+    let frame = pattern(1280, 720);
+
+    let scaled = frame.scale(640, 360).unwrap();
+    assert_eq!((scaled.width, scaled.height), (640, 360));
+    assert_eq!(scaled.data.len(), 640 * 360 * 3 / 2);
+
+    let scaled = frame.scale(99, 55).unwrap();
+    assert_eq!(scaled.data.len(), 99 * 55 + 2 * 50 * 28);
+}
+
+#[test]
+fn test_scale_keeps_solid_color() {
+    let frame = solid(1280, 720, 81, 90);
+
+    for (width, height) in [(320, 180), (1920, 1080), (7, 5)] {
+        let scaled = frame.scale(width, height).unwrap();
+        let luma_size = (width * height) as usize;
+        assert!(scaled.data[..luma_size].iter().all(|&s| s == 81));
+        assert!(scaled.data[luma_size..].iter().all(|&s| s == 90));
+    }
+}
+
+#[test]
+fn test_scale_interpolates_bilinearly() {
+    // 2x2 luma with a horizontal and vertical ramp
+    let frame = VideoFrame::new(
+        2,
+        2,
+        PixelFormat::YUV420,
+        vec![0, 100, 100, 200, 40, 240],
+        Duration::ZERO,
+    );
+
+    let scaled = frame.scale(4, 4).unwrap();
+
+    // Samples a quarter of the way between source samples
+    let expected_luma = [
+        0, 25, 75, 100, //
+        25, 50, 100, 125, //
+        75, 100, 150, 175, //
+        100, 125, 175, 200,
+    ];
+    assert_eq!(&scaled.data[..16], expected_luma);
+    // The single chroma samples are spread over 2x2 planes
+    assert_eq!(&scaled.data[16..], [40, 40, 40, 40, 240, 240, 240, 240]);
+}
+
+#[test]
+fn test_scale_downscale_averages_neighbors() {
+    // Columns alternating between 0 and 200
+    let mut frame = solid(8, 2, 0, 128);
+    for x in (1..8).step_by(2) {
+        frame.data[x] = 200;
+        frame.data[8 + x] = 200;
+    }
+
+    let scaled = frame.scale(4, 2).unwrap();
+
+    assert!(scaled.data[..8].iter().all(|&s| s == 100));
+}
+
+#[test]
+fn test_scale_preserves_timing_and_metadata() {
+    let frame = pattern(64, 48);
+
+    let scaled = frame.scale(32, 24).unwrap();
+
+    assert_eq!(scaled.timestamp, frame.timestamp);
+    assert_eq!(scaled.duration, frame.duration);
+    assert_eq!(scaled.metadata, frame.metadata);
+}
+
+#[test]
+fn test_scale_rejects_empty_target() {
+    let frame = pattern(64, 48);
+
+    assert!(matches!(
+        frame.scale(0, 24),
+        Err(MediaError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        frame.scale(32, 0),
+        Err(MediaError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_transforms_require_yuv420() {
+    let frame = VideoFrame::new(4, 4, PixelFormat::RGB24, vec![0; 48], Duration::ZERO);
+
+    assert!(matches!(
+        frame.crop(0, 0, 2, 2),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+    assert!(matches!(
+        frame.scale(2, 2),
+        Err(MediaError::UnsupportedFormat { .. })
+    ));
+}
+
+#[test]
+fn test_transforms_reject_mismatched_data() {
+    let frame = VideoFrame::new(4, 4, PixelFormat::YUV420, vec![0; 16], Duration::ZERO);
+
+    assert!(matches!(
+        frame.crop(0, 0, 2, 2),
+        Err(MediaError::InvalidParameter(_))
+    ));
+    assert!(matches!(
+        frame.scale(2, 2),
+        Err(MediaError::InvalidParameter(_))
+    ));
+}
+
+#[test]
+fn test_crop_and_scale_skip_row_padding() {
+    let frame = pattern(20, 10);
+    let mut padded = frame.clone();
+    padded.data.clear();
+    for index in 0..3 {
+        let width = frame.plane_stride(index);
+        for row in frame.plane(index).chunks(width) {
+            padded.data.extend_from_slice(row);
+            padded.data.resize(padded.data.len() + 12, 0xEE);
+        }
+    }
+    padded.strides = Some([32, 22, 22]);
+
+    assert_eq!(
+        padded.crop(4, 2, 8, 6).unwrap(),
+        frame.crop(4, 2, 8, 6).unwrap()
+    );
+    assert_eq!(padded.scale(10, 5).unwrap(), frame.scale(10, 5).unwrap());
+}
@@ -64,8 +64,8 @@ impl AV1Decoder {
         let width = picture.width();
         let height = picture.height();
 
-        // Get picture planes based on pixel layout
-        let data = match picture.pixel_layout() {
+        // Get picture planes based on pixel layout, with dav1d's padded rows
+        let (data, strides) = match picture.pixel_layout() {
             PixelLayout::I420 => {
                 // YUV420 format
                 let stride_y = picture.stride(PlanarImageComponent::Y) as usize;
@@ -85,7 +85,7 @@ impl AV1Decoder {
                 data.extend_from_slice(plane_y.as_ref());
                 data.extend_from_slice(plane_u.as_ref());
                 data.extend_from_slice(plane_v.as_ref());
-                data
+                (data, [stride_y, stride_u, stride_v])
             }
             _ => {
                 return Err(MediaError::CodecError {
@@ -107,6 +107,7 @@ impl AV1Decoder {
             height: height as u32,
             format: PixelFormat::YUV420,
             data,
+            strides: Some(strides),
             timestamp,
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata {
@@ -114,6 +115,7 @@ impl AV1Decoder {
                 pts,
                 dts: None,
                 sequence: Some(self.frame_count - 1),
+                ..Default::default()
             },
         })
     }
@@ -183,6 +185,7 @@ mod tests {
             pts: None,
             dts: None,
             is_keyframe: false,
+            encryption: None,
         };
 
         let result = decoder.decode(&packet);
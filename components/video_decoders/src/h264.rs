@@ -3,7 +3,8 @@
 //! This module provides H.264 decoding using the openh264 library.
 
 use cortenbrowser_shared_types::{
-    FrameMetadata, MediaError, PixelFormat, VideoDecoder, VideoFrame, VideoPacket,
+    AvcDecoderConfig, FrameMetadata, MediaError, PixelFormat, VideoDecoder, VideoFrame,
+    VideoPacket,
 };
 use openh264::decoder::Decoder as OpenH264Decoder;
 use openh264::formats::YUVSource;
@@ -85,20 +86,18 @@ impl VideoDecoder for H264Decoder {
                 // Get dimensions using dimensions() method from YUVSource trait
                 let (width, height) = yuv_frame.dimensions();
 
-                // Get YUV planes
-                let y_plane = yuv_frame.y();
-                let u_plane = yuv_frame.u();
-                let v_plane = yuv_frame.v();
-
-                // For YUV420, calculate sizes
-                let y_size = width * height;
-                let uv_size = width * height / 4;
+                // Get YUV planes, whose rows OpenH264 pads
+                let (stride_y, stride_u, stride_v) = yuv_frame.strides();
+                let chroma_height = height.div_ceil(2);
+                let y_plane = rows(yuv_frame.y(), stride_y, height);
+                let u_plane = rows(yuv_frame.u(), stride_u, chroma_height);
+                let v_plane = rows(yuv_frame.v(), stride_v, chroma_height);
 
                 // Create frame data
-                let mut data = Vec::with_capacity(y_size + uv_size * 2);
-                data.extend_from_slice(&y_plane[..y_size]);
-                data.extend_from_slice(&u_plane[..uv_size]);
-                data.extend_from_slice(&v_plane[..uv_size]);
+                let mut data = Vec::with_capacity(y_plane.len() + u_plane.len() + v_plane.len());
+                data.extend_from_slice(y_plane);
+                data.extend_from_slice(u_plane);
+                data.extend_from_slice(v_plane);
 
                 // Calculate timestamp
                 let timestamp = if let Some(pts_value) = pts {
@@ -116,6 +115,7 @@ impl VideoDecoder for H264Decoder {
                     height: height as u32,
                     format: PixelFormat::YUV420,
                     data,
+                    strides: Some([stride_y, stride_u, stride_v]),
                     timestamp,
                     duration: Some(Duration::from_millis(33)),
                     metadata: FrameMetadata {
@@ -123,6 +123,7 @@ impl VideoDecoder for H264Decoder {
                         pts,
                         dts,
                         sequence: Some(self.frame_count - 1),
+                        ..Default::default()
                     },
                 })
             }
@@ -135,6 +136,18 @@ impl VideoDecoder for H264Decoder {
         }
     }
 
+    /// Feeds the SPS and PPS of an `avcC` record to OpenH264, for streams
+    /// that carry no parameter sets in-band
+    fn configure(&mut self, extradata: &[u8]) -> Result<(), MediaError> {
+        let config = AvcDecoderConfig::parse(extradata)?;
+        self.decoder
+            .decode(&config.to_annex_b())
+            .map_err(|e| MediaError::CodecError {
+                details: format!("H.264 parameter set error: {:?}", e),
+            })?;
+        Ok(())
+    }
+
     fn flush(&mut self) -> Result<Vec<VideoFrame>, MediaError> {
         // OpenH264 doesn't require explicit flushing
         // Return empty vec as there are no buffered frames
@@ -142,6 +155,11 @@ impl VideoDecoder for H264Decoder {
     }
 }
 
+/// The first `count` rows of a plane `stride` bytes per row
+fn rows(plane: &[u8], stride: usize, count: usize) -> &[u8] {
+    &plane[..(stride * count).min(plane.len())]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,9 +178,18 @@ mod tests {
             pts: None,
             dts: None,
             is_keyframe: false,
+            encryption: None,
         };
 
         let result = decoder.decode(&packet);
         assert!(result.is_err(), "Empty packet should return error");
     }
+
+    #[test]
+    fn test_configure_rejects_invalid_avcc() {
+        let mut decoder = H264Decoder::new().unwrap();
+
+        let result = decoder.configure(&[0x01, 0x42, 0x00]);
+        assert!(matches!(result, Err(MediaError::CodecError { .. })));
+    }
 }
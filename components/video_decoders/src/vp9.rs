@@ -83,10 +83,12 @@ impl VP9Decoder {
         let width = img.d_w;
         let height = img.d_h;
 
-        // For YUV420, calculate total data size
-        let y_size = (img.stride[0] as u32 * height) as usize;
-        let u_size = (img.stride[1] as u32 * height / 2) as usize;
-        let v_size = (img.stride[2] as u32 * height / 2) as usize;
+        // For YUV420, calculate total data size; the chroma planes have
+        // half the rows, rounded up for odd heights
+        let chroma_height = (height as usize).div_ceil(2);
+        let y_size = img.stride[0] as usize * height as usize;
+        let u_size = img.stride[1] as usize * chroma_height;
+        let v_size = img.stride[2] as usize * chroma_height;
 
         // Copy plane data
         let mut data = Vec::with_capacity(y_size + u_size + v_size);
@@ -114,6 +116,11 @@ impl VP9Decoder {
             height,
             format: PixelFormat::YUV420,
             data,
+            strides: Some([
+                img.stride[0] as usize,
+                img.stride[1] as usize,
+                img.stride[2] as usize,
+            ]),
             timestamp,
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata {
@@ -121,6 +128,7 @@ impl VP9Decoder {
                 pts,
                 dts: None,
                 sequence: Some(self.frame_count - 1),
+                ..Default::default()
             },
         }
     }
@@ -211,9 +219,34 @@ mod tests {
             pts: None,
             dts: None,
             is_keyframe: false,
+            encryption: None,
         };
 
         let result = decoder.decode(&packet);
         assert!(result.is_err(), "Empty packet should return error");
     }
+
+    #[test]
+    fn test_odd_height_copies_last_chroma_row() {
+        let mut decoder = VP9Decoder::new().unwrap();
+        // 3x3 image: three luma rows and two chroma rows per plane
+        let mut y = vec![0x10u8; 4 * 3];
+        let mut u = vec![0x20u8, 0x21, 0x22, 0x23];
+        let mut v = vec![0x30u8, 0x31, 0x32, 0x33];
+        let mut img = unsafe { std::mem::zeroed::<vpx_sys::vpx_image_t>() };
+        img.d_w = 3;
+        img.d_h = 3;
+        img.planes[0] = y.as_mut_ptr();
+        img.planes[1] = u.as_mut_ptr();
+        img.planes[2] = v.as_mut_ptr();
+        img.stride[0] = 4;
+        img.stride[1] = 2;
+        img.stride[2] = 2;
+
+        let frame = decoder.vpx_img_to_video_frame(&img, Some(0));
+
+        assert_eq!(frame.strides, Some([4, 2, 2]));
+        assert_eq!(frame.data.len(), 12 + 4 + 4);
+        assert_eq!(&frame.data[12..], &[u, v].concat()[..]);
+    }
 }
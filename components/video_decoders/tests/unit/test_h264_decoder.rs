@@ -30,6 +30,7 @@ fn test_h264_decoder_decode_valid_packet() {
         pts: Some(0),
         dts: Some(0),
         is_keyframe: true,
+        encryption: None,
     };
 
     let result = decoder.decode(&packet);
@@ -56,6 +57,7 @@ fn test_h264_decoder_decode_invalid_packet() {
         pts: Some(0),
         dts: Some(0),
         is_keyframe: false,
+        encryption: None,
     };
 
     let result = decoder.decode(&packet);
@@ -85,6 +87,7 @@ fn test_h264_decoder_flush() {
         pts: Some(0),
         dts: Some(0),
         is_keyframe: true,
+        encryption: None,
     };
 
     let _ = decoder.decode(&packet);
@@ -114,12 +117,14 @@ fn test_h264_decoder_multiple_packets() {
             pts: Some(0),
             dts: Some(0),
             is_keyframe: true,
+            encryption: None,
         },
         VideoPacket {
             data: create_test_h264_pframe(),
             pts: Some(33),
             dts: Some(33),
             is_keyframe: false,
+            encryption: None,
         },
     ];
 
@@ -28,6 +28,7 @@ fn test_vp9_decoder_decode_valid_packet() {
         pts: Some(0),
         dts: Some(0),
         is_keyframe: true,
+        encryption: None,
     };
 
     let result = decoder.decode(&packet);
@@ -53,6 +54,7 @@ fn test_vp9_decoder_decode_invalid_packet() {
         pts: Some(0),
         dts: Some(0),
         is_keyframe: false,
+        encryption: None,
     };
 
     let result = decoder.decode(&packet);
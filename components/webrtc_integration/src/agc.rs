@@ -0,0 +1,180 @@
+//! Automatic gain control for WebRTC audio
+//!
+//! Normalizes microphone levels so that quiet and loud talkers are sent at a
+//! similar loudness.
+//!
+//! # Algorithm
+//!
+//! For each frame:
+//!
+//! 1. Compute the frame RMS level
+//! 2. Compute the gain needed to bring the RMS to the target level
+//! 3. Move the current gain toward the needed gain, limited to
+//!    `attack_rate` dB/second when reducing gain and `release_rate`
+//!    dB/second when increasing it
+//! 4. Multiply the frame by the current gain
+//! 5. Hard-limit the output at 0 dBFS
+//!
+//! Frames below a silence threshold do not update the gain, so the
+//! controller does not amplify background noise during pauses.
+//!
+//! # Example
+//!
+//! ```
+//! use cortenbrowser_webrtc_integration::AutoGainController;
+//!
+//! let mut agc = AutoGainController::new(48000, 0.1);
+//!
+//! let quiet = vec![0.01f32; 480];
+//! let output = agc.process(&quiet);
+//! assert_eq!(output.len(), 480);
+//! ```
+
+/// Default gain reduction rate in dB/second
+const DEFAULT_ATTACK_RATE: f32 = 60.0;
+
+/// Default gain increase rate in dB/second
+const DEFAULT_RELEASE_RATE: f32 = 10.0;
+
+/// Maximum gain applied by the controller in dB
+const MAX_GAIN_DB: f32 = 30.0;
+
+/// Minimum gain applied by the controller in dB
+const MIN_GAIN_DB: f32 = -30.0;
+
+/// Frames with an RMS below this level are treated as silence
+const SILENCE_RMS: f32 = 1e-4;
+
+/// Automatic gain controller
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::AutoGainController;
+///
+/// let agc = AutoGainController::new(16000, 0.1).with_rates(90.0, 6.0);
+/// assert_eq!(agc.target_rms(), 0.1);
+/// assert_eq!(agc.current_gain(), 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AutoGainController {
+    sample_rate: u32,
+    target_rms: f32,
+    attack_rate: f32,
+    release_rate: f32,
+    current_gain: f32,
+}
+
+impl AutoGainController {
+    /// Create a new gain controller
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Audio sample rate in Hz (e.g., 16000, 48000)
+    /// * `target_rms` - Target RMS level (linear, 0.0 to 1.0)
+    pub fn new(sample_rate: u32, target_rms: f32) -> Self {
+        Self {
+            sample_rate,
+            target_rms,
+            attack_rate: DEFAULT_ATTACK_RATE,
+            release_rate: DEFAULT_RELEASE_RATE,
+            current_gain: 1.0,
+        }
+    }
+
+    /// Set the attack (gain reduction) and release (gain increase) rates
+    ///
+    /// # Arguments
+    ///
+    /// * `attack_rate` - Maximum gain reduction in dB/second
+    /// * `release_rate` - Maximum gain increase in dB/second
+    pub fn with_rates(mut self, attack_rate: f32, release_rate: f32) -> Self {
+        self.attack_rate = attack_rate;
+        self.release_rate = release_rate;
+        self
+    }
+
+    /// Get the target RMS level
+    pub fn target_rms(&self) -> f32 {
+        self.target_rms
+    }
+
+    /// Get the gain currently applied (linear)
+    pub fn current_gain(&self) -> f32 {
+        self.current_gain
+    }
+
+    /// Process one audio frame
+    ///
+    /// # Arguments
+    ///
+    /// * `frame` - Mono time-domain samples
+    ///
+    /// # Returns
+    ///
+    /// Gain-adjusted samples, hard-limited to [-1.0, 1.0]
+    pub fn process(&mut self, frame: &[f32]) -> Vec<f32> {
+        if frame.is_empty() {
+            return Vec::new();
+        }
+
+        let rms = (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt();
+
+        if rms > SILENCE_RMS {
+            let frame_secs = frame.len() as f32 / self.sample_rate as f32;
+            let needed_db =
+                (20.0 * (self.target_rms / rms).log10()).clamp(MIN_GAIN_DB, MAX_GAIN_DB);
+            let current_db = 20.0 * self.current_gain.log10();
+
+            let delta = needed_db - current_db;
+            let max_step = if delta < 0.0 {
+                self.attack_rate * frame_secs
+            } else {
+                self.release_rate * frame_secs
+            };
+            let new_db = current_db + delta.clamp(-max_step, max_step);
+
+            self.current_gain = 10f32.powf(new_db / 20.0);
+        }
+
+        frame
+            .iter()
+            .map(|s| (s * self.current_gain).clamp(-1.0, 1.0))
+            .collect()
+    }
+
+    /// Reset the gain to unity
+    pub fn reset(&mut self) {
+        self.current_gain = 1.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agc_creation() {
+        let agc = AutoGainController::new(48000, 0.1);
+        assert_eq!(agc.sample_rate, 48000);
+        assert_eq!(agc.attack_rate, DEFAULT_ATTACK_RATE);
+        assert_eq!(agc.release_rate, DEFAULT_RELEASE_RATE);
+    }
+
+    #[test]
+    fn test_agc_silence_keeps_gain() {
+        let mut agc = AutoGainController::new(48000, 0.1);
+        agc.process(&[0.0f32; 480]);
+        assert_eq!(agc.current_gain(), 1.0);
+    }
+
+    #[test]
+    fn test_agc_reset() {
+        let mut agc = AutoGainController::new(48000, 0.1);
+        agc.process(&[0.01f32; 480]);
+        assert!(agc.current_gain() > 1.0);
+
+        agc.reset();
+        assert_eq!(agc.current_gain(), 1.0);
+    }
+}
@@ -0,0 +1,275 @@
+//! Capture-side audio processing chain
+//!
+//! Chains the individual audio processing stages in the order used for
+//! microphone audio:
+//!
+//! ```text
+//! Microphone ─> NoiseSuppressor ─> AutoGainController ─> EchoCanceller ─> Encoder
+//! ```
+//!
+//! Each stage is enabled through [`AudioProcessingConfig`]. Disabled stages
+//! are skipped entirely.
+
+use crate::agc::AutoGainController;
+use crate::echo_cancellation::EchoCanceller;
+use crate::noise_suppression::NoiseSuppressor;
+use cortenbrowser_shared_types::{AudioBuffer, AudioProcessingConfig};
+use std::collections::VecDeque;
+
+/// Noise suppression frames per second, i.e. 10 ms frames
+const NOISE_FRAMES_PER_SEC: u32 = 100;
+
+/// Processing stages for a single channel
+struct ChannelChain {
+    noise: Option<NoiseStage>,
+    agc: Option<AutoGainController>,
+    echo_canceller: Option<EchoCanceller>,
+}
+
+/// Noise suppression of frames of any size
+///
+/// The suppressor works on fixed 10 ms frames, so samples are queued until
+/// a whole frame is available, delaying the output by one frame.
+struct NoiseStage {
+    suppressor: NoiseSuppressor,
+    /// Samples not yet making up a whole frame
+    pending: Vec<f32>,
+    /// Suppressed samples not yet returned, starting with one frame of
+    /// silence
+    output: VecDeque<f32>,
+}
+
+impl NoiseStage {
+    fn new(sample_rate: u32) -> Self {
+        let frame_size = (sample_rate / NOISE_FRAMES_PER_SEC).max(1) as usize;
+        Self {
+            suppressor: NoiseSuppressor::new(sample_rate, frame_size),
+            pending: Vec::with_capacity(frame_size),
+            output: vec![0.0; frame_size].into(),
+        }
+    }
+
+    /// Suppress noise in `samples`, returning as many samples
+    fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        let frame_size = self.suppressor.frame_size();
+        self.pending.extend_from_slice(samples);
+        let whole = self.pending.len() - self.pending.len() % frame_size;
+        for frame in self.pending[..whole].chunks_exact(frame_size) {
+            self.output.extend(self.suppressor.process(frame));
+        }
+        self.pending.drain(..whole);
+
+        // At least the frame of silence minus the pending samples is left
+        self.output.drain(..samples.len()).collect()
+    }
+}
+
+/// Audio processing chain for microphone audio
+///
+/// Processes mono frames with [`AudioProcessingChain::process`], or
+/// interleaved [`AudioBuffer`]s with [`AudioProcessingChain::process_buffer`],
+/// in which case each channel keeps its own processing state. Frames may be
+/// of any size; noise suppression delays the audio by 10 ms.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::AudioProcessingChain;
+/// use cortenbrowser_shared_types::AudioProcessingConfig;
+///
+/// let config = AudioProcessingConfig::new()
+///     .with_noise_suppression(true)
+///     .with_auto_gain_control(true);
+///
+/// let mut chain = AudioProcessingChain::new(48000, config);
+///
+/// let frame = vec![0.0f32; 480];
+/// let output = chain.process(&frame, None);
+/// assert_eq!(output.len(), 480);
+/// ```
+pub struct AudioProcessingChain {
+    sample_rate: u32,
+    config: AudioProcessingConfig,
+    channels: Vec<ChannelChain>,
+}
+
+impl AudioProcessingChain {
+    /// Create a new processing chain
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Audio sample rate in Hz
+    /// * `config` - Which processing stages to enable
+    pub fn new(sample_rate: u32, config: AudioProcessingConfig) -> Self {
+        Self {
+            sample_rate,
+            config,
+            channels: Vec::new(),
+        }
+    }
+
+    /// Get the processing configuration
+    pub fn config(&self) -> &AudioProcessingConfig {
+        &self.config
+    }
+
+    /// Process one mono frame
+    ///
+    /// # Arguments
+    ///
+    /// * `near_end` - Microphone samples
+    /// * `far_end` - Speaker samples used as the echo reference, if available.
+    ///   Echo cancellation is skipped without a reference.
+    pub fn process(&mut self, near_end: &[f32], far_end: Option<&[f32]>) -> Vec<f32> {
+        self.process_channel(0, near_end, far_end)
+    }
+
+    /// Process an interleaved audio buffer
+    ///
+    /// Each channel is processed independently.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - Microphone audio
+    /// * `far_end` - Speaker audio played during `buffer`, at its sample
+    ///   rate, used as the echo reference if available. Each channel is
+    ///   cancelled against the far-end channel of the same index, or the
+    ///   last one if the far end has fewer channels. Echo cancellation is
+    ///   skipped without a reference.
+    pub fn process_buffer(
+        &mut self,
+        buffer: &AudioBuffer,
+        far_end: Option<&AudioBuffer>,
+    ) -> AudioBuffer {
+        let channels = buffer.channels.max(1) as usize;
+        let mut samples = vec![0.0f32; buffer.samples.len()];
+
+        for channel in 0..channels {
+            let input = deinterleave(buffer, channel);
+            let reference = far_end.map(|far_end| {
+                let far_channels = far_end.channels.max(1) as usize;
+                deinterleave(far_end, channel.min(far_channels - 1))
+            });
+            let output = self.process_channel(channel, &input, reference.as_deref());
+
+            for (i, sample) in output.into_iter().enumerate().take(input.len()) {
+                samples[i * channels + channel] = sample;
+            }
+        }
+
+        AudioBuffer {
+            samples,
+            ..buffer.clone()
+        }
+    }
+
+    /// Reset the state of all processing stages
+    pub fn reset(&mut self) {
+        self.channels.clear();
+    }
+
+    fn process_channel(
+        &mut self,
+        channel: usize,
+        near_end: &[f32],
+        far_end: Option<&[f32]>,
+    ) -> Vec<f32> {
+        while self.channels.len() <= channel {
+            self.channels.push(ChannelChain {
+                noise: self
+                    .config
+                    .noise_suppression
+                    .then(|| NoiseStage::new(self.sample_rate)),
+                agc: self.config.auto_gain_control.then(|| {
+                    AutoGainController::new(self.sample_rate, self.config.agc_target_rms)
+                        .with_rates(self.config.agc_attack_rate, self.config.agc_release_rate)
+                }),
+                echo_canceller: self
+                    .config
+                    .echo_cancellation
+                    .then(|| EchoCanceller::new(self.sample_rate, self.config.echo_filter_length)),
+            });
+        }
+
+        let chain = &mut self.channels[channel];
+        let mut frame = near_end.to_vec();
+
+        if let Some(noise) = chain.noise.as_mut() {
+            frame = noise.process(&frame);
+        }
+
+        if let Some(agc) = chain.agc.as_mut() {
+            frame = agc.process(&frame);
+        }
+
+        if let (Some(canceller), Some(far_end)) = (chain.echo_canceller.as_mut(), far_end) {
+            frame = canceller.process(far_end, &frame);
+        }
+
+        frame
+    }
+}
+
+/// Samples of one channel of an interleaved buffer
+fn deinterleave(buffer: &AudioBuffer, channel: usize) -> Vec<f32> {
+    buffer
+        .samples
+        .iter()
+        .skip(channel)
+        .step_by(buffer.channels.max(1) as usize)
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chain_disabled_passes_through() {
+        let mut chain = AudioProcessingChain::new(48000, AudioProcessingConfig::default());
+        let frame = vec![0.25f32; 480];
+
+        assert_eq!(chain.process(&frame, None), frame);
+    }
+
+    #[test]
+    fn test_chain_keeps_state_per_channel() {
+        let config = AudioProcessingConfig::new().with_auto_gain_control(true);
+        let mut chain = AudioProcessingChain::new(48000, config);
+
+        chain.process_channel(1, &[0.01f32; 480], None);
+
+        assert_eq!(chain.channels.len(), 2);
+        assert_eq!(chain.channels[0].agc.as_ref().unwrap().current_gain(), 1.0);
+        assert!(chain.channels[1].agc.as_ref().unwrap().current_gain() > 1.0);
+    }
+
+    #[test]
+    fn test_noise_suppression_keeps_suppressor_across_frame_sizes() {
+        let config = AudioProcessingConfig::new().with_noise_suppression(true);
+        let mut chain = AudioProcessingChain::new(48000, config);
+
+        // 10 ms, then 1024 and 7 samples
+        for len in [480, 1024, 7] {
+            assert_eq!(chain.process(&vec![0.1f32; len], None).len(), len);
+        }
+
+        let noise = chain.channels[0].noise.as_ref().unwrap();
+        assert_eq!(noise.suppressor.frame_size(), 480);
+        // 1511 samples make three whole frames, the noise of which is tracked
+        assert_eq!(noise.pending.len(), 1511 - 3 * 480);
+        assert!(noise.suppressor.noise_estimate().iter().any(|&p| p > 0.0));
+    }
+
+    #[test]
+    fn test_noise_suppression_delays_by_one_frame() {
+        let config = AudioProcessingConfig::new().with_noise_suppression(true);
+        let mut chain = AudioProcessingChain::new(16000, config);
+
+        // The first 10 ms are the silence the suppressor starts with
+        let output = chain.process(&[0.5f32; 200], None);
+        assert!(output[..160].iter().all(|&s| s == 0.0));
+        assert!(output[160..].iter().any(|&s| s != 0.0));
+    }
+}
@@ -0,0 +1,152 @@
+//! Receive-side audio path
+//!
+//! Feeds RTP audio packets through a [`JitterBuffer`] into an
+//! [`AudioDecoder`]:
+//!
+//! ```text
+//! Network ─> JitterBuffer ─> AudioDecoder ─> Playout
+//! ```
+//!
+//! Packets that are still missing at playout time while later ones have
+//! arrived are treated as lost. The decoder is told which packet follows the
+//! lost one so that it can recover the lost frame from in-band FEC data, or
+//! conceal the gap otherwise.
+
+use crate::jitter_buffer::{JitterBuffer, OverflowPolicy};
+use crate::rtp::RTPPacket;
+use cortenbrowser_shared_types::{AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, MediaError};
+use std::time::Duration;
+
+/// Audio receive path from RTP packets to decoded audio
+///
+/// Insert packets with [`AudioReceiver::insert`] as they arrive and call
+/// [`AudioReceiver::pull`] once per playout interval. Every pull that
+/// returns audio covers exactly one packet, whether it was received or
+/// lost, so the output stays as long as the stream.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_shared_types::{AudioDecoder, MediaError};
+/// use cortenbrowser_webrtc_integration::{AudioReceiver, RTPPacket};
+///
+/// fn receive<D: AudioDecoder>(decoder: D, packets: Vec<RTPPacket>) -> Result<(), MediaError> {
+///     let mut receiver = AudioReceiver::new(decoder, 48000, 2, 50);
+///     for packet in packets {
+///         receiver.insert(packet)?;
+///     }
+///
+///     // Once per 20 ms playout interval
+///     while let Some(_audio) = receiver.pull()? {
+///         // Hand the audio to the output device
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct AudioReceiver<D: AudioDecoder> {
+    jitter_buffer: JitterBuffer,
+    decoder: D,
+    sample_rate: u32,
+    channels: u8,
+    /// Samples per channel of the last decoded frame, used as the length of
+    /// concealment audio
+    frame_size: usize,
+}
+
+impl<D: AudioDecoder> AudioReceiver<D> {
+    /// Create a new audio receive path
+    ///
+    /// # Arguments
+    ///
+    /// * `decoder` - Decoder for the negotiated audio codec
+    /// * `sample_rate` - Output sample rate of the decoder in Hz
+    /// * `channels` - Output channel count of the decoder
+    /// * `capacity` - Maximum number of packets to buffer
+    pub fn new(decoder: D, sample_rate: u32, channels: u8, capacity: usize) -> Self {
+        Self {
+            // Losing the stalest packet beats stalling the stream
+            jitter_buffer: JitterBuffer::new(capacity)
+                .with_overflow_policy(OverflowPolicy::DropOldest),
+            decoder,
+            sample_rate,
+            channels,
+            // 20 ms, the usual WebRTC audio packet duration
+            frame_size: sample_rate as usize / 50,
+        }
+    }
+
+    /// Get the decoder
+    pub fn decoder(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Get the number of packets waiting for playout
+    pub fn buffered(&self) -> usize {
+        self.jitter_buffer.len()
+    }
+
+    /// Insert a received packet
+    ///
+    /// If the jitter buffer is full, the packet that would play out first is
+    /// dropped to make room.
+    pub fn insert(&mut self, packet: RTPPacket) -> Result<(), MediaError> {
+        self.jitter_buffer.insert(packet)
+    }
+
+    /// Produce the audio for the next packet in sequence
+    ///
+    /// If the next packet is missing but a later one has arrived, the
+    /// missing packet is declared lost and concealment audio is produced in
+    /// its place, using the following packet's FEC data when that packet is
+    /// buffered. Decoders without packet loss concealment fill the gap with
+    /// silence.
+    ///
+    /// Returns `None` if no packets are buffered.
+    ///
+    /// # Errors
+    ///
+    /// Returns the decoder's error if a packet fails to decode.
+    pub fn pull(&mut self) -> Result<Option<AudioBuffer>, MediaError> {
+        if let Some(packet) = self.jitter_buffer.get_next() {
+            let buffer = self.decoder.decode(&Self::audio_packet(&packet))?;
+            if self.channels > 0 && !buffer.samples.is_empty() {
+                self.frame_size = buffer.samples.len() / self.channels as usize;
+            }
+            return Ok(Some(buffer));
+        }
+
+        if self.jitter_buffer.skip_missing().is_none() {
+            return Ok(None);
+        }
+
+        // The packet after the lost one, if it has arrived
+        let next = self.jitter_buffer.peek_next().map(Self::audio_packet);
+        match self.decoder.conceal_loss(next.as_ref(), self.frame_size) {
+            Ok(buffer) => Ok(Some(buffer)),
+            Err(MediaError::NotImplemented(_)) => Ok(Some(self.silence())),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn audio_packet(packet: &RTPPacket) -> AudioPacket {
+        AudioPacket {
+            data: packet.payload.clone(),
+            pts: None,
+            dts: None,
+            is_last: false,
+            encryption: None,
+        }
+    }
+
+    fn silence(&self) -> AudioBuffer {
+        AudioBuffer {
+            format: AudioFormat::F32LE,
+            sample_rate: self.sample_rate,
+            channels: self.channels,
+            samples: vec![0.0; self.frame_size * self.channels as usize],
+            timestamp: Duration::ZERO,
+            duration: Duration::from_secs_f64(self.frame_size as f64 / self.sample_rate as f64),
+            channel_map: None,
+        }
+    }
+}
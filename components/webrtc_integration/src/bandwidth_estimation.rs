@@ -0,0 +1,431 @@
+//! Bandwidth estimation for WebRTC streams
+//!
+//! Estimates the available send bandwidth using the delay-based part of
+//! Google Congestion Control (GCC), capped by any receiver estimate delivered
+//! in REMB packets.
+//!
+//! # Algorithm
+//!
+//! For each packet group (typically one video frame):
+//!
+//! 1. **Arrival-time filter** - Compute the inter-group delay variation
+//!    `d = (arrival_i - arrival_{i-1}) - (send_i - send_{i-1})`, accumulate
+//!    it into a smoothed one-way delay, and fit a line through the last 20
+//!    smoothed delays (the trendline filter). The slope, scaled by the number
+//!    of deltas seen, is the queuing delay trend `m`: positive when queues
+//!    are building up.
+//! 2. **Overuse detector** - Compare `m` against an adaptive threshold.
+//!    `m > threshold` signals overuse, `m < -threshold` signals underuse.
+//!    The threshold tracks `|m|` slowly so the detector is not starved by
+//!    concurrent TCP flows.
+//! 3. **Rate controller** - On overuse, reduce the target to
+//!    `decrease_factor` times the measured incoming rate. On normal usage,
+//!    grow the target multiplicatively by `increase_factor` per second.
+//!    On underuse, hold the current rate while queues drain.
+//!
+//! The final target is clamped to the configured bitrate range and to the
+//! latest remote (REMB) estimate.
+//!
+//! # Example
+//!
+//! ```
+//! use cortenbrowser_webrtc_integration::BandwidthEstimator;
+//!
+//! let mut estimator = BandwidthEstimator::new(1_000_000, 100_000, 5_000_000);
+//!
+//! // Packet groups sent every 33ms and received with a constant delay
+//! for i in 0..30u64 {
+//!     estimator.on_packet_arrival(i * 33, i * 33 + 50, 1200);
+//! }
+//!
+//! // A receiver estimate caps the target bitrate
+//! estimator.update_remote_estimate(500_000);
+//! assert_eq!(estimator.current_target_bitrate(), 500_000);
+//! ```
+//!
+//! # References
+//!
+//! - draft-ietf-rmcat-gcc-02: A Google Congestion Control Algorithm for
+//!   Real-Time Communication
+//! - draft-alvestrand-rmcat-remb-03: RTCP message for Receiver Estimated
+//!   Maximum Bitrate
+
+use std::collections::VecDeque;
+
+/// Default multiplicative increase per second in the increase state
+const DEFAULT_INCREASE_FACTOR: f64 = 1.08;
+
+/// Default fraction of the incoming rate used after overuse
+const DEFAULT_DECREASE_FACTOR: f64 = 0.85;
+
+/// Number of smoothed delay samples used for the trendline fit
+const TRENDLINE_WINDOW: usize = 20;
+
+/// Smoothing factor for the accumulated delay
+const DELAY_SMOOTHING: f64 = 0.9;
+
+/// Gain applied to the trendline slope before threshold comparison
+const TRENDLINE_GAIN: f64 = 4.0;
+
+/// Cap on the delta count used to scale the trendline slope
+const MAX_DELTA_SCALE: usize = 60;
+
+/// Initial overuse threshold in milliseconds
+const INITIAL_THRESHOLD_MS: f64 = 12.5;
+
+/// Bounds for the adaptive overuse threshold in milliseconds
+const MIN_THRESHOLD_MS: f64 = 6.0;
+const MAX_THRESHOLD_MS: f64 = 600.0;
+
+/// Threshold adaptation gains when `|m|` is above/below the threshold
+const THRESHOLD_GAIN_UP: f64 = 0.01;
+const THRESHOLD_GAIN_DOWN: f64 = 0.00018;
+
+/// Samples this far above the threshold do not adapt it (in milliseconds)
+const THRESHOLD_ADAPT_LIMIT_MS: f64 = 15.0;
+
+/// Window over which the incoming bitrate is measured
+const RATE_WINDOW_MS: u64 = 500;
+
+/// Network usage signal produced by the overuse detector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BandwidthUsage {
+    /// Queuing delay is stable
+    Normal,
+    /// Queuing delay is growing; the link is congested
+    Overusing,
+    /// Queuing delay is shrinking; queues are draining
+    Underusing,
+}
+
+/// Rate controller state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RateControlState {
+    Hold,
+    Increase,
+    Decrease,
+}
+
+/// Send/arrival times of the previous packet group
+#[derive(Debug, Clone, Copy)]
+struct PacketTiming {
+    send_time_ms: u64,
+    arrival_time_ms: u64,
+}
+
+/// GCC delay-based bandwidth estimator
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::{BandwidthEstimator, BandwidthUsage};
+///
+/// let estimator = BandwidthEstimator::new(1_000_000, 100_000, 5_000_000)
+///     .with_rate_steps(1.05, 0.9);
+/// assert_eq!(estimator.current_target_bitrate(), 1_000_000);
+/// assert_eq!(estimator.usage(), BandwidthUsage::Normal);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BandwidthEstimator {
+    min_bitrate: u32,
+    max_bitrate: u32,
+    target_bitrate: f64,
+    remote_estimate: Option<u32>,
+    increase_factor: f64,
+    decrease_factor: f64,
+
+    // Arrival-time filter
+    last_group: Option<PacketTiming>,
+    first_arrival_ms: Option<u64>,
+    accumulated_delay: f64,
+    smoothed_delay: f64,
+    delay_history: VecDeque<(f64, f64)>,
+    num_deltas: usize,
+    trend: f64,
+
+    // Overuse detector
+    threshold: f64,
+    usage: BandwidthUsage,
+
+    // Rate controller
+    state: RateControlState,
+    last_update_ms: Option<u64>,
+    arrivals: VecDeque<(u64, usize)>,
+}
+
+impl BandwidthEstimator {
+    /// Create a new bandwidth estimator
+    ///
+    /// # Arguments
+    ///
+    /// * `initial_bitrate` - Starting target bitrate in bits per second
+    /// * `min_bitrate` - Lowest target the estimator will produce
+    /// * `max_bitrate` - Highest target the estimator will produce
+    pub fn new(initial_bitrate: u32, min_bitrate: u32, max_bitrate: u32) -> Self {
+        Self {
+            min_bitrate,
+            max_bitrate,
+            target_bitrate: initial_bitrate.clamp(min_bitrate, max_bitrate) as f64,
+            remote_estimate: None,
+            increase_factor: DEFAULT_INCREASE_FACTOR,
+            decrease_factor: DEFAULT_DECREASE_FACTOR,
+            last_group: None,
+            first_arrival_ms: None,
+            accumulated_delay: 0.0,
+            smoothed_delay: 0.0,
+            delay_history: VecDeque::with_capacity(TRENDLINE_WINDOW),
+            num_deltas: 0,
+            trend: 0.0,
+            threshold: INITIAL_THRESHOLD_MS,
+            usage: BandwidthUsage::Normal,
+            state: RateControlState::Hold,
+            last_update_ms: None,
+            arrivals: VecDeque::new(),
+        }
+    }
+
+    /// Set the rate controller step sizes
+    ///
+    /// # Arguments
+    ///
+    /// * `increase_factor` - Multiplicative increase per second (e.g., 1.08)
+    /// * `decrease_factor` - Fraction of the incoming rate kept on overuse (e.g., 0.85)
+    pub fn with_rate_steps(mut self, increase_factor: f64, decrease_factor: f64) -> Self {
+        self.increase_factor = increase_factor;
+        self.decrease_factor = decrease_factor;
+        self
+    }
+
+    /// Record the arrival of a packet group
+    ///
+    /// Timestamps only need to be consistent with each other; the send and
+    /// arrival clocks may have an arbitrary offset.
+    ///
+    /// # Arguments
+    ///
+    /// * `send_time_ms` - When the group was sent, in milliseconds
+    /// * `arrival_time_ms` - When the group was received, in milliseconds
+    /// * `size_bytes` - Total payload size of the group
+    pub fn on_packet_arrival(&mut self, send_time_ms: u64, arrival_time_ms: u64, size_bytes: usize) {
+        self.arrivals.push_back((arrival_time_ms, size_bytes));
+        while let Some(&(t, _)) = self.arrivals.front() {
+            if arrival_time_ms.saturating_sub(t) > RATE_WINDOW_MS {
+                self.arrivals.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let current = PacketTiming {
+            send_time_ms,
+            arrival_time_ms,
+        };
+
+        if let Some(previous) = self.last_group.replace(current) {
+            let send_delta = send_time_ms as f64 - previous.send_time_ms as f64;
+            let arrival_delta = arrival_time_ms as f64 - previous.arrival_time_ms as f64;
+            self.update_filter(arrival_time_ms, arrival_delta - send_delta);
+
+            let dt = arrival_delta.max(0.0);
+            self.update_usage(dt);
+        }
+
+        self.update_rate(arrival_time_ms);
+    }
+
+    /// Apply a receiver-side estimate, e.g. from an RTCP REMB packet
+    ///
+    /// The target bitrate never exceeds the most recent remote estimate.
+    ///
+    /// # Arguments
+    ///
+    /// * `bps` - Estimated maximum bitrate in bits per second
+    pub fn update_remote_estimate(&mut self, bps: u32) {
+        self.remote_estimate = Some(bps);
+        self.target_bitrate = self.target_bitrate.min(bps as f64);
+    }
+
+    /// Get the target bitrate in bits per second
+    pub fn current_target_bitrate(&self) -> u32 {
+        let mut target = self.target_bitrate as u32;
+        if let Some(remote) = self.remote_estimate {
+            target = target.min(remote);
+        }
+        target.clamp(self.min_bitrate, self.max_bitrate)
+    }
+
+    /// Get the bitrate measured over the last 500ms of arrivals
+    pub fn incoming_bitrate(&self) -> Option<u32> {
+        let (first, _) = *self.arrivals.front()?;
+        let (last, _) = *self.arrivals.back()?;
+        let span_ms = last.saturating_sub(first);
+        if span_ms == 0 {
+            return None;
+        }
+        let bytes: usize = self.arrivals.iter().map(|&(_, size)| size).sum();
+        Some((bytes as u64 * 8 * 1000 / span_ms) as u32)
+    }
+
+    /// Get the latest overuse detector signal
+    pub fn usage(&self) -> BandwidthUsage {
+        self.usage
+    }
+
+    /// Get the queuing delay trend compared against the overuse threshold
+    ///
+    /// Positive values mean the one-way delay is growing.
+    pub fn delay_trend(&self) -> f64 {
+        self.trend
+    }
+
+    /// Trendline filter over the inter-group delay variation
+    fn update_filter(&mut self, arrival_time_ms: u64, delay_variation: f64) {
+        let first_arrival = *self.first_arrival_ms.get_or_insert(arrival_time_ms);
+        self.num_deltas += 1;
+
+        self.accumulated_delay += delay_variation;
+        self.smoothed_delay = DELAY_SMOOTHING * self.smoothed_delay
+            + (1.0 - DELAY_SMOOTHING) * self.accumulated_delay;
+
+        if self.delay_history.len() == TRENDLINE_WINDOW {
+            self.delay_history.pop_front();
+        }
+        self.delay_history.push_back((
+            arrival_time_ms.saturating_sub(first_arrival) as f64,
+            self.smoothed_delay,
+        ));
+
+        if self.delay_history.len() == TRENDLINE_WINDOW {
+            if let Some(slope) = linear_fit_slope(&self.delay_history) {
+                self.trend =
+                    slope * self.num_deltas.min(MAX_DELTA_SCALE) as f64 * TRENDLINE_GAIN;
+            }
+        }
+    }
+
+    /// Compare the delay gradient against the adaptive threshold
+    fn update_usage(&mut self, dt_ms: f64) {
+        let magnitude = self.trend.abs();
+
+        self.usage = if self.trend > self.threshold {
+            BandwidthUsage::Overusing
+        } else if self.trend < -self.threshold {
+            BandwidthUsage::Underusing
+        } else {
+            BandwidthUsage::Normal
+        };
+
+        if magnitude - self.threshold <= THRESHOLD_ADAPT_LIMIT_MS {
+            let gain = if magnitude < self.threshold {
+                THRESHOLD_GAIN_DOWN
+            } else {
+                THRESHOLD_GAIN_UP
+            };
+            self.threshold += gain * (magnitude - self.threshold) * dt_ms.min(100.0);
+            self.threshold = self.threshold.clamp(MIN_THRESHOLD_MS, MAX_THRESHOLD_MS);
+        }
+    }
+
+    /// Move the rate controller state machine and adjust the target
+    fn update_rate(&mut self, now_ms: u64) {
+        let elapsed_ms = self
+            .last_update_ms
+            .map(|last| now_ms.saturating_sub(last))
+            .unwrap_or(0);
+        self.last_update_ms = Some(now_ms);
+
+        self.state = match (self.usage, self.state) {
+            (BandwidthUsage::Overusing, _) => RateControlState::Decrease,
+            (BandwidthUsage::Underusing, _) => RateControlState::Hold,
+            (BandwidthUsage::Normal, RateControlState::Decrease) => RateControlState::Hold,
+            (BandwidthUsage::Normal, _) => RateControlState::Increase,
+        };
+
+        match self.state {
+            RateControlState::Increase => {
+                let factor = self.increase_factor.powf(elapsed_ms as f64 / 1000.0);
+                self.target_bitrate *= factor;
+            }
+            RateControlState::Decrease => {
+                let incoming = self
+                    .incoming_bitrate()
+                    .map(|bps| bps as f64)
+                    .unwrap_or(self.target_bitrate);
+                // Only decrease; an incoming rate above target is not a reason to grow
+                self.target_bitrate = self
+                    .target_bitrate
+                    .min(self.decrease_factor * incoming);
+            }
+            RateControlState::Hold => {}
+        }
+
+        let mut upper = self.max_bitrate as f64;
+        if let Some(remote) = self.remote_estimate {
+            upper = upper.min(remote as f64);
+        }
+        self.target_bitrate = self
+            .target_bitrate
+            .clamp(self.min_bitrate as f64, upper.max(self.min_bitrate as f64));
+    }
+}
+
+/// Least-squares slope of `(x, y)` points, or `None` if all `x` are equal
+fn linear_fit_slope(points: &VecDeque<(f64, f64)>) -> Option<f64> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+
+    let mut numerator = 0.0;
+    let mut denominator = 0.0;
+    for &(x, y) in points {
+        numerator += (x - mean_x) * (y - mean_y);
+        denominator += (x - mean_x) * (x - mean_x);
+    }
+
+    if denominator == 0.0 {
+        None
+    } else {
+        Some(numerator / denominator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimator_creation() {
+        let estimator = BandwidthEstimator::new(1_000_000, 100_000, 5_000_000);
+        assert_eq!(estimator.increase_factor, DEFAULT_INCREASE_FACTOR);
+        assert_eq!(estimator.decrease_factor, DEFAULT_DECREASE_FACTOR);
+        assert_eq!(estimator.threshold, INITIAL_THRESHOLD_MS);
+    }
+
+    #[test]
+    fn test_initial_bitrate_is_clamped() {
+        let estimator = BandwidthEstimator::new(10_000_000, 100_000, 5_000_000);
+        assert_eq!(estimator.current_target_bitrate(), 5_000_000);
+    }
+
+    #[test]
+    fn test_incoming_bitrate_window() {
+        let mut estimator = BandwidthEstimator::new(1_000_000, 100_000, 5_000_000);
+        assert_eq!(estimator.incoming_bitrate(), None);
+
+        // 1000 bytes every 10ms = 800 kbps
+        for i in 0..100u64 {
+            estimator.on_packet_arrival(i * 10, i * 10, 1000);
+        }
+        let rate = estimator.incoming_bitrate().unwrap();
+        assert!((790_000..=820_000).contains(&rate), "rate was {}", rate);
+    }
+
+    #[test]
+    fn test_linear_fit_slope() {
+        let points: VecDeque<(f64, f64)> = (0..10).map(|i| (i as f64, 2.0 * i as f64 + 1.0)).collect();
+        assert!((linear_fit_slope(&points).unwrap() - 2.0).abs() < 1e-9);
+
+        let flat: VecDeque<(f64, f64)> = (0..10).map(|_| (5.0, 1.0)).collect();
+        assert_eq!(linear_fit_slope(&flat), None);
+    }
+}
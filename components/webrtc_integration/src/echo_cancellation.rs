@@ -1,11 +1,11 @@
 //! Echo cancellation for WebRTC audio
 //!
-//! **STATUS: STUB IMPLEMENTATION**
+//! **STATUS: PARTIAL IMPLEMENTATION**
 //!
-//! This module provides a placeholder for acoustic echo cancellation (AEC).
-//! Full implementation will include:
+//! The adaptive filter of acoustic echo cancellation (AEC) is implemented as
+//! an NLMS filter, which removes the linear echo. Full implementation will
+//! include:
 //!
-//! - Adaptive filtering to remove echo
 //! - Residual echo suppression
 //! - Non-linear processing
 //! - Double-talk detection
@@ -101,9 +101,9 @@
 //!                                      Processed Output
 //! ```
 //!
-//! # Example Future Implementation
+//! # Example
 //!
-//! ```ignore
+//! ```
 //! use cortenbrowser_webrtc_integration::EchoCanceller;
 //!
 //! let mut canceller = EchoCanceller::new(16000, 128);
@@ -112,8 +112,9 @@
 //! let far_end = vec![0.0f32; 160];  // From speaker (10ms at 16kHz)
 //! let near_end = vec![0.0f32; 160]; // From microphone
 //!
-//! let output = canceller.process(&far_end, &near_end)?;
+//! let output = canceller.process(&far_end, &near_end);
 //! // output contains echo-cancelled near_end signal
+//! assert_eq!(output.len(), 160);
 //! ```
 //!
 //! # References
@@ -123,22 +124,51 @@
 //! - NLMS and RLS adaptive algorithms
 //! - Speex echo canceller
 
-/// Echo canceller (stub)
+use std::collections::VecDeque;
+
+/// Default NLMS step size
+const DEFAULT_STEP_SIZE: f32 = 0.5;
+
+/// Added to the far-end energy when normalizing filter updates, so that
+/// silence does not divide by zero
+const REGULARIZATION: f32 = 1e-6;
+
+/// Echo canceller
+///
+/// An NLMS (normalized least-mean-squares) adaptive filter of
+/// `filter_length` taps models the echo path from the far end to the near
+/// end. For each sample, the echo estimated from the recent far-end samples
+/// is subtracted from the near-end sample, and the filter taps move toward
+/// the echo path by `step_size` times the residual, normalized by the energy
+/// of those far-end samples.
 ///
-/// **STUB IMPLEMENTATION**: This is a placeholder for echo cancellation functionality.
-/// See module documentation for specification details.
+/// Echo delayed by more than `filter_length` samples is not cancelled.
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::EchoCanceller;
+///
+/// let canceller = EchoCanceller::new(16000, 256).with_step_size(0.2);
+/// assert_eq!(canceller.filter_length(), 256);
+/// assert_eq!(canceller.step_size(), 0.2);
+/// ```
+#[derive(Debug, Clone)]
 pub struct EchoCanceller {
-    #[allow(dead_code)]
     sample_rate: u32,
-    #[allow(dead_code)]
     filter_length: usize,
+    step_size: f32,
+    /// Filter taps, the estimated echo path
+    taps: Vec<f32>,
+    /// Delay line of far-end samples, most recent first
+    delay_line: VecDeque<f32>,
+    /// Energy of the samples in the delay line
+    far_energy: f32,
 }
 
 impl EchoCanceller {
     /// Create a new echo canceller
     ///
-    /// **STUB**: Returns a placeholder canceller.
-    ///
     /// # Arguments
     ///
     /// * `sample_rate` - Audio sample rate in Hz (e.g., 16000, 48000)
@@ -155,12 +185,47 @@ impl EchoCanceller {
         Self {
             sample_rate,
             filter_length,
+            step_size: DEFAULT_STEP_SIZE,
+            taps: vec![0.0; filter_length],
+            delay_line: VecDeque::from(vec![0.0; filter_length]),
+            far_energy: 0.0,
         }
     }
 
-    /// Process audio frame (stub)
+    /// Set the NLMS step size
+    ///
+    /// Larger steps converge faster, smaller ones are more robust to noise
+    /// and near-end speech. The filter is stable for steps between 0.0
+    /// and 2.0.
+    ///
+    /// # Arguments
+    ///
+    /// * `step_size` - Step size, clamped to 0.0 to 2.0
+    pub fn with_step_size(mut self, step_size: f32) -> Self {
+        self.step_size = step_size.clamp(0.0, 2.0);
+        self
+    }
+
+    /// Get the sample rate
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Get the adaptive filter length in taps
+    pub fn filter_length(&self) -> usize {
+        self.filter_length
+    }
+
+    /// Get the NLMS step size
+    pub fn step_size(&self) -> f32 {
+        self.step_size
+    }
+
+    /// Process audio frame
     ///
-    /// **STUB**: Future implementation will perform actual echo cancellation.
+    /// The far-end and near-end samples are aligned: `far_end[i]` is what the
+    /// speaker played when the microphone captured `near_end[i]`. A far end
+    /// shorter than the near end is padded with silence.
     ///
     /// # Arguments
     ///
@@ -176,36 +241,61 @@ impl EchoCanceller {
     /// ```
     /// use cortenbrowser_webrtc_integration::EchoCanceller;
     ///
-    /// let canceller = EchoCanceller::new(16000, 128);
+    /// let mut canceller = EchoCanceller::new(16000, 128);
     /// let far_end = vec![0.0f32; 160];
     /// let near_end = vec![0.1f32; 160];
     ///
+    /// // Without far-end audio there is no echo to remove
     /// let output = canceller.process(&far_end, &near_end);
-    /// assert_eq!(output.len(), 160);
+    /// assert_eq!(output, near_end);
     /// ```
-    #[allow(dead_code)]
-    pub fn process(&self, _far_end: &[f32], near_end: &[f32]) -> Vec<f32> {
-        // Stub: Pass through near_end signal unchanged
-        // Real implementation will:
-        // 1. Apply adaptive filter to far_end to estimate echo
-        // 2. Subtract echo estimate from near_end
-        // 3. Apply residual echo suppression
-        // 4. Detect double-talk
-        // 5. Add comfort noise if needed
-        near_end.to_vec()
+    pub fn process(&mut self, far_end: &[f32], near_end: &[f32]) -> Vec<f32> {
+        if self.filter_length == 0 {
+            return near_end.to_vec();
+        }
+
+        near_end
+            .iter()
+            .enumerate()
+            .map(|(i, &near)| {
+                let far = far_end.get(i).copied().unwrap_or(0.0);
+                self.push_far_end(far);
+
+                let echo: f32 = self
+                    .taps
+                    .iter()
+                    .zip(&self.delay_line)
+                    .map(|(tap, x)| tap * x)
+                    .sum();
+                let residual = near - echo;
+
+                let gain = self.step_size * residual / (self.far_energy + REGULARIZATION);
+                for (tap, x) in self.taps.iter_mut().zip(&self.delay_line) {
+                    *tap += gain * x;
+                }
+                residual
+            })
+            .collect()
     }
 
-    /// Reset echo canceller state (stub)
+    /// Reset echo canceller state
     ///
-    /// **STUB**: Future implementation will reset adaptive filter.
-    #[allow(dead_code)]
+    /// Zeroes the filter taps and the delay line, as when the echo path
+    /// changes completely, e.g. when switching audio devices.
     pub fn reset(&mut self) {
-        // Stub: No state to reset
-        // Real implementation will:
-        // - Reset adaptive filter coefficients
-        // - Clear delay buffers
-        // - Reset double-talk detector
-        // - Reset comfort noise generator
+        self.taps.fill(0.0);
+        self.delay_line.iter_mut().for_each(|x| *x = 0.0);
+        self.far_energy = 0.0;
+    }
+
+    /// Shift a far-end sample into the delay line
+    fn push_far_end(&mut self, far: f32) {
+        if let Some(oldest) = self.delay_line.pop_back() {
+            self.far_energy -= oldest * oldest;
+        }
+        self.delay_line.push_front(far);
+        // Rounding errors must not make the energy negative
+        self.far_energy = (self.far_energy + far * far).max(0.0);
     }
 }
 
@@ -221,22 +311,36 @@ mod tests {
     }
 
     #[test]
-    fn test_echo_canceller_process_stub() {
-        let canceller = EchoCanceller::new(16000, 128);
-        let far_end = vec![0.5f32; 160];
-        let near_end = vec![0.1f32; 160];
+    fn test_echo_canceller_removes_scaled_far_end() {
+        // The echo path is a single tap: the far end at half amplitude
+        let mut canceller = EchoCanceller::new(16000, 4);
+        let far_end: Vec<f32> = (0..1600)
+            .map(|n| ((n * 7) % 13) as f32 / 13.0 - 0.5)
+            .collect();
+        let near_end: Vec<f32> = far_end.iter().map(|x| 0.5 * x).collect();
 
         let output = canceller.process(&far_end, &near_end);
 
-        // Stub passes through near_end unchanged
-        assert_eq!(output, near_end);
-        assert_eq!(output.len(), 160);
+        assert!(output[1500..].iter().all(|e| e.abs() < 1e-3));
+        assert!((canceller.taps[0] - 0.5).abs() < 1e-2);
     }
 
     #[test]
-    fn test_echo_canceller_reset_stub() {
-        let mut canceller = EchoCanceller::new(48000, 256);
+    fn test_echo_canceller_reset_zeroes_state() {
+        let mut canceller = EchoCanceller::new(48000, 8);
+        canceller.process(&[0.5f32; 160], &[0.25f32; 160]);
+        assert!(canceller.taps.iter().any(|&tap| tap != 0.0));
+
         canceller.reset();
-        // Stub has no effect, but shouldn't panic
+        assert!(canceller.taps.iter().all(|&tap| tap == 0.0));
+        assert!(canceller.delay_line.iter().all(|&x| x == 0.0));
+        assert_eq!(canceller.far_energy, 0.0);
+    }
+
+    #[test]
+    fn test_echo_canceller_without_taps_passes_through() {
+        let mut canceller = EchoCanceller::new(16000, 0);
+        let near_end = vec![0.1f32; 160];
+        assert_eq!(canceller.process(&[0.5f32; 160], &near_end), near_end);
     }
 }
@@ -1,8 +1,37 @@
 //! WebRTC encoder wrapper
 //!
 //! Provides a wrapper around video encoders for WebRTC streaming.
+//!
+//! When a [`BandwidthEstimator`] is attached, the encoder polls its target
+//! bitrate on every frame and picks the quantizer from it, so the output
+//! rate follows network conditions.
+//!
+//! H.264 is encoded with OpenH264 (`h264` feature, on by default), and VP8
+//! and VP9 with libvpx (`vpx` feature). Codecs without an encoder compiled
+//! in, such as AV1, produce placeholder data of realistic size that cannot
+//! be decoded.
 
+use crate::bandwidth_estimation::BandwidthEstimator;
+#[cfg(feature = "h264")]
+use crate::h264_encoder::H264Encoder;
+#[cfg(feature = "vpx")]
+use crate::vpx_encoder::VpxEncoder;
 use cortenbrowser_shared_types::{VideoCodec, VideoFrame, MediaError};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Lowest quantizer the encoder selects
+const MIN_QP: u8 = 10;
+
+/// Highest quantizer the encoder selects
+const MAX_QP: u8 = 51;
+
+/// Quantizer selected at the reference bits per pixel
+const REFERENCE_QP: f64 = 26.0;
+
+/// Bits per pixel that map to the reference quantizer
+const REFERENCE_BITS_PER_PIXEL: f64 = 0.1;
 
 /// Encoder configuration
 ///
@@ -29,10 +58,48 @@ pub struct EncoderConfig {
     pub keyframe_interval: u32,
 }
 
+/// An encoded video frame
+///
+/// Produced by [`WebRTCEncoder::encode`], ready for RTP packetization.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedFrame {
+    /// Compressed frame data; Annex B NAL units for H.264
+    pub data: Vec<u8>,
+    /// Whether the frame can be decoded without earlier frames
+    pub is_keyframe: bool,
+    /// Timestamp of the source frame
+    pub timestamp: Duration,
+}
+
+/// Codec implementation behind a [`WebRTCEncoder`]
+enum Backend {
+    /// Placeholder output for codecs without an encoder compiled in
+    Mock,
+    #[cfg(feature = "h264")]
+    H264(Box<H264Encoder>),
+    #[cfg(feature = "vpx")]
+    Vpx(VpxEncoder),
+}
+
+impl Backend {
+    fn for_codec(codec: &VideoCodec, config: &EncoderConfig) -> Self {
+        match codec {
+            #[cfg(feature = "h264")]
+            VideoCodec::H264 { .. } => Backend::H264(Box::new(H264Encoder::new(*config))),
+            #[cfg(feature = "vpx")]
+            VideoCodec::VP8 => Backend::Vpx(VpxEncoder::vp8(*config)),
+            #[cfg(feature = "vpx")]
+            VideoCodec::VP9 { .. } => Backend::Vpx(VpxEncoder::vp9(*config)),
+            _ => Backend::Mock,
+        }
+    }
+}
+
 /// WebRTC video encoder
 ///
-/// Wraps video encoders for WebRTC streaming. Currently a stub implementation
-/// that validates inputs and returns mock encoded data.
+/// Wraps video encoders for WebRTC streaming, encoding YUV420 frames at the
+/// configured framerate and target bitrate, with a keyframe every
+/// `keyframe_interval` frames or whenever a frame's metadata asks for one.
 ///
 /// # Examples
 ///
@@ -60,18 +127,22 @@ pub struct EncoderConfig {
 ///     height: 480,
 ///     format: PixelFormat::YUV420,
 ///     data: vec![0u8; 640 * 480 * 3 / 2],
+///     strides: None,
 ///     timestamp: Duration::from_millis(0),
 ///     duration: Some(Duration::from_millis(33)),
 ///     metadata: FrameMetadata::default(),
 /// };
 ///
 /// let encoded = encoder.encode(&frame).unwrap();
-/// assert!(!encoded.is_empty());
+/// assert!(encoded.is_keyframe);
+/// assert!(!encoded.data.is_empty());
 /// ```
 pub struct WebRTCEncoder {
     codec: VideoCodec,
     config: EncoderConfig,
     frame_count: std::cell::Cell<u32>,
+    bandwidth_estimator: Option<Arc<Mutex<BandwidthEstimator>>>,
+    backend: RefCell<Backend>,
 }
 
 impl WebRTCEncoder {
@@ -137,12 +208,55 @@ impl WebRTCEncoder {
         }
 
         Ok(Self {
+            backend: RefCell::new(Backend::for_codec(&codec, &config)),
             codec,
             config,
             frame_count: std::cell::Cell::new(0),
+            bandwidth_estimator: None,
         })
     }
 
+    /// Drive the encoder bitrate from a bandwidth estimator
+    ///
+    /// The estimator's target replaces `EncoderConfig::bitrate` each time a
+    /// frame is encoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::{BandwidthEstimator, EncoderConfig, WebRTCEncoder};
+    /// use cortenbrowser_shared_types::VideoCodec;
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let estimator = Arc::new(Mutex::new(BandwidthEstimator::new(500_000, 100_000, 2_000_000)));
+    /// let encoder = WebRTCEncoder::new(
+    ///     VideoCodec::VP8,
+    ///     EncoderConfig { bitrate: 1_000_000, framerate: 30, keyframe_interval: 30 },
+    /// )
+    /// .unwrap()
+    /// .with_bandwidth_estimator(estimator);
+    ///
+    /// assert_eq!(encoder.target_bitrate(), 500_000);
+    /// ```
+    pub fn with_bandwidth_estimator(mut self, estimator: Arc<Mutex<BandwidthEstimator>>) -> Self {
+        self.bandwidth_estimator = Some(estimator);
+        self
+    }
+
+    /// Get the bitrate the encoder is currently targeting
+    ///
+    /// Returns the attached estimator's target, or the configured bitrate
+    /// if no estimator is attached.
+    pub fn target_bitrate(&self) -> u32 {
+        match &self.bandwidth_estimator {
+            Some(estimator) => estimator
+                .lock()
+                .map(|e| e.current_target_bitrate())
+                .unwrap_or(self.config.bitrate),
+            None => self.config.bitrate,
+        }
+    }
+
     /// Encode a video frame
     ///
     /// # Arguments
@@ -151,13 +265,15 @@ impl WebRTCEncoder {
     ///
     /// # Returns
     ///
-    /// Encoded frame data as bytes
+    /// The encoded frame, flagged as a keyframe when it is one
     ///
     /// # Errors
     ///
     /// Returns `MediaError::CodecError` if:
     /// - Frame dimensions don't match expected data size
     /// - Frame data is invalid
+    /// - A real encoder is given a frame that is not YUV420 with even
+    ///   dimensions, or fails to encode it
     ///
     /// # Examples
     ///
@@ -180,6 +296,7 @@ impl WebRTCEncoder {
     ///     height: 240,
     ///     format: PixelFormat::YUV420,
     ///     data: vec![0u8; 320 * 240 * 3 / 2],
+    ///     strides: None,
     ///     timestamp: Duration::from_millis(0),
     ///     duration: Some(Duration::from_millis(33)),
     ///     metadata: FrameMetadata::default(),
@@ -188,7 +305,7 @@ impl WebRTCEncoder {
     /// let encoded = encoder.encode(&frame);
     /// assert!(encoded.is_ok());
     /// ```
-    pub fn encode(&self, frame: &VideoFrame) -> Result<Vec<u8>, MediaError> {
+    pub fn encode(&self, frame: &VideoFrame) -> Result<EncodedFrame, MediaError> {
         // Validate frame data size
         let expected_size = self.calculate_expected_frame_size(frame);
         if frame.data.len() < expected_size {
@@ -204,20 +321,45 @@ impl WebRTCEncoder {
             });
         }
 
-        // Increment frame count
         let count = self.frame_count.get();
+        let is_keyframe = frame.metadata.is_keyframe || count.is_multiple_of(self.config.keyframe_interval);
+        let bitrate = self.target_bitrate();
+
+        let encoded = match &mut *self.backend.borrow_mut() {
+            Backend::Mock => EncodedFrame {
+                data: self.encode_mock(frame, is_keyframe, bitrate),
+                is_keyframe,
+                timestamp: frame.timestamp,
+            },
+            #[cfg(feature = "h264")]
+            Backend::H264(encoder) => encoder.encode(frame, is_keyframe, bitrate)?,
+            #[cfg(feature = "vpx")]
+            Backend::Vpx(encoder) => encoder.encode(frame, is_keyframe, bitrate)?,
+        };
+
+        // Only frames that were encoded count towards the keyframe interval
         self.frame_count.set(count + 1);
 
-        // Generate mock encoded data
-        // In real implementation, this would call actual codec
-        let is_keyframe = frame.metadata.is_keyframe || count.is_multiple_of(self.config.keyframe_interval);
+        Ok(encoded)
+    }
+
+    /// Generate placeholder data for a codec without an encoder
+    ///
+    /// The data starts with a codec marker, and is sized as a real frame of
+    /// its type would be at `bitrate`.
+    fn encode_mock(&self, frame: &VideoFrame, is_keyframe: bool, bitrate: u32) -> Vec<u8> {
+        // Mock output has its nominal size at the configured bitrate; each
+        // +6 QP above the configured quantizer halves it
+        let qp = self.select_quantizer(frame, bitrate);
+        let nominal_qp = self.select_quantizer(frame, self.config.bitrate);
+        let scale = 2f64.powf((nominal_qp as f64 - qp as f64) / 6.0);
 
         let encoded_size = if is_keyframe {
             // Keyframes are larger
-            (frame.data.len() / 4).max(1000)
+            ((frame.data.len() / 4) as f64 * scale).max(1000.0) as usize
         } else {
             // P-frames are smaller
-            (frame.data.len() / 8).max(500)
+            ((frame.data.len() / 8) as f64 * scale).max(500.0) as usize
         };
 
         // Create mock encoded data with codec-specific marker
@@ -235,7 +377,22 @@ impl WebRTCEncoder {
         // Add mock compressed data
         encoded.resize(encoded_size, if is_keyframe { 0xFF } else { 0xAA });
 
-        Ok(encoded)
+        encoded
+    }
+
+    /// Select the quantizer for a frame at the given bitrate
+    ///
+    /// Halving the bits available per pixel raises the quantizer by 6.
+    fn select_quantizer(&self, frame: &VideoFrame, bitrate: u32) -> u8 {
+        let pixels_per_second =
+            frame.width as f64 * frame.height as f64 * self.config.framerate as f64;
+        if pixels_per_second == 0.0 {
+            return MIN_QP;
+        }
+
+        let bits_per_pixel = bitrate as f64 / pixels_per_second;
+        let qp = REFERENCE_QP - 6.0 * (bits_per_pixel / REFERENCE_BITS_PER_PIXEL).log2();
+        qp.round().clamp(MIN_QP as f64, MAX_QP as f64) as u8
     }
 
     /// Calculate expected frame size for validation
@@ -275,10 +432,60 @@ impl WebRTCEncoder {
     }
 }
 
+/// Y, U and V planes of a YUV420 frame, each with its bytes per row
+#[cfg(any(feature = "h264", feature = "vpx"))]
+pub(crate) type Planes<'a> = [(&'a [u8], usize); 3];
+
+/// Split a frame into its Y, U and V planes for a codec encoder
+///
+/// # Errors
+///
+/// Returns `MediaError::CodecError` if the frame is not YUV420, has an odd
+/// width or height, or a plane is too short for its rows.
+#[cfg(any(feature = "h264", feature = "vpx"))]
+pub(crate) fn i420_planes(frame: &VideoFrame) -> Result<Planes<'_>, MediaError> {
+    use cortenbrowser_shared_types::PixelFormat;
+
+    if frame.format != PixelFormat::YUV420 {
+        return Err(MediaError::CodecError {
+            details: format!("Cannot encode {:?} frames, only YUV420", frame.format),
+        });
+    }
+    if !frame.width.is_multiple_of(2) || !frame.height.is_multiple_of(2) {
+        return Err(MediaError::CodecError {
+            details: format!(
+                "Cannot encode {}x{} frames, dimensions must be even",
+                frame.width, frame.height
+            ),
+        });
+    }
+
+    let (width, height) = (frame.width as usize, frame.height as usize);
+    let planes = [0, 1, 2].map(|index| (frame.plane(index), frame.plane_stride(index)));
+    for (index, &(plane, stride)) in planes.iter().enumerate() {
+        let (row_bytes, rows) = if index == 0 {
+            (width, height)
+        } else {
+            (width / 2, height / 2)
+        };
+        if stride < row_bytes || plane.len() < stride * rows {
+            return Err(MediaError::CodecError {
+                details: format!(
+                    "Plane {} of {}x{} frame is too short to encode",
+                    index, frame.width, frame.height
+                ),
+            });
+        }
+    }
+    Ok(planes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cortenbrowser_shared_types::{H264Profile, H264Level, PixelFormat, FrameMetadata};
+    use cortenbrowser_shared_types::{
+        AV1Level, AV1Profile, H264Profile, H264Level, PixelFormat, FrameMetadata,
+    };
     use std::time::Duration;
 
     #[test]
@@ -330,6 +537,7 @@ mod tests {
             height: 480,
             format: PixelFormat::YUV420,
             data: vec![0u8; 100], // Too small
+            strides: None,
             timestamp: Duration::from_millis(0),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -342,10 +550,9 @@ mod tests {
     #[test]
     fn test_encoder_basic_encoding() {
         let encoder = WebRTCEncoder::new(
-            VideoCodec::H264 {
-                profile: H264Profile::Main,
-                level: H264Level::Level4_0,
-                hardware_accel: false,
+            VideoCodec::AV1 {
+                profile: AV1Profile::Main,
+                level: AV1Level::Level4_0,
             },
             EncoderConfig {
                 bitrate: 1_000_000,
@@ -360,6 +567,7 @@ mod tests {
             height: 480,
             format: PixelFormat::YUV420,
             data: vec![0u8; 640 * 480 * 3 / 2],
+            strides: None,
             timestamp: Duration::from_millis(0),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -369,7 +577,76 @@ mod tests {
         assert!(result.is_ok());
 
         let encoded = result.unwrap();
-        assert!(!encoded.is_empty());
-        assert!(encoded.starts_with(b"H264"));
+        assert!(encoded.is_keyframe);
+        assert!(encoded.data.starts_with(b"AV1\0"));
+    }
+
+    #[cfg(feature = "h264")]
+    #[test]
+    fn test_encoder_h264_output() {
+        let encoder = WebRTCEncoder::new(
+            VideoCodec::H264 {
+                profile: H264Profile::Main,
+                level: H264Level::Level4_0,
+                hardware_accel: false,
+            },
+            EncoderConfig {
+                bitrate: 500_000,
+                framerate: 30,
+                keyframe_interval: 3,
+            },
+        )
+        .unwrap();
+
+        let frame = VideoFrame {
+            width: 320,
+            height: 240,
+            format: PixelFormat::YUV420,
+            data: vec![128u8; 320 * 240 * 3 / 2],
+            strides: None,
+            timestamp: Duration::from_millis(0),
+            duration: Some(Duration::from_millis(33)),
+            metadata: FrameMetadata::default(),
+        };
+
+        let keyframes: Vec<bool> = (0..4)
+            .map(|_| {
+                let encoded = encoder.encode(&frame).unwrap();
+                assert!(encoded.data.starts_with(&[0, 0, 0, 1]));
+                encoded.is_keyframe
+            })
+            .collect();
+        assert_eq!(keyframes, vec![true, false, false, true]);
+
+        // Only YUV420 frames reach the encoder
+        let rgb = VideoFrame {
+            format: PixelFormat::RGB24,
+            data: vec![0u8; 320 * 240 * 3],
+            ..frame
+        };
+        assert!(encoder.encode(&rgb).is_err());
+    }
+
+    #[cfg(feature = "h264")]
+    #[test]
+    fn test_i420_planes_with_strides() {
+        // 4x2 frame with rows padded to 8 luma and 4 chroma bytes
+        let mut frame = VideoFrame::new(
+            4,
+            2,
+            PixelFormat::YUV420,
+            vec![16; 8 * 2 + 4 + 4],
+            Duration::ZERO,
+        );
+        frame.strides = Some([8, 4, 4]);
+
+        let [(y, y_stride), (u, u_stride), (v, v_stride)] = i420_planes(&frame).unwrap();
+        assert_eq!((y.len(), y_stride), (16, 8));
+        assert_eq!((u.len(), u_stride), (4, 4));
+        assert_eq!((v.len(), v_stride), (4, 4));
+
+        // The padded V plane is cut short
+        frame.data.truncate(22);
+        assert!(i420_planes(&frame).is_err());
     }
 }
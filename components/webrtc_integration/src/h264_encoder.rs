@@ -0,0 +1,130 @@
+//! H.264 encoding with OpenH264
+//!
+//! Backs [`WebRTCEncoder`](crate::WebRTCEncoder) for H.264. Output is an
+//! Annex B access unit, with SPS and PPS ahead of every IDR frame, which
+//! `RTPPacketizer::packetize` sends per RFC 6184.
+
+use crate::encoder::{i420_planes, EncodedFrame, EncoderConfig};
+use cortenbrowser_shared_types::{MediaError, VideoFrame};
+use openh264::encoder::{
+    Encoder, EncoderConfig as OpenH264Config, FrameType, RateControlMode, UsageType,
+};
+use openh264::formats::YUVSlices;
+use openh264::{OpenH264API, Timestamp};
+use openh264_sys2::{SBitrateInfo, ENCODER_OPTION_BITRATE, SPATIAL_LAYER_ALL};
+
+/// OpenH264 encoder for one stream
+pub(crate) struct H264Encoder {
+    config: EncoderConfig,
+    /// Created with the bitrate of the first frame
+    encoder: Option<Encoder>,
+    /// Bitrate the encoder currently targets
+    bitrate: u32,
+}
+
+impl H264Encoder {
+    /// Create an encoder for frames at `config`'s framerate
+    pub(crate) fn new(config: EncoderConfig) -> Self {
+        Self {
+            config,
+            encoder: None,
+            bitrate: config.bitrate,
+        }
+    }
+
+    /// Encode a frame at `bitrate`
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::CodecError` if the frame is not YUV420 with even
+    /// dimensions, or OpenH264 fails to create the encoder or encode.
+    pub(crate) fn encode(
+        &mut self,
+        frame: &VideoFrame,
+        force_keyframe: bool,
+        bitrate: u32,
+    ) -> Result<EncodedFrame, MediaError> {
+        let [(y, y_stride), (u, u_stride), (v, v_stride)] = i420_planes(frame)?;
+        let source = YUVSlices::new(
+            (y, u, v),
+            (frame.width as usize, frame.height as usize),
+            (y_stride, u_stride, v_stride),
+        );
+
+        let encoder = match &mut self.encoder {
+            Some(encoder) => {
+                if bitrate != self.bitrate {
+                    set_bitrate(encoder, bitrate)?;
+                    self.bitrate = bitrate;
+                }
+                if force_keyframe {
+                    encoder.force_intra_frame();
+                }
+                encoder
+            }
+            // The first frame is always an IDR frame
+            slot @ None => {
+                self.bitrate = bitrate;
+                slot.insert(create(&self.config, bitrate)?)
+            }
+        };
+
+        let timestamp = Timestamp::from_millis(frame.timestamp.as_millis() as u64);
+        let bitstream =
+            encoder
+                .encode_at(&source, timestamp)
+                .map_err(|e| MediaError::CodecError {
+                    details: format!("H.264 encode error: {:?}", e),
+                })?;
+
+        Ok(EncodedFrame {
+            data: bitstream.to_vec(),
+            is_keyframe: matches!(bitstream.frame_type(), FrameType::IDR | FrameType::I),
+            timestamp: frame.timestamp,
+        })
+    }
+}
+
+/// Create an OpenH264 encoder for real-time video at `bitrate`
+fn create(config: &EncoderConfig, bitrate: u32) -> Result<Encoder, MediaError> {
+    // Skipping frames would leave RTP timestamps without a frame
+    let config = OpenH264Config::new()
+        .set_bitrate_bps(bitrate)
+        .max_frame_rate(config.framerate as f32)
+        .rate_control_mode(RateControlMode::Bitrate)
+        .usage_type(UsageType::CameraVideoRealTime)
+        .enable_skip_frame(false);
+
+    Encoder::with_api_config(OpenH264API::from_source(), config).map_err(|e| {
+        MediaError::CodecError {
+            details: format!("Failed to create OpenH264 encoder: {:?}", e),
+        }
+    })
+}
+
+/// Retarget a running encoder to `bitrate` without restarting the stream
+fn set_bitrate(encoder: &mut Encoder, bitrate: u32) -> Result<(), MediaError> {
+    let mut info = SBitrateInfo {
+        iLayer: SPATIAL_LAYER_ALL,
+        iBitrate: bitrate.min(i32::MAX as u32) as i32,
+    };
+
+    // SAFETY: the encoder is initialized by its first frame, and OpenH264
+    // only reads `info` during the call
+    let ret = unsafe {
+        encoder.raw_api().set_option(
+            ENCODER_OPTION_BITRATE,
+            (&mut info as *mut SBitrateInfo).cast(),
+        )
+    };
+    if ret != 0 {
+        return Err(MediaError::CodecError {
+            details: format!(
+                "Failed to set H.264 bitrate to {}: error code {}",
+                bitrate, ret
+            ),
+        });
+    }
+
+    Ok(())
+}
@@ -1,10 +1,68 @@
 //! Jitter buffer for RTP packet reordering
 //!
 //! Handles out-of-order packet arrival and sequence number wraparound.
+//! With a target delay set, packets are also held until their scheduled
+//! playout time so that network jitter is smoothed out.
 
 use crate::rtp::RTPPacket;
 use cortenbrowser_shared_types::MediaError;
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// RTP clock rate assumed until [`JitterBuffer::with_clock_rate`] is called
+///
+/// This is the rate used by all video payload formats.
+pub const DEFAULT_CLOCK_RATE: u32 = 90_000;
+
+/// Most sequence numbers [`JitterBuffer::missing_sequences`] reports
+///
+/// A larger gap is more likely a stream discontinuity than packet loss, and
+/// retransmitting its older packets would not arrive in time anyway.
+pub const MAX_MISSING_SEQUENCES: usize = 256;
+
+/// RFC 3550 reception statistics of an RTP stream
+///
+/// What a receiver reports about a source in the report blocks of RTCP
+/// receiver reports, see [`JitterBuffer::reception_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReceptionStats {
+    /// Packets received, including late ones
+    pub packets_received: u64,
+    /// Packets expected from the first to the highest sequence number
+    /// received
+    pub packets_expected: u64,
+    /// Highest sequence number received, extended with the number of
+    /// sequence number cycles in its upper 16 bits
+    pub extended_highest_seq: u32,
+    /// Interarrival jitter in RTP timestamp units
+    pub jitter: u32,
+}
+
+impl ReceptionStats {
+    /// Packets lost since the first packet: expected but not received
+    ///
+    /// Negative when more packets arrived than were expected, e.g. through
+    /// duplicates.
+    pub fn cumulative_lost(&self) -> i64 {
+        self.packets_expected as i64 - self.packets_received as i64
+    }
+}
+
+/// What a [`JitterBuffer`] does with a packet arriving while it is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Reject the new packet with `MediaError::OutOfMemory`
+    #[default]
+    Error,
+    /// Drop the packet that would play out first, giving up on any packets
+    /// still missing before it
+    ///
+    /// This keeps a real-time stream flowing under sustained overload.
+    DropOldest,
+    /// Drop the packet with the numerically lowest sequence number,
+    /// ignoring wraparound
+    DropLowestSeq,
+}
 
 /// Jitter buffer for reordering RTP packets
 ///
@@ -23,6 +81,7 @@ use std::collections::HashMap;
 ///     sequence_number: 5,
 ///     timestamp: 1000,
 ///     ssrc: 12345,
+///     ..Default::default()
 /// };
 ///
 /// buffer.insert(packet).unwrap();
@@ -31,8 +90,34 @@ use std::collections::HashMap;
 /// ```
 pub struct JitterBuffer {
     capacity: usize,
+    overflow_policy: OverflowPolicy,
+    /// Packets dropped to make room under the overflow policy
+    dropped: u64,
     packets: HashMap<u16, RTPPacket>,
     next_expected_seq: Option<u16>,
+    /// Highest sequence number received, considering wraparound
+    max_received_seq: Option<u16>,
+    /// Sequence number of the first packet received
+    base_seq: Option<u16>,
+    /// Times the highest sequence number received wrapped around
+    seq_cycles: u32,
+    /// Packets received, including late ones
+    received: u64,
+    /// Whether packets have been taken out, after which the playout
+    /// position only moves forward
+    playing: bool,
+    /// RTP timestamp units per second
+    clock_rate: u32,
+    /// How long packets are held after the arrival time their timestamp
+    /// implies, or `None` to release packets as soon as they are in order
+    target_delay: Option<Duration>,
+    /// RTP timestamp and arrival time of the first packet, which anchor the
+    /// playout schedule
+    reference: Option<(u32, Instant)>,
+    /// Relative transit time of the previous packet in timestamp units
+    last_transit: Option<f64>,
+    /// RFC 3550 interarrival jitter estimate in timestamp units
+    jitter: f64,
 }
 
 impl JitterBuffer {
@@ -54,11 +139,142 @@ impl JitterBuffer {
     pub fn new(capacity: usize) -> Self {
         Self {
             capacity,
+            overflow_policy: OverflowPolicy::Error,
+            dropped: 0,
             packets: HashMap::new(),
             next_expected_seq: None,
+            max_received_seq: None,
+            base_seq: None,
+            seq_cycles: 0,
+            received: 0,
+            playing: false,
+            clock_rate: DEFAULT_CLOCK_RATE,
+            target_delay: None,
+            reference: None,
+            last_transit: None,
+            jitter: 0.0,
         }
     }
 
+    /// Create a jitter buffer that holds packets for a playout delay
+    ///
+    /// Each packet is scheduled for playout at the time its RTP timestamp
+    /// says it should have arrived, relative to the first packet, plus
+    /// `target`. [`pop_ready`](Self::pop_ready) releases packets only once
+    /// that time has come, so packets delayed by less than `target` play
+    /// out evenly spaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - Maximum number of packets to buffer
+    /// * `target` - Playout delay added to each packet's expected arrival
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::{JitterBuffer, RTPPacket};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let mut buffer = JitterBuffer::with_target_delay(100, Duration::from_millis(40))
+    ///     .with_clock_rate(48000);
+    ///
+    /// let start = Instant::now();
+    /// buffer.insert_at(RTPPacket {
+    ///     payload: vec![1, 2, 3],
+    ///     sequence_number: 0,
+    ///     timestamp: 0,
+    ///     ssrc: 12345,
+    ///     ..Default::default()
+    /// }, start).unwrap();
+    ///
+    /// assert!(buffer.pop_ready(start).is_none());
+    /// let packet = buffer.pop_ready(start + Duration::from_millis(40)).unwrap();
+    /// assert_eq!(packet.sequence_number, 0);
+    /// ```
+    pub fn with_target_delay(capacity: usize, target: Duration) -> Self {
+        Self {
+            target_delay: Some(target),
+            ..Self::new(capacity)
+        }
+    }
+
+    /// Set the RTP clock rate of the stream
+    ///
+    /// The clock rate converts RTP timestamps to time for the playout
+    /// schedule and the jitter estimate. Defaults to [`DEFAULT_CLOCK_RATE`].
+    ///
+    /// # Arguments
+    ///
+    /// * `clock_rate` - RTP timestamp units per second, e.g. 48000 for Opus
+    pub fn with_clock_rate(mut self, clock_rate: u32) -> Self {
+        self.clock_rate = clock_rate.max(1);
+        self
+    }
+
+    /// Set what happens to packets arriving while the buffer is full
+    ///
+    /// Defaults to [`OverflowPolicy::Error`].
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The overflow policy
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::{JitterBuffer, OverflowPolicy, RTPPacket};
+    ///
+    /// let mut buffer = JitterBuffer::new(2).with_overflow_policy(OverflowPolicy::DropOldest);
+    /// for seq in 0..3 {
+    ///     buffer.insert(RTPPacket {
+    ///         payload: vec![seq as u8],
+    ///         sequence_number: seq,
+    ///         timestamp: 960 * seq as u32,
+    ///         ssrc: 12345,
+    ///         ..Default::default()
+    ///     }).unwrap();
+    /// }
+    ///
+    /// assert_eq!(buffer.dropped_count(), 1);
+    /// assert_eq!(buffer.get_next().unwrap().sequence_number, 1);
+    /// ```
+    pub fn with_overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Get the overflow policy
+    pub fn overflow_policy(&self) -> OverflowPolicy {
+        self.overflow_policy
+    }
+
+    /// Get the number of packets dropped because the buffer was full
+    ///
+    /// Always zero with [`OverflowPolicy::Error`], which rejects packets
+    /// instead.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Get the RTP clock rate
+    pub fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    /// Get the playout delay, if the buffer is time-aware
+    pub fn target_delay(&self) -> Option<Duration> {
+        self.target_delay
+    }
+
+    /// Get the measured interarrival jitter
+    ///
+    /// This is the smoothed mean deviation of packet spacing on arrival
+    /// from packet spacing in RTP timestamps, as defined in RFC 3550
+    /// section 6.4.1. It is zero until two packets have arrived.
+    pub fn jitter(&self) -> Duration {
+        Duration::from_secs_f64(self.jitter / self.clock_rate as f64)
+    }
+
     /// Get the buffer capacity
     pub fn capacity(&self) -> usize {
         self.capacity
@@ -76,7 +292,8 @@ impl JitterBuffer {
 
     /// Insert a packet into the buffer
     ///
-    /// Handles duplicates by keeping the first packet received.
+    /// Handles duplicates by keeping the first packet received. If the
+    /// buffer is full, the [`OverflowPolicy`] decides which packet is lost.
     ///
     /// # Arguments
     ///
@@ -84,7 +301,8 @@ impl JitterBuffer {
     ///
     /// # Errors
     ///
-    /// Returns `MediaError::OutOfMemory` if buffer is at capacity.
+    /// Returns `MediaError::OutOfMemory` if buffer is at capacity and the
+    /// policy is [`OverflowPolicy::Error`].
     ///
     /// # Examples
     ///
@@ -97,22 +315,92 @@ impl JitterBuffer {
     ///     sequence_number: 0,
     ///     timestamp: 1000,
     ///     ssrc: 12345,
+    ///     ..Default::default()
     /// };
     ///
     /// assert!(buffer.insert(packet).is_ok());
     /// ```
     pub fn insert(&mut self, packet: RTPPacket) -> Result<(), MediaError> {
+        self.insert_at(packet, Instant::now())
+    }
+
+    /// Insert a packet that arrived at the given time
+    ///
+    /// Behaves like [`insert`](Self::insert), using `arrival` rather than
+    /// the current time for the playout schedule and the jitter estimate.
+    ///
+    /// # Arguments
+    ///
+    /// * `packet` - The RTP packet to insert
+    /// * `arrival` - When the packet was received
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::OutOfMemory` if buffer is at capacity and the
+    /// policy is [`OverflowPolicy::Error`].
+    pub fn insert_at(&mut self, packet: RTPPacket, arrival: Instant) -> Result<(), MediaError> {
         // Save sequence number before move
         let seq = packet.sequence_number;
+        let timestamp = packet.timestamp;
+        self.received += 1;
+        self.base_seq.get_or_insert(seq);
+
+        // Packets arriving after their slot was played out or declared lost
+        // are too late to be used
+        if let Some(expected) = self.next_expected_seq {
+            if self.playing && Self::sequence_before(seq, expected) {
+                return Ok(());
+            }
+        }
 
         // Check capacity (exclude duplicates from count)
         if self.packets.len() >= self.capacity && !self.packets.contains_key(&seq) {
-            return Err(MediaError::OutOfMemory);
+            let victim = match self.overflow_policy {
+                OverflowPolicy::Error => return Err(MediaError::OutOfMemory),
+                OverflowPolicy::DropOldest => self
+                    .first_in_order()
+                    .filter(|&oldest| !Self::sequence_before(seq, oldest)),
+                OverflowPolicy::DropLowestSeq => self
+                    .packets
+                    .keys()
+                    .min()
+                    .copied()
+                    .filter(|&lowest| seq > lowest),
+            };
+
+            self.dropped += 1;
+            // The new packet itself is the one to drop
+            let Some(victim) = victim else {
+                return Ok(());
+            };
+            let oldest = self.first_in_order() == Some(victim);
+            self.packets.remove(&victim);
+            if oldest {
+                // Playout continues after the dropped packet
+                self.next_expected_seq = Some(victim.wrapping_add(1));
+                self.playing = true;
+            }
         }
 
         // Insert packet (duplicates are kept as first)
         // But we only insert if not already present
-        self.packets.entry(seq).or_insert(packet);
+        if self.packets.contains_key(&seq) {
+            return Ok(());
+        }
+        self.packets.insert(seq, packet);
+        self.update_jitter(timestamp, arrival);
+
+        match self.max_received_seq {
+            None => self.max_received_seq = Some(seq),
+            Some(max) if Self::sequence_before(max, seq) => {
+                // Passing 65535 starts another sequence number cycle
+                if seq < max {
+                    self.seq_cycles += 1;
+                }
+                self.max_received_seq = Some(seq);
+            }
+            Some(_) => {}
+        }
 
         // Update expected sequence
         if self.next_expected_seq.is_none() {
@@ -150,6 +438,7 @@ impl JitterBuffer {
     ///     sequence_number: 0,
     ///     timestamp: 1000,
     ///     ssrc: 12345,
+    ///     ..Default::default()
     /// };
     ///
     /// let packet2 = RTPPacket {
@@ -157,6 +446,7 @@ impl JitterBuffer {
     ///     sequence_number: 1,
     ///     timestamp: 1100,
     ///     ssrc: 12345,
+    ///     ..Default::default()
     /// };
     ///
     /// buffer.insert(packet1).unwrap();
@@ -171,6 +461,7 @@ impl JitterBuffer {
             if let Some(packet) = self.packets.remove(&expected_seq) {
                 // Move to next expected sequence (with wraparound)
                 self.next_expected_seq = Some(expected_seq.wrapping_add(1));
+                self.playing = true;
                 return Some(packet);
             }
         }
@@ -178,6 +469,250 @@ impl JitterBuffer {
         None
     }
 
+    /// Get the next packet whose playout time has come
+    ///
+    /// The playout time of a packet is the arrival time its RTP timestamp
+    /// implies, relative to the first packet, plus the target delay. If the
+    /// next packet in sequence is still missing when a later packet is due,
+    /// the missing ones are given up on as with
+    /// [`skip_missing`](Self::skip_missing) and the later packet is
+    /// returned.
+    ///
+    /// Without a target delay this behaves like
+    /// [`get_next`](Self::get_next).
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - The current time
+    ///
+    /// # Returns
+    ///
+    /// The next packet to play, or `None` if no packet is due yet
+    pub fn pop_ready(&mut self, now: Instant) -> Option<RTPPacket> {
+        if self.target_delay.is_none() {
+            return self.get_next();
+        }
+
+        // Earliest packet in sequence order, possibly after a gap
+        let seq = self.first_in_order()?;
+        let packet = self.packets.get(&seq)?;
+        if self.playout_time(packet.timestamp)? > now {
+            return None;
+        }
+
+        self.next_expected_seq = Some(seq);
+        self.get_next()
+    }
+
+    /// Get the buffered packet that plays out first
+    fn first_in_order(&self) -> Option<u16> {
+        let expected_seq = self.next_expected_seq?;
+        self.packets
+            .keys()
+            .min_by_key(|&&seq| seq.wrapping_sub(expected_seq))
+            .copied()
+    }
+
+    /// Get the scheduled playout time of a buffered packet
+    ///
+    /// Returns `None` if the buffer has no target delay.
+    fn playout_time(&self, timestamp: u32) -> Option<Instant> {
+        let target = self.target_delay?;
+        let (reference_ts, reference_arrival) = self.reference?;
+
+        // Timestamps may wrap, so take the signed distance
+        let offset = timestamp.wrapping_sub(reference_ts) as i32 as f64 / self.clock_rate as f64;
+        let scheduled = if offset >= 0.0 {
+            reference_arrival + Duration::from_secs_f64(offset)
+        } else {
+            reference_arrival
+                .checked_sub(Duration::from_secs_f64(-offset))
+                .unwrap_or(reference_arrival)
+        };
+        Some(scheduled + target)
+    }
+
+    /// Update the interarrival jitter estimate with a newly arrived packet
+    fn update_jitter(&mut self, timestamp: u32, arrival: Instant) {
+        let (reference_ts, reference_arrival) = *self.reference.get_or_insert((timestamp, arrival));
+
+        // Transit time relative to the first packet, in timestamp units
+        let elapsed = if arrival >= reference_arrival {
+            arrival.duration_since(reference_arrival).as_secs_f64()
+        } else {
+            -reference_arrival.duration_since(arrival).as_secs_f64()
+        };
+        let sent = timestamp.wrapping_sub(reference_ts) as i32 as f64;
+        let transit = elapsed * self.clock_rate as f64 - sent;
+
+        if let Some(last_transit) = self.last_transit {
+            let d = (transit - last_transit).abs();
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+        self.last_transit = Some(transit);
+    }
+
+    /// Get the next packet in sequence order without removing it
+    pub fn peek_next(&self) -> Option<&RTPPacket> {
+        self.next_expected_seq
+            .and_then(|expected_seq| self.packets.get(&expected_seq))
+    }
+
+    /// Declare the next expected packet lost and move past it
+    ///
+    /// Call this at playout time when [`get_next`](Self::get_next) returns
+    /// `None`. If a later packet has already arrived, the missing one is
+    /// given up on: its sequence number is returned and playout continues
+    /// with the packet after it, which [`peek_next`](Self::peek_next) shows
+    /// if it is the one that has arrived. A packet arriving after it was
+    /// declared lost is dropped.
+    ///
+    /// Returns `None` if the next packet is present, or if the buffer is
+    /// empty and a loss cannot be told apart from a late packet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::{JitterBuffer, RTPPacket};
+    ///
+    /// let mut buffer = JitterBuffer::new(10);
+    /// for seq in [0, 2] {
+    ///     buffer.insert(RTPPacket {
+    ///         payload: vec![seq as u8],
+    ///         sequence_number: seq,
+    ///         timestamp: 960 * seq as u32,
+    ///         ssrc: 12345,
+    ///         ..Default::default()
+    ///     }).unwrap();
+    /// }
+    ///
+    /// assert_eq!(buffer.get_next().unwrap().sequence_number, 0);
+    /// assert_eq!(buffer.get_next(), None);
+    /// assert_eq!(buffer.skip_missing(), Some(1));
+    /// assert_eq!(buffer.peek_next().unwrap().sequence_number, 2);
+    /// ```
+    pub fn skip_missing(&mut self) -> Option<u16> {
+        let expected_seq = self.next_expected_seq?;
+        if self.packets.is_empty() || self.packets.contains_key(&expected_seq) {
+            return None;
+        }
+
+        self.next_expected_seq = Some(expected_seq.wrapping_add(1));
+        self.playing = true;
+        Some(expected_seq)
+    }
+
+    /// Get the highest sequence number received, considering wraparound
+    ///
+    /// Returns `None` until a packet has been inserted.
+    pub fn max_received_seq(&self) -> Option<u16> {
+        self.max_received_seq
+    }
+
+    /// Get the RFC 3550 reception statistics of the stream
+    ///
+    /// Packets are expected from the first one received up to the highest
+    /// sequence number received. Statistics start over with
+    /// [`reset`](Self::reset).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::{JitterBuffer, RTPPacket};
+    ///
+    /// let mut buffer = JitterBuffer::new(10);
+    /// for seq in [0, 1, 4] {
+    ///     buffer.insert(RTPPacket {
+    ///         sequence_number: seq,
+    ///         timestamp: 960 * seq as u32,
+    ///         ..Default::default()
+    ///     }).unwrap();
+    /// }
+    ///
+    /// let stats = buffer.reception_stats();
+    /// assert_eq!(stats.extended_highest_seq, 4);
+    /// assert_eq!(stats.packets_expected, 5);
+    /// assert_eq!(stats.cumulative_lost(), 2);
+    /// ```
+    pub fn reception_stats(&self) -> ReceptionStats {
+        let (Some(base), Some(max)) = (self.base_seq, self.max_received_seq) else {
+            return ReceptionStats::default();
+        };
+        let extended_highest_seq = (self.seq_cycles << 16) | u32::from(max);
+        ReceptionStats {
+            packets_received: self.received,
+            packets_expected: (u64::from(extended_highest_seq) + 1).saturating_sub(u64::from(base)),
+            extended_highest_seq,
+            jitter: self.jitter as u32,
+        }
+    }
+
+    /// Get the sequence numbers missing before the highest one received
+    ///
+    /// These are the packets between the next one to play out and the
+    /// highest received that have not arrived, in sequence order. They can
+    /// be requested again with RTCP NACK feedback. At most the
+    /// [`MAX_MISSING_SEQUENCES`] sequence numbers before the highest one
+    /// received are considered.
+    ///
+    /// # Returns
+    ///
+    /// The missing sequence numbers, oldest first
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::{JitterBuffer, RTPPacket};
+    ///
+    /// let mut buffer = JitterBuffer::new(10);
+    /// for seq in [0, 1, 4] {
+    ///     buffer.insert(RTPPacket {
+    ///         payload: vec![seq as u8],
+    ///         sequence_number: seq,
+    ///         timestamp: 960 * seq as u32,
+    ///         ssrc: 12345,
+    ///         ..Default::default()
+    ///     }).unwrap();
+    /// }
+    ///
+    /// assert_eq!(buffer.max_received_seq(), Some(4));
+    /// assert_eq!(buffer.missing_sequences(), vec![2, 3]);
+    /// ```
+    pub fn missing_sequences(&self) -> Vec<u16> {
+        let (Some(expected), Some(max)) = (self.next_expected_seq, self.max_received_seq) else {
+            return Vec::new();
+        };
+        if Self::sequence_before(max, expected) {
+            return Vec::new();
+        }
+
+        let span = (max.wrapping_sub(expected) as usize).min(MAX_MISSING_SEQUENCES);
+        let first = max.wrapping_sub(span as u16);
+        (0..span as u16)
+            .map(|offset| first.wrapping_add(offset))
+            .filter(|seq| !self.packets.contains_key(seq))
+            .collect()
+    }
+
+    /// Clear all packets and stream state
+    ///
+    /// Call this when the stream restarts, e.g. with a new SSRC. The next
+    /// packet inserted starts a new sequence and playout schedule. Capacity,
+    /// overflow policy, clock rate and target delay are kept.
+    pub fn reset(&mut self) {
+        self.packets.clear();
+        self.dropped = 0;
+        self.next_expected_seq = None;
+        self.max_received_seq = None;
+        self.base_seq = None;
+        self.seq_cycles = 0;
+        self.received = 0;
+        self.playing = false;
+        self.reference = None;
+        self.last_transit = None;
+        self.jitter = 0.0;
+    }
+
     /// Helper function to check if sequence a comes before sequence b
     /// considering wraparound
     fn sequence_before(a: u16, b: u16) -> bool {
@@ -201,6 +736,7 @@ mod tests {
             sequence_number: 2,
             timestamp: 1200,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         buffer.insert(RTPPacket {
@@ -208,6 +744,7 @@ mod tests {
             sequence_number: 0,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         buffer.insert(RTPPacket {
@@ -215,6 +752,7 @@ mod tests {
             sequence_number: 1,
             timestamp: 1100,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         // Should retrieve in order
@@ -234,6 +772,7 @@ mod tests {
             sequence_number: 0,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         // Insert packet 2 (gap at 1)
@@ -242,6 +781,7 @@ mod tests {
             sequence_number: 2,
             timestamp: 1200,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         // Should return packet 0
@@ -256,6 +796,7 @@ mod tests {
             sequence_number: 1,
             timestamp: 1100,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         // Now should return 1 and 2
@@ -273,6 +814,7 @@ mod tests {
             sequence_number: 65535,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         buffer.insert(RTPPacket {
@@ -280,6 +822,7 @@ mod tests {
             sequence_number: 0,
             timestamp: 1100,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         buffer.insert(RTPPacket {
@@ -287,6 +830,7 @@ mod tests {
             sequence_number: 1,
             timestamp: 1200,
             ssrc: 12345,
+            ..Default::default()
         }).unwrap();
 
         // Should retrieve in wrapped order
@@ -304,6 +848,7 @@ mod tests {
             sequence_number: 5,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet1).unwrap();
@@ -314,6 +859,7 @@ mod tests {
             sequence_number: 5,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet1_dup).unwrap();
@@ -335,6 +881,7 @@ mod tests {
                 sequence_number: i as u16,
                 timestamp: 1000,
                 ssrc: 12345,
+                ..Default::default()
             }).unwrap();
         }
 
@@ -344,8 +891,55 @@ mod tests {
             sequence_number: 3,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         });
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_jitter_buffer_drops_packet_after_skip() {
+        let mut buffer = JitterBuffer::new(10);
+
+        for seq in [0u16, 2] {
+            buffer.insert(RTPPacket {
+                payload: vec![seq as u8],
+                sequence_number: seq,
+                timestamp: 1000,
+                ssrc: 12345,
+                ..Default::default()
+            }).unwrap();
+        }
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 0);
+        assert_eq!(buffer.skip_missing(), Some(1));
+
+        // Packet 1 turns up after it was given up on
+        buffer.insert(RTPPacket {
+            payload: vec![1],
+            sequence_number: 1,
+            timestamp: 1000,
+            ssrc: 12345,
+            ..Default::default()
+        }).unwrap();
+
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 2);
+    }
+
+    #[test]
+    fn test_jitter_buffer_pop_ready_without_target_delay() {
+        let mut buffer = JitterBuffer::new(10);
+        assert_eq!(buffer.target_delay(), None);
+
+        buffer.insert(RTPPacket {
+            payload: vec![0],
+            sequence_number: 0,
+            timestamp: 1000,
+            ssrc: 12345,
+            ..Default::default()
+        }).unwrap();
+
+        // Released as soon as it is in order
+        assert_eq!(buffer.pop_ready(Instant::now()).unwrap().sequence_number, 0);
+    }
 }
@@ -0,0 +1,238 @@
+//! Audio level metering and voice activity detection
+//!
+//! Measures microphone audio for input level meters and "you appear to be
+//! muted" hints.
+//!
+//! # Algorithm
+//!
+//! For each buffer:
+//!
+//! 1. Compute the mean square and peak of all samples, and count the zero
+//!    crossings of the channels mixed down to mono
+//! 2. Move the smoothed mean square toward the buffer's with an exponential
+//!    moving average over the smoothing window, and let the smoothed peak
+//!    decay over the same window unless the buffer's peak is higher
+//! 3. Detect voice when the buffer's RMS level is above the voice threshold
+//!    and its zero-crossing rate is below [`MAX_VOICE_ZERO_CROSSINGS`],
+//!    which rejects broadband noise such as hiss
+//! 4. Keep voice detected for the hangover after the last voiced buffer, so
+//!    that the pauses between words do not end it
+//!
+//! Processing does not allocate, so it can run on the capture thread.
+//!
+//! # Example
+//!
+//! ```
+//! use cortenbrowser_webrtc_integration::AudioLevelMeter;
+//!
+//! let mut meter = AudioLevelMeter::new(48000, 1);
+//!
+//! let level = meter.process(&[0.0f32; 480]);
+//! assert!(!level.voice_active);
+//! ```
+
+use cortenbrowser_shared_types::AudioLevel;
+use std::time::Duration;
+
+/// Default smoothing window of the RMS and peak levels
+const DEFAULT_SMOOTHING: Duration = Duration::from_millis(300);
+
+/// Default RMS level above which a buffer may be voice, in dBFS
+const DEFAULT_VOICE_THRESHOLD_DBFS: f32 = -45.0;
+
+/// Default time voice stays detected after the last voiced buffer
+const DEFAULT_HANGOVER: Duration = Duration::from_millis(300);
+
+/// Zero crossings per second above which a buffer is noise rather than voice
+pub const MAX_VOICE_ZERO_CROSSINGS: f32 = 5000.0;
+
+/// Audio level meter and voice activity detector
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::AudioLevelMeter;
+/// use std::time::Duration;
+///
+/// let meter = AudioLevelMeter::new(16000, 2)
+///     .with_smoothing(Duration::from_millis(100))
+///     .with_voice_detection(-50.0, Duration::from_millis(500));
+/// assert_eq!(meter.smoothing(), Duration::from_millis(100));
+/// ```
+#[derive(Debug, Clone)]
+pub struct AudioLevelMeter {
+    sample_rate: u32,
+    channels: u8,
+    smoothing: Duration,
+    voice_threshold_dbfs: f32,
+    hangover: Duration,
+    /// Smoothed mean square of the samples
+    mean_square: f32,
+    /// Smoothed peak magnitude of the samples
+    peak: f32,
+    /// Sign of the last mono sample, for counting zero crossings
+    positive: bool,
+    voice_active: bool,
+    /// Time voice stays detected without another voiced buffer
+    hangover_left: Duration,
+}
+
+impl AudioLevelMeter {
+    /// Create a new level meter
+    ///
+    /// # Arguments
+    ///
+    /// * `sample_rate` - Audio sample rate in Hz (e.g., 16000, 48000)
+    /// * `channels` - Number of interleaved channels
+    pub fn new(sample_rate: u32, channels: u8) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            smoothing: DEFAULT_SMOOTHING,
+            voice_threshold_dbfs: DEFAULT_VOICE_THRESHOLD_DBFS,
+            hangover: DEFAULT_HANGOVER,
+            mean_square: 0.0,
+            peak: 0.0,
+            positive: true,
+            voice_active: false,
+            hangover_left: Duration::ZERO,
+        }
+    }
+
+    /// Set the smoothing window of the RMS and peak levels
+    ///
+    /// A zero window reports the level of each buffer as is.
+    pub fn with_smoothing(mut self, window: Duration) -> Self {
+        self.smoothing = window;
+        self
+    }
+
+    /// Set the voice activity detector's threshold and hangover
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold_dbfs` - RMS level above which a buffer may be voice
+    /// * `hangover` - Time voice stays detected after the last voiced buffer
+    pub fn with_voice_detection(mut self, threshold_dbfs: f32, hangover: Duration) -> Self {
+        self.voice_threshold_dbfs = threshold_dbfs;
+        self.hangover = hangover;
+        self
+    }
+
+    /// Get the smoothing window of the RMS and peak levels
+    pub fn smoothing(&self) -> Duration {
+        self.smoothing
+    }
+
+    /// Get the current level, as of the last processed buffer
+    pub fn level(&self) -> AudioLevel {
+        AudioLevel {
+            rms_dbfs: to_dbfs(self.mean_square.sqrt()),
+            peak_dbfs: to_dbfs(self.peak),
+            voice_active: self.voice_active,
+        }
+    }
+
+    /// Process one buffer of audio
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Interleaved samples, whole frames of all channels
+    ///
+    /// # Returns
+    ///
+    /// The level after the buffer
+    pub fn process(&mut self, samples: &[f32]) -> AudioLevel {
+        let channels = self.channels.max(1) as usize;
+        let frames = samples.len() / channels;
+        if frames == 0 || self.sample_rate == 0 {
+            return self.level();
+        }
+
+        let mut sum_squares = 0.0f32;
+        let mut peak = 0.0f32;
+        let mut crossings = 0u32;
+        for frame in samples.chunks_exact(channels) {
+            let mut mono = 0.0;
+            for &sample in frame {
+                sum_squares += sample * sample;
+                peak = peak.max(sample.abs());
+                mono += sample;
+            }
+            let positive = mono >= 0.0;
+            if positive != self.positive {
+                crossings += 1;
+            }
+            self.positive = positive;
+        }
+        let mean_square = sum_squares / (frames * channels) as f32;
+        let duration =
+            Duration::from_nanos(frames as u64 * 1_000_000_000 / self.sample_rate as u64);
+        let seconds = duration.as_secs_f32();
+
+        // The weight of the buffer over the smoothing window
+        let decay = if self.smoothing.is_zero() {
+            0.0
+        } else {
+            (-seconds / self.smoothing.as_secs_f32()).exp()
+        };
+        self.mean_square = mean_square + (self.mean_square - mean_square) * decay;
+        self.peak = peak.max(self.peak * decay);
+
+        let voiced = to_dbfs(mean_square.sqrt()) > self.voice_threshold_dbfs
+            && crossings as f32 / seconds < MAX_VOICE_ZERO_CROSSINGS;
+        if voiced {
+            self.voice_active = true;
+            self.hangover_left = self.hangover;
+        } else if self.voice_active {
+            self.hangover_left = self.hangover_left.saturating_sub(duration);
+            self.voice_active = !self.hangover_left.is_zero();
+        }
+
+        self.level()
+    }
+
+    /// Reset the meter to silence
+    pub fn reset(&mut self) {
+        self.mean_square = 0.0;
+        self.peak = 0.0;
+        self.positive = true;
+        self.voice_active = false;
+        self.hangover_left = Duration::ZERO;
+    }
+}
+
+/// Convert a linear magnitude to dBFS, floored at [`AudioLevel::MIN_DBFS`]
+fn to_dbfs(magnitude: f32) -> f32 {
+    (20.0 * magnitude.log10()).max(AudioLevel::MIN_DBFS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meter_creation() {
+        let meter = AudioLevelMeter::new(48000, 2);
+        assert_eq!(meter.smoothing, DEFAULT_SMOOTHING);
+        assert_eq!(meter.voice_threshold_dbfs, DEFAULT_VOICE_THRESHOLD_DBFS);
+        assert_eq!(meter.level(), AudioLevel::SILENCE);
+    }
+
+    #[test]
+    fn test_dbfs_floor() {
+        assert_eq!(to_dbfs(0.0), AudioLevel::MIN_DBFS);
+        assert_eq!(to_dbfs(1.0), 0.0);
+        assert!((to_dbfs(0.5) + 6.0206).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut meter = AudioLevelMeter::new(48000, 1);
+        meter.process(&[0.5f32; 480]);
+        assert!(meter.level().peak_dbfs > AudioLevel::MIN_DBFS);
+
+        meter.reset();
+        assert_eq!(meter.level(), AudioLevel::SILENCE);
+    }
+}
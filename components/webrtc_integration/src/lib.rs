@@ -5,24 +5,54 @@
 //! This component provides:
 //! - RTP packet creation and serialization
 //! - RTP packetization for media payloads
-//! - Jitter buffer for packet reordering
-//! - WebRTC encoder wrapper
-//! - RTCP handling (stub)
+//! - Jitter buffer for packet reordering, loss detection and playout delay
+//! - Receive-side audio path with packet loss concealment
+//! - WebRTC video encoder (H.264 via OpenH264, VP8/VP9 via libvpx)
+//! - RTCP handling (REMB feedback; SR/RR stubs)
+//! - Bandwidth estimation (Google Congestion Control)
+//! - SDP offer/answer generation, parsing and negotiation
 //! - Echo cancellation hooks (stub)
+//! - Noise suppression (spectral subtraction)
+//! - Automatic gain control
+//! - Capture-side audio processing chain
+//! - Audio level metering and voice activity detection
 
 #![warn(missing_docs)]
 
 mod rtp;
 mod jitter_buffer;
+mod audio_receiver;
 mod encoder;
+#[cfg(feature = "h264")]
+mod h264_encoder;
+#[cfg(feature = "vpx")]
+mod vpx_encoder;
 mod rtcp;
 mod echo_cancellation;
+mod noise_suppression;
+mod agc;
+mod audio_processing;
+mod level_meter;
+mod bandwidth_estimation;
+mod sdp;
 
-pub use rtp::{RTPPacket, RTPPacketizer};
-pub use jitter_buffer::JitterBuffer;
-pub use encoder::{WebRTCEncoder, EncoderConfig};
-pub use rtcp::RTCPHandler;
+pub use rtp::{RTPPacket, RTPPacketizer, RtpExtension};
+pub use jitter_buffer::{
+    JitterBuffer, OverflowPolicy, ReceptionStats, DEFAULT_CLOCK_RATE, MAX_MISSING_SEQUENCES,
+};
+pub use audio_receiver::AudioReceiver;
+pub use encoder::{EncodedFrame, EncoderConfig, WebRTCEncoder};
+pub use rtcp::{RTCPHandler, ReceiverReport, RembPacket, ReportBlock, RtcpPacket, SenderReport};
 pub use echo_cancellation::EchoCanceller;
+pub use noise_suppression::NoiseSuppressor;
+pub use agc::AutoGainController;
+pub use audio_processing::AudioProcessingChain;
+pub use level_meter::{AudioLevelMeter, MAX_VOICE_ZERO_CROSSINGS};
+pub use bandwidth_estimation::{BandwidthEstimator, BandwidthUsage};
+pub use sdp::{
+    IceCredentials, MediaDescription, MediaDirection, SdpBuilder, SdpCodec, SdpNegotiator,
+    SdpParser, SessionDescription, TransportInfo,
+};
 
 // Re-export from shared_types
-pub use cortenbrowser_shared_types::MediaError;
+pub use cortenbrowser_shared_types::{AudioLevel, AudioProcessingConfig, MediaError};
@@ -1,16 +1,43 @@
 //! RTCP (RTP Control Protocol) handling
 //!
-//! **STATUS: STUB IMPLEMENTATION**
+//! **STATUS: PARTIAL IMPLEMENTATION**
 //!
-//! This module provides a placeholder for RTCP handling. Full implementation
-//! will include:
+//! Sender Reports (SR) and Receiver Reports (RR) are built and parsed, with
+//! report blocks filled in from a [`JitterBuffer`]'s reception statistics.
+//! Receiver Estimated Maximum Bitrate (REMB) feedback is parsed and fed to a
+//! [`BandwidthEstimator`]. The remaining packet types are placeholders. Full
+//! implementation will include:
 //!
-//! - Sender Reports (SR) - Statistics from media senders
-//! - Receiver Reports (RR) - Quality feedback from receivers
 //! - Source Description (SDES) - Participant information
 //! - Goodbye (BYE) - End of participation notification
 //! - Application-Defined (APP) - Custom RTCP packets
 //!
+//! # REMB
+//!
+//! REMB is a payload-specific feedback message (PT=206, FMT=15) carrying the
+//! receiver's estimate of the available bandwidth:
+//!
+//! ```text
+//!  0                   1                   2                   3
+//!  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |V=2|P| FMT=15  |   PT=206      |             length            |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                  SSRC of packet sender                        |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                  SSRC of media source (0)                     |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  Unique identifier 'R' 'E' 'M' 'B'                            |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  Num SSRC     | BR Exp    |  BR Mantissa                      |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |   SSRC feedback                                               |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  ...                                                          |
+//! ```
+//!
+//! The bitrate is `mantissa << exp` bits per second.
+//!
 //! # RTCP Specification
 //!
 //! RTCP is defined in RFC 3550 and provides:
@@ -61,25 +88,25 @@
 //!    - Coordinate with RTP transmission
 //!    - Handle clock skew
 //!
-//! # Example Future Implementation
+//! # Example
 //!
-//! ```ignore
-//! use cortenbrowser_webrtc_integration::RTCPHandler;
+//! ```
+//! use cortenbrowser_webrtc_integration::{JitterBuffer, RTCPHandler, RtcpPacket};
 //!
-//! let mut rtcp = RTCPHandler::new(ssrc);
+//! // The sender reports what it sent
+//! let mut sender = RTCPHandler::new(0x1111);
+//! let sr = sender.build_sender_report(0xE8A0_0000_8000_0000, 90_000, 100, 120_000);
 //!
-//! // Send sender report
-//! let sr = rtcp.create_sender_report(
-//!     ntp_timestamp,
-//!     rtp_timestamp,
-//!     packet_count,
-//!     octet_count,
-//! );
-//! send_rtcp_packet(sr);
+//! // The receiver notes the report and answers with its reception quality
+//! let mut receiver = RTCPHandler::new(0x2222);
+//! let buffer = JitterBuffer::new(100);
+//! assert!(matches!(receiver.parse(&sr)[..], [RtcpPacket::SenderReport(_)]));
+//! let rr = receiver.build_receiver_report(0x1111, &buffer);
 //!
-//! // Process received report
-//! let report = rtcp.parse_receiver_report(data)?;
-//! let quality = rtcp.calculate_quality_metrics(&report);
+//! match &sender.parse(&rr)[..] {
+//!     [RtcpPacket::ReceiverReport(report)] => assert_eq!(report.reports[0].ssrc, 0x1111),
+//!     other => panic!("unexpected packets {:?}", other),
+//! }
 //! ```
 //!
 //! # References
@@ -87,52 +114,535 @@
 //! - RFC 3550: RTP: A Transport Protocol for Real-Time Applications
 //! - RFC 3551: RTP Profile for Audio and Video Conferences
 //! - RFC 4585: Extended RTP Profile for RTCP-Based Feedback
+//! - draft-alvestrand-rmcat-remb-03: RTCP message for Receiver Estimated
+//!   Maximum Bitrate
+
+use crate::bandwidth_estimation::BandwidthEstimator;
+use crate::jitter_buffer::JitterBuffer;
+use cortenbrowser_shared_types::MediaError;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// RTCP sender report packet type
+const PT_SR: u8 = 200;
+
+/// RTCP receiver report packet type
+const PT_RR: u8 = 201;
+
+/// RTCP payload-specific feedback packet type
+const PT_PSFB: u8 = 206;
+
+/// Size of the RTCP header with the sender's SSRC
+const RTCP_HEADER_LEN: usize = 8;
+
+/// Size of the sender information in a sender report
+const SENDER_INFO_LEN: usize = 20;
+
+/// Size of a report block
+const REPORT_BLOCK_LEN: usize = 24;
+
+/// Most report blocks one report carries
+const MAX_REPORT_BLOCKS: usize = 31;
+
+/// Feedback message type for application layer feedback (REMB)
+const FMT_AFB: u8 = 15;
+
+/// REMB unique identifier
+const REMB_IDENTIFIER: &[u8; 4] = b"REMB";
+
+/// Fixed part of a REMB packet (header through mantissa)
+const REMB_FIXED_LEN: usize = 20;
+
+/// Receiver Estimated Maximum Bitrate packet
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::RembPacket;
+///
+/// let remb = RembPacket {
+///     sender_ssrc: 1,
+///     bitrate_bps: 750_000,
+///     ssrcs: vec![12345],
+/// };
+///
+/// let parsed = RembPacket::from_bytes(&remb.to_bytes()).unwrap();
+/// assert_eq!(parsed, remb);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RembPacket {
+    /// SSRC of the receiver sending the estimate
+    pub sender_ssrc: u32,
+    /// Estimated maximum bitrate in bits per second
+    pub bitrate_bps: u32,
+    /// Media sources the estimate applies to
+    pub ssrcs: Vec<u32>,
+}
+
+impl RembPacket {
+    /// Serialize to an RTCP REMB packet
+    ///
+    /// Bitrates that do not fit the 18-bit mantissa are encoded with the
+    /// smallest exponent that fits, rounding down.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut mantissa = self.bitrate_bps;
+        let mut exp = 0u8;
+        while mantissa >= (1 << 18) {
+            mantissa >>= 1;
+            exp += 1;
+        }
+
+        let num_ssrcs = self.ssrcs.len().min(u8::MAX as usize);
+        let length_words = (REMB_FIXED_LEN + num_ssrcs * 4) / 4 - 1;
+
+        let mut bytes = Vec::with_capacity(REMB_FIXED_LEN + num_ssrcs * 4);
+        bytes.push(0x80 | FMT_AFB);
+        bytes.push(PT_PSFB);
+        bytes.extend_from_slice(&(length_words as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        bytes.extend_from_slice(&0u32.to_be_bytes());
+        bytes.extend_from_slice(REMB_IDENTIFIER);
+        bytes.push(num_ssrcs as u8);
+        bytes.push((exp << 2) | ((mantissa >> 16) as u8 & 0x03));
+        bytes.extend_from_slice(&(mantissa as u16).to_be_bytes());
+        for ssrc in self.ssrcs.iter().take(num_ssrcs) {
+            bytes.extend_from_slice(&ssrc.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Parse an RTCP REMB packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::NetworkError` if the data is not a well-formed
+    /// REMB packet.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MediaError> {
+        let malformed = |details: &str| MediaError::NetworkError {
+            details: format!("Malformed REMB packet: {}", details),
+        };
+
+        if data.len() < REMB_FIXED_LEN {
+            return Err(malformed("too short"));
+        }
+        if data[0] >> 6 != 2 {
+            return Err(malformed("unsupported RTCP version"));
+        }
+        if data[0] & 0x1F != FMT_AFB || data[1] != PT_PSFB {
+            return Err(malformed("not an application layer feedback packet"));
+        }
+        if &data[12..16] != REMB_IDENTIFIER {
+            return Err(malformed("missing REMB identifier"));
+        }
+
+        let length = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        let num_ssrcs = data[16] as usize;
+        if data.len() < length || length < REMB_FIXED_LEN + num_ssrcs * 4 {
+            return Err(malformed("truncated SSRC list"));
+        }
+
+        let sender_ssrc = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+        let exp = u32::from(data[17] >> 2);
+        let mantissa = (u32::from(data[17] & 0x03) << 16)
+            | u32::from(u16::from_be_bytes([data[18], data[19]]));
+        let bitrate_bps = u32::try_from(u64::from(mantissa) << exp).unwrap_or(u32::MAX);
+
+        let ssrcs = data[REMB_FIXED_LEN..REMB_FIXED_LEN + num_ssrcs * 4]
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+
+        Ok(Self {
+            sender_ssrc,
+            bitrate_bps,
+            ssrcs,
+        })
+    }
+}
+
+/// Reception report about one source, carried in sender and receiver
+/// reports
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::ReportBlock;
+///
+/// // A quarter of the packets since the last report were lost
+/// let block = ReportBlock {
+///     ssrc: 0x1234,
+///     fraction_lost: 64,
+///     cumulative_lost: 25,
+///     ..Default::default()
+/// };
+/// assert_eq!(block.fraction_lost_ratio(), 0.25);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReportBlock {
+    /// Source the block reports on
+    pub ssrc: u32,
+    /// Packets lost since the previous report, as a fraction of 256
+    pub fraction_lost: u8,
+    /// Packets lost since reception began, a 24-bit signed number
+    pub cumulative_lost: i32,
+    /// Highest sequence number received, extended with the number of
+    /// sequence number cycles
+    pub extended_highest_seq: u32,
+    /// Interarrival jitter in RTP timestamp units
+    pub jitter: u32,
+    /// Middle 32 bits of the NTP timestamp of the source's last sender
+    /// report, or zero if none was received
+    pub last_sr: u32,
+    /// Time since that sender report, in units of 1/65536 seconds
+    pub delay_since_last_sr: u32,
+}
+
+impl ReportBlock {
+    /// Packets lost since the previous report, from 0.0 to 1.0
+    pub fn fraction_lost_ratio(&self) -> f64 {
+        f64::from(self.fraction_lost) / 256.0
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        let lost = self.cumulative_lost.clamp(-0x80_0000, 0x7F_FFFF) as u32 & 0xFF_FFFF;
+        bytes.extend_from_slice(&self.ssrc.to_be_bytes());
+        bytes.extend_from_slice(&((u32::from(self.fraction_lost) << 24) | lost).to_be_bytes());
+        bytes.extend_from_slice(&self.extended_highest_seq.to_be_bytes());
+        bytes.extend_from_slice(&self.jitter.to_be_bytes());
+        bytes.extend_from_slice(&self.last_sr.to_be_bytes());
+        bytes.extend_from_slice(&self.delay_since_last_sr.to_be_bytes());
+    }
+
+    fn read(data: &[u8]) -> Self {
+        let word = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        // Sign-extend the 24-bit cumulative loss
+        let cumulative_lost = ((word(4) << 8) as i32) >> 8;
+        Self {
+            ssrc: word(0),
+            fraction_lost: data[4],
+            cumulative_lost,
+            extended_highest_seq: word(8),
+            jitter: word(12),
+            last_sr: word(16),
+            delay_since_last_sr: word(20),
+        }
+    }
+}
+
+/// RTCP sender report (RFC 3550 section 6.4.1)
+///
+/// Sent by media senders, relating their RTP timestamps to wall clock time
+/// and counting what they sent, along with reports on the sources they
+/// receive.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SenderReport {
+    /// SSRC of the sender
+    pub ssrc: u32,
+    /// Wall clock time of the report as a 64-bit NTP timestamp
+    pub ntp_time: u64,
+    /// RTP timestamp corresponding to `ntp_time`
+    pub rtp_time: u32,
+    /// RTP packets sent since the stream started
+    pub packet_count: u32,
+    /// Payload bytes sent since the stream started
+    pub octet_count: u32,
+    /// Reports on the sources the sender receives
+    pub reports: Vec<ReportBlock>,
+}
+
+impl SenderReport {
+    /// Serialize to an RTCP SR packet
+    ///
+    /// At most 31 report blocks are written.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let count = self.reports.len().min(MAX_REPORT_BLOCKS);
+        let mut bytes = rtcp_header(PT_SR, count, SENDER_INFO_LEN, self.ssrc);
+        bytes.extend_from_slice(&self.ntp_time.to_be_bytes());
+        bytes.extend_from_slice(&self.rtp_time.to_be_bytes());
+        bytes.extend_from_slice(&self.packet_count.to_be_bytes());
+        bytes.extend_from_slice(&self.octet_count.to_be_bytes());
+        for block in &self.reports[..count] {
+            block.write(&mut bytes);
+        }
+        bytes
+    }
+}
 
-/// RTCP packet handler (stub)
+/// RTCP receiver report (RFC 3550 section 6.4.2)
 ///
-/// **STUB IMPLEMENTATION**: This is a placeholder for RTCP functionality.
+/// Sent by participants that are not sending media, with reports on the
+/// sources they receive.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReceiverReport {
+    /// SSRC of the receiver
+    pub ssrc: u32,
+    /// Reports on the sources the receiver receives
+    pub reports: Vec<ReportBlock>,
+}
+
+impl ReceiverReport {
+    /// Serialize to an RTCP RR packet
+    ///
+    /// At most 31 report blocks are written.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let count = self.reports.len().min(MAX_REPORT_BLOCKS);
+        let mut bytes = rtcp_header(PT_RR, count, 0, self.ssrc);
+        for block in &self.reports[..count] {
+            block.write(&mut bytes);
+        }
+        bytes
+    }
+}
+
+/// A packet of a compound RTCP packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtcpPacket {
+    /// Sender report
+    SenderReport(SenderReport),
+    /// Receiver report
+    ReceiverReport(ReceiverReport),
+    /// Receiver estimated maximum bitrate
+    Remb(RembPacket),
+    /// A packet of a type not handled here, such as SDES or BYE
+    Other {
+        /// RTCP packet type
+        packet_type: u8,
+    },
+}
+
+/// Start an RTCP packet with `count` report blocks after `body_len` bytes
+/// following the sender's SSRC
+fn rtcp_header(packet_type: u8, count: usize, body_len: usize, ssrc: u32) -> Vec<u8> {
+    let len = RTCP_HEADER_LEN + body_len + count * REPORT_BLOCK_LEN;
+    let mut bytes = Vec::with_capacity(len);
+    bytes.push(0x80 | count as u8);
+    bytes.push(packet_type);
+    bytes.extend_from_slice(&((len / 4 - 1) as u16).to_be_bytes());
+    bytes.extend_from_slice(&ssrc.to_be_bytes());
+    bytes
+}
+
+/// Parse one packet of a compound RTCP packet, its length already checked
+fn parse_packet(packet: &[u8]) -> Option<RtcpPacket> {
+    let count = (packet[0] & 0x1F) as usize;
+    let packet_type = packet[1];
+    let body_len = match packet_type {
+        PT_SR => SENDER_INFO_LEN,
+        PT_RR => 0,
+        PT_PSFB if count as u8 == FMT_AFB => {
+            return RembPacket::from_bytes(packet)
+                .ok()
+                .map(RtcpPacket::Remb)
+                .or(Some(RtcpPacket::Other { packet_type }));
+        }
+        _ => return Some(RtcpPacket::Other { packet_type }),
+    };
+
+    let blocks_start = RTCP_HEADER_LEN + body_len;
+    let blocks = packet.get(blocks_start..blocks_start + count * REPORT_BLOCK_LEN)?;
+    let ssrc = u32::from_be_bytes([packet[4], packet[5], packet[6], packet[7]]);
+    let reports = blocks
+        .chunks_exact(REPORT_BLOCK_LEN)
+        .map(ReportBlock::read)
+        .collect();
+
+    if packet_type == PT_RR {
+        return Some(RtcpPacket::ReceiverReport(ReceiverReport { ssrc, reports }));
+    }
+    let info = &packet[RTCP_HEADER_LEN..blocks_start];
+    let word = |i: usize| u32::from_be_bytes([info[i], info[i + 1], info[i + 2], info[i + 3]]);
+    Some(RtcpPacket::SenderReport(SenderReport {
+        ssrc,
+        ntp_time: (u64::from(word(0)) << 32) | u64::from(word(4)),
+        rtp_time: word(8),
+        packet_count: word(12),
+        octet_count: word(16),
+        reports,
+    }))
+}
+
+/// RTCP packet handler
+///
+/// Builds the sender and receiver reports of one participant and parses
+/// the RTCP packets it receives. It remembers what it needs between reports:
+/// the reception statistics of the previous report on each source, for the
+/// fraction lost, and when each source's last sender report arrived.
 /// See module documentation for specification details.
 pub struct RTCPHandler {
-    #[allow(dead_code)]
     ssrc: u32,
+    /// Packets expected and received from each source at its previous report
+    prior_reception: HashMap<u32, (u64, u64)>,
+    /// Middle 32 bits of the NTP timestamp of each source's last sender
+    /// report, and when it arrived
+    last_sender_reports: HashMap<u32, (u32, Instant)>,
 }
 
 impl RTCPHandler {
     /// Create a new RTCP handler
     ///
-    /// **STUB**: Returns a placeholder handler.
-    ///
     /// # Arguments
     ///
     /// * `ssrc` - Synchronization source identifier
     pub fn new(ssrc: u32) -> Self {
-        Self { ssrc }
+        Self {
+            ssrc,
+            prior_reception: HashMap::new(),
+            last_sender_reports: HashMap::new(),
+        }
     }
 
-    /// Create a sender report (stub)
+    /// Build a sender report
+    ///
+    /// # Arguments
     ///
-    /// **STUB**: Future implementation will create proper SR packets.
-    #[allow(dead_code)]
-    pub fn create_sender_report(
+    /// * `ntp_time` - Wall clock time of the report as a 64-bit NTP timestamp
+    /// * `rtp_time` - RTP timestamp corresponding to `ntp_time`
+    /// * `packet_count` - RTP packets sent since the stream started
+    /// * `octet_count` - Payload bytes sent since the stream started
+    ///
+    /// # Returns
+    ///
+    /// The serialized SR packet, without report blocks
+    pub fn build_sender_report(
         &self,
-        _ntp_timestamp: u64,
-        _rtp_timestamp: u32,
-        _packet_count: u32,
-        _octet_count: u32,
+        ntp_time: u64,
+        rtp_time: u32,
+        packet_count: u32,
+        octet_count: u32,
     ) -> Vec<u8> {
-        // Stub: Return empty for now
-        // Real implementation will format SR according to RFC 3550
-        vec![]
+        SenderReport {
+            ssrc: self.ssrc,
+            ntp_time,
+            rtp_time,
+            packet_count,
+            octet_count,
+            reports: Vec::new(),
+        }
+        .to_bytes()
     }
 
-    /// Parse a receiver report (stub)
+    /// Build a receiver report on a source
+    ///
+    /// The report block is filled in from the reception statistics of the
+    /// jitter buffer receiving the source. The fraction lost covers the
+    /// packets since the previous report on the source, and the last sender
+    /// report is the last one [`parse`](Self::parse) saw from it.
     ///
-    /// **STUB**: Future implementation will parse RR packets.
-    #[allow(dead_code)]
-    pub fn parse_receiver_report(&self, _data: &[u8]) -> Result<(), String> {
-        // Stub: Accept any input for now
-        // Real implementation will parse RR according to RFC 3550
-        Ok(())
+    /// # Arguments
+    ///
+    /// * `source_ssrc` - SSRC of the source reported on
+    /// * `buffer` - Jitter buffer receiving the source
+    ///
+    /// # Returns
+    ///
+    /// The serialized RR packet
+    pub fn build_receiver_report(&mut self, source_ssrc: u32, buffer: &JitterBuffer) -> Vec<u8> {
+        let block = self.report_block(source_ssrc, buffer, Instant::now());
+        ReceiverReport {
+            ssrc: self.ssrc,
+            reports: vec![block],
+        }
+        .to_bytes()
+    }
+
+    /// Report on a source as of `now`, starting a new loss interval
+    fn report_block(
+        &mut self,
+        source_ssrc: u32,
+        buffer: &JitterBuffer,
+        now: Instant,
+    ) -> ReportBlock {
+        let stats = buffer.reception_stats();
+        let (prior_expected, prior_received) = self
+            .prior_reception
+            .insert(
+                source_ssrc,
+                (stats.packets_expected, stats.packets_received),
+            )
+            .unwrap_or_default();
+
+        // RFC 3550 appendix A.3
+        let expected_interval = stats.packets_expected.saturating_sub(prior_expected);
+        let received_interval = stats.packets_received.saturating_sub(prior_received);
+        let fraction_lost = if expected_interval == 0 || received_interval >= expected_interval {
+            0
+        } else {
+            (((expected_interval - received_interval) << 8) / expected_interval) as u8
+        };
+
+        let (last_sr, delay_since_last_sr) = self
+            .last_sender_reports
+            .get(&source_ssrc)
+            .map(|&(last_sr, arrival)| {
+                let delay = now.saturating_duration_since(arrival).as_secs_f64();
+                (last_sr, (delay * 65536.0) as u32)
+            })
+            .unwrap_or_default();
+
+        ReportBlock {
+            ssrc: source_ssrc,
+            fraction_lost,
+            cumulative_lost: stats.cumulative_lost().clamp(-0x80_0000, 0x7F_FFFF) as i32,
+            extended_highest_seq: stats.extended_highest_seq,
+            jitter: stats.jitter,
+            last_sr,
+            delay_since_last_sr,
+        }
+    }
+
+    /// Parse a compound RTCP packet
+    ///
+    /// Sender reports are remembered, so that the next receiver report on
+    /// their source says when the last one arrived. Parsing stops at the
+    /// first malformed packet, returning the packets before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - One or more RTCP packets, as received
+    ///
+    /// # Returns
+    ///
+    /// The packets in order
+    pub fn parse(&mut self, data: &[u8]) -> Vec<RtcpPacket> {
+        let mut packets = Vec::new();
+        let mut rest = data;
+        while rest.len() >= 4 && rest[0] >> 6 == 2 {
+            let len = (u16::from_be_bytes([rest[2], rest[3]]) as usize + 1) * 4;
+            if len < RTCP_HEADER_LEN || len > rest.len() {
+                break;
+            }
+            let Some(packet) = parse_packet(&rest[..len]) else {
+                break;
+            };
+            if let RtcpPacket::SenderReport(report) = &packet {
+                let last_sr = (report.ntp_time >> 16) as u32;
+                self.last_sender_reports
+                    .insert(report.ssrc, (last_sr, Instant::now()));
+            }
+            packets.push(packet);
+            rest = &rest[len..];
+        }
+        packets
+    }
+
+    /// Parse a REMB packet and apply its estimate to a bandwidth estimator
+    ///
+    /// # Returns
+    ///
+    /// The parsed REMB packet
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::NetworkError` if the data is not a well-formed
+    /// REMB packet. The estimator is left unchanged in that case.
+    pub fn handle_remb(
+        &self,
+        data: &[u8],
+        estimator: &mut BandwidthEstimator,
+    ) -> Result<RembPacket, MediaError> {
+        let remb = RembPacket::from_bytes(data)?;
+        estimator.update_remote_estimate(remb.bitrate_bps);
+        Ok(remb)
     }
 }
 
@@ -147,18 +657,67 @@ mod tests {
     }
 
     #[test]
-    fn test_rtcp_sender_report_stub() {
+    fn test_rtcp_sender_report_layout() {
         let handler = RTCPHandler::new(12345);
-        let sr = handler.create_sender_report(1000, 2000, 100, 50000);
-        // Stub returns empty vec
-        assert!(sr.is_empty());
+        let sr = handler.build_sender_report(1000, 2000, 100, 50000);
+        assert_eq!(sr.len(), 28);
+        assert_eq!((sr[0], sr[1]), (0x80, PT_SR));
+        assert_eq!(u16::from_be_bytes([sr[2], sr[3]]), 6);
+        assert_eq!(u32::from_be_bytes([sr[4], sr[5], sr[6], sr[7]]), 12345);
     }
 
     #[test]
-    fn test_rtcp_receiver_report_stub() {
-        let handler = RTCPHandler::new(12345);
-        let result = handler.parse_receiver_report(&[1, 2, 3, 4]);
-        // Stub accepts any input
-        assert!(result.is_ok());
+    fn test_report_block_cumulative_lost_sign_extends() {
+        let block = ReportBlock {
+            cumulative_lost: -3,
+            ..Default::default()
+        };
+        let mut bytes = Vec::new();
+        block.write(&mut bytes);
+        assert_eq!(&bytes[5..8], &[0xFF, 0xFF, 0xFD]);
+        assert_eq!(ReportBlock::read(&bytes), block);
+    }
+
+    #[test]
+    fn test_rtcp_parse_stops_at_malformed_packet() {
+        let mut handler = RTCPHandler::new(1);
+        let mut data = ReceiverReport {
+            ssrc: 2,
+            reports: vec![ReportBlock::default()],
+        }
+        .to_bytes();
+        // Claims longer than the data that follows
+        data.extend_from_slice(&[0x80, PT_RR, 0x00, 0x10, 0, 0, 0, 3]);
+        assert!(matches!(
+            handler.parse(&data)[..],
+            [RtcpPacket::ReceiverReport(_)]
+        ));
+    }
+
+    #[test]
+    fn test_remb_large_bitrate_uses_exponent() {
+        let remb = RembPacket {
+            sender_ssrc: 1,
+            bitrate_bps: 10_000_000,
+            ssrcs: vec![],
+        };
+        let bytes = remb.to_bytes();
+        assert!(bytes[17] >> 2 > 0);
+
+        let parsed = RembPacket::from_bytes(&bytes).unwrap();
+        assert!(parsed.bitrate_bps <= 10_000_000);
+        assert!(parsed.bitrate_bps > 9_990_000);
+    }
+
+    #[test]
+    fn test_remb_rejects_wrong_packet_type() {
+        let mut bytes = RembPacket {
+            sender_ssrc: 1,
+            bitrate_bps: 500_000,
+            ssrcs: vec![2],
+        }
+        .to_bytes();
+        bytes[1] = 200;
+        assert!(RembPacket::from_bytes(&bytes).is_err());
     }
 }
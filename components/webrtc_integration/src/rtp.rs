@@ -2,11 +2,51 @@
 //!
 //! Implements RTP (Real-time Transport Protocol) packet structure and packetization.
 
+use cortenbrowser_shared_types::MediaError;
 use std::cell::Cell;
 
 /// Maximum Transmission Unit for RTP packets (bytes)
 const RTP_MTU: usize = 1200;
 
+/// Size of the fixed RTP header (bytes)
+const RTP_HEADER_LEN: usize = 12;
+
+/// NAL unit type of H.264 FU-A fragmentation units (RFC 6184)
+const FU_A_TYPE: u8 = 28;
+
+/// FU header bit marking the first fragment of a NAL unit
+const FU_START: u8 = 0x80;
+
+/// FU header bit marking the last fragment of a NAL unit
+const FU_END: u8 = 0x40;
+
+/// Profile of RFC 8285 header extensions with one-byte element headers
+const ONE_BYTE_PROFILE: u16 = 0xBEDE;
+/// Profile of RFC 8285 header extensions with two-byte element headers,
+/// whose low 4 bits are application bits
+const TWO_BYTE_PROFILE: u16 = 0x1000;
+
+/// RTP header extension element (RFC 8285)
+///
+/// # Examples
+///
+/// ```
+/// use cortenbrowser_webrtc_integration::RtpExtension;
+///
+/// // abs-send-time, negotiated as extension 3
+/// let extension = RtpExtension {
+///     id: 3,
+///     data: vec![0x12, 0x34, 0x56],
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpExtension {
+    /// Extension ID negotiated in SDP (`a=extmap`)
+    pub id: u8,
+    /// Extension element data
+    pub data: Vec<u8>,
+}
+
 /// RTP packet structure
 ///
 /// Represents an RTP packet with header fields and payload.
@@ -21,9 +61,10 @@ const RTP_MTU: usize = 1200;
 ///     sequence_number: 100,
 ///     timestamp: 1000,
 ///     ssrc: 0x12345678,
+///     ..Default::default()
 /// };
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct RTPPacket {
     /// Packet payload data
     pub payload: Vec<u8>,
@@ -33,6 +74,14 @@ pub struct RTPPacket {
     pub timestamp: u32,
     /// Synchronization source identifier
     pub ssrc: u32,
+    /// Marker bit, e.g. set on the last packet of a video frame
+    pub marker: bool,
+    /// Payload type (7 bits)
+    pub payload_type: u8,
+    /// Contributing source identifiers (at most 15)
+    pub csrcs: Vec<u32>,
+    /// Header extension elements
+    pub extensions: Vec<RtpExtension>,
 }
 
 impl RTPPacket {
@@ -40,7 +89,10 @@ impl RTPPacket {
     ///
     /// Creates a properly formatted RTP packet with:
     /// - Version 2
-    /// - Fixed 12-byte header
+    /// - Fixed 12-byte header, followed by the CSRC list
+    /// - A header extension if there are extension elements, using
+    ///   one-byte element headers when every ID is 1-14 and every element
+    ///   holds 1-16 bytes, two-byte element headers otherwise
     /// - Payload appended after header
     ///
     /// # Examples
@@ -53,20 +105,22 @@ impl RTPPacket {
     ///     sequence_number: 42,
     ///     timestamp: 9000,
     ///     ssrc: 0xDEADBEEF,
+    ///     ..Default::default()
     /// };
     ///
     /// let bytes = packet.to_bytes();
     /// assert!(bytes.len() >= 12 + 2);
     /// ```
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::with_capacity(12 + self.payload.len());
+        let csrc_count = self.csrcs.len().min(15);
+        let mut bytes = Vec::with_capacity(RTP_HEADER_LEN + csrc_count * 4 + self.payload.len());
 
-        // Byte 0: Version (2 bits) = 2, P=0, X=0, CC=0
-        // 10 00 0000 = 0x80
-        bytes.push(0x80);
+        // Byte 0: Version (2 bits) = 2, P=0, X, CC
+        let extension_bit = if self.extensions.is_empty() { 0 } else { 0x10 };
+        bytes.push(0x80 | extension_bit | csrc_count as u8);
 
-        // Byte 1: M=0, PT=0 (payload type, can be extended later)
-        bytes.push(0x00);
+        // Byte 1: M, PT
+        bytes.push(u8::from(self.marker) << 7 | (self.payload_type & 0x7F));
 
         // Bytes 2-3: Sequence number (big-endian)
         bytes.extend_from_slice(&self.sequence_number.to_be_bytes());
@@ -77,17 +131,209 @@ impl RTPPacket {
         // Bytes 8-11: SSRC (big-endian)
         bytes.extend_from_slice(&self.ssrc.to_be_bytes());
 
-        // Bytes 12+: Payload
+        // CSRC list
+        for csrc in self.csrcs.iter().take(csrc_count) {
+            bytes.extend_from_slice(&csrc.to_be_bytes());
+        }
+
+        if !self.extensions.is_empty() {
+            self.write_extensions(&mut bytes);
+        }
+
+        // Payload
         bytes.extend_from_slice(&self.payload);
 
         bytes
     }
+
+    /// Append the header extension: profile, length in 32-bit words and
+    /// the elements, zero-padded to a multiple of 4 bytes
+    fn write_extensions(&self, bytes: &mut Vec<u8>) {
+        let one_byte = self
+            .extensions
+            .iter()
+            .all(|ext| (1..=14).contains(&ext.id) && (1..=16).contains(&ext.data.len()));
+
+        let mut elements = Vec::new();
+        for ext in &self.extensions {
+            // Two-byte element headers cannot describe more than 255 bytes
+            let data = &ext.data[..ext.data.len().min(u8::MAX as usize)];
+            if one_byte {
+                elements.push(ext.id << 4 | (data.len() - 1) as u8);
+            } else {
+                elements.push(ext.id);
+                elements.push(data.len() as u8);
+            }
+            elements.extend_from_slice(data);
+        }
+        elements.resize(elements.len().div_ceil(4) * 4, 0);
+
+        let profile = if one_byte {
+            ONE_BYTE_PROFILE
+        } else {
+            TWO_BYTE_PROFILE
+        };
+        bytes.extend_from_slice(&profile.to_be_bytes());
+        bytes.extend_from_slice(&((elements.len() / 4) as u16).to_be_bytes());
+        bytes.extend_from_slice(&elements);
+    }
+
+    /// Parse an RTP packet
+    ///
+    /// Reads the fixed header, the CSRC list and the header extension, and
+    /// drops any padding. Header extensions in RFC 8285 one-byte or
+    /// two-byte form are split into their elements; extensions of other
+    /// profiles are skipped.
+    ///
+    /// # Returns
+    ///
+    /// The packet, and its payload as a slice of `data`
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::NetworkError` if the data is shorter than its
+    /// header, padding or extension says, or is not RTP version 2.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::RTPPacket;
+    ///
+    /// let packet = RTPPacket {
+    ///     payload: vec![0xAA, 0xBB],
+    ///     sequence_number: 42,
+    ///     timestamp: 9000,
+    ///     ssrc: 0xDEADBEEF,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let bytes = packet.to_bytes();
+    /// let (parsed, payload) = RTPPacket::from_bytes(&bytes).unwrap();
+    /// assert_eq!(parsed, packet);
+    /// assert_eq!(payload, &[0xAA, 0xBB]);
+    /// ```
+    pub fn from_bytes(data: &[u8]) -> Result<(Self, &[u8]), MediaError> {
+        let malformed = |details: &str| MediaError::NetworkError {
+            details: format!("Malformed RTP packet: {}", details),
+        };
+
+        if data.len() < RTP_HEADER_LEN {
+            return Err(malformed("shorter than the fixed header"));
+        }
+        if data[0] >> 6 != 2 {
+            return Err(malformed("unsupported RTP version"));
+        }
+        let has_padding = data[0] & 0x20 != 0;
+        let has_extension = data[0] & 0x10 != 0;
+        let csrc_count = (data[0] & 0x0F) as usize;
+
+        let mut offset = RTP_HEADER_LEN;
+        let csrc_end = offset + csrc_count * 4;
+        let csrcs = data
+            .get(offset..csrc_end)
+            .ok_or_else(|| malformed("truncated CSRC list"))?
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        offset = csrc_end;
+
+        let mut extensions = Vec::new();
+        if has_extension {
+            let header = data
+                .get(offset..offset + 4)
+                .ok_or_else(|| malformed("truncated extension header"))?;
+            let profile = u16::from_be_bytes([header[0], header[1]]);
+            let length = u16::from_be_bytes([header[2], header[3]]) as usize * 4;
+            let elements = data
+                .get(offset + 4..offset + 4 + length)
+                .ok_or_else(|| malformed("truncated extension"))?;
+            if profile == ONE_BYTE_PROFILE {
+                extensions = parse_one_byte_extensions(elements)
+                    .ok_or_else(|| malformed("truncated extension element"))?;
+            } else if profile & 0xFFF0 == TWO_BYTE_PROFILE {
+                extensions = parse_two_byte_extensions(elements)
+                    .ok_or_else(|| malformed("truncated extension element"))?;
+            }
+            offset += 4 + length;
+        }
+
+        let mut end = data.len();
+        if has_padding {
+            let padding = data[end - 1] as usize;
+            if padding == 0 || offset + padding > end {
+                return Err(malformed("invalid padding"));
+            }
+            end -= padding;
+        }
+        let payload = &data[offset..end];
+
+        let packet = Self {
+            payload: payload.to_vec(),
+            sequence_number: u16::from_be_bytes([data[2], data[3]]),
+            timestamp: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            ssrc: u32::from_be_bytes([data[8], data[9], data[10], data[11]]),
+            marker: data[1] & 0x80 != 0,
+            payload_type: data[1] & 0x7F,
+            csrcs,
+            extensions,
+        };
+        Ok((packet, payload))
+    }
+}
+
+/// Split RFC 8285 one-byte header extension elements
+///
+/// Each element starts with a byte holding its ID and its length minus
+/// one. ID 0 is padding, and ID 15 ends the elements.
+fn parse_one_byte_extensions(mut elements: &[u8]) -> Option<Vec<RtpExtension>> {
+    let mut extensions = Vec::new();
+    while let Some((&header, rest)) = elements.split_first() {
+        let id = header >> 4;
+        if id == 0 {
+            elements = rest;
+            continue;
+        }
+        if id == 15 {
+            break;
+        }
+        let len = (header & 0x0F) as usize + 1;
+        let data = rest.get(..len)?;
+        extensions.push(RtpExtension {
+            id,
+            data: data.to_vec(),
+        });
+        elements = &rest[len..];
+    }
+    Some(extensions)
+}
+
+/// Split RFC 8285 two-byte header extension elements
+///
+/// Each element starts with its ID and its length. ID 0 is padding.
+fn parse_two_byte_extensions(mut elements: &[u8]) -> Option<Vec<RtpExtension>> {
+    let mut extensions = Vec::new();
+    while let Some((&id, rest)) = elements.split_first() {
+        if id == 0 {
+            elements = rest;
+            continue;
+        }
+        let (&len, rest) = rest.split_first()?;
+        let data = rest.get(..len as usize)?;
+        extensions.push(RtpExtension {
+            id,
+            data: data.to_vec(),
+        });
+        elements = &rest[len as usize..];
+    }
+    Some(extensions)
 }
 
 /// RTP packetizer for fragmenting payloads
 ///
 /// Handles fragmentation of large payloads into RTP packets
-/// that fit within MTU constraints.
+/// that fit within MTU constraints. Opaque payloads are split at the MTU;
+/// H.264 is split at NAL unit boundaries with
+/// [`packetize_h264`](Self::packetize_h264).
 ///
 /// # Examples
 ///
@@ -160,30 +406,123 @@ impl RTPPacketizer {
             return vec![];
         }
 
-        let mut packets = Vec::new();
-        let mut offset = 0;
-
-        while offset < payload.len() {
-            let chunk_size = std::cmp::min(RTP_MTU, payload.len() - offset);
-            let chunk = payload[offset..offset + chunk_size].to_vec();
+        payload
+            .chunks(RTP_MTU)
+            .map(|chunk| self.next_packet(chunk.to_vec(), timestamp))
+            .collect()
+    }
 
-            let seq = self.sequence_number.get();
-            let packet = RTPPacket {
-                payload: chunk,
-                sequence_number: seq,
-                timestamp,
-                ssrc: self.ssrc,
-            };
+    /// Packetize an H.264 access unit into RTP packets (RFC 6184)
+    ///
+    /// The access unit is an Annex B bitstream, its NAL units separated by
+    /// start codes. NAL units that fit the MTU are sent in single NAL unit
+    /// packets; larger ones are split into FU-A fragmentation units, the
+    /// first with the start bit and the last with the end bit set. The last
+    /// packet of the access unit has the marker bit set.
+    ///
+    /// # Arguments
+    ///
+    /// * `access_unit` - The NAL units of one picture, in Annex B format
+    /// * `timestamp` - The RTP timestamp of the picture
+    ///
+    /// # Returns
+    ///
+    /// A vector of RTP packets, empty if the access unit has no NAL units
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use cortenbrowser_webrtc_integration::RTPPacketizer;
+    ///
+    /// let packetizer = RTPPacketizer::new();
+    /// let mut access_unit = vec![0, 0, 0, 1, 0x67, 0x42, 0x00, 0x1F];
+    /// access_unit.extend_from_slice(&[0, 0, 1, 0x65]);
+    /// access_unit.extend_from_slice(&[0xAB; 3000]);
+    ///
+    /// let packets = packetizer.packetize_h264(&access_unit, 3000);
+    /// // The SPS fits one packet, the slice is fragmented
+    /// assert_eq!(packets[0].payload, [0x67, 0x42, 0x00, 0x1F]);
+    /// assert_eq!(packets[1].payload[0] & 0x1F, 28);
+    /// assert!(packets.last().unwrap().marker);
+    /// ```
+    pub fn packetize_h264(&self, access_unit: &[u8], timestamp: u32) -> Vec<RTPPacket> {
+        let mut packets = Vec::new();
+        for nal in split_nal_units(access_unit) {
+            if nal.len() <= RTP_MTU {
+                packets.push(self.next_packet(nal.to_vec(), timestamp));
+                continue;
+            }
+
+            // FU indicator keeps the NAL's F and NRI bits, FU header its type
+            let indicator = (nal[0] & 0xE0) | FU_A_TYPE;
+            let nal_type = nal[0] & 0x1F;
+            let fragments: Vec<&[u8]> = nal[1..].chunks(RTP_MTU - 2).collect();
+            let last = fragments.len() - 1;
+            for (i, fragment) in fragments.into_iter().enumerate() {
+                let mut header = nal_type;
+                if i == 0 {
+                    header |= FU_START;
+                }
+                if i == last {
+                    header |= FU_END;
+                }
+                let mut payload = Vec::with_capacity(fragment.len() + 2);
+                payload.extend_from_slice(&[indicator, header]);
+                payload.extend_from_slice(fragment);
+                packets.push(self.next_packet(payload, timestamp));
+            }
+        }
 
-            packets.push(packet);
+        if let Some(last) = packets.last_mut() {
+            last.marker = true;
+        }
+        packets
+    }
 
-            // Increment sequence number with wraparound
-            self.sequence_number.set(seq.wrapping_add(1));
-            offset += chunk_size;
+    /// Build the stream's next packet, incrementing the sequence number
+    fn next_packet(&self, payload: Vec<u8>, timestamp: u32) -> RTPPacket {
+        let seq = self.sequence_number.get();
+        // Increment sequence number with wraparound
+        self.sequence_number.set(seq.wrapping_add(1));
+        RTPPacket {
+            payload,
+            sequence_number: seq,
+            timestamp,
+            ssrc: self.ssrc,
+            ..Default::default()
         }
+    }
+}
 
-        packets
+/// Split an Annex B bitstream into its NAL units
+///
+/// NAL units are separated by three-byte or four-byte start codes; empty
+/// NAL units and data before the first start code are dropped.
+fn split_nal_units(data: &[u8]) -> Vec<&[u8]> {
+    let mut nals = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i..i + 3] == [0, 0, 1] {
+            if let Some(begin) = start {
+                // A zero before the start code belongs to a four-byte one
+                let mut end = i;
+                if end > begin && data[end - 1] == 0 {
+                    end -= 1;
+                }
+                nals.push(&data[begin..end]);
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+    if let Some(begin) = start {
+        nals.push(&data[begin..]);
     }
+    nals.retain(|nal| !nal.is_empty());
+    nals
 }
 
 impl Default for RTPPacketizer {
@@ -203,6 +542,7 @@ mod tests {
             sequence_number: 42,
             timestamp: 9000,
             ssrc: 0xDEADBEEF,
+            ..Default::default()
         };
 
         let bytes = packet.to_bytes();
@@ -241,6 +581,16 @@ mod tests {
         assert_eq!(total_size, 3000);
     }
 
+    #[test]
+    fn test_split_nal_units() {
+        let data = [
+            0xFF, 0, 0, 0, 1, 0x67, 0x42, 0, 0, 1, 0x68, 0, 0, 1, 0, 0, 0, 1, 0x65, 0,
+        ];
+        let nals = split_nal_units(&data);
+        assert_eq!(nals, vec![&[0x67, 0x42][..], &[0x68], &[0x65, 0]]);
+        assert!(split_nal_units(&[0x65, 0x88]).is_empty());
+    }
+
     #[test]
     fn test_packetizer_sequence_increment() {
         let packetizer = RTPPacketizer::new();
@@ -250,4 +600,42 @@ mod tests {
 
         assert_eq!(packets2[0].sequence_number, packets1[0].sequence_number + 1);
     }
+
+    #[test]
+    fn test_parse_extensions_skips_padding_and_unknown_profiles() {
+        let mut bytes = RTPPacket {
+            payload: vec![0xEE],
+            ..Default::default()
+        }
+        .to_bytes();
+        bytes[0] |= 0x30;
+        // One-byte elements: padding, ID 5 with 2 bytes, then padding to 8
+        // bytes. The packet ends in 3 bytes of padding.
+        let extension = [
+            0xBE, 0xDE, 0x00, 0x02, 0x00, 0x51, 0xAB, 0xCD, 0x00, 0x00, 0x00, 0x00,
+        ];
+        bytes.splice(12..12, extension);
+        bytes.extend_from_slice(&[0, 0, 3]);
+
+        let (packet, payload) = RTPPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(
+            packet.extensions,
+            vec![RtpExtension {
+                id: 5,
+                data: vec![0xAB, 0xCD]
+            }]
+        );
+        assert_eq!(payload, &[0xEE]);
+
+        // An extension of another profile is skipped
+        bytes[12..14].copy_from_slice(&[0x12, 0x34]);
+        let (packet, payload) = RTPPacket::from_bytes(&bytes).unwrap();
+        assert!(packet.extensions.is_empty());
+        assert_eq!(payload, &[0xEE]);
+
+        // Element longer than the extension
+        bytes[12..14].copy_from_slice(&[0xBE, 0xDE]);
+        bytes[17] = 0x5F;
+        assert!(RTPPacket::from_bytes(&bytes).is_err());
+    }
 }
@@ -0,0 +1,923 @@
+//! SDP (Session Description Protocol) offer/answer
+//!
+//! Generates, parses and negotiates video session descriptions for WebRTC
+//! peer connections.
+//!
+//! # Offer/Answer Flow
+//!
+//! ```text
+//! Local:  SdpBuilder::create_offer ──> SDP text ──> remote peer
+//! Remote: SdpParser::parse ──> SdpNegotiator::answer ──> SDP text ──> local peer
+//! Local:  SdpParser::parse ──> SessionDescription::select_encoder ──> WebRTCEncoder
+//! ```
+//!
+//! Only video media sections are described. Codecs are mapped to RTP
+//! payload formats as follows:
+//!
+//! | Codec | rtpmap        | fmtp                                   |
+//! |-------|---------------|----------------------------------------|
+//! | H.264 | `H264/90000`  | `packetization-mode`, `profile-level-id` |
+//! | VP8   | `VP8/90000`   | -                                      |
+//! | VP9   | `VP9/90000`   | `profile-id`                           |
+//! | AV1   | `AV1/90000`   | `profile`, `level-idx`                 |
+//!
+//! H.265 and Theora have no WebRTC payload format and are never offered.
+//!
+//! # Example
+//!
+//! ```
+//! use cortenbrowser_webrtc_integration::{EncoderConfig, SdpBuilder, SdpNegotiator, SdpParser};
+//! use cortenbrowser_shared_types::VideoCodec;
+//!
+//! let config = EncoderConfig { bitrate: 1_000_000, framerate: 30, keyframe_interval: 60 };
+//! let offer_sdp = SdpBuilder::create_offer(&config, &[VideoCodec::VP8]);
+//!
+//! let offer = SdpParser::parse(&offer_sdp).unwrap();
+//! let answer = SdpNegotiator::answer(&offer, &[VideoCodec::VP8]).unwrap();
+//!
+//! let (codec, config) = answer.select_encoder(&config).unwrap();
+//! assert_eq!(codec, VideoCodec::VP8);
+//! assert_eq!(config.bitrate, 1_000_000);
+//! ```
+//!
+//! # References
+//!
+//! - RFC 8866: SDP: Session Description Protocol
+//! - RFC 3264: An Offer/Answer Model with SDP
+//! - RFC 6184: RTP Payload Format for H.264 Video
+//! - RFC 7741: RTP Payload Format for VP8 Video
+
+use crate::encoder::EncoderConfig;
+use cortenbrowser_shared_types::{AV1Level, AV1Profile, H264Level, H264Profile, MediaError, VideoCodec, VP9Profile};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+/// First dynamic RTP payload type
+const FIRST_DYNAMIC_PAYLOAD_TYPE: u8 = 96;
+
+/// RTP clock rate for all video payload formats
+const VIDEO_CLOCK_RATE: u32 = 90_000;
+
+/// Transport protocol used for WebRTC media
+const WEBRTC_PROTOCOL: &str = "UDP/TLS/RTP/SAVPF";
+
+/// Placeholder port used before ICE candidates are gathered
+const DISCARD_PORT: u16 = 9;
+
+/// CNAME advertised with local SSRCs
+const CNAME: &str = "corten";
+
+/// Media direction attribute
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaDirection {
+    /// Send and receive (`a=sendrecv`)
+    SendRecv,
+    /// Send only (`a=sendonly`)
+    SendOnly,
+    /// Receive only (`a=recvonly`)
+    RecvOnly,
+    /// Neither send nor receive (`a=inactive`)
+    Inactive,
+}
+
+impl MediaDirection {
+    /// The direction the answerer uses in response to this offered direction
+    pub fn reverse(self) -> Self {
+        match self {
+            MediaDirection::SendOnly => MediaDirection::RecvOnly,
+            MediaDirection::RecvOnly => MediaDirection::SendOnly,
+            other => other,
+        }
+    }
+
+    fn as_attribute(self) -> &'static str {
+        match self {
+            MediaDirection::SendRecv => "sendrecv",
+            MediaDirection::SendOnly => "sendonly",
+            MediaDirection::RecvOnly => "recvonly",
+            MediaDirection::Inactive => "inactive",
+        }
+    }
+}
+
+/// ICE username fragment and password
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IceCredentials {
+    /// `a=ice-ufrag` value
+    pub ufrag: String,
+    /// `a=ice-pwd` value
+    pub pwd: String,
+}
+
+impl IceCredentials {
+    /// Generate random credentials (4 character ufrag, 24 character password)
+    pub fn generate() -> Self {
+        Self {
+            ufrag: random_token(4),
+            pwd: random_token(24),
+        }
+    }
+}
+
+/// Transport information from the `m=` and `c=` lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransportInfo {
+    /// Media port
+    pub port: u16,
+    /// Transport protocol (e.g., `UDP/TLS/RTP/SAVPF`)
+    pub protocol: String,
+    /// Connection address
+    pub address: String,
+}
+
+/// A codec mapped to an RTP payload type
+#[derive(Debug, Clone, PartialEq)]
+pub struct SdpCodec {
+    /// RTP payload type
+    pub payload_type: u8,
+    /// Codec carried by this payload type
+    pub codec: VideoCodec,
+    /// RTP clock rate in Hz
+    pub clock_rate: u32,
+}
+
+/// A video media section (`m=video ...`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaDescription {
+    /// Media identification tag (`a=mid`)
+    pub mid: Option<String>,
+    /// Port, protocol and connection address
+    pub transport: TransportInfo,
+    /// Codecs in order of preference
+    pub codecs: Vec<SdpCodec>,
+    /// Media direction
+    pub direction: MediaDirection,
+    /// ICE credentials, from the media section or the session level
+    pub ice: Option<IceCredentials>,
+    /// SSRCs announced with `a=ssrc`
+    pub ssrcs: Vec<u32>,
+    /// Application-specific maximum bandwidth in kbps (`b=AS`)
+    pub bandwidth_kbps: Option<u32>,
+    /// Maximum frame rate (`a=framerate`)
+    pub framerate: Option<u32>,
+}
+
+/// A parsed or generated session description
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionDescription {
+    /// Session identifier from the `o=` line
+    pub session_id: u64,
+    /// Session version from the `o=` line
+    pub session_version: u64,
+    /// Video media sections
+    pub media: Vec<MediaDescription>,
+}
+
+impl SessionDescription {
+    /// Pick the codec and encoder configuration to send with
+    ///
+    /// Uses the first codec of the first media section that has one.
+    /// `b=AS` and `a=framerate` override the bitrate and framerate of
+    /// `defaults`; the keyframe interval is always taken from `defaults`.
+    ///
+    /// # Returns
+    ///
+    /// `None` if no media section carries a codec
+    pub fn select_encoder(&self, defaults: &EncoderConfig) -> Option<(VideoCodec, EncoderConfig)> {
+        let media = self.media.iter().find(|m| !m.codecs.is_empty())?;
+        let codec = media.codecs[0].codec.clone();
+
+        let config = EncoderConfig {
+            bitrate: media
+                .bandwidth_kbps
+                .map(|kbps| kbps.saturating_mul(1000))
+                .unwrap_or(defaults.bitrate),
+            framerate: media.framerate.unwrap_or(defaults.framerate),
+            keyframe_interval: defaults.keyframe_interval,
+        };
+
+        Some((codec, config))
+    }
+
+    /// Serialize to SDP text
+    pub fn to_sdp(&self) -> String {
+        let mut lines = vec![
+            "v=0".to_string(),
+            format!(
+                "o=- {} {} IN IP4 127.0.0.1",
+                self.session_id, self.session_version
+            ),
+            "s=-".to_string(),
+            "t=0 0".to_string(),
+        ];
+
+        for media in &self.media {
+            let payload_types: Vec<String> = media
+                .codecs
+                .iter()
+                .map(|c| c.payload_type.to_string())
+                .collect();
+            lines.push(format!(
+                "m=video {} {} {}",
+                media.transport.port,
+                media.transport.protocol,
+                payload_types.join(" ")
+            ));
+            lines.push(format!("c=IN IP4 {}", media.transport.address));
+            if let Some(kbps) = media.bandwidth_kbps {
+                lines.push(format!("b=AS:{}", kbps));
+            }
+            if let Some(mid) = &media.mid {
+                lines.push(format!("a=mid:{}", mid));
+            }
+            if let Some(ice) = &media.ice {
+                lines.push(format!("a=ice-ufrag:{}", ice.ufrag));
+                lines.push(format!("a=ice-pwd:{}", ice.pwd));
+            }
+            lines.push(format!("a={}", media.direction.as_attribute()));
+            lines.push("a=rtcp-mux".to_string());
+
+            for codec in &media.codecs {
+                if let Some(name) = encoding_name(&codec.codec) {
+                    lines.push(format!(
+                        "a=rtpmap:{} {}/{}",
+                        codec.payload_type, name, codec.clock_rate
+                    ));
+                }
+                if let Some(fmtp) = format_parameters(&codec.codec) {
+                    lines.push(format!("a=fmtp:{} {}", codec.payload_type, fmtp));
+                }
+            }
+
+            if let Some(framerate) = media.framerate {
+                lines.push(format!("a=framerate:{}", framerate));
+            }
+            for ssrc in &media.ssrcs {
+                lines.push(format!("a=ssrc:{} cname:{}", ssrc, CNAME));
+            }
+        }
+
+        let mut sdp = lines.join("\r\n");
+        sdp.push_str("\r\n");
+        sdp
+    }
+}
+
+/// Builds SDP offers
+pub struct SdpBuilder;
+
+impl SdpBuilder {
+    /// Create an SDP offer for one send/receive video section
+    ///
+    /// Codecs are offered in the given order with dynamic payload types
+    /// starting at 96. Codecs without a WebRTC payload format are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Encoder configuration; sets `b=AS` and `a=framerate`
+    /// * `codecs` - Codecs to offer, most preferred first
+    pub fn create_offer(config: &EncoderConfig, codecs: &[VideoCodec]) -> String {
+        Self::offer_description(config, codecs).to_sdp()
+    }
+
+    /// Create the session description behind [`SdpBuilder::create_offer`]
+    pub fn offer_description(config: &EncoderConfig, codecs: &[VideoCodec]) -> SessionDescription {
+        let codecs = codecs
+            .iter()
+            .filter(|codec| encoding_name(codec).is_some())
+            .zip(FIRST_DYNAMIC_PAYLOAD_TYPE..)
+            .map(|(codec, payload_type)| SdpCodec {
+                payload_type,
+                codec: codec.clone(),
+                clock_rate: VIDEO_CLOCK_RATE,
+            })
+            .collect();
+
+        let mut rng = rand::thread_rng();
+        SessionDescription {
+            session_id: rng.gen_range(1..i64::MAX as u64),
+            session_version: 2,
+            media: vec![MediaDescription {
+                mid: Some("0".to_string()),
+                transport: TransportInfo {
+                    port: DISCARD_PORT,
+                    protocol: WEBRTC_PROTOCOL.to_string(),
+                    address: "0.0.0.0".to_string(),
+                },
+                codecs,
+                direction: MediaDirection::SendRecv,
+                ice: Some(IceCredentials::generate()),
+                ssrcs: vec![rng.gen()],
+                bandwidth_kbps: Some(config.bitrate / 1000),
+                framerate: Some(config.framerate),
+            }],
+        }
+    }
+}
+
+/// Parses SDP text
+pub struct SdpParser;
+
+impl SdpParser {
+    /// Parse an SDP session description
+    ///
+    /// Non-video media sections and unknown codecs are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::InvalidParameter` if:
+    /// - The `v=` or `o=` line is missing or malformed
+    /// - A line is not of the form `<type>=<value>`
+    /// - An `m=`, `c=`, `b=`, `a=rtpmap` or `a=ssrc` line is malformed
+    pub fn parse(sdp: &str) -> Result<SessionDescription, MediaError> {
+        let mut version_seen = false;
+        let mut origin: Option<(u64, u64)> = None;
+        let mut session_ice_ufrag: Option<String> = None;
+        let mut session_ice_pwd: Option<String> = None;
+        let mut session_address: Option<String> = None;
+
+        let mut media: Vec<PartialMedia> = Vec::new();
+        // Whether the current m= section is a video section
+        let mut in_video = false;
+
+        for line in sdp.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+
+            let (kind, value) = line
+                .split_once('=')
+                .filter(|(kind, _)| kind.len() == 1)
+                .ok_or_else(|| invalid(format!("malformed line '{}'", line)))?;
+
+            if kind == "m" {
+                in_video = value.starts_with("video ");
+                if in_video {
+                    media.push(PartialMedia::from_media_line(value)?);
+                }
+                continue;
+            }
+
+            if media.is_empty() {
+                // Session-level line
+                match kind {
+                    "v" => {
+                        if value != "0" {
+                            return Err(invalid(format!("unsupported version '{}'", value)));
+                        }
+                        version_seen = true;
+                    }
+                    "o" => origin = Some(parse_origin(value)?),
+                    "c" => session_address = Some(parse_connection(value)?),
+                    "a" => {
+                        if let Some(ufrag) = value.strip_prefix("ice-ufrag:") {
+                            session_ice_ufrag = Some(ufrag.to_string());
+                        } else if let Some(pwd) = value.strip_prefix("ice-pwd:") {
+                            session_ice_pwd = Some(pwd.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            if !in_video {
+                continue;
+            }
+
+            let current = media.last_mut().expect("media section present");
+            match kind {
+                "c" => current.address = Some(parse_connection(value)?),
+                "b" => {
+                    if let Some(kbps) = value.strip_prefix("AS:") {
+                        current.bandwidth_kbps = Some(
+                            kbps.parse()
+                                .map_err(|_| invalid(format!("malformed bandwidth '{}'", value)))?,
+                        );
+                    }
+                }
+                "a" => current.apply_attribute(value)?,
+                _ => {}
+            }
+        }
+
+        if !version_seen {
+            return Err(invalid("missing v= line".to_string()));
+        }
+        let (session_id, session_version) =
+            origin.ok_or_else(|| invalid("missing o= line".to_string()))?;
+
+        let session_ice = match (session_ice_ufrag, session_ice_pwd) {
+            (Some(ufrag), Some(pwd)) => Some(IceCredentials { ufrag, pwd }),
+            _ => None,
+        };
+
+        Ok(SessionDescription {
+            session_id,
+            session_version,
+            media: media
+                .into_iter()
+                .map(|m| m.finish(session_address.as_deref(), session_ice.as_ref()))
+                .collect(),
+        })
+    }
+}
+
+/// Negotiates answers to SDP offers
+pub struct SdpNegotiator;
+
+impl SdpNegotiator {
+    /// Answer an offer with the codecs both sides support
+    ///
+    /// Each offered media section keeps the offered codecs, in the offerer's
+    /// order and with the offerer's payload types, that match a supported
+    /// codec. H.264 matches on profile and uses the lower of the two levels;
+    /// VP9 and AV1 match on profile. Media sections without a common codec
+    /// are rejected with port 0, as RFC 3264 requires.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::UnsupportedFormat` if no media section has a
+    /// codec in common with `supported`.
+    pub fn answer(
+        offer: &SessionDescription,
+        supported: &[VideoCodec],
+    ) -> Result<SessionDescription, MediaError> {
+        let mut rng = rand::thread_rng();
+        let ice = IceCredentials::generate();
+
+        let media: Vec<MediaDescription> = offer
+            .media
+            .iter()
+            .map(|offered| {
+                let codecs: Vec<SdpCodec> = offered
+                    .codecs
+                    .iter()
+                    .filter_map(|c| {
+                        supported
+                            .iter()
+                            .find_map(|s| negotiate_codec(&c.codec, s))
+                            .map(|codec| SdpCodec {
+                                payload_type: c.payload_type,
+                                codec,
+                                clock_rate: c.clock_rate,
+                            })
+                    })
+                    .collect();
+
+                let accepted = !codecs.is_empty();
+                MediaDescription {
+                    mid: offered.mid.clone(),
+                    transport: TransportInfo {
+                        port: if accepted { DISCARD_PORT } else { 0 },
+                        protocol: offered.transport.protocol.clone(),
+                        address: "0.0.0.0".to_string(),
+                    },
+                    codecs,
+                    direction: if accepted {
+                        offered.direction.reverse()
+                    } else {
+                        MediaDirection::Inactive
+                    },
+                    ice: Some(ice.clone()),
+                    ssrcs: if accepted { vec![rng.gen()] } else { Vec::new() },
+                    bandwidth_kbps: offered.bandwidth_kbps,
+                    framerate: offered.framerate,
+                }
+            })
+            .collect();
+
+        if media.iter().all(|m| m.codecs.is_empty()) {
+            let offered: Vec<String> = offer
+                .media
+                .iter()
+                .flat_map(|m| m.codecs.iter())
+                .filter_map(|c| encoding_name(&c.codec))
+                .map(str::to_string)
+                .collect();
+            return Err(MediaError::UnsupportedFormat {
+                format: format!("no common video codec in offer [{}]", offered.join(", ")),
+            });
+        }
+
+        Ok(SessionDescription {
+            session_id: rng.gen_range(1..i64::MAX as u64),
+            session_version: 2,
+            media,
+        })
+    }
+}
+
+/// Media section being assembled during parsing
+struct PartialMedia {
+    port: u16,
+    protocol: String,
+    payload_types: Vec<u8>,
+    address: Option<String>,
+    mid: Option<String>,
+    rtpmaps: Vec<(u8, String, u32)>,
+    fmtps: Vec<(u8, String)>,
+    direction: MediaDirection,
+    ice_ufrag: Option<String>,
+    ice_pwd: Option<String>,
+    ssrcs: Vec<u32>,
+    bandwidth_kbps: Option<u32>,
+    framerate: Option<u32>,
+}
+
+impl PartialMedia {
+    /// Parse `video <port> <proto> <fmt> ...`
+    fn from_media_line(value: &str) -> Result<Self, MediaError> {
+        let mut fields = value.split_whitespace().skip(1);
+        let port = fields
+            .next()
+            .and_then(|p| p.split('/').next())
+            .and_then(|p| p.parse().ok())
+            .ok_or_else(|| invalid(format!("malformed media line 'm={}'", value)))?;
+        let protocol = fields
+            .next()
+            .ok_or_else(|| invalid(format!("malformed media line 'm={}'", value)))?
+            .to_string();
+        let payload_types = fields.filter_map(|pt| pt.parse().ok()).collect();
+
+        Ok(Self {
+            port,
+            protocol,
+            payload_types,
+            address: None,
+            mid: None,
+            rtpmaps: Vec::new(),
+            fmtps: Vec::new(),
+            direction: MediaDirection::SendRecv,
+            ice_ufrag: None,
+            ice_pwd: None,
+            ssrcs: Vec::new(),
+            bandwidth_kbps: None,
+            framerate: None,
+        })
+    }
+
+    fn apply_attribute(&mut self, value: &str) -> Result<(), MediaError> {
+        let (name, arg) = value.split_once(':').unwrap_or((value, ""));
+        match name {
+            "sendrecv" => self.direction = MediaDirection::SendRecv,
+            "sendonly" => self.direction = MediaDirection::SendOnly,
+            "recvonly" => self.direction = MediaDirection::RecvOnly,
+            "inactive" => self.direction = MediaDirection::Inactive,
+            "mid" => self.mid = Some(arg.to_string()),
+            "ice-ufrag" => self.ice_ufrag = Some(arg.to_string()),
+            "ice-pwd" => self.ice_pwd = Some(arg.to_string()),
+            "framerate" => {
+                // Frame rates may be fractional (e.g., 29.97)
+                self.framerate = arg.parse::<f64>().ok().map(|f| f.round() as u32);
+            }
+            "rtpmap" => {
+                let malformed = || invalid(format!("malformed rtpmap 'a={}'", value));
+                let (pt, encoding) = arg.split_once(' ').ok_or_else(malformed)?;
+                let mut parts = encoding.split('/');
+                let name = parts.next().ok_or_else(malformed)?;
+                let clock_rate = parts
+                    .next()
+                    .and_then(|r| r.parse().ok())
+                    .ok_or_else(malformed)?;
+                let pt = pt.parse().map_err(|_| malformed())?;
+                self.rtpmaps.push((pt, name.to_string(), clock_rate));
+            }
+            "fmtp" => {
+                if let Some((pt, params)) = arg.split_once(' ') {
+                    if let Ok(pt) = pt.parse() {
+                        self.fmtps.push((pt, params.to_string()));
+                    }
+                }
+            }
+            "ssrc" => {
+                let id = arg
+                    .split_whitespace()
+                    .next()
+                    .and_then(|id| id.parse().ok())
+                    .ok_or_else(|| invalid(format!("malformed ssrc 'a={}'", value)))?;
+                if !self.ssrcs.contains(&id) {
+                    self.ssrcs.push(id);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn finish(self, session_address: Option<&str>, session_ice: Option<&IceCredentials>) -> MediaDescription {
+        let codecs = self
+            .payload_types
+            .iter()
+            .filter_map(|&pt| {
+                let (_, name, clock_rate) = self.rtpmaps.iter().find(|(p, _, _)| *p == pt)?;
+                let fmtp = self
+                    .fmtps
+                    .iter()
+                    .find(|(p, _)| *p == pt)
+                    .map(|(_, params)| params.as_str());
+                Some(SdpCodec {
+                    payload_type: pt,
+                    codec: codec_from_sdp(name, fmtp)?,
+                    clock_rate: *clock_rate,
+                })
+            })
+            .collect();
+
+        let ice = match (self.ice_ufrag, self.ice_pwd) {
+            (Some(ufrag), Some(pwd)) => Some(IceCredentials { ufrag, pwd }),
+            _ => session_ice.cloned(),
+        };
+
+        MediaDescription {
+            mid: self.mid,
+            transport: TransportInfo {
+                port: self.port,
+                protocol: self.protocol,
+                address: self
+                    .address
+                    .or_else(|| session_address.map(str::to_string))
+                    .unwrap_or_else(|| "0.0.0.0".to_string()),
+            },
+            codecs,
+            direction: self.direction,
+            ice,
+            ssrcs: self.ssrcs,
+            bandwidth_kbps: self.bandwidth_kbps,
+            framerate: self.framerate,
+        }
+    }
+}
+
+fn invalid(details: String) -> MediaError {
+    MediaError::InvalidParameter(format!("Invalid SDP: {}", details))
+}
+
+fn random_token(len: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+/// Parse `- <sess-id> <sess-version> IN IP4 <address>`
+fn parse_origin(value: &str) -> Result<(u64, u64), MediaError> {
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    if fields.len() != 6 {
+        return Err(invalid(format!("malformed origin 'o={}'", value)));
+    }
+    let id = fields[1]
+        .parse()
+        .map_err(|_| invalid(format!("malformed session id '{}'", fields[1])))?;
+    let version = fields[2]
+        .parse()
+        .map_err(|_| invalid(format!("malformed session version '{}'", fields[2])))?;
+    Ok((id, version))
+}
+
+/// Parse `IN IP4 <address>`
+fn parse_connection(value: &str) -> Result<String, MediaError> {
+    let fields: Vec<&str> = value.split_whitespace().collect();
+    if fields.len() != 3 || fields[0] != "IN" {
+        return Err(invalid(format!("malformed connection 'c={}'", value)));
+    }
+    Ok(fields[2].to_string())
+}
+
+/// rtpmap encoding name, or `None` if the codec has no WebRTC payload format
+fn encoding_name(codec: &VideoCodec) -> Option<&'static str> {
+    match codec {
+        VideoCodec::H264 { .. } => Some("H264"),
+        VideoCodec::VP8 => Some("VP8"),
+        VideoCodec::VP9 { .. } => Some("VP9"),
+        VideoCodec::AV1 { .. } => Some("AV1"),
+        VideoCodec::H265 { .. } | VideoCodec::Theora => None,
+    }
+}
+
+/// fmtp parameters for a codec
+fn format_parameters(codec: &VideoCodec) -> Option<String> {
+    match codec {
+        VideoCodec::H264 { profile, level, .. } => Some(format!(
+            "level-asymmetry-allowed=1;packetization-mode=1;profile-level-id={}",
+            h264_profile_level_id(*profile, *level)
+        )),
+        VideoCodec::VP9 { profile } => Some(format!("profile-id={}", vp9_profile_id(*profile))),
+        VideoCodec::AV1 { profile, level } => Some(format!(
+            "profile={};level-idx={}",
+            av1_profile_id(*profile),
+            av1_level_idx(*level)
+        )),
+        _ => None,
+    }
+}
+
+/// Build a codec from an rtpmap encoding name and fmtp parameters
+fn codec_from_sdp(name: &str, fmtp: Option<&str>) -> Option<VideoCodec> {
+    let param = |key: &str| {
+        fmtp?.split(';').find_map(|p| {
+            let (k, v) = p.trim().split_once('=')?;
+            (k == key).then(|| v.to_string())
+        })
+    };
+
+    match name.to_ascii_uppercase().as_str() {
+        "H264" => {
+            // RFC 6184 default when profile-level-id is absent: Baseline
+            let (profile, level) = param("profile-level-id")
+                .and_then(|id| parse_h264_profile_level_id(&id))
+                .unwrap_or((H264Profile::Baseline, H264Level::Level3_0));
+            Some(VideoCodec::H264 {
+                profile,
+                level,
+                hardware_accel: false,
+            })
+        }
+        "VP8" => Some(VideoCodec::VP8),
+        "VP9" => {
+            let profile = match param("profile-id").as_deref() {
+                Some("1") => VP9Profile::Profile1,
+                Some("2") => VP9Profile::Profile2,
+                Some("3") => VP9Profile::Profile3,
+                _ => VP9Profile::Profile0,
+            };
+            Some(VideoCodec::VP9 { profile })
+        }
+        "AV1" => {
+            let profile = match param("profile").as_deref() {
+                Some("1") => AV1Profile::High,
+                Some("2") => AV1Profile::Professional,
+                _ => AV1Profile::Main,
+            };
+            // RFC default level-idx is 5 (level 3.1); clamp to the lowest known level
+            let level = match param("level-idx").and_then(|l| l.parse::<u8>().ok()) {
+                Some(idx) if idx >= 13 => AV1Level::Level5_1,
+                Some(12) => AV1Level::Level5_0,
+                Some(idx) if idx >= 9 => AV1Level::Level4_1,
+                _ => AV1Level::Level4_0,
+            };
+            Some(VideoCodec::AV1 { profile, level })
+        }
+        _ => None,
+    }
+}
+
+/// Match an offered codec against a locally supported one
+///
+/// Returns the codec to use, or `None` if they are incompatible.
+fn negotiate_codec(offered: &VideoCodec, supported: &VideoCodec) -> Option<VideoCodec> {
+    match (offered, supported) {
+        (
+            VideoCodec::H264 { profile: op, level: ol, .. },
+            VideoCodec::H264 { profile: sp, level: sl, hardware_accel },
+        ) if op == sp => Some(VideoCodec::H264 {
+            profile: *op,
+            level: (*ol).min(*sl),
+            hardware_accel: *hardware_accel,
+        }),
+        (VideoCodec::VP8, VideoCodec::VP8) => Some(VideoCodec::VP8),
+        (VideoCodec::VP9 { profile: op }, VideoCodec::VP9 { profile: sp }) if op == sp => {
+            Some(offered.clone())
+        }
+        (
+            VideoCodec::AV1 { profile: op, level: ol },
+            VideoCodec::AV1 { profile: sp, level: sl },
+        ) if op == sp => Some(VideoCodec::AV1 {
+            profile: *op,
+            level: (*ol).min(*sl),
+        }),
+        _ => None,
+    }
+}
+
+/// Hex `profile-level-id` (profile_idc, constraint flags, level_idc)
+fn h264_profile_level_id(profile: H264Profile, level: H264Level) -> String {
+    let (profile_idc, constraints) = match profile {
+        // Constrained Baseline, the profile every WebRTC endpoint supports
+        H264Profile::Baseline => (0x42, 0xE0),
+        H264Profile::Main => (0x4D, 0x00),
+        H264Profile::High => (0x64, 0x00),
+        H264Profile::High10 => (0x6E, 0x00),
+        H264Profile::High422 => (0x7A, 0x00),
+        H264Profile::High444 => (0xF4, 0x00),
+    };
+    let level_idc = match level {
+        H264Level::Level3_0 => 30,
+        H264Level::Level3_1 => 31,
+        H264Level::Level4_0 => 40,
+        H264Level::Level4_1 => 41,
+        H264Level::Level5_0 => 50,
+        H264Level::Level5_1 => 51,
+    };
+    format!("{:02x}{:02x}{:02x}", profile_idc, constraints, level_idc)
+}
+
+fn parse_h264_profile_level_id(id: &str) -> Option<(H264Profile, H264Level)> {
+    if id.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(id, 16).ok()?;
+    let profile = match (value >> 16) as u8 {
+        0x42 => H264Profile::Baseline,
+        0x4D => H264Profile::Main,
+        0x64 => H264Profile::High,
+        0x6E => H264Profile::High10,
+        0x7A => H264Profile::High422,
+        0xF4 => H264Profile::High444,
+        _ => return None,
+    };
+    // Levels below 3.0 map to 3.0, the lowest level we model
+    let level = match value as u8 {
+        idc if idc >= 51 => H264Level::Level5_1,
+        50 => H264Level::Level5_0,
+        idc if idc >= 41 => H264Level::Level4_1,
+        40 => H264Level::Level4_0,
+        idc if idc >= 31 => H264Level::Level3_1,
+        _ => H264Level::Level3_0,
+    };
+    Some((profile, level))
+}
+
+fn vp9_profile_id(profile: VP9Profile) -> u8 {
+    match profile {
+        VP9Profile::Profile0 => 0,
+        VP9Profile::Profile1 => 1,
+        VP9Profile::Profile2 => 2,
+        VP9Profile::Profile3 => 3,
+    }
+}
+
+fn av1_profile_id(profile: AV1Profile) -> u8 {
+    match profile {
+        AV1Profile::Main => 0,
+        AV1Profile::High => 1,
+        AV1Profile::Professional => 2,
+    }
+}
+
+/// AV1 `seq_level_idx` (level X.Y = (X - 2) * 4 + Y)
+fn av1_level_idx(level: AV1Level) -> u8 {
+    match level {
+        AV1Level::Level4_0 => 8,
+        AV1Level::Level4_1 => 9,
+        AV1Level::Level5_0 => 12,
+        AV1Level::Level5_1 => 13,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_h264_profile_level_id_roundtrip() {
+        for profile in [H264Profile::Baseline, H264Profile::Main, H264Profile::High] {
+            for level in [H264Level::Level3_1, H264Level::Level4_0, H264Level::Level5_1] {
+                let id = h264_profile_level_id(profile, level);
+                assert_eq!(parse_h264_profile_level_id(&id), Some((profile, level)));
+            }
+        }
+        assert_eq!(h264_profile_level_id(H264Profile::Baseline, H264Level::Level3_1), "42e01f");
+    }
+
+    #[test]
+    fn test_codec_from_sdp_defaults() {
+        assert_eq!(
+            codec_from_sdp("h264", None),
+            Some(VideoCodec::H264 {
+                profile: H264Profile::Baseline,
+                level: H264Level::Level3_0,
+                hardware_accel: false,
+            })
+        );
+        assert_eq!(
+            codec_from_sdp("VP9", None),
+            Some(VideoCodec::VP9 { profile: VP9Profile::Profile0 })
+        );
+        assert_eq!(codec_from_sdp("opus", None), None);
+    }
+
+    #[test]
+    fn test_negotiate_h264_uses_lower_level() {
+        let offered = VideoCodec::H264 {
+            profile: H264Profile::High,
+            level: H264Level::Level5_1,
+            hardware_accel: false,
+        };
+        let supported = VideoCodec::H264 {
+            profile: H264Profile::High,
+            level: H264Level::Level4_0,
+            hardware_accel: true,
+        };
+        assert_eq!(
+            negotiate_codec(&offered, &supported),
+            Some(VideoCodec::H264 {
+                profile: H264Profile::High,
+                level: H264Level::Level4_0,
+                hardware_accel: true,
+            })
+        );
+    }
+}
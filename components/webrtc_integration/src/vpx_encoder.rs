@@ -0,0 +1,217 @@
+//! VP8 and VP9 encoding with libvpx
+//!
+//! Backs [`WebRTCEncoder`](crate::WebRTCEncoder) for VP8 and VP9 through the
+//! `vpx_codec_*_cx` encoder interfaces (vpx-sys bindings), in one-pass
+//! constant bitrate mode without lookahead, as real-time streaming needs.
+
+use crate::encoder::{i420_planes, EncodedFrame, EncoderConfig};
+use cortenbrowser_shared_types::{MediaError, VideoFrame};
+use std::ptr;
+
+/// Encoder interface of a libvpx codec
+type Interface = unsafe extern "C" fn() -> *mut vpx_sys::vpx_codec_iface_t;
+
+/// libvpx encoder for one stream
+pub(crate) struct VpxEncoder {
+    /// Codec name for error messages
+    name: &'static str,
+    interface: Interface,
+    config: EncoderConfig,
+    /// VPX codec context
+    ctx: Box<vpx_sys::vpx_codec_ctx_t>,
+    /// Configuration the context was initialized with
+    cfg: vpx_sys::vpx_codec_enc_cfg_t,
+    /// Frame size the context was initialized for, once initialized
+    dimensions: Option<(u32, u32)>,
+}
+
+// SAFETY: the codec context is only used through `&mut self`, and libvpx
+// keeps no thread-local state for it
+unsafe impl Send for VpxEncoder {}
+
+impl VpxEncoder {
+    /// Create a VP8 encoder
+    pub(crate) fn vp8(config: EncoderConfig) -> Self {
+        Self::new("VP8", vpx_sys::vpx_codec_vp8_cx, config)
+    }
+
+    /// Create a VP9 encoder
+    pub(crate) fn vp9(config: EncoderConfig) -> Self {
+        Self::new("VP9", vpx_sys::vpx_codec_vp9_cx, config)
+    }
+
+    fn new(name: &'static str, interface: Interface, config: EncoderConfig) -> Self {
+        Self {
+            name,
+            interface,
+            config,
+            ctx: Box::new(unsafe { std::mem::zeroed::<vpx_sys::vpx_codec_ctx_t>() }),
+            cfg: unsafe { std::mem::zeroed::<vpx_sys::vpx_codec_enc_cfg_t>() },
+            dimensions: None,
+        }
+    }
+
+    /// Encode a frame at `bitrate`
+    ///
+    /// # Errors
+    ///
+    /// Returns `MediaError::CodecError` if the frame is not YUV420 with even
+    /// dimensions, or libvpx fails to initialize or encode.
+    pub(crate) fn encode(
+        &mut self,
+        frame: &VideoFrame,
+        force_keyframe: bool,
+        bitrate: u32,
+    ) -> Result<EncodedFrame, MediaError> {
+        let planes = i420_planes(frame)?;
+
+        // A new frame size restarts the stream, beginning with a keyframe
+        if self.dimensions != Some((frame.width, frame.height)) {
+            self.init(frame.width, frame.height, bitrate)?;
+        } else if kbps(bitrate) != self.cfg.rc_target_bitrate {
+            self.cfg.rc_target_bitrate = kbps(bitrate);
+            let ret = unsafe { vpx_sys::vpx_codec_enc_config_set(self.ctx.as_mut(), &self.cfg) };
+            self.check(ret, "bitrate change")?;
+        }
+
+        // libvpx only reads the planes, which `i420_planes` checked are
+        // sized for the frame with their strides
+        let mut image = unsafe { std::mem::zeroed::<vpx_sys::vpx_image_t>() };
+        let wrapped = unsafe {
+            vpx_sys::vpx_img_wrap(
+                &mut image,
+                vpx_sys::vpx_img_fmt::VPX_IMG_FMT_I420,
+                frame.width,
+                frame.height,
+                1,
+                planes[0].0.as_ptr() as *mut u8,
+            )
+        };
+        if wrapped.is_null() {
+            return Err(MediaError::CodecError {
+                details: format!(
+                    "Failed to wrap {}x{} frame for {}",
+                    frame.width, frame.height, self.name
+                ),
+            });
+        }
+        for (index, (plane, stride)) in planes.into_iter().enumerate() {
+            image.planes[index] = plane.as_ptr() as *mut u8;
+            image.stride[index] = stride as i32;
+        }
+
+        let flags = if force_keyframe {
+            vpx_sys::VPX_EFLAG_FORCE_KF
+        } else {
+            0
+        };
+        let duration = frame
+            .duration
+            .map(|d| d.as_millis())
+            .unwrap_or(1000 / self.config.framerate as u128);
+        let ret = unsafe {
+            vpx_sys::vpx_codec_encode(
+                self.ctx.as_mut(),
+                &image,
+                frame.timestamp.as_millis() as vpx_sys::vpx_codec_pts_t,
+                duration as _,
+                flags as _,
+                vpx_sys::VPX_DL_REALTIME as _,
+            )
+        };
+        self.check(ret, "encode")?;
+
+        // Without lookahead, the frame's packets are ready straight away
+        let mut data = Vec::new();
+        let mut is_keyframe = false;
+        let mut iter = ptr::null();
+        loop {
+            let packet = unsafe { vpx_sys::vpx_codec_get_cx_data(self.ctx.as_mut(), &mut iter) };
+            if packet.is_null() {
+                break;
+            }
+
+            let packet = unsafe { &*packet };
+            if packet.kind != vpx_sys::vpx_codec_cx_pkt_kind::VPX_CODEC_CX_FRAME_PKT {
+                continue;
+            }
+
+            let compressed = unsafe { packet.data.frame };
+            data.extend_from_slice(unsafe {
+                std::slice::from_raw_parts(compressed.buf as *const u8, compressed.sz)
+            });
+            is_keyframe |= compressed.flags
+                & vpx_sys::VPX_FRAME_IS_KEY as vpx_sys::vpx_codec_frame_flags_t
+                != 0;
+        }
+
+        Ok(EncodedFrame {
+            data,
+            is_keyframe,
+            timestamp: frame.timestamp,
+        })
+    }
+
+    /// Initialize the codec context for `width`x`height` frames
+    fn init(&mut self, width: u32, height: u32, bitrate: u32) -> Result<(), MediaError> {
+        self.destroy();
+
+        let iface = unsafe { (self.interface)() };
+        let ret = unsafe { vpx_sys::vpx_codec_enc_config_default(iface, &mut self.cfg, 0) };
+        self.check(ret, "configuration")?;
+
+        // Millisecond timestamps; keyframes are forced by `WebRTCEncoder`
+        // so that they follow `keyframe_interval` and frame metadata
+        self.cfg.g_w = width;
+        self.cfg.g_h = height;
+        self.cfg.g_timebase.num = 1;
+        self.cfg.g_timebase.den = 1000;
+        self.cfg.g_lag_in_frames = 0;
+        self.cfg.rc_end_usage = vpx_sys::vpx_rc_mode::VPX_CBR;
+        self.cfg.rc_target_bitrate = kbps(bitrate);
+        self.cfg.kf_mode = vpx_sys::vpx_kf_mode::VPX_KF_DISABLED;
+
+        let ret = unsafe {
+            vpx_sys::vpx_codec_enc_init_ver(
+                self.ctx.as_mut(),
+                iface,
+                &self.cfg,
+                0,
+                vpx_sys::VPX_ENCODER_ABI_VERSION as i32,
+            )
+        };
+        self.check(ret, "initialization")?;
+
+        self.dimensions = Some((width, height));
+        Ok(())
+    }
+
+    /// Map a libvpx status to a `MediaError`
+    fn check(&self, ret: vpx_sys::vpx_codec_err_t, operation: &str) -> Result<(), MediaError> {
+        if ret != vpx_sys::vpx_codec_err_t::VPX_CODEC_OK {
+            return Err(MediaError::CodecError {
+                details: format!("{} encoder {} failed: {:?}", self.name, operation, ret),
+            });
+        }
+        Ok(())
+    }
+
+    fn destroy(&mut self) {
+        if self.dimensions.take().is_some() {
+            unsafe {
+                vpx_sys::vpx_codec_destroy(self.ctx.as_mut());
+            }
+        }
+    }
+}
+
+impl Drop for VpxEncoder {
+    fn drop(&mut self) {
+        self.destroy();
+    }
+}
+
+/// Convert bits per second to the kilobits per second libvpx targets
+fn kbps(bitrate: u32) -> u32 {
+    (bitrate / 1000).max(1)
+}
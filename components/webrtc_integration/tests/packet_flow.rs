@@ -33,18 +33,19 @@ fn test_complete_packet_flow() {
         height: 480,
         format: PixelFormat::YUV420,
         data: vec![42u8; 640 * 480 * 3 / 2],
+        strides: None,
         timestamp: Duration::from_millis(0),
         duration: Some(Duration::from_millis(33)),
         metadata: FrameMetadata::default(),
     };
 
     let encoded = encoder.encode(&frame).expect("Failed to encode frame");
-    assert!(!encoded.is_empty(), "Encoded data should not be empty");
+    assert!(!encoded.data.is_empty(), "Encoded data should not be empty");
 
     // Step 3: Packetize encoded data
     let packetizer = RTPPacketizer::new();
     let timestamp = 3000;
-    let packets = packetizer.packetize(&encoded, timestamp);
+    let packets = packetizer.packetize(&encoded.data, timestamp);
 
     assert!(!packets.is_empty(), "Should produce at least one packet");
     assert_eq!(packets[0].timestamp, timestamp, "Timestamp should match");
@@ -100,6 +101,7 @@ fn test_multiple_frame_flow() {
             height: 240,
             format: PixelFormat::YUV420,
             data: vec![(frame_idx * 10) as u8; 320 * 240 * 3 / 2],
+            strides: None,
             timestamp: Duration::from_millis(frame_idx * 33),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata {
@@ -110,7 +112,7 @@ fn test_multiple_frame_flow() {
 
         let encoded = encoder.encode(&frame).unwrap();
         let timestamp = (frame_idx * 3000) as u32;
-        let packets = packetizer.packetize(&encoded, timestamp);
+        let packets = packetizer.packetize(&encoded.data, timestamp);
 
         // Insert all packets
         for packet in packets {
@@ -214,16 +216,17 @@ fn test_different_codecs() {
             height: 480,
             format: PixelFormat::YUV420,
             data: vec![0u8; 640 * 480 * 3 / 2],
+            strides: None,
             timestamp: Duration::from_millis(0),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
         };
 
         let encoded = encoder.encode(&frame).expect("Should encode frame");
-        assert!(!encoded.is_empty());
+        assert!(!encoded.data.is_empty());
 
         let packetizer = RTPPacketizer::new();
-        let packets = packetizer.packetize(&encoded, 1000);
+        let packets = packetizer.packetize(&encoded.data, 1000);
         assert!(!packets.is_empty());
     }
 }
@@ -257,13 +260,14 @@ fn test_high_throughput() {
             height: 720,
             format: PixelFormat::YUV420,
             data: vec![i as u8; 1280 * 720 * 3 / 2],
+            strides: None,
             timestamp: Duration::from_millis(i * 33), // ~30fps
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
         };
 
         let encoded = encoder.encode(&frame).unwrap();
-        let packets = packetizer.packetize(&encoded, (i * 3000) as u32); // Timestamp increment
+        let packets = packetizer.packetize(&encoded.data, (i * 3000) as u32); // Timestamp increment
 
         // Insert packets
         for packet in packets {
@@ -0,0 +1,120 @@
+//! Unit tests for automatic gain control
+//!
+//! Tests for AutoGainController and the audio processing chain
+
+#[cfg(test)]
+mod tests {
+    use cortenbrowser_shared_types::{AudioBuffer, AudioFormat};
+    use cortenbrowser_webrtc_integration::{
+        AudioProcessingChain, AudioProcessingConfig, AutoGainController,
+    };
+    use std::f32::consts::PI;
+    use std::time::Duration;
+
+    const SAMPLE_RATE: u32 = 48000;
+    const FRAME_SIZE: usize = 480;
+
+    fn sine_frame(amplitude: f32) -> Vec<f32> {
+        (0..FRAME_SIZE)
+            .map(|n| amplitude * (2.0 * PI * 1000.0 * n as f32 / SAMPLE_RATE as f32).sin())
+            .collect()
+    }
+
+    fn rms(frame: &[f32]) -> f32 {
+        (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_agc_boosts_quiet_signal_to_target() {
+        let mut agc = AutoGainController::new(SAMPLE_RATE, 0.1);
+        let quiet = sine_frame(0.02);
+
+        // 10 dB/s release over 3 seconds is enough for the ~11 dB needed
+        let mut output = Vec::new();
+        for _ in 0..300 {
+            output = agc.process(&quiet);
+        }
+
+        assert!(
+            (rms(&output) - 0.1).abs() < 0.01,
+            "RMS was {}",
+            rms(&output)
+        );
+    }
+
+    #[test]
+    fn test_agc_attenuates_loud_signal() {
+        let mut agc = AutoGainController::new(SAMPLE_RATE, 0.1);
+        let loud = sine_frame(0.8);
+
+        let mut output = Vec::new();
+        for _ in 0..100 {
+            output = agc.process(&loud);
+        }
+
+        assert!(agc.current_gain() < 1.0);
+        assert!(
+            (rms(&output) - 0.1).abs() < 0.01,
+            "RMS was {}",
+            rms(&output)
+        );
+    }
+
+    #[test]
+    fn test_agc_gain_changes_are_rate_limited() {
+        let mut agc = AutoGainController::new(SAMPLE_RATE, 0.1).with_rates(60.0, 10.0);
+
+        // One 10ms frame allows at most 0.1 dB of gain increase
+        agc.process(&sine_frame(0.001));
+
+        let gain_db = 20.0 * agc.current_gain().log10();
+        assert!(
+            gain_db > 0.0 && gain_db <= 0.1 + 1e-4,
+            "Gain was {} dB",
+            gain_db
+        );
+    }
+
+    #[test]
+    fn test_agc_hard_limits_at_full_scale() {
+        let mut agc = AutoGainController::new(SAMPLE_RATE, 0.9).with_rates(60.0, 1000.0);
+        let signal = sine_frame(0.5);
+
+        for _ in 0..50 {
+            let output = agc.process(&signal);
+            assert!(output.iter().all(|s| s.abs() <= 1.0));
+        }
+    }
+
+    #[test]
+    fn test_processing_chain_runs_enabled_stages() {
+        let config = AudioProcessingConfig::new()
+            .with_noise_suppression(true)
+            .with_auto_gain_control(true)
+            .with_echo_cancellation(true);
+        let mut chain = AudioProcessingChain::new(SAMPLE_RATE, config);
+
+        let far_end = vec![0.0f32; FRAME_SIZE];
+        let output = chain.process(&sine_frame(0.05), Some(&far_end));
+
+        assert_eq!(output.len(), FRAME_SIZE);
+    }
+
+    #[test]
+    fn test_processing_chain_processes_interleaved_buffer() {
+        let config = AudioProcessingConfig::new().with_auto_gain_control(true);
+        let mut chain = AudioProcessingChain::new(SAMPLE_RATE, config);
+
+        let left = sine_frame(0.02);
+        let samples: Vec<f32> = left.iter().flat_map(|&s| [s, 0.0]).collect();
+        let buffer = AudioBuffer::new(AudioFormat::F32LE, SAMPLE_RATE, 2, samples, Duration::ZERO);
+
+        let output = chain.process_buffer(&buffer, None);
+
+        assert_eq!(output.samples.len(), buffer.samples.len());
+        assert_eq!(output.channels, 2);
+        // Left channel is boosted, silent right channel stays silent
+        assert!(output.samples[2].abs() > buffer.samples[2].abs());
+        assert!(output.samples.iter().skip(1).step_by(2).all(|&s| s == 0.0));
+    }
+}
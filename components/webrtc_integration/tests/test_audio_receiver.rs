@@ -0,0 +1,161 @@
+//! Unit tests for the receive-side audio path
+//!
+//! Tests for AudioReceiver loss detection and concealment
+
+#[cfg(test)]
+mod tests {
+    use cortenbrowser_shared_types::{
+        AudioBuffer, AudioDecoder, AudioFormat, AudioPacket, MediaError,
+    };
+    use cortenbrowser_webrtc_integration::{AudioReceiver, RTPPacket};
+    use std::time::Duration;
+
+    const FRAME_SIZE: usize = 960;
+
+    fn buffer(value: f32, samples: usize) -> AudioBuffer {
+        AudioBuffer {
+            format: AudioFormat::F32LE,
+            sample_rate: 48000,
+            channels: 1,
+            samples: vec![value; samples],
+            timestamp: Duration::ZERO,
+            duration: Duration::from_millis(20),
+            channel_map: None,
+        }
+    }
+
+    /// Decoder whose frames hold the packet's first payload byte, and that
+    /// recovers lost frames from the next packet as the negated byte
+    #[derive(Default)]
+    struct FecDecoder {
+        recovered_from: Vec<u8>,
+        concealed: usize,
+    }
+
+    impl AudioDecoder for FecDecoder {
+        fn decode(&mut self, packet: &AudioPacket) -> Result<AudioBuffer, MediaError> {
+            Ok(buffer(packet.data[0] as f32, FRAME_SIZE))
+        }
+
+        fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
+            Ok(vec![])
+        }
+
+        fn reset(&mut self) {}
+
+        fn conceal_loss(
+            &mut self,
+            next: Option<&AudioPacket>,
+            sample_count: usize,
+        ) -> Result<AudioBuffer, MediaError> {
+            match next {
+                Some(next) => {
+                    self.recovered_from.push(next.data[0]);
+                    Ok(buffer(-(next.data[0] as f32), FRAME_SIZE))
+                }
+                None => {
+                    self.concealed += 1;
+                    Ok(buffer(0.0, sample_count))
+                }
+            }
+        }
+    }
+
+    /// Decoder without packet loss concealment
+    struct PlainDecoder;
+
+    impl AudioDecoder for PlainDecoder {
+        fn decode(&mut self, packet: &AudioPacket) -> Result<AudioBuffer, MediaError> {
+            Ok(buffer(packet.data[0] as f32, FRAME_SIZE))
+        }
+
+        fn flush(&mut self) -> Result<Vec<AudioBuffer>, MediaError> {
+            Ok(vec![])
+        }
+
+        fn reset(&mut self) {}
+    }
+
+    fn packet(seq: u16) -> RTPPacket {
+        RTPPacket {
+            payload: vec![seq as u8],
+            sequence_number: seq,
+            timestamp: seq as u32 * FRAME_SIZE as u32,
+            ssrc: 12345,
+            ..Default::default()
+        }
+    }
+
+    fn pull_all<D: AudioDecoder>(receiver: &mut AudioReceiver<D>) -> Vec<AudioBuffer> {
+        let mut output = Vec::new();
+        while let Some(buffer) = receiver.pull().expect("Pull should succeed") {
+            output.push(buffer);
+        }
+        output
+    }
+
+    #[test]
+    fn test_audio_receiver_recovers_every_tenth_packet_lost() {
+        let mut receiver = AudioReceiver::new(FecDecoder::default(), 48000, 1, 100);
+
+        // Drop every 10th packet
+        for seq in (0..50u16).filter(|seq| seq % 10 != 5) {
+            receiver.insert(packet(seq)).unwrap();
+        }
+        let output = pull_all(&mut receiver);
+
+        // Output length is preserved
+        assert_eq!(output.len(), 50);
+        let total: usize = output.iter().map(|b| b.samples.len()).sum();
+        assert_eq!(total, 50 * FRAME_SIZE);
+
+        // Each lost packet was recovered from the packet after it
+        assert_eq!(receiver.decoder().recovered_from, vec![6, 16, 26, 36, 46]);
+        assert_eq!(output[15].samples[0], -16.0);
+        assert_eq!(output[16].samples[0], 16.0);
+        assert_eq!(receiver.buffered(), 0);
+    }
+
+    #[test]
+    fn test_audio_receiver_conceals_consecutive_losses() {
+        let mut receiver = AudioReceiver::new(FecDecoder::default(), 48000, 1, 100);
+
+        for seq in [0u16, 1, 4, 5] {
+            receiver.insert(packet(seq)).unwrap();
+        }
+        let output = pull_all(&mut receiver);
+
+        // Packet 2 is concealed without FEC, packet 3 is recovered from 4
+        assert_eq!(output.len(), 6);
+        assert_eq!(receiver.decoder().concealed, 1);
+        assert_eq!(receiver.decoder().recovered_from, vec![4]);
+        assert!(output.iter().all(|b| b.samples.len() == FRAME_SIZE));
+    }
+
+    #[test]
+    fn test_audio_receiver_waits_while_buffer_empty() {
+        let mut receiver = AudioReceiver::new(FecDecoder::default(), 48000, 1, 100);
+
+        receiver.insert(packet(0)).unwrap();
+        assert!(receiver.pull().unwrap().is_some());
+
+        // Packet 1 may still arrive
+        assert!(receiver.pull().unwrap().is_none());
+        receiver.insert(packet(1)).unwrap();
+        assert_eq!(receiver.pull().unwrap().unwrap().samples[0], 1.0);
+    }
+
+    #[test]
+    fn test_audio_receiver_fills_loss_with_silence_without_concealment() {
+        let mut receiver = AudioReceiver::new(PlainDecoder, 48000, 1, 100);
+
+        for seq in (0..20u16).filter(|seq| seq % 10 != 9) {
+            receiver.insert(packet(seq)).unwrap();
+        }
+        let output = pull_all(&mut receiver);
+
+        assert_eq!(output.len(), 19);
+        assert_eq!(output[9].samples, vec![0.0; FRAME_SIZE]);
+        assert_eq!(output[10].samples[0], 10.0);
+    }
+}
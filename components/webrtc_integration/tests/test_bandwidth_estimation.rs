@@ -0,0 +1,202 @@
+//! Unit tests for bandwidth estimation
+//!
+//! Tests for BandwidthEstimator, REMB handling and encoder rate adaptation
+
+#[cfg(test)]
+mod tests {
+    use cortenbrowser_shared_types::{FrameMetadata, PixelFormat, VideoCodec, VideoFrame};
+    use cortenbrowser_webrtc_integration::{
+        BandwidthEstimator, BandwidthUsage, EncoderConfig, RTCPHandler, RembPacket,
+        WebRTCEncoder,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const FRAME_INTERVAL_MS: u64 = 33;
+    const FRAME_BYTES: usize = 4000;
+
+    /// Feed `count` packet groups whose one-way delay grows by `delay_step_ms` per group
+    fn feed(estimator: &mut BandwidthEstimator, start: u64, count: u64, base_delay: u64, delay_step_ms: u64) {
+        for i in 0..count {
+            let send = (start + i) * FRAME_INTERVAL_MS;
+            let arrival = send + base_delay + i * delay_step_ms;
+            estimator.on_packet_arrival(send, arrival, FRAME_BYTES);
+        }
+    }
+
+    fn frame() -> VideoFrame {
+        VideoFrame {
+            width: 640,
+            height: 480,
+            format: PixelFormat::YUV420,
+            data: vec![0u8; 640 * 480 * 3 / 2],
+            strides: None,
+            timestamp: Duration::from_millis(0),
+            duration: Some(Duration::from_millis(33)),
+            metadata: FrameMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_stable_delay_increases_bitrate() {
+        let mut estimator = BandwidthEstimator::new(500_000, 100_000, 5_000_000);
+        feed(&mut estimator, 0, 90, 40, 0);
+
+        assert_eq!(estimator.usage(), BandwidthUsage::Normal);
+        assert!(
+            estimator.current_target_bitrate() > 500_000,
+            "target was {}",
+            estimator.current_target_bitrate()
+        );
+    }
+
+    #[test]
+    fn test_growing_delay_reduces_bitrate() {
+        let mut estimator = BandwidthEstimator::new(2_000_000, 100_000, 5_000_000);
+        feed(&mut estimator, 0, 30, 40, 0);
+        let before = estimator.current_target_bitrate();
+
+        // Queue builds up: each frame arrives 20ms later than the last
+        feed(&mut estimator, 30, 30, 40, 20);
+
+        assert_eq!(estimator.usage(), BandwidthUsage::Overusing);
+        let after = estimator.current_target_bitrate();
+        assert!(after < before, "target {} should be below {}", after, before);
+
+        // Incoming rate is ~1 Mbps, so the target falls below it
+        assert!(after < 1_000_000, "target was {}", after);
+    }
+
+    #[test]
+    fn test_draining_queue_signals_underuse() {
+        let mut estimator = BandwidthEstimator::new(1_000_000, 100_000, 5_000_000);
+        feed(&mut estimator, 0, 30, 40, 20);
+
+        // Queue drains: arrivals get closer together than sends
+        for i in 0..30u64 {
+            let send = (30 + i) * FRAME_INTERVAL_MS;
+            let arrival = send + 620 - i * 20;
+            estimator.on_packet_arrival(send, arrival, FRAME_BYTES);
+        }
+
+        assert_eq!(estimator.usage(), BandwidthUsage::Underusing);
+        assert!(estimator.delay_trend() < 0.0);
+    }
+
+    #[test]
+    fn test_bitrate_respects_bounds() {
+        let mut estimator = BandwidthEstimator::new(500_000, 400_000, 600_000);
+        feed(&mut estimator, 0, 300, 40, 0);
+        assert_eq!(estimator.current_target_bitrate(), 600_000);
+
+        feed(&mut estimator, 300, 60, 40, 50);
+        assert_eq!(estimator.current_target_bitrate(), 400_000);
+    }
+
+    #[test]
+    fn test_custom_rate_steps() {
+        let mut slow = BandwidthEstimator::new(500_000, 100_000, 5_000_000);
+        let mut fast = BandwidthEstimator::new(500_000, 100_000, 5_000_000).with_rate_steps(1.5, 0.5);
+        feed(&mut slow, 0, 90, 40, 0);
+        feed(&mut fast, 0, 90, 40, 0);
+
+        assert!(fast.current_target_bitrate() > slow.current_target_bitrate());
+    }
+
+    #[test]
+    fn test_remote_estimate_caps_target() {
+        let mut estimator = BandwidthEstimator::new(1_000_000, 100_000, 5_000_000);
+        estimator.update_remote_estimate(300_000);
+        assert_eq!(estimator.current_target_bitrate(), 300_000);
+
+        // Delay-based increase does not exceed the remote estimate
+        feed(&mut estimator, 0, 300, 40, 0);
+        assert_eq!(estimator.current_target_bitrate(), 300_000);
+
+        // A higher remote estimate lets the target grow again
+        estimator.update_remote_estimate(2_000_000);
+        feed(&mut estimator, 300, 90, 40, 0);
+        assert!(estimator.current_target_bitrate() > 300_000);
+    }
+
+    #[test]
+    fn test_remb_roundtrip() {
+        let remb = RembPacket {
+            sender_ssrc: 0xDEADBEEF,
+            bitrate_bps: 123_456,
+            ssrcs: vec![1, 2, 3],
+        };
+        let bytes = remb.to_bytes();
+        assert_eq!(bytes.len(), 20 + 3 * 4);
+        assert_eq!(bytes[1], 206);
+        assert_eq!(&bytes[12..16], b"REMB");
+
+        assert_eq!(RembPacket::from_bytes(&bytes).unwrap(), remb);
+    }
+
+    #[test]
+    fn test_remb_rejects_malformed() {
+        assert!(RembPacket::from_bytes(&[0x8F, 206, 0, 4]).is_err());
+
+        let mut bytes = RembPacket {
+            sender_ssrc: 1,
+            bitrate_bps: 100_000,
+            ssrcs: vec![7, 8],
+        }
+        .to_bytes();
+        bytes.truncate(24);
+        assert!(RembPacket::from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_rtcp_handler_feeds_remb_to_estimator() {
+        let handler = RTCPHandler::new(12345);
+        let mut estimator = BandwidthEstimator::new(2_000_000, 100_000, 5_000_000);
+
+        let bytes = RembPacket {
+            sender_ssrc: 99,
+            bitrate_bps: 800_000,
+            ssrcs: vec![12345],
+        }
+        .to_bytes();
+
+        let remb = handler.handle_remb(&bytes, &mut estimator).unwrap();
+        assert_eq!(remb.sender_ssrc, 99);
+        assert_eq!(estimator.current_target_bitrate(), 800_000);
+
+        // Malformed packets leave the estimator untouched
+        assert!(handler.handle_remb(&[0u8; 8], &mut estimator).is_err());
+        assert_eq!(estimator.current_target_bitrate(), 800_000);
+    }
+
+    #[test]
+    fn test_encoder_output_follows_congestion() {
+        let estimator = Arc::new(Mutex::new(BandwidthEstimator::new(
+            2_000_000, 100_000, 5_000_000,
+        )));
+        let encoder = WebRTCEncoder::new(
+            VideoCodec::VP8,
+            EncoderConfig {
+                bitrate: 2_000_000,
+                framerate: 30,
+                keyframe_interval: 1000,
+            },
+        )
+        .unwrap()
+        .with_bandwidth_estimator(Arc::clone(&estimator));
+
+        // Skip the first (key) frame
+        encoder.encode(&frame()).unwrap();
+        let before = encoder.encode(&frame()).unwrap().data.len();
+
+        {
+            let mut estimator = estimator.lock().unwrap();
+            feed(&mut estimator, 0, 30, 40, 0);
+            feed(&mut estimator, 30, 30, 40, 20);
+        }
+
+        assert!(encoder.target_bitrate() < 2_000_000);
+        let after = encoder.encode(&frame()).unwrap().data.len();
+        assert!(after < before, "encoded size {} should be below {}", after, before);
+    }
+}
@@ -0,0 +1,143 @@
+//! Unit tests for echo cancellation
+//!
+//! Tests for EchoCanceller convergence on synthetic echo
+
+#[cfg(test)]
+mod tests {
+    use cortenbrowser_shared_types::{AudioBuffer, AudioFormat};
+    use cortenbrowser_webrtc_integration::{
+        AudioProcessingChain, AudioProcessingConfig, EchoCanceller,
+    };
+    use std::time::Duration;
+
+    const SAMPLE_RATE: u32 = 16000;
+    const FRAME_SIZE: usize = 160;
+
+    /// Echo path delay in samples
+    const ECHO_DELAY: usize = 40;
+
+    /// Echo path attenuation
+    const ECHO_GAIN: f32 = 0.6;
+
+    /// Deterministic white noise in [-0.5, 0.5), standing in for far-end speech
+    fn noise(len: usize, seed: u32) -> Vec<f32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+            })
+            .collect()
+    }
+
+    /// The far end as the microphone picks it up: delayed and attenuated
+    fn echo_of(far_end: &[f32]) -> Vec<f32> {
+        (0..far_end.len())
+            .map(|n| {
+                n.checked_sub(ECHO_DELAY)
+                    .map_or(0.0, |m| ECHO_GAIN * far_end[m])
+            })
+            .collect()
+    }
+
+    fn energy(samples: &[f32]) -> f32 {
+        samples.iter().map(|s| s * s).sum()
+    }
+
+    /// Run `far_end` and `near_end` through `process` frame by frame
+    fn process_frames(
+        far_end: &[f32],
+        near_end: &[f32],
+        mut process: impl FnMut(&[f32], &[f32]) -> Vec<f32>,
+    ) -> Vec<f32> {
+        far_end
+            .chunks(FRAME_SIZE)
+            .zip(near_end.chunks(FRAME_SIZE))
+            .flat_map(|(far, near)| process(far, near))
+            .collect()
+    }
+
+    #[test]
+    fn test_echo_canceller_converges_on_delayed_echo() {
+        let mut canceller = EchoCanceller::new(SAMPLE_RATE, 128);
+        // One second of far-end audio
+        let far_end = noise(SAMPLE_RATE as usize, 1);
+        let near_end = echo_of(&far_end);
+
+        let output = process_frames(&far_end, &near_end, |far, near| {
+            canceller.process(far, near)
+        });
+        assert_eq!(output.len(), near_end.len());
+
+        // Over the last 100 ms the echo is attenuated by more than 30 dB
+        let tail = near_end.len() - 1600;
+        let erle = 10.0 * (energy(&near_end[tail..]) / energy(&output[tail..])).log10();
+        assert!(erle > 30.0, "ERLE was {} dB", erle);
+    }
+
+    #[test]
+    fn test_echo_canceller_reset_forgets_echo_path() {
+        let mut canceller = EchoCanceller::new(SAMPLE_RATE, 128);
+        let far_end = noise(SAMPLE_RATE as usize, 1);
+        let near_end = echo_of(&far_end);
+        process_frames(&far_end, &near_end, |far, near| {
+            canceller.process(far, near)
+        });
+
+        canceller.reset();
+
+        // With zero taps the first sample has no echo estimate to remove
+        let mut impulse = vec![0.0f32; FRAME_SIZE];
+        impulse[0] = 1.0;
+        let output = canceller.process(&impulse, &[0.25f32; FRAME_SIZE]);
+        assert_eq!(output[0], 0.25);
+    }
+
+    #[test]
+    fn test_chain_cancels_echo_with_far_end_reference() {
+        let mut config = AudioProcessingConfig::new().with_echo_cancellation(true);
+        config.echo_filter_length = 128;
+        let mut chain = AudioProcessingChain::new(SAMPLE_RATE, config);
+        let far_end = noise(SAMPLE_RATE as usize, 1);
+        let near_end = echo_of(&far_end);
+
+        let output = process_frames(&far_end, &near_end, |far, near| {
+            chain.process(near, Some(far))
+        });
+
+        let tail = near_end.len() - 1600;
+        assert!(energy(&output[tail..]) < 0.01 * energy(&near_end[tail..]));
+    }
+
+    #[test]
+    fn test_chain_cancels_echo_in_buffers_with_far_end_reference() {
+        let mut config = AudioProcessingConfig::new().with_echo_cancellation(true);
+        config.echo_filter_length = 128;
+        let mut chain = AudioProcessingChain::new(SAMPLE_RATE, config);
+        let far_end = noise(SAMPLE_RATE as usize, 2);
+        let near_end = echo_of(&far_end);
+        let buffer = |channels: u8, samples: Vec<f32>| {
+            AudioBuffer::new(
+                AudioFormat::F32LE,
+                SAMPLE_RATE,
+                channels,
+                samples,
+                Duration::ZERO,
+            )
+        };
+
+        // Stereo microphone buffers, with the echo on the left channel only,
+        // against a mono far end
+        let output: Vec<f32> = process_frames(&far_end, &near_end, |far, near| {
+            let stereo = near.iter().flat_map(|&s| [s, 0.0]).collect();
+            chain
+                .process_buffer(&buffer(2, stereo), Some(&buffer(1, far.to_vec())))
+                .samples
+        });
+
+        let left: Vec<f32> = output.iter().step_by(2).copied().collect();
+        let tail = near_end.len() - 1600;
+        assert!(energy(&left[tail..]) < 0.01 * energy(&near_end[tail..]));
+        assert!(output.iter().skip(1).step_by(2).all(|&s| s == 0.0));
+    }
+}
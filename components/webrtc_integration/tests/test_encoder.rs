@@ -64,6 +64,7 @@ mod tests {
             height: 480,
             format: PixelFormat::YUV420,
             data: vec![0u8; 640 * 480 * 3 / 2], // YUV420 size
+            strides: None,
             timestamp: Duration::from_millis(0),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -73,7 +74,7 @@ mod tests {
         assert!(result.is_ok(), "Should encode frame successfully");
 
         let encoded = result.unwrap();
-        assert!(!encoded.is_empty(), "Encoded data should not be empty");
+        assert!(!encoded.data.is_empty(), "Encoded data should not be empty");
     }
 
     #[test]
@@ -94,6 +95,7 @@ mod tests {
                 height: 240,
                 format: PixelFormat::YUV420,
                 data: vec![i as u8; 320 * 240 * 3 / 2],
+                strides: None,
                 timestamp: Duration::from_millis(i * 33),
                 duration: Some(Duration::from_millis(33)),
                 metadata: FrameMetadata::default(),
@@ -133,6 +135,7 @@ mod tests {
                 height: 240,
                 format: PixelFormat::YUV420,
                 data: vec![0u8; 320 * 240 * 3 / 2],
+                strides: None,
                 timestamp: Duration::from_millis(i * 33),
                 duration: Some(Duration::from_millis(33)),
                 metadata,
@@ -164,6 +167,7 @@ mod tests {
             height: 480,
             format: PixelFormat::YUV420,
             data: vec![0u8; 100], // Too small for 640x480 YUV420
+            strides: None,
             timestamp: Duration::from_millis(0),
             duration: Some(Duration::from_millis(33)),
             metadata: FrameMetadata::default(),
@@ -202,4 +206,153 @@ mod tests {
         let encoder = WebRTCEncoder::new(codec, config);
         assert!(encoder.is_err(), "Should fail with zero framerate");
     }
+
+    /// A frame of gradient plus noise, which a codec cannot predict from
+    /// earlier frames
+    fn noise_frame(width: u32, height: u32, timestamp: Duration) -> VideoFrame {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        VideoFrame {
+            width,
+            height,
+            format: PixelFormat::YUV420,
+            data: (0..width * height * 3 / 2)
+                .map(|i| ((i % width) / 2) as u8 + rng.gen_range(0..32))
+                .collect(),
+            strides: None,
+            timestamp,
+            duration: Some(Duration::from_millis(33)),
+            metadata: FrameMetadata::default(),
+        }
+    }
+
+    #[cfg(feature = "h264")]
+    #[test]
+    fn test_encoder_h264_round_trip() {
+        use openh264::decoder::Decoder;
+        use openh264::formats::YUVSource;
+
+        let encoder = WebRTCEncoder::new(
+            VideoCodec::H264 {
+                profile: H264Profile::Main,
+                level: H264Level::Level4_0,
+                hardware_accel: false,
+            },
+            EncoderConfig {
+                bitrate: 1_000_000,
+                framerate: 30,
+                keyframe_interval: 30,
+            },
+        )
+        .unwrap();
+        let mut decoder = Decoder::new().unwrap();
+
+        for i in 0..3 {
+            let encoded = encoder
+                .encode(&noise_frame(320, 240, Duration::from_millis(i * 33)))
+                .unwrap();
+            assert_eq!(encoded.is_keyframe, i == 0);
+            assert_eq!(encoded.timestamp, Duration::from_millis(i * 33));
+
+            let decoded = decoder
+                .decode(&encoded.data)
+                .unwrap()
+                .expect("Every encoded frame should decode to a picture");
+            assert_eq!(decoded.dimensions(), (320, 240));
+        }
+    }
+
+    #[cfg(feature = "h264")]
+    #[test]
+    fn test_encoder_h264_follows_target_bitrate() {
+        use cortenbrowser_webrtc_integration::BandwidthEstimator;
+        use std::sync::{Arc, Mutex};
+
+        let estimator = Arc::new(Mutex::new(BandwidthEstimator::new(
+            4_000_000, 100_000, 4_000_000,
+        )));
+        let encoder = WebRTCEncoder::new(
+            VideoCodec::H264 {
+                profile: H264Profile::Main,
+                level: H264Level::Level4_0,
+                hardware_accel: false,
+            },
+            EncoderConfig {
+                bitrate: 4_000_000,
+                framerate: 30,
+                keyframe_interval: 1000,
+            },
+        )
+        .unwrap()
+        .with_bandwidth_estimator(Arc::clone(&estimator));
+
+        let mut encode = |i: u64| {
+            encoder
+                .encode(&noise_frame(320, 240, Duration::from_millis(i * 33)))
+                .unwrap()
+                .data
+                .len()
+        };
+
+        // Skip the keyframe
+        let before: usize = (1..10).map(&mut encode).sum();
+
+        // Give rate control a couple of frames to settle
+        estimator.lock().unwrap().update_remote_estimate(200_000);
+        encode(10);
+        encode(11);
+        let after: usize = (12..21).map(&mut encode).sum();
+
+        assert!(after * 4 < before, "encoded size {} should be well below {}", after, before);
+    }
+
+    #[cfg(feature = "vpx")]
+    #[test]
+    fn test_encoder_vp8_round_trip() {
+        use std::ptr;
+
+        let encoder = WebRTCEncoder::new(
+            VideoCodec::VP8,
+            EncoderConfig {
+                bitrate: 1_000_000,
+                framerate: 30,
+                keyframe_interval: 30,
+            },
+        )
+        .unwrap();
+
+        let encoded = encoder
+            .encode(&noise_frame(320, 240, Duration::ZERO))
+            .unwrap();
+        assert!(encoded.is_keyframe);
+
+        unsafe {
+            let mut ctx = std::mem::zeroed::<vpx_sys::vpx_codec_ctx_t>();
+            let ret = vpx_sys::vpx_codec_dec_init_ver(
+                &mut ctx,
+                vpx_sys::vpx_codec_vp8_dx(),
+                ptr::null(),
+                0,
+                vpx_sys::VPX_DECODER_ABI_VERSION as i32,
+            );
+            assert_eq!(ret, vpx_sys::vpx_codec_err_t::VPX_CODEC_OK);
+
+            let ret = vpx_sys::vpx_codec_decode(
+                &mut ctx,
+                encoded.data.as_ptr(),
+                encoded.data.len() as u32,
+                ptr::null_mut(),
+                0,
+            );
+            assert_eq!(ret, vpx_sys::vpx_codec_err_t::VPX_CODEC_OK);
+
+            let mut iter = ptr::null();
+            let image = vpx_sys::vpx_codec_get_frame(&mut ctx, &mut iter);
+            assert!(!image.is_null(), "The keyframe should decode to a picture");
+            assert_eq!(((*image).d_w, (*image).d_h), (320, 240));
+
+            vpx_sys::vpx_codec_destroy(&mut ctx);
+        }
+    }
 }
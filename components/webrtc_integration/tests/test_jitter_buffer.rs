@@ -4,7 +4,10 @@
 
 #[cfg(test)]
 mod tests {
-    use cortenbrowser_webrtc_integration::{JitterBuffer, RTPPacket, MediaError};
+    use cortenbrowser_webrtc_integration::{
+        JitterBuffer, MediaError, OverflowPolicy, RTPPacket, MAX_MISSING_SEQUENCES,
+    };
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_jitter_buffer_creation() {
@@ -26,6 +29,7 @@ mod tests {
             sequence_number: 0,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         let packet2 = RTPPacket {
@@ -33,6 +37,7 @@ mod tests {
             sequence_number: 1,
             timestamp: 1100,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet1).expect("Insert should succeed");
@@ -62,6 +67,7 @@ mod tests {
             sequence_number: 2,
             timestamp: 1200,
             ssrc: 12345,
+            ..Default::default()
         };
 
         let packet1 = RTPPacket {
@@ -69,6 +75,7 @@ mod tests {
             sequence_number: 1,
             timestamp: 1100,
         ssrc: 12345,
+        ..Default::default()
         };
 
         let packet0 = RTPPacket {
@@ -76,6 +83,7 @@ mod tests {
             sequence_number: 0,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet2).unwrap();
@@ -100,6 +108,7 @@ mod tests {
             sequence_number: 65535, // u16::MAX
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         let packet_zero = RTPPacket {
@@ -107,6 +116,7 @@ mod tests {
             sequence_number: 0, // Wraps around
             timestamp: 1100,
             ssrc: 12345,
+            ..Default::default()
         };
 
         let packet_one = RTPPacket {
@@ -114,6 +124,7 @@ mod tests {
             sequence_number: 1,
             timestamp: 1200,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet_zero).unwrap();
@@ -136,6 +147,7 @@ mod tests {
             sequence_number: 5,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         let packet1_dup = RTPPacket {
@@ -143,6 +155,7 @@ mod tests {
             sequence_number: 5, // Same sequence
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet1.clone()).unwrap();
@@ -167,6 +180,7 @@ mod tests {
                 sequence_number: i as u16,
                 timestamp: 1000 + i as u32 * 100,
                 ssrc: 12345,
+                ..Default::default()
             };
 
             let result = buffer.insert(packet);
@@ -199,6 +213,7 @@ mod tests {
             sequence_number: 0,
             timestamp: 1000,
             ssrc: 12345,
+            ..Default::default()
         };
 
         let packet2 = RTPPacket {
@@ -206,6 +221,7 @@ mod tests {
             sequence_number: 2, // Gap: missing seq 1
             timestamp: 1200,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet0).unwrap();
@@ -224,6 +240,7 @@ mod tests {
             sequence_number: 1,
             timestamp: 1100,
             ssrc: 12345,
+            ..Default::default()
         };
 
         buffer.insert(packet1).unwrap();
@@ -232,4 +249,264 @@ mod tests {
         assert_eq!(buffer.get_next().unwrap().sequence_number, 1);
         assert_eq!(buffer.get_next().unwrap().sequence_number, 2);
     }
+
+    fn audio_packet(sequence_number: u16) -> RTPPacket {
+        RTPPacket {
+            payload: vec![sequence_number as u8],
+            sequence_number,
+            // 20 ms frames at 48 kHz
+            timestamp: 960 * sequence_number as u32,
+            ssrc: 12345,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_jitter_buffer_releases_packets_at_playout_time() {
+        let mut buffer =
+            JitterBuffer::with_target_delay(10, Duration::from_millis(60)).with_clock_rate(48000);
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+
+        // Packets sent every 20 ms arrive with varying delay and out of order
+        buffer.insert_at(audio_packet(0), start).unwrap();
+        buffer.insert_at(audio_packet(2), start + ms(45)).unwrap();
+        buffer.insert_at(audio_packet(1), start + ms(50)).unwrap();
+        buffer.insert_at(audio_packet(3), start + ms(61)).unwrap();
+
+        // Nothing plays before the first packet's playout time
+        assert_eq!(buffer.pop_ready(start + ms(59)), None);
+
+        // Each packet plays 60 ms after its expected arrival, in order
+        let mut released = Vec::new();
+        for t in (60..=120).step_by(20) {
+            let packet = buffer
+                .pop_ready(start + ms(t))
+                .expect("packet should be due");
+            released.push(packet.sequence_number);
+            assert_eq!(
+                buffer.pop_ready(start + ms(t)),
+                None,
+                "only one packet due at {} ms",
+                t
+            );
+        }
+        assert_eq!(released, vec![0, 1, 2, 3]);
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_jitter_buffer_skips_packet_missing_at_playout_time() {
+        let mut buffer =
+            JitterBuffer::with_target_delay(10, Duration::from_millis(40)).with_clock_rate(48000);
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+
+        buffer.insert_at(audio_packet(0), start).unwrap();
+        buffer.insert_at(audio_packet(2), start + ms(40)).unwrap();
+
+        assert_eq!(buffer.pop_ready(start + ms(40)).unwrap().sequence_number, 0);
+        // Packet 1 is waited for until packet 2 is due
+        assert_eq!(buffer.pop_ready(start + ms(60)), None);
+        assert_eq!(buffer.pop_ready(start + ms(80)).unwrap().sequence_number, 2);
+
+        // Packet 1 arriving now is too late to be played
+        buffer.insert_at(audio_packet(1), start + ms(85)).unwrap();
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_jitter_buffer_measures_interarrival_jitter() {
+        let start = Instant::now();
+        let ms = Duration::from_millis;
+
+        // Evenly spaced arrivals have no jitter
+        let mut steady = JitterBuffer::new(100).with_clock_rate(48000);
+        for seq in 0..50 {
+            steady
+                .insert_at(audio_packet(seq), start + ms(20 * seq as u64))
+                .unwrap();
+        }
+        assert!(steady.jitter() < Duration::from_micros(1));
+
+        // Arrivals alternating 5 ms early and late converge to about 10 ms
+        let mut jittery = JitterBuffer::new(100).with_clock_rate(48000);
+        for seq in 0..100 {
+            let arrival = 20 * seq as u64 + if seq % 2 == 0 { 0 } else { 10 };
+            jittery
+                .insert_at(audio_packet(seq), start + ms(arrival))
+                .unwrap();
+        }
+        let jitter = jittery.jitter();
+        assert!(jitter > ms(9) && jitter < ms(11), "jitter was {:?}", jitter);
+    }
+
+    #[test]
+    fn test_jitter_buffer_missing_sequences() {
+        let mut buffer = JitterBuffer::new(10);
+        assert!(buffer.missing_sequences().is_empty());
+        assert_eq!(buffer.max_received_seq(), None);
+
+        for seq in [0, 1, 4] {
+            buffer.insert(audio_packet(seq)).unwrap();
+        }
+        assert_eq!(buffer.max_received_seq(), Some(4));
+        assert_eq!(buffer.missing_sequences(), vec![2, 3]);
+
+        // A retransmitted packet is no longer missing
+        buffer.insert(audio_packet(3)).unwrap();
+        assert_eq!(buffer.missing_sequences(), vec![2]);
+
+        // A packet given up on at playout time is no longer requested
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 0);
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 1);
+        assert_eq!(buffer.skip_missing(), Some(2));
+        assert!(buffer.missing_sequences().is_empty());
+    }
+
+    #[test]
+    fn test_jitter_buffer_missing_sequences_wraparound() {
+        let mut buffer = JitterBuffer::new(10);
+
+        for seq in [65534, 65535, 2] {
+            buffer.insert(audio_packet(seq)).unwrap();
+        }
+        assert_eq!(buffer.max_received_seq(), Some(2));
+        assert_eq!(buffer.missing_sequences(), vec![0, 1]);
+    }
+
+    #[test]
+    fn test_jitter_buffer_missing_sequences_capped() {
+        let mut buffer = JitterBuffer::new(10);
+
+        buffer.insert(audio_packet(0)).unwrap();
+        buffer.insert(audio_packet(1000)).unwrap();
+
+        // Only the most recent gap is reported
+        let missing = buffer.missing_sequences();
+        assert_eq!(missing.len(), MAX_MISSING_SEQUENCES);
+        assert_eq!(missing[0], 1000 - MAX_MISSING_SEQUENCES as u16);
+        assert_eq!(*missing.last().unwrap(), 999);
+    }
+
+    #[test]
+    fn test_jitter_buffer_reset() {
+        let mut buffer = JitterBuffer::new(10);
+
+        for seq in [100, 103] {
+            buffer.insert(audio_packet(seq)).unwrap();
+        }
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 100);
+
+        buffer.reset();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.max_received_seq(), None);
+        assert!(buffer.missing_sequences().is_empty());
+
+        // A restarted stream may begin at a lower sequence number
+        buffer.insert(audio_packet(5)).unwrap();
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 5);
+    }
+
+    fn full_buffer(policy: OverflowPolicy, sequences: [u16; 3]) -> JitterBuffer {
+        let mut buffer = JitterBuffer::new(3).with_overflow_policy(policy);
+        for seq in sequences {
+            buffer.insert(audio_packet(seq)).unwrap();
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_jitter_buffer_overflow_error() {
+        let mut buffer = full_buffer(OverflowPolicy::Error, [0, 1, 2]);
+        assert_eq!(
+            JitterBuffer::new(3).overflow_policy(),
+            OverflowPolicy::Error
+        );
+
+        assert!(matches!(
+            buffer.insert(audio_packet(3)),
+            Err(MediaError::OutOfMemory)
+        ));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.dropped_count(), 0);
+    }
+
+    #[test]
+    fn test_jitter_buffer_overflow_drop_oldest() {
+        let mut buffer = full_buffer(OverflowPolicy::DropOldest, [0, 1, 2]);
+
+        buffer.insert(audio_packet(3)).unwrap();
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.dropped_count(), 1);
+        for seq in [1, 2, 3] {
+            assert_eq!(buffer.get_next().unwrap().sequence_number, seq);
+        }
+
+        // Across wraparound, the oldest has the highest sequence number
+        let mut buffer = full_buffer(OverflowPolicy::DropOldest, [65534, 65535, 0]);
+        buffer.insert(audio_packet(1)).unwrap();
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 65535);
+
+        // A packet older than all buffered ones is dropped itself
+        let mut buffer = full_buffer(OverflowPolicy::DropOldest, [10, 12, 13]);
+        buffer.insert(audio_packet(9)).unwrap();
+        assert_eq!(buffer.dropped_count(), 1);
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 10);
+    }
+
+    #[test]
+    fn test_jitter_buffer_overflow_drop_lowest_seq() {
+        let mut buffer = full_buffer(OverflowPolicy::DropLowestSeq, [0, 1, 2]);
+
+        buffer.insert(audio_packet(3)).unwrap();
+        assert_eq!(buffer.dropped_count(), 1);
+        for seq in [1, 2, 3] {
+            assert_eq!(buffer.get_next().unwrap().sequence_number, seq);
+        }
+
+        // Wraparound is ignored, so the newest packet goes
+        let mut buffer = full_buffer(OverflowPolicy::DropLowestSeq, [65534, 65535, 0]);
+        buffer.insert(audio_packet(1)).unwrap();
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 65534);
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 65535);
+        assert_eq!(buffer.get_next(), None);
+        assert_eq!(buffer.skip_missing(), Some(0));
+        assert_eq!(buffer.get_next().unwrap().sequence_number, 1);
+
+        buffer.reset();
+        assert_eq!(buffer.dropped_count(), 0);
+        assert_eq!(buffer.overflow_policy(), OverflowPolicy::DropLowestSeq);
+    }
+
+    #[test]
+    fn test_jitter_buffer_reception_stats() {
+        let mut buffer = JitterBuffer::new(10);
+        for seq in [10, 11, 13, 14] {
+            buffer.insert(audio_packet(seq)).unwrap();
+        }
+
+        let stats = buffer.reception_stats();
+        assert_eq!(stats.packets_received, 4);
+        assert_eq!(stats.packets_expected, 5);
+        assert_eq!(stats.extended_highest_seq, 14);
+        assert_eq!(stats.cumulative_lost(), 1);
+    }
+
+    #[test]
+    fn test_jitter_buffer_reception_stats_wraparound() {
+        let mut buffer = JitterBuffer::new(10);
+        for seq in [65534, 65535, 1] {
+            buffer.insert(audio_packet(seq)).unwrap();
+        }
+
+        // One sequence number cycle, with 0 missing
+        let stats = buffer.reception_stats();
+        assert_eq!(stats.extended_highest_seq, 0x1_0001);
+        assert_eq!(stats.packets_expected, 4);
+        assert_eq!(stats.cumulative_lost(), 1);
+
+        buffer.reset();
+        assert_eq!(buffer.reception_stats().packets_received, 0);
+    }
 }
@@ -0,0 +1,207 @@
+//! Unit tests for audio level metering
+//!
+//! Tests for AudioLevelMeter levels and voice activity detection
+
+#[cfg(test)]
+mod tests {
+    use cortenbrowser_webrtc_integration::{AudioLevel, AudioLevelMeter};
+    use std::f32::consts::PI;
+    use std::time::Duration;
+
+    const SAMPLE_RATE: u32 = 48000;
+    const FRAME_SIZE: usize = 480;
+
+    /// Deterministic white noise in [-amplitude, amplitude]
+    struct WhiteNoise(u32);
+
+    impl WhiteNoise {
+        fn next(&mut self, amplitude: f32) -> f32 {
+            self.0 = self.0.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((self.0 >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0) * amplitude
+        }
+    }
+
+    /// Low-pass noise, whose zero-crossing rate is in the range of voice
+    struct LowPassNoise {
+        white: WhiteNoise,
+        last: f32,
+    }
+
+    impl LowPassNoise {
+        fn frame(&mut self) -> Vec<f32> {
+            (0..FRAME_SIZE)
+                .map(|_| {
+                    self.last = 0.97 * self.last + self.white.next(0.1);
+                    self.last
+                })
+                .collect()
+        }
+    }
+
+    fn sine_frame(index: usize, amplitude: f32) -> Vec<f32> {
+        (0..FRAME_SIZE)
+            .map(|n| {
+                let t = (index * FRAME_SIZE + n) as f32 / SAMPLE_RATE as f32;
+                amplitude * (2.0 * PI * 1000.0 * t).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_silence_is_minimum_level() {
+        let mut meter = AudioLevelMeter::new(SAMPLE_RATE, 1);
+
+        let mut level = AudioLevel::default();
+        for _ in 0..50 {
+            level = meter.process(&[0.0; FRAME_SIZE]);
+        }
+
+        assert_eq!(level.rms_dbfs, AudioLevel::MIN_DBFS);
+        assert_eq!(level.peak_dbfs, AudioLevel::MIN_DBFS);
+        assert!(!level.voice_active);
+    }
+
+    #[test]
+    fn test_tone_level_matches_amplitude() {
+        for amplitude in [1.0f32, 0.5, 0.1, 0.01] {
+            let mut meter = AudioLevelMeter::new(SAMPLE_RATE, 1);
+
+            // Two seconds is several smoothing windows
+            let mut level = AudioLevel::default();
+            for index in 0..200 {
+                level = meter.process(&sine_frame(index, amplitude));
+            }
+
+            let peak = 20.0 * amplitude.log10();
+            let rms = 20.0 * (amplitude / 2.0f32.sqrt()).log10();
+            assert!(
+                (level.peak_dbfs - peak).abs() < 0.5,
+                "peak at amplitude {} was {} dBFS",
+                amplitude,
+                level.peak_dbfs
+            );
+            assert!(
+                (level.rms_dbfs - rms).abs() < 0.5,
+                "RMS at amplitude {} was {} dBFS",
+                amplitude,
+                level.rms_dbfs
+            );
+        }
+    }
+
+    #[test]
+    fn test_stereo_tone_level() {
+        let mut meter = AudioLevelMeter::new(SAMPLE_RATE, 2);
+
+        let mut level = AudioLevel::default();
+        for index in 0..200 {
+            let stereo: Vec<f32> = sine_frame(index, 0.5)
+                .into_iter()
+                .flat_map(|s| [s, s])
+                .collect();
+            level = meter.process(&stereo);
+        }
+
+        assert!((level.rms_dbfs + 9.03).abs() < 0.5);
+        assert!(level.voice_active);
+    }
+
+    #[test]
+    fn test_smoothing_window() {
+        let mut smoothed = AudioLevelMeter::new(SAMPLE_RATE, 1);
+        let mut immediate = AudioLevelMeter::new(SAMPLE_RATE, 1).with_smoothing(Duration::ZERO);
+
+        for index in 0..100 {
+            smoothed.process(&sine_frame(index, 0.5));
+            immediate.process(&sine_frame(index, 0.5));
+        }
+        let smoothed = smoothed.process(&[0.0; FRAME_SIZE]);
+        let immediate = immediate.process(&[0.0; FRAME_SIZE]);
+
+        // One 10ms buffer of silence barely moves a 300ms average
+        assert!(smoothed.rms_dbfs > -10.0);
+        assert!(smoothed.peak_dbfs > -7.0);
+        assert_eq!(immediate.rms_dbfs, AudioLevel::MIN_DBFS);
+        assert_eq!(immediate.peak_dbfs, AudioLevel::MIN_DBFS);
+    }
+
+    #[test]
+    fn test_noise_bursts_toggle_voice_activity() {
+        let mut meter = AudioLevelMeter::new(SAMPLE_RATE, 1);
+        let mut noise = LowPassNoise {
+            white: WhiteNoise(42),
+            last: 0.0,
+        };
+
+        for _ in 0..3 {
+            // 500ms burst
+            for _ in 0..50 {
+                meter.process(&noise.frame());
+            }
+            assert!(meter.level().voice_active);
+
+            // Still active within the 300ms hangover
+            for _ in 0..29 {
+                assert!(meter.process(&[0.0; FRAME_SIZE]).voice_active);
+            }
+            assert!(!meter.process(&[0.0; FRAME_SIZE]).voice_active);
+
+            // And stays off through the rest of the pause
+            for _ in 0..50 {
+                assert!(!meter.process(&[0.0; FRAME_SIZE]).voice_active);
+            }
+        }
+    }
+
+    #[test]
+    fn test_voice_activity_hangover_is_configurable() {
+        let mut meter = AudioLevelMeter::new(SAMPLE_RATE, 1)
+            .with_voice_detection(-45.0, Duration::from_millis(100));
+        let mut noise = LowPassNoise {
+            white: WhiteNoise(7),
+            last: 0.0,
+        };
+
+        for _ in 0..10 {
+            meter.process(&noise.frame());
+        }
+        assert!(meter.level().voice_active);
+
+        for _ in 0..9 {
+            assert!(meter.process(&[0.0; FRAME_SIZE]).voice_active);
+        }
+        assert!(!meter.process(&[0.0; FRAME_SIZE]).voice_active);
+    }
+
+    #[test]
+    fn test_quiet_noise_is_not_voice() {
+        let mut meter = AudioLevelMeter::new(SAMPLE_RATE, 1);
+        let mut noise = WhiteNoise(3);
+
+        // Low-pass noise well below the -45 dBFS threshold
+        let mut last = 0.0f32;
+        for _ in 0..100 {
+            let frame: Vec<f32> = (0..FRAME_SIZE)
+                .map(|_| {
+                    last = 0.97 * last + noise.next(0.001);
+                    last
+                })
+                .collect();
+            assert!(!meter.process(&frame).voice_active);
+        }
+        assert!(meter.level().rms_dbfs > AudioLevel::MIN_DBFS);
+    }
+
+    #[test]
+    fn test_white_noise_is_not_voice() {
+        let mut meter = AudioLevelMeter::new(SAMPLE_RATE, 1);
+        let mut noise = WhiteNoise(11);
+
+        // Loud, but crossing zero far more often than voice
+        for _ in 0..100 {
+            let frame: Vec<f32> = (0..FRAME_SIZE).map(|_| noise.next(0.5)).collect();
+            assert!(!meter.process(&frame).voice_active);
+        }
+        assert!(meter.level().rms_dbfs > -20.0);
+    }
+}
@@ -0,0 +1,159 @@
+//! Unit tests for RTCP
+//!
+//! Tests for sender and receiver report generation and parsing
+
+#[cfg(test)]
+mod tests {
+    use cortenbrowser_webrtc_integration::{
+        JitterBuffer, RTCPHandler, RTPPacket, ReceiverReport, ReportBlock, RtcpPacket, SenderReport,
+    };
+
+    fn packet(sequence_number: u16) -> RTPPacket {
+        RTPPacket {
+            payload: vec![0],
+            sequence_number,
+            timestamp: 3000 * sequence_number as u32,
+            ssrc: 0xAAAA,
+            ..Default::default()
+        }
+    }
+
+    fn receiver_report(packets: &[RtcpPacket]) -> &ReceiverReport {
+        match packets {
+            [RtcpPacket::ReceiverReport(report)] => report,
+            other => panic!("expected one receiver report, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sender_report_roundtrip() {
+        let report = SenderReport {
+            ssrc: 0xAAAA,
+            ntp_time: 0xE8A0_1234_5678_9ABC,
+            rtp_time: 90_000,
+            packet_count: 250,
+            octet_count: 300_000,
+            reports: vec![ReportBlock {
+                ssrc: 0xBBBB,
+                fraction_lost: 12,
+                cumulative_lost: -2,
+                extended_highest_seq: 0x1_0005,
+                jitter: 40,
+                last_sr: 0x1234_5678,
+                delay_since_last_sr: 65536,
+            }],
+        };
+        let bytes = report.to_bytes();
+        assert_eq!(bytes.len(), 28 + 24);
+        assert_eq!((bytes[0], bytes[1]), (0x81, 200));
+
+        let mut handler = RTCPHandler::new(0xBBBB);
+        assert_eq!(
+            handler.parse(&bytes),
+            vec![RtcpPacket::SenderReport(report)]
+        );
+    }
+
+    #[test]
+    fn test_receiver_report_reports_loss_and_jitter() {
+        let mut buffer = JitterBuffer::new(100);
+        // 20 of 25 packets arrive
+        for seq in (0..25).filter(|seq| seq % 5 != 4) {
+            buffer.insert(packet(seq)).unwrap();
+        }
+
+        let mut handler = RTCPHandler::new(0xBBBB);
+        let bytes = handler.build_receiver_report(0xAAAA, &buffer);
+        assert_eq!(bytes.len(), 8 + 24);
+
+        let packets = handler.parse(&bytes);
+        let report = receiver_report(&packets);
+        assert_eq!(report.ssrc, 0xBBBB);
+        let block = report.reports[0];
+        assert_eq!(block.ssrc, 0xAAAA);
+        // The last packet, 24, was lost and is not yet expected
+        assert_eq!(block.extended_highest_seq, 23);
+        assert_eq!(block.cumulative_lost, 4);
+        assert_eq!(block.fraction_lost, (4 * 256 / 24) as u8);
+        assert_eq!(block.jitter, buffer.reception_stats().jitter);
+        assert_eq!((block.last_sr, block.delay_since_last_sr), (0, 0));
+    }
+
+    #[test]
+    fn test_receiver_report_fraction_lost_covers_interval() {
+        let mut buffer = JitterBuffer::new(100);
+        for seq in [0, 2, 3] {
+            buffer.insert(packet(seq)).unwrap();
+        }
+        let mut handler = RTCPHandler::new(0xBBBB);
+        let first = handler.build_receiver_report(0xAAAA, &buffer);
+        assert_eq!(
+            receiver_report(&handler.parse(&first)).reports[0].fraction_lost,
+            64
+        );
+
+        // No loss since the first report
+        for seq in 4..8 {
+            buffer.insert(packet(seq)).unwrap();
+        }
+        let second = handler.build_receiver_report(0xAAAA, &buffer);
+        let packets = handler.parse(&second);
+        let block = receiver_report(&packets).reports[0];
+        assert_eq!(block.fraction_lost, 0);
+        assert_eq!(block.cumulative_lost, 1);
+    }
+
+    #[test]
+    fn test_receiver_report_references_last_sender_report() {
+        let mut sender = RTCPHandler::new(0xAAAA);
+        let mut receiver = RTCPHandler::new(0xBBBB);
+        let sr = sender.build_sender_report(0xE8A0_1234_5678_9ABC, 0, 0, 0);
+        receiver.parse(&sr);
+
+        let rr = receiver.build_receiver_report(0xAAAA, &JitterBuffer::new(10));
+        let packets = sender.parse(&rr);
+        let block = receiver_report(&packets).reports[0];
+        assert_eq!(block.last_sr, 0x1234_5678);
+        assert!(block.delay_since_last_sr < 65536);
+    }
+
+    #[test]
+    fn test_parse_compound_packet() {
+        let mut data = SenderReport {
+            ssrc: 1,
+            ..Default::default()
+        }
+        .to_bytes();
+        data.extend(
+            ReceiverReport {
+                ssrc: 2,
+                reports: Vec::new(),
+            }
+            .to_bytes(),
+        );
+        // A BYE packet
+        data.extend_from_slice(&[0x81, 203, 0x00, 0x01, 0, 0, 0, 1]);
+
+        let packets = RTCPHandler::new(3).parse(&data);
+        assert_eq!(packets.len(), 3);
+        assert!(matches!(packets[0], RtcpPacket::SenderReport(_)));
+        assert!(matches!(packets[1], RtcpPacket::ReceiverReport(_)));
+        assert_eq!(packets[2], RtcpPacket::Other { packet_type: 203 });
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed() {
+        let mut handler = RTCPHandler::new(3);
+        assert!(handler.parse(&[]).is_empty());
+        assert!(handler.parse(&[0x80, 200, 0x00]).is_empty());
+
+        // Report count larger than the packet
+        let mut bytes = ReceiverReport {
+            ssrc: 2,
+            reports: Vec::new(),
+        }
+        .to_bytes();
+        bytes[0] = 0x81;
+        assert!(handler.parse(&bytes).is_empty());
+    }
+}
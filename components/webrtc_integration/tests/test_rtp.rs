@@ -4,7 +4,7 @@
 
 #[cfg(test)]
 mod tests {
-    use cortenbrowser_webrtc_integration::{RTPPacket, RTPPacketizer};
+    use cortenbrowser_webrtc_integration::{RTPPacket, RTPPacketizer, RtpExtension};
 
     #[test]
     fn test_rtp_packet_creation() {
@@ -14,6 +14,7 @@ mod tests {
             sequence_number: 100,
             timestamp: 1000,
             ssrc: 0x12345678,
+            ..Default::default()
         };
 
         assert_eq!(packet.payload, vec![1, 2, 3, 4, 5]);
@@ -30,6 +31,7 @@ mod tests {
             sequence_number: 42,
             timestamp: 9000,
             ssrc: 0xDEADBEEF,
+            ..Default::default()
         };
 
         let bytes = packet.to_bytes();
@@ -136,4 +138,168 @@ mod tests {
         assert_eq!(packets1[0].ssrc, packets2[0].ssrc, "SSRC should be consistent");
         assert_ne!(packets1[0].ssrc, 0, "SSRC should not be zero");
     }
+
+    #[test]
+    fn test_rtp_packetizer_h264_multi_nal_access_unit() {
+        let mut packetizer = RTPPacketizer::new();
+        packetizer.set_sequence_number(500);
+
+        // SPS and PPS fit one packet each, the IDR slice needs three
+        let sps = [0x67, 0x42, 0xC0, 0x1F, 0x8C];
+        let pps = [0x68, 0xCE, 0x3C, 0x80];
+        let mut idr = vec![0x65];
+        idr.extend((0..3000u32).map(|i| (i % 251) as u8));
+        let mut access_unit = vec![0, 0, 0, 1];
+        access_unit.extend_from_slice(&sps);
+        access_unit.extend_from_slice(&[0, 0, 0, 1]);
+        access_unit.extend_from_slice(&pps);
+        access_unit.extend_from_slice(&[0, 0, 1]);
+        access_unit.extend_from_slice(&idr);
+
+        let packets = packetizer.packetize_h264(&access_unit, 90_000);
+        assert_eq!(packets.len(), 5);
+        assert_eq!(packets[0].payload, sps);
+        assert_eq!(packets[1].payload, pps);
+        for (i, packet) in packets.iter().enumerate() {
+            assert_eq!(packet.sequence_number, 500 + i as u16);
+            assert_eq!(packet.timestamp, 90_000);
+            assert!(packet.payload.len() <= 1200);
+            // Only the last packet of the access unit is marked
+            assert_eq!(packet.marker, i == packets.len() - 1);
+        }
+
+        // FU-A indicator keeps NRI, header carries start/end bits and type
+        let fragments = &packets[2..];
+        for fragment in fragments {
+            assert_eq!(fragment.payload[0], 0x60 | 28);
+            assert_eq!(fragment.payload[1] & 0x1F, 5);
+        }
+        assert_eq!(fragments[0].payload[1] & 0xC0, 0x80);
+        assert_eq!(fragments[1].payload[1] & 0xC0, 0x00);
+        assert_eq!(fragments[2].payload[1] & 0xC0, 0x40);
+
+        // The fragments reassemble to the NAL unit
+        let mut reassembled =
+            vec![(fragments[0].payload[0] & 0xE0) | (fragments[0].payload[1] & 0x1F)];
+        for fragment in fragments {
+            reassembled.extend_from_slice(&fragment.payload[2..]);
+        }
+        assert_eq!(reassembled, idr);
+    }
+
+    #[test]
+    fn test_rtp_packetizer_h264_without_nal_units() {
+        let packetizer = RTPPacketizer::new();
+        assert!(packetizer.packetize_h264(&[], 0).is_empty());
+        assert!(packetizer.packetize_h264(&[0, 0, 0, 1], 0).is_empty());
+    }
+
+    #[test]
+    fn test_rtp_packet_round_trip_without_extensions() {
+        let packet = RTPPacket {
+            payload: vec![0xAA, 0xBB, 0xCC],
+            sequence_number: 42,
+            timestamp: 9000,
+            ssrc: 0xDEADBEEF,
+            marker: true,
+            payload_type: 111,
+            csrcs: vec![0x11111111, 0x22222222],
+            ..Default::default()
+        };
+
+        let bytes = packet.to_bytes();
+        assert_eq!(bytes.len(), 12 + 2 * 4 + 3);
+
+        let (parsed, payload) = RTPPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, packet);
+        assert_eq!(payload, &[0xAA, 0xBB, 0xCC]);
+    }
+
+    #[test]
+    fn test_rtp_packet_round_trip_with_extensions() {
+        // One-byte element headers
+        let packet = RTPPacket {
+            payload: vec![1, 2, 3, 4],
+            sequence_number: 7,
+            timestamp: 48000,
+            ssrc: 0x12345678,
+            payload_type: 96,
+            extensions: vec![
+                RtpExtension { id: 1, data: vec![0x10] },
+                RtpExtension { id: 3, data: vec![0x12, 0x34, 0x56] },
+            ],
+            ..Default::default()
+        };
+        let bytes = packet.to_bytes();
+        assert_eq!(u16::from_be_bytes([bytes[12], bytes[13]]), 0xBEDE);
+        let (parsed, payload) = RTPPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, packet);
+        assert_eq!(payload, &[1, 2, 3, 4]);
+
+        // IDs above 14 need two-byte element headers
+        let packet = RTPPacket {
+            extensions: vec![
+                RtpExtension { id: 20, data: vec![0xFF; 20] },
+                RtpExtension { id: 2, data: vec![] },
+            ],
+            ..packet
+        };
+        let bytes = packet.to_bytes();
+        assert_eq!(u16::from_be_bytes([bytes[12], bytes[13]]), 0x1000);
+        let (parsed, _) = RTPPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed, packet);
+    }
+
+    #[test]
+    fn test_rtp_packet_from_bytes_with_csrcs_and_padding() {
+        let mut bytes = vec![
+            0xA2, // V=2, P=1, X=0, CC=2
+            0xE0, // M=1, PT=96
+            0x12, 0x34, // sequence number
+            0x00, 0x01, 0x5F, 0x90, // timestamp
+            0xCA, 0xFE, 0xBA, 0xBE, // SSRC
+            0x00, 0x00, 0x00, 0x01, // CSRC 1
+            0x00, 0x00, 0x00, 0x02, // CSRC 2
+        ];
+        bytes.extend_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF, 0x42]);
+        bytes.extend_from_slice(&[0, 0, 3]);
+
+        let (packet, payload) = RTPPacket::from_bytes(&bytes).unwrap();
+        assert_eq!(packet.sequence_number, 0x1234);
+        assert_eq!(packet.timestamp, 90_000);
+        assert_eq!(packet.ssrc, 0xCAFEBABE);
+        assert!(packet.marker);
+        assert_eq!(packet.payload_type, 96);
+        assert_eq!(packet.csrcs, vec![1, 2]);
+        assert!(packet.extensions.is_empty());
+        assert_eq!(payload, &[0xDE, 0xAD, 0xBE, 0xEF, 0x42]);
+        assert_eq!(packet.payload, payload);
+    }
+
+    #[test]
+    fn test_rtp_packet_from_malformed_bytes() {
+        // Shorter than the fixed header
+        assert!(RTPPacket::from_bytes(&[0x80, 0x00, 0x00, 0x01, 0, 0, 0, 0]).is_err());
+
+        let bytes = RTPPacket {
+            payload: vec![1, 2],
+            csrcs: vec![1],
+            ..Default::default()
+        }
+        .to_bytes();
+
+        // Not version 2
+        let mut version_1 = bytes.clone();
+        version_1[0] = (version_1[0] & 0x3F) | 0x40;
+        assert!(RTPPacket::from_bytes(&version_1).is_err());
+
+        // CSRC list cut off
+        assert!(RTPPacket::from_bytes(&bytes[..14]).is_err());
+
+        // More padding than payload
+        let mut padded = bytes.clone();
+        padded[0] |= 0x20;
+        *padded.last_mut().unwrap() = 10;
+        assert!(RTPPacket::from_bytes(&padded).is_err());
+    }
 }
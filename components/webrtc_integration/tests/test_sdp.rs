@@ -0,0 +1,234 @@
+//! Unit tests for SDP offer/answer
+//!
+//! Tests for SdpBuilder, SdpParser and SdpNegotiator
+
+#[cfg(test)]
+mod tests {
+    use cortenbrowser_shared_types::{
+        AV1Level, AV1Profile, H264Level, H264Profile, MediaError, VP9Profile, VideoCodec,
+    };
+    use cortenbrowser_webrtc_integration::{
+        EncoderConfig, MediaDirection, SdpBuilder, SdpNegotiator, SdpParser,
+    };
+
+    fn config() -> EncoderConfig {
+        EncoderConfig {
+            bitrate: 1_500_000,
+            framerate: 30,
+            keyframe_interval: 60,
+        }
+    }
+
+    fn h264(profile: H264Profile, level: H264Level) -> VideoCodec {
+        VideoCodec::H264 {
+            profile,
+            level,
+            hardware_accel: false,
+        }
+    }
+
+    #[test]
+    fn test_offer_contains_required_lines() {
+        let codecs = [
+            h264(H264Profile::Baseline, H264Level::Level3_1),
+            VideoCodec::VP8,
+            VideoCodec::VP9 { profile: VP9Profile::Profile0 },
+        ];
+        let sdp = SdpBuilder::create_offer(&config(), &codecs);
+
+        assert!(sdp.starts_with("v=0\r\n"));
+        assert!(sdp.contains("m=video 9 UDP/TLS/RTP/SAVPF 96 97 98\r\n"));
+        assert!(sdp.contains("a=rtpmap:96 H264/90000\r\n"));
+        assert!(sdp.contains("a=fmtp:96 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f\r\n"));
+        assert!(sdp.contains("a=rtpmap:97 VP8/90000\r\n"));
+        assert!(sdp.contains("a=rtpmap:98 VP9/90000\r\n"));
+        assert!(sdp.contains("a=fmtp:98 profile-id=0\r\n"));
+        assert!(sdp.contains("a=sendrecv\r\n"));
+        assert!(sdp.contains("a=ice-ufrag:"));
+        assert!(sdp.contains("a=ice-pwd:"));
+        assert!(sdp.contains("b=AS:1500\r\n"));
+        assert!(sdp.contains("a=ssrc:"));
+    }
+
+    #[test]
+    fn test_offer_skips_codecs_without_payload_format() {
+        let sdp = SdpBuilder::create_offer(&config(), &[VideoCodec::Theora, VideoCodec::VP8]);
+        assert!(sdp.contains("m=video 9 UDP/TLS/RTP/SAVPF 96\r\n"));
+        assert!(sdp.contains("a=rtpmap:96 VP8/90000"));
+        assert!(!sdp.contains("Theora"));
+    }
+
+    #[test]
+    fn test_offer_roundtrip() {
+        let codecs = [
+            h264(H264Profile::High, H264Level::Level4_1),
+            VideoCodec::AV1 {
+                profile: AV1Profile::Main,
+                level: AV1Level::Level5_0,
+            },
+        ];
+        let offer = SdpBuilder::offer_description(&config(), &codecs);
+        let parsed = SdpParser::parse(&offer.to_sdp()).unwrap();
+
+        assert_eq!(parsed, offer);
+        assert_eq!(parsed.media[0].codecs[0].codec, codecs[0]);
+        assert_eq!(parsed.media[0].codecs[1].codec, codecs[1]);
+    }
+
+    #[test]
+    fn test_parse_browser_offer() {
+        let sdp = "v=0\n\
+            o=- 4611731400430051336 2 IN IP4 127.0.0.1\n\
+            s=-\n\
+            t=0 0\n\
+            a=ice-ufrag:abcd\n\
+            a=ice-pwd:0123456789abcdefghijklmn\n\
+            m=audio 9 UDP/TLS/RTP/SAVPF 111\n\
+            a=rtpmap:111 opus/48000/2\n\
+            a=ssrc:1 cname:audio\n\
+            m=video 50000 UDP/TLS/RTP/SAVPF 102 100 127\n\
+            c=IN IP4 192.0.2.10\n\
+            a=mid:1\n\
+            a=recvonly\n\
+            a=rtpmap:102 H264/90000\n\
+            a=fmtp:102 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e01f\n\
+            a=rtpmap:100 VP8/90000\n\
+            a=rtpmap:127 red/90000\n\
+            a=framerate:29.97\n\
+            a=ssrc:3735928559 cname:video\n\
+            a=ssrc:3735928559 msid:stream track\n\
+            a=ssrc:42 cname:video\n";
+
+        let desc = SdpParser::parse(sdp).unwrap();
+        assert_eq!(desc.session_id, 4611731400430051336);
+        assert_eq!(desc.media.len(), 1, "audio sections are ignored");
+
+        let video = &desc.media[0];
+        assert_eq!(video.mid.as_deref(), Some("1"));
+        assert_eq!(video.transport.port, 50000);
+        assert_eq!(video.transport.address, "192.0.2.10");
+        assert_eq!(video.direction, MediaDirection::RecvOnly);
+        assert_eq!(video.framerate, Some(30));
+        assert_eq!(video.ssrcs, vec![3735928559, 42]);
+
+        // Session-level ICE credentials apply to the media section
+        let ice = video.ice.as_ref().unwrap();
+        assert_eq!(ice.ufrag, "abcd");
+        assert_eq!(ice.pwd, "0123456789abcdefghijklmn");
+
+        // Unknown payload formats (red) are dropped; order is preserved
+        assert_eq!(video.codecs.len(), 2);
+        assert_eq!(video.codecs[0].payload_type, 102);
+        assert_eq!(video.codecs[0].codec, h264(H264Profile::Baseline, H264Level::Level3_1));
+        assert_eq!(video.codecs[1].payload_type, 100);
+        assert_eq!(video.codecs[1].codec, VideoCodec::VP8);
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_sdp() {
+        assert!(matches!(
+            SdpParser::parse("o=- 1 2 IN IP4 127.0.0.1\r\n"),
+            Err(MediaError::InvalidParameter(_))
+        ));
+        assert!(SdpParser::parse("v=0\r\n").is_err());
+        assert!(SdpParser::parse("v=0\r\no=- 1 2 IN IP4 127.0.0.1\r\nnot sdp\r\n").is_err());
+        assert!(SdpParser::parse(
+            "v=0\r\no=- 1 2 IN IP4 127.0.0.1\r\nm=video 9 RTP/AVP 96\r\na=rtpmap:96 VP8\r\n"
+        )
+        .is_err());
+        assert!(SdpParser::parse(
+            "v=0\r\no=- 1 2 IN IP4 127.0.0.1\r\nm=video abc RTP/AVP 96\r\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_answer_selects_intersection() {
+        let offer_sdp = SdpBuilder::create_offer(
+            &config(),
+            &[
+                h264(H264Profile::High, H264Level::Level5_1),
+                VideoCodec::VP9 { profile: VP9Profile::Profile2 },
+                VideoCodec::VP8,
+            ],
+        );
+        let offer = SdpParser::parse(&offer_sdp).unwrap();
+
+        let supported = [
+            VideoCodec::VP8,
+            h264(H264Profile::High, H264Level::Level4_0),
+            VideoCodec::VP9 { profile: VP9Profile::Profile0 },
+        ];
+        let answer = SdpNegotiator::answer(&offer, &supported).unwrap();
+
+        let media = &answer.media[0];
+        assert_eq!(media.direction, MediaDirection::SendRecv);
+        assert_eq!(media.codecs.len(), 2);
+
+        // Offerer's preference and payload types are kept
+        assert_eq!(media.codecs[0].payload_type, 96);
+        assert_eq!(media.codecs[0].codec, h264(H264Profile::High, H264Level::Level4_0));
+        assert_eq!(media.codecs[1].payload_type, 98);
+        assert_eq!(media.codecs[1].codec, VideoCodec::VP8);
+
+        // Answer carries its own ICE credentials
+        assert_ne!(media.ice, offer.media[0].ice);
+
+        // The answer text parses back to the same description
+        assert_eq!(SdpParser::parse(&answer.to_sdp()).unwrap(), answer);
+    }
+
+    #[test]
+    fn test_answer_reverses_direction() {
+        let mut offer = SdpBuilder::offer_description(&config(), &[VideoCodec::VP8]);
+        offer.media[0].direction = MediaDirection::SendOnly;
+
+        let answer = SdpNegotiator::answer(&offer, &[VideoCodec::VP8]).unwrap();
+        assert_eq!(answer.media[0].direction, MediaDirection::RecvOnly);
+    }
+
+    #[test]
+    fn test_answer_without_common_codec_fails() {
+        let offer = SdpBuilder::offer_description(&config(), &[VideoCodec::VP8]);
+        let result = SdpNegotiator::answer(
+            &offer,
+            &[h264(H264Profile::Main, H264Level::Level4_0)],
+        );
+        assert!(matches!(result, Err(MediaError::UnsupportedFormat { .. })));
+    }
+
+    #[test]
+    fn test_select_encoder_from_answer() {
+        let offer = SdpBuilder::offer_description(
+            &config(),
+            &[h264(H264Profile::Main, H264Level::Level4_0)],
+        );
+        let answer = SdpNegotiator::answer(
+            &offer,
+            &[h264(H264Profile::Main, H264Level::Level4_1)],
+        )
+        .unwrap();
+
+        let defaults = EncoderConfig {
+            bitrate: 500_000,
+            framerate: 15,
+            keyframe_interval: 90,
+        };
+        let (codec, selected) = answer.select_encoder(&defaults).unwrap();
+        assert_eq!(codec, h264(H264Profile::Main, H264Level::Level4_0));
+        assert_eq!(selected.bitrate, 1_500_000);
+        assert_eq!(selected.framerate, 30);
+        assert_eq!(selected.keyframe_interval, 90);
+    }
+
+    #[test]
+    fn test_select_encoder_falls_back_to_defaults() {
+        let sdp = "v=0\r\no=- 1 2 IN IP4 127.0.0.1\r\ns=-\r\nt=0 0\r\n\
+                   m=video 9 RTP/AVP 96\r\na=rtpmap:96 VP8/90000\r\n";
+        let desc = SdpParser::parse(sdp).unwrap();
+
+        let (codec, selected) = desc.select_encoder(&config()).unwrap();
+        assert_eq!(codec, VideoCodec::VP8);
+        assert_eq!(selected, config());
+    }
+}